@@ -44,8 +44,13 @@ fn frag(@location(0) in_pos: vec3<f32>, @location(1) in_uv: vec2<f32>, @builtin(
         );
     }
 
-    color = color + max(pos.z + 0.1, 0.0) * 5.0 * textureSample(t_starfield, s_starfield, vec2(longitude, pos.z)).rgb; // starfield
-    color = color + max(pos.z, 0.0) * 10000.0 * smoothstep(0.99993, 1.0, dot(fsun, pos)); // sun
+    // the clouds gray the sky and hide the stars and the sun
+    let cloud: vec3<f32> = vec3(dot(color, vec3(0.2126, 0.7152, 0.0722)) * 0.7);
+    color = mix(color, cloud, params.overcast);
+    let clear: f32 = 1.0 - params.overcast;
+
+    color = color + clear * max(pos.z + 0.1, 0.0) * 5.0 * textureSample(t_starfield, s_starfield, vec2(longitude, pos.z)).rgb; // starfield
+    color = color + clear * max(pos.z, 0.0) * 10000.0 * smoothstep(0.99993, 1.0, dot(fsun, pos)); // sun
 
     var ocrgb = tonemap(color);
     ocrgb = ocrgb + dither(position.xy);
@@ -88,8 +88,10 @@ fn textureNoTile(tex: texture_2d<f32>, samp: sampler, uv: vec2<f32>) -> vec4<f32
     var ofc: vec4<f32> = hash4( iuv + vec2(0.0,1.0) );
     var ofd: vec4<f32> = hash4( iuv + vec2(1.0,1.0) );
 
-    let ddx: vec2<f32> = dpdxCoarse(uv);
-    let ddy: vec2<f32> = dpdyCoarse(uv);
+    // scaling the derivatives moves the mip level by the bias
+    let bias: f32 = exp2(params.terrain_mip_bias);
+    let ddx: vec2<f32> = dpdxCoarse(uv) * bias;
+    let ddy: vec2<f32> = dpdyCoarse(uv) * bias;
 
     // transform per-tile uvs
     ofa.z = sign(ofa.z - 0.5);
@@ -145,8 +147,8 @@ fn frag(@builtin(position) position: vec4<f32>,
 
     // tri-planar mapping
     if (wgrass  > min_contribution) { c += wgrass  * textureNoTile(t_grass, s_grass, in_wpos.xy / 200.0).rgb; }
-    if (wcliffE > min_contribution) { c += wcliffE * textureSample(t_cliff, s_cliff, in_wpos.xz / 100.0).rgb; }
-    if (wcliffN > min_contribution) { c += wcliffN * textureSample(t_cliff, s_cliff, in_wpos.yz / 100.0).rgb; }
+    if (wcliffE > min_contribution) { c += wcliffE * textureSampleBias(t_cliff, s_cliff, in_wpos.xz / 100.0, params.terrain_mip_bias).rgb; }
+    if (wcliffN > min_contribution) { c += wcliffN * textureSampleBias(t_cliff, s_cliff, in_wpos.yz / 100.0, params.terrain_mip_bias).rgb; }
 
     c = c / sum;
 
@@ -157,6 +159,10 @@ fn frag(@builtin(position) position: vec4<f32>,
     c = mix(params.sand_col.rgb, c, smoothstep(-5.0, 0.0, in_wpos.z));
     c = mix(params.sea_col.rgb, c, smoothstep(-25.0, -20.0, in_wpos.z));
 
+    // snow settles on flat ground above the shore
+    let snow = params.snow * smoothstep(0.7, 0.9, normalize(in_normal).z) * smoothstep(0.0, 5.0, in_wpos.z);
+    c = mix(c, vec3(0.85, 0.88, 0.92), snow);
+
     let irradiance_diffuse: vec3<f32> = textureSample(t_diffuse_irradiance, s_diffuse_irradiance, in_normal).rgb;
     let V_denorm: vec3<f32> = params.cam_pos.xyz - in_wpos;
     let dist: f32 = length(V_denorm);
@@ -215,7 +221,9 @@ fn frag(@builtin(position) position: vec4<f32>,
                                       ssao,
                                       lightdata,
                                       in_wpos,
-                                      fog
+                                      fog,
+                                      params.lamp_intensity,
+                                      vec3(0.0)
                                       );
     return FragmentOutput(vec4(final_rgb, 1.0));
 }
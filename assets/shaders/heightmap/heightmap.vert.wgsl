@@ -47,34 +47,33 @@ fn vert(@builtin(vertex_index) vid: u32,
     let idx_x: u32 = vid % cdata.resolution;
     let idx_y: u32 = vid / cdata.resolution;
 
-    var in_position: vec2<i32> = vec2(i32(idx_x), i32(idx_y));
-
-    //if (idx_x == 0u) { // x_neg
-    //    in_position.y &= -1 << ((stitch_dir_flags & 4u) >> 2u);
-    //}
-    //else if (idx_x == cdata.resolution - 1u) { // x_pos
-    //    in_position.y &= -1 << (stitch_dir_flags & 1u);
-    //}
-    //if (idx_y == 0u) { // y_neg
-    //    in_position.x &= -1 << ((stitch_dir_flags & 8u) >> 3u);
-    //}
-    //else if (idx_y == cdata.resolution - 1u) { // y_pos
-    //    in_position.x &= -1 << ((stitch_dir_flags & 2u) >> 1u);
-    //}
+    let in_position: vec2<i32> = vec2(i32(idx_x), i32(idx_y));
 
     let tpos: vec2<i32> = in_position + vec2<i32>(in_off * cdata.inv_cell_size / f32(cdata.lod_pow2));
 
     let height_normal: vec4<f32> = sampleHeightDxDy(tpos, i32(cdata.lod));
     var normal: vec3<f32> = height_normal.yzw;
 
-    var world_pos: vec3<f32> = vec3(vec2<f32>(in_position * i32(cdata.lod_pow2)) * cdata.cell_size + in_off, height_normal.x);
+    // relative to the chunk, the chunk offset is rebased on the camera separately
+    var local_pos: vec3<f32> = vec3(vec2<f32>(in_position * i32(cdata.lod_pow2)) * cdata.cell_size, height_normal.x);
+    var world_pos: vec3<f32> = local_pos + vec3(in_off, 0.0);
 
 #ifdef DEBUG
     var debug = 1.0;
 #endif
 
     let dist_to_cam: f32 = length(params.cam_pos.xyz - vec3(world_pos.xy, 0.0));
-    let transition_alpha: f32 = smoothstep(cdata.distance_lod_cutoff * 0.8, cdata.distance_lod_cutoff, dist_to_cam);
+    var transition_alpha: f32 = smoothstep(cdata.distance_lod_cutoff * 0.8, cdata.distance_lod_cutoff, dist_to_cam);
+
+    // the index buffer already skips the odd vertices of the stitched edges,
+    // the even ones take the heights of the next lod to match the neighbor chunk
+    let last: u32 = cdata.resolution - 1u;
+    if ((idx_x == last && (stitch_dir_flags & 1u) != 0u) ||
+        (idx_y == last && (stitch_dir_flags & 2u) != 0u) ||
+        (idx_x == 0u   && (stitch_dir_flags & 4u) != 0u) ||
+        (idx_y == 0u   && (stitch_dir_flags & 8u) != 0u)) {
+        transition_alpha = 1.0;
+    }
 
     if (cdata.lod < 4u && transition_alpha > 0.0) {
 #ifdef DEBUG
@@ -83,14 +82,16 @@ fn vert(@builtin(vertex_index) vid: u32,
 #endif
         let height_normal_next: vec4<f32> = sampleHeightDxDy(tpos / 2, i32(cdata.lod) + 1);
 
-        var world_pos_next: vec3<f32> = vec3(vec2<f32>(in_position / 2 * i32(cdata.lod_pow2)) * cdata.cell_size * 2.0 + in_off, height_normal_next.x);
+        let local_pos_next: vec3<f32> = vec3(vec2<f32>(in_position / 2 * i32(cdata.lod_pow2)) * cdata.cell_size * 2.0, height_normal_next.x);
 
 
         normal = normalize(mix(normal, height_normal_next.yzw, transition_alpha));
-        world_pos = mix(world_pos, world_pos_next, transition_alpha);
+        local_pos = mix(local_pos, local_pos_next, transition_alpha);
+        world_pos = local_pos + vec3(in_off, 0.0);
     }
 
-    let clip_pos: vec4<f32> = params.proj * vec4(world_pos, 1.0);
+    let rel_pos: vec3<f32> = local_pos + cam_relative(vec3(in_off, 0.0), params.cam_pos, params.cam_pos_low);
+    let clip_pos: vec4<f32> = params.proj * vec4(rel_pos, 1.0);
 
 
     return VertexOutput(clip_pos,
@@ -17,6 +17,6 @@ fn vert(@location(0) in_position: vec3<f32>,
         @location(2) in_uv: vec2<f32>,
         @location(3) in_color: vec4<f32>,
         @location(4) in_tangent: vec4<f32>) -> VertexOutput {
-    let position = global.proj * vec4(in_position, 1.0);
+    let position = global.proj * vec4(cam_relative(in_position, global.cam_pos, global.cam_pos_low), 1.0);
     return VertexOutput(in_color, in_normal, in_tangent, in_position, in_uv, position);
 }
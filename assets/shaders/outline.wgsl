@@ -0,0 +1,83 @@
+#include "render_params.wgsl"
+
+struct VertexOutput {
+    @location(0) out_uv: vec2<f32>,
+    @builtin(position) member: vec4<f32>,
+}
+
+@vertex
+fn vert(@location(0) in_pos: vec3<f32>,
+        @location(1) in_uv: vec2<f32>) -> VertexOutput {
+    return VertexOutput(in_uv, vec4(in_pos.xy, 1.0, 1.0));
+}
+
+struct OutlineParams {
+    color: vec4<f32>,
+    thickness: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: RenderParams;
+
+#ifdef MSAA
+@group(1) @binding(0) var t_mask: texture_multisampled_2d<f32>;
+#else
+@group(1) @binding(0) var t_mask: texture_2d<f32>;
+#endif
+@group(1) @binding(1) var s_mask: sampler;
+
+@group(2) @binding(0) var<uniform> outline: OutlineParams;
+
+fn inside(coords: vec2<i32>) -> bool {
+    let size = vec2<i32>(textureDimensions(t_mask));
+    if (any(coords < vec2(0)) || any(coords >= size)) {
+        return false;
+    }
+    return textureLoad(t_mask, coords, 0).r > 0.0;
+}
+
+@fragment
+fn frag(@location(0) in_uv: vec2<f32>) -> @location(0) vec4<f32> {
+    let pos: vec2<i32> = vec2<i32>(in_uv * params.viewport);
+
+    if (inside(pos)) {
+        discard;
+    }
+
+    // cheap rejection of the pixels far from the object: probe two rings around the pixel
+    var near: bool = false;
+    for (var i: i32 = 0; i < 8; i++) {
+        let a = f32(i) * 0.7853982;
+        let dir = vec2(cos(a), sin(a));
+        if (inside(pos + vec2<i32>(round(dir * outline.thickness)))
+            || inside(pos + vec2<i32>(round(dir * outline.thickness * 0.5)))) {
+            near = true;
+            break;
+        }
+    }
+    if (!near) {
+        discard;
+    }
+
+    // dilate the mask to find the distance to the object
+    let radius = i32(ceil(outline.thickness));
+    var closest: f32 = 1e10;
+    for (var y: i32 = -radius; y <= radius; y++) {
+        for (var x: i32 = -radius; x <= radius; x++) {
+            let d = length(vec2<f32>(f32(x), f32(y)));
+            if (d > outline.thickness || d >= closest) {
+                continue;
+            }
+            if (inside(pos + vec2(x, y))) {
+                closest = d;
+            }
+        }
+    }
+
+    if (closest > outline.thickness) {
+        discard;
+    }
+
+    // soften the outer edge
+    let alpha = clamp(outline.thickness + 0.5 - closest, 0.0, 1.0);
+    return vec4(outline.color.rgb, outline.color.a * alpha);
+}
@@ -158,6 +158,8 @@ fn render(sun: vec3<f32>,
           lightdata: LightData,
           wpos: vec3<f32>,
           fog: vec3<f32>,
+          lamp_intensity: f32,
+          emissive: vec3<f32>,
           ) -> vec3<f32>  {
 
     var Lo: vec3<f32> = vec3(0.0);
@@ -165,17 +167,18 @@ fn render(sun: vec3<f32>,
     if(sun.z >= 0.0) {
        Lo = calc_light(vec3(0.0), sun, V, normal, albedo, metallic, roughness,  F0, sun_col, shadow_v, ssao);
     }
-    if(sun.z < 0.1) {
-        Lo = calc_packed_light(Lo, lightdata.chunk_id, lightdata.data, V, normal, albedo, metallic, roughness, F0, wpos, ssao);
+    if(lamp_intensity > 0.0) {
+        var lamps: vec3<f32> = calc_packed_light(vec3(0.0), lightdata.chunk_id, lightdata.data, V, normal, albedo, metallic, roughness, F0, wpos, ssao);
         if (lightdata.data.w != 0) {
-            Lo = calc_packed_light(Lo, lightdata.chunk_id, lightdata.data2, V, normal, albedo, metallic, roughness, F0, wpos, ssao);
+            lamps = calc_packed_light(lamps, lightdata.chunk_id, lightdata.data2, V, normal, albedo, metallic, roughness, F0, wpos, ssao);
         }
+        Lo += lamps * lamp_intensity;
     }
 
     let dkD: vec3<f32> = (1.0 - F_spec) * (1.0 - vec3(metallic));
 
     let ambient: vec3<f32> = (0.2 * dkD * (0.04 + irradiance_diffuse) * albedo + specular) * ssao;
-    var color: vec3<f32>   = ambient + Lo + fog;
+    var color: vec3<f32>   = ambient + Lo + emissive + fog;
 
     let autoexposure = 1.0 + smoothstep(0.0, 0.1, -sun.z) * 10.0;
 
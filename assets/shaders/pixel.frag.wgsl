@@ -7,11 +7,15 @@ struct FragmentOutput {
 
 const HAS_METALLIC_ROUGHNESS_TEXTURE: u32 = 1u;
 const HAS_NORMAL_MAP: u32 = 2u;
+const IN_ATLAS: u32 = 4u;
+const LIT_WINDOWS: u32 = 8u;
 
 struct MaterialParams {
     flags: u32,
     metallic: f32,
     roughness: f32,
+    uv_rect: vec4<f32>,
+    emissive: vec4<f32>,
 }
 
 @group(0) @binding(0) var<uniform> params: RenderParams;
@@ -46,21 +50,66 @@ struct MaterialParams {
 @group(2) @binding(5) var t_normal: texture_2d<f32>;
 @group(2) @binding(6) var s_normal: sampler;
 
+#ifdef INSTANCED
+struct Palette {
+    colors: array<vec4<f32>, 256>,
+}
+
+@group(3) @binding(0) var<uniform> u_palette: Palette;
+#endif
+
 #include "shadow.wgsl"
 #include "pbr/render.wgsl"
 
 const MAX_REFLECTION_LOD: f32 = 4.0;
 
+fn window_hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+// windows of 3x3 meters cells along the walls, a bit more than half of them are lit
+fn lit_window(wpos: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if (abs(normal.z) > 0.1) {
+        return 0.0;
+    }
+    let wall = normalize(normal.xy);
+    let cell: vec2<f32> = vec2(dot(wpos.xy, vec2(-wall.y, wall.x)), wpos.z) / 3.0;
+    let in_cell: vec2<f32> = fract(cell);
+    let is_window: f32 = step(0.3, in_cell.x) * step(in_cell.x, 0.7) * step(0.35, in_cell.y) * step(in_cell.y, 0.8);
+    // the walls facing each other don't light the same windows
+    let lit: f32 = step(0.45, window_hash(floor(cell) + dot(wpos.xy, wall)));
+    return is_window * lit;
+}
+
 @fragment
 fn frag(@location(0) in_tint: vec4<f32>,
         @location(1) in_normal: vec3<f32>,
         @location(2) in_tangent: vec4<f32>,
         @location(3) in_wpos: vec3<f32>,
         @location(4) in_uv: vec2<f32>,
+#ifdef INSTANCED
+        @location(5) @interpolate(flat) in_variation: u32,
+#endif
         @builtin(position) position: vec4<f32>,
         ) -> FragmentOutput {
+    var tint: vec4<f32> = in_tint;
+    #ifdef INSTANCED
+    tint *= u_palette.colors[in_variation];
+    #endif
+
 
-    let albedo: vec4<f32> = textureSample(t_albedo, s_albedo, in_uv);
+    // atlased textures repeat inside their part of the page, the gradients are taken before
+    // the wrap so that the seams do not pick the smallest mip
+    var uv: vec2<f32> = in_uv;
+    var duv_dx: vec2<f32> = dpdx(in_uv);
+    var duv_dy: vec2<f32> = dpdy(in_uv);
+    if ((u_mat.flags & IN_ATLAS) != 0u) {
+        uv = u_mat.uv_rect.xy + fract(in_uv) * u_mat.uv_rect.zw;
+        duv_dx *= u_mat.uv_rect.zw;
+        duv_dy *= u_mat.uv_rect.zw;
+    }
+
+    let albedo: vec4<f32> = textureSampleGrad(t_albedo, s_albedo, uv, duv_dx, duv_dy);
     var ssao = 1.0;
     #ifdef SSAO
     #ifndef OFFSCREEN_RENDER
@@ -79,7 +128,7 @@ fn frag(@location(0) in_tint: vec4<f32>,
 
     var normal = in_normal;
     if ((u_mat.flags & HAS_NORMAL_MAP) != 0u) {
-        let vNt: vec3<f32> = textureSample(t_normal, s_normal, in_uv).rgb * 2.0 - 1.0;
+        let vNt: vec3<f32> = textureSampleGrad(t_normal, s_normal, uv, duv_dx, duv_dy).rgb * 2.0 - 1.0;
         let vT = in_tangent.xyz;
         let sign = in_tangent.w;
         // http://www.mikktspace.com/
@@ -91,13 +140,13 @@ fn frag(@location(0) in_tint: vec4<f32>,
     var metallic: f32 = u_mat.metallic;
     var roughness: f32 = u_mat.roughness;
     if ((u_mat.flags & HAS_METALLIC_ROUGHNESS_TEXTURE) != 0u) {
-        let sampled: vec2<f32> = textureSample(t_metallic_roughness, s_metallic_rougness, in_uv).gb;
+        let sampled: vec2<f32> = textureSampleGrad(t_metallic_roughness, s_metallic_rougness, uv, duv_dx, duv_dy).gb;
         roughness = sampled[0] * roughness;
         metallic  = sampled[1] * metallic;
     }
 
     let irradiance_diffuse: vec3<f32> = textureSample(t_diffuse_irradiance, s_diffuse_irradiance, normal).rgb;
-    let c = mix(in_tint, vec4(1.0), metallic) * albedo;
+    let c = mix(tint, vec4(1.0), metallic) * albedo;
 
     let V_denorm: vec3<f32> = params.cam_pos.xyz - in_wpos;
     let dist: f32 = length(V_denorm);
@@ -131,6 +180,11 @@ fn frag(@location(0) in_tint: vec4<f32>,
     #endif
     #endif
 
+    var emissive: vec3<f32> = u_mat.emissive.rgb * params.lamp_intensity;
+    if ((u_mat.flags & LIT_WINDOWS) != 0u) {
+        emissive *= lit_window(in_wpos, in_normal);
+    }
+
     #ifdef OFFSCREEN_RENDER
     let lightdata = LightData(vec4(0), vec4(0), vec2(0));
     #else
@@ -153,7 +207,9 @@ fn frag(@location(0) in_tint: vec4<f32>,
                                       ssao,
                                       lightdata,
                                       in_wpos,
-                                      fog
+                                      fog,
+                                      params.lamp_intensity,
+                                      emissive
                                       );
 
     return FragmentOutput(vec4<f32>(final_rgb, c.a));
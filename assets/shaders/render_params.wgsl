@@ -5,6 +5,7 @@ struct RenderParams {
     invproj: mat4x4<f32>,
     sunproj: array<mat4x4<f32>, N_SHADOWS>,
     cam_pos: vec4<f32>,
+    cam_pos_low: vec4<f32>,
     cam_dir: vec4<f32>,
     sun: vec3<f32>,
     sun_col: vec4<f32>,
@@ -16,4 +17,15 @@ struct RenderParams {
     time_always: f32,
     shadow_mapping_resolution: i32,
     terraforming_mode_radius: f32,
-}
\ No newline at end of file
+    snow: f32,
+    lamp_intensity: f32,
+    terrain_mip_bias: f32,
+    overcast: f32,
+}
+
+// Position relative to the camera, to be transformed by proj.
+// The nearest f32 of the camera position is subtracted first, which is exact for the positions
+// close to it even far from the origin, then what the f32 misses.
+fn cam_relative(wpos: vec3<f32>, cam_pos: vec4<f32>, cam_pos_low: vec4<f32>) -> vec3<f32> {
+    return (wpos - cam_pos.xyz) - cam_pos_low.xyz;
+}
@@ -23,9 +23,11 @@ fn vert(@location(0) in_pos: vec3<f32>,
     let z: vec3<f32> = cross(x, normalize(y));
 
     let scaled: vec3<f32> = vec3(in_pos.xy * in_scale, in_pos.z);
-    let wpos: vec3<f32> = scaled.x * x + scaled.y * y + scaled.z * z + in_instance_pos;
+    let local: vec3<f32> = scaled.x * x + scaled.y * y + scaled.z * z;
+    let wpos: vec3<f32> = local + in_instance_pos;
+    let rel: vec3<f32> = local + cam_relative(in_instance_pos, globals.cam_pos, globals.cam_pos_low);
 
-    let position = globals.proj * vec4(wpos, 1.0);
+    let position = globals.proj * vec4(rel, 1.0);
 
     return VertexOutput(in_tint, z, vec4(0.0), wpos, in_uv, position);
 }
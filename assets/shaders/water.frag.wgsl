@@ -15,8 +15,18 @@ struct FragmentOutput {
 #endif
 @group(1) @binding(1) var s_depth: sampler;
 
+struct WaterParams {
+    map_origin: vec2<f32>,
+    map_size: vec2<f32>,
+    wave_scale: f32,
+    wave_speed: f32,
+}
+
 @group(2) @binding(0) var t_wavy: texture_2d<f32>;
 @group(2) @binding(1) var s_wavy: sampler;
+@group(2) @binding(2) var t_flow: texture_2d<f32>;
+@group(2) @binding(3) var s_flow: sampler;
+@group(2) @binding(4) var<uniform> water: WaterParams;
 
 @group(3) @binding(0) var t_fog: texture_2d<f32>;
 @group(3) @binding(1) var s_fog: sampler;
@@ -52,6 +62,28 @@ fn gerstnerWaveNormal(p: vec2<f32>, t: f32) -> vec3<f32> {
 	return normal;
 }
 
+// distance between the water surface and what is behind it (terrain, bridge piers...)
+fn water_thickness(position: vec4<f32>, wpos: vec3<f32>) -> f32 {
+    let depth: f32 = textureLoad(t_depth, vec2<i32>(position.xy), 0).r;
+    if (depth <= 0.00001) {
+        return 1e38;
+    }
+    let uv = position.xy / params.viewport;
+    let ndc = vec2(uv.x * 2.0 - 1.0, -uv.y * 2.0 + 1.0);
+    let sceneP = params.invproj * vec4<f32>(ndc, depth, 1.0);
+    return length(sceneP.xyz / sceneP.w - wpos);
+}
+
+// scrolls the wavy texture along the flow, blending two phases to hide the reset
+fn flow_normal(p: vec2<f32>, flow: vec2<f32>, t: f32) -> vec3<f32> {
+    let phase0: f32 = fract(t * 0.05);
+    let phase1: f32 = fract(t * 0.05 + 0.5);
+    let w0: vec3<f32> = textureSample(t_wavy, s_wavy, p - flow * phase0 * 0.5).xyz * 2.0 - 1.0;
+    let w1: vec3<f32> = textureSample(t_wavy, s_wavy, p + 0.37 - flow * phase1 * 0.5).xyz * 2.0 - 1.0;
+    let blend: f32 = abs(0.5 - phase0) * 2.0;
+    return mix(w0, w1, blend);
+}
+
 @fragment
 fn frag(@location(0) _in_tint: vec4<f32>,
         @location(1) _in_normal: vec3<f32>,
@@ -59,11 +91,26 @@ fn frag(@location(0) _in_tint: vec4<f32>,
         @location(3) wpos: vec3<f32>,
         @location(4) _in_uv: vec2<f32>,
         @builtin(position) position: vec4<f32>) -> FragmentOutput {
-    var normal = gerstnerWaveNormal(wpos.xy * 0.01, params.time_always);
+    let t: f32 = params.time_always * water.wave_speed;
+    let wave_p: vec2<f32> = wpos.xy / water.wave_scale;
+
+    let flow_data: vec4<f32> = textureSample(t_flow, s_flow, (wpos.xy - water.map_origin) / water.map_size);
+    let flow: vec2<f32> = flow_data.xy * 2.0 - 1.0;
+    let flow_speed: f32 = length(flow);
+    let water_depth: f32 = flow_data.z * 255.0;
+
+    var normal = gerstnerWaveNormal(wave_p * 0.01, t);
+
+    let wavy: vec3<f32> = textureSample(t_wavy, s_wavy, t * 0.02 + wave_p * 0.001).xyz * 2.0 - 1.0;
+    let wavy2: vec3<f32> = textureSample(t_wavy, s_wavy, 30.0 + t * 0.01 - wave_p.yx * vec2(0.001, -0.001)).xyz * 2.0 - 1.0;
+    let still: vec3<f32> = wavy * 0.15 + wavy2 * 0.1;
+    let flowing: vec3<f32> = flow_normal(wave_p * 0.004, flow, t) * 0.3;
+    normal = normalize(normal + mix(still, flowing, smoothstep(0.0, 0.2, flow_speed)));
 
-    let wavy: vec3<f32> = textureSample(t_wavy, s_wavy, params.time_always * 0.02 + wpos.xy * 0.001).xyz * 2.0 - 1.0;
-    let wavy2: vec3<f32> = textureSample(t_wavy, s_wavy, 30.0 + params.time_always * 0.01 - wpos.yx * vec2(0.001, -0.001)).xyz * 2.0 - 1.0;
-    normal = normalize(normal + wavy * 0.15 + wavy2 * 0.1);
+    // foam where the water is shallow (shores) or something crosses it (bridge piers)
+    let thickness: f32 = min(water_depth, water_thickness(position, wpos));
+    let foam_noise: f32 = textureSample(t_wavy, s_wavy, wave_p * 0.02 - flow * t * 0.05).x;
+    let foam: f32 = (1.0 - smoothstep(0.0, 2.0 + 2.0 * flow_speed, thickness)) * smoothstep(0.3, 0.7, foam_noise);
 
     let R: vec3<f32> = normalize(2.0 * normal * dot(normal,params.sun) - params.sun);
     let cam_to_wpos: vec3<f32> = params.cam_pos.xyz - wpos;
@@ -84,6 +131,7 @@ fn frag(@location(0) _in_tint: vec4<f32>,
     let sunpower: f32 = 0.1 * reflect_coeff;
 
     var final_rgb: vec3<f32> = base_color + sunpower * reflected_atmo;
+    final_rgb = mix(final_rgb, 0.3 * params.sun_col.rgb * (0.2 + sun_contrib), foam);
 
     #ifdef FOG
     var fog = vec3(0.0);
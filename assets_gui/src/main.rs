@@ -162,11 +162,11 @@ fn create_shown(gfx: &mut GfxContext, _state: &State, inspected: Inspected) -> S
                         cpy.lods = vec![lod].into_boxed_slice();
 
                         let mut b: InstancedMeshBuilder<false> = InstancedMeshBuilder::new(cpy);
-                        b.instances.push(MeshInstance {
-                            pos: Vec3::x(i as f32 * size * 2.0),
-                            dir: Vec3::X,
-                            tint: LinearColor::WHITE,
-                        });
+                        b.instances.push(MeshInstance::new(
+                            Vec3::x(i as f32 * size * 2.0),
+                            Vec3::X,
+                            LinearColor::WHITE,
+                        ));
 
                         meshes.push(unwrap_cont!(b.build(gfx)));
                     }
@@ -181,7 +181,7 @@ fn create_shown(gfx: &mut GfxContext, _state: &State, inspected: Inspected) -> S
 fn main() {
     engine::framework::init();
     unsafe {
-        prototypes::load_prototypes("./").unwrap();
+        prototypes::load_prototypes(&prototypes::LoadConfig::new("./")).unwrap();
     }
     engine::framework::start::<State>();
 }
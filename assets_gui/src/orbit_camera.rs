@@ -25,7 +25,6 @@ impl OrbitCamera {
         ctx.gfx.set_camera(self.camera);
 
         let params = ctx.gfx.render_params.value_mut();
-        params.cam_pos = self.camera.eye();
         params.cam_dir = -self.camera.dir();
     }
 
@@ -0,0 +1,43 @@
+-- Sounds played when events are logged, road_lay stands in until dedicated stingers are made
+data:extend {
+    {
+        type = "audio-event",
+        name = "construction-completed",
+        label = "Construction completed",
+        category = "construction",
+        text = "completed",
+        sound = "road_lay",
+        positional = true,
+        cooldown = 2,
+    },
+    {
+        type = "audio-event",
+        name = "company-closed",
+        label = "Company closed",
+        category = "economy",
+        min_severity = "warning",
+        text = "bankrupt",
+        sound = "road_lay",
+        positional = true,
+        cooldown = 20,
+    },
+    {
+        type = "audio-event",
+        name = "traffic-incident",
+        label = "Traffic incident",
+        category = "incident",
+        min_severity = "warning",
+        sound = "road_lay",
+        positional = true,
+        cooldown = 10,
+    },
+    {
+        type = "audio-event",
+        name = "scenario-news",
+        label = "Scenario news",
+        category = "scenario",
+        min_severity = "success",
+        sound = "road_lay",
+        cooldown = 5,
+    },
+}
@@ -0,0 +1,48 @@
+data:extend {
+    {
+        type = "build-category",
+        order = "a",
+        name = "farming",
+        label = "Farming",
+        icon = "icon/cereal",
+        subcategories = {"crops", "livestock"},
+    },
+    {
+        type = "build-category",
+        order = "b",
+        name = "industry",
+        label = "Industry",
+        icon = "icon/metal",
+        subcategories = {"extraction", "processing", "manufacturing"},
+    },
+    {
+        type = "build-category",
+        order = "c",
+        name = "commerce",
+        label = "Commerce",
+        icon = "icon/bread",
+        subcategories = {"food", "goods"},
+    },
+    {
+        type = "build-category",
+        order = "d",
+        name = "energy",
+        label = "Energy",
+        icon = "icon/no_power",
+    },
+    {
+        type = "build-category",
+        order = "e",
+        name = "logistics",
+        label = "Logistics",
+        icon = "toolbar_train",
+        subcategories = {"storage", "shipping"},
+    },
+    {
+        type = "build-category",
+        order = "f",
+        name = "services",
+        label = "Services",
+        icon = "toolbar_road_edit",
+    },
+}
@@ -65,6 +65,16 @@ data:extend {
         g = 0.50980395,
         b = 0.50980395,
     },
+    road_bus_col = {
+        r = 0.42,
+        g = 0.2,
+        b = 0.17,
+    },
+    rail_ballast_col = {
+        r = 0.36,
+        g = 0.34,
+        b = 0.31,
+    },
     road_pylon_col = {
         r = 0.48789835,
         g = 0.4879001,
@@ -79,5 +89,15 @@ data:extend {
         r = 0.2,
         g = 0.6,
         b = 0.25,
+    },
+    lot_commercial_col = {
+        r = 0.2,
+        g = 0.35,
+        b = 0.75,
+    },
+    lot_industrial_col = {
+        r = 0.75,
+        g = 0.6,
+        b = 0.15,
     }
 }
\ No newline at end of file
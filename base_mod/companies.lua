@@ -4,11 +4,15 @@ data:extend {
         order = "a-0",
         name = "bakery",
         label = "Bakery",
+        category = "commerce",
+        subcategory = "food",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
         },
         kind = "store",
+        amenity = "grocery",
+        zone_kind = "commercial",
         recipe = {
             consumption = {{"flour", 1}},
             production = {{"bread", 1}},
@@ -16,16 +20,52 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 3,
+        opening_hours = "7h -> 20h",
+        shifts = {"6h -> 14h", "12h -> 20h"},
         size = 10.0,
         asset = "bakery.glb",
         price = 1000,
         power_consumption = "200W",
+        upgrades_to = "bakery-2",
+        upgrade_price = 2000,
+        upgrade_conditions = {
+            min_nearby_population = 20,
+        },
+    },
+    {
+        type = "goods-company",
+        order = "a-0b",
+        name = "bakery-2",
+        label = "Large Bakery",
+        category = "commerce",
+        subcategory = "food",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        kind = "store",
+        amenity = "grocery",
+        recipe = {
+            consumption = {{"flour", 2}},
+            production = {{"bread", 2}},
+            duration = "100s",
+            storage_multiplier = 8,
+        },
+        n_workers = 6,
+        opening_hours = "7h -> 20h",
+        shifts = {"6h -> 14h", "12h -> 20h"},
+        size = 16.0,
+        asset = "bakery.glb",
+        price = 3000,
+        power_consumption = "400W",
     },
     {
         type = "goods-company",
         order = "a-1",
         name = "flour-factory",
         label = "Flour Factory",
+        category = "industry",
+        subcategory = "processing",
         bgen = {
             kind = "centered_door",
             vertical_factor = 0.6,
@@ -39,6 +79,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        shifts = {"6h -> 14h", "14h -> 22h"},
         size = 80.0,
         asset = "flour_factory.glb",
         price = 1000,
@@ -49,6 +90,8 @@ data:extend {
         order = "a-2",
         name = "cereal-farm",
         label = "Cereal Farm",
+        category = "farming",
+        subcategory = "crops",
         bgen = "farm",
         kind = "factory",
         n_trucks = 1,
@@ -59,6 +102,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        crops = {"wheat"},
         size = 120.0,
         asset = "assets/sprites/dirt.jpg",
         price = 200,
@@ -75,6 +119,7 @@ data:extend {
         order = "b-1",
         name = "solar-panel",
         label = "Solar Panelm",
+        category = "energy",
         max_power = "1kW",
         bgen = {
             kind = "centered_door",
@@ -97,6 +142,7 @@ data:extend {
         order = "b-2",
         name = "coal-power-plant",
         label = "Coal power plant",
+        category = "energy",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -109,6 +155,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        shifts = {"6h -> 14h", "14h -> 22h", "22h -> 6h"},
         size = 165.0,
         asset = "coal_power_plant.glb",
         price = 1000,
@@ -119,11 +166,14 @@ data:extend {
         order = "c-1",
         name = "supermarket",
         label = "Supermarket",
+        category = "commerce",
+        subcategory = "food",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
         },
         kind = "store",
+        amenity = "grocery",
         recipe = {
             consumption = {{"meat", 1}, {"vegetable", 1}, {"cereal", 1}},
             production = {},
@@ -131,21 +181,32 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        opening_hours = "8h -> 21h",
+        shifts = {"7h -> 15h", "13h -> 21h"},
         size = 80.0,
         asset = "assets/sprites/supermarket.png",
         price = 1000,
         power_consumption = "1kW",
+        entrances = {
+            {pos = {-25, -40}, kind = "pedestrian"},
+            {pos = {25, -40}, kind = "pedestrian"},
+            {pos = {0, -40}, kind = "vehicle"},
+            {pos = {40, 0}, kind = "pedestrian", optional = true},
+        },
     },
     {
         type = "goods-company",
         order = "d-1",
         name = "clothes-store",
         label = "Clothes store",
+        category = "commerce",
+        subcategory = "goods",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
         },
         kind = "store",
+        zone_kind = "commercial",
         recipe = {
             consumption = {{"cloth", 1}},
             production = {},
@@ -153,6 +214,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        opening_hours = "9h -> 19h",
         size = 10.0,
         asset = "assets/sprites/clothes_store.png",
         price = 1000,
@@ -163,6 +225,8 @@ data:extend {
         order = "d-2",
         name = "cloth-factory",
         label = "Cloth factory",
+        category = "industry",
+        subcategory = "manufacturing",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -176,6 +240,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        shifts = {"6h -> 14h", "14h -> 22h"},
         size = 80.0,
         asset = "assets/sprites/cloth_factory.png",
         price = 1000,
@@ -186,6 +251,8 @@ data:extend {
         order = "d-3",
         name = "textile-processing-facility",
         label = "Textile processing facility",
+        category = "industry",
+        subcategory = "processing",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -209,6 +276,8 @@ data:extend {
         order = "d-4",
         name = "polyester-refinery",
         label = "Polyester refinery",
+        category = "industry",
+        subcategory = "processing",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -232,11 +301,14 @@ data:extend {
         order = "e-1",
         name = "oil-pump",
         label = "Oil pump",
+        category = "industry",
+        subcategory = "extraction",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
         },
         kind = "factory",
+        zone_kind = "industrial",
         n_trucks = 1,
         recipe = {
             consumption = {},
@@ -248,6 +320,7 @@ data:extend {
         size = 20.0,
         asset = "assets/sprites/oil_pump.png",
         price = 1000,
+        deposit = "oil",
         power_consumption = "10kW",
     },
     {
@@ -255,11 +328,14 @@ data:extend {
         order = "e-2",
         name = "coal-mine",
         label = "Coal mine",
+        category = "industry",
+        subcategory = "extraction",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
         },
         kind = "factory",
+        zone_kind = "industrial",
         n_trucks = 1,
         recipe = {
             consumption = {},
@@ -271,12 +347,40 @@ data:extend {
         size = 20.0,
         asset = "assets/sprites/oil_pump.png",
         price = 1000,
+        deposit = "coal",
+    },
+    {
+        type = "goods-company",
+        order = "e-3",
+        name = "stone-quarry",
+        label = "Stone quarry",
+        category = "industry",
+        subcategory = "extraction",
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        kind = "factory",
+        n_trucks = 1,
+        recipe = {
+            consumption = {},
+            production = {{"stone", 1}},
+            duration = "80s",
+            storage_multiplier = 5,
+        },
+        n_workers = 6,
+        size = 40.0,
+        asset = "assets/sprites/iron_mine.png",
+        price = 800,
+        deposit = "stone",
     },
     {
         type = "goods-company",
         order = "f-1",
         name = "wool-farm",
         label = "Wool farm",
+        category = "farming",
+        subcategory = "livestock",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -300,11 +404,14 @@ data:extend {
         order = "g-1",
         name = "florist",
         label = "Florist",
+        category = "commerce",
+        subcategory = "goods",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
         },
         kind = "store",
+        zone_kind = "commercial",
         recipe = {
             consumption = {{"flower", 1}},
             production = {},
@@ -312,6 +419,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        opening_hours = "9h -> 19h",
         size = 10.0,
         asset = "assets/sprites/florist.png",
         price = 1000,
@@ -322,6 +430,8 @@ data:extend {
         order = "g-2",
         name = "horticulturalist",
         label = "Horticulturalist",
+        category = "farming",
+        subcategory = "crops",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -335,6 +445,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 5,
+        seasonal_output = { spring = 1.4, summer = 1.2, autumn = 0.9, winter = 0.5 },
         size = 80.0,
         asset = "assets/sprites/horticulturalist.png",
         price = 1000,
@@ -345,6 +456,9 @@ data:extend {
         order = "h-1",
         name = "high-tech-store",
         label = "High tech store",
+        category = "commerce",
+        subcategory = "goods",
+        unlock = { population = 500 },
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -357,6 +471,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        opening_hours = "9h -> 19h",
         size = 80.0,
         asset = "assets/sprites/hightech_store.png",
         price = 1000,
@@ -367,6 +482,9 @@ data:extend {
         order = "h-2",
         name = "high-tech-facility",
         label = "High tech facility",
+        category = "industry",
+        subcategory = "manufacturing",
+        unlock = { population = 500 },
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -380,16 +498,46 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        shifts = {"6h -> 14h", "14h -> 22h"},
         size = 80.0,
         asset = "assets/sprites/hightech_facility.png",
         price = 1000,
         power_consumption = "100kW",
     },
+    {
+        type = "goods-company",
+        order = "h-3",
+        name = "car-factory",
+        label = "Car factory",
+        category = "industry",
+        subcategory = "manufacturing",
+        unlock = { population = 1000 },
+        bgen = {
+            kind = "centered_door",
+            vertical_factor = 1.0,
+        },
+        kind = "factory",
+        n_trucks = 1,
+        recipe = {
+            consumption = {{"metal", 4}, {"high-tech-product", 1}, {"polyester", 1}},
+            production = {{"car", 1}},
+            duration = "2h",
+            storage_multiplier = 3,
+        },
+        n_workers = 20,
+        shifts = {"6h -> 14h", "14h -> 22h"},
+        size = 80.0,
+        asset = "assets/sprites/hightech_facility.png",
+        price = 5000,
+        power_consumption = "200kW",
+    },
     {
         type = "goods-company",
         order = "i-1",
         name = "iron-mine",
         label = "Iron mine",
+        category = "industry",
+        subcategory = "extraction",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -406,6 +554,7 @@ data:extend {
         size = 80.0,
         asset = "assets/sprites/iron_mine.png",
         price = 1000,
+        deposit = "iron-ore",
         power_consumption = "1kW",
     },
     {
@@ -413,6 +562,8 @@ data:extend {
         order = "i-1",
         name = "gold-mine",
         label = "Gold mine",
+        category = "industry",
+        subcategory = "extraction",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -429,6 +580,7 @@ data:extend {
         size = 80.0,
         asset = "assets/sprites/rare_metal_mine.png",
         price = 1000,
+        deposit = "gold-ore",
         power_consumption = "1kW",
     },
     {
@@ -436,6 +588,8 @@ data:extend {
         order = "j-1",
         name = "lumber-yard",
         label = "Lumber yard",
+        category = "industry",
+        subcategory = "extraction",
         bgen = "farm",
         kind = "factory",
         n_trucks = 1,
@@ -446,6 +600,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        harvest_radius = 400.0,
         size = 200.0,
         asset = "assets/sprites/lumber_yard.png",
         price = 1000,
@@ -456,6 +611,8 @@ data:extend {
         order = "j-2",
         name = "woodmill",
         label = "Woodmill",
+        category = "industry",
+        subcategory = "processing",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -479,6 +636,8 @@ data:extend {
         order = "j-3",
         name = "furniture-store",
         label = "Furniture store",
+        category = "commerce",
+        subcategory = "goods",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -491,6 +650,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        opening_hours = "9h -> 19h",
         size = 80.0,
         asset = "assets/sprites/furniture_store.png",
         price = 1000,
@@ -501,6 +661,8 @@ data:extend {
         order = "h-1",
         name = "foundry",
         label = "Foundry",
+        category = "industry",
+        subcategory = "processing",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -514,6 +676,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        shifts = {"6h -> 14h", "14h -> 22h"},
         size = 80.0,
         asset = "assets/sprites/foundry.png",
         price = 1000,
@@ -524,6 +687,8 @@ data:extend {
         order = "k-1",
         name = "slaughterhouse",
         label = "Slaughterhouse",
+        category = "industry",
+        subcategory = "processing",
         bgen = {
             kind = "centered_door",
             vertical_factor = 1.0,
@@ -547,6 +712,8 @@ data:extend {
         order = "k-1",
         name = "animal-farm",
         label = "Animal Farm",
+        category = "farming",
+        subcategory = "livestock",
         bgen = "farm",
         kind = "factory",
         n_trucks = 1,
@@ -567,6 +734,8 @@ data:extend {
         order = "k-2",
         name = "meat-facility",
         label = "Meat facility",
+        category = "industry",
+        subcategory = "processing",
         bgen = {
             kind = "centered_door",
             vertical_factor = 0.6,
@@ -590,6 +759,8 @@ data:extend {
         order = "l-1",
         name = "vegetable-farm",
         label = "Vegetable Farm",
+        category = "farming",
+        subcategory = "crops",
         bgen = "farm",
         kind = "factory",
         n_trucks = 1,
@@ -600,6 +771,7 @@ data:extend {
             storage_multiplier = 5,
         },
         n_workers = 10,
+        crops = {"vegetables"},
         size = 70.0,
         asset = "assets/sprites/vegetable_farm.png",
         price = 1000,
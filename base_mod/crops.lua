@@ -0,0 +1,24 @@
+data:extend {
+    {
+        type = "crop",
+        order = "a-1",
+        name = "wheat",
+        label = "Wheat",
+        growth = "5d",
+        harvest = "3d",
+        fallow = "1d",
+        sow_seasons = {"spring", "autumn"},
+        yield_per_field = 3000,
+    },
+    {
+        type = "crop",
+        order = "a-2",
+        name = "vegetables",
+        label = "Vegetables",
+        growth = "3d",
+        harvest = "2d",
+        fallow = "1d",
+        sow_seasons = {"spring", "summer"},
+        yield_per_field = 40000,
+    },
+}
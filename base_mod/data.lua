@@ -1,9 +1,18 @@
 require("items")
+require("crops")
+require("deposits")
 require("companies")
 require("leisure")
 require("colors")
 require("roadvehicles")
 require("rollingstock")
+require("roads")
+require("props")
+require("tutorials")
+require("scenarios")
+require("streetnames")
+require("audioevents")
+require("buildmenu")
 
 data:extend {
     {
@@ -13,5 +22,84 @@ data:extend {
         asset = "rail_freight_station.glb",
         price = 1000,
         size = {160, 200},
+    },
+    {
+        type = "dock",
+        name = "dock",
+        label = "Dock",
+        category = "logistics",
+        subcategory = "shipping",
+        unlock = { scenario_flag = "harbor" },
+        asset = "dock.glb",
+        price = 400,
+        size = {40, 30},
+        boat_asset = "cargo_boat.glb",
+        boat_capacity = 400,
+        boat_speed = 6.0,
+    },
+    {
+        type = "warehouse",
+        name = "warehouse",
+        label = "Warehouse",
+        category = "logistics",
+        subcategory = "storage",
+        asset = "rail_freight_station.glb",
+        price = 600,
+        size = {60, 40},
+        capacity = 2000,
+    },
+    {
+        type = "warehouse",
+        name = "grain-silo",
+        label = "Grain Silo",
+        category = "logistics",
+        subcategory = "storage",
+        asset = "flour_factory.glb",
+        price = 300,
+        size = {30, 30},
+        capacity = 1500,
+        items = {"cereal", "flour"},
+    },
+    {
+        type = "service-depot",
+        name = "tow-depot",
+        label = "Tow Truck Depot",
+        category = "services",
+        asset = "external_trading.glb",
+        price = 800,
+        size = {40, 30},
+        service = "towing",
+        vehicle_price = 300,
+        vehicle_upkeep = 40,
+        initial_fleet = 1,
+        max_fleet = 8,
+    },
+    {
+        type = "service-depot",
+        name = "fire-station",
+        label = "Fire Station",
+        category = "services",
+        asset = "external_trading.glb",
+        price = 1200,
+        size = {40, 30},
+        service = "fire",
+        vehicle_price = 500,
+        vehicle_upkeep = 60,
+        initial_fleet = 2,
+        max_fleet = 6,
+    },
+    {
+        type = "service-depot",
+        name = "garbage-depot",
+        label = "Garbage Truck Depot",
+        category = "services",
+        asset = "external_trading.glb",
+        price = 800,
+        size = {40, 30},
+        service = "garbage",
+        vehicle_price = 250,
+        vehicle_upkeep = 30,
+        initial_fleet = 2,
+        max_fleet = 12,
     }
 }
\ No newline at end of file
@@ -0,0 +1,52 @@
+data:extend {
+    {
+        type = "deposit",
+        order = "a-1",
+        name = "iron-ore",
+        label = "Iron ore",
+        color = { r = 0.65, g = 0.3, b = 0.2 },
+        density = 0.4,
+        radius = 90.0,
+        amount = 1500,
+    },
+    {
+        type = "deposit",
+        order = "a-2",
+        name = "gold-ore",
+        label = "Gold ore",
+        color = { r = 0.95, g = 0.8, b = 0.2 },
+        density = 0.1,
+        radius = 70.0,
+        amount = 600,
+    },
+    {
+        type = "deposit",
+        order = "a-3",
+        name = "coal",
+        label = "Coal",
+        color = { r = 0.15, g = 0.15, b = 0.15 },
+        density = 0.4,
+        radius = 60.0,
+        amount = 1200,
+    },
+    {
+        type = "deposit",
+        order = "a-4",
+        name = "stone",
+        label = "Stone",
+        color = { r = 0.7, g = 0.7, b = 0.72 },
+        density = 0.6,
+        radius = 60.0,
+        amount = 2000,
+    },
+    {
+        type = "deposit",
+        order = "a-5",
+        name = "oil",
+        label = "Oil",
+        color = { r = 0.3, g = 0.1, b = 0.35 },
+        density = 0.2,
+        radius = 50.0,
+        amount = 1000,
+    },
+}
@@ -9,11 +9,13 @@ data:extend {
         type = "item",
         name = "cereal",
         label = "Cereal",
+        cargo_color = { r = 0.85, g = 0.75, b = 0.4 },
     },
     {
         type = "item",
         name = "flour",
         label = "Flour",
+        cargo_color = { r = 0.95, g = 0.93, b = 0.88 },
     },
     {
         type = "item",
@@ -44,21 +46,25 @@ data:extend {
         type = "item",
         name = "tree-log",
         label = "Tree Log",
+        cargo_color = { r = 0.4, g = 0.28, b = 0.15 },
     },
     {
         type = "item",
         name = "wood-plank",
         label = "Wood Plank",
+        cargo_color = { r = 0.8, g = 0.65, b = 0.45 },
     },
     {
         type = "item",
         name = "iron-ore",
         label = "Iron Ore",
+        cargo_color = { r = 0.45, g = 0.3, b = 0.25 },
     },
     {
         type = "item",
         name = "metal",
         label = "Metal",
+        cargo_color = { r = 0.6, g = 0.62, b = 0.65 },
     },
     {
         type = "item",
@@ -94,15 +100,28 @@ data:extend {
         type = "item",
         name = "oil",
         label = "Oil",
+        cargo_color = { r = 0.2, g = 0.2, b = 0.25 },
     },
     {
         type = "item",
         name = "coal",
         label = "Coal",
+        cargo_color = { r = 0.12, g = 0.12, b = 0.12 },
+    },
+    {
+        type = "item",
+        name = "stone",
+        label = "Stone",
+        cargo_color = { r = 0.62, g = 0.62, b = 0.6 },
     },
     {
         type = "item",
         name = "polyester",
         label = "Polyester",
     },
+    {
+        type = "item",
+        name = "car",
+        label = "Car",
+    },
 }
@@ -15,5 +15,11 @@ data:extend {
         opening_hours = "18h -> 1h",
         capacity = 50,
         entry_fee = "10$",
+        event = {
+            hours = "20h -> 22h",
+            every_days = 2,
+            attendance = 40,
+        },
+        amenity = "leisure",
     },
 }
\ No newline at end of file
@@ -0,0 +1,52 @@
+data:extend {
+    {
+        type = "prop",
+        name = "rock",
+        label = "Rock",
+        asset = "sphere.glb",
+        tint = { r = 0.45, g = 0.43, b = 0.4 },
+        min_scale = 0.5,
+        max_scale = 2.0,
+        density = 2,
+        biome = "clearing",
+        max_slope = 0.6,
+    },
+    {
+        type = "prop",
+        name = "mossy-rock",
+        label = "Mossy Rock",
+        asset = "sphere.glb",
+        tint = { r = 0.3, g = 0.38, b = 0.25 },
+        min_scale = 0.4,
+        max_scale = 1.2,
+        density = 4,
+        biome = "forest",
+        max_slope = 0.8,
+    },
+    {
+        type = "prop",
+        name = "bush",
+        label = "Bush",
+        asset = "sphere.glb",
+        tint = { r = 0.2, g = 0.4, b = 0.15 },
+        min_scale = 0.8,
+        max_scale = 1.6,
+        density = 8,
+        biome = "any",
+        max_slope = 0.4,
+    },
+    {
+        type = "prop",
+        name = "roadside-hedge",
+        label = "Roadside Hedge",
+        asset = "sphere.glb",
+        tint = { r = 0.18, g = 0.35, b = 0.12 },
+        min_scale = 0.6,
+        max_scale = 0.9,
+        density = 4,
+        biome = "any",
+        max_slope = 0.2,
+        min_road_distance = 2,
+        max_road_distance = 6,
+    },
+}
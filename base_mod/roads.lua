@@ -0,0 +1,18 @@
+data:extend {
+    {
+        type = "road",
+        name = "road",
+        label = "Road",
+        price_per_meter = "50c",
+        bridge_multiplier = 3.0,
+        tunnel_multiplier = 5.0,
+    },
+    {
+        type = "road",
+        name = "rail",
+        label = "Rail",
+        price_per_meter = 1,
+        bridge_multiplier = 3.0,
+        tunnel_multiplier = 5.0,
+    }
+}
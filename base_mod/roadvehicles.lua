@@ -1,3 +1,4 @@
+-- the body styles share the simple car mesh until they get their own
 data:extend {
     {
         type = "road-vehicle",
@@ -5,23 +6,88 @@ data:extend {
         name = "simple_car",
         label = "Simple Car",
         max_speed = 50.0,
-        acceleration = 8.0,
-        deceleration = 12.0,
+        acceleration = 3.0,
+        deceleration = 6.0,
+        mass = 1.3,
+        power = 90.0,
+        length = 4.5,
+        popularity = 4.0,
         asset = "simple_car.glb",
         price = 100.0,
     },
+    {
+        type = "road-vehicle",
+        order = "a-2",
+        name = "compact_car",
+        label = "Compact Car",
+        max_speed = 42.0,
+        acceleration = 2.6,
+        deceleration = 6.0,
+        mass = 1.0,
+        power = 60.0,
+        length = 3.9,
+        popularity = 5.0,
+        asset = "simple_car.glb",
+        price = 70.0,
+    },
+    {
+        type = "road-vehicle",
+        order = "a-3",
+        name = "station_wagon",
+        label = "Station Wagon",
+        max_speed = 50.0,
+        acceleration = 2.8,
+        deceleration = 6.0,
+        mass = 1.5,
+        power = 100.0,
+        length = 4.8,
+        popularity = 2.0,
+        asset = "simple_car.glb",
+        price = 130.0,
+    },
+    {
+        type = "road-vehicle",
+        order = "a-4",
+        name = "suv",
+        label = "SUV",
+        max_speed = 50.0,
+        acceleration = 2.8,
+        deceleration = 5.5,
+        mass = 2.1,
+        power = 150.0,
+        length = 4.9,
+        popularity = 2.0,
+        asset = "simple_car.glb",
+        price = 180.0,
+    },
+    {
+        type = "road-vehicle",
+        order = "a-5",
+        name = "sports_car",
+        label = "Sports Car",
+        max_speed = 65.0,
+        acceleration = 4.5,
+        deceleration = 7.0,
+        mass = 1.4,
+        power = 250.0,
+        length = 4.3,
+        popularity = 0.5,
+        asset = "simple_car.glb",
+        price = 300.0,
+    },
     {
         type = "road-vehicle",
         order = "b-1",
         name = "simple_truck",
         label = "simple truck",
         max_speed = 22.0,
-        acceleration = 6.0,
-        deceleration = 10.0,
+        acceleration = 2.0,
+        deceleration = 4.5,
+        mass = 18.0,
+        power = 300.0,
+        capacity = 20,
+        length = 6.0,
         asset = "truck.glb",
         price = 100.0,
     }
 }
-
-
-
@@ -7,8 +7,9 @@ data:extend {
         length = 16.75,
         mass = 60,
         max_speed = 200.0,
-        acc_force = 2000.0,
-        dec_force = 360.0,
+        acc_force = 300.0,
+        dec_force = 90.0,
+        power = 4000.0,
         asset = "train.glb",
         price = 100,
     },
@@ -21,7 +22,7 @@ data:extend {
         mass = 40,
         max_speed = 200.0,
         acc_force = 0.0,
-        dec_force = 240.0,
+        dec_force = 60.0,
         asset = "wagon.glb",
         price = 100,
     },
@@ -34,7 +35,7 @@ data:extend {
         mass = 80,
         max_speed = 160.0,
         acc_force = 0.0,
-        dec_force = 480.0,
+        dec_force = 80.0,
         asset = "wagon_freight.glb",
         price = 100,
     },
@@ -47,7 +48,8 @@ data:extend {
         mass = 60,
         max_speed = 360.0,
         acc_force = 240.0,
-        dec_force = 360.0,
+        dec_force = 120.0,
+        power = 1200.0,
         asset = "passenger-emu-front.glb",
         price = 500,
     },
@@ -60,7 +62,8 @@ data:extend {
         mass = 60,
         max_speed = 360.0,
         acc_force = 240.0,
-        dec_force = 360.0,
+        dec_force = 120.0,
+        power = 1200.0,
         asset = "passenger-emu-middle.glb",
         price = 200,
     },
@@ -73,7 +76,8 @@ data:extend {
         mass = 60,
         max_speed = 360.0,
         acc_force = 240.0,
-        dec_force = 360.0,
+        dec_force = 120.0,
+        power = 1200.0,
         asset = "passenger-emu-rear.glb",
         price = 500,
     },
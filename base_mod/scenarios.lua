@@ -0,0 +1,70 @@
+data:extend {
+    {
+        type = "scenario",
+        name = "first-steps",
+        label = "Tutorial: First Steps",
+        description = "Learn to build roads, zone houses and open a first company.",
+        terrain_size = 20,
+        starting_money = 1000000,
+        tutorial = "first-steps",
+        objectives = {
+            {
+                label = "Reach 20 inhabitants",
+                condition = { kind = "population", at_least = 20 },
+            },
+        }
+    },
+    {
+        type = "scenario",
+        name = "bread-basket",
+        label = "Bread Basket",
+        description = "Start a small farming town and feed it with its own bread.",
+        terrain_size = 20,
+        starting_money = 200000,
+        unlocked = {"cereal-farm", "flour-factory", "bakery", "supermarket"},
+        rules = { utilities_required = false },
+        restrictions = {
+            {
+                name = "East bank",
+                kind = "no_build",
+                shape = { {7000, 0}, {10240, 0}, {10240, 10240}, {7000, 10240} },
+            },
+        },
+        objectives = {
+            {
+                label = "Reach 50 inhabitants",
+                condition = { kind = "population", at_least = 50 },
+                time_limit_days = 10,
+                reward_money = 50000,
+                reward_unlocks = {"vegetable-farm"},
+                reward_lifts = {"East bank"},
+            },
+            {
+                label = "Bake 100 bread per day",
+                condition = { kind = "production", item = "bread", at_least_per_day = 100 },
+                time_limit_days = 30,
+                reward_money = 100000,
+                reward_unlocks = {"animal-farm", "slaughterhouse", "meat-facility"},
+                reward_flags = {"harbor"},
+            },
+        }
+    },
+    {
+        type = "scenario",
+        name = "short-commute",
+        label = "Short Commute",
+        description = "Grow a city where nobody spends long on the way to work.",
+        starting_money = 500000,
+        objectives = {
+            {
+                label = "Reach 200 inhabitants",
+                condition = { kind = "population", at_least = 200 },
+                reward_money = 100000,
+            },
+            {
+                label = "Keep the average commute under 20 minutes",
+                condition = { kind = "commute", at_most_minutes = 20 },
+            },
+        }
+    }
+}
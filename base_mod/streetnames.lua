@@ -0,0 +1,18 @@
+data:extend {
+    {
+        type = "street-names",
+        name = "trees",
+        label = "Trees",
+        names = {"Elm", "Oak", "Maple", "Birch", "Cedar", "Pine", "Willow", "Ash", "Chestnut", "Linden",
+                 "Poplar", "Walnut", "Cherry", "Hazel", "Alder", "Spruce", "Juniper", "Magnolia"},
+        suffixes = {"Street", "Avenue", "Road", "Lane", "Drive"},
+    },
+    {
+        type = "street-names",
+        name = "landmarks",
+        label = "Landmarks",
+        names = {"Mill", "Church", "Station", "Market", "Bridge", "River", "Park", "Meadow", "Harbor",
+                 "Hill", "Orchard", "Quarry", "Garden", "Lake", "Forest", "Castle", "School", "Spring"},
+        suffixes = {"Street", "Road", "Way", "Boulevard", "Row"},
+    },
+}
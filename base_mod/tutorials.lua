@@ -0,0 +1,62 @@
+data:extend {
+    {
+        type = "tutorial",
+        name = "first-steps",
+        label = "First Steps",
+        steps = {
+            {
+                title = "Welcome",
+                text = "This tutorial walks you through the basics of building a city. You can skip a step or close the tutorial at any time.",
+            },
+            {
+                title = "Roads",
+                text = "Everything in the city starts with roads. Select the straight road tool in the toolbox.",
+                highlight = { widget = "toolbar_straight_road" },
+                condition = { kind = "tool_selected", tool = "toolbar_straight_road" },
+            },
+            {
+                title = "Build a road",
+                text = "Click on the terrain to start a road and click again to end it. Build at least 300 meters of roads.",
+                condition = { kind = "road_length", at_least = 300 },
+            },
+            {
+                title = "Zoning",
+                text = "Lots appear along the roads. Select the zoning tool to choose what can be built on them.",
+                highlight = { widget = "toolbar_housetool" },
+                condition = { kind = "tool_selected", tool = "toolbar_housetool" },
+            },
+            {
+                title = "Residential lots",
+                text = "Paint residential zoning over at least 5 lots.",
+                condition = { kind = "residential_lots", at_least = 5 },
+            },
+            {
+                title = "Houses",
+                text = "Houses are built on residential lots by themselves as people move in. Wait for the first 3 houses.",
+                condition = { kind = "houses", at_least = 3 },
+            },
+            {
+                title = "Companies",
+                text = "Inhabitants need jobs and goods. Select the companies tool.",
+                highlight = { widget = "toolbar_companies" },
+                condition = { kind = "tool_selected", tool = "toolbar_companies" },
+            },
+            {
+                title = "A first company",
+                text = "Pick a company and place it next to a road.",
+                condition = { kind = "companies", at_least = 1 },
+            },
+            {
+                title = "Economy",
+                text = "Open the economy window to follow what your city produces and trades.",
+                highlight = { widget = "window_economy" },
+                condition = { kind = "window_open", window = "economy" },
+            },
+            {
+                title = "Well done",
+                text = "You know the basics. Keep growing the city to reach 20 inhabitants.",
+                condition = { kind = "population", at_least = 20 },
+            },
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Named debug-draw channels that can be toggled at runtime.
+//! A channel is declared as a static next to the code it debugs and registered once at startup.
+//! Checking whether it is enabled is a single atomic load so the drawing code can stay in release builds.
+
+use geom::LinearColor;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+pub struct DebugChannel {
+    /// Dotted name shown in the debug window and used to persist the settings, e.g. `router.paths`
+    pub name: &'static str,
+    pub color: LinearColor,
+    enabled: AtomicBool,
+}
+
+impl DebugChannel {
+    pub const fn new(name: &'static str, color: LinearColor) -> Self {
+        Self {
+            name,
+            color,
+            enabled: AtomicBool::new(false),
+        }
+    }
+
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+}
+
+static CHANNELS: Mutex<Vec<&'static DebugChannel>> = Mutex::new(Vec::new());
+
+/// Registers the channels, registering a channel twice does nothing
+pub fn register(channels: &[&'static DebugChannel]) {
+    let mut registered = CHANNELS.lock().unwrap();
+    for &channel in channels {
+        if registered.iter().any(|c| c.name == channel.name) {
+            continue;
+        }
+        registered.push(channel);
+    }
+    registered.sort_by_key(|c| c.name);
+}
+
+/// All the registered channels, sorted by name
+pub fn channels() -> Vec<&'static DebugChannel> {
+    CHANNELS.lock().unwrap().clone()
+}
+
+pub fn find(name: &str) -> Option<&'static DebugChannel> {
+    CHANNELS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|c| c.name == name)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static A: DebugChannel = DebugChannel::new("test.b", LinearColor::RED);
+    static B: DebugChannel = DebugChannel::new("test.a", LinearColor::GREEN);
+
+    #[test]
+    fn register_and_toggle() {
+        register(&[&A, &B, &A]);
+
+        let names: Vec<_> = channels()
+            .into_iter()
+            .map(|c| c.name)
+            .filter(|n| n.starts_with("test."))
+            .collect();
+        assert_eq!(names, vec!["test.a", "test.b"]);
+
+        assert!(!A.enabled());
+        find("test.b").unwrap().set_enabled(true);
+        assert!(A.enabled());
+        assert!(!B.enabled());
+        assert!(find("test.c").is_none());
+    }
+}
@@ -0,0 +1,180 @@
+//! Content problems reported by the subsystems: missing assets, broken prototypes, failed loads.
+//! They are collected in a global sink so that players and mod authors find them in one place
+//! rather than scattered across the log. Reporting is thread-safe, the loaders report from their
+//! worker threads.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Diagnostics kept at most, the oldest are dropped past that
+const MAX_DIAGNOSTICS: usize = 1000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Only logged, not shown to the player
+    Debug,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn level(self) -> log::Level {
+        match self {
+            Severity::Debug => log::Level::Debug,
+            Severity::Warning => log::Level::Warn,
+            Severity::Error => log::Level::Error,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Subsystem that reported it, e.g. "prototypes" or "audio"
+    pub source: &'static str,
+    pub message: String,
+    /// The file the problem is about
+    pub asset: Option<PathBuf>,
+    /// Name of the prototype the problem is about
+    pub prototype: Option<String>,
+    /// Times the same problem was reported
+    pub count: u32,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, source: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            source,
+            message: message.into(),
+            asset: None,
+            prototype: None,
+            count: 1,
+        }
+    }
+
+    pub fn warning(source: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, source, message)
+    }
+
+    pub fn error(source: &'static str, message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, source, message)
+    }
+
+    pub fn asset(mut self, path: impl Into<PathBuf>) -> Self {
+        self.asset = Some(path.into());
+        self
+    }
+
+    pub fn prototype(mut self, name: impl Into<String>) -> Self {
+        self.prototype = Some(name.into());
+        self
+    }
+
+    /// Logs it and adds it to the sink, see [`report`]
+    pub fn report(self) {
+        report(self);
+    }
+
+    fn same_problem(&self, other: &Diagnostic) -> bool {
+        self.severity == other.severity
+            && self.source == other.source
+            && self.message == other.message
+            && self.asset == other.asset
+            && self.prototype == other.prototype
+    }
+
+    /// The source, message, prototype and asset on one line
+    fn describe(&self) -> String {
+        let mut line = format!("{}: {}", self.source, self.message);
+        if let Some(ref proto) = self.prototype {
+            line += &format!(" (prototype {proto})");
+        }
+        if let Some(ref asset) = self.asset {
+            line += &format!(" ({})", asset.display());
+        }
+        line
+    }
+
+    /// One line with everything known about the problem, to be pasted in a bug report
+    pub fn to_line(&self) -> String {
+        format!("[{:?}] {}", self.severity, self.describe())
+    }
+}
+
+static DIAGNOSTICS: Mutex<Vec<Diagnostic>> = Mutex::new(Vec::new());
+/// Incremented when a new non-debug diagnostic is reported
+static REVISION: AtomicU64 = AtomicU64::new(0);
+
+/// Logs the diagnostic and adds it to the sink.
+/// A problem reported again only increments the count of the first report, and is not logged.
+pub fn report(diagnostic: Diagnostic) {
+    let mut diagnostics = DIAGNOSTICS.lock().unwrap();
+    if let Some(d) = diagnostics.iter_mut().find(|d| d.same_problem(&diagnostic)) {
+        d.count += diagnostic.count;
+        return;
+    }
+
+    log::log!(diagnostic.severity.level(), "{}", diagnostic.describe());
+    if diagnostic.severity > Severity::Debug {
+        REVISION.fetch_add(1, Ordering::Relaxed);
+    }
+    if diagnostics.len() >= MAX_DIAGNOSTICS {
+        diagnostics.remove(0);
+    }
+    diagnostics.push(diagnostic);
+}
+
+/// All the diagnostics, in the order they were reported
+pub fn diagnostics() -> Vec<Diagnostic> {
+    DIAGNOSTICS.lock().unwrap().clone()
+}
+
+/// Number of the diagnostics that are shown to the player, without copying them
+pub fn shown_count() -> usize {
+    DIAGNOSTICS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|d| d.severity > Severity::Debug)
+        .count()
+}
+
+/// Changes each time a new non-debug diagnostic is reported, to tell when there are new ones
+pub fn revision() -> u64 {
+    REVISION.load(Ordering::Relaxed)
+}
+
+pub fn clear() {
+    DIAGNOSTICS.lock().unwrap().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_reports_are_merged() {
+        let before = revision();
+        let d = Diagnostic::warning("test.merge", "missing").asset("assets/missing.png");
+        for _ in 0..3 {
+            d.clone().report();
+        }
+        Diagnostic::new(Severity::Debug, "test.merge", "quiet").report();
+
+        let ours: Vec<_> = diagnostics()
+            .into_iter()
+            .filter(|d| d.source == "test.merge")
+            .collect();
+        assert_eq!(ours.len(), 2);
+        assert_eq!(ours[0].count, 3);
+        assert!(shown_count() >= 1);
+        // other tests may report concurrently, the revision only grows
+        assert!(revision() > before);
+        assert_eq!(
+            ours[0].to_line(),
+            "[Warning] test.merge: missing (assets/missing.png)"
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
 
 mod chunkid;
+pub mod debug_draw;
+pub mod diagnostics;
 pub mod error;
 mod hash;
 pub mod history;
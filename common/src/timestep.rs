@@ -14,6 +14,13 @@ pub struct Timestep {
     acc: Duration,
     real_delta: Duration,
     pub period: Duration,
+    /// Real time between two batches of updates, the updates due meanwhile are run together.
+    /// Zero runs them every frame. The period is unchanged so the speed of the game is too.
+    pub batch: Duration,
+    batch_acc: Duration,
+    batch_due: bool,
+    /// The updates could not keep up this frame and the time left was dropped
+    pub lagging: bool,
 }
 
 impl Default for Timestep {
@@ -31,6 +38,10 @@ impl Timestep {
             acc: Default::default(),
             real_delta: Default::default(),
             period,
+            batch: Duration::ZERO,
+            batch_acc: Default::default(),
+            batch_due: false,
+            lagging: false,
         }
     }
 
@@ -40,19 +51,71 @@ impl Timestep {
             self.real_delta = self.period;
         }
         self.last_time = Instant::now();
+        self.lagging = false;
 
         self.acc += self.real_delta * warp;
+
+        self.batch_acc += self.real_delta;
+        self.batch_due = self.batch_acc >= self.batch;
+        if self.batch_due {
+            // a late batch does not make the next one come sooner
+            self.batch_acc = (self.batch_acc - self.batch).min(self.batch);
+        }
+    }
+
+    /// How far the time is between the last updates and the next ones, from 0 to 1.
+    /// Used to interpolate what is drawn between the states before and after the last updates.
+    pub fn alpha(&self) -> f32 {
+        if self.lagging {
+            return 1.0;
+        }
+        if self.batch > Duration::ZERO {
+            return (self.batch_acc.as_secs_f32() / self.batch.as_secs_f32()).min(1.0);
+        }
+        (self.acc.as_secs_f32() / self.period.as_secs_f32()).min(1.0)
     }
 
     pub fn tick(&mut self) -> bool {
-        if self.acc < self.period {
+        if !self.batch_due || self.acc < self.period {
             return false;
         }
         if self.last_time.elapsed() > Timestep::MAXTIME {
             self.acc = Default::default();
+            self.lagging = true;
             return true;
         }
         self.acc -= self.period;
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ticks run over `frames` frames of 16ms
+    fn run(batch: Duration, frames: u32) -> (u32, u32) {
+        let mut step = Timestep::default();
+        step.batch = batch;
+        let (mut ticks, mut batches) = (0, 0);
+        for _ in 0..frames {
+            step.last_time = Instant::now() - Duration::from_millis(16);
+            step.prepare_frame(1);
+            let before = ticks;
+            while step.tick() {
+                ticks += 1;
+            }
+            batches += (ticks > before) as u32;
+        }
+        (ticks, batches)
+    }
+
+    #[test]
+    fn batches_keep_the_speed() {
+        let (ticks, _) = run(Duration::ZERO, 100);
+        let (batched_ticks, batches) = run(Duration::from_millis(50), 100);
+        assert!((79..=80).contains(&ticks), "{}", ticks);
+        assert!((79..=80).contains(&batched_ticks), "{}", batched_ticks);
+        assert!((31..=33).contains(&batches), "{}", batches);
+    }
+}
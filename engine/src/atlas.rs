@@ -0,0 +1,419 @@
+//! Packs the small textures of the mesh materials into shared atlas pages, so that the
+//! primitives using them can share a bind group and be drawn together.
+//!
+//! Each page holds three textures with the same layout: albedo, normal map and
+//! metallic/roughness. The metallic and roughness factors are baked in the page, so that all the
+//! materials of a page only differ by where their textures are.
+
+use std::sync::Arc;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, RgbaImage};
+use wgpu::{AddressMode, FilterMode, ImageCopyTexture, ImageDataLayout, SamplerDescriptor};
+
+use common::FastMap;
+
+use crate::{GfxContext, GpuCategory, Material, MaterialID, Texture, TextureBuilder};
+
+/// Width and height of a page
+pub const ATLAS_PAGE_SIZE: u32 = 2048;
+/// Textures bigger than this on any side keep their own texture
+pub const ATLAS_MAX_TEXTURE_SIZE: u32 = 256;
+/// Texels duplicated around each texture so that filtering does not bleed into its neighbours
+pub const ATLAS_PADDING: u32 = 8;
+/// Mip levels of the pages, the padding still separates the textures in the smallest one
+const ATLAS_MIP_LEVELS: u32 = 4;
+/// The textures start on multiples of this so that they do not share texels in any mip level
+const ATLAS_ALIGN: u32 = 1 << (ATLAS_MIP_LEVELS - 1);
+
+/// Where the textures of a material are in the atlas
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasRegion {
+    pub page: usize,
+    /// Offset and scale from the texture UVs to the page UVs
+    pub uv_rect: [f32; 4],
+    pub has_normal: bool,
+}
+
+impl AtlasRegion {
+    /// Moves UVs between 0 and 1 to the page
+    pub fn page_uv(&self, uv: [f32; 2]) -> [f32; 2] {
+        [
+            self.uv_rect[0] + uv[0] * self.uv_rect[2],
+            self.uv_rect[1] + uv[1] * self.uv_rect[3],
+        ]
+    }
+}
+
+/// The decoded textures of a material
+pub struct AtlasTextures<'a> {
+    pub albedo: (&'a DynamicImage, &'a SamplerDescriptor<'static>),
+    pub normal: Option<(&'a DynamicImage, &'a SamplerDescriptor<'static>)>,
+    pub metallic_roughness: Option<(&'a DynamicImage, &'a SamplerDescriptor<'static>)>,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Identifies the material, to pack it once for all the meshes using it
+    pub hash: u64,
+}
+
+impl AtlasTextures<'_> {
+    fn images(&self) -> impl Iterator<Item = (&DynamicImage, &SamplerDescriptor<'static>)> {
+        std::iter::once(self.albedo)
+            .chain(self.normal)
+            .chain(self.metallic_roughness)
+    }
+
+    /// Small opaque textures that repeat and are filtered linearly can go in the atlas,
+    /// the plain colors always can
+    fn fits(&self) -> bool {
+        let opaque = !self.albedo.0.color().has_alpha()
+            || self.albedo.0.to_rgba8().pixels().all(|p| p.0[3] == 255);
+        opaque
+            && self.images().all(|(img, sampler)| {
+                let plain = img.width() == 1 && img.height() == 1;
+                let small =
+                    img.width() <= ATLAS_MAX_TEXTURE_SIZE && img.height() <= ATLAS_MAX_TEXTURE_SIZE;
+                let repeats = sampler.address_mode_u == AddressMode::Repeat
+                    && sampler.address_mode_v == AddressMode::Repeat;
+                let linear = sampler.mag_filter == FilterMode::Linear
+                    && sampler.min_filter == FilterMode::Linear;
+                plain || (small && repeats && linear)
+            })
+    }
+
+    /// Size of the tile, the smaller textures are stretched to the biggest one
+    fn size(&self) -> (u32, u32) {
+        self.images().fold((1, 1), |(w, h), (img, _)| {
+            (w.max(img.width()), h.max(img.height()))
+        })
+    }
+}
+
+/// Packs rectangles in rows of similar heights
+#[derive(Debug)]
+struct ShelfPacker {
+    size: u32,
+    /// Top, height and used width of each row
+    shelves: Vec<(u32, u32, u32)>,
+}
+
+impl ShelfPacker {
+    fn new(size: u32) -> Self {
+        Self {
+            size,
+            shelves: vec![],
+        }
+    }
+
+    /// Top left corner of a free space of that size, None if the page is full
+    fn pack(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        if w > self.size || h > self.size {
+            return None;
+        }
+        let size = self.size;
+        let best = self
+            .shelves
+            .iter_mut()
+            .filter(|(_, height, used)| *height >= h && used + w <= size)
+            .min_by_key(|(_, height, _)| *height);
+        if let Some((top, _, used)) = best {
+            let x = *used;
+            *used += w;
+            return Some((x, *top));
+        }
+
+        let top = self
+            .shelves
+            .last()
+            .map_or(0, |(top, height, _)| top + height);
+        if top + h > self.size {
+            return None;
+        }
+        self.shelves.push((top, h, w));
+        Some((0, top))
+    }
+}
+
+/// Copies the image with `pad` texels around it, taken from the opposite side as the textures
+/// repeat, and grows it to the alignment of the mip levels
+fn pad_tile(img: &RgbaImage, pad: u32) -> RgbaImage {
+    let (w, h) = img.dimensions();
+    let tw = (w + 2 * pad).next_multiple_of(ATLAS_ALIGN);
+    let th = (h + 2 * pad).next_multiple_of(ATLAS_ALIGN);
+    RgbaImage::from_fn(tw, th, |x, y| {
+        let sx = (x as i64 - pad as i64).rem_euclid(w as i64) as u32;
+        let sy = (y as i64 - pad as i64).rem_euclid(h as i64) as u32;
+        *img.get_pixel(sx, sy)
+    })
+}
+
+/// The metallic/roughness texture with the factors applied, as the pages have none
+fn bake_metallic_roughness(
+    tex: Option<&DynamicImage>,
+    (w, h): (u32, u32),
+    metallic: f32,
+    roughness: f32,
+) -> RgbaImage {
+    let mut img = match tex {
+        Some(tex) => stretch(tex, (w, h)),
+        None => RgbaImage::from_pixel(w, h, image::Rgba([0, 255, 255, 255])),
+    };
+    for p in img.pixels_mut() {
+        p.0[1] = (p.0[1] as f32 * roughness).round().clamp(0.0, 255.0) as u8;
+        p.0[2] = (p.0[2] as f32 * metallic).round().clamp(0.0, 255.0) as u8;
+        p.0[3] = 255;
+    }
+    img
+}
+
+fn stretch(img: &DynamicImage, (w, h): (u32, u32)) -> RgbaImage {
+    let img = img.to_rgba8();
+    if img.dimensions() == (w, h) {
+        return img;
+    }
+    image::imageops::resize(&img, w, h, FilterType::Triangle)
+}
+
+struct AtlasPage {
+    packer: ShelfPacker,
+    albedo: Arc<Texture>,
+    normal: Arc<Texture>,
+    metallic_roughness: Arc<Texture>,
+    /// Materials for the primitives whose UVs were moved to the page, without and with normal map
+    merged: [Option<MaterialID>; 2],
+}
+
+impl AtlasPage {
+    fn new(gfx: &GfxContext) -> Self {
+        let page = |format, label| {
+            Arc::new(
+                TextureBuilder::empty(ATLAS_PAGE_SIZE, ATLAS_PAGE_SIZE, 1, format)
+                    .with_label(label)
+                    .with_category(GpuCategory::Meshes)
+                    .with_fixed_mipmaps(ATLAS_MIP_LEVELS)
+                    .with_sampler(SamplerDescriptor {
+                        label: Some("atlas sampler"),
+                        address_mode_u: AddressMode::ClampToEdge,
+                        address_mode_v: AddressMode::ClampToEdge,
+                        mag_filter: FilterMode::Linear,
+                        min_filter: FilterMode::Linear,
+                        mipmap_filter: FilterMode::Linear,
+                        ..Default::default()
+                    })
+                    .with_no_anisotropy()
+                    .build_no_queue(&gfx.device),
+            )
+        };
+        Self {
+            packer: ShelfPacker::new(ATLAS_PAGE_SIZE),
+            albedo: page(wgpu::TextureFormat::Rgba8UnormSrgb, "atlas albedo"),
+            normal: page(wgpu::TextureFormat::Rgba8Unorm, "atlas normal"),
+            metallic_roughness: page(wgpu::TextureFormat::Rgba8Unorm, "atlas metallic roughness"),
+            merged: [None, None],
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TextureAtlas {
+    pages: Vec<AtlasPage>,
+    /// Materials already packed, by the hash of their textures
+    packed: FastMap<u64, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    pub fn n_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn n_materials(&self) -> usize {
+        self.packed.len()
+    }
+}
+
+impl GfxContext {
+    /// Packs the textures of a material, None if they do not fit in the atlas
+    pub(crate) fn pack_in_atlas(&mut self, textures: AtlasTextures) -> Option<AtlasRegion> {
+        if let Some(region) = self.atlas.packed.get(&textures.hash) {
+            return Some(*region);
+        }
+        if !textures.fits() {
+            return None;
+        }
+
+        let size = textures.size();
+        let albedo = pad_tile(&stretch(textures.albedo.0, size), ATLAS_PADDING);
+        let (tw, th) = albedo.dimensions();
+
+        let mut spot = None;
+        for (i, page) in self.atlas.pages.iter_mut().enumerate() {
+            if let Some(pos) = page.packer.pack(tw, th) {
+                spot = Some((i, pos));
+                break;
+            }
+        }
+        let (page_id, (x, y)) = match spot {
+            Some(spot) => spot,
+            None => {
+                let mut page = AtlasPage::new(self);
+                let pos = page.packer.pack(tw, th)?;
+                self.atlas.pages.push(page);
+                (self.atlas.pages.len() - 1, pos)
+            }
+        };
+
+        let page = &self.atlas.pages[page_id];
+        write_tile(&self.queue, &page.albedo, &albedo, (x, y));
+        if let Some((normal, _)) = textures.normal {
+            let normal = pad_tile(&stretch(normal, size), ATLAS_PADDING);
+            write_tile(&self.queue, &page.normal, &normal, (x, y));
+        }
+        let mr = bake_metallic_roughness(
+            textures.metallic_roughness.map(|(img, _)| img),
+            size,
+            textures.metallic,
+            textures.roughness,
+        );
+        write_tile(
+            &self.queue,
+            &page.metallic_roughness,
+            &pad_tile(&mr, ATLAS_PADDING),
+            (x, y),
+        );
+
+        let s = ATLAS_PAGE_SIZE as f32;
+        let region = AtlasRegion {
+            page: page_id,
+            uv_rect: [
+                (x + ATLAS_PADDING) as f32 / s,
+                (y + ATLAS_PADDING) as f32 / s,
+                size.0 as f32 / s,
+                size.1 as f32 / s,
+            ],
+            has_normal: textures.normal.is_some(),
+        };
+        self.atlas.packed.insert(textures.hash, region);
+        self.perf
+            .atlas(self.atlas.n_pages(), self.atlas.n_materials());
+        Some(region)
+    }
+
+    /// Material of a packed texture, it uses the whole page and moves the UVs in the shader
+    pub(crate) fn atlas_region_material(&mut self, region: AtlasRegion) -> MaterialID {
+        let page = &self.atlas.pages[region.page];
+        let mat = Material::new_in_atlas(
+            &self.device,
+            &page.albedo,
+            &page.metallic_roughness,
+            region.has_normal.then_some(&*page.normal),
+            Some(region.uv_rect),
+            &self.null_texture,
+        );
+        self.register_material(mat)
+    }
+
+    /// Material shared by the primitives of a page whose UVs were already moved to the page,
+    /// they can all be drawn at once
+    pub(crate) fn atlas_page_material(&mut self, page_id: usize, has_normal: bool) -> MaterialID {
+        if let Some(id) = self.atlas.pages[page_id].merged[has_normal as usize] {
+            return id;
+        }
+        let page = &self.atlas.pages[page_id];
+        let mat = Material::new_in_atlas(
+            &self.device,
+            &page.albedo,
+            &page.metallic_roughness,
+            has_normal.then_some(&*page.normal),
+            None,
+            &self.null_texture,
+        );
+        let id = self.register_material(mat);
+        self.atlas.pages[page_id].merged[has_normal as usize] = Some(id);
+        id
+    }
+}
+
+/// Writes the tile and its mip levels in the page
+fn write_tile(queue: &wgpu::Queue, page: &Texture, tile: &RgbaImage, (x, y): (u32, u32)) {
+    let (w, h) = tile.dimensions();
+    for level in 0..ATLAS_MIP_LEVELS {
+        let mip;
+        let data = if level == 0 {
+            tile
+        } else {
+            mip = image::imageops::resize(tile, w >> level, h >> level, FilterType::Triangle);
+            &mip
+        };
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &page.texture,
+                mip_level: level,
+                origin: wgpu::Origin3d {
+                    x: x >> level,
+                    y: y >> level,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            data.as_raw(),
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * data.width()),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: data.width(),
+                height: data.height(),
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_tiles_do_not_overlap() {
+        let mut packer = ShelfPacker::new(64);
+        let mut rects = vec![];
+        for (w, h) in [(24, 24), (32, 16), (24, 24), (40, 24), (8, 8), (64, 16)] {
+            let (x, y) = packer.pack(w, h).unwrap();
+            assert!(x + w <= 64 && y + h <= 64);
+            rects.push((x, y, w, h));
+        }
+        for (i, a) in rects.iter().enumerate() {
+            for b in &rects[i + 1..] {
+                let apart =
+                    a.0 + a.2 <= b.0 || b.0 + b.2 <= a.0 || a.1 + a.3 <= b.1 || b.1 + b.3 <= a.1;
+                assert!(apart, "{:?} overlaps {:?}", a, b);
+            }
+        }
+        assert_eq!(packer.pack(64, 64), None);
+        assert_eq!(packer.pack(65, 1), None);
+    }
+
+    #[test]
+    fn padding_repeats_the_texture() {
+        let img = RgbaImage::from_fn(2, 1, |x, _| image::Rgba([x as u8, 0, 0, 255]));
+        let tile = pad_tile(&img, 3);
+        assert_eq!(tile.dimensions(), (ATLAS_ALIGN, ATLAS_ALIGN));
+        // the texture starts after the padding, the texel before it is its right edge
+        assert_eq!(tile.get_pixel(3, 3).0[0], 0);
+        assert_eq!(tile.get_pixel(4, 3).0[0], 1);
+        assert_eq!(tile.get_pixel(2, 0).0[0], 1);
+        assert_eq!(tile.get_pixel(5, 7).0[0], 0);
+    }
+
+    #[test]
+    fn factors_are_baked_in_metallic_roughness() {
+        let mr = bake_metallic_roughness(None, (2, 2), 0.0, 0.5);
+        assert_eq!(mr.get_pixel(1, 1).0, [0, 128, 0, 255]);
+
+        let tex =
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, image::Rgba([0, 200, 100, 255])));
+        let mr = bake_metallic_roughness(Some(&tex), (1, 1), 0.5, 1.0);
+        assert_eq!(mr.get_pixel(0, 0).0, [0, 200, 50, 255]);
+    }
+}
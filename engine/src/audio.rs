@@ -1,3 +1,4 @@
+use common::diagnostics::Diagnostic;
 use common::{FastMap, FastSet};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use oddio::{
@@ -126,13 +127,23 @@ impl AudioContext {
         let buf = match common::saveload::load_raw(&p) {
             Ok(x) => x,
             Err(e) => {
-                log::error!("Could not load sound {}: {}", name, e);
+                Diagnostic::error("audio", format!("could not load sound {name}: {e}"))
+                    .asset(&p)
+                    .report();
                 return None;
             }
         };
 
-        let mut decoder =
-            lewton::inside_ogg::OggStreamReader::new(std::io::Cursor::new(buf)).ok()?;
+        let cursor = std::io::Cursor::new(buf);
+        let mut decoder = match lewton::inside_ogg::OggStreamReader::new(cursor) {
+            Ok(x) => x,
+            Err(e) => {
+                Diagnostic::error("audio", format!("could not decode sound {name}: {e}"))
+                    .asset(&p)
+                    .report();
+                return None;
+            }
+        };
 
         let mut samples = vec![];
         let mono = decoder.ident_hdr.audio_channels == 1;
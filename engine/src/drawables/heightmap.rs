@@ -11,7 +11,7 @@ use geom::{vec2, vec3, Camera, HeightmapChunk, Intersect3, Matrix4, Vec2, AABB3}
 
 use crate::{
     bg_layout_litmesh, pbuffer::PBuffer, CompiledModule, Drawable, FrameContext, GfxContext,
-    IndexType, PipelineBuilder, PipelineKey, Texture, TextureBuilder, Uniform, TL,
+    GpuCategory, IndexType, PipelineBuilder, PipelineKey, Texture, TextureBuilder, Uniform, TL,
 };
 
 const LOD: usize = 5;
@@ -19,6 +19,9 @@ const LOD_MIN_DIST_LOG2: f32 = 9.0; // 2^9 = 512, meaning until 1048m away, we u
 const MAX_HEIGHT: f32 = 2008.0;
 const MIN_HEIGHT: f32 = -40.0;
 const UPSCALE_LOD: usize = 2; // amount of LOD that are superior to base heightmap data
+const STITCHES: usize = 16; // every combination of the 4 stitch directions
+
+type StitchedIndices = [(PBuffer, u32); STITCHES];
 
 /// CSIZE is the size of a chunk in meters
 /// CRESOLUTION is the resolution of a chunk, in vertices, at the chunk data level (not LOD0 since we upsample)
@@ -26,9 +29,20 @@ pub struct HeightmapRender<const CSIZE: u32, const CRESOLUTION: usize> {
     heightmap_tex: Arc<Texture>,
     normal_tex: Arc<Texture>,
 
-    indices: [(PBuffer, u32); LOD],
+    /// One index buffer per LOD and per combination of stitched edges
+    indices: Arc<[StitchedIndices; LOD]>,
+    /// The instances of a LOD are sorted by stitch flags, see `stitch_ranges`
     instances: [(PBuffer, u32); LOD],
+    /// Start of the instances of each stitch combination, the last one is the total
+    stitch_ranges: [[u32; STITCHES + 1]; LOD],
     bgs: Arc<[wgpu::BindGroup; LOD]>,
+    /// The bind groups are rebuilt when the samplers change
+    samplers_generation: u32,
+    /// Most detailed mips left out of the grass and cliff textures to save memory
+    dropped_mips: u32,
+    grass: Arc<Texture>,
+    cliff: Arc<Texture>,
+    chunk_unis: [Uniform<HeightmapChunkData>; LOD],
     w: u32,
     h: u32,
 
@@ -40,8 +54,9 @@ pub struct HeightmapRender<const CSIZE: u32, const CRESOLUTION: usize> {
 
 pub struct HeightmapPrepared {
     heightmapbgs: Arc<[wgpu::BindGroup; LOD]>,
-    indices: [(PBuffer, u32); LOD],
+    indices: Arc<[StitchedIndices; LOD]>,
     instances: [(PBuffer, u32); LOD],
+    stitch_ranges: [[u32; STITCHES + 1]; LOD],
 }
 
 impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUTION> {
@@ -54,10 +69,10 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
             1 << LOD
         );
 
-        let grass = gfx.texture("assets/sprites/grass.jpg", "grass");
-        let cliff = gfx.texture("assets/sprites/cliff.jpg", "cliff");
+        let grass = Self::splat_texture(gfx, "assets/sprites/grass.jpg", "grass");
+        let cliff = Self::splat_texture(gfx, "assets/sprites/cliff.jpg", "cliff");
 
-        let indices = Self::generate_indices_mesh(gfx);
+        let indices = Arc::new(Self::generate_indices_mesh(gfx));
 
         let heightmap_tex = TextureBuilder::empty(
             w * Self::LOD0_RESOLUTION as u32,
@@ -66,6 +81,7 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
             TextureFormat::R16Uint,
         )
         .with_fixed_mipmaps(LOD as u32)
+        .with_category(GpuCategory::Terrain)
         .with_sampler(wgpu::SamplerDescriptor {
             label: Some("heightmap sampler"),
             mag_filter: FilterMode::Linear,
@@ -85,6 +101,7 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
             TextureFormat::R16Uint,
         )
         .with_fixed_mipmaps(LOD as u32)
+        .with_category(GpuCategory::Terrain)
         .with_sampler(wgpu::SamplerDescriptor {
             label: Some("heightmap normals sampler"),
             mag_filter: FilterMode::Linear,
@@ -97,10 +114,9 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
         .with_no_anisotropy()
         .build(&gfx.device, &gfx.queue);
 
-        let mut bgs = vec![];
-        for lod in 0..LOD {
+        let chunk_unis = collect_arrlod((0..LOD).map(|lod| {
             let scale = 1 << lod as u32;
-            let uni = Uniform::new(
+            Uniform::new(
                 HeightmapChunkData {
                     lod: lod as u32,
                     lod_pow2: scale,
@@ -111,25 +127,14 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
                     inv_cell_size: Self::LOD0_RESOLUTION as f32 / CSIZE as f32,
                 },
                 &gfx.device,
-            );
+            )
+        }));
 
-            let texs = &[&heightmap_tex, &normals_tex, &grass, &cliff];
-            let mut bg_entries = Vec::with_capacity(12);
-            bg_entries.extend(Texture::multi_bindgroup_entries(0, texs));
-            bg_entries.push(uni.bindgroup_entry(8));
-            bgs.push(
-                gfx.device.create_bind_group(&BindGroupDescriptor {
-                    layout: &gfx
-                        .get_pipeline(HeightmapPipeline {
-                            depth: false,
-                            smap: false,
-                        })
-                        .get_bind_group_layout(1),
-                    entries: &bg_entries,
-                    label: Some("heightmap bindgroup"),
-                }),
-            );
-        }
+        let bgs = Self::create_bgs(
+            gfx,
+            [&heightmap_tex, &normals_tex, &grass, &cliff],
+            &chunk_unis,
+        );
 
         defer!(log::info!("finished init of heightmap render"));
         Self {
@@ -143,16 +148,73 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
             downsample_pipeline: resample_pipeline(gfx, &heightmap_tex, "downsample"),
             upsample_pipeline: resample_pipeline(gfx, &heightmap_tex, "upsample"),
 
-            bgs: Arc::new(collect_arrlod(bgs)),
+            bgs: Arc::new(bgs),
+            samplers_generation: gfx.samplers.generation(),
+            dropped_mips: gfx.terrain_dropped_mips(),
             heightmap_tex: Arc::new(heightmap_tex),
             normal_tex: Arc::new(normals_tex),
+            grass,
+            cliff,
+            chunk_unis,
             indices,
             w,
             h,
-            instances: collect_arrlod((0..LOD).map(|_| (PBuffer::new(BufferUsages::VERTEX), 0))),
+            instances: collect_arrlod((0..LOD).map(|_| {
+                let buf = PBuffer::new(BufferUsages::VERTEX).with_category(GpuCategory::Terrain);
+                (buf, 0)
+            })),
+            stitch_ranges: [[0; STITCHES + 1]; LOD],
         }
     }
 
+    /// The grass and cliff textures are not shared through the texture cache, so that
+    /// their detail can be lowered when the GPU memory runs out
+    fn splat_texture(gfx: &GfxContext, path: &str, label: &'static str) -> Arc<Texture> {
+        Arc::new(
+            TextureBuilder::from_path(path)
+                .with_dropped_mips(gfx.terrain_dropped_mips())
+                .with_label(label)
+                .with_mipmaps(&gfx.mipmap_gen)
+                .with_category(GpuCategory::Terrain)
+                .build(&gfx.device, &gfx.queue),
+        )
+    }
+
+    /// The heightmap, normals, grass and cliff textures, then the data of the LOD
+    fn create_bgs(
+        gfx: &GfxContext,
+        texs: [&Texture; 4],
+        chunk_unis: &[Uniform<HeightmapChunkData>; LOD],
+    ) -> [wgpu::BindGroup; LOD] {
+        let samplers = texs.map(|tex| gfx.samplers.get(&gfx.device, &tex.sampler_desc));
+        let layout = gfx
+            .get_pipeline(HeightmapPipeline {
+                depth: false,
+                smap: false,
+            })
+            .get_bind_group_layout(1);
+
+        collect_arrlod(chunk_unis.iter().map(|uni| {
+            let mut bg_entries = Vec::with_capacity(12);
+            for (i, (tex, sampler)) in texs.iter().zip(&samplers).enumerate() {
+                bg_entries.push(wgpu::BindGroupEntry {
+                    binding: (i * 2) as u32,
+                    resource: wgpu::BindingResource::TextureView(&tex.view),
+                });
+                bg_entries.push(wgpu::BindGroupEntry {
+                    binding: (i * 2 + 1) as u32,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                });
+            }
+            bg_entries.push(uni.bindgroup_entry(8));
+            gfx.device.create_bind_group(&BindGroupDescriptor {
+                layout: &layout,
+                entries: &bg_entries,
+                label: Some("heightmap bindgroup"),
+            })
+        }))
+    }
+
     pub fn update_chunk(
         &mut self,
         gfx: &mut GfxContext,
@@ -206,6 +268,28 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
         profiling::scope!("heightmap::draw_heightmap");
         let eye = cam.eye();
 
+        if self.dropped_mips != fctx.gfx.terrain_dropped_mips() {
+            self.dropped_mips = fctx.gfx.terrain_dropped_mips();
+            self.grass = Self::splat_texture(fctx.gfx, "assets/sprites/grass.jpg", "grass");
+            self.cliff = Self::splat_texture(fctx.gfx, "assets/sprites/cliff.jpg", "cliff");
+            // forces the bind groups to be rebuilt with the new textures
+            self.samplers_generation = u32::MAX;
+        }
+
+        if self.samplers_generation != fctx.gfx.samplers.generation() {
+            self.samplers_generation = fctx.gfx.samplers.generation();
+            self.bgs = Arc::new(Self::create_bgs(
+                fctx.gfx,
+                [
+                    &self.heightmap_tex,
+                    &self.normal_tex,
+                    &self.grass,
+                    &self.cliff,
+                ],
+                &self.chunk_unis,
+            ));
+        }
+
         let mut instances = vec![Vec::<HeightmapInstance>::new(); LOD];
 
         // We calculate lod in 2 passes to be able to generate the stitches
@@ -261,70 +345,116 @@ impl<const CSIZE: u32, const CRESOLUTION: usize> HeightmapRender<CSIZE, CRESOLUT
             }
         }
 
-        for (lod, instance) in instances.into_iter().enumerate() {
+        // group the instances by stitch flags so each group can be drawn with its own index buffer
+        let mut n_chunks = 0;
+        for (lod, mut instance) in instances.into_iter().enumerate() {
+            instance.sort_unstable_by_key(|i| i.stitch_dir_flags);
+
+            let ranges = &mut self.stitch_ranges[lod];
+            let mut start = 0;
+            for (flags, range) in ranges.iter_mut().enumerate() {
+                *range = start as u32;
+                start += instance[start..]
+                    .iter()
+                    .take_while(|i| i.stitch_dir_flags == flags as u32)
+                    .count();
+            }
+
+            n_chunks += instance.len();
             self.instances[lod].1 = instance.len() as u32;
             self.instances[lod]
                 .0
                 .write(fctx.gfx, bytemuck::cast_slice(&instance));
         }
 
+        fctx.gfx
+            .perf
+            .heightmap_full_triangles(self.indices[0][0].1 as usize / 3 * n_chunks);
+
         fctx.objs.push(Box::new(HeightmapPrepared {
             heightmapbgs: self.bgs.clone(),
             indices: self.indices.clone(),
             instances: self.instances.clone(),
+            stitch_ranges: self.stitch_ranges,
         }));
     }
 
-    fn generate_indices_mesh(gfx: &GfxContext) -> [(PBuffer, u32); LOD] {
+    /// Generates the index buffers of every LOD and every combination of stitched edges.
+    /// On a stitched edge, the odd vertices are collapsed onto their even neighbor so the edge
+    /// matches the vertices of the neighbor chunk which is one LOD lower.
+    fn generate_indices_mesh(gfx: &GfxContext) -> [StitchedIndices; LOD] {
         let mut indlod = vec![];
 
         for lod in 0..LOD {
             let scale = 1 << lod;
             let resolution = Self::LOD0_RESOLUTION / scale;
 
-            let mut indices: Vec<IndexType> = Vec::with_capacity(6 * resolution * resolution);
-
-            let resolution = resolution as IndexType;
-            let w = resolution + 1;
-
-            // iterate over the grid, adding two triangles for each cell
-            for y in 0..resolution {
-                for x in 0..resolution {
-                    let idx = y * w + x;
-                    // avoid aliasing by alternating the triangles
-                    // alternate at 2 different levels (x + y) and (x / 2 + y / 2)
-                    // because of the LOD interpolation (each rectangle might end up being 2 times smaller)
-                    if (x + y + x / 2 + y / 2) % 2 == 0 {
-                        indices.push(idx);
-                        indices.push(idx + 1);
-                        indices.push(idx + w);
-
-                        indices.push(idx + 1);
-                        indices.push(idx + w + 1);
-                        indices.push(idx + w);
-                        continue;
+            let mut stitches = vec![];
+
+            for flags in 0..STITCHES {
+                let mut indices: Vec<IndexType> = Vec::with_capacity(6 * resolution * resolution);
+
+                let resolution = resolution as IndexType;
+                let w = resolution + 1;
+
+                let vertex = |mut x: IndexType, mut y: IndexType| {
+                    if y % 2 == 1
+                        && ((x == resolution && flags & 1 != 0) || (x == 0 && flags & 4 != 0))
+                    {
+                        y -= 1;
                     }
-                    indices.push(idx);
-                    indices.push(idx + 1);
-                    indices.push(idx + w + 1);
+                    if x % 2 == 1
+                        && ((y == resolution && flags & 2 != 0) || (y == 0 && flags & 8 != 0))
+                    {
+                        x -= 1;
+                    }
+                    y * w + x
+                };
 
-                    indices.push(idx);
-                    indices.push(idx + w + 1);
-                    indices.push(idx + w);
+                let mut push_tri = |a: IndexType, b: IndexType, c: IndexType| {
+                    // collapsed triangles are degenerate
+                    if a == b || b == c || a == c {
+                        return;
+                    }
+                    indices.push(a);
+                    indices.push(b);
+                    indices.push(c);
+                };
+
+                // iterate over the grid, adding two triangles for each cell
+                for y in 0..resolution {
+                    for x in 0..resolution {
+                        let v00 = vertex(x, y);
+                        let v10 = vertex(x + 1, y);
+                        let v01 = vertex(x, y + 1);
+                        let v11 = vertex(x + 1, y + 1);
+                        // avoid aliasing by alternating the triangles
+                        // alternate at 2 different levels (x + y) and (x / 2 + y / 2)
+                        // because of the LOD interpolation (each rectangle might end up being 2 times smaller)
+                        if (x + y + x / 2 + y / 2) % 2 == 0 {
+                            push_tri(v00, v10, v01);
+                            push_tri(v10, v11, v01);
+                            continue;
+                        }
+                        push_tri(v00, v10, v11);
+                        push_tri(v00, v11, v01);
+                    }
                 }
-            }
 
-            let l = indices.len();
+                let l = indices.len();
+
+                let mut buf = PBuffer::new(BufferUsages::INDEX).with_category(GpuCategory::Terrain);
+                buf.write(gfx, bytemuck::cast_slice(&indices));
+                stitches.push((buf, l as u32));
+            }
 
-            let mut buf = PBuffer::new(BufferUsages::INDEX);
-            buf.write(gfx, bytemuck::cast_slice(&indices));
-            indlod.push((buf, l as u32));
+            let mut stitches = stitches.into_iter();
+            indlod.push([(); STITCHES].map(move |_| stitches.next().unwrap()));
         }
 
         collect_arrlod(indlod)
     }
 
-    /// Updates the normals of the heightmap and gen mipmaps
     pub fn invalidate_height_normals(&mut self, gfx: &GfxContext) {
         if cfg!(debug_assertions) {
             self.downsample_pipeline = resample_pipeline(gfx, &self.heightmap_tex, "downsample");
@@ -679,13 +809,7 @@ impl Drawable for HeightmapPrepared {
 
         self.set_buffers(rp);
 
-        for lod in 0..LOD {
-            let (_, n_instances) = &self.instances[lod];
-            let (_, n_indices) = &self.indices[lod];
-
-            gfx.perf
-                .heightmap_drawcall(*n_indices as usize / 3 * *n_instances as usize)
-        }
+        gfx.perf.heightmap_drawcall(self.triangles());
     }
 
     fn draw_depth<'a>(
@@ -706,15 +830,8 @@ impl Drawable for HeightmapPrepared {
 
         self.set_buffers(rp);
 
-        for lod in 0..LOD {
-            let (_, n_instances) = &self.instances[lod];
-            let (_, n_indices) = &self.indices[lod];
-
-            gfx.perf.heightmap_depth_drawcall(
-                *n_indices as usize / 3 * *n_instances as usize,
-                shadow_cascade.is_some(),
-            );
-        }
+        gfx.perf
+            .heightmap_depth_drawcall(self.triangles(), shadow_cascade.is_some());
     }
 }
 
@@ -726,13 +843,30 @@ impl HeightmapPrepared {
                 continue;
             }
 
-            let (ind, n_indices) = &self.indices[lod];
-
             rp.set_bind_group(1, &self.heightmapbgs[lod], &[]);
             rp.set_vertex_buffer(0, instances.slice().unwrap());
-            rp.set_index_buffer(ind.slice().unwrap(), IndexFormat::Uint32);
-            rp.draw_indexed(0..*n_indices, 0, 0..*n_instances);
+
+            let ranges = &self.stitch_ranges[lod];
+            for (flags, (ind, n_indices)) in self.indices[lod].iter().enumerate() {
+                let (start, end) = (ranges[flags], ranges[flags + 1]);
+                if start == end {
+                    continue;
+                }
+                rp.set_index_buffer(ind.slice().unwrap(), IndexFormat::Uint32);
+                rp.draw_indexed(0..*n_indices, 0, start..end);
+            }
+        }
+    }
+
+    fn triangles(&self) -> usize {
+        let mut triangles = 0;
+        for lod in 0..LOD {
+            let ranges = &self.stitch_ranges[lod];
+            for (flags, (_, n_indices)) in self.indices[lod].iter().enumerate() {
+                triangles += *n_indices as usize / 3 * (ranges[flags + 1] - ranges[flags]) as usize;
+            }
         }
+        triangles
     }
 }
 
@@ -1,25 +1,46 @@
 #![allow(clippy::collapsible_else_if)]
 
-use crate::pbuffer::PBuffer;
+use crate::pbuffer::{GpuBuffer, PBuffer};
 use crate::{Drawable, GfxContext, Mesh, MeshPipeline};
 use geom::{LinearColor, Matrix4, Vec3};
 use std::sync::Arc;
 use wgpu::{BufferUsages, IndexFormat, RenderPass, VertexAttribute, VertexBufferLayout};
 
+/// The tint and the variation are packed in bytes, there can be a hundred thousand instances
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct MeshInstance {
     pub pos: Vec3,
     pub dir: Vec3,
-    pub tint: LinearColor,
+    tint: [u8; 4],
+    /// Slot of the palette, only the first byte is used
+    variation: [u8; 4],
 }
 
 u8slice_impl!(MeshInstance);
 
 const ATTRS: &[VertexAttribute] =
-    &wgpu::vertex_attr_array![5 => Float32x3, 6 => Float32x3, 7 => Float32x4];
+    &wgpu::vertex_attr_array![5 => Float32x3, 6 => Float32x3, 7 => Unorm8x4, 8 => Uint8x4];
 
 impl MeshInstance {
+    /// The tint is clamped between black and white
+    pub fn new(pos: Vec3, dir: Vec3, tint: LinearColor) -> Self {
+        let tint =
+            [tint.r, tint.g, tint.b, tint.a].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        Self {
+            pos,
+            dir,
+            tint,
+            variation: [0; 4],
+        }
+    }
+
+    /// Multiplies the instance with a color of the palette, see [`crate::Palette::add`]
+    pub fn with_variation(mut self, variation: u8) -> Self {
+        self.variation[0] = variation;
+        self
+    }
+
     pub(crate) const fn desc() -> VertexBufferLayout<'static> {
         VertexBufferLayout {
             array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
@@ -52,6 +73,10 @@ impl<const PERSISTENT: bool> InstancedMeshBuilder<PERSISTENT> {
         }
     }
 
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
     pub fn build(&mut self, gfx: &GfxContext) -> Option<InstancedMesh> {
         if self.instances.is_empty() {
             return None;
@@ -79,7 +104,7 @@ impl<const PERSISTENT: bool> InstancedMeshBuilder<PERSISTENT> {
 #[derive(Clone)]
 pub struct InstancedMesh {
     mesh: Mesh,
-    instance_buffer: Arc<wgpu::Buffer>,
+    instance_buffer: Arc<GpuBuffer>,
     n_instances: u32,
 }
 
@@ -90,6 +115,7 @@ impl Drawable for InstancedMesh {
         };
 
         rp.set_bind_group(1, &gfx.simplelit_bg, &[]);
+        rp.set_bind_group(3, gfx.palette.bg(), &[]);
         rp.set_vertex_buffer(0, self.mesh.vertex_buffer.slice(..));
         rp.set_vertex_buffer(1, self.instance_buffer.slice(..));
         rp.set_index_buffer(self.mesh.index_buffer.slice(..), IndexFormat::Uint32);
@@ -109,6 +135,7 @@ impl Drawable for InstancedMesh {
             gfx.perf
                 .drawcall((indices.end - indices.start) / 3 * self.n_instances);
         }
+        gfx.perf.atlas_merged_drawcalls(lod_select.merged_draws);
     }
 
     fn draw_depth<'a>(
@@ -146,3 +173,9 @@ impl Drawable for InstancedMesh {
         }
     }
 }
+
+#[cfg(test)]
+#[test]
+fn test_meshinstance_size() {
+    assert_eq!(std::mem::size_of::<MeshInstance>(), 32);
+}
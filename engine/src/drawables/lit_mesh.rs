@@ -8,15 +8,16 @@ use wgpu::{
 use geom::{Camera, InfiniteFrustrum, LinearColor, Matrix4, Sphere, Vec2, Vec3};
 
 use crate::meshbuild::MeshLod;
+use crate::pbuffer::GpuBuffer;
 use crate::{
-    CompiledModule, Drawable, GfxContext, Material, MeshInstance, MeshVertex, PipelineBuilder,
-    PipelineKey, RenderParams, Texture, TextureBuilder, Uniform, TL,
+    CompiledModule, Drawable, GfxContext, GpuCategory, Material, MeshInstance, MeshVertex, Palette,
+    PipelineBuilder, PipelineKey, RenderParams, Texture, TextureBuilder, Uniform, TL,
 };
 
 #[derive(Clone)]
 pub struct Mesh {
-    pub vertex_buffer: Arc<wgpu::Buffer>,
-    pub index_buffer: Arc<wgpu::Buffer>,
+    pub vertex_buffer: Arc<GpuBuffer>,
+    pub index_buffer: Arc<GpuBuffer>,
     pub lods: Box<[MeshLod]>,
     pub skip_depth: bool,
 }
@@ -29,10 +30,11 @@ impl Mesh {
 
 /// Returns the screen area of a sphere between [0..1] where 1 is the entire screen (if the sphere fits within the screen)
 pub fn screen_coverage(gfx: &GfxContext, s: Sphere) -> f32 {
-    let v = &gfx.render_params.value().proj;
-    let proj_center = v * s.center.w(1.0);
-    let proj_center_side =
-        v * (s.center + s.radius * gfx.render_params.value().cam_dir.perp_up()).w(1.0);
+    let params = gfx.render_params.value();
+    let v = &params.proj;
+    let center = (s.center - params.cam_pos) - params.cam_pos_low;
+    let proj_center = v * center.w(1.0);
+    let proj_center_side = v * (center + s.radius * params.cam_dir.perp_up()).w(1.0);
 
     let proj_center = proj_center.xyz() / proj_center.w;
     let proj_center_side = proj_center_side.xyz() / proj_center_side.w;
@@ -69,24 +71,28 @@ impl PipelineKey for MeshPipeline {
         let vb: &[VertexBufferLayout] = if self.instanced { VB_INSTANCED } else { VB };
 
         if !self.depth {
-            let extra_defines = if self.offscreen_render {
-                &["OFFSCREEN_RENDER"] as &[&str]
-            } else {
-                &[]
-            };
+            let mut extra_defines = vec![];
+            if self.offscreen_render {
+                extra_defines.push("OFFSCREEN_RENDER");
+            }
+            if self.instanced {
+                extra_defines.push("INSTANCED");
+            }
 
-            let frag = mk_module("pixel.frag", extra_defines);
+            let frag = mk_module("pixel.frag", &extra_defines);
 
             let bglayout = match self.offscreen_render {
                 true => bg_layout_offscreen_render(&gfx.device),
                 false => bg_layout_litmesh(&gfx.device),
             };
 
-            let layouts = [
-                &Uniform::<RenderParams>::bindgroup_layout(&gfx.device),
-                &bglayout,
-                &Material::bindgroup_layout(&gfx.device),
-            ];
+            let params_layout = Uniform::<RenderParams>::bindgroup_layout(&gfx.device);
+            let material_layout = Material::bindgroup_layout(&gfx.device);
+            let palette_layout = Palette::bindgroup_layout(&gfx.device);
+            let mut layouts = vec![&params_layout, &bglayout, &material_layout];
+            if self.instanced {
+                layouts.push(&palette_layout);
+            }
 
             let mut builder = PipelineBuilder::color(
                 "lit_mesh",
@@ -152,10 +158,12 @@ impl Mesh {
 
         let mut params = gfx.render_params.clone(&gfx.device);
         let value = params.value_mut();
-        value.proj = cam.proj_cache;
+        let (cam_pos, cam_pos_low) = cam.eye_split();
+        value.proj = cam.rel_proj_cache;
         value.inv_proj = cam.inv_proj_cache;
         value.cam_dir = cam.dir();
-        value.cam_pos = cam.eye();
+        value.cam_pos = cam_pos;
+        value.cam_pos_low = cam_pos_low;
         value.viewport = Vec2::new(dest.extent.width as f32, dest.extent.height as f32);
         value.sun = sun;
         value.sun_col = 3.5 * LinearColor::new(1.0, 0.95, 1.0, 1.0);
@@ -178,14 +186,16 @@ impl Mesh {
             TextureFormat::Depth32Float,
         )
         .with_sample_count(4)
+        .with_category(GpuCategory::UI)
         .build_no_queue(&gfx.device);
 
         let mut smap = TextureBuilder::empty(1024, 1024, 1, TextureFormat::Depth32Float)
+            .with_category(GpuCategory::UI)
             .build_no_queue(&gfx.device);
 
         let mut params_smap = params.clone(&gfx.device);
         let value_smap = params_smap.value_mut();
-        value_smap.proj = smap_mat[0];
+        value_smap.proj = smap_mat[0] * Matrix4::from_translation(cam_pos);
         params_smap.upload_to_gpu(&gfx.queue);
 
         let smap_view = smap.mip_view(0);
@@ -317,6 +327,7 @@ impl Drawable for Mesh {
 
             gfx.perf.drawcall((index_range.end - index_range.start) / 3);
         }
+        gfx.perf.atlas_merged_drawcalls(lod.merged_draws);
     }
 
     fn draw_depth<'a>(
@@ -49,6 +49,23 @@ impl<T: ?Sized + Drawable> Drawable for Arc<T> {
     }
 }
 
+impl<T: ?Sized + Drawable> Drawable for Box<T> {
+    fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
+        let s: &T = self;
+        s.draw(gfx, rp);
+    }
+
+    fn draw_depth<'a>(
+        &'a self,
+        gfx: &'a GfxContext,
+        rp: &mut RenderPass<'a>,
+        shadow_cascade: Option<&Matrix4>,
+    ) {
+        let s: &T = self;
+        s.draw_depth(gfx, rp, shadow_cascade);
+    }
+}
+
 impl<T: Drawable> Drawable for Option<T> {
     fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
         if let Some(s) = self {
@@ -1,4 +1,4 @@
-use crate::pbuffer::PBuffer;
+use crate::pbuffer::{GpuBuffer, PBuffer};
 use crate::{
     bg_layout_litmesh, CompiledModule, Drawable, GfxContext, Material, MaterialID,
     MetallicRoughness, PipelineBuilder, PipelineKey, Texture, UvVertex,
@@ -20,7 +20,7 @@ pub struct SpriteBatchBuilder<const PERSISTENT: bool> {
 
 #[derive(Clone)]
 pub struct SpriteBatch {
-    instance_buf: Arc<wgpu::Buffer>,
+    instance_buf: Arc<GpuBuffer>,
     pub n_instances: u32,
     pub material: MaterialID,
 }
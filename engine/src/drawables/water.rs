@@ -1,17 +1,42 @@
 use crate::meshbuild::MeshBuilder;
 use crate::{
-    CompiledModule, Drawable, GfxContext, Mesh, MeshVertex, PipelineBuilder, PipelineKey, Texture,
-    TextureBuilder, TL,
+    CompiledModule, Drawable, GfxContext, GpuCategory, Mesh, MeshVertex, PipelineBuilder,
+    PipelineKey, Texture, TextureBuilder, Uniform, TL,
 };
-use geom::AABB;
+use geom::{Vec2, AABB};
 use std::sync::Arc;
-use wgpu::{BindGroup, RenderPass, RenderPipeline};
+use wgpu::{
+    BindGroup, BindGroupLayout, BindGroupLayoutEntry, Device, Extent3d, ImageCopyTexture,
+    ImageDataLayout, Origin3d, RenderPass, RenderPipeline, TextureFormat,
+};
+
+/// Size in meters of a texel of the flow texture
+pub const WATER_FLOW_CELL: f32 = 16.0;
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct WaterParams {
+    pub map_origin: Vec2,
+    pub map_size: Vec2,
+    /// Multiplies the size of the waves
+    pub wave_scale: f32,
+    /// Multiplies the speed of the waves and of the flow
+    pub wave_speed: f32,
+    pub _pad: [f32; 2],
+}
+
+u8slice_impl!(WaterParams);
 
 #[derive(Clone)]
 pub struct Water {
-    mesh: Mesh,
+    /// The surface of the lakes and the sea, None while the map is dry
+    mesh: Option<Mesh>,
     n_indices: u32,
-    wavy_bg: Arc<BindGroup>,
+    /// wavy texture, flow texture and water params
+    water_bg: Arc<BindGroup>,
+    flow_tex: Arc<Texture>,
+    params: Arc<Uniform<WaterParams>>,
+    waves: (f32, f32),
 }
 
 #[derive(Hash)]
@@ -19,54 +44,151 @@ pub struct WaterPipeline;
 
 impl Water {
     pub fn new(gfx: &mut GfxContext, bounds: AABB) -> Self {
-        let mut mb = MeshBuilder::<false>::new_without_mat();
-
-        mb.extend(
-            None,
-            &[
-                MeshVertex {
-                    position: [bounds.ll.x, bounds.ll.y, -10.0],
-                    ..Default::default()
-                },
-                MeshVertex {
-                    position: [bounds.ur.x, bounds.ll.y, -10.0],
-                    ..Default::default()
-                },
-                MeshVertex {
-                    position: [bounds.ur.x, bounds.ur.y, -10.0],
-                    ..Default::default()
-                },
-                MeshVertex {
-                    position: [bounds.ll.x, bounds.ur.y, -10.0],
-                    ..Default::default()
-                },
-            ],
-            &[0, 1, 2, 2, 3, 0],
-        );
-
-        // unwrap ok: we just added vertices
-        let mesh = mb.build(gfx).unwrap();
-
         let wavy = TextureBuilder::try_from_path("assets/sprites/wavy.jpeg")
             .expect("no wavy texture")
             .with_label("wavy")
+            .with_category(GpuCategory::Terrain)
             .with_mipmaps(&gfx.mipmap_gen)
             .with_srgb(false)
             .build(&gfx.device, &gfx.queue);
 
-        let wavy_bg = Arc::new(wavy.bindgroup(
+        let flow_w = (bounds.w() / WATER_FLOW_CELL).ceil().max(1.0) as u32;
+        let flow_h = (bounds.h() / WATER_FLOW_CELL).ceil().max(1.0) as u32;
+        let flow_tex = TextureBuilder::empty(flow_w, flow_h, 1, TextureFormat::Rgba8Unorm)
+            .with_label("water flow")
+            .with_category(GpuCategory::Terrain)
+            .with_srgb(false)
+            .with_sampler(wgpu::SamplerDescriptor {
+                label: Some("water flow sampler"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            })
+            .build(&gfx.device, &gfx.queue);
+
+        let params = Uniform::new(
+            WaterParams {
+                map_origin: bounds.ll,
+                map_size: bounds.size(),
+                wave_scale: 1.0,
+                wave_speed: 1.0,
+                _pad: [0.0; 2],
+            },
             &gfx.device,
-            &Texture::bindgroup_layout(&gfx.device, [TL::Float]),
-        ));
+        );
+
+        let water_bg = Arc::new(
+            gfx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("water"),
+                layout: &water_layout(&gfx.device),
+                entries: &Texture::multi_bindgroup_entries(0, &[&wavy, &flow_tex])
+                    .chain(std::iter::once(params.bindgroup_entry(4)))
+                    .collect::<Vec<_>>(),
+            }),
+        );
 
         Self {
-            mesh,
-            n_indices: 6,
-            wavy_bg,
+            mesh: None,
+            n_indices: 0,
+            water_bg,
+            flow_tex: Arc::new(flow_tex),
+            params: Arc::new(params),
+            waves: (1.0, 1.0),
+        }
+    }
+
+    /// Replaces the surface of the water by the given rectangles, each at its own level
+    pub fn set_surface(&mut self, gfx: &GfxContext, rects: impl IntoIterator<Item = (AABB, f32)>) {
+        let mut mb = MeshBuilder::<false>::new_without_mat();
+        let mut n_indices = 0;
+        for (rect, z) in rects {
+            let corners = [
+                rect.ll,
+                Vec2::new(rect.ur.x, rect.ll.y),
+                rect.ur,
+                Vec2::new(rect.ll.x, rect.ur.y),
+            ];
+            mb.extend(
+                None,
+                &corners.map(|p| MeshVertex {
+                    position: [p.x, p.y, z],
+                    ..Default::default()
+                }),
+                &[0, 1, 2, 2, 3, 0],
+            );
+            n_indices += 6;
+        }
+        self.mesh = mb.build(gfx);
+        self.n_indices = n_indices;
+    }
+
+    pub fn set_waves(&mut self, gfx: &GfxContext, wave_scale: f32, wave_speed: f32) {
+        if self.waves == (wave_scale, wave_speed) {
+            return;
+        }
+        self.waves = (wave_scale, wave_speed);
+        self.params.write_direct(
+            &gfx.queue,
+            &WaterParams {
+                wave_scale,
+                wave_speed,
+                ..*self.params.value()
+            },
+        );
+    }
+
+    /// Uploads a rectangle of the flow texture starting at the given cell.
+    /// Each texel is (flow x, flow y) mapped from [-1, 1] to [0, 255], then the depth of the water
+    /// in meters (saturating) and 255.
+    pub fn update_flow(&self, gfx: &GfxContext, cell: (u32, u32), w: u32, h: u32, data: &[u8]) {
+        let size = self.flow_tex.extent;
+        if cell.0 >= size.width || cell.1 >= size.height {
+            return;
         }
+        let w_clamped = w.min(size.width - cell.0);
+        let h_clamped = h.min(size.height - cell.1);
+
+        gfx.queue.write_texture(
+            ImageCopyTexture {
+                texture: &self.flow_tex.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: cell.0,
+                    y: cell.1,
+                    z: 0,
+                },
+                aspect: Default::default(),
+            },
+            data,
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(w * 4),
+                rows_per_image: Some(h),
+            },
+            Extent3d {
+                width: w_clamped,
+                height: h_clamped,
+                depth_or_array_layers: 1,
+            },
+        );
     }
 }
 
+fn water_layout(device: &Device) -> BindGroupLayout {
+    let entries: Vec<BindGroupLayoutEntry> =
+        Texture::bindgroup_layout_entries(0, [TL::Float, TL::Float].into_iter())
+            .chain(std::iter::once(
+                Uniform::<WaterParams>::bindgroup_layout_entry(4),
+            ))
+            .collect();
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("water"),
+        entries: &entries,
+    })
+}
+
 impl PipelineKey for WaterPipeline {
     fn build(
         &self,
@@ -86,7 +208,7 @@ impl PipelineKey for WaterPipeline {
                     TL::NonfilterableFloat
                 }],
             ),
-            &Texture::bindgroup_layout(&gfx.device, [TL::Float]),
+            &water_layout(&gfx.device),
             &Texture::bindgroup_layout(&gfx.device, [TL::Float]),
         ];
 
@@ -105,16 +227,19 @@ impl PipelineKey for WaterPipeline {
 
 impl Drawable for Water {
     fn draw<'a>(&'a self, gfx: &'a GfxContext, rp: &mut RenderPass<'a>) {
+        let Some(mesh) = &self.mesh else {
+            return;
+        };
         let pipeline = gfx.get_pipeline(WaterPipeline);
 
         rp.set_pipeline(pipeline);
 
         rp.set_bind_group(1, &gfx.fbos.depth_bg, &[]);
-        rp.set_bind_group(2, &self.wavy_bg, &[]);
+        rp.set_bind_group(2, &self.water_bg, &[]);
         rp.set_bind_group(3, &gfx.water_bg, &[]);
 
-        rp.set_vertex_buffer(0, self.mesh.vertex_buffer.slice(..));
-        rp.set_index_buffer(self.mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        rp.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        rp.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
         rp.draw_indexed(0..self.n_indices, 0, 0..1);
 
         gfx.perf.drawcall(self.n_indices / 3);
@@ -0,0 +1,276 @@
+//! Writes every Nth rendered frame to a directory as PNG files, to assemble videos with other tools.
+//! Single frames can also be grabbed in memory, to stitch them into bigger images.
+//! The frames are copied to a ring of buffers that are read back once the GPU is done with them,
+//! so that recording does not wait for the GPU every frame.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+
+use wgpu::{Device, ImageCopyTexture, ImageDataLayout, MapMode, TextureFormat, TextureUsages};
+
+use crate::texture::write_png;
+
+/// Frames that can be waiting for the GPU at the same time
+const RING_SIZE: usize = 4;
+
+const PENDING: u8 = 0;
+const MAPPED: u8 = 1;
+const FAILED: u8 = 2;
+
+/// Pixels of a rendered frame, tightly packed RGBA rows from the top
+pub struct FrameImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+#[derive(Default)]
+pub enum GrabState {
+    #[default]
+    Pending,
+    Done(FrameImage),
+    Failed,
+}
+
+/// Filled once the grabbed frame is read back, see [`FrameDump::grab`]
+pub type FrameGrab = Arc<Mutex<GrabState>>;
+
+enum Target {
+    File(PathBuf),
+    Memory(FrameGrab),
+}
+
+struct ReadbackSlot {
+    buffer: wgpu::Buffer,
+    state: Arc<AtomicU8>,
+    /// Where the frame goes once read back, None when the slot is free
+    target: Option<Target>,
+    width: u32,
+    height: u32,
+    padded_row: u32,
+    bgra: bool,
+}
+
+#[derive(Default)]
+pub struct FrameDump {
+    dir: Option<PathBuf>,
+    every: u32,
+    rendered: u64,
+    written: u32,
+    slots: Vec<ReadbackSlot>,
+    /// Grabs waiting for the next rendered frames
+    grabs: Vec<FrameGrab>,
+}
+
+impl FrameDump {
+    /// Starts writing one frame every `every` rendered frames to `dir`
+    pub fn start(&mut self, dir: PathBuf, every: u32) {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::error!("could not create the frame dump directory {:?}: {}", dir, e);
+            return;
+        }
+        self.dir = Some(dir);
+        self.every = every.max(1);
+        self.rendered = 0;
+        self.written = 0;
+    }
+
+    /// The frames still in flight are written over the next frames
+    pub fn stop(&mut self) {
+        self.dir = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    /// Number of frames written since the recording started
+    pub fn written(&self) -> u32 {
+        self.written
+    }
+
+    /// Reads the next rendered frame back in memory
+    pub fn grab(&mut self) -> FrameGrab {
+        let grab = FrameGrab::default();
+        self.grabs.push(grab.clone());
+        grab
+    }
+
+    /// Stops everything, the pending grabs fail
+    fn fail(&mut self) {
+        self.dir = None;
+        for grab in self.grabs.drain(..) {
+            *grab.lock().unwrap() = GrabState::Failed;
+        }
+    }
+
+    pub(crate) fn capture(&mut self, device: &Device, queue: &wgpu::Queue, frame: &wgpu::Texture) {
+        if self.dir.is_none()
+            && self.grabs.is_empty()
+            && self.slots.iter().all(|s| s.target.is_none())
+        {
+            return;
+        }
+        device.poll(wgpu::Maintain::Poll);
+        self.collect();
+
+        let mut dump_path = None;
+        if let Some(ref dir) = self.dir {
+            let n = self.rendered;
+            self.rendered += 1;
+            if n % self.every as u64 == 0 {
+                dump_path = Some(dir.join(format!("frame_{:06}.png", self.written)));
+            }
+        }
+        if dump_path.is_none() && self.grabs.is_empty() {
+            return;
+        }
+
+        let bgra = match frame.format() {
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            format => {
+                log::error!("frame dump not implemented for format {:?}", format);
+                self.fail();
+                return;
+            }
+        };
+        if !frame.usage().contains(TextureUsages::COPY_SRC) {
+            log::error!("frame dump is not supported, the frames cannot be copied");
+            self.fail();
+            return;
+        }
+
+        // the GPU is a whole ring behind, waiting is the only way to not drop the frame
+        if self.slots.len() >= RING_SIZE && self.slots.iter().all(|s| s.target.is_some()) {
+            device.poll(wgpu::Maintain::Wait);
+            self.collect();
+        }
+
+        let width = frame.width();
+        let height = frame.height();
+        let padded_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let idx = match self.slots.iter().position(|s| s.target.is_none()) {
+            Some(idx) => idx,
+            None => {
+                self.slots
+                    .push(ReadbackSlot::new(device, width, height, padded_row));
+                self.slots.len() - 1
+            }
+        };
+        let slot = &mut self.slots[idx];
+        if slot.width != width || slot.height != height {
+            *slot = ReadbackSlot::new(device, width, height, padded_row);
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("frame dump"),
+        });
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: frame,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &slot.buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: None,
+                },
+            },
+            frame.size(),
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let state = slot.state.clone();
+        slot.buffer.slice(..).map_async(MapMode::Read, move |v| {
+            if v.is_err() {
+                log::error!("Failed to map buffer for reading for frame dump");
+                state.store(FAILED, Ordering::Release);
+                return;
+            }
+            state.store(MAPPED, Ordering::Release);
+        });
+        slot.bgra = bgra;
+        slot.target = Some(match dump_path {
+            Some(path) => {
+                self.written += 1;
+                Target::File(path)
+            }
+            None => Target::Memory(self.grabs.remove(0)),
+        });
+    }
+
+    /// Writes the frames the GPU is done with, the encoding happens on another thread
+    fn collect(&mut self) {
+        for slot in &mut self.slots {
+            if slot.target.is_none() {
+                continue;
+            }
+            match slot.state.swap(PENDING, Ordering::Acquire) {
+                PENDING => continue,
+                FAILED => {
+                    if let Some(Target::Memory(grab)) = slot.target.take() {
+                        *grab.lock().unwrap() = GrabState::Failed;
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let target = slot.target.take().unwrap();
+
+            let row = (slot.width * 4) as usize;
+            let mut rgba = Vec::with_capacity(row * slot.height as usize);
+            {
+                let mapped = slot.buffer.slice(..).get_mapped_range();
+                for padded in mapped.chunks(slot.padded_row as usize) {
+                    rgba.extend_from_slice(&padded[..row]);
+                }
+            }
+            slot.buffer.unmap();
+
+            if slot.bgra {
+                for px in rgba.chunks_exact_mut(4) {
+                    px.swap(0, 2);
+                }
+            }
+            let (width, height) = (slot.width, slot.height);
+            match target {
+                Target::File(path) => {
+                    std::thread::spawn(move || write_png(path, width, height, rgba));
+                }
+                Target::Memory(grab) => {
+                    *grab.lock().unwrap() = GrabState::Done(FrameImage {
+                        width,
+                        height,
+                        rgba,
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl ReadbackSlot {
+    fn new(device: &Device, width: u32, height: u32, padded_row: u32) -> Self {
+        Self {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("frame dump"),
+                size: (padded_row * height) as u64,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            state: Arc::new(AtomicU8::new(PENDING)),
+            target: None,
+            width,
+            height,
+            padded_row,
+            bgra: false,
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Transient allocation of the screen targets.
+//! The passes of a frame declare the screen targets they read and write, in the order their
+//! command buffers are submitted. Targets with the same descriptor whose lifetimes don't overlap
+//! share the same texture.
+//! Persistent targets are read across frames (e.g. the UI blur sampled by the GUI), they keep
+//! their own texture and are not listed here.
+//! The passes are the same every frame, so the allocation is done when the screen targets are
+//! (re)created.
+
+use crate::resize::ScreenTexture;
+use crate::Texture;
+use wgpu::{Device, TextureFormat, TextureUsages};
+
+/// A pass of the frame and the screen targets it uses
+pub(crate) struct FramePass {
+    pub reads: &'static [ScreenTexture],
+    pub writes: &'static [ScreenTexture],
+}
+
+impl FramePass {
+    fn uses(&self, target: ScreenTexture) -> bool {
+        self.reads.contains(&target) || self.writes.contains(&target)
+    }
+}
+
+/// The passes of a frame, in submission order (see [`crate::GfxContext::finish_frame`])
+pub(crate) const FRAME_PASSES: &[FramePass] = &[
+    // depth prepass
+    FramePass {
+        reads: &[],
+        writes: &[ScreenTexture::Depth],
+    },
+    // ssao
+    FramePass {
+        reads: &[ScreenTexture::Depth],
+        writes: &[ScreenTexture::Ssao],
+    },
+    // fog
+    FramePass {
+        reads: &[ScreenTexture::Depth],
+        writes: &[ScreenTexture::Fog],
+    },
+    // main
+    FramePass {
+        reads: &[
+            ScreenTexture::Depth,
+            ScreenTexture::Ssao,
+            ScreenTexture::Fog,
+        ],
+        writes: &[ScreenTexture::ColorMsaa],
+    },
+    // background
+    FramePass {
+        reads: &[ScreenTexture::Depth],
+        writes: &[ScreenTexture::ColorMsaa],
+    },
+    // outlines
+    FramePass {
+        reads: &[ScreenTexture::OutlineMask],
+        writes: &[ScreenTexture::OutlineMask],
+    },
+    // ui blur
+    FramePass {
+        reads: &[],
+        writes: &[ScreenTexture::UiBlur],
+    },
+    // gui
+    FramePass {
+        reads: &[ScreenTexture::UiBlur],
+        writes: &[ScreenTexture::ColorMsaa],
+    },
+];
+
+/// Textures are only shared when their descriptors are exactly the same
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) struct TargetDesc {
+    pub size: (u32, u32),
+    pub format: TextureFormat,
+    pub usage: TextureUsages,
+    pub samples: u32,
+}
+
+impl TargetDesc {
+    pub fn bytes(&self) -> u64 {
+        let texel = self.format.block_copy_size(None).unwrap_or(4) as u64;
+        self.size.0 as u64 * self.size.1 as u64 * texel * self.samples as u64
+    }
+
+    pub fn create(&self, device: &Device) -> Texture {
+        Texture::create_fbo(
+            device,
+            self.size,
+            self.format,
+            self.usage,
+            Some(self.samples),
+        )
+    }
+}
+
+/// The physical textures backing the logical targets
+pub(crate) struct TargetAllocation {
+    /// Index into `textures` of each logical target, in the order they were given
+    pub physical: Vec<usize>,
+    pub textures: Vec<TargetDesc>,
+}
+
+impl TargetAllocation {
+    /// Bytes that would have been allocated without aliasing
+    pub fn saved_bytes(&self, targets: &[(ScreenTexture, TargetDesc)]) -> u64 {
+        let logical: u64 = targets.iter().map(|(_, desc)| desc.bytes()).sum();
+        let physical: u64 = self.textures.iter().map(TargetDesc::bytes).sum();
+        logical - physical
+    }
+}
+
+/// Assigns the targets to textures, a texture is reused by a target whose first pass is after
+/// the last pass of the targets already using it.
+/// A target used by none of the passes gets its own texture.
+pub(crate) fn allocate(
+    targets: &[(ScreenTexture, TargetDesc)],
+    passes: &[FramePass],
+) -> TargetAllocation {
+    let lifetimes: Vec<Option<(usize, usize)>> = targets
+        .iter()
+        .map(|&(target, _)| {
+            let first = passes.iter().position(|p| p.uses(target))?;
+            let last = passes.iter().rposition(|p| p.uses(target))?;
+            Some((first, last))
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..targets.len()).collect();
+    order.sort_by_key(|&i| lifetimes[i].map_or(0, |(first, _)| first));
+
+    let mut physical = vec![0; targets.len()];
+    let mut textures: Vec<TargetDesc> = vec![];
+    // last pass using each texture, None if it can't be shared
+    let mut busy_until: Vec<Option<usize>> = vec![];
+
+    for i in order {
+        let desc = targets[i].1;
+        let reused = lifetimes[i].and_then(|(first, _)| {
+            (0..textures.len())
+                .find(|&j| textures[j] == desc && busy_until[j].is_some_and(|last| last < first))
+        });
+
+        let j = reused.unwrap_or_else(|| {
+            textures.push(desc);
+            busy_until.push(None);
+            textures.len() - 1
+        });
+        physical[i] = j;
+        busy_until[j] = lifetimes[i].map(|(_, last)| last);
+    }
+
+    TargetAllocation { physical, textures }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn desc(format: TextureFormat, samples: u32) -> TargetDesc {
+        TargetDesc {
+            size: (1280, 720),
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT.union(TextureUsages::TEXTURE_BINDING),
+            samples,
+        }
+    }
+
+    #[test]
+    fn outline_mask_reuses_the_depth() {
+        let depth = desc(TextureFormat::Depth32Float, 4);
+        let targets = [
+            (ScreenTexture::Depth, depth),
+            (ScreenTexture::Ssao, desc(TextureFormat::R8Unorm, 1)),
+            (ScreenTexture::Fog, desc(TextureFormat::Rgba16Float, 1)),
+            (ScreenTexture::OutlineMask, depth),
+        ];
+        let alloc = allocate(&targets, FRAME_PASSES);
+
+        assert_eq!(alloc.textures.len(), 3);
+        assert_eq!(alloc.physical[0], alloc.physical[3]);
+        assert_ne!(alloc.physical[1], alloc.physical[2]);
+        assert_eq!(alloc.saved_bytes(&targets), depth.bytes());
+    }
+
+    #[test]
+    fn overlapping_or_different_targets_are_not_shared() {
+        let passes = [
+            FramePass {
+                reads: &[],
+                writes: &[ScreenTexture::Ssao],
+            },
+            FramePass {
+                reads: &[ScreenTexture::Ssao],
+                writes: &[ScreenTexture::Fog],
+            },
+            FramePass {
+                reads: &[ScreenTexture::Fog],
+                writes: &[ScreenTexture::Depth],
+            },
+        ];
+        let r8 = desc(TextureFormat::R8Unorm, 1);
+        let targets = [
+            (ScreenTexture::Ssao, r8),
+            (ScreenTexture::Fog, r8),
+            (ScreenTexture::Depth, desc(TextureFormat::R8Unorm, 4)),
+            (ScreenTexture::UiBlur, r8),
+        ];
+        let alloc = allocate(&targets, &passes);
+
+        // ssao and fog are both alive during the second pass, the depth has more samples,
+        // and the ui blur is never used by these passes
+        assert_eq!(alloc.textures.len(), 4);
+        assert_eq!(alloc.saved_bytes(&targets), 0);
+    }
+}
@@ -31,6 +31,12 @@ pub trait State: 'static {
         true
     }
 
+    /// Called every frame once it is presented, to close the window without asking (e.g. at the end of a benchmark).
+    /// Return true to exit.
+    fn should_close(&mut self) -> bool {
+        false
+    }
+
     /// Called every frame to prepare the gui rendering.
     fn render_gui(&mut self, ui: &egui::Context) {}
 
@@ -56,7 +62,10 @@ async fn run<S: State>(el: EventLoop<()>, window: Arc<Window>) {
         }
 
         #[cfg(feature = "yakui")]
-        if ctx.yakui.handle_event(&event) && !ctx.keybind_mode {
+        if ctx.yakui.handle_event(&event)
+            && !ctx.keybind_mode
+            && !crate::yakui::is_pointer_event(&event)
+        {
             return;
         }
 
@@ -78,17 +87,16 @@ async fn run<S: State>(el: EventLoop<()>, window: Arc<Window>) {
                     WindowEvent::Resized(physical_size) => {
                         log::info!("resized: {:?}", physical_size);
                         let size = (physical_size.width, physical_size.height, scale_factor);
-                        ctx.gfx.resize(size);
-                        state.resized(&mut ctx, size);
+                        ctx.gfx.request_resize(size);
                     }
                     WindowEvent::ScaleFactorChanged {
                         scale_factor: sf, ..
                     } => {
                         log::info!("scale_factor: {:?}", scale_factor);
                         scale_factor = sf;
-                        let size = (ctx.gfx.size.0, ctx.gfx.size.1, scale_factor);
-                        ctx.gfx.resize(size);
-                        state.resized(&mut ctx, size);
+                        let physical_size = ctx.gfx.window.inner_size();
+                        let size = (physical_size.width, physical_size.height, scale_factor);
+                        ctx.gfx.request_resize(size);
                     }
                     WindowEvent::CloseRequested => {
                         if state.exit() {
@@ -96,6 +104,14 @@ async fn run<S: State>(el: EventLoop<()>, window: Arc<Window>) {
                         }
                     }
                     WindowEvent::RedrawRequested => {
+                        // resize before acquiring, so the frame is rendered at the new size
+                        if let Some(size) = ctx.gfx.apply_pending_resize(false) {
+                            state.resized(&mut ctx, size);
+                        }
+                        if ctx.gfx.is_minimized() {
+                            ctx.gfx.window.request_redraw();
+                            return;
+                        }
                         let sco = match ctx.gfx.surface.get_current_texture() {
                             Ok(swapchainframe) => swapchainframe,
                             Err(wgpu::SurfaceError::Timeout) => ctx
@@ -106,8 +122,14 @@ async fn run<S: State>(el: EventLoop<()>, window: Arc<Window>) {
                             Err(wgpu::SurfaceError::Outdated)
                             | Err(wgpu::SurfaceError::Lost)
                             | Err(wgpu::SurfaceError::OutOfMemory) => {
-                                let size = ctx.gfx.size;
-                                ctx.gfx.resize(size);
+                                let size = match ctx.gfx.apply_pending_resize(true) {
+                                    Some(size) => size,
+                                    None => {
+                                        let size = ctx.gfx.size;
+                                        ctx.gfx.resize(size);
+                                        size
+                                    }
+                                };
                                 state.resized(&mut ctx, size);
                                 ctx.gfx
                                     .surface
@@ -121,7 +143,36 @@ async fn run<S: State>(el: EventLoop<()>, window: Arc<Window>) {
                         let d = last_update.elapsed();
                         last_update = Instant::now();
                         ctx.delta = d.as_secs_f32();
+
+                        if let Some(gilrs) = &mut ctx.gamepads {
+                            ctx.input.handle_gamepad(gilrs);
+                        }
+                        for event in ctx.input.virtual_cursor_events(ctx.delta, (ctx.gfx.size.0, ctx.gfx.size.1)) {
+                            if let WindowEvent::CursorMoved { position, .. } = event {
+                                let _ = ctx.gfx.window.set_cursor_position(position);
+                            }
+                            ctx.egui.handle_event(&ctx.gfx.window, &event);
+                            let event = Event::WindowEvent {
+                                window_id: ctx.gfx.window.id(),
+                                event,
+                            };
+                            #[cfg(feature = "yakui")]
+                            if ctx.yakui.handle_event(&event)
+                                && !ctx.keybind_mode
+                                && !crate::yakui::is_pointer_event(&event)
+                            {
+                                continue;
+                            }
+                            if let Event::WindowEvent { event, .. } = &event {
+                                ctx.input.handle(event);
+                            }
+                        }
+
                         state.update(&mut ctx);
+                        #[cfg(feature = "yakui")]
+                        {
+                            ctx.yakui.pointer_captured = false;
+                        }
 
                         let (mut enc, view) = ctx.gfx.start_frame(&sco);
                         (ctx.times.render_time, ctx.times.gui_time) = ctx
@@ -137,6 +188,7 @@ async fn run<S: State>(el: EventLoop<()>, window: Arc<Window>) {
                             });
 
                         ctx.gfx.finish_frame(enc);
+                        ctx.gfx.dump_frame(&sco.texture);
                         let (icon, changed) = get_cursor_icon();
                         if changed {
                             ctx.gfx.window.set_cursor_icon(icon);
@@ -145,6 +197,10 @@ async fn run<S: State>(el: EventLoop<()>, window: Arc<Window>) {
                         ctx.times.total_cpu_time = last_update.elapsed().as_secs_f32();
 
                         sco.present();
+                        if state.should_close() {
+                            target.exit();
+                            return;
+                        }
                         ctx.gfx.window.request_redraw();
                     }
                     _ => (),
@@ -245,6 +301,8 @@ pub struct EngineTimes {
 pub struct Context {
     pub gfx: GfxContext,
     pub input: InputContext,
+    /// None if the gamepad backend could not be initialized
+    pub gamepads: Option<gilrs::Gilrs>,
     pub audio: AudioContext,
     pub delta: f32,
     /// Makes sure all events go to InputContext even if catched by yakui
@@ -260,10 +318,14 @@ impl Context {
         let gfx = GfxContext::new(window).await;
         let input = InputContext::default();
         let audio = AudioContext::new();
+        let gamepads = gilrs::Gilrs::new()
+            .map_err(|e| log::warn!("could not initialize gamepads: {}", e))
+            .ok();
         let egui = EguiWrapper::new(&gfx, el);
 
         Self {
             input,
+            gamepads,
             audio,
             delta: 0.0,
             keybind_mode: false,
@@ -10,33 +10,45 @@ use wgpu::{
     CompositeAlphaMode, DepthBiasState, Device, Extent3d, Face, FilterMode, FragmentState,
     FrontFace, ImageCopyTexture, ImageDataLayout, InstanceDescriptor, MultisampleState,
     PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPassColorAttachment,
-    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, SamplerDescriptor, Surface,
-    SurfaceConfiguration, SurfaceTexture, TextureFormat, TextureUsages, TextureView,
-    TextureViewDescriptor, VertexBufferLayout, VertexState,
+    RenderPassDescriptor, RenderPassTimestampWrites, RenderPipeline, RenderPipelineDescriptor,
+    SamplerDescriptor, Surface, SurfaceConfiguration, SurfaceTexture, TextureFormat, TextureUsages,
+    TextureView, TextureViewDescriptor, VertexBufferLayout, VertexState,
 };
 use winit::window::{Fullscreen, Window};
 
+use common::diagnostics::Diagnostic;
 use common::FastMap;
 use geom::{vec2, Camera, InfiniteFrustrum, LinearColor, Matrix4, Plane, Vec2, Vec3};
 
+use crate::frame_graph::{self, TargetDesc, FRAME_PASSES};
 use crate::framework::State;
 use crate::meshload::{load_mesh, LoadMeshError};
-use crate::passes::{BackgroundPipeline, Pbr};
+use crate::pass_timer::{PassTimer, TimedPass};
+use crate::passes::{BackgroundPipeline, OutlineParams, Outlined, Pbr, MAX_OUTLINES};
 use crate::perf_counters::PerfCounters;
+use crate::resize::{PendingResize, ScreenBindGroup, ScreenTexture};
 use crate::{
-    bg_layout_litmesh, passes, CompiledModule, Drawable, IndexType, LampLights, Material,
-    MaterialID, MaterialMap, Mesh, MetallicRoughness, MipmapGenerator, PipelineKey, Pipelines,
-    Texture, TextureBuildError, TextureBuilder, Uniform, UvVertex, WaterPipeline, TL,
+    bg_layout_litmesh, default_memory_budget, gpu_memory_total, passes, CompiledModule, Drawable,
+    FrameDump, GpuAllocation, GpuCategory, IndexType, LampLights, Material, MaterialID,
+    MaterialMap, Mesh, MetallicRoughness, MipmapGenerator, Palette, PipelineKey, Pipelines,
+    SamplerRegistry, Texture, TextureAtlas, TextureBuildError, TextureBuilder, Uniform, UvVertex,
+    WaterPipeline, MAX_ANISOTROPY, TL,
 };
 
+/// The screen targets, the transient ones can share a texture, see [`crate::frame_graph`]
 pub struct FBOs {
-    pub(crate) depth: Texture,
+    pub(crate) depth: Arc<Texture>,
     pub(crate) depth_bg: wgpu::BindGroup,
     pub(crate) color_msaa: TextureView,
-    pub(crate) ssao: Texture,
-    pub(crate) fog: Texture,
+    pub(crate) ssao: Arc<Texture>,
+    pub(crate) fog: Arc<Texture>,
+    /// Persistent, the GUI samples it
     pub(crate) ui_blur: Texture,
+    /// Depth of the outlined objects, None when outlines are disabled
+    pub(crate) outline_mask: Option<(Arc<Texture>, wgpu::BindGroup)>,
     pub format: TextureFormat,
+    /// Number of logical targets, of textures allocated for them, and the bytes saved by aliasing
+    pub(crate) aliasing: (usize, usize, u64),
 }
 
 pub struct GfxContext {
@@ -49,6 +61,7 @@ pub struct GfxContext {
     pub size: (u32, u32, f64),
     pub(crate) sc_desc: SurfaceConfiguration,
     pub update_sc: bool,
+    pending_resize: PendingResize,
     settings: Option<GfxSettings>,
 
     pub(crate) materials: MaterialMap,
@@ -59,10 +72,15 @@ pub struct GfxContext {
     pub frustrum: InfiniteFrustrum,
     pub(crate) sun_params: [Uniform<RenderParams>; N_CASCADES],
     pub render_params: Uniform<RenderParams>,
+    /// Colors of the instance variations
+    pub palette: Palette,
+    pub(crate) outline_params: Vec<Uniform<OutlineParams>>,
     pub(crate) texture_cache_paths: FastMap<PathBuf, Arc<Texture>>,
     pub(crate) texture_cache_bytes: Mutex<HashMap<u64, Arc<Texture>, common::TransparentHasherU64>>,
     pub null_texture: Texture,
-    pub(crate) linear_sampler: wgpu::Sampler,
+    pub(crate) linear_sampler: Arc<wgpu::Sampler>,
+    /// Samplers with the anisotropic filtering of the settings
+    pub(crate) samplers: SamplerRegistry,
 
     pub(crate) mesh_cache: FastMap<PathBuf, Arc<Mesh>>,
     pub(crate) mesh_errors: FastMap<PathBuf, LoadMeshError>,
@@ -75,6 +93,8 @@ pub struct GfxContext {
     pub lamplights: LampLights,
     pub(crate) defines: FastMap<String, String>,
     pub(crate) defines_changed: bool,
+    pub frame_dump: FrameDump,
+    pub(crate) atlas: TextureAtlas,
 
     pub simplelit_bg: wgpu::BindGroup,
     pub bnoise_bg: wgpu::BindGroup,
@@ -83,8 +103,17 @@ pub struct GfxContext {
 
     #[allow(dead_code)] // keep adapter alive
     pub(crate) adapter: Adapter,
+    /// Budget of the GPU memory guessed from the adapter, used when the settings don't set one
+    adapter_memory_budget: u64,
+    /// Most detailed mips left out of the terrain textures because of the memory budget
+    terrain_dropped_mips: u32,
+    /// Notches the shadow resolution was lowered by because of the memory budget
+    shadow_notches: u8,
+    /// Nothing is left to lower, the warning was already shown
+    memory_exhausted: bool,
 
     pub perf: PerfCounters,
+    pub(crate) pass_timer: PassTimer,
 }
 
 #[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq)]
@@ -122,6 +151,14 @@ impl From<u8> for ShadowQuality {
 }
 
 impl ShadowQuality {
+    /// Lower by that many notches, down to the lowest quality that still has shadows
+    pub fn lowered(self, notches: u8) -> Self {
+        match self {
+            ShadowQuality::NoShadows => self,
+            _ => ShadowQuality::from((self as u8).saturating_sub(notches).max(1)),
+        }
+    }
+
     pub fn size(&self) -> Option<u32> {
         match self {
             ShadowQuality::Low => Some(512),
@@ -134,6 +171,7 @@ impl ShadowQuality {
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GfxSettings {
     pub vsync: bool,
     pub fullscreen: bool,
@@ -146,6 +184,17 @@ pub struct GfxSettings {
     pub fog_shader_debug: bool,
     pub parallel_render: bool,
     pub msaa: bool,
+    /// Outlines around selected objects, needs an extra depth target
+    pub outlines: bool,
+    /// Lights further from the camera than the closest max_lights are not drawn
+    pub max_lights: u32,
+    /// Anisotropic filtering level of the textures, one of [`crate::ANISOTROPY_LEVELS`]
+    pub anisotropy: u16,
+    /// Keeps the terrain textures sharp at grazing angles and far away, at the cost of some
+    /// shimmering
+    pub terrain_mip_bias: bool,
+    /// Budget of the GPU memory in MiB, 0 to guess it from the adapter
+    pub vram_budget_mb: u32,
 }
 
 impl Default for GfxSettings {
@@ -162,6 +211,11 @@ impl Default for GfxSettings {
             fog_shader_debug: false,
             parallel_render: false,
             msaa: false,
+            outlines: true,
+            max_lights: 4096,
+            anisotropy: MAX_ANISOTROPY,
+            terrain_mip_bias: false,
+            vram_budget_mb: 0,
         }
     }
 }
@@ -178,14 +232,29 @@ pub struct Encoders {
 
 pub const N_CASCADES: usize = 4;
 
+/// Frames between two checks of the GPU memory against the budget
+const MEMORY_CHECK_INTERVAL: u64 = 60;
+/// Share of the budget above which the detail starts to be lowered
+const MEMORY_PRESSURE: f64 = 0.9;
+const MAX_TERRAIN_DROPPED_MIPS: u32 = 2;
+
+/// Mip bias of the terrain textures when [`GfxSettings::terrain_mip_bias`] is set
+const TERRAIN_MIP_BIAS: f32 = -0.75;
+
 #[derive(Copy, Clone)]
 #[repr(C)]
 pub struct RenderParams {
+    /// View projection of the positions relative to the camera, see `cam_relative` in the shaders
     pub proj: Matrix4,
+    /// Inverse of the view projection of the world positions
     pub inv_proj: Matrix4,
     pub sun_shadow_proj: [Matrix4; N_CASCADES],
+    /// Nearest f32 of the camera position, see [`Camera::eye_split`]
     pub cam_pos: Vec3,
     pub _pad: f32,
+    /// What the f32 camera position misses, far from the origin it is a few millimeters
+    pub cam_pos_low: Vec3,
+    pub _pad6: f32,
     pub cam_dir: Vec3, // Vec3s need to be 16 aligned
     pub _pad4: f32,
     pub sun: Vec3,
@@ -199,6 +268,14 @@ pub struct RenderParams {
     pub time_always: f32,
     pub shadow_mapping_resolution: i32,
     pub terraforming_mode_radius: f32,
+    /// How much of the flat terrain is covered by snow, from 0 to 1
+    pub snow: f32,
+    /// How bright the street lamps and headlights are, from 0 at day to 1 at night
+    pub lamp_intensity: f32,
+    /// Added to the mip level of the terrain textures, negative to keep more detail
+    pub terrain_mip_bias: f32,
+    /// How much the clouds cover the sky, from 0 for a clear sky to 1 when the sun is hidden
+    pub overcast: f32,
 }
 
 #[cfg(test)]
@@ -221,6 +298,7 @@ impl Default for RenderParams {
             sand_col: Default::default(),
             sea_col: Default::default(),
             cam_pos: Default::default(),
+            cam_pos_low: Default::default(),
             cam_dir: Default::default(),
             sun: Default::default(),
             viewport: vec2(1000.0, 1000.0),
@@ -229,9 +307,14 @@ impl Default for RenderParams {
             time_always: 0.0,
             shadow_mapping_resolution: 2048,
             terraforming_mode_radius: 0.0,
+            snow: 0.0,
+            lamp_intensity: 0.0,
+            terrain_mip_bias: 0.0,
+            overcast: 0.0,
             _pad: 0.0,
             _pad2: 0.0,
             _pad4: 0.0,
+            _pad6: 0.0,
         }
     }
 }
@@ -248,12 +331,22 @@ pub struct GuiRenderContext<'a> {
 pub struct FrameContext<'a> {
     pub gfx: &'a mut GfxContext,
     pub objs: &'a mut Vec<Box<dyn Drawable>>,
+    pub outlines: &'a mut Vec<Outlined>,
 }
 
 impl<'a> FrameContext<'a> {
     pub fn draw(&mut self, v: impl Drawable + 'static) {
         self.objs.push(Box::new(v))
     }
+
+    /// Draws an outline of the given color around the object, which should also be drawn normally.
+    /// Only the first [`MAX_OUTLINES`] calls of a frame are taken into account.
+    pub fn draw_outlined(&mut self, color: LinearColor, v: impl Drawable + 'static) {
+        self.outlines.push(Outlined {
+            color,
+            obj: Box::new(v),
+        })
+    }
 }
 
 impl GfxContext {
@@ -286,7 +379,8 @@ impl GfxContext {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: wgpu::Features::empty(),
+                    // the passes are timed on the CPU without it
+                    required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
                     required_limits: limit,
                 },
                 None,
@@ -306,8 +400,11 @@ impl GfxContext {
         let win_height = window.inner_size().height;
         let win_scale_factor = window.scale_factor();
 
+        // the frames are copied to be dumped to disk when the surface allows it
+        let copy_src = capabilities.usages & TextureUsages::COPY_SRC;
+
         let sc_desc = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING | copy_src,
             format,
             width: win_width,
             height: win_height,
@@ -318,7 +415,7 @@ impl GfxContext {
         };
         //        let samples = if cfg!(target_arch = "wasm32") { 1 } else { 4 };
         let samples = 1;
-        let fbos = Self::create_textures(&device, &sc_desc, samples);
+        let fbos = Self::create_textures(&device, &sc_desc, samples, true);
         surface.configure(&device, &sc_desc);
 
         let screen_uv_vertices = device.create_buffer_init(&BufferInitDescriptor {
@@ -376,13 +473,8 @@ impl GfxContext {
             },
         );
 
-        let linear_sampler = device.create_sampler(&SamplerDescriptor {
-            label: Some("basic linear sampler"),
-            mag_filter: FilterMode::Linear,
-            min_filter: FilterMode::Linear,
-            mipmap_filter: FilterMode::Linear,
-            ..Default::default()
-        });
+        let samplers = SamplerRegistry::new();
+        let linear_sampler = samplers.get(&device, &Self::linear_sampler_desc());
 
         let mut materials = MaterialMap::default();
 
@@ -404,7 +496,12 @@ impl GfxContext {
             size: (win_width, win_height, win_scale_factor),
             sc_desc,
             update_sc: false,
+            pending_resize: PendingResize::default(),
+            adapter_memory_budget: default_memory_budget(&adapter.get_info()),
             adapter,
+            terrain_dropped_mips: 0,
+            shadow_notches: 0,
+            memory_exhausted: false,
             fbos,
             surface,
             pipelines: RwLock::new(Pipelines::new()),
@@ -415,10 +512,15 @@ impl GfxContext {
             frustrum: InfiniteFrustrum::new([Plane::X; 5]),
             sun_params: [(); 4].map(|_| Uniform::new(Default::default(), &device)),
             render_params: Uniform::new(Default::default(), &device),
+            palette: Palette::new(&device),
+            outline_params: (0..MAX_OUTLINES)
+                .map(|_| Uniform::new(Default::default(), &device))
+                .collect(),
             texture_cache_paths: textures,
             texture_cache_bytes: Default::default(),
             null_texture,
             linear_sampler,
+            samplers,
             mesh_cache: Default::default(),
             mesh_errors: Default::default(),
             samples,
@@ -430,11 +532,14 @@ impl GfxContext {
             bnoise_bg,
             sun_shadowmap: Self::mk_shadowmap(&device, 2048),
             lamplights: LampLights::new(&device, &queue),
+            pass_timer: PassTimer::new(&device, &queue),
             device,
             queue,
             pbr,
             defines: Default::default(),
             defines_changed: false,
+            frame_dump: FrameDump::default(),
+            atlas: TextureAtlas::default(),
             settings: None,
             perf: Default::default(),
             mipmap_gen,
@@ -452,10 +557,40 @@ impl GfxContext {
         me
     }
 
-    pub fn register_material(&mut self, material: Material) -> MaterialID {
+    pub fn register_material(&mut self, mut material: Material) -> MaterialID {
+        if self.samplers.anisotropy() != MAX_ANISOTROPY {
+            material.rebuild_bg(&self.device, &self.samplers);
+        }
         self.materials.insert(material)
     }
 
+    fn linear_sampler_desc() -> SamplerDescriptor<'static> {
+        SamplerDescriptor {
+            label: Some("basic linear sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            anisotropy_clamp: MAX_ANISOTROPY,
+            ..Default::default()
+        }
+    }
+
+    /// Recreates the samplers and the material bind groups with the new anisotropy, the other
+    /// bind groups check [`SamplerRegistry::generation`]
+    fn set_anisotropy(&mut self, level: u16) {
+        if !self.samplers.set_anisotropy(level) {
+            return;
+        }
+        self.linear_sampler = self
+            .samplers
+            .get(&self.device, &Self::linear_sampler_desc());
+        for material in self.materials.values_mut() {
+            material.rebuild_bg(&self.device, &self.samplers);
+        }
+        self.default_material
+            .rebuild_bg(&self.device, &self.samplers);
+    }
+
     pub fn material(&self, id: MaterialID) -> &Material {
         self.materials.get(id).unwrap_or(&self.default_material)
     }
@@ -494,16 +629,20 @@ impl GfxContext {
             view_formats: &[],
         };
         let texture = device.create_texture(&desc);
+        let memory = GpuAllocation::texture(GpuCategory::Shadows, "shadow map texture", &texture);
 
         let view = texture.create_view(&TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&Texture::depth_compare_sampler());
+        let sampler_desc = Texture::depth_compare_sampler();
+        let sampler = device.create_sampler(&sampler_desc);
         Texture {
             texture,
             view,
             sampler,
+            sampler_desc,
             format,
             extent,
             transparent: false,
+            memory,
         }
     }
 
@@ -534,8 +673,14 @@ impl GfxContext {
             return Ok(tex.clone());
         }
 
+        let builder = TextureBuilder::try_from_path(&p).map_err(|e| {
+            Diagnostic::error("textures", format!("could not load texture: {e}"))
+                .asset(&p)
+                .report();
+            e
+        })?;
         let tex = Arc::new(
-            TextureBuilder::try_from_path(&p)?
+            builder
                 .with_label(label)
                 .with_mipmaps(&self.mipmap_gen)
                 .build(&self.device, &self.queue),
@@ -568,6 +713,12 @@ impl GfxContext {
         }
     }
 
+    /// Makes `path` give a mesh built in code, for the models that have no file
+    pub fn set_mesh(&mut self, path: &Path, mesh: Mesh) {
+        self.mesh_errors.remove(path);
+        self.mesh_cache.insert(path.to_path_buf(), Arc::new(mesh));
+    }
+
     pub fn palette(&self) -> Arc<Texture> {
         self.texture_cache_paths
             .get(&*PathBuf::from("assets/sprites/palette.png"))
@@ -604,15 +755,13 @@ impl GfxContext {
             self.update_sc = true;
         }
 
+        self.apply_shadows(settings.shadows);
         let params = self.render_params.value_mut();
-        params.shadow_mapping_resolution = settings.shadows.size().unwrap_or(0) as i32;
-
-        if let Some(v) = settings.shadows.size() {
-            if self.sun_shadowmap.extent.width != v {
-                self.sun_shadowmap = GfxContext::mk_shadowmap(&self.device, v);
-                self.update_simplelit_bg();
-            }
-        }
+        params.terrain_mip_bias = if settings.terrain_mip_bias {
+            TERRAIN_MIP_BIAS
+        } else {
+            0.0
+        };
 
         let samples = match settings.msaa {
             true => 4,
@@ -622,8 +771,14 @@ impl GfxContext {
         if self.samples != samples {
             self.samples = samples;
             self.pipelines.write().unwrap().invalidate_all();
-            self.fbos = Self::create_textures(&self.device, &self.sc_desc, samples);
-            self.update_simplelit_bg();
+            self.fbos =
+                Self::create_textures(&self.device, &self.sc_desc, samples, settings.outlines);
+            self.rebuild_screen_bgs(ScreenTexture::ALL);
+        } else if self.fbos.outline_mask.is_some() != settings.outlines {
+            // the outline mask may share its texture with another target
+            self.fbos =
+                Self::create_textures(&self.device, &self.sc_desc, samples, settings.outlines);
+            self.rebuild_screen_bgs(ScreenTexture::ALL);
         }
 
         self.set_define_flag("FOG", settings.fog);
@@ -634,16 +789,37 @@ impl GfxContext {
         self.set_define_flag("PBR_ENABLED", settings.pbr_enabled);
         self.set_define_flag("MSAA", settings.msaa);
 
+        self.lamplights.set_max_lights(settings.max_lights as usize);
+        self.set_anisotropy(settings.anisotropy);
+
         self.settings = Some(settings);
     }
 
+    /// Sets the shadow quality, lowered when the GPU memory ran out
+    fn apply_shadows(&mut self, quality: ShadowQuality) {
+        let shadows = quality.lowered(self.shadow_notches);
+        self.render_params.value_mut().shadow_mapping_resolution =
+            shadows.size().unwrap_or(0) as i32;
+
+        if let Some(v) = shadows.size() {
+            if self.sun_shadowmap.extent.width != v {
+                self.sun_shadowmap = GfxContext::mk_shadowmap(&self.device, v);
+                self.update_simplelit_bg();
+            }
+        }
+    }
+
     pub fn set_time(&mut self, time: f32) {
         self.render_params.value_mut().time = time;
     }
 
     pub fn set_camera(&mut self, cam: Camera) {
-        self.render_params.value_mut().proj = cam.proj_cache;
-        self.render_params.value_mut().inv_proj = cam.inv_proj_cache;
+        let (cam_pos, cam_pos_low) = cam.eye_split();
+        let params = self.render_params.value_mut();
+        params.proj = cam.rel_proj_cache;
+        params.inv_proj = cam.inv_proj_cache;
+        params.cam_pos = cam_pos;
+        params.cam_pos_low = cam_pos_low;
 
         self.frustrum = InfiniteFrustrum::from_reversez_invviewproj(cam.eye(), cam.inv_proj_cache);
     }
@@ -666,12 +842,16 @@ impl GfxContext {
             .zip(self.render_params.value().sun_shadow_proj)
         {
             let mut cpy = *self.render_params.value();
-            cpy.proj = mat;
+            // the vertex shaders give positions relative to the camera
+            cpy.proj = mat * Matrix4::from_translation(cpy.cam_pos);
             *uni.value_mut() = cpy;
             uni.upload_to_gpu(&self.queue);
         }
 
         self.render_params.upload_to_gpu(&self.queue);
+        self.palette.upload_to_gpu(&self.queue);
+        self.lamplights
+            .cull(self.render_params.value().cam_pos, &mut self.perf);
         self.lamplights
             .apply_changes(&self.queue, &self.device, &mut before_main);
 
@@ -711,18 +891,29 @@ impl GfxContext {
     ) -> (f32, f32) {
         profiling::scope!("gfx::render_objs");
         self.perf.clear();
+        let (logical, physical, saved) = self.fbos.aliasing;
+        self.perf.screen_targets(logical, physical, saved);
 
         let mut objs = vec![];
+        let mut outlines = vec![];
         let mut fc = FrameContext {
             objs: &mut objs,
+            outlines: &mut outlines,
             gfx: self,
         };
 
         state.render(&mut fc);
 
+        outlines.truncate(MAX_OUTLINES);
+        for (outlined, params) in outlines.iter().zip(&mut self.outline_params) {
+            params.value_mut().color = outlined.color;
+            params.upload_to_gpu(&self.queue);
+        }
+
         let start_time = Instant::now();
 
         let objsref = &*objs;
+        let outlinesref = &*outlines;
 
         let mut gui_elapsed = 0.0;
 
@@ -737,7 +928,13 @@ impl GfxContext {
                 scope.spawn(|_| {
                     use rayon::prelude::*;
                     if self.render_params.value().shadow_mapping_resolution != 0 {
-                        encs.smap = self.shadow_map_pass(objsref).par_bridge().collect();
+                        // kept in order, the first cascade starts the timing and the last ends it
+                        encs.smap = self
+                            .sun_params
+                            .par_iter()
+                            .enumerate()
+                            .map(|(i, u)| self.shadow_cascade(i, u, objsref))
+                            .collect();
                     }
                 });
                 scope.spawn(|_| {
@@ -745,6 +942,7 @@ impl GfxContext {
                     passes::render_fog(self, &mut encs.before_main);
 
                     passes::render_background(self, &mut encs.after_main, frame);
+                    passes::render_outlines(self, &mut encs.after_main, frame, outlinesref);
                     passes::gen_ui_blur(self, &mut encs.after_main, frame);
                 });
 
@@ -764,6 +962,7 @@ impl GfxContext {
             passes::render_fog(self, &mut encs.before_main);
             encs.main = Some(self.main_render_pass(frame, objsref));
             passes::render_background(self, &mut encs.after_main, frame);
+            passes::render_outlines(self, &mut encs.after_main, frame, outlinesref);
             passes::gen_ui_blur(self, &mut encs.after_main, frame);
             (gui_elapsed, encs.gui) = self.render_gui(frame, state, render_gui);
         }
@@ -802,6 +1001,7 @@ impl GfxContext {
         objsref: &[Box<dyn Drawable>],
     ) -> CommandBuffer {
         profiling::scope!("main render pass");
+        let _timer = self.pass_timer.cpu_scope(TimedPass::Main);
         let mut main_enc = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
@@ -835,7 +1035,7 @@ impl GfxContext {
                 depth_ops: None,
                 stencil_ops: None,
             }),
-            timestamp_writes: None,
+            timestamp_writes: self.pass_timer.writes(TimedPass::Main, true, true),
             occlusion_query_set: None,
         });
         render_pass.set_bind_group(0, &self.render_params.bg, &[]);
@@ -867,17 +1067,31 @@ impl GfxContext {
         &'a self,
         objsref: &'a [Box<dyn Drawable>],
     ) -> impl Iterator<Item = CommandBuffer> + 'a {
-        self.sun_params.iter().enumerate().map(move |(i, u)| {
-            profiling::scope!(&format!("cascade shadow pass {}", i));
-            let mut smap_enc = self
-                .device
-                .create_command_encoder(&CommandEncoderDescriptor {
-                    label: Some("shadow map encoder"),
-                });
-            let sun_view = self.sun_shadowmap.layer_view(i as u32);
-            self.shadow_map_one_pass(u, objsref, &sun_view, &mut smap_enc);
-            smap_enc.finish()
-        })
+        self.sun_params
+            .iter()
+            .enumerate()
+            .map(move |(i, u)| self.shadow_cascade(i, u, objsref))
+    }
+
+    fn shadow_cascade(
+        &self,
+        i: usize,
+        u: &Uniform<RenderParams>,
+        objsref: &[Box<dyn Drawable>],
+    ) -> CommandBuffer {
+        profiling::scope!(&format!("cascade shadow pass {}", i));
+        let _timer = self.pass_timer.cpu_scope(TimedPass::Shadows);
+        let mut smap_enc = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("shadow map encoder"),
+            });
+        let sun_view = self.sun_shadowmap.layer_view(i as u32);
+        let timestamps = self
+            .pass_timer
+            .writes(TimedPass::Shadows, i == 0, i + 1 == N_CASCADES);
+        self.shadow_map_one_pass(u, objsref, &sun_view, &mut smap_enc, timestamps);
+        smap_enc.finish()
     }
 
     fn shadow_map_one_pass<'a>(
@@ -886,6 +1100,7 @@ impl GfxContext {
         objsref: &[Box<dyn Drawable>],
         shadowmap_view: &'a TextureView,
         enc: &'a mut CommandEncoder,
+        timestamp_writes: Option<RenderPassTimestampWrites<'_>>,
     ) {
         profiling::scope!("cascade shadow pass");
         let mut sun_shadow_pass = enc.begin_render_pass(&RenderPassDescriptor {
@@ -899,7 +1114,7 @@ impl GfxContext {
                 }),
                 stencil_ops: None,
             }),
-            timestamp_writes: None,
+            timestamp_writes,
             occlusion_query_set: None,
         });
 
@@ -912,6 +1127,7 @@ impl GfxContext {
 
     fn depth_prepass(&self, objsref: &[Box<dyn Drawable>]) -> CommandBuffer {
         profiling::scope!("depth prepass");
+        let _timer = self.pass_timer.cpu_scope(TimedPass::DepthPrepass);
         let mut prepass = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
@@ -928,7 +1144,7 @@ impl GfxContext {
                 }),
                 stencil_ops: None,
             }),
-            timestamp_writes: None,
+            timestamp_writes: self.pass_timer.writes(TimedPass::DepthPrepass, true, true),
             occlusion_query_set: None,
         });
 
@@ -941,7 +1157,13 @@ impl GfxContext {
         prepass.finish()
     }
 
+    /// Hands the finished frame to the frame dump, which copies it when recording
+    pub fn dump_frame(&mut self, frame: &wgpu::Texture) {
+        self.frame_dump.capture(&self.device, &self.queue, frame);
+    }
+
     pub fn finish_frame(&mut self, encoder: Encoders) {
+        let timestamps = self.pass_timer.end_frame(&self.device);
         self.queue.submit(
             encoder
                 .depth_prepass
@@ -951,12 +1173,24 @@ impl GfxContext {
                 .chain(Some(encoder.before_main.finish()))
                 .chain(encoder.main)
                 .chain(Some(encoder.after_main.finish()))
-                .chain(encoder.gui),
+                .chain(encoder.gui)
+                .chain(timestamps),
+        );
+        self.pass_timer.after_submit();
+        self.perf.pass_times(
+            self.pass_timer.is_gpu(),
+            self.pass_timer
+                .times()
+                .map(|(pass, ms)| (pass.label(), ms))
+                .collect(),
         );
         if self.defines_changed {
             self.defines_changed = false;
             self.pipelines.write().unwrap().invalidate_all();
         }
+        if self.tick % MEMORY_CHECK_INTERVAL == 0 {
+            self.check_memory_budget();
+        }
         if self.tick % 30 == 0 {
             #[cfg(debug_assertions)]
             self.pipelines
@@ -967,64 +1201,218 @@ impl GfxContext {
         self.tick += 1;
     }
 
-    pub fn create_textures(device: &Device, desc: &SurfaceConfiguration, samples: u32) -> FBOs {
+    pub fn memory_budget(&self) -> u64 {
+        match self.settings.map_or(0, |s| s.vram_budget_mb) {
+            0 => self.adapter_memory_budget,
+            mb => mb as u64 * 1024 * 1024,
+        }
+    }
+
+    pub fn terrain_dropped_mips(&self) -> u32 {
+        self.terrain_dropped_mips
+    }
+
+    /// Lowers the detail of the terrain textures, then the resolution of the shadows, one step
+    /// at a time while the GPU memory is close to the budget
+    fn check_memory_budget(&mut self) {
+        let used = gpu_memory_total();
+        let budget = self.memory_budget();
+        self.perf
+            .gpu_memory(budget, self.terrain_dropped_mips, self.shadow_notches);
+        if (used as f64) < budget as f64 * MEMORY_PRESSURE {
+            return;
+        }
+
+        let can_lower_shadows = self.settings.is_some_and(|s| {
+            let cur = s.shadows.lowered(self.shadow_notches);
+            cur.lowered(1) != cur
+        });
+
+        let step = if self.terrain_dropped_mips < MAX_TERRAIN_DROPPED_MIPS {
+            self.terrain_dropped_mips += 1;
+            "lowering the terrain texture detail"
+        } else if let Some(settings) = self.settings.filter(|_| can_lower_shadows) {
+            self.shadow_notches += 1;
+            self.apply_shadows(settings.shadows);
+            "lowering the shadow resolution"
+        } else if !self.memory_exhausted {
+            self.memory_exhausted = true;
+            "nothing left to lower"
+        } else {
+            return;
+        };
+
+        Diagnostic::warning(
+            "graphics",
+            format!(
+                "GPU memory at {:.0}MB out of a {:.0}MB budget, {}",
+                used as f64 / (1024.0 * 1024.0),
+                budget as f64 / (1024.0 * 1024.0),
+                step
+            ),
+        )
+        .report();
+    }
+
+    pub fn create_textures(
+        device: &Device,
+        desc: &SurfaceConfiguration,
+        samples: u32,
+        outlines: bool,
+    ) -> FBOs {
         let size = (desc.width, desc.height);
-        let ssao = Texture::create_fbo(
-            device,
+        let depth_desc = TargetDesc {
             size,
-            TextureFormat::R8Unorm,
-            TextureUsages::RENDER_ATTACHMENT
-                | TextureUsages::TEXTURE_BINDING
-                | TextureUsages::COPY_SRC,
-            None,
-        );
-        let depth = Texture::create_depth_texture(device, size, samples);
-        let depth_bg = depth.bindgroup(
-            device,
-            &Texture::bindgroup_layout(
-                device,
-                [if samples > 1 {
-                    TL::NonfilterableFloatMultisampled
-                } else {
-                    TL::NonfilterableFloat
-                }],
+            format: TextureFormat::Depth32Float,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            samples,
+        };
+
+        let mut targets = vec![
+            (ScreenTexture::Depth, depth_desc),
+            (
+                ScreenTexture::Ssao,
+                TargetDesc {
+                    size,
+                    format: TextureFormat::R8Unorm,
+                    usage: TextureUsages::RENDER_ATTACHMENT
+                        | TextureUsages::TEXTURE_BINDING
+                        | TextureUsages::COPY_SRC,
+                    samples: 1,
+                },
             ),
-        );
-        let fog = Texture::create_fbo(
+            (
+                ScreenTexture::Fog,
+                TargetDesc {
+                    size: ((size.0 / 3).max(1), (size.1 / 3).max(1)),
+                    format: TextureFormat::Rgba16Float,
+                    usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                    samples: 1,
+                },
+            ),
+        ];
+        if samples > 1 {
+            targets.push((
+                ScreenTexture::ColorMsaa,
+                TargetDesc {
+                    size,
+                    format: desc.format,
+                    usage: TextureUsages::RENDER_ATTACHMENT,
+                    samples,
+                },
+            ));
+        }
+        if outlines {
+            targets.push((ScreenTexture::OutlineMask, depth_desc));
+        }
+
+        let alloc = frame_graph::allocate(&targets, FRAME_PASSES);
+        let textures: Vec<Arc<Texture>> = alloc
+            .textures
+            .iter()
+            .map(|desc| Arc::new(desc.create(device)))
+            .collect();
+        let target = |t: ScreenTexture| {
+            let i = targets.iter().position(|&(target, _)| target == t)?;
+            Some(textures[alloc.physical[i]].clone())
+        };
+
+        let ssao = target(ScreenTexture::Ssao).unwrap();
+        let depth = target(ScreenTexture::Depth).unwrap();
+        let depth_layout = Texture::bindgroup_layout(
             device,
-            (size.0 / 3, size.1 / 3),
-            TextureFormat::Rgba16Float,
-            TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
-            None,
+            [if samples > 1 {
+                TL::NonfilterableFloatMultisampled
+            } else {
+                TL::NonfilterableFloat
+            }],
         );
-        let ui_blur = passes::gen_blur_texture(device, desc);
+        let depth_bg = depth.bindgroup(device, &depth_layout);
+        let outline_mask = target(ScreenTexture::OutlineMask).map(|mask| {
+            let bg = mask.bindgroup(device, &depth_layout);
+            (mask, bg)
+        });
 
         FBOs {
             depth,
             depth_bg,
-            color_msaa: if samples > 1 {
-                Texture::create_color_msaa(device, desc, samples)
-            } else {
-                ssao.mip_view(0) // bogus
+            color_msaa: match target(ScreenTexture::ColorMsaa) {
+                Some(color) => color.texture.create_view(&TextureViewDescriptor::default()),
+                None => ssao.mip_view(0), // bogus
             },
+            fog: target(ScreenTexture::Fog).unwrap(),
             ssao,
-            fog,
-            ui_blur,
+            ui_blur: passes::gen_blur_texture(device, desc),
+            outline_mask,
             format: desc.format,
+            // the ui blur is persistent and has its own texture
+            aliasing: (
+                targets.len() + 1,
+                alloc.textures.len() + 1,
+                alloc.saved_bytes(&targets),
+            ),
         }
     }
 
+    /// Resizes the surface and the screen textures right away, sizes without area are ignored
     pub fn resize(&mut self, size: (u32, u32, f64)) {
+        if size.0 == 0 || size.1 == 0 {
+            return;
+        }
         self.size = size;
         self.sc_desc.width = self.size.0;
         self.sc_desc.height = self.size.1;
 
         self.surface.configure(&self.device, &self.sc_desc);
-        self.fbos = Self::create_textures(&self.device, &self.sc_desc, self.samples);
-        self.update_simplelit_bg();
+        self.fbos = Self::create_textures(
+            &self.device,
+            &self.sc_desc,
+            self.samples,
+            self.settings.map_or(true, |s| s.outlines),
+        );
+        self.rebuild_screen_bgs(ScreenTexture::ALL);
+    }
+
+    /// Resizes once the window is done resizing, see [`GfxContext::apply_pending_resize`]
+    pub fn request_resize(&mut self, size: (u32, u32, f64)) {
+        self.pending_resize.request(size, Instant::now());
+    }
+
+    /// Applies the requested resize before acquiring the next frame, so that the frame is
+    /// rendered at the new size. With `force`, the resize is not debounced, e.g. when the
+    /// surface is outdated. Returns the new size if the context was resized.
+    pub fn apply_pending_resize(&mut self, force: bool) -> Option<(u32, u32, f64)> {
+        let size = self.pending_resize.take(Instant::now(), force)?;
+        self.resize(size);
+        Some(size)
+    }
+
+    /// Whether the window has no area, nothing can be rendered until it is resized
+    pub fn is_minimized(&self) -> bool {
+        self.pending_resize.is_minimized()
+    }
+
+    /// Rebuilds the bind groups sampling the screen textures that were recreated
+    fn rebuild_screen_bgs(&mut self, changed: &[ScreenTexture]) {
+        for &bg in ScreenBindGroup::ALL {
+            if !bg.depends_on(changed) {
+                continue;
+            }
+            match bg {
+                ScreenBindGroup::SimpleLit => self.build_simplelit_bg(),
+                ScreenBindGroup::Sky => self.build_sky_bg(),
+                ScreenBindGroup::Water => self.build_water_bg(),
+            }
+        }
     }
 
     pub fn update_simplelit_bg(&mut self) {
+        self.build_simplelit_bg();
+        self.build_sky_bg();
+        self.build_water_bg();
+    }
+
+    fn build_simplelit_bg(&mut self) {
         self.simplelit_bg = Texture::multi_bindgroup(
             &[
                 &self
@@ -1042,7 +1430,9 @@ impl GfxContext {
             &self.device,
             &bg_layout_litmesh(&self.device),
         );
+    }
 
+    fn build_sky_bg(&mut self) {
         let starfield = self.texture("assets/sprites/starfield.png", "starfield");
         self.sky_bg = Texture::multi_bindgroup(
             &[&*starfield, &self.fbos.fog, &self.pbr.environment_cube],
@@ -1051,6 +1441,9 @@ impl GfxContext {
                 .get_pipeline(BackgroundPipeline)
                 .get_bind_group_layout(2),
         );
+    }
+
+    fn build_water_bg(&mut self) {
         self.water_bg = Texture::multi_bindgroup(
             &[&self.fbos.fog],
             &self.device,
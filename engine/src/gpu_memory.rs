@@ -0,0 +1,198 @@
+//! Accounting of the memory allocated on the GPU.
+//! Textures and persistent buffers carry a [`GpuAllocation`] which records their size when
+//! created and removes it when dropped, so the totals follow the resources kept alive by Arcs.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use wgpu::AdapterInfo;
+
+/// What a GPU resource is used for
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GpuCategory {
+    Terrain,
+    Meshes,
+    Instances,
+    Shadows,
+    /// Screen targets and the textures of the post processing passes
+    Post,
+    UI,
+    Other,
+}
+
+impl GpuCategory {
+    pub const ALL: [GpuCategory; 7] = [
+        GpuCategory::Terrain,
+        GpuCategory::Meshes,
+        GpuCategory::Instances,
+        GpuCategory::Shadows,
+        GpuCategory::Post,
+        GpuCategory::UI,
+        GpuCategory::Other,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GpuCategory::Terrain => "Terrain",
+            GpuCategory::Meshes => "Meshes",
+            GpuCategory::Instances => "Instances",
+            GpuCategory::Shadows => "Shadows",
+            GpuCategory::Post => "Post",
+            GpuCategory::UI => "UI",
+            GpuCategory::Other => "Other",
+        }
+    }
+}
+
+struct Record {
+    label: String,
+    category: GpuCategory,
+    bytes: u64,
+}
+
+#[derive(Default)]
+struct Records {
+    slots: Vec<Option<Record>>,
+    free: Vec<usize>,
+}
+
+/// The totals are atomics so reading them every frame is free,
+/// the records are only locked when a resource is created or dropped
+struct GpuMemory {
+    totals: [AtomicU64; GpuCategory::ALL.len()],
+    records: Mutex<Records>,
+}
+
+static GPU_MEMORY: GpuMemory = GpuMemory {
+    totals: [const { AtomicU64::new(0) }; GpuCategory::ALL.len()],
+    records: Mutex::new(Records {
+        slots: Vec::new(),
+        free: Vec::new(),
+    }),
+};
+
+/// The accounting of one GPU resource, to keep next to it
+pub struct GpuAllocation {
+    slot: usize,
+}
+
+impl GpuAllocation {
+    pub fn new(category: GpuCategory, label: &str, bytes: u64) -> Self {
+        GPU_MEMORY.totals[category as usize].fetch_add(bytes, Ordering::Relaxed);
+        let record = Record {
+            label: label.to_string(),
+            category,
+            bytes,
+        };
+        let mut records = GPU_MEMORY.records.lock().unwrap();
+        let slot = match records.free.pop() {
+            Some(slot) => {
+                records.slots[slot] = Some(record);
+                slot
+            }
+            None => {
+                records.slots.push(Some(record));
+                records.slots.len() - 1
+            }
+        };
+        Self { slot }
+    }
+
+    pub fn texture(category: GpuCategory, label: &str, texture: &wgpu::Texture) -> Self {
+        Self::new(category, label, texture_bytes(texture))
+    }
+
+    /// Moves the resource to another category, for shared resources whose use is known later
+    pub fn set_category(&self, category: GpuCategory) {
+        let mut records = GPU_MEMORY.records.lock().unwrap();
+        let Some(Some(record)) = records.slots.get_mut(self.slot) else {
+            return;
+        };
+        GPU_MEMORY.totals[record.category as usize].fetch_sub(record.bytes, Ordering::Relaxed);
+        GPU_MEMORY.totals[category as usize].fetch_add(record.bytes, Ordering::Relaxed);
+        record.category = category;
+    }
+}
+
+impl Drop for GpuAllocation {
+    fn drop(&mut self) {
+        let mut records = GPU_MEMORY.records.lock().unwrap();
+        let Some(record) = records.slots.get_mut(self.slot).and_then(Option::take) else {
+            return;
+        };
+        GPU_MEMORY.totals[record.category as usize].fetch_sub(record.bytes, Ordering::Relaxed);
+        records.free.push(self.slot);
+    }
+}
+
+/// Size of the texture with all its mips, layers and samples
+pub fn texture_bytes(texture: &wgpu::Texture) -> u64 {
+    let format = texture.format();
+    let block_size = format.block_copy_size(None).unwrap_or(4) as u64;
+    let (block_w, block_h) = format.block_dimensions();
+    let size = texture.size();
+
+    let mut bytes = 0;
+    for mip in 0..texture.mip_level_count() {
+        let w = (size.width >> mip).max(1).div_ceil(block_w) as u64;
+        let h = (size.height >> mip).max(1).div_ceil(block_h) as u64;
+        bytes += w * h * block_size;
+    }
+    bytes * size.depth_or_array_layers as u64 * texture.sample_count() as u64
+}
+
+/// Bytes allocated in the category
+pub fn gpu_memory_used(category: GpuCategory) -> u64 {
+    GPU_MEMORY.totals[category as usize].load(Ordering::Relaxed)
+}
+
+/// Bytes allocated in every category
+pub fn gpu_memory_total() -> u64 {
+    GpuCategory::ALL.iter().map(|&c| gpu_memory_used(c)).sum()
+}
+
+/// The largest resources, biggest first
+pub fn gpu_memory_largest(n: usize) -> Vec<(String, GpuCategory, u64)> {
+    let records = GPU_MEMORY.records.lock().unwrap();
+    let mut largest: Vec<&Record> = records.slots.iter().flatten().collect();
+    largest.sort_unstable_by_key(|r| std::cmp::Reverse(r.bytes));
+    largest
+        .into_iter()
+        .take(n)
+        .map(|r| (r.label.clone(), r.category, r.bytes))
+        .collect()
+}
+
+/// Budget used when the settings don't set one, wgpu doesn't report the size of the VRAM
+/// so it is guessed from the kind of adapter
+pub fn default_memory_budget(info: &AdapterInfo) -> u64 {
+    const GIB: u64 = 1024 * 1024 * 1024;
+    match info.device_type {
+        wgpu::DeviceType::DiscreteGpu => 4 * GIB,
+        wgpu::DeviceType::IntegratedGpu | wgpu::DeviceType::VirtualGpu => 2 * GIB,
+        wgpu::DeviceType::Cpu | wgpu::DeviceType::Other => GIB,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocations_are_freed_on_drop() {
+        let before = gpu_memory_used(GpuCategory::UI);
+        let a = GpuAllocation::new(GpuCategory::UI, "test a", 1000);
+        let b = std::sync::Arc::new(GpuAllocation::new(GpuCategory::UI, "test b", 500));
+        let b2 = b.clone();
+        assert_eq!(gpu_memory_used(GpuCategory::UI), before + 1500);
+
+        a.set_category(GpuCategory::Shadows);
+        assert_eq!(gpu_memory_used(GpuCategory::UI), before + 500);
+
+        drop(a);
+        drop(b);
+        assert_eq!(gpu_memory_used(GpuCategory::UI), before + 500);
+        drop(b2);
+        assert_eq!(gpu_memory_used(GpuCategory::UI), before);
+    }
+}
@@ -4,7 +4,8 @@ use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use std::sync::{Arc, Mutex};
-use winit::event::{ElementState, KeyEvent, MouseScrollDelta, WindowEvent};
+use winit::dpi::PhysicalPosition;
+use winit::event::{DeviceId, ElementState, KeyEvent, MouseScrollDelta, WindowEvent};
 use winit::keyboard::{NamedKey, PhysicalKey, SmolStr};
 use winit::platform::scancode::PhysicalKeyExtScancode;
 use winit::window::CursorIcon;
@@ -30,6 +31,7 @@ pub fn get_cursor_icon() -> (CursorIcon, bool) {
 pub struct InputContext {
     pub mouse: MouseInfo,
     pub keyboard: KeyboardInfo,
+    pub gamepad: GamepadInfo,
     pub cursor_left: bool,
 }
 
@@ -51,6 +53,90 @@ impl InputContext {
         }
     }
 
+    pub fn handle_gamepad(&mut self, gilrs: &mut gilrs::Gilrs) {
+        use gilrs::{Axis, Button, EventType};
+
+        while let Some(gilrs::Event { event, .. }) = gilrs.next_event() {
+            let g = &mut self.gamepad;
+            match event {
+                EventType::Connected => {
+                    g.connected = true;
+                }
+                EventType::Disconnected => {
+                    g.connected = gilrs.gamepads().next().is_some();
+                    g.pressed.clear();
+                    g.left_stick = Vec2::ZERO;
+                    g.right_stick = Vec2::ZERO;
+                }
+                EventType::ButtonPressed(b, _) => {
+                    g.connected = true;
+                    if let Some(b) = GamepadButton::from_gilrs(b) {
+                        g.pressed.insert(b);
+                    }
+                }
+                EventType::ButtonReleased(b, _) => {
+                    if let Some(b) = GamepadButton::from_gilrs(b) {
+                        g.pressed.remove(&b);
+                    }
+                }
+                EventType::ButtonChanged(Button::LeftTrigger2, v, _) => g.left_trigger = v,
+                EventType::ButtonChanged(Button::RightTrigger2, v, _) => g.right_trigger = v,
+                EventType::AxisChanged(axis, v, _) => {
+                    g.connected = true;
+                    match axis {
+                        Axis::LeftStickX => g.left_stick.x = v,
+                        Axis::LeftStickY => g.left_stick.y = v,
+                        Axis::RightStickX => g.right_stick.x = v,
+                        Axis::RightStickY => g.right_stick.y = v,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Moves the mouse cursor with the right stick and clicks with the cursor button.
+    /// Returns the equivalent window events so every UI layer handles them like a real mouse.
+    pub fn virtual_cursor_events(&mut self, delta: f32, size: (u32, u32)) -> Vec<WindowEvent> {
+        let mut events = vec![];
+        let g = &mut self.gamepad;
+        if !g.connected {
+            return events;
+        }
+        // Safety: the dummy id is only used to tag synthesized events
+        let device_id = unsafe { DeviceId::dummy() };
+
+        let stick = g.settings.apply(g.right_stick);
+        if g.cursor_enabled && stick != Vec2::ZERO {
+            let mut p =
+                self.mouse.screen + vec2(stick.x, -stick.y) * g.settings.cursor_speed * delta;
+            p.x = p.x.clamp(0.0, size.0 as f32);
+            p.y = p.y.clamp(0.0, size.1 as f32);
+            self.mouse.screen = p;
+            events.push(WindowEvent::CursorMoved {
+                device_id,
+                position: PhysicalPosition::new(p.x as f64, p.y as f64),
+            });
+        }
+
+        let down = g.pressed.contains(&g.settings.cursor_click);
+        if down != g.cursor_down {
+            g.cursor_down = down;
+            events.push(WindowEvent::MouseInput {
+                device_id,
+                state: if down {
+                    ElementState::Pressed
+                } else {
+                    ElementState::Released
+                },
+                button: winit::event::MouseButton::Left,
+            });
+        }
+
+        events
+    }
+
     pub fn handle(&mut self, event: &WindowEvent) -> bool {
         match event {
             WindowEvent::CursorLeft { .. } => {
@@ -137,6 +223,116 @@ pub struct MouseInfo {
     pub pressed: FastSet<MouseButton>,
 }
 
+#[derive(Clone, Default)]
+pub struct GamepadInfo {
+    pub connected: bool,
+    pub pressed: FastSet<GamepadButton>,
+    /// Raw stick values in [-1; 1], y pointing up
+    pub left_stick: Vec2,
+    pub right_stick: Vec2,
+    pub left_trigger: f32,
+    pub right_trigger: f32,
+    /// Set by the game each frame, false while the right stick is used for something else
+    pub cursor_enabled: bool,
+    cursor_down: bool,
+    pub settings: GamepadSettings,
+}
+
+impl GamepadInfo {
+    /// Left stick after dead zone and response curve
+    pub fn left(&self) -> Vec2 {
+        self.settings.apply(self.left_stick)
+    }
+
+    /// Right stick after dead zone and response curve
+    pub fn right(&self) -> Vec2 {
+        self.settings.apply(self.right_stick)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadSettings {
+    /// Stick magnitude under which input is ignored
+    pub dead_zone: f32,
+    /// Exponent applied to the stick magnitude, higher values give more precision near the center
+    pub curve: f32,
+    /// Virtual cursor speed in pixels per second
+    pub cursor_speed: f32,
+    pub cursor_click: GamepadButton,
+}
+
+impl Default for GamepadSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.15,
+            curve: 2.0,
+            cursor_speed: 800.0,
+            cursor_click: GamepadButton::South,
+        }
+    }
+}
+
+impl GamepadSettings {
+    pub fn apply(&self, stick: Vec2) -> Vec2 {
+        let Some((dir, mag)) = stick.dir_dist() else {
+            return Vec2::ZERO;
+        };
+        if mag <= self.dead_zone {
+            return Vec2::ZERO;
+        }
+        let t = ((mag - self.dead_zone) / (1.0 - self.dead_zone).max(0.001)).min(1.0);
+        dir * t.powf(self.curve.max(0.1))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+impl GamepadButton {
+    fn from_gilrs(b: gilrs::Button) -> Option<Self> {
+        use gilrs::Button as B;
+        Some(match b {
+            B::South => Self::South,
+            B::East => Self::East,
+            B::North => Self::North,
+            B::West => Self::West,
+            B::LeftTrigger => Self::LeftBumper,
+            B::RightTrigger => Self::RightBumper,
+            B::LeftTrigger2 => Self::LeftTrigger,
+            B::RightTrigger2 => Self::RightTrigger,
+            B::Select => Self::Select,
+            B::Start => Self::Start,
+            B::Mode => Self::Mode,
+            B::LeftThumb => Self::LeftThumb,
+            B::RightThumb => Self::RightThumb,
+            B::DPadUp => Self::DPadUp,
+            B::DPadDown => Self::DPadDown,
+            B::DPadLeft => Self::DPadLeft,
+            B::DPadRight => Self::DPadRight,
+            _ => return None,
+        })
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct KeyboardInfo {
     pub pressed: FastSet<Key>,
@@ -317,3 +513,22 @@ pub enum Key {
     Paste,
     Cut,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::GamepadSettings;
+    use geom::vec2;
+
+    #[test]
+    fn stick_dead_zone_and_curve() {
+        let s = GamepadSettings {
+            dead_zone: 0.2,
+            curve: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(s.apply(vec2(0.1, 0.1)), geom::Vec2::ZERO);
+        assert!(s.apply(vec2(1.0, 0.0)).is_close(vec2(1.0, 0.0), 1e-5));
+        // halfway between the dead zone and the edge gives a quarter with a squared curve
+        assert!(s.apply(vec2(0.0, -0.6)).is_close(vec2(0.0, -0.25), 1e-5));
+    }
+}
@@ -1,14 +1,15 @@
 use crate::pbuffer::PBuffer;
-use crate::{compile_shader, Texture, TextureBuilder};
+use crate::{compile_shader, PerfCounters, Texture, TextureBuilder};
 use common::FastMap;
-use geom::Vec3;
+use geom::{Vec2, Vec3};
 use ordered_float::OrderedFloat;
+use std::time::Instant;
 use wgpu::{
     BufferUsages, CommandEncoder, ComputePassDescriptor, Device, Queue, TextureFormat,
     TextureUsages,
 };
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[repr(transparent)]
 struct EncodedLight(u32);
 
@@ -30,7 +31,10 @@ impl EncodedLight {
 
 pub type LightChunkID = (u16, u16);
 
-#[derive(Copy, Clone)]
+/// Distance at which a light stops lighting, as in lightPower of pbr/render.wgsl
+const LIGHT_RADIUS: f32 = LampLights::LIGHTCHUNK_SIZE as f32;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
 #[repr(C)]
 struct LightChunkUpdate {
     lights: [EncodedLight; 4],
@@ -42,9 +46,20 @@ struct LightChunkUpdate {
 
 u8slice_impl!(LightChunkUpdate);
 
+/// Light culling in world space tiles: every light is listed in each tile within its radius,
+/// so the shaders only go through the lights of the tile a fragment is in.
+/// Lights are stored per chunk on the CPU and culled by distance to the camera every time
+/// they or the camera's chunk change, only the tiles whose list changed are uploaded.
 pub struct LampLights {
     pub(crate) lightdata: Texture,
     pub(crate) lightdata2: Texture,
+    /// Most lights drawn at once, the ones closest to the camera are kept
+    max_lights: usize,
+    static_lights: FastMap<LightChunkID, Vec<Vec3>>,
+    dynamic_lights: FastMap<LightChunkID, Vec<Vec3>>,
+    uploaded: FastMap<LightChunkID, LightChunkUpdate>,
+    dirty: bool,
+    last_cam_chunk: LightChunkID,
     pending_changes: Vec<LightChunkUpdate>,
     changes_buffer: PBuffer,
     buffer_layout: wgpu::BindGroupLayout,
@@ -53,7 +68,7 @@ pub struct LampLights {
 }
 
 impl LampLights {
-    pub const LIGHTCHUNK_SIZE: u32 = 32; // in meters, side length of a light chunk, can contain at most 4 lights
+    pub const LIGHTCHUNK_SIZE: u32 = 32; // in meters, side length of a light chunk, lists at most 8 lights
     pub const MAP_SIZE: u32 = 50 * 512; // in meters, side length of the map
     pub const LIGHTMAP_SIZE: u32 = Self::MAP_SIZE / Self::LIGHTCHUNK_SIZE; // in light chunks
 
@@ -158,6 +173,12 @@ impl LampLights {
         Self {
             lightdata,
             lightdata2,
+            max_lights: usize::MAX,
+            static_lights: FastMap::default(),
+            dynamic_lights: FastMap::default(),
+            uploaded: FastMap::default(),
+            dirty: false,
+            last_cam_chunk: (0, 0),
             pending_changes: Vec::new(),
             changes_buffer: PBuffer::new(BufferUsages::COPY_DST | BufferUsages::STORAGE),
             buffer_layout,
@@ -167,7 +188,9 @@ impl LampLights {
     }
 
     pub fn reset(&mut self, device: &Device, queue: &Queue) {
+        let max_lights = self.max_lights;
         *self = Self::new(device, queue);
+        self.max_lights = max_lights;
     }
 
     pub fn chunk_id(pos: Vec3) -> LightChunkID {
@@ -186,14 +209,68 @@ impl LampLights {
         (xu, yu)
     }
 
+    /// Tiles reached by a light, the ones around its chunk that are within its radius
+    fn lit_tiles(light: Vec3) -> impl Iterator<Item = LightChunkID> {
+        let (x, y) = Self::chunk_id(light);
+        let size = Self::LIGHTCHUNK_SIZE as f32;
+        let max = Self::LIGHTMAP_SIZE as i32 - 1;
+        (-1..=1)
+            .flat_map(move |dy| (-1..=1).map(move |dx| (x as i32 + dx, y as i32 + dy)))
+            .filter(move |&(tx, ty)| {
+                if tx < 0 || ty < 0 || tx > max || ty > max {
+                    return false;
+                }
+                let closest = Vec2::new(
+                    light.x.clamp(tx as f32 * size, (tx + 1) as f32 * size),
+                    light.y.clamp(ty as f32 * size, (ty + 1) as f32 * size),
+                );
+                closest.distance2(light.xy()) < LIGHT_RADIUS * LIGHT_RADIUS
+            })
+            .map(|(tx, ty)| (tx as u16, ty as u16))
+    }
+
+    pub fn set_max_lights(&mut self, max_lights: usize) {
+        if self.max_lights != max_lights {
+            self.max_lights = max_lights;
+            self.dirty = true;
+        }
+    }
+
+    /// Replaces the static lights (street lamps) of a chunk
     pub fn register_update(&mut self, chunk: LightChunkID, lights: impl Iterator<Item = Vec3>) {
+        let l = lights.collect::<Vec<Vec3>>();
+        if l.is_empty() {
+            self.static_lights.remove(&chunk);
+        } else {
+            self.static_lights.insert(chunk, l);
+        }
+        self.dirty = true;
+    }
+
+    /// Replaces all the dynamic lights (headlights), meant to be called every frame
+    pub fn set_dynamic_lights(&mut self, lights: impl Iterator<Item = Vec3>) {
+        let had_lights = !self.dynamic_lights.is_empty();
+        self.dynamic_lights.clear();
+        for light in lights {
+            self.dynamic_lights
+                .entry(Self::chunk_id(light))
+                .or_default()
+                .push(light);
+        }
+        self.dirty |= had_lights || !self.dynamic_lights.is_empty();
+    }
+
+    fn encode_chunk<'a>(
+        chunk: LightChunkID,
+        lights: impl Iterator<Item = &'a Vec3>,
+    ) -> LightChunkUpdate {
         let origin = Vec3::new(
             chunk.0 as f32 * Self::LIGHTCHUNK_SIZE as f32,
             chunk.1 as f32 * Self::LIGHTCHUNK_SIZE as f32,
             0.0,
         );
 
-        let mut l = lights.collect::<Vec<Vec3>>();
+        let mut l = lights.copied().collect::<Vec<Vec3>>();
         l.sort_unstable_by_key(|x| {
             OrderedFloat(x.distance2(origin + Vec3::splat(Self::LIGHTCHUNK_SIZE as f32 / 2.0)))
         });
@@ -209,13 +286,69 @@ impl LampLights {
                 break;
             }
         }
-        self.pending_changes.push(LightChunkUpdate {
+        LightChunkUpdate {
             x: chunk.0 as u32,
             y: chunk.1 as u32,
             lights: encoded_lights,
             lights2: extra_lights,
             _pad: (0, 0),
-        });
+        }
+    }
+
+    /// Keeps the max_lights lights closest to the camera, lists them in the tiles they reach and
+    /// queues the uploads of the tiles that changed
+    pub fn cull(&mut self, cam_pos: Vec3, perf: &mut PerfCounters) {
+        let cam_chunk = Self::chunk_id(cam_pos);
+        if !self.dirty && cam_chunk == self.last_cam_chunk {
+            return;
+        }
+        self.dirty = false;
+        self.last_cam_chunk = cam_chunk;
+
+        let start = Instant::now();
+
+        let mut lights: Vec<Vec3> = self
+            .static_lights
+            .values()
+            .chain(self.dynamic_lights.values())
+            .flatten()
+            .copied()
+            .collect();
+        let total = lights.len();
+        if lights.len() > self.max_lights {
+            let cam = cam_pos.xy();
+            lights.select_nth_unstable_by_key(self.max_lights, |l| {
+                OrderedFloat(l.xy().distance2(cam))
+            });
+            lights.truncate(self.max_lights);
+        }
+        let active = lights.len();
+
+        let mut tiles: FastMap<LightChunkID, Vec<Vec3>> = FastMap::default();
+        for &light in &lights {
+            for tile in Self::lit_tiles(light) {
+                tiles.entry(tile).or_default().push(light);
+            }
+        }
+
+        let mut kept = FastMap::default();
+        for (tile, lights) in tiles {
+            let update = Self::encode_chunk(tile, lights.iter());
+            if self.uploaded.get(&tile) != Some(&update) {
+                self.pending_changes.push(update);
+            }
+            kept.insert(tile, update);
+        }
+
+        for &chunk in self.uploaded.keys() {
+            if !kept.contains_key(&chunk) {
+                self.pending_changes
+                    .push(Self::encode_chunk(chunk, std::iter::empty()));
+            }
+        }
+        self.uploaded = kept;
+
+        perf.lights(active, total, start.elapsed().as_secs_f32());
     }
 
     pub fn apply_changes(&mut self, queue: &Queue, device: &Device, encoder: &mut CommandEncoder) {
@@ -243,3 +376,33 @@ impl LampLights {
         self.pending_changes.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lights_reach_the_neighbouring_tiles() {
+        let mut tiles: Vec<LightChunkID> =
+            LampLights::lit_tiles(Vec3::new(100.0, 70.0, 5.0)).collect();
+        tiles.sort_unstable();
+        // the far corner of the neighbourhood is 38m away
+        assert_eq!(
+            tiles,
+            vec![
+                (2, 1),
+                (2, 2),
+                (2, 3),
+                (3, 1),
+                (3, 2),
+                (3, 3),
+                (4, 1),
+                (4, 2)
+            ]
+        );
+
+        // a light at the corner of the map does not go out of it
+        let tiles: Vec<LightChunkID> = LampLights::lit_tiles(Vec3::new(1.0, 1.0, 5.0)).collect();
+        assert_eq!(tiles, vec![(0, 0), (1, 0), (0, 1)]);
+    }
+}
@@ -4,22 +4,30 @@ extern crate common;
 #[macro_use]
 pub mod u8slice;
 
+mod atlas;
 mod audio;
 mod drawables;
 pub mod egui;
+mod frame_dump;
+mod frame_graph;
 pub mod framework;
 mod geometry;
 mod gfx;
+mod gpu_memory;
 pub mod input;
 mod lamplights;
 mod material;
 mod meshbuild;
 pub mod meshload;
+mod palette;
+mod pass_timer;
 mod passes;
 pub mod pbuffer;
 mod perf_counters;
 mod pipeline_builder;
 mod pipelines;
+mod resize;
+mod samplers;
 mod shader;
 mod texture;
 mod uniform;
@@ -28,18 +36,24 @@ mod vertex_types;
 #[cfg(feature = "yakui")]
 pub mod yakui;
 
+pub use atlas::*;
 pub use audio::*;
 pub use drawables::*;
+pub use frame_dump::*;
 pub use framework::Context;
 pub use geometry::*;
 pub use gfx::*;
+pub use gpu_memory::*;
 pub use input::*;
 pub use lamplights::*;
 pub use material::*;
 pub use meshbuild::*;
+pub use palette::*;
+pub use pass_timer::*;
 pub use perf_counters::*;
 pub use pipeline_builder::*;
 pub use pipelines::*;
+pub use samplers::*;
 pub use shader::*;
 pub use texture::*;
 pub use u8slice::*;
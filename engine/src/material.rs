@@ -1,12 +1,13 @@
-use crate::{GfxContext, Texture, TextureBuilder, ToU8Slice};
+use crate::{GfxContext, SamplerRegistry, Texture, TextureBuilder, ToU8Slice};
+use geom::LinearColor;
 use image::DynamicImage;
 use slotmapd::new_key_type;
 use std::sync::Arc;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BufferBinding, BufferSize, Device, Queue, SamplerBindingType,
-    TextureSampleType,
+    BindGroupLayoutEntry, BufferBinding, BufferSize, Device, Queue, Sampler, SamplerBindingType,
+    SamplerDescriptor, TextureSampleType, TextureView, TextureViewDescriptor,
 };
 
 new_key_type! {
@@ -18,8 +19,11 @@ pub type MaterialMap = slotmapd::SlotMap<MaterialID, Material>;
 pub struct Material {
     pub bg: BindGroup,
     pub mat_params: wgpu::Buffer,
+    params: MaterialParams,
     pub metallic_roughness_map: Option<Arc<Texture>>,
     pub transparent: bool,
+    /// Albedo, metallic roughness and normal map, to rebuild the bind group with other samplers
+    textures: [(TextureView, SamplerDescriptor<'static>); 3],
 }
 
 pub struct MetallicRoughness {
@@ -30,6 +34,8 @@ pub struct MetallicRoughness {
 
 const HAS_METALLIC_ROUGHNESS_MAP: u32 = 1 << 0;
 const HAS_NORMAL_MAP: u32 = 1 << 1;
+const IN_ATLAS: u32 = 1 << 2;
+const LIT_WINDOWS: u32 = 1 << 3;
 
 #[derive(Copy, Clone)]
 #[repr(C)]
@@ -37,6 +43,11 @@ struct MaterialParams {
     flags: u32,
     metallic: f32,
     roughness: f32,
+    _pad: f32,
+    /// Offset and scale of the repeated texture in the atlas page
+    uv_rect: [f32; 4],
+    /// Light given off at night, scaled by the lamp intensity
+    emissive: [f32; 4],
 }
 
 u8slice_impl!(MaterialParams);
@@ -63,6 +74,48 @@ impl Material {
         metallic_roughness: MetallicRoughness,
         normal_map: Option<&Texture>,
         bogus_tex: &Texture,
+    ) -> Self {
+        Self::new_inner(
+            device,
+            albedo,
+            metallic_roughness,
+            normal_map,
+            bogus_tex,
+            None,
+        )
+    }
+
+    /// Material of textures packed in an atlas page. The factors are baked in the page.
+    /// Without `uv_rect`, the UVs of the mesh were already moved to the page.
+    pub(crate) fn new_in_atlas(
+        device: &Device,
+        albedo: &Texture,
+        metallic_roughness: &Arc<Texture>,
+        normal_map: Option<&Texture>,
+        uv_rect: Option<[f32; 4]>,
+        bogus_tex: &Texture,
+    ) -> Self {
+        Self::new_inner(
+            device,
+            albedo,
+            MetallicRoughness {
+                metallic: 1.0,
+                roughness: 1.0,
+                tex: Some(metallic_roughness.clone()),
+            },
+            normal_map,
+            bogus_tex,
+            uv_rect,
+        )
+    }
+
+    fn new_inner(
+        device: &Device,
+        albedo: &Texture,
+        metallic_roughness: MetallicRoughness,
+        normal_map: Option<&Texture>,
+        bogus_tex: &Texture,
+        uv_rect: Option<[f32; 4]>,
     ) -> Self {
         let mut flags = 0;
         if metallic_roughness.tex.is_some() {
@@ -71,95 +124,136 @@ impl Material {
         if normal_map.is_some() {
             flags |= HAS_NORMAL_MAP;
         }
+        if uv_rect.is_some() {
+            flags |= IN_ATLAS;
+        }
 
+        let params = MaterialParams {
+            roughness: metallic_roughness.roughness,
+            metallic: metallic_roughness.metallic,
+            flags,
+            _pad: 0.0,
+            uv_rect: uv_rect.unwrap_or([0.0, 0.0, 1.0, 1.0]),
+            emissive: [0.0; 4],
+        };
         let mat_params = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("metallic"),
-            contents: ToU8Slice::cast_slice(std::slice::from_ref(&MaterialParams {
-                roughness: metallic_roughness.roughness,
-                metallic: metallic_roughness.metallic,
-                flags,
-            })),
+            contents: ToU8Slice::cast_slice(std::slice::from_ref(&params)),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        let layout = &Self::bindgroup_layout(device);
+        let mr_tex = metallic_roughness.tex.as_deref().unwrap_or(bogus_tex);
+        let normal_tex = normal_map.unwrap_or(bogus_tex);
+        let texs = [albedo, mr_tex, normal_tex];
 
-        let mut entries = vec![
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(&albedo.view),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::Sampler(&albedo.sampler),
-            },
-            wgpu::BindGroupEntry {
-                binding: 2,
-                resource: wgpu::BindingResource::Buffer(BufferBinding {
-                    buffer: &mat_params,
-                    offset: 0,
-                    size: None,
-                }),
-            },
-        ];
-
-        if let Some(ref metallic_roughness_tex) = metallic_roughness.tex {
-            entries.push(wgpu::BindGroupEntry {
-                binding: 3,
-                resource: wgpu::BindingResource::TextureView(&metallic_roughness_tex.view),
-            });
-            entries.push(wgpu::BindGroupEntry {
-                binding: 4,
-                resource: wgpu::BindingResource::Sampler(&metallic_roughness_tex.sampler),
-            });
-        } else {
-            // used as placeholder
-            entries.push(wgpu::BindGroupEntry {
-                binding: 3,
-                resource: wgpu::BindingResource::TextureView(&bogus_tex.view),
-            });
-            entries.push(wgpu::BindGroupEntry {
-                binding: 4,
-                resource: wgpu::BindingResource::Sampler(&bogus_tex.sampler),
-            });
-        }
-
-        if let Some(normal_map) = normal_map {
-            entries.push(wgpu::BindGroupEntry {
-                binding: 5,
-                resource: wgpu::BindingResource::TextureView(&normal_map.view),
-            });
-            entries.push(wgpu::BindGroupEntry {
-                binding: 6,
-                resource: wgpu::BindingResource::Sampler(&normal_map.sampler),
-            });
-        } else {
-            // used as placeholder
-            entries.push(wgpu::BindGroupEntry {
-                binding: 5,
-                resource: wgpu::BindingResource::TextureView(&bogus_tex.view),
-            });
-            entries.push(wgpu::BindGroupEntry {
-                binding: 6,
-                resource: wgpu::BindingResource::Sampler(&bogus_tex.sampler),
-            });
-        }
-
-        let bgdesc = BindGroupDescriptor {
-            layout,
-            entries: &entries,
-            label: None,
-        };
-        let bg = device.create_bind_group(&bgdesc);
+        let bg = Self::create_bg(
+            device,
+            texs.map(|tex| (&tex.view, &tex.sampler)),
+            &mat_params,
+        );
 
         Self {
             bg,
             mat_params,
+            params,
             metallic_roughness_map: metallic_roughness.tex,
             transparent: false,
+            textures: texs.map(|tex| {
+                (
+                    tex.texture.create_view(&TextureViewDescriptor::default()),
+                    tex.sampler_desc.clone(),
+                )
+            }),
         }
     }
 
+    /// Makes the whole material glow at night, e.g. the emissive parts of a model
+    pub fn set_emissive(&mut self, queue: &Queue, color: LinearColor) {
+        self.params.emissive = [color.r, color.g, color.b, 1.0];
+        self.params.flags &= !LIT_WINDOWS;
+        self.upload_params(queue);
+    }
+
+    /// Makes the vertical faces glow at night in a pattern of lit windows, for the buildings
+    /// that are generated without textures
+    pub fn set_lit_windows(&mut self, queue: &Queue, color: LinearColor) {
+        self.params.emissive = [color.r, color.g, color.b, 1.0];
+        self.params.flags |= LIT_WINDOWS;
+        self.upload_params(queue);
+    }
+
+    fn upload_params(&self, queue: &Queue) {
+        queue.write_buffer(
+            &self.mat_params,
+            0,
+            ToU8Slice::cast_slice(std::slice::from_ref(&self.params)),
+        );
+    }
+
+    /// Recreates the bind group with the samplers of the registry, e.g. after the anisotropic
+    /// filtering level changed
+    pub(crate) fn rebuild_bg(&mut self, device: &Device, samplers: &SamplerRegistry) {
+        let [albedo, mr, normal] = &self.textures;
+        let sampler =
+            |(_, desc): &(TextureView, SamplerDescriptor<'static>)| samplers.get(device, desc);
+        let (albedo_s, mr_s, normal_s) = (sampler(albedo), sampler(mr), sampler(normal));
+        self.bg = Self::create_bg(
+            device,
+            [
+                (&albedo.0, &*albedo_s),
+                (&mr.0, &*mr_s),
+                (&normal.0, &*normal_s),
+            ],
+            &self.mat_params,
+        );
+    }
+
+    /// Albedo, metallic roughness and normal map textures, then the parameters
+    fn create_bg(
+        device: &Device,
+        [albedo, mr, normal]: [(&TextureView, &Sampler); 3],
+        mat_params: &wgpu::Buffer,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            layout: &Self::bindgroup_layout(device),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(albedo.0),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(albedo.1),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(BufferBinding {
+                        buffer: mat_params,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(mr.0),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(mr.1),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(normal.0),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::Sampler(normal.1),
+                },
+            ],
+            label: None,
+        })
+    }
+
     pub(crate) fn bindgroup_layout(device: &Device) -> BindGroupLayout {
         device.create_bind_group_layout(&BindGroupLayoutDescriptor {
             label: Some("material layout"),
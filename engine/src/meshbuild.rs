@@ -1,6 +1,8 @@
 use crate::pbuffer::PBuffer;
-use crate::{GfxContext, IndexType, MaterialID, Mesh, MeshVertex, MikktGeometry, Tesselator};
-use geom::{Sphere, Vec3, AABB3};
+use crate::{
+    GfxContext, GpuCategory, IndexType, MaterialID, Mesh, MeshVertex, MikktGeometry, Tesselator,
+};
+use geom::{LinearColor, Sphere, Vec3, AABB3};
 use std::ops::Range;
 use wgpu::BufferUsages;
 
@@ -63,7 +65,7 @@ impl<const PERSISTENT: bool> MeshBuilder<PERSISTENT> {
             indices: vec![],
             vi_buffers: PERSISTENT.then(|| {
                 Box::new((
-                    PBuffer::new(BufferUsages::VERTEX),
+                    PBuffer::new(BufferUsages::VERTEX).with_category(GpuCategory::Meshes),
                     PBuffer::new(BufferUsages::INDEX),
                 ))
             }),
@@ -173,6 +175,45 @@ impl<const PERSISTENT: bool> MeshBuilder<PERSISTENT> {
         self.lods[self.current_lod].n_indices += self.indices.len() - n_indices;
     }
 
+    /// Adds an axis aligned box without its bottom face, for small procedural details
+    pub fn extend_box(
+        &mut self,
+        mat: Option<MaterialID>,
+        min: Vec3,
+        max: Vec3,
+        color: LinearColor,
+    ) {
+        let size = max - min;
+        let (x, y, z) = (Vec3::x(size.x), Vec3::y(size.y), Vec3::z(size.z));
+        // corner and two sides of each face, the normal is their cross product
+        let faces = [
+            (min.up(size.z), x, y),
+            (min, z, y),
+            (min + x, y, z),
+            (min, x, z),
+            (min + y, z, x),
+        ];
+        self.extend_with(mat, |vertices, add_index| {
+            let start = vertices.len();
+            for (o, u, v) in faces {
+                let base = (vertices.len() - start) as IndexType;
+                let normal = u.cross(v).normalize();
+                for p in [o, o + u, o + u + v, o + v] {
+                    vertices.push(MeshVertex {
+                        position: p.into(),
+                        normal,
+                        uv: [0.0; 2],
+                        color: color.into(),
+                        tangent: [0.0; 4],
+                    });
+                }
+                for i in [0, 1, 2, 0, 2, 3] {
+                    add_index(base + i);
+                }
+            }
+        });
+    }
+
     pub fn compute_tangents(&mut self) {
         for lod in &mut self.lods {
             for (_, range) in &lod.primitives {
@@ -203,7 +244,7 @@ impl<const PERSISTENT: bool> MeshBuilder<PERSISTENT> {
             vbuffer = &mut x.0;
             ibuffer = &mut x.1;
         } else {
-            tmpv = PBuffer::new(BufferUsages::VERTEX);
+            tmpv = PBuffer::new(BufferUsages::VERTEX).with_category(GpuCategory::Meshes);
             tmpi = PBuffer::new(BufferUsages::INDEX);
             vbuffer = &mut tmpv;
             ibuffer = &mut tmpi;
@@ -249,6 +290,8 @@ pub struct MeshLod {
     pub bounding_sphere: Sphere,
     pub n_vertices: usize,
     pub n_indices: usize,
+    /// Draw calls saved by drawing the primitives packed in the same atlas page at once
+    pub merged_draws: usize,
 }
 
 impl MeshLod {
@@ -270,3 +313,27 @@ impl MeshLod {
         screen_area >= self.screen_coverage
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::vec3;
+
+    #[test]
+    fn box_faces_point_outward() {
+        let mut builder = MeshBuilder::<false>::new_without_mat();
+        builder.extend(None, &[MeshVertex::default()], &[]);
+        let (min, max) = (vec3(-1.0, -2.0, 0.0), vec3(1.0, 2.0, 0.5));
+        builder.extend_box(None, min, max, LinearColor::WHITE);
+
+        let center = (min + max) * 0.5;
+        assert_eq!(builder.indices.len(), 5 * 6);
+        for tri in builder.indices.chunks(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| builder.vertices[i as usize]);
+            let [pa, pb, pc]: [Vec3; 3] = [a, b, c].map(|v| v.position.into());
+            let winding = (pb - pa).cross(pc - pa);
+            assert!(winding.dot(a.normal) > 0.0);
+            assert!(((pa + pb + pc) / 3.0 - center).dot(a.normal) > 0.0);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::meshbuild::MeshBuilder;
 use crate::{
-    GfxContext, IndexType, Material, MaterialID, Mesh, MeshVertex, MetallicRoughness, Texture,
-    TextureBuilder,
+    AtlasRegion, AtlasTextures, GfxContext, GpuCategory, IndexType, Material, MaterialID, Mesh,
+    MeshVertex, MetallicRoughness, Texture, TextureBuilder,
 };
 use geom::{Color, LinearColor, Matrix4, Quaternion, Vec2, Vec3, AABB3};
 use gltf::buffer::Source;
@@ -10,6 +10,7 @@ use gltf::json::texture::{MagFilter, MinFilter};
 use gltf::texture::WrappingMode;
 use gltf::{Document, Node, Scene};
 use image::{DynamicImage, ImageBuffer};
+use slotmapd::Key;
 use std::collections::hash_map::Entry;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -23,13 +24,14 @@ pub enum ImageLoadError {
     ImageNotFound,
 }
 
-pub fn load_image(
-    gfx: &GfxContext,
-    matname: Option<&str>,
-    tex: &gltf::Texture,
-    images: &[Data],
-    srgb: bool,
-) -> Result<Arc<Texture>, ImageLoadError> {
+/// A texture of a mesh, decoded but not uploaded yet
+pub struct DecodedImage {
+    pub img: DynamicImage,
+    pub sampler: wgpu::SamplerDescriptor<'static>,
+    pub hash: u64,
+}
+
+pub fn decode_image(tex: &gltf::Texture, images: &[Data]) -> Result<DecodedImage, ImageLoadError> {
     let idx = tex.source().index();
     if idx > images.len() {
         return Err(ImageLoadError::ImageNotFound);
@@ -48,17 +50,6 @@ pub fn load_image(
         sampl.wrap_t().as_gl_enum(),
     ));
 
-    let mut cache = gfx.texture_cache_bytes.lock().unwrap();
-
-    let ent = cache.entry(hash);
-
-    let ent = match ent {
-        Entry::Occupied(ent) => {
-            return Ok(ent.get().clone());
-        }
-        Entry::Vacant(v) => v,
-    };
-
     let w = data.width;
     let h = data.height;
     let d = data.pixels;
@@ -127,87 +118,159 @@ pub fn load_image(
         ..Default::default()
     };
 
+    Ok(DecodedImage { img, sampler, hash })
+}
+
+pub fn load_image(
+    gfx: &GfxContext,
+    matname: Option<&str>,
+    tex: &gltf::Texture,
+    images: &[Data],
+    srgb: bool,
+) -> Result<Arc<Texture>, ImageLoadError> {
+    let decoded = decode_image(tex, images)?;
+    Ok(upload_image(
+        gfx,
+        tex.name().or(matname).unwrap_or("mesh texture"),
+        decoded,
+        srgb,
+    ))
+}
+
+fn upload_image(gfx: &GfxContext, label: &str, decoded: DecodedImage, srgb: bool) -> Arc<Texture> {
+    let mut cache = gfx.texture_cache_bytes.lock().unwrap();
+
+    let ent = match cache.entry(decoded.hash) {
+        Entry::Occupied(ent) => {
+            return ent.get().clone();
+        }
+        Entry::Vacant(v) => v,
+    };
+
     let tex = Arc::new(
-        TextureBuilder::from_img(img)
-            .with_label(tex.name().or(matname).unwrap_or("mesh texture"))
-            .with_sampler(sampler)
+        TextureBuilder::from_img(decoded.img)
+            .with_label(label)
+            .with_category(GpuCategory::Meshes)
+            .with_sampler(decoded.sampler)
             .with_mipmaps(&gfx.mipmap_gen)
             .with_srgb(srgb)
             .build(&gfx.device, &gfx.queue),
     );
 
-    Ok(ent.insert(tex).clone())
+    ent.insert(tex).clone()
+}
+
+/// A material of a mesh, with where its textures are if they were packed in the atlas
+#[derive(Copy, Clone)]
+struct LoadedMaterial {
+    id: MaterialID,
+    atlas: Option<AtlasRegion>,
 }
 
 fn load_materials(
     gfx: &mut GfxContext,
     doc: &Document,
     images: &[Data],
-) -> Result<(Vec<MaterialID>, bool), LoadMeshError> {
+) -> Result<(Vec<LoadedMaterial>, bool), LoadMeshError> {
     let mut v = Vec::with_capacity(doc.materials().len());
     let mut needs_tangents = false;
     for gltfmat in doc.materials() {
         let pbr_mr = gltfmat.pbr_metallic_roughness();
+        let name = gltfmat.name();
 
         let metallic_v = pbr_mr.metallic_factor();
         let roughness_v = pbr_mr.roughness_factor();
 
-        let mut metallic_roughness = MetallicRoughness {
-            metallic: metallic_v,
-            roughness: roughness_v,
-            tex: None,
+        let metallic_roughness = pbr_mr
+            .metallic_roughness_texture()
+            .map(|t| decode_image(&t.texture(), images))
+            .transpose()?;
+
+        let normal = gltfmat
+            .normal_texture()
+            .map(|t| decode_image(&t.texture(), images))
+            .transpose()?;
+        needs_tangents |= normal.is_some();
+
+        let plain_color = pbr_mr.base_color_texture().is_none();
+        let albedo = match pbr_mr.base_color_texture() {
+            Some(albedo_tex) => decode_image(&albedo_tex.texture(), images)?,
+            None => {
+                let v: LinearColor = LinearColor::from(pbr_mr.base_color_factor());
+                let srgb: Color = v.into();
+                let rgba = [srgb.r, srgb.g, srgb.b, srgb.a].map(|c| (c * 255.0).round() as u8);
+                DecodedImage {
+                    img: DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+                        1,
+                        1,
+                        image::Rgba::<u8>::from(rgba),
+                    )),
+                    sampler: Texture::nearest_sampler(),
+                    hash: common::hash_u64(rgba),
+                }
+            }
         };
 
-        if let Some(metallic_roughness_tex) = pbr_mr.metallic_roughness_texture() {
-            metallic_roughness.tex = Some(load_image(
-                gfx,
-                gltfmat.name(),
-                &metallic_roughness_tex.texture(),
-                images,
-                false,
-            )?);
-        }
-
-        let mut normal = None;
-        if let Some(normal_tex) = gltfmat.normal_texture() {
-            normal = Some(load_image(
-                gfx,
-                gltfmat.name(),
-                &normal_tex.texture(),
-                images,
-                false,
-            )?);
-            needs_tangents = true;
+        let [r, g, b] = gltfmat.emissive_factor();
+        let emissive = (r > 0.0 || g > 0.0 || b > 0.0).then(|| LinearColor::new(r, g, b, 1.0));
+
+        // the materials of an atlas page are shared, the emissive ones are kept apart
+        let region = emissive.is_none().then(|| {
+            gfx.pack_in_atlas(AtlasTextures {
+                albedo: (&albedo.img, &albedo.sampler),
+                normal: normal.as_ref().map(|n| (&n.img, &n.sampler)),
+                metallic_roughness: metallic_roughness.as_ref().map(|mr| (&mr.img, &mr.sampler)),
+                metallic: metallic_v,
+                roughness: roughness_v,
+                hash: common::hash_u64((
+                    albedo.hash,
+                    normal.as_ref().map(|n| n.hash),
+                    metallic_roughness.as_ref().map(|mr| mr.hash),
+                    metallic_v.to_bits(),
+                    roughness_v.to_bits(),
+                )),
+            })
+        });
+        if let Some(region) = region.flatten() {
+            let id = gfx.atlas_region_material(region);
+            v.push(LoadedMaterial {
+                id,
+                atlas: Some(region),
+            });
+            continue;
         }
 
-        let albedo;
-        if let Some(albedo_tex) = pbr_mr.base_color_texture() {
-            albedo = load_image(gfx, gltfmat.name(), &albedo_tex.texture(), images, true)?;
+        let label = name.unwrap_or("mesh texture");
+        let albedo = if plain_color {
+            Arc::new(
+                TextureBuilder::from_img(albedo.img)
+                    .with_srgb(true)
+                    .with_label(&format!("{}: albedo 1x1", name.unwrap_or("mat")))
+                    .with_category(GpuCategory::Meshes)
+                    .with_sampler(albedo.sampler)
+                    .build(&gfx.device, &gfx.queue),
+            )
         } else {
-            let v: LinearColor = LinearColor::from(pbr_mr.base_color_factor());
-            let srgb: Color = v.into();
-            albedo = Arc::new(
-                TextureBuilder::from_img(DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
-                    1,
-                    1,
-                    image::Rgba::<u8>::from([
-                        (srgb.r * 255.0).round() as u8,
-                        (srgb.g * 255.0).round() as u8,
-                        (srgb.b * 255.0).round() as u8,
-                        (srgb.a * 255.0).round() as u8,
-                    ]),
-                )))
-                .with_srgb(true)
-                .with_label(&format!("{}: albedo 1x1", gltfmat.name().unwrap_or("mat")))
-                .with_sampler(Texture::nearest_sampler())
-                .build(&gfx.device, &gfx.queue),
-            );
-        }
+            upload_image(gfx, label, albedo, true)
+        };
+        let metallic_roughness = MetallicRoughness {
+            metallic: metallic_v,
+            roughness: roughness_v,
+            tex: metallic_roughness.map(|mr| upload_image(gfx, label, mr, false)),
+        };
+        let normal = normal.map(|n| upload_image(gfx, label, n, false));
+
         let transparent = albedo.transparent;
         let mut gfxmat = Material::new(gfx, &albedo, metallic_roughness, normal.as_deref());
         gfxmat.transparent = transparent;
+        if let Some(emissive) = emissive {
+            gfxmat.set_emissive(&gfx.queue, emissive);
+        }
         let matid = gfx.register_material(gfxmat);
-        v.push(matid)
+        v.push(LoadedMaterial {
+            id: matid,
+            atlas: None,
+        })
     }
     debug_assert_eq!(v.len(), doc.materials().len());
     Ok((v, needs_tangents))
@@ -349,40 +412,73 @@ pub fn load_mesh_with_properties(
 
     let getnode = |id| doc.nodes().nth(id).unwrap();
 
+    // draw calls of each lod without the atlas, one per material of each node
+    let mut n_draws = vec![];
+
     for (node, lod_id, coverage, transform_mat, rot_qat) in find_nodes(&scene, getnode) {
         let mesh = unwrap_cont!(node.mesh());
-        let mut primitives = mesh.primitives().collect::<Vec<_>>();
-        primitives.sort_unstable_by_key(|x| x.material().index());
 
         meshb.set_lod(lod_id, coverage);
+        if n_draws.len() <= lod_id {
+            n_draws.resize(lod_id + 1, 0);
+        }
 
-        for primitive in primitives {
-            let bbox = primitive.bounding_box();
-
+        let mut primitives = vec![];
+        for primitive in mesh.primitives() {
             let reader = primitive.reader(|b| Some(&data.get(b.index())?.0[..b.length()]));
             let matid = primitive
                 .material()
                 .index()
                 .ok_or(LoadMeshError::NoMaterial)?;
+            let uv: Vec<Vec2> = unwrap_cont!(reader.read_tex_coords(0))
+                .into_f32()
+                .map(Vec2::from)
+                .collect();
+
+            // the UVs that do not repeat the texture are moved to the atlas page, so that all
+            // the primitives of the page can be drawn at once
+            let mat = mats[matid];
+            let merged = mat.atlas.filter(|_| {
+                uv.iter()
+                    .all(|uv| (0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y))
+            });
+            let draw_mat = match merged {
+                Some(region) => gfx.atlas_page_material(region.page, region.has_normal),
+                None => mat.id,
+            };
+            primitives.push((primitive, uv, merged, draw_mat));
+        }
+        primitives.sort_unstable_by_key(|(_, _, _, mat)| mat.data().as_ffi());
+        let mut node_mats: Vec<_> = primitives
+            .iter()
+            .filter_map(|(p, ..)| p.material().index())
+            .collect();
+        node_mats.sort_unstable();
+        node_mats.dedup();
+        n_draws[lod_id] += node_mats.len();
+
+        for (primitive, uv, merged, draw_mat) in primitives {
+            let bbox = primitive.bounding_box();
+
+            let reader = primitive.reader(|b| Some(&data.get(b.index())?.0[..b.length()]));
 
             let positions = unwrap_cont!(reader.read_positions()).map(Vec3::from);
             let normals = unwrap_cont!(reader.read_normals()).map(Vec3::from);
-            let uv = unwrap_cont!(reader.read_tex_coords(0))
-                .into_f32()
-                .map(Vec2::from);
             let read_indices: Vec<u32> = unwrap_cont!(reader.read_indices()).into_u32().collect();
             let raw = positions.zip(normals).zip(uv).map(|((p, n), uv)| {
                 let pos = transform_mat * p.w(1.0);
                 let pos = pos.xyz() / pos.w;
+                let uv: [f32; 2] = uv.into();
+                let uv = merged.map_or(uv, |region| region.page_uv(uv));
                 (pos, rot_qat * n, uv)
             });
 
-            meshb.extend_with(Some(mats[matid]), |vertices, add_idx| {
+            meshb.extend_with(Some(draw_mat), |vertices, add_idx| {
                 for (pos, normal, uv) in raw {
                     vertices.push(MeshVertex {
                         position: pos.into(),
                         normal,
-                        uv: uv.into(),
+                        uv,
                         color: [1.0, 1.0, 1.0, 1.0],
                         tangent: [0.0; 4],
                     })
@@ -409,7 +505,10 @@ pub fn load_mesh_with_properties(
     if needs_tangents {
         meshb.compute_tangents();
     }
-    let m = meshb.build(gfx).ok_or(LoadMeshError::NoVertices)?;
+    let mut m = meshb.build(gfx).ok_or(LoadMeshError::NoVertices)?;
+    for (lod, n) in m.lods.iter_mut().zip(n_draws) {
+        lod.merged_draws = n.saturating_sub(lod.primitives.len());
+    }
 
     log::info!(
         "loaded mesh {:?} in {}ms{}",
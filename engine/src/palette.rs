@@ -0,0 +1,120 @@
+use crate::Uniform;
+use common::FastMap;
+use geom::LinearColor;
+use wgpu::{Device, Queue};
+
+/// Number of colors of the palette, the variation of a [`crate::MeshInstance`] is an index in it
+pub const PALETTE_SIZE: usize = 256;
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct PaletteColors([LinearColor; PALETTE_SIZE]);
+
+u8slice_impl!(PaletteColors);
+
+/// Slots of the palette given to a kind of instances, e.g. the paint colors of the cars
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaletteRange {
+    start: u8,
+    len: u8,
+}
+
+impl PaletteRange {
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Variation of an instance using the i-th color of the range, 0 (no variation) if empty
+    pub fn variation(&self, i: usize) -> u8 {
+        if self.len == 0 {
+            return 0;
+        }
+        self.start + (i % self.len as usize) as u8
+    }
+}
+
+/// Colors the instanced meshes are multiplied with, selected by the variation of each instance.
+/// The slot 0 is white so that the instances without variation keep their colors.
+pub struct Palette {
+    uniform: Uniform<PaletteColors>,
+    ranges: FastMap<&'static str, PaletteRange>,
+    used: usize,
+}
+
+impl Palette {
+    pub(crate) fn new(device: &Device) -> Self {
+        Self {
+            uniform: Uniform::new(PaletteColors([LinearColor::WHITE; PALETTE_SIZE]), device),
+            ranges: Default::default(),
+            used: 1,
+        }
+    }
+
+    /// Reserves slots for the colors, the slots of the same name are reused so that the
+    /// renderers can be recreated. An empty range is returned once the palette is full.
+    pub fn add(&mut self, name: &'static str, colors: &[LinearColor]) -> PaletteRange {
+        if let Some(&range) = self.ranges.get(name) {
+            if range.len() == colors.len() {
+                self.set(range, colors);
+                return range;
+            }
+        }
+        if colors.is_empty() {
+            return PaletteRange::default();
+        }
+        if self.used + colors.len() > PALETTE_SIZE {
+            log::warn!(
+                "palette is full, {} colors were not added and will not vary",
+                colors.len()
+            );
+            return PaletteRange::default();
+        }
+        let range = PaletteRange {
+            start: self.used as u8,
+            len: colors.len() as u8,
+        };
+        self.used += colors.len();
+        self.ranges.insert(name, range);
+        self.set(range, colors);
+        range
+    }
+
+    /// Changes the colors of the range, e.g. with the seasons
+    pub fn set(&mut self, range: PaletteRange, colors: &[LinearColor]) {
+        let start = range.start as usize;
+        let slots = &mut self.uniform.value_mut().0[start..start + range.len()];
+        for (slot, color) in slots.iter_mut().zip(colors) {
+            *slot = *color;
+        }
+    }
+
+    pub(crate) fn upload_to_gpu(&self, queue: &Queue) {
+        self.uniform.upload_to_gpu(queue);
+    }
+
+    pub(crate) fn bg(&self) -> &wgpu::BindGroup {
+        &self.uniform.bg
+    }
+
+    pub(crate) fn bindgroup_layout(device: &Device) -> wgpu::BindGroupLayout {
+        Uniform::<PaletteColors>::bindgroup_layout(device)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variations_stay_in_range() {
+        let range = PaletteRange { start: 5, len: 3 };
+        assert_eq!(range.variation(0), 5);
+        assert_eq!(range.variation(2), 7);
+        assert_eq!(range.variation(3), 5);
+        assert_eq!(PaletteRange::default().variation(12), 0);
+    }
+}
@@ -0,0 +1,306 @@
+//! Time taken by the render passes of a frame.
+//! When the adapter supports timestamp queries, the passes write GPU timestamps at their
+//! beginning and end. They are resolved into a ring of buffers that are read back once the GPU
+//! is done with them, like the frame dump does, so the times are a few frames late.
+//! Without timestamp queries, the CPU time spent encoding each pass is measured instead.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use wgpu::{
+    Buffer, BufferUsages, CommandBuffer, CommandEncoderDescriptor, Device, MapMode, QuerySet,
+    Queue, RenderPassTimestampWrites,
+};
+
+/// Frames that can be waiting for the GPU at the same time, the frames beyond are not measured
+const RING_SIZE: usize = 3;
+
+const PENDING: u8 = 0;
+const MAPPED: u8 = 1;
+const FAILED: u8 = 2;
+
+/// The passes that are timed. The GUI is drawn by its own renderers, its time is the CPU one
+/// the game already measures.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimedPass {
+    DepthPrepass,
+    Shadows,
+    Ssao,
+    Fog,
+    Main,
+    Background,
+    Outlines,
+    UiBlur,
+}
+
+impl TimedPass {
+    pub const ALL: [TimedPass; 8] = [
+        TimedPass::DepthPrepass,
+        TimedPass::Shadows,
+        TimedPass::Ssao,
+        TimedPass::Fog,
+        TimedPass::Main,
+        TimedPass::Background,
+        TimedPass::Outlines,
+        TimedPass::UiBlur,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimedPass::DepthPrepass => "depth prepass",
+            TimedPass::Shadows => "shadows",
+            TimedPass::Ssao => "ssao",
+            TimedPass::Fog => "fog",
+            TimedPass::Main => "main",
+            TimedPass::Background => "background",
+            TimedPass::Outlines => "outlines",
+            TimedPass::UiBlur => "ui blur",
+        }
+    }
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+const N_PASSES: usize = TimedPass::ALL.len();
+/// A timestamp at the beginning and one at the end of each pass
+const N_QUERIES: u32 = 2 * N_PASSES as u32;
+const RESOLVE_SIZE: u64 = N_QUERIES as u64 * wgpu::QUERY_SIZE as u64;
+
+struct ReadbackSlot {
+    buffer: Buffer,
+    state: Arc<AtomicU8>,
+    /// Passes that wrote their timestamps in the frame copied here, None when the slot is free
+    ran: Option<u32>,
+}
+
+struct Timestamps {
+    set: QuerySet,
+    resolve: Buffer,
+    slots: Vec<ReadbackSlot>,
+    /// Slot filled by the frame being submitted, it is mapped once the frame is submitted
+    filled: Option<usize>,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    /// Passes that wrote their timestamps this frame
+    ran: AtomicU32,
+}
+
+pub struct PassTimer {
+    timestamps: Option<Timestamps>,
+    /// Nanoseconds spent encoding each pass this frame
+    cpu_nanos: [AtomicU64; N_PASSES],
+    /// Passes encoded this frame
+    cpu_ran: AtomicU32,
+    /// Milliseconds taken by each pass the last time it was measured, None when it did not run
+    times: [Option<f32>; N_PASSES],
+}
+
+impl PassTimer {
+    pub(crate) fn new(device: &Device, queue: &Queue) -> Self {
+        let timestamps = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| Timestamps {
+                set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("pass timestamps"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count: N_QUERIES,
+                }),
+                resolve: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("pass timestamps resolve"),
+                    size: RESOLVE_SIZE,
+                    usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                slots: vec![],
+                filled: None,
+                period: queue.get_timestamp_period(),
+                ran: AtomicU32::new(0),
+            });
+        if timestamps.is_none() {
+            log::info!("timestamp queries are not supported, the passes are timed on the CPU");
+        }
+
+        Self {
+            timestamps,
+            cpu_nanos: std::array::from_fn(|_| AtomicU64::new(0)),
+            cpu_ran: AtomicU32::new(0),
+            times: [None; N_PASSES],
+        }
+    }
+
+    /// Whether the times are measured on the GPU, they are the CPU encoding times otherwise
+    pub fn is_gpu(&self) -> bool {
+        self.timestamps.is_some()
+    }
+
+    /// Milliseconds taken by the passes that ran, the last time they were measured
+    pub fn times(&self) -> impl Iterator<Item = (TimedPass, f32)> {
+        TimedPass::ALL
+            .into_iter()
+            .zip(self.times)
+            .filter_map(|(pass, t)| Some((pass, t?)))
+    }
+
+    /// The timestamps to write in a render pass of `pass`, None without timestamp queries.
+    /// A pass made of several render passes writes the beginning in the first one and the end in
+    /// the last one.
+    pub(crate) fn writes(
+        &self,
+        pass: TimedPass,
+        first: bool,
+        last: bool,
+    ) -> Option<RenderPassTimestampWrites<'_>> {
+        let ts = self.timestamps.as_ref()?;
+        if first {
+            ts.ran.fetch_or(pass.bit(), Ordering::Relaxed);
+        }
+        let i = pass as u32 * 2;
+        Some(RenderPassTimestampWrites {
+            query_set: &ts.set,
+            beginning_of_pass_write_index: first.then_some(i),
+            end_of_pass_write_index: last.then_some(i + 1),
+        })
+    }
+
+    /// Measures the CPU time spent encoding `pass` until the scope is dropped.
+    /// The passes are encoded in parallel, each pass adds its own time.
+    pub(crate) fn cpu_scope(&self, pass: TimedPass) -> CpuScope<'_> {
+        self.cpu_ran.fetch_or(pass.bit(), Ordering::Relaxed);
+        CpuScope {
+            timer: self,
+            pass,
+            start: Instant::now(),
+        }
+    }
+
+    /// Reads the times of the previous frames back, and resolves the timestamps of this frame.
+    /// The returned commands go after the passes in the submission.
+    pub(crate) fn end_frame(&mut self, device: &Device) -> Option<CommandBuffer> {
+        let cpu_ran = std::mem::take(self.cpu_ran.get_mut());
+        for (i, nanos) in self.cpu_nanos.iter_mut().enumerate() {
+            let nanos = std::mem::take(nanos.get_mut());
+            if self.timestamps.is_none() {
+                let ran = cpu_ran & TimedPass::ALL[i].bit() != 0;
+                self.times[i] = ran.then_some(nanos as f32 / 1_000_000.0);
+            }
+        }
+
+        let ts = self.timestamps.as_mut()?;
+        device.poll(wgpu::Maintain::Poll);
+        ts.collect(&mut self.times);
+
+        let ran = std::mem::take(ts.ran.get_mut());
+        if ran == 0 {
+            return None;
+        }
+        let idx = match ts.slots.iter().position(|s| s.ran.is_none()) {
+            Some(idx) => idx,
+            None if ts.slots.len() < RING_SIZE => {
+                ts.slots.push(ReadbackSlot::new(device));
+                ts.slots.len() - 1
+            }
+            // the GPU is a whole ring behind, this frame is not measured
+            None => return None,
+        };
+        let slot = &mut ts.slots[idx];
+
+        let mut enc = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("pass timestamps"),
+        });
+        enc.resolve_query_set(&ts.set, 0..N_QUERIES, &ts.resolve, 0);
+        enc.copy_buffer_to_buffer(&ts.resolve, 0, &slot.buffer, 0, RESOLVE_SIZE);
+        slot.ran = Some(ran);
+        ts.filled = Some(idx);
+        Some(enc.finish())
+    }
+
+    /// Starts reading back the timestamps of the frame, once it is submitted
+    pub(crate) fn after_submit(&mut self) {
+        let Some(ts) = self.timestamps.as_mut() else {
+            return;
+        };
+        let Some(idx) = ts.filled.take() else {
+            return;
+        };
+        let slot = &ts.slots[idx];
+        let state = slot.state.clone();
+        slot.buffer.slice(..).map_async(MapMode::Read, move |v| {
+            if v.is_err() {
+                log::error!("Failed to map buffer for reading the pass timestamps");
+                state.store(FAILED, Ordering::Release);
+                return;
+            }
+            state.store(MAPPED, Ordering::Release);
+        });
+    }
+}
+
+impl Timestamps {
+    /// Turns the timestamps the GPU is done with into times
+    fn collect(&mut self, times: &mut [Option<f32>; N_PASSES]) {
+        for slot in &mut self.slots {
+            let Some(ran) = slot.ran else {
+                continue;
+            };
+            match slot.state.swap(PENDING, Ordering::Acquire) {
+                PENDING => continue,
+                FAILED => {
+                    slot.ran = None;
+                    continue;
+                }
+                _ => {}
+            }
+            slot.ran = None;
+
+            {
+                let mapped = slot.buffer.slice(..).get_mapped_range();
+                let stamps: Vec<u64> = mapped
+                    .chunks_exact(8)
+                    .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+                    .collect();
+                for (i, pass) in TimedPass::ALL.into_iter().enumerate() {
+                    times[i] = if ran & pass.bit() == 0 {
+                        None
+                    } else {
+                        stamps[2 * i + 1]
+                            .checked_sub(stamps[2 * i])
+                            .map(|ticks| ticks as f32 * self.period / 1_000_000.0)
+                    };
+                }
+            }
+            slot.buffer.unmap();
+        }
+    }
+}
+
+impl ReadbackSlot {
+    fn new(device: &Device) -> Self {
+        Self {
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("pass timestamps readback"),
+                size: RESOLVE_SIZE,
+                usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            state: Arc::new(AtomicU8::new(PENDING)),
+            ran: None,
+        }
+    }
+}
+
+pub(crate) struct CpuScope<'a> {
+    timer: &'a PassTimer,
+    pass: TimedPass,
+    start: Instant,
+}
+
+impl Drop for CpuScope<'_> {
+    fn drop(&mut self) {
+        self.timer.cpu_nanos[self.pass as usize]
+            .fetch_add(self.start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{
-    CompiledModule, GfxContext, PipelineKey, RenderParams, Texture, Uniform, UvVertex, TL,
+    CompiledModule, GfxContext, PipelineKey, RenderParams, Texture, TimedPass, Uniform, UvVertex,
+    TL,
 };
 use wgpu::{
     BindGroupLayout, BlendState, CommandEncoder, DepthBiasState, FragmentState, IndexFormat,
@@ -10,6 +11,7 @@ use wgpu::{
 
 pub fn render_background(gfx: &GfxContext, enc: &mut CommandEncoder, frame: &TextureView) {
     profiling::scope!("bg pass");
+    let _timer = gfx.pass_timer.cpu_scope(TimedPass::Background);
     let ops = wgpu::Operations {
         load: wgpu::LoadOp::Load, // Don't clear! We're drawing after main pass
         store: wgpu::StoreOp::Store,
@@ -40,7 +42,7 @@ pub fn render_background(gfx: &GfxContext, enc: &mut CommandEncoder, frame: &Tex
             }),
             stencil_ops: None,
         }),
-        timestamp_writes: None,
+        timestamp_writes: gfx.pass_timer.writes(TimedPass::Background, true, true),
         occlusion_query_set: None,
     });
 
@@ -1,10 +1,12 @@
 use wgpu::{
     CommandEncoder, Device, FragmentState, PipelineLayoutDescriptor, PrimitiveState,
-    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
-    SurfaceConfiguration, TextureUsages, TextureView, VertexState,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPassTimestampWrites, RenderPipeline,
+    RenderPipelineDescriptor, SurfaceConfiguration, TextureUsages, TextureView, VertexState,
 };
 
-use crate::{CompiledModule, GfxContext, PipelineKey, Texture, TextureBuilder, TL};
+use crate::{
+    CompiledModule, GfxContext, GpuCategory, PipelineKey, Texture, TextureBuilder, TimedPass, TL,
+};
 
 const DOWNSCALE_PASSES: u32 = 2;
 
@@ -18,8 +20,10 @@ const DOWNSCALE_PASSES: u32 = 2;
 /// 3. Sample from the UI directly (bi-linearly filtered)
 pub fn gen_ui_blur(gfx: &GfxContext, enc: &mut CommandEncoder, frame: &TextureView) {
     profiling::scope!("ui blur pass");
+    let _timer = gfx.pass_timer.cpu_scope(TimedPass::UiBlur);
 
     let tex = &gfx.fbos.ui_blur;
+    let passes = tex.n_mips() - 1;
 
     initial_downscale(gfx, enc, frame);
 
@@ -31,17 +35,19 @@ pub fn gen_ui_blur(gfx: &GfxContext, enc: &mut CommandEncoder, frame: &TextureVi
     //    &tex.mip_view(0),
     //);
 
-    for mip_level in 0..DOWNSCALE_PASSES {
+    for mip_level in 0..passes {
         do_pass(
             gfx,
             enc,
             UIBlurPipeline::Downscale,
             &tex.mip_view(mip_level),
             &tex.mip_view(mip_level + 1),
+            gfx.pass_timer
+                .writes(TimedPass::UiBlur, mip_level == 0, false),
         );
     }
 
-    for mip_level in (0..DOWNSCALE_PASSES).rev() {
+    for mip_level in (0..passes).rev() {
         do_pass(
             gfx,
             enc,
@@ -52,6 +58,8 @@ pub fn gen_ui_blur(gfx: &GfxContext, enc: &mut CommandEncoder, frame: &TextureVi
             },
             &tex.mip_view(mip_level + 1),
             &tex.mip_view(mip_level),
+            gfx.pass_timer
+                .writes(TimedPass::UiBlur, false, mip_level == 0),
         );
     }
 }
@@ -77,6 +85,7 @@ fn do_pass(
     pipeline: UIBlurPipeline,
     src_view: &TextureView,
     dst_view: &TextureView,
+    timestamp_writes: Option<RenderPassTimestampWrites<'_>>,
 ) {
     let pipe = gfx.get_pipeline(pipeline);
 
@@ -106,7 +115,7 @@ fn do_pass(
             },
         })],
         depth_stencil_attachment: None,
-        timestamp_writes: None,
+        timestamp_writes,
         occlusion_query_set: None,
     });
 
@@ -120,11 +129,16 @@ fn do_pass(
     blur_pass.draw(0..3, 0..1);
 }
 
+/// The small windows get fewer passes, each pass halves the size
 pub fn gen_blur_texture(device: &Device, sc: &SurfaceConfiguration) -> Texture {
-    TextureBuilder::empty(sc.width / 2, sc.height / 2, 1, sc.format)
+    let width = (sc.width / 2).max(1);
+    let height = (sc.height / 2).max(1);
+    let passes = DOWNSCALE_PASSES.min(width.min(height).ilog2());
+    TextureBuilder::empty(width, height, 1, sc.format)
         .with_usage(TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING)
         .with_no_anisotropy()
-        .with_fixed_mipmaps(1 + DOWNSCALE_PASSES)
+        .with_category(GpuCategory::Post)
+        .with_fixed_mipmaps(1 + passes)
         .build_no_queue(device)
 }
 
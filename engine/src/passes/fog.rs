@@ -1,5 +1,6 @@
 use crate::{
-    CompiledModule, GfxContext, PipelineKey, RenderParams, Texture, Uniform, UvVertex, TL,
+    CompiledModule, GfxContext, PipelineKey, RenderParams, Texture, TimedPass, Uniform, UvVertex,
+    TL,
 };
 use wgpu::{
     BlendComponent, BlendState, CommandEncoder, FragmentState, IndexFormat,
@@ -15,6 +16,7 @@ pub fn render_fog(gfx: &GfxContext, enc: &mut CommandEncoder) {
     //    return;
     //}
     profiling::scope!("fog");
+    let _timer = gfx.pass_timer.cpu_scope(TimedPass::Fog);
     let pipeline = gfx.get_pipeline(FogPipeline);
 
     let mut fog_pass = enc.begin_render_pass(&RenderPassDescriptor {
@@ -33,7 +35,7 @@ pub fn render_fog(gfx: &GfxContext, enc: &mut CommandEncoder) {
             },
         })],
         depth_stencil_attachment: None,
-        timestamp_writes: None,
+        timestamp_writes: gfx.pass_timer.writes(TimedPass::Fog, true, true),
         occlusion_query_set: None,
     });
 
@@ -0,0 +1,165 @@
+use crate::{
+    CompiledModule, Drawable, GfxContext, PipelineKey, Texture, TimedPass, Uniform, UvVertex, TL,
+};
+use geom::LinearColor;
+use wgpu::{
+    BlendState, CommandEncoder, FragmentState, IndexFormat, PipelineLayoutDescriptor,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    TextureView, VertexState,
+};
+
+/// Maximum number of differently colored outlines drawn in a frame
+pub const MAX_OUTLINES: usize = 4;
+
+/// Objects to draw an outline around, on top of the scene
+pub struct Outlined {
+    pub color: LinearColor,
+    pub obj: Box<dyn Drawable>,
+}
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct OutlineParams {
+    pub color: LinearColor,
+    /// In pixels
+    pub thickness: f32,
+    pub _pad: [f32; 3],
+}
+
+u8slice_impl!(OutlineParams);
+
+impl Default for OutlineParams {
+    fn default() -> Self {
+        Self {
+            color: LinearColor::WHITE,
+            thickness: 2.5,
+            _pad: [0.0; 3],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Hash)]
+pub struct OutlinePipeline;
+
+/// Renders each group of outlined objects into the depth mask,
+/// then dilates the mask in screen space to draw the outline onto the frame.
+pub fn render_outlines(
+    gfx: &GfxContext,
+    enc: &mut CommandEncoder,
+    frame: &TextureView,
+    outlines: &[Outlined],
+) {
+    let Some(ref mask) = gfx.fbos.outline_mask else {
+        return;
+    };
+    if outlines.is_empty() {
+        return;
+    }
+    profiling::scope!("outlines");
+    let _timer = gfx.pass_timer.cpu_scope(TimedPass::Outlines);
+    let pipeline = gfx.get_pipeline(OutlinePipeline);
+
+    let n = outlines.len().min(gfx.outline_params.len());
+    for (i, (outlined, params)) in outlines.iter().zip(&gfx.outline_params).enumerate() {
+        let mut mask_pass = enc.begin_render_pass(&RenderPassDescriptor {
+            label: Some("outline mask pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &mask.0.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: gfx.pass_timer.writes(TimedPass::Outlines, i == 0, false),
+            occlusion_query_set: None,
+        });
+        mask_pass.set_bind_group(0, &gfx.render_params.bg, &[]);
+        outlined.obj.draw_depth(gfx, &mut mask_pass, None);
+        drop(mask_pass);
+
+        let mut outline_pass = enc.begin_render_pass(&RenderPassDescriptor {
+            label: Some("outline pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: gfx
+                .pass_timer
+                .writes(TimedPass::Outlines, false, i + 1 == n),
+            occlusion_query_set: None,
+        });
+
+        outline_pass.set_pipeline(pipeline);
+        outline_pass.set_bind_group(0, &gfx.render_params.bg, &[]);
+        outline_pass.set_bind_group(1, &mask.1, &[]);
+        outline_pass.set_bind_group(2, &params.bg, &[]);
+        outline_pass.set_vertex_buffer(0, gfx.screen_uv_vertices.slice(..));
+        outline_pass.set_index_buffer(gfx.rect_indices.slice(..), IndexFormat::Uint32);
+        outline_pass.draw_indexed(0..6, 0, 0..1);
+    }
+}
+
+impl PipelineKey for OutlinePipeline {
+    fn build(
+        &self,
+        gfx: &GfxContext,
+        mut mk_module: impl FnMut(&str, &[&str]) -> CompiledModule,
+    ) -> RenderPipeline {
+        let render_pipeline_layout = gfx
+            .device
+            .create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("outline pipeline"),
+                bind_group_layouts: &[
+                    &gfx.render_params.layout,
+                    &Texture::bindgroup_layout(
+                        &gfx.device,
+                        [if gfx.samples > 1 {
+                            TL::NonfilterableFloatMultisampled
+                        } else {
+                            TL::NonfilterableFloat
+                        }],
+                    ),
+                    &Uniform::<OutlineParams>::bindgroup_layout(&gfx.device),
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let color_states = [Some(wgpu::ColorTargetState {
+            format: gfx.sc_desc.format,
+            write_mask: wgpu::ColorWrites::ALL,
+            blend: Some(BlendState::ALPHA_BLENDING),
+        })];
+
+        let outline = mk_module("outline", &[]);
+
+        let render_pipeline_desc = RenderPipelineDescriptor {
+            label: Some("outline pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &outline,
+                entry_point: "vert",
+                compilation_options: Default::default(),
+                buffers: &[UvVertex::desc()],
+            },
+            fragment: Some(FragmentState {
+                module: &outline,
+                entry_point: "frag",
+                compilation_options: Default::default(),
+                targets: &color_states,
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        };
+
+        gfx.device.create_render_pipeline(&render_pipeline_desc)
+    }
+}
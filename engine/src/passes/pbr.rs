@@ -1,5 +1,6 @@
 use crate::{
-    compile_shader, CompiledModule, GfxContext, PipelineKey, Texture, TextureBuilder, Uniform, TL,
+    compile_shader, CompiledModule, GfxContext, GpuCategory, PipelineKey, Texture, TextureBuilder,
+    Uniform, TL,
 };
 use common::FastMap;
 use geom::{Vec3, Vec4};
@@ -54,18 +55,21 @@ impl Pbr {
     pub fn new(device: &Device, queue: &Queue) -> Self {
         let environment_cube = TextureBuilder::empty(128, 128, 6, TextureFormat::Rgba16Float)
             .with_label("environment cubemap")
+            .with_category(GpuCategory::Post)
             .with_srgb(false)
             .with_sampler(Texture::linear_sampler())
             .build(device, queue);
 
         let diffuse_irradiance_cube = TextureBuilder::empty(16, 16, 6, TextureFormat::Rgba16Float)
             .with_label("irradiance cubemap")
+            .with_category(GpuCategory::Post)
             .with_srgb(false)
             .with_sampler(Texture::linear_sampler())
             .build(device, queue);
 
         let specular_prefilter_cube = TextureBuilder::empty(64, 64, 6, TextureFormat::Rgba16Float)
             .with_label("specular prefilter cubemap")
+            .with_category(GpuCategory::Post)
             .with_srgb(false)
             .with_sampler(Texture::linear_sampler())
             .with_fixed_mipmaps(5)
@@ -249,6 +253,7 @@ impl Pbr {
     fn make_split_sum_brdf_lut(device: &Device, queue: &Queue) -> Texture {
         let brdf_tex = TextureBuilder::empty(512, 512, 1, TextureFormat::Rg16Float)
             .with_label("brdf split sum lut")
+            .with_category(GpuCategory::Post)
             .with_srgb(false)
             .with_sampler(SamplerDescriptor {
                 address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -1,4 +1,4 @@
-use crate::{CompiledModule, GfxContext, PipelineKey, Texture, UvVertex, TL};
+use crate::{CompiledModule, GfxContext, PipelineKey, Texture, TimedPass, UvVertex, TL};
 use wgpu::{
     BlendComponent, BlendState, CommandEncoder, FragmentState, IndexFormat,
     PipelineLayoutDescriptor, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
@@ -13,6 +13,7 @@ pub fn render_ssao(gfx: &GfxContext, enc: &mut CommandEncoder) {
         return;
     }
     profiling::scope!("ssao");
+    let _timer = gfx.pass_timer.cpu_scope(TimedPass::Ssao);
     let pipeline = gfx.get_pipeline(SSAOPipeline);
     let mut ssao_pass = enc.begin_render_pass(&RenderPassDescriptor {
         label: Some("ssao pass"),
@@ -25,7 +26,7 @@ pub fn render_ssao(gfx: &GfxContext, enc: &mut CommandEncoder) {
             },
         })],
         depth_stencil_attachment: None,
-        timestamp_writes: None,
+        timestamp_writes: gfx.pass_timer.writes(TimedPass::Ssao, true, true),
         occlusion_query_set: None,
     });
 
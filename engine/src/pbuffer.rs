@@ -1,3 +1,4 @@
+use std::ops::Deref;
 use std::sync::Arc;
 
 use wgpu::{
@@ -5,27 +6,57 @@ use wgpu::{
     BufferDescriptor, BufferSize, BufferSlice, BufferUsages, Device, Queue,
 };
 
-use crate::GfxContext;
+use crate::{GfxContext, GpuAllocation, GpuCategory};
+
+/// A buffer accounted in the GPU memory, freed when the last Arc to it drops
+pub struct GpuBuffer {
+    buffer: wgpu::Buffer,
+    _memory: GpuAllocation,
+}
+
+impl Deref for GpuBuffer {
+    type Target = wgpu::Buffer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
 
 /// Short for Persistent Buffer, keeps memory around to reuse it
 #[derive(Clone)]
 pub struct PBuffer {
-    inner: Option<Arc<wgpu::Buffer>>,
+    inner: Option<Arc<GpuBuffer>>,
     len: u32,
     capacity: u32,
     usage: BufferUsages,
+    category: GpuCategory,
 }
 
 impl PBuffer {
+    /// Index buffers are counted as meshes and vertex buffers as instances,
+    /// use [`PBuffer::with_category`] otherwise
     pub fn new(usage: BufferUsages) -> Self {
+        let category = if usage.contains(BufferUsages::INDEX) {
+            GpuCategory::Meshes
+        } else if usage.contains(BufferUsages::VERTEX) {
+            GpuCategory::Instances
+        } else {
+            GpuCategory::Other
+        };
         Self {
             inner: None,
             len: 0,
             capacity: 0,
             usage,
+            category,
         }
     }
 
+    pub fn with_category(mut self, category: GpuCategory) -> Self {
+        self.category = category;
+        self
+    }
+
     pub fn write(&mut self, gfx: &GfxContext, data: &[u8]) {
         self.write_qd(&gfx.queue, &gfx.device, data);
     }
@@ -37,7 +68,7 @@ impl PBuffer {
         }
         if self.capacity < self.len {
             self.capacity = self.len.next_power_of_two();
-            self.inner = Some(mk_buffer(device, self.usage, self.capacity));
+            self.inner = Some(mk_buffer(device, self.usage, self.category, self.capacity));
             //log::info!("reallocating {} bytes", self.capacity);
         }
         queue.write_buffer(
@@ -73,7 +104,7 @@ impl PBuffer {
         self.inner.as_ref().map(|x| x.slice(..))
     }
 
-    pub fn inner(&self) -> Option<Arc<wgpu::Buffer>> {
+    pub fn inner(&self) -> Option<Arc<GpuBuffer>> {
         if self.len == 0 {
             return None;
         }
@@ -81,11 +112,19 @@ impl PBuffer {
     }
 }
 
-fn mk_buffer(device: &Device, usage: BufferUsages, size: u32) -> Arc<wgpu::Buffer> {
-    Arc::new(device.create_buffer(&BufferDescriptor {
-        label: Some("pbuffer"),
-        size: size as u64,
-        usage: usage | BufferUsages::COPY_DST,
-        mapped_at_creation: false,
-    }))
+fn mk_buffer(
+    device: &Device,
+    usage: BufferUsages,
+    category: GpuCategory,
+    size: u32,
+) -> Arc<GpuBuffer> {
+    Arc::new(GpuBuffer {
+        buffer: device.create_buffer(&BufferDescriptor {
+            label: Some("pbuffer"),
+            size: size as u64,
+            usage: usage | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }),
+        _memory: GpuAllocation::new(category, "pbuffer", size as u64),
+    })
 }
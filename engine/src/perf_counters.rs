@@ -14,6 +14,31 @@ pub struct PerfCounters {
     heightmap_triangles: AtomicUsize,
     heightmap_depth_triangles: AtomicUsize,
     heightmap_shadows_triangles: AtomicUsize,
+    heightmap_full_triangles: AtomicUsize,
+
+    atlas_merged_drawcalls: AtomicUsize,
+    /// The atlas only grows while meshes are loaded, so these are not cleared every frame
+    atlas_pages: usize,
+    atlas_materials: usize,
+
+    /// Lights are only culled when they change, so these are not cleared every frame
+    lights_active: usize,
+    lights_total: usize,
+    lights_cull_time: f32,
+
+    /// The screen targets are only allocated on resize, so these are not cleared every frame
+    screen_targets_logical: usize,
+    screen_targets_physical: usize,
+    screen_targets_saved_bytes: u64,
+
+    /// The memory budget is only checked every few frames, so these are not cleared every frame
+    gpu_memory_budget: u64,
+    terrain_dropped_mips: u32,
+    shadow_notches: u8,
+
+    /// The timestamps are read back a few frames late, so these are not cleared every frame
+    pass_times: Vec<(&'static str, f32)>,
+    pass_times_gpu: bool,
 }
 
 pub struct PerfCountersStatic {
@@ -29,6 +54,37 @@ pub struct PerfCountersStatic {
     pub heightmap_triangles: usize,
     pub heightmap_depth_triangles: usize,
     pub heightmap_shadows_triangles: usize,
+    /// Triangles the drawn heightmap chunks would take if they were all at the highest LOD
+    pub heightmap_full_triangles: usize,
+
+    /// Draw calls saved by drawing the materials of a texture atlas page at once
+    pub atlas_merged_drawcalls: usize,
+    pub atlas_pages: usize,
+    pub atlas_materials: usize,
+
+    pub lights_active: usize,
+    pub lights_total: usize,
+    /// Seconds spent culling the lights the last time they changed
+    pub lights_cull_time: f32,
+
+    /// Screen targets used by the passes of the frame
+    pub screen_targets_logical: usize,
+    /// Textures allocated for them, transient targets share textures when their lifetimes allow it
+    pub screen_targets_physical: usize,
+    pub screen_targets_saved_bytes: u64,
+
+    /// Bytes of GPU memory the detail is lowered to stay under
+    pub gpu_memory_budget: u64,
+    /// Most detailed mips left out of the terrain textures to stay under the budget
+    pub terrain_dropped_mips: u32,
+    /// Notches the shadow resolution was lowered by to stay under the budget
+    pub shadow_notches: u8,
+
+    /// Milliseconds taken by the render passes that ran
+    pub pass_times: Vec<(&'static str, f32)>,
+    /// Whether the pass times are measured on the GPU, else they are the CPU time spent encoding
+    /// them as the adapter has no timestamp queries
+    pub pass_times_gpu: bool,
 }
 
 impl PerfCounters {
@@ -47,6 +103,21 @@ impl PerfCounters {
             heightmap_triangles: *self.heightmap_triangles.get_mut(),
             heightmap_depth_triangles: *self.heightmap_depth_triangles.get_mut(),
             heightmap_shadows_triangles: *self.heightmap_shadows_triangles.get_mut(),
+            heightmap_full_triangles: *self.heightmap_full_triangles.get_mut(),
+            atlas_merged_drawcalls: *self.atlas_merged_drawcalls.get_mut(),
+            atlas_pages: self.atlas_pages,
+            atlas_materials: self.atlas_materials,
+            lights_active: self.lights_active,
+            lights_total: self.lights_total,
+            lights_cull_time: self.lights_cull_time,
+            screen_targets_logical: self.screen_targets_logical,
+            screen_targets_physical: self.screen_targets_physical,
+            screen_targets_saved_bytes: self.screen_targets_saved_bytes,
+            gpu_memory_budget: self.gpu_memory_budget,
+            terrain_dropped_mips: self.terrain_dropped_mips,
+            shadow_notches: self.shadow_notches,
+            pass_times: self.pass_times.clone(),
+            pass_times_gpu: self.pass_times_gpu,
         }
     }
 
@@ -60,6 +131,8 @@ impl PerfCounters {
         *self.heightmap_triangles.get_mut() = 0;
         *self.heightmap_depth_triangles.get_mut() = 0;
         *self.heightmap_shadows_triangles.get_mut() = 0;
+        *self.heightmap_full_triangles.get_mut() = 0;
+        *self.atlas_merged_drawcalls.get_mut() = 0;
     }
 
     pub fn drawcall(&self, triangles: impl TryInto<usize>) {
@@ -96,6 +169,13 @@ impl PerfCounters {
         );
     }
 
+    pub fn heightmap_full_triangles(&self, triangles: impl TryInto<usize>) {
+        self.heightmap_full_triangles.fetch_add(
+            triangles.try_into().unwrap_or(0),
+            std::sync::atomic::Ordering::Relaxed,
+        );
+    }
+
     pub fn heightmap_depth_drawcall(&self, triangles: impl TryInto<usize>, shadows: bool) {
         if shadows {
             self.heightmap_shadows_triangles.fetch_add(
@@ -109,4 +189,37 @@ impl PerfCounters {
             std::sync::atomic::Ordering::Relaxed,
         );
     }
+
+    pub fn atlas_merged_drawcalls(&self, merged: usize) {
+        self.atlas_merged_drawcalls
+            .fetch_add(merged, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn atlas(&mut self, pages: usize, materials: usize) {
+        self.atlas_pages = pages;
+        self.atlas_materials = materials;
+    }
+
+    pub fn lights(&mut self, active: usize, total: usize, cull_time: f32) {
+        self.lights_active = active;
+        self.lights_total = total;
+        self.lights_cull_time = cull_time;
+    }
+
+    pub fn screen_targets(&mut self, logical: usize, physical: usize, saved_bytes: u64) {
+        self.screen_targets_logical = logical;
+        self.screen_targets_physical = physical;
+        self.screen_targets_saved_bytes = saved_bytes;
+    }
+
+    pub fn gpu_memory(&mut self, budget: u64, terrain_dropped_mips: u32, shadow_notches: u8) {
+        self.gpu_memory_budget = budget;
+        self.terrain_dropped_mips = terrain_dropped_mips;
+        self.shadow_notches = shadow_notches;
+    }
+
+    pub fn pass_times(&mut self, gpu: bool, times: Vec<(&'static str, f32)>) {
+        self.pass_times_gpu = gpu;
+        self.pass_times = times;
+    }
 }
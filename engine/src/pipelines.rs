@@ -1,4 +1,5 @@
 use crate::{compile_shader, CompiledModule, GfxContext};
+use common::diagnostics::Diagnostic;
 use common::FastMap;
 use std::collections::hash_map::Entry;
 use std::collections::{BTreeMap, HashMap, HashSet};
@@ -150,7 +151,12 @@ impl Pipelines {
             let new_shader = compile_shader(device, shader_name, total_defines);
             let scope = beul::execute(device.pop_error_scope());
             if scope.is_some() {
-                log::error!("failed to compile shader for invalidation {}", shader_name);
+                Diagnostic::error(
+                    "shaders",
+                    format!("failed to compile shader {shader_name}, the previous one is kept"),
+                )
+                .asset(format!("assets/shaders/{shader_name}.wgsl"))
+                .report();
                 return;
             }
             *x = new_shader;
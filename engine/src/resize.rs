@@ -0,0 +1,187 @@
+//! Resizing of the screen sized textures.
+//! While the window is dragged, the resize events come every frame, so the textures are
+//! recreated at most once per debounce period, and once more when the size settled.
+//! The bind groups sampling the screen textures are listed here so that only those are rebuilt.
+
+use std::time::{Duration, Instant};
+
+/// Time between two recreations of the textures
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Screen sized textures of [`crate::FBOs`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ScreenTexture {
+    Depth,
+    ColorMsaa,
+    Ssao,
+    Fog,
+    UiBlur,
+    OutlineMask,
+}
+
+impl ScreenTexture {
+    pub const ALL: &'static [ScreenTexture] = &[
+        ScreenTexture::Depth,
+        ScreenTexture::ColorMsaa,
+        ScreenTexture::Ssao,
+        ScreenTexture::Fog,
+        ScreenTexture::UiBlur,
+        ScreenTexture::OutlineMask,
+    ];
+}
+
+/// Bind groups of the context that sample screen sized textures.
+/// The bind groups created during the frame (blur, outlines) always use the current textures.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ScreenBindGroup {
+    SimpleLit,
+    Sky,
+    Water,
+}
+
+impl ScreenBindGroup {
+    pub const ALL: &'static [ScreenBindGroup] = &[
+        ScreenBindGroup::SimpleLit,
+        ScreenBindGroup::Sky,
+        ScreenBindGroup::Water,
+    ];
+
+    fn textures(self) -> &'static [ScreenTexture] {
+        match self {
+            ScreenBindGroup::SimpleLit => &[ScreenTexture::Ssao, ScreenTexture::Fog],
+            ScreenBindGroup::Sky => &[ScreenTexture::Fog],
+            ScreenBindGroup::Water => &[ScreenTexture::Fog],
+        }
+    }
+
+    /// Whether the bind group must be rebuilt after these textures were recreated
+    pub fn depends_on(self, changed: &[ScreenTexture]) -> bool {
+        self.textures().iter().any(|t| changed.contains(t))
+    }
+}
+
+/// The last size the window was resized to, waiting to be applied
+#[derive(Default)]
+pub(crate) struct PendingResize {
+    size: Option<(u32, u32, f64)>,
+    requested: Option<Instant>,
+    last_applied: Option<Instant>,
+}
+
+impl PendingResize {
+    pub fn request(&mut self, size: (u32, u32, f64), now: Instant) {
+        self.size = Some(size);
+        self.requested = Some(now);
+    }
+
+    /// The size to resize to, if the last resize is old enough, once the size did not change
+    /// for a moment, or if `force` is set.
+    /// A window without area (e.g. minimized) is never resized.
+    pub fn take(&mut self, now: Instant, force: bool) -> Option<(u32, u32, f64)> {
+        let size = self.size?;
+        if size.0 == 0 || size.1 == 0 {
+            return None;
+        }
+        let elapsed =
+            |t: Option<Instant>| t.map_or(true, |t| now.duration_since(t) >= RESIZE_DEBOUNCE);
+        if !force && !elapsed(self.requested) && !elapsed(self.last_applied) {
+            return None;
+        }
+        self.size = None;
+        self.requested = None;
+        self.last_applied = Some(now);
+        Some(size)
+    }
+
+    /// Whether the window has no area, nothing can be rendered
+    pub fn is_minimized(&self) -> bool {
+        self.size.is_some_and(|s| s.0 == 0 || s.1 == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GfxContext;
+    use wgpu::{SurfaceConfiguration, TextureFormat, TextureUsages};
+
+    #[test]
+    fn resizes_are_debounced() {
+        let t0 = Instant::now();
+        let mut pending = PendingResize::default();
+        assert_eq!(pending.take(t0, true), None);
+
+        // the first resize is applied right away
+        pending.request((800, 600, 1.0), t0);
+        assert_eq!(pending.take(t0, false), Some((800, 600, 1.0)));
+
+        // then the window is dragged, one event per frame
+        let frame = Duration::from_millis(16);
+        let mut applied = 0;
+        for i in 1..=30 {
+            let now = t0 + frame * i;
+            pending.request((800 + i, 600, 1.0), now);
+            applied += pending.take(now, false).is_some() as u32;
+        }
+        assert!(applied <= 30 * frame.as_millis() as u32 / RESIZE_DEBOUNCE.as_millis() as u32);
+
+        // and the last size is applied once it settled
+        let last = t0 + frame * 30 + RESIZE_DEBOUNCE;
+        assert_eq!(pending.take(last, false), Some((830, 600, 1.0)));
+        assert_eq!(pending.take(last, true), None);
+
+        // forced when the surface is outdated
+        pending.request((1024, 768, 1.0), last);
+        assert_eq!(pending.take(last, true), Some((1024, 768, 1.0)));
+
+        pending.request((0, 768, 1.0), last);
+        assert!(pending.is_minimized());
+        assert_eq!(pending.take(last + RESIZE_DEBOUNCE, true), None);
+    }
+
+    #[test]
+    fn screen_bind_groups_follow_their_textures() {
+        assert!(ScreenBindGroup::SimpleLit.depends_on(ScreenTexture::ALL));
+        assert!(ScreenBindGroup::Water.depends_on(&[ScreenTexture::Fog]));
+        assert!(!ScreenBindGroup::Sky.depends_on(&[ScreenTexture::OutlineMask]));
+    }
+
+    /// Recreates the screen textures for several sizes on an offscreen device, skipped on the
+    /// machines without a GPU
+    #[test]
+    fn resize_without_validation_errors() {
+        let instance = wgpu::Instance::default();
+        let Some(adapter) = beul::execute(instance.request_adapter(&Default::default())) else {
+            println!("no adapter, skipping");
+            return;
+        };
+        let (device, _queue) = beul::execute(adapter.request_device(&Default::default(), None))
+            .expect("failed to find a suitable device");
+
+        let mut desc = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format: TextureFormat::Bgra8UnormSrgb,
+            width: 0,
+            height: 0,
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        };
+        for (w, h) in [
+            (1280, 720),
+            (1, 1),
+            (3, 7),
+            (1919, 1081),
+            (640, 2000),
+            (1280, 720),
+        ] {
+            desc.width = w;
+            desc.height = h;
+            device.push_error_scope(wgpu::ErrorFilter::Validation);
+            let _fbos = GfxContext::create_textures(&device, &desc, 4, true);
+            let err = beul::execute(device.pop_error_scope());
+            assert!(err.is_none(), "{}x{}: {:?}", w, h, err);
+        }
+    }
+}
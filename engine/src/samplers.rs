@@ -0,0 +1,135 @@
+use std::sync::{Arc, Mutex};
+
+use common::FastMap;
+use wgpu::{AddressMode, CompareFunction, Device, FilterMode, SamplerBorderColor};
+
+/// Anisotropy of the textures built with [`crate::TextureBuilder`] when filtering is linear
+pub const MAX_ANISOTROPY: u16 = 16;
+
+/// Anisotropic filtering levels offered in the settings
+pub const ANISOTROPY_LEVELS: [u16; 5] = [1, 2, 4, 8, 16];
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+struct SamplerKey {
+    address_modes: [AddressMode; 3],
+    filters: [FilterMode; 3],
+    lod_clamp: [u32; 2],
+    compare: Option<CompareFunction>,
+    anisotropy_clamp: u16,
+    border_color: Option<SamplerBorderColor>,
+}
+
+impl SamplerKey {
+    fn new(desc: &wgpu::SamplerDescriptor<'_>) -> Self {
+        Self {
+            address_modes: [
+                desc.address_mode_u,
+                desc.address_mode_v,
+                desc.address_mode_w,
+            ],
+            filters: [desc.mag_filter, desc.min_filter, desc.mipmap_filter],
+            lod_clamp: [desc.lod_min_clamp.to_bits(), desc.lod_max_clamp.to_bits()],
+            compare: desc.compare,
+            anisotropy_clamp: desc.anisotropy_clamp,
+            border_color: desc.border_color,
+        }
+    }
+}
+
+/// Samplers of the textures, with the anisotropic filtering level of the settings.
+/// A texture asks for anisotropic filtering by having an anisotropy clamp above 1 in its
+/// sampler descriptor, it is replaced by the current level.
+/// The bind groups built with these samplers must be rebuilt when the generation changes.
+pub struct SamplerRegistry {
+    anisotropy: u16,
+    generation: u32,
+    cache: Mutex<FastMap<SamplerKey, Arc<wgpu::Sampler>>>,
+}
+
+impl SamplerRegistry {
+    pub(crate) fn new() -> Self {
+        Self {
+            anisotropy: MAX_ANISOTROPY,
+            generation: 0,
+            cache: Default::default(),
+        }
+    }
+
+    pub fn anisotropy(&self) -> u16 {
+        self.anisotropy
+    }
+
+    /// Incremented each time the samplers change
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Rounds the level up to a supported one, returns whether it changed
+    pub(crate) fn set_anisotropy(&mut self, level: u16) -> bool {
+        let level = level.clamp(1, MAX_ANISOTROPY).next_power_of_two();
+        if level == self.anisotropy {
+            return false;
+        }
+        self.anisotropy = level;
+        self.generation += 1;
+        self.cache.lock().unwrap().clear();
+        true
+    }
+
+    /// The descriptor with the current anisotropy
+    pub fn descriptor(
+        &self,
+        desc: &wgpu::SamplerDescriptor<'static>,
+    ) -> wgpu::SamplerDescriptor<'static> {
+        let mut desc = desc.clone();
+        if desc.anisotropy_clamp > 1 {
+            desc.anisotropy_clamp = self.anisotropy;
+        }
+        desc
+    }
+
+    /// The sampler of a texture, shared by the textures with the same descriptor
+    pub fn get(
+        &self,
+        device: &Device,
+        desc: &wgpu::SamplerDescriptor<'static>,
+    ) -> Arc<wgpu::Sampler> {
+        let desc = self.descriptor(desc);
+        self.cache
+            .lock()
+            .unwrap()
+            .entry(SamplerKey::new(&desc))
+            .or_insert_with(|| Arc::new(device.create_sampler(&desc)))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Texture;
+
+    #[test]
+    fn anisotropy_only_applies_to_anisotropic_samplers() {
+        let mut samplers = SamplerRegistry::new();
+        assert!(!samplers.set_anisotropy(MAX_ANISOTROPY));
+        assert!(samplers.set_anisotropy(3));
+        assert_eq!(samplers.anisotropy(), 4);
+        assert_eq!(samplers.generation(), 1);
+
+        let anisotropic = wgpu::SamplerDescriptor {
+            anisotropy_clamp: MAX_ANISOTROPY,
+            ..Texture::linear_sampler()
+        };
+        assert_eq!(samplers.descriptor(&anisotropic).anisotropy_clamp, 4);
+        assert_eq!(
+            samplers
+                .descriptor(&Texture::nearest_sampler())
+                .anisotropy_clamp,
+            1
+        );
+
+        assert!(samplers.set_anisotropy(0));
+        assert_eq!(samplers.descriptor(&anisotropic).anisotropy_clamp, 1);
+    }
+}
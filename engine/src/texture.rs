@@ -17,15 +17,18 @@ use wgpu::{
 
 use common::FastMap;
 
-use crate::{compile_shader, CompiledModule};
+use crate::{compile_shader, CompiledModule, GpuAllocation, GpuCategory, MAX_ANISOTROPY};
 
 pub struct Texture {
     pub texture: wgpu::Texture,
     pub view: TextureView,
     pub sampler: wgpu::Sampler,
+    /// Descriptor of the sampler, to recreate it with the anisotropy of the settings
+    pub sampler_desc: SamplerDescriptor<'static>,
     pub format: TextureFormat,
     pub extent: Extent3d,
     pub transparent: bool,
+    pub memory: GpuAllocation,
 }
 
 /// TextureLayout
@@ -76,17 +79,21 @@ impl Texture {
             view_formats: &[],
         };
         let texture = device.create_texture(&desc);
+        let memory = GpuAllocation::texture(GpuCategory::Post, "fbo texture", &texture);
 
         let view = texture.create_view(&TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&Self::linear_sampler());
+        let sampler_desc = Self::linear_sampler();
+        let sampler = device.create_sampler(&sampler_desc);
 
         Self {
             texture,
             view,
             sampler,
+            sampler_desc,
             format,
             extent,
             transparent: false,
+            memory,
         }
     }
 
@@ -296,14 +303,7 @@ impl Texture {
 
             let v = image_data_buf_cpy.slice(..).get_mapped_range();
 
-            let Some(rgba) = image::RgbaImage::from_raw(w, h, v.to_vec()) else {
-                log::error!("Failed to create image from buffer for save_to_file");
-                return;
-            };
-
-            if let Err(e) = rgba.save(path) {
-                log::error!("Failed to save image to file: {}", e);
-            }
+            write_png(path, w, h, v.to_vec());
         });
     }
 
@@ -395,6 +395,7 @@ pub struct TextureBuilder<'a> {
     usage: TextureUsages,
     no_anisotropy: bool,
     sample_count: u32,
+    category: GpuCategory,
 }
 
 impl<'a> TextureBuilder<'a> {
@@ -403,6 +404,26 @@ impl<'a> TextureBuilder<'a> {
         self
     }
 
+    pub fn with_category(mut self, category: GpuCategory) -> Self {
+        self.category = category;
+        self
+    }
+
+    /// Leaves out the n most detailed mip levels of the image, halving its size each time
+    pub fn with_dropped_mips(mut self, n: u32) -> Self {
+        if let Some(img) = self.img.take() {
+            let w = (img.width() >> n).max(1);
+            let h = (img.height() >> n).max(1);
+            let img = match n {
+                0 => img,
+                _ => img.resize_exact(w, h, image::imageops::FilterType::Triangle),
+            };
+            self.dimensions = (w, h, 1);
+            self.img = Some(img);
+        }
+        self
+    }
+
     pub fn with_usage(mut self, usage: TextureUsages) -> Self {
         self.usage = usage;
         self
@@ -500,6 +521,7 @@ impl<'a> TextureBuilder<'a> {
                 | TextureUsages::RENDER_ATTACHMENT,
             no_anisotropy: false,
             sample_count: 1,
+            category: GpuCategory::Other,
         }
     }
 
@@ -519,6 +541,7 @@ impl<'a> TextureBuilder<'a> {
                 | TextureUsages::RENDER_ATTACHMENT,
             no_anisotropy: false,
             sample_count: 1,
+            category: GpuCategory::Other,
         }
     }
 
@@ -561,6 +584,7 @@ impl<'a> TextureBuilder<'a> {
             usage: self.usage,
             view_formats: &[],
         });
+        let memory = GpuAllocation::texture(self.category, self.label, &texture);
 
         let view = texture.create_view(&TextureViewDescriptor {
             dimension: Some(if self.dimensions.2 <= 1 {
@@ -577,7 +601,7 @@ impl<'a> TextureBuilder<'a> {
                 || sampl.mag_filter == wgpu::FilterMode::Linear)
             && !self.no_anisotropy
         {
-            sampl.anisotropy_clamp = 16;
+            sampl.anisotropy_clamp = MAX_ANISOTROPY;
         }
 
         let sampler = device.create_sampler(&sampl);
@@ -586,9 +610,11 @@ impl<'a> TextureBuilder<'a> {
             texture,
             view,
             sampler,
+            sampler_desc: sampl,
             format: self.format.unwrap(),
             extent,
             transparent: false,
+            memory,
         }
     }
 
@@ -651,6 +677,7 @@ impl<'a> TextureBuilder<'a> {
             usage: self.usage,
             view_formats: &[],
         });
+        let memory = GpuAllocation::texture(self.category, self.label, &texture);
 
         if let Some((data, pixwidth)) = data {
             queue.write_texture(
@@ -698,7 +725,7 @@ impl<'a> TextureBuilder<'a> {
                 || sampl.mag_filter == wgpu::FilterMode::Linear)
             && !self.no_anisotropy
         {
-            sampl.anisotropy_clamp = 16;
+            sampl.anisotropy_clamp = MAX_ANISOTROPY;
         }
 
         let sampler = device.create_sampler(&sampl);
@@ -707,9 +734,11 @@ impl<'a> TextureBuilder<'a> {
             texture,
             view,
             sampler,
+            sampler_desc: sampl,
             format,
             extent,
             transparent,
+            memory,
         }
     }
 }
@@ -875,3 +904,15 @@ impl MipmapGenerator {
         rpass.draw(0..3, 0..1);
     }
 }
+
+/// Saves tightly packed RGBA pixels read back from the GPU
+pub fn write_png(path: PathBuf, w: u32, h: u32, rgba: Vec<u8>) {
+    let Some(rgba) = image::RgbaImage::from_raw(w, h, rgba) else {
+        log::error!("Failed to create image from buffer for {:?}", path);
+        return;
+    };
+
+    if let Err(e) = rgba.save(path) {
+        log::error!("Failed to save image to file: {}", e);
+    }
+}
@@ -13,6 +13,8 @@ pub struct YakuiWrapper {
     pub zoom_factor: f32,
     pub format: TextureFormat,
     pub blur_bg_texture: TextureId,
+    /// A widget took a mouse press or scroll since the last frame
+    pub pointer_captured: bool,
 }
 
 impl YakuiWrapper {
@@ -52,6 +54,7 @@ impl YakuiWrapper {
             platform,
             zoom_factor: 1.0,
             format: gfx.fbos.format,
+            pointer_captured: false,
         }
     }
 
@@ -108,6 +111,40 @@ impl YakuiWrapper {
     }
 
     pub fn handle_event(&mut self, e: &winit::event::Event<()>) -> bool {
-        self.platform.handle_event(&mut self.yakui, e)
+        let sunk = self.platform.handle_event(&mut self.yakui, e);
+        if sunk && is_pointer_claim(e) {
+            self.pointer_captured = true;
+        }
+        sunk
     }
 }
+
+/// Mouse events always reach the InputContext, so a button released over the interface is not
+/// missed. The game decides by itself whether the mouse belongs to the interface.
+pub fn is_pointer_event(e: &winit::event::Event<()>) -> bool {
+    use winit::event::{Event, WindowEvent};
+    matches!(
+        e,
+        Event::WindowEvent {
+            event: WindowEvent::CursorMoved { .. }
+                | WindowEvent::CursorLeft { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::MouseWheel { .. },
+            ..
+        }
+    )
+}
+
+fn is_pointer_claim(e: &winit::event::Event<()>) -> bool {
+    use winit::event::{ElementState, Event, WindowEvent};
+    matches!(
+        e,
+        Event::WindowEvent {
+            event: WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                ..
+            } | WindowEvent::MouseWheel { .. },
+            ..
+        }
+    )
+}
@@ -19,11 +19,11 @@ impl DemoElement for Helmet {
             return Self { mesh: None };
         };
         let mut i = InstancedMeshBuilder::<true>::new_ref(&mesh);
-        i.instances.push(MeshInstance {
-            pos: vec3(0.0, 10.0, 0.0),
-            dir: Vec3::X * 3.0,
-            tint: LinearColor::WHITE,
-        });
+        i.instances.push(MeshInstance::new(
+            vec3(0.0, 10.0, 0.0),
+            Vec3::X * 3.0,
+            LinearColor::WHITE,
+        ));
         let mesh = i.build(gfx).unwrap();
 
         Self { mesh: Some(mesh) }
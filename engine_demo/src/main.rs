@@ -48,11 +48,11 @@ impl engine::framework::State for State {
 
         if let Ok(m) = gfx.mesh("DamagedHelmet.glb".as_ref()) {
             let mut i = InstancedMeshBuilder::<true>::new_ref(&m);
-            i.instances.push(MeshInstance {
-                pos: vec3(50.0, 00.0, 0.0),
-                dir: Vec3::X,
-                tint: LinearColor::WHITE,
-            });
+            i.instances.push(MeshInstance::new(
+                vec3(50.0, 00.0, 0.0),
+                Vec3::X,
+                LinearColor::WHITE,
+            ));
             meshes.push(i.build(gfx).unwrap());
         }
 
@@ -108,7 +108,6 @@ impl engine::framework::State for State {
         params.sun_col = 4.0
             * sun.z.max(0.0).sqrt().sqrt()
             * LinearColor::new(1.0, 0.95 + sun.z * 0.05, 0.95 + sun.z * 0.05, 1.0);
-        params.cam_pos = self.camera.eye();
         params.cam_dir = self.camera.dir();
         params.sun = sun;
         params.viewport = Vec2::new(gfx.size.0 as f32, gfx.size.1 as f32);
@@ -42,11 +42,11 @@ impl DemoElement for Spheres {
 
                 c.lods[0].primitives[0].0 = gfx.register_material(mat);
                 let mut i = InstancedMeshBuilder::<true>::new(c);
-                i.instances.push(MeshInstance {
-                    pos: 2.3 * vec3(x as f32, 0.0, z as f32),
-                    dir: Vec3::X,
-                    tint: LinearColor::WHITE,
-                });
+                i.instances.push(MeshInstance::new(
+                    2.3 * vec3(x as f32, 0.0, z as f32),
+                    Vec3::X,
+                    LinearColor::WHITE,
+                ));
                 meshes.push(i.build(gfx).unwrap());
             }
         }
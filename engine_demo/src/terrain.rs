@@ -102,18 +102,14 @@ impl DemoElement for Terrain {
 
         self.hitmesh.instances.clear();
         if let Some(pos) = self.last_hitpos {
-            self.hitmesh.instances.push(MeshInstance {
-                pos,
-                dir: Vec3::X * 20.0,
-                tint: LinearColor::WHITE,
-            });
+            self.hitmesh
+                .instances
+                .push(MeshInstance::new(pos, Vec3::X * 20.0, LinearColor::WHITE));
         }
         if let Some(pos) = self.plane_hitpos {
-            self.hitmesh.instances.push(MeshInstance {
-                pos,
-                dir: Vec3::X * 10.0,
-                tint: LinearColor::RED,
-            });
+            self.hitmesh
+                .instances
+                .push(MeshInstance::new(pos, Vec3::X * 10.0, LinearColor::RED));
         }
 
         fc.draw(self.hitmesh.build(fc.gfx));
@@ -167,7 +167,7 @@ impl Default for LinearColor {
 }
 
 impl LinearColor {
-    pub fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
         LinearColor { r, g, b, a }
     }
 
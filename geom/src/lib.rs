@@ -54,6 +54,7 @@ mod sphere;
 mod spline;
 mod spline1;
 mod spline3;
+mod sun;
 mod transform;
 mod v2;
 mod v3;
@@ -89,6 +90,7 @@ pub use sphere::*;
 pub use spline::*;
 pub use spline1::*;
 pub use spline3::*;
+pub use sun::*;
 pub use transform::*;
 pub use v2::*;
 pub use v3::*;
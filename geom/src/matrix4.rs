@@ -14,7 +14,7 @@
 //
 // Modified for the Egregoria project by the Egregoria developers.
 
-use crate::Vec4;
+use crate::{Vec3, Vec4};
 use std::ops::Mul;
 
 /// Column major matrix
@@ -32,6 +32,16 @@ impl Matrix4 {
         Self::default()
     }
 
+    #[rustfmt::skip]
+    pub fn from_translation(v: Vec3) -> Self {
+        Self::from([
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            v.x, v.y, v.z, 1.0,
+        ])
+    }
+
     pub fn determinent(&self) -> f32 {
         let tmp0 = unsafe { det_sub_proc_unsafe(self, 1, 2, 3) };
         tmp0.dot(&Vec4::from([self.x.x, self.y.x, self.z.x, self.w.x]))
@@ -33,6 +33,9 @@ pub struct Camera {
     pub proj_cache: Matrix4,
     #[serde(default, skip)]
     pub inv_proj_cache: Matrix4,
+    /// View projection of the positions relative to the eye, see [`Camera::eye_relative`]
+    #[serde(default, skip)]
+    pub rel_proj_cache: Matrix4,
 }
 
 impl Camera {
@@ -49,6 +52,7 @@ impl Camera {
             fovy: 60.0,
             proj_cache: Matrix4::zero(),
             inv_proj_cache: Matrix4::zero(),
+            rel_proj_cache: Matrix4::zero(),
         }
     }
 
@@ -73,6 +77,29 @@ impl Camera {
         self.pos + self.offset()
     }
 
+    /// The eye computed in f64, split in the nearest f32 and the remainder it cannot hold.
+    /// Far from the origin a f32 is a few millimeters apart from the next one, which is
+    /// enough to make the whole scene jitter when the camera is close to the ground.
+    pub fn eye_split(&self) -> (Vec3, Vec3) {
+        let offset = self.offset();
+        let split = |pos: f32, offset: f32| {
+            let v = pos as f64 + offset as f64;
+            let high = v as f32;
+            (high, (v - high as f64) as f32)
+        };
+        let (x, xl) = split(self.pos.x, offset.x);
+        let (y, yl) = split(self.pos.y, offset.y);
+        let (z, zl) = split(self.pos.z, offset.z);
+        (vec3(x, y, z), vec3(xl, yl, zl))
+    }
+
+    /// The position relative to the eye, as computed by the vertex shaders.
+    /// The high part is subtracted first: it is exact for positions close to the eye.
+    pub fn eye_relative(&self, pos: Vec3) -> Vec3 {
+        let (high, low) = self.eye_split();
+        (pos - high) - low
+    }
+
     pub fn unproj_ray(&self, pos: Vec2) -> Option<Ray3> {
         let v = self.inv_proj_cache
             * vec4(
@@ -96,7 +123,7 @@ impl Camera {
     /// Project a 3D point to the screen
     /// Returns the screen position (in viewport space) and the depth (inverse z)
     pub fn project(&self, pos: Vec3) -> (Vec2, f32) {
-        let v = self.proj_cache * vec4(pos.x, pos.y, pos.z, 1.0);
+        let v = self.rel_proj_cache * self.eye_relative(pos).w(1.0);
         let v = Vec3 {
             x: v.x / v.w,
             y: v.y / v.w,
@@ -111,12 +138,12 @@ impl Camera {
     }
 
     pub fn update(&mut self) {
-        self.proj_cache = self.build_view_projection_matrix();
+        self.proj_cache = self.build_view_projection_matrix(self.eye());
         self.inv_proj_cache = self.proj_cache.invert().unwrap_or_else(Matrix4::zero);
+        self.rel_proj_cache = self.build_view_projection_matrix(Vec3::ZERO);
     }
 
-    fn build_view_projection_matrix(&self) -> Matrix4 {
-        let eye = self.eye();
+    fn build_view_projection_matrix(&self, eye: Vec3) -> Matrix4 {
         let view = look_to_rh(eye, -self.dir(), self.up);
         let proj = PerspectiveFovReversedZ::new(
             self.fovy / 180.0 * std::f32::consts::PI,
@@ -138,6 +165,13 @@ impl Camera {
             dir.x = 0.01;
             dir.y = 0.01;
         }
+        // grazing light stretches the shadows across the whole cascade, they are cast from a bit
+        // higher. the sky still uses the real sun
+        if dir.z < MIN_SHADOW_SUN_Z {
+            let horizontal =
+                dir.xy().normalize() * (1.0 - MIN_SHADOW_SUN_Z * MIN_SHADOW_SUN_Z).sqrt();
+            dir = horizontal.z(MIN_SHADOW_SUN_Z);
+        }
         let center_cam = self.pos;
 
         let mut cascades = Vec::with_capacity(4);
@@ -176,27 +210,23 @@ impl Camera {
 
             let mut near: f32 = f32::INFINITY;
             let mut far: f32 = f32::NEG_INFINITY;
-            let mut left: f32 = f32::INFINITY;
-            let mut right: f32 = f32::NEG_INFINITY;
-            let mut bottom: f32 = f32::INFINITY;
-            let mut top: f32 = f32::NEG_INFINITY;
+            // fitted to the sphere around the cascade and not to its box, so the size of the
+            // texels does not change when the sun moves and the snapping keeps the edges still
+            let mut radius: f32 = 0.0;
 
             for &p in points {
+                radius = radius.max(p.distance(center));
                 let p = vec3(p.x, p.y, p.z);
                 let p = light_view * p.w(1.0);
                 near = near.min(p.z);
                 far = far.max(p.z);
-                left = left.min(p.x);
-                right = right.max(p.x);
-                bottom = bottom.min(p.y);
-                top = top.max(p.y);
             }
 
             let proj: Matrix4 = Ortho {
-                left,
-                right,
-                bottom,
-                top,
+                left: -radius,
+                right: radius,
+                bottom: -radius,
+                top: radius,
                 near: near + near.signum() * 300.0,
                 far,
             }
@@ -211,6 +241,9 @@ impl Camera {
     }
 }
 
+/// Lowest sine of the elevation the shadows are cast from
+const MIN_SHADOW_SUN_Z: f32 = 0.08;
+
 pub fn texelsnap(resolution: f32, projview: Matrix4) -> Matrix4 {
     let proj_base = projview * Vec4::from([0.0, 0.0, 0.0, 1.0]);
 
@@ -366,3 +399,89 @@ impl PerspectiveFovReversedZ {
         Matrix4::from([c0, c1, c2, c3])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Camera;
+    use crate::{vec3, InfiniteFrustrum, Vec2, Vec3};
+
+    /// Screen positions of a static object while the camera slowly moves, `origin` is added
+    /// to every position of the scene
+    fn frames(origin: Vec3) -> Vec<Vec2> {
+        let obj = vec3(1.0, 2.0, 0.5);
+        let mut cam = Camera::new(Vec3::ZERO, 1920.0, 1080.0);
+        cam.dist = 5.0;
+
+        (0..50)
+            .map(|i| {
+                // larger than the gaps between f32 so far from the origin
+                cam.pos = origin + vec3(0.02 * i as f32, 0.0, 0.0);
+                cam.update();
+                cam.project(origin + obj).0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn far_from_origin_does_not_jitter() {
+        let origin = vec3(150_000.0, 120_000.0, 0.0);
+        let far = frames(origin);
+
+        // the same scene near the origin, where the camera steps are the ones taken far away
+        let obj = vec3(1.0, 2.0, 0.5);
+        let mut cam = Camera::new(Vec3::ZERO, 1920.0, 1080.0);
+        cam.dist = 5.0;
+        for (i, &p) in far.iter().enumerate() {
+            let step = (origin + vec3(0.02 * i as f32, 0.0, 0.0)) - origin;
+            cam.pos = step;
+            cam.update();
+            let reference = cam.project(obj).0;
+            assert!(
+                p.distance(reference) < 0.05,
+                "frame {}: {:?} instead of {:?}",
+                i,
+                p,
+                reference
+            );
+        }
+
+        // the object slides one way on the screen as the camera goes the other, never back
+        let dir = (far[far.len() - 1] - far[0]).normalize();
+        for w in far.windows(2) {
+            assert!((w[1] - w[0]).dot(dir) >= -0.01, "{:?} jittered back", w);
+        }
+    }
+
+    #[test]
+    fn shadow_texels_keep_their_size_as_the_sun_moves() {
+        let mut cam = Camera::new(Vec3::ZERO, 1920.0, 1080.0);
+        cam.dist = 300.0;
+        cam.update();
+
+        // the scale of the light space x axis, what the texel snapping relies on
+        let texel_scales = |sun: Vec3| -> Vec<f32> {
+            cam.build_sun_shadowmap_matrix(sun.normalize(), 2048.0, &InfiniteFrustrum::EMPTY)
+                .iter()
+                .map(|m| vec3(m.x.x, m.y.x, m.z.x).mag())
+                .collect()
+        };
+
+        let reference = texel_scales(vec3(0.3, 0.4, 0.8));
+        for sun in [
+            vec3(0.5, -0.2, 0.6),
+            vec3(1.0, 0.1, 0.05),
+            vec3(-0.7, 0.7, 0.01),
+            vec3(0.0, 1.0, -0.2),
+        ] {
+            for (a, b) in texel_scales(sun).iter().zip(&reference) {
+                assert!(
+                    (a - b).abs() < b * 1e-3,
+                    "{} instead of {} for {:?}",
+                    a,
+                    b,
+                    sun
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{vec3, Vec3};
+use std::f32::consts::TAU;
+
+/// Tilt of the rotation axis of the earth, in radians
+const OBLIQUITY: f32 = 23.44 * TAU / 360.0;
+pub const DAYS_PER_YEAR: f32 = 365.0;
+
+/// Where the sun is in the sky, angles in radians.
+/// The azimuth goes clockwise from the north (+y) through the east (+x).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SunPosition {
+    pub azimuth: f32,
+    pub elevation: f32,
+}
+
+impl SunPosition {
+    /// Position of the sun at `latitude` in degrees (negative in the south hemisphere),
+    /// `day_of_year` days after the 1st of january and at `hour` on the clock, between 0 and 24
+    pub fn compute(latitude: f32, day_of_year: f32, hour: f32) -> Self {
+        let lat = latitude.to_radians();
+        let decl = declination(day_of_year);
+        let solar_hour = hour + equation_of_time(day_of_year) / 60.0;
+        let hour_angle = (solar_hour - 12.0) * TAU / 24.0;
+
+        let east = -decl.cos() * hour_angle.sin();
+        let north = lat.cos() * decl.sin() - lat.sin() * decl.cos() * hour_angle.cos();
+        let up = lat.sin() * decl.sin() + lat.cos() * decl.cos() * hour_angle.cos();
+
+        Self {
+            azimuth: east.atan2(north).rem_euclid(TAU),
+            elevation: up.clamp(-1.0, 1.0).asin(),
+        }
+    }
+
+    pub fn from_direction(dir: Vec3) -> Self {
+        Self {
+            azimuth: dir.x.atan2(dir.y).rem_euclid(TAU),
+            elevation: dir.z.clamp(-1.0, 1.0).asin(),
+        }
+    }
+
+    /// Normalized direction towards the sun
+    pub fn direction(&self) -> Vec3 {
+        let (sin_el, cos_el) = self.elevation.sin_cos();
+        vec3(
+            self.azimuth.sin() * cos_el,
+            self.azimuth.cos() * cos_el,
+            sin_el,
+        )
+    }
+}
+
+/// Angle between the sun and the plane of the equator, positive in the northern summer
+pub fn declination(day_of_year: f32) -> f32 {
+    -OBLIQUITY * (TAU * (day_of_year + 10.0) / DAYS_PER_YEAR).cos()
+}
+
+/// Minutes the sundial is ahead of the clock, the orbit being elliptic and the axis tilted.
+/// It is what turns the noon positions over the year into a figure eight.
+pub fn equation_of_time(day_of_year: f32) -> f32 {
+    let b = TAU * (day_of_year - 81.0) / 364.0;
+    9.87 * (2.0 * b).sin() - 7.53 * b.cos() - 1.5 * b.sin()
+}
+
+/// Hours between sunrise and sunset, 0 during the polar night and 24 under the midnight sun
+pub fn day_length(latitude: f32, day_of_year: f32) -> f32 {
+    let cos_sunset = -latitude.to_radians().tan() * declination(day_of_year).tan();
+    if cos_sunset <= -1.0 {
+        return 24.0;
+    }
+    if cos_sunset >= 1.0 {
+        return 0.0;
+    }
+    24.0 * cos_sunset.acos() / (TAU / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{day_length, SunPosition};
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    const MARCH_EQUINOX: f32 = 79.0;
+    const JUNE_SOLSTICE: f32 = 171.0;
+    const DECEMBER_SOLSTICE: f32 = 354.0;
+
+    #[test]
+    fn days_get_longer_away_from_the_equator_in_summer() {
+        for day in [MARCH_EQUINOX, JUNE_SOLSTICE, DECEMBER_SOLSTICE] {
+            assert!((day_length(0.0, day) - 12.0).abs() < 0.1);
+        }
+
+        assert!(day_length(45.0, JUNE_SOLSTICE) > 15.0);
+        assert!(day_length(45.0, DECEMBER_SOLSTICE) < 9.0);
+        assert!((day_length(45.0, MARCH_EQUINOX) - 12.0).abs() < 0.2);
+
+        assert!(day_length(62.0, JUNE_SOLSTICE) > 18.0);
+        assert!(day_length(62.0, DECEMBER_SOLSTICE) < 6.0);
+        assert_eq!(day_length(80.0, JUNE_SOLSTICE), 24.0);
+        assert_eq!(day_length(80.0, DECEMBER_SOLSTICE), 0.0);
+    }
+
+    #[test]
+    fn the_sun_rises_in_the_east_and_culminates_in_the_south() {
+        let morning = SunPosition::compute(45.0, MARCH_EQUINOX, 6.5);
+        assert!((morning.azimuth - FRAC_PI_2).abs() < 0.2);
+        assert!(morning.elevation.abs() < 0.15);
+
+        let noon = SunPosition::compute(45.0, JUNE_SOLSTICE, 12.0);
+        assert!((noon.azimuth - PI).abs() < 0.1);
+        assert!((noon.elevation.to_degrees() - (90.0 - 45.0 + 23.44)).abs() < 0.5);
+
+        let dir = noon.direction();
+        assert!((dir.mag() - 1.0).abs() < 1e-5);
+        let back = SunPosition::from_direction(dir);
+        assert!((back.azimuth - noon.azimuth).abs() < 1e-4);
+        assert!((back.elevation - noon.elevation).abs() < 1e-4);
+    }
+
+    #[test]
+    fn noon_positions_draw_an_analemma() {
+        let noons: Vec<SunPosition> = (0..365)
+            .map(|day| SunPosition::compute(45.0, day as f32, 12.0))
+            .collect();
+
+        let (min_el, max_el) = noons.iter().fold((f32::MAX, f32::MIN), |(lo, hi), p| {
+            (lo.min(p.elevation), hi.max(p.elevation))
+        });
+        assert!((min_el.to_degrees() - (45.0 - 23.44)).abs() < 0.5);
+        assert!((max_el.to_degrees() - (45.0 + 23.44)).abs() < 0.5);
+
+        // the noon sun is on both sides of the south during the year, a few degrees away
+        assert!(noons.iter().any(|p| p.azimuth < PI - 0.05));
+        assert!(noons.iter().any(|p| p.azimuth > PI + 0.05));
+        assert!(noons.iter().all(|p| (p.azimuth - PI).abs() < 0.2));
+    }
+}
@@ -0,0 +1,42 @@
+use std::cell::RefCell;
+
+use yakui_core::geometry::Rect;
+use yakui_core::widget::{PaintContext, Widget};
+use yakui_core::Response;
+use yakui_widgets::util::widget_children;
+
+thread_local! {
+    /// Areas painted by [capture_pointer] since the last [take_captured_regions]
+    static CAPTURED: RefCell<Vec<Rect>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Marks the area of the children as covered by the interface: the game does not use the mouse
+/// over it, even where no widget handles the events (backgrounds, labels, lists).
+pub fn capture_pointer(children: impl FnOnce()) -> Response<()> {
+    widget_children::<CapturePointerWidget, _>(children, ())
+}
+
+/// Areas covered by the interface when it was last painted, in logical pixels
+pub fn take_captured_regions() -> Vec<Rect> {
+    CAPTURED.with(|c| std::mem::take(&mut *c.borrow_mut()))
+}
+
+#[derive(Debug)]
+pub struct CapturePointerWidget;
+
+impl Widget for CapturePointerWidget {
+    type Props<'a> = ();
+    type Response = ();
+
+    fn new() -> Self {
+        Self
+    }
+
+    fn update(&mut self, _: Self::Props<'_>) -> Self::Response {}
+
+    fn paint(&self, ctx: PaintContext<'_>) {
+        let rect = ctx.layout.get(ctx.dom.current()).unwrap().rect;
+        CAPTURED.with(|c| c.borrow_mut().push(rect));
+        self.default_paint(ctx);
+    }
+}
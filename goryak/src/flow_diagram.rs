@@ -0,0 +1,226 @@
+use std::cell::RefCell;
+
+use yakui_core::event::{EventInterest, EventResponse, WidgetEvent};
+use yakui_core::geometry::{Color, Constraints, Rect, Vec2};
+use yakui_core::paint::{PaintMesh, PaintRect, Vertex};
+use yakui_core::widget::{EventContext, LayoutContext, PaintContext, Widget};
+use yakui_core::Response;
+use yakui_widgets::util::widget;
+
+/// Width of the node bars on both sides
+const NODE_WIDTH: f32 = 12.0;
+/// Vertical space between two nodes of the same side
+const NODE_GAP: f32 = 6.0;
+/// Number of quads a band is made of
+const BAND_SEGMENTS: usize = 24;
+
+/// A band of the diagram, from a node on the left to a node on the right
+#[derive(Debug, Copy, Clone)]
+pub struct FlowLink {
+    pub from: usize,
+    pub to: usize,
+    pub value: f32,
+}
+
+/**
+Sankey-style diagram: the sources stacked on the left, the sinks on the right and bands between
+them, as wide as the quantity they carry.
+The nodes are as tall as the sum of their bands, they are drawn in the given order.
+
+Responds with [FlowDiagramResponse].
+ */
+#[derive(Debug, Clone)]
+pub struct FlowDiagram {
+    pub size: Vec2,
+    /// Color of each node on the left, the bands take the color of their source
+    pub left: Vec<Color>,
+    /// Color of each node on the right
+    pub right: Vec<Color>,
+    pub links: Vec<FlowLink>,
+}
+
+impl FlowDiagram {
+    pub fn show(self) -> Response<FlowDiagramResponse> {
+        widget::<FlowDiagramWidget>(self)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct FlowDiagramResponse {
+    /// Index of the link under the mouse
+    pub hovered: Option<usize>,
+}
+
+/// Where a band starts and ends, the top and bottom of each end
+#[derive(Debug, Copy, Clone)]
+struct BandShape {
+    x0: f32,
+    x1: f32,
+    left: (f32, f32),
+    right: (f32, f32),
+}
+
+impl BandShape {
+    /// Top and bottom of the band at x
+    fn span_at(&self, x: f32) -> (f32, f32) {
+        let t = ((x - self.x0) / (self.x1 - self.x0)).clamp(0.0, 1.0);
+        let s = t * t * (3.0 - 2.0 * t);
+        (
+            self.left.0 + (self.right.0 - self.left.0) * s,
+            self.left.1 + (self.right.1 - self.left.1) * s,
+        )
+    }
+
+    fn contains(&self, p: Vec2) -> bool {
+        if p.x < self.x0 || p.x > self.x1 {
+            return false;
+        }
+        let (top, bottom) = self.span_at(p.x);
+        p.y >= top && p.y <= bottom
+    }
+}
+
+#[derive(Debug)]
+pub struct FlowDiagramWidget {
+    props: FlowDiagram,
+    mouse: Option<Vec2>,
+    /// Shapes of the bands as last painted, for the hover test
+    shapes: RefCell<Vec<BandShape>>,
+    resp: FlowDiagramResponse,
+}
+
+impl FlowDiagramWidget {
+    /// Rectangles of the nodes and shapes of the bands inside the rect
+    fn geometry(&self, rect: Rect) -> (Vec<Rect>, Vec<Rect>, Vec<BandShape>) {
+        let p = &self.props;
+        let mut left_totals = vec![0.0; p.left.len()];
+        let mut right_totals = vec![0.0; p.right.len()];
+        for l in &p.links {
+            left_totals[l.from] += l.value;
+            right_totals[l.to] += l.value;
+        }
+
+        let total = left_totals.iter().sum::<f32>();
+        let max_nodes = p.left.len().max(p.right.len()).max(1);
+        let free = (rect.size().y - NODE_GAP * (max_nodes - 1) as f32).max(0.0);
+        let scale = if total > 0.0 { free / total } else { 0.0 };
+
+        let stack = |totals: &[f32], x: f32| -> Vec<Rect> {
+            let mut y = rect.pos().y;
+            totals
+                .iter()
+                .map(|v| {
+                    let r = Rect::from_pos_size(Vec2::new(x, y), Vec2::new(NODE_WIDTH, v * scale));
+                    y += v * scale + NODE_GAP;
+                    r
+                })
+                .collect()
+        };
+        let left = stack(&left_totals, rect.pos().x);
+        let right = stack(&right_totals, rect.pos().x + rect.size().x - NODE_WIDTH);
+
+        let mut left_used = vec![0.0; left.len()];
+        let mut right_used = vec![0.0; right.len()];
+        let shapes = p
+            .links
+            .iter()
+            .map(|l| {
+                let h = l.value * scale;
+                let y0 = left[l.from].pos().y + left_used[l.from];
+                let y1 = right[l.to].pos().y + right_used[l.to];
+                left_used[l.from] += h;
+                right_used[l.to] += h;
+                BandShape {
+                    x0: rect.pos().x + NODE_WIDTH,
+                    x1: rect.pos().x + rect.size().x - NODE_WIDTH,
+                    left: (y0, y0 + h),
+                    right: (y1, y1 + h),
+                }
+            })
+            .collect();
+
+        (left, right, shapes)
+    }
+}
+
+impl Widget for FlowDiagramWidget {
+    type Props<'a> = FlowDiagram;
+    type Response = FlowDiagramResponse;
+
+    fn new() -> Self {
+        Self {
+            props: FlowDiagram {
+                size: Vec2::ZERO,
+                left: vec![],
+                right: vec![],
+                links: vec![],
+            },
+            mouse: None,
+            shapes: RefCell::new(vec![]),
+            resp: FlowDiagramResponse::default(),
+        }
+    }
+
+    fn update(&mut self, props: Self::Props<'_>) -> Self::Response {
+        self.props = props;
+        self.resp
+    }
+
+    fn layout(&self, _: LayoutContext<'_>, constraints: Constraints) -> Vec2 {
+        constraints.constrain(self.props.size)
+    }
+
+    fn paint(&self, mut ctx: PaintContext<'_>) {
+        let rect = ctx.layout.get(ctx.dom.current()).unwrap().rect;
+        let (left, right, shapes) = self.geometry(rect);
+
+        for (i, (l, shape)) in self.props.links.iter().zip(&shapes).enumerate() {
+            let alpha = if self.resp.hovered == Some(i) {
+                0.8
+            } else {
+                0.45
+            };
+            let color = self.props.left[l.from].with_alpha(alpha).to_linear();
+
+            let vertices = (0..=BAND_SEGMENTS).flat_map(|j| {
+                let x = shape.x0 + (shape.x1 - shape.x0) * j as f32 / BAND_SEGMENTS as f32;
+                let (top, bottom) = shape.span_at(x);
+                [
+                    Vertex::new([x, top], [0.0, 0.0], color),
+                    Vertex::new([x, bottom], [0.0, 0.0], color),
+                ]
+            });
+            let indices = (0..BAND_SEGMENTS as u16).flat_map(|j| {
+                let k = j * 2;
+                [k, k + 1, k + 2, k + 1, k + 3, k + 2]
+            });
+            ctx.paint.add_mesh(PaintMesh::new(vertices, indices));
+        }
+
+        for (rects, colors) in [(&left, &self.props.left), (&right, &self.props.right)] {
+            for (r, color) in rects.iter().zip(colors) {
+                let mut node = PaintRect::new(*r);
+                node.color = *color;
+                node.add(ctx.paint);
+            }
+        }
+
+        *self.shapes.borrow_mut() = shapes;
+    }
+
+    fn event_interest(&self) -> EventInterest {
+        EventInterest::MOUSE_INSIDE | EventInterest::MOUSE_MOVE
+    }
+
+    fn event(&mut self, _: EventContext<'_>, event: &WidgetEvent) -> EventResponse {
+        match *event {
+            WidgetEvent::MouseMoved(pos) => self.mouse = pos,
+            WidgetEvent::MouseLeave => self.mouse = None,
+            _ => return EventResponse::Bubble,
+        }
+        self.resp.hovered = self
+            .mouse
+            .and_then(|p| self.shapes.get_mut().iter().position(|s| s.contains(p)));
+        EventResponse::Bubble
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::RwLock;
+use yakui_core::geometry::{Constraints, Rect, Vec2};
+use yakui_core::paint::PaintRect;
+use yakui_core::widget::{LayoutContext, PaintContext, Widget};
+use yakui_core::Response;
+use yakui_widgets::util::widget_children;
+
+use crate::primary;
+
+/// Name of the widget to point at, used by tutorials
+static HIGHLIGHTED: RwLock<Option<String>> = RwLock::new(None);
+
+const HIGHLIGHT_THICKNESS: f32 = 3.0;
+
+pub fn set_highlighted(name: Option<String>) {
+    *HIGHLIGHTED.write().unwrap() = name;
+}
+
+pub fn is_highlighted(name: &str) -> bool {
+    HIGHLIGHTED.read().unwrap().as_deref() == Some(name)
+}
+
+/// Names the children so they can be outlined with [set_highlighted]
+pub fn highlight_target(name: &str, children: impl FnOnce()) -> Response<()> {
+    widget_children::<HighlightWidget, _>(children, is_highlighted(name))
+}
+
+#[derive(Debug)]
+pub struct HighlightWidget {
+    highlighted: bool,
+}
+
+impl Widget for HighlightWidget {
+    type Props<'a> = bool;
+    type Response = ();
+
+    fn new() -> Self {
+        Self { highlighted: false }
+    }
+
+    fn update(&mut self, highlighted: Self::Props<'_>) -> Self::Response {
+        self.highlighted = highlighted;
+    }
+
+    fn layout(&self, mut ctx: LayoutContext<'_>, input: Constraints) -> Vec2 {
+        let node = ctx.dom.get_current();
+        let mut size = Vec2::ZERO;
+
+        for &child in &node.children {
+            let child_size = ctx.calculate_layout(child, input);
+            size = size.max(child_size);
+        }
+
+        input.constrain_min(size)
+    }
+
+    fn paint(&self, mut ctx: PaintContext<'_>) {
+        let node = ctx.dom.get_current();
+        for &child in &node.children {
+            ctx.paint(child);
+        }
+
+        if !self.highlighted {
+            return;
+        }
+
+        // outline drawn around the children so it doesn't hide them
+        let rect = ctx.layout.get(ctx.dom.current()).unwrap().rect;
+        let t = HIGHLIGHT_THICKNESS;
+        let pos = rect.pos() - Vec2::splat(t);
+        let size = rect.size() + Vec2::splat(t * 2.0);
+
+        let strips = [
+            Rect::from_pos_size(pos, Vec2::new(size.x, t)),
+            Rect::from_pos_size(pos + Vec2::new(0.0, size.y - t), Vec2::new(size.x, t)),
+            Rect::from_pos_size(pos, Vec2::new(t, size.y)),
+            Rect::from_pos_size(pos + Vec2::new(size.x - t, 0.0), Vec2::new(t, size.y)),
+        ];
+        for strip in strips {
+            let mut r = PaintRect::new(strip);
+            r.color = primary();
+            r.add(ctx.paint);
+        }
+    }
+}
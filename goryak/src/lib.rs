@@ -1,7 +1,10 @@
 mod blur_bg;
+mod capture_pointer;
 mod combo_box;
 mod constrained_viewport;
 mod dragvalue;
+mod flow_diagram;
+mod highlight;
 mod hovered;
 mod icon;
 mod imagebutton;
@@ -20,9 +23,12 @@ mod util;
 mod window;
 
 pub use blur_bg::*;
+pub use capture_pointer::*;
 pub use combo_box::*;
 pub use constrained_viewport::*;
 pub use dragvalue::*;
+pub use flow_diagram::*;
+pub use highlight::*;
 pub use hovered::*;
 pub use icon::*;
 pub use imagebutton::*;
@@ -36,6 +42,7 @@ pub use selectable_label::*;
 pub use sized_canvas::*;
 pub use text::*;
 pub use theme::*;
+pub use tooltip::*;
 pub use util::*;
 pub use window::*;
 
@@ -1,49 +1,34 @@
-/*
-use lazy_static::lazy_static;
-use std::sync::Mutex;
-use yakui_core::geometry::{Constraints, Vec2};
+use yakui_core::geometry::{Constraints, Dim2, Vec2};
 use yakui_core::widget::{LayoutContext, Widget};
-use yakui_core::Response;
-use yakui_widgets::util::widget_children;
+use yakui_core::{context, Alignment, Flow};
 
-struct TooltipState(Option<TooltipStateInner>);
+use crate::{blur_bg, padxy, primary_container};
 
-struct TooltipStateInner {
-    tooltip: &'static str,
-    position: Vec2,
-}
+/// Distance between the cursor and the tooltip
+const CURSOR_OFFSET: Vec2 = Vec2::new(16.0, 16.0);
 
-lazy_static! {
-    static ref TOOLTIP_STATE: Mutex<TooltipState> = Mutex::new(TooltipState(None));
-}
+/// Shows the children in a small box next to the given screen position, on top of everything.
+/// The box is moved to the other side of the position when it would go out of the window.
+pub fn tooltip(position: Vec2, children: impl FnOnce()) {
+    let dom = context::dom();
+    let response = dom.begin_widget::<PositionTooltipWidget>(PositionTooltip { position });
 
-/// Call this at the end of the gui where the "reflow" refers to the root widget
-/// so that we can put the tooltip at the right place but the tooltip is necessarily
-/// above everything
-pub fn render_tooltip() {
-    let mut tooltip_state = TOOLTIP_STATE.lock().unwrap();
-    if let Some(tooltip_state_inner) = tooltip_state.0 {}
-}
+    blur_bg(primary_container().with_alpha(0.7), 5.0, || {
+        padxy(8.0, 5.0, children);
+    });
 
-/// Positions the children of this widget as close to the mouse as possible while staying
-/// within the bounds of the window.
-#[derive(Debug)]
-struct PositionTooltip {
-    position: Vec2,
+    dom.end_widget::<PositionTooltipWidget>(response.id);
 }
 
-impl PositionTooltip {
-    pub fn new(position: Vec2) -> Self {
-        Self { position }
-    }
-
-    pub fn show<F: FnOnce()>(self, children: F) -> Response<()> {
-        widget_children::<PositionTooltipWidget, F>(children, self)
-    }
+#[derive(Debug)]
+pub struct PositionTooltip {
+    pub position: Vec2,
 }
 
+/// Positions its child as close to the mouse as possible while staying
+/// within the bounds of the window.
 #[derive(Debug)]
-struct PositionTooltipWidget {
+pub struct PositionTooltipWidget {
     props: PositionTooltip,
 }
 
@@ -53,7 +38,9 @@ impl Widget for PositionTooltipWidget {
 
     fn new() -> Self {
         Self {
-            props: PositionTooltip::new(Vec2::ZERO),
+            props: PositionTooltip {
+                position: Vec2::ZERO,
+            },
         }
     }
 
@@ -61,16 +48,33 @@ impl Widget for PositionTooltipWidget {
         self.props = props;
     }
 
-    fn layout(&self, mut ctx: LayoutContext<'_>, constraints: Constraints) -> Vec2 {
+    fn flow(&self) -> Flow {
+        Flow::Relative {
+            anchor: Alignment::TOP_LEFT,
+            offset: Dim2::ZERO,
+        }
+    }
+
+    fn layout(&self, mut ctx: LayoutContext<'_>, _: Constraints) -> Vec2 {
+        ctx.layout.new_layer(ctx.dom);
         let node = ctx.dom.get_current();
-        let mut size = Vec2::ZERO;
+        let vp = ctx.layout.viewport().size();
+
         for &child in &node.children {
-            let child_size = ctx.calculate_layout(child, constraints);
-            size = size.max(child_size);
+            let size = ctx.calculate_layout(child, Constraints::loose(vp));
+
+            let mut pos = self.props.position + CURSOR_OFFSET;
+            if pos.x + size.x > vp.x {
+                pos.x = self.props.position.x - CURSOR_OFFSET.x - size.x;
+            }
+            if pos.y + size.y > vp.y {
+                pos.y = self.props.position.y - CURSOR_OFFSET.y - size.y;
+            }
+            pos = pos.max(Vec2::ZERO);
+
+            ctx.layout.set_pos(child, pos);
         }
-        let v = ctx.layout.viewport();
 
         Vec2::ZERO
     }
 }
- */
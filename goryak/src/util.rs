@@ -62,9 +62,14 @@ pub fn titlec(c: Color, text: impl Into<Cow<'static, str>>) {
 }
 
 pub fn textc(c: Color, text: impl Into<Cow<'static, str>>) {
+    textc_scaled(c, 1.0, text);
+}
+
+/// Like [`textc`], with the font size multiplied by scale
+pub fn textc_scaled(c: Color, scale: f32, text: impl Into<Cow<'static, str>>) {
     let mut t = Text::label(text.into());
     t.style.color = c;
-    t.style.font_size = DEFAULT_FONT_SIZE;
+    t.style.font_size = DEFAULT_FONT_SIZE * scale;
     t.padding = Pad::all(0.0);
     t.show();
 }
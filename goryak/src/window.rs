@@ -9,7 +9,10 @@ use yakui_core::{context, Alignment, Flow, Pivot};
 use yakui_widgets::widgets::{Button, Pad, Text};
 use yakui_widgets::{center, constrained, divider, draggable, offset, reflow};
 
-use crate::{blur_bg, icon_button, mincolumn, on_primary_container, outline, primary_container};
+use crate::{
+    blur_bg, capture_pointer, icon_button, mincolumn, on_primary_container, outline,
+    primary_container,
+};
 
 pub struct Window<'a> {
     pub title: Cow<'static, str>,
@@ -26,58 +29,61 @@ impl<'a> Window<'a> {
 
         let off = draggable(|| {
             if *self.opened {
-                blur_bg(primary_container().with_alpha(0.5), self.radius, || {
-                    self.pad.show(|| {
-                        if self.title.is_empty() {
-                            if self.child_spacing != 0.0 {
-                                mincolumn(self.child_spacing, children);
-                            } else {
-                                children();
+                capture_pointer(|| {
+                    blur_bg(primary_container().with_alpha(0.5), self.radius, || {
+                        self.pad.show(|| {
+                            if self.title.is_empty() {
+                                if self.child_spacing != 0.0 {
+                                    mincolumn(self.child_spacing, children);
+                                } else {
+                                    children();
+                                }
+                                return;
                             }
-                            return;
-                        }
-                        mincolumn(0.0, || {
-                            reflow(Alignment::TOP_RIGHT, Pivot::TOP_LEFT, Dim2::ZERO, || {
-                                offset(Vec2::new(-25.0, -15.0), || {
-                                    constrained(Constraints::tight(Vec2::splat(40.0)), || {
-                                        center(|| {
-                                            let mut b = Button::unstyled("close");
-                                            b.padding = Pad::balanced(4.0, 2.0);
-                                            b.border_radius = 10.0;
-                                            b.style.fill = Color::CLEAR;
-                                            b.style.text.font_size = 20.0;
-                                            b.style.text.color = on_primary_container().adjust(0.5);
-                                            b.down_style.fill = Color::CLEAR;
-                                            b.down_style.text = b.style.text.clone();
-                                            b.hover_style.fill = Color::CLEAR;
-                                            b.hover_style.text = b.style.text.clone();
-                                            b.hover_style.text.font_size = 25.0;
-                                            b.hover_style.text.color = on_primary_container();
-
-                                            if icon_button(b).show().clicked {
-                                                *self.opened = false;
-                                            }
+                            mincolumn(0.0, || {
+                                reflow(Alignment::TOP_RIGHT, Pivot::TOP_LEFT, Dim2::ZERO, || {
+                                    offset(Vec2::new(-25.0, -15.0), || {
+                                        constrained(Constraints::tight(Vec2::splat(40.0)), || {
+                                            center(|| {
+                                                let mut b = Button::unstyled("close");
+                                                b.padding = Pad::balanced(4.0, 2.0);
+                                                b.border_radius = 10.0;
+                                                b.style.fill = Color::CLEAR;
+                                                b.style.text.font_size = 20.0;
+                                                b.style.text.color =
+                                                    on_primary_container().adjust(0.5);
+                                                b.down_style.fill = Color::CLEAR;
+                                                b.down_style.text = b.style.text.clone();
+                                                b.hover_style.fill = Color::CLEAR;
+                                                b.hover_style.text = b.style.text.clone();
+                                                b.hover_style.text.font_size = 25.0;
+                                                b.hover_style.text.color = on_primary_container();
+
+                                                if icon_button(b).show().clicked {
+                                                    *self.opened = false;
+                                                }
+                                            });
                                         });
                                     });
                                 });
-                            });
-
-                            {
-                                // title
-                                let mut t = Text::label(self.title);
-                                t.style.color = on_primary_container();
-                                t.style.font_size = crate::DEFAULT_FONT_SIZE;
-                                t.padding = Pad::ZERO;
-                                t.padding.right = 15.0;
-                                t.show();
-                            }
 
-                            divider(outline(), 10.0, 1.0);
-                            if self.child_spacing != 0.0 {
-                                mincolumn(self.child_spacing, children);
-                            } else {
-                                children();
-                            }
+                                {
+                                    // title
+                                    let mut t = Text::label(self.title);
+                                    t.style.color = on_primary_container();
+                                    t.style.font_size = crate::DEFAULT_FONT_SIZE;
+                                    t.padding = Pad::ZERO;
+                                    t.padding.right = 15.0;
+                                    t.show();
+                                }
+
+                                divider(outline(), 10.0, 1.0);
+                                if self.child_spacing != 0.0 {
+                                    mincolumn(self.child_spacing, children);
+                                } else {
+                                    children();
+                                }
+                            });
                         });
                     });
                 });
@@ -1,8 +1,11 @@
 use common::logger::MyLog;
+use common::saveload::{Encoder, JSONPretty};
 use common::unwrap_or;
 use networking::{Frame, Server, ServerConfiguration, ServerPollResult};
+use simulation::economy::{balance_report, diff_reports, BalanceReport};
 use simulation::world_command::WorldCommands;
 use simulation::Simulation;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 
@@ -27,13 +30,28 @@ struct Opt {
     /// i.e. 20ms = 50FPS
     #[structopt(long, default_value = "20")]
     timestep: u64,
+
+    /// Writes the balance figures derived from the prototypes to this file and exits
+    #[structopt(long, parse(from_os_str))]
+    balance_dump: Option<PathBuf>,
+
+    /// Prints the changes between two balance dumps (old then new) and exits
+    #[structopt(long, number_of_values = 2, parse(from_os_str))]
+    balance_diff: Vec<PathBuf>,
 }
 
 fn main() {
     let opt: Opt = Opt::from_args();
     MyLog::init();
+    if let [old, new] = &*opt.balance_diff {
+        std::process::exit(balance_diff(old, new));
+    }
     simulation::init::init();
 
+    if let Some(path) = opt.balance_dump {
+        std::process::exit(balance_dump(&path));
+    }
+
     log::info!("starting server with version: {}", VERSION);
 
     let mut w = unwrap_or!(Simulation::load_from_disk("world"), {
@@ -62,7 +80,7 @@ fn main() {
     let mut last_saved = Instant::now();
 
     loop {
-        if let ServerPollResult::Input(inputs) = server.poll(&w, Frame(w.get_tick()), None) {
+        if let ServerPollResult::Input(inputs) = server.poll(None) {
             for frame in inputs {
                 assert_eq!(frame.frame.0, w.get_tick() + 1);
                 let merged: WorldCommands = frame.inputs.into_iter().map(|x| x.inp).collect();
@@ -70,6 +88,14 @@ fn main() {
             }
         }
 
+        let frame = Frame(w.get_tick());
+        if let Some(snapshot) = server.take_snapshot_request() {
+            snapshot.send(frame, &w);
+        }
+        if server.world_hash_wanted(frame) {
+            server.send_world_hash(frame, w.hash());
+        }
+
         if last_saved.elapsed().as_secs() > opt.autosave {
             w.save_to_disk("world");
             last_saved = Instant::now();
@@ -78,3 +104,39 @@ fn main() {
         std::thread::sleep(Duration::from_millis(1));
     }
 }
+
+fn balance_dump(path: &Path) -> i32 {
+    match JSONPretty::encode(&balance_report()).and_then(|x| std::fs::write(path, x)) {
+        Ok(()) => {
+            log::info!("wrote balance report to {:?}", path);
+            0
+        }
+        Err(e) => {
+            log::error!("could not write balance report: {}", e);
+            2
+        }
+    }
+}
+
+fn balance_diff(old: &Path, new: &Path) -> i32 {
+    let load = |p: &Path| -> Result<BalanceReport, String> {
+        let data = std::fs::read(p).map_err(|e| format!("could not read {:?}: {}", p, e))?;
+        JSONPretty::decode(&data).map_err(|e| format!("could not decode {:?}: {}", p, e))
+    };
+    let (old, new) = match (load(old), load(new)) {
+        (Ok(o), Ok(n)) => (o, n),
+        (Err(e), _) | (_, Err(e)) => {
+            log::error!("{}", e);
+            return 2;
+        }
+    };
+
+    let lines = diff_reports(&old, &new);
+    if lines.is_empty() {
+        println!("no balance change");
+    }
+    for line in lines {
+        println!("{}", line);
+    }
+    0
+}
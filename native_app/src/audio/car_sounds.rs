@@ -1,3 +1,4 @@
+use crate::rendering::SimTimeScale;
 use crate::uiworld::UiWorld;
 use engine::{AudioContext, AudioKind, Gain, GainControl};
 use flat_spatial::grid::GridHandle;
@@ -120,6 +121,14 @@ impl CarSounds {
             }
         }
 
+        // the cars are silent on pause, and fade into the traffic loop when sped up a lot
+        let time_scale = uiworld.read::<SimTimeScale>();
+        let pitch = time_scale.pitch();
+        let own_sound = match time_scale.is_paused() {
+            true => 0.0,
+            false => 1.0 - time_scale.fast_mix(),
+        };
+
         // Update
         for (h, cs) in &mut self.sounds {
             let (pos, obj) = transport_grid.get(h).unwrap(); // Unwrap ok: checked it existed before
@@ -131,13 +140,15 @@ impl CarSounds {
             let boost = 300.0 / (300.0 - speed_to_me);
 
             if let Some((ref mut speed, ref mut gain, _)) = cs.road {
-                gain.set_amplitude_ratio(obj.speed.sqrt() * 3.0 / pos.z0().distance(campos));
-                speed.set_speed(boost)
+                gain.set_amplitude_ratio(
+                    own_sound * obj.speed.sqrt() * 3.0 / pos.z0().distance(campos),
+                );
+                speed.set_speed(boost * pitch)
             }
 
             if let Some((ref mut speed, ref mut gain, _)) = cs.engine {
-                gain.set_amplitude_ratio(obj.speed.sqrt() / pos.z0().distance(campos));
-                speed.set_speed(boost)
+                gain.set_amplitude_ratio(own_sound * obj.speed.sqrt() / pos.z0().distance(campos));
+                speed.set_speed(boost * pitch)
             }
         }
 
@@ -152,10 +163,15 @@ impl CarSounds {
                     )
                 })
                 .count();
+            let traffic = match time_scale.is_paused() {
+                true => 0.0,
+                false => 1.0 + time_scale.fast_mix(),
+            };
             if let Some(ref mut s) = self.generic_car_sound {
                 s.set_amplitude_ratio(
-                    ((cars_on_screen as f32).min(100.0) / 100.0 * (1.0 - campos.z / 1000.0))
-                        .min(0.03),
+                    traffic
+                        * ((cars_on_screen as f32).min(100.0) / 100.0 * (1.0 - campos.z / 1000.0))
+                            .min(0.03),
                 );
             }
         } else if let Some(ref mut s) = self.generic_car_sound {
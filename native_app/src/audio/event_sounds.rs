@@ -0,0 +1,148 @@
+use crate::newgui::windows::settings::Settings;
+use crate::uiworld::UiWorld;
+use engine::{AudioContext, AudioKind, Gain};
+use geom::Camera;
+use oddio::FramesSignal;
+use prototypes::AudioEventPrototype;
+use simulation::event_log::{Event, EventCategory, EventLog, Severity};
+use simulation::Simulation;
+use std::time::{Duration, Instant};
+
+/// Distance from the camera at which the positional event sounds can't be heard anymore
+const HEAR_RADIUS: f32 = 2000.0;
+
+/// An audio-event prototype with its category and severity resolved
+struct Rule {
+    proto: &'static AudioEventPrototype,
+    category: Option<EventCategory>,
+    min_severity: Option<Severity>,
+    /// Lowercase
+    text: Option<String>,
+    last_played: Option<Instant>,
+}
+
+impl Rule {
+    /// None if the prototype names a category or severity that doesn't exist
+    fn new(proto: &'static AudioEventPrototype) -> Option<Self> {
+        let category = match proto.category {
+            Some(ref id) => {
+                let Some(category) = EventCategory::from_id(id) else {
+                    log::warn!("audio event {}: unknown category {}", proto.name, id);
+                    return None;
+                };
+                Some(category)
+            }
+            None => None,
+        };
+        let min_severity = match proto.min_severity {
+            Some(ref id) => {
+                let Some(severity) = Severity::from_id(id) else {
+                    log::warn!("audio event {}: unknown severity {}", proto.name, id);
+                    return None;
+                };
+                Some(severity)
+            }
+            None => None,
+        };
+
+        Some(Self {
+            proto,
+            category,
+            min_severity,
+            text: proto.text.as_ref().map(|t| t.to_lowercase()),
+            last_played: None,
+        })
+    }
+
+    fn matches(&self, e: &Event) -> bool {
+        self.category.map_or(true, |c| c == e.category)
+            && self.min_severity.map_or(true, |s| e.severity >= s)
+            && self
+                .text
+                .as_ref()
+                .map_or(true, |t| e.text.to_lowercase().contains(t))
+    }
+
+    fn cooling_down(&self, now: Instant) -> bool {
+        self.last_played
+            .is_some_and(|t| now.duration_since(t) < Duration::from_secs_f32(self.proto.cooldown))
+    }
+}
+
+/// EventSounds play the sounds of the audio-event rules when events are logged.
+/// A rule plays at most once per cooldown, however many events it matches.
+pub struct EventSounds {
+    rules: Vec<Rule>,
+    /// Id of the newest event already heard
+    last_seen: Option<u64>,
+    /// The events of the loaded save are not played
+    started: bool,
+}
+
+impl EventSounds {
+    pub fn new() -> Self {
+        Self {
+            rules: AudioEventPrototype::iter().filter_map(Rule::new).collect(),
+            last_seen: None,
+            started: false,
+        }
+    }
+
+    pub fn update(&mut self, sim: &Simulation, uiworld: &UiWorld, ctx: &mut AudioContext) {
+        let log = sim.read::<EventLog>();
+        let last_seen = self.last_seen;
+        let new: Vec<&Event> = log
+            .iter()
+            .take_while(|e| last_seen.map_or(true, |id| e.id > id))
+            .collect();
+        if let Some(newest) = new.first() {
+            self.last_seen = Some(newest.id);
+        }
+
+        if !std::mem::replace(&mut self.started, true) {
+            return;
+        }
+        if new.is_empty() || !uiworld.read::<Settings>().event_sounds {
+            return;
+        }
+
+        let eye = uiworld.read::<Camera>().eye();
+        let now = Instant::now();
+
+        for rule in &mut self.rules {
+            if rule.cooling_down(now) {
+                continue;
+            }
+
+            let sound = rule.proto.sound.as_str();
+
+            if !rule.proto.positional {
+                if new.iter().any(|e| rule.matches(e)) {
+                    ctx.play(sound, AudioKind::Ui);
+                    rule.last_played = Some(now);
+                }
+                continue;
+            }
+
+            // the closest of the matching events, the ones without a location are heard anywhere
+            let volume = new
+                .iter()
+                .filter(|e| rule.matches(e))
+                .map(|e| {
+                    e.pos
+                        .map_or(1.0, |pos| 1.0 - pos.distance(eye) / HEAR_RADIUS)
+                })
+                .fold(0.0f32, f32::max);
+            if volume <= 0.0 {
+                continue;
+            }
+
+            ctx.play_with_control(
+                sound,
+                |x| Gain::new(FramesSignal::new(x, 0.0).1, volume),
+                AudioKind::Effect,
+            );
+            rule.last_played = Some(now);
+        }
+    }
+}
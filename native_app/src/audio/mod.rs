@@ -1,5 +1,6 @@
 use crate::audio::ambient::Ambient;
 use crate::audio::car_sounds::CarSounds;
+use crate::audio::event_sounds::EventSounds;
 use crate::audio::music::Music;
 use crate::uiworld::UiWorld;
 use engine::AudioContext;
@@ -7,6 +8,7 @@ use simulation::Simulation;
 
 mod ambient;
 mod car_sounds;
+mod event_sounds;
 mod music;
 
 pub static SOUNDS_LIST: include_dir::Dir = include_dir::include_dir!("assets/sounds");
@@ -15,6 +17,7 @@ pub struct GameAudio {
     music: Music,
     ambiant: Ambient,
     carsounds: CarSounds,
+    eventsounds: EventSounds,
 }
 
 impl GameAudio {
@@ -33,6 +36,7 @@ impl GameAudio {
             music: Music::new(),
             ambiant: Ambient::new(ctx),
             carsounds: CarSounds::new(ctx),
+            eventsounds: EventSounds::new(),
         }
     }
 
@@ -40,5 +44,6 @@ impl GameAudio {
         self.music.update(ctx);
         self.ambiant.update(sim, uiworld);
         self.carsounds.update(sim, uiworld, ctx);
+        self.eventsounds.update(sim, uiworld, ctx);
     }
 }
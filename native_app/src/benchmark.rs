@@ -0,0 +1,573 @@
+//! Benchmark mode.
+//! `--bench <scene>` plays one of the generated scenes for a fixed number of frames, with the
+//! camera on a scripted path and the simulation advancing by a fixed number of ticks per frame,
+//! then writes a JSON report and closes the game.
+//! `--bench-inline-paths` finds the paths inside the ticks instead of on the workers, comparing
+//! its report on the freight scene with a normal run shows the worst tick the workers save.
+//! `--bench-compare <before.json> <after.json>` diffs two reports and exits with an error if
+//! anything regressed beyond its threshold.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use common::saveload::{Encoder, JSONPretty};
+use engine::{Context, PerfCountersStatic};
+use geom::Radians;
+use simulation::bench_scenes::BenchScene;
+use simulation::map_dynamic::set_inline_paths;
+use simulation::utils::scheduler::SeqSchedule;
+use simulation::world_command::WorldCommands;
+use simulation::Simulation;
+
+use crate::newgui::camera_path::{CameraKeyframe, CameraPath};
+use crate::rendering::{CameraPose, OrbitCamera};
+use crate::uiworld::UiWorld;
+
+const DEFAULT_FRAMES: u32 = 1800;
+/// Simulation ticks per frame, so that every run simulates the same thing whatever the frame rate
+const TICKS_PER_FRAME: u32 = 1;
+/// Seconds of camera path per frame, the path does not depend on the frame rate either
+const PATH_STEP: f32 = 1.0 / 60.0;
+/// Frames skipped once the meshes are built, before the measures start
+const WARMUP_FRAMES: u32 = 30;
+
+/// Relative increase of a measure over which it counts as a regression
+const TIME_THRESHOLD: f32 = 0.10;
+const COUNT_THRESHOLD: f32 = 0.02;
+const MEMORY_THRESHOLD: f32 = 0.10;
+/// Differences of times under this many milliseconds are noise
+const TIME_NOISE_MS: f32 = 0.1;
+
+static OPTIONS: OnceLock<BenchOptions> = OnceLock::new();
+
+#[derive(Clone, Debug)]
+pub struct BenchOptions {
+    pub scene: BenchScene,
+    pub frames: u32,
+    /// Where the report is written
+    pub out: PathBuf,
+    /// The paths are found inside the ticks instead of on the workers
+    pub inline_paths: bool,
+}
+
+/// What the command line asks for
+#[derive(Debug)]
+pub enum BenchArgs {
+    None,
+    Run(BenchOptions),
+    Compare { before: PathBuf, after: PathBuf },
+}
+
+/// Parses the benchmark arguments, the others are ignored
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<BenchArgs, String> {
+    let mut args = args.into_iter();
+    let mut scene = None;
+    let mut frames = DEFAULT_FRAMES;
+    let mut out = None;
+    let mut inline_paths = false;
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--bench" => {
+                let name = args.next().ok_or("--bench needs a scene")?;
+                scene = Some(BenchScene::from_name(&name).ok_or_else(|| {
+                    let names: Vec<_> = BenchScene::ALL.iter().map(|s| s.name()).collect();
+                    format!(
+                        "unknown scene {}, expected one of {}",
+                        name,
+                        names.join(", ")
+                    )
+                })?);
+            }
+            "--frames" => {
+                frames = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|&x| x > 0)
+                    .ok_or("--frames needs a positive number")?;
+            }
+            "--bench-out" => {
+                out = Some(PathBuf::from(
+                    args.next().ok_or("--bench-out needs a file")?,
+                ))
+            }
+            "--bench-inline-paths" => inline_paths = true,
+            "--bench-compare" => {
+                let (Some(before), Some(after)) = (args.next(), args.next()) else {
+                    return Err("--bench-compare needs two reports".to_string());
+                };
+                return Ok(BenchArgs::Compare {
+                    before: before.into(),
+                    after: after.into(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let Some(scene) = scene else {
+        return Ok(BenchArgs::None);
+    };
+    Ok(BenchArgs::Run(BenchOptions {
+        scene,
+        frames,
+        out: out.unwrap_or_else(|| PathBuf::from(format!("bench_{}.json", scene.name()))),
+        inline_paths,
+    }))
+}
+
+pub fn set_options(opts: BenchOptions) {
+    let _ = OPTIONS.set(opts);
+}
+
+/// The benchmark to run, if the game was started with `--bench`
+pub fn options() -> Option<&'static BenchOptions> {
+    OPTIONS.get()
+}
+
+/// Loads the scene from its cache, generating it on the first run
+pub fn load_scene(scene: BenchScene) -> Simulation {
+    let name = scene.save_name();
+    if let Some(sim) = Simulation::load_from_disk(&name) {
+        return sim;
+    }
+    log::info!("generating benchmark scene {}", scene.name());
+    let sim = scene.generate();
+    sim.save_to_disk(&name);
+    sim
+}
+
+/// A benchmark being run
+pub struct Benchmark {
+    opts: BenchOptions,
+    path: CameraPath,
+    /// Frames left before measuring, once the meshes are built
+    warmup: u32,
+    frame: u32,
+    samples: Samples,
+    pub done: bool,
+}
+
+#[derive(Default)]
+struct Samples {
+    frame_time: Vec<f32>,
+    tick_time: Vec<f32>,
+    cpu_time: Vec<f32>,
+    engine_render_time: Vec<f32>,
+    gui_time: Vec<f32>,
+    draw_calls: Vec<f32>,
+    atlas_saved_draw_calls: Vec<f32>,
+    triangles: Vec<f32>,
+    pass_times: BTreeMap<&'static str, Vec<f32>>,
+    pass_times_gpu: bool,
+}
+
+impl Benchmark {
+    pub fn new(opts: BenchOptions, sim: &Simulation) -> Self {
+        let path = camera_path(sim, opts.frames as f32 * PATH_STEP);
+        set_inline_paths(opts.inline_paths);
+        Self {
+            opts,
+            path,
+            warmup: WARMUP_FRAMES,
+            frame: 0,
+            samples: Samples::default(),
+            done: false,
+        }
+    }
+
+    /// Advances the simulation and the camera by one frame, and measures the previous one.
+    /// `pending_chunks` are the meshes still to build, the measures start once there are none.
+    pub fn update(
+        &mut self,
+        sim: &RwLock<Simulation>,
+        schedule: &mut SeqSchedule,
+        uiw: &UiWorld,
+        ctx: &Context,
+        pending_chunks: usize,
+    ) {
+        if self.done {
+            return;
+        }
+
+        let measuring = pending_chunks == 0 && self.warmup == 0;
+        if pending_chunks == 0 {
+            self.warmup = self.warmup.saturating_sub(1);
+        }
+
+        if let Some(pose) = self.path.sample(self.frame as f32 * PATH_STEP, |_| None) {
+            uiw.write::<OrbitCamera>().set_pose(pose);
+        }
+
+        let mut tick_time = 0.0;
+        {
+            let mut sim = sim.write().unwrap();
+            for _ in 0..TICKS_PER_FRAME {
+                tick_time += sim
+                    .tick(schedule, WorldCommands::default().as_ref())
+                    .as_secs_f32();
+            }
+        }
+
+        if !measuring {
+            return;
+        }
+
+        let s = &mut self.samples;
+        s.frame_time.push(ctx.delta * 1000.0);
+        s.tick_time.push(tick_time * 1000.0);
+        s.cpu_time.push(ctx.times.total_cpu_time * 1000.0);
+        s.engine_render_time.push(ctx.times.render_time * 1000.0);
+        s.gui_time.push(ctx.times.gui_time * 1000.0);
+        // counters of the previous frame, the game loop stores them every frame
+        let perf = uiw.read::<PerfCountersStatic>();
+        s.draw_calls.push(perf.total_drawcalls as f32);
+        s.atlas_saved_draw_calls
+            .push(perf.atlas_merged_drawcalls as f32);
+        s.triangles.push(perf.total_triangles as f32);
+        for &(pass, ms) in &perf.pass_times {
+            s.pass_times.entry(pass).or_default().push(ms);
+        }
+        s.pass_times_gpu = perf.pass_times_gpu;
+        drop(perf);
+
+        self.frame += 1;
+        if self.frame >= self.opts.frames {
+            self.finish();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.done = true;
+        let s = &mut self.samples;
+        let report = BenchReport {
+            scene: self.opts.scene.name().to_string(),
+            version: crate::game_loop::VERSION.trim().to_string(),
+            frames: self.opts.frames,
+            ticks_per_frame: TICKS_PER_FRAME,
+            frame_time: Percentiles::new(&mut s.frame_time),
+            tick_time: Percentiles::new(&mut s.tick_time),
+            cpu_time: Percentiles::new(&mut s.cpu_time),
+            engine_render_time: Percentiles::new(&mut s.engine_render_time),
+            gui_time: Percentiles::new(&mut s.gui_time),
+            draw_calls: Percentiles::new(&mut s.draw_calls),
+            atlas_saved_draw_calls: Percentiles::new(&mut s.atlas_saved_draw_calls),
+            triangles: Percentiles::new(&mut s.triangles),
+            pass_times: s
+                .pass_times
+                .iter_mut()
+                .map(|(pass, times)| (pass.to_string(), Percentiles::new(times)))
+                .collect(),
+            pass_times_gpu: s.pass_times_gpu,
+            inline_paths: self.opts.inline_paths,
+            peak_memory_mb: peak_memory_mb(),
+        };
+
+        match JSONPretty::encode(&report).and_then(|x| std::fs::write(&self.opts.out, x)) {
+            Ok(()) => log::info!("wrote benchmark report to {:?}", self.opts.out),
+            Err(e) => log::error!("could not write benchmark report: {}", e),
+        }
+    }
+}
+
+/// Flies over the middle of the scene: from far away down to the streets and back up
+fn camera_path(sim: &Simulation, duration: f32) -> CameraPath {
+    let map = sim.map();
+    let center = map.environment.bounds().center();
+    let pos = center.z(map.environment.height(center).unwrap_or(0.0));
+
+    let mut path = CameraPath::default();
+    let keys = [
+        (0.0, 0.0, 0.6, 2500.0),
+        (0.25, 1.5, 0.4, 1200.0),
+        (0.5, 3.0, 0.25, 400.0),
+        (0.75, 4.5, 0.5, 1500.0),
+        (1.0, 6.0, 0.9, 3000.0),
+    ];
+    for (t, yaw, pitch, dist) in keys {
+        let pose = CameraPose {
+            pos,
+            yaw: Radians(yaw),
+            pitch: Radians(pitch),
+            dist,
+        };
+        path.insert(CameraKeyframe::new(t * duration, pose, None));
+    }
+    path
+}
+
+/// Peak resident memory of the process, only known on linux
+fn peak_memory_mb() -> Option<f32> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    let kb: f32 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb / 1024.0)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub scene: String,
+    pub version: String,
+    pub frames: u32,
+    pub ticks_per_frame: u32,
+    /// Milliseconds between two frames
+    pub frame_time: Percentiles,
+    /// Milliseconds spent ticking the simulation in a frame
+    pub tick_time: Percentiles,
+    /// Milliseconds of CPU work in a frame
+    pub cpu_time: Percentiles,
+    /// Milliseconds the engine took to process the render commands
+    pub engine_render_time: Percentiles,
+    pub gui_time: Percentiles,
+    pub draw_calls: Percentiles,
+    /// Draw calls the texture atlas saved, the frame would take this many more without it
+    #[serde(default)]
+    pub atlas_saved_draw_calls: Percentiles,
+    pub triangles: Percentiles,
+    /// Milliseconds taken by each render pass, on the GPU when `pass_times_gpu`
+    #[serde(default)]
+    pub pass_times: BTreeMap<String, Percentiles>,
+    /// Whether the pass times are GPU times, else they are the CPU time spent encoding the passes
+    #[serde(default)]
+    pub pass_times_gpu: bool,
+    /// Whether the paths were found inside the ticks instead of on the workers
+    #[serde(default)]
+    pub inline_paths: bool,
+    pub peak_memory_mb: Option<f32>,
+}
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub mean: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub p99: f32,
+    pub max: f32,
+}
+
+impl Percentiles {
+    pub fn new(values: &mut [f32]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(f32::total_cmp);
+        let at = |p: f32| values[((values.len() - 1) as f32 * p).round() as usize];
+        Self {
+            mean: values.iter().sum::<f32>() / values.len() as f32,
+            p50: at(0.5),
+            p95: at(0.95),
+            p99: at(0.99),
+            max: values[values.len() - 1],
+        }
+    }
+}
+
+/// A measure that got worse beyond its threshold
+#[derive(Debug)]
+pub struct Regression {
+    pub measure: &'static str,
+    pub before: f32,
+    pub after: f32,
+}
+
+/// The measures compared between two reports, with their threshold and the difference ignored
+fn measures(r: &BenchReport) -> [(&'static str, f32, f32, f32); 10] {
+    let time = |name, value| (name, value, TIME_THRESHOLD, TIME_NOISE_MS);
+    let count = |name, value| (name, value, COUNT_THRESHOLD, 0.0);
+    [
+        time("frame time p50", r.frame_time.p50),
+        time("frame time p95", r.frame_time.p95),
+        time("frame time p99", r.frame_time.p99),
+        time("tick time p50", r.tick_time.p50),
+        time("tick time p95", r.tick_time.p95),
+        time("tick time max", r.tick_time.max),
+        time("cpu time p95", r.cpu_time.p95),
+        count("draw calls p50", r.draw_calls.p50),
+        count("triangles p50", r.triangles.p50),
+        (
+            "peak memory (MB)",
+            r.peak_memory_mb.unwrap_or(0.0),
+            MEMORY_THRESHOLD,
+            0.0,
+        ),
+    ]
+}
+
+/// The measures of `after` that are worse than in `before`
+pub fn compare(before: &BenchReport, after: &BenchReport) -> Vec<Regression> {
+    measures(before)
+        .into_iter()
+        .zip(measures(after))
+        .filter(|&((_, b, threshold, noise), (_, a, _, _))| {
+            a - b > noise && a > b * (1.0 + threshold)
+        })
+        .map(|((measure, before, _, _), (_, after, _, _))| Regression {
+            measure,
+            before,
+            after,
+        })
+        .collect()
+}
+
+/// Prints the differences between two report files, returns the exit code of the game
+pub fn compare_files(before: &Path, after: &Path) -> i32 {
+    let load = |p: &Path| -> Result<BenchReport, String> {
+        let data = std::fs::read(p).map_err(|e| format!("could not read {:?}: {}", p, e))?;
+        JSONPretty::decode(&data).map_err(|e| format!("could not decode {:?}: {}", p, e))
+    };
+    let (before, after) = match (load(before), load(after)) {
+        (Ok(b), Ok(a)) => (b, a),
+        (Err(e), _) | (_, Err(e)) => {
+            log::error!("{}", e);
+            return 2;
+        }
+    };
+    if before.scene != after.scene {
+        log::warn!(
+            "comparing different scenes: {} and {}",
+            before.scene,
+            after.scene
+        );
+    }
+    if before.inline_paths != after.inline_paths {
+        println!(
+            "paths found {} -> {}",
+            paths_label(before.inline_paths),
+            paths_label(after.inline_paths)
+        );
+    }
+
+    for ((measure, b, _, _), (_, a, _, _)) in measures(&before).into_iter().zip(measures(&after)) {
+        let change = if b != 0.0 { (a - b) / b * 100.0 } else { 0.0 };
+        println!(
+            "{:<18} {:>12.2} -> {:>12.2} ({:+.1}%)",
+            measure, b, a, change
+        );
+    }
+
+    println!(
+        "{:<18} {:>12.2} -> {:>12.2}",
+        "atlas saved p50", before.atlas_saved_draw_calls.p50, after.atlas_saved_draw_calls.p50
+    );
+
+    // the pass times are only shown, they are too noisy to fail on and the GPU ones can't be
+    // compared to the CPU ones of a machine without timestamp queries
+    if before.pass_times_gpu == after.pass_times_gpu {
+        for (pass, a) in &after.pass_times {
+            let Some(b) = before.pass_times.get(pass) else {
+                continue;
+            };
+            println!(
+                "{:<18} {:>12.2} -> {:>12.2}",
+                format!("{} p95", pass),
+                b.p95,
+                a.p95
+            );
+        }
+    }
+
+    let regressions = compare(&before, &after);
+    if regressions.is_empty() {
+        println!("no regression");
+        return 0;
+    }
+    for r in &regressions {
+        println!(
+            "REGRESSION: {} went from {:.2} to {:.2}",
+            r.measure, r.before, r.after
+        );
+    }
+    1
+}
+
+fn paths_label(inline: bool) -> &'static str {
+    if inline {
+        "inside the ticks"
+    } else {
+        "on the workers"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(frame_p95: f32, draw_calls: f32) -> BenchReport {
+        let p = |x: f32| Percentiles {
+            mean: x,
+            p50: x,
+            p95: x,
+            p99: x,
+            max: x,
+        };
+        BenchReport {
+            scene: "downtown".to_string(),
+            version: "test".to_string(),
+            frames: 10,
+            ticks_per_frame: 1,
+            frame_time: Percentiles {
+                p95: frame_p95,
+                ..p(10.0)
+            },
+            tick_time: p(2.0),
+            cpu_time: p(8.0),
+            engine_render_time: p(3.0),
+            gui_time: p(1.0),
+            draw_calls: p(draw_calls),
+            atlas_saved_draw_calls: p(50.0),
+            triangles: p(100000.0),
+            pass_times: BTreeMap::from([("main".to_string(), p(4.0))]),
+            pass_times_gpu: true,
+            inline_paths: false,
+            peak_memory_mb: Some(500.0),
+        }
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut values: Vec<f32> = (1..=100).rev().map(|x| x as f32).collect();
+        let p = Percentiles::new(&mut values);
+        assert_eq!(p.p50, 51.0);
+        assert_eq!(p.p95, 95.0);
+        assert_eq!(p.max, 100.0);
+        assert_eq!(p.mean, 50.5);
+        assert_eq!(Percentiles::new(&mut []).max, 0.0);
+    }
+
+    #[test]
+    fn flags_regressions_beyond_thresholds() {
+        let before = report(16.0, 1000.0);
+        assert!(compare(&before, &report(16.5, 1010.0)).is_empty());
+        assert!(compare(&before, &report(12.0, 900.0)).is_empty());
+
+        let regressions = compare(&before, &report(20.0, 1100.0));
+        let names: Vec<_> = regressions.iter().map(|r| r.measure).collect();
+        assert_eq!(names, ["frame time p95", "draw calls p50"]);
+    }
+
+    #[test]
+    fn parse_bench_args() {
+        let args = |x: &[&str]| parse_args(x.iter().map(|x| x.to_string()));
+
+        assert!(matches!(args(&[]), Ok(BenchArgs::None)));
+        let Ok(BenchArgs::Run(opts)) = args(&["--bench", "suburb", "--frames", "100"]) else {
+            panic!("expected a run");
+        };
+        assert_eq!(opts.scene, BenchScene::Suburb);
+        assert_eq!(opts.frames, 100);
+        assert_eq!(opts.out, PathBuf::from("bench_suburb.json"));
+        assert!(!opts.inline_paths);
+        let Ok(BenchArgs::Run(opts)) = args(&["--bench", "freight", "--bench-inline-paths"]) else {
+            panic!("expected a run");
+        };
+        assert!(opts.inline_paths);
+        assert!(args(&["--bench", "moon"]).is_err());
+        assert!(matches!(
+            args(&["--bench-compare", "a.json", "b.json"]),
+            Ok(BenchArgs::Compare { .. })
+        ));
+    }
+}
@@ -1,33 +1,67 @@
-use std::ptr::addr_of;
+use std::f32::consts::TAU;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
 use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
 use common::history::History;
-use engine::{Context, FrameContext, MeshBuilder};
-use geom::{vec2, vec3, Camera, LinearColor};
-use simulation::Simulation;
+use engine::{Context, Drawable, FrameContext, MeshBuilder};
+use geom::{vec2, Camera, Color, SunPosition};
+use simulation::{Simulation, SimulationOptions};
 
 use crate::audio::GameAudio;
+use crate::benchmark::{self, Benchmark};
+use crate::gui::debug_channels::{draw_channels, DevSettings};
 use crate::gui::debug_window::DebugObjs;
 use crate::gui::render_oldgui;
+use crate::input_arbiter::{InputArbiter, UiClaims};
 use crate::inputmap::{Bindings, InputAction, InputMap};
+use crate::network::NetworkState;
 use crate::newgui;
+use crate::newgui::camera_path::CameraPathPlayer;
+use crate::newgui::cinematic::CinematicDirector;
 use crate::newgui::follow::FollowEntity;
+use crate::newgui::hover::{HoverState, HoveredObject};
+use crate::newgui::input_recording::InputRecorder;
+use crate::newgui::journal::Journal;
 use crate::newgui::keybinds::KeybindState;
+use crate::newgui::main_menu::{load_demo, AppState, Loading, LoadingStage, MainMenu};
+use crate::newgui::map_export::MapExporter;
+use crate::newgui::pause_menu::PauseMenu;
 use crate::newgui::terraforming::TerraformingResource;
 use crate::newgui::toolbox::building;
+use crate::newgui::trip_route::TripRoute;
+use crate::newgui::tutorial::TutorialState;
+use crate::newgui::windows::diagnostics::DiagnosticsState;
+use crate::newgui::windows::economy::EconomyState;
 use crate::newgui::windows::settings::{manage_settings, Settings};
 use crate::newgui::UiTextures;
-use crate::newgui::{render_newgui, ExitState, GuiState, TimeAlways, Tool};
-use crate::rendering::{InstancedRender, MapRenderOptions, MapRenderer, OrbitCamera};
+use crate::newgui::{
+    render_newgui, ExitState, GuiState, InspectedBuilding, InspectedEntity, TimeAlways, Tool,
+};
+use crate::rendering::{
+    palette, register_harbor_meshes, InstancedRender, Interpolation, Lighting, MapRenderOptions,
+    MapRenderer, OrbitCamera, SimTimeScale,
+};
 use crate::uiworld::{SaveLoadState, UiWorld};
-use prototypes::GameTime;
+use prototypes::{prototypes_generation, try_prototype, GameTime, ItemPrototype, Season};
 use simulation::utils::scheduler::SeqSchedule;
 
 pub const VERSION: &str = include_str!("../../VERSION");
 
+/// Radians per second of the camera turning around the map behind the main menu
+const MENU_ORBIT_SPEED: f32 = 0.05;
+
+/// Runs `f` on another thread with the simulation read locked.
+/// The simulation does not advance meanwhile, but the game is still drawn.
+pub fn read_in_background(
+    sim: &Arc<RwLock<Simulation>>,
+    f: impl FnOnce(&Simulation) + Send + 'static,
+) {
+    let cpy = sim.clone();
+    std::thread::spawn(move || f(&cpy.read().unwrap()));
+}
+
 /// State is the main struct that contains all the state of the game and game UI.
 pub struct State {
     pub sim: Arc<RwLock<Simulation>>,
@@ -39,6 +73,9 @@ pub struct State {
     immediate_renderer: MeshBuilder<true>,
 
     all_audio: GameAudio,
+
+    /// Set when the game was started with `--bench`
+    bench: Option<Benchmark>,
 }
 
 impl engine::framework::State for State {
@@ -50,21 +87,20 @@ impl engine::framework::State for State {
 
         log::info!("loaded egui_render");
 
-        let sim: Simulation =
-            Simulation::load_from_disk("world").unwrap_or_else(|| Simulation::new(true));
+        let bench_opts = benchmark::options();
+        let sim = match bench_opts {
+            Some(opts) => benchmark::load_scene(opts.scene),
+            None => load_demo(),
+        };
+        let bench = bench_opts.map(|opts| Benchmark::new(opts.clone(), &sim));
         let game_schedule = Simulation::schedule();
         let mut uiworld = UiWorld::init();
 
         let mut bindings = uiworld.write::<Bindings>();
-        let default_bindings = Bindings::default();
-        bindings
-            .0
-            .retain(|act, _| default_bindings.0.contains_key(act));
-        for (act, comb) in default_bindings.0 {
-            bindings.0.entry(act).or_insert(comb);
-        }
+        bindings.merge_defaults();
         uiworld.write::<InputMap>().build_input_tree(&mut bindings);
         drop(bindings);
+        uiworld.read::<DevSettings>().apply();
 
         uiworld.insert(UiTextures::new(&mut ctx.gfx, &mut ctx.yakui));
 
@@ -79,8 +115,14 @@ impl engine::framework::State for State {
         }
 
         defer!(log::info!("finished init of game loop"));
+        register_harbor_meshes(&mut ctx.gfx);
         building::do_icons(ctx, &uiworld);
 
+        if bench.is_some() {
+            *uiworld.write::<AppState>() = AppState::InGame;
+            uiworld.write::<GuiState>().hidden = true;
+        }
+
         let me = Self {
             uiw: uiworld,
             game_schedule,
@@ -89,6 +131,7 @@ impl engine::framework::State for State {
             all_audio: GameAudio::new(&mut ctx.audio),
             sim: Arc::new(RwLock::new(sim)),
             immediate_renderer: MeshBuilder::new(ctx.gfx.tess_material),
+            bench,
         };
         me.sim.write().unwrap().map().dispatch_all();
         me
@@ -108,24 +151,53 @@ impl engine::framework::State for State {
         let mut slstate = self.uiw.write::<SaveLoadState>();
         if slstate.please_save && !slstate.saving_status.load(Ordering::SeqCst) {
             slstate.please_save = false;
-            let cpy = self.sim.clone();
+            slstate.changes_since_save = 0;
             slstate.saving_status.store(true, Ordering::SeqCst);
             let status = slstate.saving_status.clone();
-            std::thread::spawn(move || {
+            let compression = slstate.save_compression;
+            read_in_background(&self.sim, move |sim| {
                 profiling::scope!("game_loop::update::save");
-                cpy.read().unwrap().save_to_disk("world");
+                sim.save_to_disk_with("world", compression);
                 status.store(false, Ordering::SeqCst);
             });
         }
         drop(slstate);
 
-        crate::network::sim_update(self);
+        if self.uiw.read::<building::BuildingIcons>().generation != prototypes_generation() {
+            building::do_icons(ctx, &self.uiw);
+        }
+
+        let in_game = self.update_app_state(ctx);
+        if let Some(ref mut bench) = self.bench {
+            let pending = self.map_renderer.pending_mesh_chunks()
+                + self.map_renderer.pending_terrain_chunks();
+            bench.update(&self.sim, &mut self.game_schedule, &self.uiw, ctx, pending);
+        } else if in_game {
+            crate::network::sim_update(self);
+            let tick = self.sim.read().unwrap().get_tick();
+            let settings = self.uiw.read::<Settings>();
+            let mut scale = self.uiw.write::<SimTimeScale>();
+            scale.pitch_shift = settings.speed_pitch_shift;
+            scale.update(tick, settings.time_warp, ctx.delta);
+        }
 
         if std::mem::take(&mut self.uiw.write::<SaveLoadState>().render_reset) {
             self.reset(ctx);
         }
 
-        if !ctx.egui.last_mouse_captured {
+        let dispatch = {
+            let mut claims = UiClaims {
+                regions: vec![],
+                pointer: ctx.egui.last_mouse_captured || ctx.yakui.pointer_captured,
+                keyboard: ctx.egui.last_kb_captured,
+            };
+            claims.take_yakui_regions(ctx.yakui.zoom_factor);
+            let mut arbiter = self.uiw.write::<InputArbiter>();
+            arbiter.set_claims(claims);
+            arbiter.arbitrate(&ctx.input)
+        };
+
+        if dispatch.pointer {
             let sim = self.sim.read().unwrap();
             let map = sim.map();
             let ray = self
@@ -157,24 +229,64 @@ impl engine::framework::State for State {
         );
         self.uiw.write::<InputMap>().prepare_frame(
             &ctx.input,
-            !ctx.egui.last_kb_captured,
-            !ctx.egui.last_mouse_captured,
+            dispatch.keyboard,
+            dispatch.pointer,
+            self.uiw.read::<Tool>().input_layer(),
         );
-        newgui::run_ui_systems(&self.sim.read().unwrap(), &self.uiw);
+        InputRecorder::update(&self.uiw, ctx.delta);
+        {
+            // the right stick drives the cursor unless it is used to rotate or pick a tool
+            let inp = self.uiw.read::<InputMap>();
+            ctx.input.gamepad.cursor_enabled = !inp.act.contains(&InputAction::CameraRotate)
+                && !inp.act.contains(&InputAction::OpenToolWheel);
+        }
+        if in_game {
+            newgui::pause_menu::pause_menu_input(&self.uiw);
+            newgui::run_ui_systems(&self.sim.read().unwrap(), &self.uiw);
+        }
 
         self.uiw.write::<Timings>().all.add_value(ctx.delta);
         self.uiw.write::<Timings>().per_game_system = self.game_schedule.times();
 
-        self.uiw.write::<GuiState>().hidden ^= self
+        if in_game {
+            self.uiw.write::<GuiState>().hidden ^= self
+                .uiw
+                .read::<InputMap>()
+                .just_act
+                .contains(&InputAction::HideInterface);
+        }
+
+        if self
             .uiw
             .read::<InputMap>()
             .just_act
-            .contains(&InputAction::HideInterface);
+            .contains(&InputAction::ReloadPrototypes)
+        {
+            self.reload_prototypes(ctx);
+        }
 
         manage_settings(ctx, &self.uiw.read::<Settings>());
-        self.manage_io(ctx);
+        if !in_game {
+            self.menu_camera(ctx);
+        } else if self.bench.is_none() {
+            // the benchmark drives the camera itself
+            self.manage_io(ctx);
+        }
 
-        self.map_renderer.update(&self.sim.read().unwrap(), ctx);
+        self.map_renderer.update(
+            &self.sim.read().unwrap(),
+            &self.uiw.read::<OrbitCamera>().camera,
+            ctx,
+        );
+        self.uiw.insert(self.map_renderer.streaming_stats());
+        {
+            let settings = self.uiw.read::<Settings>();
+            self.map_renderer.water.set_waves(
+                &ctx.gfx,
+                settings.water_wave_scale,
+                settings.water_wave_speed,
+            );
+        }
 
         ctx.gfx
             .set_time(self.sim.read().unwrap().read::<GameTime>().timestamp as f32);
@@ -185,7 +297,18 @@ impl engine::framework::State for State {
         self.all_audio
             .update(&self.sim.read().unwrap(), &self.uiw, &mut ctx.audio);
 
-        FollowEntity::update_camera(self);
+        if in_game {
+            FollowEntity::update_camera(self);
+            CinematicDirector::update_camera(self, ctx.delta);
+        }
+        CameraPathPlayer::update_camera(self, ctx);
+        let pending =
+            self.map_renderer.pending_mesh_chunks() + self.map_renderer.pending_terrain_chunks();
+        MapExporter::update_camera(&self.uiw, ctx, pending);
+        if in_game {
+            Journal::update(&self.uiw, &self.sim.read().unwrap(), ctx);
+            TripRoute::update(&self.uiw, &self.sim.read().unwrap());
+        }
         self.uiw.camera_mut().update(ctx);
         self.manage_gfx_params(ctx);
     }
@@ -211,12 +334,21 @@ impl engine::framework::State for State {
             ctx,
         );
 
-        self.instanced_renderer
-            .render(&self.sim.read().unwrap(), ctx);
+        self.instanced_renderer.render(
+            &self.sim.read().unwrap(),
+            &self.uiw.read::<Interpolation>(),
+            &self.uiw.read::<SimTimeScale>(),
+            ctx,
+        );
 
         drop(sim);
         drop(camera);
 
+        // the cinematic camera films the city as it is, without any selection
+        if !self.uiw.read::<CinematicDirector>().enabled {
+            self.outlines(ctx);
+        }
+
         self.immediate_draw(ctx);
 
         self.uiw
@@ -231,7 +363,14 @@ impl engine::framework::State for State {
             .resize(ctx, size.0 as f32, size.1 as f32);
     }
 
+    fn should_close(&mut self) -> bool {
+        self.bench.as_ref().is_some_and(|b| b.done)
+    }
+
     fn exit(&mut self) -> bool {
+        if !self.uiw.read::<AppState>().in_game() {
+            return true;
+        }
         if self.uiw.read::<GuiState>().last_save.elapsed() < Duration::from_secs(30) {
             return true;
         }
@@ -251,6 +390,9 @@ impl engine::framework::State for State {
     fn render_gui(&mut self, ui: &egui::Context) {
         let sim = self.sim.read().unwrap();
         render_oldgui(ui, &self.uiw, &sim);
+        if let Some(text) = self.uiw.write::<DiagnosticsState>().copy.take() {
+            ui.output_mut(|o| o.copied_text = text);
+        }
     }
 
     fn render_yakui(&mut self) {
@@ -260,27 +402,198 @@ impl engine::framework::State for State {
 }
 
 impl State {
+    /// Runs the main menu and loading screen, returns whether the game is being played
+    fn update_app_state(&mut self, ctx: &mut Context) -> bool {
+        let saving = {
+            let slstate = self.uiw.read::<SaveLoadState>();
+            slstate.please_save || slstate.saving_status.load(Ordering::SeqCst)
+        };
+        // wait for the save to finish before replacing the simulation
+        if !saving && self.uiw.read::<MainMenu>().please_return {
+            *self.uiw.write::<MainMenu>() = MainMenu::default();
+            *self.sim.write().unwrap() = load_demo();
+            self.uiw.write::<SaveLoadState>().render_reset = true;
+            *self.uiw.write::<AppState>() = AppState::MainMenu;
+        }
+
+        let mut state = self.uiw.write::<AppState>();
+        let loading = match *state {
+            AppState::InGame => return true,
+            AppState::MainMenu => {
+                // the load window hands over the simulation directly
+                let slstate = self.uiw.read::<SaveLoadState>();
+                if slstate.please_load_sim.is_some() || slstate.please_load.is_some() {
+                    *state = AppState::Loading(Loading::handed_over());
+                }
+                return false;
+            }
+            AppState::Loading(ref mut loading) => loading,
+        };
+
+        if loading.stage < LoadingStage::RoadMeshes {
+            if let Some(ref task) = loading.task {
+                // the prototypes are loaded at startup, before the menu is shown
+                loading.stage = task
+                    .progress
+                    .try_iter()
+                    .last()
+                    .unwrap_or(loading.stage)
+                    .max(LoadingStage::ReadingFile);
+
+                if task.handle.is_finished() {
+                    let sim = loading.task.take().unwrap().handle.join().ok().flatten();
+                    let Some(sim) = sim else {
+                        self.uiw.write::<MainMenu>().error = "Failed to load the game".to_string();
+                        *state = AppState::MainMenu;
+                        return false;
+                    };
+                    self.uiw.write::<SaveLoadState>().please_load_sim = Some(sim);
+                }
+            }
+
+            let mut slstate = self.uiw.write::<SaveLoadState>();
+            loading.sim_replaced |= slstate.please_load_sim.is_some();
+            crate::network::handle_replay(
+                &mut self.sim.write().unwrap(),
+                &mut self.game_schedule,
+                &mut slstate,
+            );
+            if loading.task.is_none()
+                && slstate.please_load_sim.is_none()
+                && slstate.please_load.is_none()
+            {
+                loading.stage = LoadingStage::RoadMeshes;
+            }
+            return false;
+        }
+
+        // wait for the map renderer to be rebuilt for the new map
+        if self.uiw.read::<SaveLoadState>().render_reset {
+            return false;
+        }
+        let left = match loading.stage {
+            LoadingStage::RoadMeshes => self.map_renderer.pending_mesh_chunks(),
+            _ => self.map_renderer.pending_terrain_chunks(),
+        };
+        loading.chunks_total = loading.chunks_total.max(left);
+        loading.chunks_left = left;
+        if left > 0 {
+            return false;
+        }
+
+        if loading.stage == LoadingStage::RoadMeshes {
+            loading.stage = LoadingStage::Terrain;
+            loading.chunks_total = 0;
+            return false;
+        }
+
+        *state = AppState::InGame;
+        drop(state);
+        *self.uiw.camera_mut() = OrbitCamera::load((ctx.gfx.size.0, ctx.gfx.size.1));
+        self.uiw.write::<GuiState>().last_save = Instant::now();
+        false
+    }
+
+    /// Slowly orbits around the middle of the map behind the main menu
+    fn menu_camera(&mut self, ctx: &mut Context) {
+        let sim = self.sim.read().unwrap();
+        let map = sim.map();
+        let center = map.environment.bounds().center();
+
+        let mut cam = self.uiw.camera_mut();
+        cam.camera.pos = center.z(map.environment.height(center).unwrap_or(0.0));
+        cam.camera.yaw.0 = (cam.camera.yaw.0 + ctx.delta * MENU_ORBIT_SPEED) % TAU;
+        cam.camera.pitch.0 = 0.4;
+        cam.camera.dist = 2000.0;
+        *self.uiw.write::<Camera>() = cam.camera;
+    }
+
+    /// Reloads the prototypes from the Lua files, and rebuilds the meshes and the interface made
+    /// from them when they changed
+    fn reload_prototypes(&mut self, ctx: &mut Context) {
+        if self.uiw.read::<NetworkState>().is_networked() {
+            // the other games would keep simulating with the previous prototypes and desync
+            log::warn!("the prototypes cannot be reloaded during a network game");
+            return;
+        }
+        let mut pinned = self.sim.read().unwrap().pinned_prototypes();
+        // the interface shows the running tutorial and the open tab of the build menu
+        let tutorial = self.uiw.read::<TutorialState>().tutorial;
+        let tab = self.uiw.read::<building::BuildMenu>().tab();
+        pinned.extend(tutorial.and_then(try_prototype).map(|p| p.qualified_name()));
+        pinned.extend(tab.and_then(try_prototype).map(|p| p.qualified_name()));
+        // Safety: the prototypes are loaded from the main thread, the simulation runs on it too
+        let Ok(diff) = (unsafe { prototypes::reload_prototypes(&prototypes::Lua::new(), &pinned) })
+        else {
+            // the errors are logged, the game keeps the previous prototypes
+            return;
+        };
+        if diff.is_empty() {
+            return;
+        }
+        for (what, names) in [
+            ("added", &diff.added),
+            ("changed", &diff.changed),
+            ("removed", &diff.removed),
+        ] {
+            if !names.is_empty() {
+                log::info!("{} prototypes: {}", what, names.join(", "));
+            }
+        }
+
+        if diff.touches::<ItemPrototype>() {
+            self.sim
+                .read()
+                .unwrap()
+                .write::<simulation::economy::Market>()
+                .sync_items();
+        }
+
+        register_harbor_meshes(&mut ctx.gfx);
+        self.instanced_renderer = InstancedRender::new(&mut ctx.gfx);
+        self.reset(ctx);
+        if diff.touches::<ItemPrototype>() {
+            self.uiw.write::<EconomyState>().reset_item_selection();
+        }
+    }
+
     fn reset(&mut self, ctx: &mut Context) {
         ctx.gfx.lamplights.reset(&ctx.gfx.device, &ctx.gfx.queue);
         self.map_renderer = MapRenderer::new(&mut ctx.gfx, &self.sim.read().unwrap());
+        self.uiw.write::<Interpolation>().clear();
         self.sim.write().unwrap().map().dispatch_all();
         ctx.gfx.update_simplelit_bg();
     }
 
     fn manage_gfx_params(&mut self, ctx: &mut Context) {
-        let t = std::f32::consts::TAU
-            * (ctx.gfx.render_params.value().time - 8.0 * GameTime::HOUR as f32)
-            / GameTime::DAY as f32;
+        let hour =
+            (ctx.gfx.render_params.value().time % GameTime::DAY as f32) / GameTime::HOUR as f32;
+
+        let (coldness, sun) = {
+            let sim = self.sim.read().unwrap();
+            let opts = sim.read::<SimulationOptions>();
+            let gametime = sim.read::<GameTime>();
+            let coldness = gametime
+                .year_progress(opts.season_days)
+                .map_or(0.5, Season::coldness);
+            let day = gametime.day_of_year(opts.season_days);
+            let sun = SunPosition::compute(opts.latitude.degrees(), day, hour).direction();
+            (coldness, sun)
+        };
 
-        let sun = vec3(t.cos(), t.sin() * 0.5, t.sin() + 0.5).normalize();
+        let lighting = self
+            .uiw
+            .read::<PauseMenu>()
+            .lighting()
+            .apply(Lighting::from_sun(sun));
+        let sun = lighting.sun;
 
         self.uiw.insert(ctx.gfx.perf.as_static());
 
         let params = ctx.gfx.render_params.value_mut();
         params.time_always = self.uiw.time_always();
-        params.sun_col = 4.0
-            * sun.z.max(0.0).sqrt().sqrt()
-            * LinearColor::new(1.0, 0.95 + sun.z * 0.05, 0.95 + sun.z * 0.05, 1.0);
+        params.sun_col = lighting.sun_col;
+        params.overcast = lighting.overcast;
         let camera = self.uiw.read::<OrbitCamera>();
         params.sun = sun;
         params.viewport = vec2(ctx.gfx.size.0 as f32, ctx.gfx.size.1 as f32);
@@ -306,6 +619,8 @@ impl State {
         let c = simulation::colors();
         params.sand_col = c.sand_col.into();
         params.sea_col = c.sea_col.into();
+        params.snow = ((coldness - 0.75) * 4.0).clamp(0.0, 1.0);
+        params.lamp_intensity = lighting.lamp_intensity;
     }
 
     fn manage_io(&mut self, ctx: &mut Context) {
@@ -325,6 +640,50 @@ impl State {
         drop(map);
     }
 
+    /// Outlines the selected, followed and hovered objects
+    fn outlines(&mut self, ctx: &mut FrameContext<'_>) {
+        profiling::scope!("game_loop::outlines");
+        let sim = self.sim.read().unwrap();
+        let selected_entity = self.uiw.read::<InspectedEntity>().e;
+        let selected_building = self.uiw.read::<InspectedBuilding>().e;
+        let followed = self.uiw.read::<FollowEntity>().0;
+        let hovered = self.uiw.read::<HoverState>().hovered;
+
+        let mut objects = vec![];
+        if let Some(e) = followed {
+            objects.push((HoveredObject::Entity(e), palette().success));
+        }
+        if let Some(e) = selected_entity.filter(|&e| Some(e) != followed) {
+            objects.push((HoveredObject::Entity(e), palette().primary));
+        }
+        if let Some(b) = selected_building {
+            objects.push((HoveredObject::Building(b), palette().primary));
+        }
+        if let Some(h) = hovered.filter(|h| objects.iter().all(|(o, _)| o != h)) {
+            objects.push((h, Color::WHITE.a(0.5)));
+        }
+
+        for (obj, color) in objects {
+            let meshes: Vec<Box<dyn Drawable>> = match obj {
+                HoveredObject::Entity(e) => self
+                    .instanced_renderer
+                    .entity_outline(&sim, &self.uiw.read::<Interpolation>(), e, ctx.gfx)
+                    .into_iter()
+                    .map(|m| Box::new(m) as Box<dyn Drawable>)
+                    .collect(),
+                HoveredObject::Building(b) => {
+                    self.map_renderer
+                        .meshb
+                        .building_outline(&sim.map(), b, ctx.gfx)
+                }
+            };
+            if meshes.is_empty() {
+                continue;
+            }
+            ctx.draw_outlined(color.into(), meshes);
+        }
+    }
+
     fn immediate_draw(&mut self, ctx: &mut FrameContext) {
         profiling::scope!("immediate_draw");
 
@@ -348,34 +707,9 @@ impl State {
         {
             let sim = self.sim.read().unwrap();
             let immediate = &mut *self.uiw.write::<ImmediateDraw>();
-            let map = sim.map();
-            let mut col = LinearColor::WHITE;
-            col.a = 0.2;
-            unsafe {
-                for v in &*addr_of!(geom::DEBUG_POS) {
-                    immediate.circle(*v, 1.0).color(LinearColor::RED);
-                }
-                for v in &*addr_of!(geom::DEBUG_OBBS) {
-                    immediate
-                        .obb(*v, map.environment.height(v.center()).unwrap_or(0.0) + 8.0)
-                        .color(col);
-                }
-                for v in &*addr_of!(geom::DEBUG_SPLINES) {
-                    immediate
-                        .polyline(
-                            v.smart_points(1.0, 0.0, 1.0)
-                                .map(|x| x.z(10.0))
-                                .collect::<Vec<_>>(),
-                            5.0,
-                            false,
-                        )
-                        .color(col);
-                }
-                geom::DEBUG_OBBS.clear();
-                geom::DEBUG_SPLINES.clear();
-                geom::DEBUG_POS.clear();
-            }
+            draw_channels(immediate, &sim, &self.uiw);
 
+            immediate.thickness_scale = self.uiw.read::<Settings>().gizmo_thickness;
             immediate.apply(&mut tess, ctx);
             immediate.orders.clear();
         }
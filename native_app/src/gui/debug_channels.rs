@@ -0,0 +1,433 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ptr::addr_of;
+
+use serde::{Deserialize, Serialize};
+
+use common::debug_draw::{self, DebugChannel};
+use geom::{Color, LinearColor, Vec3};
+use simulation::map::{Map, TraverseKind};
+use simulation::transportation::deadlock::Deadlocks;
+use simulation::transportation::train::TrainReservations;
+use simulation::utils::debug_channels::{
+    INTERSECTION_OCCUPANCY, LANE_RESERVATIONS, ROUTER_PATHS, SPATIAL_GRID, TRAIN_RESERVATIONS,
+    VEHICLE_WAITS,
+};
+use simulation::{AnyEntity, Simulation, TrainID, VehicleID};
+
+use crate::newgui::follow::FollowEntity;
+use crate::newgui::InspectedEntity;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+
+/// Shapes pushed to `geom::DEBUG_POS`, `DEBUG_OBBS` and `DEBUG_SPLINES`
+pub static GEOM_SHAPES: DebugChannel =
+    DebugChannel::new("geom.shapes", LinearColor::new(1.0, 1.0, 1.0, 0.2));
+/// Itinerary of the entity followed by the camera
+pub static FOLLOWED_ITINERARY: DebugChannel =
+    DebugChannel::new("follow.itinerary", LinearColor::CYAN);
+
+type Drawer = fn(&mut ImmediateDraw, LinearColor, &Simulation, &UiWorld) -> Option<()>;
+
+static DRAWERS: &[(&DebugChannel, Drawer)] = &[
+    (&ROUTER_PATHS, draw_router_paths),
+    (&TRAIN_RESERVATIONS, draw_train_reservations),
+    (&LANE_RESERVATIONS, draw_lane_reservations),
+    (&INTERSECTION_OCCUPANCY, draw_intersection_occupancy),
+    (&VEHICLE_WAITS, draw_vehicle_waits),
+    (&SPATIAL_GRID, draw_spatial_grid),
+    (&GEOM_SHAPES, draw_geom_shapes),
+    (&FOLLOWED_ITINERARY, draw_followed_itinerary),
+];
+
+/// Debug channels that are enabled and their color overrides, saved as "dev_settings"
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct DevSettings {
+    pub enabled_channels: BTreeSet<String>,
+    pub channel_colors: BTreeMap<String, LinearColor>,
+}
+
+impl Default for DevSettings {
+    fn default() -> Self {
+        Self {
+            // they were always drawn before being channels
+            enabled_channels: [ROUTER_PATHS.name, GEOM_SHAPES.name]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            channel_colors: BTreeMap::new(),
+        }
+    }
+}
+
+impl DevSettings {
+    /// Enables the registered channels that were saved as enabled
+    pub fn apply(&self) {
+        for channel in debug_draw::channels() {
+            channel.set_enabled(self.enabled_channels.contains(channel.name));
+        }
+    }
+
+    pub fn color(&self, channel: &DebugChannel) -> LinearColor {
+        self.channel_colors
+            .get(channel.name)
+            .copied()
+            .unwrap_or(channel.color)
+    }
+}
+
+pub fn register_channels() {
+    debug_draw::register(&[&GEOM_SHAPES, &FOLLOWED_ITINERARY]);
+}
+
+/// Draws the enabled channels
+pub fn draw_channels(immediate: &mut ImmediateDraw, sim: &Simulation, uiw: &UiWorld) {
+    profiling::scope!("debug_channels");
+    let settings = uiw.read::<DevSettings>();
+    for &(channel, draw) in DRAWERS {
+        if channel.enabled() {
+            draw(immediate, settings.color(channel), sim, uiw);
+        }
+    }
+
+    // the shapes are pushed every frame, even if nobody looks at them
+    unsafe {
+        geom::DEBUG_OBBS.clear();
+        geom::DEBUG_SPLINES.clear();
+        geom::DEBUG_POS.clear();
+    }
+}
+
+/// Ids of the vehicles crossing the intersections, written next to them
+pub fn channel_labels(ui: &egui::Context, uiw: &UiWorld, sim: &Simulation) {
+    if !INTERSECTION_OCCUPANCY.enabled() {
+        return;
+    }
+    profiling::scope!("debug_channels::labels");
+    let cam = uiw.camera();
+    let ppp = ui.pixels_per_point();
+    let painter = ui.layer_painter(egui::LayerId::background());
+
+    for (id, v) in turn_occupants(sim) {
+        let (screenpos, depth) = cam.project(v.up(2.0));
+        if depth <= 0.0 {
+            continue;
+        }
+        let Color { r, g, b, .. } = vehicle_color(id);
+        painter.text(
+            egui::pos2(screenpos.x / ppp, screenpos.y / ppp),
+            egui::Align2::CENTER_BOTTOM,
+            format!("{:?}", id),
+            egui::FontId::monospace(11.0),
+            egui::Color32::from_rgb((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8),
+        );
+    }
+}
+
+/// Checkboxes and color pickers of all the registered channels
+pub fn channels_ui(ui: &mut egui::Ui, uiworld: &UiWorld) {
+    let mut settings = uiworld.write::<DevSettings>();
+    ui.label("Debug draw channels");
+    for channel in debug_draw::channels() {
+        ui.horizontal(|ui| {
+            let mut enabled = channel.enabled();
+            if ui.checkbox(&mut enabled, channel.name).changed() {
+                channel.set_enabled(enabled);
+                if enabled {
+                    settings.enabled_channels.insert(channel.name.to_string());
+                } else {
+                    settings.enabled_channels.remove(channel.name);
+                }
+            }
+
+            let c = settings.color(channel);
+            let mut rgba = [c.r, c.g, c.b, c.a];
+            if ui.color_edit_button_rgba_unmultiplied(&mut rgba).changed() {
+                let [r, g, b, a] = rgba;
+                settings
+                    .channel_colors
+                    .insert(channel.name.to_string(), LinearColor::new(r, g, b, a));
+            }
+            if settings.channel_colors.contains_key(channel.name)
+                && ui.small_button("reset").clicked()
+            {
+                settings.channel_colors.remove(channel.name);
+            }
+        });
+    }
+}
+
+fn up(points: &[Vec3], z: f32) -> Vec<Vec3> {
+    points.iter().map(|x| x.up(z)).collect()
+}
+
+/// The local path and the remaining route of an entity
+fn draw_itinerary(
+    immediate: &mut ImmediateDraw,
+    color: LinearColor,
+    sim: &Simulation,
+    e: AnyEntity,
+) -> Option<()> {
+    let map: &Map = &sim.map();
+    let pos = sim.pos_any(e)?;
+    let itinerary = sim.world().it_any(e)?;
+
+    immediate
+        .polyline(up(itinerary.local_path(), 0.15), 1.0, false)
+        .color(color);
+    if let Some(p) = itinerary.get_point() {
+        immediate.line(p.up(0.18), pos.up(0.18), 1.0).color(color);
+    }
+
+    let r = itinerary.get_route()?;
+    for (i, l) in r.reversed_route.iter().enumerate() {
+        let Some(l) = l.raw_points(map) else {
+            continue;
+        };
+        if i == 0 {
+            // the last lane is only followed up to the destination
+            let to_cut = l.length() - l.length_at_proj(l.project(r.end_pos));
+            immediate
+                .polyline(up(l.cut(0.0, to_cut).as_slice(), 0.1), 3.0, false)
+                .color(color);
+            continue;
+        }
+        immediate
+            .polyline(up(l.as_slice(), 0.1), 3.0, false)
+            .color(color.a(color.a * 0.5));
+    }
+
+    let end_color = if itinerary.has_ended(0.0) {
+        color
+    } else {
+        LinearColor::MAGENTA
+    };
+    immediate.circle(r.end_pos.up(0.2), 1.0).color(end_color);
+    Some(())
+}
+
+fn draw_router_paths(
+    immediate: &mut ImmediateDraw,
+    color: LinearColor,
+    sim: &Simulation,
+    uiw: &UiWorld,
+) -> Option<()> {
+    let selected = uiw.read::<InspectedEntity>().e?;
+    draw_itinerary(immediate, color, sim, selected)
+}
+
+fn draw_followed_itinerary(
+    immediate: &mut ImmediateDraw,
+    color: LinearColor,
+    sim: &Simulation,
+    uiw: &UiWorld,
+) -> Option<()> {
+    let followed = uiw.read::<FollowEntity>().0?;
+    draw_itinerary(immediate, color, sim, followed)?;
+    let pos = sim.pos_any(followed)?;
+    immediate.stroke_circle(pos.up(0.3), 4.0, 0.5).color(color);
+    Some(())
+}
+
+fn draw_train_reservations(
+    immediate: &mut ImmediateDraw,
+    color: LinearColor,
+    sim: &Simulation,
+    uiw: &UiWorld,
+) -> Option<()> {
+    let reservs = sim.read::<TrainReservations>();
+    let map = sim.map();
+
+    for (inter, e) in &reservs.reservations {
+        let inter = unwrap_cont!(map.intersections().get(*inter));
+        immediate.circle(inter.pos.up(0.3), 3.0).color(color);
+
+        let p = unwrap_cont!(sim.pos(*e));
+        immediate
+            .line(inter.pos.up(0.5), p, 2.0)
+            .color(LinearColor::new(0.2, 0.2, 0.2, 1.0));
+    }
+
+    // intersections the inspected train looks at to brake in time
+    let selected = uiw.read::<InspectedEntity>().e?;
+    let t_id: TrainID = selected.try_into().ok()?;
+    let t = sim.world().trains.get(t_id)?;
+
+    let travers = t.it.get_travers()?;
+    let dist_to_next = travers
+        .kind
+        .length(map.lanes(), map.intersections())
+        .unwrap_or(0.0)
+        - t.res.cur_travers_dist;
+
+    let stop_dist = t.locomotive.braking_distance(t.speed.0, t.trans.dir.z);
+    for (v, _, _, _) in simulation::transportation::train::traverse_forward(
+        &map,
+        &t.it,
+        stop_dist + 15.0,
+        dist_to_next,
+        t.locomotive.length + 50.0,
+    ) {
+        let TraverseKind::Turn(turn) = v else {
+            continue;
+        };
+        let Some(inter) = map.intersections().get(turn.parent) else {
+            continue;
+        };
+        if inter.roads.len() <= 2 {
+            continue;
+        }
+        immediate
+            .stroke_circle(inter.pos.up(3.0), 3.5, 0.5)
+            .color(color);
+    }
+
+    Some(())
+}
+
+fn draw_lane_reservations(
+    immediate: &mut ImmediateDraw,
+    color: LinearColor,
+    sim: &Simulation,
+    _: &UiWorld,
+) -> Option<()> {
+    let reservs = sim.read::<TrainReservations>();
+    let map = sim.map();
+
+    for (id, poses) in &reservs.localisations {
+        let points = match id {
+            TraverseKind::Lane(lid) => &unwrap_cont!(map.lanes().get(*lid)).points,
+            TraverseKind::Turn(tid) => {
+                &unwrap_cont!(unwrap_cont!(map.intersections().get(tid.parent)).find_turn(*tid))
+                    .points
+            }
+        };
+
+        immediate
+            .polyline(up(points.as_slice(), 0.2), 1.0, false)
+            .color(color.a(color.a * 0.4));
+        for p in poses.values() {
+            let along = points.point_along(*p + points.length());
+            immediate.circle(along.up(0.3), 3.0).color(color);
+        }
+    }
+
+    Some(())
+}
+
+fn draw_spatial_grid(
+    immediate: &mut ImmediateDraw,
+    color: LinearColor,
+    sim: &Simulation,
+    _: &UiWorld,
+) -> Option<()> {
+    let map: &Map = &sim.map();
+    for r in map.spatial_map().debug_grid() {
+        let z = map.environment.height(r.center()).unwrap_or(0.0);
+        immediate.aabb(r, z).color(color);
+    }
+
+    Some(())
+}
+
+fn draw_geom_shapes(
+    immediate: &mut ImmediateDraw,
+    color: LinearColor,
+    sim: &Simulation,
+    _: &UiWorld,
+) -> Option<()> {
+    let map = sim.map();
+    unsafe {
+        for v in &*addr_of!(geom::DEBUG_POS) {
+            immediate.circle(*v, 1.0).color(LinearColor::RED);
+        }
+        for v in &*addr_of!(geom::DEBUG_OBBS) {
+            immediate
+                .obb(*v, map.environment.height(v.center()).unwrap_or(0.0) + 8.0)
+                .color(color);
+        }
+        for v in &*addr_of!(geom::DEBUG_SPLINES) {
+            immediate
+                .polyline(
+                    v.smart_points(1.0, 0.0, 1.0)
+                        .map(|x| x.z(10.0))
+                        .collect::<Vec<_>>(),
+                    5.0,
+                    false,
+                )
+                .color(color);
+        }
+    }
+
+    Some(())
+}
+
+/// Vehicles on a turn of an intersection and where they are
+fn turn_occupants(sim: &Simulation) -> impl Iterator<Item = (VehicleID, Vec3)> + '_ {
+    sim.world().vehicles.iter().filter_map(|(id, v)| {
+        let TraverseKind::Turn(_) = v.it.get_travers()?.kind else {
+            return None;
+        };
+        Some((id, v.trans.pos))
+    })
+}
+
+/// Same color for a vehicle every frame, different from its neighbours
+fn vehicle_color(id: VehicleID) -> Color {
+    let r = common::rand::randu(common::hash_u64(id) as u32);
+    Color::hsv(r * 360.0, 0.8, 0.9, 1.0)
+}
+
+fn draw_intersection_occupancy(
+    immediate: &mut ImmediateDraw,
+    color: LinearColor,
+    sim: &Simulation,
+    _: &UiWorld,
+) -> Option<()> {
+    let map = sim.map();
+
+    for (id, v) in sim.world().vehicles.iter() {
+        let travers = unwrap_cont!(v.it.get_travers());
+        let TraverseKind::Turn(tid) = travers.kind else {
+            continue;
+        };
+        let turn = unwrap_cont!(unwrap_cont!(map.intersections().get(tid.parent)).find_turn(tid));
+        let vcolor = LinearColor::from(vehicle_color(id));
+
+        immediate
+            .polyline(up(turn.points.as_slice(), 0.2), 1.5, false)
+            .color(vcolor.a(0.6));
+        immediate
+            .stroke_circle(v.trans.pos.up(0.3), 2.5, 0.4)
+            .color(color);
+        immediate.circle(v.trans.pos.up(0.35), 1.5).color(vcolor);
+    }
+
+    Some(())
+}
+
+fn draw_vehicle_waits(
+    immediate: &mut ImmediateDraw,
+    color: LinearColor,
+    sim: &Simulation,
+    _: &UiWorld,
+) -> Option<()> {
+    let deadlocks = sim.read::<Deadlocks>();
+    let vehicles = &sim.world().vehicles;
+
+    for (&waiter, &blocker) in &deadlocks.waits {
+        let from = unwrap_cont!(vehicles.get(waiter)).trans.pos;
+        let to = unwrap_cont!(vehicles.get(blocker)).trans.pos;
+        let (color, thickness) = if deadlocks.in_cycle(waiter) {
+            (LinearColor::RED, 1.0)
+        } else {
+            (color, 0.5)
+        };
+        immediate
+            .line(from.up(1.5), to.up(1.5), thickness)
+            .color(color);
+        immediate.circle(to.up(1.5), thickness).color(color);
+    }
+
+    Some(())
+}
@@ -6,8 +6,8 @@ use egui_inspect::{Inspect, InspectArgs};
 use simulation::economy::Market;
 use simulation::transportation::Location;
 use simulation::{
-    AnyEntity, CompanyEnt, FreightStationEnt, HumanEnt, Simulation, SoulID, TrainEnt, VehicleEnt,
-    WagonEnt,
+    AnyEntity, CompanyEnt, DockEnt, FreightStationEnt, HumanEnt, Simulation, SoulID, TrainEnt,
+    VehicleEnt, WagonEnt, WarehouseEnt,
 };
 
 use crate::newgui::follow::FollowEntity;
@@ -73,6 +73,12 @@ impl InspectRenderer {
                     &args,
                 )
             }
+            AnyEntity::DockID(x) => {
+                <DockEnt as Inspect<DockEnt>>::render(sim.get(x).unwrap(), "", ui, &args)
+            }
+            AnyEntity::WarehouseID(x) => {
+                <WarehouseEnt as Inspect<WarehouseEnt>>::render(sim.get(x).unwrap(), "", ui, &args)
+            }
             AnyEntity::CompanyID(x) => {
                 <CompanyEnt as Inspect<CompanyEnt>>::render(sim.get(x).unwrap(), "", ui, &args)
             }
@@ -1,23 +1,27 @@
 #![allow(clippy::type_complexity)]
 
 use crate::game_loop::Timings;
-use crate::newgui::{GuiState, InspectedEntity};
+use crate::gui::debug_channels::channels_ui;
+use crate::newgui::GuiState;
+use crate::rendering::StreamingStats;
 use crate::uiworld::UiWorld;
 use simulation::map_dynamic::ParkingManagement;
 use simulation::transportation::TransportGrid;
-use simulation::{Simulation, TrainID};
+use simulation::{Simulation, SimulationOptions};
 use std::time::{Duration, Instant};
 
 use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::windows::settings::Settings;
 use egui::{Context, Widget};
-use engine::{PerfCountersStatic, Tesselator};
-use geom::{Camera, Color, LinearColor, Spline3, Vec2};
+use engine::{
+    gpu_memory_largest, gpu_memory_total, gpu_memory_used, GpuCategory, PerfCountersStatic,
+    Tesselator,
+};
+use geom::{day_length, Camera, Color, LinearColor, Spline3, SunPosition, DAYS_PER_YEAR};
 use prototypes::{GameDuration, GameTime, SECONDS_PER_DAY};
 use simulation::map::{
-    IntersectionID, Map, MapSubscriber, NetworkObjectID, RoadSegmentKind, TraverseKind, UpdateType,
+    IntersectionID, Map, MapSubscriber, NetworkObjectID, RoadSegmentKind, UpdateType,
 };
-use simulation::transportation::train::TrainReservations;
 use simulation::world_command::WorldCommand;
 
 #[derive(Default)]
@@ -38,11 +42,8 @@ pub struct DebugObjs(
 impl Default for DebugObjs {
     fn default() -> Self {
         DebugObjs(vec![
-            (true, "Debug pathfinder", debug_pathfinder),
-            (false, "Debug train reservations", debug_trainreservations),
             (false, "Debug connectivity", debug_connectivity),
             (false, "Debug electricity", debug_electricity),
-            (false, "Debug spatialmap", debug_spatialmap),
             (false, "Debug transport grid", debug_transport_grid),
             (false, "Debug splines", debug_spline),
             (false, "Debug lots", debug_lots),
@@ -86,6 +87,53 @@ pub fn debug_window(ui: &Context, uiworld: &UiWorld, sim: &Simulation) {
     uiworld.write::<GuiState>().debug_window = opened;
 }
 
+/// Time taken by each render pass
+fn pass_times(ui: &mut egui::Ui, counters: &PerfCountersStatic) {
+    ui.label(if counters.pass_times_gpu {
+        "GPU time"
+    } else {
+        "CPU encoding time, no timestamp queries"
+    });
+    for (pass, ms) in &counters.pass_times {
+        ui.label(format!("{}: {:.2}ms", pass, ms));
+    }
+}
+
+/// GPU memory per category and the largest resources
+fn vram(ui: &mut egui::Ui, counters: &PerfCountersStatic) {
+    const MB: f64 = 1024.0 * 1024.0;
+    let total = gpu_memory_total();
+    ui.label(format!(
+        "{:.1}MB used out of a {:.0}MB budget",
+        total as f64 / MB,
+        counters.gpu_memory_budget as f64 / MB
+    ));
+    if counters.terrain_dropped_mips > 0 || counters.shadow_notches > 0 {
+        ui.label(format!(
+            "Lowered to fit: terrain textures -{} mips, shadows -{} notches",
+            counters.terrain_dropped_mips, counters.shadow_notches
+        ));
+    }
+    ui.add_space(5.0);
+    for category in GpuCategory::ALL {
+        ui.label(format!(
+            "{}: {:.1}MB",
+            category.label(),
+            gpu_memory_used(category) as f64 / MB
+        ));
+    }
+    ui.add_space(5.0);
+    ui.label("Largest resources:");
+    for (label, category, bytes) in gpu_memory_largest(10) {
+        ui.label(format!(
+            "{:.1}MB {} ({})",
+            bytes as f64 / MB,
+            label,
+            category.label()
+        ));
+    }
+}
+
 /// debug window for various debug options
 fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &Simulation) {
     window.show(ui, |ui| {
@@ -102,6 +150,9 @@ fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &
             "Debug fog shader",
         );
         drop(objs);
+        ui.separator();
+        channels_ui(ui, uiworld);
+        ui.separator();
 
         let time = *sim.read::<GameTime>();
         let daysecleft = SECONDS_PER_DAY - sim.read::<GameTime>().daytime.daysec();
@@ -143,6 +194,9 @@ fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &
 
         ui.label(format!("Tick: {}", time.tick));
 
+        #[cfg(feature = "multiplayer")]
+        network_stats(ui, uiworld);
+
         let mouse = uiworld.read::<InputMap>().unprojected;
         let cam = uiworld.read::<Camera>().pos;
 
@@ -188,6 +242,14 @@ fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &
         let counters = uiworld.read::<PerfCountersStatic>();
         ui.label(format!("{} drawcalls", counters.total_drawcalls));
         ui.label(format!("{}k triangles", counters.total_triangles / 1000));
+        ui.label(format!(
+            "{} drawcalls saved by the texture atlas",
+            counters.atlas_merged_drawcalls
+        ));
+        ui.label(format!(
+            "{} atlas pages, {} materials",
+            counters.atlas_pages, counters.atlas_materials
+        ));
         ui.add_space(5.0);
         ui.label(format!("{} depth drawcalls", counters.depth_drawcalls));
         ui.label(format!(
@@ -205,6 +267,15 @@ fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &
             "{}k heightmap triangles",
             counters.heightmap_triangles / 1000
         ));
+        if counters.heightmap_full_triangles > 0 {
+            ui.label(format!(
+                "{:.1}% saved by the heightmap LOD",
+                100.0
+                    * (1.0
+                        - counters.heightmap_triangles as f64
+                            / counters.heightmap_full_triangles as f64)
+            ));
+        }
         ui.label(format!(
             "{}k heightmap depth triangles",
             counters.heightmap_depth_triangles / 1000
@@ -213,8 +284,42 @@ fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &
             "{}k heightmap shadow triangles",
             counters.heightmap_shadows_triangles / 1000
         ));
+        ui.add_space(5.0);
+        ui.label(format!(
+            "{}/{} lights",
+            counters.lights_active, counters.lights_total
+        ));
+        ui.label(format!(
+            "Light culling: {:.2}ms",
+            counters.lights_cull_time * 1000.0
+        ));
+        ui.add_space(5.0);
+        ui.label(format!(
+            "{} screen targets in {} textures",
+            counters.screen_targets_logical, counters.screen_targets_physical
+        ));
+        ui.label(format!(
+            "{:.1}MB of VRAM saved by aliasing",
+            counters.screen_targets_saved_bytes as f64 / (1024.0 * 1024.0)
+        ));
+        ui.collapsing("VRAM", |ui| vram(ui, &counters));
+        ui.collapsing("Render passes", |ui| pass_times(ui, &counters));
         drop(counters);
 
+        let streaming = *uiworld.read::<StreamingStats>();
+        ui.add_space(5.0);
+        ui.label(format!(
+            "Resident chunks within {:.0}m: {} map, {} trees, {} props",
+            streaming.radius,
+            streaming.resident_mesh_chunks,
+            streaming.resident_tree_chunks,
+            streaming.resident_prop_chunks
+        ));
+        ui.label(format!(
+            "Build queue: {} map, {} trees, {} terrain",
+            streaming.mesh_queue, streaming.tree_queue, streaming.terrain_queue
+        ));
+
         if let Some(mouse) = mouse {
             ui.label(format!(
                 "World mouse pos: {:.1} {:.1} {:.2}",
@@ -232,6 +337,22 @@ fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &
                 .commands()
                 .push(WorldCommand::SpawnRandomCars { n_cars: 10 })
         }
+        if ui.small_button("Park one car of each model").clicked() {
+            uiworld
+                .commands()
+                .push(WorldCommand::SpawnCarModels { pos: cam })
+        }
+        if ui
+            .small_button("Break down the car nearest to the camera")
+            .clicked()
+        {
+            uiworld
+                .commands()
+                .push(WorldCommand::CreateIncident { pos: cam })
+        }
+        ui.separator();
+        ui.collapsing("Sun path", |ui| sun_path(ui, sim));
+        ui.collapsing("Prototypes from mods", prototype_provenance);
         ui.separator();
         let mut state = uiworld.write::<TestFieldProperties>();
 
@@ -275,6 +396,92 @@ fn debug(window: egui::Window<'_>, ui: &egui::Context, uiworld: &UiWorld, sim: &
     });
 }
 
+/// The prototypes the mods added or overrode, with the mod whose declaration was kept
+fn prototype_provenance(ui: &mut egui::Ui) {
+    let Some(p) = prototypes::try_prototypes() else {
+        return;
+    };
+    let mut any = false;
+    egui::Grid::new("prototype_provenance").show(ui, |ui| {
+        for (name, folder) in p.provenances().filter(|(_, f)| *f != "base_mod") {
+            ui.label(name);
+            ui.label(folder);
+            ui.end_row();
+            any = true;
+        }
+    });
+    if !any {
+        ui.label("Every prototype comes from base_mod");
+    }
+}
+
+/// The sun at noon over the year for the latitude of the map, the figure eight of the analemma
+fn sun_path(ui: &mut egui::Ui, sim: &Simulation) {
+    let opts = sim.read::<SimulationOptions>();
+    let latitude = opts.latitude.degrees();
+    let today = sim.read::<GameTime>().day_of_year(opts.season_days);
+
+    ui.label(format!(
+        "{}: {:.1}h of daylight today, {:.1}h to {:.1}h over the year",
+        opts.latitude.label(),
+        day_length(latitude, today),
+        day_length(latitude, 354.0),
+        day_length(latitude, 171.0),
+    ));
+
+    let noons: Vec<SunPosition> = (0..DAYS_PER_YEAR as u32)
+        .map(|day| SunPosition::compute(latitude, day as f32, 12.0))
+        .collect();
+    let today_noon = SunPosition::compute(latitude, today, 12.0);
+
+    let (response, painter) = ui.allocate_painter(egui::vec2(200.0, 200.0), egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+
+    // azimuth around the south horizontally, elevation vertically, both in degrees
+    let to_screen = |p: &SunPosition| {
+        let x = (p.azimuth.to_degrees() - 180.0) / 10.0;
+        let y = p.elevation.to_degrees() / 90.0;
+        egui::pos2(
+            rect.center().x + x * rect.width() * 0.5,
+            rect.bottom() - y * rect.height(),
+        )
+    };
+
+    let points: Vec<egui::Pos2> = noons.iter().map(to_screen).collect();
+    painter.add(egui::Shape::closed_line(
+        points,
+        egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 200, 80)),
+    ));
+    painter.circle_filled(to_screen(&today_noon), 4.0, egui::Color32::WHITE);
+    ui.label("Noon positions, 10° either side of the south and 0° to 90° high");
+}
+
+#[cfg(feature = "multiplayer")]
+fn network_stats(ui: &mut egui::Ui, uiworld: &UiWorld) {
+    let Some(stats) = uiworld.read::<crate::network::NetworkState>().stats() else {
+        return;
+    };
+    let (total, sec) = (stats.total, stats.last_second);
+    ui.label(format!(
+        "Inputs: {} frames, {:.1} bytes per frame before compression",
+        total.input_frames,
+        stats.avg_input_size()
+    ));
+    ui.label(format!(
+        "Sent: {:.1}kB/s in {} packets/s, {:.1}kB total",
+        sec.sent_bytes as f32 / 1000.0,
+        sec.sent_packets,
+        total.sent_bytes as f32 / 1000.0
+    ));
+    ui.label(format!(
+        "Received: {:.1}kB/s in {} packets/s, {:.1}kB total",
+        sec.received_bytes as f32 / 1000.0,
+        sec.received_packets,
+        total.received_bytes as f32 / 1000.0
+    ));
+}
+
 pub fn debug_spline(tess: &mut Tesselator, sim: &Simulation, _: &UiWorld) -> Option<()> {
     for road in sim.map().roads().values() {
         if let RoadSegmentKind::Curved((fr_dr, to_der)) = road.segment {
@@ -382,7 +589,7 @@ pub fn debug_connectivity(tess: &mut Tesselator, sim: &Simulation, uiw: &UiWorld
     let map = sim.map();
 
     if state.connectivity.0.is_none() {
-        state.connectivity.0 = Some(map.subscribe(UpdateType::Road));
+        state.connectivity.0 = Some(map.subscribe(UpdateType::RoadGeometry));
     }
     let sub = state.connectivity.0.as_mut().unwrap();
 
@@ -524,138 +731,6 @@ pub fn debug_parking(tess: &mut Tesselator, sim: &Simulation, _: &UiWorld) -> Op
     Some(())
 }
 
-pub fn debug_trainreservations(
-    tess: &mut Tesselator,
-    sim: &Simulation,
-    uiworld: &UiWorld,
-) -> Option<()> {
-    let reservs = sim.read::<TrainReservations>();
-    let map = sim.map();
-    tess.set_color(LinearColor::new(0.8, 0.3, 0.3, 1.0));
-    for (id, poses) in &reservs.localisations {
-        let points = match id {
-            TraverseKind::Lane(lid) => &unwrap_cont!(map.lanes().get(*lid)).points,
-            TraverseKind::Turn(tid) => {
-                &unwrap_cont!(map.intersections().get(tid.parent))
-                    .find_turn(*tid)?
-                    .points
-            }
-        };
-
-        for p in poses.values() {
-            let along = points.point_along(*p + points.length());
-            tess.draw_circle(along.up(0.3), 3.0);
-        }
-    }
-
-    for (inter, e) in &reservs.reservations {
-        tess.set_color(LinearColor::new(0.3, 0.8, 0.3, 1.0));
-        let inter = unwrap_cont!(map.intersections().get(*inter));
-        tess.draw_circle(inter.pos.up(0.3), 3.0);
-
-        let p = unwrap_cont!(sim.pos(*e));
-
-        tess.set_color(LinearColor::new(0.2, 0.2, 0.2, 1.0));
-        tess.draw_stroke(inter.pos.up(0.5), p, 2.0);
-    }
-    let selected = uiworld.read::<InspectedEntity>().e?;
-
-    let t_id: TrainID = selected.try_into().ok()?;
-    let t = sim.world().trains.get(t_id)?;
-
-    let travers = t.it.get_travers()?;
-    let dist_to_next = travers
-        .kind
-        .length(map.lanes(), map.intersections())
-        .unwrap_or(0.0)
-        - t.res.cur_travers_dist;
-
-    let stop_dist = t.speed.0 * t.speed.0 / (2.0 * t.locomotive.dec_force);
-    for (v, _, _, _) in simulation::transportation::train::traverse_forward(
-        &map,
-        &t.it,
-        stop_dist + 15.0,
-        dist_to_next,
-        t.locomotive.length + 50.0,
-    ) {
-        match v {
-            TraverseKind::Lane(_) => {}
-            TraverseKind::Turn(t) => {
-                if map
-                    .intersections()
-                    .get(t.parent)
-                    .map(|i| i.roads.len() <= 2)
-                    .unwrap_or(true)
-                {
-                    continue;
-                }
-                tess.draw_circle(map.intersections().get(t.parent)?.pos.up(3.0), 3.5);
-            }
-        }
-    }
-
-    Some(())
-}
-
-pub fn debug_pathfinder(tess: &mut Tesselator, sim: &Simulation, uiworld: &UiWorld) -> Option<()> {
-    let map: &Map = &sim.map();
-    let selected = uiworld.read::<InspectedEntity>().e?;
-    let pos = sim.pos_any(selected)?;
-
-    let itinerary = sim.world().it_any(selected)?;
-
-    tess.set_color(LinearColor::GREEN);
-    tess.draw_polyline(
-        &itinerary
-            .local_path()
-            .iter()
-            .map(|x| x.up(0.15))
-            .collect::<Vec<_>>(),
-        1.0,
-        false,
-    );
-
-    if let Some(p) = itinerary.get_point() {
-        tess.draw_stroke(p.up(0.18), pos.up(0.18), 1.0);
-    }
-
-    if let Some(r) = itinerary.get_route() {
-        tess.set_color(LinearColor::RED);
-        for (i, l) in r.reversed_route.iter().enumerate() {
-            if let Some(l) = l.raw_points(map) {
-                if i == 0 {
-                    tess.set_color(LinearColor::GREEN);
-                    let to_cut = l.length() - l.length_at_proj(l.project(r.end_pos));
-                    tess.draw_polyline(
-                        &l.cut(0.0, to_cut)
-                            .as_slice()
-                            .iter()
-                            .map(|x| x.up(0.1))
-                            .collect::<Vec<_>>(),
-                        3.0,
-                        false,
-                    );
-                    continue;
-                }
-                tess.set_color(LinearColor::RED);
-                tess.draw_polyline(
-                    &l.as_slice().iter().map(|x| x.up(0.1)).collect::<Vec<_>>(),
-                    3.0,
-                    false,
-                );
-            }
-        }
-        tess.set_color(if itinerary.has_ended(0.0) {
-            LinearColor::GREEN
-        } else {
-            LinearColor::MAGENTA
-        });
-
-        tess.draw_circle(r.end_pos.up(0.2), 1.0);
-    }
-    Some(())
-}
-
 /*
 pub fn debug_rays(tess: &mut Tesselator<true, sim: &Simulation, uiworld: &UiWorld) -> Option<()> {
     let time = sim.read::<GameTime>();
@@ -690,19 +765,3 @@ pub fn debug_rays(tess: &mut Tesselator<true, sim: &Simulation, uiworld: &UiWorl
 
     Some(())
 }*/
-
-pub fn debug_spatialmap(tess: &mut Tesselator, sim: &Simulation, _: &UiWorld) -> Option<()> {
-    let map: &Map = &sim.map();
-    for r in map.spatial_map().debug_grid() {
-        tess.set_color(LinearColor::BLUE.a(0.1));
-        tess.draw_rect_cos_sin(
-            r.center()
-                .z(map.environment.height(r.center()).unwrap_or(0.0)),
-            r.w(),
-            r.h(),
-            Vec2::X,
-        );
-    }
-
-    Some(())
-}
@@ -4,6 +4,7 @@ use prototypes::Money;
 use simulation::economy::Government;
 use simulation::Simulation;
 
+use crate::gui::debug_channels::channel_labels;
 use crate::gui::debug_inspect::debug_inspector;
 use crate::gui::debug_window::debug_window;
 use crate::newgui::{ErrorTooltip, GuiState, PotentialCommands};
@@ -20,6 +21,8 @@ pub fn render_oldgui(ui: &Context, uiworld: &UiWorld, sim: &Simulation) {
 
     debug_window(ui, uiworld, sim);
 
+    channel_labels(ui, uiworld, sim);
+
     tooltip(ui, uiworld, sim);
 }
 
@@ -42,17 +45,16 @@ pub fn tooltip(ui: &Context, uiworld: &UiWorld, sim: &Simulation) {
         return;
     }
     let pot = &mut uiworld.write::<PotentialCommands>().0;
-    let cost: Money = pot
-        .drain(..)
-        .map(|cmd| Government::action_cost(&cmd, sim))
-        .sum();
+    let cost: Money = pot.drain(..).map(|cmd| cmd.cost(sim)).sum();
 
     if cost == Money::ZERO {
         return;
     }
 
     egui::show_tooltip(ui, Id::new("tooltip_command_cost"), |ui| {
-        if cost > sim.read::<Government>().money {
+        if cost < Money::ZERO {
+            ui.colored_label(Color32::GREEN, format!("+{}", -cost));
+        } else if cost > sim.read::<Government>().money {
             ui.colored_label(Color32::RED, format!("{cost} too expensive"));
         } else {
             ui.label(cost.to_string());
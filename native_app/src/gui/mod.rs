@@ -1,3 +1,4 @@
+pub mod debug_channels;
 pub mod debug_inspect;
 pub mod debug_window;
 pub mod hud;
@@ -1,27 +1,54 @@
 use crate::game_loop::Timings;
+use crate::gui::debug_channels::DevSettings;
 use crate::gui::debug_window::{DebugObjs, DebugState, TestFieldProperties};
+use crate::input_arbiter::InputArbiter;
 use crate::inputmap::{Bindings, InputMap};
 use crate::network::NetworkState;
 use crate::newgui::addtrain::TrainSpawnResource;
 use crate::newgui::bulldozer::BulldozerState;
+use crate::newgui::camera_path::CameraPathPlayer;
 use crate::newgui::chat::GUIChatState;
+use crate::newgui::cinematic::CinematicDirector;
+use crate::newgui::commutes::CommuteView;
+use crate::newgui::deposits::DepositsView;
+use crate::newgui::districts::{DistrictPaintResource, DistrictStatsView};
 use crate::newgui::follow::FollowEntity;
+use crate::newgui::hover::HoverState;
+use crate::newgui::input_recording::InputRecorder;
+use crate::newgui::journal::Journal;
 use crate::newgui::keybinds::KeybindState;
 use crate::newgui::lotbrush::LotBrushResource;
+use crate::newgui::main_menu::{AppState, MainMenu};
+use crate::newgui::map_export::MapExporter;
+use crate::newgui::pause_menu::PauseMenu;
+use crate::newgui::restrictions::RestrictionPaintResource;
+use crate::newgui::road_condition::RoadConditionView;
 use crate::newgui::roadbuild::RoadBuildResource;
 use crate::newgui::roadeditor::RoadEditorResource;
+use crate::newgui::roadlayout::RoadLayoutResource;
+use crate::newgui::service_coverage::ServiceCoverageView;
+use crate::newgui::snapping::SnapSettings;
 use crate::newgui::specialbuilding::SpecialBuildingResource;
+use crate::newgui::supply_chain::SupplyChainView;
 use crate::newgui::terraforming::TerraformingResource;
-use crate::newgui::toolbox::building::BuildingIcons;
+use crate::newgui::tool_wheel::ToolWheelState;
+use crate::newgui::toolbox::building::{BuildMenu, BuildingIcons};
+use crate::newgui::trip_route::TripRoute;
+use crate::newgui::tutorial::TutorialState;
+use crate::newgui::walkability::WalkabilityView;
+use crate::newgui::windows::citizens::CitizensState;
+use crate::newgui::windows::diagnostics::DiagnosticsState;
 use crate::newgui::windows::economy::EconomyState;
+use crate::newgui::windows::event_log::EventLogState;
 use crate::newgui::windows::load::LoadState;
 use crate::newgui::windows::settings::{Settings, SettingsState};
 use crate::newgui::zoneedit::ZoneEditState;
 use crate::newgui::{
-    ErrorTooltip, ExitState, GuiState, InspectedBuilding, InspectedEntity, PotentialCommands,
-    TimeAlways, Tool,
+    ErrorTooltip, ExitState, GuiState, InspectedBuilding, InspectedDistrict, InspectedEntity,
+    PotentialCommands, TimeAlways, Tool,
 };
 use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
+use crate::rendering::{Interpolation, SimTimeScale, StreamingStats};
 use crate::uiworld::{ReceivedCommands, SaveLoadState, UiWorld};
 use common::saveload::Encoder;
 use serde::de::DeserializeOwned;
@@ -32,11 +59,16 @@ use simulation::world_command::WorldCommands;
 /// It is mostly to register types for serialization and initialization of the engine
 pub fn init() {
     simulation::init::init();
+    crate::gui::debug_channels::register_channels();
     register_resource::<Settings>("settings");
     #[cfg(feature = "multiplayer")]
     register_resource::<crate::newgui::windows::network::NetworkConnectionInfo>("netinfo");
     register_resource::<LotBrushResource>("lot_brush");
     register_resource::<Bindings>("bindings");
+    register_resource::<SnapSettings>("snap_settings");
+    register_resource::<prototypes::ModOrder>("mods");
+    register_resource::<DevSettings>("dev_settings");
+    register_resource::<Journal>("journal");
 
     register_resource_noserialize::<GuiState>();
     register_resource_noserialize::<TerraformingResource>();
@@ -46,31 +78,61 @@ pub fn init() {
     register_resource_noserialize::<ErrorTooltip>();
     register_resource_noserialize::<ExitState>();
     register_resource_noserialize::<FollowEntity>();
+    register_resource_noserialize::<TripRoute>();
+    register_resource_noserialize::<CinematicDirector>();
+    register_resource_noserialize::<CameraPathPlayer>();
+    register_resource_noserialize::<InputRecorder>();
+    register_resource_noserialize::<MapExporter>();
+    register_resource_noserialize::<HoverState>();
+    register_resource_noserialize::<DiagnosticsState>();
+    register_resource_noserialize::<StreamingStats>();
+    register_resource_noserialize::<SupplyChainView>();
+    register_resource_noserialize::<CommuteView>();
+    register_resource_noserialize::<DistrictPaintResource>();
+    register_resource_noserialize::<RestrictionPaintResource>();
+    register_resource_noserialize::<DistrictStatsView>();
+    register_resource_noserialize::<RoadConditionView>();
+    register_resource_noserialize::<ServiceCoverageView>();
+    register_resource_noserialize::<WalkabilityView>();
+    register_resource_noserialize::<SimTimeScale>();
+    register_resource_noserialize::<DepositsView>();
     register_resource_noserialize::<GUIChatState>();
     register_resource_noserialize::<TimeAlways>();
     register_resource_noserialize::<ImmediateDraw>();
     register_resource_noserialize::<ImmediateSound>();
+    register_resource_noserialize::<Interpolation>();
     register_resource_noserialize::<InputMap>();
+    register_resource_noserialize::<InputArbiter>();
     register_resource_noserialize::<InspectedEntity>();
     register_resource_noserialize::<InspectedBuilding>();
+    register_resource_noserialize::<InspectedDistrict>();
     register_resource_noserialize::<NetworkState>();
     register_resource_noserialize::<PotentialCommands>();
     register_resource_noserialize::<ZoneEditState>();
     register_resource_noserialize::<TestFieldProperties>();
     register_resource_noserialize::<ReceivedCommands>();
     register_resource_noserialize::<RoadBuildResource>();
+    register_resource_noserialize::<RoadLayoutResource>();
     register_resource_noserialize::<RoadEditorResource>();
     register_resource_noserialize::<SpecialBuildingResource>();
     register_resource_noserialize::<TrainSpawnResource>();
     register_resource_noserialize::<Timings>();
     register_resource_noserialize::<Tool>();
+    register_resource_noserialize::<ToolWheelState>();
     register_resource_noserialize::<WorldCommands>();
     register_resource_noserialize::<LoadState>();
+    register_resource_noserialize::<EventLogState>();
+    register_resource_noserialize::<CitizensState>();
     register_resource_noserialize::<SaveLoadState>();
     register_resource_noserialize::<EconomyState>();
     register_resource_noserialize::<SettingsState>();
     register_resource_noserialize::<BuildingIcons>();
+    register_resource_noserialize::<BuildMenu>();
     register_resource_noserialize::<KeybindState>();
+    register_resource_noserialize::<PauseMenu>();
+    register_resource_noserialize::<AppState>();
+    register_resource_noserialize::<MainMenu>();
+    register_resource_noserialize::<TutorialState>();
 }
 
 pub struct InitFunc {
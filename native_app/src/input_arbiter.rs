@@ -0,0 +1,205 @@
+use engine::InputContext;
+use geom::{vec2, AABB};
+
+/// What the interface claimed during the last frame, reported by egui and the goryak panels
+#[derive(Default)]
+pub struct UiClaims {
+    /// Screen areas covered by the interface, in physical pixels
+    pub regions: Vec<AABB>,
+    /// A widget took the last mouse press or scroll, or egui wants the mouse
+    pub pointer: bool,
+    /// A text field has the keyboard focus
+    pub keyboard: bool,
+}
+
+impl UiClaims {
+    /// The areas of the goryak panels painted during the last frame.
+    /// yakui lays out in logical pixels, scaled by its zoom factor.
+    pub fn take_yakui_regions(&mut self, zoom_factor: f32) {
+        self.regions
+            .extend(goryak::take_captured_regions().into_iter().map(|r| {
+                let ll = vec2(r.pos().x, r.pos().y) * zoom_factor;
+                let size = vec2(r.size().x, r.size().y) * zoom_factor;
+                AABB::new_ll_ur(ll, ll + size)
+            }));
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PointerOwner {
+    /// The tools and the camera
+    World,
+    Ui,
+}
+
+/// Who the inputs of this frame go to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Dispatch {
+    pub pointer: bool,
+    pub keyboard: bool,
+}
+
+/// Decides each frame whether the mouse and the keyboard drive the world or stay in the interface.
+/// The mouse belongs to the interface when the cursor is over an area it claimed. A drag keeps
+/// the owner it had when the first button was pressed until every button is released, so a
+/// camera drag crossing a window goes on and a click on a window never selects what is behind.
+#[derive(Default)]
+pub struct InputArbiter {
+    claims: UiClaims,
+    drag: Option<PointerOwner>,
+}
+
+impl InputArbiter {
+    /// Replaces the claims used by the next arbitration
+    pub fn set_claims(&mut self, claims: UiClaims) {
+        self.claims = claims;
+    }
+
+    pub fn arbitrate(&mut self, input: &InputContext) -> Dispatch {
+        let cursor = input.mouse.screen;
+        let over_ui = self.claims.pointer || self.claims.regions.iter().any(|r| r.contains(cursor));
+        let hovered = if over_ui {
+            PointerOwner::Ui
+        } else {
+            PointerOwner::World
+        };
+
+        let owner = if input.mouse.pressed.is_empty() {
+            self.drag = None;
+            hovered
+        } else {
+            *self.drag.get_or_insert(hovered)
+        };
+
+        Dispatch {
+            pointer: owner == PointerOwner::World,
+            keyboard: !self.claims.keyboard,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use engine::{InputContext, MouseButton};
+    use geom::{vec2, Vec2, AABB};
+
+    use crate::inputmap::{Bindings, InputAction, InputMap};
+
+    use super::{InputArbiter, PointerOwner, UiClaims};
+
+    /// A window in the top left corner of the screen
+    fn window() -> UiClaims {
+        UiClaims {
+            regions: vec![AABB::new_ll_ur(Vec2::ZERO, vec2(300.0, 300.0))],
+            ..Default::default()
+        }
+    }
+
+    struct Session {
+        arbiter: InputArbiter,
+        map: InputMap,
+        input: InputContext,
+    }
+
+    impl Session {
+        fn new() -> Self {
+            let mut map = InputMap::default();
+            map.build_input_tree(&mut Bindings::default());
+            Self {
+                arbiter: InputArbiter::default(),
+                map,
+                input: InputContext::default(),
+            }
+        }
+
+        /// Runs a frame like the game loop does, with the events already in the InputContext
+        fn frame(&mut self, claims: UiClaims) -> &InputMap {
+            self.arbiter.set_claims(claims);
+            let dispatch = self.arbiter.arbitrate(&self.input);
+            self.map
+                .prepare_frame(&self.input, dispatch.keyboard, dispatch.pointer, None);
+            self.input.end_frame();
+            &self.map
+        }
+
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.input.mouse.screen = vec2(x, y);
+        }
+
+        fn press(&mut self, b: MouseButton) {
+            self.input.mouse.pressed.insert(b);
+        }
+
+        fn release(&mut self, b: MouseButton) {
+            self.input.mouse.pressed.remove(&b);
+        }
+    }
+
+    #[test]
+    fn clicks_on_a_window_do_not_reach_the_map() {
+        let mut s = Session::new();
+
+        s.move_to(100.0, 100.0);
+        s.press(MouseButton::Left);
+        assert!(!s.frame(window()).just_act.contains(&InputAction::Select));
+        s.release(MouseButton::Left);
+        s.frame(window());
+
+        s.move_to(500.0, 100.0);
+        s.press(MouseButton::Left);
+        assert!(s.frame(window()).just_act.contains(&InputAction::Select));
+        s.release(MouseButton::Left);
+        s.frame(window());
+
+        // a button outside the panels that took the press
+        s.press(MouseButton::Left);
+        let claimed = UiClaims {
+            pointer: true,
+            ..window()
+        };
+        assert!(!s.frame(claimed).just_act.contains(&InputAction::Select));
+    }
+
+    #[test]
+    fn scrolling_over_a_list_does_not_zoom() {
+        let mut s = Session::new();
+
+        s.move_to(100.0, 100.0);
+        s.input.mouse.wheel_delta = 10.0;
+        assert!(!s.frame(window()).act.contains(&InputAction::Zoom));
+
+        s.move_to(500.0, 100.0);
+        s.input.mouse.wheel_delta = 10.0;
+        assert!(s.frame(window()).act.contains(&InputAction::Zoom));
+        assert!(!s.frame(window()).act.contains(&InputAction::Zoom));
+    }
+
+    #[test]
+    fn drags_keep_their_owner_across_windows() {
+        let mut s = Session::new();
+
+        // a camera rotation started on the map goes on over the window
+        s.move_to(500.0, 100.0);
+        s.press(MouseButton::Right);
+        assert!(s.frame(window()).act.contains(&InputAction::CameraRotate));
+        s.move_to(100.0, 100.0);
+        assert!(s.frame(window()).act.contains(&InputAction::CameraRotate));
+        assert_eq!(s.arbiter.drag, Some(PointerOwner::World));
+
+        // and ends when released over it
+        s.release(MouseButton::Right);
+        assert!(s.frame(window()).act.is_empty());
+        assert_eq!(s.arbiter.drag, None);
+
+        // dragging a window onto the map does not rotate the camera
+        s.press(MouseButton::Right);
+        s.frame(window());
+        s.move_to(500.0, 100.0);
+        assert!(!s.frame(window()).act.contains(&InputAction::CameraRotate));
+        s.release(MouseButton::Right);
+        s.frame(window());
+
+        s.press(MouseButton::Right);
+        assert!(s.frame(window()).act.contains(&InputAction::CameraRotate));
+    }
+}
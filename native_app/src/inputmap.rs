@@ -1,13 +1,14 @@
-use common::{FastMap, FastSet};
-use engine::{InputContext, Key, MouseButton};
+use common::FastSet;
+use engine::{GamepadButton, InputContext, Key, MouseButton};
 use geom::{Ray3, Vec2, Vec3};
-use serde::{Deserialize, Serialize};
-use std::collections::hash_map::Entry;
-use std::collections::{BTreeMap, HashSet};
+use serde::de::value::StrDeserializer;
+use serde::de::{DeserializeOwned, IntoDeserializer};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 use std::fmt::{Debug, Display, Formatter};
 
 // Either combinations can work
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct InputCombinations(pub Vec<InputCombination>);
 
 #[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
@@ -17,6 +18,7 @@ pub enum UnitInput {
     Mouse(MouseButton),
     WheelUp,
     WheelDown,
+    Gamepad(GamepadButton),
 }
 
 #[derive(Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
@@ -41,18 +43,78 @@ pub enum InputAction {
     DownElevation,
     OpenEconomyMenu,
     OpenDebugMenu,
+    ReloadPrototypes,
     PausePlay,
     OpenChat,
+    OpenToolWheel,
+    ToggleCinematic,
+}
+
+/// Input contexts, each with its own binding set.
+/// Active layers are matched in declaration order, so a layer consumes inputs before the ones below it.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum InputLayer {
+    /// Active while a text field has the focus, keeps the keyboard for itself
+    TextInput,
+    RoadTool,
+    BuildingTool,
+    BrushTool,
+    Camera,
+    Global,
+}
+
+impl InputLayer {
+    /// Keyboard inputs never go past this layer
+    pub fn blocks_keyboard(self) -> bool {
+        matches!(self, InputLayer::TextInput)
+    }
 }
 
 // All unit inputs need to match
 #[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
 pub struct InputCombination(Vec<UnitInput>);
 
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+struct Modifiers(u8);
+
+impl Modifiers {
+    fn from_units<'a>(units: impl IntoIterator<Item = &'a UnitInput>) -> Self {
+        let mut m = 0;
+        for u in units {
+            m |= match u {
+                Key(Key::Control) => 1,
+                Key(Key::Shift) => 2,
+                Key(Key::Alt) => 4,
+                _ => 0,
+            };
+        }
+        Self(m)
+    }
+
+    fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+}
+
+/// A binding compiled for matching: the modifiers that must be held and the primary input.
+/// Modifier-only chords (like holding Ctrl) have no primary.
+struct Chord {
+    mods: Modifiers,
+    primary: Option<UnitInput>,
+    action: InputAction,
+}
+
 #[derive(Default)]
-struct InputTree {
-    actions: Vec<InputAction>,
-    childs: FastMap<UnitInput, Box<InputTree>>,
+struct ChordTable {
+    layers: BTreeMap<InputLayer, Vec<Chord>>,
 }
 
 #[derive(Default)]
@@ -71,56 +133,125 @@ pub struct InputMap {
     pub ray: Option<Ray3>,
     /// Mouse position in screen space
     pub screen: Vec2,
-    input_tree: InputTree,
+    /// Left stick after dead zone and curve, pans the camera
+    pub pan: Vec2,
+    /// Right stick after dead zone and curve, rotates the camera or picks in the tool wheel
+    pub look: Vec2,
+    /// Set by widgets owning the keyboard focus, activates the text input layer next frame
+    pub text_input: bool,
+    chords: ChordTable,
+}
+
+#[derive(Serialize)]
+pub struct Bindings(pub BTreeMap<InputLayer, BTreeMap<InputAction, InputCombinations>>);
+
+impl<'de> Deserialize<'de> for Bindings {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        /// Names are kept as strings so the layers and actions that no longer exist are skipped
+        /// instead of failing the whole file
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Saved {
+            Layered(BTreeMap<String, BTreeMap<String, InputCombinations>>),
+            /// Saved before the layers, every action in one map
+            Flat(BTreeMap<String, InputCombinations>),
+        }
+
+        Ok(match Saved::deserialize(d)? {
+            Saved::Layered(m) => {
+                let mut layers = BTreeMap::new();
+                for (layer, acts) in m {
+                    let Some(layer) = from_name::<InputLayer>(&layer) else {
+                        log::warn!("ignoring bindings of unknown input layer {}", layer);
+                        continue;
+                    };
+                    layers.insert(layer, known_actions(acts));
+                }
+                Bindings(layers)
+            }
+            Saved::Flat(m) => Bindings::from_flat(known_actions(m)),
+        })
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct Bindings(pub BTreeMap<InputAction, InputCombinations>);
+fn from_name<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let d: StrDeserializer<'_, serde::de::value::Error> = name.into_deserializer();
+    T::deserialize(d).ok()
+}
 
+fn known_actions(
+    acts: BTreeMap<String, InputCombinations>,
+) -> BTreeMap<InputAction, InputCombinations> {
+    acts.into_iter()
+        .filter_map(|(name, comb)| {
+            let act = from_name::<InputAction>(&name);
+            if act.is_none() {
+                log::warn!("ignoring binding of unknown action {}", name);
+            }
+            Some((act?, comb))
+        })
+        .collect()
+}
+
+use GamepadButton as G;
 use InputAction::*;
+use InputLayer::*;
 use Key as K;
 use MouseButton::*;
 use UnitInput::*;
 
 // https://stackoverflow.com/a/38068969/5000800 for key scans
 #[rustfmt::skip]
-const DEFAULT_BINDINGS: &[(InputAction, &[&[UnitInput]])] = &[
-    (GoForward,       &[&[KeyScan(17)], &[Key(K::ArrowUp)]]),
-    (GoBackward,      &[&[KeyScan(31)], &[Key(K::ArrowDown)]]),
-    (GoLeft,          &[&[KeyScan(30)], &[Key(K::ArrowLeft)]]),
-    (GoRight,         &[&[KeyScan(32)], &[Key(K::ArrowRight)]]),
-    (CameraRotate,    &[&[Mouse(Right)]]),
-    (CameraMove,      &[&[Key(K::Shift), Mouse(Right)], &[Mouse(Middle)]]),
-    (Zoom,            &[&[Key(K::c("+"))], &[WheelUp]]),
-    (Dezoom,          &[&[Key(K::c("-"))], &[WheelDown]]),
-    (Rotate,          &[&[Key(K::Control), WheelUp], &[Key(K::Control), WheelDown]]),
-    (SizeUp,          &[&[Key(K::Control), WheelUp]]),
-    (SizeDown,        &[&[Key(K::Control), WheelDown]]),
-    (Close,           &[&[Key(K::Escape)]]),
-    (Select,          &[&[Mouse(Left)]]),
-    (SecondarySelect, &[&[Key(K::Control), Mouse(Left)]]),
-    (NoSnapping,      &[&[Key(K::Control)]]),
-    (HideInterface,   &[&[Key(K::c("H"))]]),
-    (UpElevation,     &[&[Key(K::Control), WheelUp]]),
-    (DownElevation,   &[&[Key(K::Control), WheelDown]]),
-    (OpenEconomyMenu, &[&[Key(K::c("E"))]]),
-    (OpenDebugMenu,   &[&[Key(K::F3)]]),
-    (PausePlay,       &[&[Key(K::Space)]]),
-    (OpenChat,        &[&[Key(K::c("T"))]]),
+const DEFAULT_BINDINGS: &[(InputLayer, InputAction, &[&[UnitInput]])] = &[
+    (TextInput,    Close,           &[&[Key(K::Escape)]]),
+
+    (RoadTool,     NoSnapping,      &[&[Key(K::Control)], &[Gamepad(G::North)]]),
+    (RoadTool,     UpElevation,     &[&[Key(K::Control), WheelUp], &[Gamepad(G::DPadUp)]]),
+    (RoadTool,     DownElevation,   &[&[Key(K::Control), WheelDown], &[Gamepad(G::DPadDown)]]),
+
+    (BuildingTool, NoSnapping,      &[&[Key(K::Control)], &[Gamepad(G::North)]]),
+    (BuildingTool, Rotate,          &[&[Key(K::Control), WheelUp], &[Key(K::Control), WheelDown]]),
+
+    (BrushTool,    SizeUp,          &[&[Key(K::Control), WheelUp]]),
+    (BrushTool,    SizeDown,        &[&[Key(K::Control), WheelDown]]),
+
+    (Camera,       GoForward,       &[&[KeyScan(17)], &[Key(K::ArrowUp)]]),
+    (Camera,       GoBackward,      &[&[KeyScan(31)], &[Key(K::ArrowDown)]]),
+    (Camera,       GoLeft,          &[&[KeyScan(30)], &[Key(K::ArrowLeft)]]),
+    (Camera,       GoRight,         &[&[KeyScan(32)], &[Key(K::ArrowRight)]]),
+    (Camera,       CameraRotate,    &[&[Mouse(Right)], &[Gamepad(G::RightBumper)]]),
+    (Camera,       CameraMove,      &[&[Key(K::Shift), Mouse(Right)], &[Mouse(Middle)]]),
+    (Camera,       Zoom,            &[&[Key(K::c("+"))], &[WheelUp], &[Gamepad(G::RightTrigger)]]),
+    (Camera,       Dezoom,          &[&[Key(K::c("-"))], &[WheelDown], &[Gamepad(G::LeftTrigger)]]),
+
+    (Global,       Close,           &[&[Key(K::Escape)], &[Gamepad(G::East)]]),
+    (Global,       Select,          &[&[Mouse(Left)], &[Gamepad(G::South)]]),
+    (Global,       SecondarySelect, &[&[Key(K::Control), Mouse(Left)], &[Gamepad(G::West)]]),
+    (Global,       HideInterface,   &[&[Key(K::c("H"))]]),
+    (Global,       OpenEconomyMenu, &[&[Key(K::c("E"))], &[Gamepad(G::Select)]]),
+    (Global,       OpenDebugMenu,   &[&[Key(K::F3)]]),
+    (Global,       ReloadPrototypes, &[&[Key(K::F5)]]),
+    (Global,       PausePlay,       &[&[Key(K::Space)], &[Gamepad(G::Start)]]),
+    (Global,       OpenChat,        &[&[Key(K::c("T"))]]),
+    (Global,       OpenToolWheel,   &[&[Key(K::Tab)], &[Gamepad(G::LeftBumper)]]),
+    (Global,       ToggleCinematic, &[&[Key(K::c("C"))]]),
 ];
 
 impl Default for Bindings {
     fn default() -> Self {
-        let mut m = BTreeMap::default();
-
-        for (k, v) in DEFAULT_BINDINGS {
-            if m.insert(
-                k.clone(),
-                InputCombinations(v.iter().map(|&x| InputCombination(x.to_vec())).collect()),
-            )
-            .is_some()
+        let mut m: BTreeMap<InputLayer, BTreeMap<InputAction, InputCombinations>> =
+            BTreeMap::default();
+
+        for (layer, k, v) in DEFAULT_BINDINGS {
+            if m.entry(*layer)
+                .or_default()
+                .insert(
+                    k.clone(),
+                    InputCombinations(v.iter().map(|&x| InputCombination(x.to_vec())).collect()),
+                )
+                .is_some()
             {
-                log::error!("inserting same action twice!");
+                log::error!("inserting same action twice in layer {:?}!", layer);
             }
         }
 
@@ -128,34 +259,94 @@ impl Default for Bindings {
     }
 }
 
+impl Bindings {
+    /// Puts each action in the layers it has by default, the actions that no longer exist are
+    /// dropped
+    fn from_flat(flat: BTreeMap<InputAction, InputCombinations>) -> Self {
+        let mut m: BTreeMap<InputLayer, BTreeMap<InputAction, InputCombinations>> =
+            BTreeMap::default();
+        for (layer, act, _) in DEFAULT_BINDINGS {
+            if let Some(comb) = flat.get(act) {
+                m.entry(*layer)
+                    .or_default()
+                    .insert(act.clone(), comb.clone());
+            }
+        }
+        Bindings(m)
+    }
+
+    /// Drops the actions that no longer exist and adds the missing ones from the defaults
+    pub fn merge_defaults(&mut self) {
+        let default_bindings = Bindings::default();
+        self.0.retain(|layer, acts| {
+            let Some(default_acts) = default_bindings.0.get(layer) else {
+                return false;
+            };
+            acts.retain(|act, _| default_acts.contains_key(act));
+            true
+        });
+        for (layer, acts) in default_bindings.0 {
+            let cur = self.0.entry(layer).or_default();
+            for (act, comb) in acts {
+                cur.entry(act).or_insert(comb);
+            }
+        }
+    }
+
+    pub fn get_mut(
+        &mut self,
+        layer: InputLayer,
+        action: &InputAction,
+    ) -> Option<&mut InputCombinations> {
+        self.0.get_mut(&layer)?.get_mut(action)
+    }
+}
+
 impl InputMap {
     pub fn build_input_tree(&mut self, bindings: &mut Bindings) {
-        for v in &mut bindings.0.values_mut() {
+        for v in bindings.0.values_mut().flat_map(|acts| acts.values_mut()) {
             for x in &mut v.0 {
                 x.0.sort()
             }
         }
-        self.input_tree = InputTree::new(bindings);
+        self.chords = ChordTable::new(bindings);
     }
 
-    pub fn prepare_frame(&mut self, input: &InputContext, kb: bool, mouse: bool) {
+    /// Matches the held inputs against the active layers, `tool_layer` being the one declared by the current tool
+    pub fn prepare_frame(
+        &mut self,
+        input: &InputContext,
+        kb: bool,
+        mouse: bool,
+        tool_layer: Option<InputLayer>,
+    ) {
         self.just_act.clear();
-        let empty1 = FastSet::default();
-        let empty2 = FastSet::default();
-        let empty3 = FastSet::default();
-        let mut acts: FastSet<_> = self
-            .input_tree
-            .query(
-                if kb { &input.keyboard.pressed } else { &empty1 },
-                if kb {
-                    &input.keyboard.pressed_scancode
-                } else {
-                    &empty2
-                },
-                if mouse { &input.mouse.pressed } else { &empty3 },
-                if mouse { input.mouse.wheel_delta } else { 0.0 },
-            )
-            .collect();
+
+        let mut held: FastSet<UnitInput> = FastSet::default();
+        if kb {
+            held.extend(input.keyboard.pressed.iter().map(|x| Key(x.clone())));
+            held.extend(input.keyboard.pressed_scancode.iter().map(|x| KeyScan(*x)));
+        }
+        if mouse {
+            held.extend(input.mouse.pressed.iter().map(|x| Mouse(*x)));
+            if input.mouse.wheel_delta > 0.0 {
+                held.insert(WheelUp);
+            }
+            if input.mouse.wheel_delta < 0.0 {
+                held.insert(WheelDown);
+            }
+        }
+        held.extend(input.gamepad.pressed.iter().map(|x| Gamepad(*x)));
+
+        let mut layers = Vec::with_capacity(4);
+        if self.text_input {
+            layers.push(TextInput);
+        }
+        layers.extend(tool_layer);
+        layers.push(Camera);
+        layers.push(Global);
+
+        let mut acts: FastSet<_> = self.chords.query(&layers, &held).collect();
         std::mem::swap(&mut self.act, &mut acts);
         for v in &self.act {
             if !acts.contains(v) {
@@ -163,7 +354,19 @@ impl InputMap {
             }
         }
         self.screen = input.mouse.screen;
-        self.wheel = input.mouse.wheel_delta;
+        self.wheel = if mouse { input.mouse.wheel_delta } else { 0.0 };
+        self.pan = input.gamepad.left();
+        self.look = input.gamepad.right();
+    }
+}
+
+impl UnitInput {
+    pub fn is_modifier(&self) -> bool {
+        matches!(self, Key(k) if k.is_modifier())
+    }
+
+    pub fn is_keyboard(&self) -> bool {
+        matches!(self, Key(_) | KeyScan(_))
     }
 }
 
@@ -181,7 +384,7 @@ impl InputCombination {
             match x {
                 // ignore modifiers
                 Key(k) if k.is_modifier() => {}
-                Key(_) | Mouse(_) | KeyScan(_) | WheelDown | WheelUp => {
+                Key(_) | Mouse(_) | KeyScan(_) | WheelDown | WheelUp | Gamepad(_) => {
                     if has_primary {
                         has_more_than_one_primary = true;
                     }
@@ -254,6 +457,7 @@ impl Display for UnitInput {
                 write!(f, "Scroll Down")
             }
             KeyScan(scan) => write!(f, "ScanCode({scan})"),
+            Gamepad(b) => write!(f, "Pad {b:?}"),
         }
     }
 }
@@ -282,87 +486,89 @@ impl Debug for InputAction {
     }
 }
 
-impl InputTree {
-    pub fn new(bindings: &Bindings) -> Self {
-        let mut root = Self {
-            actions: Vec::new(),
-            childs: Default::default(),
-        };
-
-        for (act, combs) in &bindings.0 {
-            for comb in &combs.0 {
-                let mut cur: &mut InputTree = &mut root;
-                for inp in &comb.0 {
-                    let ent = cur.childs.entry(inp.clone());
-                    match ent {
-                        Entry::Occupied(_) => {}
-                        Entry::Vacant(v) => {
-                            v.insert(Box::new(InputTree {
-                                actions: Vec::new(),
-                                childs: Default::default(),
-                            }));
-                        }
+impl ChordTable {
+    fn new(bindings: &Bindings) -> Self {
+        let mut layers: BTreeMap<InputLayer, Vec<Chord>> = BTreeMap::new();
+
+        for (layer, acts) in &bindings.0 {
+            let chords = layers.entry(*layer).or_default();
+            for (act, combs) in acts {
+                for comb in &combs.0 {
+                    if !comb.is_valid() && !comb.is_modifiers_only() {
+                        log::warn!("ignoring invalid binding {} for {}", comb, act);
+                        continue;
                     }
-                    cur = &mut **cur.childs.get_mut(inp).unwrap();
+                    chords.push(Chord {
+                        mods: Modifiers::from_units(&comb.0),
+                        primary: comb.0.iter().find(|x| !x.is_modifier()).cloned(),
+                        action: act.clone(),
+                    });
                 }
-                cur.actions.push(act.clone());
             }
         }
 
-        root
+        Self { layers }
     }
 
-    pub fn query(
-        &self,
-        kb: &FastSet<Key>,
-        kb_scans: &FastSet<u32>,
-        mouse: &FastSet<MouseButton>,
-        wheel: f32,
-    ) -> impl Iterator<Item = InputAction> + '_ {
-        let mut units: HashSet<UnitInput> = HashSet::with_capacity(kb.len() + mouse.len() + 1);
-
-        units.extend(kb.iter().map(|x| UnitInput::Key(x.clone())));
-        units.extend(mouse.iter().map(|x| Mouse(*x)));
-        units.extend(kb_scans.iter().map(|x| KeyScan(*x)));
-        if wheel > 0.0 {
-            units.insert(WheelUp);
-        }
-        if wheel < 0.0 {
-            units.insert(WheelDown);
-        }
+    /// Layers are tried in the given order. For each held input, the chord with the most modifiers
+    /// wins and consumes it, so Ctrl+Z does not also trigger Z.
+    /// A modifier-only chord (like holding Ctrl) matches if no chord of its layer or above used
+    /// its modifiers, and then hides them from the layers below.
+    fn query<'a>(
+        &'a self,
+        active: &[InputLayer],
+        held: &FastSet<UnitInput>,
+    ) -> impl Iterator<Item = InputAction> + 'a {
+        let mods = Modifiers::from_units(held);
+        let primaries: Vec<&UnitInput> = held.iter().filter(|x| !x.is_modifier()).collect();
+
+        let mut consumed: FastSet<&UnitInput> = FastSet::default();
+        let mut used_mods = Modifiers::default();
+        let mut claimed_mods = Modifiers::default();
+        let mut matches: Vec<&'a InputAction> = vec![];
+
+        for layer in active {
+            let Some(chords) = self.layers.get(layer) else {
+                continue;
+            };
+            let available = Modifiers(mods.0 & !claimed_mods.0);
+
+            for &p in &primaries {
+                if consumed.contains(p) {
+                    continue;
+                }
+                let candidates: Vec<&'a Chord> = chords
+                    .iter()
+                    .filter(|c| c.primary.as_ref() == Some(p) && available.contains(c.mods))
+                    .collect();
+                let Some(best) = candidates.iter().map(|c| c.mods.count()).max() else {
+                    continue;
+                };
+                for c in candidates.into_iter().filter(|c| c.mods.count() == best) {
+                    matches.push(&c.action);
+                    used_mods.0 |= c.mods.0;
+                }
+                consumed.insert(p);
+            }
 
-        let mut matches = vec![];
-        let mut queue = vec![(vec![], self)];
-
-        while !queue.is_empty() {
-            for (input_stack, q) in std::mem::take(&mut queue) {
-                for key in units.iter().cloned() {
-                    if let Some(inp) = q.childs.get(&key) {
-                        let mut newstack = input_stack.clone();
-                        newstack.push(key);
-                        if !inp.actions.is_empty() {
-                            matches.push((newstack.clone(), &inp.actions));
-                        }
-                        queue.push((newstack, &**inp));
-                    }
+            for c in chords {
+                if c.primary.is_none()
+                    && c.mods != Modifiers::default()
+                    && available.contains(c.mods)
+                    && !used_mods.intersects(c.mods)
+                {
+                    matches.push(&c.action);
+                    claimed_mods.0 |= c.mods.0;
                 }
             }
+
+            if layer.blocks_keyboard() {
+                consumed.extend(primaries.iter().copied().filter(|x| x.is_keyboard()));
+                claimed_mods = mods;
+            }
         }
 
-        matches
-            .into_iter()
-            .rev()
-            .filter_map(move |(inp, act)| {
-                if !inp.iter().all(|x| units.contains(x)) {
-                    return None;
-                }
-                for v in inp {
-                    units.remove(&v);
-                }
-                Some(act)
-            })
-            .flatten()
-            .cloned()
+        matches.into_iter().cloned()
     }
 }
 
@@ -394,7 +600,209 @@ impl Display for InputAction {
                 SizeUp => "Size Up",
                 SizeDown => "Size Down",
                 OpenDebugMenu => "Debug Menu",
+                ReloadPrototypes => "Reload Prototypes",
+                OpenToolWheel => "Tool Wheel",
+                ToggleCinematic => "Cinematic Camera",
+            }
+        )
+    }
+}
+
+impl Display for InputLayer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TextInput => "Text Input",
+                RoadTool => "Road Tool",
+                BuildingTool => "Building Tool",
+                BrushTool => "Brush Tool",
+                Camera => "Camera",
+                Global => "Global",
             }
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(bindings: &[(InputLayer, InputAction, &[UnitInput])]) -> ChordTable {
+        let mut m: BTreeMap<InputLayer, BTreeMap<InputAction, InputCombinations>> = BTreeMap::new();
+        for (layer, act, comb) in bindings {
+            m.entry(*layer)
+                .or_default()
+                .entry(act.clone())
+                .or_insert(InputCombinations(vec![]))
+                .0
+                .push(InputCombination(comb.to_vec()));
+        }
+        ChordTable::new(&Bindings(m))
+    }
+
+    fn query(t: &ChordTable, layers: &[InputLayer], held: &[UnitInput]) -> Vec<InputAction> {
+        let held: FastSet<UnitInput> = held.iter().cloned().collect();
+        let mut v: Vec<_> = t.query(layers, &held).collect();
+        v.sort();
+        v.dedup();
+        v
+    }
+
+    #[test]
+    fn chord_does_not_fire_plain_key() {
+        let t = table(&[
+            (Global, Close, &[Key(K::c("Z"))]),
+            (Global, HideInterface, &[Key(K::Control), Key(K::c("Z"))]),
+        ]);
+
+        assert_eq!(query(&t, &[Global], &[Key(K::c("Z"))]), vec![Close]);
+        assert_eq!(
+            query(&t, &[Global], &[Key(K::Control), Key(K::c("Z"))]),
+            vec![HideInterface]
+        );
+    }
+
+    #[test]
+    fn unbound_chord_falls_back_to_plain_key() {
+        let t = table(&[
+            (Camera, GoForward, &[KeyScan(17)]),
+            (RoadTool, NoSnapping, &[Key(K::Control)]),
+        ]);
+
+        assert_eq!(
+            query(&t, &[RoadTool, Camera], &[Key(K::Control), KeyScan(17)]),
+            vec![GoForward, NoSnapping]
+        );
+    }
+
+    #[test]
+    fn tool_modifier_hides_global_chord() {
+        let t = ChordTable::new(&Bindings::default());
+
+        let held = &[Key(K::Control), Mouse(Left)];
+        assert_eq!(query(&t, &[Camera, Global], held), vec![SecondarySelect]);
+        assert_eq!(
+            query(&t, &[RoadTool, Camera, Global], held),
+            vec![Select, NoSnapping]
+        );
+    }
+
+    #[test]
+    fn tool_layer_consumes_before_camera() {
+        let t = ChordTable::new(&Bindings::default());
+
+        let held = &[Key(K::Control), WheelUp];
+        assert_eq!(
+            query(&t, &[BuildingTool, Camera, Global], held),
+            vec![Rotate]
+        );
+        assert_eq!(
+            query(&t, &[RoadTool, Camera, Global], held),
+            vec![UpElevation]
+        );
+        assert_eq!(query(&t, &[Camera, Global], &[WheelUp]), vec![Zoom]);
+    }
+
+    #[test]
+    fn text_input_keeps_the_keyboard() {
+        let t = ChordTable::new(&Bindings::default());
+
+        let held = &[KeyScan(17), Key(K::c("T")), Mouse(Left)];
+        assert_eq!(query(&t, &[TextInput, Camera, Global], held), vec![Select]);
+        assert_eq!(
+            query(&t, &[TextInput, Camera, Global], &[Key(K::Escape)]),
+            vec![Close]
+        );
+        assert_eq!(
+            query(&t, &[Camera, Global], held),
+            vec![GoForward, Select, OpenChat]
+        );
+    }
+
+    #[test]
+    fn flat_bindings_go_to_their_default_layers() {
+        use common::saveload::{Encoder, JSON};
+
+        let comb = |u: UnitInput| InputCombinations(vec![InputCombination(vec![u])]);
+        let mut flat = BTreeMap::new();
+        flat.insert(GoForward, comb(KeyScan(13)));
+        flat.insert(Close, comb(KeyScan(1)));
+        flat.insert(NoSnapping, comb(WheelUp));
+
+        let mut bindings: Bindings = JSON::decode(&JSON::encode(&flat).unwrap()).unwrap();
+        let keys = |b: &mut Bindings, layer: InputLayer, act: &InputAction| {
+            b.get_mut(layer, act).unwrap().to_string()
+        };
+
+        assert_eq!(
+            keys(&mut bindings, Camera, &GoForward),
+            comb(KeyScan(13)).to_string()
+        );
+        for layer in [TextInput, Global] {
+            assert_eq!(
+                keys(&mut bindings, layer, &Close),
+                comb(KeyScan(1)).to_string()
+            );
+        }
+        for layer in [RoadTool, BuildingTool] {
+            assert_eq!(
+                keys(&mut bindings, layer, &NoSnapping),
+                comb(WheelUp).to_string()
+            );
+        }
+        assert!(bindings.get_mut(Global, &Select).is_none());
+
+        bindings.merge_defaults();
+        assert!(bindings.get_mut(Global, &Select).is_some());
+        assert_eq!(
+            keys(&mut bindings, Camera, &GoForward),
+            comb(KeyScan(13)).to_string()
+        );
+
+        // the layered bindings read back as they were written
+        let again: Bindings = JSON::decode(&JSON::encode(&bindings).unwrap()).unwrap();
+        assert_eq!(
+            JSON::encode(&again).unwrap(),
+            JSON::encode(&bindings).unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_actions_are_skipped() {
+        use common::saveload::{Encoder, JSON};
+
+        let comb = |u: UnitInput| InputCombinations(vec![InputCombination(vec![u])]);
+        let mut flat = BTreeMap::new();
+        flat.insert("GoForward".to_string(), comb(KeyScan(13)));
+        flat.insert("TeleportHome".to_string(), comb(KeyScan(35)));
+
+        let mut bindings: Bindings = JSON::decode(&JSON::encode(&flat).unwrap()).unwrap();
+        assert_eq!(
+            bindings.get_mut(Camera, &GoForward).unwrap().to_string(),
+            comb(KeyScan(13)).to_string()
+        );
+        assert_eq!(bindings.0.values().map(BTreeMap::len).sum::<usize>(), 1);
+
+        let mut layered: BTreeMap<&str, BTreeMap<String, InputCombinations>> = BTreeMap::new();
+        layered.insert("Camera", flat.clone());
+        layered.insert("MinimapTool", flat);
+
+        let mut bindings: Bindings = JSON::decode(&JSON::encode(&layered).unwrap()).unwrap();
+        assert!(bindings.get_mut(Camera, &GoForward).is_some());
+        assert_eq!(bindings.0.len(), 1);
+        assert_eq!(bindings.0[&Camera].len(), 1);
+    }
+
+    #[test]
+    fn default_bindings_are_valid() {
+        for acts in Bindings::default().0.values() {
+            for combs in acts.values() {
+                for comb in &combs.0 {
+                    assert!(comb.is_valid() || comb.is_modifiers_only(), "{}", comb);
+                }
+            }
+        }
+    }
+}
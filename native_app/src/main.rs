@@ -13,9 +13,11 @@ extern crate inline_tweak;
 mod uiworld;
 
 mod audio;
+mod benchmark;
 mod game_loop;
 mod gui;
 mod init;
+mod input_arbiter;
 mod inputmap;
 mod network;
 mod newgui;
@@ -27,7 +29,58 @@ fn main() {
     profiling::register_thread!("Main Thread");
 
     engine::framework::init();
+    match benchmark::parse_args(std::env::args().skip(1)) {
+        Ok(benchmark::BenchArgs::None) => {}
+        Ok(benchmark::BenchArgs::Run(opts)) => benchmark::set_options(opts),
+        Ok(benchmark::BenchArgs::Compare { before, after }) => {
+            std::process::exit(benchmark::compare_files(&before, &after));
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(2);
+        }
+    }
     init::init();
+    if let Some(code) = export_prototypes(std::env::args().skip(1)) {
+        std::process::exit(code);
+    }
+    if let Some(code) = measure_save(std::env::args().skip(1)) {
+        std::process::exit(code);
+    }
 
     engine::framework::start::<game_loop::State>();
 }
+
+/// `--export-prototypes <file>` writes the loaded prototypes as JSON for the tools working on the
+/// game data, and quits with the exit code
+fn export_prototypes(mut args: impl Iterator<Item = String>) -> Option<i32> {
+    args.find(|arg| arg == "--export-prototypes")?;
+    let Some(path) = args.next() else {
+        log::error!("--export-prototypes needs a file");
+        return Some(2);
+    };
+    let Some(p) = prototypes::try_prototypes() else {
+        log::error!("no prototypes to export, they failed to load");
+        return Some(1);
+    };
+    if let Err(e) = prototypes::export_json_to(p, &path) {
+        log::error!("could not write the prototypes to {}: {}", path, e);
+        return Some(1);
+    }
+    log::info!("prototypes exported to {}", path);
+    Some(0)
+}
+
+/// `--measure-save <name>` saves and loads the save `name` (`world` for the game's save) again with
+/// each codec, logging the times and the sizes, and quits with the exit code
+fn measure_save(mut args: impl Iterator<Item = String>) -> Option<i32> {
+    args.find(|arg| arg == "--measure-save")?;
+    let Some(name) = args.next() else {
+        log::error!("--measure-save needs the name of a save");
+        return Some(2);
+    };
+    if !simulation::Simulation::measure_save(&name) {
+        return Some(1);
+    }
+    Some(0)
+}
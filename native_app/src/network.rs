@@ -1,11 +1,13 @@
 pub use self::inner::*;
 use crate::game_loop::{State, Timings};
-use crate::newgui::windows::settings::Settings;
+use crate::newgui::windows::settings::{Settings, MAX_BATCH_RATE, MIN_BATCH_RATE};
+use crate::rendering::Interpolation;
 use crate::uiworld::{ReceivedCommands, SaveLoadState};
 use common::timestep::Timestep;
 use simulation::utils::scheduler::SeqSchedule;
 use simulation::world_command::{WorldCommand, WorldCommands};
 use simulation::Simulation;
+use std::time::Duration;
 
 impl Default for NetworkState {
     fn default() -> Self {
@@ -22,6 +24,16 @@ mod inner {
         Singleplayer(Timestep),
     }
 
+    impl NetworkState {
+        pub fn is_spectator(&self) -> bool {
+            false
+        }
+
+        pub fn is_networked(&self) -> bool {
+            false
+        }
+    }
+
     pub fn sim_update(state: &mut State) {
         super::handle_singleplayer(state);
     }
@@ -31,7 +43,7 @@ mod inner {
 fn handle_singleplayer(state: &mut State) {
     let mut sim = unwrap_orr!(state.sim.try_write(), return); // mut for tick
 
-    let timewarp = state.uiw.read::<Settings>().time_warp;
+    let settings = *state.uiw.read::<Settings>();
     let mut commands = std::mem::take(&mut *state.uiw.write::<WorldCommands>());
     *state.uiw.write::<ReceivedCommands>() = ReceivedCommands::default();
 
@@ -52,6 +64,7 @@ fn handle_singleplayer(state: &mut State) {
         for v in commands.iter() {
             v.apply(&mut sim);
         }
+        state.uiw.write::<SaveLoadState>().changes_since_save += commands.iter().count();
         commands = WorldCommands::default();
         has_commands = false;
     }
@@ -64,21 +77,46 @@ fn handle_singleplayer(state: &mut State) {
         return;
     };
 
+    let mut interpolation = state.uiw.write::<Interpolation>();
+    // the ticks stay DELTA of game time every DELTA of real time, the rate only groups them
+    let batch_rate = settings.batch_rate.clamp(MIN_BATCH_RATE, MAX_BATCH_RATE);
+    step.batch = Duration::from_secs_f64(1.0 / batch_rate as f64);
+
     let mut commands_once = Some(commands.clone());
-    step.prepare_frame(timewarp);
-    while step.tick() || (has_commands && commands_once.is_some()) {
+    let mut snapshot = settings.interpolation;
+    step.prepare_frame(settings.time_warp);
+    loop {
+        let due = step.tick();
+        if !due && !(has_commands && commands_once.is_some()) {
+            break;
+        }
+        // a tick forced by the commands is not a new batch, the motion keeps interpolating from
+        // the start of the current one
+        if due && snapshot {
+            interpolation.snapshot(sim.world());
+            snapshot = false;
+        }
         let t = sim.tick(sched, commands_once.take().unwrap_or_default().as_ref());
         timings.world_update.add_value(t.as_secs_f32());
     }
 
+    // drawn as is when paused, when the sim lags behind or when interpolation is off
+    interpolation.set_lagging(step.lagging);
+    interpolation.alpha = if settings.interpolation && settings.time_warp > 0 {
+        step.alpha()
+    } else {
+        1.0
+    };
+
     if commands_once.is_none() {
+        state.uiw.write::<SaveLoadState>().changes_since_save += commands.iter().count();
         *state.uiw.write::<ReceivedCommands>() = ReceivedCommands::new(commands);
     } else {
         *state.uiw.write::<WorldCommands>() = commands;
     }
 }
 
-fn handle_replay(
+pub(crate) fn handle_replay(
     sim: &mut Simulation,
     schedule: &mut SeqSchedule,
     slstate: &mut SaveLoadState,
@@ -86,6 +124,7 @@ fn handle_replay(
     if let Some(new_sim) = slstate.please_load_sim.take() {
         *sim = new_sim;
         slstate.render_reset = true;
+        slstate.changes_since_save = 0;
         log::info!("replaced sim");
     }
     if let Some(ref mut replay) = slstate.please_load {
@@ -100,13 +139,15 @@ fn handle_replay(
 
 #[cfg(feature = "multiplayer")]
 mod inner {
-    use crate::game_loop::{State, Timings, VERSION};
+    use crate::game_loop::{read_in_background, State, Timings, VERSION};
     use crate::network::handle_replay;
     use crate::newgui::windows::network::NetworkConnectionInfo;
+    use crate::rendering::Interpolation;
     use crate::uiworld::{ReceivedCommands, SaveLoadState};
     use common::timestep::Timestep;
     use networking::{
-        ConnectConf, Frame, PollResult, ServerConfiguration, ServerPollResult, VirtualClientConf,
+        ConnectConf, Frame, NetworkStats, PollResult, ServerConfiguration, ServerPollResult,
+        VirtualClientConf,
     };
     use prototypes::DELTA_F64;
     use simulation::world_command::WorldCommands;
@@ -125,6 +166,38 @@ mod inner {
         Server(Server),
     }
 
+    impl NetworkState {
+        pub fn stats(&self) -> Option<NetworkStats> {
+            match self {
+                NetworkState::Singleplayer(_) => None,
+                NetworkState::Client(client) => Some(*client.lock().unwrap().stats()),
+                NetworkState::Server(server) => Some(*server.lock().unwrap().stats()),
+            }
+        }
+
+        /// Whether the simulation runs in lockstep with other games, which must all run the same
+        /// prototypes
+        pub fn is_networked(&self) -> bool {
+            !matches!(self, NetworkState::Singleplayer(_))
+        }
+
+        /// Spectators cannot change the world, their commands are dropped
+        pub fn is_spectator(&self) -> bool {
+            match self {
+                NetworkState::Client(client) => client.lock().unwrap().is_spectator(),
+                _ => false,
+            }
+        }
+
+        /// What is left before playing and how much of it is done, while joining a server
+        pub fn join_progress(&self) -> Option<(&'static str, f32)> {
+            match self {
+                NetworkState::Client(client) => client.lock().unwrap().join_progress(),
+                _ => None,
+            }
+        }
+    }
+
     pub fn sim_update(state: &mut State) {
         if matches!(
             *state.uiw.read::<NetworkState>(),
@@ -133,10 +206,16 @@ mod inner {
             super::handle_singleplayer(state);
             return;
         }
+        // the ticks follow the network, they are drawn as they arrive
+        state.uiw.write::<Interpolation>().clear();
 
         let mut sim = unwrap_orr!(state.sim.try_write(), return); // mut for tick
 
-        let commands = std::mem::take(&mut *state.uiw.write::<WorldCommands>());
+        let mut commands = std::mem::take(&mut *state.uiw.write::<WorldCommands>());
+        commands.coalesce();
+        if state.uiw.read::<NetworkState>().is_spectator() {
+            commands = WorldCommands::default();
+        }
         *state.uiw.write::<ReceivedCommands>() = ReceivedCommands::default();
 
         if handle_replay(
@@ -153,11 +232,7 @@ mod inner {
         match &mut *net_state {
             NetworkState::Singleplayer(_) => unreachable!(),
             NetworkState::Server(ref mut server) => {
-                let polled =
-                    server
-                        .get_mut()
-                        .unwrap()
-                        .poll(&sim, Frame(sim.get_tick()), Some(commands));
+                let polled = server.get_mut().unwrap().poll(Some(commands));
                 match polled {
                     ServerPollResult::Wait(commands) => {
                         if let Some(commands) = commands {
@@ -203,6 +278,7 @@ mod inner {
                     .map(|x| x.inp.clone())
                     .collect();
                 let t = sim.tick(&mut state.game_schedule, commands.as_ref());
+                state.uiw.write::<SaveLoadState>().changes_since_save += commands.iter().count();
                 state
                     .uiw
                     .write::<Timings>()
@@ -219,6 +295,29 @@ mod inner {
             }
             *state.uiw.write::<ReceivedCommands>() = ReceivedCommands::new(merged);
         }
+
+        let frame = Frame(sim.get_tick());
+        match &mut *net_state {
+            NetworkState::Singleplayer(_) => {}
+            NetworkState::Server(ref mut server) => {
+                let server = server.get_mut().unwrap();
+                if server.world_hash_wanted(frame) {
+                    server.send_world_hash(frame, sim.hash());
+                }
+                if let Some(snapshot) = server.take_snapshot_request() {
+                    read_in_background(&state.sim, move |sim| {
+                        profiling::scope!("network::snapshot");
+                        snapshot.send(Frame(sim.get_tick()), sim);
+                    });
+                }
+            }
+            NetworkState::Client(ref mut client) => {
+                let client = client.get_mut().unwrap();
+                if client.world_hash_wanted(frame) {
+                    client.set_world_hash(frame, sim.hash());
+                }
+            }
+        }
     }
 
     pub fn start_server(info: &mut NetworkConnectionInfo, sim: &Simulation) -> Option<Server> {
@@ -269,6 +368,7 @@ mod inner {
             port: if port != 23019 { Some(port) } else { None },
             frame_buffer_advance: 8,
             version: VERSION.to_string(),
+            spectator: info.spectator,
         }) {
             Ok(x) => x,
             Err(e) => {
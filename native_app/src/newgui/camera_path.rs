@@ -0,0 +1,372 @@
+use std::f32::consts::{PI, TAU};
+use std::ops::{Add, Mul, Sub};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use common::saveload::{Encoder, JSONPretty};
+use engine::Context;
+use geom::{Camera, Radians, Vec3};
+use simulation::AnyEntity;
+
+use crate::game_loop::State;
+use crate::newgui::cinematic::CinematicDirector;
+use crate::newgui::windows::settings::Settings;
+use crate::newgui::GuiState;
+use crate::rendering::{CameraPose, OrbitCamera};
+use crate::uiworld::UiWorld;
+
+/// Saved paths are named after this prefix in the save directory
+const SAVE_PREFIX: &str = "camera_path_";
+/// Seconds between two points of the drawn path
+const PREVIEW_STEP: f32 = 0.2;
+/// Points of the drawn path
+const MAX_PREVIEW_POINTS: usize = 1000;
+
+/// A point the camera goes through
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    /// Seconds since the start of the path
+    pub time: f32,
+    pub pos: Vec3,
+    pub yaw: Radians,
+    pub pitch: Radians,
+    pub dist: f32,
+    /// The camera looks at this entity instead of `pos` while it exists
+    pub target: Option<AnyEntity>,
+}
+
+impl CameraKeyframe {
+    pub fn new(time: f32, pose: CameraPose, target: Option<AnyEntity>) -> Self {
+        Self {
+            time,
+            pos: pose.pos,
+            yaw: pose.yaw,
+            pitch: pose.pitch,
+            dist: pose.dist,
+            target,
+        }
+    }
+
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            pos: self.pos,
+            yaw: self.yaw,
+            pitch: self.pitch,
+            dist: self.dist,
+        }
+    }
+}
+
+/// Keyframes the camera flies through, to film timelapses of the city
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraPath {
+    /// Sorted by time
+    keyframes: Vec<CameraKeyframe>,
+    /// Time warp of the simulation during the playback
+    pub warp: u32,
+}
+
+impl Default for CameraPath {
+    fn default() -> Self {
+        Self {
+            keyframes: vec![],
+            warp: 1,
+        }
+    }
+}
+
+impl CameraPath {
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        &self.keyframes
+    }
+
+    /// Adds a keyframe, in the order of the time offsets
+    pub fn insert(&mut self, key: CameraKeyframe) {
+        let i = self.keyframes.partition_point(|k| k.time <= key.time);
+        self.keyframes.insert(i, key);
+    }
+
+    pub fn remove(&mut self, i: usize) -> Option<CameraKeyframe> {
+        (i < self.keyframes.len()).then(|| self.keyframes.remove(i))
+    }
+
+    /// Changes a keyframe, it is moved if its time offset changed
+    pub fn replace(&mut self, i: usize, key: CameraKeyframe) {
+        if self.remove(i).is_some() {
+            self.insert(key);
+        }
+    }
+
+    /// Time offset of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Where the camera is at `time` seconds into the path.
+    /// The position goes along a Catmull-Rom spline through the keyframes, so that the camera
+    /// does not stop at each one, and the angles turn the short way around.
+    /// `target_pos` gives where the target entities are, the stored position is used for the
+    /// ones that are gone.
+    pub fn sample(
+        &self,
+        time: f32,
+        target_pos: impl Fn(AnyEntity) -> Option<Vec3>,
+    ) -> Option<CameraPose> {
+        let keys = &self.keyframes;
+        let last = keys.len().checked_sub(1)?;
+        let pos = |i: usize| {
+            let k = &keys[i.min(last)];
+            k.target.and_then(&target_pos).unwrap_or(k.pos)
+        };
+
+        let i = keys
+            .partition_point(|k| k.time <= time)
+            .saturating_sub(1)
+            .min(last.saturating_sub(1));
+        let j = (i + 1).min(last);
+        let (a, b) = (&keys[i], &keys[j]);
+        let span = b.time - a.time;
+        let t = if span > 0.0 {
+            ((time - a.time) / span).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let prev = i.saturating_sub(1);
+        Some(CameraPose {
+            pos: catmull_rom(pos(prev), pos(i), pos(j), pos(j + 1), t),
+            yaw: Radians(lerp_angle(a.yaw.0, b.yaw.0, t)),
+            pitch: Radians(a.pitch.0 + (b.pitch.0 - a.pitch.0) * t),
+            dist: catmull_rom(
+                keys[prev].dist,
+                a.dist,
+                b.dist,
+                keys[(j + 1).min(last)].dist,
+                t,
+            )
+            .max(1.0),
+        })
+    }
+
+    pub fn save(&self, name: &str) -> Option<()> {
+        JSONPretty::save(self, &format!("{SAVE_PREFIX}{name}"))
+    }
+
+    pub fn load(name: &str) -> Option<Self> {
+        JSONPretty::load(&format!("{SAVE_PREFIX}{name}"))
+            .map_err(|e| log::error!("could not load camera path {}: {}", name, e))
+            .ok()
+    }
+
+    /// Names of the paths saved next to the game
+    pub fn saved_names() -> Vec<String> {
+        saved_names(SAVE_PREFIX)
+    }
+}
+
+/// Names of the files saved next to the game starting with `prefix`, without it
+pub fn saved_names(prefix: &str) -> Vec<String> {
+    let Ok(dir) = std::fs::read_dir("world") else {
+        return vec![];
+    };
+    let suffix = format!(".{}", JSONPretty::EXTENSION);
+    let mut names: Vec<String> = dir
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            let name = name.strip_prefix(prefix)?.strip_suffix(&suffix)?;
+            Some(name.to_string())
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+/// Turns from one angle to the other the short way around
+fn lerp_angle(from: f32, to: f32, t: f32) -> f32 {
+    let mut d = (to - from) % TAU;
+    if d > PI {
+        d -= TAU;
+    } else if d < -PI {
+        d += TAU;
+    }
+    from + d * t
+}
+
+/// Plays the camera path with the interface hidden, and writes the frames to disk if asked
+#[derive(Default)]
+pub struct CameraPathPlayer {
+    pub path: CameraPath,
+    /// Name of the path in the save directory
+    pub name: String,
+    /// Write one frame out of this many during the playback, 0 to not write any
+    pub dump_every: u32,
+    playing: bool,
+    /// Seconds since the playback started
+    time: f32,
+    /// Time warp to go back to after the playback
+    resume_warp: u32,
+    pub frames_written: u32,
+}
+
+impl CameraPathPlayer {
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn play(&mut self, uiw: &UiWorld) {
+        if self.path.keyframes.len() < 2 {
+            return;
+        }
+        uiw.write::<CinematicDirector>().stop();
+        let mut settings = uiw.write::<Settings>();
+        self.resume_warp = settings.time_warp;
+        settings.time_warp = self.path.warp;
+        self.playing = true;
+        self.time = 0.0;
+        self.frames_written = 0;
+    }
+
+    pub fn stop(&mut self, uiw: &UiWorld) {
+        if !self.playing {
+            return;
+        }
+        self.playing = false;
+        uiw.write::<Settings>().time_warp = self.resume_warp;
+        uiw.write::<GuiState>().hidden = false;
+    }
+
+    /// Where the frames of this path are written
+    pub fn frames_dir(&self) -> PathBuf {
+        let name = if self.name.is_empty() {
+            "unnamed"
+        } else {
+            &self.name
+        };
+        PathBuf::from("world/frames").join(name)
+    }
+
+    /// Points along the path the camera eye goes through, to draw it in the world
+    pub fn preview(
+        &self,
+        base: &Camera,
+        target_pos: impl Fn(AnyEntity) -> Option<Vec3>,
+    ) -> Vec<Vec3> {
+        let duration = self.path.duration();
+        let n = ((duration / PREVIEW_STEP) as usize).clamp(1, MAX_PREVIEW_POINTS);
+        (0..=n)
+            .filter_map(|i| {
+                self.path
+                    .sample(duration * i as f32 / n as f32, &target_pos)
+            })
+            .map(|pose| eye(base, pose))
+            .collect()
+    }
+
+    pub fn update_camera(state: &mut State, ctx: &mut Context) {
+        let mut player = state.uiw.write::<CameraPathPlayer>();
+
+        let dump = &mut ctx.gfx.frame_dump;
+        let recording = player.playing && player.dump_every > 0;
+        if recording && !dump.is_recording() {
+            dump.start(player.frames_dir(), player.dump_every);
+        } else if !recording && dump.is_recording() {
+            dump.stop();
+        }
+        if !player.playing {
+            return;
+        }
+        player.frames_written = dump.written();
+        // the windows starting the playback are rendered while the GUI state is borrowed
+        state.uiw.write::<GuiState>().hidden = true;
+
+        player.time += ctx.delta;
+        let sim = state.sim.read().unwrap();
+        let pose = player.path.sample(player.time, |e| sim.pos_any(e));
+        match pose {
+            Some(pose) if player.time <= player.path.duration() => {
+                state.uiw.write::<OrbitCamera>().set_pose(pose);
+            }
+            _ => player.stop(&state.uiw),
+        }
+    }
+}
+
+/// Where the camera eye is for this pose
+pub fn eye(base: &Camera, pose: CameraPose) -> Vec3 {
+    let mut cam = *base;
+    cam.pos = pose.pos;
+    cam.yaw = pose.yaw;
+    cam.pitch = pose.pitch;
+    cam.dist = pose.dist;
+    cam.eye()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(time: f32, x: f32, yaw: f32) -> CameraKeyframe {
+        CameraKeyframe {
+            time,
+            pos: Vec3::x(x),
+            yaw: Radians(yaw),
+            pitch: Radians(0.5),
+            dist: 100.0,
+            target: None,
+        }
+    }
+
+    #[test]
+    fn path_goes_through_the_keyframes() {
+        let mut path = CameraPath::default();
+        assert!(path.sample(0.0, |_| None).is_none());
+
+        path.insert(key(10.0, 100.0, 0.0));
+        path.insert(key(0.0, 0.0, 0.0));
+        path.insert(key(4.0, 30.0, 0.0));
+        assert_eq!(path.duration(), 10.0);
+
+        for k in path.keyframes().to_vec() {
+            let pose = path.sample(k.time, |_| None).unwrap();
+            assert!(pose.pos.distance(k.pos) < 1e-3);
+        }
+
+        // smooth through the keyframes, not only straight segments
+        let before = path.sample(3.9, |_| None).unwrap().pos.x;
+        let after = path.sample(4.1, |_| None).unwrap().pos.x;
+        assert!(before < 30.0 && after > 30.0);
+
+        // clamped outside of the path
+        assert!(
+            path.sample(20.0, |_| None)
+                .unwrap()
+                .pos
+                .distance(Vec3::x(100.0))
+                < 1e-3
+        );
+    }
+
+    #[test]
+    fn yaw_turns_the_short_way() {
+        let mut path = CameraPath::default();
+        path.insert(key(0.0, 0.0, 0.1));
+        path.insert(key(1.0, 0.0, TAU - 0.1));
+
+        let mid = path.sample(0.5, |_| None).unwrap().yaw.0;
+        assert!(mid.abs() < 1e-3, "{}", mid);
+    }
+}
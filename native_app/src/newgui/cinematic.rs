@@ -0,0 +1,368 @@
+use std::collections::VecDeque;
+
+use common::FastSet;
+use geom::{Camera, Radians, Ray3, Transform, Vec3};
+use simulation::map::{BuildingID, IntersectionID, Map, ProjectFilter, ProjectKind};
+use simulation::{AnyEntity, Simulation};
+
+use crate::game_loop::State;
+use crate::newgui::windows::settings::Settings;
+use crate::rendering::{CameraPose, Interpolation, OrbitCamera};
+
+/// Shots that cannot be picked again right away
+const RECENT_SHOTS: usize = 5;
+/// Seconds to ease from the previous shot into the new one
+const TRANSITION: f32 = 2.5;
+/// A new building stays interesting for that long, in seconds
+const CONSTRUCTION_INTEREST: f32 = 300.0;
+/// Space kept between the camera and the buildings, as they have no known height
+const BUILDING_CLEARANCE: f32 = 30.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ShotTarget {
+    Entity(AnyEntity),
+    Intersection(IntersectionID),
+    Building(BuildingID),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ShotKind {
+    /// Turns slowly around the target
+    Orbit,
+    /// Slides past the target from low
+    Flyby,
+    /// Stays behind a moving target
+    Follow,
+}
+
+struct Shot {
+    target: ShotTarget,
+    kind: ShotKind,
+    /// Time since the shot started, in seconds
+    elapsed: f32,
+    from: CameraPose,
+    /// Heading of the flyby, or the orbit start
+    yaw: Radians,
+}
+
+/// Moves the camera by itself between the interesting places of the city, to film trailers.
+/// Targets are scored from what happens in the simulation: busy intersections, departing trains
+/// and new buildings.
+#[derive(Default)]
+pub struct CinematicDirector {
+    pub enabled: bool,
+    /// Orbit this entity until unpinned instead of picking targets
+    pub pinned: Option<AnyEntity>,
+    shot: Option<Shot>,
+    recent: VecDeque<ShotTarget>,
+    n_shots: u32,
+    known_buildings: Option<FastSet<BuildingID>>,
+    /// Buildings that appeared while filming, with the time they were seen at
+    new_buildings: Vec<(BuildingID, f32)>,
+    time: f32,
+}
+
+impl CinematicDirector {
+    pub fn start(&mut self, pinned: Option<AnyEntity>) {
+        self.enabled = true;
+        self.pinned = pinned;
+        self.shot = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.enabled = false;
+        self.pinned = None;
+        self.shot = None;
+    }
+
+    pub fn update_camera(state: &mut State, delta: f32) {
+        let mut director = state.uiw.write::<CinematicDirector>();
+        if !director.enabled {
+            return;
+        }
+        let shot_duration = state.uiw.read::<Settings>().cinematic_shot_duration;
+        let sim = state.sim.read().unwrap();
+        let map = sim.map();
+        let mut cam = state.uiw.write::<OrbitCamera>();
+
+        director.time += delta;
+        director.track_new_buildings(&map);
+
+        let over = director.shot.as_ref().map_or(true, |s| {
+            director.pinned.is_none() && s.elapsed > shot_duration
+        });
+        if over {
+            director.next_shot(&sim, &map, cam.pose());
+        }
+
+        let Some(shot) = director.shot.as_mut() else {
+            return;
+        };
+        shot.elapsed += delta;
+
+        let interp = state.uiw.read::<Interpolation>();
+        let Some(pose) = shot_pose(&sim, &interp, &map, shot, shot_duration) else {
+            // the target is gone, a pinned entity falls back to the automatic shots
+            director.pinned = None;
+            director.shot = None;
+            return;
+        };
+        let pose = shot.from.lerp(pose, shot.elapsed / TRANSITION);
+        let pose = unclip(&map, &cam.camera, pose);
+
+        cam.set_pose(pose);
+    }
+
+    fn next_shot(&mut self, sim: &Simulation, map: &Map, from: CameraPose) {
+        let target = match self.pinned {
+            Some(e) => Some(ShotTarget::Entity(e)),
+            None => pick_target(&self.candidates(sim, map), &self.recent),
+        };
+        let Some(target) = target else {
+            self.shot = None;
+            return;
+        };
+
+        let moving = matches!(
+            target,
+            ShotTarget::Entity(AnyEntity::TrainID(_) | AnyEntity::VehicleID(_))
+        );
+        let kind = match (self.pinned.is_some(), moving, self.n_shots % 2) {
+            (true, _, _) => ShotKind::Orbit,
+            (false, true, 0) => ShotKind::Follow,
+            (false, false, 0) => ShotKind::Flyby,
+            _ => ShotKind::Orbit,
+        };
+
+        if self.pinned.is_none() {
+            self.recent.push_back(target);
+            if self.recent.len() > RECENT_SHOTS {
+                self.recent.pop_front();
+            }
+        }
+        self.n_shots += 1;
+
+        let h = common::rand::rand2(self.time, self.n_shots as f32);
+        self.shot = Some(Shot {
+            target,
+            kind,
+            elapsed: 0.0,
+            from,
+            yaw: Radians(h * std::f32::consts::TAU),
+        });
+    }
+
+    fn track_new_buildings(&mut self, map: &Map) {
+        let known = self
+            .known_buildings
+            .get_or_insert_with(|| map.buildings().keys().collect());
+        for id in map.buildings().keys() {
+            if known.insert(id) {
+                self.new_buildings.push((id, self.time));
+            }
+        }
+        let time = self.time;
+        self.new_buildings.retain(|&(id, seen)| {
+            time - seen < CONSTRUCTION_INTEREST && map.buildings().contains_key(id)
+        });
+    }
+
+    /// The possible targets with how interesting they are right now
+    fn candidates(&self, sim: &Simulation, map: &Map) -> Vec<(ShotTarget, f32)> {
+        let world = sim.world();
+        let mut candidates = vec![];
+
+        // busy intersections, from the cars around them
+        let mut traffic: Vec<(IntersectionID, f32)> = vec![];
+        for v in world.vehicles.values() {
+            for kind in map
+                .spatial_map()
+                .query_around(v.trans.pos.xy(), 20.0, ProjectFilter::INTER)
+            {
+                let ProjectKind::Inter(id) = kind else {
+                    continue;
+                };
+                match traffic.iter_mut().find(|(i, _)| *i == id) {
+                    Some((_, n)) => *n += 1.0,
+                    None => traffic.push((id, 1.0)),
+                }
+            }
+        }
+        candidates.extend(
+            traffic
+                .into_iter()
+                .map(|(id, n)| (ShotTarget::Intersection(id), n)),
+        );
+
+        // trains leaving a station are slow for a while
+        for (id, train) in world.trains.iter() {
+            let speed = train.speed.0;
+            let score = if speed > 0.5 && speed < 8.0 {
+                15.0
+            } else if speed >= 8.0 {
+                5.0
+            } else {
+                0.0
+            };
+            if score > 0.0 {
+                candidates.push((ShotTarget::Entity(AnyEntity::TrainID(id)), score));
+            }
+        }
+
+        for &(id, seen) in &self.new_buildings {
+            let freshness = 1.0 - (self.time - seen) / CONSTRUCTION_INTEREST;
+            candidates.push((ShotTarget::Building(id), 20.0 * freshness));
+        }
+
+        candidates
+    }
+}
+
+/// The most interesting target that was not filmed recently
+fn pick_target(
+    candidates: &[(ShotTarget, f32)],
+    recent: &VecDeque<ShotTarget>,
+) -> Option<ShotTarget> {
+    candidates
+        .iter()
+        .filter(|(t, score)| *score > 0.0 && !recent.contains(t))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(t, _)| *t)
+}
+
+fn target_trans(
+    sim: &Simulation,
+    interp: &Interpolation,
+    map: &Map,
+    target: ShotTarget,
+) -> Option<Transform> {
+    match target {
+        ShotTarget::Entity(e) => interp.trans_any(sim.world(), e),
+        ShotTarget::Intersection(id) => {
+            let inter = map.intersections().get(id)?;
+            Some(Transform::new(inter.pos))
+        }
+        ShotTarget::Building(id) => {
+            let b = map.buildings().get(id)?;
+            Some(Transform::new(b.obb.center().z(b.height)))
+        }
+    }
+}
+
+/// Where the camera should be at this point of the shot, None if the target is gone
+fn shot_pose(
+    sim: &Simulation,
+    interp: &Interpolation,
+    map: &Map,
+    shot: &Shot,
+    duration: f32,
+) -> Option<CameraPose> {
+    let trans = target_trans(sim, interp, map, shot.target)?;
+    let t = shot.elapsed;
+
+    Some(match shot.kind {
+        ShotKind::Orbit => CameraPose {
+            pos: trans.pos,
+            yaw: Radians(shot.yaw.0 + t * 0.15),
+            pitch: Radians(0.4),
+            dist: 120.0,
+        },
+        ShotKind::Flyby => {
+            let along = shot.yaw.vec2();
+            let u = (t / duration).min(1.0) - 0.5;
+            CameraPose {
+                pos: trans.pos + (along * u * 150.0).z0(),
+                yaw: Radians(shot.yaw.0 + std::f32::consts::FRAC_PI_2),
+                pitch: Radians(0.2),
+                dist: 70.0,
+            }
+        }
+        ShotKind::Follow => {
+            let back = -trans.dir.xy();
+            CameraPose {
+                pos: trans.pos,
+                yaw: Radians(back.y.atan2(back.x)),
+                pitch: Radians(0.3),
+                dist: 60.0,
+            }
+        }
+    })
+}
+
+/// Raises the camera, then brings it closer, until nothing is between it and the target
+fn unclip(map: &Map, base: &Camera, mut pose: CameraPose) -> CameraPose {
+    for _ in 0..16 {
+        if line_of_sight(map, base, pose) {
+            return pose;
+        }
+        if pose.pitch.0 < 1.3 {
+            pose.pitch.0 += 0.1;
+        } else {
+            pose.dist *= 0.8;
+        }
+    }
+    pose
+}
+
+fn line_of_sight(map: &Map, base: &Camera, pose: CameraPose) -> bool {
+    let mut cam = *base;
+    cam.pos = pose.pos;
+    cam.yaw = pose.yaw;
+    cam.pitch = pose.pitch;
+    cam.dist = pose.dist;
+
+    let from = pose.pos + Vec3::z(2.0);
+    let eye = cam.eye();
+    let length = from.distance(eye);
+    let Some(dir) = (eye - from).try_normalize() else {
+        return true;
+    };
+
+    if let Some((hit, _)) = map.environment.raycast(Ray3 { from, dir }) {
+        if hit.distance(from) < length {
+            return false;
+        }
+    }
+
+    // the ray starts on the target, which can be a building itself
+    let mut d = 20.0;
+    while d < length {
+        let p = from + dir * d;
+        for kind in map.spatial_map().query(p.xy(), ProjectFilter::BUILDING) {
+            let ProjectKind::Building(id) = kind else {
+                continue;
+            };
+            if let Some(b) = map.buildings().get(id) {
+                if p.z < b.height + BUILDING_CLEARANCE {
+                    return false;
+                }
+            }
+        }
+        d += 5.0;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simulation::map::IntersectionID;
+    use slotmapd::KeyData;
+
+    #[test]
+    fn picks_the_busiest_target_not_filmed_recently() {
+        let a = ShotTarget::Intersection(IntersectionID::from(KeyData::from_ffi(1)));
+        let b = ShotTarget::Intersection(IntersectionID::from(KeyData::from_ffi(2)));
+        let c = ShotTarget::Intersection(IntersectionID::from(KeyData::from_ffi(3)));
+        let candidates = [(a, 10.0), (b, 5.0), (c, 0.0)];
+
+        let mut recent = VecDeque::new();
+        assert_eq!(pick_target(&candidates, &recent), Some(a));
+
+        recent.push_back(a);
+        assert_eq!(pick_target(&candidates, &recent), Some(b));
+
+        recent.push_back(b);
+        assert_eq!(pick_target(&candidates, &recent), None);
+    }
+}
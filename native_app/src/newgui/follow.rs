@@ -1,5 +1,6 @@
 use crate::game_loop::State;
 use crate::inputmap::{InputAction, InputMap};
+use crate::rendering::Interpolation;
 use simulation::AnyEntity;
 
 /// FollowEntity is a component that tells the camera to follow an entity
@@ -24,7 +25,12 @@ impl FollowEntity {
         }
 
         if let Some(e) = state.uiw.read::<FollowEntity>().0 {
-            if let Some(pos) = state.sim.read().unwrap().pos_any(e) {
+            let sim = state.sim.read().unwrap();
+            let pos = match state.uiw.read::<Interpolation>().trans_any(sim.world(), e) {
+                Some(trans) => Some(trans.pos),
+                None => sim.pos_any(e),
+            };
+            if let Some(pos) = pos {
                 state.uiw.camera_mut().follow(pos);
             }
         }
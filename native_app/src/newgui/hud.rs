@@ -1,57 +1,118 @@
 use std::time::Instant;
 
+use geom::Vec3;
 use goryak::{image_button, minrow, on_secondary_container, textc};
 use ordered_float::OrderedFloat;
 use prototypes::ItemID;
 use yakui::{reflow, Alignment, Color, Dim2, Pivot, Vec2};
 
 use simulation::map_dynamic::ElectricityFlow;
+use simulation::transportation::incident::Incidents;
 use simulation::Simulation;
 
+use crate::network::NetworkState;
+use crate::newgui::hud::main_menu::{main_menu, AppState};
 use crate::newgui::hud::menu::menu_bar;
+use crate::newgui::hud::pause_menu::{pause_menu, PauseMenu};
 use crate::newgui::hud::time_controls::time_controls;
 use crate::newgui::hud::toolbox::new_toolbox;
 use crate::newgui::inspect::new_inspector;
 use crate::newgui::textures::UiTextures;
 use crate::newgui::windows::settings::Settings;
-use crate::newgui::GuiState;
+use crate::newgui::{GuiState, Tool};
 use crate::uiworld::{SaveLoadState, UiWorld};
 
 pub mod chat;
+mod deposit_labels;
+mod district_names;
+mod hover_tooltip;
 pub mod keybinds;
+pub mod main_menu;
 mod menu;
+mod objectives;
+pub mod pause_menu;
+pub mod speed_signs;
+mod street_names;
+mod supply_chain;
 mod time_controls;
+pub mod tool_wheel;
 pub mod toolbox;
+pub mod tutorial;
 pub mod windows;
 
 /// Root GUI entrypoint
 pub fn render_newgui(uiworld: &UiWorld, sim: &Simulation) {
     profiling::scope!("hud::render");
+    if !uiworld.read::<AppState>().in_game() {
+        main_menu(uiworld, sim);
+        return;
+    }
+
     auto_save(uiworld);
 
+    if uiworld.read::<PauseMenu>().open {
+        pause_menu(uiworld, sim);
+        return;
+    }
+
+    #[cfg(feature = "multiplayer")]
+    if windows::network::joining_screen(uiworld) {
+        return;
+    }
+
     if uiworld.read::<GuiState>().hidden {
         return;
     }
 
+    // spectators only look around
+    let spectator = uiworld.read::<NetworkState>().is_spectator();
+    if spectator {
+        *uiworld.write::<Tool>() = Tool::Hand;
+    }
+
+    tutorial::tutorial_update(uiworld, sim);
+
     yakui::column(|| {
+        street_names::street_names(uiworld, sim);
+        speed_signs::speed_signs(uiworld, sim);
+        district_names::district_names(uiworld, sim);
+        deposit_labels::deposit_labels(uiworld, sim);
         power_errors(uiworld, sim);
-        new_toolbox(uiworld, sim);
+        incident_icons(uiworld, sim);
+        if !spectator {
+            new_toolbox(uiworld, sim);
+        }
         menu_bar(uiworld, sim);
         chat::chat(uiworld, sim);
         new_inspector(uiworld, sim);
         uiworld.write::<GuiState>().windows.render(uiworld, sim);
+        windows::map_export::export_toast(uiworld);
         time_controls(uiworld, sim);
+        objectives::objectives(uiworld, sim);
+        tutorial::tutorial(uiworld, sim);
+        supply_chain::supply_chain_labels(uiworld, sim);
+        hover_tooltip::hover_tooltip(uiworld, sim);
+        if !spectator {
+            tool_wheel::tool_wheel(uiworld, sim);
+        }
         keybinds::keybind_modal(uiworld, sim)
     });
     //goryak::debug_layout();
 }
 
 fn auto_save(uiworld: &UiWorld) {
+    // the benchmark scenes must not replace the player's save
+    if crate::benchmark::options().is_some() {
+        return;
+    }
     let every = uiworld.read::<Settings>().auto_save_every.into();
     let mut gui = uiworld.write::<GuiState>();
     if let Some(every) = every {
         if gui.last_save.elapsed() > every {
-            uiworld.write::<SaveLoadState>().please_save = true;
+            let mut slstate = uiworld.write::<SaveLoadState>();
+            slstate.please_save = true;
+            slstate.save_compression = uiworld.read::<Settings>().auto_save_compression;
+            drop(slstate);
             uiworld.save_to_disk();
             gui.last_save = Instant::now();
         }
@@ -105,6 +166,42 @@ fn power_errors(uiworld: &UiWorld, sim: &Simulation) {
     }
 }
 
+fn incident_icons(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::incident_icons");
+    let incidents = sim.read::<Incidents>();
+    if incidents.active.is_empty() {
+        return;
+    }
+
+    let blocked_img = uiworld.read::<UiTextures>().get("roadedit_forbidden");
+
+    let mut icons = Vec::with_capacity(incidents.active.len());
+    for incident in incidents.active.values() {
+        let pos = incident.pos
+            + Vec3::z(8.0 + 1.0 * f32::cos(uiworld.time_always() + incident.pos.mag() * 0.05));
+        let (screenpos, depth) = uiworld.camera().project(pos);
+
+        let size = 10000.0 / depth;
+
+        icons.push((screenpos, size));
+    }
+
+    icons.sort_by_key(|x| OrderedFloat(x.1));
+
+    for (screenpos, size) in icons {
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::TOP_LEFT,
+            Dim2::pixels(screenpos.x - size * 0.5, screenpos.y - size * 0.5),
+            || {
+                let mut image = yakui::widgets::Image::new(blocked_img, Vec2::new(size, size));
+                image.color = Color::WHITE.with_alpha(0.7);
+                image.show();
+            },
+        );
+    }
+}
+
 pub fn item_icon_yakui(uiworld: &UiWorld, id: ItemID, multiplier: i32) {
     let item = id.prototype();
     minrow(5.0, || {
@@ -2,16 +2,19 @@ use egui::TextBuffer;
 use yakui::{constrained, reflow, Alignment, Color, Constraints, Dim2, Pivot, Vec2};
 
 use goryak::{
-    blur_bg, fixed_spacer, mincolumn, padxy, secondary_container, text_edit, textc, VertScroll,
-    VertScrollSize,
+    blur_bg, capture_pointer, fixed_spacer, mincolumn, padxy, secondary_container, text_edit,
+    textc, VertScroll, VertScrollSize,
 };
-use prototypes::{GameDuration, GameTime};
+use prototypes::{GameDuration, GameInstant, GameTime};
+use simulation::event_log::{EventCategory, EventLog, Severity};
 use simulation::multiplayer::chat::{Message, MessageKind};
 use simulation::multiplayer::MultiplayerState;
 use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 
 use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::windows::settings::Settings;
+use crate::rendering::palette;
 use crate::uiworld::UiWorld;
 
 #[derive(Default)]
@@ -45,11 +48,34 @@ pub fn chat(uiw: &UiWorld, sim: &Simulation) {
         state.cur_msg.clear();
     }
 
-    let msgs: Vec<_> = mstate
+    // keep typed letters from triggering camera movement or shortcuts
+    uiw.write::<InputMap>().text_input = state.chat_bar_showed;
+
+    // the notable events of the log are shown along the messages, newest first
+    let log = sim.read::<EventLog>();
+    let incident_toasts = uiw.read::<Settings>().incident_toasts;
+    let mut msgs: Vec<(GameInstant, geom::Color, String)> = mstate
         .chat
         .messages_since(five_minute_ago)
         .take(MAX_MESSAGES)
+        .map(|m| {
+            // the warnings are colored by the sim, follow the palette instead
+            let color = match m.kind {
+                MessageKind::Warning => palette().danger,
+                _ => m.color,
+            };
+            (m.sent_at, color, m.text.clone())
+        })
+        .chain(
+            log.toasts_since(five_minute_ago)
+                .filter(|e| incident_toasts || e.category != EventCategory::Incident)
+                .take(MAX_MESSAGES)
+                .map(|e| (e.at, severity_color(e.severity), e.text.clone())),
+        )
         .collect();
+    drop(log);
+    msgs.sort_by_key(|m| std::cmp::Reverse(m.0));
+    msgs.truncate(MAX_MESSAGES);
 
     if !state.chat_bar_showed && msgs.is_empty() {
         return;
@@ -60,59 +86,75 @@ pub fn chat(uiw: &UiWorld, sim: &Simulation) {
         Pivot::BOTTOM_LEFT,
         Dim2::pixels(0.0, -192.0),
         || {
-            let alpha = if state.chat_bar_showed { 0.7 } else { 0.2 };
-            blur_bg(secondary_container().with_alpha(alpha), 0.0, || {
-                mincolumn(0.0, || {
-                    VertScroll {
-                        size: VertScrollSize::Exact(300.0),
-                        align_bot: true,
-                    }
-                    .show(|| {
-                        constrained(
-                            Constraints {
-                                min: Vec2::new(250.0, 0.0),
-                                max: Vec2::new(250.0, f32::INFINITY),
-                            },
-                            || {
-                                padxy(8.0, 8.0, || {
-                                    mincolumn(8.0, || {
-                                        for message in msgs.iter().rev() {
-                                            let color = message.color;
-
-                                            let text = message.text.clone();
+            let showed = state.chat_bar_showed;
+            let alpha = if showed { 0.7 } else { 0.2 };
+            let mut panel = || {
+                blur_bg(secondary_container().with_alpha(alpha), 0.0, || {
+                    mincolumn(0.0, || {
+                        VertScroll {
+                            size: VertScrollSize::Exact(300.0),
+                            align_bot: true,
+                        }
+                        .show(|| {
+                            constrained(
+                                Constraints {
+                                    min: Vec2::new(250.0, 0.0),
+                                    max: Vec2::new(250.0, f32::INFINITY),
+                                },
+                                || {
+                                    padxy(8.0, 8.0, || {
+                                        mincolumn(8.0, || {
+                                            for (_, color, text) in msgs.iter().rev() {
+                                                let text = text.clone();
 
-                                            textc(
-                                                Color::rgb(
-                                                    (color.r * 255.0) as u8,
-                                                    (color.g * 255.0) as u8,
-                                                    (color.b * 255.0) as u8,
-                                                ),
-                                                text,
-                                            );
-                                        }
+                                                textc(
+                                                    Color::rgb(
+                                                        (color.r * 255.0) as u8,
+                                                        (color.g * 255.0) as u8,
+                                                        (color.b * 255.0) as u8,
+                                                    ),
+                                                    text,
+                                                );
+                                            }
+                                        });
                                     });
-                                });
-                            },
-                        );
-                    });
-                    if state.chat_bar_showed {
-                        if text_edit(250.0, &mut state.cur_msg, "") && !state.cur_msg.is_empty() {
-                            uiw.commands().push(WorldCommand::SendMessage {
-                                message: Message {
-                                    name: "player".to_string(),
-                                    text: state.cur_msg.take(),
-                                    sent_at: sim.read::<GameTime>().instant(),
-                                    color: geom::Color::WHITE,
-                                    kind: MessageKind::PlayerChat,
                                 },
-                            });
-                            state.chat_bar_showed = false;
+                            );
+                        });
+                        if state.chat_bar_showed {
+                            if text_edit(250.0, &mut state.cur_msg, "") && !state.cur_msg.is_empty()
+                            {
+                                uiw.commands().push(WorldCommand::SendMessage {
+                                    message: Message {
+                                        name: "player".to_string(),
+                                        text: state.cur_msg.take(),
+                                        sent_at: sim.read::<GameTime>().instant(),
+                                        color: geom::Color::WHITE,
+                                        kind: MessageKind::PlayerChat,
+                                    },
+                                });
+                                state.chat_bar_showed = false;
+                            }
+                        } else {
+                            fixed_spacer((0.0, 30.0));
                         }
-                    } else {
-                        fixed_spacer((0.0, 30.0));
-                    }
-                });
-            });
+                    });
+                })
+            };
+            // the faded messages leave the mouse to the map
+            if showed {
+                capture_pointer(panel);
+            } else {
+                panel();
+            }
         },
     );
 }
+
+pub fn severity_color(severity: Severity) -> geom::Color {
+    match severity {
+        Severity::Info => geom::Color::WHITE,
+        Severity::Success => palette().success,
+        Severity::Warning => palette().danger,
+    }
+}
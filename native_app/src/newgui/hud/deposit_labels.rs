@@ -0,0 +1,54 @@
+use goryak::{blur_bg, on_secondary_container, padxy, secondary_container, textc_scaled};
+use yakui::{reflow, Alignment, Dim2, Pivot};
+
+use simulation::Simulation;
+
+use crate::newgui::deposits::{deposit_anchor, shown_deposits};
+use crate::newgui::windows::settings::Settings;
+use crate::uiworld::UiWorld;
+
+/// The labels are hidden when the camera is further than that, the patches are too many to read
+const MAX_CAMERA_DIST: f32 = 5000.0;
+
+/// Writes the kind and remaining quantity of the deposits shown on the map
+pub fn deposit_labels(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::deposit_labels");
+    let Some(only) = shown_deposits(uiworld) else {
+        return;
+    };
+    let cam = uiworld.camera();
+    if cam.camera.dist > MAX_CAMERA_DIST {
+        return;
+    }
+    let map = sim.map();
+    let scale = uiworld.read::<Settings>().overlay_label_scale;
+
+    for d in map.deposits().values() {
+        if only.is_some_and(|kind| kind != d.kind) {
+            continue;
+        }
+        let (screenpos, depth) = cam.project(deposit_anchor(sim, d));
+        if depth <= 0.0 {
+            continue;
+        }
+
+        let label = &d.kind.prototype().label;
+        let text = if d.is_exhausted() {
+            format!("{label}: exhausted")
+        } else {
+            format!("{label}: {}/{}", d.remaining, d.initial)
+        };
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::CENTER,
+            Dim2::pixels(screenpos.x, screenpos.y),
+            || {
+                blur_bg(secondary_container().with_alpha(0.5), 5.0, || {
+                    padxy(4.0, 2.0, || {
+                        textc_scaled(on_secondary_container(), scale, text);
+                    });
+                });
+            },
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use goryak::{blur_bg, padxy, textc_scaled};
+use yakui::{reflow, Alignment, Color, Dim2, Pivot};
+
+use simulation::Simulation;
+
+use crate::newgui::districts::MAX_BORDER_CAMERA_DIST;
+use crate::newgui::windows::settings::Settings;
+use crate::uiworld::UiWorld;
+
+/// District names are replaced by the street names when the camera is closer than that
+const MIN_CAMERA_DIST: f32 = 1500.0;
+
+/// Writes the district names at the middle of the districts at medium zoom
+pub fn district_names(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::district_names");
+    let cam = uiworld.camera();
+    let dist = cam.camera.dist;
+    if !(MIN_CAMERA_DIST..MAX_BORDER_CAMERA_DIST).contains(&dist) {
+        return;
+    }
+    let map = sim.map();
+    let scale = uiworld.read::<Settings>().overlay_label_scale;
+
+    for d in map.districts().values() {
+        let center = d.shape.barycenter();
+        let height = map.environment.height(center).unwrap_or(0.0);
+        let (screenpos, depth) = cam.project(center.z(height + 1.0));
+        if depth <= 0.0 {
+            continue;
+        }
+
+        let bg = Color::rgba(
+            (d.color.r * 255.0) as u8,
+            (d.color.g * 255.0) as u8,
+            (d.color.b * 255.0) as u8,
+            160,
+        );
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::CENTER,
+            Dim2::pixels(screenpos.x, screenpos.y),
+            || {
+                blur_bg(bg, 5.0, || {
+                    padxy(6.0, 3.0, || {
+                        textc_scaled(Color::WHITE, scale, d.name.clone());
+                    });
+                });
+            },
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use goryak::{mincolumn, on_primary_container, on_secondary_container, textc, tooltip};
+use yakui::Vec2;
+
+use simulation::map::BuildingKind;
+use simulation::map_dynamic::BuildingInfos;
+use simulation::souls::venue_events::VenueEvents;
+use simulation::transportation::service_fleet::ServiceFleets;
+use simulation::transportation::Location;
+use simulation::{AnyEntity, Simulation, SoulID};
+
+use crate::inputmap::InputMap;
+use crate::newgui::hover::{HoverState, HoveredObject};
+use crate::newgui::units::fmt_speed;
+use crate::uiworld::UiWorld;
+
+/// Shows the name and a few stats of the object under the cursor, once hovered for a while
+pub fn hover_tooltip(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::hover_tooltip");
+    let Some(hovered) = uiworld.read::<HoverState>().tooltip_object() else {
+        return;
+    };
+    let Some((title, stats)) = describe(sim, hovered) else {
+        return;
+    };
+    let screen = uiworld.read::<InputMap>().screen;
+
+    tooltip(Vec2::new(screen.x, screen.y), || {
+        mincolumn(2.0, || {
+            textc(on_primary_container(), title);
+            for stat in stats {
+                textc(on_secondary_container(), stat);
+            }
+            textc(on_secondary_container().adjust(0.6), "Click to inspect");
+        });
+    });
+}
+
+fn describe(sim: &Simulation, hovered: HoveredObject) -> Option<(String, Vec<String>)> {
+    let world = sim.world();
+    match hovered {
+        HoveredObject::Entity(AnyEntity::HumanID(id)) => {
+            let h = world.get(id)?;
+            let pinfo = &h.personal_info;
+            Some((
+                format!("{}{:?} • {}", pinfo.age, pinfo.gender, pinfo.name),
+                vec![match h.location {
+                    Location::Outside => "Walking".to_string(),
+                    Location::Vehicle(_) => "Driving".to_string(),
+                    Location::Building(_) => "In a building".to_string(),
+                }],
+            ))
+        }
+        HoveredObject::Entity(AnyEntity::VehicleID(id)) => {
+            let v = world.get(id)?;
+            Some((format!("{:?}", v.vehicle.kind), vec![fmt_speed(v.speed.0)]))
+        }
+        HoveredObject::Entity(AnyEntity::TrainID(id)) => {
+            let t = world.get(id)?;
+            Some(("Train".to_string(), vec![fmt_speed(t.speed.0)]))
+        }
+        HoveredObject::Entity(AnyEntity::WagonID(id)) => {
+            let w = world.get(id)?;
+            Some(("Wagon".to_string(), vec![fmt_speed(w.speed.0)]))
+        }
+        HoveredObject::Entity(_) => None,
+        HoveredObject::Building(id) => {
+            let map = sim.map();
+            let b = map.buildings().get(id)?;
+            let owner = sim.read::<BuildingInfos>().owner(id);
+
+            match b.kind {
+                BuildingKind::House => {
+                    let inside = sim.read::<BuildingInfos>().get(id)?.inside.len();
+                    Some(("House".to_string(), vec![format!("{} inside", inside)]))
+                }
+                BuildingKind::GoodsCompany(proto) => {
+                    let mut stats = vec![];
+                    if let Some(SoulID::GoodsCompany(c)) = owner {
+                        let c = world.companies.get(c)?;
+                        stats.push(format!(
+                            "workers: {}/{}",
+                            c.workers.0.len(),
+                            c.comp.max_workers
+                        ));
+                    }
+                    Some((proto.prototype().label.clone(), stats))
+                }
+                BuildingKind::RailFreightStation(proto) => {
+                    let mut stats = vec![];
+                    if let Some(SoulID::FreightStation(f)) = owner {
+                        let f = world.get(f)?;
+                        stats.push(format!("waiting cargo: {}", f.f.waiting_cargo));
+                    }
+                    Some((proto.prototype().label.clone(), stats))
+                }
+                BuildingKind::Dock(proto) => {
+                    let mut stats = vec![];
+                    if let Some(SoulID::Dock(d)) = owner {
+                        let d = world.get(d)?;
+                        stats.push(format!("waiting cargo: {}", d.d.waiting_cargo));
+                        if d.d.mooring.is_none() {
+                            stats.push("no navigable water".to_string());
+                        }
+                    }
+                    Some((proto.prototype().label.clone(), stats))
+                }
+                BuildingKind::Warehouse(proto) => {
+                    let mut stats = vec![];
+                    if let Some(SoulID::Warehouse(w)) = owner {
+                        let w = world.get(w)?;
+                        stats.push(format!("tenants: {}", w.w.rentals.len()));
+                        stats.push(format!("stock rules: {}", w.w.rules.len()));
+                    }
+                    Some((proto.prototype().label.clone(), stats))
+                }
+                BuildingKind::ServiceDepot(proto) => {
+                    let mut stats = vec![];
+                    if let Some(depot) = sim.read::<ServiceFleets>().depots.get(&id) {
+                        stats.push(format!(
+                            "vehicles: {} idle / {}",
+                            depot.n_idle(),
+                            depot.vehicles.len()
+                        ));
+                    }
+                    Some((proto.prototype().label.clone(), stats))
+                }
+                BuildingKind::Leisure(proto) => {
+                    let mut stats = vec![];
+                    if let Some(e) = sim.read::<VenueEvents>().running(id) {
+                        stats.push(format!(
+                            "event at {}: {} expected",
+                            e.start,
+                            e.expected_attendance()
+                        ));
+                    }
+                    Some((proto.prototype().label.clone(), stats))
+                }
+                BuildingKind::TrainStation => Some(("Train Station".to_string(), vec![])),
+                BuildingKind::ExternalTrading => Some(("External Trading".to_string(), vec![])),
+            }
+        }
+    }
+}
@@ -2,10 +2,12 @@ use yakui::widgets::Layer;
 use yakui::{center, reflow, Alignment, Dim2, Pivot};
 
 use engine::InputContext;
-use goryak::{blur_bg, constrained_viewport, mincolumn, on_secondary, primary, textc, titlec};
+use goryak::{
+    blur_bg, capture_pointer, constrained_viewport, mincolumn, on_secondary, primary, textc, titlec,
+};
 use simulation::Simulation;
 
-use crate::inputmap::{Bindings, InputAction, InputCombination, InputMap, UnitInput};
+use crate::inputmap::{Bindings, InputAction, InputCombination, InputLayer, InputMap, UnitInput};
 use crate::uiworld::UiWorld;
 
 #[derive(Default)]
@@ -14,6 +16,7 @@ pub struct KeybindState {
 }
 
 pub struct KeybindStateInner {
+    pub layer: InputLayer,
     pub to_bind_to: InputAction,
     pub bind_index: usize,
     pub cur: InputCombination,
@@ -33,16 +36,21 @@ pub fn keybind_modal(uiw: &UiWorld, _: &Simulation) {
             Pivot::TOP_LEFT,
             Dim2::pixels(0.0, 0.0),
             || {
-                blur_bg(primary().with_alpha(0.5), 0.0, || {
-                    constrained_viewport(|| {
-                        center(|| {
-                            mincolumn(10.0, || {
-                                titlec(on_secondary(), format!("{}", state.to_bind_to));
-                                textc(on_secondary(), "Press key/mouse to bind to action");
+                capture_pointer(|| {
+                    blur_bg(primary().with_alpha(0.5), 0.0, || {
+                        constrained_viewport(|| {
+                            center(|| {
+                                mincolumn(10.0, || {
+                                    titlec(
+                                        on_secondary(),
+                                        format!("{} ({})", state.to_bind_to, state.layer),
+                                    );
+                                    textc(on_secondary(), "Press key/mouse to bind to action");
+                                });
                             });
                         });
-                    });
-                })
+                    })
+                });
             },
         );
     });
@@ -65,6 +73,9 @@ impl KeybindState {
         for mouse in &inp.mouse.pressed {
             state.cur.push_unique(UnitInput::Mouse(mouse.clone()));
         }
+        for button in &inp.gamepad.pressed {
+            state.cur.push_unique(UnitInput::Gamepad(*button));
+        }
         if inp.mouse.wheel_delta > 0.0 {
             state.cur.push_unique(UnitInput::WheelUp);
         }
@@ -81,7 +92,7 @@ impl KeybindState {
             return;
         }
 
-        let ref mut comb = bindings.0.get_mut(&state.to_bind_to).unwrap().0;
+        let ref mut comb = bindings.get_mut(state.layer, &state.to_bind_to).unwrap().0;
 
         let mut cur = std::mem::take(&mut state.cur);
         cur.sort();
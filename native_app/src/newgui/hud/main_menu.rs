@@ -0,0 +1,644 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::JoinHandle;
+
+use yakui::widgets::Layer;
+use yakui::{center, checkbox, reflow, Alignment, Dim2, Pivot, Vec2};
+
+use common::diagnostics::Diagnostic;
+use common::saveload::{CompressedBincode, Encoder, JSONPretty};
+use goryak::{
+    blur_bg, button_primary, button_secondary, capture_pointer, checkbox_value,
+    constrained_viewport, dragvalue, error, mincolumn, minrow, on_secondary, padxy, primary,
+    text_edit, textc, titlec, ProgressBar,
+};
+use prototypes::{
+    detect_mods, loaded_mods, validate_mods, DetectedMod, GameTime, ModError, ModOrder,
+    ScenarioPrototype, MODS_DIR,
+};
+use simulation::map::procgen::osm::{read_osm, OsmImportOptions, OsmImportStep};
+use simulation::utils::savefile::{read_header_from_disk, SaveHeader};
+use simulation::world_command::WorldCommand;
+use simulation::{SaveLoadStep, Simulation, SimulationOptions};
+
+use crate::newgui::hud::keybinds::keybind_modal;
+use crate::newgui::windows::load::{new_game_options, new_scenario};
+use crate::newgui::GuiState;
+use crate::uiworld::{SaveLoadState, UiWorld};
+
+/// Save shown behind the main menu, a terrain is generated if it is missing
+const DEMO_SAVE: &str = "assets/demo_world.zip";
+
+/// Whether the player is in the main menu, waiting for a game to start or playing
+#[derive(Default)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    Loading(Loading),
+    InGame,
+}
+
+impl AppState {
+    pub fn in_game(&self) -> bool {
+        matches!(self, AppState::InGame)
+    }
+}
+
+/// The steps of starting a game, in order.
+/// A save skips the map generation, a new game skips reading the save and
+/// the OpenStreetMap steps are only there when a new game imports an extract.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LoadingStage {
+    Prototypes,
+    ReadingFile,
+    DeserializingEntities,
+    DeserializingMap,
+    MapGen,
+    OsmReading,
+    OsmParsing,
+    OsmConverting,
+    OsmBuilding,
+    RoadMeshes,
+    Terrain,
+}
+
+impl LoadingStage {
+    pub const ALL: [LoadingStage; 11] = [
+        LoadingStage::Prototypes,
+        LoadingStage::ReadingFile,
+        LoadingStage::DeserializingEntities,
+        LoadingStage::DeserializingMap,
+        LoadingStage::MapGen,
+        LoadingStage::OsmReading,
+        LoadingStage::OsmParsing,
+        LoadingStage::OsmConverting,
+        LoadingStage::OsmBuilding,
+        LoadingStage::RoadMeshes,
+        LoadingStage::Terrain,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            LoadingStage::Prototypes => "Loading prototypes",
+            LoadingStage::ReadingFile => "Reading the save",
+            LoadingStage::DeserializingEntities => "Loading the entities",
+            LoadingStage::DeserializingMap => "Loading the map",
+            LoadingStage::MapGen => "Generating the map",
+            LoadingStage::OsmReading => "Reading the OpenStreetMap extract",
+            LoadingStage::OsmParsing => "Parsing the extract",
+            LoadingStage::OsmConverting => "Converting the roads",
+            LoadingStage::OsmBuilding => "Building the imported roads",
+            LoadingStage::RoadMeshes => "Building the road meshes",
+            LoadingStage::Terrain => "Building the terrain",
+        }
+    }
+}
+
+impl From<SaveLoadStep> for LoadingStage {
+    fn from(step: SaveLoadStep) -> Self {
+        match step {
+            SaveLoadStep::ReadingFile => LoadingStage::ReadingFile,
+            SaveLoadStep::DeserializingEntities => LoadingStage::DeserializingEntities,
+            SaveLoadStep::DeserializingMap => LoadingStage::DeserializingMap,
+        }
+    }
+}
+
+impl From<OsmImportStep> for LoadingStage {
+    fn from(step: OsmImportStep) -> Self {
+        match step {
+            OsmImportStep::ReadingFile => LoadingStage::OsmReading,
+            OsmImportStep::Parsing => LoadingStage::OsmParsing,
+            OsmImportStep::Converting => LoadingStage::OsmConverting,
+        }
+    }
+}
+
+/// A simulation being created on another thread
+pub struct LoadingTask {
+    pub handle: JoinHandle<Option<Simulation>>,
+    pub progress: Receiver<LoadingStage>,
+}
+
+pub struct Loading {
+    pub stage: LoadingStage,
+    /// None once the simulation is handed over to the game loop,
+    /// or if it already was when loading a replay.
+    pub task: Option<LoadingTask>,
+    /// Whether the menu simulation was replaced, so cancelling has to bring it back
+    pub sim_replaced: bool,
+    /// Chunks to build when the current mesh stage started
+    pub chunks_total: usize,
+    pub chunks_left: usize,
+}
+
+impl Loading {
+    pub fn start(
+        f: impl FnOnce(Sender<LoadingStage>) -> Option<Simulation> + Send + 'static,
+    ) -> Self {
+        let (tx, rx) = channel();
+        Self {
+            task: Some(LoadingTask {
+                handle: std::thread::spawn(move || f(tx)),
+                progress: rx,
+            }),
+            ..Self::handed_over()
+        }
+    }
+
+    /// The simulation was already given to the game loop through the SaveLoadState
+    pub fn handed_over() -> Self {
+        Self {
+            stage: LoadingStage::Prototypes,
+            task: None,
+            sim_replaced: false,
+            chunks_total: 0,
+            chunks_left: 0,
+        }
+    }
+
+    /// Share of the loading done, between 0 and 1
+    pub fn progress(&self) -> f32 {
+        let i = LoadingStage::ALL
+            .iter()
+            .position(|&s| s == self.stage)
+            .unwrap_or(0);
+        let within = if self.chunks_total > 0 {
+            1.0 - self.chunks_left as f32 / self.chunks_total as f32
+        } else {
+            0.0
+        };
+        (i as f32 + within) / LoadingStage::ALL.len() as f32
+    }
+}
+
+#[derive(Default, Copy, Clone, PartialEq, Eq)]
+enum MenuScreen {
+    #[default]
+    Root,
+    NewGame,
+    Scenarios,
+    Mods,
+}
+
+pub struct MainMenu {
+    screen: MenuScreen,
+    /// Options of the map generated by New Game
+    new_game: SimulationOptions,
+    /// OpenStreetMap extract whose roads New Game imports, nothing is imported if empty
+    osm_path: String,
+    osm: OsmImportOptions,
+    /// Latitude and longitude put at the center of the map when `osm_custom_origin` is set
+    osm_origin: (f64, f64),
+    osm_custom_origin: bool,
+    has_save: bool,
+    /// Read from the start of the save, missing for the saves made before it existed
+    save_header: Option<SaveHeader>,
+    /// Data packs found when opening the mods screen, with the problems of the current order
+    detected_mods: Vec<DetectedMod>,
+    mod_errors: Vec<(String, ModError)>,
+    pub error: String,
+    /// Set from the game to go back to the menu
+    pub please_return: bool,
+}
+
+impl Default for MainMenu {
+    fn default() -> Self {
+        Self {
+            screen: MenuScreen::Root,
+            new_game: SimulationOptions::default(),
+            osm_path: String::new(),
+            osm: OsmImportOptions::default(),
+            osm_origin: (0.0, 0.0),
+            osm_custom_origin: false,
+            has_save: std::fs::metadata(CompressedBincode::filename("world")).is_ok(),
+            save_header: read_header_from_disk(&CompressedBincode::filename("world")),
+            detected_mods: Vec::new(),
+            mod_errors: Vec::new(),
+            error: String::new(),
+            please_return: false,
+        }
+    }
+}
+
+/// The simulation shown behind the main menu
+pub fn load_demo() -> Simulation {
+    File::open(DEMO_SAVE)
+        .ok()
+        .and_then(|f| CompressedBincode::decode_reader(BufReader::new(f)).ok())
+        .unwrap_or_else(|| Simulation::new(true))
+}
+
+/// The main menu, or the loading screen while a game starts
+pub fn main_menu(uiw: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::main_menu");
+    let loading = match *uiw.read::<AppState>() {
+        AppState::Loading(ref loading) => Some((loading.stage, loading.progress())),
+        _ => None,
+    };
+    if let Some((stage, progress)) = loading {
+        if loading_screen(uiw, stage, progress) {
+            cancel_loading(uiw);
+        }
+        return;
+    }
+
+    reflow(
+        Alignment::CENTER_LEFT,
+        Pivot::CENTER_LEFT,
+        Dim2::pixels(50.0, 0.0),
+        || {
+            capture_pointer(|| {
+                blur_bg(primary().with_alpha(0.5), 10.0, || {
+                    padxy(20.0, 20.0, || {
+                        mincolumn(10.0, || {
+                            titlec(on_secondary(), "Egregoria");
+                            let screen = uiw.read::<MainMenu>().screen;
+                            match screen {
+                                MenuScreen::Root => root_screen(uiw),
+                                MenuScreen::NewGame => new_game_screen(uiw),
+                                MenuScreen::Scenarios => scenarios_screen(uiw),
+                                MenuScreen::Mods => mods_screen(uiw),
+                            }
+
+                            let menu = uiw.read::<MainMenu>();
+                            if !menu.error.is_empty() {
+                                textc(error(), menu.error.clone());
+                            }
+                        });
+                    });
+                })
+            });
+        },
+    );
+
+    uiw.write::<GuiState>().windows.render(uiw, sim);
+    keybind_modal(uiw, sim);
+}
+
+fn start_loading(
+    uiw: &UiWorld,
+    f: impl FnOnce(Sender<LoadingStage>) -> Option<Simulation> + Send + 'static,
+) {
+    *uiw.write::<AppState>() = AppState::Loading(Loading::start(f));
+}
+
+/// Whether the mod setup is valid, a game cannot start otherwise
+fn check_mods(uiw: &UiWorld, menu: &mut MainMenu) -> bool {
+    let errors = validate_mods(&uiw.read::<ModOrder>(), &detect_mods("./"));
+    if errors.is_empty() {
+        return true;
+    }
+    menu.error = format!(
+        "{} problem(s) with the enabled mods, fix them in the Mods screen",
+        errors.len()
+    );
+    false
+}
+
+fn root_screen(uiw: &UiWorld) {
+    let mut menu = uiw.write::<MainMenu>();
+
+    if menu.has_save && button_primary("Continue").show().clicked && check_mods(uiw, &mut menu) {
+        menu.error.clear();
+        start_loading(uiw, |tx| {
+            Simulation::load_from_disk_with_progress("world", |step| {
+                let _ = tx.send(step.into());
+            })
+        });
+    }
+    if let Some(ref header) = menu.save_header {
+        textc(
+            on_secondary(),
+            format!(
+                "Day {}, saved with version {}",
+                GameTime::new(header.tick).daytime.day,
+                header.version.trim()
+            ),
+        );
+    }
+
+    if button_primary("New Game").show().clicked {
+        menu.screen = MenuScreen::NewGame;
+    }
+
+    if button_primary("Load").show().clicked {
+        uiw.write::<GuiState>().windows.open_load();
+    }
+
+    if button_primary("Scenarios").show().clicked {
+        menu.screen = MenuScreen::Scenarios;
+    }
+
+    if button_primary("Mods").show().clicked {
+        let mut order = uiw.write::<ModOrder>();
+        menu.detected_mods = detect_mods("./");
+        order.merge_detected(&menu.detected_mods);
+        menu.mod_errors = validate_mods(&order, &menu.detected_mods);
+        menu.screen = MenuScreen::Mods;
+    }
+
+    if button_primary("Settings").show().clicked {
+        uiw.write::<GuiState>().windows.open_settings();
+    }
+
+    if button_secondary("Quit").show().clicked {
+        std::process::exit(0);
+    }
+}
+
+fn back_button(menu: &mut MainMenu) {
+    if button_secondary("Back").show().clicked {
+        menu.screen = MenuScreen::Root;
+    }
+}
+
+fn new_game_screen(uiw: &UiWorld) {
+    let mut menu = uiw.write::<MainMenu>();
+
+    minrow(5.0, || {
+        dragvalue()
+            .min(1.0)
+            .max(100.0)
+            .step(1.0)
+            .show(&mut menu.new_game.terrain_size);
+        textc(on_secondary(), "Terrain size, in chunks");
+    });
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.0)
+            .max(5.0)
+            .step(0.1)
+            .show(&mut menu.new_game.deposit_abundance);
+        textc(on_secondary(), "Deposit abundance");
+    });
+    new_game_options(&mut menu.new_game);
+    osm_options(&mut menu);
+
+    if button_primary("Start").show().clicked && check_mods(uiw, &mut menu) {
+        let opts = menu.new_game.clone();
+        let osm = (!menu.osm_path.trim().is_empty()).then(|| {
+            let mut osm = menu.osm.clone();
+            osm.origin = menu.osm_custom_origin.then_some(menu.osm_origin);
+            (PathBuf::from(menu.osm_path.trim()), osm)
+        });
+        menu.error.clear();
+        start_loading(uiw, move |tx| {
+            let _ = tx.send(LoadingStage::MapGen);
+            let mut sim = Simulation::new_with_options(opts);
+            if let Some((path, osm)) = osm {
+                import_osm(&mut sim, &path, &osm, &tx)?;
+            }
+            Some(sim)
+        });
+    }
+    back_button(&mut menu);
+}
+
+/// The extract whose roads are imported on the generated terrain
+fn osm_options(menu: &mut MainMenu) {
+    textc(on_secondary(), "OpenStreetMap import (.osm.pbf or .osm)");
+    text_edit(
+        300.0,
+        &mut menu.osm_path,
+        "Path to an extract, empty for none",
+    );
+    if menu.osm_path.trim().is_empty() {
+        return;
+    }
+
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.1)
+            .max(4.0)
+            .step(0.05)
+            .show(&mut menu.osm.scale);
+        textc(on_secondary(), "Scale, 1 keeps the real distances");
+    });
+    checkbox_value(
+        &mut menu.osm_custom_origin,
+        on_secondary(),
+        "Choose the center, the middle of the roads otherwise",
+    );
+    if menu.osm_custom_origin {
+        minrow(5.0, || {
+            dragvalue()
+                .min(-90.0)
+                .max(90.0)
+                .step(0.0001)
+                .show(&mut menu.osm_origin.0);
+            textc(on_secondary(), "Latitude");
+            dragvalue()
+                .min(-180.0)
+                .max(180.0)
+                .step(0.0001)
+                .show(&mut menu.osm_origin.1);
+            textc(on_secondary(), "Longitude");
+        });
+    }
+    checkbox_value(
+        &mut menu.osm.water,
+        on_secondary(),
+        "Dig lakes from water areas",
+    );
+    checkbox_value(
+        &mut menu.osm.forests,
+        on_secondary(),
+        "Plant forests and woods",
+    );
+}
+
+/// Builds the roads of the extract on the new map, the problems go to the diagnostics panel
+fn import_osm(
+    sim: &mut Simulation,
+    path: &Path,
+    opts: &OsmImportOptions,
+    tx: &Sender<LoadingStage>,
+) -> Option<()> {
+    let bounds = sim.map().environment.bounds();
+    let import = read_osm(path, opts, bounds, |step| {
+        let _ = tx.send(step.into());
+    });
+    let import = match import {
+        Ok(import) => import,
+        Err(e) => {
+            Diagnostic::error("osm import", e.to_string())
+                .asset(path)
+                .report();
+            return None;
+        }
+    };
+    import.report_skipped(path);
+
+    let _ = tx.send(LoadingStage::OsmBuilding);
+    WorldCommand::MapImport(Box::new(import.map)).apply(sim);
+    Some(())
+}
+
+fn scenarios_screen(uiw: &UiWorld) {
+    let mut menu = uiw.write::<MainMenu>();
+
+    for scenario in ScenarioPrototype::iter() {
+        minrow(5.0, || {
+            if button_primary(&scenario.label).show().clicked && check_mods(uiw, &mut menu) {
+                menu.error.clear();
+                start_loading(uiw, move |tx| {
+                    let _ = tx.send(LoadingStage::MapGen);
+                    new_scenario(scenario)
+                });
+            }
+            textc(on_secondary(), scenario.description.clone());
+        });
+    }
+    back_button(&mut menu);
+}
+
+/// The data packs found in the mods folder, loaded after base_mod in the listed order
+fn mods_screen(uiw: &UiWorld) {
+    let mut menu = uiw.write::<MainMenu>();
+    let mut order = uiw.write::<ModOrder>();
+
+    if order.mods.is_empty() {
+        textc(
+            on_secondary(),
+            format!("No data packs found in {}", MODS_DIR),
+        );
+    }
+
+    let n = order.mods.len();
+    let mut changed = false;
+    let mut swap = None;
+    for (i, m) in order.mods.iter_mut().enumerate() {
+        minrow(5.0, || {
+            let enabled = checkbox(m.enabled).checked;
+            changed |= enabled != m.enabled;
+            m.enabled = enabled;
+            let manifest = menu
+                .detected_mods
+                .iter()
+                .find(|d| d.folder == m.name)
+                .and_then(|d| d.manifest.as_ref().ok());
+            let label = match manifest {
+                Some(manifest) if manifest.author.is_empty() => {
+                    format!("{} {}", manifest.name, manifest.version)
+                }
+                Some(manifest) => format!(
+                    "{} {} by {}",
+                    manifest.name, manifest.version, manifest.author
+                ),
+                None => m.name.clone(),
+            };
+            textc(on_secondary(), label);
+            if i > 0 && button_secondary("Up").show().clicked {
+                swap = Some(i - 1);
+            }
+            if i + 1 < n && button_secondary("Down").show().clicked {
+                swap = Some(i);
+            }
+        });
+        for (_, e) in menu.mod_errors.iter().filter(|(name, _)| name == &m.name) {
+            textc(error(), format!("    {}", e));
+        }
+    }
+    if let Some(i) = swap {
+        order.mods.swap(i, i + 1);
+        changed = true;
+    }
+    if changed {
+        JSONPretty::save(&*order, "mods");
+        menu.mod_errors = validate_mods(&order, &menu.detected_mods);
+    }
+
+    if !menu.mod_errors.is_empty() {
+        textc(
+            error(),
+            "Games cannot start until the mod problems are fixed",
+        );
+    }
+
+    // the mods the next start will load, to compare with the ones of the current prototypes
+    let next_mods: Vec<String> = order
+        .enabled()
+        .filter_map(|name| {
+            let d = menu.detected_mods.iter().find(|d| d.folder == name)?;
+            let manifest = d.manifest.as_ref().ok()?;
+            Some(format!("{} {}", manifest.name, manifest.version))
+        })
+        .collect();
+    if menu.has_save && next_mods != loaded_mods() {
+        textc(
+            error(),
+            "A save exists, changing the mods may affect it when it is loaded",
+        );
+    }
+
+    textc(on_secondary(), "Changes apply on the next start");
+    back_button(&mut menu);
+}
+
+/// Goes back to the main menu, dropping the simulation being loaded
+fn cancel_loading(uiw: &UiWorld) {
+    let mut state = uiw.write::<AppState>();
+    let AppState::Loading(ref loading) = *state else {
+        return;
+    };
+    let replaced = loading.sim_replaced;
+    // a running task is detached, its simulation is dropped once it finishes
+    *state = AppState::MainMenu;
+
+    let mut slstate = uiw.write::<SaveLoadState>();
+    slstate.please_load = None;
+    slstate.please_load_sim = None;
+    if replaced {
+        uiw.write::<MainMenu>().please_return = true;
+    }
+}
+
+/// Dims the menu and shows the current loading stage, returns whether loading was cancelled
+fn loading_screen(uiw: &UiWorld, stage: LoadingStage, progress: f32) -> bool {
+    let mut cancel = false;
+    Layer::new().show(|| {
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::TOP_LEFT,
+            Dim2::pixels(0.0, 0.0),
+            || {
+                capture_pointer(|| {
+                    blur_bg(primary().with_alpha(0.8), 0.0, || {
+                        constrained_viewport(|| {
+                            center(|| {
+                                mincolumn(10.0, || {
+                                    titlec(on_secondary(), "Loading");
+                                    ProgressBar {
+                                        value: progress,
+                                        size: Vec2::new(400.0, 25.0),
+                                        color: primary().adjust(0.7),
+                                    }
+                                    .show_children(|| {
+                                        textc(on_secondary(), stage.label());
+                                    });
+
+                                    if let Some(ref loading) =
+                                        uiw.read::<SaveLoadState>().please_load
+                                    {
+                                        let ticks_done = loading.pastt.0;
+                                        let ticks_total = loading.replay.last_tick_recorded.0;
+                                        textc(
+                                            on_secondary(),
+                                            format!("Replaying: {ticks_done}/{ticks_total}"),
+                                        );
+                                    }
+
+                                    cancel = button_secondary("Cancel").show().clicked;
+                                });
+                            });
+                        });
+                    })
+                });
+            },
+        );
+    });
+    cancel
+}
@@ -2,16 +2,24 @@ use std::sync::atomic::Ordering;
 use std::time::Instant;
 
 use yakui::widgets::{List, Pad};
-use yakui::{column, opaque, reflow, spacer, Alignment, CrossAxisAlignment, Dim2, Pivot};
+use yakui::{
+    column, opaque, reflow, spacer, Alignment, Color, CrossAxisAlignment, Dim2, Pivot, Vec2,
+};
 
 use goryak::{
-    blur_bg, button_primary, button_secondary, constrained_viewport, on_primary_container,
-    on_secondary_container, padxy, secondary_container, textc, Window,
+    blur_bg, button_primary, button_secondary, capture_pointer, constrained_viewport, error,
+    on_error, on_primary_container, on_secondary_container, padxy, secondary_container, textc,
+    ProgressBar, Window,
 };
-use simulation::economy::Government;
+use simulation::economy::{Government, ZoneDemand};
+use simulation::map::LotKind;
 use simulation::Simulation;
 
 use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::lotbrush::lot_kind_color;
+use crate::newgui::units::fmt_money;
+use crate::newgui::windows::diagnostics::DiagnosticsState;
+use crate::newgui::windows::settings::Settings;
 use crate::newgui::{ExitState, GuiState};
 use crate::uiworld::{SaveLoadState, UiWorld};
 
@@ -22,22 +30,29 @@ pub fn menu_bar(uiworld: &UiWorld, sim: &Simulation) {
         constrained_viewport(|| {
             column(|| {
                 opaque(|| {
-                    blur_bg(secondary_container().with_alpha(0.5), 0.0, || {
-                        padxy(5.0, 5.0, || {
-                            let mut l = List::row();
-                            l.item_spacing = 10.0;
-                            l.cross_axis_alignment = CrossAxisAlignment::Center;
-
-                            l.show(|| {
-                                let mut gui = uiworld.write::<GuiState>();
-                                gui.windows.menu();
-                                save_window(&mut gui, uiworld);
-                                textc(
-                                    on_primary_container(),
-                                    format!("Money: {}", sim.read::<Government>().money),
-                                );
+                    capture_pointer(|| {
+                        blur_bg(secondary_container().with_alpha(0.5), 0.0, || {
+                            padxy(5.0, 5.0, || {
+                                let mut l = List::row();
+                                l.item_spacing = 10.0;
+                                l.cross_axis_alignment = CrossAxisAlignment::Center;
+
+                                l.show(|| {
+                                    let mut gui = uiworld.write::<GuiState>();
+                                    gui.windows.menu();
+                                    save_window(&mut gui, uiworld);
+                                    diagnostics_badge(&mut gui, uiworld);
+                                    textc(
+                                        on_primary_container(),
+                                        format!(
+                                            "Money: {}",
+                                            fmt_money(sim.read::<Government>().money)
+                                        ),
+                                    );
+                                    zone_demand(sim);
+                                });
                             });
-                        });
+                        })
                     });
                 });
                 spacer(1);
@@ -46,16 +61,105 @@ pub fn menu_bar(uiworld: &UiWorld, sim: &Simulation) {
     });
 }
 
+/// Shown once content problems were reported, stands out while some are new
+fn diagnostics_badge(gui: &mut GuiState, uiw: &UiWorld) {
+    let count = common::diagnostics::shown_count();
+    if count == 0 {
+        return;
+    }
+    let label = if count == 1 {
+        "1 problem".to_string()
+    } else {
+        format!("{count} problems")
+    };
+    let mut b = button_secondary(label);
+    if uiw.read::<DiagnosticsState>().has_new() {
+        b.style.fill = error();
+        b.style.text.color = on_error();
+        b.hover_style.fill = error().adjust(1.2);
+        b.hover_style.text.color = on_error();
+    }
+    if b.show().clicked {
+        gui.windows.toggle_diagnostics();
+    }
+}
+
+/// Residential, commercial and industrial demand bars
+fn zone_demand(sim: &Simulation) {
+    let demand = sim.read::<ZoneDemand>();
+    for (label, kind) in [
+        ("R", LotKind::Residential),
+        ("C", LotKind::Commercial),
+        ("I", LotKind::Industrial),
+    ] {
+        let Some(zone) = kind.zone_kind() else {
+            continue;
+        };
+        let col = lot_kind_color(kind);
+        ProgressBar {
+            value: (demand.get(zone) / ZoneDemand::FULL).clamp(0.0, 1.0),
+            size: Vec2::new(40.0, 20.0),
+            color: Color::rgb(
+                (col.r * 255.0) as u8,
+                (col.g * 255.0) as u8,
+                (col.b * 255.0) as u8,
+            ),
+        }
+        .show_children(|| {
+            textc(on_primary_container(), label);
+        });
+    }
+}
+
 fn save_window(gui: &mut GuiState, uiw: &UiWorld) {
-    let mut slstate = uiw.write::<SaveLoadState>();
-    if slstate.saving_status.load(Ordering::SeqCst) {
+    if uiw
+        .read::<SaveLoadState>()
+        .saving_status
+        .load(Ordering::SeqCst)
+    {
         textc(on_secondary_container(), "Saving...");
     } else if button_primary("Save").show().clicked {
-        slstate.please_save = true;
-        gui.last_save = Instant::now();
-        uiw.save_to_disk();
+        save_game(gui, uiw);
     }
 
+    exit_modal(uiw);
+
+    let mut slstate = uiw.write::<SaveLoadState>();
+    let mut estate = uiw.write::<ExitState>();
+    match *estate {
+        ExitState::NoExit => {
+            if button_secondary("Exit").show().clicked {
+                *estate = ExitState::ExitAsk;
+            }
+        }
+        ExitState::ExitAsk => {
+            if button_secondary("Save and exit").show().clicked {
+                if let ExitState::ExitAsk = *estate {
+                    slstate.please_save = true;
+                    slstate.save_compression = uiw.read::<Settings>().save_compression;
+                    *estate = ExitState::Saving;
+                }
+            }
+        }
+        ExitState::Saving => {
+            textc(on_secondary_container(), "Saving...");
+        }
+    }
+}
+
+/// Saves the simulation and the interface state
+pub fn save_game(gui: &mut GuiState, uiw: &UiWorld) {
+    let mut slstate = uiw.write::<SaveLoadState>();
+    slstate.please_save = true;
+    slstate.save_compression = uiw.read::<Settings>().save_compression;
+    drop(slstate);
+    gui.last_save = Instant::now();
+    uiw.save_to_disk();
+}
+
+/// Asks whether to save before exiting, exits once the save is done
+pub fn exit_modal(uiw: &UiWorld) {
+    let mut slstate = uiw.write::<SaveLoadState>();
     let mut estate = uiw.write::<ExitState>();
 
     match *estate {
@@ -80,6 +184,7 @@ fn save_window(gui: &mut GuiState, uiw: &UiWorld) {
                 if button_secondary("Save and exit").show().clicked {
                     if let ExitState::ExitAsk = *estate {
                         slstate.please_save = true;
+                        slstate.save_compression = uiw.read::<Settings>().save_compression;
                         *estate = ExitState::Saving;
                     }
                 }
@@ -104,23 +209,4 @@ fn save_window(gui: &mut GuiState, uiw: &UiWorld) {
             }
         }
     }
-
-    match *estate {
-        ExitState::NoExit => {
-            if button_secondary("Exit").show().clicked {
-                *estate = ExitState::ExitAsk;
-            }
-        }
-        ExitState::ExitAsk => {
-            if button_secondary("Save and exit").show().clicked {
-                if let ExitState::ExitAsk = *estate {
-                    slstate.please_save = true;
-                    *estate = ExitState::Saving;
-                }
-            }
-        }
-        ExitState::Saving => {
-            textc(on_secondary_container(), "Saving...");
-        }
-    }
 }
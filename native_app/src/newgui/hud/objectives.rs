@@ -0,0 +1,105 @@
+use yakui::widgets::List;
+use yakui::{
+    constrained, opaque, reflow, Alignment, Constraints, CrossAxisAlignment, Dim2,
+    MainAxisAlignment, MainAxisSize, Pivot, Vec2,
+};
+
+use goryak::{
+    blur_bg, capture_pointer, constrained_viewport, error, on_secondary_container, padxy, primary,
+    secondary_container, textc, titlec, ProgressBar,
+};
+use prototypes::GameTime;
+use simulation::scenario::{ObjectiveStatus, ScenarioState};
+use simulation::Simulation;
+
+use crate::uiworld::UiWorld;
+
+/// Objectives panel
+/// Shows the progress of the scenario objectives as of the last daily check
+pub fn objectives(_uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::objectives");
+    let state = sim.read::<ScenarioState>();
+    let Some(id) = state.scenario else {
+        return;
+    };
+    let proto = id.prototype();
+    let day = sim.read::<GameTime>().daytime.day;
+
+    reflow(
+        Alignment::TOP_RIGHT,
+        Pivot::TOP_RIGHT,
+        Dim2::pixels(-10.0, 80.0),
+        || {
+            constrained_viewport(|| {
+                let mut l = List::row();
+                l.main_axis_alignment = MainAxisAlignment::End;
+                l.show(|| {
+                    opaque(|| {
+                        capture_pointer(|| {
+                            blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
+                                padxy(10.0, 5.0, || {
+                                    constrained(
+                                        Constraints::loose(Vec2::new(250.0, f32::INFINITY)),
+                                        || {
+                                            let mut l = List::column();
+                                            l.cross_axis_alignment = CrossAxisAlignment::Stretch;
+                                            l.main_axis_size = MainAxisSize::Min;
+                                            l.item_spacing = 5.0;
+                                            l.show(|| {
+                                                titlec(
+                                                    on_secondary_container(),
+                                                    proto.label.clone(),
+                                                );
+                                                if state.is_completed() {
+                                                    titlec(primary(), "Scenario completed!");
+                                                }
+
+                                                for (objective, progress) in
+                                                    proto.objectives.iter().zip(&state.objectives)
+                                                {
+                                                    let mut label = objective.label.clone();
+                                                    if let Some(limit) = objective.time_limit_days {
+                                                        if progress.status
+                                                            == ObjectiveStatus::InProgress
+                                                        {
+                                                            let left = state.start_day
+                                                                + limit as i32
+                                                                - day;
+                                                            label +=
+                                                                &format!(" ({left} days left)");
+                                                        }
+                                                    }
+
+                                                    let (value, color) = match progress.status {
+                                                        ObjectiveStatus::InProgress => (
+                                                            progress.progress,
+                                                            primary().adjust(0.7),
+                                                        ),
+                                                        ObjectiveStatus::Completed => {
+                                                            (1.0, primary())
+                                                        }
+                                                        ObjectiveStatus::Failed => {
+                                                            (progress.progress, error())
+                                                        }
+                                                    };
+
+                                                    textc(on_secondary_container(), label);
+                                                    ProgressBar {
+                                                        value,
+                                                        size: Vec2::new(250.0, 10.0),
+                                                        color,
+                                                    }
+                                                    .show();
+                                                }
+                                            });
+                                        },
+                                    );
+                                });
+                            })
+                        });
+                    });
+                });
+            });
+        },
+    );
+}
@@ -0,0 +1,284 @@
+use std::sync::atomic::Ordering;
+
+use yakui::widgets::{Layer, Pad};
+use yakui::{center, reflow, Alignment, Dim2, Pivot};
+
+use goryak::{
+    blur_bg, button_primary, button_secondary, capture_pointer, combo_box, constrained_viewport,
+    mincolumn, minrow, on_secondary, primary, textc, titlec, Window,
+};
+use simulation::{AnyEntity, Simulation};
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::camera_path::CameraPathPlayer;
+use crate::newgui::cinematic::CinematicDirector;
+use crate::newgui::follow::FollowEntity;
+use crate::newgui::hud::keybinds::{keybind_modal, KeybindState};
+use crate::newgui::hud::main_menu::MainMenu;
+use crate::newgui::hud::menu::{exit_modal, save_game};
+use crate::newgui::windows::settings::Settings;
+use crate::newgui::{ExitState, GuiState, InspectedBuilding, InspectedEntity, Tool};
+use crate::rendering::LightingPreset;
+use crate::uiworld::{SaveLoadState, UiWorld};
+
+/// The menu shown when pressing escape with nothing else to close
+#[derive(Default)]
+pub struct PauseMenu {
+    pub open: bool,
+    /// Time warp to go back to when resuming
+    resume_warp: u32,
+    /// The interface is hidden to take pictures, escape brings the menu back
+    photo_mode: bool,
+    /// Lighting of the photo mode
+    pub lighting: LightingPreset,
+    /// Asking whether to save before going back to the main menu
+    confirm_quit_to_menu: bool,
+}
+
+impl PauseMenu {
+    /// The lighting preset in use, the photo mode being the only one to override the time of day
+    pub fn lighting(&self) -> LightingPreset {
+        if self.photo_mode {
+            return self.lighting;
+        }
+        LightingPreset::Computed
+    }
+
+    fn pause(&mut self, uiw: &UiWorld) {
+        let mut settings = uiw.write::<Settings>();
+        self.open = true;
+        self.resume_warp = settings.time_warp;
+        settings.time_warp = 0;
+    }
+
+    fn resume(&mut self, uiw: &UiWorld) {
+        self.open = false;
+        self.photo_mode = false;
+        self.confirm_quit_to_menu = false;
+        uiw.write::<Settings>().time_warp = self.resume_warp;
+    }
+
+    /// Hides the interface and lets the cinematic camera film the city, or orbit `pinned`.
+    /// The game runs during the shots, at the speed it had before pausing
+    pub fn start_cinematic(&mut self, uiw: &UiWorld, pinned: Option<AnyEntity>) {
+        let mut settings = uiw.write::<Settings>();
+        if !self.open && !self.photo_mode {
+            self.resume_warp = settings.time_warp;
+        }
+        settings.time_warp = self.resume_warp.max(1);
+        self.open = false;
+        self.photo_mode = true;
+        uiw.write::<GuiState>().hidden = true;
+        uiw.write::<CinematicDirector>().start(pinned);
+    }
+
+    fn stop_cinematic(&mut self, uiw: &UiWorld) {
+        uiw.write::<CinematicDirector>().stop();
+        uiw.write::<Settings>().time_warp = 0;
+    }
+}
+
+/// Opens and closes the pause menu on escape.
+/// Must run before the other ui systems as the menu captures all the game inputs while open.
+pub fn pause_menu_input(uiw: &UiWorld) {
+    let mut menu = uiw.write::<PauseMenu>();
+    let mut inp = uiw.write::<InputMap>();
+    let close = inp.just_act.contains(&InputAction::Close);
+
+    {
+        let mut player = uiw.write::<CameraPathPlayer>();
+        if player.is_playing() {
+            if close {
+                player.stop(uiw);
+                inp.just_act.clear();
+            }
+            return;
+        }
+    }
+
+    if menu.photo_mode {
+        if inp.just_act.contains(&InputAction::ToggleCinematic) {
+            if uiw.read::<CinematicDirector>().enabled {
+                menu.stop_cinematic(uiw);
+            } else {
+                menu.start_cinematic(uiw, None);
+            }
+        }
+        if close {
+            menu.stop_cinematic(uiw);
+            menu.photo_mode = false;
+            menu.open = true;
+            uiw.write::<GuiState>().hidden = false;
+            inp.just_act.clear();
+        }
+        return;
+    }
+
+    if menu.open {
+        if close {
+            let mut estate = uiw.write::<ExitState>();
+            if let ExitState::ExitAsk = *estate {
+                *estate = ExitState::NoExit;
+            } else if menu.confirm_quit_to_menu {
+                menu.confirm_quit_to_menu = false;
+            } else if uiw.read::<KeybindState>().enabled.is_none() {
+                menu.resume(uiw);
+            }
+        }
+        inp.just_act.clear();
+        inp.act.clear();
+        return;
+    }
+
+    if close && !wants_escape(uiw, &inp) {
+        menu.pause(uiw);
+        inp.just_act.clear();
+    }
+}
+
+/// Whether escape is used to close something else than the pause menu
+fn wants_escape(uiw: &UiWorld, inp: &InputMap) -> bool {
+    !matches!(*uiw.read::<Tool>(), Tool::Hand)
+        || uiw.read::<InspectedEntity>().e.is_some()
+        || uiw.read::<InspectedBuilding>().e.is_some()
+        || uiw.read::<FollowEntity>().0.is_some()
+        || inp.text_input
+        || !matches!(*uiw.read::<ExitState>(), ExitState::NoExit)
+        || uiw.read::<KeybindState>().enabled.is_some()
+}
+
+/// Dims the world and shows the pause menu in the middle of the screen,
+/// the windows opened from the menu are drawn over it
+pub fn pause_menu(uiw: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::pause_menu");
+
+    Layer::new().show(|| {
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::TOP_LEFT,
+            Dim2::pixels(0.0, 0.0),
+            || {
+                capture_pointer(|| {
+                    blur_bg(primary().with_alpha(0.5), 0.0, || {
+                        constrained_viewport(|| {
+                            center(|| {
+                                mincolumn(10.0, || {
+                                    titlec(on_secondary(), "Paused");
+                                    menu_buttons(uiw);
+                                });
+                            });
+                        });
+                    })
+                });
+            },
+        );
+
+        uiw.write::<GuiState>().windows.render(uiw, sim);
+        exit_modal(uiw);
+        quit_to_menu_modal(uiw);
+    });
+
+    keybind_modal(uiw, sim);
+}
+
+fn menu_buttons(uiw: &UiWorld) {
+    if button_primary("Resume").show().clicked {
+        uiw.write::<PauseMenu>().resume(uiw);
+    }
+
+    if uiw
+        .read::<SaveLoadState>()
+        .saving_status
+        .load(Ordering::SeqCst)
+    {
+        textc(on_secondary(), "Saving...");
+    } else if button_primary("Save").show().clicked {
+        save_game(&mut uiw.write::<GuiState>(), uiw);
+    }
+
+    if button_primary("Load").show().clicked {
+        uiw.write::<GuiState>().windows.open_load();
+    }
+
+    if button_primary("Settings").show().clicked {
+        uiw.write::<GuiState>().windows.open_settings();
+    }
+
+    if button_primary("Photo Mode").show().clicked {
+        let mut menu = uiw.write::<PauseMenu>();
+        menu.open = false;
+        menu.photo_mode = true;
+        uiw.write::<GuiState>().hidden = true;
+    }
+
+    minrow(5.0, || {
+        let labels = LightingPreset::ALL.map(LightingPreset::label);
+        let mut menu = uiw.write::<PauseMenu>();
+        let mut selected = LightingPreset::ALL
+            .iter()
+            .position(|&l| l == menu.lighting)
+            .unwrap_or(0);
+        if combo_box(&mut selected, &labels, 150.0) {
+            menu.lighting = LightingPreset::ALL[selected];
+        }
+        textc(on_secondary(), "Photo lighting");
+    });
+
+    if button_primary("Cinematic Mode").show().clicked {
+        uiw.write::<PauseMenu>().start_cinematic(uiw, None);
+    }
+
+    if button_secondary("Quit to menu").show().clicked {
+        if uiw.read::<SaveLoadState>().changes_since_save > 0 {
+            uiw.write::<PauseMenu>().confirm_quit_to_menu = true;
+        } else {
+            quit_to_menu(uiw);
+        }
+    }
+
+    if button_secondary("Quit to desktop").show().clicked {
+        if uiw.read::<SaveLoadState>().changes_since_save > 0 {
+            *uiw.write::<ExitState>() = ExitState::ExitAsk;
+        } else {
+            std::process::exit(0);
+        }
+    }
+}
+
+fn quit_to_menu(uiw: &UiWorld) {
+    uiw.write::<PauseMenu>().resume(uiw);
+    uiw.write::<MainMenu>().please_return = true;
+}
+
+/// Asks whether to save before going back to the main menu
+fn quit_to_menu_modal(uiw: &UiWorld) {
+    if !uiw.read::<PauseMenu>().confirm_quit_to_menu {
+        return;
+    }
+
+    let mut opened = true;
+    let mut cancel = false;
+    Window {
+        title: "Unsaved changes".into(),
+        pad: Pad::all(15.0),
+        radius: 10.0,
+        opened: &mut opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        if button_secondary("Save and quit to menu").show().clicked {
+            save_game(&mut uiw.write::<GuiState>(), uiw);
+            quit_to_menu(uiw);
+        }
+        if button_secondary("Quit without saving").show().clicked {
+            quit_to_menu(uiw);
+        }
+        if button_secondary("Cancel").show().clicked {
+            cancel = true;
+        }
+    });
+
+    if !opened || cancel {
+        uiw.write::<PauseMenu>().confirm_quit_to_menu = false;
+    }
+}
@@ -0,0 +1,73 @@
+use goryak::{padxy, round_rect, textc_scaled};
+use yakui::{reflow, Alignment, Color, Dim2, Pivot};
+
+use simulation::map::{ProjectFilter, ProjectKind};
+use simulation::Simulation;
+
+use crate::newgui::units::UnitSystem;
+use crate::newgui::windows::settings::Settings;
+use crate::newgui::Tool;
+use crate::uiworld::UiWorld;
+
+/// Signs are hidden when the camera is further away than that
+const MAX_CAMERA_DIST: f32 = 1000.0;
+
+/// Puts a speed limit sign on the roads while the road editor is selected.
+/// The sign sits at a quarter of the road so that it does not cover the street name.
+pub fn speed_signs(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::speed_signs");
+    if !matches!(*uiworld.read::<Tool>(), Tool::RoadEditor) {
+        return;
+    }
+    let cam = uiworld.camera();
+    let dist = cam.camera.dist;
+    if dist > MAX_CAMERA_DIST {
+        return;
+    }
+    let map = sim.map();
+    let scale = uiworld.read::<Settings>().overlay_label_scale;
+    let units = UnitSystem::current();
+
+    for kind in map
+        .spatial_map()
+        .query_around(cam.camera.pos.xy(), dist * 1.5, ProjectFilter::ROAD)
+    {
+        let ProjectKind::Road(id) = kind else {
+            continue;
+        };
+        let Some(road) = map.roads().get(id) else {
+            continue;
+        };
+        let Some(limit) = road.speed_limit(map.lanes()) else {
+            continue;
+        };
+        let length = road.points.length();
+        if length < dist * 0.2 {
+            continue;
+        }
+
+        let pos = road.points.point_along(length * 0.25).up(1.0);
+        let (screenpos, depth) = cam.project(pos);
+        if depth <= 0.0 {
+            continue;
+        }
+
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::CENTER,
+            Dim2::pixels(screenpos.x, screenpos.y),
+            || {
+                round_rect(12.0, Color::rgb(200, 30, 30), || {
+                    padxy(2.0, 2.0, || {
+                        round_rect(10.0, Color::WHITE, || {
+                            padxy(5.0, 2.0, || {
+                                let limit = units.speed_value(limit).round();
+                                textc_scaled(Color::BLACK, scale, format!("{}", limit));
+                            });
+                        });
+                    });
+                });
+            },
+        );
+    }
+}
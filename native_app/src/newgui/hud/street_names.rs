@@ -0,0 +1,59 @@
+use goryak::{blur_bg, on_secondary_container, padxy, secondary_container, textc_scaled};
+use yakui::{reflow, Alignment, Dim2, Pivot};
+
+use simulation::map::{ProjectFilter, ProjectKind};
+use simulation::Simulation;
+
+use crate::newgui::windows::settings::Settings;
+use crate::uiworld::UiWorld;
+
+/// Street names are hidden when the camera is further away than that
+const MAX_CAMERA_DIST: f32 = 1500.0;
+
+/// Writes the street names over the roads when the camera is close enough.
+/// Short roads are only named when zoomed in so the labels do not pile up.
+pub fn street_names(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::street_names");
+    let cam = uiworld.camera();
+    let dist = cam.camera.dist;
+    if dist > MAX_CAMERA_DIST {
+        return;
+    }
+    let map = sim.map();
+    let scale = uiworld.read::<Settings>().overlay_label_scale;
+
+    for kind in map
+        .spatial_map()
+        .query_around(cam.camera.pos.xy(), dist * 1.5, ProjectFilter::ROAD)
+    {
+        let ProjectKind::Road(id) = kind else {
+            continue;
+        };
+        let Some(road) = map.roads().get(id) else {
+            continue;
+        };
+        let length = road.points.length();
+        if road.name.is_empty() || length < dist * 0.2 {
+            continue;
+        }
+
+        let mid = road.points.point_along(length * 0.5).up(1.0);
+        let (screenpos, depth) = cam.project(mid);
+        if depth <= 0.0 {
+            continue;
+        }
+
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::CENTER,
+            Dim2::pixels(screenpos.x, screenpos.y),
+            || {
+                blur_bg(secondary_container().with_alpha(0.5), 5.0, || {
+                    padxy(4.0, 2.0, || {
+                        textc_scaled(on_secondary_container(), scale, road.name.clone());
+                    });
+                });
+            },
+        );
+    }
+}
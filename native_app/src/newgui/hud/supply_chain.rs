@@ -0,0 +1,50 @@
+use goryak::{blur_bg, minrow, on_secondary_container, padxy, secondary_container, textc_scaled};
+use yakui::{reflow, Alignment, Dim2, Pivot};
+
+use simulation::Simulation;
+
+use crate::newgui::item_icon_yakui;
+use crate::newgui::supply_chain::{chain_arrows, SupplyChainView};
+use crate::newgui::windows::settings::Settings;
+use crate::newgui::InspectedBuilding;
+use crate::uiworld::UiWorld;
+
+/// Labels the supply chain arrows of the inspected building with the traded item and quantity
+pub fn supply_chain_labels(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::supply_chain_labels");
+    if !uiworld.read::<SupplyChainView>().enabled {
+        return;
+    }
+    let Some(inspected) = uiworld.read::<InspectedBuilding>().e else {
+        return;
+    };
+
+    let scale = uiworld.read::<Settings>().overlay_label_scale;
+    for arrow in chain_arrows(sim, inspected) {
+        let mid = (arrow.from() + arrow.to()) * 0.5;
+        let (screenpos, depth) = uiworld.camera().project(mid);
+        if depth <= 0.0 {
+            continue;
+        }
+
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::CENTER,
+            Dim2::pixels(screenpos.x, screenpos.y),
+            || {
+                blur_bg(secondary_container().with_alpha(0.5), 5.0, || {
+                    padxy(4.0, 2.0, || {
+                        minrow(2.0, || {
+                            item_icon_yakui(uiworld, arrow.link.item, arrow.link.qty);
+                            textc_scaled(
+                                on_secondary_container(),
+                                scale,
+                                format!("x{}", arrow.link.qty),
+                            );
+                        });
+                    });
+                });
+            },
+        );
+    }
+}
@@ -5,20 +5,24 @@ use yakui::{
 };
 
 use goryak::{
-    blur_bg, button_primary, button_secondary, constrained_viewport, icon_button, monospace,
-    on_secondary_container, padx, padxy, secondary_container,
+    blur_bg, button_primary, button_secondary, capture_pointer, constrained_viewport, error,
+    icon_button, monospace, on_secondary_container, padx, padxy, secondary_container,
 };
 use prototypes::GameTime;
-use simulation::Simulation;
+use simulation::{Simulation, SimulationOptions};
 
 use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::windows::settings::Settings;
 use crate::newgui::GuiState;
+use crate::rendering::Interpolation;
 use crate::uiworld::UiWorld;
 
 pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
     profiling::scope!("hud::time_controls");
-    let time = sim.read::<GameTime>().daytime;
+    let gametime = *sim.read::<GameTime>();
+    let time = gametime.daytime;
+    let season = gametime.season(sim.read::<SimulationOptions>().season_days);
+    let lagging = uiworld.read::<Interpolation>().is_lagging();
     let warp = &mut uiworld.write::<Settings>().time_warp;
     let mut gui = uiworld.write::<GuiState>();
     let depause_warp = &mut gui.depause_warp;
@@ -58,6 +62,16 @@ pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
                 );
             });
         });
+        if let Some(season) = season {
+            padx(5.0, || {
+                monospace(on_secondary_container(), season.to_string());
+            });
+        }
+        if lagging {
+            padx(5.0, || {
+                monospace(error(), "Simulation lagging");
+            });
+        }
         let mut l = List::row();
         l.main_axis_alignment = MainAxisAlignment::SpaceBetween;
         l.show(|| {
@@ -98,19 +112,21 @@ pub fn time_controls(uiworld: &UiWorld, sim: &Simulation) {
                 l.main_axis_alignment = MainAxisAlignment::End;
                 l.show(|| {
                     opaque(|| {
-                        blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
-                            padxy(10.0, 5.0, || {
-                                constrained(
-                                    Constraints::loose(Vec2::new(170.0, f32::INFINITY)),
-                                    || {
-                                        let mut l = List::column();
-                                        l.cross_axis_alignment = CrossAxisAlignment::Stretch;
-                                        l.main_axis_size = MainAxisSize::Min;
-                                        l.item_spacing = 5.0;
-                                        l.show(time_text);
-                                    },
-                                );
-                            });
+                        capture_pointer(|| {
+                            blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
+                                padxy(10.0, 5.0, || {
+                                    constrained(
+                                        Constraints::loose(Vec2::new(170.0, f32::INFINITY)),
+                                        || {
+                                            let mut l = List::column();
+                                            l.cross_axis_alignment = CrossAxisAlignment::Stretch;
+                                            l.main_axis_size = MainAxisSize::Min;
+                                            l.item_spacing = 5.0;
+                                            l.show(time_text);
+                                        },
+                                    );
+                                });
+                            })
                         });
                     });
                 });
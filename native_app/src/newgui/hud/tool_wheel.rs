@@ -0,0 +1,78 @@
+use yakui::{image, reflow, Alignment, Color, Dim2, Pivot, Vec2};
+
+use goryak::{primary, round_rect};
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::hud::toolbox::TOOLS;
+use crate::newgui::textures::UiTextures;
+use crate::newgui::Tool;
+use crate::uiworld::UiWorld;
+
+const WHEEL_RADIUS: f32 = 150.0;
+const ICON_SIZE: f32 = 64.0;
+
+#[derive(Default)]
+pub struct ToolWheelState {
+    pub open: bool,
+    pub hovered: Option<Tool>,
+}
+
+/// Radial tool selection, held open with the tool wheel binding.
+/// The tool is picked with the right stick (or the mouse) and selected on release.
+pub fn tool_wheel(uiworld: &UiWorld, _: &Simulation) {
+    profiling::scope!("hud::tool_wheel");
+    let mut state = uiworld.write::<ToolWheelState>();
+    let inp = uiworld.read::<InputMap>();
+
+    if !inp.act.contains(&InputAction::OpenToolWheel) {
+        if std::mem::take(&mut state.open) {
+            if let Some(tool) = state.hovered.take() {
+                *uiworld.write::<Tool>() = tool;
+            }
+        }
+        return;
+    }
+    state.open = true;
+
+    let pick_dir = if inp.look != geom::Vec2::ZERO {
+        Some(geom::vec2(inp.look.x, -inp.look.y))
+    } else {
+        let cam = &uiworld.camera().camera;
+        let center = geom::vec2(cam.viewport_w, cam.viewport_h) * 0.5;
+        (inp.screen - center).try_normalize_to(1.0)
+    };
+
+    let n = TOOLS.len();
+    let sector = std::f32::consts::TAU / n as f32;
+
+    if let Some(dir) = pick_dir {
+        // sectors start at the top and go clockwise in screen space
+        let ang = f32::atan2(dir.x, -dir.y).rem_euclid(std::f32::consts::TAU);
+        let i = ((ang + sector * 0.5) / sector) as usize % n;
+        state.hovered = Some(TOOLS[i].1);
+    }
+
+    let textures = uiworld.read::<UiTextures>();
+    for (i, (icon, tool)) in TOOLS.iter().enumerate() {
+        let ang = i as f32 * sector;
+        let offset = Vec2::new(ang.sin(), -ang.cos()) * WHEEL_RADIUS;
+        let hovered = state.hovered == Some(*tool);
+
+        reflow(
+            Alignment::CENTER,
+            Pivot::CENTER,
+            Dim2::pixels(offset.x, offset.y),
+            || {
+                let bg = if hovered {
+                    primary()
+                } else {
+                    Color::BLACK.with_alpha(0.5)
+                };
+                round_rect(ICON_SIZE * 0.5, bg, || {
+                    image(textures.get(icon), Vec2::splat(ICON_SIZE));
+                });
+            },
+        );
+    }
+}
@@ -1,8 +1,8 @@
 use common::FastMap;
-use engine::{Context, TextureBuilder};
-use yakui::widgets::List;
+use engine::{Context, GpuCategory, TextureBuilder};
+use yakui::widgets::{CountGrid, List};
 use yakui::{
-    reflow, use_state, Alignment, Color, CrossAxisAlignment, Dim2, MainAxisAlignment, Pivot,
+    reflow, Alignment, Color, CrossAxisAlignment, Dim2, MainAxisAlignment, MainAxisSize, Pivot,
     TextureId, Vec2,
 };
 
@@ -10,225 +10,499 @@ use crate::newgui::item_icon_yakui;
 use engine::wgpu::TextureFormat;
 use geom::{Camera, Degrees, Polygon, Vec3};
 use goryak::{
-    blur_bg, fixed_spacer, image_button, is_hovered, mincolumn, minrow, on_secondary_container,
-    padxy, primary, secondary_container, textc, titlec,
+    blur_bg, capture_pointer, error, fixed_spacer, image_button, is_hovered, mincolumn, minrow,
+    on_secondary_container, padxy, primary, primary_image_button, secondary_container,
+    selectable_label_primary, text_edit, textc, titlec,
 };
 use prototypes::{
-    prototypes_iter, BuildingPrototypeID, GoodsCompanyID, GoodsCompanyPrototype, Prototype,
-    RenderAsset,
+    prototypes_generation, prototypes_iter, BuildCategoryID, BuildCategoryPrototype,
+    BuildMenuEntry, BuildingGen, DockPrototype, GoodsCompanyPrototype, LeisurePrototype, Money,
+    RenderAsset, ServiceDepotPrototype, Size2D, WarehousePrototype,
 };
 use simulation::map::{BuildingKind, Zone};
+use simulation::scenario::ScenarioState;
 use simulation::world_command::WorldCommand;
+use simulation::Simulation;
 use std::path::PathBuf;
 use std::time::Instant;
 
+use crate::newgui::deposits::DepositsView;
+use crate::newgui::hud::toolbox::snap_properties;
 use crate::newgui::specialbuilding::{SpecialBuildKind, SpecialBuildingResource};
+use crate::newgui::textures::UiTextures;
+use crate::newgui::units::{fmt_distance, fmt_money};
 use crate::uiworld::UiWorld;
 
-pub fn special_building_properties(uiw: &UiWorld) {
+/// Icons shown on a row of a subcategory before wrapping
+const MENU_COLUMNS: usize = 6;
+
+/// What the build menu shows of a building, whatever its prototype
+struct MenuBuilding {
+    kind: BuildingKind,
+    name: &'static str,
+    label: &'static str,
+    asset: &'static RenderAsset,
+    size: Size2D,
+    price: Money,
+    menu: &'static BuildMenuEntry,
+}
+
+/// Every building that can be placed from the build menu, in prototype order
+fn menu_buildings() -> Vec<MenuBuilding> {
+    let mut v = vec![];
+    for descr in prototypes_iter::<GoodsCompanyPrototype>() {
+        v.push(MenuBuilding {
+            kind: BuildingKind::GoodsCompany(descr.id),
+            name: &descr.name,
+            label: &descr.label,
+            asset: &descr.asset,
+            size: descr.size,
+            price: descr.price,
+            menu: &descr.menu,
+        });
+    }
+    for descr in prototypes_iter::<DockPrototype>() {
+        v.push(MenuBuilding {
+            kind: BuildingKind::Dock(descr.id),
+            name: &descr.name,
+            label: &descr.label,
+            asset: &descr.asset,
+            size: descr.size,
+            price: descr.price,
+            menu: &descr.menu,
+        });
+    }
+    for descr in prototypes_iter::<WarehousePrototype>() {
+        v.push(MenuBuilding {
+            kind: BuildingKind::Warehouse(descr.id),
+            name: &descr.name,
+            label: &descr.label,
+            asset: &descr.asset,
+            size: descr.size,
+            price: descr.price,
+            menu: &descr.menu,
+        });
+    }
+    for descr in prototypes_iter::<ServiceDepotPrototype>() {
+        v.push(MenuBuilding {
+            kind: BuildingKind::ServiceDepot(descr.id),
+            name: &descr.name,
+            label: &descr.label,
+            asset: &descr.asset,
+            size: descr.size,
+            price: descr.price,
+            menu: &descr.menu,
+        });
+    }
+    for descr in prototypes_iter::<LeisurePrototype>() {
+        v.push(MenuBuilding {
+            kind: BuildingKind::Leisure(descr.id),
+            name: &descr.name,
+            label: &descr.label,
+            asset: &descr.asset,
+            size: descr.size,
+            price: descr.price,
+            menu: &descr.menu,
+        });
+    }
+    v
+}
+
+/// The buildings of the build menu sorted by category and subcategory.
+/// They are gathered again when the prototypes are reloaded.
+pub struct BuildMenu {
+    generation: u64,
+    buildings: Vec<MenuBuilding>,
+    /// None is the "Other" tab, for the buildings without a category
+    tab: Option<BuildCategoryID>,
+    selected: Option<BuildingKind>,
+    search: String,
+    /// Building whose tooltip is open, and when the pointer was last over it
+    tooltip: Option<(BuildingKind, Instant)>,
+}
+
+impl Default for BuildMenu {
+    fn default() -> Self {
+        Self {
+            // the prototypes are loaded at least once, so the menu is built on first use
+            generation: 0,
+            buildings: vec![],
+            tab: None,
+            selected: None,
+            search: String::new(),
+            tooltip: None,
+        }
+    }
+}
+
+impl BuildMenu {
+    fn refresh(&mut self) {
+        let generation = prototypes_generation();
+        if self.generation == generation {
+            return;
+        }
+        self.generation = generation;
+
+        let categories: Vec<BuildCategoryID> = BuildCategoryPrototype::iter_ids().collect();
+        let rank = |b: &MenuBuilding| {
+            let Some(cat) = b.menu.category else {
+                return (categories.len(), 0);
+            };
+            let cat_rank = categories.iter().position(|&c| c == cat).unwrap_or(0);
+            let sub_rank = b
+                .menu
+                .subcategory
+                .as_ref()
+                .and_then(|sub| cat.prototype().subcategories.iter().position(|s| s == sub))
+                .unwrap_or(0);
+            (cat_rank, sub_rank)
+        };
+
+        self.buildings = menu_buildings();
+        self.buildings.sort_by_key(rank);
+
+        if !self.has_tab(self.tab) {
+            self.tab = categories.first().copied();
+        }
+        if self
+            .selected
+            .is_some_and(|k| !self.buildings.iter().any(|b| b.kind == k))
+        {
+            self.selected = None;
+        }
+    }
+
+    /// The category shown, None for the "Other" tab
+    pub fn tab(&self) -> Option<BuildCategoryID> {
+        self.tab
+    }
+
+    fn has_tab(&self, tab: Option<BuildCategoryID>) -> bool {
+        self.buildings.iter().any(|b| b.menu.category == tab)
+    }
+
+    /// The buildings shown grouped by subcategory, those of the tab or the ones matching the
+    /// search in every tab
+    fn shown_groups(&self) -> Vec<Vec<&MenuBuilding>> {
+        let search = self.search.to_lowercase();
+        let mut groups: Vec<Vec<&MenuBuilding>> = vec![];
+        for b in &self.buildings {
+            let shown = match search.is_empty() {
+                true => b.menu.category == self.tab,
+                false => b.label.to_lowercase().contains(&search),
+            };
+            if !shown {
+                continue;
+            }
+            match groups.last_mut() {
+                Some(group)
+                    if group[0].menu.category == b.menu.category
+                        && group[0].menu.subcategory == b.menu.subcategory =>
+                {
+                    group.push(b)
+                }
+                _ => groups.push(vec![b]),
+            }
+        }
+        groups
+    }
+}
+
+fn subcategory_label(sub: &str) -> String {
+    let mut chars = sub.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn special_build_kind(b: &MenuBuilding) -> SpecialBuildKind {
+    let bkind = b.kind;
+    let (gen, has_zone, deposit) = match bkind {
+        BuildingKind::GoodsCompany(id) => {
+            let descr = id.prototype();
+            (descr.bgen, descr.zone.is_some(), descr.deposit)
+        }
+        BuildingKind::Leisure(id) => (id.prototype().bgen, false, None),
+        _ => (
+            BuildingGen::CenteredDoor {
+                vertical_factor: 1.0,
+            },
+            false,
+            None,
+        ),
+    };
+    SpecialBuildKind {
+        road_snap: true,
+        make: Box::new(move |args| {
+            vec![WorldCommand::MapBuildSpecialBuilding {
+                pos: args.obb,
+                kind: bkind,
+                gen,
+                zone: has_zone
+                    .then(|| Zone::new(Polygon::from(args.obb.corners.as_slice()), geom::Vec2::X)),
+                connected_road: args.connected_road,
+            }]
+        }),
+        size: b.size,
+        asset: b.asset.clone(),
+        shore: matches!(bkind, BuildingKind::Dock(_)),
+        deposit,
+    }
+}
+
+pub fn special_building_properties(uiw: &UiWorld, sim: &Simulation) {
     let mut state = uiw.write::<SpecialBuildingResource>();
+    let mut menu = uiw.write::<BuildMenu>();
+    menu.refresh();
     let icons = uiw.read::<BuildingIcons>();
+    let texs = uiw.read::<UiTextures>();
+    let scenario = sim.read::<ScenarioState>();
+    let population = sim.world().humans.len() as u32;
+
+    mincolumn(5.0, || {
+        padxy(0.0, 5.0, || {
+            let mut l = List::row();
+            l.main_axis_alignment = MainAxisAlignment::Center;
+            l.cross_axis_alignment = CrossAxisAlignment::Center;
+            l.item_spacing = 10.0;
+            l.show(|| {
+                snap_properties(uiw);
+
+                let mut deposits = uiw.write::<DepositsView>();
+                if selectable_label_primary(deposits.enabled, "Deposits").clicked {
+                    deposits.enabled = !deposits.enabled;
+                }
+                drop(deposits);
+
+                let searching = !menu.search.is_empty();
+                let tabs = BuildCategoryPrototype::iter()
+                    .map(|cat| (Some(cat.id), &*cat.label, texs.try_get(&cat.icon)))
+                    .chain(std::iter::once((None, "Other", None)));
+                for (tab, label, icon) in tabs {
+                    if !menu.has_tab(tab) {
+                        continue;
+                    }
+                    let selected = !searching && menu.tab == tab;
+                    let clicked = match icon {
+                        Some(icon) => {
+                            primary_image_button(icon, Vec2::splat(40.0), selected, label).clicked
+                        }
+                        None => selectable_label_primary(selected, label).clicked,
+                    };
+                    if clicked {
+                        menu.tab = tab;
+                        menu.search.clear();
+                    }
+                }
+
+                text_edit(200.0, &mut menu.search, "Search");
+            });
+        });
+
+        let mut tooltip = menu.tooltip;
+        let mut clicked = None;
 
-    padxy(0.0, 10.0, || {
         let mut l = List::row();
         l.main_axis_alignment = MainAxisAlignment::Center;
-        l.cross_axis_alignment = CrossAxisAlignment::Center;
-        l.item_spacing = 10.0;
+        l.cross_axis_alignment = CrossAxisAlignment::Start;
+        l.item_spacing = 20.0;
         l.show(|| {
-            let tooltip_active = use_state(|| Option::<(GoodsCompanyID, Instant)>::None);
-            for descr in prototypes_iter::<GoodsCompanyPrototype>() {
-                let Some(tex_id) = icons.ids.get(&descr.parent().id) else {
-                    continue;
-                };
-
-                minrow(0.0, || {
-                    let default_col = Color::WHITE;
-                    let hover_col = primary();
-                    let active_col = default_col.with_alpha(0.5);
-
-                    let resp = image_button(
-                        *tex_id,
-                        Vec2::splat(64.0),
-                        default_col,
-                        hover_col,
-                        active_col,
-                        "",
-                    );
-
-                    if resp.hovering {
-                        tooltip_active.set(Some((descr.id, Instant::now())));
-                    }
-
-                    if tooltip_active
-                        .borrow()
-                        .map(|(id, last)| id == descr.id && last.elapsed().as_secs_f32() < 0.2)
-                        .unwrap_or(false)
-                    {
-                        reflow(
-                            Alignment::TOP_CENTER,
-                            Pivot::BOTTOM_CENTER,
-                            Dim2::pixels(0.0, -20.0),
-                            || {
-                                let hov_resp = is_hovered(|| {
-                                    blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
-                                        padxy(10.0, 10.0, || {
-                                            mincolumn(3.0, || {
-                                                titlec(on_secondary_container(), &descr.label);
-                                                textc(
-                                                    on_secondary_container(),
-                                                    format!("workers: {}", descr.n_workers),
-                                                );
-
-                                                if let Some(ref recipe) = descr.recipe {
-                                                    fixed_spacer((0.0, 10.0));
-                                                    if !recipe.consumption.is_empty() {
-                                                        textc(
-                                                            on_secondary_container(),
-                                                            "consumption:",
-                                                        );
-                                                        for item in &recipe.consumption {
-                                                            item_icon_yakui(
-                                                                uiw,
-                                                                item.id,
-                                                                item.amount,
-                                                            );
-                                                        }
-                                                        fixed_spacer((0.0, 10.0));
-                                                    }
-                                                    if !recipe.production.is_empty() {
-                                                        textc(
-                                                            on_secondary_container(),
-                                                            "production:",
-                                                        );
-                                                        for item in &recipe.production {
-                                                            item_icon_yakui(
-                                                                uiw,
-                                                                item.id,
-                                                                item.amount,
-                                                            );
-                                                        }
-                                                        fixed_spacer((0.0, 10.0));
-                                                    }
-                                                    textc(
-                                                        on_secondary_container(),
-                                                        format!("time: {}", recipe.duration),
-                                                    );
-                                                    textc(
-                                                        on_secondary_container(),
-                                                        format!(
-                                                            "storage multiplier: {}",
-                                                            recipe.storage_multiplier
-                                                        ),
-                                                    );
-                                                }
-
-                                                if let Some(p) = descr.power_consumption {
-                                                    fixed_spacer((0.0, 10.0));
-                                                    textc(
-                                                        on_secondary_container(),
-                                                        format!("Power: {}", p),
-                                                    );
-                                                }
-                                                if let Some(p) = descr.power_production {
-                                                    fixed_spacer((0.0, 10.0));
-                                                    textc(
-                                                        on_secondary_container(),
-                                                        format!("Power production: {}", p),
-                                                    );
-                                                }
-                                            });
-                                        });
-                                    });
-                                });
-                                if hov_resp.hovered {
-                                    tooltip_active.set(Some((descr.id, Instant::now())));
-                                }
-                            },
-                        );
-                    }
+            let groups = menu.shown_groups();
+            if groups.is_empty() {
+                padxy(0.0, 10.0, || {
+                    textc(on_secondary_container(), "No building found");
+                });
+                return;
+            }
 
-                    if resp.clicked || state.opt.is_none() {
-                        let bkind = BuildingKind::GoodsCompany(descr.id);
-                        let bgen = descr.bgen;
-                        let has_zone = descr.zone.is_some();
-                        state.opt = Some(SpecialBuildKind {
-                            road_snap: true,
-                            make: Box::new(move |args| {
-                                vec![WorldCommand::MapBuildSpecialBuilding {
-                                    pos: args.obb,
-                                    kind: bkind,
-                                    gen: bgen,
-                                    zone: has_zone.then(|| {
-                                        Zone::new(
-                                            Polygon::from(args.obb.corners.as_slice()),
-                                            geom::Vec2::X,
-                                        )
-                                    }),
-                                    connected_road: args.connected_road,
-                                }]
-                            }),
-                            size: descr.size,
-                            asset: descr.asset.clone(),
-                        });
+            for group in &groups {
+                mincolumn(3.0, || {
+                    if let Some(ref sub) = group[0].menu.subcategory {
+                        textc(on_secondary_container(), subcategory_label(sub));
                     }
+                    let mut grid = CountGrid::col(group.len().min(MENU_COLUMNS));
+                    grid.main_axis_size = MainAxisSize::Min;
+                    grid.show(|| {
+                        for b in group {
+                            let locked = scenario.locked_reason(b.kind, population);
+                            let selected = menu.selected == Some(b.kind);
+                            if menu_button(
+                                uiw,
+                                &icons,
+                                b,
+                                locked.as_deref(),
+                                selected,
+                                &mut tooltip,
+                            ) && locked.is_none()
+                            {
+                                clicked = Some(b.kind);
+                            }
+                        }
+                    });
                 });
             }
+
+            // pick something so the tool has a building to place
+            if clicked.is_none() && state.opt.is_none() {
+                clicked = groups
+                    .iter()
+                    .flatten()
+                    .find(|b| scenario.locked_reason(b.kind, population).is_none())
+                    .map(|b| b.kind);
+            }
         });
+
+        menu.tooltip = tooltip;
+        if let Some(b) = clicked.and_then(|kind| menu.buildings.iter().find(|b| b.kind == kind)) {
+            state.opt = Some(special_build_kind(b));
+            menu.selected = clicked;
+        }
     });
+}
 
-    /*
-    let bdescrpt_w = 180.0;
-
-    if let Some(descr) = picked_descr {
-        Window::new("Building description")
-            .default_width(bdescrpt_w)
-            .auto_sized()
-            .fixed_pos([
-                w - toolbox_w - building_select_w - bdescrpt_w,
-                h * 0.5 - 30.0,
-            ])
-            .hscroll(false)
-            .title_bar(true)
-            .collapsible(false)
-            .resizable(false)
-            .show(ui.ctx(), |ui| {
-                ui.label(format!("workers: {}", descr.n_workers));
-
-                if let Some(ref recipe) = descr.recipe {
-                    ui.add_space(10.0);
-                    if !recipe.consumption.is_empty() {
-                        ui.label("consumption:");
-                        for item in &recipe.consumption {
-                            item_icon(ui, uiworld, item.id, item.amount);
-                        }
-                        ui.add_space(10.0);
-                    }
-                    if !recipe.production.is_empty() {
-                        ui.label("production:");
-                        for item in &recipe.production {
-                            item_icon(ui, uiworld, item.id, item.amount);
-                        }
-                        ui.add_space(10.0);
-                    }
-                    ui.label(format!("time: {}", recipe.duration));
-                    ui.label(format!("storage multiplier: {}", recipe.storage_multiplier));
+/// The icon of a building in the menu, greyed out when locked, with its tooltip.
+/// Returns true if it was clicked.
+fn menu_button(
+    uiw: &UiWorld,
+    icons: &BuildingIcons,
+    b: &MenuBuilding,
+    locked: Option<&str>,
+    selected: bool,
+    tooltip: &mut Option<(BuildingKind, Instant)>,
+) -> bool {
+    let mut clicked = false;
+    minrow(0.0, || {
+        let Some(&tex_id) = icons.ids.get(&b.kind) else {
+            clicked = match locked {
+                Some(reason) => {
+                    textc(error(), format!("{} ({})", b.label, reason));
+                    false
                 }
+                None => selectable_label_primary(selected, b.label).clicked,
+            };
+            return;
+        };
 
-                if let Some(p) = descr.power_consumption {
-                    ui.add_space(10.0);
-                    ui.label(format!("Power: {}", p));
-                }
-                if let Some(p) = descr.power_production {
-                    ui.add_space(10.0);
-                    ui.label(format!("Power production: {}", p));
+        let (default_col, hover_col) = match (locked.is_some(), selected) {
+            (true, _) => (Color::WHITE.with_alpha(0.3), Color::WHITE.with_alpha(0.5)),
+            (false, true) => (primary().lerp(&Color::WHITE, 0.3), primary()),
+            (false, false) => (Color::WHITE, primary()),
+        };
+        let active_col = default_col.with_alpha(0.5);
+
+        let resp = image_button(
+            tex_id,
+            Vec2::splat(64.0),
+            default_col,
+            hover_col,
+            active_col,
+            "",
+        );
+        clicked = resp.clicked;
+
+        if resp.hovering {
+            *tooltip = Some((b.kind, Instant::now()));
+        }
+
+        if !tooltip
+            .map(|(kind, last)| kind == b.kind && last.elapsed().as_secs_f32() < 0.2)
+            .unwrap_or(false)
+        {
+            return;
+        }
+        reflow(
+            Alignment::TOP_CENTER,
+            Pivot::BOTTOM_CENTER,
+            Dim2::pixels(0.0, -20.0),
+            || {
+                let hov_resp = is_hovered(|| {
+                    capture_pointer(|| {
+                        blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
+                            padxy(10.0, 10.0, || {
+                                mincolumn(3.0, || building_tooltip(uiw, b, locked));
+                            });
+                        })
+                    });
+                });
+                if hov_resp.hovered {
+                    *tooltip = Some((b.kind, Instant::now()));
                 }
-            });
-     */
+            },
+        );
+    });
+    clicked
+}
+
+/// Details of the building pulled from its prototype
+fn building_tooltip(uiw: &UiWorld, b: &MenuBuilding, locked: Option<&str>) {
+    titlec(on_secondary_container(), b.label);
+    if let Some(reason) = locked {
+        textc(error(), format!("Locked: {}", reason));
+    }
+    textc(
+        on_secondary_container(),
+        format!("price: {}", fmt_money(b.price)),
+    );
+    textc(
+        on_secondary_container(),
+        format!(
+            "size: {} x {}",
+            fmt_distance(b.size.w),
+            fmt_distance(b.size.h)
+        ),
+    );
+
+    let BuildingKind::GoodsCompany(id) = b.kind else {
+        return;
+    };
+    let descr = id.prototype();
+    textc(
+        on_secondary_container(),
+        format!("workers: {}", descr.n_workers),
+    );
+
+    if let Some(ref recipe) = descr.recipe {
+        fixed_spacer((0.0, 10.0));
+        if !recipe.consumption.is_empty() {
+            textc(on_secondary_container(), "consumption:");
+            for item in &recipe.consumption {
+                item_icon_yakui(uiw, item.id, item.amount);
+            }
+            fixed_spacer((0.0, 10.0));
+        }
+        if !recipe.production.is_empty() {
+            textc(on_secondary_container(), "production:");
+            for item in &recipe.production {
+                item_icon_yakui(uiw, item.id, item.amount);
+            }
+            fixed_spacer((0.0, 10.0));
+        }
+        textc(
+            on_secondary_container(),
+            format!("time: {}", recipe.duration),
+        );
+        textc(
+            on_secondary_container(),
+            format!("storage multiplier: {}", recipe.storage_multiplier),
+        );
+    }
+
+    if let Some(p) = descr.power_consumption {
+        fixed_spacer((0.0, 10.0));
+        textc(on_secondary_container(), format!("Power: {}", p));
+    }
+    if let Some(p) = descr.power_production {
+        fixed_spacer((0.0, 10.0));
+        textc(on_secondary_container(), format!("Power production: {}", p));
+    }
 }
 
 #[derive(Default)]
 pub struct BuildingIcons {
-    ids: FastMap<BuildingPrototypeID, TextureId>,
+    ids: FastMap<BuildingKind, TextureId>,
+    /// Generation of the prototypes the icons were made for
+    pub generation: u64,
 }
 
 pub fn do_icons(ctx: &mut Context, uiw: &UiWorld) {
@@ -241,17 +515,18 @@ pub fn do_icons(ctx: &mut Context, uiw: &UiWorld) {
     cam.yaw = Degrees(-130.0).into();
 
     state.ids.clear();
+    state.generation = prototypes_generation();
 
     let gfx = &mut ctx.gfx;
 
-    for building in prototypes::BuildingPrototype::iter() {
-        if state.ids.contains_key(&building.id) {
+    for building in menu_buildings() {
+        if state.ids.contains_key(&building.kind) {
             continue;
         }
         if let RenderAsset::Sprite { ref path } = building.asset {
             let t = gfx.texture(path, "building icon");
             let tex_id = ctx.yakui.add_texture(&t);
-            state.ids.insert(building.id, tex_id);
+            state.ids.insert(building.kind, tex_id);
             continue;
         }
 
@@ -260,12 +535,12 @@ pub fn do_icons(ctx: &mut Context, uiw: &UiWorld) {
         };
         let cache_path = PathBuf::from(format!(
             "assets/generated/building_icons/{}.png",
-            building.id.hash()
+            common::hash_u64(building.name)
         ));
         //if std::fs::metadata(&cache_path).is_ok() {
         //    let t = TextureBuilder::from_path(&cache_path).build(&gfx.device, &gfx.queue);
         //    let tex_id = yakui.add_texture(&t);
-        //    state.ids.insert(building.id, tex_id);
+        //    state.ids.insert(building.kind, tex_id);
         //    continue;
         //}
 
@@ -275,6 +550,7 @@ pub fn do_icons(ctx: &mut Context, uiw: &UiWorld) {
 
         let t = TextureBuilder::empty(128, 128, 1, TextureFormat::Rgba8UnormSrgb)
             .with_label("building icon")
+            .with_category(GpuCategory::UI)
             .with_usage(
                 engine::wgpu::TextureUsages::COPY_DST
                     | engine::wgpu::TextureUsages::COPY_SRC
@@ -285,6 +561,7 @@ pub fn do_icons(ctx: &mut Context, uiw: &UiWorld) {
 
         let t_msaa = TextureBuilder::empty(128, 128, 1, TextureFormat::Rgba8UnormSrgb)
             .with_label("building icon msaa")
+            .with_category(GpuCategory::UI)
             .with_usage(engine::wgpu::TextureUsages::RENDER_ATTACHMENT)
             .with_sample_count(4)
             .build_no_queue(&gfx.device);
@@ -300,6 +577,6 @@ pub fn do_icons(ctx: &mut Context, uiw: &UiWorld) {
 
         let tex_id = ctx.yakui.add_texture(&t);
 
-        state.ids.insert(building.id, tex_id);
+        state.ids.insert(building.kind, tex_id);
     }
 }
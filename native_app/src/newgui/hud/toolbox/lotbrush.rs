@@ -0,0 +1,67 @@
+use yakui::widgets::List;
+use yakui::{CrossAxisAlignment, MainAxisAlignment};
+
+use goryak::{fixed_spacer, on_secondary_container, padxy, selectable_label_primary, textc};
+use simulation::map::LotKind;
+
+use crate::newgui::districts::DistrictPaintResource;
+use crate::newgui::hud::toolbox::updown_distance;
+use crate::newgui::lotbrush::{LotBrushResource, ZoneShape};
+use crate::uiworld::UiWorld;
+
+pub fn lotbrush_properties(uiw: &UiWorld) {
+    let state = &mut *uiw.write::<LotBrushResource>();
+    let districts = &mut *uiw.write::<DistrictPaintResource>();
+
+    padxy(0.0, 10.0, || {
+        let mut l = List::row();
+        l.main_axis_alignment = MainAxisAlignment::Center;
+        l.cross_axis_alignment = CrossAxisAlignment::Center;
+        l.item_spacing = 10.0;
+        l.show(|| {
+            if selectable_label_primary(districts.active, "Districts").clicked {
+                districts.active = !districts.active;
+            }
+
+            fixed_spacer((30.0, 0.0));
+
+            if districts.active {
+                textc(
+                    on_secondary_container(),
+                    "Click the corners of the district, then its first corner again to close it",
+                );
+                return;
+            }
+
+            let kind_choices = &[
+                (LotKind::Residential, "Residential"),
+                (LotKind::Commercial, "Commercial"),
+                (LotKind::Industrial, "Industrial"),
+                (LotKind::Unassigned, "Dezone"),
+            ];
+
+            for (kind, label) in kind_choices {
+                if selectable_label_primary(state.kind == *kind, label).clicked {
+                    state.kind = *kind;
+                }
+            }
+
+            fixed_spacer((30.0, 0.0));
+
+            let shape_choices = &[
+                (ZoneShape::Brush, "Brush"),
+                (ZoneShape::Rectangle, "Rectangle"),
+            ];
+
+            for (shape, label) in shape_choices {
+                if selectable_label_primary(state.shape == *shape, label).clicked {
+                    state.shape = *shape;
+                }
+            }
+
+            if state.shape == ZoneShape::Brush {
+                updown_distance(&mut state.radius, 5.0);
+            }
+        });
+    });
+}
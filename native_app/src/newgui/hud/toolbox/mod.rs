@@ -4,19 +4,23 @@ use yakui::{
     CrossAxisAlignment, Dim2, MainAxisAlignment, MainAxisSize, Pivot, Vec2,
 };
 
+use geom::Degrees;
 use goryak::{
-    blur_bg, button_primary, constrained_viewport, fixed_spacer, icon_button, image_button,
-    monospace, on_primary, outline, padxy, primary, primary_container, round_rect,
-    secondary_container,
+    blur_bg, button_primary, capture_pointer, constrained_viewport, fixed_spacer, highlight_target,
+    icon_button, image_button, mincolumn, minrow, monospace, on_primary, outline, padxy, primary,
+    primary_container, round_rect, secondary_container, selectable_label_primary,
 };
 use simulation::Simulation;
 
 use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::snapping::{SnapSettings, ANGLE_INCREMENTS};
 use crate::newgui::textures::UiTextures;
+use crate::newgui::units::fmt_distance;
 use crate::newgui::Tool;
 use crate::uiworld::UiWorld;
 
 pub mod building;
+pub mod lotbrush;
 pub mod roadbuild;
 pub mod roadedit;
 pub mod terraforming;
@@ -42,23 +46,27 @@ pub fn new_toolbox(uiworld: &UiWorld, sim: &Simulation) {
                     l.cross_axis_alignment = CrossAxisAlignment::Stretch;
                     l.show(|| {
                         let mut needs_outline = false;
-                        blur_bg(primary_container().with_alpha(0.3), 0.0, || {
-                            needs_outline = tool_properties(uiworld, sim);
+                        capture_pointer(|| {
+                            blur_bg(primary_container().with_alpha(0.3), 0.0, || {
+                                needs_outline = tool_properties(uiworld, sim);
+                            })
                         });
                         if needs_outline {
                             colored_box_container(outline().with_alpha(0.5), || {
                                 fixed_spacer((1.0, 1.0));
                             });
                         }
-                        blur_bg(secondary_container().with_alpha(0.3), 0.0, || {
-                            padxy(0.0, 10.0, || {
-                                let mut l = List::row();
-                                l.main_axis_alignment = MainAxisAlignment::Center;
-                                l.item_spacing = 10.0;
-                                l.show(|| {
-                                    tools_list(uiworld);
+                        capture_pointer(|| {
+                            blur_bg(secondary_container().with_alpha(0.3), 0.0, || {
+                                padxy(0.0, 10.0, || {
+                                    let mut l = List::row();
+                                    l.main_axis_alignment = MainAxisAlignment::Center;
+                                    l.item_spacing = 10.0;
+                                    l.show(|| {
+                                        tools_list(uiworld);
+                                    });
                                 });
-                            });
+                            })
                         });
                     });
                 });
@@ -67,45 +75,48 @@ pub fn new_toolbox(uiworld: &UiWorld, sim: &Simulation) {
     });
 }
 
-fn tool_properties(uiw: &UiWorld, _sim: &Simulation) -> bool {
+fn tool_properties(uiw: &UiWorld, sim: &Simulation) -> bool {
     let tool = *uiw.read::<Tool>();
 
     match tool {
         Tool::Hand => return false,
         Tool::Bulldozer => return false,
-        Tool::LotBrush => return false,
+        Tool::LotBrush => {
+            lotbrush::lotbrush_properties(uiw);
+        }
         Tool::RoadbuildStraight | Tool::RoadbuildCurved => {
             roadbuild::roadbuild_properties(uiw);
         }
         Tool::RoadEditor => {
-            roadedit::roadedit_properties(uiw);
+            roadedit::roadedit_properties(uiw, sim);
         }
         Tool::SpecialBuilding => {
-            building::special_building_properties(uiw);
+            building::special_building_properties(uiw, sim);
         }
         Tool::Train => {
             train::train_properties(uiw);
         }
         Tool::Terraforming => {
-            terraforming::terraform_properties(uiw);
+            terraforming::terraform_properties(uiw, sim);
         }
     }
     true
 }
 
+/// Icon name and tool shown in the toolbox and the tool wheel
+pub const TOOLS: [(&str, Tool); 8] = [
+    ("toolbar_straight_road", Tool::RoadbuildStraight),
+    ("toolbar_curved_road", Tool::RoadbuildCurved),
+    ("toolbar_road_edit", Tool::RoadEditor),
+    ("toolbar_housetool", Tool::LotBrush),
+    ("toolbar_companies", Tool::SpecialBuilding),
+    ("toolbar_bulldozer", Tool::Bulldozer),
+    ("toolbar_train", Tool::Train),
+    ("toolbar_terraform", Tool::Terraforming),
+];
+
 fn tools_list(uiworld: &UiWorld) {
-    let tools = [
-        ("toolbar_straight_road", Tool::RoadbuildStraight),
-        ("toolbar_curved_road", Tool::RoadbuildCurved),
-        ("toolbar_road_edit", Tool::RoadEditor),
-        ("toolbar_housetool", Tool::LotBrush),
-        ("toolbar_companies", Tool::SpecialBuilding),
-        ("toolbar_bulldozer", Tool::Bulldozer),
-        ("toolbar_train", Tool::Train),
-        ("toolbar_terraform", Tool::Terraforming),
-    ];
-
-    for (name, tool) in &tools {
+    for (name, tool) in &TOOLS {
         column(|| {
             let (default_col, hover_col) = if *tool == *uiworld.read::<Tool>() {
                 let c = primary().lerp(&Color::WHITE, 0.3);
@@ -113,18 +124,20 @@ fn tools_list(uiworld: &UiWorld) {
             } else {
                 (Color::WHITE, Color::WHITE.with_alpha(0.7))
             };
-            if image_button(
-                uiworld.read::<UiTextures>().get(name),
-                Vec2::new(64.0, 64.0),
-                default_col,
-                hover_col,
-                primary(),
-                "",
-            )
-            .clicked
-            {
-                *uiworld.write::<Tool>() = *tool;
-            }
+            highlight_target(name, || {
+                if image_button(
+                    uiworld.read::<UiTextures>().get(name),
+                    Vec2::new(64.0, 64.0),
+                    default_col,
+                    hover_col,
+                    primary(),
+                    "",
+                )
+                .clicked
+                {
+                    *uiworld.write::<Tool>() = *tool;
+                }
+            });
 
             if *tool == *uiworld.read::<Tool>() {
                 select_triangle(uiworld);
@@ -156,7 +169,16 @@ pub fn updown_button(text: &str) -> Button {
     b
 }
 
-pub fn updown_value(v: &mut f32, step: f32, suffix: &'static str) -> bool {
+pub fn updown_value(v: &mut f32, step: f32, suffix: &str) -> bool {
+    updown(v, step, |v| format!("{:.0}{}", v, suffix))
+}
+
+/// A distance in meters, shown in the units of the settings
+pub fn updown_distance(v: &mut f32, step: f32) -> bool {
+    updown(v, step, fmt_distance)
+}
+
+fn updown(v: &mut f32, step: f32, text: impl FnOnce(f32) -> String) -> bool {
     let mut changed = false;
     let mut l = List::column();
     l.cross_axis_alignment = CrossAxisAlignment::Center;
@@ -169,7 +191,7 @@ pub fn updown_value(v: &mut f32, step: f32, suffix: &'static str) -> bool {
         }
         round_rect(3.0, primary(), || {
             padxy(5.0, 1.0, || {
-                monospace(on_primary(), format!("{:.0}{}", *v, suffix));
+                monospace(on_primary(), text(*v));
             });
         });
         if updown_button("caret-down").show().clicked {
@@ -179,3 +201,22 @@ pub fn updown_value(v: &mut f32, step: f32, suffix: &'static str) -> bool {
     });
     changed
 }
+
+/// Snapping toggle and angle increment, shared by the construction tools
+pub fn snap_properties(uiw: &UiWorld) {
+    let mut snap = uiw.write::<SnapSettings>();
+
+    mincolumn(4.0, || {
+        if selectable_label_primary(snap.enabled, "Snapping").clicked {
+            snap.enabled = !snap.enabled;
+        }
+        minrow(2.0, || {
+            for inc in ANGLE_INCREMENTS {
+                let selected = snap.angle_increment.0 == inc;
+                if selectable_label_primary(selected, &format!("{}°", inc)).clicked {
+                    snap.angle_increment = Degrees(inc);
+                }
+            }
+        });
+    });
+}
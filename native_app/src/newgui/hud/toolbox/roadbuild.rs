@@ -4,11 +4,12 @@ use yakui::{
     Pivot, Vec2,
 };
 
-use goryak::{image_button, mincolumn, minrow, padxy, primary};
-use simulation::map::LanePatternBuilder;
+use goryak::{image_button, mincolumn, minrow, padxy, primary, selectable_label_primary};
+use simulation::map::{LanePatternBuilder, MIN_BLOCK_SIZE};
 
-use crate::newgui::hud::toolbox::updown_value;
+use crate::newgui::hud::toolbox::{snap_properties, updown_distance};
 use crate::newgui::roadbuild::{HeightReference, RoadBuildResource, Snapping};
+use crate::newgui::roadlayout::{RoadLayoutResource, RoadMode};
 use crate::newgui::textures::UiTextures;
 use crate::uiworld::UiWorld;
 
@@ -25,6 +26,9 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
             let active = (c, c.with_alpha(0.7));
             let default = (Color::WHITE.with_alpha(0.3), Color::WHITE.with_alpha(0.5));
 
+            snap_properties(uiw);
+            layout_properties(uiw);
+
             mincolumn(4.0, || {
                 minrow(2.0, || {
                     let (snapping_none, snapping_grid, snapping_angel) = match state.snapping {
@@ -129,7 +133,7 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
                 });
             });
             // Road elevation
-            updown_value(&mut state.height_offset, 2.0, "m");
+            updown_distance(&mut state.height_offset, 2.0);
 
             // image name, label, builder
             let builders: &[(&str, &str, LanePatternBuilder)] = &[
@@ -242,3 +246,36 @@ pub fn roadbuild_properties(uiw: &UiWorld) {
         });
     });
 }
+
+/// Single road, parallel road or grid, with the options of the grid
+fn layout_properties(uiw: &UiWorld) {
+    let mut layout = uiw.write::<RoadLayoutResource>();
+
+    mincolumn(4.0, || {
+        minrow(2.0, || {
+            for (mode, label) in [
+                (RoadMode::Single, "Single"),
+                (RoadMode::Parallel, "Parallel"),
+                (RoadMode::Grid, "Grid"),
+            ] {
+                if selectable_label_primary(layout.mode == mode, label).clicked {
+                    layout.mode = mode;
+                }
+            }
+        });
+        if layout.mode == RoadMode::Single {
+            return;
+        }
+        if selectable_label_primary(layout.bridge_steep, "Bridge steep parts").clicked {
+            layout.bridge_steep = !layout.bridge_steep;
+        }
+    });
+
+    if layout.mode == RoadMode::Grid {
+        // Block size along x and y
+        updown_distance(&mut layout.block_size.x, 10.0);
+        updown_distance(&mut layout.block_size.y, 10.0);
+        layout.block_size.x = layout.block_size.x.max(MIN_BLOCK_SIZE);
+        layout.block_size.y = layout.block_size.y.max(MIN_BLOCK_SIZE);
+    }
+}
@@ -1,19 +1,40 @@
-use yakui::widgets::List;
+use yakui::widgets::{CountGrid, List};
 use yakui::{
-    column, image, reflow, Alignment, CrossAxisAlignment, Dim2, MainAxisAlignment, Pivot, Vec2,
+    column, image, reflow, Alignment, CrossAxisAlignment, Dim2, MainAxisAlignment, MainAxisSize,
+    Pivot, Vec2,
 };
 
-use goryak::{padxy, primary_image_button};
-use simulation::map::LightPolicy;
+use goryak::{
+    button_primary, checkbox_value, minrow, on_secondary_container, padxy, primary_image_button,
+    text_edit, textc,
+};
+use prototypes::{GameDuration, GameTime};
+use simulation::map::{
+    IntersectionID, LightPolicy, RoadID, MAX_SPEED_LIMIT, MAX_STREET_NAME_LEN, MIN_SPEED_LIMIT,
+};
+use simulation::transportation::intersection_stats::IntersectionStats;
+use simulation::Simulation;
 
 use crate::newgui::hud::toolbox;
 use crate::newgui::hud::toolbox::select_triangle;
 use crate::newgui::roadeditor::RoadEditorResource;
 use crate::newgui::textures::UiTextures;
+use crate::newgui::units::UnitSystem;
 use crate::uiworld::UiWorld;
 
-pub fn roadedit_properties(uiw: &UiWorld) {
+pub fn roadedit_properties(uiw: &UiWorld, sim: &Simulation) {
     let state = &mut *uiw.write::<RoadEditorResource>();
+    if let Some(road) = state.road {
+        roadworks(sim, road);
+        street_name(uiw, road, &mut state.road_name);
+        if let Some(ref mut electrified) = state.electrified {
+            electrification(uiw, road, electrified);
+        }
+        if let Some(ref mut limit) = state.speed_limit {
+            speed_limit(uiw, &state.corridor, limit);
+        }
+        return;
+    }
     let Some(ref mut v) = state.inspect else {
         return;
     };
@@ -102,8 +123,135 @@ pub fn roadedit_properties(uiw: &UiWorld) {
             }
 
             if let Some(ref mut roundabout) = v.turn_policy.roundabout {
-                state.dirty |= toolbox::updown_value(&mut roundabout.radius, 2.0, "m");
+                state.dirty |= toolbox::updown_distance(&mut roundabout.radius, 2.0);
+            }
+        });
+    });
+
+    intersection_traffic(sim, v.id);
+}
+
+/// Traffic through the selected intersection over the last game day, per approach
+fn intersection_traffic(sim: &Simulation, id: IntersectionID) {
+    let map = sim.map();
+    let stats = sim.read::<IntersectionStats>();
+    let hour = IntersectionStats::hour(&sim.read::<GameTime>());
+    let Some(traffic) = stats.get(id) else {
+        textc(on_secondary_container(), "No traffic yet");
+        return;
+    };
+    let summary = traffic.summary(hour);
+    padxy(0.0, 5.0, || {
+        textc(
+            on_secondary_container(),
+            format!(
+                "{:.0} vehicles/h, {:.1}s average wait, queue of {} at most, {} gridlock(s)",
+                summary.entered_per_hour,
+                summary.total.avg_wait(),
+                summary.total.max_queue,
+                summary.recoveries,
+            ),
+        );
+    });
+
+    let mut grid = CountGrid::col(4);
+    grid.main_axis_size = MainAxisSize::Min;
+    grid.show(|| {
+        for header in ["Approach", "Vehicles", "Avg. wait", "Max queue"] {
+            padxy(5.0, 2.0, || textc(on_secondary_container(), header));
+        }
+        for (lane, counts) in traffic.approaches(hour) {
+            let name = map
+                .lanes()
+                .get(lane)
+                .and_then(|l| map.roads().get(l.parent))
+                .map(|r| r.name.clone())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| "Unnamed road".to_string());
+            padxy(5.0, 2.0, || textc(on_secondary_container(), name));
+            padxy(5.0, 2.0, || {
+                textc(on_secondary_container(), counts.entered.to_string())
+            });
+            padxy(5.0, 2.0, || {
+                textc(
+                    on_secondary_container(),
+                    format!("{:.1}s", counts.avg_wait()),
+                )
+            });
+            padxy(5.0, 2.0, || {
+                textc(on_secondary_container(), counts.max_queue.to_string())
+            });
+        }
+    });
+}
+
+/// Progress of the works on the selected road, if there are any
+fn roadworks(sim: &Simulation, road: RoadID) {
+    let map = sim.map();
+    let Some(works) = map.roads().get(road).and_then(|r| r.roadworks.as_ref()) else {
+        return;
+    };
+    let closed = map.roads()[road]
+        .lanes_iter()
+        .filter(|&(lane, _)| map.lanes().get(lane).is_some_and(|l| l.closed))
+        .count();
+    padxy(0.0, 5.0, || {
+        textc(
+            on_secondary_container(),
+            format!(
+                "Roadworks ({}): {:.0}% done, {} left, {} lane(s) closed",
+                works.kind.label(),
+                works.progress() * 100.0,
+                GameDuration::from_secs(works.remaining().ceil() as u64),
+                closed,
+            ),
+        );
+    });
+}
+
+/// Renames the selected street, the addresses along it follow
+fn street_name(uiw: &UiWorld, road: RoadID, name: &mut String) {
+    padxy(0.0, 10.0, || {
+        minrow(10.0, || {
+            let entered = text_edit(300.0, name, "Street name");
+            let valid = !name.trim().is_empty() && name.trim().len() <= MAX_STREET_NAME_LEN;
+            if (button_primary("Rename").show().clicked || entered) && valid {
+                uiw.commands().rename_road(road, name.trim().to_string());
             }
         });
     });
 }
+
+/// Sets the speed limit of the selected road and of the roads dragged over from it, in km/h or
+/// mph
+fn speed_limit(uiw: &UiWorld, corridor: &[RoadID], limit: &mut f32) {
+    let units = UnitSystem::current();
+    let step = match units {
+        UnitSystem::Metric => 10.0,
+        UnitSystem::Imperial => 5.0,
+    };
+    minrow(10.0, || {
+        if toolbox::updown_value(limit, step, &format!(" {}", units.speed_unit())) {
+            *limit = limit.clamp(
+                units.speed_value(MIN_SPEED_LIMIT).ceil(),
+                units.speed_value(MAX_SPEED_LIMIT).floor(),
+            );
+            uiw.commands()
+                .set_speed_limit(corridor.to_vec(), units.speed_to_ms(*limit));
+        }
+        let roads = match corridor.len() {
+            1 => "Drag to add the connected roads".to_string(),
+            n => format!("{} roads", n),
+        };
+        textc(on_secondary_container(), roads);
+    });
+}
+
+/// Adds or removes the overhead line above the rails of the selected road
+fn electrification(uiw: &UiWorld, road: RoadID, electrified: &mut bool) {
+    let old = *electrified;
+    checkbox_value(electrified, on_secondary_container(), "Electrified");
+    if *electrified != old {
+        uiw.commands().set_road_electrified(road, *electrified);
+    }
+}
@@ -1,16 +1,25 @@
 use yakui::widgets::List;
 use yakui::{column, CrossAxisAlignment, MainAxisAlignment, Vec2};
 
-use goryak::{fixed_spacer, padxy, primary_image_button};
+use goryak::{
+    fixed_spacer, on_secondary_container, padxy, primary_image_button, selectable_label_primary,
+    textc,
+};
+use prototypes::RestrictionKind;
 use simulation::map::TerraformKind;
+use simulation::rules::GameRules;
+use simulation::Simulation;
 
-use crate::newgui::hud::toolbox::{select_triangle, updown_value};
+use crate::newgui::hud::toolbox::{select_triangle, updown_distance, updown_value};
+use crate::newgui::restrictions::RestrictionPaintResource;
 use crate::newgui::terraforming::TerraformingResource;
 use crate::newgui::textures::UiTextures;
 use crate::uiworld::UiWorld;
 
-pub fn terraform_properties(uiw: &UiWorld) {
+pub fn terraform_properties(uiw: &UiWorld, sim: &Simulation) {
     let state = &mut *uiw.write::<TerraformingResource>();
+    let restrictions = &mut *uiw.write::<RestrictionPaintResource>();
+    let sandbox = sim.read::<GameRules>().sandbox;
 
     padxy(0.0, 10.0, || {
         let mut l = List::row();
@@ -18,6 +27,35 @@ pub fn terraform_properties(uiw: &UiWorld) {
         l.cross_axis_alignment = CrossAxisAlignment::Center;
         l.item_spacing = 10.0;
         l.show(|| {
+            if sandbox {
+                if selectable_label_primary(restrictions.active, "Restrictions").clicked {
+                    restrictions.active = !restrictions.active;
+                }
+
+                fixed_spacer((30.0, 0.0));
+
+                if restrictions.active {
+                    let kind_choices = &[
+                        (RestrictionKind::NoBuild, "No-build"),
+                        (RestrictionKind::Nature, "Protected nature"),
+                        (RestrictionKind::Water, "Protected water"),
+                    ];
+                    for (kind, label) in kind_choices {
+                        if selectable_label_primary(restrictions.kind == *kind, label).clicked {
+                            restrictions.kind = *kind;
+                        }
+                    }
+
+                    fixed_spacer((30.0, 0.0));
+
+                    textc(
+                        on_secondary_container(),
+                        "Click the corners of the area, then its first corner again to close it. Right click an area to remove it",
+                    );
+                    return;
+                }
+            }
+
             let texs = uiw.read::<UiTextures>();
 
             let terraform_choices = &[
@@ -47,6 +85,26 @@ pub fn terraform_properties(uiw: &UiWorld) {
                 });
             }
 
+            column(|| {
+                let enabled = state.kind == TerraformKind::Plant;
+                if selectable_label_primary(enabled, "Plant trees").clicked {
+                    state.kind = TerraformKind::Plant;
+                }
+                if enabled {
+                    select_triangle(uiw);
+                }
+            });
+
+            column(|| {
+                let enabled = state.kind == TerraformKind::WaterLevel;
+                if selectable_label_primary(enabled, "Water level (up/down)").clicked {
+                    state.kind = TerraformKind::WaterLevel;
+                }
+                if enabled {
+                    select_triangle(uiw);
+                }
+            });
+
             fixed_spacer((30.0, 0.0));
 
             let radius_choices = &[
@@ -78,7 +136,7 @@ pub fn terraform_properties(uiw: &UiWorld) {
                 100.0
             };
 
-            updown_value(&mut state.radius, step, "m");
+            updown_distance(&mut state.radius, step);
 
             fixed_spacer((30.0, 0.0));
 
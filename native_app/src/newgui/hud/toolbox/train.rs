@@ -22,6 +22,10 @@ pub fn train_properties(uiw: &UiWorld) {
                 }
                 label(format!("Acceleration: {:.1} m/s^2", state.acceleration));
                 label(format!("Deceleration: {:.1} m/s^2", state.deceleration));
+                label(format!(
+                    "Braking Distance: {} m",
+                    state.braking_distance.ceil()
+                ));
                 label(format!("Total Lenght: {} m", state.total_lenght.ceil()));
             });
 
@@ -97,6 +101,7 @@ if ui.button(freightstation).clicked() {
        size: proto.size,
        asset: proto.asset.clone(),
        road_snap: false,
+       shore: false,
    });
 }
 */
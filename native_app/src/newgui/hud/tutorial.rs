@@ -0,0 +1,191 @@
+use yakui::widgets::List;
+use yakui::{
+    constrained, opaque, reflow, Alignment, Constraints, CrossAxisAlignment, Dim2, MainAxisSize,
+    Pivot, Vec2,
+};
+
+use goryak::{
+    blur_bg, button_primary, button_secondary, capture_pointer, constrained_viewport, minrow,
+    on_secondary_container, padxy, secondary_container, set_highlighted, textc, titlec,
+};
+use prototypes::{ScenarioID, TutorialCondition, TutorialHighlight, TutorialID};
+use simulation::map::{BuildingKind, LotKind};
+use simulation::scenario::ScenarioState;
+use simulation::Simulation;
+
+use crate::newgui::hud::toolbox::TOOLS;
+use crate::newgui::{GuiState, Tool};
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+
+/// Progress through the tutorial of the current scenario
+#[derive(Default)]
+pub struct TutorialState {
+    /// Scenario and start day the tutorial was started for, starting a scenario restarts it
+    started_for: Option<(ScenarioID, i32)>,
+    /// None once the tutorial is finished or closed
+    pub tutorial: Option<TutorialID>,
+    pub step: usize,
+}
+
+/// Restarts the tutorial when a scenario starts and advances the steps whose condition is met
+pub fn tutorial_update(uiworld: &UiWorld, sim: &Simulation) {
+    profiling::scope!("hud::tutorial_update");
+    let mut state = uiworld.write::<TutorialState>();
+
+    let scenario = sim.read::<ScenarioState>();
+    let key = scenario.scenario.map(|id| (id, scenario.start_day));
+    if state.started_for != key {
+        state.started_for = key;
+        state.tutorial = scenario.scenario.and_then(|id| id.prototype().tutorial);
+        state.step = 0;
+    }
+    drop(scenario);
+
+    let Some(tutorial) = state.tutorial else {
+        set_highlighted(None);
+        return;
+    };
+    let Some(step) = tutorial.prototype().steps.get(state.step) else {
+        state.tutorial = None;
+        set_highlighted(None);
+        return;
+    };
+
+    if let Some(ref cond) = step.condition {
+        if condition_met(cond, uiworld, sim) {
+            state.step += 1;
+            return;
+        }
+    }
+
+    match step.highlight {
+        Some(TutorialHighlight::Widget(ref name)) => set_highlighted(Some(name.clone())),
+        Some(TutorialHighlight::Position(pos)) => {
+            set_highlighted(None);
+            let z = sim.map().environment.height(pos).unwrap_or(0.0);
+            let radius = 30.0 + 5.0 * f32::cos(uiworld.time_always() * 3.0);
+            uiworld
+                .write::<ImmediateDraw>()
+                .stroke_circle(pos.z(z + 0.5), radius, 3.0)
+                .color(palette().primary);
+        }
+        None => set_highlighted(None),
+    }
+}
+
+fn condition_met(cond: &TutorialCondition, uiworld: &UiWorld, sim: &Simulation) -> bool {
+    let map = sim.map();
+    match *cond {
+        TutorialCondition::ToolSelected { ref tool } => {
+            let cur = *uiworld.read::<Tool>();
+            TOOLS.iter().any(|(name, t)| name == tool && *t == cur)
+        }
+        TutorialCondition::WindowOpen { ref window } => {
+            uiworld.read::<GuiState>().windows.is_open(window)
+        }
+        TutorialCondition::RoadLength { at_least } => {
+            map.roads().values().map(|r| r.length()).sum::<f32>() >= at_least
+        }
+        TutorialCondition::ResidentialLots { at_least } => {
+            let n = map
+                .lots()
+                .values()
+                .filter(|lot| lot.kind == LotKind::Residential)
+                .count();
+            n >= at_least as usize
+        }
+        TutorialCondition::Houses { at_least } => {
+            let n = map
+                .buildings()
+                .values()
+                .filter(|b| matches!(b.kind, BuildingKind::House))
+                .count();
+            n >= at_least as usize
+        }
+        TutorialCondition::Companies { at_least } => {
+            let n = map
+                .buildings()
+                .values()
+                .filter(|b| matches!(b.kind, BuildingKind::GoodsCompany(_)))
+                .count();
+            n >= at_least as usize
+        }
+        TutorialCondition::Population { at_least } => sim.world().humans.len() >= at_least as usize,
+    }
+}
+
+/// Tutorial panel
+/// Explains the current step, steps without a condition wait for "Next"
+pub fn tutorial(uiworld: &UiWorld, _sim: &Simulation) {
+    profiling::scope!("hud::tutorial");
+    let mut state = uiworld.write::<TutorialState>();
+    let Some(tutorial) = state.tutorial else {
+        return;
+    };
+    let proto = tutorial.prototype();
+    let Some(step) = proto.steps.get(state.step) else {
+        return;
+    };
+    let last = state.step + 1 == proto.steps.len();
+
+    reflow(
+        Alignment::TOP_CENTER,
+        Pivot::TOP_CENTER,
+        Dim2::pixels(0.0, 60.0),
+        || {
+            constrained_viewport(|| {
+                opaque(|| {
+                    capture_pointer(|| {
+                        blur_bg(secondary_container().with_alpha(0.5), 10.0, || {
+                            padxy(10.0, 10.0, || {
+                                constrained(
+                                    Constraints::loose(Vec2::new(350.0, f32::INFINITY)),
+                                    || {
+                                        let mut l = List::column();
+                                        l.cross_axis_alignment = CrossAxisAlignment::Stretch;
+                                        l.main_axis_size = MainAxisSize::Min;
+                                        l.item_spacing = 5.0;
+                                        l.show(|| {
+                                            textc(
+                                                on_secondary_container(),
+                                                format!(
+                                                    "{} ({}/{})",
+                                                    proto.label,
+                                                    state.step + 1,
+                                                    proto.steps.len()
+                                                ),
+                                            );
+                                            titlec(on_secondary_container(), step.title.clone());
+                                            textc(on_secondary_container(), step.text.clone());
+
+                                            minrow(5.0, || {
+                                                if step.condition.is_none() {
+                                                    let label = if last { "Done" } else { "Next" };
+                                                    if button_primary(label).show().clicked {
+                                                        state.step += 1;
+                                                    }
+                                                } else if button_secondary("Skip step")
+                                                    .show()
+                                                    .clicked
+                                                {
+                                                    state.step += 1;
+                                                }
+
+                                                if button_secondary("Close tutorial").show().clicked
+                                                {
+                                                    state.tutorial = None;
+                                                }
+                                            });
+                                        });
+                                    },
+                                );
+                            });
+                        })
+                    });
+                });
+            });
+        },
+    );
+}
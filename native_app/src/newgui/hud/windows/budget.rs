@@ -0,0 +1,265 @@
+use yakui::paint::PaintRect;
+use yakui::widgets::{CountGrid, Pad};
+use yakui::{checkbox, Color, MainAxisSize, Rect, Vec2};
+
+use goryak::{
+    button_secondary, checkbox_value, dragvalue, minrow, on_primary_container,
+    on_secondary_container, padxy, primary, sized_canvas, textc, Window,
+};
+use prototypes::{Money, ServiceKind};
+use simulation::economy::{CityStats, Government, GovernmentOrders};
+use simulation::map::RoadCondition;
+use simulation::map_dynamic::{repair_cost, RoadMaintenance, SPENDING_HISTORY};
+use simulation::souls::welfare::Welfare;
+use simulation::transportation::service_fleet::ServiceFleets;
+use simulation::Simulation;
+
+use crate::newgui::road_condition::{condition_color, RoadConditionView};
+use crate::newgui::service_coverage::ServiceCoverageView;
+use crate::newgui::units::fmt_money;
+use crate::uiworld::UiWorld;
+
+/// Budget window
+/// Shows the treasury and lets the player fund the road maintenance, the welfare and the services
+pub fn budget(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: "Budget".into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 10.0,
+    }
+    .show(|| {
+        let money = sim.read::<Government>().money;
+        textc(
+            on_secondary_container(),
+            format!("Treasury: {}", fmt_money(money)),
+        );
+
+        render_welfare(uiw, sim);
+
+        let trading = sim.read::<GovernmentOrders>();
+        textc(
+            on_secondary_container(),
+            format!(
+                "Market trading today: {}, yesterday: {}",
+                fmt_money(trading.balance_today),
+                fmt_money(trading.balance_last_day)
+            ),
+        );
+        drop(trading);
+
+        let maintenance = sim.read::<RoadMaintenance>();
+        minrow(5.0, || {
+            let mut bucks = maintenance.daily_budget.bucks();
+            if dragvalue().min(0.0).step(10.0).show(&mut bucks) {
+                uiw.commands()
+                    .set_road_maintenance_budget(Money::new_bucks(bucks));
+            }
+            textc(on_secondary_container(), "Daily road maintenance budget");
+        });
+
+        let spent_today = maintenance.spent.back().copied().unwrap_or(Money::ZERO);
+        textc(
+            on_secondary_container(),
+            format!("Spent on repairs today: {}", fmt_money(spent_today)),
+        );
+        render_spending(&maintenance.spent.iter().copied().collect::<Vec<_>>());
+
+        render_road_conditions(sim);
+
+        let mut view = uiw.write::<RoadConditionView>();
+        checkbox_value(
+            &mut view.enabled,
+            on_secondary_container(),
+            "Show road condition",
+        );
+        drop(view);
+
+        render_services(uiw, sim);
+    });
+}
+
+/// Fleet and upkeep of each service, and the coverage of their depots
+fn render_services(uiw: &UiWorld, sim: &Simulation) {
+    let fleets = sim.read::<ServiceFleets>();
+
+    let mut grid = CountGrid::col(4);
+    grid.main_axis_size = MainAxisSize::Min;
+    grid.show(|| {
+        for header in ["Service", "Vehicles", "Daily upkeep", "Paid today"] {
+            padxy(5.0, 3.0, || textc(on_secondary_container(), header));
+        }
+        for service in ServiceKind::ALL {
+            let paid = fleets
+                .upkeep_paid
+                .get(&service)
+                .copied()
+                .unwrap_or(Money::ZERO);
+            padxy(5.0, 3.0, || {
+                textc(on_secondary_container(), service.to_string())
+            });
+            padxy(5.0, 3.0, || {
+                textc(
+                    on_secondary_container(),
+                    format!("{}", fleets.fleet_size(service)),
+                )
+            });
+            padxy(5.0, 3.0, || {
+                textc(
+                    on_secondary_container(),
+                    fmt_money(fleets.daily_upkeep(service)),
+                )
+            });
+            padxy(5.0, 3.0, || {
+                textc(on_secondary_container(), fmt_money(paid))
+            });
+        }
+    });
+
+    let mut view = uiw.write::<ServiceCoverageView>();
+    for service in ServiceKind::ALL {
+        let mut shown = view.service == Some(service);
+        minrow(5.0, || {
+            shown = checkbox(shown).checked;
+            textc(
+                on_secondary_container(),
+                format!("Show {} coverage", service),
+            );
+        });
+        if shown {
+            view.service = Some(service);
+        } else if view.service == Some(service) {
+            view.service = None;
+        }
+    }
+    if view.service.is_some() {
+        minrow(5.0, || {
+            dragvalue()
+                .min(10.0)
+                .step(10.0)
+                .show(&mut view.response_time);
+            textc(on_secondary_container(), "Response time (s)");
+        });
+    }
+}
+
+/// Allowance paid to the unemployed and the unemployment rates raising an alert
+fn render_welfare(uiw: &UiWorld, sim: &Simulation) {
+    let welfare = sim.read::<Welfare>();
+    let mut policy = welfare.policy.clone();
+
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.0)
+            .max(100.0)
+            .step(5.0)
+            .show(&mut policy.allowance_percent);
+        textc(
+            on_secondary_container(),
+            format!(
+                "Welfare, in % of a wage ({} per minute)",
+                fmt_money(policy.allowance_per_minute())
+            ),
+        );
+    });
+    let rate = CityStats::new(sim.world()).unemployment_rate.unwrap_or(0.0);
+    textc(
+        on_secondary_container(),
+        format!(
+            "Unemployment: {:.1}%, welfare paid today: {}, yesterday: {}",
+            rate * 100.0,
+            fmt_money(welfare.paid_today),
+            fmt_money(welfare.paid_last_day)
+        ),
+    );
+
+    minrow(5.0, || {
+        textc(on_secondary_container(), "Unemployment alerts at (%)");
+        for threshold in &mut policy.alert_thresholds {
+            dragvalue().min(1.0).max(100.0).show(threshold);
+        }
+        if button_secondary("+").show().clicked {
+            let last = policy.alert_thresholds.last().copied().unwrap_or(0);
+            policy.alert_thresholds.push((last + 5).min(100));
+        }
+        if !policy.alert_thresholds.is_empty() && button_secondary("-").show().clicked {
+            policy.alert_thresholds.pop();
+        }
+    });
+
+    policy.alert_thresholds.sort_unstable();
+    if policy != welfare.policy {
+        uiw.commands().set_welfare_policy(policy);
+    }
+}
+
+/// Bars of the maintenance spending of the last days, the current day on the right
+fn render_spending(spent: &[Money]) {
+    let max = spent
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or(Money::ZERO)
+        .max(Money::new_bucks(1));
+    let col = primary();
+    let spent = spent.to_vec();
+
+    textc(
+        on_primary_container(),
+        format!("Maintenance spending (last {} days)", SPENDING_HISTORY),
+    );
+    sized_canvas(Vec2::new(240.0, 60.0), Color::BLACK, move |paint| {
+        let rect = paint.layout.get(paint.dom.current()).unwrap().rect;
+        let bar_w = rect.size().x / SPENDING_HISTORY as f32;
+        let bottom = rect.pos().y + rect.size().y;
+        let first = SPENDING_HISTORY - spent.len();
+
+        for (i, &m) in spent.iter().enumerate() {
+            let h = (m.inner() as f32 / max.inner() as f32 * rect.size().y).max(1.0);
+            let x = rect.pos().x + (first + i) as f32 * bar_w;
+
+            let mut bar = PaintRect::new(Rect::from_pos_size(
+                Vec2::new(x + 1.0, bottom - h),
+                Vec2::new(bar_w - 2.0, h),
+            ));
+            bar.color = col;
+            bar.add(paint.paint);
+        }
+    });
+}
+
+/// Number of roads and cost of repairing them for each condition
+fn render_road_conditions(sim: &Simulation) {
+    let map = sim.map();
+    let mut counts = [0; RoadCondition::ALL.len()];
+    let mut costs = [Money::ZERO; RoadCondition::ALL.len()];
+    for road in map.roads().values() {
+        let i = road.condition() as usize;
+        counts[i] += 1;
+        costs[i] += repair_cost(road, &map.environment);
+    }
+
+    let mut grid = CountGrid::col(3);
+    grid.main_axis_size = MainAxisSize::Min;
+    grid.show(|| {
+        padxy(5.0, 3.0, || textc(on_secondary_container(), "Condition"));
+        padxy(5.0, 3.0, || textc(on_secondary_container(), "Roads"));
+        padxy(5.0, 3.0, || textc(on_secondary_container(), "Repair cost"));
+        for (i, condition) in RoadCondition::ALL.into_iter().enumerate() {
+            let col = condition_color(condition);
+            let col = Color::rgb(
+                (col.r * 255.0) as u8,
+                (col.g * 255.0) as u8,
+                (col.b * 255.0) as u8,
+            );
+            padxy(5.0, 3.0, || textc(col, condition.to_string()));
+            padxy(5.0, 3.0, || {
+                textc(on_secondary_container(), format!("{}", counts[i]))
+            });
+            padxy(5.0, 3.0, || {
+                textc(on_secondary_container(), fmt_money(costs[i]))
+            });
+        }
+    });
+}
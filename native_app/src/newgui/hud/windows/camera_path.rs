@@ -0,0 +1,180 @@
+use yakui::widgets::Pad;
+
+use goryak::{
+    button_primary, button_secondary, dragvalue, fixed_spacer, mincolumn, minrow,
+    on_secondary_container, text_edit, textc, VertScrollSize, Window,
+};
+use simulation::Simulation;
+
+use crate::newgui::camera_path::{eye, CameraKeyframe, CameraPath, CameraPathPlayer};
+use crate::newgui::inspect::entity_link;
+use crate::newgui::InspectedEntity;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::{palette, OrbitCamera};
+use crate::uiworld::UiWorld;
+
+/// Seconds between a new keyframe and the last one
+const DEFAULT_KEYFRAME_GAP: f32 = 5.0;
+
+enum KeyframeEdit {
+    Time(f32),
+    View,
+    SetToView,
+    Remove,
+}
+
+/// Camera path window
+/// Places the keyframes of a camera path, plays it with the interface hidden and writes the
+/// frames to disk to make timelapse videos
+pub fn camera_path(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: "Camera path".into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut player = uiw.write::<CameraPathPlayer>();
+
+        minrow(5.0, || {
+            text_edit(150.0, &mut player.name, "Path name");
+            if button_primary("Save").show().clicked && !player.name.is_empty() {
+                player.path.save(&player.name);
+            }
+        });
+        minrow(5.0, || {
+            for name in CameraPath::saved_names() {
+                if button_secondary(&name).show().clicked {
+                    if let Some(path) = CameraPath::load(&name) {
+                        player.path = path;
+                        player.name = name;
+                    }
+                }
+            }
+        });
+
+        fixed_spacer((0.0, 10.0));
+        let next_time = match player.path.keyframes().last() {
+            Some(k) => k.time + DEFAULT_KEYFRAME_GAP,
+            None => 0.0,
+        };
+        minrow(5.0, || {
+            if button_primary("Add current view").show().clicked {
+                let pose = uiw.read::<OrbitCamera>().pose();
+                player
+                    .path
+                    .insert(CameraKeyframe::new(next_time, pose, None));
+            }
+            let inspected = uiw.read::<InspectedEntity>().e;
+            if let Some(e) = inspected {
+                if button_secondary("Add looking at the inspected entity")
+                    .show()
+                    .clicked
+                {
+                    let pose = uiw.read::<OrbitCamera>().pose();
+                    player
+                        .path
+                        .insert(CameraKeyframe::new(next_time, pose, Some(e)));
+                }
+            }
+        });
+
+        let mut edit = None;
+        VertScrollSize::Exact(250.0).show(|| {
+            mincolumn(3.0, || {
+                for (i, key) in player.path.keyframes().iter().enumerate() {
+                    minrow(5.0, || {
+                        let mut time = key.time;
+                        if dragvalue().min(0.0).step(0.1).show(&mut time) {
+                            edit = Some((i, KeyframeEdit::Time(time)));
+                        }
+                        textc(on_secondary_container(), "s");
+                        if let Some(e) = key.target {
+                            entity_link(uiw, sim, e);
+                        }
+                        if button_secondary("View").show().clicked {
+                            edit = Some((i, KeyframeEdit::View));
+                        }
+                        if button_secondary("Set to current view").show().clicked {
+                            edit = Some((i, KeyframeEdit::SetToView));
+                        }
+                        if button_secondary("Remove").show().clicked {
+                            edit = Some((i, KeyframeEdit::Remove));
+                        }
+                    });
+                }
+            });
+        });
+
+        if let Some((i, edit)) = edit {
+            let key = player.path.keyframes()[i];
+            match edit {
+                KeyframeEdit::Time(time) => {
+                    player.path.replace(i, CameraKeyframe { time, ..key });
+                }
+                KeyframeEdit::View => {
+                    if let Some(pose) = player.path.sample(key.time, |e| sim.pos_any(e)) {
+                        uiw.write::<OrbitCamera>().set_pose(pose);
+                    }
+                }
+                KeyframeEdit::SetToView => {
+                    let pose = uiw.read::<OrbitCamera>().pose();
+                    player
+                        .path
+                        .replace(i, CameraKeyframe::new(key.time, pose, key.target));
+                }
+                KeyframeEdit::Remove => {
+                    player.path.remove(i);
+                }
+            }
+        }
+
+        fixed_spacer((0.0, 10.0));
+        minrow(5.0, || {
+            textc(on_secondary_container(), "Simulation speed");
+            dragvalue().min(1.0).max(1000.0).show(&mut player.path.warp);
+        });
+        minrow(5.0, || {
+            textc(on_secondary_container(), "Write one frame out of");
+            dragvalue().min(0.0).max(600.0).show(&mut player.dump_every);
+            textc(on_secondary_container(), "(0: none)");
+        });
+        if player.dump_every > 0 {
+            textc(
+                on_secondary_container(),
+                format!("Frames are written to {}", player.frames_dir().display()),
+            );
+        }
+        if player.frames_written > 0 {
+            textc(
+                on_secondary_container(),
+                format!(
+                    "{} frames written by the last playback",
+                    player.frames_written
+                ),
+            );
+        }
+
+        if player.path.keyframes().len() < 2 {
+            textc(
+                on_secondary_container(),
+                "Add at least two keyframes to play the path",
+            );
+        } else if button_primary("Play (escape to stop)").show().clicked {
+            player.play(uiw);
+        }
+
+        let cam = uiw.read::<OrbitCamera>();
+        let points = player.preview(&cam.camera, |e| sim.pos_any(e));
+        let mut draw = uiw.write::<ImmediateDraw>();
+        let colors = palette();
+        for key in player.path.keyframes() {
+            if let Some(pose) = player.path.sample(key.time, |e| sim.pos_any(e)) {
+                draw.circle(eye(&cam.camera, pose), 3.0)
+                    .color(colors.primary);
+            }
+        }
+        draw.polyline(points, 1.0, false).color(colors.primary);
+    });
+}
@@ -0,0 +1,244 @@
+use yakui::paint::PaintRect;
+use yakui::widgets::Pad;
+use yakui::{Color, Rect, Vec2};
+
+use goryak::{
+    button_primary, dragvalue, fixed_spacer, mincolumn, minrow, on_secondary_container,
+    selectable_label_primary, sized_canvas, textc, VertScrollSize, Window,
+};
+use prototypes::Money;
+use simulation::souls::sampling::{
+    Activity, CitizenSample, CitizenSampling, SampleReport, DEFAULT_SAMPLE_SIZE,
+};
+use simulation::Simulation;
+
+use crate::newgui::inspect::entity_link;
+use crate::newgui::units::{fmt_distance, fmt_money};
+use crate::uiworld::UiWorld;
+
+pub struct CitizensState {
+    count: u32,
+    seed: u32,
+    /// Index of the citizen whose day is shown
+    selected: Option<usize>,
+    export_result: Option<String>,
+}
+
+impl Default for CitizensState {
+    fn default() -> Self {
+        Self {
+            count: DEFAULT_SAMPLE_SIZE,
+            seed: 1,
+            selected: None,
+            export_result: None,
+        }
+    }
+}
+
+fn activity_color(activity: Activity) -> Color {
+    match activity {
+        Activity::Sleeping => Color::rgb(60, 60, 140),
+        Activity::AtHome => Color::rgb(110, 110, 200),
+        Activity::Working => Color::rgb(70, 170, 80),
+        Activity::Commuting => Color::rgb(230, 170, 40),
+        Activity::Waiting => Color::rgb(210, 60, 50),
+        Activity::Shopping => Color::rgb(180, 90, 190),
+    }
+}
+
+/// Citizens window
+/// Follows random citizens for a day and shows how they spent it, to balance the simulation
+pub fn citizens(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: "Citizens".into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut state = uiw.write::<CitizensState>();
+        let sampling = sim.read::<CitizenSampling>();
+
+        minrow(5.0, || {
+            dragvalue().min(1.0).max(500.0).show(&mut state.count);
+            textc(on_secondary_container(), "citizens, seed");
+            dragvalue().min(0.0).show(&mut state.seed);
+            if button_primary("Follow for a day").show().clicked {
+                uiw.commands()
+                    .start_citizen_sampling(state.count, state.seed as u64);
+                state.selected = None;
+            }
+        });
+        if !sampling.requested.is_empty() {
+            textc(
+                on_secondary_container(),
+                format!(
+                    "{} chosen citizens will be added to the next sample",
+                    sampling.requested.len()
+                ),
+            );
+        }
+
+        if let Some(ref recording) = sampling.recording {
+            textc(
+                on_secondary_container(),
+                format!(
+                    "Following {} citizens until {}",
+                    recording.samples.len(),
+                    recording.end
+                ),
+            );
+        }
+
+        let Some(ref report) = sampling.report else {
+            textc(on_secondary_container(), "No report yet");
+            return;
+        };
+
+        fixed_spacer((0.0, 10.0));
+        textc(
+            on_secondary_container(),
+            format!(
+                "{} citizens from {} to {} (seed {})",
+                report.samples.len(),
+                report.start,
+                report.end,
+                report.seed
+            ),
+        );
+        distributions(report);
+
+        minrow(5.0, || {
+            if button_primary("Export CSV").show().clicked {
+                state.export_result = Some(export_csv(report));
+            }
+            if let Some(ref result) = state.export_result {
+                textc(on_secondary_container(), result.clone());
+            }
+        });
+
+        fixed_spacer((0.0, 10.0));
+        VertScrollSize::Exact(300.0).show(|| {
+            mincolumn(3.0, || {
+                for (i, sample) in report.samples.iter().enumerate() {
+                    let selected = state.selected == Some(i);
+                    if selectable_label_primary(selected, &sample.name).clicked {
+                        state.selected = if selected { None } else { Some(i) };
+                    }
+                    if selected {
+                        timeline(uiw, sim, report, sample);
+                    }
+                }
+            });
+        });
+    });
+}
+
+/// Median and spread of the time spent on each activity
+fn distributions(report: &SampleReport) {
+    for activity in Activity::ALL {
+        let minutes = report.distribution(activity);
+        if minutes.is_empty() {
+            continue;
+        }
+        let at = |q: f64| minutes[((minutes.len() - 1) as f64 * q).round() as usize] / 60.0;
+        minrow(5.0, || {
+            legend(activity);
+            textc(
+                on_secondary_container(),
+                format!(
+                    "{}: median {:.1}h, 10% {:.1}h, 90% {:.1}h",
+                    activity.label(),
+                    at(0.5),
+                    at(0.1),
+                    at(0.9)
+                ),
+            );
+        });
+    }
+
+    let n = report.samples.len().max(1) as f32;
+    let distance: f32 = report.samples.iter().map(|s| s.distance).sum();
+    let earned: i64 = report.samples.iter().map(|s| s.earned.inner()).sum();
+    let spent: i64 = report.samples.iter().map(|s| s.spent.inner()).sum();
+    textc(
+        on_secondary_container(),
+        format!(
+            "Average: {} travelled, {} earned, {} spent",
+            fmt_distance(distance / n),
+            fmt_money(Money::new_inner((earned as f32 / n) as i64)),
+            fmt_money(Money::new_inner((spent as f32 / n) as i64))
+        ),
+    );
+}
+
+fn legend(activity: Activity) {
+    let col = activity_color(activity);
+    sized_canvas(Vec2::new(12.0, 12.0), col, |_| {});
+}
+
+/// The day of one citizen as a colored bar, followed by each activity
+fn timeline(uiw: &UiWorld, sim: &Simulation, report: &SampleReport, sample: &CitizenSample) {
+    let total = (report.end.0 .0 - report.start.0 .0).max(1) as f32;
+    let spans: Vec<_> = sample.spans(report.end).collect();
+    let start = report.start.0 .0;
+
+    let bar_spans = spans.clone();
+    sized_canvas(Vec2::new(300.0, 16.0), Color::BLACK, move |paint| {
+        let rect = paint.layout.get(paint.dom.current()).unwrap().rect;
+        for &(from, until, activity) in &bar_spans {
+            let x0 = (from.0 .0.saturating_sub(start)) as f32 / total * rect.size().x;
+            let x1 = (until.0 .0.saturating_sub(start)) as f32 / total * rect.size().x;
+            let mut bar = PaintRect::new(Rect::from_pos_size(
+                Vec2::new(rect.pos().x + x0, rect.pos().y),
+                Vec2::new((x1 - x0).max(1.0), rect.size().y),
+            ));
+            bar.color = activity_color(activity);
+            bar.add(paint.paint);
+        }
+    });
+
+    minrow(5.0, || {
+        entity_link(uiw, sim, sample.human);
+        textc(
+            on_secondary_container(),
+            format!(
+                "{}, earned {}, spent {}",
+                fmt_distance(sample.distance),
+                fmt_money(sample.earned),
+                fmt_money(sample.spent)
+            ),
+        );
+    });
+    if let Some(gone) = sample.gone {
+        textc(
+            on_secondary_container(),
+            format!("Left the city at {}", gone),
+        );
+    }
+    for (from, _, activity) in spans {
+        minrow(5.0, || {
+            legend(activity);
+            textc(
+                on_secondary_container(),
+                format!("{} {}", from, activity.label()),
+            );
+        });
+    }
+}
+
+fn export_csv(report: &SampleReport) -> String {
+    let summary = "world/citizen_sample.csv";
+    let timeline = "world/citizen_sample_timeline.csv";
+    let written = std::fs::create_dir_all("world")
+        .and_then(|_| std::fs::write(summary, report.summary_csv()))
+        .and_then(|_| std::fs::write(timeline, report.timeline_csv()));
+    match written {
+        Ok(_) => format!("Saved to {} and {}", summary, timeline),
+        Err(e) => {
+            log::error!("could not export the citizen sample: {}", e);
+            format!("Export failed: {}", e)
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+
+use yakui::widgets::Pad;
+
+use common::diagnostics::{Diagnostic, Severity};
+use goryak::{
+    button_secondary, error, mincolumn, minrow, on_secondary_container, textc, titlec,
+    VertScrollSize, Window,
+};
+use simulation::Simulation;
+
+use crate::newgui::map_export::open_folder;
+use crate::uiworld::UiWorld;
+
+#[derive(Default)]
+pub struct DiagnosticsState {
+    /// Revision of the diagnostics when the window was last open
+    pub seen: u64,
+    /// Text to put in the clipboard, yakui has no clipboard so egui hands it over to the platform
+    pub copy: Option<String>,
+}
+
+impl DiagnosticsState {
+    /// Whether diagnostics were reported since the window was last open
+    pub fn has_new(&self) -> bool {
+        common::diagnostics::revision() > self.seen
+    }
+}
+
+/// Diagnostics window
+/// The content problems reported by the subsystems, grouped by subsystem
+pub fn diagnostics(uiw: &UiWorld, _sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: "Diagnostics".into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut state = uiw.write::<DiagnosticsState>();
+        state.seen = common::diagnostics::revision();
+
+        let mut by_source: BTreeMap<&'static str, Vec<Diagnostic>> = BTreeMap::new();
+        for d in common::diagnostics::diagnostics() {
+            if d.severity == Severity::Debug {
+                continue;
+            }
+            by_source.entry(d.source).or_default().push(d);
+        }
+
+        if by_source.is_empty() {
+            textc(on_secondary_container(), "No problems were found");
+            return;
+        }
+
+        minrow(5.0, || {
+            if button_secondary("Copy all").show().clicked {
+                state.copy = Some(lines(by_source.values().flatten()));
+            }
+            if button_secondary("Clear").show().clicked {
+                common::diagnostics::clear();
+            }
+        });
+
+        VertScrollSize::Exact(500.0).show(|| {
+            mincolumn(10.0, || {
+                for (source, diagnostics) in &by_source {
+                    minrow(5.0, || {
+                        titlec(
+                            on_secondary_container(),
+                            format!("{} ({})", source, diagnostics.len()),
+                        );
+                        if button_secondary("Copy").show().clicked {
+                            state.copy = Some(lines(diagnostics));
+                        }
+                    });
+                    for d in diagnostics {
+                        diagnostic(d);
+                    }
+                }
+            });
+        });
+    });
+}
+
+fn diagnostic(d: &Diagnostic) {
+    mincolumn(2.0, || {
+        let color = match d.severity {
+            Severity::Error => error(),
+            _ => on_secondary_container(),
+        };
+        if d.count > 1 {
+            textc(color, format!("{} (x{})", d.message, d.count));
+        } else {
+            textc(color, d.message.clone());
+        }
+        if let Some(ref proto) = d.prototype {
+            textc(on_secondary_container(), format!("prototype: {}", proto));
+        }
+        if let Some(ref asset) = d.asset {
+            minrow(5.0, || {
+                textc(on_secondary_container(), asset.display().to_string());
+                if button_secondary("Open folder").show().clicked {
+                    open_folder(asset);
+                }
+            });
+        }
+    });
+}
+
+fn lines<'a>(diagnostics: impl IntoIterator<Item = &'a Diagnostic>) -> String {
+    diagnostics
+        .into_iter()
+        .map(|d| d.to_line())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -1,24 +1,43 @@
+use std::cmp::Reverse;
 use std::collections::HashSet;
 
-use yakui::paint::PaintMesh;
+use yakui::paint::{PaintMesh, PaintRect};
 use yakui::widgets::{CountGrid, List, Pad};
 use yakui::{
-    constrained, use_state, Color, Constraints, CrossAxisAlignment, MainAxisAlignItems,
-    MainAxisSize, Vec2,
+    checkbox, constrained, use_state, Color, Constraints, CrossAxisAlignment, MainAxisAlignItems,
+    MainAxisSize, Rect, Vec2,
 };
 
 use engine::Tesselator;
 use geom::AABB;
 use goryak::{
-    constrained_viewport, mincolumn, minrow, on_primary_container, padxy, pady,
-    selectable_label_primary, sized_canvas, textc, VertScrollSize, Window,
+    button_primary, button_secondary, checkbox_value, combo_box, constrained_viewport, dragvalue,
+    mincolumn, minrow, on_primary_container, padxy, pady, primary, primary_link,
+    selectable_label_primary, sized_canvas, text_edit, textc, FlowDiagram, FlowLink,
+    VertScrollSize, Window,
+};
+use prototypes::{
+    AmenityKind, GameDuration, GameTime, ItemID, ItemPrototype, Money, ServiceKind, Tick,
+    DELTA_F64, HOURS_PER_DAY, TICKS_PER_HOUR,
 };
-use prototypes::{ItemID, DELTA_F64};
 use simulation::economy::{
-    EcoStats, ItemHistories, Market, HISTORY_SIZE, LEVEL_FREQS, LEVEL_NAMES,
+    CityStats, EcoStats, FlowBand, FlowEnd, GovernmentOrders, ItemHistories, Market, OrderSide,
+    TradeLedger, TripStats, HISTORY_SIZE, LEDGER_WINDOW, LEVEL_FREQS, LEVEL_NAMES,
 };
+use simulation::map_dynamic::Walkability;
+use simulation::souls::company_lifecycle::{CompanyEventKind, CompanyLifecycle};
+use simulation::souls::warehouse::city_stock;
+use simulation::souls::welfare::{happiness, unemployed_for, LONG_TERM_UNEMPLOYMENT};
+use simulation::transportation::deadlock::Deadlocks;
+use simulation::transportation::intersection_stats::{IntersectionSort, IntersectionStats};
 use simulation::Simulation;
+use slotmapd::Key;
 
+use crate::newgui::districts::{coverage_text, land_value_text, DistrictStatsView};
+use crate::newgui::inspect::{building_link, entity_link};
+use crate::newgui::units::{fmt_duration, fmt_money};
+use crate::newgui::walkability::WalkabilityView;
+use crate::newgui::InspectedDistrict;
 use crate::uiworld::UiWorld;
 
 #[derive(Copy, Clone, Default, PartialEq, Eq)]
@@ -26,7 +45,14 @@ pub enum EconomyTab {
     #[default]
     ImportExports,
     InternalTrade,
+    Flows,
     MarketPrices,
+    City,
+    Companies,
+    Employment,
+    Districts,
+    Government,
+    Intersections,
 }
 
 #[derive(Copy, Clone, Default, PartialEq, Eq)]
@@ -36,11 +62,39 @@ pub enum HistoryType {
     Items,
 }
 
+/// How far back the flows tab looks, the trade ledgers remember a game day
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+pub enum FlowWindow {
+    LastHour,
+    #[default]
+    LastDay,
+}
+
 #[derive(Default)]
 pub struct EconomyState {
     pub curlevel: usize,
     pub tab: EconomyTab,
     pub hist_type: HistoryType,
+    /// Only the unemployed whose name contains this are listed
+    pub unemployed_search: String,
+    pub long_term_only: bool,
+    /// Form of the next government order, the item is an index in the market's items
+    pub order_item: usize,
+    pub order_price: f64,
+    pub order_qty: u32,
+    /// Item shown in the flows tab, an index in the market's items
+    pub flow_item: usize,
+    pub flow_window: FlowWindow,
+    /// How the intersections tab ranks the worst intersections
+    pub intersection_sort: IntersectionSort,
+}
+
+impl EconomyState {
+    /// The items picked by index may have moved after a reload of the prototypes
+    pub fn reset_item_selection(&mut self) {
+        self.order_item = 0;
+        self.flow_item = 0;
+    }
 }
 
 /// Economy window
@@ -60,7 +114,14 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
             let tabs = &[
                 ("Import/Exports", EconomyTab::ImportExports),
                 ("Internal Trade", EconomyTab::InternalTrade),
+                ("Flows", EconomyTab::Flows),
                 ("Market Prices", EconomyTab::MarketPrices),
+                ("City", EconomyTab::City),
+                ("Companies", EconomyTab::Companies),
+                ("Employment", EconomyTab::Employment),
+                ("Districts", EconomyTab::Districts),
+                ("Government", EconomyTab::Government),
+                ("Intersections", EconomyTab::Intersections),
             ];
 
             for (label, tab) in tabs {
@@ -103,6 +164,7 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
             curlevel,
             ref tab,
             hist_type,
+            ..
         } = *state;
 
         let render_history = |history: &ItemHistories, hist_type: HistoryType| {
@@ -277,19 +339,22 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
                                             }
                                         }
                                     });
-                                    let suffix = match hist_type {
-                                        HistoryType::Items => "",
-                                        HistoryType::Money => "$",
+                                    let sum_text = match hist_type {
+                                        HistoryType::Items => sum.to_string(),
+                                        HistoryType::Money => fmt_money(Money::new_bucks(sum)),
                                     };
                                     padxy(5.0, 5.0, || {
-                                        textc(on_primary_container(), format!("{}{}", sum, suffix));
+                                        textc(on_primary_container(), sum_text);
                                     });
                                     overall_total += sum;
                                 }
                                 if matches!(hist_type, HistoryType::Money) {
                                     textc(
                                         on_primary_container(),
-                                        format!("Total: {}$", overall_total),
+                                        format!(
+                                            "Total: {}",
+                                            fmt_money(Money::new_bucks(overall_total))
+                                        ),
                                     );
                                 }
                             });
@@ -321,26 +386,708 @@ pub fn economy(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
             EconomyTab::InternalTrade => {
                 render_history(&ecostats.internal_trade, HistoryType::Items);
             }
+            EconomyTab::Flows => {
+                render_item_flows(uiw, sim, &mut state);
+            }
             EconomyTab::MarketPrices => {
                 render_market_prices(sim);
             }
+            EconomyTab::City => {
+                render_city_stats(uiw, sim, &ecostats);
+            }
+            EconomyTab::Companies => {
+                render_companies(uiw, sim);
+            }
+            EconomyTab::Employment => {
+                let EconomyState {
+                    ref mut unemployed_search,
+                    ref mut long_term_only,
+                    ..
+                } = *state;
+                render_employment(uiw, sim, unemployed_search, long_term_only);
+            }
+            EconomyTab::Districts => {
+                render_district_stats(uiw, sim);
+            }
+            EconomyTab::Government => {
+                render_government_trading(uiw, sim, &mut state);
+            }
+            EconomyTab::Intersections => {
+                render_intersections(uiw, sim, &mut state.intersection_sort);
+            }
         }
     });
 }
 
-fn render_market_prices(sim: &Simulation) {
+/// Number of buildings listed on each side of the hovered band
+const SHOWN_FLOW_BUILDINGS: usize = 3;
+
+/// Where the goods of one item come from and where they go, as a flow diagram built from the
+/// trade ledgers. Hovering a band details it below the diagram.
+fn render_item_flows(uiw: &UiWorld, sim: &Simulation, state: &mut EconomyState) {
+    let selected = use_state(|| None::<(FlowEnd, FlowEnd)>);
+
+    let market = sim.read::<Market>();
+    let job_opening = ItemID::new("job-opening");
+    let items: Vec<ItemID> = market
+        .iter()
+        .map(|(&id, _)| id)
+        .filter(|&id| id != job_opening)
+        .collect();
+    drop(market);
+    if items.is_empty() {
+        return;
+    }
+    state.flow_item = state.flow_item.min(items.len() - 1);
+    let item = items[state.flow_item];
+
+    minrow(5.0, || {
+        let labels: Vec<&str> = items
+            .iter()
+            .map(|id| id.prototype().label.as_str())
+            .collect();
+        if combo_box(&mut state.flow_item, &labels, 150.0) {
+            selected.set(None);
+        }
+        for (label, window) in [
+            ("Last hour", FlowWindow::LastHour),
+            ("Last day", FlowWindow::LastDay),
+        ] {
+            if selectable_label_primary(state.flow_window == window, label).clicked {
+                state.flow_window = window;
+            }
+        }
+    });
+
+    let tick = sim.read::<GameTime>().tick;
+    let since = Tick(tick.0.saturating_sub(match state.flow_window {
+        FlowWindow::LastHour => TICKS_PER_HOUR,
+        FlowWindow::LastDay => LEDGER_WINDOW,
+    }));
+    let bands = sim
+        .read::<TradeLedger>()
+        .item_flows(item, since, &sim.map());
+    if bands.is_empty() {
+        textc(on_primary_container(), "Not traded recently");
+        return;
+    }
+
+    // nodes ordered by the quantity going through them
+    let totals = |end: fn(&FlowBand) -> FlowEnd| {
+        let mut totals: Vec<(FlowEnd, i64)> = vec![];
+        for b in &bands {
+            match totals.iter_mut().find(|(e, _)| *e == end(b)) {
+                Some((_, qty)) => *qty += b.qty,
+                None => totals.push((end(b), b.qty)),
+            }
+        }
+        totals.sort_by_key(|&(_, qty)| Reverse(qty));
+        totals
+    };
+    let producers = totals(|b| b.from);
+    let consumers = totals(|b| b.to);
+    let index = |ends: &[(FlowEnd, i64)], end: FlowEnd| ends.iter().position(|(e, _)| *e == end);
+
+    let links = bands
+        .iter()
+        .filter_map(|b| {
+            Some(FlowLink {
+                from: index(&producers, b.from)?,
+                to: index(&consumers, b.to)?,
+                value: b.qty as f32,
+            })
+        })
+        .collect();
+
+    let legend = |ends: &[(FlowEnd, i64)]| {
+        mincolumn(4.0, || {
+            for &(end, qty) in ends {
+                textc(flow_color(end), format!("{} ({})", end.label(), qty));
+            }
+        });
+    };
+
+    minrow(10.0, || {
+        legend(&producers);
+        let resp = FlowDiagram {
+            size: Vec2::new(300.0, 200.0),
+            left: producers.iter().map(|&(end, _)| flow_color(end)).collect(),
+            right: consumers.iter().map(|&(end, _)| flow_color(end)).collect(),
+            links,
+        }
+        .show();
+        if let Some(b) = resp.hovered.and_then(|i| bands.get(i)) {
+            selected.set(Some((b.from, b.to)));
+        }
+        legend(&consumers);
+    });
+
+    let Some((from, to)) = selected.get() else {
+        textc(on_primary_container(), "Hover a band for details");
+        return;
+    };
+    let Some(band) = bands.iter().find(|b| b.from == from && b.to == to) else {
+        return;
+    };
+    textc(
+        on_primary_container(),
+        format!(
+            "{} to {}: {} {}",
+            from.label(),
+            to.label(),
+            band.qty,
+            item.prototype().label
+        ),
+    );
+    let mut grid = CountGrid::col(2);
+    grid.main_axis_size = MainAxisSize::Min;
+    grid.show(|| {
+        for (header, buildings) in [("Sold by", &band.sellers), ("Bought by", &band.buyers)] {
+            padxy(5.0, 3.0, || textc(on_primary_container(), header));
+            mincolumn(2.0, || {
+                for &(building, qty) in buildings.iter().take(SHOWN_FLOW_BUILDINGS) {
+                    minrow(5.0, || {
+                        building_link(uiw, sim, building);
+                        textc(on_primary_container(), qty.to_string());
+                    });
+                }
+            });
+        }
+    });
+}
+
+/// Random but stable color of a side of the flows
+fn flow_color(end: FlowEnd) -> Color {
+    let h = common::hash_u64(end) as f32;
+    let channel = |i: f32| ((0.5 + 0.5 * common::rand::rand2(h, i)) * 255.0) as u8;
+    Color::rgb(channel(0.0), channel(1.0), channel(2.0))
+}
+
+/// Standing orders of the government: a form to place new ones and the open ones with their fills
+fn render_government_trading(uiw: &UiWorld, sim: &Simulation, state: &mut EconomyState) {
     let market = sim.read::<Market>();
+    let ecostats = sim.read::<EcoStats>();
+    let gov = sim.read::<GovernmentOrders>();
+    let job_opening = ItemID::new("job-opening");
+
+    let items: Vec<(ItemID, Money)> = market
+        .iter()
+        .filter(|(&id, _)| id != job_opening)
+        .map(|(&id, m)| (id, ecostats.local_price(id, m.ext_value)))
+        .collect();
+    if items.is_empty() {
+        return;
+    }
+    state.order_item = state.order_item.min(items.len() - 1);
+    let (item, local_price) = items[state.order_item];
+
+    minrow(5.0, || {
+        let labels: Vec<&str> = items
+            .iter()
+            .map(|(id, _)| id.prototype().label.as_str())
+            .collect();
+        if combo_box(&mut state.order_item, &labels, 150.0) {
+            // start from the current price of the new item
+            state.order_price = items[state.order_item].1.inner() as f64 / 10000.0;
+        }
+        textc(
+            on_primary_container(),
+            format!(
+                "City price: {}, held: {}",
+                fmt_money(local_price),
+                gov.stock(item)
+            ),
+        );
+    });
+    minrow(5.0, || {
+        dragvalue().min(0.0).step(0.1).show(&mut state.order_price);
+        textc(on_primary_container(), "Price limit");
+        dragvalue().min(1.0).step(1.0).show(&mut state.order_qty);
+        textc(on_primary_container(), "Quantity");
+    });
+    let price = Money::from_float_bucks(state.order_price);
+    minrow(5.0, || {
+        if state.order_qty > 0 && price > Money::ZERO {
+            if button_primary("Buy").show().clicked {
+                uiw.commands()
+                    .place_government_order(item, OrderSide::Buy, price, state.order_qty);
+            }
+            if button_primary("Sell").show().clicked {
+                uiw.commands().place_government_order(
+                    item,
+                    OrderSide::Sell,
+                    price,
+                    state.order_qty,
+                );
+            }
+        }
+        textc(
+            on_primary_container(),
+            format!(
+                "Trading today: {}, yesterday: {}",
+                gov.balance_today, gov.balance_last_day
+            ),
+        );
+    });
+
+    VertScrollSize::Fixed(300.0).show(|| {
+        let mut grid = CountGrid::col(5);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            for header in ["Item", "Order", "Filled", "Total", ""] {
+                padxy(5.0, 3.0, || textc(on_primary_container(), header));
+            }
+
+            for (id, order) in gov.orders.iter() {
+                let side = match order.side {
+                    OrderSide::Buy => "Buy at most",
+                    OrderSide::Sell => "Sell at least",
+                };
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), &order.item.prototype().label)
+                });
+                padxy(5.0, 3.0, || {
+                    textc(
+                        on_primary_container(),
+                        format!("{} {}", side, fmt_money(order.price)),
+                    )
+                });
+                padxy(5.0, 3.0, || {
+                    textc(
+                        on_primary_container(),
+                        format!("{}/{}", order.filled, order.qty),
+                    )
+                });
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), fmt_money(order.total))
+                });
+                padxy(5.0, 3.0, || {
+                    if button_secondary("Cancel").show().clicked {
+                        uiw.commands().cancel_government_order(id);
+                    }
+                });
+            }
+        });
+    });
+}
+
+/// Shows the same counters the scenario objectives are checked against
+fn render_city_stats(uiw: &UiWorld, sim: &Simulation, ecostats: &EcoStats) {
+    let city = CityStats::new(sim.world());
 
     VertScrollSize::Fixed(300.0).show(|| {
         let mut grid = CountGrid::col(2);
         grid.main_axis_size = MainAxisSize::Min;
         grid.show(|| {
-            for (id, market) in market.iter() {
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Population"));
+            padxy(5.0, 3.0, || {
+                textc(on_primary_container(), city.population.to_string())
+            });
+
+            padxy(5.0, 3.0, || {
+                textc(on_primary_container(), "Average commute")
+            });
+            padxy(5.0, 3.0, || {
+                textc(
+                    on_primary_container(),
+                    match city.avg_commute_minutes {
+                        Some(avg) => fmt_duration(GameDuration::from_secs((avg * 60.0) as u64)),
+                        None => "-".to_string(),
+                    },
+                )
+            });
+
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Unemployment"));
+            padxy(5.0, 3.0, || {
+                textc(
+                    on_primary_container(),
+                    match city.unemployment_rate {
+                        Some(rate) => format!("{:.1}%", rate * 100.0),
+                        None => "-".to_string(),
+                    },
+                )
+            });
+
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Car ownership"));
+            padxy(5.0, 3.0, || {
+                textc(
+                    on_primary_container(),
+                    match city.car_ownership {
+                        Some(rate) => format!("{:.0}%", rate * 100.0),
+                        None => "-".to_string(),
+                    },
+                )
+            });
+
+            let deadlocks = sim.read::<Deadlocks>();
+            padxy(5.0, 3.0, || {
+                textc(on_primary_container(), "Gridlocks cleared (last hour)")
+            });
+            padxy(5.0, 3.0, || {
+                textc(
+                    on_primary_container(),
+                    format!(
+                        "{} ({} total)",
+                        deadlocks.recoveries_last_hour(),
+                        deadlocks.total_recoveries
+                    ),
+                )
+            });
+            drop(deadlocks);
+
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Walkability"));
+            padxy(5.0, 3.0, || {
+                textc(
+                    on_primary_container(),
+                    match sim.read::<Walkability>().average() {
+                        Some(avg) => format!("{:.0}/100", avg),
+                        None => "-".to_string(),
+                    },
+                )
+            });
+
+            for item in ItemPrototype::iter() {
+                let produced = ecostats.produced_last_day(item.id);
+                if produced == 0 {
+                    continue;
+                }
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), format!("{} per day", item.label))
+                });
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), produced.to_string())
+                });
+            }
+        });
+    });
+
+    render_trips_per_hour(&sim.read::<TripStats>());
+    render_walkability_overlay(uiw);
+}
+
+/// Toggles the walk-time overlay of one kind of amenity at a time
+fn render_walkability_overlay(uiw: &UiWorld) {
+    let mut view = uiw.write::<WalkabilityView>();
+    for amenity in AmenityKind::ALL {
+        let mut shown = view.amenity == Some(amenity);
+        minrow(5.0, || {
+            shown = checkbox(shown).checked;
+            textc(
+                on_primary_container(),
+                format!("Show walking time to {}", amenity),
+            );
+        });
+        if shown {
+            view.amenity = Some(amenity);
+        } else if view.amenity == Some(amenity) {
+            view.amenity = None;
+        }
+    }
+}
+
+/// Number of unemployed citizens listed at once, the longest unemployed first
+const SHOWN_UNEMPLOYED: usize = 100;
+
+/// Lists the unemployed citizens, the search narrows down the list by name
+fn render_employment(
+    uiw: &UiWorld,
+    sim: &Simulation,
+    search: &mut String,
+    long_term_only: &mut bool,
+) {
+    let city = CityStats::new(sim.world());
+    let time = sim.read::<GameTime>();
+
+    let mut unemployed: Vec<_> = sim
+        .world()
+        .humans
+        .iter()
+        .filter_map(|(id, h)| Some((id, h, unemployed_for(h, &time)?)))
+        .collect();
+    let n_long_term = unemployed
+        .iter()
+        .filter(|(_, _, d)| *d >= LONG_TERM_UNEMPLOYMENT)
+        .count();
+
+    textc(
+        on_primary_container(),
+        format!(
+            "Unemployment: {}, {} citizens, {} for more than {:.0} days",
+            match city.unemployment_rate {
+                Some(rate) => format!("{:.1}%", rate * 100.0),
+                None => "-".to_string(),
+            },
+            unemployed.len(),
+            n_long_term,
+            LONG_TERM_UNEMPLOYMENT.minutes() / 60.0 / HOURS_PER_DAY as f64,
+        ),
+    );
+
+    minrow(5.0, || {
+        text_edit(200.0, search, "Search");
+        checkbox_value(long_term_only, on_primary_container(), "Long-term only");
+    });
+
+    let search = search.to_lowercase();
+    unemployed.retain(|(_, h, d)| {
+        (!*long_term_only || *d >= LONG_TERM_UNEMPLOYMENT)
+            && h.personal_info.name.to_lowercase().contains(&search)
+    });
+    unemployed.sort_by_key(|&(id, _, d)| (Reverse(d), id));
+
+    VertScrollSize::Fixed(300.0).show(|| {
+        let mut grid = CountGrid::col(3);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Citizen"));
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Unemployed for"));
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Happiness"));
+            for &(id, _, d) in unemployed.iter().take(SHOWN_UNEMPLOYED) {
+                padxy(5.0, 3.0, || entity_link(uiw, sim, id));
+                padxy(5.0, 3.0, || textc(on_primary_container(), fmt_duration(d)));
+                padxy(5.0, 3.0, || {
+                    textc(
+                        on_primary_container(),
+                        format!("{:.0}%", happiness(Some(d)) * 100.0),
+                    )
+                });
+            }
+        });
+    });
+}
+
+/// Bar chart of the trips started during each hour of the previous day, shows the rush hours
+fn render_trips_per_hour(trips: &TripStats) {
+    let counts = trips.last_day;
+    let max = counts.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let col = primary();
+
+    textc(on_primary_container(), "Trips per hour (yesterday)");
+    sized_canvas(Vec2::new(240.0, 60.0), Color::BLACK, move |paint| {
+        let rect = paint.layout.get(paint.dom.current()).unwrap().rect;
+        let bar_w = rect.size().x / counts.len() as f32;
+        let bottom = rect.pos().y + rect.size().y;
+
+        for (hour, &n) in counts.iter().enumerate() {
+            let h = (n as f32 / max * rect.size().y).max(1.0);
+            let x = rect.pos().x + hour as f32 * bar_w;
+
+            let mut bar = PaintRect::new(Rect::from_pos_size(
+                Vec2::new(x + 1.0, bottom - h),
+                Vec2::new(bar_w - 2.0, h),
+            ));
+            bar.color = col;
+            bar.add(paint.paint);
+        }
+    });
+    minrow(0.0, || {
+        for hour in ["0h", "6h", "12h", "18h"] {
+            constrained(Constraints::tight(Vec2::new(60.0, 15.0)), || {
+                textc(on_primary_container(), hour);
+            });
+        }
+    });
+}
+
+/// Number of company events shown, most recent first
+const SHOWN_COMPANY_EVENTS: usize = 20;
+
+/// Lists the companies with their profit of the last day, and the latest openings and closings
+fn render_companies(uiw: &UiWorld, sim: &Simulation) {
+    let sort_by_profit = use_state(|| true);
+
+    let mut companies: Vec<_> = sim
+        .world()
+        .companies
+        .values()
+        .map(|c| {
+            (
+                c.comp.building,
+                c.comp.proto.prototype().label.clone(),
+                c.workers.0.len(),
+                c.comp.max_workers,
+                c.comp.finances.yesterday(),
+            )
+        })
+        .collect();
+    if sort_by_profit.get() {
+        companies.sort_by_key(|(_, _, _, _, profit)| Reverse(*profit));
+    } else {
+        companies.sort_by(|a, b| a.1.cmp(&b.1));
+    }
+
+    minrow(10.0, || {
+        if selectable_label_primary(sort_by_profit.get(), "Sort by profit").clicked {
+            sort_by_profit.set(true);
+        }
+        if selectable_label_primary(!sort_by_profit.get(), "Sort by name").clicked {
+            sort_by_profit.set(false);
+        }
+    });
+
+    VertScrollSize::Fixed(300.0).show(|| {
+        let mut grid = CountGrid::col(3);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            for (building, label, workers, max_workers, profit) in companies {
+                padxy(5.0, 3.0, || {
+                    minrow(5.0, || {
+                        textc(on_primary_container(), label);
+                        building_link(uiw, sim, building);
+                    });
+                });
+                padxy(5.0, 3.0, || {
+                    textc(
+                        on_primary_container(),
+                        format!("{}/{} workers", workers, max_workers),
+                    )
+                });
+                padxy(5.0, 3.0, || {
+                    textc(
+                        on_primary_container(),
+                        match profit {
+                            Some(profit) => format!("{} yesterday", fmt_money(profit)),
+                            None => "-".to_string(),
+                        },
+                    )
+                });
+            }
+        });
+    });
+
+    let lifecycle = sim.read::<CompanyLifecycle>();
+    if lifecycle.events.is_empty() {
+        return;
+    }
+    textc(on_primary_container(), "History");
+    mincolumn(2.0, || {
+        for event in lifecycle.events.iter().rev().take(SHOWN_COMPANY_EVENTS) {
+            let name = &event.proto.prototype().label;
+            textc(
+                on_primary_container(),
+                match event.kind {
+                    CompanyEventKind::Opened => format!("Day {}: {} opened", event.day, name),
+                    CompanyEventKind::Shrunk { max_workers } => format!(
+                        "Day {}: {} shrunk to {} workers",
+                        event.day, name, max_workers
+                    ),
+                    CompanyEventKind::Closed => format!("Day {}: {} closed", event.day, name),
+                },
+            );
+        }
+    });
+}
+
+/// Number of intersections listed in the intersections tab
+const SHOWN_INTERSECTIONS: usize = 20;
+
+/// The intersections where the traffic is the worst over the last game day.
+/// Clicking a column ranks them by it, clicking a name moves the camera there.
+fn render_intersections(uiw: &UiWorld, sim: &Simulation, sort: &mut IntersectionSort) {
+    let map = sim.map();
+    let stats = sim.read::<IntersectionStats>();
+    let hour = IntersectionStats::hour(&sim.read::<GameTime>());
+    let worst = stats.worst(hour, *sort, SHOWN_INTERSECTIONS);
+
+    if worst.is_empty() {
+        textc(
+            on_primary_container(),
+            "No traffic through the intersections yet",
+        );
+        return;
+    }
+
+    VertScrollSize::Fixed(300.0).show(|| {
+        let mut grid = CountGrid::col(5);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            padxy(5.0, 3.0, || textc(on_primary_container(), "Intersection"));
+            for (label, s) in [
+                ("Avg. wait", IntersectionSort::AvgWait),
+                ("Vehicles/h", IntersectionSort::Entered),
+                ("Max queue", IntersectionSort::MaxQueue),
+                ("Gridlocks", IntersectionSort::Recoveries),
+            ] {
+                padxy(5.0, 3.0, || {
+                    if selectable_label_primary(*sort == s, label).clicked {
+                        *sort = s;
+                    }
+                });
+            }
+
+            for (id, summary) in worst {
+                padxy(5.0, 3.0, || {
+                    let name = map
+                        .intersection_name(id)
+                        .unwrap_or_else(|| format!("Intersection {:?}", id.data()));
+                    if primary_link(name) {
+                        if let Some(inter) = map.intersections().get(id) {
+                            uiw.camera_mut().targetpos = inter.pos;
+                        }
+                    }
+                });
+                padxy(5.0, 3.0, || {
+                    textc(
+                        on_primary_container(),
+                        format!("{:.1}s", summary.total.avg_wait()),
+                    )
+                });
+                padxy(5.0, 3.0, || {
+                    textc(
+                        on_primary_container(),
+                        format!("{:.0}", summary.entered_per_hour),
+                    )
+                });
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), summary.total.max_queue.to_string())
+                });
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), summary.recoveries.to_string())
+                });
+            }
+        });
+    });
+}
+
+/// Prices of the goods, with the stocks held by the producers and in the warehouses
+fn render_market_prices(sim: &Simulation) {
+    let market = sim.read::<Market>();
+    let ecostats = sim.read::<EcoStats>();
+    let stocks = city_stock(sim.world(), &market);
+
+    VertScrollSize::Fixed(300.0).show(|| {
+        let mut grid = CountGrid::col(5);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            for header in [
+                "Item",
+                "Market value",
+                "City price",
+                "Producer stock",
+                "Warehouse stock",
+            ] {
+                padxy(5.0, 3.0, || textc(on_primary_container(), header));
+            }
+
+            for (&id, m) in market.iter() {
+                let stock = stocks.get(&id).copied().unwrap_or_default();
                 padxy(5.0, 3.0, || {
                     textc(on_primary_container(), &id.prototype().name)
                 });
                 padxy(5.0, 3.0, || {
-                    textc(on_primary_container(), market.ext_value.to_string())
+                    textc(on_primary_container(), m.ext_value.to_string())
+                });
+                padxy(5.0, 3.0, || {
+                    textc(
+                        on_primary_container(),
+                        ecostats.local_price(id, m.ext_value).to_string(),
+                    )
+                });
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), stock.producers.to_string())
+                });
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), stock.warehouses.to_string())
                 });
             }
         });
@@ -434,3 +1181,70 @@ let render_history = |ui: &mut Ui, history: &ItemHistories, hist_type: HistoryTy
         });
 };
 */
+
+/// Compares the districts side by side, clicking a name opens its inspector
+fn render_district_stats(uiw: &UiWorld, sim: &Simulation) {
+    let map = sim.map();
+    if map.districts().is_empty() {
+        textc(
+            on_primary_container(),
+            "No district yet, paint some with the zoning tool",
+        );
+        return;
+    }
+    let stats = uiw.write::<DistrictStatsView>().get(sim).clone();
+
+    let mut districts: Vec<_> = map.districts().values().collect();
+    districts.sort_by(|a, b| a.name.cmp(&b.name));
+
+    VertScrollSize::Fixed(300.0).show(|| {
+        let mut grid = CountGrid::col(6 + ServiceKind::ALL.len());
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            for header in [
+                "District",
+                "Buildings",
+                "Population",
+                "Jobs",
+                "Traffic",
+                "Land value",
+            ] {
+                padxy(5.0, 3.0, || textc(on_primary_container(), header));
+            }
+            for service in ServiceKind::ALL {
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), format!("{} coverage", service))
+                });
+            }
+
+            for d in districts {
+                let s = stats.get(&d.id).cloned().unwrap_or_default();
+                padxy(5.0, 3.0, || {
+                    if selectable_label_primary(false, &d.name).clicked {
+                        let mut inspected = uiw.write::<InspectedDistrict>();
+                        inspected.e = Some(d.id);
+                        inspected.name = d.name.clone();
+                    }
+                });
+                for value in [s.buildings, s.population, s.jobs, s.traffic] {
+                    padxy(5.0, 3.0, || {
+                        textc(on_primary_container(), value.to_string())
+                    });
+                }
+                padxy(5.0, 3.0, || {
+                    textc(on_primary_container(), land_value_text(s.land_value))
+                });
+                for service in ServiceKind::ALL {
+                    let coverage = s
+                        .coverage
+                        .iter()
+                        .find(|(k, _)| *k == service)
+                        .and_then(|(_, c)| *c);
+                    padxy(5.0, 3.0, || {
+                        textc(on_primary_container(), coverage_text(coverage))
+                    });
+                }
+            }
+        });
+    });
+}
@@ -0,0 +1,114 @@
+use yakui::widgets::Pad;
+use yakui::Color;
+
+use goryak::{
+    mincolumn, minrow, on_secondary_container, primary_link, selectable_label_primary, text_edit,
+    textc, VertScrollSize, Window,
+};
+use simulation::event_log::{EventCategory, EventLog, EventSubject};
+use simulation::Simulation;
+
+use crate::newgui::chat::severity_color;
+use crate::newgui::{InspectedBuilding, InspectedEntity};
+use crate::uiworld::UiWorld;
+
+/// Events shown at once, the search narrows down the older ones
+const MAX_SHOWN: usize = 200;
+
+pub struct EventLogState {
+    /// Categories shown
+    categories: Vec<EventCategory>,
+    search: String,
+}
+
+impl Default for EventLogState {
+    fn default() -> Self {
+        Self {
+            categories: EventCategory::ALL.to_vec(),
+            search: String::new(),
+        }
+    }
+}
+
+/// Event log window
+/// Lists what happened in the city, newest first. Clicking an event moves the camera to it
+pub fn event_log(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: "Events".into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut state = uiw.write::<EventLogState>();
+
+        minrow(5.0, || {
+            for category in EventCategory::ALL {
+                let shown = state.categories.contains(&category);
+                if selectable_label_primary(shown, category.label()).clicked {
+                    if shown {
+                        state.categories.retain(|&c| c != category);
+                    } else {
+                        state.categories.push(category);
+                    }
+                }
+            }
+        });
+        text_edit(300.0, &mut state.search, "Search");
+
+        let log = sim.read::<EventLog>();
+        let map = sim.map();
+        let events: Vec<_> = log
+            .filtered(&state.categories, &state.search, |b| map.address(b))
+            .take(MAX_SHOWN)
+            .collect();
+        if events.is_empty() {
+            textc(on_secondary_container(), "No events");
+            return;
+        }
+
+        VertScrollSize::Exact(400.0).show(|| {
+            mincolumn(3.0, || {
+                for event in events {
+                    minrow(5.0, || {
+                        textc(on_secondary_container(), format!("{}", event.at));
+                        let col = severity_color(event.severity);
+                        textc(
+                            Color::rgb(
+                                (col.r * 255.0) as u8,
+                                (col.g * 255.0) as u8,
+                                (col.b * 255.0) as u8,
+                            ),
+                            event.category.label(),
+                        );
+
+                        if event.pos.is_none() && event.subject.is_none() {
+                            textc(on_secondary_container(), event.text.clone());
+                            return;
+                        }
+                        if primary_link(event.text.clone()) {
+                            if let Some(pos) = event.pos {
+                                uiw.camera_mut().follow(pos);
+                            }
+                            match event.subject {
+                                Some(EventSubject::Building(b)) => {
+                                    uiw.write::<InspectedBuilding>().e = Some(b);
+                                }
+                                Some(EventSubject::Entity(e)) => {
+                                    // the entity might have moved since, follow where it is now
+                                    if let Some(pos) = sim.pos_any(e) {
+                                        uiw.camera_mut().follow(pos);
+                                    }
+                                    uiw.write::<InspectedEntity>().e = Some(e);
+                                }
+                                // the camera already went to the road
+                                Some(EventSubject::Road(_)) | None => {}
+                            }
+                        }
+                    });
+                }
+            });
+        });
+    });
+}
@@ -0,0 +1,81 @@
+use yakui::widgets::Pad;
+
+use goryak::{
+    button_primary, button_secondary, fixed_spacer, minrow, on_secondary_container, text_edit,
+    textc, Window,
+};
+use simulation::Simulation;
+
+use crate::newgui::input_recording::{InputRecorder, InputRecording};
+use crate::uiworld::UiWorld;
+
+/// Input recording window
+/// Records what the player does with the tools and the camera, and plays it back in place of
+/// the real input, e.g. to show how a tool is used
+pub fn input_recording(uiw: &UiWorld, _sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: "Input recording".into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut rec = uiw.write::<InputRecorder>();
+
+        if rec.is_recording() {
+            let frames = rec.recording.as_ref().map_or(0, |r| r.frames.len());
+            textc(
+                on_secondary_container(),
+                format!("Recording, {} frames", frames),
+            );
+            if button_primary("Stop").show().clicked {
+                rec.stop();
+            }
+            return;
+        }
+        if rec.is_replaying() {
+            textc(on_secondary_container(), "Replaying, escape to stop");
+            return;
+        }
+
+        minrow(5.0, || {
+            text_edit(150.0, &mut rec.name, "Recording name");
+            if button_primary("Save").show().clicked && !rec.name.is_empty() {
+                if let Some(ref recording) = rec.recording {
+                    recording.save(&rec.name);
+                }
+            }
+        });
+        minrow(5.0, || {
+            for name in InputRecording::saved_names() {
+                if button_secondary(&name).show().clicked {
+                    if let Some(recording) = InputRecording::load(&name) {
+                        rec.recording = Some(recording);
+                        rec.name = name;
+                    }
+                }
+            }
+        });
+
+        fixed_spacer((0.0, 10.0));
+        if let Some(ref recording) = rec.recording {
+            textc(
+                on_secondary_container(),
+                format!(
+                    "{:.1}s, {} frames",
+                    recording.duration(),
+                    recording.frames.len()
+                ),
+            );
+        }
+        minrow(5.0, || {
+            if button_primary("Record").show().clicked {
+                rec.record(uiw);
+            }
+            if rec.recording.is_some() && button_primary("Replay").show().clicked {
+                rec.replay(uiw);
+            }
+        });
+    });
+}
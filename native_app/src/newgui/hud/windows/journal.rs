@@ -0,0 +1,94 @@
+use yakui::widgets::Pad;
+use yakui::{image, Vec2};
+
+use goryak::{
+    button_primary, button_secondary, fixed_spacer, mincolumn, minrow, on_secondary_container,
+    text_edit, textc, titlec, VertScrollSize, Window,
+};
+use simulation::Simulation;
+
+use crate::newgui::journal::{Journal, JournalEntry, THUMB_WIDTH};
+use crate::newgui::map_export::open_folder;
+use crate::uiworld::UiWorld;
+
+/// Journal window
+/// The timeline of the photos taken at the milestones of the city and by the player
+pub fn journal(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: "Journal".into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut journal = uiw.write::<Journal>();
+
+        minrow(5.0, || {
+            let entered = text_edit(300.0, &mut journal.note, "Note");
+            if button_primary("Add journal entry").show().clicked || entered {
+                let note = std::mem::take(&mut journal.note);
+                journal.add_entry(sim, "Journal entry".to_string(), note);
+            }
+            if journal.is_capturing() {
+                textc(on_secondary_container(), "Taking the photo...");
+            }
+        });
+
+        if journal.entries.is_empty() {
+            textc(
+                on_secondary_container(),
+                "Photos are added when the city reaches a milestone",
+            );
+            return;
+        }
+
+        let mut delete = None;
+        VertScrollSize::Exact(500.0).show(|| {
+            mincolumn(10.0, || {
+                for entry in &journal.entries {
+                    minrow(10.0, || {
+                        if let Some(thumb) = journal.thumb(entry.id) {
+                            let w = THUMB_WIDTH as f32;
+                            image(thumb, Vec2::new(w, w / entry.aspect));
+                        } else {
+                            fixed_spacer((THUMB_WIDTH as f32, 0.0));
+                        }
+                        mincolumn(3.0, || {
+                            caption(entry);
+                            minrow(5.0, || {
+                                if entry.has_photo && button_secondary("Open folder").show().clicked
+                                {
+                                    open_folder(&entry.photo_path());
+                                }
+                                if button_secondary("Delete").show().clicked {
+                                    delete = Some(entry.id);
+                                }
+                            });
+                        });
+                    });
+                }
+            });
+        });
+
+        if let Some(id) = delete {
+            journal.delete(id);
+        }
+    });
+}
+
+fn caption(entry: &JournalEntry) {
+    titlec(on_secondary_container(), entry.caption.clone());
+    textc(on_secondary_container(), entry.date.clone());
+    if !entry.note.is_empty() {
+        textc(on_secondary_container(), entry.note.clone());
+    }
+    let s = &entry.stats;
+    textc(
+        on_secondary_container(),
+        format!(
+            "{} residents, {} buildings, {} trains, {}",
+            s.population, s.buildings, s.trains, s.money
+        ),
+    );
+}
@@ -1,12 +1,16 @@
 #![allow(unused)]
+use crate::newgui::windows::mod_settings::mod_settings_editor;
+use crate::newgui::windows::rules::rules_editor;
 use crate::uiworld::{SaveLoadState, UiWorld};
 use egui::{Color32, DroppedFile, Widget};
 use goryak::{
-    button_primary, error, minrow, on_primary, on_secondary_container, primary, textc, ProgressBar,
-    Window,
+    button_primary, combo_box, dragvalue, error, minrow, on_primary, on_secondary_container,
+    primary, textc, ProgressBar, Window,
 };
+use prototypes::ScenarioPrototype;
+use simulation::scenario::start_scenario;
 use simulation::utils::scheduler::SeqSchedule;
-use simulation::Simulation;
+use simulation::{Latitude, Simulation, SimulationOptions};
 use std::path::PathBuf;
 use yakui::widgets::Pad;
 use yakui::{Color, Vec2};
@@ -15,6 +19,8 @@ pub struct LoadState {
     curpath: Option<PathBuf>,
     load_fail: String,
     has_save: bool,
+    /// Options of the next new game
+    new_game: SimulationOptions,
 }
 
 impl Default for LoadState {
@@ -23,10 +29,69 @@ impl Default for LoadState {
             curpath: None,
             load_fail: String::new(),
             has_save: std::fs::metadata("world/world_replay.json").is_ok(),
+            new_game: SimulationOptions::default(),
         }
     }
 }
 
+/// Starts a scenario either from its bundled save or from a newly generated terrain
+pub fn new_scenario(scenario: &ScenarioPrototype) -> Option<Simulation> {
+    let Some(ref save) = scenario.save else {
+        return Some(Simulation::new_with_options(SimulationOptions {
+            terrain_size: scenario.terrain_size,
+            scenario: Some(scenario.id),
+            ..Default::default()
+        }));
+    };
+    let mut sim = Simulation::load_from_disk(save)?;
+    start_scenario(&mut sim, scenario.id);
+    Some(sim)
+}
+
+/// Parameters of the terrain and the simulation of a new game
+pub fn new_game_options(opts: &mut SimulationOptions) {
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.0)
+            .max(1.0)
+            .step(0.05)
+            .show(&mut opts.car_ownership_rate);
+        textc(
+            on_secondary_container(),
+            "Share of new inhabitants owning a car",
+        );
+    });
+    minrow(5.0, || {
+        dragvalue()
+            .min(0.0)
+            .max(60.0)
+            .step(1.0)
+            .show(&mut opts.season_days);
+        textc(
+            on_secondary_container(),
+            "Days per season, 0 disables seasons",
+        );
+    });
+    minrow(5.0, || {
+        let labels = Latitude::ALL.map(Latitude::label);
+        let mut selected = Latitude::ALL
+            .iter()
+            .position(|&l| l == opts.latitude)
+            .unwrap_or(0);
+        if combo_box(&mut selected, &labels, 150.0) {
+            opts.latitude = Latitude::ALL[selected];
+        }
+        textc(
+            on_secondary_container(),
+            "Latitude, the days are shorter in winter away from the equator",
+        );
+    });
+    textc(on_secondary_container(), "Rules");
+    rules_editor(&mut opts.rules, false);
+    textc(on_secondary_container(), "Mod settings");
+    mod_settings_editor(&mut opts.mod_settings);
+}
+
 /// Load window
 /// Allows to load a replay from disk and play it
 pub fn load(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
@@ -41,7 +106,24 @@ pub fn load(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
         let mut state = uiw.write::<LoadState>();
 
         if button_primary("New Game").show().clicked {
-            uiw.write::<SaveLoadState>().please_load_sim = Some(Simulation::new(true));
+            uiw.write::<SaveLoadState>().please_load_sim =
+                Some(Simulation::new_with_options(state.new_game.clone()));
+        }
+        new_game_options(&mut state.new_game);
+
+        textc(on_secondary_container(), "Scenarios");
+        for scenario in ScenarioPrototype::iter() {
+            minrow(5.0, || {
+                if button_primary(&scenario.label).show().clicked {
+                    match new_scenario(scenario) {
+                        Some(sim) => uiw.write::<SaveLoadState>().please_load_sim = Some(sim),
+                        None => {
+                            state.load_fail = format!("Failed to load scenario {}", scenario.label)
+                        }
+                    }
+                }
+                textc(on_secondary_container(), scenario.description.clone());
+            });
         }
 
         if state.has_save {
@@ -0,0 +1,136 @@
+use yakui::widgets::Pad;
+use yakui::{reflow, Alignment, Dim2, Pivot};
+
+use goryak::{
+    blur_bg, button_primary, button_secondary, capture_pointer, checkbox_value, dragvalue,
+    fixed_spacer, mincolumn, minrow, on_secondary_container, padxy, secondary_container,
+    selectable_label_primary, textc, Window,
+};
+use simulation::map::ExportBackground;
+use simulation::Simulation;
+
+use crate::newgui::map_export::{open_folder, ExportFormat, ExportOutcome, MapExporter};
+use crate::uiworld::UiWorld;
+
+/// Seconds the result of an export stays on screen
+const TOAST_SECONDS: f32 = 10.0;
+
+/// Map export window
+/// Chooses the format, the scale and what is drawn before writing the whole map to a file
+pub fn map_export(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: "Export map".into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut exporter = uiw.write::<MapExporter>();
+
+        minrow(5.0, || {
+            textc(on_secondary_container(), "Format");
+            for (format, label) in [
+                (ExportFormat::Svg, "SVG (vector)"),
+                (ExportFormat::Png, "PNG (rendered)"),
+            ] {
+                if selectable_label_primary(exporter.format == format, label).clicked {
+                    exporter.format = format;
+                }
+            }
+        });
+
+        minrow(5.0, || {
+            textc(on_secondary_container(), "Scale");
+            dragvalue()
+                .min(0.05)
+                .max(8.0)
+                .step(0.05)
+                .show(&mut exporter.options.scale);
+            textc(on_secondary_container(), "pixels per meter");
+        });
+        let (w, h) = exporter.output_size(sim);
+        textc(on_secondary_container(), format!("Output size: {w} x {h}"));
+
+        match exporter.format {
+            ExportFormat::Svg => {
+                fixed_spacer((0.0, 5.0));
+                textc(on_secondary_container(), "Layers");
+                let layers = &mut exporter.options.layers;
+                let col = on_secondary_container();
+                checkbox_value(&mut layers.water, col, "Water");
+                checkbox_value(&mut layers.roads, col, "Roads");
+                checkbox_value(&mut layers.rails, col, "Rails");
+                checkbox_value(&mut layers.buildings, col, "Buildings");
+                checkbox_value(&mut layers.labels, col, "Major road names");
+
+                minrow(5.0, || {
+                    textc(on_secondary_container(), "Background");
+                    for bg in ExportBackground::ALL {
+                        let label = format!("{bg:?}");
+                        if selectable_label_primary(exporter.options.background == bg, &label)
+                            .clicked
+                        {
+                            exporter.options.background = bg;
+                        }
+                    }
+                });
+            }
+            ExportFormat::Png => {
+                textc(
+                    on_secondary_container(),
+                    "The city is rendered as seen in game, from far above",
+                );
+            }
+        }
+
+        fixed_spacer((0.0, 5.0));
+        if let Some((done, total)) = exporter.progress() {
+            textc(
+                on_secondary_container(),
+                format!("Rendering tile {done} / {total}"),
+            );
+        } else if button_primary("Export").show().clicked {
+            exporter.export(uiw, sim);
+        }
+    });
+}
+
+/// Tells where the last export went, with a button to open its folder
+pub fn export_toast(uiw: &UiWorld) {
+    let exporter = uiw.read::<MapExporter>();
+    let Some((ref outcome, at)) = exporter.last else {
+        return;
+    };
+    if at.elapsed().as_secs_f32() > TOAST_SECONDS {
+        return;
+    }
+
+    reflow(
+        Alignment::TOP_CENTER,
+        Pivot::TOP_CENTER,
+        Dim2::pixels(0.0, 60.0),
+        || {
+            capture_pointer(|| {
+                blur_bg(secondary_container().with_alpha(0.7), 10.0, || {
+                    padxy(10.0, 8.0, || {
+                        mincolumn(5.0, || match outcome {
+                            ExportOutcome::Written(path) => {
+                                textc(
+                                    on_secondary_container(),
+                                    format!("Map exported to {}", path.display()),
+                                );
+                                if button_secondary("Open folder").show().clicked {
+                                    open_folder(path);
+                                }
+                            }
+                            ExportOutcome::Failed(e) => {
+                                textc(on_secondary_container(), format!("Map export failed: {e}"));
+                            }
+                        });
+                    });
+                })
+            });
+        },
+    );
+}
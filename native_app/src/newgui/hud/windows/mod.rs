@@ -1,10 +1,20 @@
+pub mod budget;
+pub mod camera_path;
+pub mod citizens;
+pub mod diagnostics;
 pub mod economy;
+pub mod event_log;
+pub mod input_recording;
+pub mod journal;
 pub mod load;
+pub mod map_export;
+pub mod mod_settings;
+pub mod rules;
 pub mod settings;
 
 use crate::inputmap::{InputAction, InputMap};
 use crate::uiworld::UiWorld;
-use goryak::button_primary;
+use goryak::{button_primary, highlight_target};
 use simulation::Simulation;
 
 #[cfg(feature = "multiplayer")]
@@ -13,6 +23,17 @@ pub mod network;
 #[derive(Default)]
 pub struct GUIWindows {
     economy_open: bool,
+    budget_open: bool,
+    citizens_open: bool,
+    event_log_open: bool,
+    camera_path_open: bool,
+    input_recording_open: bool,
+    journal_open: bool,
+    map_export_open: bool,
+    /// Opened from the badge of the menu bar
+    diagnostics_open: bool,
+    rules_open: bool,
+    mod_settings_open: bool,
     settings_open: bool,
     load_open: bool,
     #[cfg(feature = "multiplayer")]
@@ -21,22 +42,58 @@ pub struct GUIWindows {
 
 impl GUIWindows {
     pub fn menu(&mut self) {
-        if button_primary("Economy").show().clicked {
-            self.economy_open ^= true;
-        }
+        menu_button("Economy", "economy", &mut self.economy_open);
+        menu_button("Budget", "budget", &mut self.budget_open);
+        menu_button("Citizens", "citizens", &mut self.citizens_open);
+        menu_button("Events", "event_log", &mut self.event_log_open);
+        menu_button("Camera", "camera_path", &mut self.camera_path_open);
+        menu_button(
+            "Recording",
+            "input_recording",
+            &mut self.input_recording_open,
+        );
+        menu_button("Journal", "journal", &mut self.journal_open);
+        menu_button("Export map", "map_export", &mut self.map_export_open);
+        menu_button("Rules", "rules", &mut self.rules_open);
+        menu_button("Mod settings", "mod_settings", &mut self.mod_settings_open);
+        menu_button("Settings", "settings", &mut self.settings_open);
+        menu_button("Load", "load", &mut self.load_open);
+        #[cfg(feature = "multiplayer")]
+        menu_button("Network", "network", &mut self.network_open);
+    }
 
-        if button_primary("Settings").show().clicked {
-            self.settings_open ^= true;
+    /// Whether the window is open, by the name of its menu button (e.g. "economy")
+    pub fn is_open(&self, window: &str) -> bool {
+        match window {
+            "economy" => self.economy_open,
+            "budget" => self.budget_open,
+            "citizens" => self.citizens_open,
+            "event_log" => self.event_log_open,
+            "camera_path" => self.camera_path_open,
+            "input_recording" => self.input_recording_open,
+            "journal" => self.journal_open,
+            "map_export" => self.map_export_open,
+            "diagnostics" => self.diagnostics_open,
+            "rules" => self.rules_open,
+            "mod_settings" => self.mod_settings_open,
+            "settings" => self.settings_open,
+            "load" => self.load_open,
+            #[cfg(feature = "multiplayer")]
+            "network" => self.network_open,
+            _ => false,
         }
+    }
 
-        if button_primary("Load").show().clicked {
-            self.load_open ^= true;
-        }
+    pub fn open_settings(&mut self) {
+        self.settings_open = true;
+    }
 
-        #[cfg(feature = "multiplayer")]
-        if button_primary("Network").show().clicked {
-            self.network_open ^= true;
-        }
+    pub fn open_load(&mut self) {
+        self.load_open = true;
+    }
+
+    pub fn toggle_diagnostics(&mut self) {
+        self.diagnostics_open ^= true;
     }
 
     pub fn render(&mut self, uiworld: &UiWorld, sim: &Simulation) {
@@ -50,6 +107,16 @@ impl GUIWindows {
         }
 
         economy::economy(uiworld, sim, &mut self.economy_open);
+        budget::budget(uiworld, sim, &mut self.budget_open);
+        citizens::citizens(uiworld, sim, &mut self.citizens_open);
+        event_log::event_log(uiworld, sim, &mut self.event_log_open);
+        camera_path::camera_path(uiworld, sim, &mut self.camera_path_open);
+        input_recording::input_recording(uiworld, sim, &mut self.input_recording_open);
+        journal::journal(uiworld, sim, &mut self.journal_open);
+        map_export::map_export(uiworld, sim, &mut self.map_export_open);
+        diagnostics::diagnostics(uiworld, sim, &mut self.diagnostics_open);
+        rules::rules(uiworld, sim, &mut self.rules_open);
+        mod_settings::mod_settings(uiworld, sim, &mut self.mod_settings_open);
         settings::settings(uiworld, sim, &mut self.settings_open);
         load::load(uiworld, sim, &mut self.load_open);
 
@@ -57,3 +124,12 @@ impl GUIWindows {
         network::network(uiworld, sim, &mut self.network_open);
     }
 }
+
+/// The button can be highlighted by tutorials as "window_{name}"
+fn menu_button(label: &'static str, name: &str, open: &mut bool) {
+    highlight_target(&format!("window_{}", name), || {
+        if button_primary(label).show().clicked {
+            *open ^= true;
+        }
+    });
+}
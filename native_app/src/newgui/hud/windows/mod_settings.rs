@@ -0,0 +1,108 @@
+use yakui::widgets::{checkbox, Pad};
+
+use common::saveload::{Encoder, JSONPretty};
+use goryak::{combo_box, dragvalue, error, minrow, on_secondary_container, textc, Window};
+use prototypes::{
+    loaded_mod_settings, loaded_setting_decls, ModSettingDecl, ModSettingKind, ModSettingValue,
+    ModSettings, SettingStage, MOD_SETTINGS_FILE,
+};
+use simulation::Simulation;
+
+use crate::uiworld::UiWorld;
+
+/// Mod settings window
+/// Shows the settings declared by the loaded mods for this save
+pub fn mod_settings(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: "Mod settings".into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut settings = sim.read::<ModSettings>().clone();
+        if let Some((mod_name, key, value)) = mod_settings_editor(&mut settings) {
+            uiw.commands()
+                .set_mod_setting(mod_name.to_string(), key.to_string(), value);
+        }
+    });
+}
+
+/// Edits the settings of the loaded mods, grouped by mod. Returns the setting that changed.
+/// The startup settings are also saved for the next start, as the prototypes are loaded with them.
+pub fn mod_settings_editor(
+    settings: &mut ModSettings,
+) -> Option<(&'static str, &'static str, ModSettingValue)> {
+    let decls = loaded_setting_decls();
+    if decls.is_empty() {
+        textc(on_secondary_container(), "No loaded mod has settings");
+        return None;
+    }
+
+    let mut changed = None;
+    for (mod_name, decls) in decls {
+        textc(on_secondary_container(), mod_name.clone());
+        for decl in decls {
+            let old = settings
+                .get(mod_name, &decl.key)
+                .cloned()
+                .unwrap_or_else(|| decl.default_value());
+            let new = setting_editor(decl, &old);
+            if new != old && settings.set(mod_name, &decl.key, &new) {
+                changed = Some((mod_name.as_str(), decl.key.as_str(), new));
+                if decl.stage == SettingStage::Startup {
+                    JSONPretty::save(&*settings, MOD_SETTINGS_FILE);
+                }
+            }
+        }
+    }
+
+    if !settings
+        .startup_differences(loaded_mod_settings())
+        .is_empty()
+    {
+        textc(
+            error(),
+            "Some startup settings differ from the loaded prototypes, restart the game to reload them",
+        );
+    }
+
+    changed
+}
+
+fn setting_editor(decl: &ModSettingDecl, value: &ModSettingValue) -> ModSettingValue {
+    let mut new = value.clone();
+    minrow(5.0, || {
+        match (&decl.kind, &mut new) {
+            (ModSettingKind::Bool { .. }, ModSettingValue::Bool(b)) => {
+                *b = checkbox(*b).checked;
+            }
+            (ModSettingKind::Number { min, max, step, .. }, ModSettingValue::Number(n)) => {
+                dragvalue()
+                    .min(*min)
+                    .max(*max)
+                    .step(step.unwrap_or((max - min) / 100.0))
+                    .show(n);
+            }
+            (ModSettingKind::Choice { options, .. }, ModSettingValue::Choice(c)) => {
+                let items: Vec<&str> = options.iter().map(String::as_str).collect();
+                let mut id = items.iter().position(|o| o == c).unwrap_or(0);
+                if combo_box(&mut id, &items, 150.0) {
+                    *c = options[id].clone();
+                }
+            }
+            _ => {}
+        }
+
+        let mut label = decl.label().to_string();
+        if decl.stage == SettingStage::Startup {
+            label.push_str(" (needs a restart)");
+        }
+        textc(on_secondary_container(), label);
+    });
+    if !decl.description.is_empty() {
+        textc(on_secondary_container(), decl.description.clone());
+    }
+    new
+}
@@ -2,14 +2,17 @@ use std::borrow::Cow;
 use std::collections::BTreeMap;
 
 use serde::{Deserialize, Serialize};
-use yakui::divider;
-use yakui::widgets::Pad;
+use yakui::widgets::{Layer, Pad};
+use yakui::{center, divider, reflow, Alignment, Dim2, Pivot, Vec2};
 
 use common::saveload::Encoder;
+use common::timestep::Timestep;
 use goryak::{
-    button_primary, checkbox_value, error, on_secondary_container, outline, text_edit, textc,
-    Window,
+    blur_bg, button_primary, button_secondary, capture_pointer, checkbox_value,
+    constrained_viewport, error, mincolumn, on_secondary, on_secondary_container, outline, primary,
+    text_edit, textc, titlec, ProgressBar, Window,
 };
+use networking::JoinCheck;
 use simulation::Simulation;
 
 use crate::network::NetworkState;
@@ -19,6 +22,9 @@ use crate::uiworld::UiWorld;
 pub struct NetworkConnectionInfo {
     pub name: String,
     pub ip: String,
+    /// Join servers without being able to change the world
+    #[serde(default)]
+    pub spectator: bool,
     #[serde(skip)]
     pub error: String,
     #[serde(skip)]
@@ -71,6 +77,7 @@ pub fn network(uiworld: &UiWorld, sim: &Simulation, opened: &mut bool) {
                 divider(outline(), 5.0, 1.0);
 
                 text_edit(200.0, &mut info.ip, "IP");
+                checkbox_value(&mut info.spectator, on_secondary_container(), "Spectate");
 
                 if button_primary("Connect").show().clicked {
                     if let Some(c) = crate::network::start_client(&mut info) {
@@ -79,7 +86,22 @@ pub fn network(uiworld: &UiWorld, sim: &Simulation, opened: &mut bool) {
                 }
             }
             NetworkState::Client(ref client) => {
-                label(client.lock().unwrap().describe());
+                let client = client.lock().unwrap();
+                label(client.describe());
+                if client.is_spectator() {
+                    label("Spectating");
+                }
+                match client.join_check() {
+                    JoinCheck::Pending => {}
+                    JoinCheck::Clean => label("Same world as the server after joining"),
+                    JoinCheck::Desync => {
+                        textc(
+                            error(),
+                            "Desync: different world from the server after joining",
+                        );
+                    }
+                }
+                drop(client);
                 show_hashes(sim, &mut info);
             }
             NetworkState::Server(ref server) => {
@@ -91,6 +113,51 @@ pub fn network(uiworld: &UiWorld, sim: &Simulation, opened: &mut bool) {
     });
 }
 
+/// Shown instead of the game while downloading the world of a server and catching up.
+/// Returns true if it was shown.
+pub fn joining_screen(uiworld: &UiWorld) -> bool {
+    let mut state = uiworld.write::<NetworkState>();
+    let Some((stage, progress)) = state.join_progress() else {
+        return false;
+    };
+
+    let mut cancel = false;
+    Layer::new().show(|| {
+        reflow(
+            Alignment::TOP_LEFT,
+            Pivot::TOP_LEFT,
+            Dim2::pixels(0.0, 0.0),
+            || {
+                capture_pointer(|| {
+                    blur_bg(primary().with_alpha(0.8), 0.0, || {
+                        constrained_viewport(|| {
+                            center(|| {
+                                mincolumn(10.0, || {
+                                    titlec(on_secondary(), "Joining");
+                                    ProgressBar {
+                                        value: progress,
+                                        size: Vec2::new(400.0, 25.0),
+                                        color: primary().adjust(0.7),
+                                    }
+                                    .show_children(|| {
+                                        textc(on_secondary(), stage);
+                                    });
+                                    cancel = button_secondary("Cancel").show().clicked;
+                                });
+                            });
+                        });
+                    })
+                });
+            },
+        );
+    });
+
+    if cancel {
+        *state = NetworkState::Singleplayer(Timestep::default());
+    }
+    true
+}
+
 fn show_hashes(sim: &Simulation, info: &mut NetworkConnectionInfo) {
     checkbox_value(
         &mut info.show_hashes,
@@ -0,0 +1,256 @@
+use yakui::widgets::Pad;
+
+use goryak::{checkbox_value, dragvalue, minrow, on_secondary_container, textc, Window};
+use prototypes::Money;
+use simulation::rules::{GameRules, Rule};
+use simulation::Simulation;
+
+use crate::uiworld::UiWorld;
+
+/// Rules window
+/// Shows the rules of the game, they can be changed if the game allows it
+pub fn rules(uiw: &UiWorld, sim: &Simulation, opened: &mut bool) {
+    Window {
+        title: "Rules".into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut rules = sim.read::<GameRules>().clone();
+        if !rules.allow_changing {
+            textc(
+                on_secondary_container(),
+                "The rules of this game cannot be changed",
+            );
+            rules_summary(&rules);
+            return;
+        }
+
+        if rules_editor(&mut rules, true) {
+            uiw.commands().set_game_rules(rules);
+        }
+    });
+}
+
+/// Rules of the game as text
+fn rules_summary(rules: &GameRules) {
+    let lines = [
+        format!("Starting money: {}", rules.starting_money),
+        format!("Road cost: x{:.2}", rules.road_cost),
+        format!("Building cost: x{:.2}", rules.building_cost),
+        format!(
+            "Blackouts stop companies: {}",
+            if rules.utilities_required {
+                "yes"
+            } else {
+                "no"
+            }
+        ),
+        format!("Road wear: x{:.2}", rules.road_wear),
+        format!(
+            "Days losing money before bankruptcy: {}",
+            rules.bankruptcy_days
+        ),
+        format!(
+            "Days empty before a building is derelict: {}",
+            rules.derelict_days
+        ),
+        format!(
+            "Restricted areas can be edited: {}",
+            if rules.sandbox { "yes" } else { "no" }
+        ),
+        format!(
+            "Lakes go back to the water table: {}",
+            if rules.lake_equalization { "yes" } else { "no" }
+        ),
+        format!(
+            "Roads built without roadworks: {}",
+            if rules.instant_construction {
+                "yes"
+            } else {
+                "no"
+            }
+        ),
+        format!("Construction time: x{:.2}", rules.construction_time),
+        format!(
+            "Buildings can catch fire: {}",
+            if rules.disasters { "yes" } else { "no" }
+        ),
+        format!("Income tax: {:.0}%", rules.income_tax * 100.0),
+    ];
+    for line in lines {
+        textc(on_secondary_container(), line);
+    }
+}
+
+/// Edits the rules, the locked ones are only shown. Returns true if a rule changed.
+/// The starting money can only be chosen before the game starts.
+pub fn rules_editor(rules: &mut GameRules, in_game: bool) -> bool {
+    let before = rules.clone();
+
+    if !in_game && !rules.is_locked(Rule::StartingMoney) {
+        minrow(5.0, || {
+            let mut bucks = rules.starting_money.bucks();
+            if dragvalue().min(0.0).step(1000.0).show(&mut bucks) {
+                rules.starting_money = Money::new_bucks(bucks);
+            }
+            textc(on_secondary_container(), "Starting money");
+        });
+    }
+
+    multiplier(rules, Rule::RoadCost, "Road cost multiplier", |r| {
+        &mut r.road_cost
+    });
+    multiplier(rules, Rule::BuildingCost, "Building cost multiplier", |r| {
+        &mut r.building_cost
+    });
+    multiplier(rules, Rule::RoadWear, "Road wear multiplier", |r| {
+        &mut r.road_wear
+    });
+    multiplier(
+        rules,
+        Rule::ConstructionTime,
+        "Construction time multiplier",
+        |r| &mut r.construction_time,
+    );
+
+    if rules.is_locked(Rule::IncomeTax) {
+        locked_rule(format!("Income tax: {:.0}%", rules.income_tax * 100.0));
+    } else {
+        minrow(5.0, || {
+            let mut percent = rules.income_tax * 100.0;
+            if dragvalue().min(0.0).max(50.0).step(1.0).show(&mut percent) {
+                rules.income_tax = percent / 100.0;
+            }
+            textc(on_secondary_container(), "Income tax (%)");
+        });
+    }
+
+    if rules.is_locked(Rule::BankruptcyDays) {
+        locked_rule(format!(
+            "Days losing money before bankruptcy: {}",
+            rules.bankruptcy_days
+        ));
+    } else {
+        minrow(5.0, || {
+            dragvalue()
+                .min(1.0)
+                .max(30.0)
+                .step(1.0)
+                .show(&mut rules.bankruptcy_days);
+            textc(
+                on_secondary_container(),
+                "Days losing money before bankruptcy",
+            );
+        });
+    }
+
+    if rules.is_locked(Rule::DerelictDays) {
+        locked_rule(format!(
+            "Days empty before a building is derelict: {}",
+            rules.derelict_days
+        ));
+    } else {
+        minrow(5.0, || {
+            dragvalue()
+                .min(1.0)
+                .max(30.0)
+                .step(1.0)
+                .show(&mut rules.derelict_days);
+            textc(
+                on_secondary_container(),
+                "Days empty before a building is derelict",
+            );
+        });
+    }
+
+    if rules.is_locked(Rule::UtilitiesRequired) {
+        locked_rule(format!(
+            "Blackouts stop companies: {}",
+            if rules.utilities_required {
+                "yes"
+            } else {
+                "no"
+            }
+        ));
+    } else {
+        checkbox_value(
+            &mut rules.utilities_required,
+            on_secondary_container(),
+            "Blackouts stop companies",
+        );
+    }
+
+    if rules.is_locked(Rule::Sandbox) {
+        locked_rule(format!(
+            "Restricted areas can be edited: {}",
+            if rules.sandbox { "yes" } else { "no" }
+        ));
+    } else {
+        checkbox_value(
+            &mut rules.sandbox,
+            on_secondary_container(),
+            "Sandbox: restricted areas can be edited",
+        );
+    }
+
+    if rules.is_locked(Rule::Disasters) {
+        locked_rule(format!(
+            "Buildings can catch fire: {}",
+            if rules.disasters { "yes" } else { "no" }
+        ));
+    } else {
+        checkbox_value(
+            &mut rules.disasters,
+            on_secondary_container(),
+            "Disasters: buildings can catch fire",
+        );
+    }
+
+    checkbox_value(
+        &mut rules.lake_equalization,
+        on_secondary_container(),
+        "Lakes go back to the water table through evaporation and rain",
+    );
+
+    checkbox_value(
+        &mut rules.instant_construction,
+        on_secondary_container(),
+        "Instant construction: roads are built without roadworks",
+    );
+
+    if !in_game {
+        checkbox_value(
+            &mut rules.allow_changing,
+            on_secondary_container(),
+            "Allow changing the rules during the game",
+        );
+    }
+
+    *rules != before
+}
+
+fn multiplier(
+    rules: &mut GameRules,
+    rule: Rule,
+    label: &'static str,
+    value: impl Fn(&mut GameRules) -> &mut f32,
+) {
+    if rules.is_locked(rule) {
+        locked_rule(format!("{}: x{:.2}", label, value(rules)));
+        return;
+    }
+    minrow(5.0, || {
+        dragvalue().min(0.0).max(5.0).step(0.1).show(value(rules));
+        textc(on_secondary_container(), label);
+    });
+}
+
+fn locked_rule(text: String) {
+    textc(
+        on_secondary_container(),
+        format!("{} (set by the scenario)", text),
+    );
+}
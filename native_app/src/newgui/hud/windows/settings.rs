@@ -2,26 +2,34 @@ use std::time::{Duration, Instant};
 
 use yakui::widgets::{CountGrid, List, Pad};
 use yakui::{
-    constrained, divider, Constraints, CrossAxisAlignment, MainAxisAlignItems, MainAxisSize, Vec2,
+    colored_box_container, constrained, divider, Constraints, CrossAxisAlignment,
+    MainAxisAlignItems, MainAxisSize, Vec2,
 };
 
 use common::saveload::Encoder;
-use engine::GfxSettings;
-use engine::ShadowQuality;
+use engine::{GamepadSettings, GfxSettings};
+use engine::{ShadowQuality, ANISOTROPY_LEVELS};
 use goryak::{
-    button_primary, checkbox_value, combo_box, dragvalue, icon_button, minrow,
+    button_primary, checkbox_value, combo_box, dragvalue, fixed_spacer, icon_button, minrow,
     on_secondary_container, outline, padx, padxy, textc, VertScrollSize, Window,
 };
+use prototypes::TICKS_PER_REALTIME_SECOND;
 use serde::{Deserialize, Serialize};
+use simulation::utils::savefile::SaveCompression;
 use simulation::Simulation;
 
 use crate::game_loop::Timings;
 use crate::inputmap::{Bindings, InputMap};
 use crate::newgui::keybinds::{KeybindState, KeybindStateInner};
+use crate::newgui::units::UnitSystem;
+use crate::rendering::ColorPalette;
 use crate::uiworld::UiWorld;
 
 const SETTINGS_SAVE_NAME: &str = "settings";
 
+pub const MIN_BATCH_RATE: u32 = 20;
+pub const MAX_BATCH_RATE: u32 = 60;
+
 #[derive(Copy, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
 pub struct Settings {
@@ -29,19 +37,48 @@ pub struct Settings {
     pub camera_smooth: bool,
     pub camera_smooth_tightness: f32,
     pub camera_fov: f32,
+    /// Seconds each shot of the cinematic camera lasts
+    pub cinematic_shot_duration: f32,
 
     pub gfx: GfxSettings,
+    pub gamepad: GamepadSettings,
 
     pub gui_scale: f32,
+    /// Units of the distances, speeds and areas shown in the UI
+    pub units: UnitSystem,
+
+    /// Colors of the overlays, zones and tool gizmos
+    pub palette: ColorPalette,
+    /// Size of the labels written over the map
+    pub overlay_label_scale: f32,
+    /// Thickness of the lines drawn by the tools and overlays
+    pub gizmo_thickness: f32,
+
+    pub water_wave_scale: f32,
+    pub water_wave_speed: f32,
 
     pub master_volume_percent: f32,
     pub music_volume_percent: f32,
     pub effects_volume_percent: f32,
     pub ui_volume_percent: f32,
+    /// Play the sounds of the audio-event prototypes when events are logged
+    pub event_sounds: bool,
+    /// Raise the pitch of the vehicles a little when the game is sped up
+    pub speed_pitch_shift: bool,
 
     #[serde(skip)]
     pub time_warp: u32,
+    /// Batches of simulation ticks per real second. The game always ticks
+    /// TICKS_PER_REALTIME_SECOND times per second, each batch runs the ticks due since the last one
+    pub batch_rate: u32,
+    /// Draw the moving entities between the states before and after the last update
+    pub interpolation: bool,
     pub auto_save_every: AutoSaveEvery,
+    pub auto_save_compression: SaveCompression,
+    /// Compression of the saves made from the menu
+    pub save_compression: SaveCompression,
+    /// Show breakdowns and cleared roads in the chat
+    pub incident_toasts: bool,
 }
 
 impl Default for Settings {
@@ -53,12 +90,27 @@ impl Default for Settings {
             music_volume_percent: 100.0,
             effects_volume_percent: 100.0,
             ui_volume_percent: 100.0,
+            event_sounds: true,
+            speed_pitch_shift: true,
             time_warp: 1,
+            batch_rate: TICKS_PER_REALTIME_SECOND as u32,
+            interpolation: true,
             auto_save_every: AutoSaveEvery::FiveMinutes,
+            auto_save_compression: SaveCompression::Fast,
+            save_compression: SaveCompression::Max,
+            incident_toasts: true,
             camera_smooth_tightness: 1.0,
             camera_fov: 60.0,
+            cinematic_shot_duration: 12.0,
             gui_scale: 1.0,
+            units: UnitSystem::Metric,
+            palette: ColorPalette::Standard,
+            overlay_label_scale: 1.0,
+            gizmo_thickness: 1.0,
+            water_wave_scale: 1.0,
+            water_wave_speed: 1.0,
             gfx: GfxSettings::default(),
+            gamepad: GamepadSettings::default(),
         }
     }
 }
@@ -102,6 +154,27 @@ impl AsRef<str> for AutoSaveEvery {
     }
 }
 
+/// Fast saves quickly, Smallest takes longer for a smaller file
+fn compression_combo(compression: &mut SaveCompression, label: &str) {
+    minrow(5.0, || {
+        textc(on_secondary_container(), label);
+        let mut id = SaveCompression::ALL
+            .iter()
+            .position(|c| c == compression)
+            .unwrap_or(0);
+        if combo_box(
+            &mut id,
+            &[
+                SaveCompression::Fast.as_ref(),
+                SaveCompression::Max.as_ref(),
+            ],
+            200.0,
+        ) {
+            *compression = SaveCompression::ALL[id];
+        }
+    });
+}
+
 pub struct SettingsState {
     fps: f32,
     ms: f32,
@@ -156,6 +229,33 @@ pub fn settings(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
                         settings.auto_save_every = AutoSaveEvery::from(id as u8);
                     }
                 });
+                compression_combo(&mut settings.auto_save_compression, "Auto save compression");
+                compression_combo(&mut settings.save_compression, "Save compression");
+                checkbox_value(
+                    &mut settings.incident_toasts,
+                    on_secondary_container(),
+                    "Notify traffic incidents",
+                );
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(MIN_BATCH_RATE as f64)
+                        .max(MAX_BATCH_RATE as f64)
+                        .step(1.0)
+                        .show(&mut settings.batch_rate);
+                    textc(on_secondary_container(), "Simulation batches per second");
+                });
+                textc(
+                    on_secondary_container(),
+                    format!(
+                        "The simulation ticks {} times per second, batches group them",
+                        TICKS_PER_REALTIME_SECOND
+                    ),
+                );
+                checkbox_value(
+                    &mut settings.interpolation,
+                    on_secondary_container(),
+                    "Smooth motion between updates",
+                );
 
                 divider(outline(), 10.0, 1.0);
                 textc(on_secondary_container(), "Input");
@@ -190,6 +290,42 @@ pub fn settings(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
                     textc(on_secondary_container(), "Camera Field of View (FOV)");
                 });
 
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(3.0)
+                        .max(60.0)
+                        .step(1.0)
+                        .show(&mut settings.cinematic_shot_duration);
+                    textc(on_secondary_container(), "Cinematic shot duration (s)");
+                });
+
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(0.0)
+                        .max(0.9)
+                        .step(0.01)
+                        .show(&mut settings.gamepad.dead_zone);
+                    textc(on_secondary_container(), "Gamepad stick dead zone");
+                });
+
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(0.5)
+                        .max(4.0)
+                        .step(0.1)
+                        .show(&mut settings.gamepad.curve);
+                    textc(on_secondary_container(), "Gamepad stick response curve");
+                });
+
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(100.0)
+                        .max(3000.0)
+                        .step(10.0)
+                        .show(&mut settings.gamepad.cursor_speed);
+                    textc(on_secondary_container(), "Gamepad cursor speed");
+                });
+
                 // only update the fps every 300ms to avoid flickering
                 if state.fps == 0.0 || state.instant.elapsed() > Duration::from_millis(300) {
                     state.ms = uiw.read::<Timings>().all.avg();
@@ -232,6 +368,11 @@ pub fn settings(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
                     on_secondary_container(),
                     "MSAA 4x Anti-aliasing",
                 );
+                checkbox_value(
+                    &mut settings.gfx.outlines,
+                    on_secondary_container(),
+                    "Selection outlines",
+                );
                 checkbox_value(&mut settings.gfx.vsync, on_secondary_container(), "VSync");
                 checkbox_value(
                     &mut settings.gfx.parallel_render,
@@ -257,12 +398,105 @@ pub fn settings(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
                     textc(on_secondary_container(), "Shadow Quality");
                 });
 
+                minrow(5.0, || {
+                    let mut id = ANISOTROPY_LEVELS
+                        .iter()
+                        .position(|&l| l == settings.gfx.anisotropy)
+                        .unwrap_or(ANISOTROPY_LEVELS.len() - 1);
+                    let labels = ANISOTROPY_LEVELS.map(|l| format!("{l}x"));
+                    let names = labels.each_ref().map(String::as_str);
+                    if combo_box(&mut id, &names, 200.0) {
+                        settings.gfx.anisotropy = ANISOTROPY_LEVELS[id];
+                    }
+                    textc(on_secondary_container(), "Anisotropic filtering");
+                });
+                checkbox_value(
+                    &mut settings.gfx.terrain_mip_bias,
+                    on_secondary_container(),
+                    "Sharper distant terrain",
+                );
+
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(0.0)
+                        .max(65536.0)
+                        .step(64.0)
+                        .show(&mut settings.gfx.max_lights);
+                    textc(on_secondary_container(), "Max lights");
+                });
+
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(0.0)
+                        .max(65536.0)
+                        .step(256.0)
+                        .show(&mut settings.gfx.vram_budget_mb);
+                    textc(on_secondary_container(), "VRAM budget (MB, 0 = auto)");
+                });
+
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(0.25)
+                        .max(4.0)
+                        .step(0.05)
+                        .show(&mut settings.water_wave_scale);
+                    textc(on_secondary_container(), "Water wave scale");
+                });
+
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(0.0)
+                        .max(4.0)
+                        .step(0.05)
+                        .show(&mut settings.water_wave_speed);
+                    textc(on_secondary_container(), "Water wave speed");
+                });
+
                 divider(outline(), 10.0, 1.0);
                 textc(on_secondary_container(), "GUI");
                 minrow(5.0, || {
                     dragvalue().min(0.5).max(2.0).show(&mut settings.gui_scale);
                     textc(on_secondary_container(), "GUI Scale");
                 });
+                minrow(5.0, || {
+                    textc(on_secondary_container(), "Units");
+                    let mut id = settings.units as u8 as usize;
+                    if combo_box(&mut id, &UnitSystem::ALL.map(UnitSystem::label), 200.0) {
+                        settings.units = UnitSystem::ALL[id];
+                        settings.units.set_current();
+                    }
+                });
+
+                divider(outline(), 10.0, 1.0);
+                textc(on_secondary_container(), "Accessibility");
+                minrow(5.0, || {
+                    textc(on_secondary_container(), "Color palette");
+                    let mut id = ColorPalette::ALL
+                        .iter()
+                        .position(|&p| p == settings.palette)
+                        .unwrap_or(0);
+                    if combo_box(&mut id, &ColorPalette::ALL.map(ColorPalette::label), 200.0) {
+                        settings.palette = ColorPalette::ALL[id];
+                        settings.palette.set_current();
+                    }
+                });
+                palette_preview(settings.palette);
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(0.5)
+                        .max(3.0)
+                        .step(0.1)
+                        .show(&mut settings.overlay_label_scale);
+                    textc(on_secondary_container(), "Map label size");
+                });
+                minrow(5.0, || {
+                    dragvalue()
+                        .min(0.5)
+                        .max(3.0)
+                        .step(0.1)
+                        .show(&mut settings.gizmo_thickness);
+                    textc(on_secondary_container(), "Tool line thickness");
+                });
 
                 divider(outline(), 10.0, 1.0);
                 textc(on_secondary_container(), "Audio");
@@ -301,6 +535,16 @@ pub fn settings(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
                         .show(&mut settings.ui_volume_percent);
                     textc(on_secondary_container(), "Ui volume");
                 });
+                checkbox_value(
+                    &mut settings.event_sounds,
+                    on_secondary_container(),
+                    "Event sounds",
+                );
+                checkbox_value(
+                    &mut settings.speed_pitch_shift,
+                    on_secondary_container(),
+                    "Pitch up the vehicles when sped up",
+                );
 
                 divider(outline(), 10.0, 1.0);
                 textc(on_secondary_container(), "Keybinds");
@@ -310,59 +554,67 @@ pub fn settings(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
                     uiw.write::<InputMap>().build_input_tree(&mut bindings);
                 }
 
-                let mut sorted_inps = bindings.0.keys().cloned().collect::<Vec<_>>();
-                sorted_inps.sort();
-
-                constrained(
-                    Constraints::loose(Vec2::new(f32::INFINITY, 100000.0)),
-                    || {
-                        CountGrid::col(4)
-                            .main_axis_size(MainAxisSize::Min)
-                            .cross_axis_aligment(CrossAxisAlignment::Start)
-                            .main_axis_align_items(MainAxisAlignItems::Center)
-                            .show(|| {
-                                for action in &sorted_inps {
-                                    let comb = bindings.0.get_mut(action).unwrap();
-                                    padx(2.0, || {
-                                        textc(on_secondary_container(), action.to_string());
-                                    });
-                                    let print_comb = |index: usize| {
+                let default_bindings = Bindings::default();
+                let layers = bindings.0.keys().copied().collect::<Vec<_>>();
+
+                for layer in layers {
+                    textc(on_secondary_container(), layer.to_string());
+                    let acts = bindings.0.get_mut(&layer).unwrap();
+                    let sorted_inps = acts.keys().cloned().collect::<Vec<_>>();
+
+                    constrained(
+                        Constraints::loose(Vec2::new(f32::INFINITY, 100000.0)),
+                        || {
+                            CountGrid::col(4)
+                                .main_axis_size(MainAxisSize::Min)
+                                .cross_axis_aligment(CrossAxisAlignment::Start)
+                                .main_axis_align_items(MainAxisAlignItems::Center)
+                                .show(|| {
+                                    for action in &sorted_inps {
+                                        let comb = acts.get_mut(action).unwrap();
                                         padx(2.0, || {
+                                            textc(on_secondary_container(), action.to_string());
+                                        });
+                                        let print_comb = |index: usize| {
+                                            padx(2.0, || {
+                                                minrow(0.0, || {
+                                                    let resp = if comb.0.len() > index {
+                                                        button_primary(format!("{}", comb.0[index]))
+                                                            .show()
+                                                    } else {
+                                                        button_primary("<empty>").show()
+                                                    };
+                                                    if resp.clicked {
+                                                        let mut state = uiw.write::<KeybindState>();
+                                                        state.enabled = Some(KeybindStateInner {
+                                                            layer,
+                                                            to_bind_to: action.clone(),
+                                                            cur: Default::default(),
+                                                            bind_index: index,
+                                                        });
+                                                    }
+                                                });
+                                            });
+                                        };
+                                        print_comb(0);
+                                        print_comb(1);
+                                        padxy(8.0, 2.0, || {
                                             minrow(0.0, || {
-                                                let resp = if comb.0.len() > index {
-                                                    button_primary(format!("{}", comb.0[index]))
-                                                        .show()
-                                                } else {
-                                                    button_primary("<empty>").show()
-                                                };
-                                                if resp.clicked {
-                                                    let mut state = uiw.write::<KeybindState>();
-                                                    state.enabled = Some(KeybindStateInner {
-                                                        to_bind_to: action.clone(),
-                                                        cur: Default::default(),
-                                                        bind_index: index,
-                                                    });
+                                                if icon_button(button_primary("arrows-rotate"))
+                                                    .show()
+                                                    .clicked
+                                                {
+                                                    comb.0 = default_bindings.0[&layer][action]
+                                                        .0
+                                                        .clone();
                                                 }
                                             });
                                         });
-                                    };
-                                    print_comb(0);
-                                    print_comb(1);
-                                    padxy(8.0, 2.0, || {
-                                        minrow(0.0, || {
-                                            if icon_button(button_primary("arrows-rotate"))
-                                                .show()
-                                                .clicked
-                                            {
-                                                comb.0 =
-                                                    Bindings::default().0.remove(action).unwrap().0;
-                                            }
-                                        });
-                                    });
-                                }
-                            });
-                    },
-                );
+                                    }
+                                });
+                        },
+                    );
+                }
 
                 if *settings != before {
                     common::saveload::JSONPretty::save_silent(&*settings, SETTINGS_SAVE_NAME);
@@ -372,8 +624,48 @@ pub fn settings(uiw: &UiWorld, _: &Simulation, opened: &mut bool) {
     })
 }
 
+/// Strips of the colors of the palette: the overlay ramp, the zones and the tool colors
+fn palette_preview(palette: ColorPalette) {
+    let colors = palette.colors();
+    let strip = |label: &'static str, cols: &[geom::Color]| {
+        minrow(0.0, || {
+            for &c in cols {
+                colored_box_container(to_yakui(c), || {
+                    fixed_spacer((24.0, 16.0));
+                });
+            }
+            padx(5.0, || textc(on_secondary_container(), label));
+        });
+    };
+
+    let ramp: Vec<_> = (0..8).map(|i| colors.ramp(i as f32 / 7.0)).collect();
+    strip("Overlays, good to bad", &ramp);
+    strip(
+        "Zones",
+        &[
+            colors.residential,
+            colors.commercial,
+            colors.industrial,
+            colors.unassigned,
+        ],
+    );
+    strip("Tools", &[colors.primary, colors.success, colors.danger]);
+}
+
+fn to_yakui(c: geom::Color) -> yakui::Color {
+    yakui::Color::rgba(
+        (c.r * 255.0) as u8,
+        (c.g * 255.0) as u8,
+        (c.b * 255.0) as u8,
+        (c.a * 255.0) as u8,
+    )
+}
+
 pub fn manage_settings(ctx: &mut engine::Context, settings: &Settings) {
+    settings.palette.set_current();
+    settings.units.set_current();
     ctx.gfx.update_settings(settings.gfx);
+    ctx.input.gamepad.settings = settings.gamepad;
 
     ctx.egui.zoom_factor = settings.gui_scale;
 
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+
+use common::saveload::{Encoder, JSONPretty};
+use geom::{Vec2, Vec3};
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::camera_path::{saved_names, CameraKeyframe};
+use crate::rendering::OrbitCamera;
+use crate::uiworld::UiWorld;
+
+/// Saved recordings are named after this prefix in the save directory
+const SAVE_PREFIX: &str = "input_recording_";
+
+/// The input of one frame, after the bindings and the interface took their share.
+/// Clicks on the interface are not recorded, only what reaches the tools and the camera.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// Seconds since the start of the recording
+    pub time: f32,
+    /// Actions held during the frame
+    pub act: Vec<InputAction>,
+    pub wheel: f32,
+    pub unprojected: Option<Vec3>,
+    pub unprojected_normal: Option<Vec3>,
+    pub screen: Vec2,
+    pub pan: Vec2,
+    pub look: Vec2,
+}
+
+impl RecordedFrame {
+    fn capture(time: f32, inp: &InputMap) -> Self {
+        let mut act: Vec<InputAction> = inp.act.iter().cloned().collect();
+        act.sort();
+        Self {
+            time,
+            act,
+            wheel: inp.wheel,
+            unprojected: inp.unprojected,
+            unprojected_normal: inp.unprojected_normal,
+            screen: inp.screen,
+            pan: inp.pan,
+            look: inp.look,
+        }
+    }
+}
+
+/// Inputs played back frame by frame, e.g. to show how a tool is used.
+/// The world positions are recorded rather than the screen ones so that the tools act at the
+/// same place whatever the size of the window.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputRecording {
+    /// Where the camera was when the recording started, it is put back there to replay
+    pub start: CameraKeyframe,
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl InputRecording {
+    pub fn duration(&self) -> f32 {
+        self.frames.last().map_or(0.0, |f| f.time)
+    }
+
+    /// Applies the frames from `next` up to `time` to the input map.
+    /// The actions pressed in any of those frames are just pressed, so that a click is not lost
+    /// when the game runs slower than when it was recorded.
+    /// Returns the index of the frame to apply next.
+    fn apply(&self, next: usize, time: f32, inp: &mut InputMap) -> usize {
+        let mut prev: &[InputAction] = match next.checked_sub(1) {
+            Some(i) => &self.frames[i].act,
+            None => &[],
+        };
+        let mut i = next;
+        inp.just_act.clear();
+        inp.wheel = 0.0;
+
+        while let Some(f) = self.frames.get(i) {
+            if f.time > time {
+                break;
+            }
+            for act in &f.act {
+                if !prev.contains(act) {
+                    inp.just_act.insert(act.clone());
+                }
+            }
+            inp.wheel += f.wheel;
+            prev = &f.act;
+            i += 1;
+        }
+
+        inp.act = prev.iter().cloned().collect();
+        if let Some(f) = i.checked_sub(1).and_then(|i| self.frames.get(i)) {
+            inp.unprojected = f.unprojected;
+            inp.unprojected_normal = f.unprojected_normal;
+            inp.screen = f.screen;
+            inp.pan = f.pan;
+            inp.look = f.look;
+        }
+        i
+    }
+
+    pub fn save(&self, name: &str) -> Option<()> {
+        JSONPretty::save(self, &format!("{SAVE_PREFIX}{name}"))
+    }
+
+    pub fn load(name: &str) -> Option<Self> {
+        JSONPretty::load(&format!("{SAVE_PREFIX}{name}"))
+            .map_err(|e| log::error!("could not load input recording {}: {}", name, e))
+            .ok()
+    }
+
+    /// Names of the recordings saved next to the game
+    pub fn saved_names() -> Vec<String> {
+        saved_names(SAVE_PREFIX)
+    }
+}
+
+#[derive(Default, Copy, Clone, PartialEq, Eq)]
+enum RecorderMode {
+    #[default]
+    Idle,
+    Recording,
+    Replaying,
+}
+
+/// Records the input of the player, and replays it in place of the real one
+#[derive(Default)]
+pub struct InputRecorder {
+    pub recording: Option<InputRecording>,
+    /// Name of the recording in the save directory
+    pub name: String,
+    mode: RecorderMode,
+    /// Seconds since the recording or the replay started
+    time: f32,
+    /// Index of the frame to replay next
+    next: usize,
+}
+
+impl InputRecorder {
+    pub fn is_recording(&self) -> bool {
+        self.mode == RecorderMode::Recording
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.mode == RecorderMode::Replaying
+    }
+
+    pub fn record(&mut self, uiw: &UiWorld) {
+        let pose = uiw.read::<OrbitCamera>().pose();
+        self.recording = Some(InputRecording {
+            start: CameraKeyframe::new(0.0, pose, None),
+            frames: vec![],
+        });
+        self.mode = RecorderMode::Recording;
+        self.time = 0.0;
+    }
+
+    pub fn replay(&mut self, uiw: &UiWorld) {
+        let Some(ref recording) = self.recording else {
+            return;
+        };
+        uiw.write::<OrbitCamera>().set_pose(recording.start.pose());
+        self.mode = RecorderMode::Replaying;
+        self.time = 0.0;
+        self.next = 0;
+    }
+
+    pub fn stop(&mut self) {
+        self.mode = RecorderMode::Idle;
+    }
+
+    /// Records the input of the frame, or replaces it with the recorded one.
+    /// Runs once the input map is ready for the frame, escape stops the replay.
+    pub fn update(uiw: &UiWorld, delta: f32) {
+        let mut rec = uiw.write::<InputRecorder>();
+        let rec = &mut *rec;
+        let mut inp = uiw.write::<InputMap>();
+        let Some(ref mut recording) = rec.recording else {
+            return;
+        };
+
+        match rec.mode {
+            RecorderMode::Idle => {}
+            RecorderMode::Recording => {
+                rec.time += delta;
+                recording
+                    .frames
+                    .push(RecordedFrame::capture(rec.time, &inp));
+            }
+            RecorderMode::Replaying => {
+                if inp.just_act.contains(&InputAction::Close) {
+                    rec.mode = RecorderMode::Idle;
+                    inp.just_act.clear();
+                    return;
+                }
+                rec.time += delta;
+                rec.next = recording.apply(rec.next, rec.time, &mut inp);
+                if rec.next >= recording.frames.len() {
+                    rec.mode = RecorderMode::Idle;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::{vec3, Radians};
+
+    use crate::inputmap::InputAction::{GoForward, Select};
+
+    fn frame(time: f32, act: &[InputAction], x: f32) -> RecordedFrame {
+        RecordedFrame {
+            time,
+            act: act.to_vec(),
+            wheel: 1.0,
+            unprojected: Some(vec3(x, 0.0, 0.0)),
+            unprojected_normal: None,
+            screen: Vec2::ZERO,
+            pan: Vec2::ZERO,
+            look: Vec2::ZERO,
+        }
+    }
+
+    fn recording(frames: Vec<RecordedFrame>) -> InputRecording {
+        InputRecording {
+            start: CameraKeyframe {
+                time: 0.0,
+                pos: Vec3::ZERO,
+                yaw: Radians(0.0),
+                pitch: Radians(0.0),
+                dist: 100.0,
+                target: None,
+            },
+            frames,
+        }
+    }
+
+    #[test]
+    fn replay_keeps_the_clicks_of_skipped_frames() {
+        let rec = recording(vec![
+            frame(0.1, &[GoForward], 1.0),
+            frame(0.2, &[GoForward, Select], 2.0),
+            frame(0.3, &[GoForward], 3.0),
+            frame(0.4, &[], 4.0),
+        ]);
+        let mut inp = InputMap::default();
+
+        // the game runs slower than the recording, the click happened in between
+        let next = rec.apply(0, 0.35, &mut inp);
+        assert_eq!(next, 3);
+        assert!(inp.just_act.contains(&Select));
+        assert!(inp.just_act.contains(&GoForward));
+        assert!(inp.act.contains(&GoForward) && !inp.act.contains(&Select));
+        assert_eq!(inp.unprojected, Some(vec3(3.0, 0.0, 0.0)));
+        assert_eq!(inp.wheel, 3.0);
+
+        // faster than the recording, nothing new this frame
+        assert_eq!(rec.apply(next, 0.36, &mut inp), 3);
+        assert!(inp.just_act.is_empty());
+        assert!(inp.act.contains(&GoForward));
+        assert_eq!(inp.wheel, 0.0);
+
+        assert_eq!(rec.apply(next, 1.0, &mut inp), 4);
+        assert!(inp.act.is_empty());
+        assert_eq!(inp.unprojected, Some(vec3(4.0, 0.0, 0.0)));
+    }
+}
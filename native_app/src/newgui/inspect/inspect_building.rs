@@ -1,19 +1,43 @@
 use goryak::{
-    dragvalue, fixed_spacer, minrow, on_secondary_container, primary, textc, ProgressBar, Window,
+    button_primary, button_secondary, checkbox_value, combo_box, dragvalue, error, fixed_spacer,
+    minrow, on_secondary_container, padxy, primary, sized_canvas, textc, ProgressBar, Window,
 };
-use prototypes::{ItemID, Recipe};
-use simulation::economy::Market;
-use simulation::map::{Building, BuildingID, BuildingKind, Zone, MAX_ZONE_AREA};
-use simulation::map_dynamic::{BuildingInfos, ElectricityFlow};
+use prototypes::{
+    prototypes_iter, DepositID, GameDuration, GameTime, ItemID, ItemPrototype, LeisurePrototypeID,
+    Money, Recipe, ServiceKind, Tick,
+};
+use simulation::economy::{ChainLink, EcoStats, Government, Market, TradeLedger};
+use simulation::map::{Building, BuildingID, BuildingKind, FieldStage, Map, Zone, MAX_ZONE_AREA};
+use simulation::map_dynamic::{
+    derelict_after, Abandonment, BuildingInfos, ElectricityFlow, Walkability,
+};
+use simulation::rules::GameRules;
+use simulation::souls::commute::{
+    average_minutes, commute_histogram, COMMUTE_BINS, COMMUTE_BIN_MINUTES,
+};
+use simulation::souls::delivery::{incoming_orders, OrderState};
+use simulation::souls::dock::BoatState;
+use simulation::souls::farm::Field;
 use simulation::souls::freight_station::FreightTrainState;
+use simulation::souls::goods_company::{
+    company_upgrade, upgrade_blockers, CompanyFinances, UpgradeBlocker, PNL_HISTORY_DAYS,
+};
+use simulation::souls::venue_events::{next_event, VenueEvents};
+use simulation::souls::warehouse::{StockRule, RENT_PER_UNIT_PER_DAY};
+use simulation::transportation::service_call::{ServiceCalls, GARBAGE_OVERFLOW};
+use simulation::transportation::service_fleet::ServiceFleets;
 use simulation::world_command::WorldCommand;
-use simulation::{Simulation, SoulID};
+use simulation::{Simulation, SimulationOptions, SoulID};
 use std::borrow::Cow;
-use yakui::widgets::Pad;
-use yakui::Vec2;
+use yakui::paint::PaintRect;
+use yakui::widgets::{CountGrid, Pad};
+use yakui::{use_state, Color, MainAxisSize, Rect, Vec2};
 
-use crate::newgui::inspect::entity_link;
+use crate::newgui::commutes::CommuteView;
+use crate::newgui::inspect::{building_link, entity_link};
 use crate::newgui::item_icon_yakui;
+use crate::newgui::supply_chain::SupplyChainView;
+use crate::newgui::units::{fmt_area, fmt_distance, fmt_duration, fmt_money};
 use crate::uiworld::UiWorld;
 
 fn label(x: impl Into<Cow<'static, str>>) {
@@ -31,6 +55,10 @@ pub fn inspect_building(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) ->
         BuildingKind::House => "House",
         BuildingKind::GoodsCompany(id) => &id.prototype().name,
         BuildingKind::RailFreightStation(id) => &id.prototype().name,
+        BuildingKind::Dock(id) => &id.prototype().name,
+        BuildingKind::Warehouse(id) => &id.prototype().name,
+        BuildingKind::ServiceDepot(id) => &id.prototype().name,
+        BuildingKind::Leisure(id) => &id.prototype().name,
         BuildingKind::TrainStation => "Train Station",
         BuildingKind::ExternalTrading => "External Trading",
     };
@@ -48,18 +76,46 @@ pub fn inspect_building(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) ->
             label(format!("{:?}", building.id));
         }
 
+        if let Some(address) = map.address(id) {
+            label(address);
+        }
+
         match building.kind {
-            BuildingKind::House => render_house(uiworld, sim, building),
+            BuildingKind::House => {
+                render_house(uiworld, sim, building);
+                render_walkability(sim, building);
+            }
             BuildingKind::GoodsCompany(_) => {
                 render_goodscompany(uiworld, sim, building);
+                render_commutes(uiworld, sim, id);
+                render_upgrade(uiworld, sim, id);
             }
             BuildingKind::RailFreightStation(_) => {
                 render_freightstation(uiworld, sim, building);
             }
+            BuildingKind::Dock(_) => {
+                render_dock(sim, building);
+            }
+            BuildingKind::Warehouse(_) => {
+                render_warehouse(uiworld, sim, building);
+            }
+            BuildingKind::ServiceDepot(_) => {
+                render_depot(uiworld, sim, building);
+            }
+            BuildingKind::Leisure(proto) => {
+                render_venue(sim, building, proto);
+            }
             BuildingKind::TrainStation => {}
             BuildingKind::ExternalTrading => {}
         };
 
+        render_abandonment(uiworld, sim, building);
+        render_service_calls(uiworld, sim, building);
+        render_entrances(&map, building);
+
+        render_incoming(uiworld, sim, id);
+        render_supply_chain(uiworld, sim, building);
+
         if let Some(ref zone) = building.zone {
             let mut cpy = zone.filldir;
             minrow(5.0, || {
@@ -85,7 +141,11 @@ pub fn inspect_building(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) ->
                 color: primary().adjust(0.7),
             }
             .show_children(|| {
-                label(format!("area: {}/{}", zone.area, MAX_ZONE_AREA));
+                label(format!(
+                    "area: {}/{}",
+                    fmt_area(zone.area),
+                    fmt_area(MAX_ZONE_AREA)
+                ));
             });
         }
     });
@@ -93,6 +153,195 @@ pub fn inspect_building(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) ->
     is_open
 }
 
+/// Where each entrance of the building connects to
+fn render_entrances(map: &Map, building: &Building) {
+    for e in &building.entrances {
+        let connection = map
+            .entrance_lane(e)
+            .and_then(|lane| map.lanes().get(lane))
+            .and_then(|lane| map.roads().get(lane.parent));
+        match connection {
+            Some(road) if !road.name.is_empty() => {
+                label(format!("{} entrance: {}", e.kind.label(), road.name))
+            }
+            Some(_) => label(format!("{} entrance: connected", e.kind.label())),
+            None if e.optional => label(format!("{} entrance: not connected", e.kind.label())),
+            None => textc(
+                error(),
+                format!("{} entrance: not connected", e.kind.label()),
+            ),
+        }
+    }
+}
+
+/// How long the building has been empty, derelict buildings can be demolished
+fn render_abandonment(uiworld: &UiWorld, sim: &Simulation, building: &Building) {
+    let Some(since) = sim.read::<Abandonment>().empty_since(building.id) else {
+        return;
+    };
+    let time = sim.read::<GameTime>();
+    let empty_for = since.elapsed(&time);
+
+    fixed_spacer((0.0, 10.0));
+    label(format!("Empty for {}", fmt_duration(empty_for)));
+    if !building.derelict {
+        let after = derelict_after(&sim.read::<GameRules>());
+        let left = GameDuration(Tick(after.0 .0.saturating_sub(empty_for.0 .0)));
+        label(format!("Derelict in {}", fmt_duration(left)));
+        return;
+    }
+
+    label("Derelict, nothing grows around it");
+    if button_secondary("Demolish").show().clicked {
+        uiworld.commands().map_remove_building(building.id);
+    }
+}
+
+/// The fire and the bins of the building, with the vehicles coming for them
+fn render_service_calls(uiworld: &UiWorld, sim: &Simulation, building: &Building) {
+    let calls = sim.read::<ServiceCalls>();
+    let garbage = calls.garbage(building.id);
+    if garbage > 0.0 {
+        fixed_spacer((0.0, 10.0));
+        label(format!("Garbage: {:.1} bins", garbage));
+        if garbage >= GARBAGE_OVERFLOW {
+            label("The bins overflow, nothing grows around it");
+        }
+    }
+    for service in ServiceKind::ALL {
+        let Some(call) = calls.active.get(&(building.id, service)) else {
+            continue;
+        };
+        minrow(5.0, || {
+            label(match (call.arrived, call.vehicle) {
+                (Some(_), _) => format!("{}: working", service),
+                (None, Some(_)) => format!("{}: on the way", service),
+                (None, None) => format!("{}: no vehicle available", service),
+            });
+            if let Some(id) = call.vehicle {
+                entity_link(uiworld, sim, id);
+            }
+        });
+    }
+}
+
+fn render_upgrade(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) {
+    let Some((upgrade, to)) = company_upgrade(sim, id) else {
+        return;
+    };
+
+    fixed_spacer((0.0, 10.0));
+    label(format!(
+        "Upgrade to {} ({})",
+        to.label,
+        fmt_money(upgrade.price)
+    ));
+
+    let blockers = upgrade_blockers(sim, id, upgrade);
+    for blocker in &blockers {
+        label(match *blocker {
+            UpgradeBlocker::NotEnoughPopulation { has, needs } => {
+                format!("Needs {needs} inhabitants nearby ({has} now)")
+            }
+            UpgradeBlocker::RoadTooSmall { has, needs } => {
+                format!("Needs a road with {needs} lanes ({has} now)")
+            }
+        });
+    }
+
+    let cmd = WorldCommand::UpgradeBuilding(id);
+    if !sim.read::<Government>().can_afford(&cmd, sim) {
+        label("Not enough money");
+        return;
+    }
+    if blockers.is_empty() && button_primary("Upgrade").show().clicked {
+        uiworld.commands().push(cmd);
+    }
+}
+
+fn render_commutes(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) {
+    let mut view = uiworld.write::<CommuteView>();
+
+    fixed_spacer((0.0, 10.0));
+    checkbox_value(&mut view.enabled, on_secondary_container(), "Show commutes");
+    if !view.enabled {
+        return;
+    }
+    view.update(sim, id);
+
+    let Some(avg) = average_minutes(&view.commutes) else {
+        label("No workers");
+        return;
+    };
+    label(format!(
+        "Average commute: {}",
+        fmt_duration(GameDuration::from_secs((avg * 60.0) as u64))
+    ));
+
+    let bins = commute_histogram(&view.commutes);
+    let max = bins.iter().copied().max().unwrap_or(1).max(1);
+    for (i, &n) in bins.iter().enumerate() {
+        if n == 0 {
+            continue;
+        }
+        let start = i as f32 * COMMUTE_BIN_MINUTES;
+        ProgressBar {
+            value: n as f32 / max as f32,
+            size: Vec2::new(200.0, 20.0),
+            color: primary().adjust(0.7),
+        }
+        .show_children(|| {
+            label(if i == COMMUTE_BINS - 1 {
+                format!("{:.0}+ min: {}", start, n)
+            } else {
+                format!("{:.0}-{:.0} min: {}", start, start + COMMUTE_BIN_MINUTES, n)
+            });
+        });
+    }
+}
+
+/// Number of suppliers and customers listed in the inspector
+const SUPPLY_CHAIN_TOP: usize = 3;
+
+fn render_supply_chain(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
+    let ledger = sim.read::<TradeLedger>();
+    let Some(ledger) = ledger.get(b.id) else {
+        return;
+    };
+
+    fixed_spacer((0.0, 10.0));
+    checkbox_value(
+        &mut uiworld.write::<SupplyChainView>().enabled,
+        on_secondary_container(),
+        "Show supply chain",
+    );
+
+    let map = sim.map();
+    let list = |title: &'static str, links: Vec<ChainLink>| {
+        if links.is_empty() {
+            return;
+        }
+        label(title);
+        for link in links.into_iter().take(SUPPLY_CHAIN_TOP) {
+            let Some(partner) = map.buildings().get(link.partner) else {
+                continue;
+            };
+            minrow(5.0, || {
+                item_icon_yakui(uiworld, link.item, link.qty);
+                label(format!(
+                    "x{} {}",
+                    link.qty,
+                    fmt_distance(partner.obb.center().distance(b.obb.center()))
+                ));
+                building_link(uiworld, sim, link.partner);
+            });
+        }
+    };
+
+    list("Top suppliers (last day)", ledger.suppliers());
+    list("Top customers (last day)", ledger.customers());
+}
+
 fn render_house(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     let binfos = sim.read::<BuildingInfos>();
     let Some(info) = binfos.get(b.id) else {
@@ -116,6 +365,34 @@ fn render_house(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     }
 }
 
+/// Score of the house with what each kind of amenity gives to it
+fn render_walkability(sim: &Simulation, b: &Building) {
+    let walkability = sim.read::<Walkability>();
+    let Some(score) = walkability.score(b.id) else {
+        return;
+    };
+
+    fixed_spacer((0.0, 10.0));
+    label(format!("Walkability: {:.0}/100", score.total));
+    if let Some(value) = walkability.land_value(b.door_pos.xy()) {
+        label(format!("Land value: {:.0}/100", value));
+    }
+    let mut grid = CountGrid::col(3);
+    grid.main_axis_size = MainAxisSize::Min;
+    grid.show(|| {
+        for part in &score.parts {
+            padxy(5.0, 2.0, || label(part.amenity.to_string()));
+            padxy(5.0, 2.0, || {
+                label(match part.distance {
+                    Some(d) => fmt_distance(d),
+                    None => "too far".to_string(),
+                })
+            });
+            padxy(5.0, 2.0, || label(format!("+{:.0}", part.points)));
+        }
+    });
+}
+
 fn render_freightstation(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     let Some(SoulID::FreightStation(owner)) = sim.read::<BuildingInfos>().owner(b.id) else {
         return;
@@ -147,6 +424,265 @@ fn render_freightstation(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     }
 }
 
+fn render_dock(sim: &Simulation, b: &Building) {
+    let Some(SoulID::Dock(owner)) = sim.read::<BuildingInfos>().owner(b.id) else {
+        return;
+    };
+    let Some(dock) = sim.world().get(owner) else {
+        return;
+    };
+
+    label(format!("Waiting cargo: {}", dock.d.waiting_cargo));
+    label(format!("Wanted cargo: {}", dock.d.wanted_cargo));
+    if dock.d.mooring.is_none() {
+        label("No navigable water nearby");
+    }
+
+    fixed_spacer((0.0, 10.0));
+    label("Boats:");
+    for boat in &dock.d.boats {
+        match boat.state {
+            BoatState::Moored(at) if at == owner => label("Moored"),
+            BoatState::Moored(_) => label("Moored away, waiting for the way back"),
+            BoatState::Delivering(_) => label(format!("Delivering {} cargo", boat.cargo)),
+            BoatState::Returning => label("Returning"),
+            BoatState::Stranded => label("Stranded"),
+        };
+    }
+}
+
+fn render_warehouse(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
+    let Some(SoulID::Warehouse(owner)) = sim.read::<BuildingInfos>().owner(b.id) else {
+        return;
+    };
+    let Some(warehouse) = sim.world().get(owner) else {
+        return;
+    };
+    let w = &warehouse.w;
+    let proto = w.proto.prototype();
+    let market = sim.read::<Market>();
+    let ecostats = sim.read::<EcoStats>();
+    let soul = SoulID::Warehouse(owner);
+
+    let stored = w.city_stock(soul, &market) + w.tenant_stock();
+    ProgressBar {
+        value: stored as f32 / proto.capacity as f32,
+        size: Vec2::new(200.0, 25.0),
+        color: primary().adjust(0.7),
+    }
+    .show_children(|| {
+        label(format!("stored: {}/{}", stored, proto.capacity));
+    });
+    if !proto.items.is_empty() {
+        minrow(5.0, || {
+            label("Stores");
+            for &item in &proto.items {
+                item_icon_yakui(uiworld, item, 1);
+            }
+        });
+    }
+
+    fixed_spacer((0.0, 10.0));
+    label(format!(
+        "Rented space: {} at {} per day per unit",
+        w.rented(),
+        fmt_money(RENT_PER_UNIT_PER_DAY)
+    ));
+    for rental in &w.rentals {
+        let Some(c) = sim.world().companies.get(rental.tenant) else {
+            continue;
+        };
+        minrow(5.0, || {
+            building_link(uiworld, sim, c.comp.building);
+            label(format!("{}/{}", rental.used(), rental.space));
+            for (&item, &qty) in &rental.stock {
+                item_icon_yakui(uiworld, item, qty);
+            }
+        });
+    }
+
+    fixed_spacer((0.0, 10.0));
+    label(format!(
+        "City stocks, {} space left by the tenants",
+        w.city_space()
+    ));
+    let mut rules = w.rules.clone();
+    let mut removed = None;
+
+    let mut grid = CountGrid::col(7);
+    grid.main_axis_size = MainAxisSize::Min;
+    grid.show(|| {
+        for header in [
+            "Item",
+            "Stock",
+            "City price",
+            "Min",
+            "Max",
+            "Buy at or below",
+            "Sell at or above",
+        ] {
+            padxy(5.0, 3.0, || label(header));
+        }
+
+        for (i, rule) in rules.iter_mut().enumerate() {
+            let price = market.inner().get(&rule.item).map_or(Money::ZERO, |m| {
+                ecostats.local_price(rule.item, m.ext_value)
+            });
+
+            padxy(5.0, 3.0, || {
+                minrow(5.0, || {
+                    item_icon_yakui(uiworld, rule.item, 1);
+                    if button_secondary("Remove").show().clicked {
+                        removed = Some(i);
+                    }
+                });
+            });
+            padxy(5.0, 3.0, || {
+                label(market.capital(soul, rule.item).to_string())
+            });
+            padxy(5.0, 3.0, || label(fmt_money(price)));
+            padxy(5.0, 3.0, || {
+                dragvalue().min(0.0).show(&mut rule.min);
+            });
+            padxy(5.0, 3.0, || {
+                dragvalue().min(rule.min as f64).show(&mut rule.max);
+            });
+            padxy(5.0, 3.0, || price_band(&mut rule.buy_below, price));
+            padxy(5.0, 3.0, || price_band(&mut rule.sell_above, price));
+        }
+    });
+    if let Some(i) = removed {
+        rules.remove(i);
+    }
+
+    let candidates: Vec<&ItemPrototype> = prototypes_iter::<ItemPrototype>()
+        .filter(|item| proto.accepts(item.id) && !rules.iter().any(|r| r.item == item.id))
+        .filter(|item| item.id != ItemID::new("job-opening"))
+        .collect();
+    if !candidates.is_empty() {
+        let selected = use_state(|| 0usize);
+        minrow(5.0, || {
+            let names: Vec<&str> = candidates.iter().map(|item| &*item.label).collect();
+            let mut i = selected.get().min(names.len() - 1);
+            combo_box(&mut i, &names, 150.0);
+            selected.set(i);
+            if button_primary("Add rule").show().clicked {
+                rules.push(StockRule::new(candidates[i].id));
+            }
+        });
+    }
+
+    if rules != w.rules {
+        uiworld.commands().set_stock_rules(b.id, rules);
+    }
+}
+
+fn render_depot(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
+    let fleets = sim.read::<ServiceFleets>();
+    let Some(depot) = fleets.depots.get(&b.id) else {
+        return;
+    };
+    let proto = depot.proto.prototype();
+
+    label(format!("Service: {}", proto.service));
+    label(format!(
+        "Vehicles: {}/{}, upkeep {} per day",
+        depot.vehicles.len(),
+        proto.max_fleet,
+        fmt_money(depot.daily_upkeep())
+    ));
+    for v in &depot.vehicles {
+        minrow(5.0, || {
+            label(v.status.to_string());
+            if let Some(id) = v.entity {
+                entity_link(uiworld, sim, id);
+            }
+        });
+    }
+
+    fixed_spacer((0.0, 10.0));
+    let gvt = sim.read::<Government>();
+    minrow(5.0, || {
+        let buy = WorldCommand::BuyServiceVehicle(b.id);
+        if depot.vehicles.len() < proto.max_fleet as usize
+            && gvt.can_afford(&buy, sim)
+            && button_primary(format!("Buy vehicle ({})", fmt_money(proto.vehicle_price)))
+                .show()
+                .clicked
+        {
+            uiworld.commands().buy_service_vehicle(b.id);
+        }
+        if depot.n_idle() > 0 && button_secondary("Sell vehicle").show().clicked {
+            uiworld.commands().sell_service_vehicle(b.id);
+        }
+    });
+}
+
+fn render_venue(sim: &Simulation, b: &Building, proto: LeisurePrototypeID) {
+    let proto = proto.prototype();
+    label(format!("Open {}", proto.opening_hours));
+    label(format!(
+        "Capacity: {}, entry fee {}",
+        proto.capacity,
+        fmt_money(proto.entry_fee)
+    ));
+    let Some(ref event) = proto.event else {
+        return;
+    };
+    let events = sim.read::<VenueEvents>();
+
+    fixed_spacer((0.0, 10.0));
+    match events.running(b.id) {
+        Some(e) => {
+            label(format!("Event from {} to {}", e.start, e.end));
+            label(format!(
+                "Expected attendance: {} of {} invited, {} inside",
+                e.expected_attendance(),
+                e.invited,
+                e.n_inside()
+            ));
+        }
+        None => {
+            let (start, _) = next_event(b.id, event, &sim.read::<GameTime>());
+            label(format!("Next event: {}", start));
+            label(format!(
+                "Expected attendance: {}",
+                event.attendance.min(proto.capacity)
+            ));
+        }
+    }
+
+    let Some(r) = events.last_report(b.id) else {
+        return;
+    };
+    let avg = |d: Option<GameDuration>| d.map_or_else(|| "-".to_string(), fmt_duration);
+    fixed_spacer((0.0, 10.0));
+    label(format!("Last event, {}:", r.start));
+    label(format!("{} of {} invited came", r.attended, r.invited));
+    label(format!(
+        "Average travel in {}, out {}",
+        avg(r.avg_travel_in),
+        avg(r.avg_travel_out)
+    ));
+}
+
+/// Edits an optional price, it starts at the current price when enabled
+fn price_band(band: &mut Option<Money>, price: Money) {
+    minrow(5.0, || {
+        let mut enabled = band.is_some();
+        checkbox_value(&mut enabled, on_secondary_container(), "");
+        if !enabled {
+            *band = None;
+            return;
+        }
+        let v = band.get_or_insert(price);
+        let mut bucks = v.bucks();
+        if dragvalue().min(0.0).show(&mut bucks) {
+            *v = Money::new_bucks(bucks);
+        }
+    });
+}
+
 fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     let owner = sim.read::<BuildingInfos>().owner(b.id);
 
@@ -174,13 +710,51 @@ fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
         label(format!("workers: {}/{}", workers.0.len(), max_workers));
     });
 
+    for shift in &proto.shifts {
+        label(format!("Shift: {}", shift));
+    }
+    if let Some(hours) = proto.opening_hours {
+        let open = b.kind.is_open(&sim.read::<GameTime>().daytime);
+        label(format!(
+            "Opening hours: {}, {} now",
+            hours,
+            if open { "open" } else { "closed" }
+        ));
+    }
+    if proto.seasonal_output.is_some() {
+        let season = sim
+            .read::<GameTime>()
+            .season(sim.read::<SimulationOptions>().season_days);
+        if let Some(season) = season {
+            label(format!(
+                "{} output: x{:.1}",
+                season,
+                proto.seasonal_output(Some(season))
+            ));
+        }
+    }
+
     if let Some(driver) = goods.driver {
         minrow(5.0, || {
             label("Driver is");
             entity_link(uiworld, sim, driver);
         });
     }
-    let productivity = c.productivity(proto, b.zone.as_ref(), map, elec_flow);
+    if !goods.trucks.is_empty() {
+        let waiting: i32 = goods.orders.iter().map(|o| o.qty).sum();
+        label(format!(
+            "Goods waiting for a truck: {} in {} orders",
+            waiting,
+            goods.orders.len()
+        ));
+    }
+    let productivity = c.productivity(
+        proto,
+        b.zone.as_ref(),
+        map,
+        elec_flow,
+        &sim.read::<GameRules>(),
+    );
     if productivity < 1.0 {
         ProgressBar {
             value: productivity,
@@ -195,7 +769,15 @@ fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
         });
     }
 
+    render_pnl(&goods.finances);
+
     if let Some(ref r) = proto.recipe {
+        if let Some(ref field) = goods.field {
+            render_field(uiworld, field, r, b.zone.as_ref());
+        }
+        if let Some(deposit) = proto.deposit {
+            render_deposit(map, deposit, b);
+        }
         render_recipe(uiworld, r);
     }
 
@@ -266,6 +848,121 @@ fn render_goodscompany(uiworld: &UiWorld, sim: &Simulation, b: &Building) {
     }
 }
 
+/// Goods bought by the building that are not delivered yet
+fn render_incoming(uiworld: &UiWorld, sim: &Simulation, id: BuildingID) {
+    let orders = incoming_orders(sim, id);
+    if orders.is_empty() {
+        return;
+    }
+
+    fixed_spacer((0.0, 10.0));
+    label("Incoming goods");
+    for (shipment, state) in orders {
+        minrow(5.0, || {
+            item_icon_yakui(uiworld, shipment.kind, shipment.qty);
+            match state {
+                OrderState::Waiting(seller) => {
+                    label("waiting for a truck at");
+                    if let Some(c) = sim.world().companies.get(seller) {
+                        building_link(uiworld, sim, c.comp.building);
+                    }
+                }
+                OrderState::InTransit(truck) => {
+                    label("in transit on");
+                    entity_link(uiworld, sim, truck);
+                }
+            }
+        });
+    }
+}
+
+/// Bar chart of the daily profit and loss of the company
+fn render_pnl(finances: &CompanyFinances) {
+    label(format!("Profit today: {}", fmt_money(finances.today)));
+    if finances.history.is_empty() {
+        return;
+    }
+
+    let profits: Vec<i64> = finances.history.iter().map(|p| p.0).collect();
+    let max = profits.iter().map(|p| p.abs()).max().unwrap_or(1).max(1) as f32;
+    let (gain_col, loss_col) = (primary(), error());
+
+    sized_canvas(Vec2::new(200.0, 60.0), Color::BLACK, move |paint| {
+        let rect = paint.layout.get(paint.dom.current()).unwrap().rect;
+        let mid = rect.pos().y + rect.size().y * 0.5;
+        let bar_w = rect.size().x / PNL_HISTORY_DAYS as f32;
+
+        for (i, &profit) in profits.iter().enumerate() {
+            let h = (profit as f32 / max * rect.size().y * 0.5).abs().max(1.0);
+            let y = if profit >= 0 { mid - h } else { mid };
+            let x = rect.pos().x + i as f32 * bar_w;
+
+            let mut bar = PaintRect::new(Rect::from_pos_size(
+                Vec2::new(x + 1.0, y),
+                Vec2::new(bar_w - 2.0, h),
+            ));
+            bar.color = if profit >= 0 { gain_col } else { loss_col };
+            bar.add(paint.paint);
+        }
+    });
+    label(format!("Last {} days", profits.len()));
+}
+
+/// Crop on the field of a farm, how far it is and what its harvest will bring
+fn render_field(uiworld: &UiWorld, field: &Field, recipe: &Recipe, zone: Option<&Zone>) {
+    let crop = field.crop.prototype();
+    label(match field.stage {
+        FieldStage::Fallow => format!(
+            "Field resting after {}: {:.0}%",
+            crop.label,
+            field.progress * 100.0
+        ),
+        FieldStage::Sown | FieldStage::Growing => format!(
+            "Crop: {}, {:?} {:.0}%",
+            crop.label,
+            field.stage,
+            field.progress * 100.0
+        ),
+        FieldStage::Ripe => format!("Crop: {}, harvesting", crop.label),
+    });
+
+    let (runs, text) = match field.stage {
+        FieldStage::Ripe => (field.to_harvest, "Left to harvest"),
+        _ => (Field::expected_yield(crop, zone), "Expected yield"),
+    };
+    label(text);
+    minrow(5.0, || {
+        for item in recipe.production.iter() {
+            item_icon_yakui(uiworld, item.id, item.amount * runs as i32);
+        }
+    });
+}
+
+/// Deposit the company extracts from, how much is left and how fast it still comes out
+fn render_deposit(map: &Map, kind: DepositID, b: &Building) {
+    let name = &kind.prototype().label;
+    let Some(d) = map.deposit_under(&b.obb, kind) else {
+        label(format!("No {} deposit here", name));
+        return;
+    };
+    ProgressBar {
+        value: d.share_left(),
+        size: Vec2::new(200.0, 25.0),
+        color: primary().adjust(0.7),
+    }
+    .show_children(|| {
+        label(format!("{}: {}/{} left", name, d.remaining, d.initial));
+    });
+    if d.is_exhausted() {
+        label("The deposit is exhausted");
+    } else if d.richness() < 1.0 {
+        label(format!(
+            "Extraction slowed down: {:.0}%",
+            d.richness() * 100.0
+        ));
+    }
+}
+
 fn render_recipe(uiworld: &UiWorld, recipe: &Recipe) {
     if recipe.consumption.is_empty() {
         label("No Inputs");
@@ -0,0 +1,82 @@
+use goryak::{
+    button_primary, button_secondary, minrow, on_secondary_container, padxy,
+    selectable_label_primary, text_edit, textc, Window,
+};
+use simulation::map::{DistrictID, MAX_DISTRICT_NAME_LEN};
+use simulation::Simulation;
+use yakui::widgets::{CountGrid, Pad};
+use yakui::MainAxisSize;
+
+use crate::newgui::districts::{
+    coverage_text, land_value_text, DistrictStatsView, DISTRICT_COLORS,
+};
+use crate::newgui::InspectedDistrict;
+use crate::uiworld::UiWorld;
+
+/// Inspect a district: rename, recolor or delete it and show its statistics
+pub fn inspect_district(uiworld: &UiWorld, sim: &Simulation, id: DistrictID) -> bool {
+    let map = sim.map();
+    let Some(district) = map.districts().get(id) else {
+        return false;
+    };
+
+    let mut is_open = true;
+    Window {
+        title: district.name.clone().into(),
+        pad: Pad::all(10.0),
+        radius: 10.0,
+        opened: &mut is_open,
+        child_spacing: 5.0,
+    }
+    .show(|| {
+        let mut inspected = uiworld.write::<InspectedDistrict>();
+        minrow(10.0, || {
+            let entered = text_edit(200.0, &mut inspected.name, "District name");
+            let name = inspected.name.trim();
+            let valid = !name.is_empty() && name.len() <= MAX_DISTRICT_NAME_LEN;
+            if (button_primary("Rename").show().clicked || entered) && valid {
+                uiworld
+                    .commands()
+                    .map_update_district(id, name.to_string(), district.color);
+            }
+        });
+        drop(inspected);
+
+        minrow(5.0, || {
+            for (label, color) in DISTRICT_COLORS {
+                if selectable_label_primary(district.color == color, label).clicked {
+                    uiworld
+                        .commands()
+                        .map_update_district(id, district.name.clone(), color);
+                }
+            }
+        });
+
+        let mut view = uiworld.write::<DistrictStatsView>();
+        let stats = view.get(sim).get(&id).cloned().unwrap_or_default();
+        drop(view);
+
+        let mut grid = CountGrid::col(2);
+        grid.main_axis_size = MainAxisSize::Min;
+        grid.show(|| {
+            let mut row = |name: String, value: String| {
+                padxy(5.0, 3.0, || textc(on_secondary_container(), name));
+                padxy(5.0, 3.0, || textc(on_secondary_container(), value));
+            };
+            row("Buildings".to_string(), stats.buildings.to_string());
+            row("Population".to_string(), stats.population.to_string());
+            row("Jobs".to_string(), stats.jobs.to_string());
+            row("Traffic".to_string(), format!("{} vehicles", stats.traffic));
+            row("Land value".to_string(), land_value_text(stats.land_value));
+            for (service, coverage) in &stats.coverage {
+                row(format!("{} coverage", service), coverage_text(*coverage));
+            }
+        });
+
+        if button_secondary("Delete district").show().clicked {
+            uiworld.commands().map_remove_district(id);
+        }
+    });
+
+    is_open
+}
@@ -1,16 +1,23 @@
-use goryak::{dragvalue, fixed_spacer, minrow, on_secondary_container, textc, Window};
-use prototypes::ItemID;
+use goryak::{
+    button_primary, dragvalue, fixed_spacer, minrow, on_secondary_container, textc, Window,
+};
+use prototypes::{GameTime, ItemID};
 use std::borrow::Cow;
 use yakui::widgets::Pad;
 
 use simulation::economy::Market;
 use simulation::map_dynamic::Destination;
 use simulation::souls::desire::WorkKind;
+use simulation::souls::household::Households;
+use simulation::souls::sampling::CitizenSampling;
+use simulation::souls::welfare::{happiness, unemployed_for};
 use simulation::transportation::Location;
 use simulation::{HumanID, Simulation};
 
-use crate::newgui::inspect::{building_link, follow_button};
+use crate::newgui::inspect::{building_link, entity_link, follow_button};
 use crate::newgui::item_icon_yakui;
+use crate::newgui::trip_route::TripRoute;
+use crate::newgui::units::{fmt_duration, fmt_money};
 use crate::uiworld::UiWorld;
 
 /// Inspect a specific building, showing useful information about it
@@ -71,6 +78,19 @@ pub fn inspect_human(uiworld: &UiWorld, sim: &Simulation, id: HumanID) -> bool {
             label("House is");
             building_link(uiworld, sim, human.home.house);
         });
+        label(format!(
+            "Household money: {}",
+            fmt_money(sim.read::<Households>().money(human.home.house))
+        ));
+
+        if let Some(car) = human.router.personal_car {
+            minrow(5.0, || {
+                label("Owns");
+                entity_link(uiworld, sim, car);
+            });
+        } else {
+            label("Has no car");
+        }
 
         label(format!("Last ate: {}", human.food.last_ate));
 
@@ -87,6 +107,24 @@ pub fn inspect_human(uiworld: &UiWorld, sim: &Simulation, id: HumanID) -> bool {
                     }
                 }
             });
+            label(format!("Shift: {}", x.work_inter));
+            label(format!(
+                "Next departure: {}",
+                x.next_departure(&sim.read::<GameTime>())
+            ));
+        } else if let Some(d) = unemployed_for(human, &sim.read::<GameTime>()) {
+            label(format!(
+                "Unemployed for {}, happiness {:.0}%",
+                fmt_duration(d),
+                happiness(Some(d)) * 100.0
+            ));
+        }
+
+        if let Some(ref e) = human.event {
+            minrow(5.0, || {
+                label(format!("Going to an event at {}", e.start));
+                building_link(uiworld, sim, e.venue);
+            });
         }
 
         fixed_spacer((0.0, 10.0));
@@ -106,6 +144,13 @@ pub fn inspect_human(uiworld: &UiWorld, sim: &Simulation, id: HumanID) -> bool {
             dragvalue().show(&mut score);
             label("Work");
         });
+        if let Some(ref e) = human.event {
+            minrow(5.0, || {
+                let mut score = e.last_score;
+                dragvalue().show(&mut score);
+                label("Event");
+            });
+        }
 
         let market = sim.read::<Market>();
 
@@ -123,6 +168,13 @@ pub fn inspect_human(uiworld: &UiWorld, sim: &Simulation, id: HumanID) -> bool {
             item_icon_yakui(uiworld, item_id, v);
         }
 
+        if sim.read::<CitizenSampling>().requested.contains(&id) {
+            label("Will be followed by the next citizen sample");
+        } else if button_primary("Record this person").show().clicked {
+            uiworld.commands().sample_citizen(id);
+        }
+
+        TripRoute::leg_list(uiworld, sim, id);
         follow_button(uiworld, id);
     });
     is_open
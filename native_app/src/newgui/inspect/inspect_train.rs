@@ -1,4 +1,6 @@
 use crate::newgui::inspect::follow_button;
+use crate::newgui::trip_route::TripRoute;
+use crate::newgui::units::fmt_speed;
 use crate::uiworld::UiWorld;
 use goryak::{on_secondary_container, textc, Window};
 use simulation::{Simulation, TrainID};
@@ -25,9 +27,10 @@ pub fn inspect_train(uiworld: &UiWorld, sim: &Simulation, id: TrainID) -> bool {
 
         textc(
             on_secondary_container(),
-            format!("Going at {:.0}km/h", t.speed.0),
+            format!("Going at {}", fmt_speed(t.speed.0)),
         );
 
+        TripRoute::leg_list(uiworld, sim, id);
         follow_button(uiworld, id);
     });
 
@@ -1,6 +1,10 @@
-use crate::newgui::inspect::{entity_link, follow_button};
+use crate::newgui::inspect::{building_link, entity_link, follow_button};
+use crate::newgui::item_icon_yakui;
+use crate::newgui::trip_route::TripRoute;
+use crate::newgui::units::fmt_speed;
 use crate::uiworld::UiWorld;
 use goryak::{minrow, on_secondary_container, textc, Window};
+use simulation::map::TraverseKind;
 use simulation::transportation::VehicleState;
 use simulation::{Simulation, VehicleID};
 use yakui::widgets::Pad;
@@ -25,24 +29,72 @@ pub fn inspect_vehicle(uiworld: &UiWorld, sim: &Simulation, id: VehicleID) -> bo
             textc(on_secondary_container(), format!("{:?}", id));
         }
 
+        if let Some(proto) = v.vehicle.prototype() {
+            textc(on_secondary_container(), proto.label.as_str());
+        }
+
         match v.vehicle.state {
             VehicleState::Parked(_) => {
                 textc(on_secondary_container(), "Parked");
             }
             VehicleState::Driving => {
+                let street =
+                    v.it.get_travers()
+                        .and_then(|t| match t.kind {
+                            TraverseKind::Lane(l) => Some(l),
+                            TraverseKind::Turn(_) => None,
+                        })
+                        .and_then(|l| {
+                            let map = sim.map();
+                            let road = map.roads().get(map.lanes().get(l)?.parent)?;
+                            Some(road.name.clone())
+                        })
+                        .filter(|name| !name.is_empty());
+                let speed = format!("Driving at {}", fmt_speed(v.speed.0));
                 textc(
                     on_secondary_container(),
-                    format!("Driving at {:.0}km/h", v.speed.0 * 3.6),
+                    match street {
+                        Some(street) => format!("{speed} on {street}"),
+                        None => speed,
+                    },
                 );
             }
             VehicleState::Panicking(_) => {
                 textc(on_secondary_container(), "Panicking");
             }
+            VehicleState::BrokenDown(_) => {
+                textc(
+                    on_secondary_container(),
+                    "Broken down, waiting for the tow truck",
+                );
+            }
             VehicleState::RoadToPark(_, _, _) => {
                 textc(on_secondary_container(), "Parking");
             }
         }
 
+        let capacity = v.vehicle.kind.capacity();
+        if capacity > 0 {
+            let load: i32 = v.vehicle.cargo.iter().map(|s| s.qty).sum();
+            textc(
+                on_secondary_container(),
+                format!("Cargo: {}/{}", load, capacity),
+            );
+            if let Some(next) = v.vehicle.cargo.first() {
+                minrow(5.0, || {
+                    textc(on_secondary_container(), "Next stop");
+                    building_link(uiworld, sim, next.to);
+                });
+            }
+            for s in &v.vehicle.cargo {
+                minrow(5.0, || {
+                    item_icon_yakui(uiworld, s.kind, s.qty);
+                    textc(on_secondary_container(), "for");
+                    building_link(uiworld, sim, s.to);
+                });
+            }
+        }
+
         for (human_id, human) in &sim.world().humans {
             if human.router.personal_car == Some(id) {
                 minrow(5.0, || {
@@ -52,6 +104,7 @@ pub fn inspect_vehicle(uiworld: &UiWorld, sim: &Simulation, id: VehicleID) -> bo
             }
         }
 
+        TripRoute::leg_list(uiworld, sim, id);
         follow_button(uiworld, id);
     });
 
@@ -1,9 +1,11 @@
 use crate::gui::debug_window::DebugState;
 use crate::newgui::follow::FollowEntity;
-use crate::newgui::{InspectedBuilding, InspectedEntity};
+use crate::newgui::pause_menu::PauseMenu;
+use crate::newgui::{InspectedBuilding, InspectedDistrict, InspectedEntity};
 use crate::uiworld::UiWorld;
 use goryak::{button_primary, primary_link};
 use inspect_building::inspect_building;
+use inspect_district::inspect_district;
 use inspect_human::inspect_human;
 use inspect_train::inspect_train;
 use inspect_vehicle::inspect_vehicle;
@@ -12,6 +14,7 @@ use simulation::{AnyEntity, Simulation};
 use slotmapd::Key;
 
 mod inspect_building;
+mod inspect_district;
 mod inspect_human;
 mod inspect_train;
 mod inspect_vehicle;
@@ -26,6 +29,14 @@ pub fn new_inspector(uiworld: &UiWorld, sim: &Simulation) {
         }
     }
 
+    let inspected_district = uiworld.read::<InspectedDistrict>().e;
+    if let Some(d) = inspected_district {
+        let is_open = inspect_district(uiworld, sim, d);
+        if !is_open {
+            uiworld.write::<InspectedDistrict>().e = None;
+        }
+    }
+
     let e = unwrap_or!(uiworld.read::<InspectedEntity>().e, return);
 
     let force_debug_inspect = uiworld.read::<DebugState>().debug_inspector;
@@ -60,7 +71,11 @@ pub fn new_inspector(uiworld: &UiWorld, sim: &Simulation) {
 }
 
 pub fn building_link(uiworld: &UiWorld, sim: &Simulation, b: BuildingID) {
-    if primary_link(format!("{:?}", b.data())) {
+    let text = sim
+        .map()
+        .address(b)
+        .unwrap_or_else(|| format!("{:?}", b.data()));
+    if primary_link(text) {
         uiworld.write::<InspectedBuilding>().e = Some(b);
         if let Some(b) = sim.map().buildings().get(b) {
             uiworld.camera_mut().targetpos = b.door_pos;
@@ -101,4 +116,11 @@ fn follow_button_inner(uiworld: &UiWorld, id: AnyEntity) {
     if follow.0 != Some(id) && button_primary("follow").show().clicked {
         follow.0 = Some(id);
     }
+    drop(follow);
+
+    if button_primary("cinematic orbit").show().clicked {
+        uiworld
+            .write::<PauseMenu>()
+            .start_cinematic(uiworld, Some(id));
+    }
 }
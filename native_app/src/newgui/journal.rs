@@ -0,0 +1,342 @@
+//! Photo journal of the city: a screenshot, the date and a few statistics taken when the city
+//! reaches a milestone, or when the player asks for it.
+//! The entries are saved with the other UI resources, the photos as PNG files next to them.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use common::FastMap;
+use engine::{Context, FrameGrab, FrameImage, GrabState};
+use prototypes::{GameTime, Money};
+use simulation::economy::Government;
+use simulation::{Simulation, SimulationOptions};
+
+use crate::newgui::map_export::MapExporter;
+use crate::newgui::GuiState;
+use crate::uiworld::UiWorld;
+
+/// Directory the photos are written to
+const JOURNAL_DIR: &str = "world/journal";
+/// Width of the thumbnails shown in the journal window, in pixels
+pub const THUMB_WIDTH: u32 = 320;
+/// Frames rendered without the interface before the photo is taken
+const SETTLE_FRAMES: u32 = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Milestone {
+    Population(u32),
+    FirstTrain,
+    /// Years elapsed since the start of the game
+    Years(u32),
+}
+
+/// The milestones that add an entry to the journal, each only once
+pub const MILESTONES: [Milestone; 7] = [
+    Milestone::Population(1_000),
+    Milestone::Population(10_000),
+    Milestone::Population(100_000),
+    Milestone::FirstTrain,
+    Milestone::Years(1),
+    Milestone::Years(10),
+    Milestone::Years(50),
+];
+
+impl Milestone {
+    pub fn caption(self) -> String {
+        match self {
+            Milestone::Population(n) => format!("{} residents", n),
+            Milestone::FirstTrain => "The first train".to_string(),
+            Milestone::Years(1) => "The first year".to_string(),
+            Milestone::Years(n) => format!("{} years", n),
+        }
+    }
+
+    fn reached(self, stats: &JournalStats, years: f64) -> bool {
+        match self {
+            Milestone::Population(n) => stats.population >= n,
+            Milestone::FirstTrain => stats.trains > 0,
+            Milestone::Years(n) => years >= n as f64,
+        }
+    }
+}
+
+/// The statistics of the city written under a photo
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct JournalStats {
+    pub population: u32,
+    pub buildings: u32,
+    pub trains: u32,
+    pub money: Money,
+}
+
+impl JournalStats {
+    pub fn new(sim: &Simulation) -> Self {
+        Self {
+            population: sim.world().humans.len() as u32,
+            buildings: sim.map().buildings().len() as u32,
+            trains: sim.world().trains.len() as u32,
+            money: sim.read::<Government>().money,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub caption: String,
+    /// Written by the player, empty for the milestones
+    pub note: String,
+    pub date: String,
+    pub stats: JournalStats,
+    /// False if the photo could not be taken
+    pub has_photo: bool,
+    /// Width over height of the photo
+    pub aspect: f32,
+}
+
+impl JournalEntry {
+    pub fn photo_path(&self) -> PathBuf {
+        PathBuf::from(JOURNAL_DIR).join(format!("{}.png", self.id))
+    }
+
+    pub fn thumb_path(&self) -> PathBuf {
+        PathBuf::from(JOURNAL_DIR).join(format!("{}_thumb.png", self.id))
+    }
+}
+
+/// An entry waiting for the interface to be hidden and the frame to be read back
+struct Capture {
+    entry: JournalEntry,
+    settled: u32,
+    grab: Option<FrameGrab>,
+    /// Whether the interface was hidden by the player, to leave it so
+    was_hidden: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Journal {
+    pub entries: Vec<JournalEntry>,
+    reached: Vec<Milestone>,
+    next_id: u64,
+    /// Note of the next manual entry, edited in the journal window
+    #[serde(skip)]
+    pub note: String,
+    #[serde(skip)]
+    queue: VecDeque<JournalEntry>,
+    #[serde(skip)]
+    capture: Option<Capture>,
+    /// Thumbnails loaded for the window, None if the file could not be read
+    #[serde(skip)]
+    thumbs: FastMap<u64, Option<yakui::TextureId>>,
+}
+
+impl Journal {
+    /// Queues an entry, its photo is taken over the next frames
+    pub fn add_entry(&mut self, sim: &Simulation, caption: String, note: String) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.queue.push_back(JournalEntry {
+            id,
+            caption,
+            note,
+            date: sim.read::<GameTime>().daytime.to_string(),
+            stats: JournalStats::new(sim),
+            has_photo: false,
+            aspect: 1.0,
+        });
+    }
+
+    /// Removes the entry and its photos
+    pub fn delete(&mut self, id: u64) {
+        let Some(i) = self.entries.iter().position(|e| e.id == id) else {
+            return;
+        };
+        let entry = self.entries.remove(i);
+        self.thumbs.remove(&id);
+        if entry.has_photo {
+            for path in [entry.photo_path(), entry.thumb_path()] {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    log::warn!("could not delete {:?}: {}", path, e);
+                }
+            }
+        }
+    }
+
+    /// Whether a photo is being taken
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some() || !self.queue.is_empty()
+    }
+
+    pub fn thumb(&self, id: u64) -> Option<yakui::TextureId> {
+        self.thumbs.get(&id).copied().flatten()
+    }
+
+    /// Checks the milestones, takes the queued photos and loads the thumbnails
+    pub fn update(uiw: &UiWorld, sim: &Simulation, ctx: &mut Context) {
+        // the benchmark scenes must not end up in the player's journal
+        if crate::benchmark::options().is_some() {
+            return;
+        }
+        let mut journal = uiw.write::<Journal>();
+        let journal = &mut *journal;
+
+        let stats = JournalStats::new(sim);
+        let years = years_elapsed(sim);
+        for milestone in MILESTONES {
+            if !journal.reached.contains(&milestone) && milestone.reached(&stats, years) {
+                journal.reached.push(milestone);
+                journal.add_entry(sim, milestone.caption(), String::new());
+            }
+        }
+
+        // the map export moves the camera around, wait for it to be done
+        if uiw.read::<MapExporter>().progress().is_none() {
+            journal.capture_step(uiw, ctx);
+        }
+
+        for entry in &journal.entries {
+            if !entry.has_photo || journal.thumbs.contains_key(&entry.id) {
+                continue;
+            }
+            // a missing photo is reported to the diagnostics by the engine
+            let thumb = ctx
+                .gfx
+                .try_texture(entry.thumb_path(), "journal photo")
+                .ok()
+                .map(|tex| ctx.yakui.add_texture(&tex));
+            journal.thumbs.insert(entry.id, thumb);
+        }
+    }
+
+    fn capture_step(&mut self, uiw: &UiWorld, ctx: &mut Context) {
+        if self.capture.is_none() {
+            let Some(entry) = self.queue.pop_front() else {
+                return;
+            };
+            self.capture = Some(Capture {
+                entry,
+                settled: 0,
+                grab: None,
+                was_hidden: uiw.read::<GuiState>().hidden,
+            });
+        }
+        let capture = self.capture.as_mut().unwrap();
+
+        // the windows are rendered while the GUI state is borrowed, so it is hidden from here
+        uiw.write::<GuiState>().hidden = true;
+
+        let Some(ref grab) = capture.grab else {
+            capture.settled += 1;
+            // the grab reads back the frame rendered right after this update
+            if capture.settled >= SETTLE_FRAMES {
+                capture.grab = Some(ctx.gfx.frame_dump.grab());
+            }
+            return;
+        };
+
+        let frame = match std::mem::take(&mut *grab.lock().unwrap()) {
+            GrabState::Pending => return,
+            GrabState::Failed => None,
+            GrabState::Done(frame) => Some(frame),
+        };
+
+        let mut capture = self.capture.take().unwrap();
+        uiw.write::<GuiState>().hidden = capture.was_hidden;
+        if let Some(frame) = frame {
+            capture.entry.aspect = frame.width as f32 / frame.height.max(1) as f32;
+            capture.entry.has_photo = write_photos(&capture.entry, frame);
+        }
+        self.entries.push(capture.entry);
+    }
+}
+
+/// Writes the thumbnail right away so that the window can show it, the photo on another thread
+fn write_photos(entry: &JournalEntry, frame: FrameImage) -> bool {
+    if let Err(e) = std::fs::create_dir_all(JOURNAL_DIR) {
+        log::error!("could not create the journal directory: {}", e);
+        return false;
+    }
+    let (w, h, thumb) = thumbnail(&frame, THUMB_WIDTH);
+    engine::write_png(entry.thumb_path(), w, h, thumb);
+
+    let path = entry.photo_path();
+    std::thread::spawn(move || engine::write_png(path, frame.width, frame.height, frame.rgba));
+    true
+}
+
+/// The frame shrunk to about that width by averaging blocks of pixels, opaque
+fn thumbnail(frame: &FrameImage, width: u32) -> (u32, u32, Vec<u8>) {
+    let step = frame.width.div_ceil(width).max(1);
+    let (w, h) = (frame.width / step, frame.height / step);
+    let mut rgba = Vec::with_capacity((w * h * 4) as usize);
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = [0u32; 3];
+            for dy in 0..step {
+                let row = ((y * step + dy) * frame.width + x * step) as usize * 4;
+                for px in frame.rgba[row..row + step as usize * 4].chunks_exact(4) {
+                    for (s, &c) in sum.iter_mut().zip(px) {
+                        *s += c as u32;
+                    }
+                }
+            }
+            let n = step * step;
+            rgba.extend_from_slice(&[
+                (sum[0] / n) as u8,
+                (sum[1] / n) as u8,
+                (sum[2] / n) as u8,
+                255,
+            ]);
+        }
+    }
+    (w, h, rgba)
+}
+
+/// Years of the calendar of the seasons, or of 365 days without seasons
+fn years_elapsed(sim: &Simulation) -> f64 {
+    let season_days = sim.read::<SimulationOptions>().season_days;
+    let year_days = match season_days {
+        0 => geom::DAYS_PER_YEAR as f64,
+        d => 4.0 * d as f64,
+    };
+    sim.read::<GameTime>().timestamp / (GameTime::DAY as f64 * year_days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumbnail_averages_the_blocks() {
+        // 4x2 frame: a black and a white 2x2 block
+        let mut rgba = vec![];
+        for _ in 0..2 {
+            rgba.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+            rgba.extend_from_slice(&[255, 255, 255, 0, 255, 255, 255, 0]);
+        }
+        let frame = FrameImage {
+            width: 4,
+            height: 2,
+            rgba,
+        };
+        let (w, h, thumb) = thumbnail(&frame, 2);
+        assert_eq!((w, h), (2, 1));
+        assert_eq!(thumb, vec![0, 0, 0, 255, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn milestones_are_reached_by_the_stats() {
+        let stats = JournalStats {
+            population: 1500,
+            trains: 0,
+            ..Default::default()
+        };
+        assert!(Milestone::Population(1_000).reached(&stats, 0.0));
+        assert!(!Milestone::Population(10_000).reached(&stats, 0.0));
+        assert!(!Milestone::FirstTrain.reached(&stats, 0.0));
+        assert!(Milestone::Years(1).reached(&stats, 1.2));
+        assert!(!Milestone::Years(10).reached(&stats, 1.2));
+    }
+}
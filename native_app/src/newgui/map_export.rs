@@ -0,0 +1,309 @@
+//! Exports the whole map to a file, to print posters or share the city.
+//! The SVG is drawn from the map data alone. The PNG is the 3D view seen from far above through
+//! a narrow field of view, rendered one screen-sized tile at a time and stitched together so that
+//! it can be bigger than the window.
+
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use engine::{Context, FrameGrab, FrameImage, GrabState};
+use geom::{Radians, Vec3, AABB};
+use simulation::map::{export_bounds, map_to_svg, MapExportOptions};
+use simulation::Simulation;
+
+use crate::newgui::windows::settings::Settings;
+use crate::newgui::GuiState;
+use crate::rendering::{CameraPose, OrbitCamera};
+use crate::uiworld::UiWorld;
+
+/// Directory the exported maps are written to
+const EXPORT_DIR: &str = "world/exports";
+/// Longest side of an exported PNG, in pixels
+const MAX_PNG_SIZE: f32 = 8192.0;
+/// Vertical field of view of the export camera, small enough for the view to look orthographic
+const EXPORT_FOVY: f32 = 2.0;
+/// Frames rendered once the chunks are built before a tile is grabbed, for the shadows and
+/// the shaders to catch up with the camera
+const SETTLE_FRAMES: u32 = 5;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+pub enum ExportOutcome {
+    Written(PathBuf),
+    Failed(String),
+}
+
+#[derive(Default)]
+pub struct MapExporter {
+    pub options: MapExportOptions,
+    pub format: ExportFormat,
+    job: Option<PngJob>,
+    /// Result of the last export, shown for a few seconds
+    pub last: Option<(ExportOutcome, Instant)>,
+}
+
+/// A PNG being rendered, tile by tile from the top left
+struct PngJob {
+    path: PathBuf,
+    bounds: AABB,
+    scale: f32,
+    width: u32,
+    height: u32,
+    tile_w: u32,
+    tile_h: u32,
+    cols: u32,
+    rows: u32,
+    next: u32,
+    settled: u32,
+    grab: Option<FrameGrab>,
+    canvas: Vec<u8>,
+    /// Where the camera goes back to once done
+    restore: CameraPose,
+}
+
+impl MapExporter {
+    /// (tiles done, total tiles) of the PNG being rendered
+    pub fn progress(&self) -> Option<(u32, u32)> {
+        self.job.as_ref().map(|j| (j.next, j.cols * j.rows))
+    }
+
+    /// Size in pixels of the exported image with the current options
+    pub fn output_size(&self, sim: &Simulation) -> (u32, u32) {
+        let bounds = export_bounds(&sim.map());
+        let scale = match self.format {
+            ExportFormat::Svg => self.options.scale,
+            ExportFormat::Png => png_scale(bounds, self.options.scale),
+        };
+        (
+            (bounds.w() * scale).ceil() as u32,
+            (bounds.h() * scale).ceil() as u32,
+        )
+    }
+
+    pub fn export(&mut self, uiw: &UiWorld, sim: &Simulation) {
+        if self.job.is_some() {
+            return;
+        }
+        if let Err(e) = std::fs::create_dir_all(EXPORT_DIR) {
+            self.finish(ExportOutcome::Failed(format!(
+                "could not create {}: {}",
+                EXPORT_DIR, e
+            )));
+            return;
+        }
+
+        match self.format {
+            ExportFormat::Svg => {
+                let path = export_path("svg");
+                let svg = map_to_svg(&sim.map(), &self.options);
+                let outcome = match std::fs::write(&path, svg) {
+                    Ok(()) => ExportOutcome::Written(path),
+                    Err(e) => ExportOutcome::Failed(format!("could not write {:?}: {}", path, e)),
+                };
+                self.finish(outcome);
+            }
+            ExportFormat::Png => {
+                let bounds = export_bounds(&sim.map());
+                let scale = png_scale(bounds, self.options.scale);
+                let width = (bounds.w() * scale).ceil() as u32;
+                let height = (bounds.h() * scale).ceil() as u32;
+
+                let camera = uiw.read::<OrbitCamera>();
+                let tile_w = camera.camera.viewport_w as u32;
+                let tile_h = camera.camera.viewport_h as u32;
+                if tile_w == 0 || tile_h == 0 || width == 0 || height == 0 {
+                    return;
+                }
+
+                self.job = Some(PngJob {
+                    path: export_path("png"),
+                    bounds,
+                    scale,
+                    width,
+                    height,
+                    tile_w,
+                    tile_h,
+                    cols: width.div_ceil(tile_w),
+                    rows: height.div_ceil(tile_h),
+                    next: 0,
+                    settled: 0,
+                    grab: None,
+                    canvas: vec![0; (width * height * 4) as usize],
+                    restore: camera.pose(),
+                });
+            }
+        }
+    }
+
+    fn finish(&mut self, outcome: ExportOutcome) {
+        match outcome {
+            ExportOutcome::Written(ref path) => log::info!("map exported to {:?}", path),
+            ExportOutcome::Failed(ref e) => log::error!("map export failed: {}", e),
+        }
+        self.last = Some((outcome, Instant::now()));
+    }
+
+    /// Points the camera at the tile being rendered and grabs it once the view is ready.
+    /// Runs after the other camera controllers so that it has the last word.
+    pub fn update_camera(uiw: &UiWorld, ctx: &mut Context, pending_chunks: usize) {
+        let mut exporter = uiw.write::<MapExporter>();
+        let Some(mut job) = exporter.job.take() else {
+            return;
+        };
+
+        let Some(outcome) = job.update(uiw, ctx, pending_chunks) else {
+            exporter.job = Some(job);
+            return;
+        };
+        uiw.write::<OrbitCamera>().set_pose(job.restore);
+        uiw.write::<GuiState>().hidden = false;
+        exporter.finish(outcome);
+    }
+}
+
+impl PngJob {
+    /// Some once the job is over
+    fn update(
+        &mut self,
+        uiw: &UiWorld,
+        ctx: &mut Context,
+        pending_chunks: usize,
+    ) -> Option<ExportOutcome> {
+        let (viewport_w, viewport_h) = {
+            let camera = &uiw.read::<OrbitCamera>().camera;
+            (camera.viewport_w as u32, camera.viewport_h as u32)
+        };
+        if (viewport_w, viewport_h) != (self.tile_w, self.tile_h) {
+            return Some(ExportOutcome::Failed(
+                "the window was resized during the export".to_string(),
+            ));
+        }
+
+        if let Some(ref grab) = self.grab {
+            match std::mem::take(&mut *grab.lock().unwrap()) {
+                GrabState::Pending => {}
+                GrabState::Failed => {
+                    return Some(ExportOutcome::Failed(
+                        "the frames could not be read back".to_string(),
+                    ));
+                }
+                GrabState::Done(frame) => {
+                    if (frame.width, frame.height) != (self.tile_w, self.tile_h) {
+                        return Some(ExportOutcome::Failed(
+                            "the frame does not match the window size".to_string(),
+                        ));
+                    }
+                    self.blit(&frame);
+                    self.next += 1;
+                    self.settled = 0;
+                    self.grab = None;
+                }
+            }
+        }
+
+        if self.next == self.cols * self.rows {
+            let path = self.path.clone();
+            let (width, height) = (self.width, self.height);
+            let canvas = std::mem::take(&mut self.canvas);
+            std::thread::spawn(move || engine::write_png(path, width, height, canvas));
+            return Some(ExportOutcome::Written(self.path.clone()));
+        }
+
+        // the windows are rendered while the GUI state is borrowed, so it is hidden from here
+        uiw.write::<GuiState>().hidden = true;
+
+        let mut camera = uiw.write::<OrbitCamera>();
+        camera.set_pose(self.tile_pose(self.next));
+        camera.camera.fovy = EXPORT_FOVY;
+        drop(camera);
+
+        // the fog would cover everything seen from that far
+        let gfx = uiw.read::<Settings>().gfx;
+        ctx.gfx
+            .update_settings(engine::GfxSettings { fog: false, ..gfx });
+
+        if self.grab.is_none() {
+            if pending_chunks > 0 {
+                self.settled = 0;
+            } else {
+                self.settled += 1;
+            }
+            // the grab reads back the frame rendered right after this update
+            if self.settled >= SETTLE_FRAMES {
+                self.grab = Some(ctx.gfx.frame_dump.grab());
+            }
+        }
+        None
+    }
+
+    /// Looks straight down at the center of the tile. The tiles line up on the z = 0 plane.
+    fn tile_pose(&self, tile: u32) -> CameraPose {
+        let col = tile % self.cols;
+        let row = tile / self.cols;
+        let tile_w = self.tile_w as f32 / self.scale;
+        let tile_h = self.tile_h as f32 / self.scale;
+
+        let fovy = EXPORT_FOVY.to_radians();
+        let eye_dist = tile_h * 0.5 / (fovy * 0.5).tan();
+
+        CameraPose {
+            pos: Vec3::new(
+                self.bounds.ll.x + (col as f32 + 0.5) * tile_w,
+                self.bounds.ur.y - (row as f32 + 0.5) * tile_h,
+                0.0,
+            ),
+            yaw: -Radians::HALFPI,
+            pitch: Radians::HALFPI - Radians(0.001),
+            // the orbit camera puts its eye at dist / sin(fovy)
+            dist: eye_dist * fovy.sin(),
+        }
+    }
+
+    fn blit(&mut self, frame: &FrameImage) {
+        let x0 = (self.next % self.cols) * self.tile_w;
+        let y0 = (self.next / self.cols) * self.tile_h;
+        let w = self.tile_w.min(self.width - x0) as usize;
+        let h = self.tile_h.min(self.height - y0);
+        for y in 0..h {
+            let src = (y * frame.width * 4) as usize;
+            let dst = (((y0 + y) * self.width + x0) * 4) as usize;
+            self.canvas[dst..dst + w * 4].copy_from_slice(&frame.rgba[src..src + w * 4]);
+        }
+    }
+}
+
+/// The requested scale, lowered so that the image stays within [`MAX_PNG_SIZE`]
+fn png_scale(bounds: AABB, scale: f32) -> f32 {
+    scale.min(MAX_PNG_SIZE / bounds.w().max(bounds.h()))
+}
+
+fn export_path(extension: &str) -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    PathBuf::from(EXPORT_DIR).join(format!("map_{}.{}", secs, extension))
+}
+
+/// Opens the directory containing the file in the file explorer of the system
+pub fn open_folder(file: &Path) {
+    let dir = file
+        .parent()
+        .and_then(|d| d.canonicalize().ok())
+        .unwrap_or_else(|| PathBuf::from(EXPORT_DIR));
+
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = "xdg-open";
+
+    if let Err(e) = std::process::Command::new(program).arg(&dir).spawn() {
+        log::error!("could not open {:?}: {}", dir, e);
+    }
+}
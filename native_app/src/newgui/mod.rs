@@ -1,17 +1,25 @@
+use crate::inputmap::InputLayer;
 use crate::newgui::windows::GUIWindows;
 use crate::uiworld::UiWorld;
 use serde::{Deserialize, Serialize};
-use simulation::map::BuildingID;
+use simulation::map::{BuildingID, DistrictID};
 use simulation::world_command::WorldCommand;
 use simulation::{AnyEntity, Simulation};
 use std::borrow::Cow;
 use std::time::Instant;
 
+pub mod camera_path;
+pub mod cinematic;
 pub mod follow;
 mod hud;
+pub mod input_recording;
 pub mod inspect;
+pub mod journal;
+pub mod map_export;
 mod textures;
 mod tools;
+pub mod trip_route;
+pub mod units;
 
 pub use hud::*;
 pub use textures::*;
@@ -40,14 +48,25 @@ impl Default for GuiState {
 pub fn run_ui_systems(sim: &Simulation, uiworld: &UiWorld) {
     profiling::scope!("gui::run_ui_systems");
     bulldozer::bulldozer(sim, uiworld);
+    hover::hover(sim, uiworld);
     inspected_aura::inspected_aura(sim, uiworld);
     lotbrush::lotbrush(sim, uiworld);
+    districts::districts(sim, uiworld);
+    restrictions::restrictions(sim, uiworld);
     roadbuild::roadbuild(sim, uiworld);
+    roadlayout::roadlayout(sim, uiworld);
     roadeditor::roadeditor(sim, uiworld);
     specialbuilding::specialbuilding(sim, uiworld);
     addtrain::addtrain(sim, uiworld);
     zoneedit::zoneedit(sim, uiworld);
     terraforming::terraforming(sim, uiworld);
+    upgrade_badges::upgrade_badges(sim, uiworld);
+    supply_chain::supply_chain(sim, uiworld);
+    commutes::commutes(sim, uiworld);
+    road_condition::road_condition(sim, uiworld);
+    service_coverage::service_coverage(sim, uiworld);
+    walkability::walkability(sim, uiworld);
+    deposits::deposits(sim, uiworld);
 
     // run last so other systems can have the chance to cancel select
     selectable::selectable(sim, uiworld);
@@ -94,6 +113,13 @@ pub struct InspectedBuilding {
     pub dontclear: bool,
 }
 
+/// District shown in the district inspector, with the name being typed for it
+#[derive(Clone, Debug, Default)]
+pub struct InspectedDistrict {
+    pub e: Option<DistrictID>,
+    pub name: String,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct InspectedEntity {
     pub e: Option<AnyEntity>,
@@ -152,6 +178,16 @@ impl Tool {
             Tool::RoadbuildStraight | Tool::RoadbuildCurved | Tool::Bulldozer | Tool::LotBrush
         )
     }
+
+    /// The input layer whose bindings take precedence while the tool is selected
+    pub fn input_layer(&self) -> Option<InputLayer> {
+        match self {
+            Tool::RoadbuildStraight | Tool::RoadbuildCurved => Some(InputLayer::RoadTool),
+            Tool::SpecialBuilding => Some(InputLayer::BuildingTool),
+            Tool::LotBrush | Tool::Terraforming => Some(InputLayer::BrushTool),
+            _ => None,
+        }
+    }
 }
 
 pub enum ExitState {
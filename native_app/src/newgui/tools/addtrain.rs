@@ -1,6 +1,7 @@
 use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::{PotentialCommands, Tool};
 use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
 use crate::uiworld::UiWorld;
 use geom::{Color, OBB};
 use prototypes::RollingStockID;
@@ -19,6 +20,8 @@ pub struct TrainSpawnResource {
     pub acceleration: f32,
     /// m/s^2
     pub deceleration: f32,
+    /// meter, from max speed on flat ground
+    pub braking_distance: f32,
     /// meter
     pub total_lenght: f32,
 }
@@ -50,8 +53,7 @@ pub fn addtrain(sim: &Simulation, uiworld: &UiWorld) {
     let nearbylane = match nearbylane.and_then(|x| map.lanes().get(x)) {
         Some(x) => x,
         None => {
-            draw.circle(mpos, 10.0)
-                .color(simulation::colors().gui_danger);
+            draw.circle(mpos, 10.0).color(palette().danger);
             return;
         }
     };
@@ -75,11 +77,11 @@ pub fn addtrain(sim: &Simulation, uiworld: &UiWorld) {
     };
 
     if dist <= trainlength {
-        drawtrain(simulation::colors().gui_danger);
+        drawtrain(palette().danger);
         return;
     }
 
-    drawtrain(simulation::colors().gui_primary);
+    drawtrain(palette().primary);
 
     let cmd = WorldCommand::SpawnTrain {
         wagons: state.wagons.clone(),
@@ -105,6 +107,7 @@ impl TrainSpawnResource {
         self.max_speed = locomotive.max_speed;
         self.acceleration = locomotive.acc_force;
         self.deceleration = locomotive.dec_force;
+        self.braking_distance = locomotive.braking_distance(locomotive.max_speed, 0.0);
         self.total_lenght = locomotive.length;
     }
 
@@ -112,6 +115,7 @@ impl TrainSpawnResource {
         self.max_speed = 0.0;
         self.acceleration = 0.0;
         self.deceleration = 0.0;
+        self.braking_distance = 0.0;
         self.total_lenght = 0.0;
     }
 }
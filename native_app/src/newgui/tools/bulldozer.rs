@@ -1,10 +1,12 @@
 use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::specialbuilding::SpecialBuildingResource;
-use crate::newgui::Tool;
+use crate::newgui::{ErrorTooltip, PotentialCommands, Tool};
 use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
 use crate::uiworld::UiWorld;
 use egui_inspect::Inspect;
 use simulation::map::{BuildingKind, Map, ProjectFilter, ProjectKind};
+use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 
 #[derive(Copy, Clone, Default, Inspect)]
@@ -27,20 +29,41 @@ pub fn bulldozer(sim: &Simulation, uiworld: &UiWorld) {
     let draw: &mut ImmediateDraw = &mut uiworld.write::<ImmediateDraw>();
     let mut commands = uiworld.commands();
     let state: &BulldozerState = &uiworld.read::<BulldozerState>();
+    let mut potential = uiworld.write::<PotentialCommands>();
 
     let cur_proj = map.project(unwrap_ret!(inp.unprojected), 0.0, ProjectFilter::ALL);
 
+    let removal = match cur_proj.kind {
+        ProjectKind::Inter(id) => Some(WorldCommand::MapRemoveIntersection(id)),
+        ProjectKind::Road(id) => Some(WorldCommand::MapRemoveRoad(id)),
+        ProjectKind::Building(id) => Some(WorldCommand::MapRemoveBuilding(id)),
+        ProjectKind::Ground | ProjectKind::Lot(_) => None,
+    };
+    if let Some(r) = removal.as_ref().and_then(|cmd| cmd.restricted_by(map)) {
+        *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(r.reason());
+        draw.circle(cur_proj.pos.up(0.5), 2.0)
+            .color(palette().disabled);
+        return;
+    }
+
     let col = if matches!(
         cur_proj.kind,
         ProjectKind::Inter(_) | ProjectKind::Road(_) | ProjectKind::Building(_)
     ) {
-        simulation::colors().gui_danger
+        palette().danger
     } else {
-        simulation::colors().gui_disabled
+        palette().disabled
     };
 
     draw.circle(cur_proj.pos.up(0.5), 2.0).color(col);
 
+    // shows the refund next to the cursor
+    match cur_proj.kind {
+        ProjectKind::Inter(id) => potential.set(WorldCommand::MapRemoveIntersection(id)),
+        ProjectKind::Road(id) => potential.set(WorldCommand::MapRemoveRoad(id)),
+        _ => {}
+    }
+
     if ((!state.hold && inp.just_act.contains(&InputAction::Select))
         || (state.hold && inp.act.contains(&InputAction::Select)))
         && !matches!(cur_proj.kind, ProjectKind::Ground)
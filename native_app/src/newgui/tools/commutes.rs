@@ -0,0 +1,64 @@
+use crate::newgui::InspectedBuilding;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+use simulation::map::BuildingID;
+use simulation::souls::commute::{employees, estimate_commutes, CommuteEstimate};
+use simulation::{HumanID, Simulation};
+
+/// Maximum number of commute lines drawn, so the map stays readable
+pub const MAX_COMMUTE_LINES: usize = 200;
+
+/// Whether the inspected building shows where its workers live.
+/// The estimates are cached as routing the whole workforce is too costly to do every frame.
+#[derive(Default)]
+pub struct CommuteView {
+    pub enabled: bool,
+    building: Option<BuildingID>,
+    employees: Vec<HumanID>,
+    pub commutes: Vec<CommuteEstimate>,
+}
+
+impl CommuteView {
+    /// Recomputes the estimates when another building is inspected or its employees changed
+    pub fn update(&mut self, sim: &Simulation, building: BuildingID) {
+        let employees = employees(sim, building);
+        if self.building == Some(building) && self.employees == employees {
+            return;
+        }
+        self.building = Some(building);
+        self.employees = employees;
+        self.commutes = estimate_commutes(sim, building);
+    }
+}
+
+/// Draws a line from the home of each worker of the inspected building to the building
+pub fn commutes(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::commutes");
+    let mut view = uiworld.write::<CommuteView>();
+    if !view.enabled {
+        return;
+    }
+    let Some(inspected) = uiworld.read::<InspectedBuilding>().e else {
+        return;
+    };
+    view.update(sim, inspected);
+
+    let map = sim.map();
+    let Some(workplace) = map.buildings().get(inspected) else {
+        return;
+    };
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let col = palette().primary.a(0.6);
+
+    for c in view.commutes.iter().take(MAX_COMMUTE_LINES) {
+        let Some(home) = map.buildings().get(c.home) else {
+            continue;
+        };
+        let from = home.door_pos;
+        let to = workplace.door_pos;
+        let arc = ((from + to) * 0.5).up(3.0 + from.distance(to) * 0.15);
+        draw.polyline([from.up(1.0), arc, to.up(1.0)], 0.3, false)
+            .color(col);
+    }
+}
@@ -0,0 +1,71 @@
+use geom::Vec3;
+use prototypes::DepositID;
+use simulation::map::Deposit;
+use simulation::Simulation;
+
+use crate::newgui::specialbuilding::SpecialBuildingResource;
+use crate::newgui::Tool;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+
+/// Height of the patches above the terrain
+const PATCH_HEIGHT: f32 = 1.0;
+
+/// Whether the raw material deposits are drawn on the map
+#[derive(Default)]
+pub struct DepositsView {
+    pub enabled: bool,
+}
+
+/// The deposit needed by the extraction company being placed, its patches are shown even
+/// with the overlay off
+pub fn placing_deposit(uiworld: &UiWorld) -> Option<DepositID> {
+    if *uiworld.read::<Tool>() != Tool::SpecialBuilding {
+        return None;
+    }
+    uiworld
+        .read::<SpecialBuildingResource>()
+        .opt
+        .as_ref()?
+        .deposit
+}
+
+/// The deposits to show, and whether they are shown at all
+pub fn shown_deposits(uiworld: &UiWorld) -> Option<Option<DepositID>> {
+    let placing = placing_deposit(uiworld);
+    if placing.is_some() {
+        return Some(placing);
+    }
+    uiworld.read::<DepositsView>().enabled.then_some(None)
+}
+
+/// Point above the center of the patch
+pub fn deposit_anchor(sim: &Simulation, d: &Deposit) -> Vec3 {
+    let center = d.shape.center;
+    let h = sim.map().environment.height(center).unwrap_or(0.0);
+    center.z(h + PATCH_HEIGHT)
+}
+
+/// Draws the deposit patches in the color of their kind, fading as they empty.
+/// While placing an extraction company, only the patches of its kind are drawn.
+pub fn deposits(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::deposits");
+    let Some(only) = shown_deposits(uiworld) else {
+        return;
+    };
+
+    let map = sim.map();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for d in map.deposits().values() {
+        if only.is_some_and(|kind| kind != d.kind) {
+            continue;
+        }
+        let col = d.kind.prototype().color;
+        let pos = deposit_anchor(sim, d);
+
+        draw.circle(pos, d.shape.radius)
+            .color(col.a(0.1 + 0.4 * d.share_left()));
+        draw.stroke_circle(pos, d.shape.radius, 1.5)
+            .color(col.a(0.8));
+    }
+}
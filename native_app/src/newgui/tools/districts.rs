@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use geom::{Color, Polygon, Vec2, Vec3};
+use simulation::economy::DistrictStats;
+use simulation::map::{valid_district_shape, DistrictID, Map};
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::snapping::snap_to_point;
+use crate::newgui::{InspectedDistrict, Tool};
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+
+/// Borders are only drawn when the camera is closer than that, unless painting districts
+pub const MAX_BORDER_CAMERA_DIST: f32 = 8000.0;
+/// Seconds between two computations of the district statistics
+const REFRESH_SECONDS: f32 = 2.0;
+
+/// Colors proposed for the districts, the new ones go through them in order
+pub const DISTRICT_COLORS: [(&str, Color); 6] = [
+    ("Red", Color::new(0.85, 0.3, 0.3, 1.0)),
+    ("Orange", Color::new(0.9, 0.6, 0.2, 1.0)),
+    ("Yellow", Color::new(0.9, 0.85, 0.3, 1.0)),
+    ("Green", Color::new(0.35, 0.75, 0.35, 1.0)),
+    ("Blue", Color::new(0.3, 0.5, 0.9, 1.0)),
+    ("Purple", Color::new(0.65, 0.4, 0.85, 1.0)),
+];
+
+/// Polygon being painted with the district mode of the zoning tool
+#[derive(Default)]
+pub struct DistrictPaintResource {
+    /// Districts are painted instead of zones
+    pub active: bool,
+    points: Vec<Vec2>,
+}
+
+/// District statistics shared by the inspector and the economy window,
+/// computed again every few seconds
+#[derive(Default)]
+pub struct DistrictStatsView {
+    stats: BTreeMap<DistrictID, DistrictStats>,
+    computed_at: Option<Instant>,
+}
+
+impl DistrictStatsView {
+    pub fn get(&mut self, sim: &Simulation) -> &BTreeMap<DistrictID, DistrictStats> {
+        if self
+            .computed_at
+            .map_or(true, |t| t.elapsed().as_secs_f32() > REFRESH_SECONDS)
+        {
+            self.stats = DistrictStats::all(sim);
+            self.computed_at = Some(Instant::now());
+        }
+        &self.stats
+    }
+}
+
+/// Share of a district a service covers, as shown in the tables
+pub fn coverage_text(coverage: Option<f32>) -> String {
+    match coverage {
+        Some(c) => format!("{:.0}%", c * 100.0),
+        None => "-".to_string(),
+    }
+}
+
+/// Average land value of a district, as shown in the tables
+pub fn land_value_text(value: Option<f32>) -> String {
+    match value {
+        Some(v) => format!("{:.0}/100", v),
+        None => "-".to_string(),
+    }
+}
+
+/// Draws the district borders at medium zoom and lets the zoning tool paint new districts.
+/// A district is painted by clicking its corners and closing the shape on the first one,
+/// clicking inside an existing district inspects it.
+pub fn districts(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::districts");
+    let mut state = uiworld.write::<DistrictPaintResource>();
+    let tool = *uiworld.read::<Tool>();
+    let painting = state.active && matches!(tool, Tool::LotBrush);
+    let map = sim.map();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+
+    if painting || uiworld.camera().camera.dist < MAX_BORDER_CAMERA_DIST {
+        let inspected = uiworld.read::<InspectedDistrict>().e;
+        for (id, d) in map.districts() {
+            let alpha = if inspected == Some(id) { 0.9 } else { 0.4 };
+            let border = drape(&map, d.shape.as_slice(), true);
+            draw.polyline(border, 2.0, true).color(d.color.a(alpha));
+        }
+    }
+
+    if !painting {
+        state.points.clear();
+        return;
+    }
+
+    let mut inp = uiworld.write::<InputMap>();
+    let unproj = unwrap_ret!(inp.unprojected);
+
+    if inp.just_act.contains(&InputAction::Close) && !state.points.is_empty() {
+        inp.just_act.remove(&InputAction::Close);
+        state.points.pop();
+    }
+
+    // snapping on the corners of the other districts lets them share their borders
+    let radius = (uiworld.camera().camera.dist * 0.01).clamp(2.0, 30.0);
+    let mut mouse = snap_to_point(
+        unproj.xy(),
+        map.districts()
+            .values()
+            .flat_map(|d| d.shape.iter().copied()),
+        radius,
+    )
+    .unwrap_or(unproj.xy());
+
+    let closing = state.points.len() >= 3 && state.points[0].is_close(mouse, radius);
+    if closing {
+        mouse = state.points[0];
+    }
+
+    let mut shape = Polygon(state.points.clone());
+    if !closing {
+        shape.push(mouse);
+    }
+    let valid = shape.len() < 3 || (valid_district_shape(&shape) && !map.district_overlaps(&shape));
+
+    let col = if valid {
+        palette().primary
+    } else {
+        palette().danger
+    };
+    draw.circle(mouse.z(unproj.z + 0.5), radius * 0.5)
+        .color(col);
+    if !state.points.is_empty() {
+        let outline = drape(&map, shape.as_slice(), shape.len() >= 3);
+        draw.polyline(outline, 3.0, shape.len() >= 3).color(col);
+    }
+
+    if !inp.just_act.contains(&InputAction::Select) {
+        return;
+    }
+
+    if state.points.is_empty() {
+        if let Some(id) = map.district_at(mouse) {
+            let mut inspected = uiworld.write::<InspectedDistrict>();
+            inspected.e = Some(id);
+            inspected.name = map.districts()[id].name.clone();
+            return;
+        }
+    }
+
+    if closing {
+        if valid {
+            let name = (1..)
+                .map(|i| format!("District {i}"))
+                .find(|name| map.districts().values().all(|d| d.name != *name))
+                .unwrap();
+            let (_, color) = DISTRICT_COLORS[map.districts().len() % DISTRICT_COLORS.len()];
+            uiworld.commands().map_add_district(
+                name,
+                color,
+                Polygon(std::mem::take(&mut state.points)),
+            );
+        }
+        return;
+    }
+
+    if valid {
+        state.points.push(mouse);
+    }
+}
+
+/// The points laid on the terrain, long edges are split so they follow it
+pub(crate) fn drape(map: &Map, points: &[Vec2], closed: bool) -> Vec<Vec3> {
+    let height = |p: Vec2| map.environment.height(p).unwrap_or(0.0) + 1.0;
+    let n = points.len();
+    let edges = if closed { n } else { n.saturating_sub(1) };
+
+    let mut out = Vec::with_capacity(n * 2);
+    for i in 0..edges {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let steps = (a.distance(b) / 20.0).ceil().max(1.0) as usize;
+        for s in 0..steps {
+            let p = a + (b - a) * (s as f32 / steps as f32);
+            out.push(p.z(height(p)));
+        }
+    }
+    if !closed {
+        if let Some(&last) = points.last() {
+            out.push(last.z(height(last)));
+        }
+    }
+    out
+}
@@ -0,0 +1,78 @@
+use crate::inputmap::InputMap;
+use crate::newgui::selectable::{pick_building, pick_entity, select_radius};
+use crate::newgui::Tool;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+use simulation::map::BuildingID;
+use simulation::{AnyEntity, Simulation};
+use std::time::{Duration, Instant};
+
+/// Time the cursor must stay on an object before its tooltip is shown
+pub const HOVER_DELAY: Duration = Duration::from_millis(400);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HoveredObject {
+    Entity(AnyEntity),
+    Building(BuildingID),
+}
+
+#[derive(Default)]
+pub struct HoverState {
+    pub hovered: Option<HoveredObject>,
+    /// When the cursor started hovering the object
+    pub since: Option<Instant>,
+}
+
+impl HoverState {
+    /// The hovered object once the cursor stayed on it long enough to show its tooltip
+    pub fn tooltip_object(&self) -> Option<HoveredObject> {
+        self.since
+            .filter(|since| since.elapsed() >= HOVER_DELAY)
+            .and(self.hovered)
+    }
+}
+
+/// Hover finds the object under the cursor when selecting, and highlights it
+pub fn hover(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::hover");
+    let mut state = uiworld.write::<HoverState>();
+    let inp = uiworld.read::<InputMap>();
+    let tool = *uiworld.read::<Tool>();
+
+    let hovered = match (tool, inp.unprojected) {
+        (Tool::Hand, Some(unproj)) => pick_entity(sim, unproj.xy())
+            .map(HoveredObject::Entity)
+            .or_else(|| pick_building(sim, unproj.xy()).map(HoveredObject::Building)),
+        _ => None,
+    };
+
+    // moving within the same object keeps the timer so the tooltip does not flicker
+    if hovered != state.hovered {
+        state.hovered = hovered;
+        state.since = hovered.map(|_| Instant::now());
+    }
+
+    let Some(hovered) = hovered else {
+        return;
+    };
+
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let col = palette().primary.a(0.3);
+    match hovered {
+        HoveredObject::Entity(e) => {
+            let Some(pos) = sim.pos_any(e) else {
+                return;
+            };
+            let radius = select_radius(e);
+            draw.circle(pos.up(0.2), radius).color(col);
+        }
+        HoveredObject::Building(b) => {
+            let map = sim.map();
+            let Some(b) = map.buildings().get(b) else {
+                return;
+            };
+            draw.obb(b.obb, b.height + 0.02).color(col);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::newgui::selectable::select_radius;
 use crate::newgui::{InspectedBuilding, InspectedEntity};
 use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
 use crate::uiworld::UiWorld;
 use geom::Color;
 use simulation::transportation::Location;
@@ -47,7 +48,6 @@ pub fn inspected_aura(sim: &Simulation, uiworld: &UiWorld) {
             return;
         }
 
-        draw.obb(b.obb, b.height + 0.01)
-            .color(simulation::colors().gui_primary);
+        draw.obb(b.obb, b.height + 0.01).color(palette().primary);
     }
 }
@@ -1,29 +1,43 @@
 use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::districts::DistrictPaintResource;
 use crate::newgui::Tool;
 use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
 use crate::uiworld::UiWorld;
+use geom::{Circle, Color, Vec2, OBB};
 use serde::{Deserialize, Serialize};
-use simulation::map::{LotKind, ProjectFilter, ProjectKind};
+use simulation::map::{LotKind, ZoneBrush};
 use simulation::Simulation;
 
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZoneShape {
+    #[default]
+    Brush,
+    Rectangle,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct LotBrushResource {
     pub kind: LotKind,
     pub radius: f32,
+    #[serde(default)]
+    pub shape: ZoneShape,
+    #[serde(skip)]
+    rect_start: Option<Vec2>,
 }
 
 /// Lot brush tool
-/// Allows to build houses on lots
-pub fn lotbrush(sim: &Simulation, uiworld: &UiWorld) {
+/// Allows to paint zones on lots, buildings then grow on them when there is demand
+pub fn lotbrush(_sim: &Simulation, uiworld: &UiWorld) {
     profiling::scope!("gui::lotbrush");
     let mut res = uiworld.write::<LotBrushResource>();
     let tool = *uiworld.read::<Tool>();
     let inp = uiworld.read::<InputMap>();
     let mut draw = uiworld.write::<ImmediateDraw>();
-    let map = sim.map();
     let commands = &mut *uiworld.commands();
 
-    if !matches!(tool, Tool::LotBrush) {
+    if !matches!(tool, Tool::LotBrush) || uiworld.read::<DistrictPaintResource>().active {
+        res.rect_start = None;
         return;
     }
 
@@ -37,25 +51,53 @@ pub fn lotbrush(sim: &Simulation, uiworld: &UiWorld) {
 
     let kind = res.kind;
 
-    let mut col = match kind {
-        LotKind::Unassigned => simulation::colors().lot_unassigned_col,
-        LotKind::Residential => simulation::colors().lot_residential_col,
-    };
-
+    let mut col = lot_kind_color(kind);
     col.a = 0.2;
 
     let mpos = unwrap_ret!(inp.unprojected);
-    draw.circle(mpos.up(0.8), res.radius).color(col);
 
-    if inp.act.contains(&InputAction::Select) {
-        for v in map
-            .spatial_map()
-            .query_around(mpos.xy(), res.radius, ProjectFilter::LOT)
-        {
-            if let ProjectKind::Lot(id) = v {
-                commands.map_build_house(id);
+    match res.shape {
+        ZoneShape::Brush => {
+            draw.circle(mpos.up(0.8), res.radius).color(col);
+
+            if inp.act.contains(&InputAction::Select) {
+                commands
+                    .map_paint_zone(ZoneBrush::Circle(Circle::new(mpos.xy(), res.radius)), kind);
             }
         }
+        ZoneShape::Rectangle => {
+            if inp.just_act.contains(&InputAction::Select) {
+                res.rect_start = Some(mpos.xy());
+            }
+
+            let Some(start) = res.rect_start else {
+                draw.circle(mpos.up(0.8), 2.0).color(col);
+                return;
+            };
+
+            let diag = mpos.xy() - start;
+            let rect = OBB::new(
+                start + diag * 0.5,
+                Vec2::X,
+                diag.x.abs().max(1.0),
+                diag.y.abs().max(1.0),
+            );
+            draw.obb(rect, mpos.z + 0.8).color(col);
+
+            if !inp.act.contains(&InputAction::Select) {
+                commands.map_paint_zone(ZoneBrush::Rect(rect), kind);
+                res.rect_start = None;
+            }
+        }
+    }
+}
+
+pub fn lot_kind_color(kind: LotKind) -> Color {
+    match kind {
+        LotKind::Unassigned => palette().unassigned,
+        LotKind::Residential => palette().residential,
+        LotKind::Commercial => palette().commercial,
+        LotKind::Industrial => palette().industrial,
     }
 }
 
@@ -64,6 +106,8 @@ impl Default for LotBrushResource {
         Self {
             kind: LotKind::Residential,
             radius: 25.0,
+            shape: ZoneShape::Brush,
+            rect_start: None,
         }
     }
 }
@@ -1,10 +1,22 @@
 pub mod addtrain;
 pub mod bulldozer;
+pub mod commutes;
+pub mod deposits;
+pub mod districts;
+pub mod hover;
 pub mod inspected_aura;
 pub mod lotbrush;
+pub mod restrictions;
+pub mod road_condition;
 pub mod roadbuild;
 pub mod roadeditor;
+pub mod roadlayout;
 pub mod selectable;
+pub mod service_coverage;
+pub mod snapping;
 pub mod specialbuilding;
+pub mod supply_chain;
 pub mod terraforming;
+pub mod upgrade_badges;
+pub mod walkability;
 pub mod zoneedit;
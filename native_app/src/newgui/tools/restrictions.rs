@@ -0,0 +1,190 @@
+use geom::{Color, Polygon, Vec2};
+use prototypes::RestrictionKind;
+use simulation::map::{valid_district_shape, Map, RestrictionID};
+use simulation::rules::GameRules;
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::districts::drape;
+use crate::newgui::snapping::snap_to_point;
+use crate::newgui::Tool;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+
+/// Smallest distance between the hatching lines of the restricted areas, in meters.
+/// The lines are further apart when the camera is far away.
+const HATCH_SPACING: f32 = 12.0;
+
+/// Polygon being drawn with the restriction mode of the terraforming tool, in sandbox games
+pub struct RestrictionPaintResource {
+    /// Restricted areas are drawn instead of terraforming
+    pub active: bool,
+    pub kind: RestrictionKind,
+    points: Vec<Vec2>,
+}
+
+impl Default for RestrictionPaintResource {
+    fn default() -> Self {
+        Self {
+            active: false,
+            kind: RestrictionKind::NoBuild,
+            points: vec![],
+        }
+    }
+}
+
+pub fn restriction_color(kind: RestrictionKind) -> Color {
+    match kind {
+        RestrictionKind::NoBuild => Color::new(0.85, 0.3, 0.3, 1.0),
+        RestrictionKind::Nature => Color::new(0.3, 0.7, 0.3, 1.0),
+        RestrictionKind::Water => Color::new(0.3, 0.5, 0.9, 1.0),
+    }
+}
+
+/// Draws the restricted areas hatched while a construction tool is active, and lets the
+/// terraforming tool draw new ones in sandbox games. An area is drawn by clicking its corners
+/// and closing the shape on the first one, right clicking inside an area removes it.
+pub fn restrictions(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::restrictions");
+    let mut state = uiworld.write::<RestrictionPaintResource>();
+    let tool = *uiworld.read::<Tool>();
+    let sandbox = sim.read::<GameRules>().sandbox;
+    let painting = state.active && sandbox && matches!(tool, Tool::Terraforming);
+    let map = sim.map();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+
+    let building = matches!(
+        tool,
+        Tool::RoadbuildStraight
+            | Tool::RoadbuildCurved
+            | Tool::RoadEditor
+            | Tool::Bulldozer
+            | Tool::LotBrush
+            | Tool::SpecialBuilding
+            | Tool::Terraforming
+    );
+    if building {
+        let spacing = HATCH_SPACING.max(uiworld.camera().camera.dist * 0.02);
+        for r in map.restrictions().values() {
+            let col = restriction_color(r.kind);
+            let border = drape(&map, r.shape.as_slice(), true);
+            draw.polyline(border, 2.0, true).color(col.a(0.8));
+            for (a, b) in hatch(&r.shape, spacing) {
+                let line = drape(&map, &[a, b], false);
+                draw.polyline(line, 0.7, false).color(col.a(0.4));
+            }
+        }
+    }
+
+    if !painting {
+        state.points.clear();
+        return;
+    }
+
+    let mut inp = uiworld.write::<InputMap>();
+    let unproj = unwrap_ret!(inp.unprojected);
+
+    if inp.just_act.contains(&InputAction::Close) && !state.points.is_empty() {
+        inp.just_act.remove(&InputAction::Close);
+        state.points.pop();
+    }
+
+    let radius = (uiworld.camera().camera.dist * 0.01).clamp(2.0, 30.0);
+    let mut mouse = snap_to_point(
+        unproj.xy(),
+        map.restrictions()
+            .values()
+            .flat_map(|r| r.shape.iter().copied()),
+        radius,
+    )
+    .unwrap_or(unproj.xy());
+
+    let closing = state.points.len() >= 3 && state.points[0].is_close(mouse, radius);
+    if closing {
+        mouse = state.points[0];
+    }
+
+    let mut shape = Polygon(state.points.clone());
+    if !closing {
+        shape.push(mouse);
+    }
+    let valid = shape.len() < 3 || valid_district_shape(&shape);
+
+    let col = if valid {
+        restriction_color(state.kind)
+    } else {
+        palette().danger
+    };
+    draw.circle(mouse.z(unproj.z + 0.5), radius * 0.5)
+        .color(col);
+    if !state.points.is_empty() {
+        let outline = drape(&map, shape.as_slice(), shape.len() >= 3);
+        draw.polyline(outline, 3.0, shape.len() >= 3).color(col);
+    }
+
+    if state.points.is_empty() && inp.just_act.contains(&InputAction::SecondarySelect) {
+        if let Some(r) = restriction_under(&map, mouse) {
+            uiworld.commands().map_remove_restriction(r);
+        }
+        return;
+    }
+
+    if !inp.just_act.contains(&InputAction::Select) {
+        return;
+    }
+
+    if closing {
+        if valid {
+            let kind = state.kind;
+            uiworld
+                .commands()
+                .map_add_restriction(kind, Polygon(std::mem::take(&mut state.points)));
+        }
+        return;
+    }
+
+    if valid {
+        state.points.push(mouse);
+    }
+}
+
+fn restriction_under(map: &Map, p: Vec2) -> Option<RestrictionID> {
+    map.restrictions()
+        .values()
+        .find(|r| r.shape.contains(p))
+        .map(|r| r.id)
+}
+
+/// Diagonal lines clipped to the inside of the shape, `spacing` apart
+fn hatch(shape: &Polygon, spacing: f32) -> Vec<(Vec2, Vec2)> {
+    let mut lines = vec![];
+    if shape.len() < 3 {
+        return lines;
+    }
+    // the lines are x + y = c, crossing the edges where the sum changes sign
+    let sum = |p: Vec2| p.x + p.y;
+    let (min, max) = shape.iter().fold((f32::MAX, f32::MIN), |(min, max), &p| {
+        (min.min(sum(p)), max.max(sum(p)))
+    });
+
+    let mut c = (min / spacing).ceil() * spacing;
+    while c < max {
+        let mut crossings: Vec<Vec2> = shape
+            .segments()
+            .filter_map(|s| {
+                let (a, b) = (sum(s.src) - c, sum(s.dst) - c);
+                if (a < 0.0) == (b < 0.0) {
+                    return None;
+                }
+                Some(s.src + (s.dst - s.src) * (a / (a - b)))
+            })
+            .collect();
+        crossings.sort_by(|a, b| a.x.total_cmp(&b.x));
+        for pair in crossings.chunks_exact(2) {
+            lines.push((pair[0], pair[1]));
+        }
+        c += spacing;
+    }
+    lines
+}
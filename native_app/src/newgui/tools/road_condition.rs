@@ -0,0 +1,38 @@
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+use geom::Color;
+use simulation::map::RoadCondition;
+use simulation::Simulation;
+
+/// Whether the roads are colored by the condition of their pavement
+#[derive(Default)]
+pub struct RoadConditionView {
+    pub enabled: bool,
+}
+
+pub fn condition_color(condition: RoadCondition) -> Color {
+    let colors = palette();
+    match condition {
+        RoadCondition::Good => colors.ramp(0.0),
+        RoadCondition::Worn => colors.ramp(0.5),
+        RoadCondition::Damaged => colors.ramp(0.75),
+        RoadCondition::Broken => colors.ramp(1.0),
+    }
+}
+
+/// Draws every road over the map in the color of its condition
+pub fn road_condition(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::road_condition");
+    if !uiworld.read::<RoadConditionView>().enabled {
+        return;
+    }
+
+    let map = sim.map();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for road in map.roads().values() {
+        let points: Vec<_> = road.points().iter().map(|p| p.up(0.5)).collect();
+        draw.polyline(points, road.width * 0.5, false)
+            .color(condition_color(road.condition()));
+    }
+}
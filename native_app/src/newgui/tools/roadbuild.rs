@@ -1,6 +1,9 @@
+use std::borrow::Cow;
+
 use engine::AudioKind;
 use geom::{BoldLine, BoldSpline, Camera, Line, PolyLine, ShapeEnum, Spline};
 use geom::{PolyLine3, Vec2, Vec3};
+use simulation::economy::Government;
 use simulation::map::{
     LanePatternBuilder, Map, MapProject, ProjectFilter, ProjectKind, PylonPosition, RoadSegmentKind,
 };
@@ -10,8 +13,13 @@ use BuildState::{Connection, Hover, Interpolation, Start, StartInterp};
 use ProjectKind::{Building, Ground, Inter, Road};
 
 use crate::inputmap::{InputAction, InputMap};
-use crate::newgui::{PotentialCommands, Tool};
+use crate::newgui::roadlayout::{RoadLayoutResource, RoadMode};
+use crate::newgui::snapping::{
+    draw_guides, snap_parallel, snap_to_angle, snap_to_point, SnapGuide, SnapSettings,
+};
+use crate::newgui::{ErrorTooltip, PotentialCommands, Tool};
 use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
+use crate::rendering::palette;
 use crate::uiworld::UiWorld;
 
 #[derive(Copy, Clone, Debug, Default)]
@@ -37,6 +45,7 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
     let map = &*sim.map();
     let commands: &mut WorldCommands = &mut uiworld.commands();
     let cam = &*uiworld.read::<Camera>();
+    let snap_settings = *uiworld.read::<SnapSettings>();
 
     if !tool.is_roadbuild() {
         state.build_state = Hover;
@@ -44,6 +53,12 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
         return;
     }
 
+    // the other modes are handled by roadlayout
+    if uiworld.read::<RoadLayoutResource>().mode != RoadMode::Single {
+        state.build_state = Hover;
+        return;
+    }
+
     let grid_size = 20.0;
     let unproj = unwrap_ret!(inp.unprojected);
     let mut interpolation_points: Vec<Vec3> = Vec::new();
@@ -58,7 +73,7 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
     };
 
     // Prepare mousepos depending on snap to grid or snap to angle
-    let mousepos = match state.snapping {
+    let mut mousepos = match state.snapping {
         Snapping::None => unproj.z0().up(mouse_height),
         Snapping::SnapToGrid => unproj.xy().snap(grid_size, grid_size).z(mouse_height),
         Snapping::SnapToAngle => {
@@ -82,13 +97,18 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
         }
     };
 
+    let mut guides = Vec::new();
+    if snap_settings.enabled && !nosnapping && !matches!(state.snapping, Snapping::SnapToGrid) {
+        mousepos = state.snap(map, mousepos, &snap_settings, &mut guides);
+    }
+
     let log_camheight = cam.eye().z.log10();
     /*
     let cutoff = 3.3;
 
     if state.snap_to_grid && log_camheight < cutoff {
         let alpha = 1.0 - log_camheight / cutoff;
-        let col = simulation::colors().gui_primary.a(alpha);
+        let col = palette().primary.a(alpha);
         let screen = AABB::new(unproj.xy(), unproj.xy()).expand(300.0);
         let startx = (screen.ll.x / grid_size).ceil() * grid_size;
         let starty = (screen.ll.y / grid_size).ceil() * grid_size;
@@ -310,6 +330,23 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
         }
     }
 
+    if let Some(r) = potential_command
+        .0
+        .first()
+        .and_then(|cmd| cmd.restricted_by(map))
+    {
+        is_valid = false;
+        *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(r.reason());
+    }
+
+    // checked again when applied, but rejecting early keeps the tool responsive
+    if let Some(cmd) = potential_command.0.first() {
+        if !sim.read::<Government>().can_afford(cmd, sim) {
+            is_valid = false;
+            *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(Cow::Borrowed("Not enough money"));
+        }
+    }
+
     state.update_drawing(
         map,
         immdraw,
@@ -319,6 +356,7 @@ pub fn roadbuild(sim: &Simulation, uiworld: &UiWorld) {
         points,
         interpolation_points,
     );
+    draw_guides(immdraw, &guides, mousepos.z);
 
     if is_valid && inp.just_act.contains(&InputAction::Select) {
         log::info!(
@@ -434,7 +472,7 @@ fn check_angle(map: &Map, from: MapProject, to: Vec2, is_rail: bool) -> bool {
     }
 }
 
-fn compatible(map: &Map, x: MapProject, y: MapProject) -> bool {
+pub(crate) fn compatible(map: &Map, x: MapProject, y: MapProject) -> bool {
     if x.pos.distance(y.pos) < 10.0 {
         return false;
     }
@@ -455,7 +493,7 @@ fn compatible(map: &Map, x: MapProject, y: MapProject) -> bool {
 }
 
 /// Check if the given shape intersects with any existing road or intersection
-fn check_intersect(
+pub(crate) fn check_intersect(
     map: &Map,
     obj: &ShapeEnum,
     z: f32,
@@ -499,9 +537,9 @@ impl RoadBuildResource {
         let mut proj_pos = proj.pos;
         proj_pos.z += 0.4;
         let col = if is_valid {
-            simulation::colors().gui_primary
+            palette().primary
         } else {
-            simulation::colors().gui_danger
+            palette().danger
         };
 
         interpolation_points.iter().for_each(|p| {
@@ -557,6 +595,84 @@ impl RoadBuildResource {
         immdraw.polyline(p.into_vec(), patwidth, false).color(col);
     }
 
+    /// Applies the shared snapping rules to the cursor, by order of priority:
+    /// existing nodes, parallel to a nearby road, then angle increments from the start point
+    fn snap(
+        &self,
+        map: &Map,
+        pos: Vec3,
+        settings: &SnapSettings,
+        guides: &mut Vec<SnapGuide>,
+    ) -> Vec3 {
+        let tol = settings.tolerance;
+        let p = pos.xy();
+
+        let node_radius = tol * 3.0;
+        let node = snap_to_point(
+            p,
+            map.spatial_map()
+                .query_around(p, node_radius, ProjectFilter::INTER)
+                .filter_map(|kind| match kind {
+                    Inter(id) => map.intersections().get(id).map(|i| i.pos.xy()),
+                    _ => None,
+                }),
+            node_radius,
+        );
+        if let Some(node) = node {
+            guides.push(SnapGuide::Point(node));
+            return node.z(pos.z);
+        }
+
+        let start = match self.build_state {
+            Start(x) | StartInterp(x) => Some(x),
+            Hover => None,
+            _ => return pos,
+        };
+
+        let offset = settings.parallel_offset;
+        let parallel = map
+            .spatial_map()
+            .query_around(p, offset + tol, ProjectFilter::ROAD)
+            .filter_map(|kind| match kind {
+                Road(id) => map.roads().get(id),
+                _ => None,
+            })
+            .filter_map(|r| {
+                let (proj, _, dir) = r.points().project_segment_dir(pos);
+                snap_parallel(p, proj.xy(), dir.xy(), offset, tol)
+            })
+            .min_by(|a, b| a.0.distance2(p).total_cmp(&b.0.distance2(p)));
+        if let Some((snapped, guide)) = parallel {
+            guides.push(guide);
+            return snapped.z(pos.z);
+        }
+
+        let Some(start) = start else {
+            return pos;
+        };
+
+        let reference = match start.kind {
+            Inter(id) => map
+                .intersections()
+                .get(id)
+                .and_then(|i| i.roads.first())
+                .and_then(|&r| Some(map.roads().get(r)?.dir_from(id))),
+            Road(id) => map
+                .roads()
+                .get(id)
+                .map(|r| r.points().project_segment_dir(start.pos).2.xy()),
+            _ => None,
+        }
+        .unwrap_or(Vec2::X);
+
+        let from = start.pos.xy();
+        let snapped = snap_to_angle(from, p, reference, settings.angle_increment.into());
+        if let Some(dir) = (snapped - from).try_normalize() {
+            guides.push(SnapGuide::Line(from, snapped + dir * 20.0));
+        }
+        snapped.z(pos.z)
+    }
+
     pub fn posible_interpolations(&self, map: &Map, mousepos: Vec3) -> Vec<Vec3> {
         let (start, end) = match self.build_state {
             Hover | Interpolation(_, _) => {
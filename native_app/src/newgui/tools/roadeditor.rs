@@ -1,9 +1,11 @@
 use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::units::UnitSystem;
 use crate::newgui::Tool;
 use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
 use crate::uiworld::UiWorld;
 use geom::Color;
-use simulation::map::{IntersectionID, LightPolicy, TurnPolicy};
+use simulation::map::{IntersectionID, LightPolicy, RoadID, TurnPolicy};
 use simulation::map::{ProjectFilter, ProjectKind};
 use simulation::Simulation;
 
@@ -18,10 +20,21 @@ pub struct IntersectionComponent {
 pub struct RoadEditorResource {
     pub inspect: Option<IntersectionComponent>,
     pub dirty: bool,
+    /// Road whose street name is being edited
+    pub road: Option<RoadID>,
+    pub road_name: String,
+    /// Whether the rails of the selected road have an overhead line, None without rails
+    pub electrified: Option<bool>,
+    /// Roads whose speed limit is edited: the selected road, and the connected ones dragged over
+    pub corridor: Vec<RoadID>,
+    /// Speed limit of the corridor in km/h or mph, None if the selected road has no vehicle lanes
+    pub speed_limit: Option<f32>,
 }
 
 /// RoadEditor tool
-/// Allows to edit intersections properties like turns and signals
+/// Allows to edit intersections properties like turns and signals, to rename streets, to
+/// electrify rails and to set speed limits. Dragging from a road adds the roads connected to it
+/// to the corridor whose speed limit is set.
 pub fn roadeditor(sim: &Simulation, uiworld: &UiWorld) {
     profiling::scope!("gui::roadeditor");
     let tool = uiworld.read::<Tool>();
@@ -33,9 +46,26 @@ pub fn roadeditor(sim: &Simulation, uiworld: &UiWorld) {
 
     if !matches!(*tool, Tool::RoadEditor) {
         state.inspect = None;
+        state.road = None;
+        state.corridor.clear();
         return;
     }
 
+    if state.road.is_some_and(|id| !map.roads().contains_key(id)) {
+        state.road = None;
+    }
+    state.corridor.retain(|&id| map.roads().contains_key(id));
+    for &id in &state.corridor {
+        let road = &map.roads()[id];
+        imm_draw
+            .polyline(
+                road.points.iter().map(|p| p.up(0.1)).collect::<Vec<_>>(),
+                road.width,
+                false,
+            )
+            .color(palette().primary.a(0.3));
+    }
+
     if let Some(id) = state.inspect.as_ref().map(|x| x.id) {
         if let Some(inter) = map.intersections().get(id) {
             let lanes = map.lanes();
@@ -60,30 +90,71 @@ pub fn roadeditor(sim: &Simulation, uiworld: &UiWorld) {
     }
 
     let mut proj_pos = unwrap_ret!(inp.unprojected);
-    let cur_proj = map.project(proj_pos, 10.0, ProjectFilter::INTER);
+    let cur_proj = map.project(proj_pos, 10.0, ProjectFilter::INTER | ProjectFilter::ROAD);
 
     let mut proj_col;
 
-    if let ProjectKind::Inter(id) = cur_proj.kind {
-        if Some(id) != state.inspect.as_ref().map(|x| x.id) {
+    match cur_proj.kind {
+        ProjectKind::Inter(id) => {
+            if Some(id) != state.inspect.as_ref().map(|x| x.id) {
+                proj_pos = cur_proj.pos;
+            }
+            proj_col = palette().primary;
+        }
+        ProjectKind::Road(_) => {
             proj_pos = cur_proj.pos;
+            proj_col = palette().primary;
+        }
+        _ => {
+            proj_col = palette().disabled;
         }
-        proj_col = simulation::colors().gui_primary;
-    } else {
-        proj_col = simulation::colors().gui_disabled;
     }
 
-    if inp.act.contains(&InputAction::Select) {
-        if let ProjectKind::Inter(id) = cur_proj.kind {
-            proj_col = simulation::colors().gui_success;
-            proj_pos = cur_proj.pos;
-            let inter = &map.intersections()[id];
-            state.inspect = Some(IntersectionComponent {
-                id,
-                turn_policy: inter.turn_policy,
-                light_policy: inter.light_policy,
+    if inp.just_act.contains(&InputAction::Select) {
+        match cur_proj.kind {
+            ProjectKind::Inter(id) => {
+                proj_col = palette().success;
+                proj_pos = cur_proj.pos;
+                let inter = &map.intersections()[id];
+                state.inspect = Some(IntersectionComponent {
+                    id,
+                    turn_policy: inter.turn_policy,
+                    light_policy: inter.light_policy,
+                });
+                state.road = None;
+                state.corridor.clear();
+                state.dirty = false;
+            }
+            ProjectKind::Road(id) => {
+                proj_col = palette().success;
+                state.inspect = None;
+                state.road = Some(id);
+                state.corridor = vec![id];
+                let road = &map.roads()[id];
+                state.road_name = road.name.clone();
+                state.electrified = road.has_rails().then_some(road.electrified);
+                state.speed_limit = road
+                    .speed_limit(map.lanes())
+                    .map(|limit| UnitSystem::current().speed_value(limit).round());
+                state.dirty = false;
+            }
+            _ => {}
+        }
+    } else if inp.act.contains(&InputAction::Select) {
+        // dragging extends the corridor with the roads connected to its last one
+        if let ProjectKind::Road(id) = cur_proj.kind {
+            let last = state
+                .corridor
+                .last()
+                .and_then(|&last| map.roads().get(last));
+            let road = &map.roads()[id];
+            let connected = last.is_some_and(|last| {
+                [last.src, last.dst].contains(&road.src) || [last.src, last.dst].contains(&road.dst)
             });
-            state.dirty = false;
+            if connected && !state.corridor.contains(&id) && state.speed_limit.is_some() {
+                state.corridor.push(id);
+                proj_col = palette().success;
+            }
         }
     }
 
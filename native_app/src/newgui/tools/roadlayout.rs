@@ -0,0 +1,280 @@
+use std::borrow::Cow;
+
+use engine::AudioKind;
+use geom::{vec2, BoldLine, PolyLine, ShapeEnum, Vec2};
+use simulation::economy::Government;
+use simulation::map::{
+    grid_layout, grid_on_border, parallel_layout, LanePattern, LanePatternBuilder, Map, MapProject,
+    PointGenerateError, ProjectFilter, ProjectKind, Road, RoadSegmentKind,
+};
+use simulation::world_command::{WorldCommand, WorldCommands};
+use simulation::Simulation;
+
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::roadbuild::{check_intersect, compatible, RoadBuildResource};
+use crate::newgui::{ErrorTooltip, PotentialCommands, Tool};
+use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RoadMode {
+    /// One road at a time, see [`super::roadbuild::roadbuild`]
+    #[default]
+    Single,
+    /// A road alongside an existing one, connected to it at both ends
+    Parallel,
+    /// A street grid dragged as a rectangle
+    Grid,
+}
+
+pub struct RoadLayoutResource {
+    pub mode: RoadMode,
+    /// Size of the grid's blocks along x and y, in meters
+    pub block_size: Vec2,
+    /// Keep the segments that are too steep for the terrain as bridges instead of skipping them
+    pub bridge_steep: bool,
+    /// Road followed by the parallel road, or first corner of the grid
+    start: Option<MapProject>,
+}
+
+impl Default for RoadLayoutResource {
+    fn default() -> Self {
+        Self {
+            mode: RoadMode::Single,
+            block_size: vec2(80.0, 80.0),
+            bridge_steep: false,
+            start: None,
+        }
+    }
+}
+
+/// Builds several roads at once, in the parallel and grid modes of the road tool.
+/// Everything is sent as a single command so it is paid and applied in one go.
+pub fn roadlayout(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::roadlayout");
+    let state = &mut *uiworld.write::<RoadLayoutResource>();
+    let tool = *uiworld.read::<Tool>();
+
+    if !tool.is_roadbuild() || state.mode == RoadMode::Single {
+        state.start = None;
+        return;
+    }
+
+    let immdraw = &mut *uiworld.write::<ImmediateDraw>();
+    let immsound = &mut *uiworld.write::<ImmediateSound>();
+    let potential_command = &mut *uiworld.write::<PotentialCommands>();
+    let mut inp = uiworld.write::<InputMap>();
+    let map = &*sim.map();
+    let commands: &mut WorldCommands = &mut uiworld.commands();
+    let builder = uiworld.read::<RoadBuildResource>().pattern_builder;
+    let patwidth = builder.width();
+
+    let unproj = unwrap_ret!(inp.unprojected);
+
+    if inp.just_act.contains(&InputAction::Close) && state.start.is_some() {
+        inp.just_act.remove(&InputAction::Close);
+        state.start = None;
+    }
+
+    potential_command.0.clear();
+
+    let Some(start) = state.start else {
+        let hover = match state.mode {
+            RoadMode::Parallel => map.project(unproj, 5.0, ProjectFilter::ROAD),
+            _ => MapProject::ground(unproj),
+        };
+        let col = match (state.mode, hover.kind) {
+            (RoadMode::Parallel, ProjectKind::Road(id)) => {
+                let r = &map.roads()[id];
+                immdraw
+                    .polyline(r.points().as_slice(), r.width, false)
+                    .color(palette().primary.a(0.5));
+                palette().primary
+            }
+            (RoadMode::Parallel, _) => palette().danger,
+            _ => palette().primary,
+        };
+        immdraw.circle(hover.pos.up(0.4), patwidth * 0.5).color(col);
+
+        if inp.just_act.contains(&InputAction::Select)
+            && (state.mode == RoadMode::Grid || !hover.is_ground())
+        {
+            state.start = Some(hover);
+        }
+        return;
+    };
+
+    let layout = match state.mode {
+        RoadMode::Parallel => parallel_projects(map, start, unproj.xy(), patwidth),
+        RoadMode::Grid => grid_projects(map, start, unproj.xy(), state.block_size, patwidth),
+        RoadMode::Single => None,
+    };
+    let Some((projects, links)) = layout else {
+        // the followed road was removed in the meantime
+        state.start = None;
+        return;
+    };
+
+    let links = plan_links(
+        map,
+        immdraw,
+        &projects,
+        &links,
+        &builder,
+        state.bridge_steep,
+    );
+    if links.is_empty() {
+        return;
+    }
+
+    potential_command.set(WorldCommand::MapMakeMultipleConnections(projects, links));
+
+    let Some(cmd) = potential_command.0.first() else {
+        return;
+    };
+    if !sim.read::<Government>().can_afford(cmd, sim) {
+        *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(Cow::Borrowed("Not enough money"));
+        return;
+    }
+
+    if inp.just_act.contains(&InputAction::Select) {
+        immsound.play("road_lay", AudioKind::Ui);
+        if let Some(wc) = potential_command.0.drain(..).next() {
+            commands.push(wc);
+        }
+        state.start = None;
+    }
+}
+
+type Layout = (Vec<MapProject>, Vec<(usize, usize, Option<Vec2>)>);
+
+/// A road on the side of the followed road where the cursor is, joined to its intersections
+fn parallel_projects(map: &Map, start: MapProject, mouse: Vec2, patwidth: f32) -> Option<Layout> {
+    let ProjectKind::Road(id) = start.kind else {
+        return None;
+    };
+    let r = map.roads().get(id)?;
+    let src = map.intersections().get(r.src)?;
+    let dst = map.intersections().get(r.dst)?;
+
+    let (proj, _, dir) = r.points().project_segment_dir(mouse.z(0.0));
+    let side = (mouse - proj.xy()).dot(dir.xy().perpendicular());
+    let min_offset = ((r.width + patwidth) * 0.5 + 2.0).max(10.0);
+    let offset = side.abs().round().max(min_offset).copysign(side);
+
+    let (from, to, elbow) = parallel_layout(src.pos.xy(), dst.pos.xy(), r.segment, offset)?;
+    let height = |p: Vec2| map.environment.height(p).map(|h| p.z(h));
+
+    let projects = vec![
+        MapProject {
+            pos: src.pos,
+            kind: ProjectKind::Inter(r.src),
+        },
+        MapProject::ground(height(from)?),
+        MapProject::ground(height(to)?),
+        MapProject {
+            pos: dst.pos,
+            kind: ProjectKind::Inter(r.dst),
+        },
+    ];
+    Some((projects, vec![(0, 1, None), (1, 2, elbow), (2, 3, None)]))
+}
+
+/// A grid between the first corner and the cursor, its border snapped to the existing roads
+fn grid_projects(
+    map: &Map,
+    start: MapProject,
+    mouse: Vec2,
+    block: Vec2,
+    patwidth: f32,
+) -> Option<Layout> {
+    let (nodes, edges) = grid_layout(start.pos.xy(), mouse, block);
+
+    let projects = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, &p)| {
+            let ground = MapProject::ground(p.z(map.environment.height(p).unwrap_or(start.pos.z)));
+            if !grid_on_border(&nodes, i) {
+                return ground;
+            }
+            map.project(
+                ground.pos,
+                patwidth,
+                ProjectFilter::INTER | ProjectFilter::ROAD,
+            )
+        })
+        .collect();
+
+    Some((
+        projects,
+        edges.into_iter().map(|(a, b)| (a, b, None)).collect(),
+    ))
+}
+
+/// Draws the planned links and keeps the ones that can be built.
+/// Links crossing existing roads or leaving the map are dropped,
+/// too steep ones are highlighted and only kept when bridging is allowed.
+fn plan_links(
+    map: &Map,
+    immdraw: &mut ImmediateDraw,
+    projects: &[MapProject],
+    links: &[(usize, usize, Option<Vec2>)],
+    builder: &LanePatternBuilder,
+    bridge_steep: bool,
+) -> Vec<(usize, usize, Option<Vec2>, LanePattern)> {
+    let pat = builder.build();
+    let patwidth = builder.width();
+    let mut kept = Vec::with_capacity(links.len());
+
+    for &(a, b, elbow) in links {
+        let (from, to) = (projects[a], projects[b]);
+        if !compatible(map, from, to) {
+            continue;
+        }
+
+        let segment = match elbow {
+            Some(elbow) => RoadSegmentKind::from_elbow(from.pos.xy(), to.pos.xy(), elbow),
+            None => RoadSegmentKind::Straight,
+        };
+        let (points, err) =
+            Road::generate_points(from.pos, to.pos, segment, builder.rail, &map.environment);
+
+        let shape = BoldLine::new(
+            PolyLine::new(points.iter().map(|p| p.xy()).collect()),
+            patwidth * 0.5,
+        );
+        if check_intersect(
+            map,
+            &ShapeEnum::BoldLine(shape),
+            (from.pos.z + to.pos.z) / 2.0,
+            from.kind,
+            to.kind,
+        ) {
+            continue;
+        }
+
+        let col = match err {
+            Some(PointGenerateError::OutsideOfMap) => continue,
+            Some(PointGenerateError::TooSteep) => {
+                if bridge_steep {
+                    kept.push((a, b, elbow, pat.clone()));
+                }
+                palette().danger
+            }
+            None => {
+                kept.push((a, b, elbow, pat.clone()));
+                palette().primary
+            }
+        };
+
+        immdraw.circle(points.first(), patwidth * 0.5).color(col);
+        immdraw.circle(points.last(), patwidth * 0.5).color(col);
+        immdraw
+            .polyline(points.into_vec(), patwidth, false)
+            .color(col);
+    }
+
+    kept
+}
@@ -2,7 +2,7 @@ use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::{InspectedBuilding, InspectedEntity, Tool};
 use crate::uiworld::UiWorld;
 use geom::Vec2;
-use simulation::map::ProjectFilter;
+use simulation::map::{BuildingID, ProjectFilter};
 use simulation::{AnyEntity, Simulation};
 
 pub fn select_radius(id: AnyEntity) -> f32 {
@@ -11,11 +11,37 @@ pub fn select_radius(id: AnyEntity) -> f32 {
         AnyEntity::TrainID(_) => 10.0,
         AnyEntity::WagonID(_) => 10.0,
         AnyEntity::FreightStationID(_) => 0.0,
+        AnyEntity::DockID(_) => 0.0,
+        AnyEntity::WarehouseID(_) => 0.0,
         AnyEntity::CompanyID(_) => 0.0,
         AnyEntity::HumanID(_) => 3.0,
     }
 }
 
+/// The closest selectable entity under the position, if any
+pub fn pick_entity(sim: &Simulation, pos: Vec2) -> Option<AnyEntity> {
+    let mut best = None;
+    let mut best_dist2 = f32::INFINITY;
+    for (id, epos) in sim.world().query_selectable_pos() {
+        let dist2 = (epos - pos).mag2();
+        let rad = select_radius(id);
+        if dist2 >= rad * rad || dist2 >= best_dist2 {
+            continue;
+        }
+        best_dist2 = dist2;
+        best = Some(id);
+    }
+    best
+}
+
+/// The building under the position, if any
+pub fn pick_building(sim: &Simulation, pos: Vec2) -> Option<BuildingID> {
+    sim.map()
+        .spatial_map()
+        .query(pos, ProjectFilter::BUILDING)
+        .find_map(|x| x.as_building())
+}
+
 /// Selectable allows to select entities by clicking on them
 pub fn selectable(sim: &Simulation, uiworld: &UiWorld) {
     profiling::scope!("gui::selectable");
@@ -30,21 +56,11 @@ pub fn selectable(sim: &Simulation, uiworld: &UiWorld) {
     {
         let unproj = unwrap_ret!(inp.unprojected);
 
-        let w = sim.world();
-
-        inspected.dist2 = f32::INFINITY;
-        inspected.e = None;
-
-        w.query_selectable_pos()
-            .for_each(|(id, pos): (AnyEntity, Vec2)| {
-                let dist2 = (pos - unproj.xy()).mag2();
-                let rad = select_radius(id);
-                if dist2 >= rad * rad || dist2 >= inspected.dist2 {
-                    return;
-                }
-                inspected.dist2 = dist2;
-                inspected.e = Some(id);
-            });
+        inspected.e = pick_entity(sim, unproj.xy());
+        inspected.dist2 = inspected
+            .e
+            .and_then(|e| sim.pos_any(e))
+            .map_or(f32::INFINITY, |pos| (pos.xy() - unproj.xy()).mag2());
     }
 
     if inp.just_act.contains(&InputAction::Select)
@@ -54,11 +70,7 @@ pub fn selectable(sim: &Simulation, uiworld: &UiWorld) {
         inspected_b.e = None;
         if inspected.e.is_none() {
             let unproj = unwrap_ret!(inp.unprojected);
-            let map = sim.map();
-            inspected_b.e = map
-                .spatial_map()
-                .query(unproj.xy(), ProjectFilter::BUILDING)
-                .find_map(|x| x.as_building());
+            inspected_b.e = pick_building(sim, unproj.xy());
         }
     }
     inspected.dontclear = false;
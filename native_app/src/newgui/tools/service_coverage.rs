@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use prototypes::ServiceKind;
+use simulation::map::IntersectionID;
+use simulation::transportation::service_fleet::{coverage, ServiceFleets};
+use simulation::Simulation;
+
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+
+/// Seconds between two computations of the coverage
+const REFRESH_SECONDS: f32 = 1.0;
+
+/// Whether the roads are colored by how fast the vehicles of a service get there
+pub struct ServiceCoverageView {
+    pub service: Option<ServiceKind>,
+    /// Seconds the vehicles can take to get to a call
+    pub response_time: f32,
+    times: BTreeMap<IntersectionID, f32>,
+    computed_at: Option<Instant>,
+}
+
+impl Default for ServiceCoverageView {
+    fn default() -> Self {
+        Self {
+            service: None,
+            response_time: 180.0,
+            times: BTreeMap::new(),
+            computed_at: None,
+        }
+    }
+}
+
+/// Draws the roads reached within the response time in green, up to twice as long in yellow
+/// and the others in red
+pub fn service_coverage(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::service_coverage");
+    let mut view = uiworld.write::<ServiceCoverageView>();
+    let Some(service) = view.service else {
+        view.computed_at = None;
+        return;
+    };
+
+    let map = sim.map();
+    if view
+        .computed_at
+        .map_or(true, |t| t.elapsed().as_secs_f32() > REFRESH_SECONDS)
+    {
+        view.times = coverage(&map, &sim.read::<ServiceFleets>(), service);
+        view.computed_at = Some(Instant::now());
+    }
+
+    let colors = palette();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for road in map.roads().values() {
+        let time = [road.src, road.dst]
+            .iter()
+            .filter_map(|i| view.times.get(i))
+            .copied()
+            .reduce(f32::min);
+        let col = match time {
+            Some(t) if t <= view.response_time => colors.ramp(0.0),
+            Some(t) if t <= view.response_time * 2.0 => colors.ramp(0.5),
+            _ => colors.ramp(1.0),
+        };
+        let points: Vec<_> = road.points().iter().map(|p| p.up(0.5)).collect();
+        draw.polyline(points, road.width * 0.5, false).color(col);
+    }
+}
@@ -0,0 +1,322 @@
+use geom::{Color, Degrees, Radians, Vec2, Vec3, OBB};
+use serde::{Deserialize, Serialize};
+
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+
+/// Angle increments proposed in the tool options
+pub const ANGLE_INCREMENTS: [f32; 4] = [15.0, 30.0, 45.0, 90.0];
+
+/// Shared snapping options used by every construction tool
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapSettings {
+    pub enabled: bool,
+    pub angle_increment: Degrees,
+    /// Distance between the center lines of two parallel roads
+    pub parallel_offset: f32,
+    /// Radius around the cursor in which snap targets are considered
+    pub tolerance: f32,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            angle_increment: Degrees(45.0),
+            parallel_offset: 30.0,
+            tolerance: 4.0,
+        }
+    }
+}
+
+/// Visual hint explaining why the cursor snapped somewhere
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SnapGuide {
+    Point(Vec2),
+    Line(Vec2, Vec2),
+}
+
+/// Returns the candidate closest to pos if it is within radius
+pub fn snap_to_point(
+    pos: Vec2,
+    candidates: impl IntoIterator<Item = Vec2>,
+    radius: f32,
+) -> Option<Vec2> {
+    candidates
+        .into_iter()
+        .map(|p| (p, p.distance2(pos)))
+        .filter(|(_, d)| *d <= radius * radius)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(p, _)| p)
+}
+
+/// Constrains the direction from origin to pos to a multiple of increment relative to reference.
+/// The point is projected on the snapped direction so the cursor stays close.
+pub fn snap_to_angle(origin: Vec2, pos: Vec2, reference: Vec2, increment: Radians) -> Vec2 {
+    let Some(reference) = reference.try_normalize() else {
+        return pos;
+    };
+    let Some(dir) = (pos - origin).try_normalize() else {
+        return pos;
+    };
+    if increment.0 <= f32::EPSILON {
+        return pos;
+    }
+
+    let ang = reference.angle(dir);
+    let snapped = (ang / increment.0).round() * increment.0;
+    let snapped_dir = reference.rotated_by_angle(Radians(snapped));
+
+    origin + snapped_dir * (pos - origin).dot(snapped_dir).max(0.0)
+}
+
+/// Snaps pos onto the line parallel to the reference at the given offset, on the side of pos.
+/// `proj` is the closest point on the reference and `dir` its tangent at that point.
+pub fn snap_parallel(
+    pos: Vec2,
+    proj: Vec2,
+    dir: Vec2,
+    offset: f32,
+    tolerance: f32,
+) -> Option<(Vec2, SnapGuide)> {
+    let dir = dir.try_normalize()?;
+    let normal = -dir.perpendicular();
+    let side_dist = (pos - proj).dot(normal);
+
+    if (side_dist.abs() - offset).abs() > tolerance {
+        return None;
+    }
+
+    let along = (pos - proj).dot(dir);
+    let base = proj + normal * offset * side_dist.signum();
+    let snapped = base + dir * along;
+
+    let guide_len = offset.max(20.0) * 2.0;
+    Some((
+        snapped,
+        SnapGuide::Line(snapped - dir * guide_len, snapped + dir * guide_len),
+    ))
+}
+
+/// Center, direction of the width axis, width and height of an OBB as built by [`OBB::new`]
+fn obb_frame(obb: &OBB) -> Option<(Vec2, Vec2, f32, f32)> {
+    let [a, b] = obb.axis();
+    let (dir, w) = b.dir_dist()?;
+    Some((obb.center(), dir, w, a.mag()))
+}
+
+/// Aligns obb so that it sits flush against neighbor, with edges lined up when close enough.
+pub fn align_to_neighbor(
+    obb: &OBB,
+    neighbor: &OBB,
+    tolerance: f32,
+) -> Option<(OBB, Vec<SnapGuide>)> {
+    let (c, dir, w, h) = obb_frame(obb)?;
+    let (nc, ndir, nw, nh) = obb_frame(neighbor)?;
+
+    let u = ndir;
+    let v = ndir.perpendicular();
+
+    let new_dir = [u, v, -u, -v]
+        .into_iter()
+        .max_by(|a, b| a.dot(dir).total_cmp(&b.dot(dir)))?;
+
+    // half extents expressed in the neighbor frame
+    let (ou, ov) = if new_dir.dot(u).abs() > 0.5 {
+        (w * 0.5, h * 0.5)
+    } else {
+        (h * 0.5, w * 0.5)
+    };
+    let (nu, nv) = (nw * 0.5, nh * 0.5);
+
+    let local = c - nc;
+    let lu = local.dot(u);
+    let lv = local.dot(v);
+
+    let gap_u = lu.abs() - (ou + nu);
+    let gap_v = lv.abs() - (ov + nv);
+
+    let align_edge = |l: f32, ours: f32, theirs: f32| -> Option<f32> {
+        [theirs - ours, ours - theirs, 0.0]
+            .into_iter()
+            .map(|t| (t, (t - l).abs()))
+            .filter(|(_, d)| *d <= tolerance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(t, _)| t)
+    };
+
+    let mut guides = Vec::with_capacity(2);
+
+    let (new_lu, new_lv) = if gap_u >= gap_v {
+        if gap_u.abs() > tolerance || lv.abs() >= ov + nv {
+            return None;
+        }
+        let s = lu.signum();
+        let new_lu = s * (ou + nu);
+        let new_lv = align_edge(lv, ov, nv).unwrap_or(lv);
+
+        let ext = nv.max(new_lv.abs() + ov);
+        guides.push(SnapGuide::Line(
+            nc + u * s * nu - v * ext,
+            nc + u * s * nu + v * ext,
+        ));
+        (new_lu, new_lv)
+    } else {
+        if gap_v.abs() > tolerance || lu.abs() >= ou + nu {
+            return None;
+        }
+        let s = lv.signum();
+        let new_lv = s * (ov + nv);
+        let new_lu = align_edge(lu, ou, nu).unwrap_or(lu);
+
+        let ext = nu.max(new_lu.abs() + ou);
+        guides.push(SnapGuide::Line(
+            nc + v * s * nv - u * ext,
+            nc + v * s * nv + u * ext,
+        ));
+        (new_lu, new_lv)
+    };
+
+    let center = nc + u * new_lu + v * new_lv;
+    guides.push(SnapGuide::Point(center));
+
+    Some((OBB::new(center, new_dir, w, h), guides))
+}
+
+/// Tries to align obb with every neighbor and keeps the one that moves it the least
+pub fn align_to_neighbors(
+    obb: &OBB,
+    neighbors: impl IntoIterator<Item = OBB>,
+    tolerance: f32,
+) -> Option<(OBB, Vec<SnapGuide>)> {
+    let c = obb.center();
+    neighbors
+        .into_iter()
+        .filter_map(|n| align_to_neighbor(obb, &n, tolerance))
+        .min_by(|a, b| {
+            a.0.center()
+                .distance2(c)
+                .total_cmp(&b.0.center().distance2(c))
+        })
+}
+
+/// Draws the guides as dashed lines and highlighted points
+pub fn draw_guides(immdraw: &mut ImmediateDraw, guides: &[SnapGuide], z: f32) {
+    let col = palette().primary.a(0.8);
+    for guide in guides {
+        match *guide {
+            SnapGuide::Point(p) => {
+                immdraw.stroke_circle(p.z(z + 0.5), 2.0, 0.4).color(col);
+                immdraw.circle(p.z(z + 0.5), 0.6).color(col);
+            }
+            SnapGuide::Line(from, to) => {
+                dashed_line(immdraw, from.z(z + 0.4), to.z(z + 0.4), 0.3, col);
+            }
+        }
+    }
+}
+
+fn dashed_line(immdraw: &mut ImmediateDraw, from: Vec3, to: Vec3, thickness: f32, col: Color) {
+    const DASH: f32 = 2.0;
+    let Some((dir, dist)) = (to - from).dir_dist() else {
+        return;
+    };
+    let mut t = 0.0;
+    while t < dist {
+        let end = (t + DASH).min(dist);
+        immdraw
+            .line(from + dir * t, from + dir * end, thickness)
+            .color(col);
+        t += DASH * 2.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::vec2;
+
+    #[test]
+    fn point_snaps_to_closest_in_radius() {
+        let pts = [vec2(0.0, 0.0), vec2(5.0, 0.0), vec2(100.0, 0.0)];
+        assert_eq!(
+            snap_to_point(vec2(4.0, 0.0), pts, 3.0),
+            Some(vec2(5.0, 0.0))
+        );
+        assert_eq!(snap_to_point(vec2(50.0, 0.0), pts, 3.0), None);
+    }
+
+    #[test]
+    fn angle_snaps_to_increment() {
+        let inc = Radians::from(Degrees(45.0));
+        let p = snap_to_angle(Vec2::ZERO, vec2(10.0, 1.0), Vec2::X, inc);
+        assert!(p.is_close(vec2(10.0, 0.0), 0.01), "{:?}", p);
+
+        let p = snap_to_angle(Vec2::ZERO, vec2(10.0, 8.0), Vec2::X, inc);
+        assert!((p.x - p.y).abs() < 0.01, "{:?}", p);
+
+        let inc = Radians::from(Degrees(90.0));
+        let p = snap_to_angle(vec2(5.0, 5.0), vec2(5.5, 15.0), Vec2::X, inc);
+        assert!(p.is_close(vec2(5.0, 15.0), 0.01), "{:?}", p);
+    }
+
+    #[test]
+    fn angle_is_relative_to_reference() {
+        let inc = Radians::from(Degrees(90.0));
+        let reference = vec2(1.0, 1.0);
+        let p = snap_to_angle(Vec2::ZERO, vec2(10.0, 9.0), reference, inc);
+        assert!((p.x - p.y).abs() < 0.01, "{:?}", p);
+    }
+
+    #[test]
+    fn parallel_snaps_on_both_sides() {
+        let (p, _) = snap_parallel(vec2(5.0, 11.0), vec2(5.0, 0.0), Vec2::X, 10.0, 2.0).unwrap();
+        assert!(p.is_close(vec2(5.0, 10.0), 0.01), "{:?}", p);
+
+        let (p, _) = snap_parallel(vec2(5.0, -9.5), vec2(5.0, 0.0), Vec2::X, 10.0, 2.0).unwrap();
+        assert!(p.is_close(vec2(5.0, -10.0), 0.01), "{:?}", p);
+
+        assert!(snap_parallel(vec2(5.0, 15.0), vec2(5.0, 0.0), Vec2::X, 10.0, 2.0).is_none());
+    }
+
+    #[test]
+    fn building_aligns_flush_with_neighbor() {
+        let neighbor = OBB::new(Vec2::ZERO, Vec2::X, 10.0, 10.0);
+        let obb = OBB::new(vec2(10.8, 0.5), Degrees(5.0).vec2(), 10.0, 10.0);
+
+        let (snapped, guides) = align_to_neighbor(&obb, &neighbor, 2.0).unwrap();
+        assert!(
+            snapped.center().is_close(vec2(10.0, 0.0), 0.01),
+            "{:?}",
+            snapped
+        );
+        assert!(snapped.axis()[1].normalize().is_close(Vec2::X, 0.01));
+        assert!(!guides.is_empty());
+    }
+
+    #[test]
+    fn building_far_from_neighbor_does_not_snap() {
+        let neighbor = OBB::new(Vec2::ZERO, Vec2::X, 10.0, 10.0);
+        let obb = OBB::new(vec2(30.0, 0.0), Vec2::X, 10.0, 10.0);
+        assert!(align_to_neighbor(&obb, &neighbor, 2.0).is_none());
+
+        // diagonal, not adjacent to any edge
+        let obb = OBB::new(vec2(10.5, 10.5), Vec2::X, 10.0, 10.0);
+        assert!(align_to_neighbor(&obb, &neighbor, 2.0).is_none());
+    }
+
+    #[test]
+    fn rotated_building_swaps_extents() {
+        let neighbor = OBB::new(Vec2::ZERO, Vec2::X, 10.0, 4.0);
+        let obb = OBB::new(vec2(0.0, 6.5), Vec2::Y, 6.0, 2.0);
+
+        let (snapped, _) = align_to_neighbor(&obb, &neighbor, 2.0).unwrap();
+        // the building keeps its 6x2 size but is now flush with the top edge
+        assert!(
+            snapped.center().is_close(vec2(0.0, 5.0), 0.01),
+            "{:?}",
+            snapped
+        );
+    }
+}
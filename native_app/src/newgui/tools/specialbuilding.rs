@@ -1,12 +1,17 @@
 use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::snapping::{align_to_neighbors, draw_guides, SnapSettings};
 use crate::newgui::{ErrorTooltip, InspectedBuilding, PotentialCommands, Tool};
 use crate::rendering::immediate::{ImmediateDraw, ImmediateSound};
+use crate::rendering::palette;
 use crate::uiworld::UiWorld;
 use engine::AudioKind;
 use geom::{Degrees, Intersect, OBB};
 use ordered_float::OrderedFloat;
-use prototypes::{RenderAsset, Size2D};
-use simulation::map::{ProjectFilter, ProjectKind, RoadID};
+use prototypes::{DepositID, RenderAsset, Size2D};
+use simulation::map::{
+    Building, Entrance, Map, ProjectFilter, ProjectKind, RestrictedAction, RoadID,
+};
+use simulation::transportation::waterway::dock_mooring;
 use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 use std::borrow::Cow;
@@ -21,6 +26,10 @@ pub struct SpecialBuildKind {
     pub size: Size2D,
     pub asset: RenderAsset,
     pub road_snap: bool,
+    /// The building needs navigable water next to it, like docks
+    pub shore: bool,
+    /// The building must be built on a deposit of that kind, like mines
+    pub deposit: Option<DepositID>,
 }
 
 #[derive(Default)]
@@ -37,10 +46,11 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &UiWorld) {
     let mut state = uiworld.write::<SpecialBuildingResource>();
     let tool = *uiworld.read::<Tool>();
     let inp = uiworld.read::<InputMap>();
-    let mut draw = uiworld.write::<ImmediateDraw>();
+    let mut immdraw = uiworld.write::<ImmediateDraw>();
     let mut sound = uiworld.write::<ImmediateSound>();
 
     let map = sim.map();
+    let snap_settings = *uiworld.read::<SnapSettings>();
 
     let commands = &mut *uiworld.commands();
 
@@ -74,6 +84,8 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &UiWorld) {
         ref asset,
         ref make,
         road_snap,
+        shore,
+        deposit,
     } = *unwrap_or!(&state.opt, return);
 
     let mpos = unwrap_ret!(inp.unprojected);
@@ -84,28 +96,31 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &UiWorld) {
 
     let mut draw = |obb: OBB, red| {
         let col = if red {
-            simulation::colors().gui_danger.adjust_luminosity(1.3)
+            palette().danger.adjust_luminosity(1.3)
         } else {
-            simulation::colors().gui_primary.adjust_luminosity(1.5)
+            palette().primary.adjust_luminosity(1.5)
         };
 
         match asset {
             RenderAsset::Mesh { path } => {
-                draw.mesh(
-                    path.to_string_lossy().to_string(),
-                    obb.center().z(mpos.z),
-                    obb.axis()[0].normalize().z0(),
-                )
-                .color(col);
+                immdraw
+                    .mesh(
+                        path.to_string_lossy().to_string(),
+                        obb.center().z(mpos.z),
+                        obb.axis()[0].normalize().z0(),
+                    )
+                    .color(col);
             }
             RenderAsset::Sprite { path } => {
-                draw.textured_obb(obb, path.to_string_lossy().to_string(), mpos.z + 0.1)
+                immdraw
+                    .textured_obb(obb, path.to_string_lossy().to_string(), mpos.z + 0.1)
                     .color(col);
             }
         }
     };
 
     let mut rid = None;
+    let mut road_side = None;
     let mut obb = hover_obb;
 
     if road_snap {
@@ -161,6 +176,50 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &UiWorld) {
         }
 
         rid = Some(closest_road.id);
+        road_side = Some(side);
+    }
+
+    let mut guides = Vec::new();
+    if snap_settings.enabled && !inp.act.contains(&InputAction::NoSnapping) {
+        let tol = snap_settings.tolerance;
+        let mut targets: Vec<OBB> = map
+            .spatial_map()
+            .query_around(obb.center(), half_diag * 2.0 + tol, ProjectFilter::BUILDING)
+            .filter_map(|x| match x {
+                ProjectKind::Building(id) => map.buildings().get(id).map(|b| b.obb),
+                _ => None,
+            })
+            .collect();
+
+        // buildings that are not attached to a road can still be aligned with its edge
+        if !road_snap {
+            let closest_road = map
+                .spatial_map()
+                .query_around(mpos.xy(), half_diag + tol, ProjectFilter::ROAD)
+                .filter_map(|x| match x {
+                    ProjectKind::Road(id) => Some(&roads[id]),
+                    _ => None,
+                })
+                .min_by_key(move |p| OrderedFloat(p.points().project_dist2(mpos)));
+            if let Some(road) = closest_road {
+                let (proj, _, dir) = road.points().project_segment_dir(mpos);
+                targets.push(OBB::new(proj.xy(), dir.xy(), half_diag * 4.0, road.width));
+            }
+        }
+
+        if let Some((snapped, g)) = align_to_neighbors(&obb, targets, tol) {
+            // a building attached to a road may only slide along it
+            let keeps_road = road_side
+                .map(|side| {
+                    (snapped.center() - obb.center()).dot(side).abs() < 0.01
+                        && snapped.axis()[1].normalize().dot(side) > 0.99
+                })
+                .unwrap_or(true);
+            if keeps_road {
+                obb = snapped;
+                guides = g;
+            }
+        }
     }
 
     if map
@@ -181,15 +240,63 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &UiWorld) {
         *uiworld.write::<ErrorTooltip>() =
             ErrorTooltip::new(Cow::Borrowed("Intersecting with something"));
         draw(obb, true);
+        draw_guides(&mut immdraw, &guides, mpos.z);
         return;
     }
 
-    draw(obb, false);
+    if shore && dock_mooring(&map.environment, &obb).is_none() {
+        *uiworld.write::<ErrorTooltip>() =
+            ErrorTooltip::new(Cow::Borrowed("Needs navigable water nearby"));
+        draw(obb, true);
+        draw_guides(&mut immdraw, &guides, mpos.z);
+        return;
+    }
+
+    if let Some(deposit) = deposit {
+        if !map.can_extract(&obb, deposit) {
+            *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(format!(
+                "Must be built on a {} deposit",
+                deposit.prototype().label
+            ));
+            draw(obb, true);
+            draw_guides(&mut immdraw, &guides, mpos.z);
+            return;
+        }
+    }
+
+    if let Some(r) = map.restriction_on(&obb, RestrictedAction::Build) {
+        *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(r.reason());
+        draw(obb, true);
+        draw_guides(&mut immdraw, &guides, mpos.z);
+        return;
+    }
 
     let cmds: Vec<WorldCommand> = make(&SpecialBuildArgs {
         obb,
         connected_road: rid,
     });
+
+    let defs = cmds
+        .iter()
+        .find_map(|c| match c {
+            WorldCommand::MapBuildSpecialBuilding { kind, .. } => Some(kind.entrances()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let entrances = Building::gen_entrances(&map.environment, &obb, defs);
+
+    if let Err(e) = map.check_entrances(&obb, defs) {
+        *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(e.reason());
+        draw(obb, true);
+        draw_entrances(&mut immdraw, &map, &entrances, mpos.z);
+        draw_guides(&mut immdraw, &guides, mpos.z);
+        return;
+    }
+
+    draw(obb, false);
+    draw_entrances(&mut immdraw, &map, &entrances, mpos.z);
+    draw_guides(&mut immdraw, &guides, mpos.z);
+
     if inp.act.contains(&InputAction::Select) {
         commands.extend(cmds);
         sound.play("road_lay", AudioKind::Ui);
@@ -198,3 +305,15 @@ pub fn specialbuilding(sim: &Simulation, uiworld: &UiWorld) {
         uiworld.write::<PotentialCommands>().set(last.clone());
     }
 }
+
+/// Marks the entrances of the ghost, red when they don't reach any way
+fn draw_entrances(immdraw: &mut ImmediateDraw, map: &Map, entrances: &[Entrance], z: f32) {
+    for e in entrances {
+        let col = if map.entrance_lane(e).is_some() {
+            palette().primary
+        } else {
+            palette().danger
+        };
+        immdraw.circle(e.pos.xy().z(z + 0.3), 1.5).color(col);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::{InspectedBuilding, Tool};
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+use geom::{Color, Vec3};
+use simulation::economy::{ChainLink, TradeLedger};
+use simulation::map::{BuildingID, Map};
+use simulation::Simulation;
+
+/// Radius of the circle drawn at the other end of an arrow, clicking it inspects that building
+pub const ENDPOINT_RADIUS: f32 = 8.0;
+/// Height of the arrows above the buildings
+const ARROW_HEIGHT: f32 = 10.0;
+
+/// Whether the inspected building shows its suppliers and customers on the map
+#[derive(Default)]
+pub struct SupplyChainView {
+    pub enabled: bool,
+}
+
+/// One arrow of the supply chain, between the inspected building and one of its partners
+pub struct ChainArrow {
+    pub link: ChainLink,
+    /// Whether the partner is a supplier, the arrow then points to the inspected building
+    pub incoming: bool,
+    pub me: Vec3,
+    pub partner: Vec3,
+}
+
+impl ChainArrow {
+    pub fn from(&self) -> Vec3 {
+        if self.incoming {
+            self.partner
+        } else {
+            self.me
+        }
+    }
+
+    pub fn to(&self) -> Vec3 {
+        if self.incoming {
+            self.me
+        } else {
+            self.partner
+        }
+    }
+}
+
+/// The arrows between the building and its suppliers and customers of the last game day
+pub fn chain_arrows(sim: &Simulation, building: BuildingID) -> Vec<ChainArrow> {
+    let map = sim.map();
+    let ledger = sim.read::<TradeLedger>();
+    let (Some(me), Some(ledger)) = (anchor(&map, building), ledger.get(building)) else {
+        return vec![];
+    };
+
+    let suppliers = ledger.suppliers().into_iter().map(|link| (link, true));
+    let customers = ledger.customers().into_iter().map(|link| (link, false));
+
+    suppliers
+        .chain(customers)
+        .filter_map(|(link, incoming)| {
+            Some(ChainArrow {
+                link,
+                incoming,
+                me,
+                partner: anchor(&map, link.partner)?,
+            })
+        })
+        .collect()
+}
+
+fn anchor(map: &Map, building: BuildingID) -> Option<Vec3> {
+    let b = map.buildings().get(building)?;
+    Some(b.obb.center().z(b.height + ARROW_HEIGHT))
+}
+
+/// Draws the supply chain of the inspected building, clicking the other end of an arrow inspects it
+pub fn supply_chain(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::supply_chain");
+    if !uiworld.read::<SupplyChainView>().enabled {
+        return;
+    }
+    let Some(inspected) = uiworld.read::<InspectedBuilding>().e else {
+        return;
+    };
+
+    let arrows = chain_arrows(sim, inspected);
+    if arrows.is_empty() {
+        return;
+    }
+
+    let inp = uiworld.read::<InputMap>();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    let colors = palette();
+
+    let mut clicked = None;
+    for arrow in &arrows {
+        let col = if arrow.incoming {
+            colors.primary
+        } else {
+            colors.success
+        };
+        let (from, to) = (arrow.from(), arrow.to());
+        let Some(dir) = (to - from).xy().try_normalize() else {
+            continue;
+        };
+        let side = dir.perpendicular();
+        let thickness = 0.5 + (arrow.link.qty as f32).sqrt() * 0.2;
+
+        let head = to - (dir * ENDPOINT_RADIUS).z0();
+        let wing = |s: f32| head - ((dir - side * s * 0.6) * 4.0).z0();
+        draw.line(from, head, thickness).color(col);
+        draw.line(wing(1.0), head, thickness).color(col);
+        draw.line(wing(-1.0), head, thickness).color(col);
+
+        let mut endpoint_col = Color::WHITE;
+        if let Some(unproj) = inp.unprojected {
+            if unproj.xy().is_close(arrow.partner.xy(), ENDPOINT_RADIUS) {
+                endpoint_col = col;
+                if inp.just_act.contains(&InputAction::Select) {
+                    clicked = Some(arrow.link.partner);
+                }
+            }
+        }
+        draw.stroke_circle(arrow.partner, ENDPOINT_RADIUS, 0.5)
+            .color(endpoint_col);
+    }
+
+    if let Some(partner) = clicked {
+        if matches!(*uiworld.read::<Tool>(), Tool::Hand) {
+            let mut inspected_b = uiworld.write::<InspectedBuilding>();
+            inspected_b.e = Some(partner);
+            inspected_b.dontclear = true;
+        }
+    }
+}
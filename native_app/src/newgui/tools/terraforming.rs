@@ -1,11 +1,19 @@
-use geom::{Vec2, Vec3, OBB};
-use simulation::map::TerraformKind;
+use std::collections::BTreeSet;
+
+use geom::{Circle, Vec2, Vec3, AABB, OBB};
+use simulation::map::{
+    Map, ProjectFilter, ProjectKind, RestrictedAction, TerraformKind, WATER_CELL_SIZE,
+    WATER_LEVEL_SPEED,
+};
+use simulation::rules::GameRules;
 use simulation::world_command::WorldCommand;
 use simulation::Simulation;
 
 use crate::inputmap::{InputAction, InputMap};
-use crate::newgui::Tool;
+use crate::newgui::restrictions::RestrictionPaintResource;
+use crate::newgui::{ErrorTooltip, Tool};
 use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
 use crate::uiworld::UiWorld;
 
 pub struct TerraformingResource {
@@ -15,6 +23,9 @@ pub struct TerraformingResource {
     level: Option<f32>,
     slope_start: Option<Vec3>,
     slope_end: Option<Vec3>,
+    /// The player was told the stroke floods buildings, clicking again goes ahead
+    flood_warned: bool,
+    flood_confirmed: bool,
 }
 
 /// Lot brush tool
@@ -25,10 +36,12 @@ pub fn terraforming(sim: &Simulation, uiworld: &UiWorld) {
     let tool = *uiworld.read::<Tool>();
     let inp = uiworld.read::<InputMap>();
     let mut draw = uiworld.write::<ImmediateDraw>();
-    let _map = sim.map();
+    let map = sim.map();
     let commands = &mut *uiworld.commands();
+    let painting_restrictions =
+        uiworld.read::<RestrictionPaintResource>().active && sim.read::<GameRules>().sandbox;
 
-    if !matches!(tool, Tool::Terraforming) {
+    if !matches!(tool, Tool::Terraforming) || painting_restrictions {
         res.slope_start = None;
         res.slope_end = None;
         return;
@@ -85,9 +98,66 @@ pub fn terraforming(sim: &Simulation, uiworld: &UiWorld) {
             }
         }
         TerraformKind::Erode => {}
+        TerraformKind::Plant => {}
+        TerraformKind::WaterLevel => {
+            if inp.act.contains(&InputAction::SecondarySelect) {
+                amount_multiplier = -1.0;
+            }
+        }
+    }
+
+    let restricted = (res.kind != TerraformKind::Plant)
+        .then(|| {
+            map.restriction_on(
+                &Circle::new(mpos.xy(), res.radius),
+                RestrictedAction::Terraform,
+            )
+        })
+        .flatten();
+    if let Some(r) = restricted {
+        *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(r.reason());
+    }
+
+    let acting =
+        inp.act.contains(&InputAction::Select) || inp.act.contains(&InputAction::SecondarySelect);
+    let flooding = flood_preview(&map, &res, mpos, amount_multiplier);
+    let flooded_buildings = flooded_buildings(&map, &flooding);
+    if flooded_buildings == 0 {
+        res.flood_warned = false;
+    }
+    if !acting {
+        res.flood_confirmed = false;
+    } else if res.flood_warned
+        && (inp.just_act.contains(&InputAction::Select)
+            || inp.just_act.contains(&InputAction::SecondarySelect))
+    {
+        res.flood_confirmed = true;
+    }
+    let flood_blocked = flooded_buildings > 0 && !res.flood_confirmed;
+    if flood_blocked && restricted.is_none() {
+        *uiworld.write::<ErrorTooltip>() = ErrorTooltip::new(format!(
+            "This would flood {} buildings, click again to go ahead",
+            flooded_buildings
+        ));
+        if acting {
+            res.flood_warned = true;
+        }
+    }
+
+    for &(pos, level) in &flooding {
+        let col = if flooded_buildings > 0 {
+            palette().danger
+        } else {
+            palette().primary
+        };
+        draw.aabb(
+            AABB::centered(pos, Vec2::splat(WATER_CELL_SIZE)),
+            level + 0.2,
+        )
+        .color(col.a(0.3));
     }
 
-    if inp.act.contains(&InputAction::Select) || inp.act.contains(&InputAction::SecondarySelect) {
+    if acting && restricted.is_none() && !flood_blocked {
         if res.kind == TerraformKind::Level && res.level.is_none() {
             return;
         }
@@ -119,7 +189,7 @@ pub fn terraforming(sim: &Simulation, uiworld: &UiWorld) {
                     ),
                     res.level.unwrap_or(mpos.z) - 0.5,
                 )
-                .color(simulation::colors().gui_primary.a(0.2));
+                .color(palette().primary.a(0.2));
             }
         }
         TerraformKind::Slope => {
@@ -130,10 +200,73 @@ pub fn terraforming(sim: &Simulation, uiworld: &UiWorld) {
             } else {
                 draw.line(res.slope_start.unwrap(), res.slope_end.unwrap(), res.radius)
             }
-            .color(simulation::colors().gui_primary.a(0.2));
+            .color(palette().primary.a(0.2));
         }
         TerraformKind::Erode => {}
+        TerraformKind::Plant => {
+            draw.circle(mpos, res.radius)
+                .color(palette().primary.a(0.2));
+        }
+        TerraformKind::WaterLevel => {}
+    }
+}
+
+/// The land one second of the stroke would flood, with the level of the water.
+/// Raising the ground only drains, so only the strokes that lower it or raise the water flood.
+fn flood_preview(
+    map: &Map,
+    res: &TerraformingResource,
+    mpos: Vec3,
+    amount_multiplier: f32,
+) -> Vec<(Vec2, f32)> {
+    let center = mpos.xy();
+    let radius = res.radius;
+    let area = AABB::centered(center, Vec2::splat(radius * 2.0));
+    // same falloff as the brush of the simulation
+    let brush = |p: Vec2| {
+        let dist = p.distance(center) / radius;
+        if dist >= 1.0 {
+            return 0.0;
+        }
+        (-1.0 / (1.0 - dist * dist)).exp()
+    };
+
+    match res.kind {
+        TerraformKind::Elevation if amount_multiplier < 0.0 => map
+            .environment
+            .flood_preview(area, |p, h| h - res.amount * brush(p)),
+        TerraformKind::Level => {
+            let level = res.level.unwrap_or(mpos.z);
+            map.environment.flood_preview(
+                area,
+                |p, h| {
+                    if brush(p) > 0.0 {
+                        h.min(level)
+                    } else {
+                        h
+                    }
+                },
+            )
+        }
+        TerraformKind::WaterLevel if amount_multiplier > 0.0 => map
+            .environment
+            .level_preview(center, res.amount * WATER_LEVEL_SPEED),
+        _ => vec![],
+    }
+}
+
+/// Buildings standing on the flooded cells
+fn flooded_buildings(map: &Map, cells: &[(Vec2, f32)]) -> usize {
+    let mut found = BTreeSet::new();
+    for &(pos, _) in cells {
+        let cell = AABB::centered(pos, Vec2::splat(WATER_CELL_SIZE));
+        for kind in map.spatial_map().query(cell, ProjectFilter::BUILDING) {
+            if let ProjectKind::Building(id) = kind {
+                found.insert(id);
+            }
+        }
     }
+    found.len()
 }
 
 impl Default for TerraformingResource {
@@ -145,6 +278,8 @@ impl Default for TerraformingResource {
             level: None,
             slope_start: None,
             slope_end: None,
+            flood_warned: false,
+            flood_confirmed: false,
         }
     }
 }
@@ -0,0 +1,39 @@
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+use geom::{Color, Vec2};
+use simulation::map::BuildingKind;
+use simulation::Simulation;
+
+/// Draws an arrow above the buildings that have an upgrade, pointing up on the screen
+pub fn upgrade_badges(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::upgrade_badges");
+    let map = sim.map();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+
+    let up = (-uiworld.camera().camera.dir().xy())
+        .try_normalize()
+        .unwrap_or(Vec2::Y);
+    let side = up.perpendicular();
+    let col = palette().success;
+
+    for b in map.buildings().values() {
+        let BuildingKind::GoodsCompany(id) = b.kind else {
+            continue;
+        };
+        if id.prototype().upgrade.is_none() {
+            continue;
+        }
+
+        let size = 3.0;
+        let tip = b.obb.center().z(b.height + 5.0) + (up * size).z(0.0);
+        let tail = tip - (up * size * 2.0).z(0.0);
+        let wing = |s: f32| tip - ((up - side * s) * size * 0.8).z(0.0);
+
+        draw.stroke_circle(tip - (up * size).z(0.0), size * 1.4, 0.4)
+            .color(Color::WHITE);
+        draw.line(tail, tip, 0.6).color(col);
+        draw.line(wing(1.0), tip, 0.6).color(col);
+        draw.line(wing(-1.0), tip, 0.6).color(col);
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use prototypes::AmenityKind;
+use simulation::map::IntersectionID;
+use simulation::map_dynamic::{amenity_isochrones, Walkability};
+use simulation::souls::commute::WALKING_SPEED;
+use simulation::Simulation;
+
+use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
+use crate::uiworld::UiWorld;
+
+/// Seconds between two computations of the isochrones
+const REFRESH_SECONDS: f32 = 1.0;
+/// Minutes of walk drawn in green, up to twice as long in yellow
+const WALK_MINUTES: f32 = 5.0;
+
+/// Whether the sidewalks are colored by how long it takes to walk to an amenity
+#[derive(Default)]
+pub struct WalkabilityView {
+    pub amenity: Option<AmenityKind>,
+    minutes: BTreeMap<IntersectionID, f32>,
+    computed_at: Option<Instant>,
+}
+
+/// Draws the roads within a short walk of the amenities in green, up to twice as long in yellow
+/// and the others in red. The walks stop at the longest distance counted in the scores.
+pub fn walkability(sim: &Simulation, uiworld: &UiWorld) {
+    profiling::scope!("gui::walkability");
+    let mut view = uiworld.write::<WalkabilityView>();
+    let Some(amenity) = view.amenity else {
+        view.computed_at = None;
+        return;
+    };
+
+    let map = sim.map();
+    if view
+        .computed_at
+        .map_or(true, |t| t.elapsed().as_secs_f32() > REFRESH_SECONDS)
+    {
+        let bound = sim.read::<Walkability>().weights().max_distance();
+        view.minutes = amenity_isochrones(&map, amenity, bound)
+            .into_iter()
+            .map(|(i, d)| (i, d / WALKING_SPEED / 60.0))
+            .collect();
+        view.computed_at = Some(Instant::now());
+    }
+
+    let colors = palette();
+    let mut draw = uiworld.write::<ImmediateDraw>();
+    for road in map.roads().values() {
+        let minutes = [road.src, road.dst]
+            .iter()
+            .filter_map(|i| view.minutes.get(i))
+            .copied()
+            .reduce(f32::min);
+        let col = match minutes {
+            Some(m) if m <= WALK_MINUTES => colors.ramp(0.0),
+            Some(m) if m <= WALK_MINUTES * 2.0 => colors.ramp(0.5),
+            _ => colors.ramp(1.0),
+        };
+        let points: Vec<_> = road.points().iter().map(|p| p.up(0.5)).collect();
+        draw.polyline(points, road.width * 0.5, false).color(col);
+    }
+}
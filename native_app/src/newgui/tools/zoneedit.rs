@@ -1,6 +1,8 @@
 use crate::inputmap::{InputAction, InputMap};
+use crate::newgui::units::{fmt_area, fmt_distance};
 use crate::newgui::{ErrorTooltip, InspectedBuilding, PotentialCommands};
 use crate::rendering::immediate::ImmediateDraw;
+use crate::rendering::palette;
 use crate::uiworld::UiWorld;
 use geom::{Polygon, Vec2};
 use ordered_float::OrderedFloat;
@@ -68,9 +70,17 @@ pub fn zoneedit(sim: &Simulation, uiworld: &UiWorld) {
 
     const MAX_PERIMETER: f32 = 3000.0;
     if area > MAX_ZONE_AREA {
-        invalidmsg = format!("Area too big ({area} > {MAX_ZONE_AREA})");
+        invalidmsg = format!(
+            "Area too big ({} > {})",
+            fmt_area(area),
+            fmt_area(MAX_ZONE_AREA)
+        );
     } else if perimeter > MAX_PERIMETER {
-        invalidmsg = format!("Perimeter too big ({perimeter} > {MAX_PERIMETER})");
+        invalidmsg = format!(
+            "Perimeter too big ({} > {})",
+            fmt_distance(perimeter),
+            fmt_distance(MAX_PERIMETER)
+        );
     } else if !newpoly.contains(b.obb.center()) {
         invalidmsg = String::from("Zone must be near the building");
     } else if let Some(v) = map
@@ -89,9 +99,9 @@ pub fn zoneedit(sim: &Simulation, uiworld: &UiWorld) {
     let base_col = if !isvalid {
         uiworld.write::<ErrorTooltip>().msg = Some(Cow::Owned(invalidmsg));
         uiworld.write::<ErrorTooltip>().isworld = true;
-        simulation::colors().gui_danger
+        palette().danger
     } else {
-        simulation::colors().gui_primary
+        palette().primary
     };
 
     for (p1, p2) in newpoly.iter().zip(newpoly.iter().cycle().skip(1)) {
@@ -128,8 +138,7 @@ pub fn zoneedit(sim: &Simulation, uiworld: &UiWorld) {
 
     for (i, &p) in newpoly.iter().enumerate() {
         if Some((i, p, false)) == closest {
-            draw.circle(p.z(1.1), 6.0)
-                .color(simulation::colors().gui_success);
+            draw.circle(p.z(1.1), 6.0).color(palette().success);
             continue;
         }
 
@@ -138,8 +147,7 @@ pub fn zoneedit(sim: &Simulation, uiworld: &UiWorld) {
 
     for (i, p) in newpoly.segments().map(|s| s.center()).enumerate() {
         if Some((i, p, true)) == closest {
-            draw.circle(p.z(1.1), 3.0)
-                .color(simulation::colors().gui_success);
+            draw.circle(p.z(1.1), 3.0).color(palette().success);
             continue;
         }
 
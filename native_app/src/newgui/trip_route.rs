@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use geom::{Color, Vec3};
+use goryak::{minrow, on_secondary_container, textc};
+use prototypes::GameTime;
+use simulation::map_dynamic::{trip_legs, Leg, LegKind};
+use simulation::{AnyEntity, Simulation};
+
+use crate::newgui::follow::FollowEntity;
+use crate::newgui::units::fmt_distance;
+use crate::newgui::InspectedEntity;
+use crate::rendering::immediate::ImmediateDraw;
+use crate::uiworld::UiWorld;
+
+/// The legs are routed again this often, the route follows the rerouting of the entity
+const REFRESH: Duration = Duration::from_millis(500);
+/// Length of the dashes of the walking legs, and of the gaps between them
+const DASH_LENGTH: f32 = 2.0;
+const ROUTE_Z: f32 = 0.3;
+
+/// The remaining route of the inspected entity, or of the followed one
+#[derive(Default)]
+pub struct TripRoute {
+    entity: Option<AnyEntity>,
+    legs: Vec<Leg>,
+    updated: Option<Instant>,
+}
+
+impl TripRoute {
+    pub fn update(uiw: &UiWorld, sim: &Simulation) {
+        profiling::scope!("newgui::trip_route");
+        let entity = uiw
+            .read::<InspectedEntity>()
+            .e
+            .or(uiw.read::<FollowEntity>().0);
+
+        let mut route = uiw.write::<TripRoute>();
+        let stale = route.updated.map_or(true, |t| t.elapsed() > REFRESH);
+        if entity != route.entity || stale {
+            route.entity = entity;
+            route.updated = Some(Instant::now());
+            route.legs = match entity {
+                Some(e) => trip_legs(&sim.world(), &sim.map(), sim.read::<GameTime>().tick, e),
+                None => vec![],
+            };
+        }
+
+        let Some(pos) = entity.and_then(|e| sim.pos_any(e)) else {
+            return;
+        };
+        let mut draw = uiw.write::<ImmediateDraw>();
+        for (i, leg) in route.legs.iter().enumerate() {
+            let mut points: Vec<Vec3> = leg.points.iter().map(|p| p.up(ROUTE_Z)).collect();
+            if i == 0 {
+                // the legs are a bit old, start from where the entity is now
+                if let Some(first) = points.first_mut() {
+                    *first = pos.up(ROUTE_Z);
+                }
+            }
+            let color = leg_color(leg.kind);
+            match leg.kind {
+                LegKind::Walk => draw_dashed(&mut draw, &points, 1.0, color),
+                LegKind::Drive => {
+                    draw.polyline(points, 2.0, false).color(color);
+                }
+                LegKind::Rail => {
+                    draw.polyline(points, 3.0, false).color(color);
+                }
+            }
+        }
+        if let Some(end) = route.legs.last().and_then(Leg::end) {
+            draw.circle(end.up(ROUTE_Z), 2.0)
+                .color(leg_color(route.legs[route.legs.len() - 1].kind));
+        }
+    }
+
+    /// The legs left with their arrival time, for the inspector of the entity
+    pub fn leg_list(uiw: &UiWorld, sim: &Simulation, entity: impl Into<AnyEntity>) {
+        let route = uiw.read::<TripRoute>();
+        if route.entity != Some(entity.into()) || route.legs.is_empty() {
+            return;
+        }
+
+        let mut eta = sim.read::<GameTime>().instant();
+        for leg in &route.legs {
+            eta = eta + leg.duration;
+            minrow(5.0, || {
+                let col = leg_color(leg.kind);
+                let col = yakui::Color::rgb(
+                    (col.r * 255.0) as u8,
+                    (col.g * 255.0) as u8,
+                    (col.b * 255.0) as u8,
+                );
+                textc(col, "•");
+                textc(
+                    on_secondary_container(),
+                    format!(
+                        "{} {}, arrives at {}",
+                        leg_name(leg.kind),
+                        fmt_distance(leg.length()),
+                        eta
+                    ),
+                );
+            });
+        }
+    }
+}
+
+fn leg_color(kind: LegKind) -> Color {
+    match kind {
+        LegKind::Walk => Color::new(0.3, 0.75, 1.0, 0.9),
+        LegKind::Drive => Color::new(1.0, 0.6, 0.1, 0.9),
+        LegKind::Rail => Color::new(0.75, 0.3, 1.0, 0.9),
+    }
+}
+
+fn leg_name(kind: LegKind) -> &'static str {
+    match kind {
+        LegKind::Walk => "Walk",
+        LegKind::Drive => "Drive",
+        LegKind::Rail => "Rail",
+    }
+}
+
+fn draw_dashed(draw: &mut ImmediateDraw, points: &[Vec3], thickness: f32, color: Color) {
+    let mut on = true;
+    let mut left = DASH_LENGTH;
+    for w in points.windows(2) {
+        let (mut a, b) = (w[0], w[1]);
+        let mut segment = a.distance(b);
+        while segment > 0.001 {
+            let step = left.min(segment);
+            let next = a + (b - a).normalize_to(step);
+            if on {
+                draw.line(a, next, thickness).color(color);
+            }
+            a = next;
+            segment -= step;
+            left -= step;
+            if left <= 0.001 {
+                on = !on;
+                left = DASH_LENGTH;
+            }
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//! Formatting of the quantities shown in the UI.
+//! Distances, speeds and areas are written in the unit system chosen in the settings, scaled to
+//! a readable unit, with the digit separators of the locale of the system.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+use prototypes::{GameDuration, Money};
+use serde::{Deserialize, Serialize};
+
+/// Unit system in use, set from the settings each frame
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+const FEET_PER_METER: f64 = 3.28084;
+const FEET_PER_MILE: f64 = 5280.0;
+const SQFT_PER_ACRE: f64 = 43560.0;
+const KMH_PER_MS: f64 = 3.6;
+const MPH_PER_MS: f64 = 2.236_936;
+/// Bucks from which the amounts of money are shortened with a suffix
+const COMPACT_MONEY_FROM: u64 = 10_000;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum UnitSystem {
+    #[default]
+    Metric = 0,
+    Imperial = 1,
+}
+
+impl UnitSystem {
+    pub const ALL: [UnitSystem; 2] = [UnitSystem::Metric, UnitSystem::Imperial];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "Metric",
+            UnitSystem::Imperial => "Imperial",
+        }
+    }
+
+    pub fn current() -> Self {
+        Self::ALL[CURRENT.load(Ordering::Relaxed) as usize % Self::ALL.len()]
+    }
+
+    pub fn set_current(self) {
+        CURRENT.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// A speed in m/s converted to km/h or mph, for the signs and the speed limit fields
+    pub fn speed_value(self, ms: f32) -> f32 {
+        match self {
+            UnitSystem::Metric => (ms as f64 * KMH_PER_MS) as f32,
+            UnitSystem::Imperial => (ms as f64 * MPH_PER_MS) as f32,
+        }
+    }
+
+    /// Inverse of [`UnitSystem::speed_value`]
+    pub fn speed_to_ms(self, v: f32) -> f32 {
+        match self {
+            UnitSystem::Metric => (v as f64 / KMH_PER_MS) as f32,
+            UnitSystem::Imperial => (v as f64 / MPH_PER_MS) as f32,
+        }
+    }
+
+    pub fn speed_unit(self) -> &'static str {
+        match self {
+            UnitSystem::Metric => "km/h",
+            UnitSystem::Imperial => "mph",
+        }
+    }
+}
+
+/// Digit separators of a locale
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NumberStyle {
+    pub thousands: char,
+    pub decimal: char,
+}
+
+impl NumberStyle {
+    pub const ENGLISH: NumberStyle = NumberStyle {
+        thousands: ',',
+        decimal: '.',
+    };
+    pub const CONTINENTAL: NumberStyle = NumberStyle {
+        thousands: '.',
+        decimal: ',',
+    };
+    pub const SPACED: NumberStyle = NumberStyle {
+        thousands: ' ',
+        decimal: ',',
+    };
+
+    /// Style of the locale of the system, read once from the usual environment variables
+    pub fn system() -> Self {
+        static STYLE: OnceLock<NumberStyle> = OnceLock::new();
+        *STYLE.get_or_init(|| {
+            let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+                .iter()
+                .filter_map(|var| std::env::var(var).ok())
+                .find(|v| !v.is_empty())
+                .unwrap_or_default();
+            Self::from_locale(&locale)
+        })
+    }
+
+    /// From a locale name like `de_DE.UTF-8`, only the language matters
+    pub fn from_locale(locale: &str) -> Self {
+        let lang = locale
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match &*lang {
+            "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" | "el" | "ro" => {
+                Self::CONTINENTAL
+            }
+            "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "uk" | "hu" => {
+                Self::SPACED
+            }
+            _ => Self::ENGLISH,
+        }
+    }
+
+    /// `value` rounded to `decimals` digits, with the thousands grouped
+    pub fn number(self, value: f64, decimals: usize) -> String {
+        let digits = format!("{:.*}", decimals, value.abs());
+        let (int, frac) = digits.split_once('.').unwrap_or((&digits, ""));
+        let is_zero = digits.bytes().all(|c| c == b'0' || c == b'.');
+
+        let mut s = String::with_capacity(digits.len() + 4);
+        if value < 0.0 && !is_zero {
+            s.push('-');
+        }
+        s.push_str(&self.group(int));
+        if !frac.is_empty() {
+            s.push(self.decimal);
+            s.push_str(frac);
+        }
+        s
+    }
+
+    fn group(self, int: &str) -> String {
+        let mut s = String::with_capacity(int.len() + int.len() / 3);
+        for (i, c) in int.chars().enumerate() {
+            if i > 0 && (int.len() - i) % 3 == 0 {
+                s.push(self.thousands);
+            }
+            s.push(c);
+        }
+        s
+    }
+}
+
+/// A unit system and a number style, the free functions of this module use the current ones
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Units {
+    pub system: UnitSystem,
+    pub style: NumberStyle,
+}
+
+impl Units {
+    pub fn current() -> Self {
+        Self {
+            system: UnitSystem::current(),
+            style: NumberStyle::system(),
+        }
+    }
+
+    pub fn distance(self, meters: f32) -> String {
+        let m = meters as f64;
+        match self.system {
+            UnitSystem::Metric => {
+                if m.abs() < 999.5 {
+                    self.scaled(m, "m")
+                } else {
+                    self.scaled(m / 1000.0, "km")
+                }
+            }
+            UnitSystem::Imperial => {
+                let ft = m * FEET_PER_METER;
+                if ft.abs() < 1.0 {
+                    self.scaled(ft * 12.0, "in")
+                } else if ft.abs() < 999.5 {
+                    self.scaled(ft, "ft")
+                } else {
+                    self.scaled(ft / FEET_PER_MILE, "mi")
+                }
+            }
+        }
+    }
+
+    /// A speed in m/s
+    pub fn speed(self, ms: f32) -> String {
+        format!(
+            "{}{}",
+            self.style.number(self.system.speed_value(ms) as f64, 0),
+            self.system.speed_unit()
+        )
+    }
+
+    /// An area in square meters
+    pub fn area(self, sqm: f32) -> String {
+        let sqm = sqm as f64;
+        match self.system {
+            UnitSystem::Metric => {
+                if sqm.abs() < 999_999.5 {
+                    format!("{}m²", self.style.number(sqm, 0))
+                } else {
+                    format!("{}km²", self.style.number(sqm / 1e6, 2))
+                }
+            }
+            UnitSystem::Imperial => {
+                let sqft = sqm * FEET_PER_METER * FEET_PER_METER;
+                if sqft.abs() < SQFT_PER_ACRE {
+                    format!("{}ft²", self.style.number(sqft, 0))
+                } else {
+                    format!("{}ac", self.style.number(sqft / SQFT_PER_ACRE, 1))
+                }
+            }
+        }
+    }
+
+    /// Exact to the cent below 10k$, above that shortened to three significant digits with a
+    /// k, M or B suffix so that the government budget stays readable
+    pub fn money(self, money: Money) -> String {
+        let v = money.inner();
+        let sign = if v < 0 { "-" } else { "" };
+        let abs = v.unsigned_abs();
+        let (bucks, cents) = (abs / 10000, (abs % 10000) / 100);
+
+        if bucks < COMPACT_MONEY_FROM {
+            if bucks == 0 && cents == 0 {
+                return "0$".to_string();
+            }
+            let mut s = format!("{}{}", sign, self.style.group(&bucks.to_string()));
+            if cents > 0 {
+                s.push(self.style.decimal);
+                s.push_str(&format!("{:02}", cents));
+            }
+            s.push('$');
+            return s;
+        }
+
+        let value = abs as f64 / 10000.0;
+        let magnitude = 10f64.powi(value.log10().floor() as i32 - 2);
+        let rounded = (value / magnitude).round() * magnitude;
+        let (factor, suffix) = match rounded {
+            _ if rounded >= 1e9 => (1e9, "B"),
+            _ if rounded >= 1e6 => (1e6, "M"),
+            _ => (1e3, "k"),
+        };
+        let scaled = rounded / factor;
+        let decimals = (2 - scaled.log10().floor() as i32).clamp(0, 2) as usize;
+        format!("{}{}{}$", sign, self.style.number(scaled, decimals), suffix)
+    }
+
+    /// The two largest units of the duration, rounded to the smallest of them
+    pub fn duration(self, d: GameDuration) -> String {
+        let secs = d.seconds().round() as u64;
+        if secs < 60 {
+            return format!("{}s", secs);
+        }
+        let mins = (secs + 30) / 60;
+        if mins < 60 {
+            return format!("{}min", mins);
+        }
+        let (h, m) = (mins / 60, mins % 60);
+        if h < 24 {
+            return match m {
+                0 => format!("{}h", h),
+                _ => format!("{}h {:02}min", h, m),
+            };
+        }
+        let hours = (mins + 30) / 60;
+        let (days, h) = (hours / 24, hours % 24);
+        let days = self.style.group(&days.to_string());
+        match h {
+            0 => format!("{}d", days),
+            _ => format!("{}d {}h", days, h),
+        }
+    }
+
+    /// One decimal under 10 of the unit, none above
+    fn scaled(self, v: f64, unit: &str) -> String {
+        // rounded first so everything that shows as zero is written "0"
+        let decimals = match v.abs() {
+            a if (a * 10.0).round() == 0.0 => 0,
+            a if a < 9.95 => 1,
+            _ => 0,
+        };
+        format!("{}{}", self.style.number(v, decimals), unit)
+    }
+}
+
+pub fn fmt_distance(meters: f32) -> String {
+    Units::current().distance(meters)
+}
+
+pub fn fmt_speed(ms: f32) -> String {
+    Units::current().speed(ms)
+}
+
+pub fn fmt_area(sqm: f32) -> String {
+    Units::current().area(sqm)
+}
+
+pub fn fmt_money(money: Money) -> String {
+    Units::current().money(money)
+}
+
+pub fn fmt_duration(d: GameDuration) -> String {
+    Units::current().duration(d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRIC: Units = Units {
+        system: UnitSystem::Metric,
+        style: NumberStyle::ENGLISH,
+    };
+    const IMPERIAL: Units = Units {
+        system: UnitSystem::Imperial,
+        style: NumberStyle::ENGLISH,
+    };
+
+    #[test]
+    fn distances_scale_their_unit() {
+        assert_eq!(METRIC.distance(0.0), "0m");
+        assert_eq!(METRIC.distance(0.27), "0.3m");
+        assert_eq!(METRIC.distance(-0.04), "0m");
+        assert_eq!(METRIC.distance(0.04), "0m");
+        assert_eq!(METRIC.distance(7.5), "7.5m");
+        assert_eq!(METRIC.distance(120.4), "120m");
+        assert_eq!(METRIC.distance(999.7), "1.0km");
+        assert_eq!(METRIC.distance(15_300.0), "15km");
+        assert_eq!(METRIC.distance(1_234_000.0), "1,234km");
+
+        assert_eq!(IMPERIAL.distance(0.05), "2.0in");
+        assert_eq!(IMPERIAL.distance(100.0), "328ft");
+        assert_eq!(IMPERIAL.distance(1609.344), "1.0mi");
+    }
+
+    #[test]
+    fn speeds_and_areas() {
+        assert_eq!(METRIC.speed(13.9), "50km/h");
+        assert_eq!(IMPERIAL.speed(13.4112), "30mph");
+        assert_eq!(METRIC.area(2500.0), "2,500m²");
+        assert_eq!(METRIC.area(3_500_000.0), "3.50km²");
+        assert_eq!(IMPERIAL.area(10.0), "108ft²");
+        assert_eq!(IMPERIAL.area(8093.7), "2.0ac");
+        let ms = UnitSystem::Imperial.speed_to_ms(30.0);
+        assert!((UnitSystem::Imperial.speed_value(ms) - 30.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn money_is_exact_then_compact() {
+        let bucks = |b: f64| Money::new_cents((b * 100.0).round() as i64);
+        assert_eq!(METRIC.money(Money::ZERO), "0$");
+        assert_eq!(METRIC.money(bucks(12.5)), "12.50$");
+        assert_eq!(METRIC.money(bucks(9999.0)), "9,999$");
+        assert_eq!(METRIC.money(bucks(-0.5)), "-0.50$");
+        assert_eq!(METRIC.money(bucks(-1234.05)), "-1,234.05$");
+        assert_eq!(METRIC.money(bucks(12_345.0)), "12.3k$");
+        assert_eq!(METRIC.money(bucks(-250_400.0)), "-250k$");
+        assert_eq!(METRIC.money(bucks(999_700.0)), "1.00M$");
+        assert_eq!(METRIC.money(bucks(45_670_000.0)), "45.7M$");
+        assert_eq!(METRIC.money(bucks(2_000_000_000.0)), "2.00B$");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(METRIC.duration(GameDuration::from_secs(0)), "0s");
+        assert_eq!(METRIC.duration(GameDuration::from_secs(45)), "45s");
+        assert_eq!(
+            METRIC.duration(GameDuration::from_secs(12 * 60 + 40)),
+            "13min"
+        );
+        assert_eq!(METRIC.duration(GameDuration::from_minutes(59)), "59min");
+        assert_eq!(METRIC.duration(GameDuration::from_minutes(60)), "1h");
+        assert_eq!(METRIC.duration(GameDuration::from_minutes(65)), "1h 05min");
+        assert_eq!(
+            METRIC.duration(GameDuration::from_minutes(50 * 60)),
+            "2d 2h"
+        );
+    }
+
+    #[test]
+    fn locale_separators() {
+        let de = NumberStyle::from_locale("de_DE.UTF-8");
+        assert_eq!(de, NumberStyle::CONTINENTAL);
+        assert_eq!(de.number(-1234567.891, 2), "-1.234.567,89");
+        assert_eq!(NumberStyle::from_locale("fr_FR").number(1500.0, 0), "1 500");
+        assert_eq!(NumberStyle::from_locale("C").number(999.0, 0), "999");
+        assert_eq!(NumberStyle::from_locale("").number(-0.001, 1), "0.0");
+    }
+}
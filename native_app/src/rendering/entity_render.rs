@@ -1,19 +1,50 @@
+use std::path::Path;
+
 use common::FastMap;
-use engine::{FrameContext, GfxContext, InstancedMeshBuilder, MeshInstance, SpriteBatchBuilder};
-use geom::{LinearColor, Vec3, V3};
-use prototypes::{RenderAsset, RollingStockID, RollingStockPrototype};
-use simulation::transportation::{Location, VehicleKind};
-use simulation::Simulation;
+use engine::{
+    FrameContext, GfxContext, InstancedMesh, InstancedMeshBuilder, Mesh, MeshBuilder, MeshInstance,
+    PaletteRange, SpriteBatchBuilder,
+};
+use geom::{Color, LinearColor, Transform, Vec3, V3};
+use prototypes::{
+    DockPrototype, DockPrototypeID, ItemID, ItemPrototype, RenderAsset, RoadVehicleID,
+    RoadVehiclePrototype, RollingStockID, RollingStockPrototype,
+};
+use simulation::souls::delivery::Shipment;
+use simulation::transportation::train::RailWagonKind;
+use simulation::transportation::{car_color_index, Location, VehicleKind, CAR_COLORS};
+use simulation::{AnyEntity, Simulation};
+
+use crate::rendering::{Interpolation, SimTimeScale, WIRE_HEIGHT};
+
+/// Where the crates sit on the flatbed of the trucks, along the truck from the back
+const CRATE_SLOTS: [f32; 3] = [-2.2, -1.2, -0.2];
+const FLATBED_HEIGHT: f32 = 1.1;
+/// Height of the roof of the locomotives, where the pantographs are
+const LOCOMOTIVE_ROOF: f32 = 4.2;
 
 /// Render all entities using instanced rendering for performance
 pub struct InstancedRender {
     pub path_not_found: SpriteBatchBuilder<true>,
     pub rolling_stock: FastMap<RollingStockID, InstancedMeshBuilder<true>>,
-    pub cars: InstancedMeshBuilder<true>,
+    pub boats: FastMap<DockPrototypeID, InstancedMeshBuilder<true>>,
+    /// Cars by mesh, painted with the colors of the palette.
+    /// The models sharing a mesh are drawn together.
+    pub cars: Vec<InstancedMeshBuilder<true>>,
+    /// Index in `cars` of the mesh of each model
+    car_meshes: FastMap<RoadVehicleID, usize>,
+    /// Paint colors of the cars
+    car_paint: PaletteRange,
     // pub locomotives: InstancedMeshBuilder<true>,
     // pub wagons_passenger: InstancedMeshBuilder<true>,
     // pub wagons_freight: InstancedMeshBuilder<true>,
     pub trucks: InstancedMeshBuilder<true>,
+    /// Goods carried by the trucks, the items without a mesh of their own use tinted crates
+    pub cargo: FastMap<ItemID, InstancedMeshBuilder<true>>,
+    pub crates: InstancedMeshBuilder<true>,
+    /// Pantographs of the locomotives, raised below the overhead lines and lowered elsewhere
+    pub pantographs_up: InstancedMeshBuilder<true>,
+    pub pantographs_down: InstancedMeshBuilder<true>,
     pub pedestrians: InstancedMeshBuilder<true>,
 }
 
@@ -40,7 +71,57 @@ impl InstancedRender {
                 rolling_stock.insert(id, InstancedMeshBuilder::new_ref(&mesh));
             });
 
-        let car = gfx.mesh("simple_car.glb".as_ref()).unwrap();
+        let mut boats = FastMap::default();
+        for proto in DockPrototype::iter() {
+            let RenderAsset::Mesh { ref path } = proto.boat_asset else {
+                log::warn!("boat of dock {} must be a mesh", proto.name);
+                continue;
+            };
+            match gfx.mesh(path) {
+                Ok(m) => {
+                    boats.insert(proto.id, InstancedMeshBuilder::new_ref(&m));
+                }
+                Err(e) => log::error!("Failed to load mesh {}: {:?}", proto.boat_asset, e),
+            }
+        }
+
+        let mut cargo = FastMap::default();
+        for proto in ItemPrototype::iter() {
+            let Some(RenderAsset::Mesh { ref path }) = proto.cargo_asset else {
+                continue;
+            };
+            match gfx.mesh(path) {
+                Ok(m) => {
+                    cargo.insert(proto.id, InstancedMeshBuilder::new_ref(&m));
+                }
+                Err(e) => log::error!("Failed to load mesh {}: {:?}", path.display(), e),
+            }
+        }
+
+        let mut cars = vec![];
+        let mut car_meshes = FastMap::default();
+        let mut by_path: FastMap<&Path, usize> = FastMap::default();
+        for proto in RoadVehiclePrototype::iter().filter(|p| p.popularity > 0.0) {
+            let RenderAsset::Mesh { ref path } = proto.asset else {
+                log::warn!("car {} must be a mesh", proto.name);
+                continue;
+            };
+            if let Some(&i) = by_path.get(path.as_path()) {
+                car_meshes.insert(proto.id, i);
+                continue;
+            }
+            match gfx.mesh(path) {
+                Ok(m) => {
+                    by_path.insert(path, cars.len());
+                    car_meshes.insert(proto.id, cars.len());
+                    cars.push(InstancedMeshBuilder::new_ref(&m));
+                }
+                Err(e) => log::error!("Failed to load mesh {}: {:?}", proto.asset, e),
+            }
+        }
+
+        let car_paint = CAR_COLORS.map(|(hex, _)| LinearColor::from(Color::from_hex(hex)));
+        let car_paint = gfx.palette.add("car paint", &car_paint);
         InstancedRender {
             path_not_found: SpriteBatchBuilder::new(
                 &gfx.texture("assets/sprites/path_not_found.png", "path_not_found"),
@@ -48,34 +129,64 @@ impl InstancedRender {
             ),
 
             rolling_stock,
+            boats,
 
-            cars: InstancedMeshBuilder::new_ref(&car),
+            cars,
+            car_meshes,
+            car_paint,
             // locomotives: InstancedMeshBuilder::new_ref(&gfx.mesh("train.glb".as_ref()).unwrap()),
             // wagons_freight: InstancedMeshBuilder::new_ref(&gfx.mesh("wagon_freight.glb".as_ref()).unwrap()),
             // wagons_passenger: InstancedMeshBuilder::new_ref(&gfx.mesh("wagon.glb".as_ref()).unwrap()),
             trucks: InstancedMeshBuilder::new_ref(&gfx.mesh("truck.glb".as_ref()).unwrap()),
+            cargo,
+            crates: InstancedMeshBuilder::new_ref(&crate_mesh(gfx)),
+            pantographs_up: InstancedMeshBuilder::new(pantograph_mesh(gfx, true)),
+            pantographs_down: InstancedMeshBuilder::new(pantograph_mesh(gfx, false)),
             pedestrians: InstancedMeshBuilder::new_ref(
                 &gfx.mesh("pedestrian.glb".as_ref()).unwrap(),
             ),
         }
     }
 
-    pub fn render(&mut self, sim: &Simulation, fctx: &mut FrameContext<'_>) {
+    pub fn render(
+        &mut self,
+        sim: &Simulation,
+        interp: &Interpolation,
+        time_scale: &SimTimeScale,
+        fctx: &mut FrameContext<'_>,
+    ) {
         profiling::scope!("entity_render::render");
-        self.cars.instances.clear();
+        self.cars.iter_mut().for_each(|m| m.instances.clear());
         self.trucks.instances.clear();
+        self.crates.instances.clear();
+        self.cargo.values_mut().for_each(|m| m.instances.clear());
         self.pedestrians.instances.clear();
-        for v in sim.world().vehicles.values() {
-            let trans = &v.trans;
-            let instance = MeshInstance {
-                pos: trans.pos,
-                dir: trans.dir,
-                tint: v.vehicle.tint.into(),
-            };
+        for (id, v) in sim.world().vehicles.iter() {
+            let trans = &interp.vehicle(id, &v.trans);
 
             match v.vehicle.kind {
-                VehicleKind::Car => self.cars.instances.push(instance),
-                VehicleKind::Truck => self.trucks.instances.push(instance),
+                VehicleKind::Car => {
+                    let Some(&i) = v
+                        .vehicle
+                        .prototype()
+                        .and_then(|p| self.car_meshes.get(&p.id))
+                    else {
+                        continue;
+                    };
+                    let mesh = &mut self.cars[i];
+                    // a stable color for each car, without saving it
+                    let x = (common::hash_u64(id) >> 40) as f32 / (1 << 24) as f32;
+                    let paint = self.car_paint.variation(car_color_index(x));
+                    mesh.instances.push(
+                        MeshInstance::new(trans.pos, trans.dir, LinearColor::WHITE)
+                            .with_variation(paint),
+                    );
+                }
+                VehicleKind::Truck => {
+                    let instance = MeshInstance::new(trans.pos, trans.dir, v.vehicle.tint.into());
+                    self.trucks.instances.push(instance);
+                    self.push_cargo(trans, &v.vehicle.cargo, v.vehicle.kind.capacity());
+                }
                 _ => {}
             }
         }
@@ -83,26 +194,60 @@ impl InstancedRender {
         self.rolling_stock.iter_mut().for_each(|(_, m)| {
             m.instances.clear();
         });
-        for wagon in sim.world().wagons.values() {
-            let trans = &wagon.trans;
-            let instance = MeshInstance {
-                pos: trans.pos,
-                dir: trans.dir,
-                tint: LinearColor::WHITE,
-            };
+        self.pantographs_up.instances.clear();
+        self.pantographs_down.instances.clear();
+        let map = sim.map();
+        for (id, wagon) in sim.world().wagons.iter() {
+            let trans = &interp.wagon(id, &wagon.trans);
+            let instance = MeshInstance::new(trans.pos, trans.dir, LinearColor::WHITE);
 
             if let Some(mesh) = self.rolling_stock.get_mut(&wagon.wagon.rolling_stock) {
                 mesh.instances.push(instance);
             }
+
+            if !matches!(wagon.wagon.kind, RailWagonKind::Locomotive) {
+                continue;
+            }
+            let electrified = sim
+                .world()
+                .trains
+                .get(wagon.itfollower.leader)
+                .and_then(|train| train.it.get_travers())
+                .is_some_and(|travers| map.is_electrified(travers.kind));
+            if electrified {
+                self.pantographs_up.instances.push(instance);
+            } else {
+                self.pantographs_down.instances.push(instance);
+            }
         }
+        drop(map);
 
-        for p in sim.world().humans.values() {
+        // boats bob on the waves, out of phase with each other, and hold still on pause
+        let t = time_scale.anim_time;
+        self.boats.values_mut().for_each(|m| m.instances.clear());
+        for dock in sim.world().docks.values() {
+            let Some(mesh) = self.boats.get_mut(&dock.d.proto) else {
+                continue;
+            };
+            for boat in &dock.d.boats {
+                let pos = boat.trans.pos;
+                let bob = 0.3 * (t * 1.5 + (pos.x + pos.y) * 0.05).sin();
+                mesh.instances.push(MeshInstance::new(
+                    pos.up(bob),
+                    boat.trans.dir,
+                    LinearColor::WHITE,
+                ));
+            }
+        }
+
+        for (id, p) in sim.world().humans.iter() {
             if matches!(p.location, Location::Outside) {
-                self.pedestrians.instances.push(MeshInstance {
-                    pos: p.trans.pos.up(0.5 + 0.4 * p.pedestrian.walk_anim.cos()),
-                    dir: p.trans.dir.xy().z0(),
-                    tint: LinearColor::WHITE,
-                });
+                let trans = interp.human(id, &p.trans);
+                self.pedestrians.instances.push(MeshInstance::new(
+                    trans.pos.up(0.5 + 0.4 * p.pedestrian.walk_anim.cos()),
+                    trans.dir.xy().z0(),
+                    LinearColor::WHITE,
+                ));
             }
         }
 
@@ -130,12 +275,28 @@ impl InstancedRender {
         if let Some(x) = self.path_not_found.build(fctx.gfx) {
             fctx.objs.push(Box::new(x));
         }
-        if let Some(x) = self.cars.build(fctx.gfx) {
+        self.cars.iter_mut().for_each(|imb| {
+            if let Some(x) = imb.build(fctx.gfx) {
+                fctx.objs.push(Box::new(x));
+            }
+        });
+        if let Some(x) = self.trucks.build(fctx.gfx) {
             fctx.objs.push(Box::new(x));
         }
-        if let Some(x) = self.trucks.build(fctx.gfx) {
+        if let Some(x) = self.crates.build(fctx.gfx) {
             fctx.objs.push(Box::new(x));
         }
+        if let Some(x) = self.pantographs_up.build(fctx.gfx) {
+            fctx.objs.push(Box::new(x));
+        }
+        if let Some(x) = self.pantographs_down.build(fctx.gfx) {
+            fctx.objs.push(Box::new(x));
+        }
+        self.cargo.values_mut().for_each(|imb| {
+            if let Some(x) = imb.build(fctx.gfx) {
+                fctx.objs.push(Box::new(x));
+            }
+        });
         if let Some(x) = self.pedestrians.build(fctx.gfx) {
             fctx.objs.push(Box::new(x));
         }
@@ -145,5 +306,181 @@ impl InstancedRender {
                 fctx.objs.push(Box::new(x));
             }
         });
+        self.boats.values_mut().for_each(|imb| {
+            if let Some(x) = imb.build(fctx.gfx) {
+                fctx.objs.push(Box::new(x));
+            }
+        });
+    }
+
+    /// Fills the flatbed of the truck in proportion to its load,
+    /// each crate showing the goods at that point of the cargo
+    fn push_cargo(&mut self, trans: &Transform, cargo: &[Shipment], capacity: u32) {
+        let total: i32 = cargo.iter().map(|s| s.qty).sum();
+        if total <= 0 || capacity == 0 {
+            return;
+        }
+        let slots = CRATE_SLOTS.len();
+        let n = ((total as f32 / capacity as f32 * slots as f32).ceil() as usize).min(slots);
+
+        for (k, &offset) in CRATE_SLOTS.iter().take(n).enumerate() {
+            let at = (k as f32 + 0.5) / n as f32 * total as f32;
+            let mut acc = 0.0;
+            let Some(s) = cargo.iter().find(|s| {
+                acc += s.qty as f32;
+                acc > at
+            }) else {
+                continue;
+            };
+
+            let pos = trans.pos + trans.dir * offset + V3::Z * FLATBED_HEIGHT;
+            match self.cargo.get_mut(&s.kind) {
+                Some(mesh) => {
+                    mesh.instances
+                        .push(MeshInstance::new(pos, trans.dir, LinearColor::WHITE))
+                }
+                None => self.crates.instances.push(MeshInstance::new(
+                    pos,
+                    trans.dir,
+                    s.kind.prototype().cargo_color.into(),
+                )),
+            }
+        }
+    }
+
+    /// Meshes of the entity alone, used to draw its outline
+    pub fn entity_outline(
+        &self,
+        sim: &Simulation,
+        interp: &Interpolation,
+        e: AnyEntity,
+        gfx: &GfxContext,
+    ) -> Vec<InstancedMesh> {
+        let world = sim.world();
+        let mut singles = vec![];
+        let mut single = |builder: &InstancedMeshBuilder<true>, instance: MeshInstance| {
+            let mut b = InstancedMeshBuilder::<false>::new_ref(builder.mesh());
+            b.instances.push(instance);
+            singles.extend(b.build(gfx));
+        };
+
+        match e {
+            AnyEntity::VehicleID(id) => {
+                let Some(v) = world.get(id) else {
+                    return vec![];
+                };
+                let builder = match v.vehicle.kind {
+                    VehicleKind::Car => {
+                        let Some(&i) = v
+                            .vehicle
+                            .prototype()
+                            .and_then(|p| self.car_meshes.get(&p.id))
+                        else {
+                            return vec![];
+                        };
+                        &self.cars[i]
+                    }
+                    VehicleKind::Truck => &self.trucks,
+                    _ => return vec![],
+                };
+                let trans = interp.vehicle(id, &v.trans);
+                single(
+                    builder,
+                    MeshInstance::new(trans.pos, trans.dir, LinearColor::WHITE),
+                );
+            }
+            AnyEntity::HumanID(id) => {
+                let Some(p) = world.get(id) else {
+                    return vec![];
+                };
+                if !matches!(p.location, Location::Outside) {
+                    return vec![];
+                }
+                let trans = interp.human(id, &p.trans);
+                single(
+                    &self.pedestrians,
+                    MeshInstance::new(
+                        trans.pos.up(0.5 + 0.4 * p.pedestrian.walk_anim.cos()),
+                        trans.dir.xy().z0(),
+                        LinearColor::WHITE,
+                    ),
+                );
+            }
+            AnyEntity::WagonID(_) | AnyEntity::TrainID(_) => {
+                let train = match e {
+                    AnyEntity::WagonID(id) => world.get(id).map(|w| w.itfollower.leader),
+                    AnyEntity::TrainID(id) => Some(id),
+                    _ => None,
+                };
+                // a train is outlined as a whole
+                for (id, wagon) in world.wagons.iter() {
+                    if Some(wagon.itfollower.leader) != train {
+                        continue;
+                    }
+                    let Some(builder) = self.rolling_stock.get(&wagon.wagon.rolling_stock) else {
+                        continue;
+                    };
+                    let trans = interp.wagon(id, &wagon.trans);
+                    single(
+                        builder,
+                        MeshInstance::new(trans.pos, trans.dir, LinearColor::WHITE),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        singles
+    }
+}
+
+/// A crate carried by the trucks, white so it takes the color of the goods
+fn crate_mesh(gfx: &GfxContext) -> Mesh {
+    let mut mb = MeshBuilder::<false>::new(gfx.tess_material);
+    mb.extend_box(
+        None,
+        Vec3::new(-0.45, -0.9, 0.0),
+        Vec3::new(0.45, 0.9, 0.8),
+        LinearColor::WHITE,
+    );
+    mb.build(gfx).unwrap() // Unwrap ok: the crate has vertices
+}
+
+/// Pantograph on the roof of the locomotives, raised up to the wire of the overhead lines
+fn pantograph_mesh(gfx: &GfxContext, raised: bool) -> Mesh {
+    let col = LinearColor::gray(0.2);
+    let top = if raised {
+        WIRE_HEIGHT
+    } else {
+        LOCOMOTIVE_ROOF + 0.4
+    };
+    let mut mb = MeshBuilder::<false>::new(gfx.tess_material);
+    mb.extend_box(
+        None,
+        Vec3::new(-0.6, -0.7, LOCOMOTIVE_ROOF),
+        Vec3::new(0.6, 0.7, LOCOMOTIVE_ROOF + 0.15),
+        col,
+    );
+    if raised {
+        mb.extend_box(
+            None,
+            Vec3::new(-0.05, -0.05, LOCOMOTIVE_ROOF),
+            Vec3::new(0.05, 0.05, top - 0.08),
+            col,
+        );
+    } else {
+        mb.extend_box(
+            None,
+            Vec3::new(-1.0, -0.05, LOCOMOTIVE_ROOF + 0.15),
+            Vec3::new(1.0, 0.05, top - 0.08),
+            col,
+        );
     }
+    mb.extend_box(
+        None,
+        Vec3::new(-0.12, -0.9, top - 0.08),
+        Vec3::new(0.12, 0.9, top),
+        col,
+    );
+    mb.build(gfx).unwrap() // Unwrap ok: the pantograph has vertices
 }
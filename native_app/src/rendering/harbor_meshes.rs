@@ -0,0 +1,248 @@
+//! Meshes of the docks and of their cargo boats. No model was made for them, so they are built
+//! from boxes like the other details. A model file with the same name replaces them.
+
+use std::path::Path;
+
+use engine::{GfxContext, IndexType, Mesh, MeshBuilder, MeshVertex};
+use geom::{vec2, vec3, LinearColor, Vec2, Vec3};
+use prototypes::{DockPrototype, RenderAsset, Size2D};
+
+/// Names the prototypes give to the generated meshes
+const DOCK_MESH: &str = "dock.glb";
+const BOAT_MESH: &str = "cargo_boat.glb";
+
+/// How far the jetty of the docks goes over the water
+const JETTY_LENGTH: f32 = 12.0;
+
+/// Builds the meshes of the dock prototypes using the generated ones.
+/// A dock mesh is fitted to the size of the prototype using it.
+pub fn register_harbor_meshes(gfx: &mut GfxContext) {
+    for proto in DockPrototype::iter() {
+        if let Some(path) = generated(&proto.asset, DOCK_MESH) {
+            let mesh = dock_mesh(gfx, proto.size);
+            gfx.set_mesh(path, mesh);
+        }
+        if let Some(path) = generated(&proto.boat_asset, BOAT_MESH) {
+            let mesh = boat_mesh(gfx);
+            gfx.set_mesh(path, mesh);
+        }
+    }
+}
+
+/// The path of the asset when it is the generated mesh and no model file replaces it
+fn generated<'a>(asset: &'a RenderAsset, name: &str) -> Option<&'a Path> {
+    let RenderAsset::Mesh { path } = asset else {
+        return None;
+    };
+    let replaced = Path::new("assets/models").join(path).exists();
+    (path == Path::new(name) && !replaced).then_some(path.as_path())
+}
+
+/// A quay with a shed, stacked containers and a crane over a jetty.
+/// The docks are placed with their back on the road, so the water is on the +y side.
+fn dock_mesh(gfx: &GfxContext, size: Size2D) -> Mesh {
+    let concrete = LinearColor::gray(0.55);
+    let wood = LinearColor::new(0.35, 0.24, 0.15, 1.0);
+    let crane = LinearColor::new(0.9, 0.6, 0.1, 1.0);
+    let containers = [
+        LinearColor::new(0.6, 0.15, 0.1, 1.0),
+        LinearColor::new(0.1, 0.25, 0.55, 1.0),
+        LinearColor::new(0.15, 0.4, 0.2, 1.0),
+    ];
+    // the obb of the building spans its height along x and its width along y
+    let (hx, hy) = (size.h * 0.5, size.w * 0.5);
+
+    let mut mb = MeshBuilder::<false>::new(gfx.tess_material);
+    let mut cube = |min: Vec3, max: Vec3, col: LinearColor| mb.extend_box(None, min, max, col);
+
+    // the quay wall goes down into the water
+    cube(vec3(-hx, -hy, -2.0), vec3(hx, hy, 0.5), concrete);
+
+    cube(
+        vec3(-3.0, hy, -0.2),
+        vec3(3.0, hy + JETTY_LENGTH, 0.5),
+        wood,
+    );
+    for k in 0..3 {
+        let y = hy + 2.0 + k as f32 * 4.5;
+        for x in [-2.6, 2.6] {
+            cube(
+                vec3(x - 0.3, y - 0.3, -4.0),
+                vec3(x + 0.3, y + 0.3, -0.2),
+                wood,
+            );
+        }
+    }
+
+    // the shed by the road
+    let shed_depth = size.w * 0.35;
+    cube(
+        vec3(-hx + 2.0, -hy + 3.0, 0.5),
+        vec3(-1.0, -hy + 3.0 + shed_depth, 7.5),
+        LinearColor::new(0.55, 0.6, 0.65, 1.0),
+    );
+    cube(
+        vec3(-hx + 1.5, -hy + 2.5, 7.5),
+        vec3(-0.5, -hy + 3.5 + shed_depth, 8.0),
+        LinearColor::gray(0.3),
+    );
+
+    // containers waiting for the boats, in stacks of one to three
+    let mut x = 1.5;
+    let mut i = 0;
+    while x + 2.4 <= hx - 2.0 {
+        for level in 0..=(i % 3) {
+            let z = 0.5 + level as f32 * 2.6;
+            cube(
+                vec3(x, -hy + 3.0, z),
+                vec3(x + 2.4, -hy + 9.0, z + 2.6),
+                containers[(i + level) % containers.len()],
+            );
+        }
+        x += 2.8;
+        i += 1;
+    }
+
+    // a gantry crane on the quay, its boom reaching over the boats
+    let cx = hx * 0.4;
+    for lx in [cx - 3.0, cx + 3.0] {
+        for ly in [hy - 7.0, hy - 1.0] {
+            cube(
+                vec3(lx - 0.25, ly - 0.25, 0.5),
+                vec3(lx + 0.25, ly + 0.25, 14.0),
+                crane,
+            );
+        }
+    }
+    cube(
+        vec3(cx - 3.3, hy - 7.3, 14.0),
+        vec3(cx + 3.3, hy - 0.7, 15.0),
+        crane,
+    );
+    cube(
+        vec3(cx - 0.6, hy - 10.0, 15.0),
+        vec3(cx + 0.6, hy + 14.0, 16.0),
+        crane,
+    );
+    cube(
+        vec3(cx - 1.2, hy - 1.0, 13.0),
+        vec3(cx + 1.2, hy + 1.5, 15.0),
+        LinearColor::gray(0.8),
+    );
+
+    // bollards along the edge of the quay
+    let mut x = -hx + 2.0;
+    while x <= hx - 2.0 {
+        cube(
+            vec3(x - 0.25, hy - 0.8, 0.5),
+            vec3(x + 0.25, hy - 0.3, 1.1),
+            LinearColor::gray(0.15),
+        );
+        x += 6.0;
+    }
+
+    mb.build(gfx).unwrap() // Unwrap ok: the dock has vertices
+}
+
+/// A small container ship, going toward +x with its bridge at the stern.
+/// The water line is at 0.
+fn boat_mesh(gfx: &GfxContext) -> Mesh {
+    let white = LinearColor::gray(0.9);
+    let containers = [
+        LinearColor::new(0.6, 0.15, 0.1, 1.0),
+        LinearColor::new(0.1, 0.25, 0.55, 1.0),
+        LinearColor::new(0.15, 0.4, 0.2, 1.0),
+        LinearColor::new(0.7, 0.55, 0.1, 1.0),
+    ];
+
+    let mut mb = MeshBuilder::<false>::new(gfx.tess_material);
+    extend_prism(
+        &mut mb,
+        &[
+            vec2(-11.0, -3.0),
+            vec2(8.0, -3.0),
+            vec2(12.0, 0.0),
+            vec2(8.0, 3.0),
+            vec2(-11.0, 3.0),
+        ],
+        -1.2,
+        1.5,
+        LinearColor::new(0.45, 0.08, 0.06, 1.0),
+    );
+    let mut cube = |min: Vec3, max: Vec3, col: LinearColor| mb.extend_box(None, min, max, col);
+
+    cube(
+        vec3(-10.8, -2.8, 1.5),
+        vec3(8.0, 2.8, 1.6),
+        LinearColor::gray(0.35),
+    );
+
+    cube(vec3(-10.5, -2.6, 1.6), vec3(-6.5, 2.6, 5.0), white);
+    cube(
+        vec3(-6.55, -2.4, 4.0),
+        vec3(-6.45, 2.4, 4.6),
+        LinearColor::gray(0.1),
+    );
+    cube(
+        vec3(-10.7, -2.8, 5.0),
+        vec3(-6.3, 2.8, 5.3),
+        LinearColor::gray(0.8),
+    );
+    cube(
+        vec3(-9.8, -0.6, 5.3),
+        vec3(-8.6, 0.6, 6.4),
+        LinearColor::gray(0.15),
+    );
+    cube(
+        vec3(-9.8, -0.6, 6.4),
+        vec3(-8.6, 0.6, 7.0),
+        LinearColor::new(0.6, 0.1, 0.05, 1.0),
+    );
+
+    for i in 0..3 {
+        let x = -5.5 + i as f32 * 4.2;
+        for (j, (y0, y1)) in [(-2.5, -0.1), (0.1, 2.5)].into_iter().enumerate() {
+            cube(
+                vec3(x, y0, 1.6),
+                vec3(x + 4.0, y1, 4.2),
+                containers[(i + 2 * j) % containers.len()],
+            );
+        }
+    }
+
+    mb.build(gfx).unwrap() // Unwrap ok: the boat has vertices
+}
+
+/// Adds the convex outline, counter clockwise, extruded from `z0` to `z1`, without its bottom
+fn extend_prism(mb: &mut MeshBuilder<false>, outline: &[Vec2], z0: f32, z1: f32, col: LinearColor) {
+    let vertex = |p: Vec3, normal: Vec3| MeshVertex {
+        position: p.into(),
+        normal,
+        uv: [0.0; 2],
+        color: col.into(),
+        tangent: [0.0; 4],
+    };
+    mb.extend_with(None, |vertices, add_index| {
+        let start = vertices.len();
+        for &p in outline {
+            vertices.push(vertex(p.z(z1), Vec3::Z));
+        }
+        for i in 1..outline.len() - 1 {
+            for k in [0, i, i + 1] {
+                add_index(k as IndexType);
+            }
+        }
+
+        for (i, &a) in outline.iter().enumerate() {
+            let b = outline[(i + 1) % outline.len()];
+            let normal = (b - a).perpendicular().normalize().z0();
+            let base = (vertices.len() - start) as IndexType;
+            for p in [a.z(z0), b.z(z0), b.z(z1), a.z(z1)] {
+                vertices.push(vertex(p, normal));
+            }
+            for k in [0, 1, 2, 0, 2, 3] {
+                add_index(base + k);
+            }
+        }
+    });
+}
@@ -67,11 +67,23 @@ pub struct ImmediateOrder {
     pub color: LinearColor,
 }
 
-#[derive(Default)]
 pub struct ImmediateDraw {
     pub orders: Vec<ImmediateOrder>,
     pub persistent_orders: Vec<ImmediateOrder>,
     pub mesh_cache: FastMap<PathBuf, InstancedMeshBuilder<true>>,
+    /// Multiplies the thickness of the lines, set from the accessibility settings
+    pub thickness_scale: f32,
+}
+
+impl Default for ImmediateDraw {
+    fn default() -> Self {
+        Self {
+            orders: Vec::new(),
+            persistent_orders: Vec::new(),
+            mesh_cache: FastMap::default(),
+            thickness_scale: 1.0,
+        }
+    }
 }
 
 pub struct ImmediateBuilder<'a> {
@@ -200,6 +212,7 @@ impl ImmediateDraw {
     }
 
     pub fn apply(&mut self, tess: &mut Tesselator, ctx: &mut FrameContext<'_>) {
+        let scale = self.thickness_scale;
         for ImmediateOrder { kind, color } in
             self.persistent_orders.iter().chain(self.orders.iter())
         {
@@ -213,21 +226,21 @@ impl ImmediateDraw {
                     to,
                     thickness,
                 } => {
-                    tess.draw_stroke(from, to, thickness);
+                    tess.draw_stroke(from, to, thickness * scale);
                 }
                 OrderKind::StrokeCircle {
                     pos,
                     radius,
                     thickness,
                 } => {
-                    tess.draw_stroke_circle(pos, radius, thickness);
+                    tess.draw_stroke_circle(pos, radius, thickness * scale);
                 }
                 OrderKind::PolyLine {
                     ref points,
                     thickness,
                     loops,
                 } => {
-                    tess.draw_polyline(points, thickness, loops);
+                    tess.draw_polyline(points, thickness * scale, loops);
                 }
                 OrderKind::Polygon { ref poly, z } => {
                     tess.draw_filled_polygon(poly.as_slice(), z);
@@ -264,11 +277,7 @@ impl ImmediateDraw {
                         self.mesh_cache.get_mut(path.as_ref()).unwrap()
                     };
 
-                    i.instances.push(MeshInstance {
-                        pos,
-                        dir,
-                        tint: color.a(1.0),
-                    });
+                    i.instances.push(MeshInstance::new(pos, dir, color.a(1.0)));
                 }
             }
         }
@@ -0,0 +1,168 @@
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use common::FastMap;
+use geom::Transform;
+use simulation::{AnyEntity, HumanID, TrainID, VehicleID, WagonID, World};
+
+/// Past this distance in one update the entity was teleported (spawned, unparked...),
+/// it is drawn where it is instead of sliding across the map
+const MAX_INTERPOLATED_DISTANCE: f32 = 20.0;
+
+/// How long the lag indicator stays up after the simulation fell behind
+const LAG_INDICATOR_DURATION: Duration = Duration::from_secs(1);
+
+/// Transforms of the moving entities before the last update of the simulation, so they can be
+/// drawn between the states before and after it when the screen refreshes faster than the
+/// updates, which can run several ticks at once.
+/// Only used to draw, the simulation never reads it.
+pub struct Interpolation {
+    vehicles: FastMap<VehicleID, Transform>,
+    humans: FastMap<HumanID, Transform>,
+    trains: FastMap<TrainID, Transform>,
+    wagons: FastMap<WagonID, Transform>,
+    /// Where the frame is between the state before the last update (0) and after it (1)
+    pub alpha: f32,
+    /// Last time the simulation could not keep up
+    last_lag: Option<Instant>,
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Self {
+            vehicles: Default::default(),
+            humans: Default::default(),
+            trains: Default::default(),
+            wagons: Default::default(),
+            alpha: 1.0,
+            last_lag: None,
+        }
+    }
+}
+
+impl Interpolation {
+    /// Remembers the transforms before an update
+    pub fn snapshot(&mut self, world: &World) {
+        profiling::scope!("interpolation::snapshot");
+        snapshot(
+            &mut self.vehicles,
+            world.vehicles.iter().map(|(id, v)| (id, v.trans)),
+        );
+        snapshot(
+            &mut self.humans,
+            world.humans.iter().map(|(id, h)| (id, h.trans)),
+        );
+        snapshot(
+            &mut self.trains,
+            world.trains.iter().map(|(id, t)| (id, t.trans)),
+        );
+        snapshot(
+            &mut self.wagons,
+            world.wagons.iter().map(|(id, w)| (id, w.trans)),
+        );
+    }
+
+    /// The simulation dropped time this frame, what is drawn stays at the last tick
+    pub fn set_lagging(&mut self, lagging: bool) {
+        if lagging {
+            self.last_lag = Some(Instant::now());
+        }
+    }
+
+    /// Whether the simulation fell behind recently, shown on screen
+    pub fn is_lagging(&self) -> bool {
+        self.last_lag
+            .is_some_and(|t| t.elapsed() < LAG_INDICATOR_DURATION)
+    }
+
+    /// Forgets the previous state, everything is drawn at its current transform
+    pub fn clear(&mut self) {
+        self.vehicles.clear();
+        self.humans.clear();
+        self.trains.clear();
+        self.wagons.clear();
+        self.alpha = 1.0;
+    }
+
+    pub fn vehicle(&self, id: VehicleID, cur: &Transform) -> Transform {
+        lerp(&self.vehicles, id, cur, self.alpha)
+    }
+
+    pub fn human(&self, id: HumanID, cur: &Transform) -> Transform {
+        lerp(&self.humans, id, cur, self.alpha)
+    }
+
+    pub fn train(&self, id: TrainID, cur: &Transform) -> Transform {
+        lerp(&self.trains, id, cur, self.alpha)
+    }
+
+    pub fn wagon(&self, id: WagonID, cur: &Transform) -> Transform {
+        lerp(&self.wagons, id, cur, self.alpha)
+    }
+
+    /// Interpolated transform of a moving entity, the cameras follow it without jitter
+    pub fn trans_any(&self, world: &World, e: AnyEntity) -> Option<Transform> {
+        Some(match e {
+            AnyEntity::VehicleID(id) => self.vehicle(id, &world.get(id)?.trans),
+            AnyEntity::HumanID(id) => self.human(id, &world.get(id)?.trans),
+            AnyEntity::TrainID(id) => self.train(id, &world.get(id)?.trans),
+            AnyEntity::WagonID(id) => self.wagon(id, &world.get(id)?.trans),
+            _ => return None,
+        })
+    }
+}
+
+fn snapshot<K: Hash + Eq>(
+    prev: &mut FastMap<K, Transform>,
+    cur: impl Iterator<Item = (K, Transform)>,
+) {
+    prev.clear();
+    prev.extend(cur);
+}
+
+fn lerp<K: Hash + Eq>(
+    prev: &FastMap<K, Transform>,
+    id: K,
+    cur: &Transform,
+    alpha: f32,
+) -> Transform {
+    let Some(prev) = prev.get(&id) else {
+        return *cur;
+    };
+    if alpha >= 1.0
+        || prev.pos.distance2(cur.pos) > MAX_INTERPOLATED_DISTANCE * MAX_INTERPOLATED_DISTANCE
+    {
+        return *cur;
+    }
+    Transform {
+        pos: prev.pos.lerp(cur.pos, alpha),
+        dir: prev
+            .dir
+            .lerp(cur.dir, alpha)
+            .try_normalize()
+            .unwrap_or(cur.dir),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::Vec3;
+    use slotmapd::KeyData;
+
+    #[test]
+    fn interpolates_between_ticks_but_not_teleports() {
+        let id = VehicleID::from(KeyData::from_ffi(1 << 32));
+        let mut prev = FastMap::default();
+        prev.insert(id, Transform::new_dir(Vec3::ZERO, Vec3::X));
+
+        let cur = Transform::new_dir(Vec3::new(2.0, 0.0, 0.0), Vec3::Y);
+        let mid = lerp(&prev, id, &cur, 0.5);
+        assert_eq!(mid.pos, Vec3::new(1.0, 0.0, 0.0));
+        assert!((mid.dir.mag() - 1.0).abs() < 1e-5);
+        assert_eq!(lerp(&prev, id, &cur, 1.0).pos, cur.pos);
+
+        let far = Transform::new(Vec3::new(500.0, 0.0, 0.0));
+        assert_eq!(lerp(&prev, id, &far, 0.5).pos, far.pos);
+    }
+}
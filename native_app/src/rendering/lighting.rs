@@ -0,0 +1,82 @@
+use geom::{LinearColor, SunPosition, Vec3};
+
+/// The sun and the sky of a frame
+#[derive(Debug, Copy, Clone)]
+pub struct Lighting {
+    /// Normalized direction towards the sun
+    pub sun: Vec3,
+    pub sun_col: LinearColor,
+    /// From 0 for a clear sky to 1 when the clouds hide the sun
+    pub overcast: f32,
+    /// How bright the street lamps are, from 0 at day to 1 at night
+    pub lamp_intensity: f32,
+}
+
+impl Lighting {
+    /// Clear sky lighting with the sun at that position
+    pub fn from_sun(sun: Vec3) -> Self {
+        Self {
+            sun,
+            sun_col: 4.0
+                * sun.z.max(0.0).sqrt().sqrt()
+                * LinearColor::new(1.0, 0.95 + sun.z * 0.05, 0.95 + sun.z * 0.05, 1.0),
+            overcast: 0.0,
+            // lamps turn on at dusk and reach full power once the sun is below the horizon
+            lamp_intensity: ((0.1 - sun.z) / 0.15).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Lighting of the photo mode, replacing the one of the time of day.
+/// The presets keep the sun on the side of the sky it is, only changing its height.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum LightingPreset {
+    /// The sun where the time of day and the latitude put it
+    #[default]
+    Computed,
+    GoldenHour,
+    Overcast,
+    Night,
+}
+
+impl LightingPreset {
+    pub const ALL: [LightingPreset; 4] = [
+        LightingPreset::Computed,
+        LightingPreset::GoldenHour,
+        LightingPreset::Overcast,
+        LightingPreset::Night,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LightingPreset::Computed => "Time of day",
+            LightingPreset::GoldenHour => "Golden hour",
+            LightingPreset::Overcast => "Overcast",
+            LightingPreset::Night => "Night",
+        }
+    }
+
+    pub fn apply(self, computed: Lighting) -> Lighting {
+        let at_elevation = |degrees: f32| {
+            SunPosition {
+                elevation: degrees.to_radians(),
+                ..SunPosition::from_direction(computed.sun)
+            }
+            .direction()
+        };
+
+        match self {
+            LightingPreset::Computed => computed,
+            LightingPreset::GoldenHour => Lighting {
+                sun_col: LinearColor::new(2.6, 1.5, 0.8, 1.0),
+                ..Lighting::from_sun(at_elevation(6.0))
+            },
+            LightingPreset::Overcast => Lighting {
+                sun_col: LinearColor::new(1.3, 1.3, 1.4, 1.0),
+                overcast: 0.85,
+                ..Lighting::from_sun(at_elevation(50.0))
+            },
+            LightingPreset::Night => Lighting::from_sun(at_elevation(-15.0)),
+        }
+    }
+}
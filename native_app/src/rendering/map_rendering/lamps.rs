@@ -5,6 +5,10 @@ use geom::{Vec3, AABB3, V3};
 use simulation::map::{
     Map, MapSubscriber, ProjectFilter, ProjectKind, SubscriberChunkID, UpdateType,
 };
+use simulation::Simulation;
+
+/// Vehicles further than this from the camera do not light the road
+const HEADLIGHTS_DISTANCE: f32 = 500.0;
 
 pub struct LampsRender {
     lamp_memory: FastMap<LightChunkID, Vec<Vec3>>,
@@ -14,7 +18,7 @@ pub struct LampsRender {
 
 impl LampsRender {
     pub fn new(map: &Map) -> Self {
-        let lamp_sub = map.subscribe(UpdateType::Road);
+        let lamp_sub = map.subscribe(UpdateType::RoadGeometry);
         Self {
             lamp_memory: FastMap::default(),
             lamp_road_memory: FastMap::default(),
@@ -119,4 +123,24 @@ impl LampsRender {
                 .register_update(chunk, lamps.iter().copied());
         }
     }
+
+    /// The headlights are approximated by a point light a few meters ahead of moving vehicles
+    pub fn update_headlights(&mut self, sim: &Simulation, ctx: &mut Context) {
+        profiling::scope!("headlights");
+        let params = ctx.gfx.render_params.value();
+        let cam = params.cam_pos;
+        if params.lamp_intensity <= 0.0 {
+            ctx.gfx.lamplights.set_dynamic_lights(std::iter::empty());
+            return;
+        }
+
+        let world = sim.world();
+        let lights = world
+            .vehicles
+            .values()
+            .filter(|v| v.speed.0 > 0.5)
+            .filter(|v| v.trans.pos.distance2(cam) < HEADLIGHTS_DISTANCE * HEADLIGHTS_DISTANCE)
+            .map(|v| v.trans.pos + v.trans.dir * 6.0 + V3::Z);
+        ctx.gfx.lamplights.set_dynamic_lights(lights);
+    }
 }
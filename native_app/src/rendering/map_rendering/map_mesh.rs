@@ -1,5 +1,6 @@
-use crate::rendering::MapRenderOptions;
-use common::FastMap;
+use crate::rendering::map_rendering::relevance::ChunkRelevance;
+use crate::rendering::{palette, ColorPalette, MapRenderOptions};
+use common::{FastMap, FastSet};
 use engine::earcut::earcut;
 use engine::MeshBuilder;
 use engine::{
@@ -7,15 +8,76 @@ use engine::{
     MeshVertex, MetallicRoughness, SpriteBatch, SpriteBatchBuilder, Tesselator,
 };
 use geom::{minmax, vec2, vec3, Color, LinearColor, PolyLine3, Polygon, Radians, Vec2, Vec3};
-use prototypes::{FreightStationPrototype, GoodsCompanyPrototype, RenderAsset};
+use prototypes::{
+    DockPrototype, FreightStationPrototype, GoodsCompanyPrototype, LeisurePrototype, RenderAsset,
+    ServiceDepotPrototype, WarehousePrototype,
+};
 use simulation::map::{
-    Building, BuildingKind, CanonicalPosition, Environment, Intersection, LaneKind, Lanes, LotKind,
-    Map, MapSubscriber, ProjectFilter, ProjectKind, PylonPosition, Road, Roads, SubscriberChunkID,
-    Turn, TurnKind, UpdateType, CROSSWALK_WIDTH, ROAD_Z_OFFSET,
+    Building, BuildingID, BuildingKind, CanonicalPosition, Environment, FieldStage, Intersection,
+    Intersections, Lane, LaneKind, Lanes, LotKind, Map, MapSubscriber, ProjectFilter, ProjectKind,
+    PylonPosition, Road, RoadCondition, Roads, SubscriberChunkID, Turn, TurnDirection, TurnKind,
+    UpdateType, CROSSWALK_WIDTH, ROAD_Z_OFFSET,
 };
 use simulation::Simulation;
 use std::ops::{Mul, Neg};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Time spent building the meshes of changed chunks each frame
+const MESH_BUILD_BUDGET: Duration = Duration::from_millis(8);
+/// Length of the dashes between the lanes vehicles can change between
+const DASH_LENGTH: f32 = 3.0;
+/// Space between two dashes
+const DASH_GAP: f32 = 6.0;
+/// Distance from the end of the lane to the stop line
+const STOP_LINE_DIST: f32 = 1.0;
+/// Distance from the end of the lane to the turn arrows
+const TURN_ARROW_DIST: f32 = 7.0;
+/// Lanes shorter than this before the arrows have none
+const TURN_ARROW_MIN_LANE: f32 = 5.0;
+/// Distance between the ties of the rails
+const TIE_SPACING: f32 = 0.75;
+/// Width of the top of the ballast, the rest of the rail lane is its shoulders
+const BALLAST_TOP_WIDTH: f32 = 3.4;
+/// Longest span of the overhead line between two poles
+const CATENARY_SPACING: f32 = 50.0;
+/// Height of the wire above the rails at the poles
+pub const WIRE_HEIGHT: f32 = 5.8;
+/// How far the wire hangs down in the middle of a span, per meter of span
+const WIRE_SAG: f32 = 0.006;
+/// Segments of the wire between two poles
+const WIRE_SEGMENTS: usize = 8;
+/// Distance from the camera to a chunk beyond which its ties, poles and wires are not drawn
+const RAIL_DETAIL_DIST: f32 = 1500.0;
+/// Distance between the cones along the lanes closed by roadworks
+const CONE_SPACING: f32 = 4.0;
+/// Grime multiplied over the colors of the derelict buildings
+const DERELICT_TINT: LinearColor = LinearColor {
+    r: 0.55,
+    g: 0.5,
+    b: 0.42,
+    a: 1.0,
+};
+
+/// Tint of the floor of a farm field and size of its plants at each growth stage,
+/// the whole field shows the same stage
+fn field_look(stage: FieldStage) -> ([f32; 4], f32) {
+    match stage {
+        FieldStage::Fallow => ([0.85, 0.75, 0.65, 1.0], 0.0),
+        FieldStage::Sown => ([0.75, 0.75, 0.6, 1.0], 0.3),
+        FieldStage::Growing => ([0.65, 0.8, 0.5, 1.0], 0.7),
+        FieldStage::Ripe => ([1.0, 0.9, 0.6, 1.0], 1.0),
+    }
+}
+
+/// Tint of the building, run down if it is derelict
+fn building_tint(building: &Building) -> LinearColor {
+    if building.derelict {
+        DERELICT_TINT
+    } else {
+        LinearColor::WHITE
+    }
+}
 
 /// This is the main struct that handles the map rendering.
 /// It is responsible for generating the meshes and sprites for the map
@@ -25,6 +87,12 @@ pub struct MapMeshHandler {
     cache: FastMap<SubscriberChunkID, CachedObj>,
     road_sub: MapSubscriber,
     building_sub: MapSubscriber,
+    /// Chunks changed or freed since their roads were built, built again once relevant
+    dirty_roads: FastSet<SubscriberChunkID>,
+    /// Same as `dirty_roads` for the buildings
+    dirty_buildings: FastSet<SubscriberChunkID>,
+    /// Palette the lots were colored with
+    palette: ColorPalette,
 }
 
 #[derive(Default)]
@@ -33,6 +101,10 @@ struct CachedObj {
     build: Vec<Arc<dyn Drawable>>,
     lots: Option<Mesh>,
     arrows: Option<SpriteBatch>,
+    /// Ties and overhead lines of the rails, only drawn close to the camera
+    rail_details: Vec<Arc<dyn Drawable>>,
+    /// Cones and barriers of the roadworks
+    roadworks: Vec<Arc<dyn Drawable>>,
 }
 
 impl CachedObj {
@@ -41,6 +113,8 @@ impl CachedObj {
             && self.lots.is_none()
             && self.arrows.is_none()
             && self.build.is_empty()
+            && self.rail_details.is_empty()
+            && self.roadworks.is_empty()
     }
 }
 
@@ -48,11 +122,17 @@ struct MapBuilders {
     buildsprites: FastMap<BuildingKind, SpriteBatchBuilder<false>>,
     buildmeshes: FastMap<BuildingKind, InstancedMeshBuilder<false>>,
     houses_mesh: MeshBuilder<false>,
+    outline_house: MeshBuilder<false>,
     zonemeshes: FastMap<BuildingKind, (MeshBuilder<false>, InstancedMeshBuilder<false>, bool)>,
     arrow_builder: SpriteBatchBuilder<false>,
     crosswalk_builder: MeshBuilder<false>,
     mesh_map: MeshBuilder<false>,
     mesh_lots: MeshBuilder<false>,
+    rail_ties: InstancedMeshBuilder<false>,
+    rail_poles: InstancedMeshBuilder<false>,
+    rail_wires: MeshBuilder<false>,
+    cones: InstancedMeshBuilder<false>,
+    barriers: InstancedMeshBuilder<false>,
 }
 
 impl MapMeshHandler {
@@ -83,6 +163,19 @@ impl MapMeshHandler {
                 FreightStationPrototype::iter()
                     .map(|descr| (&descr.asset, BuildingKind::RailFreightStation(descr.id))),
             )
+            .chain(DockPrototype::iter().map(|descr| (&descr.asset, BuildingKind::Dock(descr.id))))
+            .chain(
+                WarehousePrototype::iter()
+                    .map(|descr| (&descr.asset, BuildingKind::Warehouse(descr.id))),
+            )
+            .chain(
+                ServiceDepotPrototype::iter()
+                    .map(|descr| (&descr.asset, BuildingKind::ServiceDepot(descr.id))),
+            )
+            .chain(
+                LeisurePrototype::iter()
+                    .map(|descr| (&descr.asset, BuildingKind::Leisure(descr.id))),
+            )
             .chain([(
                 &RenderAsset::Mesh {
                     path: "external_trading.glb".into(),
@@ -149,7 +242,7 @@ impl MapMeshHandler {
             },
             None,
         ));
-        let houses_mat = gfx.register_material(Material::new(
+        let mut houses_mat = Material::new(
             gfx,
             &gfx.palette(),
             MetallicRoughness {
@@ -158,111 +251,347 @@ impl MapMeshHandler {
                 tex: None,
             },
             None,
-        ));
+        );
+        houses_mat.set_lit_windows(&gfx.queue, LinearColor::new(1.0, 0.75, 0.45, 1.0));
+        let houses_mat = gfx.register_material(houses_mat);
         let builders = MapBuilders {
             arrow_builder,
             buildsprites,
             crosswalk_builder: MeshBuilder::new(crosswalk_mat),
             mesh_map: MeshBuilder::new(gfx.tess_material),
             houses_mesh: MeshBuilder::new(houses_mat),
+            outline_house: MeshBuilder::new(houses_mat),
             buildmeshes,
             zonemeshes,
             mesh_lots: MeshBuilder::new(gfx.tess_material),
+            rail_ties: detail_mesh(
+                gfx,
+                &[(
+                    vec3(-0.12, -1.3, -0.04),
+                    vec3(0.12, 1.3, 0.015),
+                    LinearColor::gray(0.4),
+                )],
+            ),
+            rail_poles: detail_mesh(
+                gfx,
+                &[
+                    (
+                        vec3(-0.15, -3.1, 0.0),
+                        vec3(0.15, -2.8, WIRE_HEIGHT + 0.6),
+                        LinearColor::gray(0.45),
+                    ),
+                    (
+                        vec3(-0.06, -2.95, WIRE_HEIGHT + 0.1),
+                        vec3(0.06, 0.3, WIRE_HEIGHT + 0.25),
+                        LinearColor::gray(0.45),
+                    ),
+                ],
+            ),
+            rail_wires: MeshBuilder::new(gfx.tess_material),
+            cones: detail_mesh(
+                gfx,
+                &[
+                    (
+                        vec3(-0.2, -0.2, 0.0),
+                        vec3(0.2, 0.2, 0.04),
+                        LinearColor::ORANGE,
+                    ),
+                    (
+                        vec3(-0.12, -0.12, 0.04),
+                        vec3(0.12, 0.12, 0.25),
+                        LinearColor::ORANGE,
+                    ),
+                    (
+                        vec3(-0.1, -0.1, 0.25),
+                        vec3(0.1, 0.1, 0.35),
+                        LinearColor::WHITE,
+                    ),
+                    (
+                        vec3(-0.07, -0.07, 0.35),
+                        vec3(0.07, 0.07, 0.5),
+                        LinearColor::ORANGE,
+                    ),
+                ],
+            ),
+            barriers: detail_mesh(
+                gfx,
+                &[
+                    (
+                        vec3(-0.05, -1.2, 0.0),
+                        vec3(0.05, -1.1, 0.9),
+                        LinearColor::gray(0.3),
+                    ),
+                    (
+                        vec3(-0.05, 1.1, 0.0),
+                        vec3(0.05, 1.2, 0.9),
+                        LinearColor::gray(0.3),
+                    ),
+                    (
+                        vec3(-0.06, -1.25, 0.6),
+                        vec3(0.06, 1.25, 0.85),
+                        LinearColor::RED,
+                    ),
+                    (
+                        vec3(-0.07, -0.4, 0.6),
+                        vec3(0.07, 0.4, 0.85),
+                        LinearColor::WHITE,
+                    ),
+                ],
+            ),
         };
 
         Self {
             builders,
             cache: Default::default(),
-            road_sub: sim.map().subscribe(UpdateType::Road),
-            building_sub: sim.map().subscribe(UpdateType::Building),
+            road_sub: sim.map().subscribe_multi(&[
+                UpdateType::RoadGeometry,
+                UpdateType::IntersectionControl,
+                UpdateType::RoadSurface,
+                UpdateType::LotZoning,
+            ]),
+            building_sub: sim.map().subscribe_multi(&[
+                UpdateType::BuildingAdded,
+                UpdateType::BuildingRemoved,
+                UpdateType::BuildingChanged,
+            ]),
+            dirty_roads: Default::default(),
+            dirty_buildings: Default::default(),
+            palette: ColorPalette::current(),
         }
     }
 
-    pub fn latest_mesh(
+    /// Meshes of the building alone, used to draw its outline
+    pub fn building_outline(
         &mut self,
         map: &Map,
-        options: MapRenderOptions,
-        ctx: &mut FrameContext<'_>,
-    ) {
-        profiling::scope!("draw map mesh");
-        for chunk in self.road_sub.take_updated_chunks() {
-            profiling::scope!("build road chunk");
-            let b = &mut self.builders;
-            b.map_mesh(map, chunk);
-
-            let cached = self.cache.entry(chunk).or_default();
+        id: BuildingID,
+        gfx: &GfxContext,
+    ) -> Vec<Box<dyn Drawable>> {
+        let mut meshes: Vec<Box<dyn Drawable>> = vec![];
+        let Some(building) = map.buildings().get(id) else {
+            return meshes;
+        };
+        let b = &mut self.builders;
 
-            cached.road.clear();
-            cached.road.reserve(2);
+        b.outline_house.clear();
+        house_faces(&mut b.outline_house, building);
+        if let Some(mesh) = b.outline_house.build(gfx) {
+            meshes.push(Box::new(mesh));
+        }
 
-            if let Some(mesh) = b.mesh_map.build(ctx.gfx) {
-                cached.road.push(Arc::new(mesh));
-            }
-            if let Some(mesh) = b.crosswalk_builder.build(ctx.gfx) {
-                cached.road.push(Arc::new(mesh));
+        if let Some(x) = b.buildmeshes.get(&building.kind) {
+            let mut single = InstancedMeshBuilder::<false>::new_ref(x.mesh());
+            single.instances.push(MeshInstance::new(
+                building.obb.center().z(building.height),
+                building.obb.axis()[0].normalize().z0(),
+                LinearColor::WHITE,
+            ));
+            if let Some(mesh) = single.build(gfx) {
+                meshes.push(Box::new(mesh));
             }
+        }
 
-            cached.lots = b.mesh_lots.build(ctx.gfx);
-            cached.arrows = b.arrow_builder.build(ctx.gfx);
+        meshes
+    }
 
-            if cached.is_empty() {
-                self.cache.remove(&chunk);
-            }
+    /// Number of relevant road chunks waiting to be meshed
+    pub fn pending_road_chunks(&self, relevance: &ChunkRelevance) -> usize {
+        self.road_sub.pending_chunks()
+            + self
+                .dirty_roads
+                .iter()
+                .filter(|&&c| relevance.is_relevant(c))
+                .count()
+    }
+
+    /// Number of relevant building chunks waiting to be meshed
+    pub fn pending_building_chunks(&self, relevance: &ChunkRelevance) -> usize {
+        self.building_sub.pending_chunks()
+            + self
+                .dirty_buildings
+                .iter()
+                .filter(|&&c| relevance.is_relevant(c))
+                .count()
+    }
+
+    /// Number of chunks with meshes on the gpu
+    pub fn resident_chunks(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Chunks in view with nothing built yet, shown with a placeholder after a camera jump
+    pub fn unbuilt_chunks<'a>(
+        &'a self,
+        relevance: &'a ChunkRelevance,
+    ) -> impl Iterator<Item = SubscriberChunkID> + 'a {
+        let roads = self.dirty_roads.iter();
+        let buildings = self
+            .dirty_buildings
+            .iter()
+            .filter(move |&c| !self.dirty_roads.contains(c));
+        roads
+            .chain(buildings)
+            .copied()
+            .filter(move |c| !self.cache.contains_key(c) && relevance.is_visible(*c))
+    }
+
+    /// Takes the chunks changed in the map, they are built once relevant.
+    /// The chunks left far behind are freed and built again when coming back.
+    pub fn take_changes(&mut self, relevance: &ChunkRelevance) {
+        if self.palette != ColorPalette::current() {
+            self.palette = ColorPalette::current();
+            self.dirty_roads.extend(self.cache.keys().copied());
+        }
+
+        self.dirty_roads.extend(self.road_sub.take_updated_chunks());
+        self.dirty_buildings
+            .extend(self.building_sub.take_updated_chunks());
+
+        let evicted: Vec<_> = self
+            .cache
+            .keys()
+            .copied()
+            .filter(|&c| relevance.should_evict(c))
+            .collect();
+        for chunk in evicted {
+            self.cache.remove(&chunk);
+            self.dirty_roads.insert(chunk);
+            self.dirty_buildings.insert(chunk);
         }
+    }
 
-        for chunk in self.building_sub.take_updated_chunks() {
-            profiling::scope!("build building chunk");
+    fn build_road_chunk(&mut self, map: &Map, chunk: SubscriberChunkID, gfx: &GfxContext) {
+        profiling::scope!("build road chunk");
+        let b = &mut self.builders;
+        b.map_mesh(map, chunk);
 
-            let b = &mut self.builders;
-            b.buildings_mesh(map, chunk);
+        let cached = self.cache.entry(chunk).or_default();
 
-            let cached = self.cache.entry(chunk).or_default();
+        cached.road.clear();
+        cached.road.reserve(2);
 
-            cached.build.clear();
-            cached.build.reserve(4);
+        if let Some(mesh) = b.mesh_map.build(gfx) {
+            cached.road.push(Arc::new(mesh));
+        }
+        if let Some(mesh) = b.crosswalk_builder.build(gfx) {
+            cached.road.push(Arc::new(mesh));
+        }
 
-            let sprites = b
-                .buildsprites
-                .values_mut()
-                .flat_map(|x| x.build(ctx.gfx))
-                .collect::<Vec<_>>();
+        cached.lots = b.mesh_lots.build(gfx);
+        cached.arrows = b.arrow_builder.build(gfx);
 
-            if !sprites.is_empty() {
-                cached.build.push(Arc::new(sprites));
-            }
+        cached.rail_details.clear();
+        if let Some(mesh) = b.rail_ties.build(gfx) {
+            cached.rail_details.push(Arc::new(mesh));
+        }
+        if let Some(mesh) = b.rail_poles.build(gfx) {
+            cached.rail_details.push(Arc::new(mesh));
+        }
+        if let Some(mesh) = b.rail_wires.build(gfx) {
+            cached.rail_details.push(Arc::new(mesh));
+        }
 
-            let buildmeshes = b
-                .buildmeshes
-                .values_mut()
-                .flat_map(|x| x.build(ctx.gfx))
-                .collect::<Vec<_>>();
+        cached.roadworks.clear();
+        if let Some(mesh) = b.cones.build(gfx) {
+            cached.roadworks.push(Arc::new(mesh));
+        }
+        if let Some(mesh) = b.barriers.build(gfx) {
+            cached.roadworks.push(Arc::new(mesh));
+        }
 
-            if !buildmeshes.is_empty() {
-                cached.build.push(Arc::new(buildmeshes));
-            }
+        if cached.is_empty() {
+            self.cache.remove(&chunk);
+        }
+    }
 
-            if let Some(mesh) = b.houses_mesh.build(ctx.gfx) {
-                cached.build.push(Arc::new(mesh));
-            }
+    fn build_building_chunk(&mut self, map: &Map, chunk: SubscriberChunkID, gfx: &GfxContext) {
+        profiling::scope!("build building chunk");
+
+        let b = &mut self.builders;
+        b.buildings_mesh(map, chunk);
+
+        let cached = self.cache.entry(chunk).or_default();
+
+        cached.build.clear();
+        cached.build.reserve(4);
+
+        let sprites = b
+            .buildsprites
+            .values_mut()
+            .flat_map(|x| x.build(gfx))
+            .collect::<Vec<_>>();
+
+        if !sprites.is_empty() {
+            cached.build.push(Arc::new(sprites));
+        }
+
+        let buildmeshes = b
+            .buildmeshes
+            .values_mut()
+            .flat_map(|x| x.build(gfx))
+            .collect::<Vec<_>>();
+
+        if !buildmeshes.is_empty() {
+            cached.build.push(Arc::new(buildmeshes));
+        }
+
+        if let Some(mesh) = b.houses_mesh.build(gfx) {
+            cached.build.push(Arc::new(mesh));
+        }
+
+        let zonemeshes = b
+            .zonemeshes
+            .values_mut()
+            .flat_map(|(a, b, _)| a.build(gfx).zip(b.build(gfx)))
+            .collect::<Vec<_>>();
+        if !zonemeshes.is_empty() {
+            cached.build.push(Arc::new(zonemeshes));
+        }
+
+        if cached.is_empty() {
+            self.cache.remove(&chunk);
+        }
+    }
+
+    pub fn latest_mesh(
+        &mut self,
+        map: &Map,
+        options: MapRenderOptions,
+        relevance: &ChunkRelevance,
+        ctx: &mut FrameContext<'_>,
+    ) {
+        profiling::scope!("draw map mesh");
 
-            let zonemeshes = b
-                .zonemeshes
-                .values_mut()
-                .flat_map(|(a, b, _)| a.build(ctx.gfx).zip(b.build(ctx.gfx)))
-                .collect::<Vec<_>>();
-            if !zonemeshes.is_empty() {
-                cached.build.push(Arc::new(zonemeshes));
+        // many chunks change at once when loading a map or jumping across it,
+        // build the nearest ones first over several frames
+        let start = Instant::now();
+        for chunk in relevance.nearest_first(self.dirty_roads.iter().copied()) {
+            if start.elapsed() >= MESH_BUILD_BUDGET {
+                break;
             }
+            self.dirty_roads.remove(&chunk);
+            self.build_road_chunk(map, chunk, ctx.gfx);
+        }
 
-            if cached.is_empty() {
-                self.cache.remove(&chunk);
+        for chunk in relevance.nearest_first(self.dirty_buildings.iter().copied()) {
+            if start.elapsed() >= MESH_BUILD_BUDGET {
+                break;
             }
+            self.dirty_buildings.remove(&chunk);
+            self.build_building_chunk(map, chunk, ctx.gfx);
         }
 
         profiling::scope!("prepare map mesh");
-        for v in self.cache.values() {
+        let cam = ctx.gfx.render_params.value().cam_pos;
+        for (&chunk, v) in &self.cache {
             ctx.draw(v.build.clone());
             ctx.draw(v.road.clone());
+            if !v.roadworks.is_empty() {
+                ctx.draw(v.roadworks.clone());
+            }
+            if !v.rail_details.is_empty() && rail_details_visible(chunk, cam) {
+                ctx.draw(v.rail_details.clone());
+            }
             if options.show_arrows {
                 if let Some(ref x) = v.arrows {
                     ctx.draw(x.clone());
@@ -322,6 +651,44 @@ impl MapBuilders {
         }
     }
 
+    /// Stop lines at the controlled intersections and arrows showing where each lane can turn
+    fn lane_markings(
+        tess: &mut Tesselator,
+        line_col: LinearColor,
+        road_lanes: &[&Lane],
+        lanes: &Lanes,
+        inters: &Intersections,
+    ) {
+        tess.set_color(line_col);
+        for lane in road_lanes {
+            if !lane.kind.vehicles() {
+                continue;
+            }
+            let l = lane.points.length();
+            if l < STOP_LINE_DIST + 1.0 {
+                continue;
+            }
+            let half_w = lane.kind.width() * 0.5 - 0.25;
+
+            if lane.control.is_stop_sign() || lane.control.is_light() {
+                let (pos, dir) = lane.points.point_dir_along(l - STOP_LINE_DIST);
+                let side = dir.perp_up() * half_w;
+                tess.draw_stroke(pos.up(0.01) - side, pos.up(0.01) + side, 0.5);
+            }
+
+            let Some(inter) = inters.get(lane.dst) else {
+                continue;
+            };
+            if inter.roads.len() < 3 || l < TURN_ARROW_DIST + TURN_ARROW_MIN_LANE {
+                continue;
+            }
+            let (pos, dir) = lane.points.point_dir_along(l - TURN_ARROW_DIST);
+            for turn in inter.turn_directions_from(lane.id, lanes) {
+                turn_arrow(tess, pos.up(0.01), dir.xy(), turn);
+            }
+        }
+    }
+
     fn crosswalks(crosswalk_builder: &mut MeshBuilder<false>, inter: &Intersection, lanes: &Lanes) {
         const WALKING_W: f32 = LaneKind::Walking.width();
 
@@ -410,7 +777,7 @@ impl MapBuilders {
                 x.push(
                     c.z(building.height + 0.1),
                     d.z0(),
-                    LinearColor::WHITE,
+                    building_tint(building),
                     (w, h),
                 );
             }
@@ -419,11 +786,8 @@ impl MapBuilders {
                 let pos = building.obb.center().z(building.height);
                 let dir = building.obb.axis()[0].normalize().z0();
 
-                x.instances.push(MeshInstance {
-                    pos,
-                    dir,
-                    tint: LinearColor::WHITE,
-                });
+                x.instances
+                    .push(MeshInstance::new(pos, dir, building_tint(building)));
             }
         }
     }
@@ -438,6 +802,16 @@ impl MapBuilders {
         let zone = &bzone.poly;
         let randomize = *randomize;
 
+        let grows_crops = matches!(
+            building.kind,
+            BuildingKind::GoodsCompany(id) if !id.prototype().crops.is_empty()
+        );
+        let (floor_tint, plant_size) = if grows_crops {
+            field_look(bzone.stage)
+        } else {
+            ([1.0; 4], 1.0)
+        };
+
         let mut hull = building
             .mesh
             .faces
@@ -487,12 +861,15 @@ impl MapBuilders {
                 if hull.contains(pos) {
                     continue;
                 }
+                if plant_size == 0.0 {
+                    continue;
+                }
 
-                filler.instances.push(MeshInstance {
-                    pos: pos.z(building.height),
-                    dir: principal_axis.perpendicular().z0(),
-                    tint: LinearColor::WHITE,
-                });
+                filler.instances.push(MeshInstance::new(
+                    pos.z(building.height),
+                    principal_axis.perpendicular().z0() * plant_size,
+                    LinearColor::WHITE,
+                ));
             }
         }
 
@@ -504,7 +881,7 @@ impl MapBuilders {
                     position: p.z(building.height + 0.05).into(),
                     normal: Vec3::Z,
                     uv: ((*p + avg) * 0.05).into(),
-                    color: [1.0; 4],
+                    color: floor_tint,
                     tangent: [0.0; 4],
                 });
             }
@@ -518,46 +895,115 @@ impl MapBuilders {
     }
 
     fn houses_mesh(&mut self, building: &Building) {
-        for (face, col) in &building.mesh.faces {
-            self.houses_mesh.extend_with(None, |vertices, add_index| {
-                let o = face[1];
-                let u = unwrap_ret!((face[0] - o).try_normalize());
-                let v = unwrap_ret!((face[2] - o).try_normalize());
-
-                let mut nor = u.cross(v);
+        house_faces(&mut self.houses_mesh, building);
+    }
 
-                let mut reverse = false;
+    /// Gravel bed of a rail lane, the shoulders are darker than the top under the ties
+    fn draw_ballast(tess: &mut Tesselator, cut: &PolyLine3, off: f32, col: LinearColor) {
+        let first_dir = unwrap_ret!(cut.first_dir()).xy();
+        let last_dir = unwrap_ret!(cut.last_dir()).xy();
+        tess.set_color(0.8 * col);
+        tess.draw_polyline_full(
+            cut.as_slice().iter().copied(),
+            first_dir,
+            last_dir,
+            LaneKind::Rail.width(),
+            off,
+        );
+        tess.set_color(col);
+        tess.draw_polyline_full(
+            cut.as_slice().iter().map(|v| v.up(0.01)),
+            first_dir,
+            last_dir,
+            BALLAST_TOP_WIDTH,
+            off,
+        );
+    }
 
-                if nor.z < 0.0 {
-                    reverse = true;
-                    nor = -nor;
-                }
+    /// Cones along the lanes closed by the works, and barriers across them at both ends.
+    /// The roads without closed lanes get their barriers on the sides.
+    fn roadworks(
+        cones: &mut InstancedMeshBuilder<false>,
+        barriers: &mut InstancedMeshBuilder<false>,
+        road: &Road,
+        road_lanes: &[&Lane],
+    ) {
+        let mut any_closed = false;
+        for l in road_lanes.iter().filter(|l| l.closed) {
+            any_closed = true;
+            for (pos, dir) in l.points.equipoints_dir(CONE_SPACING, false) {
+                cones
+                    .instances
+                    .push(MeshInstance::new(pos, dir, LinearColor::WHITE));
+            }
+            for (pos, dir) in [
+                (l.points.first(), l.points.first_dir()),
+                (l.points.last(), l.points.last_dir()),
+            ] {
+                let Some(dir) = dir else {
+                    continue;
+                };
+                barriers
+                    .instances
+                    .push(MeshInstance::new(pos, dir, LinearColor::WHITE));
+            }
+        }
+        if any_closed {
+            return;
+        }
 
-                let mut projected = Polygon(Vec::with_capacity(face.len()));
-                for &p in face {
-                    let off = p - o;
-                    projected.0.push(vec2(off.dot(u), off.dot(v)));
+        let side = road.width * 0.5 - 1.0;
+        for (pos, dir) in [
+            (road.points.first(), road.points.first_dir()),
+            (road.points.last(), road.points.last_dir()),
+        ] {
+            let Some(dir) = dir else {
+                continue;
+            };
+            let perp = dir.xy().perpendicular().z0();
+            for off in [-side, side] {
+                barriers.instances.push(MeshInstance::new(
+                    pos + perp * off,
+                    dir,
+                    LinearColor::WHITE,
+                ));
+            }
+        }
+    }
 
-                    vertices.push(MeshVertex {
-                        position: p.into(),
-                        normal: nor,
-                        uv: [0.0; 2],
-                        color: col.into(),
-                        tangent: [0.0; 4],
-                    })
-                }
+    /// Ties at fixed spacing along the middle of the track
+    fn rail_ties(ties: &mut InstancedMeshBuilder<false>, line: &PolyLine3) {
+        for (pos, dir) in line.equipoints_dir(TIE_SPACING, true) {
+            ties.instances
+                .push(MeshInstance::new(pos, dir, LinearColor::WHITE));
+        }
+    }
 
-                projected.simplify();
+    /// Poles on the right of the track and the wire hanging between them above its middle
+    fn catenary(poles: &mut InstancedMeshBuilder<false>, wires: &mut Tesselator, line: &PolyLine3) {
+        let l = line.length();
+        let n_spans = (l / CATENARY_SPACING).ceil().max(1.0) as usize;
+        let span = l / n_spans as f32;
+
+        wires.set_color(LinearColor::gray(0.1));
+        let mut wire = Vec::with_capacity(WIRE_SEGMENTS + 1);
+        for i in 0..=n_spans {
+            let (pos, dir) = line.point_dir_along(i as f32 * span);
+            poles
+                .instances
+                .push(MeshInstance::new(pos, dir, LinearColor::WHITE));
+            if i == n_spans {
+                break;
+            }
 
-                earcut(&projected.0, &[], |mut a, b, mut c| {
-                    if reverse {
-                        std::mem::swap(&mut a, &mut c);
-                    }
-                    add_index(a as u32);
-                    add_index(b as u32);
-                    add_index(c as u32);
-                })
-            });
+            wire.clear();
+            wire.extend((0..=WIRE_SEGMENTS).map(|j| {
+                let t = j as f32 / WIRE_SEGMENTS as f32;
+                let (p, _) = line.point_dir_along((i as f32 + t) * span);
+                p.up(WIRE_HEIGHT - wire_sag(span, t))
+            }));
+            let (_, last_dir) = line.point_dir_along((i + 1) as f32 * span);
+            wires.draw_polyline_full(wire.iter().copied(), dir.xy(), last_dir.xy(), 0.06, 0.0);
         }
     }
 
@@ -594,14 +1040,22 @@ impl MapBuilders {
         self.crosswalk_builder.clear();
         self.mesh_map.clear();
         self.mesh_lots.clear();
+        self.rail_ties.instances.clear();
+        self.rail_poles.instances.clear();
+        self.rail_wires.clear();
+        self.cones.instances.clear();
+        self.barriers.instances.clear();
 
         let mut tess_map = self.mesh_map.mk_tess();
         let mut tess_lots = self.mesh_lots.mk_tess();
+        let mut tess_wires = self.rail_wires.mk_tess();
 
         let low_col: LinearColor = simulation::colors().road_low_col.into();
         let mid_col: LinearColor = simulation::colors().road_mid_col.into();
         let hig_col: LinearColor = simulation::colors().road_hig_col.into();
         let line_col: LinearColor = simulation::colors().road_line_col.into();
+        let bus_col: LinearColor = simulation::colors().road_bus_col.into();
+        let ballast_col: LinearColor = simulation::colors().rail_ballast_col.into();
 
         let objs = map.spatial_map().query(
             chunk.bbox(),
@@ -641,6 +1095,9 @@ impl MapBuilders {
 
             road_pylons(&mut tess_map, env, road);
 
+            let wear = wear_darkening(road.condition());
+            let (road_low_col, road_mid_col) = (wear * low_col, wear * mid_col);
+
             tess_map.normal.z = -1.0;
             tess_map.draw_polyline_full(
                 cut.iter().map(|x| x.up(-0.3)),
@@ -662,12 +1119,18 @@ impl MapBuilders {
                 );
             };
 
+            let road_lanes: Vec<&Lane> =
+                road.lanes_iter().flat_map(|(l, _)| lanes.get(l)).collect();
             let mut start = true;
-            for l in road.lanes_iter().flat_map(|(l, _)| lanes.get(l)) {
+            for (i, l) in road_lanes.iter().enumerate() {
                 if l.kind.is_rail() {
                     let off = l.dist_from_bottom - road.width * 0.5 + LaneKind::Rail.width() * 0.5;
-                    draw_off(&mut tess_map, mid_col, LaneKind::Rail.width(), off);
+                    Self::draw_ballast(&mut tess_map, cut, off, ballast_col);
                     Self::draw_rail(&mut tess_map, cut, off, true);
+                    Self::rail_ties(&mut self.rail_ties, &l.points);
+                    if road.electrified {
+                        Self::catenary(&mut self.rail_poles, &mut tess_wires, &l.points);
+                    }
                     start = true;
                     continue;
                 }
@@ -684,18 +1147,29 @@ impl MapBuilders {
                     &mut tess_map,
                     match l.kind {
                         LaneKind::Walking => hig_col,
-                        LaneKind::Parking => low_col,
-                        _ => mid_col,
+                        LaneKind::Parking => road_low_col,
+                        LaneKind::Bus => wear * bus_col,
+                        _ => road_mid_col,
                     },
                     l.kind.width() - 0.25,
                     l.dist_from_bottom - road.width * 0.5 + l.kind.width() * 0.5,
                 );
-                draw_off(
-                    &mut tess_map,
-                    line_col,
-                    0.25,
-                    l.dist_from_bottom - road.width * 0.5 + l.kind.width(),
-                );
+                let sep_off = l.dist_from_bottom - road.width * 0.5 + l.kind.width();
+                if road_lanes
+                    .get(i + 1)
+                    .is_some_and(|next| l.can_change_to(next))
+                {
+                    tess_map.set_color(line_col);
+                    draw_dashed(&mut tess_map, cut, 0.25, sep_off);
+                } else {
+                    draw_off(&mut tess_map, line_col, 0.25, sep_off);
+                }
+            }
+
+            Self::lane_markings(&mut tess_map, line_col, &road_lanes, lanes, inters);
+
+            if road.roadworks.is_some() {
+                Self::roadworks(&mut self.cones, &mut self.barriers, road, &road_lanes);
             }
         }
 
@@ -755,15 +1229,19 @@ impl MapBuilders {
             {
                 ppoly.clear_extend(turn.points.as_slice());
                 Self::draw_rail(&mut tess_map, &ppoly, 0.0, false);
+                Self::rail_ties(&mut self.rail_ties, &ppoly);
             }
         }
 
         // Lots
+        let colors = palette();
         for lot in chunk_lots {
             let lot = &lots[lot];
             let col = match lot.kind {
-                LotKind::Unassigned => simulation::colors().lot_unassigned_col,
-                LotKind::Residential => simulation::colors().lot_residential_col,
+                LotKind::Unassigned => colors.unassigned,
+                LotKind::Residential => colors.residential,
+                LotKind::Commercial => colors.commercial,
+                LotKind::Industrial => colors.industrial,
             };
             tess_lots.set_color(col);
             tess_lots.draw_filled_polygon(&lot.shape.corners, lot.height + 0.3);
@@ -771,6 +1249,103 @@ impl MapBuilders {
     }
 }
 
+/// Dashed line along the polyline, offset like [`Tesselator::draw_polyline_full`]
+/// Instanced mesh made of boxes, in the frame of the instances: x along their direction,
+/// y to its left and z up
+fn detail_mesh(
+    gfx: &GfxContext,
+    boxes: &[(Vec3, Vec3, LinearColor)],
+) -> InstancedMeshBuilder<false> {
+    let mut builder = MeshBuilder::<false>::new(gfx.tess_material);
+    for &(min, max, col) in boxes {
+        builder.extend_box(None, min, max, col);
+    }
+    InstancedMeshBuilder::new(builder.build(gfx).expect("detail mesh has boxes"))
+}
+
+/// How far below the poles the wire hangs, `t` going from 0 to 1 along the span
+fn wire_sag(span: f32, t: f32) -> f32 {
+    4.0 * WIRE_SAG * span * t * (1.0 - t)
+}
+
+/// Whether the chunk is close enough to the camera for the small details of the rails
+fn rail_details_visible(chunk: SubscriberChunkID, cam: Vec3) -> bool {
+    let bbox = chunk.bbox();
+    let closest = cam.xy().max(bbox.ll).min(bbox.ur);
+    cam.distance(closest.z(0.0)) < RAIL_DETAIL_DIST
+}
+
+fn draw_dashed(tess: &mut Tesselator, line: &PolyLine3, thickness: f32, offset: f32) {
+    let l = line.length();
+    let mut d = DASH_GAP * 0.5;
+    while d + DASH_LENGTH < l {
+        let (a, dir_a) = line.point_dir_along(d);
+        let (b, dir_b) = line.point_dir_along(d + DASH_LENGTH);
+        tess.draw_polyline_full(
+            [a, b].into_iter(),
+            dir_a.xy(),
+            dir_b.xy(),
+            thickness,
+            offset,
+        );
+        d += DASH_LENGTH + DASH_GAP;
+    }
+}
+
+/// Arrow painted on the lane, `dir` is the direction of the lane at `pos`
+fn turn_arrow(tess: &mut Tesselator, pos: Vec3, dir: Vec2, turn: TurnDirection) {
+    const THICKNESS: f32 = 0.3;
+    let left = -dir.perpendicular();
+    let z = pos.z;
+    let at = |forward: f32, side: f32| (pos.xy() + dir * forward + left * side).z(z);
+
+    let base = at(-2.5, 0.0);
+    let fork = at(0.0, 0.0);
+    match turn {
+        TurnDirection::Straight => {
+            let tip = at(1.2, 0.0);
+            tess.draw_stroke(base, tip, THICKNESS);
+            arrow_head(tess, tip, dir);
+        }
+        TurnDirection::Left | TurnDirection::Right => {
+            let side = if turn == TurnDirection::Left {
+                1.0
+            } else {
+                -1.0
+            };
+            let tip = at(0.8, side * 0.9);
+            tess.draw_stroke(base, fork, THICKNESS);
+            tess.draw_stroke(fork, tip, THICKNESS);
+            arrow_head(tess, tip, ((dir + left * side) * 0.5).normalize());
+        }
+        TurnDirection::UTurn => {
+            let top = at(0.6, 0.0);
+            let top_side = at(0.6, 1.2);
+            let tip = at(-1.0, 1.2);
+            tess.draw_stroke(base, top, THICKNESS);
+            tess.draw_stroke(top, top_side, THICKNESS);
+            tess.draw_stroke(top_side, tip, THICKNESS);
+            arrow_head(tess, tip, -dir);
+        }
+    }
+}
+
+fn arrow_head(tess: &mut Tesselator, tip: Vec3, dir: Vec2) {
+    let side = dir.perpendicular() * 0.5;
+    let tip2 = tip.xy();
+    tess.draw_filled_polygon(&[tip2 + dir * 0.8, tip2 + side, tip2 - side], tip.z);
+}
+
+/// Worn pavement is drawn darker
+fn wear_darkening(condition: RoadCondition) -> f32 {
+    match condition {
+        RoadCondition::Good => 1.0,
+        RoadCondition::Worn => 0.9,
+        RoadCondition::Damaged => 0.8,
+        RoadCondition::Broken => 0.65,
+    }
+}
+
 fn add_polyon(
     mut tess: &mut Tesselator,
     w: f32,
@@ -997,3 +1572,49 @@ fn intersection_mesh(
         });
     });
 }
+
+fn house_faces(builder: &mut MeshBuilder<false>, building: &Building) {
+    let tint = building_tint(building);
+    for (face, col) in &building.mesh.faces {
+        let col = LinearColor::new(col.r * tint.r, col.g * tint.g, col.b * tint.b, col.a);
+        builder.extend_with(None, |vertices, add_index| {
+            let o = face[1];
+            let u = unwrap_ret!((face[0] - o).try_normalize());
+            let v = unwrap_ret!((face[2] - o).try_normalize());
+
+            let mut nor = u.cross(v);
+
+            let mut reverse = false;
+
+            if nor.z < 0.0 {
+                reverse = true;
+                nor = -nor;
+            }
+
+            let mut projected = Polygon(Vec::with_capacity(face.len()));
+            for &p in face {
+                let off = p - o;
+                projected.0.push(vec2(off.dot(u), off.dot(v)));
+
+                vertices.push(MeshVertex {
+                    position: p.into(),
+                    normal: nor,
+                    uv: [0.0; 2],
+                    color: col.into(),
+                    tangent: [0.0; 4],
+                })
+            }
+
+            projected.simplify();
+
+            earcut(&projected.0, &[], |mut a, b, mut c| {
+                if reverse {
+                    std::mem::swap(&mut a, &mut c);
+                }
+                add_index(a as u32);
+                add_index(b as u32);
+                add_index(c as u32);
+            })
+        });
+    }
+}
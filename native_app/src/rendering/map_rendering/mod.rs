@@ -1,26 +1,46 @@
-use engine::{Context, FrameContext, GfxContext, Water};
-use geom::{Camera, Circle, InfiniteFrustrum, Intersect3};
+use engine::{Context, FrameContext, GfxContext};
+use geom::{Camera, Circle, Color, InfiniteFrustrum, Intersect3};
 use map_mesh::MapMeshHandler;
-use simulation::map::{Lane, LaneID, LaneKind, Map, ProjectFilter, ProjectKind, TrafficBehavior};
-use simulation::Simulation;
+pub use map_mesh::WIRE_HEIGHT;
+use prototypes::GameTime;
+pub use relevance::StreamingStats;
+use simulation::map::{
+    CanonicalPosition, CrossingPhase, Lane, LaneID, LaneKind, Map, ProjectFilter, ProjectKind,
+    Road, SubscriberChunkID, TrafficBehavior,
+};
+use simulation::{Simulation, SimulationOptions};
 use terrain::TerrainRender;
 
 use crate::rendering::immediate::ImmediateDraw;
 use crate::rendering::map_rendering::lamps::LampsRender;
+use crate::rendering::map_rendering::props::PropsRender;
+use crate::rendering::map_rendering::relevance::ChunkRelevance;
 use crate::rendering::map_rendering::trees::TreesRender;
+use crate::rendering::map_rendering::water::WaterRender;
 
 mod lamps;
 mod map_mesh;
+mod props;
+mod relevance;
 mod terrain;
 mod trees;
+mod water;
 
-/// Render the entire map including the terrain, trees, water etc
+const CROSSING_WALK_COLOR: Color = Color::new(0.9, 0.95, 1.0, 1.0);
+const CROSSING_DONT_WALK_COLOR: Color = Color::new(1.0, 0.35, 0.05, 1.0);
+/// Flat shapes standing in for the roads and buildings not built yet after a camera jump
+const PLACEHOLDER_COLOR: Color = Color::new(0.45, 0.45, 0.47, 1.0);
+
+/// Render the entire map including the terrain, trees, props, water etc
 pub struct MapRenderer {
     pub meshb: MapMeshHandler,
     pub terrain: TerrainRender,
     pub trees: TreesRender,
-    pub water: Water,
+    pub props: PropsRender,
+    pub water: WaterRender,
     pub lamps: LampsRender,
+    /// Which chunks have their gpu resources built, see [`ChunkRelevance`]
+    relevance: ChunkRelevance,
 }
 
 pub struct MapRenderOptions {
@@ -34,17 +54,51 @@ impl MapRenderer {
         MapRenderer {
             meshb: MapMeshHandler::new(gfx, sim),
             trees: TreesRender::new(gfx, &sim.map()),
+            props: PropsRender::new(gfx, &sim.map()),
             terrain: TerrainRender::new(gfx, sim),
-            water: Water::new(gfx, sim.map().environment.bounds()),
+            water: WaterRender::new(gfx, &sim.map()),
             lamps: LampsRender::new(&sim.map()),
+            relevance: ChunkRelevance::default(),
         }
     }
 
-    pub fn update(&mut self, sim: &Simulation, ctx: &mut Context) {
+    pub fn update(&mut self, sim: &Simulation, cam: &Camera, ctx: &mut Context) {
         profiling::scope!("update map renderer");
         let map = sim.map();
+        self.relevance.update(cam);
+        self.meshb.take_changes(&self.relevance);
         self.lamps.update(&map, ctx);
-        self.terrain.update(ctx, &map);
+        self.lamps.update_headlights(sim, ctx);
+        self.terrain.update(ctx, &map, &self.relevance);
+        self.water.update(ctx, &map);
+        self.trees.set_season(
+            &mut ctx.gfx,
+            sim.read::<GameTime>()
+                .year_progress(sim.read::<SimulationOptions>().season_days),
+        );
+    }
+
+    /// Number of relevant road and building chunks left to mesh, used to show the loading progress
+    pub fn pending_mesh_chunks(&self) -> usize {
+        self.meshb.pending_road_chunks(&self.relevance)
+            + self.meshb.pending_building_chunks(&self.relevance)
+    }
+
+    /// Number of relevant terrain chunks left to upload, used to show the loading progress
+    pub fn pending_terrain_chunks(&self) -> usize {
+        self.terrain.pending_chunks(&self.relevance)
+    }
+
+    pub fn streaming_stats(&self) -> StreamingStats {
+        StreamingStats {
+            radius: self.relevance.radius(),
+            resident_mesh_chunks: self.meshb.resident_chunks(),
+            resident_tree_chunks: self.trees.resident_chunks(),
+            resident_prop_chunks: self.props.resident_chunks(),
+            mesh_queue: self.pending_mesh_chunks(),
+            tree_queue: self.trees.pending_chunks(&self.relevance),
+            terrain_queue: self.terrain.queue_depth(),
+        }
     }
 
     pub fn render(
@@ -59,13 +113,55 @@ impl MapRenderer {
         profiling::scope!("render map renderer");
         self.terrain.draw(cam, ctx);
 
-        self.trees.draw(map, cam, ctx);
+        self.trees.draw(map, cam, &self.relevance, ctx);
 
-        self.meshb.latest_mesh(map, options, ctx);
+        self.props.draw(map, cam, &self.relevance, ctx);
+
+        self.meshb.latest_mesh(map, options, &self.relevance, ctx);
+
+        if self.relevance.just_jumped() {
+            self.placeholder(map, draw);
+        }
 
         Self::signals_render(map, time, cam, &ctx.gfx.frustrum, draw);
 
-        ctx.draw(self.water.clone());
+        self.water.draw(ctx);
+    }
+
+    /// Flat roads and building footprints over the chunks in view not built yet,
+    /// so that the destination of a camera jump is not empty while it builds
+    fn placeholder(&self, map: &Map, draw: &mut ImmediateDraw) {
+        profiling::scope!("map placeholder");
+        for chunk in self.meshb.unbuilt_chunks(&self.relevance) {
+            for kind in map
+                .spatial_map()
+                .query(chunk.bbox(), ProjectFilter::ROAD | ProjectFilter::BUILDING)
+            {
+                match kind {
+                    ProjectKind::Road(id) => {
+                        let Some(road) = map.roads().get(id) else {
+                            continue;
+                        };
+                        if SubscriberChunkID::new(road.canonical_position()) != chunk {
+                            continue;
+                        }
+                        draw.polyline(road.points.as_slice(), road.width, false)
+                            .color(PLACEHOLDER_COLOR);
+                    }
+                    ProjectKind::Building(id) => {
+                        let Some(building) = map.buildings().get(id) else {
+                            continue;
+                        };
+                        if SubscriberChunkID::new(building.canonical_position()) != chunk {
+                            continue;
+                        }
+                        draw.obb(building.obb, building.height + 0.5)
+                            .color(PLACEHOLDER_COLOR);
+                    }
+                    _ => {}
+                }
+            }
+        }
     }
 
     fn render_lane_signals(n: &Lane, draw: &mut ImmediateDraw, time: u32) {
@@ -92,6 +188,35 @@ impl MapRenderer {
         draw.mesh(mesh, r_center, dir_perp.z(0.0));
     }
 
+    /// Walk signals on the ground at both ends of the crosswalks going over the road
+    fn render_crossing_signals(map: &Map, road: &Road, draw: &mut ImmediateDraw, time: u32) {
+        let lanes = map.lanes();
+        for inter in [road.src, road.dst] {
+            let Some(inter) = map.intersections().get(inter) else {
+                continue;
+            };
+            for turn in inter.turns() {
+                if !turn.kind.is_crosswalk() {
+                    continue;
+                }
+                if lanes.get(turn.id.src).map(|l| l.parent) != Some(road.id) {
+                    continue;
+                }
+                let control = turn.id.crossing_control(lanes, map.roads());
+                if !control.is_light() {
+                    continue;
+                }
+                let color = match control.crossing_phase(time) {
+                    CrossingPhase::Walk => CROSSING_WALK_COLOR,
+                    CrossingPhase::DontWalk => CROSSING_DONT_WALK_COLOR,
+                };
+                for end in [turn.points.first(), turn.points.last()] {
+                    draw.circle(end.up(0.03), 0.35).color(color);
+                }
+            }
+        }
+    }
+
     fn render_lanes(
         map: &Map,
         lanes: impl Iterator<Item = (LaneID, LaneKind)>,
@@ -150,6 +275,8 @@ impl MapRenderer {
                 }
             }
 
+            Self::render_crossing_signals(map, r, draw, time);
+
             Self::render_lanes(
                 map,
                 r.outgoing_lanes_from(r.dst).iter().copied(),
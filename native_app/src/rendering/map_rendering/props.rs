@@ -0,0 +1,166 @@
+use common::{FastMap, FastSet};
+use engine::{FrameContext, GfxContext, InstancedMesh, InstancedMeshBuilder, MeshInstance};
+use geom::{vec3, Camera, HeightmapChunk, Intersect3, LinearColor, AABB3};
+use prototypes::{PropPrototype, PropPrototypeID, RenderAsset};
+use simulation::map::{Map, MapSubscriber, SceneryChunkID, UpdateType};
+
+use crate::rendering::map_rendering::relevance::ChunkRelevance;
+
+/// Chunks closer than this to the camera get their props generated
+const GENERATE_DISTANCE: f32 = 3000.0;
+/// Chunks generated per frame at most, so that moving the camera does not stutter
+const GENERATE_PER_FRAME: usize = 2;
+
+/// Renders the scenery props, one instance buffer per prototype and chunk.
+/// The props of a chunk are generated the first time it is seen close enough,
+/// and forgotten with the other gpu resources of the chunk when it stops being relevant.
+pub struct PropsRender {
+    builders: FastMap<PropPrototypeID, InstancedMeshBuilder<false>>,
+    cache: FastMap<SceneryChunkID, Vec<InstancedMesh>>,
+    sub: MapSubscriber,
+}
+
+impl PropsRender {
+    pub fn new(gfx: &mut GfxContext, map: &Map) -> Self {
+        let mut builders = FastMap::default();
+        for proto in PropPrototype::iter() {
+            let RenderAsset::Mesh { ref path } = proto.asset else {
+                log::warn!("prop {} must be a mesh", proto.name);
+                continue;
+            };
+            match gfx.mesh(path) {
+                Ok(mesh) => {
+                    builders.insert(proto.id, InstancedMeshBuilder::new_ref(&mesh));
+                }
+                Err(e) => log::error!("could not load prop {}: {}", proto.name, e),
+            }
+        }
+
+        Self {
+            builders,
+            cache: FastMap::default(),
+            sub: map.subscribe_multi(&[
+                UpdateType::TerrainHeight,
+                UpdateType::TerrainSplat,
+                UpdateType::RoadGeometry,
+                UpdateType::BuildingAdded,
+                UpdateType::BuildingRemoved,
+                UpdateType::BuildingChanged,
+            ]),
+        }
+    }
+
+    fn build_chunk(&mut self, map: &Map, chunk: SceneryChunkID, gfx: &GfxContext) {
+        for b in self.builders.values_mut() {
+            b.instances.clear();
+        }
+
+        for prop in map.generate_props(chunk) {
+            let Some(b) = self.builders.get_mut(&prop.id.proto) else {
+                continue;
+            };
+            let tint: LinearColor = prop.id.proto.prototype().tint.into();
+            b.instances.push(MeshInstance::new(
+                prop.pos,
+                prop.dir.z0() * prop.scale,
+                tint,
+            ));
+        }
+
+        let meshes = self
+            .builders
+            .values_mut()
+            .filter_map(|b| b.build(gfx))
+            .collect();
+        self.cache.insert(chunk, meshes);
+    }
+
+    /// Number of chunks with props on the gpu
+    pub fn resident_chunks(&self) -> usize {
+        self.cache.len()
+    }
+
+    fn update(
+        &mut self,
+        map: &Map,
+        cam: &Camera,
+        relevance: &ChunkRelevance,
+        ctx: &mut FrameContext<'_>,
+    ) {
+        if self.sub.take_cleared() {
+            self.cache.clear();
+        }
+
+        let camcenter = cam.pos.xy();
+        self.cache
+            .retain(|&chunk, _| !relevance.should_evict(chunk));
+
+        // only the chunks already generated are rebuilt, the others wait until they are seen
+        let updated: FastSet<SceneryChunkID> = self.sub.take_updated_chunks().collect();
+        for chunk in updated {
+            if self.cache.contains_key(&chunk) {
+                self.build_chunk(map, chunk, ctx.gfx);
+            }
+        }
+
+        let bounds = map.environment.bounds();
+        let ll = SceneryChunkID::new(bounds.ll);
+        let ur = SceneryChunkID::new(bounds.ur);
+        let mut missing = vec![];
+        for y in ll.1..=ur.1 {
+            for x in ll.0..=ur.0 {
+                let chunk = SceneryChunkID::new_i16(x, y);
+                if self.cache.contains_key(&chunk)
+                    || chunk.center().distance(camcenter) > GENERATE_DISTANCE
+                    || !ctx.gfx.frustrum.intersects(&chunk_aabb(map, chunk))
+                {
+                    continue;
+                }
+                missing.push(chunk);
+            }
+        }
+        missing.sort_by_key(|c| c.center().distance2(camcenter) as i64);
+
+        for chunk in missing.into_iter().take(GENERATE_PER_FRAME) {
+            self.build_chunk(map, chunk, ctx.gfx);
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        map: &Map,
+        cam: &Camera,
+        relevance: &ChunkRelevance,
+        ctx: &mut FrameContext<'_>,
+    ) {
+        profiling::scope!("draw props");
+        self.update(map, cam, relevance, ctx);
+
+        for (&chunk, meshes) in &self.cache {
+            if !ctx.gfx.frustrum.intersects(&chunk_aabb(map, chunk)) {
+                continue;
+            }
+            for mesh in meshes {
+                ctx.draw(mesh.clone());
+            }
+        }
+    }
+}
+
+/// Box containing every prop of the chunk, from the terrain heights
+fn chunk_aabb(map: &Map, chunk: SceneryChunkID) -> AABB3 {
+    let max_height = chunk
+        .convert()
+        .filter_map(|c| map.environment.get_chunk(c))
+        .map(HeightmapChunk::max_height)
+        .fold(0.0, f32::max);
+
+    AABB3::new_size(
+        chunk.corner().z(-40.0),
+        vec3(
+            5.0 + SceneryChunkID::SIZE_F32,
+            5.0 + SceneryChunkID::SIZE_F32,
+            40.0 + max_height + 20.0,
+        ),
+    )
+}
@@ -0,0 +1,152 @@
+//! Decides which chunks of the map have their gpu resources built.
+//! On a large map, building the meshes of every chunk takes more memory than a player ever sees at
+//! once. Only the chunks around the camera are built, nearest first, and the ones left far behind
+//! are freed. The map itself stays whole in memory, the simulation does not know about this.
+
+use std::time::{Duration, Instant};
+
+use common::ChunkID;
+use geom::{Camera, Vec2};
+
+/// Chunks closer than this to the camera focus are resident, whatever the camera height
+const BASE_RADIUS: f32 = 5000.0;
+/// The radius grows with the distance of the eye to the focus, so that the horizon stays filled
+const RADIUS_PER_DIST: f32 = 3.0;
+/// Ring beyond the radius built in advance, so that panning does not show holes
+const PREFETCH_RING: f32 = 1024.0;
+/// Chunks are only freed this far beyond the prefetch ring,
+/// so that moving back and forth over the edge does not rebuild the same chunks
+const EVICT_MARGIN: f32 = 1024.0;
+/// A focus moving more than this in a frame jumped there (bookmark, search result)
+const JUMP_DISTANCE: f32 = 2000.0;
+/// The low detail placeholder is shown at most this long after a jump
+const PLACEHOLDER_TIME: Duration = Duration::from_secs(3);
+
+/// Shared by the terrain, the map mesh and the scenery, updated once per frame from the camera
+pub struct ChunkRelevance {
+    focus: Vec2,
+    radius: f32,
+    jumped_at: Option<Instant>,
+    initialized: bool,
+}
+
+impl Default for ChunkRelevance {
+    fn default() -> Self {
+        Self {
+            focus: Vec2::ZERO,
+            radius: BASE_RADIUS,
+            jumped_at: None,
+            initialized: false,
+        }
+    }
+}
+
+impl ChunkRelevance {
+    pub fn update(&mut self, cam: &Camera) {
+        self.set_focus(cam.pos.xy(), cam.dist);
+    }
+
+    fn set_focus(&mut self, focus: Vec2, dist: f32) {
+        if self.initialized && focus.distance(self.focus) > JUMP_DISTANCE {
+            self.jumped_at = Some(Instant::now());
+        }
+        self.initialized = true;
+        self.focus = focus;
+        self.radius = BASE_RADIUS.max(dist * RADIUS_PER_DIST);
+    }
+
+    /// Radius around the focus of the chunks in view range
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Distance from the focus to the closest point of the chunk
+    fn distance<const LEVEL: u16>(&self, chunk: ChunkID<LEVEL>) -> f32 {
+        let bbox = chunk.bbox();
+        let closest = self.focus.max(bbox.ll).min(bbox.ur);
+        self.focus.distance(closest)
+    }
+
+    /// Whether the chunk is in view range, it should be shown now
+    pub fn is_visible<const LEVEL: u16>(&self, chunk: ChunkID<LEVEL>) -> bool {
+        self.distance(chunk) <= self.radius
+    }
+
+    /// Whether the chunk should have its gpu resources, in view range or about to be
+    pub fn is_relevant<const LEVEL: u16>(&self, chunk: ChunkID<LEVEL>) -> bool {
+        self.distance(chunk) <= self.radius + PREFETCH_RING
+    }
+
+    /// Whether the gpu resources of the chunk should be freed
+    pub fn should_evict<const LEVEL: u16>(&self, chunk: ChunkID<LEVEL>) -> bool {
+        self.distance(chunk) > self.radius + PREFETCH_RING + EVICT_MARGIN
+    }
+
+    /// The relevant chunks among the given ones, nearest to the focus first
+    pub fn nearest_first<const LEVEL: u16>(
+        &self,
+        chunks: impl Iterator<Item = ChunkID<LEVEL>>,
+    ) -> Vec<ChunkID<LEVEL>> {
+        let mut relevant: Vec<_> = chunks.filter(|&c| self.is_relevant(c)).collect();
+        relevant.sort_by(|&a, &b| self.distance(a).total_cmp(&self.distance(b)));
+        relevant
+    }
+
+    /// Whether the camera jumped recently, the destination may still be building
+    pub fn just_jumped(&self) -> bool {
+        self.jumped_at
+            .is_some_and(|t| t.elapsed() < PLACEHOLDER_TIME)
+    }
+}
+
+/// Shown in the performance window
+#[derive(Default, Clone, Copy)]
+pub struct StreamingStats {
+    pub radius: f32,
+    pub resident_mesh_chunks: usize,
+    pub resident_tree_chunks: usize,
+    pub resident_prop_chunks: usize,
+    /// Relevant chunks waiting for their meshes to be built
+    pub mesh_queue: usize,
+    pub tree_queue: usize,
+    /// Terrain chunks waiting to be uploaded, relevant or not
+    pub terrain_queue: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use common::ChunkID_1024;
+    use geom::vec2;
+
+    use super::*;
+
+    #[test]
+    fn ring_and_jumps() {
+        let mut r = ChunkRelevance::default();
+        r.set_focus(vec2(0.0, 0.0), 100.0);
+        assert!(!r.just_jumped());
+
+        let near = ChunkID_1024::new(vec2(100.0, 100.0));
+        let prefetched = ChunkID_1024::new(vec2(BASE_RADIUS + 500.0, 0.0));
+        let kept = ChunkID_1024::new(vec2(BASE_RADIUS + PREFETCH_RING + 500.0, 0.0));
+        let far = ChunkID_1024::new(vec2(BASE_RADIUS * 3.0, 0.0));
+
+        assert!(r.is_visible(near));
+        assert!(!r.is_visible(prefetched) && r.is_relevant(prefetched));
+        assert!(!r.is_relevant(kept) && !r.should_evict(kept));
+        assert!(r.should_evict(far));
+        assert_eq!(
+            r.nearest_first([far, prefetched, near].into_iter()),
+            vec![near, prefetched]
+        );
+
+        // zooming out widens the ring
+        r.set_focus(vec2(0.0, 0.0), BASE_RADIUS * 2.0);
+        assert!(r.is_visible(prefetched));
+        assert!(!r.just_jumped());
+
+        r.set_focus(vec2(BASE_RADIUS * 3.0, 0.0), 100.0);
+        assert!(r.just_jumped());
+        assert!(r.is_visible(far) && r.should_evict(near));
+    }
+}
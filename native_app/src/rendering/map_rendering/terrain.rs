@@ -1,15 +1,24 @@
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
 use engine::heightmap::HeightmapRender;
 use engine::{Context, FrameContext, GfxContext};
 use geom::Camera;
-use simulation::map::{Map, MapSubscriber, UpdateType};
+use simulation::map::{Map, MapSubscriber, TerrainChunkID, UpdateType};
 use simulation::Simulation;
 
+use crate::rendering::map_rendering::relevance::ChunkRelevance;
+
 const CSIZE: u32 = simulation::map::Heightmap::SIZE;
 const CRESO: usize = simulation::map::Heightmap::RESOLUTION;
+/// Time spent uploading terrain chunks each frame
+const UPLOAD_BUDGET: Duration = Duration::from_millis(4);
 
 pub struct TerrainRender {
     heightmap: HeightmapRender<CSIZE, CRESO>,
     terrain_sub: MapSubscriber,
+    /// Chunks changed but not uploaded yet
+    pending: BTreeSet<TerrainChunkID>,
 }
 
 impl TerrainRender {
@@ -20,7 +29,8 @@ impl TerrainRender {
 
         Self {
             heightmap: terrain,
-            terrain_sub: sim.map().subscribe(UpdateType::Terrain),
+            terrain_sub: sim.map().subscribe(UpdateType::TerrainHeight),
+            pending: BTreeSet::new(),
         }
     }
 
@@ -28,34 +38,52 @@ impl TerrainRender {
         self.heightmap.draw_heightmap(cam, fctx);
     }
 
-    pub fn update(&mut self, ctx: &mut Context, map: &Map) {
+    /// Number of relevant terrain chunks waiting to be uploaded
+    pub fn pending_chunks(&self, relevance: &ChunkRelevance) -> usize {
+        self.terrain_sub.pending_chunks()
+            + self
+                .pending
+                .iter()
+                .filter(|&&c| relevance.is_relevant(c))
+                .count()
+    }
+
+    /// Number of terrain chunks waiting to be uploaded, relevant or not
+    pub fn queue_depth(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Uploads the changed chunks to the gpu, spread over several frames when many changed at once.
+    /// The relevant chunks go first, nearest first. The heightmap is a single texture allocated for
+    /// the whole map so nothing is freed, the budget left is spent on the far chunks.
+    pub fn update(&mut self, ctx: &mut Context, map: &Map, relevance: &ChunkRelevance) {
         let ter = &map.environment;
 
         if self.terrain_sub.take_cleared() {
-            for (chunk_id, chunk) in ter.chunks() {
-                self.heightmap.update_chunk(
-                    &mut ctx.gfx,
-                    (chunk_id.0 as u32, chunk_id.1 as u32),
-                    chunk,
-                );
-            }
+            self.pending.clear();
+            self.pending.extend(ter.chunks().map(|(id, _)| id));
+        }
 
-            self.heightmap.invalidate_height_normals(&ctx.gfx);
-            return;
+        for cell in self.terrain_sub.take_updated_chunks() {
+            self.pending.extend(cell.convert());
         }
 
+        let start = Instant::now();
         let mut changed = false;
-        for cell in self.terrain_sub.take_updated_chunks() {
-            for chunkid in cell.convert() {
-                let chunk =
-                    unwrap_retlog!(ter.get_chunk(chunkid), "trying to update nonexistent chunk");
-
-                self.heightmap.update_chunk(
-                    &mut ctx.gfx,
-                    (chunkid.0 as u32, chunkid.1 as u32),
-                    chunk,
-                );
-            }
+        let relevant = relevance.nearest_first(self.pending.iter().copied());
+        let mut order = relevant.into_iter();
+        while start.elapsed() < UPLOAD_BUDGET {
+            let Some(chunkid) = order.next().or_else(|| self.pending.first().copied()) else {
+                break;
+            };
+            self.pending.remove(&chunkid);
+            let Some(chunk) = ter.get_chunk(chunkid) else {
+                log::error!("trying to update nonexistent chunk");
+                continue;
+            };
+
+            self.heightmap
+                .update_chunk(&mut ctx.gfx, (chunkid.0 as u32, chunkid.1 as u32), chunk);
             changed = true;
         }
 
@@ -1,47 +1,135 @@
 use std::ops::Mul;
 
-use common::FastMap;
+use common::{FastMap, FastSet};
 use engine::wgpu::RenderPass;
 use engine::{
     Drawable, FrameContext, GfxContext, InstancedMesh, InstancedMeshBuilder, MeshInstance,
+    PaletteRange,
 };
 use geom::{vec3, vec4, Camera, HeightmapChunk, Intersect3, LinearColor, Matrix4, Vec3, AABB3};
-use simulation::map::{Map, MapSubscriber, SubscriberChunkID, UpdateType};
+use simulation::map::{Map, MapSubscriber, SubscriberChunkID, Tree, UpdateType};
+
+use crate::rendering::map_rendering::relevance::ChunkRelevance;
+
+/// Steps of the foliage tint through the year, the palette is updated at each step
+const FOLIAGE_STEPS: f32 = 32.0;
+/// Groups of trees that change color one step after the other
+const TREE_VARIANTS: usize = 4;
+
+/// Tint of the foliage through the year, white if seasons are disabled
+fn foliage_tint(year_progress: Option<f32>) -> LinearColor {
+    let Some(progress) = year_progress else {
+        return LinearColor::WHITE;
+    };
+    // at the middle of spring, summer, autumn and winter
+    let keys = [
+        LinearColor::new(0.9, 1.05, 0.85, 1.0),
+        LinearColor::WHITE,
+        LinearColor::new(1.3, 0.85, 0.55, 1.0),
+        LinearColor::new(0.75, 0.8, 0.8, 1.0),
+    ];
+    let p = (progress * FOLIAGE_STEPS).floor() / FOLIAGE_STEPS;
+    let x = (p * 4.0 - 0.5).rem_euclid(4.0);
+    let i = x as usize % 4;
+    let t = x.fract();
+    (1.0 - t) * keys[i] + t * keys[(i + 1) % 4]
+}
+
+/// Bigger trees change color earlier, with some randomness so that the forests are not uniform
+fn tree_variant(t: &Tree) -> usize {
+    let size = ((t.size - 5.0) / 3.0).clamp(0.0, 1.0);
+    let x = 0.5 * size + 0.5 * common::rand::rand3(t.pos.x, t.pos.y, 4.0);
+    ((x * TREE_VARIANTS as f32) as usize).min(TREE_VARIANTS - 1)
+}
 
 pub struct TreesRender {
     tree_builder: InstancedMeshBuilder<false>,
     trees_cache: FastMap<SubscriberChunkID, InstancedMesh>,
     tree_sub: MapSubscriber,
+    /// Chunks changed or freed since their trees were built, built again once relevant
+    dirty: FastSet<SubscriberChunkID>,
+    /// Foliage of each variant
+    foliage: PaletteRange,
+    foliage_step: Option<u32>,
 }
 
 impl TreesRender {
     pub fn new(gfx: &mut GfxContext, map: &Map) -> Self {
         let mesh = gfx.mesh("pine.glb".as_ref()).expect("could not load pine");
 
-        let tree_sub = map.subscribe(UpdateType::Terrain);
+        let tree_sub = map.subscribe_multi(&[UpdateType::TerrainHeight, UpdateType::TerrainSplat]);
         Self {
             tree_builder: InstancedMeshBuilder::new_ref(&mesh),
             trees_cache: FastMap::default(),
             tree_sub,
+            dirty: FastSet::default(),
+            foliage: gfx
+                .palette
+                .add("foliage", &[LinearColor::WHITE; TREE_VARIANTS]),
+            foliage_step: None,
+        }
+    }
+
+    /// Changes the color of the foliage with the seasons, the variants are a step apart
+    pub fn set_season(&mut self, gfx: &mut GfxContext, year_progress: Option<f32>) {
+        let step = year_progress.map(|p| (p * FOLIAGE_STEPS) as u32);
+        if step == self.foliage_step {
+            return;
         }
+        self.foliage_step = step;
+        let colors: [LinearColor; TREE_VARIANTS] = std::array::from_fn(|i| {
+            foliage_tint(year_progress.map(|p| p + i as f32 / FOLIAGE_STEPS))
+        });
+        gfx.palette.set(self.foliage, &colors);
     }
 
-    fn build(&mut self, map: &Map, ctx: &mut FrameContext<'_>) {
-        for chunkid in self.tree_sub.take_updated_chunks() {
+    /// Number of chunks with trees on the gpu
+    pub fn resident_chunks(&self) -> usize {
+        self.trees_cache.len()
+    }
+
+    /// Number of relevant chunks waiting for their trees to be built
+    pub fn pending_chunks(&self, relevance: &ChunkRelevance) -> usize {
+        self.dirty
+            .iter()
+            .filter(|&&c| relevance.is_relevant(c))
+            .count()
+    }
+
+    fn build(&mut self, map: &Map, relevance: &ChunkRelevance, ctx: &mut FrameContext<'_>) {
+        self.dirty.extend(self.tree_sub.take_updated_chunks());
+
+        let evicted: Vec<_> = self
+            .trees_cache
+            .keys()
+            .copied()
+            .filter(|&c| relevance.should_evict(c))
+            .collect();
+        for chunkid in evicted {
+            self.trees_cache.remove(&chunkid);
+            self.dirty.insert(chunkid);
+        }
+
+        for chunkid in relevance.nearest_first(self.dirty.iter().copied()) {
+            self.dirty.remove(&chunkid);
             self.tree_builder.instances.clear();
 
             let aabb = chunkid.bbox();
+            let foliage = self.foliage;
             map.environment
                 .trees
                 .query_aabb_visitor(aabb.ll, aabb.ur, |obj| {
                     let Some((_, t)) = map.environment.trees.get(obj.0) else {
                         return;
                     };
-                    self.tree_builder.instances.push(MeshInstance {
-                        pos: t.pos.z(map.environment.height(t.pos).unwrap_or_default()),
-                        dir: t.dir.z0() * t.size * 0.2,
-                        tint: ((1.0 - t.size * 0.05) * t.col * LinearColor::WHITE).a(1.0),
-                    });
+                    self.tree_builder.instances.push(
+                        MeshInstance::new(
+                            t.pos.z(map.environment.height(t.pos).unwrap_or_default()),
+                            t.dir.z0() * t.scale() * 0.2,
+                            LinearColor::gray((1.0 - t.size * 0.05) * t.col),
+                        )
+                        .with_variation(foliage.variation(tree_variant(t))),
+                    );
                 });
 
             if let Some(m) = self.tree_builder.build(ctx.gfx) {
@@ -52,9 +140,15 @@ impl TreesRender {
         }
     }
 
-    pub fn draw(&mut self, map: &Map, cam: &Camera, ctx: &mut FrameContext<'_>) {
+    pub fn draw(
+        &mut self,
+        map: &Map,
+        cam: &Camera,
+        relevance: &ChunkRelevance,
+        ctx: &mut FrameContext<'_>,
+    ) {
         profiling::scope!("draw trees");
-        self.build(map, ctx);
+        self.build(map, relevance, ctx);
 
         let camcenter = cam.pos.xy();
 
@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+use engine::{Context, FrameContext, GfxContext, Water, WATER_FLOW_CELL};
+use geom::{vec2, AABB};
+use simulation::map::{Map, MapSubscriber, TerrainChunkID, UpdateType, WATER_CELL_SIZE};
+
+/// Flow cells along the side of a terrain chunk
+const CHUNK_CELLS: u32 = (TerrainChunkID::SIZE_F32 / WATER_FLOW_CELL) as u32;
+/// Water cells along the side of a terrain chunk
+const SURFACE_CELLS: u32 = (TerrainChunkID::SIZE_F32 / WATER_CELL_SIZE) as u32;
+/// Time spent baking flow chunks each frame
+const BAKE_BUDGET: Duration = Duration::from_millis(2);
+
+/// Renders the surface of the lakes and the sea, each at its level,
+/// and bakes the flow of the rivers from the terrain
+pub struct WaterRender {
+    water: Water,
+    terrain_sub: MapSubscriber,
+    /// Chunks changed but not baked yet
+    pending: BTreeSet<TerrainChunkID>,
+    /// Rectangles of water surface of each chunk, with their level
+    surfaces: BTreeMap<TerrainChunkID, Vec<(AABB, f32)>>,
+}
+
+impl WaterRender {
+    pub fn new(gfx: &mut GfxContext, map: &Map) -> Self {
+        Self {
+            water: Water::new(gfx, map.environment.bounds()),
+            terrain_sub: map.subscribe(UpdateType::TerrainHeight | UpdateType::Water),
+            pending: map.environment.chunks().map(|(id, _)| id).collect(),
+            surfaces: BTreeMap::new(),
+        }
+    }
+
+    pub fn set_waves(&mut self, gfx: &GfxContext, wave_scale: f32, wave_speed: f32) {
+        self.water.set_waves(gfx, wave_scale, wave_speed);
+    }
+
+    pub fn update(&mut self, ctx: &mut Context, map: &Map) {
+        if self.terrain_sub.take_cleared() {
+            self.surfaces.clear();
+            self.pending.clear();
+            self.pending
+                .extend(map.environment.chunks().map(|(id, _)| id));
+        }
+
+        // the flow depends on the valley around, so the neighbours are baked again too
+        let (w, h) = map.environment.size();
+        for cell in self.terrain_sub.take_updated_chunks() {
+            for chunk in cell.convert() {
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let (x, y) = (chunk.0 + dx, chunk.1 + dy);
+                        if x >= 0 && y >= 0 && (x as u16) < w && (y as u16) < h {
+                            self.pending.insert(TerrainChunkID::new_i16(x, y));
+                        }
+                    }
+                }
+            }
+        }
+
+        let start = Instant::now();
+        let mut baked = false;
+        while start.elapsed() < BAKE_BUDGET {
+            let Some(chunk) = self.pending.pop_first() else {
+                break;
+            };
+            self.bake_chunk(&ctx.gfx, map, chunk);
+            self.surfaces.insert(chunk, surface(map, chunk));
+            baked = true;
+        }
+
+        if baked {
+            self.water
+                .set_surface(&ctx.gfx, self.surfaces.values().flatten().copied());
+        }
+    }
+
+    fn bake_chunk(&self, gfx: &GfxContext, map: &Map, chunk: TerrainChunkID) {
+        let corner = chunk.corner();
+        let mut data = Vec::with_capacity((CHUNK_CELLS * CHUNK_CELLS * 4) as usize);
+        for y in 0..CHUNK_CELLS {
+            for x in 0..CHUNK_CELLS {
+                let pos = corner + vec2(x as f32 + 0.5, y as f32 + 0.5) * WATER_FLOW_CELL;
+                let flow = map.environment.water_flow(pos).unwrap_or_default();
+                data.extend_from_slice(&[
+                    ((flow.dir.x * 0.5 + 0.5) * 255.0) as u8,
+                    ((flow.dir.y * 0.5 + 0.5) * 255.0) as u8,
+                    flow.depth.min(255.0) as u8,
+                    255,
+                ]);
+            }
+        }
+
+        self.water.update_flow(
+            gfx,
+            (chunk.0 as u32 * CHUNK_CELLS, chunk.1 as u32 * CHUNK_CELLS),
+            CHUNK_CELLS,
+            CHUNK_CELLS,
+            &data,
+        );
+    }
+
+    pub fn draw(&self, ctx: &mut FrameContext<'_>) {
+        ctx.draw(self.water.clone());
+    }
+}
+
+/// The water surface of the chunk as rectangles, a cell being drawn at the highest level of its
+/// wet corners. The cells of a row at the same level are merged, and so are the rows alike.
+fn surface(map: &Map, chunk: TerrainChunkID) -> Vec<(AABB, f32)> {
+    let corner = chunk.corner();
+    let mut rects: Vec<(AABB, f32)> = vec![];
+    // the runs of the previous row, to grow them when the row is the same
+    let mut prev_row: Vec<usize> = vec![];
+
+    for y in 0..SURFACE_CELLS {
+        let mut row: Vec<(u32, u32, f32)> = vec![];
+        for x in 0..SURFACE_CELLS {
+            let center = corner + vec2(x as f32 + 0.5, y as f32 + 0.5) * WATER_CELL_SIZE;
+            let Some(level) = map.environment.water_level(center) else {
+                continue;
+            };
+            match row.last_mut() {
+                Some((_, end, l)) if *end == x && *l == level => *end = x + 1,
+                _ => row.push((x, x + 1, level)),
+            }
+        }
+
+        let cell = |x: u32, y: u32| corner + vec2(x as f32, y as f32) * WATER_CELL_SIZE;
+        let same = prev_row.len() == row.len()
+            && prev_row.iter().zip(&row).all(|(&i, &(start, end, level))| {
+                let (rect, l) = rects[i];
+                rect.ll.x == cell(start, 0).x && rect.ur.x == cell(end, 0).x && l == level
+            });
+        if same {
+            for &i in &prev_row {
+                rects[i].0.ur.y = cell(0, y + 1).y;
+            }
+            continue;
+        }
+
+        prev_row.clear();
+        for (start, end, level) in row {
+            prev_row.push(rects.len());
+            rects.push((AABB::new_ll_ur(cell(start, y), cell(end, y + 1)), level));
+        }
+    }
+    rects
+}
@@ -1,8 +1,18 @@
 pub use entity_render::*;
+pub use harbor_meshes::*;
+pub use interpolation::*;
+pub use lighting::*;
 pub use map_rendering::*;
 pub use orbit_camera::*;
+pub use palette::*;
+pub use sim_time_scale::*;
 
 mod entity_render;
+mod harbor_meshes;
 pub mod immediate;
+mod interpolation;
+mod lighting;
 mod map_rendering;
 mod orbit_camera;
+mod palette;
+mod sim_time_scale;
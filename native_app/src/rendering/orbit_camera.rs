@@ -4,6 +4,7 @@ use common::saveload::Encoder;
 use engine::{Context, Tesselator};
 use geom::{Camera, Plane, Radians, Vec2, Vec3, AABB};
 use simulation::map::pathfinding_crate::num_traits::Pow;
+use std::f32::consts::{PI, TAU};
 
 use crate::inputmap::{InputAction, InputMap};
 use crate::newgui::windows::settings::Settings;
@@ -21,12 +22,64 @@ pub struct OrbitCamera {
     pub maxdist: f32,
 }
 
+/// Where the orbit camera looks from, used to animate it from one view to another
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraPose {
+    pub pos: Vec3,
+    pub yaw: Radians,
+    pub pitch: Radians,
+    pub dist: f32,
+}
+
+impl CameraPose {
+    /// Eases from one pose to the other, t goes from 0 to 1.
+    /// The yaw turns the short way around.
+    pub fn lerp(self, to: CameraPose, t: f32) -> CameraPose {
+        let t = t.clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+
+        let mut dyaw = (to.yaw.0 - self.yaw.0) % TAU;
+        if dyaw > PI {
+            dyaw -= TAU;
+        } else if dyaw < -PI {
+            dyaw += TAU;
+        }
+
+        CameraPose {
+            pos: self.pos + (to.pos - self.pos) * t,
+            yaw: Radians(self.yaw.0 + dyaw * t),
+            pitch: Radians(self.pitch.0 + (to.pitch.0 - self.pitch.0) * t),
+            dist: self.dist + (to.dist - self.dist) * t,
+        }
+    }
+}
+
 impl OrbitCamera {
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            pos: self.camera.pos,
+            yaw: self.camera.yaw,
+            pitch: self.camera.pitch,
+            dist: self.camera.dist,
+        }
+    }
+
+    /// Places the camera right away, the targets follow so that the smoothing does not pull it back
+    pub fn set_pose(&mut self, pose: CameraPose) {
+        self.camera.pos = pose.pos;
+        self.camera.yaw = pose.yaw;
+        self.camera.pitch = pose.pitch;
+        self.camera.dist = pose.dist;
+        self.targetpos = pose.pos;
+        self.targetyaw = pose.yaw;
+        self.targetpitch = pose.pitch;
+        self.targetdist = pose.dist;
+    }
+
     pub fn update(&mut self, ctx: &mut Context) {
         self.camera.update();
         ctx.gfx.set_camera(self.camera);
         let params = ctx.gfx.render_params.value_mut();
-        params.cam_pos = self.camera.eye();
         params.cam_dir = -self.camera.dir();
     }
 
@@ -138,6 +191,9 @@ impl OrbitCamera {
             self.targetpos += delta * d.z0();
         }
 
+        // gamepad left stick, y pointing forward
+        self.targetpos += -delta * (d * inps.pan.y + d.perpendicular() * inps.pan.x).z0();
+
         if inps.act.contains(&InputAction::Zoom) {
             self.targetdist *= (1.0f32 / 1.05).pow(0.5 + 0.1 * inps.wheel.abs());
         }
@@ -167,8 +223,8 @@ impl OrbitCamera {
         let unprojected = self.unproject(screenpos, |_| Some(0.0));
 
         if inps.act.contains(&InputAction::CameraRotate) {
-            self.targetyaw -= Radians(delta_mouse.x / 100.0);
-            self.targetpitch += Radians(delta_mouse.y / 100.0);
+            self.targetyaw -= Radians(delta_mouse.x / 100.0 + inps.look.x * delta * 2.0);
+            self.targetpitch += Radians(delta_mouse.y / 100.0 - inps.look.y * delta * 2.0);
             self.targetpitch = self
                 .targetpitch
                 .min(Radians::HALFPI - Radians(0.01))
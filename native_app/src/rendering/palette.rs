@@ -0,0 +1,173 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use geom::Color;
+use serde::{Deserialize, Serialize};
+
+/// Palette in use, set from the settings each frame
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// Colors of the overlays, the zones and the tool gizmos.
+/// The colorblind-safe palettes keep the good and bad ends of the ramps apart in lightness too.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum ColorPalette {
+    /// The colors of the colors prototype
+    #[default]
+    Standard = 0,
+    /// Red-green, green weak
+    Deuteranopia = 1,
+    /// Red-green, red weak
+    Protanopia = 2,
+    /// Blue-yellow
+    Tritanopia = 3,
+}
+
+impl ColorPalette {
+    pub const ALL: [ColorPalette; 4] = [
+        ColorPalette::Standard,
+        ColorPalette::Deuteranopia,
+        ColorPalette::Protanopia,
+        ColorPalette::Tritanopia,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorPalette::Standard => "Standard",
+            ColorPalette::Deuteranopia => "Deuteranopia",
+            ColorPalette::Protanopia => "Protanopia",
+            ColorPalette::Tritanopia => "Tritanopia",
+        }
+    }
+
+    pub fn current() -> Self {
+        Self::ALL[CURRENT.load(Ordering::Relaxed) as usize % Self::ALL.len()]
+    }
+
+    pub fn set_current(self) {
+        CURRENT.store(self as u8, Ordering::Relaxed);
+    }
+
+    pub fn colors(self) -> PaletteColors {
+        match self {
+            ColorPalette::Standard => {
+                let colors = simulation::colors();
+                PaletteColors {
+                    success: colors.gui_success,
+                    danger: colors.gui_danger,
+                    primary: colors.gui_primary,
+                    disabled: colors.gui_disabled,
+                    ramp: [
+                        colors.gui_success,
+                        Color::new(0.9, 0.8, 0.2, 1.0),
+                        colors.gui_danger,
+                    ],
+                    unassigned: colors.lot_unassigned_col,
+                    residential: colors.lot_residential_col,
+                    commercial: colors.lot_commercial_col,
+                    industrial: colors.lot_industrial_col,
+                }
+            }
+            ColorPalette::Deuteranopia => DEUTERANOPIA,
+            ColorPalette::Protanopia => PROTANOPIA,
+            ColorPalette::Tritanopia => TRITANOPIA,
+        }
+    }
+}
+
+/// The colors of a palette
+#[derive(Copy, Clone, Debug)]
+pub struct PaletteColors {
+    pub success: Color,
+    pub danger: Color,
+    pub primary: Color,
+    pub disabled: Color,
+    /// Good, average and bad ends of the overlay ramps
+    pub ramp: [Color; 3],
+    pub unassigned: Color,
+    pub residential: Color,
+    pub commercial: Color,
+    pub industrial: Color,
+}
+
+impl PaletteColors {
+    /// Color of the overlay ramp, from 0 for good to 1 for bad
+    pub fn ramp(&self, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0) * (self.ramp.len() - 1) as f32;
+        let i = (t as usize).min(self.ramp.len() - 2);
+        let (a, b, f) = (self.ramp[i], self.ramp[i + 1], t - i as f32);
+        Color::new(
+            a.r + (b.r - a.r) * f,
+            a.g + (b.g - a.g) * f,
+            a.b + (b.b - a.b) * f,
+            a.a + (b.a - a.a) * f,
+        )
+    }
+}
+
+/// Colors of the palette in use
+pub fn palette() -> PaletteColors {
+    ColorPalette::current().colors()
+}
+
+const GRAY: Color = Color::new(0.8, 0.8, 0.8, 0.2);
+
+// Okabe-Ito colors, told apart by the red-green deficiencies
+const BLUE: Color = Color::new(0.0, 0.45, 0.7, 1.0);
+const SKY_BLUE: Color = Color::new(0.34, 0.71, 0.91, 1.0);
+const ORANGE: Color = Color::new(0.9, 0.62, 0.0, 1.0);
+const VERMILLION: Color = Color::new(0.84, 0.37, 0.0, 1.0);
+const YELLOW: Color = Color::new(0.94, 0.89, 0.26, 1.0);
+const REDDISH_PURPLE: Color = Color::new(0.8, 0.47, 0.65, 1.0);
+
+const DEUTERANOPIA: PaletteColors = PaletteColors {
+    success: SKY_BLUE,
+    danger: VERMILLION,
+    primary: BLUE,
+    disabled: GRAY,
+    ramp: [BLUE, YELLOW, VERMILLION],
+    unassigned: Color::new(0.6, 0.6, 0.6, 1.0),
+    residential: SKY_BLUE,
+    commercial: YELLOW,
+    industrial: REDDISH_PURPLE,
+};
+
+/// Reds look dark to protanopes, so the bad end is a bright orange
+const PROTANOPIA: PaletteColors = PaletteColors {
+    danger: ORANGE,
+    ramp: [BLUE, Color::new(0.75, 0.75, 0.75, 1.0), ORANGE],
+    ..DEUTERANOPIA
+};
+
+/// Blues and yellows are confused, the ramp goes from teal to red through white
+const TRITANOPIA: PaletteColors = PaletteColors {
+    success: Color::new(0.0, 0.6, 0.55, 1.0),
+    danger: Color::new(0.86, 0.15, 0.3, 1.0),
+    primary: Color::new(0.0, 0.55, 0.6, 1.0),
+    disabled: GRAY,
+    ramp: [
+        Color::new(0.0, 0.6, 0.55, 1.0),
+        Color::new(0.95, 0.95, 0.95, 1.0),
+        Color::new(0.86, 0.15, 0.3, 1.0),
+    ],
+    unassigned: Color::new(0.6, 0.6, 0.6, 1.0),
+    residential: Color::new(0.0, 0.6, 0.55, 1.0),
+    commercial: Color::new(0.95, 0.55, 0.65, 1.0),
+    industrial: Color::new(0.45, 0.2, 0.35, 1.0),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ramp_goes_through_its_stops() {
+        let p = DEUTERANOPIA;
+        assert_eq!(p.ramp(0.0), BLUE);
+        assert_eq!(p.ramp(0.5), YELLOW);
+        assert_eq!(p.ramp(1.0), VERMILLION);
+        assert_eq!(p.ramp(2.0), VERMILLION);
+
+        let quarter = p.ramp(0.25);
+        assert!((quarter.g - (BLUE.g + YELLOW.g) * 0.5).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,123 @@
+use prototypes::DELTA;
+
+/// Weight of the last frame in the measured speed, so it follows the warp changes in a few frames
+/// without jittering when a frame runs one tick more than the next
+const SPEED_SMOOTHING: f32 = 0.1;
+/// Pitch gained per doubling of the speed
+const PITCH_PER_DOUBLING: f32 = 0.05;
+const MAX_PITCH: f32 = 1.15;
+/// Speeds between which the sounds of the entities crossfade to the fast variant
+const FAST_FADE_START: f32 = 3.0;
+const FAST_FADE_END: f32 = 6.0;
+
+/// How fast the simulation actually runs compared to real time, for the animations and sounds of
+/// the simulated entities. They follow it and freeze on pause, whereas the camera and the UI keep
+/// real time.
+pub struct SimTimeScale {
+    /// Speed relative to the normal speed, 0 when paused
+    pub scale: f32,
+    /// Seconds of animation of the simulated entities, advanced at the scale
+    pub anim_time: f32,
+    /// Shift the pitch of the sounds of the entities up a bit with the speed
+    pub pitch_shift: bool,
+    last_tick: Option<u64>,
+}
+
+impl Default for SimTimeScale {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            anim_time: 0.0,
+            pitch_shift: true,
+            last_tick: None,
+        }
+    }
+}
+
+impl SimTimeScale {
+    /// Measures the speed from the ticks run during the frame: it is the speed the player sees,
+    /// lower than the warp when the simulation cannot keep up
+    pub fn update(&mut self, tick: u64, time_warp: u32, real_delta: f32) {
+        let ticks = self.last_tick.map_or(0, |last| tick.saturating_sub(last));
+        self.last_tick = Some(tick);
+
+        if time_warp == 0 {
+            self.scale = 0.0;
+            return;
+        }
+        if real_delta > 0.0 {
+            let measured = ticks as f32 * DELTA / real_delta;
+            self.scale += (measured - self.scale) * SPEED_SMOOTHING;
+        }
+        self.anim_time += self.advance(real_delta);
+    }
+
+    /// Seconds the animations of the simulated entities move during that much real time
+    pub fn advance(&self, real_delta: f32) -> f32 {
+        real_delta * self.scale
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.scale == 0.0
+    }
+
+    /// Playback rate of the sounds of the simulated entities, only slightly higher when sped up
+    /// to avoid chipmunk voices
+    pub fn pitch(&self) -> f32 {
+        if !self.pitch_shift || self.scale <= 1.0 {
+            return 1.0;
+        }
+        (1.0 + PITCH_PER_DOUBLING * self.scale.log2()).min(MAX_PITCH)
+    }
+
+    /// How much of the sounds of the entities is replaced by the fast variant, from 0 to 1
+    pub fn fast_mix(&self) -> f32 {
+        ((self.scale - FAST_FADE_START) / (FAST_FADE_END - FAST_FADE_START)).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: f32 = DELTA;
+
+    #[test]
+    fn pause_freezes_the_animations() {
+        let mut s = SimTimeScale::default();
+        for tick in 0..50 {
+            s.update(tick, 1, FRAME);
+        }
+        let before = s.anim_time;
+        assert!(before > 0.0);
+
+        for _ in 0..50 {
+            s.update(50, 0, FRAME);
+        }
+        assert!(s.is_paused());
+        assert_eq!(s.advance(1.0), 0.0);
+        assert_eq!(s.anim_time, before);
+        assert_eq!(s.pitch(), 1.0);
+        assert_eq!(s.fast_mix(), 0.0);
+    }
+
+    #[test]
+    fn speed_follows_the_ticks_run() {
+        let mut s = SimTimeScale::default();
+        let mut tick = 0;
+        for _ in 0..200 {
+            tick += 3;
+            s.update(tick, 3, FRAME);
+        }
+        assert!((s.scale - 3.0).abs() < 0.01, "{}", s.scale);
+        assert!(s.pitch() > 1.0 && s.pitch() < MAX_PITCH);
+        assert_eq!(s.fast_mix(), 0.0);
+
+        for _ in 0..200 {
+            tick += 10;
+            s.update(tick, 10, FRAME);
+        }
+        assert_eq!(s.pitch(), MAX_PITCH);
+        assert_eq!(s.fast_mix(), 1.0);
+    }
+}
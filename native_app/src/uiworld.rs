@@ -1,6 +1,7 @@
 use crate::init::{INIT_FUNCS, SAVELOAD_FUNCS};
 use crate::newgui::TimeAlways;
 use simulation::utils::resources::{RefMutSingle, RefSingle, ResourcesSingleThread};
+use simulation::utils::savefile::SaveCompression;
 use simulation::world_command::{WorldCommand, WorldCommands};
 use simulation::{Simulation, SimulationReplayLoader};
 use std::any::Any;
@@ -19,7 +20,10 @@ pub struct SaveLoadState {
     pub please_load_sim: Option<Simulation>,
     pub render_reset: bool,
     pub please_save: bool,
+    pub save_compression: SaveCompression,
     pub saving_status: Arc<AtomicBool>,
+    /// Number of world commands applied since the last save
+    pub changes_since_save: usize,
 }
 
 #[allow(dead_code)]
@@ -23,6 +23,8 @@ impl AuthentID {
 
 #[derive(PartialEq, Eq, Debug)]
 pub(crate) enum ClientGameState {
+    /// Accepted, but the world to send is still being serialized
+    WaitingForWorld,
     Downloading,
     CatchingUp,
     Playing,
@@ -37,6 +39,8 @@ pub(crate) struct Client {
     pub udp_addr: SocketAddr,
     pub tcp_addr: SocketAddr,
     pub state: ClientGameState,
+    /// Spectators follow the game but their inputs are dropped
+    pub spectator: bool,
 }
 
 enum ClientConnectState {
@@ -80,6 +84,7 @@ impl Authent {
         ack: Frame,
         name: String,
         version: String,
+        spectator: bool,
         period: Duration,
     ) -> Option<AuthentResponse> {
         let v = self.get_client_state_mut(addr)?;
@@ -117,7 +122,8 @@ impl Authent {
 
                 udp_addr,
                 tcp_addr,
-                state: ClientGameState::Downloading,
+                state: ClientGameState::WaitingForWorld,
+                spectator,
             });
 
             self.n_connected_clients += 1;
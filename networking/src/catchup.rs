@@ -33,6 +33,20 @@ impl CatchUp {
         }
     }
 
+    /// The world sent to the client was taken at `frame`, so the inputs up to it are already applied.
+    /// Returns false if the inputs after `frame` are not remembered.
+    pub fn start_from(&mut self, c: &Client, frame: Frame) -> bool {
+        let Some(state) = self.frame_history.get_mut(&c.id) else {
+            return false;
+        };
+        if frame < state.from || frame.0 > state.from.0 + state.inputs.len() as u64 {
+            return false;
+        }
+        state.inputs.drain(..(frame.0 - state.from.0) as usize);
+        state.from = frame;
+        true
+    }
+
     pub fn add_merged_inputs(&mut self, frame: Frame, inp: MergedInputs) {
         for v in self.frame_history.values_mut() {
             if frame.0 != v.from.0 + 1 + v.inputs.len() as u64 {
@@ -48,14 +62,12 @@ impl CatchUp {
         }
     }
 
-    pub fn update(&mut self, c: &mut Client, net: &Connections) {
-        let state = match self.frame_history.get_mut(&c.id) {
-            Some(x) => x,
-            None => return,
-        };
+    /// Returns the frame the client starts playing at once the catch up is over
+    pub fn update(&mut self, c: &mut Client, net: &Connections) -> Option<Frame> {
+        let state = self.frame_history.get_mut(&c.id)?;
 
         if !state.ready {
-            return;
+            return None;
         }
 
         state.ready = false;
@@ -78,12 +90,13 @@ impl CatchUp {
             );
             c.state = ClientGameState::Playing;
             self.frame_history.remove(&c.id);
-            return;
+            return Some(c.ack);
         }
 
         let pack = ServerReliablePacket::CatchUp { inputs };
 
         net.send_tcp(c.tcp_addr, encode(&pack));
+        None
     }
 
     pub fn disconnected(&mut self, id: AuthentID) {
@@ -11,9 +11,10 @@ use crate::packets::{
     AuthentResponse, ClientReliablePacket, ClientUnreliablePacket, ServerReliablePacket,
     ServerUnreliablePacket,
 };
+use crate::stats::NetworkStats;
 use crate::worldsend::WorldReceive;
 use crate::{
-    decode, decode_merged, encode, AuthentID, Frame, PhantomSendSync, PlayerInput, DEFAULT_PORT,
+    decode, decode_merged, encode, encode_input, AuthentID, Frame, PhantomSendSync, DEFAULT_PORT,
 };
 use common::timestep::Timestep;
 
@@ -38,6 +39,21 @@ pub enum PollResult<W, I> {
     Disconnect(String),
 }
 
+/// Whether the world got after joining is the same as the server one
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JoinCheck {
+    Pending,
+    Clean,
+    Desync,
+}
+
+/// Hashes of the world at the frame the client started playing
+struct HashCheck {
+    frame: Frame,
+    mine: Option<u64>,
+    server: Option<u64>,
+}
+
 #[allow(clippy::large_enum_variant)]
 enum ClientState<W, I> {
     Connecting,
@@ -65,11 +81,14 @@ pub struct Client<WORLD: DeserializeOwned, INPUT: Serialize + DeserializeOwned +
 
     name: String,
     version: String,
+    spectator: bool,
 
     state: ClientState<WORLD, INPUT>,
+    hash_check: Option<HashCheck>,
 
     pub step: Timestep,
     lag_compensate: u64,
+    stats: NetworkStats,
 
     _phantom: PhantomSendSync<(INPUT, WORLD)>,
 }
@@ -80,6 +99,8 @@ pub struct ConnectConf {
     pub port: Option<u16>,
     pub frame_buffer_advance: u64,
     pub version: String,
+    /// Only watch the game, the inputs are not sent
+    pub spectator: bool,
 }
 
 impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I> {
@@ -93,9 +114,12 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
         Ok(Self {
             net,
             state: ClientState::Connecting,
+            hash_check: None,
             name: conf.name,
+            spectator: conf.spectator,
             lag_compensate: conf.frame_buffer_advance,
             step: Timestep::default(),
+            stats: NetworkStats::default(),
             _phantom: Default::default(),
             version: conf.version,
         })
@@ -127,6 +151,7 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
         }
 
         while let Some(data) = self.net.recv_udp() {
+            self.stats.received(data.len());
             if let Some(packet) = decode(&data) {
                 self.message_unreliable(packet);
             } else {
@@ -203,12 +228,13 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
                     return PollResult::Wait(input);
                 }
 
-                let mut inp = Some(&input);
+                let mut inp = (!self.spectator).then_some(&input);
+                let stats = &mut self.stats;
                 let mut mk_input = || {
                     let d = Default::default();
-                    let v = inp.take().unwrap_or(&d);
-                    let serialized = encode(&v);
-                    PlayerInput(serialized)
+                    let v = encode_input(inp.take().unwrap_or(&d));
+                    stats.input(v.0.len());
+                    v
                 };
 
                 let advance = buffer.advance();
@@ -227,14 +253,22 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
 
                     let net = &mut self.net;
 
-                    let multi: Vec<_> = (0..to_consume)
+                    let consumed: Vec<_> = (0..to_consume)
                         .map(move |_| {
                             // unwrap ok: to_consume must be less than advance
                             let (inp, pack) = buffer.try_consume(&mut mk_input).unwrap();
-                            net.send_udp(encode(&ClientUnreliablePacket::Input { input: pack }));
-                            decode_merged(id, inp, buffer.consumed_frame())
+                            let packet = encode(&ClientUnreliablePacket::Input { input: pack });
+                            let sent = packet.len();
+                            net.send_udp(packet);
+                            (decode_merged(id, inp, buffer.consumed_frame()), sent)
                         })
                         .collect();
+
+                    let mut multi = Vec::with_capacity(consumed.len());
+                    for (inputs, sent) in consumed {
+                        self.stats.sent(sent);
+                        multi.push(inputs);
+                    }
                     //log::info!("consuming {:?} inputs from unreliable channel", multi.len());
                     return PollResult::Input(multi);
                 }
@@ -325,6 +359,11 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
                         final_consumed_frame,
                         Frame(consumed_frame.0 + final_inputs.len() as u64)
                     );
+                    self.hash_check = Some(HashCheck {
+                        frame: final_consumed_frame,
+                        mine: None,
+                        server: None,
+                    });
                     self.state = ClientState::Playing {
                         id,
                         buffer: ClientPlayoutBuffer::new(final_consumed_frame, 3),
@@ -344,6 +383,16 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
                     );
                 }
             }
+            ServerReliablePacket::WorldHash { frame, hash } => {
+                log::info!("{}: received world hash of {:?}", self.name, frame);
+                match self.hash_check {
+                    Some(ref mut check) if check.frame == frame => {
+                        check.server = Some(hash);
+                        self.log_join_check();
+                    }
+                    _ => log::error!("received world hash of a frame that was not asked"),
+                }
+            }
         }
         None
     }
@@ -367,12 +416,79 @@ impl<W: DeserializeOwned, I: Serialize + DeserializeOwned + Default> Client<W, I
                 let connect = ClientReliablePacket::Connect {
                     name: self.name.clone(),
                     version: self.version.clone(),
+                    spectator: self.spectator,
                 };
                 self.net.send_tcp(encode(&connect));
             }
         }
     }
 
+    pub fn stats(&self) -> &NetworkStats {
+        &self.stats
+    }
+
+    pub fn is_spectator(&self) -> bool {
+        self.spectator
+    }
+
+    /// Whether the world hash at this frame is needed to check the join
+    pub fn world_hash_wanted(&self, frame: Frame) -> bool {
+        matches!(self.hash_check, Some(HashCheck { frame: f, mine: None, .. }) if f == frame)
+    }
+
+    /// Gives the hash of the world at the frame the client started playing
+    pub fn set_world_hash(&mut self, frame: Frame, hash: u64) {
+        if let Some(ref mut check) = self.hash_check {
+            if check.frame == frame {
+                check.mine = Some(hash);
+                self.log_join_check();
+            }
+        }
+    }
+
+    pub fn join_check(&self) -> JoinCheck {
+        match self.hash_check {
+            Some(HashCheck {
+                mine: Some(mine),
+                server: Some(server),
+                ..
+            }) => {
+                if mine == server {
+                    JoinCheck::Clean
+                } else {
+                    JoinCheck::Desync
+                }
+            }
+            _ => JoinCheck::Pending,
+        }
+    }
+
+    fn log_join_check(&self) {
+        match self.join_check() {
+            JoinCheck::Pending => {}
+            JoinCheck::Clean => log::info!("{}: world is the same as the server", self.name),
+            JoinCheck::Desync => log::error!(
+                "{}: world is different from the server after joining, desync!",
+                self.name
+            ),
+        }
+    }
+
+    /// What is left before playing and how much of it is done, None once playing
+    pub fn join_progress(&self) -> Option<(&'static str, f32)> {
+        match self.state {
+            ClientState::Connecting => Some(("Connecting", 0.0)),
+            ClientState::Downloading { ref wr, .. } => match wr.progress() {
+                Some((cur, total)) if total > 0 => {
+                    Some(("Downloading the world", cur as f32 / total as f32))
+                }
+                _ => Some(("Waiting for the world", 0.0)),
+            },
+            ClientState::CatchingUp { .. } => Some(("Catching up", 1.0)),
+            ClientState::Playing { .. } | ClientState::Disconnected { .. } => None,
+        }
+    }
+
     pub fn describe(&self) -> String {
         match self.state {
             ClientState::Connecting => "Connecting...".to_string(),
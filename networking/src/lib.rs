@@ -1,7 +1,7 @@
 #![allow(clippy::uninlined_format_args)]
 
 use crate::authent::AuthentID;
-use common::saveload::{CompressedBincode, Encoder};
+use common::saveload::{Bincode, CompressedBincode, Encoder};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::hash_map::DefaultHasher;
@@ -17,11 +17,15 @@ mod connections;
 mod packets;
 mod ring;
 mod server;
+mod stats;
 mod worldsend;
 
 use crate::client::FrameInputs;
-pub use client::{Client, ConnectConf, PollResult, ServerInput};
-pub use server::{Server, ServerConfiguration, ServerPollResult, VirtualClientConf};
+pub use client::{Client, ConnectConf, JoinCheck, PollResult, ServerInput};
+pub use server::{
+    Server, ServerConfiguration, ServerPollResult, SnapshotRequest, VirtualClientConf,
+};
+pub use stats::{NetworkStats, TrafficCounters};
 
 pub(crate) const MAX_WORLDSEND_PACKET_SIZE: usize = 262144; //32 ko at least 1.3Mo per s at 50FPS
 pub(crate) const DEFAULT_PORT: u16 = 23019;
@@ -77,6 +81,15 @@ pub(crate) fn decode<T: DeserializeOwned>(x: &[u8]) -> Option<T> {
     Enc::decode(x).ok()
 }
 
+/// Inputs are not compressed on their own as the packets carrying them are
+pub(crate) fn encode_input<T: Serialize>(x: &T) -> PlayerInput {
+    PlayerInput(Bincode::encode(x).expect("failed serializing input"))
+}
+
+pub(crate) fn decode_input<T: DeserializeOwned>(x: &PlayerInput) -> Option<T> {
+    Bincode::decode(&x.0).ok()
+}
+
 pub(crate) struct PhantomSendSync<T>(PhantomData<T>);
 
 unsafe impl<T> Send for PhantomSendSync<T> {}
@@ -106,7 +119,7 @@ fn decode_merged<I: DeserializeOwned>(
             .flat_map(|(id, x)| {
                 Some(ServerInput {
                     sent_by_me: id == me,
-                    inp: decode(&x.0)?,
+                    inp: decode_input(&x)?,
                 })
             })
             .collect(),
@@ -21,6 +21,11 @@ pub(crate) enum ServerReliablePacket {
         inputs: Vec<MergedInputs>,
     },
     WorldSend(WorldDataFragment),
+    /// Hash of the server world once the client is ready to play, to check the join went well
+    WorldHash {
+        frame: Frame,
+        hash: u64,
+    },
 }
 
 #[derive(Serialize, Deserialize)]
@@ -31,7 +36,11 @@ pub(crate) enum ClientUnreliablePacket {
 
 #[derive(Serialize, Deserialize)]
 pub(crate) enum ClientReliablePacket {
-    Connect { name: String, version: String },
+    Connect {
+        name: String,
+        version: String,
+        spectator: bool,
+    },
     BeginCatchUp,
     CatchUpAck,
     WorldAck,
@@ -11,11 +11,13 @@ use crate::packets::{
     ServerUnreliablePacket,
 };
 use crate::server::server_playout::ServerPlayoutBuffer;
+use crate::stats::NetworkStats;
 use crate::worldsend::WorldSend;
-use crate::{decode, decode_merged, encode, Frame, PhantomSendSync, PlayerInput, DEFAULT_PORT};
+use crate::{decode, decode_merged, encode, encode_input, Frame, PhantomSendSync, DEFAULT_PORT};
 use common::timestep::Timestep;
 use serde::de::DeserializeOwned;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 
 mod server_playout;
 
@@ -43,6 +45,20 @@ struct VirtualClient {
     name: String,
 }
 
+/// The world is needed to let a client join.
+/// It can be answered from another thread so the game is not stopped while it is serialized.
+pub struct SnapshotRequest<WORLD> {
+    tx: Sender<(Frame, Vec<u8>)>,
+    _phantom: PhantomSendSync<WORLD>,
+}
+
+impl<WORLD: Serialize> SnapshotRequest<WORLD> {
+    /// `frame` must be the last frame applied to the world
+    pub fn send(self, frame: Frame, world: &WORLD) {
+        let _ = self.tx.send((frame, encode(world)));
+    }
+}
+
 pub struct Server<WORLD: Serialize, INPUT> {
     net: Connections,
 
@@ -52,9 +68,13 @@ pub struct Server<WORLD: Serialize, INPUT> {
     buffer: ServerPlayoutBuffer,
     catchup: CatchUp,
     worldsend: WorldSend,
+    snapshot: Option<Receiver<(Frame, Vec<u8>)>>,
+    /// Frames at which the world hash is sent to the clients that just caught up
+    hash_checks: Vec<(Frame, SocketAddr)>,
 
     step: Timestep,
     always_run: bool,
+    stats: NetworkStats,
 
     _phantom: PhantomSendSync<(WORLD, INPUT)>,
 }
@@ -78,18 +98,16 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
             authent,
             catchup: CatchUp::default(),
             worldsend: Default::default(),
+            snapshot: None,
+            hash_checks: vec![],
             _phantom: Default::default(),
             always_run: conf.always_run,
+            stats: NetworkStats::default(),
             next_inputs: vec![],
         })
     }
 
-    pub fn poll(
-        &mut self,
-        world: &WORLD,
-        frame: Frame,
-        local_inputs: Option<INPUT>,
-    ) -> ServerPollResult<INPUT> {
+    pub fn poll(&mut self, local_inputs: Option<INPUT>) -> ServerPollResult<INPUT> {
         let (new, deleted) = self.net.handle_tcp_conns();
         for addr in new {
             self.tcp_connected(addr);
@@ -105,7 +123,7 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
             }
             for p in v {
                 if let Some(packet) = decode(&p.data) {
-                    let _ = self.message_reliable(p.addr, packet, world, frame);
+                    let _ = self.message_reliable(p.addr, packet);
                 } else {
                     log::error!("client sent invalid reliable packet");
                 }
@@ -113,6 +131,7 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
         }
 
         while let Some(p) = self.net.recv_udp() {
+            self.stats.received(p.data.len());
             if let Some(packet) = decode(&p.data) {
                 let _ = self.message_unreliable(p.addr, packet);
             } else {
@@ -120,16 +139,19 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
             }
         }
 
+        self.receive_snapshot();
         self.send_merged_inputs();
         self.send_long_running();
 
         if !self.next_inputs.is_empty() {
             if self.v_client.is_some() {
                 if let Some(inp) = local_inputs {
+                    let inp = encode_input(&inp);
+                    self.stats.input(inp.0.len());
                     self.buffer.insert_input(
                         AuthentID::VIRTUAL_ID,
                         self.buffer.consumed_frame.incred(),
-                        PlayerInput(encode(&inp)),
+                        inp,
                     );
                 }
             }
@@ -138,6 +160,78 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
         ServerPollResult::Wait(local_inputs)
     }
 
+    /// Asks for the world when a client is waiting for it and it is not being serialized already
+    pub fn take_snapshot_request(&mut self) -> Option<SnapshotRequest<WORLD>> {
+        if self.snapshot.is_some()
+            || !self
+                .authent
+                .iter()
+                .any(|c| c.state == ClientGameState::WaitingForWorld)
+        {
+            return None;
+        }
+        let (tx, rx) = channel();
+        self.snapshot = Some(rx);
+        Some(SnapshotRequest {
+            tx,
+            _phantom: Default::default(),
+        })
+    }
+
+    fn receive_snapshot(&mut self) {
+        let Some(ref rx) = self.snapshot else {
+            return;
+        };
+        let (frame, data) = match rx.try_recv() {
+            Ok(x) => x,
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                log::error!("world snapshot was dropped before being sent, asking again");
+                self.snapshot = None;
+                return;
+            }
+        };
+        self.snapshot = None;
+        log::info!(
+            "world snapshot of {}ko taken at {:?}",
+            data.len() / 1000,
+            frame
+        );
+
+        for c in self.authent.iter_mut() {
+            if c.state != ClientGameState::WaitingForWorld {
+                continue;
+            }
+            // the snapshot is older than the client, it will be asked again
+            if !self.catchup.start_from(c, frame) {
+                continue;
+            }
+            self.worldsend.begin_send(c, data.clone(), frame);
+            c.state = ClientGameState::Downloading;
+        }
+    }
+
+    /// Whether the world hash at this frame has to be sent to a client that just caught up
+    pub fn world_hash_wanted(&self, frame: Frame) -> bool {
+        self.hash_checks.iter().any(|&(f, _)| f == frame)
+    }
+
+    /// Sends the hash of the world at `frame` to the clients that started playing at that frame
+    pub fn send_world_hash(&mut self, frame: Frame, hash: u64) {
+        let net = &self.net;
+        self.hash_checks.retain(|&(f, tcp_addr)| {
+            if f == frame {
+                net.send_tcp(
+                    tcp_addr,
+                    encode(&ServerReliablePacket::WorldHash { frame, hash }),
+                );
+            } else if f < frame {
+                log::warn!("world hash of {:?} was not sent in time to {}", f, tcp_addr);
+            }
+            f > frame
+        });
+    }
+
     fn send_merged_inputs(&mut self) {
         let n_playing = self.authent.iter_playing().count() + self.v_client.is_some() as usize;
 
@@ -171,10 +265,9 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
                 self.buffer.consume(clients_playing.clone().map(|c| c.ack));
 
             for (playing, packet) in clients_playing.zip(inputs) {
-                self.net.send_udp(
-                    playing.udp_addr,
-                    encode(&ServerUnreliablePacket::Input(packet)),
-                );
+                let packet = encode(&ServerUnreliablePacket::Input(packet));
+                self.stats.sent(packet.len());
+                self.net.send_udp(playing.udp_addr, packet);
             }
 
             self.next_inputs.push(decode_merged(
@@ -195,7 +288,9 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
                     self.worldsend.update(c, &self.net);
                 }
                 ClientGameState::CatchingUp => {
-                    self.catchup.update(c, &self.net);
+                    if let Some(frame) = self.catchup.update(c, &self.net) {
+                        self.hash_checks.push((frame, c.tcp_addr));
+                    }
                 }
                 _ => {}
             }
@@ -215,6 +310,10 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
 
                 for (frame, input) in input {
                     client.ack = client.ack.max(frame);
+                    // spectators only tell how far they are
+                    if client.spectator {
+                        continue;
+                    }
                     self.buffer.insert_input(client.id, frame, input);
                 }
             }
@@ -225,21 +324,25 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
         Some(())
     }
 
-    fn message_reliable(
-        &mut self,
-        addr: SocketAddr,
-        packet: ClientReliablePacket,
-        w: &WORLD,
-        w_frame: Frame,
-    ) -> Option<()> {
+    fn message_reliable(&mut self, addr: SocketAddr, packet: ClientReliablePacket) -> Option<()> {
         match packet {
-            ClientReliablePacket::Connect { name, version } => {
-                log::info!("received tcp game handshake: {} {}", name, version);
+            ClientReliablePacket::Connect {
+                name,
+                version,
+                spectator,
+            } => {
+                log::info!(
+                    "received tcp game handshake: {} {} spectator: {}",
+                    name,
+                    version,
+                    spectator
+                );
                 let auth_r = self.authent.tcp_client_auth(
                     addr,
                     self.buffer.consumed_frame,
                     name,
                     version,
+                    spectator,
                     self.step.period,
                 )?;
 
@@ -250,13 +353,10 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
 
                 match auth_r {
                     AuthentResponse::Accepted { .. } => {
+                        // the world is sent once a snapshot is taken, see take_snapshot_request
                         let c = self.authent.get_client(addr)?;
-                        assert_eq!(self.buffer.consumed_frame, w_frame);
-                        self.worldsend.begin_send(c, encode(&w), w_frame);
                         self.catchup
                             .begin_remembering(self.buffer.consumed_frame, c);
-
-                        self.authent.get_client_mut(addr)?.state = ClientGameState::Downloading;
                     }
                     AuthentResponse::Refused { reason } => {
                         log::error!("refused authent because: {}", reason);
@@ -292,6 +392,10 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
         self.disconnect(tcp_addr);
     }
 
+    pub fn stats(&self) -> &NetworkStats {
+        &self.stats
+    }
+
     pub fn describe(&self) -> String {
         let mut s = "".to_string();
         s += "Users:\n";
@@ -299,7 +403,8 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
             s += &*format!("{}: Playing...\n", c.name)
         }
         for c in self.authent.iter() {
-            s += &*format!("{}: {:?}...\n", c.name, c.state);
+            let spectating = if c.spectator { " (spectating)" } else { "" };
+            s += &*format!("{}{}: {:?}...\n", c.name, spectating, c.state);
         }
         s
     }
@@ -310,6 +415,7 @@ impl<WORLD: 'static + Serialize, INPUT: Serialize + DeserializeOwned> Server<WOR
             self.buffer.disconnected(c.id);
             self.catchup.disconnected(c.id);
             self.worldsend.disconnected(c.id);
+            self.hash_checks.retain(|&(_, addr)| addr != c.tcp_addr);
         }
     }
 }
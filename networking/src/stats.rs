@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+
+/// What was sent and received for the inputs
+#[derive(Default, Copy, Clone, Debug)]
+pub struct TrafficCounters {
+    /// Frames whose inputs of this player were sent
+    pub input_frames: u64,
+    /// Size of the inputs of this player, before the packets are compressed
+    pub input_bytes: u64,
+    /// Size of the input packets sent, as they go on the wire
+    pub sent_bytes: u64,
+    pub sent_packets: u64,
+    /// Size of the input packets received, as they come from the wire
+    pub received_bytes: u64,
+    pub received_packets: u64,
+}
+
+/// Traffic of the inputs, in total and over the last second
+#[derive(Copy, Clone, Debug)]
+pub struct NetworkStats {
+    pub total: TrafficCounters,
+    pub last_second: TrafficCounters,
+    current: TrafficCounters,
+    current_start: Instant,
+}
+
+impl Default for NetworkStats {
+    fn default() -> Self {
+        Self {
+            total: Default::default(),
+            last_second: Default::default(),
+            current: Default::default(),
+            current_start: Instant::now(),
+        }
+    }
+}
+
+impl NetworkStats {
+    pub(crate) fn input(&mut self, bytes: usize) {
+        self.record(|c| {
+            c.input_frames += 1;
+            c.input_bytes += bytes as u64;
+        });
+    }
+
+    pub(crate) fn sent(&mut self, bytes: usize) {
+        self.record(|c| {
+            c.sent_packets += 1;
+            c.sent_bytes += bytes as u64;
+        });
+    }
+
+    pub(crate) fn received(&mut self, bytes: usize) {
+        self.record(|c| {
+            c.received_packets += 1;
+            c.received_bytes += bytes as u64;
+        });
+    }
+
+    fn record(&mut self, f: impl Fn(&mut TrafficCounters)) {
+        if self.current_start.elapsed() >= Duration::from_secs(1) {
+            self.last_second = std::mem::take(&mut self.current);
+            self.current_start = Instant::now();
+        }
+        f(&mut self.total);
+        f(&mut self.current);
+    }
+
+    /// Average size of the inputs of a frame, before compression
+    pub fn avg_input_size(&self) -> f32 {
+        self.total.input_bytes as f32 / self.total.input_frames.max(1) as f32
+    }
+}
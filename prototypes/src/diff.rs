@@ -0,0 +1,53 @@
+//! What changed between two loads of the prototypes, e.g. after a reload
+
+use crate::{Prototype, Prototypes};
+
+/// Prototypes by qualified name, e.g. "goods-company/bakery"
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PrototypeDiff {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl PrototypeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    /// Whether a prototype of the type is added, changed or removed, so that what is built from
+    /// that type only is rebuilt when needed
+    pub fn touches<T: Prototype>(&self) -> bool {
+        let prefix = format!("{}/", T::NAME);
+        self.added
+            .iter()
+            .chain(&self.changed)
+            .chain(&self.removed)
+            .any(|name| name.starts_with(&prefix))
+    }
+}
+
+impl Prototypes {
+    /// The prototypes `new` adds, changes or removes compared to `old`, sorted by qualified name.
+    /// A prototype changed if any of its fields did.
+    pub fn diff(old: &Prototypes, new: &Prototypes) -> PrototypeDiff {
+        let old = old.descriptions();
+        let new = new.descriptions();
+
+        let mut diff = PrototypeDiff::default();
+        for (name, description) in &new {
+            match old.get(name) {
+                None => diff.added.push(name.clone()),
+                Some(old_description) if old_description != description => {
+                    diff.changed.push(name.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .into_keys()
+            .filter(|name| !new.contains_key(name))
+            .collect();
+        diff
+    }
+}
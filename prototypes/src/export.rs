@@ -0,0 +1,147 @@
+//! The loaded prototypes as JSON, for the balancing tools and the wiki working on the game data.
+//! The fields written in a way the saves cannot use, like the IDs as names, go through the
+//! serializers of this module with `#[serde(serialize_with)]`.
+
+use crate::{BuildingGen, EntranceDef, EntranceKind, GameDuration, Money, PrototypeID, Prototypes};
+use geom::Vec2;
+use serde::{Serialize, Serializer};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Every prototype by type, in the order of their ordering so that two versions of the game give
+/// comparable files. The parents' fields are inlined, the IDs are the names of the prototypes and
+/// the durations read like "2h". JSON has no infinite numbers, they are written as null.
+pub fn export_json(p: &Prototypes) -> serde_json::Result<String> {
+    struct ByType<'a>(&'a Prototypes);
+
+    impl Serialize for ByType<'_> {
+        fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize_by_type(s)
+        }
+    }
+
+    with_names(p, || {
+        let mut out = serde_json::to_string_pretty(&ByType(p))?;
+        out.push('\n');
+        Ok(out)
+    })
+}
+
+/// [`export_json`] written to a file
+pub fn export_json_to(p: &Prototypes, path: impl AsRef<Path>) -> std::io::Result<()> {
+    std::fs::write(path, export_json(p)?)
+}
+
+thread_local! {
+    /// Name of the prototypes by the value of their ID while exporting, the IDs are written as
+    /// that value otherwise
+    static NAMES: RefCell<Option<BTreeMap<u64, String>>> = RefCell::new(None);
+}
+
+/// Runs `f` with the IDs serialized as the names of the prototypes of `p`
+fn with_names<R>(p: &Prototypes, f: impl FnOnce() -> R) -> R {
+    let prev = NAMES.with(|names| names.replace(Some(p.names())));
+    let v = f();
+    NAMES.with(|names| *names.borrow_mut() = prev);
+    v
+}
+
+struct Named<ID>(ID);
+
+impl<ID: PrototypeID> Serialize for Named<ID> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let hash = self.0.name_hash();
+        NAMES.with(
+            |names| match names.borrow().as_ref().and_then(|n| n.get(&hash)) {
+                Some(name) => s.serialize_str(name),
+                None => s.serialize_u64(hash),
+            },
+        )
+    }
+}
+
+pub(crate) fn id<ID: PrototypeID, S: Serializer>(id: &ID, s: S) -> Result<S::Ok, S::Error> {
+    Named(*id).serialize(s)
+}
+
+pub(crate) fn opt_id<ID: PrototypeID, S: Serializer>(
+    id: &Option<ID>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    id.map(Named).serialize(s)
+}
+
+pub(crate) fn ids<ID: PrototypeID, S: Serializer>(ids: &[ID], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(ids.iter().copied().map(Named))
+}
+
+pub(crate) fn opt_ids<ID: PrototypeID, S: Serializer>(
+    ids: &Option<Vec<ID>>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match ids {
+        Some(ids) => s.serialize_some(&ids.iter().copied().map(Named).collect::<Vec<_>>()),
+        None => s.serialize_none(),
+    }
+}
+
+/// Like the prototypes write it when it is exact, e.g. "2h", in ticks otherwise
+pub(crate) fn duration<S: Serializer>(d: &GameDuration, s: S) -> Result<S::Ok, S::Error> {
+    let readable = d.to_string();
+    if readable.parse::<GameDuration>().ok() == Some(*d) {
+        return s.serialize_str(&readable);
+    }
+    s.collect_str(&format_args!("{}t", d.0 .0))
+}
+
+/// In dollars, the saves keep the ten-thousandths
+pub(crate) fn money<S: Serializer>(m: &Money, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(m.0 as f64 / 10000.0)
+}
+
+/// As `[x, y]`, the saves pack the two floats in one integer
+pub(crate) fn vec2<S: Serializer>(v: &Vec2, s: S) -> Result<S::Ok, S::Error> {
+    [v.x, v.y].serialize(s)
+}
+
+pub(crate) fn vec2s<S: Serializer>(v: &[Vec2], s: S) -> Result<S::Ok, S::Error> {
+    s.collect_seq(v.iter().map(|v| [v.x, v.y]))
+}
+
+/// [`BuildingGen`] with its door as `[x, y]`
+pub(crate) fn bgen<S: Serializer>(gen: &BuildingGen, s: S) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    enum Gen {
+        House,
+        Farm,
+        CenteredDoor { vertical_factor: f32 },
+        NoWalkway { door_pos: [f32; 2] },
+    }
+
+    match *gen {
+        BuildingGen::House => Gen::House,
+        BuildingGen::Farm => Gen::Farm,
+        BuildingGen::CenteredDoor { vertical_factor } => Gen::CenteredDoor { vertical_factor },
+        BuildingGen::NoWalkway { door_pos } => Gen::NoWalkway {
+            door_pos: [door_pos.x, door_pos.y],
+        },
+    }
+    .serialize(s)
+}
+
+/// [`EntranceDef`]s with their position as `[x, y]`
+pub(crate) fn entrances<S: Serializer>(v: &[EntranceDef], s: S) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct Entrance {
+        pos: [f32; 2],
+        kind: EntranceKind,
+        optional: bool,
+    }
+
+    s.collect_seq(v.iter().map(|e| Entrance {
+        pos: [e.pos.x, e.pos.y],
+        kind: e.kind,
+        optional: e.optional,
+    }))
+}
@@ -0,0 +1,218 @@
+//! Inheritance between the data tables: a table with `extends = "other-name"` starts from the
+//! table of the prototype of the same type with that name, and its own fields win.
+//! Nested tables are merged the same way, except lists which the child replaces, or adds to the
+//! end of the parent's when it has `__append = true`.
+
+use crate::PrototypeError;
+use mlua::{Lua, Table, Value};
+use std::collections::BTreeMap;
+
+const EXTENDS: &str = "extends";
+const APPEND: &str = "__append";
+
+#[derive(Clone, PartialEq)]
+enum State {
+    Unresolved,
+    InProgress,
+    Resolved,
+    Broken(String),
+}
+
+struct Resolver<'a, 'lua> {
+    l: &'lua Lua,
+    tables: &'a [Option<Table<'lua>>],
+    /// (type, name) of each table, None if it has none
+    keys: Vec<Option<(String, String)>>,
+    /// The tables declaring each prototype, in order
+    index: BTreeMap<(String, String), Vec<usize>>,
+    states: Vec<State>,
+    /// Tables being resolved, the parents after the children
+    stack: Vec<usize>,
+}
+
+/// Merges the parents of the tables declaring an `extends` under them, in place.
+/// Returns the index of the tables that could not be resolved with their error, their `location`
+/// is left to the caller.
+pub(crate) fn resolve_extends(
+    l: &Lua,
+    tables: &[Option<Table>],
+) -> mlua::Result<Vec<(usize, PrototypeError)>> {
+    let keys: Vec<_> = tables
+        .iter()
+        .map(|t| {
+            let t = t.as_ref()?;
+            if t.get::<_, Option<bool>>("deleted").ok().flatten() == Some(true) {
+                // nothing to inherit from a deletion
+                return None;
+            }
+            let kind = t.get::<_, Option<String>>("type").ok().flatten()?;
+            let name = t.get::<_, Option<String>>("name").ok().flatten()?;
+            Some((kind, name))
+        })
+        .collect();
+    let mut index: BTreeMap<_, Vec<usize>> = BTreeMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        if let Some(key) = key {
+            index.entry(key.clone()).or_default().push(i);
+        }
+    }
+
+    let mut r = Resolver {
+        l,
+        tables,
+        keys,
+        index,
+        states: vec![State::Unresolved; tables.len()],
+        stack: vec![],
+    };
+    for i in 0..tables.len() {
+        r.resolve(i)?;
+    }
+
+    Ok(r.states
+        .iter()
+        .enumerate()
+        .filter_map(|(i, state)| {
+            let State::Broken(ref message) = *state else {
+                return None;
+            };
+            let (kind, name) = r.keys[i].clone().unwrap_or_default();
+            Some((
+                i,
+                PrototypeError {
+                    location: None,
+                    kind,
+                    name,
+                    field: Some(EXTENDS.to_string()),
+                    message: message.clone(),
+                },
+            ))
+        })
+        .collect())
+}
+
+impl<'a, 'lua> Resolver<'a, 'lua> {
+    /// Resolves the parents of the table first, the state of the table tells if it worked
+    fn resolve(&mut self, i: usize) -> mlua::Result<()> {
+        if self.states[i] != State::Unresolved {
+            return Ok(());
+        }
+        let (Some(t), Some((kind, name))) = (&self.tables[i], self.keys[i].clone()) else {
+            self.states[i] = State::Resolved;
+            return Ok(());
+        };
+        let Some(target) = t.get::<_, Option<String>>(EXTENDS).ok().flatten() else {
+            self.states[i] = State::Resolved;
+            return Ok(());
+        };
+
+        let Some(parent) = self.parent(i, &kind, &name, &target) else {
+            self.states[i] = State::Broken(format!("extends unknown {kind} {target}"));
+            return Ok(());
+        };
+
+        self.states[i] = State::InProgress;
+        self.stack.push(i);
+        if self.states[parent] == State::InProgress {
+            self.break_cycle(parent);
+        } else {
+            self.resolve(parent)?;
+        }
+        self.stack.pop();
+
+        if self.states[i] != State::InProgress {
+            // broken by a cycle found through the parent
+            return Ok(());
+        }
+        let (State::Resolved, Some(parent)) = (&self.states[parent], &self.tables[parent]) else {
+            self.states[i] = State::Broken(format!("extends broken {kind} {target}"));
+            return Ok(());
+        };
+        merge(self.l, parent, t)?;
+        t.raw_set(EXTENDS, Value::Nil)?;
+        strip_append(t)?;
+        self.states[i] = State::Resolved;
+        Ok(())
+    }
+
+    /// The table named by `extends`. A table extending its own name, like a mod changing a
+    /// prototype of the base game, extends the declaration before it.
+    fn parent(&self, i: usize, kind: &str, name: &str, target: &str) -> Option<usize> {
+        let declarations = self.index.get(&(kind.to_string(), target.to_string()))?;
+        if target == name {
+            declarations.iter().rev().copied().find(|&j| j < i)
+        } else {
+            declarations.last().copied()
+        }
+    }
+
+    /// Every prototype of the cycle from `parent` to the top of the stack is broken, each is told
+    /// the cycle starting from itself
+    fn break_cycle(&mut self, parent: usize) {
+        let start = self.stack.iter().position(|&j| j == parent).unwrap_or(0);
+        let cycle = self.stack[start..].to_vec();
+        for (n, &j) in cycle.iter().enumerate() {
+            let chain: Vec<&str> = cycle[n..]
+                .iter()
+                .chain(&cycle[..=n])
+                .map(|&k| self.keys[k].as_ref().map_or("?", |(_, name)| name.as_str()))
+                .collect();
+            let message = format!("extends cycle: {}", chain.join(" -> "));
+            self.states[j] = State::Broken(message);
+        }
+    }
+}
+
+/// Copies the fields of `parent` missing from `child`, merging the tables found in both
+fn merge<'lua>(l: &'lua Lua, parent: &Table<'lua>, child: &Table<'lua>) -> mlua::Result<()> {
+    for pair in parent.clone().pairs::<Value, Value>() {
+        let (k, v) = pair?;
+        if matches!(k, Value::String(ref s) if s.to_str().ok() == Some(EXTENDS)) {
+            continue;
+        }
+        match (child.raw_get::<_, Value>(k.clone())?, v) {
+            (Value::Nil, v) => child.raw_set(k, deep_copy(l, v)?)?,
+            (Value::Table(c), Value::Table(p)) => {
+                if c.raw_get::<_, Option<bool>>(APPEND)?.unwrap_or(false) {
+                    let appended = l.create_table()?;
+                    for v in p.sequence_values::<Value>() {
+                        appended.raw_set(appended.raw_len() + 1, deep_copy(l, v?)?)?;
+                    }
+                    for v in c.sequence_values::<Value>() {
+                        appended.raw_set(appended.raw_len() + 1, v?)?;
+                    }
+                    child.raw_set(k, appended)?;
+                } else if p.raw_len() == 0 && c.raw_len() == 0 {
+                    merge(l, &p, &c)?;
+                }
+                // otherwise the child's list replaces the parent's
+            }
+            // the child's value wins
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn deep_copy<'lua>(l: &'lua Lua, v: Value<'lua>) -> mlua::Result<Value<'lua>> {
+    let Value::Table(t) = v else {
+        return Ok(v);
+    };
+    let copy = l.create_table()?;
+    for pair in t.pairs::<Value, Value>() {
+        let (k, v) = pair?;
+        copy.raw_set(k, deep_copy(l, v)?)?;
+    }
+    Ok(Value::Table(copy))
+}
+
+/// Removes the append markers left in the tables the parent did not have
+fn strip_append(t: &Table) -> mlua::Result<()> {
+    t.raw_set(APPEND, Value::Nil)?;
+    for pair in t.clone().pairs::<Value, Value>() {
+        if let (_, Value::Table(nested)) = pair? {
+            strip_append(&nested)?;
+        }
+    }
+    Ok(())
+}
@@ -3,19 +3,32 @@ use geom::Vec2;
 use mlua::{FromLua, Table};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
 
 mod macros;
 
+mod diff;
+mod export;
+mod extends;
 mod load;
+mod mod_settings;
+mod mods;
 mod prototypes;
 mod tests;
 mod types;
 mod validation;
 
+pub use diff::*;
+pub use export::*;
 pub use load::*;
+pub use mod_settings::*;
+pub use mods::*;
 pub use prototypes::*;
 pub use types::*;
 
+/// The state the data files run in, see [`reload_prototypes`]
+pub use mlua::Lua;
+
 /// A prototype is a collection of data that is dynamically loaded with Lua and defines a type of object
 pub trait Prototype: 'static + Sized {
     /// The parent prototype of this prototype (optional). Use NoParent if there is no parent
@@ -54,11 +67,24 @@ pub trait ConcretePrototype: Prototype + Clone {
         Self::Parent::storage_mut(prototypes).insert(p.id(), p.clone());
         p.insert_parents(prototypes);
     }
+
+    /// The other way around, the parents go with a prototype skipped for being broken
+    fn remove_parents(&self, prototypes: &mut Prototypes) {
+        let p = self.parent();
+        if !<Self::Parent as ConcretePrototype>::HAS_PARENT {
+            return;
+        }
+        Self::Parent::storage_mut(prototypes).remove(&p.id());
+        p.remove_parents(prototypes);
+    }
 }
 
 /// The unique ID of a prototype
 pub trait PrototypeID: Debug + Copy + Clone + Eq + Ord + Hash + 'static {
     type Prototype: Prototype<ID = Self>;
+
+    /// The hash of the name of the prototype, what the saves store
+    fn name_hash(self) -> u64;
 }
 
 #[derive(Clone)]
@@ -98,23 +124,45 @@ impl ConcretePrototype for NoParent {
     }
 
     fn insert_parents(&self, _prototypes: &mut Prototypes) {}
+
+    fn remove_parents(&self, _prototypes: &mut Prototypes) {}
 }
 
-static mut PROTOTYPES: Option<&'static Prototypes> = None;
+/// Swapped as a whole when the prototypes are reloaded, the previous ones are leaked so that the
+/// references to them stay valid
+static PROTOTYPES: AtomicPtr<Prototypes> = AtomicPtr::new(std::ptr::null_mut());
+
+static PROTOTYPES_GENERATION: AtomicU64 = AtomicU64::new(0);
 
 #[inline]
 pub fn prototypes() -> &'static Prototypes {
+    let p = PROTOTYPES.load(Ordering::Acquire);
+
     #[cfg(debug_assertions)]
     {
-        assert!(unsafe { PROTOTYPES.is_some() });
+        assert!(!p.is_null());
     }
 
     // Safety: Please just don't use prototypes before they were loaded... We can allow this footgun
-    unsafe { PROTOTYPES.unwrap_unchecked() }
+    unsafe { &*p }
 }
 
 pub fn try_prototypes() -> Option<&'static Prototypes> {
-    unsafe { PROTOTYPES }
+    // Safety: the pointer is either null or comes from a leaked box
+    unsafe { PROTOTYPES.load(Ordering::Acquire).as_ref() }
+}
+
+/// Makes `p` the current prototypes, with a new generation
+pub(crate) fn set_prototypes(p: Box<Prototypes>) {
+    let generation = PROTOTYPES_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    p.generation.store(generation, Ordering::Relaxed);
+    PROTOTYPES.store(Box::leak(p), Ordering::Release);
+}
+
+/// Incremented each time the prototypes are loaded, the caches built from them compare it to know
+/// when to rebuild
+pub fn prototypes_generation() -> u64 {
+    try_prototypes().map_or(0, Prototypes::generation)
 }
 
 #[inline]
@@ -136,6 +184,28 @@ where
     <ID as PrototypeID>::Prototype::storage(prototypes()).get(&id)
 }
 
+/// Like [`try_prototype_by_name`] but panics if there is no such prototype
+#[inline]
+pub fn prototype_by_name<T: ConcretePrototype>(name: &str) -> &'static T
+where
+    for<'a> T::ID: From<&'a str>,
+{
+    match try_prototype_by_name(name) {
+        Some(v) => v,
+        None => panic!("no {} named {}", T::NAME, name),
+    }
+}
+
+/// The prototype of the type with that name, e.g. `try_prototype_by_name::<ItemPrototype>("cereal")`.
+/// The IDs are made from the names, so it is as fast as [`try_prototype`].
+#[inline]
+pub fn try_prototype_by_name<T: ConcretePrototype>(name: &str) -> Option<&'static T>
+where
+    for<'a> T::ID: From<&'a str>,
+{
+    T::storage(prototypes()).get(&T::ID::from(name))
+}
+
 #[inline]
 pub(crate) fn try_prototype_preload<ID: PrototypeID>(
     id: ID,
@@ -158,14 +228,56 @@ pub fn prototypes_iter_ids<T: ConcretePrototype>() -> impl Iterator<Item = T::ID
     T::ordering(prototypes()).iter().copied()
 }
 
+/// Error in a field of a prototype, the errors in nested tables are chained
+#[derive(Debug, thiserror::Error)]
+#[error("field {field}: {source}")]
+pub(crate) struct FieldError {
+    field: &'static str,
+    source: mlua::Error,
+}
+
+impl FieldError {
+    fn wrap(field: &'static str) -> impl FnOnce(mlua::Error) -> mlua::Error {
+        move |source| mlua::Error::external(FieldError { field, source })
+    }
+
+    /// The path of the field at fault, like "upgrade_conditions.min_road_lanes", and what is wrong
+    /// with it
+    pub(crate) fn split(mut e: &mlua::Error) -> (Option<String>, String) {
+        let mut path = vec![];
+        loop {
+            match e {
+                mlua::Error::ExternalError(ext) => match ext.downcast_ref::<FieldError>() {
+                    Some(f) => {
+                        path.push(f.field);
+                        e = &f.source;
+                    }
+                    None => break,
+                },
+                mlua::Error::CallbackError { cause, .. } => e = cause,
+                _ => break,
+            }
+        }
+        ((!path.is_empty()).then(|| path.join(".")), e.to_string())
+    }
+}
+
 fn get_lua<'a, T: FromLua<'a>>(t: &Table<'a>, field: &'static str) -> mlua::Result<T> {
-    t.get::<_, T>(field)
-        .map_err(|e| mlua::Error::external(format!("field {}: {}", field, e)))
+    t.get::<_, T>(field).map_err(FieldError::wrap(field))
 }
 
 fn get_lua_opt<'a, T: FromLua<'a>>(t: &Table<'a>, field: &'static str) -> mlua::Result<Option<T>> {
     t.get::<_, Option<T>>(field)
-        .map_err(|e| mlua::Error::external(format!("field {}: {}", field, e)))
+        .map_err(FieldError::wrap(field))
+}
+
+/// The field, or the default when the table does not have it. A value of the wrong type is still
+/// an error naming the field.
+fn get_lua_default<'a, T: FromLua<'a> + Default>(
+    t: &Table<'a>,
+    field: &'static str,
+) -> mlua::Result<T> {
+    Ok(get_lua_opt(t, field)?.unwrap_or_default())
 }
 
 fn get_v2(t: &Table, field: &'static str) -> mlua::Result<Vec2> {
@@ -1,68 +1,592 @@
 use crate::validation::ValidationError;
-use crate::{validation, Prototypes, PROTOTYPES};
+use crate::{
+    detect_mods, extends, set_loaded_mods, set_loaded_settings, set_prototypes, try_prototypes,
+    validate_mods, validation, FieldError, ModOrder, ModSettings, PrototypeDiff, Prototypes,
+    MODS_DIR, MOD_SETTINGS_FILE,
+};
+use common::diagnostics::Diagnostic;
 use common::error::MultiError;
+use common::saveload::{Encoder, JSON};
 use mlua::{Lua, Table};
+use std::collections::{BTreeMap, BTreeSet};
 use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use thiserror::Error;
 
 pub fn test_prototypes(lua: &str) {
     let l = Lua::new();
 
-    unsafe { load_prototypes_str(l, lua).unwrap() };
+    let (p, _) = unsafe {
+        load_prototypes_str(
+            &l,
+            lua,
+            &[],
+            &ModSettings::default(),
+            None,
+            LoadMode::Strict,
+            ConflictMode::LastWins,
+        )
+        .unwrap()
+    };
+    set_prototypes(p);
 }
 
-/// Loads the prototypes from the data.lua file
+/// What to do with the broken prototypes
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum LoadMode {
+    /// The loading fails with the errors of every broken prototype
+    #[default]
+    Strict,
+    /// The broken prototypes are reported and skipped, with the ones relying on them
+    Lenient,
+}
+
+/// What happens when a folder declares a prototype that an earlier folder already declared
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ConflictMode {
+    /// The later declaration replaces the earlier one, so that mods can change the base game
+    #[default]
+    LastWins,
+    /// The later declaration is a broken prototype, for setups where mods may only add content
+    Forbid,
+}
+
+/// Where the prototypes are loaded from and how
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadConfig {
+    /// The game folder first, holding base_mod, the models and the mods folder.
+    /// Then the data folders loaded after the enabled mods, in order, each with a data.lua.
+    pub search_paths: Vec<PathBuf>,
+    pub conflict: ConflictMode,
+    pub mode: LoadMode,
+}
+
+impl LoadConfig {
+    /// Only the game folder, the later declarations win and the broken prototypes fail the load
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self {
+            search_paths: vec![base.into()],
+            conflict: ConflictMode::default(),
+            mode: LoadMode::default(),
+        }
+    }
+
+    pub fn base(&self) -> &Path {
+        self.search_paths
+            .first()
+            .map_or(Path::new("./"), PathBuf::as_path)
+    }
+}
+
+/// What the folders loaded after base_mod contributed, by qualified name in load order
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LoadReport {
+    /// Prototypes declared again, with the folder of the declaration that was kept
+    pub overridden: Vec<(String, PathBuf)>,
+    /// Prototypes base_mod does not have
+    pub added: Vec<String>,
+    /// Prototypes removed with `deleted = true`, with the folder that removed them
+    pub deleted: Vec<(String, PathBuf)>,
+}
+
+impl LoadReport {
+    /// Logs what each folder overrode and how many prototypes the folders added
+    pub fn log(&self) {
+        for (name, folder) in &self.overridden {
+            log::info!("{} overridden by {}", name, folder.display());
+        }
+        for (name, folder) in &self.deleted {
+            log::info!("{} deleted by {}", name, folder.display());
+        }
+        if !self.added.is_empty() {
+            log::info!("{} prototypes added by the mods", self.added.len());
+        }
+    }
+}
+
+impl Prototypes {
+    /// The folder whose declaration of the prototype was kept, e.g. "base_mod" or the folder of a
+    /// mod, by qualified name
+    pub fn provenance(&self, qualified_name: &str) -> Option<&str> {
+        self.provenance.get(qualified_name).map(String::as_str)
+    }
+
+    /// Every prototype with the folder its declaration comes from, sorted by qualified name
+    pub fn provenances(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.provenance
+            .iter()
+            .map(|(name, folder)| (name.as_str(), folder.as_str()))
+    }
+}
+
+/// Folders the prototypes were loaded from and how, to reload them the same way
+static PROTOTYPES_CONFIG: Mutex<Option<LoadConfig>> = Mutex::new(None);
+
+/// Loads the prototypes from the data.lua file of the game folder, then from the enabled mods and
+/// the other folders of the search paths in order
 /// # Safety
 /// This function is not thread safe, and should only be called once at the start of the program.
-pub unsafe fn load_prototypes(base: &str) -> Result<(), PrototypeLoadError> {
-    log::info!("loading prototypes from {}", base);
-    let l = Lua::new();
+pub unsafe fn load_prototypes(config: &LoadConfig) -> Result<LoadReport, PrototypeLoadError> {
+    log::info!("loading prototypes from {:?}", config.search_paths);
+    *PROTOTYPES_CONFIG.lock().unwrap() = Some(config.clone());
+    load_prototypes_lua(&Lua::new(), config, |_| Ok(()))
+}
+
+/// Loads the prototypes again from the same files while the game runs, and tells what changed.
+/// The data files run in `lua`, a state the previous loads did not use.
+/// The references to the previous prototypes stay valid, and they are kept if the new ones have
+/// errors, which are logged like at the first load, or if they remove one of the `pinned`
+/// prototypes, the ones live entities use, by qualified name.
+/// The caches built from the prototypes see the change through [`crate::prototypes_generation`].
+/// # Safety
+/// Must be called from the thread that loaded the prototypes, never at the same time as another
+/// load, and not while another thread is iterating over the prototypes.
+pub unsafe fn reload_prototypes(
+    lua: &Lua,
+    pinned: &BTreeSet<String>,
+) -> Result<PrototypeDiff, PrototypeLoadError> {
+    let config = PROTOTYPES_CONFIG
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| LoadConfig::new("./"));
+    reload_prototypes_with(lua, &config, pinned)
+}
+
+/// [`reload_prototypes`] from the folders of `config`
+/// # Safety
+/// Same as [`reload_prototypes`]
+pub(crate) unsafe fn reload_prototypes_with(
+    lua: &Lua,
+    config: &LoadConfig,
+    pinned: &BTreeSet<String>,
+) -> Result<PrototypeDiff, PrototypeLoadError> {
+    log::info!("reloading prototypes from {:?}", config.search_paths);
+
+    let mut diff = PrototypeDiff::default();
+    let r = load_prototypes_lua(lua, config, |new| {
+        if let Some(old) = try_prototypes() {
+            diff = Prototypes::diff(old, new);
+        }
+        let removed: Vec<_> = diff
+            .removed
+            .iter()
+            .filter(|name| pinned.contains(*name))
+            .cloned()
+            .collect();
+        if !removed.is_empty() {
+            return Err(PrototypeLoadError::PinnedRemoved(removed));
+        }
+        Ok(())
+    });
+
+    match r {
+        Ok(_) => {
+            log::info!(
+                "reloaded prototypes: {} added, {} changed, {} removed",
+                diff.added.len(),
+                diff.changed.len(),
+                diff.removed.len()
+            );
+            Ok(diff)
+        }
+        Err(e) => {
+            log::error!(
+                "could not reload prototypes, keeping the previous ones: {}",
+                e
+            );
+            Err(e)
+        }
+    }
+}
+
+/// A data.lua run after the one of base_mod
+pub(crate) struct DataStage {
+    /// Folder of the data.lua, the overrides are reported with it
+    pub dir: PathBuf,
+    /// Name of the file in the errors, e.g. "my-mod/data.lua"
+    pub chunk_name: String,
+    /// Where `require` looks for the files, the folder first then base_mod
+    pub package_path: String,
+    pub code: String,
+}
+
+impl DataStage {
+    fn load(dir: PathBuf, name: &str, base_mod: &Path) -> Result<Self, PrototypeLoadError> {
+        let code = common::saveload::load_string(dir.join("data.lua"))?;
+        Ok(Self {
+            package_path: format!(
+                "{};{}",
+                dir.join("?.lua").display(),
+                base_mod.join("?.lua").display()
+            ),
+            chunk_name: format!("{name}/data.lua"),
+            dir,
+            code,
+        })
+    }
+}
+
+/// Loads the prototypes of the search paths, they replace the current ones if `check` accepts them
+unsafe fn load_prototypes_lua(
+    l: &Lua,
+    config: &LoadConfig,
+    check: impl FnOnce(&Prototypes) -> Result<(), PrototypeLoadError>,
+) -> Result<LoadReport, PrototypeLoadError> {
+    let base = config.base();
+    let base_mod = base.join("base_mod");
 
-    let base = base.to_string();
+    l.globals().get::<_, Table>("package")?.set(
+        "path",
+        base_mod.join("?.lua").to_string_lossy().into_owned(),
+    )?;
 
-    l.globals()
-        .get::<_, Table>("package")?
-        .set("path", base.clone() + "base_mod/?.lua")?;
+    let main = common::saveload::load_string(base_mod.join("data.lua"))?;
 
-    load_prototypes_str(
+    let order = <JSON as Encoder>::load::<ModOrder>("mods").unwrap_or_default();
+    let detected = detect_mods(base);
+    let errors = validate_mods(&order, &detected);
+    for (name, err) in &errors {
+        Diagnostic::error("mods", format!("mod {}: {}", name, err))
+            .asset(base.join(MODS_DIR).join(name))
+            .report();
+    }
+    if !errors.is_empty() {
+        log::error!("the mod setup is invalid, loading without mods");
+    }
+
+    let mut stages = vec![];
+    let mut loaded = vec![];
+    let mut manifests = vec![];
+    for name in order.enabled().filter(|_| errors.is_empty()) {
+        if let Some(Ok(manifest)) = detected
+            .iter()
+            .find(|d| d.folder == name)
+            .map(|d| &d.manifest)
+        {
+            loaded.push(format!("{} {}", manifest.name, manifest.version));
+            manifests.push(manifest);
+        }
+        // each mod requires its own files relative to its folder
+        let dir = base.join(MODS_DIR).join(name);
+        stages.push(DataStage::load(dir, name, &base_mod)?);
+    }
+    for dir in config.search_paths.iter().skip(1) {
+        let name = dir
+            .file_name()
+            .map_or_else(|| dir.to_string_lossy(), |n| n.to_string_lossy());
+        stages.push(DataStage::load(dir.clone(), &name, &base_mod)?);
+    }
+
+    let mut settings =
+        <JSON as Encoder>::load::<ModSettings>(MOD_SETTINGS_FILE).unwrap_or_default();
+    settings.sanitize(
+        manifests
+            .iter()
+            .map(|m| (m.name.as_str(), m.settings.as_slice())),
+    );
+
+    let models = base.join("assets/models/");
+    let (p, report) = load_prototypes_str(
         l,
-        &common::saveload::load_string(base + "base_mod/data.lua")?,
-    )
+        &main,
+        &stages,
+        &settings,
+        Some(&models),
+        config.mode,
+        config.conflict,
+    )?;
+    check(&p)?;
+    set_prototypes(p);
+    set_loaded_mods(loaded);
+    let decls = manifests
+        .iter()
+        .map(|m| (m.name.clone(), m.settings.clone()))
+        .filter(|(_, decls)| !decls.is_empty())
+        .collect();
+    set_loaded_settings(decls, settings);
+    Ok(report)
 }
 
-unsafe fn load_prototypes_str(l: Lua, main: &str) -> Result<(), PrototypeLoadError> {
+/// Runs the main data file, then each stage, and parses the prototypes.
+/// The files can read the settings of the mods, the meshes are checked against the `models` folder.
+/// Every broken prototype is found before failing, or skipping them in lenient mode.
+pub(crate) unsafe fn load_prototypes_str(
+    l: &Lua,
+    main: &str,
+    stages: &[DataStage],
+    settings: &ModSettings,
+    models: Option<&Path>,
+    mode: LoadMode,
+    conflict: ConflictMode,
+) -> Result<(Box<Prototypes>, LoadReport), PrototypeLoadError> {
+    register_caller_location(l)?;
     l.load(include_str!("prototype_init.lua")).exec()?;
+    settings.register_lua(l)?;
+
+    let data_table = l.globals().get::<_, Table>("data")?;
+
+    l.load(main).set_name("data.lua").exec()?;
+    // the data of stage i ends at ends[i], base_mod being stage 0
+    let mut ends = vec![data_table.raw_len()];
 
-    l.load(main).exec()?;
+    for stage in stages {
+        log::info!("loading mod {}", stage.chunk_name);
+        l.globals()
+            .get::<_, Table>("package")?
+            .set("path", stage.package_path.as_str())?;
+        l.load(stage.code.as_str())
+            .set_name(stage.chunk_name.as_str())
+            .exec()?;
+        ends.push(data_table.raw_len());
+    }
 
     let mut p = Box::<Prototypes>::default();
 
     let mut errors = Vec::new();
 
-    let data_table = l.globals().get::<_, Table>("data")?;
+    let data_sources = l.globals().get::<_, Table>("data_sources")?;
+
+    let tables: Vec<Option<Table>> = (1..=data_table.raw_len())
+        .map(|i| data_table.raw_get::<_, Table>(i).ok())
+        .collect();
+    let mut broken = vec![false; tables.len()];
+    for (i, mut e) in extends::resolve_extends(l, &tables)? {
+        e.location = tables[i]
+            .as_ref()
+            .and_then(|t| data_sources.get::<_, Option<String>>(t.clone()).ok())
+            .flatten();
+        errors.push(e);
+        broken[i] = true;
+    }
+    let mut sources = BTreeMap::new();
+    // stage of the declaration kept for each prototype
+    let mut declared_by = BTreeMap::new();
+    let mut overridden = vec![];
+    let mut added = vec![];
+    let mut deleted = vec![];
+
+    for (i, t) in tables.into_iter().enumerate() {
+        let Some(t) = t.filter(|_| !broken[i]) else {
+            continue;
+        };
+        let i = i + 1;
+        let stage = ends.iter().position(|&end| i <= end).unwrap_or(0);
+        let source = data_sources
+            .get::<_, Option<String>>(t.clone())
+            .ok()
+            .flatten();
+        let kind = t.get::<_, Option<String>>("type").ok().flatten();
+        let name = t.get::<_, Option<String>>("name").ok().flatten();
+        let key = kind.clone().zip(name.clone());
+        let deleting = t.get::<_, Option<bool>>("deleted").ok().flatten() == Some(true);
+
+        let previous = key.as_ref().and_then(|k| declared_by.get(k).copied());
+        match (previous, &key) {
+            (Some(prev), Some((kind, name))) if prev == stage && !deleting => {
+                Diagnostic::warning(
+                    "prototypes",
+                    format!("duplicate {kind}, the last one is kept"),
+                )
+                .prototype(name.clone())
+                .report();
+            }
+            (Some(prev), Some((kind, name))) if conflict == ConflictMode::Forbid => {
+                errors.push(PrototypeError {
+                    location: source,
+                    kind: kind.clone(),
+                    name: name.clone(),
+                    field: None,
+                    message: format!("already declared by {}", stage_name(stages, prev)),
+                });
+                continue;
+            }
+            _ => {}
+        }
 
-    let _ = data_table.for_each(|_: String, t: Table| {
-        let r = p.parse_prototype(t);
-        if let Err(e) = r {
+        if deleting {
+            let Some(key) = key else {
+                continue;
+            };
+            let (kind, name) = &key;
+            if !p.remove(kind, name) {
+                Diagnostic::warning("prototypes", format!("deletes unknown {kind}"))
+                    .prototype(name.clone())
+                    .report();
+                continue;
+            }
+            added.retain(|k| k != &key);
+            overridden.retain(|(k, n, _)| (k, n) != (kind, name));
+            sources.remove(&key);
+            declared_by.remove(&key);
+            if stage > 0 {
+                deleted.push((key, stage));
+            }
+            continue;
+        }
+
+        if let Err(mut e) = p.parse_prototype(t) {
+            e.location = source;
             errors.push(e);
+            continue;
         }
-        Ok(())
-    });
+        let Some((kind, name)) = key else {
+            continue;
+        };
+        deleted.retain(|((k, n), _)| (k, n) != (&kind, &name));
+        match previous {
+            None if stage > 0 => added.push((kind.clone(), name.clone())),
+            Some(prev) if prev < stage => {
+                overridden.retain(|(k, n, _)| (k, n) != (&kind, &name));
+                overridden.push((kind.clone(), name.clone(), stage));
+            }
+            _ => {}
+        }
+        if let Some(source) = source {
+            sources.insert((kind.clone(), name.clone()), source);
+        }
+        declared_by.insert((kind, name), stage);
+    }
+
+    let mut invalid = validation::validate(&p, models);
+    if mode == LoadMode::Lenient {
+        // the prototypes referencing a skipped one are broken in turn
+        while !invalid.is_empty() {
+            let mut removed = false;
+            for e in &invalid {
+                removed |= p.remove(&e.kind, &e.name);
+            }
+            errors.append(&mut invalid);
+            if !removed {
+                break;
+            }
+            invalid = validation::validate(&p, models);
+        }
+    }
+    errors.append(&mut invalid);
+    for e in &mut errors {
+        if e.location.is_none() {
+            e.location = sources.get(&(e.kind.clone(), e.name.clone())).cloned();
+        }
+    }
 
     if !errors.is_empty() {
-        return Err(PrototypeLoadError::MultiError(MultiError(errors)));
+        if mode == LoadMode::Strict {
+            return Err(PrototypeLoadError::Prototypes(MultiError(errors)));
+        }
+        for e in errors {
+            Diagnostic::error("prototypes", format!("skipped: {}", e))
+                .prototype(e.name)
+                .report();
+        }
     }
 
-    validation::validate(&p)?;
+    // the prototypes skipped after being declared did not contribute anything
+    let report = LoadReport {
+        overridden: overridden
+            .into_iter()
+            .filter(|(kind, name, _)| p.contains(kind, name))
+            .map(|(kind, name, stage)| (format!("{kind}/{name}"), stages[stage - 1].dir.clone()))
+            .collect(),
+        added: added
+            .into_iter()
+            .filter(|(kind, name)| p.contains(kind, name))
+            .map(|(kind, name)| format!("{kind}/{name}"))
+            .collect(),
+        deleted: deleted
+            .into_iter()
+            .map(|((kind, name), stage)| (format!("{kind}/{name}"), stages[stage - 1].dir.clone()))
+            .collect(),
+    };
+    p.provenance = declared_by
+        .into_iter()
+        .filter(|((kind, name), _)| p.contains(kind, name))
+        .map(|((kind, name), stage)| (format!("{kind}/{name}"), stage_folder(stages, stage)))
+        .collect();
 
     p.compute_orderings();
     p.print_stats();
 
-    unsafe {
-        PROTOTYPES = Some(Box::leak(p));
+    Ok((p, report))
+}
+
+/// Name of the folder of a stage, e.g. "base_mod" or the folder of the mod
+fn stage_folder(stages: &[DataStage], stage: usize) -> String {
+    match stage {
+        0 => "base_mod".to_string(),
+        i => stages[i - 1].dir.file_name().map_or_else(
+            || stages[i - 1].dir.display().to_string(),
+            |f| f.to_string_lossy().into_owned(),
+        ),
+    }
+}
+
+/// How the errors name the folder of a stage
+fn stage_name(stages: &[DataStage], stage: usize) -> &str {
+    match stage {
+        0 => "base_mod",
+        i => &stages[i - 1].chunk_name,
+    }
+}
+
+/// Defines `caller_location()` for the Lua files, it tells where the function calling it was called
+/// from as "file:line"
+fn register_caller_location(l: &Lua) -> mlua::Result<()> {
+    let f = l.create_function(|lua, ()| {
+        // 0 is this function, 1 the one calling it
+        let Some(d) = lua.inspect_stack(2) else {
+            return Ok(None);
+        };
+        let Some(source) = d.source().source else {
+            return Ok(None);
+        };
+        let file = source.trim_start_matches(['@', '=']);
+        Ok(Some(format!("{}:{}", file, d.curr_line())))
+    })?;
+    l.globals().set("caller_location", f)
+}
+
+/// A broken prototype, found while parsing it or checking it against the others
+#[derive(Error, Debug)]
+#[error(
+    "{}{kind} {name}{}: {message}",
+    .location.as_ref().map(|s| format!("{}: ", s)).unwrap_or_default(),
+    .field.as_ref().map(|f| format!(".{}", f)).unwrap_or_default()
+)]
+pub struct PrototypeError {
+    /// Where the prototype is defined as "file:line", when known
+    pub location: Option<String>,
+    /// Type of the prototype, e.g. "goods-company"
+    pub kind: String,
+    pub name: String,
+    /// Path of the field at fault, None when it is about the whole prototype
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl PrototypeError {
+    pub(crate) fn from_lua(kind: &str, name: &str, e: &mlua::Error) -> Self {
+        let (field, message) = FieldError::split(e);
+        Self {
+            location: None,
+            kind: kind.to_string(),
+            name: name.to_string(),
+            field,
+            message,
+        }
     }
 
-    Ok(())
+    pub(crate) fn from_validation(kind: &'static str, e: ValidationError) -> Self {
+        let (name, field) = e.location();
+        Self {
+            location: None,
+            kind: kind.to_string(),
+            name: name.to_string(),
+            field: Some(field.to_string()),
+            message: e.to_string(),
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -71,10 +595,8 @@ pub enum PrototypeLoadError {
     LoadingDataLua(#[from] io::Error),
     #[error("lua error: {0}")]
     LuaError(#[from] mlua::Error),
-    #[error("lua error for {0} {1}: {2}")]
-    PrototypeLuaError(String, String, mlua::Error),
-    #[error("multiple errors: {0}")]
-    MultiError(MultiError<PrototypeLoadError>),
-    #[error("validation errors: {0}")]
-    ValidationErrors(#[from] MultiError<ValidationError>),
+    #[error("{} broken prototypes:\n{0}", .0.0.len())]
+    Prototypes(MultiError<PrototypeError>),
+    #[error("prototypes used by the game cannot be removed: {}", .0.join(", "))]
+    PinnedRemoved(Vec<String>),
 }
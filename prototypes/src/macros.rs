@@ -38,6 +38,12 @@ macro_rules! gen_prototypes {
                 pub(crate) $name: common::TransparentMap<$id, $t>,
             )+
             pub(crate) orderings: Orderings,
+            /// Folder of the declaration kept for each prototype by qualified name, see
+            /// [`Prototypes::provenance`]
+            pub(crate) provenance: std::collections::BTreeMap<String, String>,
+            /// Value of the load counter when these prototypes were loaded, a cache built from
+            /// other prototypes is stale
+            pub(crate) generation: std::sync::atomic::AtomicU64,
         }
 
         $(
@@ -65,10 +71,56 @@ macro_rules! gen_prototypes {
             pub fn iter_ids() -> impl Iterator<Item = $id> {
                 $crate::prototypes_iter_ids::<Self>()
             }
+            /// Its type and name, e.g. "goods-company/bakery"
+            pub fn qualified_name(&self) -> String {
+                format!("{}/{}", <Self as $crate::Prototype>::NAME, self.name)
+            }
         }
         )+
 
         impl Prototypes {
+            pub fn generation(&self) -> u64 {
+                self.generation.load(std::sync::atomic::Ordering::Relaxed)
+            }
+
+            /// Every prototype as JSON by qualified name, to compare two loads
+            pub(crate) fn descriptions(&self) -> std::collections::BTreeMap<String, serde_json::Value> {
+                let mut descriptions = std::collections::BTreeMap::new();
+                $(
+                    for id in &self.orderings.$name {
+                        let proto = &self.$name[id];
+                        // only maps with keys that are not strings fail, the prototypes have none
+                        let value = serde_json::to_value(proto).expect("prototypes serialize to JSON");
+                        descriptions.insert(proto.qualified_name(), value);
+                    }
+                )+
+                descriptions
+            }
+
+            /// Name of every prototype by the value of its ID
+            pub(crate) fn names(&self) -> std::collections::BTreeMap<u64, String> {
+                let mut names = std::collections::BTreeMap::new();
+                $(
+                    for proto in self.$name.values() {
+                        names.insert(common::hash_u64(&proto.name), proto.name.clone());
+                    }
+                )+
+                names
+            }
+
+            /// The prototypes of each type as a list in ordering, by the name of the type
+            pub(crate) fn serialize_by_type<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                use serde::ser::SerializeMap;
+                let mut map = s.serialize_map(None)?;
+                $(
+                    map.serialize_entry(
+                        <$t as $crate::Prototype>::NAME,
+                        &self.orderings.$name.iter().map(|id| &self.$name[id]).collect::<Vec<_>>(),
+                    )?;
+                )+
+                map.end()
+            }
+
             pub(crate) fn print_stats(&self) {
                 $(
                     if <$t as $crate::ConcretePrototype>::storage(self).is_empty() {
@@ -94,26 +146,58 @@ macro_rules! gen_prototypes {
                 }
             }
 
-            pub(crate) fn parse_prototype(&mut self, table: mlua::Table) -> Result<(), $crate::PrototypeLoadError> {
-                let _type = table.get::<_, String>("type")?;
+            pub(crate) fn contains(&self, kind: &str, name: &str) -> bool {
+                match kind {
+                    $(
+                        <$t as $crate::Prototype>::NAME => self.$name.contains_key(&$id::new(name)),
+                    )+
+                    _ => false,
+                }
+            }
+
+            /// Removes a broken prototype with its parents, they share its name.
+            /// Returns whether it was there.
+            pub(crate) fn remove(&mut self, kind: &str, name: &str) -> bool {
+                match kind {
+                    $(
+                        <$t as $crate::Prototype>::NAME => {
+                            let Some(proto) = self.$name.remove(&$id::new(name)) else {
+                                return false;
+                            };
+                            <$t as $crate::ConcretePrototype>::remove_parents(&proto, self);
+                            true
+                        }
+                    )+
+                    _ => false,
+                }
+            }
+
+            pub(crate) fn parse_prototype(&mut self, table: mlua::Table) -> Result<(), $crate::PrototypeError> {
+                let name = table.get::<_, Option<String>>("name").ok().flatten().unwrap_or_default();
+                let _type = table.get::<_, String>("type").map_err(|e| {
+                    $crate::PrototypeError::from_lua("", &name, &e)
+                })?;
                 let _type_str = _type.as_str();
                 match _type_str {
                     $(
                         <$t as $crate::Prototype>::NAME => {
                             let proto: $t = $crate::Prototype::from_lua(&table).map_err(|e| {
-                                  $crate::PrototypeLoadError::PrototypeLuaError(_type_str.to_string(), table.get::<_, String>("name").unwrap(), e)
+                                $crate::PrototypeError::from_lua(_type_str, &name, &e)
                             })?;
 
                             <$t as $crate::ConcretePrototype>::insert_parents(&proto, self);
 
-                            if let Some(v) = self.$name.insert((&proto.name).into(), proto) {
-                                log::warn!("duplicate {} with name: {}", <$t as $crate::Prototype>::NAME, v.name);
-                            }
+                            // the loading decides what a duplicate means before parsing it
+                            self.$name.insert((&proto.name).into(), proto);
                         }
                     ),+
                     _ => {
                         if let Ok(s) = table.get::<_, String>("type") {
-                            log::warn!("unknown prototype: {}", s)
+                            let mut d = common::diagnostics::Diagnostic::warning("prototypes", format!("unknown prototype type {}", s));
+                            if let Ok(name) = table.get::<_, String>("name") {
+                                d = d.prototype(name);
+                            }
+                            d.report();
                         }
                     }
                 }
@@ -198,6 +282,10 @@ macro_rules! prototype_id {
 
         impl $crate::PrototypeID for $id {
             type Prototype = $proto;
+
+            fn name_hash(self) -> u64 {
+                self.0
+            }
         }
     };
 }
@@ -0,0 +1,336 @@
+//! Options of the mods, declared in their manifest and set by the players for each save.
+//! The startup settings are read by the data stage with `settings.get("mymod", "key")` so
+//! changing them only applies once the prototypes are loaded again. The runtime settings are
+//! read by the simulation through the values saved with the game.
+
+use mlua::{Lua, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ptr::addr_of;
+
+/// File next to the mod order holding the values the prototypes are loaded with
+pub const MOD_SETTINGS_FILE: &str = "mod_settings";
+
+/// When a setting is read
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingStage {
+    /// While loading the prototypes, changing it needs a reload
+    Startup,
+    /// While the game runs
+    #[default]
+    Runtime,
+}
+
+/// Type of a setting with its default and accepted values
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ModSettingKind {
+    Bool {
+        default: bool,
+    },
+    Number {
+        default: f64,
+        min: f64,
+        max: f64,
+        #[serde(default)]
+        step: Option<f64>,
+    },
+    Choice {
+        default: String,
+        options: Vec<String>,
+    },
+}
+
+/// A setting declared in the manifest of a mod
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModSettingDecl {
+    pub key: String,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub stage: SettingStage,
+    #[serde(flatten)]
+    pub kind: ModSettingKind,
+}
+
+impl ModSettingDecl {
+    pub fn label(&self) -> &str {
+        if self.label.is_empty() {
+            &self.key
+        } else {
+            &self.label
+        }
+    }
+
+    pub fn default_value(&self) -> ModSettingValue {
+        match self.kind {
+            ModSettingKind::Bool { default } => ModSettingValue::Bool(default),
+            ModSettingKind::Number { default, .. } => ModSettingValue::Number(default),
+            ModSettingKind::Choice { ref default, .. } => ModSettingValue::Choice(default.clone()),
+        }
+    }
+
+    /// The value brought in the accepted range, the default if it has the wrong type
+    pub fn sanitize(&self, value: &ModSettingValue) -> ModSettingValue {
+        match (&self.kind, value) {
+            (ModSettingKind::Bool { .. }, ModSettingValue::Bool(_)) => value.clone(),
+            (ModSettingKind::Number { min, max, .. }, &ModSettingValue::Number(v))
+                if v.is_finite() =>
+            {
+                ModSettingValue::Number(v.clamp(*min, min.max(*max)))
+            }
+            (ModSettingKind::Choice { options, .. }, ModSettingValue::Choice(c))
+                if options.contains(c) =>
+            {
+                value.clone()
+            }
+            _ => self.default_value(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ModSettingValue {
+    Bool(bool),
+    Number(f64),
+    Choice(String),
+}
+
+impl ModSettingValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            ModSettingValue::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match *self {
+            ModSettingValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_choice(&self) -> Option<&str> {
+        match self {
+            ModSettingValue::Choice(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    fn to_lua<'lua>(&self, lua: &'lua Lua) -> mlua::Result<Value<'lua>> {
+        Ok(match self {
+            ModSettingValue::Bool(b) => Value::Boolean(*b),
+            ModSettingValue::Number(n) => Value::Number(*n),
+            ModSettingValue::Choice(c) => Value::String(lua.create_string(c)?),
+        })
+    }
+}
+
+/// Values of the mod settings, by mod name then key
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModSettings {
+    values: BTreeMap<String, BTreeMap<String, ModSettingValue>>,
+}
+
+impl ModSettings {
+    pub fn get(&self, mod_name: &str, key: &str) -> Option<&ModSettingValue> {
+        self.values.get(mod_name)?.get(key)
+    }
+
+    /// Sets the value of a declared setting, brought in its range.
+    /// Returns false if the mod did not declare it.
+    pub fn set(&mut self, mod_name: &str, key: &str, value: &ModSettingValue) -> bool {
+        let Some(decl) = find_setting_decl(mod_name, key) else {
+            return false;
+        };
+        self.values
+            .entry(mod_name.to_string())
+            .or_default()
+            .insert(key.to_string(), decl.sanitize(value));
+        true
+    }
+
+    /// Keeps the values of the declared settings, brought in their range, and adds the defaults
+    /// of the missing ones. The declarations are given by mod name.
+    pub fn sanitize<'a>(
+        &mut self,
+        decls: impl IntoIterator<Item = (&'a str, &'a [ModSettingDecl])>,
+    ) {
+        let mut values = BTreeMap::new();
+        for (mod_name, decls) in decls {
+            let old = self.values.remove(mod_name).unwrap_or_default();
+            let new: BTreeMap<_, _> = decls
+                .iter()
+                .map(|decl| {
+                    let v = old
+                        .get(&decl.key)
+                        .map(|v| decl.sanitize(v))
+                        .unwrap_or_else(|| decl.default_value());
+                    (decl.key.clone(), v)
+                })
+                .collect();
+            if !new.is_empty() {
+                values.insert(mod_name.to_string(), new);
+            }
+        }
+        self.values = values;
+    }
+
+    /// Sanitizes the values with the declarations of the loaded mods
+    pub fn sanitize_loaded(&mut self) {
+        self.sanitize(
+            loaded_setting_decls()
+                .iter()
+                .map(|(name, decls)| (name.as_str(), decls.as_slice())),
+        );
+    }
+
+    /// The startup settings of the loaded mods with a different value than in `other`
+    pub fn startup_differences(&self, other: &ModSettings) -> Vec<(&'static str, &'static str)> {
+        loaded_setting_decls()
+            .iter()
+            .flat_map(|(mod_name, decls)| decls.iter().map(move |d| (mod_name.as_str(), d)))
+            .filter(|(mod_name, d)| {
+                d.stage == SettingStage::Startup
+                    && self.get(mod_name, &d.key) != other.get(mod_name, &d.key)
+            })
+            .map(|(mod_name, d)| (mod_name, d.key.as_str()))
+            .collect()
+    }
+
+    /// Adds the `settings.get(mod, key)` function read by the data stage, nil if not declared
+    pub(crate) fn register_lua(&self, lua: &Lua) -> mlua::Result<()> {
+        let values = self.clone();
+        let get =
+            lua.create_function(move |lua, (mod_name, key): (String, String)| {
+                match values.get(&mod_name, &key) {
+                    Some(v) => v.to_lua(lua),
+                    None => Ok(Value::Nil),
+                }
+            })?;
+        let settings = lua.create_table()?;
+        settings.set("get", get)?;
+        lua.globals().set("settings", settings)
+    }
+}
+
+/// Declared settings of the loaded mods, by mod name, in load order
+static mut LOADED_SETTING_DECLS: Vec<(String, Vec<ModSettingDecl>)> = Vec::new();
+/// Values of the settings the prototypes were loaded with
+static mut LOADED_SETTINGS: ModSettings = ModSettings {
+    values: BTreeMap::new(),
+};
+
+pub fn loaded_setting_decls() -> &'static [(String, Vec<ModSettingDecl>)] {
+    unsafe { &*addr_of!(LOADED_SETTING_DECLS) }
+}
+
+pub fn loaded_mod_settings() -> &'static ModSettings {
+    unsafe { &*addr_of!(LOADED_SETTINGS) }
+}
+
+pub fn find_setting_decl(mod_name: &str, key: &str) -> Option<&'static ModSettingDecl> {
+    loaded_setting_decls()
+        .iter()
+        .find(|(name, _)| name == mod_name)?
+        .1
+        .iter()
+        .find(|d| d.key == key)
+}
+
+/// # Safety
+/// Must only be called while loading the prototypes
+pub(crate) unsafe fn set_loaded_settings(
+    decls: Vec<(String, Vec<ModSettingDecl>)>,
+    values: ModSettings,
+) {
+    LOADED_SETTING_DECLS = decls;
+    LOADED_SETTINGS = values;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ModManifest;
+    use common::saveload::{Encoder, JSON};
+
+    const MANIFEST: &str = r#"{
+        "name": "traffic",
+        "version": "1.0",
+        "settings": [
+            { "key": "multiplier", "label": "Traffic multiplier", "type": "number",
+              "default": 1.0, "min": 0.5, "max": 3.0, "stage": "startup" },
+            { "key": "hard", "type": "bool", "default": false },
+            { "key": "side", "type": "choice", "default": "right", "options": ["left", "right"] }
+        ]
+    }"#;
+
+    #[test]
+    fn settings_are_sanitized() {
+        let manifest: ModManifest = JSON::decode(MANIFEST.as_bytes()).unwrap();
+        let multiplier = &manifest.settings[0];
+        assert_eq!(multiplier.stage, SettingStage::Startup);
+        assert_eq!(manifest.settings[1].label(), "hard");
+
+        assert_eq!(
+            multiplier.sanitize(&ModSettingValue::Number(10.0)),
+            ModSettingValue::Number(3.0)
+        );
+        assert_eq!(
+            multiplier.sanitize(&ModSettingValue::Bool(true)),
+            ModSettingValue::Number(1.0)
+        );
+        assert_eq!(
+            manifest.settings[2].sanitize(&ModSettingValue::Choice("up".to_string())),
+            ModSettingValue::Choice("right".to_string())
+        );
+
+        let mut settings = ModSettings::default();
+        settings
+            .values
+            .entry("traffic".to_string())
+            .or_default()
+            .insert("hard".to_string(), ModSettingValue::Bool(true));
+        settings
+            .values
+            .entry("removed_mod".to_string())
+            .or_default()
+            .insert("x".to_string(), ModSettingValue::Bool(true));
+        settings.sanitize([(manifest.name.as_str(), manifest.settings.as_slice())]);
+
+        assert_eq!(
+            settings.get("traffic", "hard"),
+            Some(&ModSettingValue::Bool(true))
+        );
+        assert_eq!(
+            settings.get("traffic", "multiplier"),
+            Some(&ModSettingValue::Number(1.0))
+        );
+        assert_eq!(settings.get("removed_mod", "x"), None);
+    }
+
+    #[test]
+    fn data_stage_reads_settings() {
+        let mut settings = ModSettings::default();
+        settings
+            .values
+            .entry("traffic".to_string())
+            .or_default()
+            .insert("multiplier".to_string(), ModSettingValue::Number(2.0));
+
+        let lua = Lua::new();
+        settings.register_lua(&lua).unwrap();
+        lua.load(
+            r#"
+            assert(settings.get("traffic", "multiplier") == 2.0)
+            assert(settings.get("traffic", "unknown") == nil)
+            "#,
+        )
+        .exec()
+        .unwrap();
+    }
+}
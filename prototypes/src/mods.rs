@@ -0,0 +1,469 @@
+use common::saveload::{Encoder, JSON};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::ModSettingDecl;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::ptr::addr_of;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Folder containing the data packs, next to base_mod
+pub const MODS_DIR: &str = "mods/";
+/// File describing a data pack, in its folder
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+const GAME_VERSION: &str = include_str!("../../VERSION");
+
+/// Names and versions of the mods the prototypes were loaded with, in order
+static mut LOADED_MODS: Vec<String> = Vec::new();
+
+pub fn loaded_mods() -> &'static [String] {
+    unsafe { &*addr_of!(LOADED_MODS) }
+}
+
+/// # Safety
+/// Must only be called while loading the prototypes
+pub(crate) unsafe fn set_loaded_mods(mods: Vec<String>) {
+    LOADED_MODS = mods;
+}
+
+pub fn game_version() -> Version {
+    GAME_VERSION.trim().parse().unwrap_or_default()
+}
+
+/// A major.minor.patch version, the missing parts are 0
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid version: {0}")]
+pub struct InvalidVersion(String);
+
+impl FromStr for Version {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split('.').map(|p| p.parse::<u32>());
+        let mut next = || match parts.next() {
+            None => Ok(0),
+            Some(p) => p.map_err(|_| InvalidVersion(s.to_string())),
+        };
+        let v = Version {
+            major: next()?,
+            minor: next()?,
+            patch: next()?,
+        };
+        if parts.next().is_some() {
+            return Err(InvalidVersion(s.to_string()));
+        }
+        Ok(v)
+    }
+}
+
+impl TryFrom<String> for Version {
+    type Error = InvalidVersion;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Version> for String {
+    fn from(v: Version) -> Self {
+        v.to_string()
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum VersionOp {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    /// Same leftmost non zero part, and not lower
+    Caret,
+}
+
+/// Comma separated comparisons like ">=0.4, <0.6" or "^1.2", a bare version means "^"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VersionReq {
+    comparisons: Vec<(VersionOp, Version)>,
+}
+
+impl VersionReq {
+    pub fn matches(&self, v: &Version) -> bool {
+        self.comparisons.iter().all(|(op, req)| match op {
+            VersionOp::Exact => v == req,
+            VersionOp::Greater => v > req,
+            VersionOp::GreaterEq => v >= req,
+            VersionOp::Less => v < req,
+            VersionOp::LessEq => v <= req,
+            VersionOp::Caret => {
+                v >= req
+                    && if req.major > 0 {
+                        v.major == req.major
+                    } else if req.minor > 0 {
+                        v.major == 0 && v.minor == req.minor
+                    } else {
+                        v.major == 0 && v.minor == 0 && v.patch == req.patch
+                    }
+            }
+        })
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = InvalidVersion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut comparisons = vec![];
+        for part in s.split(',') {
+            let part = part.trim();
+            let (op, v) = [
+                (">=", VersionOp::GreaterEq),
+                ("<=", VersionOp::LessEq),
+                (">", VersionOp::Greater),
+                ("<", VersionOp::Less),
+                ("=", VersionOp::Exact),
+                ("^", VersionOp::Caret),
+            ]
+            .into_iter()
+            .find_map(|(prefix, op)| Some((op, part.strip_prefix(prefix)?)))
+            .unwrap_or((VersionOp::Caret, part));
+            comparisons.push((op, v.parse()?));
+        }
+        Ok(Self { comparisons })
+    }
+}
+
+impl TryFrom<String> for VersionReq {
+    type Error = InvalidVersion;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<VersionReq> for String {
+    fn from(v: VersionReq) -> Self {
+        v.to_string()
+    }
+}
+
+impl Display for VersionReq {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, (op, v)) in self.comparisons.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            let op = match op {
+                VersionOp::Exact => "=",
+                VersionOp::Greater => ">",
+                VersionOp::GreaterEq => ">=",
+                VersionOp::Less => "<",
+                VersionOp::LessEq => "<=",
+                VersionOp::Caret => "^",
+            };
+            write!(f, "{op}{v}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Description of a data pack, read from the manifest.json in its folder
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModManifest {
+    pub name: String,
+    pub version: Version,
+    #[serde(default)]
+    pub author: String,
+    /// Names of the mods that must be loaded before this one, with their accepted versions
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, VersionReq>,
+    /// Versions of the game the mod works with, any if missing
+    #[serde(default)]
+    pub game_version: Option<VersionReq>,
+    /// Options the players can set for each save
+    #[serde(default)]
+    pub settings: Vec<ModSettingDecl>,
+}
+
+/// A folder of the mods folder containing a data.lua file
+#[derive(Debug, Clone)]
+pub struct DetectedMod {
+    pub folder: String,
+    pub manifest: Result<ModManifest, String>,
+}
+
+/// Folders of the mods folder containing a data.lua file with their manifest, sorted by folder
+pub fn detect_mods(base: impl AsRef<Path>) -> Vec<DetectedMod> {
+    let Ok(dir) = std::fs::read_dir(base.as_ref().join(MODS_DIR)) else {
+        return vec![];
+    };
+    let mut mods: Vec<DetectedMod> = dir
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let path = entry.path();
+            if !path.join("data.lua").is_file() {
+                return None;
+            }
+            let manifest = common::saveload::load_string(path.join(MANIFEST_FILE))
+                .map_err(|e| format!("could not read {MANIFEST_FILE}: {e}"))
+                .and_then(|s| {
+                    JSON::decode(s.as_bytes())
+                        .map_err(|e| format!("could not parse {MANIFEST_FILE}: {e}"))
+                });
+            Some(DetectedMod {
+                folder: entry.file_name().into_string().ok()?,
+                manifest,
+            })
+        })
+        .collect();
+    mods.sort_by(|a, b| a.folder.cmp(&b.folder));
+    mods
+}
+
+/// A data pack found in the mods folder
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModEntry {
+    /// Folder of the mod in the mods folder
+    pub name: String,
+    pub enabled: bool,
+}
+
+/// The data packs loaded after base_mod, in order.
+/// Saved by the game and read when loading the prototypes.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModOrder {
+    pub mods: Vec<ModEntry>,
+}
+
+impl ModOrder {
+    /// Adds the newly found data packs at the end, disabled, and forgets the removed ones
+    pub fn merge_detected(&mut self, detected: &[DetectedMod]) {
+        self.mods
+            .retain(|m| detected.iter().any(|d| d.folder == m.name));
+        for d in detected {
+            if self.mods.iter().all(|m| m.name != d.folder) {
+                self.mods.push(ModEntry {
+                    name: d.folder.clone(),
+                    enabled: false,
+                });
+            }
+        }
+    }
+
+    pub fn enabled(&self) -> impl Iterator<Item = &str> {
+        self.mods
+            .iter()
+            .filter(|m| m.enabled)
+            .map(|m| m.name.as_str())
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ModError {
+    #[error("not found in the mods folder")]
+    NotFound,
+    #[error("{0}")]
+    InvalidManifest(String),
+    #[error("needs game version {0}")]
+    GameVersion(VersionReq),
+    #[error("needs {0} which is not installed")]
+    MissingDependency(String),
+    #[error("needs {0} which is disabled")]
+    DisabledDependency(String),
+    #[error("needs {0} {1} but {2} is installed")]
+    DependencyVersion(String, VersionReq, Version),
+    #[error("must be loaded after {0}")]
+    DependencyOrder(String),
+    #[error("depends on itself through {0}")]
+    Cycle(String),
+}
+
+/// Checks the enabled mods of the order, returns the errors with the folder of the mod they are about
+pub fn validate_mods(order: &ModOrder, detected: &[DetectedMod]) -> Vec<(String, ModError)> {
+    let game = game_version();
+    let mut errors = vec![];
+
+    // manifests of the enabled mods, in load order
+    let mut enabled: Vec<(&str, &ModManifest)> = vec![];
+    for folder in order.enabled() {
+        match detected.iter().find(|d| d.folder == folder) {
+            None => errors.push((folder.to_string(), ModError::NotFound)),
+            Some(DetectedMod {
+                manifest: Err(e), ..
+            }) => errors.push((folder.to_string(), ModError::InvalidManifest(e.clone()))),
+            Some(DetectedMod {
+                manifest: Ok(m), ..
+            }) => enabled.push((folder, m)),
+        }
+    }
+
+    for (i, &(folder, manifest)) in enabled.iter().enumerate() {
+        let mut err = |e| errors.push((folder.to_string(), e));
+
+        if let Some(ref req) = manifest.game_version {
+            if !req.matches(&game) {
+                err(ModError::GameVersion(req.clone()));
+            }
+        }
+
+        for (dep, req) in &manifest.dependencies {
+            let installed = detected.iter().find_map(|d| {
+                d.manifest
+                    .as_ref()
+                    .ok()
+                    .filter(|m| &m.name == dep)
+                    .map(|m| m.version)
+            });
+            let Some(version) = installed else {
+                err(ModError::MissingDependency(dep.clone()));
+                continue;
+            };
+            let Some(pos) = enabled.iter().position(|(_, m)| &m.name == dep) else {
+                err(ModError::DisabledDependency(dep.clone()));
+                continue;
+            };
+            if !req.matches(&version) {
+                err(ModError::DependencyVersion(
+                    dep.clone(),
+                    req.clone(),
+                    version,
+                ));
+            }
+            if pos > i {
+                err(ModError::DependencyOrder(dep.clone()));
+            }
+        }
+
+        if let Some(through) = find_cycle(&enabled, manifest) {
+            err(ModError::Cycle(through));
+        }
+    }
+
+    errors
+}
+
+/// Name of a dependency of `start` leading back to it through the enabled mods
+fn find_cycle(enabled: &[(&str, &ModManifest)], start: &ModManifest) -> Option<String> {
+    let by_name = |name: &str| enabled.iter().find(|(_, m)| m.name == name).map(|x| x.1);
+
+    for first in start.dependencies.keys() {
+        let mut stack = vec![first.as_str()];
+        let mut seen = vec![];
+        while let Some(name) = stack.pop() {
+            if name == start.name {
+                return Some(first.clone());
+            }
+            if seen.contains(&name) {
+                continue;
+            }
+            seen.push(name);
+            if let Some(m) = by_name(name) {
+                stack.extend(m.dependencies.keys().map(String::as_str));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detected(name: &str, version: &str, deps: &[(&str, &str)]) -> DetectedMod {
+        DetectedMod {
+            folder: name.to_string(),
+            manifest: Ok(ModManifest {
+                name: name.to_string(),
+                version: version.parse().unwrap(),
+                author: String::new(),
+                dependencies: deps
+                    .iter()
+                    .map(|(n, r)| (n.to_string(), r.parse().unwrap()))
+                    .collect(),
+                game_version: None,
+                settings: vec![],
+            }),
+        }
+    }
+
+    fn order(mods: &[(&str, bool)]) -> ModOrder {
+        ModOrder {
+            mods: mods
+                .iter()
+                .map(|&(name, enabled)| ModEntry {
+                    name: name.to_string(),
+                    enabled,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn version_requirements() {
+        let v = |s: &str| s.parse::<Version>().unwrap();
+        let req = |s: &str| s.parse::<VersionReq>().unwrap();
+
+        assert_eq!(v("1.2"), v("1.2.0"));
+        assert!("1.x".parse::<Version>().is_err());
+        assert!(req("^1.2").matches(&v("1.5.3")));
+        assert!(!req("^1.2").matches(&v("2.0.0")));
+        assert!(!req("0.6").matches(&v("0.7.0")));
+        assert!(req(">=0.4, <0.6").matches(&v("0.5.9")));
+        assert!(!req(">=0.4, <0.6").matches(&v("0.6.0")));
+        assert_eq!(req(">=0.4, <0.6").to_string(), ">=0.4.0, <0.6.0");
+    }
+
+    #[test]
+    fn dependencies_are_validated() {
+        let detected = vec![
+            detected("base_roads", "1.0.0", &[]),
+            detected("trams", "0.2.0", &[("base_roads", "^1.0")]),
+            detected("loop_a", "1.0.0", &[("loop_b", "1")]),
+            detected("loop_b", "1.0.0", &[("loop_a", "1")]),
+        ];
+
+        let ok = order(&[("base_roads", true), ("trams", true)]);
+        assert!(validate_mods(&ok, &detected).is_empty());
+
+        let disabled = order(&[("base_roads", false), ("trams", true)]);
+        assert_eq!(
+            validate_mods(&disabled, &detected),
+            vec![(
+                "trams".to_string(),
+                ModError::DisabledDependency("base_roads".to_string())
+            )]
+        );
+
+        let misordered = order(&[("trams", true), ("base_roads", true)]);
+        assert_eq!(
+            validate_mods(&misordered, &detected),
+            vec![(
+                "trams".to_string(),
+                ModError::DependencyOrder("base_roads".to_string())
+            )]
+        );
+
+        let cycle = order(&[("loop_a", true), ("loop_b", true)]);
+        let errors = validate_mods(&cycle, &detected);
+        assert!(errors.contains(&("loop_a".to_string(), ModError::Cycle("loop_b".to_string()))));
+        assert!(errors.contains(&("loop_b".to_string(), ModError::Cycle("loop_a".to_string()))));
+    }
+}
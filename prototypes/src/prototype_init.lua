@@ -1,13 +1,19 @@
 data = {}
 
+-- "file:line" of the call to data:extend that defined each prototype, for the error messages
+data_sources = setmetatable({}, { __mode = "k" })
+
 function data:extend (t)
+    local source = caller_location()
     if t.type ~= nil then -- we're extending a single prototype
         rawset(self, rawlen(self)+1, t)
+        data_sources[t] = source
         return
     end
 
     for _, v in ipairs(t) do
         rawset(self, rawlen(self)+1, v)
+        data_sources[v] = source
     end
 end
 
@@ -2,14 +2,17 @@
 
 use crate::{NoParent, Prototype, PrototypeBase, get_lua};
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
 /// $protoPrototype is
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct $protoPrototype {
+    #[serde(flatten)]
     pub base: $parent,
+    #[serde(skip)]
     pub id: $protoPrototypeID,
 }
 
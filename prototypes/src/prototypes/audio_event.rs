@@ -0,0 +1,64 @@
+use crate::{get_lua, get_lua_default, get_lua_opt, NoParent, Prototype, PrototypeBase};
+use mlua::Table;
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// AudioEventPrototype is a rule playing a sound when an event matching it is logged
+#[derive(Clone, Debug, Serialize)]
+pub struct AudioEventPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: AudioEventID,
+    /// Category of the matched events, e.g. "construction" or "incident", None matches all of them
+    pub category: Option<String>,
+    /// Lowest severity of the matched events: "info", "success" or "warning"
+    pub min_severity: Option<String>,
+    /// Only matches the events whose text contains it, ignoring case
+    pub text: Option<String>,
+    /// Name of the sound in assets/sounds, without the extension
+    pub sound: String,
+    /// Played at the location of the event, quieter the further it is from the camera.
+    /// Otherwise played flat on the UI channel.
+    pub positional: bool,
+    /// Seconds before the rule can play again, the events matched meanwhile are silent
+    pub cooldown: f32,
+}
+
+impl Prototype for AudioEventPrototype {
+    type Parent = NoParent;
+    type ID = AudioEventID;
+    const NAME: &'static str = "audio-event";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            category: get_lua_opt(table, "category")?,
+            min_severity: get_lua_opt(table, "min_severity")?,
+            text: get_lua_opt(table, "text")?,
+            sound: get_lua(table, "sound")?,
+            positional: get_lua_default(table, "positional")?,
+            cooldown: get_lua_opt(table, "cooldown")?.unwrap_or(1.0),
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for AudioEventPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, egui_inspect::Inspect)]
+#[derive(Debug, Clone, egui_inspect::Inspect, serde::Serialize)]
 pub struct PrototypeBase {
     pub name: String,
     pub order: String,
@@ -11,10 +11,10 @@ impl crate::Prototype for PrototypeBase {
     const NAME: &'static str = "base";
 
     fn from_lua(table: &mlua::Table) -> mlua::Result<Self> {
-        use crate::get_lua;
+        use crate::{get_lua, get_lua_default};
         Ok(Self {
             name: get_lua(table, "name")?,
-            order: get_lua(table, "order").unwrap_or(String::new()),
+            order: get_lua_default(table, "order")?,
             label: get_lua(table, "label")?,
         })
     }
@@ -0,0 +1,51 @@
+use crate::{get_lua, get_lua_default, NoParent, Prototype, PrototypeBase};
+use mlua::Table;
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// BuildCategoryPrototype is a tab of the build menu, like "Food" or "Logistics"
+#[derive(Clone, Debug, Serialize)]
+pub struct BuildCategoryPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: BuildCategoryID,
+    /// Name of the ui texture shown on the tab
+    pub icon: String,
+    /// Groups of the buildings in the tab, in the order they are shown
+    pub subcategories: Vec<String>,
+}
+
+impl Prototype for BuildCategoryPrototype {
+    type Parent = NoParent;
+    type ID = BuildCategoryID;
+    const NAME: &'static str = "build-category";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            icon: get_lua(table, "icon")?,
+            subcategories: get_lua_default(table, "subcategories")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for BuildCategoryPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
-    get_lua, get_v2, Money, NoParent, Power, Prototype, PrototypeBase, RenderAsset, Size2D,
+    get_lua, get_lua_default, get_lua_opt, get_v2, AmenityKind, BuildMenuEntry, Money, NoParent,
+    Power, Prototype, PrototypeBase, RenderAsset, Size2D, ZoneKind,
 };
 use egui_inspect::debug_inspect_impl;
-use geom::Vec2;
+use geom::{Vec2, OBB};
 use mlua::{FromLua, Lua, Table, Value};
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
@@ -22,17 +23,82 @@ pub enum BuildingGen {
 }
 debug_inspect_impl!(BuildingGen);
 
+/// Who may go through an entrance
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EntranceKind {
+    Pedestrian,
+    Vehicle,
+    /// Trucks delivering or picking up goods
+    Freight,
+}
+debug_inspect_impl!(EntranceKind);
+
+impl EntranceKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            EntranceKind::Pedestrian => "Pedestrian",
+            EntranceKind::Vehicle => "Vehicle",
+            EntranceKind::Freight => "Freight",
+        }
+    }
+}
+
+/// An access point on the footprint of a building
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct EntranceDef {
+    /// Relative to the center of the building, the side facing the road is towards -y
+    pub pos: Vec2,
+    pub kind: EntranceKind,
+    /// The building can be placed even if the entrance doesn't reach any way
+    pub optional: bool,
+}
+
+impl EntranceDef {
+    /// Position of the entrance on a building occupying the obb
+    pub fn pos_on(&self, obb: &OBB) -> Vec2 {
+        let axis = (obb.corners[1] - obb.corners[0]).normalize();
+        obb.center() + self.pos.rotated_by(axis)
+    }
+}
+
 /// BuildingPrototype is a building
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BuildingPrototype {
+    #[serde(flatten)]
     pub base: PrototypeBase,
+    #[serde(skip)]
     pub id: BuildingPrototypeID,
     pub size: Size2D,
+    #[serde(serialize_with = "crate::export::bgen")]
     pub bgen: BuildingGen,
     pub asset: RenderAsset,
+    #[serde(serialize_with = "crate::export::money")]
     pub price: Money,
     pub power_consumption: Option<Power>,
     pub power_production: Option<Power>,
+    /// Zone on which the building grows on its own when there is demand
+    pub zone_kind: Option<ZoneKind>,
+    pub upgrade: Option<BuildingUpgrade>,
+    /// Empty when the building only has the door made by its bgen
+    #[serde(serialize_with = "crate::export::entrances")]
+    pub entrances: Vec<EntranceDef>,
+    /// Where the building is in the build menu
+    pub menu: BuildMenuEntry,
+    /// What the building offers to the residences within walking distance
+    pub amenity: Option<AmenityKind>,
+}
+
+/// What a building can be upgraded into, and when
+#[derive(Clone, Debug, Serialize)]
+pub struct BuildingUpgrade {
+    #[serde(serialize_with = "crate::export::id")]
+    pub to: BuildingPrototypeID,
+    #[serde(serialize_with = "crate::export::money")]
+    pub price: Money,
+    /// Inhabitants that must live around the building
+    pub min_nearby_population: u32,
+    /// Lanes the road the building is connected to must have
+    pub min_road_lanes: u32,
 }
 
 impl Prototype for BuildingPrototype {
@@ -51,6 +117,26 @@ impl Prototype for BuildingPrototype {
             price: get_lua(table, "price")?,
             power_consumption: get_lua(table, "power_consumption")?,
             power_production: get_lua(table, "power_production")?,
+            zone_kind: get_lua_opt(table, "zone_kind")?,
+            upgrade: match get_lua_opt(table, "upgrades_to")? {
+                Some(to) => {
+                    let conditions: Option<Table> = get_lua_opt(table, "upgrade_conditions")?;
+                    let condition = |field: &'static str| match conditions {
+                        Some(ref t) => get_lua_opt(t, field).map(Option::unwrap_or_default),
+                        None => Ok(0),
+                    };
+                    Some(BuildingUpgrade {
+                        to,
+                        price: get_lua(table, "upgrade_price")?,
+                        min_nearby_population: condition("min_nearby_population")?,
+                        min_road_lanes: condition("min_road_lanes")?,
+                    })
+                }
+                None => None,
+            },
+            entrances: get_lua_default(table, "entrances")?,
+            menu: BuildMenuEntry::from_building(table)?,
+            amenity: get_lua_opt(table, "amenity")?,
         })
     }
 
@@ -109,3 +195,35 @@ impl<'a> FromLua<'a> for BuildingGen {
         }
     }
 }
+
+impl<'a> FromLua<'a> for EntranceKind {
+    fn from_lua(value: Value<'a>, lua: &'a Lua) -> mlua::Result<Self> {
+        let s = String::from_lua(value, lua)?;
+        match s.as_str() {
+            "pedestrian" => Ok(Self::Pedestrian),
+            "vehicle" => Ok(Self::Vehicle),
+            "freight" => Ok(Self::Freight),
+            _ => Err(mlua::Error::external(format!(
+                "Unknown entrance kind: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl<'a> FromLua<'a> for EntranceDef {
+    fn from_lua(value: Value<'a>, _: &'a Lua) -> mlua::Result<Self> {
+        let Value::Table(table) = value else {
+            return Err(mlua::Error::FromLuaConversionError {
+                from: value.type_name(),
+                to: "EntranceDef",
+                message: Some("expected table".into()),
+            });
+        };
+        Ok(Self {
+            pos: get_v2(&table, "pos")?,
+            kind: get_lua(&table, "kind")?,
+            optional: get_lua_default(&table, "optional")?,
+        })
+    }
+}
@@ -1,14 +1,17 @@
 use crate::{get_color, NoParent, Prototype, PrototypeBase};
 use geom::Color;
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
 /// ColorsPrototype is the prototype to hold data about colors
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct ColorsPrototype {
+    #[serde(flatten)]
     pub base: PrototypeBase,
+    #[serde(skip)]
     pub id: ColorsPrototypeID,
 
     pub sand_col: Color,
@@ -26,10 +29,16 @@ pub struct ColorsPrototype {
     pub road_mid_col: Color,
     pub road_hig_col: Color,
     pub road_line_col: Color,
+    /// Pavement of the bus lanes
+    pub road_bus_col: Color,
+    /// Gravel bed of the rails
+    pub rail_ballast_col: Color,
     pub road_pylon_col: Color,
 
     pub lot_unassigned_col: Color,
     pub lot_residential_col: Color,
+    pub lot_commercial_col: Color,
+    pub lot_industrial_col: Color,
 }
 
 impl Prototype for ColorsPrototype {
@@ -58,10 +67,14 @@ impl Prototype for ColorsPrototype {
             road_mid_col: get_color(table, "road_mid_col")?,
             road_hig_col: get_color(table, "road_hig_col")?,
             road_line_col: get_color(table, "road_line_col")?,
+            road_bus_col: get_color(table, "road_bus_col")?,
+            rail_ballast_col: get_color(table, "rail_ballast_col")?,
             road_pylon_col: get_color(table, "road_pylon_col")?,
 
             lot_unassigned_col: get_color(table, "lot_unassigned_col")?,
             lot_residential_col: get_color(table, "lot_residential_col")?,
+            lot_commercial_col: get_color(table, "lot_commercial_col")?,
+            lot_industrial_col: get_color(table, "lot_industrial_col")?,
         })
     }
 
@@ -0,0 +1,77 @@
+use crate::{
+    get_lua, get_lua_default, get_lua_opt, GameDuration, NoParent, Prototype, PrototypeBase, Season,
+};
+use mlua::Table;
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// CropPrototype is what a farm grows on its field: it is sown in its seasons, grows,
+/// and is harvested all at once before the field rests
+#[derive(Clone, Debug, Serialize)]
+pub struct CropPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: CropID,
+    /// Time from sowing to harvest
+    #[serde(serialize_with = "crate::export::duration")]
+    pub growth: GameDuration,
+    /// Time to harvest a field of the largest size with all the workers
+    #[serde(serialize_with = "crate::export::duration")]
+    pub harvest: GameDuration,
+    /// Time the field rests after the harvest before the next crop is sown
+    #[serde(serialize_with = "crate::export::duration")]
+    pub fallow: GameDuration,
+    /// Seasons in which the crop can be sown, any season if empty
+    pub sow_seasons: Vec<Season>,
+    /// Runs of the farm's recipe harvested from a field of the largest size,
+    /// smaller fields yield less
+    pub yield_per_field: u32,
+}
+
+impl CropPrototype {
+    /// Whether the crop can be sown now, always when seasons are disabled
+    pub fn can_sow(&self, season: Option<Season>) -> bool {
+        match season {
+            Some(season) => self.sow_seasons.is_empty() || self.sow_seasons.contains(&season),
+            None => true,
+        }
+    }
+}
+
+impl Prototype for CropPrototype {
+    type Parent = NoParent;
+    type ID = CropID;
+    const NAME: &'static str = "crop";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            growth: get_lua(table, "growth")?,
+            harvest: get_lua(table, "harvest")?,
+            fallow: get_lua_opt(table, "fallow")?.unwrap_or(GameDuration::from_secs(0)),
+            sow_seasons: get_lua_default(table, "sow_seasons")?,
+            yield_per_field: get_lua(table, "yield_per_field")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for CropPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{get_lua, LuaColor, NoParent, Prototype, PrototypeBase};
+use geom::Color;
+use mlua::Table;
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// DepositPrototype is a raw material found in patches under the terrain, like ore or oil.
+/// The patches are scattered at map creation and hold a finite quantity.
+#[derive(Clone, Debug, Serialize)]
+pub struct DepositPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: DepositID,
+    /// Color of the patches in the resource overlay
+    pub color: Color,
+    /// Average number of patches per square kilometer of land, before the map's abundance
+    pub density: f32,
+    /// Average radius of a patch, in meters
+    pub radius: f32,
+    /// Average runs of the extraction recipe a patch holds before running dry
+    pub amount: u32,
+}
+
+impl Prototype for DepositPrototype {
+    type Parent = NoParent;
+    type ID = DepositID;
+    const NAME: &'static str = "deposit";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            color: get_lua::<LuaColor>(table, "color")?.0,
+            density: get_lua(table, "density")?,
+            radius: get_lua(table, "radius")?,
+            amount: get_lua(table, "amount")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for DepositPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
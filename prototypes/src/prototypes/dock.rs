@@ -0,0 +1,73 @@
+use crate::{
+    get_lua, get_lua_default, get_lua_opt, AmenityKind, BuildMenuEntry, EntranceDef, Money,
+    NoParent, Prototype, PrototypeBase, RenderAsset, Size2D,
+};
+use mlua::Table;
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// DockPrototype is a harbor on the shore, its cargo boats carry goods to the other docks
+#[derive(Clone, Debug, Serialize)]
+pub struct DockPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: DockPrototypeID,
+    pub asset: RenderAsset,
+    #[serde(serialize_with = "crate::export::money")]
+    pub price: Money,
+    pub size: Size2D,
+    /// Empty when the building only has its door
+    #[serde(serialize_with = "crate::export::entrances")]
+    pub entrances: Vec<EntranceDef>,
+    /// What the building offers to the residences within walking distance
+    pub amenity: Option<AmenityKind>,
+    /// Where the building is in the build menu
+    pub menu: BuildMenuEntry,
+    pub boat_asset: RenderAsset,
+    /// Cargo carried by a boat in one trip
+    pub boat_capacity: u32,
+    /// in m/s
+    pub boat_speed: f32,
+}
+
+impl Prototype for DockPrototype {
+    type Parent = NoParent;
+    type ID = DockPrototypeID;
+    const NAME: &'static str = "dock";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            asset: get_lua(table, "asset")?,
+            price: get_lua(table, "price")?,
+            size: get_lua(table, "size")?,
+            entrances: get_lua_default(table, "entrances")?,
+            amenity: get_lua_opt(table, "amenity")?,
+            menu: BuildMenuEntry::from_building(table)?,
+            boat_asset: get_lua(table, "boat_asset")?,
+            boat_capacity: get_lua(table, "boat_capacity")?,
+            boat_speed: get_lua(table, "boat_speed")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for DockPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
@@ -1,17 +1,27 @@
-use crate::{get_lua, Money, NoParent, Prototype, PrototypeBase, RenderAsset, Size2D};
+use crate::{
+    get_lua, get_lua_default, EntranceDef, Money, NoParent, Prototype, PrototypeBase, RenderAsset,
+    Size2D,
+};
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
 /// FreightStationPrototype is a freight station
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct FreightStationPrototype {
+    #[serde(flatten)]
     pub base: PrototypeBase,
+    #[serde(skip)]
     pub id: FreightStationPrototypeID,
     pub asset: RenderAsset,
+    #[serde(serialize_with = "crate::export::money")]
     pub price: Money,
     pub size: Size2D,
+    /// Empty when the building only has its door
+    #[serde(serialize_with = "crate::export::entrances")]
+    pub entrances: Vec<EntranceDef>,
 }
 
 impl Prototype for FreightStationPrototype {
@@ -27,6 +37,7 @@ impl Prototype for FreightStationPrototype {
             asset: get_lua(table, "asset")?,
             price: get_lua(table, "price")?,
             size: get_lua(table, "size")?,
+            entrances: get_lua_default(table, "entrances")?,
         })
     }
 
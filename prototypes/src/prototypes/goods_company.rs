@@ -5,7 +5,10 @@ use serde::{Deserialize, Serialize};
 
 use egui_inspect::Inspect;
 
-use crate::{get_lua, get_lua_opt, BuildingPrototype, GoodsCompanyID, Prototype, Recipe, Zone};
+use crate::{
+    get_lua, get_lua_default, get_lua_opt, BuildingPrototype, CropID, DepositID, GoodsCompanyID,
+    Prototype, RecTimeInterval, Recipe, Season, Zone,
+};
 
 #[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Inspect)]
 pub enum CompanyKind {
@@ -15,15 +18,46 @@ pub enum CompanyKind {
     Factory,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct GoodsCompanyPrototype {
+    #[serde(flatten)]
     pub base: BuildingPrototype,
+    #[serde(skip)]
     pub id: GoodsCompanyID,
     pub kind: CompanyKind,
     pub recipe: Option<Recipe>,
     pub n_trucks: u32,
     pub n_workers: u32,
     pub zone: Option<Zone>,
+    /// Working hours, workers are spread evenly over the shifts
+    pub shifts: Vec<RecTimeInterval>,
+    /// When customers can visit, always open if None
+    pub opening_hours: Option<RecTimeInterval>,
+    /// Production multiplier of each season, in the order of [`Season::ALL`]
+    pub seasonal_output: Option<[f32; 4]>,
+    /// The company cuts a mature tree within this radius for each production, it idles without one
+    pub harvest_radius: Option<f32>,
+    /// Crops sown in turn on the field, the recipe only runs during their harvests
+    #[serde(serialize_with = "crate::export::ids")]
+    pub crops: Vec<CropID>,
+    /// The company must be built on a deposit of that kind, and each production extracts from it
+    #[serde(serialize_with = "crate::export::opt_id")]
+    pub deposit: Option<DepositID>,
+}
+
+impl GoodsCompanyPrototype {
+    /// The shift of the n-th worker
+    pub fn shift(&self, n: usize) -> RecTimeInterval {
+        self.shifts[n % self.shifts.len()]
+    }
+
+    /// Production multiplier during the season, 1 if seasons are disabled
+    pub fn seasonal_output(&self, season: Option<Season>) -> f32 {
+        match (self.seasonal_output, season) {
+            (Some(multipliers), Some(season)) => multipliers[season as usize],
+            _ => 1.0,
+        }
+    }
 }
 
 impl Prototype for GoodsCompanyPrototype {
@@ -38,9 +72,19 @@ impl Prototype for GoodsCompanyPrototype {
             base,
             kind: get_lua(table, "kind")?,
             recipe: get_lua(table, "recipe")?,
-            n_trucks: get_lua_opt(table, "n_trucks")?.unwrap_or(0),
-            n_workers: get_lua_opt(table, "n_workers")?.unwrap_or(0),
+            n_trucks: get_lua_default(table, "n_trucks")?,
+            n_workers: get_lua_default(table, "n_workers")?,
             zone: get_lua(table, "zone").ok(),
+            shifts: get_lua_opt::<Vec<RecTimeInterval>>(table, "shifts")?
+                .filter(|shifts| !shifts.is_empty())
+                .unwrap_or_else(|| vec![RecTimeInterval::new((8, 0), (18, 0))]),
+            opening_hours: get_lua_opt(table, "opening_hours")?,
+            seasonal_output: get_lua_opt::<Table>(table, "seasonal_output")?
+                .map(|t| seasonal_multipliers(&t))
+                .transpose()?,
+            harvest_radius: get_lua_opt(table, "harvest_radius")?,
+            crops: get_lua_default(table, "crops")?,
+            deposit: get_lua_opt(table, "deposit")?,
         })
     }
 
@@ -61,6 +105,17 @@ impl Deref for GoodsCompanyPrototype {
     }
 }
 
+/// Seasons missing from the table keep a multiplier of 1
+fn seasonal_multipliers(table: &Table) -> mlua::Result<[f32; 4]> {
+    let mut multipliers = [1.0; 4];
+    for (m, season) in multipliers.iter_mut().zip(Season::ALL) {
+        if let Some(v) = get_lua_opt(table, season.lua_name())? {
+            *m = v;
+        }
+    }
+    Ok(multipliers)
+}
+
 impl<'a> FromLua<'a> for CompanyKind {
     fn from_lua(value: Value<'a>, lua: &'a Lua) -> mlua::Result<Self> {
         let s: String = FromLua::from_lua(value, lua)?;
@@ -1,16 +1,26 @@
 use crate::prototypes::PrototypeBase;
-use crate::{get_lua, ItemID, NoParent, Prototype};
+use crate::{get_lua_default, get_lua_opt, ItemID, LuaColor, NoParent, Prototype, RenderAsset};
+use geom::Color;
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 /// Item is the runtime representation of an item, such as meat, wood, etc.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct ItemPrototype {
+    #[serde(flatten)]
     pub base: PrototypeBase,
+    #[serde(skip)]
     pub id: ItemID,
     pub optout_exttrade: bool,
+    /// Mesh drawn on the trucks carrying the item, a crate of `cargo_color` if None
+    pub cargo_asset: Option<RenderAsset>,
+    pub cargo_color: Color,
 }
 
+/// Color of the crates of the items that do not choose one
+const DEFAULT_CARGO_COLOR: Color = Color::new(0.6, 0.45, 0.3, 1.0);
+
 impl Prototype for ItemPrototype {
     type Parent = NoParent;
     type ID = ItemID;
@@ -21,7 +31,10 @@ impl Prototype for ItemPrototype {
         Ok(Self {
             id: Self::ID::new(&base.name),
             base,
-            optout_exttrade: get_lua(table, "optout_exttrade").unwrap_or(false),
+            optout_exttrade: get_lua_default(table, "optout_exttrade")?,
+            cargo_asset: get_lua_opt(table, "cargo_asset")?,
+            cargo_color: get_lua_opt::<LuaColor>(table, "cargo_color")?
+                .map_or(DEFAULT_CARGO_COLOR, |c| c.0),
         })
     }
 
@@ -1,17 +1,33 @@
-use crate::{get_lua, Money, Prototype, RecTimeInterval};
+use crate::{get_lua, get_lua_opt, Money, Prototype, RecTimeInterval};
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
 /// LeisurePrototype is a building where people can go to relax
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct LeisurePrototype {
+    #[serde(flatten)]
     pub base: BuildingPrototype,
+    #[serde(skip)]
     pub id: LeisurePrototypeID,
     pub opening_hours: RecTimeInterval,
     pub capacity: u32,
+    #[serde(serialize_with = "crate::export::money")]
     pub entry_fee: Money,
+    /// Event held on a schedule, drawing a crowd from across the city
+    pub event: Option<LeisureEvent>,
+}
+
+/// Show, match or concert held at a venue every few days
+#[derive(Clone, Debug, Serialize)]
+pub struct LeisureEvent {
+    pub hours: RecTimeInterval,
+    /// Days between two events
+    pub every_days: u32,
+    /// People invited to each event, no more than the capacity come
+    pub attendance: u32,
 }
 
 impl Prototype for LeisurePrototype {
@@ -27,6 +43,14 @@ impl Prototype for LeisurePrototype {
             opening_hours: get_lua(table, "opening_hours")?,
             capacity: get_lua(table, "capacity")?,
             entry_fee: get_lua(table, "entry_fee")?,
+            event: match get_lua_opt::<Table>(table, "event")? {
+                Some(t) => Some(LeisureEvent {
+                    hours: get_lua(&t, "hours")?,
+                    every_days: get_lua::<u32>(&t, "every_days")?.max(1),
+                    attendance: get_lua(&t, "attendance")?,
+                }),
+                None => None,
+            },
         })
     }
 
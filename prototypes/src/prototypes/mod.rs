@@ -5,6 +5,8 @@ crate::gen_prototypes!(
     mod goods_company: GoodsCompanyID      = GoodsCompanyPrototype => BuildingPrototypeID,
     mod leisure:       LeisurePrototypeID  = LeisurePrototype => BuildingPrototypeID,
     mod solar:         SolarPanelID        = SolarPanelPrototype => GoodsCompanyID,
+    mod crop:          CropID              = CropPrototype,
+    mod deposit:       DepositID           = DepositPrototype,
 
     mod vehicle:       VehiclePrototypeID = VehiclePrototype,
     mod road_vehicle:  RoadVehicleID      = RoadVehiclePrototype => VehiclePrototypeID,
@@ -12,6 +14,16 @@ crate::gen_prototypes!(
 
     mod colors:         ColorsPrototypeID   = ColorsPrototype,
     mod freightstation: FreightStationPrototypeID = FreightStationPrototype,
+    mod dock:           DockPrototypeID           = DockPrototype,
+    mod warehouse:      WarehousePrototypeID      = WarehousePrototype,
+    mod service_depot:  ServiceDepotID            = ServiceDepotPrototype,
+    mod road:           RoadPrototypeID           = RoadPrototype,
+    mod prop:           PropPrototypeID           = PropPrototype,
+    mod scenario:       ScenarioID                = ScenarioPrototype,
+    mod street_names:   StreetNamesID             = StreetNamesPrototype,
+    mod audio_event:    AudioEventID              = AudioEventPrototype,
+    mod tutorial:       TutorialID                = TutorialPrototype,
+    mod build_category: BuildCategoryID           = BuildCategoryPrototype,
 );
 
 mod base;
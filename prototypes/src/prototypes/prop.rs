@@ -0,0 +1,87 @@
+use crate::{get_color, get_lua, get_lua_opt, NoParent, Prototype, PrototypeBase, RenderAsset};
+use geom::Color;
+use mlua::{FromLua, Lua, Table, Value};
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// PropPrototype is a piece of scenery scattered over the terrain, like rocks or bushes
+#[derive(Clone, Debug, Serialize)]
+pub struct PropPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: PropPrototypeID,
+    pub asset: RenderAsset,
+    pub tint: Color,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    /// Average number of props per hectare where the rules allow them
+    pub density: f32,
+    pub biome: PropBiome,
+    /// Steepest terrain the prop can stand on, as height over distance
+    pub max_slope: f32,
+    /// Distance from the edge of the nearest road, the prop faces the road if set
+    pub road_distance: Option<(f32, f32)>,
+}
+
+/// Which part of the land a prop grows on, from the tree density of the terrain
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum PropBiome {
+    Any,
+    Forest,
+    Clearing,
+}
+
+impl Prototype for PropPrototype {
+    type Parent = NoParent;
+    type ID = PropPrototypeID;
+    const NAME: &'static str = "prop";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        let min_road = get_lua_opt::<f32>(table, "min_road_distance")?;
+        let max_road = get_lua_opt::<f32>(table, "max_road_distance")?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            asset: get_lua(table, "asset")?,
+            tint: get_color(table, "tint")?,
+            min_scale: get_lua_opt(table, "min_scale")?.unwrap_or(1.0),
+            max_scale: get_lua_opt(table, "max_scale")?.unwrap_or(1.0),
+            density: get_lua(table, "density")?,
+            biome: get_lua_opt(table, "biome")?.unwrap_or(PropBiome::Any),
+            max_slope: get_lua_opt(table, "max_slope")?.unwrap_or(1.0),
+            road_distance: max_road.map(|max| (min_road.unwrap_or(0.0), max)),
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl<'a> FromLua<'a> for PropBiome {
+    fn from_lua(value: Value<'a>, lua: &'a Lua) -> mlua::Result<Self> {
+        let s: String = FromLua::from_lua(value, lua)?;
+        match &*s {
+            "any" => Ok(Self::Any),
+            "forest" => Ok(Self::Forest),
+            "clearing" => Ok(Self::Clearing),
+            _ => Err(mlua::Error::external(format!("Unknown prop biome: {}", s))),
+        }
+    }
+}
+
+impl Deref for PropPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
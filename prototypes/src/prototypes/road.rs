@@ -0,0 +1,55 @@
+use crate::{get_lua, Money, NoParent, Prototype, PrototypeBase};
+use mlua::Table;
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// RoadPrototype holds the construction costs of a kind of road (or rail)
+#[derive(Clone, Debug, Serialize)]
+pub struct RoadPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: RoadPrototypeID,
+    /// Price of one meter of a single lane
+    #[serde(serialize_with = "crate::export::money")]
+    pub price_per_meter: Money,
+    /// Price multiplier for the parts built high above the terrain
+    pub bridge_multiplier: f32,
+    /// Price multiplier for the parts built below the terrain
+    pub tunnel_multiplier: f32,
+}
+
+impl Prototype for RoadPrototype {
+    type Parent = NoParent;
+    type ID = RoadPrototypeID;
+    const NAME: &'static str = "road";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            price_per_meter: get_lua(table, "price_per_meter")?,
+            bridge_multiplier: get_lua(table, "bridge_multiplier")?,
+            tunnel_multiplier: get_lua(table, "tunnel_multiplier")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for RoadPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
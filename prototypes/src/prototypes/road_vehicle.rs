@@ -1,20 +1,34 @@
-use crate::{get_lua, Prototype};
+use crate::{get_lua, get_lua_default, Prototype};
 
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct RoadVehiclePrototype {
+    #[serde(flatten)]
     pub base: VehiclePrototype,
+    #[serde(skip)]
     pub id: RoadVehicleID,
     /// m/s
     pub max_speed: f32,
-    /// m.s^2
+    /// m.s^2, the most the tires can give at low speed
     pub acceleration: f32,
-    /// m.s^2
+    /// m.s^2, braking on flat ground
     pub deceleration: f32,
+    /// metric ton
+    pub mass: f32,
+    /// kW, limits the acceleration once the vehicle is moving
+    pub power: f32,
+    /// Units of goods it can carry
+    pub capacity: u32,
+    /// m, bumper to bumper
+    pub length: f32,
+    /// How often citizens pick it among the models their household can afford,
+    /// 0 for the vehicles they never drive
+    pub popularity: f32,
 }
 
 impl Prototype for RoadVehiclePrototype {
@@ -30,6 +44,11 @@ impl Prototype for RoadVehiclePrototype {
             max_speed: get_lua::<f32>(table, "max_speed")?,
             acceleration: get_lua::<f32>(table, "acceleration")?,
             deceleration: get_lua::<f32>(table, "deceleration")?,
+            mass: get_lua::<f32>(table, "mass")?,
+            power: get_lua::<f32>(table, "power")?,
+            capacity: get_lua_default::<u32>(table, "capacity")?,
+            length: get_lua::<f32>(table, "length")?,
+            popularity: get_lua_default::<f32>(table, "popularity")?,
         })
     }
     fn id(&self) -> Self::ID {
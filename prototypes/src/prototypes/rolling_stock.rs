@@ -1,12 +1,15 @@
-use crate::{get_lua, Prototype};
+use crate::{get_lua, get_lua_default, Prototype};
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct RollingStockPrototype {
+    #[serde(flatten)]
     pub base: VehiclePrototype,
+    #[serde(skip)]
     pub id: RollingStockID,
     /// meter
     pub length: f32,
@@ -14,10 +17,12 @@ pub struct RollingStockPrototype {
     pub mass: u32,
     /// m/s
     pub max_speed: f32,
-    /// kN
+    /// kN, the most the wheels can pull at low speed
     pub acc_force: f32,
     /// kN
     pub dec_force: f32,
+    /// kW, limits the pulling force once the train is moving
+    pub power: f32,
 }
 
 impl Prototype for RollingStockPrototype {
@@ -35,6 +40,7 @@ impl Prototype for RollingStockPrototype {
             max_speed: get_lua::<f32>(table, "max_speed")?,
             acc_force: get_lua::<f32>(table, "acc_force")?,
             dec_force: get_lua::<f32>(table, "dec_force")?,
+            power: get_lua_default::<f32>(table, "power")?,
         })
     }
     fn id(&self) -> Self::ID {
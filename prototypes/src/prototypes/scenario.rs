@@ -0,0 +1,206 @@
+use crate::{
+    get_lua, get_lua_default, get_lua_opt, ItemID, LuaVec2, Money, NoParent, Prototype,
+    PrototypeBase, RestrictionKind,
+};
+use geom::Vec2;
+use mlua::{FromLua, Lua, Table, Value};
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// ScenarioPrototype is a game with starting conditions and objectives to complete
+#[derive(Clone, Debug, Serialize)]
+pub struct ScenarioPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: ScenarioID,
+    /// Short text shown in the scenario picker
+    pub description: String,
+    /// Size of the generated terrain, ignored when starting from a save
+    pub terrain_size: u16,
+    /// Save to start from instead of generating a terrain
+    pub save: Option<String>,
+    #[serde(serialize_with = "crate::export::money")]
+    pub starting_money: Money,
+    /// Buildings that can be built from the start, None means everything is unlocked
+    #[serde(serialize_with = "crate::export::opt_ids")]
+    pub unlocked: Option<Vec<BuildingPrototypeID>>,
+    pub objectives: Vec<Objective>,
+    /// Game rules the scenario imposes, the player cannot change them
+    pub rules: ScenarioRules,
+    /// Tutorial shown while playing the scenario
+    #[serde(serialize_with = "crate::export::opt_id")]
+    pub tutorial: Option<TutorialID>,
+    /// Areas of the map restricted from the start, objectives can lift them
+    pub restrictions: Vec<ScenarioRestriction>,
+    /// Flags set from the start, the buildings can require them to be unlocked
+    pub flags: Vec<String>,
+}
+
+/// Restricted area placed when the scenario starts
+#[derive(Clone, Debug, Serialize)]
+pub struct ScenarioRestriction {
+    /// Shown to the player and referenced by the objectives lifting the restriction
+    pub name: String,
+    pub kind: RestrictionKind,
+    /// Corners of the area in world coordinates
+    #[serde(serialize_with = "crate::export::vec2s")]
+    pub shape: Vec<Vec2>,
+}
+
+/// Game rules fixed by a scenario, None leaves the rule to the player
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ScenarioRules {
+    pub road_cost: Option<f32>,
+    pub building_cost: Option<f32>,
+    pub utilities_required: Option<bool>,
+    pub road_wear: Option<f32>,
+    pub bankruptcy_days: Option<u32>,
+    pub derelict_days: Option<u32>,
+    pub construction_time: Option<f32>,
+    pub disasters: Option<bool>,
+    pub income_tax: Option<f32>,
+}
+
+/// A goal of a scenario, checked once per game day
+#[derive(Clone, Debug, Serialize)]
+pub struct Objective {
+    pub label: String,
+    pub condition: ObjectiveCondition,
+    /// Number of game days to complete the objective, counted from the start of the scenario
+    pub time_limit_days: Option<u32>,
+    #[serde(serialize_with = "crate::export::money")]
+    pub reward_money: Money,
+    #[serde(serialize_with = "crate::export::ids")]
+    pub reward_unlocks: Vec<BuildingPrototypeID>,
+    /// Names of the scenario restrictions removed once the objective is completed
+    pub reward_lifts: Vec<String>,
+    /// Scenario flags set once the objective is completed
+    pub reward_flags: Vec<String>,
+}
+
+/// A condition over the city statistics
+#[derive(Copy, Clone, Debug, Serialize)]
+pub enum ObjectiveCondition {
+    /// At least this many inhabitants
+    Population { at_least: u32 },
+    /// At least this quantity of the item produced in the city per day
+    Production {
+        #[serde(serialize_with = "crate::export::id")]
+        item: ItemID,
+        at_least_per_day: u32,
+    },
+    /// Average commute to work of at most this many minutes
+    Commute { at_most_minutes: f32 },
+}
+
+impl Prototype for ScenarioPrototype {
+    type Parent = NoParent;
+    type ID = ScenarioID;
+    const NAME: &'static str = "scenario";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            description: get_lua_default(table, "description")?,
+            terrain_size: get_lua_opt(table, "terrain_size")?.unwrap_or(50),
+            save: get_lua_opt(table, "save")?,
+            starting_money: get_lua(table, "starting_money")?,
+            unlocked: get_lua_opt(table, "unlocked")?,
+            objectives: get_lua(table, "objectives")?,
+            rules: get_lua_default(table, "rules")?,
+            tutorial: get_lua_opt(table, "tutorial")?,
+            restrictions: get_lua_default(table, "restrictions")?,
+            flags: get_lua_default(table, "flags")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for ScenarioPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<'lua> FromLua<'lua> for Objective {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table: Table = FromLua::from_lua(value, lua)?;
+        Ok(Self {
+            label: get_lua(&table, "label")?,
+            condition: get_lua(&table, "condition")?,
+            time_limit_days: get_lua_opt(&table, "time_limit_days")?,
+            reward_money: get_lua_opt(&table, "reward_money")?.unwrap_or(Money::ZERO),
+            reward_unlocks: get_lua_default(&table, "reward_unlocks")?,
+            reward_lifts: get_lua_default(&table, "reward_lifts")?,
+            reward_flags: get_lua_default(&table, "reward_flags")?,
+        })
+    }
+}
+
+impl<'lua> FromLua<'lua> for ScenarioRestriction {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table: Table = FromLua::from_lua(value, lua)?;
+        Ok(Self {
+            name: get_lua(&table, "name")?,
+            kind: get_lua(&table, "kind")?,
+            shape: get_lua::<Vec<LuaVec2>>(&table, "shape")?
+                .into_iter()
+                .map(|v| v.0)
+                .collect(),
+        })
+    }
+}
+
+impl<'lua> FromLua<'lua> for ScenarioRules {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table: Table = FromLua::from_lua(value, lua)?;
+        Ok(Self {
+            road_cost: get_lua_opt(&table, "road_cost")?,
+            building_cost: get_lua_opt(&table, "building_cost")?,
+            utilities_required: get_lua_opt(&table, "utilities_required")?,
+            road_wear: get_lua_opt(&table, "road_wear")?,
+            bankruptcy_days: get_lua_opt(&table, "bankruptcy_days")?,
+            derelict_days: get_lua_opt(&table, "derelict_days")?,
+            construction_time: get_lua_opt(&table, "construction_time")?,
+            disasters: get_lua_opt(&table, "disasters")?,
+            income_tax: get_lua_opt(&table, "income_tax")?,
+        })
+    }
+}
+
+impl<'lua> FromLua<'lua> for ObjectiveCondition {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table: Table = FromLua::from_lua(value, lua)?;
+        let kind: String = get_lua(&table, "kind")?;
+        match &*kind {
+            "population" => Ok(Self::Population {
+                at_least: get_lua(&table, "at_least")?,
+            }),
+            "production" => Ok(Self::Production {
+                item: get_lua(&table, "item")?,
+                at_least_per_day: get_lua(&table, "at_least_per_day")?,
+            }),
+            "commute" => Ok(Self::Commute {
+                at_most_minutes: get_lua(&table, "at_most_minutes")?,
+            }),
+            _ => Err(mlua::Error::external(format!(
+                "Unknown objective condition: {}",
+                kind
+            ))),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{
+    get_lua, get_lua_default, get_lua_opt, AmenityKind, BuildMenuEntry, EntranceDef, Money,
+    NoParent, Prototype, PrototypeBase, RenderAsset, ServiceKind, Size2D,
+};
+use mlua::Table;
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// ServiceDepotPrototype is the home of the vehicles of a city service.
+/// The government buys the vehicles and pays their upkeep every day.
+#[derive(Clone, Debug, Serialize)]
+pub struct ServiceDepotPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: ServiceDepotID,
+    pub asset: RenderAsset,
+    #[serde(serialize_with = "crate::export::money")]
+    pub price: Money,
+    pub size: Size2D,
+    /// Empty when the building only has its door
+    #[serde(serialize_with = "crate::export::entrances")]
+    pub entrances: Vec<EntranceDef>,
+    /// What the building offers to the residences within walking distance
+    pub amenity: Option<AmenityKind>,
+    /// Where the building is in the build menu
+    pub menu: BuildMenuEntry,
+    pub service: ServiceKind,
+    /// Price of each vehicle bought for the depot
+    #[serde(serialize_with = "crate::export::money")]
+    pub vehicle_price: Money,
+    /// Money paid each day for each vehicle of the depot
+    #[serde(serialize_with = "crate::export::money")]
+    pub vehicle_upkeep: Money,
+    /// Vehicles the depot comes with, included in its price
+    pub initial_fleet: u32,
+    pub max_fleet: u32,
+}
+
+impl Prototype for ServiceDepotPrototype {
+    type Parent = NoParent;
+    type ID = ServiceDepotID;
+    const NAME: &'static str = "service-depot";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            asset: get_lua(table, "asset")?,
+            price: get_lua(table, "price")?,
+            size: get_lua(table, "size")?,
+            entrances: get_lua_default(table, "entrances")?,
+            amenity: get_lua_opt(table, "amenity")?,
+            menu: BuildMenuEntry::from_building(table)?,
+            service: get_lua(table, "service")?,
+            vehicle_price: get_lua(table, "vehicle_price")?,
+            vehicle_upkeep: get_lua(table, "vehicle_upkeep")?,
+            initial_fleet: get_lua_opt(table, "initial_fleet")?.unwrap_or(1),
+            max_fleet: get_lua(table, "max_fleet")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for ServiceDepotPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
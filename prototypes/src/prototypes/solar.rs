@@ -1,9 +1,12 @@
 use crate::{GoodsCompanyPrototype, Prototype, SolarPanelID};
+use serde::Serialize;
 use std::ops::Deref;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SolarPanelPrototype {
+    #[serde(flatten)]
     pub base: GoodsCompanyPrototype,
+    #[serde(skip)]
     pub id: SolarPanelID,
 }
 
@@ -0,0 +1,50 @@
+use crate::{get_lua, NoParent, Prototype, PrototypeBase};
+use mlua::Table;
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// StreetNamesPrototype is a pool of names given to the new streets, like "Elm" and "Street"
+#[derive(Clone, Debug, Serialize)]
+pub struct StreetNamesPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: StreetNamesID,
+    pub names: Vec<String>,
+    /// Appended after the name, e.g. "Street" or "Avenue"
+    pub suffixes: Vec<String>,
+}
+
+impl Prototype for StreetNamesPrototype {
+    type Parent = NoParent;
+    type ID = StreetNamesID;
+    const NAME: &'static str = "street-names";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            names: get_lua(table, "names")?,
+            suffixes: get_lua(table, "suffixes")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for StreetNamesPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
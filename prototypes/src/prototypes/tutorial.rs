@@ -0,0 +1,144 @@
+use crate::{get_lua, get_lua_opt, get_v2, NoParent, Prototype, PrototypeBase};
+use geom::Vec2;
+use mlua::{FromLua, Lua, Table, Value};
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// TutorialPrototype is a sequence of steps guiding the player through the interface.
+/// It is started on its own or embedded in a scenario.
+#[derive(Clone, Debug, Serialize)]
+pub struct TutorialPrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: TutorialID,
+    pub steps: Vec<TutorialStep>,
+}
+
+/// A step of a tutorial, the next one starts when its condition is met
+#[derive(Clone, Debug, Serialize)]
+pub struct TutorialStep {
+    pub title: String,
+    /// Explanation shown in the tutorial panel
+    pub text: String,
+    pub highlight: Option<TutorialHighlight>,
+    /// None waits for the player to press "Next"
+    pub condition: Option<TutorialCondition>,
+}
+
+/// What the step points at
+#[derive(Clone, Debug, Serialize)]
+pub enum TutorialHighlight {
+    /// A named widget of the interface, e.g. "toolbar_straight_road" or "window_economy"
+    Widget(String),
+    /// A position on the map
+    Position(#[serde(serialize_with = "crate::export::vec2")] Vec2),
+}
+
+/// A condition over the interface or the city
+#[derive(Clone, Debug, Serialize)]
+pub enum TutorialCondition {
+    /// The tool of the toolbox with this icon is selected, e.g. "toolbar_housetool"
+    ToolSelected { tool: String },
+    /// The window is open, e.g. "economy"
+    WindowOpen { window: String },
+    /// At least this many meters of roads
+    RoadLength { at_least: f32 },
+    /// At least this many lots zoned as residential
+    ResidentialLots { at_least: u32 },
+    /// At least this many houses
+    Houses { at_least: u32 },
+    /// At least this many companies
+    Companies { at_least: u32 },
+    /// At least this many inhabitants
+    Population { at_least: u32 },
+}
+
+impl Prototype for TutorialPrototype {
+    type Parent = NoParent;
+    type ID = TutorialID;
+    const NAME: &'static str = "tutorial";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            steps: get_lua(table, "steps")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for TutorialPrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl<'lua> FromLua<'lua> for TutorialStep {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table: Table = FromLua::from_lua(value, lua)?;
+        Ok(Self {
+            title: get_lua(&table, "title")?,
+            text: get_lua(&table, "text")?,
+            highlight: get_lua_opt(&table, "highlight")?,
+            condition: get_lua_opt(&table, "condition")?,
+        })
+    }
+}
+
+impl<'lua> FromLua<'lua> for TutorialHighlight {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table: Table = FromLua::from_lua(value, lua)?;
+        if let Some(widget) = get_lua_opt(&table, "widget")? {
+            return Ok(Self::Widget(widget));
+        }
+        Ok(Self::Position(get_v2(&table, "pos")?))
+    }
+}
+
+impl<'lua> FromLua<'lua> for TutorialCondition {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table: Table = FromLua::from_lua(value, lua)?;
+        let kind: String = get_lua(&table, "kind")?;
+        match &*kind {
+            "tool_selected" => Ok(Self::ToolSelected {
+                tool: get_lua(&table, "tool")?,
+            }),
+            "window_open" => Ok(Self::WindowOpen {
+                window: get_lua(&table, "window")?,
+            }),
+            "road_length" => Ok(Self::RoadLength {
+                at_least: get_lua(&table, "at_least")?,
+            }),
+            "residential_lots" => Ok(Self::ResidentialLots {
+                at_least: get_lua(&table, "at_least")?,
+            }),
+            "houses" => Ok(Self::Houses {
+                at_least: get_lua(&table, "at_least")?,
+            }),
+            "companies" => Ok(Self::Companies {
+                at_least: get_lua(&table, "at_least")?,
+            }),
+            "population" => Ok(Self::Population {
+                at_least: get_lua(&table, "at_least")?,
+            }),
+            _ => Err(mlua::Error::external(format!(
+                "Unknown tutorial condition: {}",
+                kind
+            ))),
+        }
+    }
+}
@@ -1,14 +1,18 @@
 use crate::{get_lua, Money, NoParent, Prototype, PrototypeBase, RenderAsset};
 use mlua::Table;
+use serde::Serialize;
 use std::ops::Deref;
 
 use super::*;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct VehiclePrototype {
+    #[serde(flatten)]
     pub base: PrototypeBase,
+    #[serde(skip)]
     pub id: VehiclePrototypeID,
     pub asset: RenderAsset,
+    #[serde(serialize_with = "crate::export::money")]
     pub price: Money,
 }
 
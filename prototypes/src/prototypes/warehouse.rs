@@ -0,0 +1,79 @@
+use crate::{
+    get_lua, get_lua_default, get_lua_opt, AmenityKind, BuildMenuEntry, EntranceDef, ItemID, Money,
+    NoParent, Prototype, PrototypeBase, RenderAsset, Size2D,
+};
+use mlua::Table;
+use serde::Serialize;
+use std::ops::Deref;
+
+use super::*;
+
+/// WarehousePrototype is a large storage building, companies rent space in it and the city keeps
+/// stocks of goods in the rest
+#[derive(Clone, Debug, Serialize)]
+pub struct WarehousePrototype {
+    #[serde(flatten)]
+    pub base: PrototypeBase,
+    #[serde(skip)]
+    pub id: WarehousePrototypeID,
+    pub asset: RenderAsset,
+    #[serde(serialize_with = "crate::export::money")]
+    pub price: Money,
+    pub size: Size2D,
+    /// Empty when the building only has its door
+    #[serde(serialize_with = "crate::export::entrances")]
+    pub entrances: Vec<EntranceDef>,
+    /// What the building offers to the residences within walking distance
+    pub amenity: Option<AmenityKind>,
+    /// Where the building is in the build menu
+    pub menu: BuildMenuEntry,
+    /// Number of goods that can be stored
+    pub capacity: u32,
+    /// The goods that can be stored, any good when empty
+    #[serde(serialize_with = "crate::export::ids")]
+    pub items: Vec<ItemID>,
+}
+
+impl WarehousePrototype {
+    pub fn accepts(&self, item: ItemID) -> bool {
+        self.items.is_empty() || self.items.contains(&item)
+    }
+}
+
+impl Prototype for WarehousePrototype {
+    type Parent = NoParent;
+    type ID = WarehousePrototypeID;
+    const NAME: &'static str = "warehouse";
+
+    fn from_lua(table: &Table) -> mlua::Result<Self> {
+        let base = PrototypeBase::from_lua(table)?;
+        Ok(Self {
+            id: Self::ID::new(&base.name),
+            base,
+            asset: get_lua(table, "asset")?,
+            price: get_lua(table, "price")?,
+            size: get_lua(table, "size")?,
+            entrances: get_lua_default(table, "entrances")?,
+            amenity: get_lua_opt(table, "amenity")?,
+            menu: BuildMenuEntry::from_building(table)?,
+            capacity: get_lua(table, "capacity")?,
+            items: get_lua_default(table, "items")?,
+        })
+    }
+
+    fn id(&self) -> Self::ID {
+        self.id
+    }
+
+    fn parent(&self) -> &Self::Parent {
+        &NoParent
+    }
+}
+
+impl Deref for WarehousePrototype {
+    type Target = PrototypeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
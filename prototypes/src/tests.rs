@@ -1,12 +1,21 @@
 #![cfg(test)]
 
-use crate::load::load_prototypes;
-use crate::{try_prototype, GoodsCompanyID, ItemID, SolarPanelID};
+use crate::load::{
+    load_prototypes, load_prototypes_str, reload_prototypes_with, ConflictMode, DataStage,
+    LoadConfig, LoadMode,
+};
+use crate::{
+    prototype_by_name, try_prototype, try_prototype_by_name, GoodsCompanyID, GoodsCompanyPrototype,
+    ItemID, ItemPrototype, ModSettings, PrototypeLoadError, Prototypes, ScenarioID, SolarPanelID,
+    StreetNamesID,
+};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
 
 #[test]
 fn test_base() {
     unsafe {
-        match load_prototypes("../") {
+        match load_prototypes(&LoadConfig::new("../")) {
             Ok(_) => {}
             Err(e) => {
                 println!("failed to load prototypes: {}", e);
@@ -27,3 +36,443 @@ fn test_base() {
         println!("{:?}", try_prototype(SolarPanelID::new("solar-panel")));
     }
 }
+
+#[test]
+fn test_by_name() {
+    unsafe {
+        load_prototypes(&LoadConfig::new("../")).unwrap();
+    }
+    assert_eq!(
+        prototype_by_name::<ItemPrototype>("cereal").id,
+        ItemID::new("cereal")
+    );
+    assert!(try_prototype_by_name::<ItemPrototype>("unknown").is_none());
+}
+
+#[test]
+fn test_diff() {
+    let load = |lua: &str| unsafe {
+        load_prototypes_str(
+            &mlua::Lua::new(),
+            lua,
+            &[],
+            &ModSettings::default(),
+            None,
+            LoadMode::Strict,
+            ConflictMode::LastWins,
+        )
+        .unwrap()
+        .0
+    };
+    let old = load(
+        r#"
+        data:extend {
+          { type = "item", name = "cereal", label = "Cereal" },
+          { type = "item", name = "flour", label = "Flour" },
+        }
+        "#,
+    );
+    let new = load(
+        r#"
+        data:extend {
+          { type = "item", name = "cereal", label = "Wheat" },
+          { type = "item", name = "bread", label = "Bread" },
+        }
+        "#,
+    );
+
+    let diff = Prototypes::diff(&old, &new);
+    assert_eq!(diff.added, ["item/bread"]);
+    assert_eq!(diff.changed, ["item/cereal"]);
+    assert_eq!(diff.removed, ["item/flour"]);
+    assert!(diff.touches::<ItemPrototype>());
+    assert!(!diff.touches::<GoodsCompanyPrototype>());
+    assert!(Prototypes::diff(&new, &new).is_empty());
+}
+
+#[test]
+fn test_all_errors() {
+    let lua = r#"
+        data:extend {
+          { type = "item", name = "cereal", label = "Cereal" },
+          { type = "item", name = "flour" },
+          { type = "crop", name = "wheat", label = "Wheat", growth = "2d", harvest = "1h", yield_per_field = "many" },
+          { type = "street-names", name = "streets", label = "Streets", names = {}, suffixes = { "Road" } },
+        }
+        "#;
+    let load = |mode| unsafe {
+        load_prototypes_str(
+            &mlua::Lua::new(),
+            lua,
+            &[],
+            &ModSettings::default(),
+            None,
+            mode,
+            ConflictMode::LastWins,
+        )
+        .map(|(p, _)| p)
+    };
+
+    let Err(PrototypeLoadError::Prototypes(errors)) = load(LoadMode::Strict) else {
+        panic!("the broken prototypes must fail the load");
+    };
+    for e in &errors.0 {
+        let source = e.location.as_deref().unwrap_or_default();
+        assert!(source.starts_with("data.lua:"), "{}", e);
+    }
+    let mut found: Vec<_> = errors
+        .0
+        .iter()
+        .map(|e| (e.kind.as_str(), e.name.as_str(), e.field.as_deref()))
+        .collect();
+    found.sort();
+    assert_eq!(
+        found,
+        [
+            ("crop", "wheat", Some("yield_per_field")),
+            ("item", "flour", Some("label")),
+            ("street-names", "streets", Some("names")),
+        ]
+    );
+
+    let p = load(LoadMode::Lenient).unwrap();
+    assert!(p.item.contains_key(&ItemID::new("cereal")));
+    assert!(!p.item.contains_key(&ItemID::new("flour")));
+    assert!(p.crop.is_empty());
+    assert!(p.street_names.is_empty());
+}
+
+#[test]
+fn test_mod_overrides() {
+    let stage = DataStage {
+        dir: PathBuf::from("mods/my-mod"),
+        chunk_name: "my-mod/data.lua".to_string(),
+        package_path: String::new(),
+        code: r#"
+        data:extend {
+          { type = "item", name = "cereal", label = "Wheat" },
+          { type = "item", name = "bread", label = "Bread" },
+        }
+        "#
+        .to_string(),
+    };
+    let load = |conflict| unsafe {
+        load_prototypes_str(
+            &mlua::Lua::new(),
+            r#"
+            data:extend {
+              { type = "item", name = "cereal", label = "Cereal" },
+              { type = "item", name = "flour", label = "Flour" },
+            }
+            "#,
+            std::slice::from_ref(&stage),
+            &ModSettings::default(),
+            None,
+            LoadMode::Strict,
+            conflict,
+        )
+    };
+
+    let (p, report) = load(ConflictMode::LastWins).unwrap();
+    assert_eq!(p.item[&ItemID::new("cereal")].label, "Wheat");
+    assert_eq!(
+        report.overridden,
+        [("item/cereal".to_string(), PathBuf::from("mods/my-mod"))]
+    );
+    assert_eq!(report.added, ["item/bread"]);
+
+    let Err(PrototypeLoadError::Prototypes(errors)) = load(ConflictMode::Forbid) else {
+        panic!("overriding must fail when forbidden");
+    };
+    assert_eq!(errors.0.len(), 1);
+    let e = &errors.0[0];
+    assert_eq!((e.kind.as_str(), e.name.as_str()), ("item", "cereal"));
+    assert!(e.message.contains("base_mod"), "{}", e);
+    assert!(e
+        .location
+        .as_deref()
+        .unwrap_or_default()
+        .starts_with("my-mod/data.lua:"));
+}
+
+#[test]
+fn test_extends() {
+    let (p, _) = unsafe {
+        load_prototypes_str(
+            &mlua::Lua::new(),
+            r#"
+            data:extend {
+              { type = "item", name = "crate", label = "Crate", optout_exttrade = true,
+                cargo_color = { r = 0.5, g = 0.4, b = 0.3 } },
+              { type = "item", name = "bricks", extends = "crate", label = "Bricks",
+                cargo_color = { r = 0.8 } },
+              { type = "item", name = "red-bricks", extends = "bricks" },
+              { type = "street-names", name = "base", label = "Streets",
+                names = { "Oak", "Elm" }, suffixes = { "Road" } },
+              { type = "street-names", name = "replaced", extends = "base", names = { "Ash" } },
+              { type = "street-names", name = "appended", extends = "base",
+                names = { "Ash", __append = true } },
+            }
+            "#,
+            &[],
+            &ModSettings::default(),
+            None,
+            LoadMode::Strict,
+            ConflictMode::LastWins,
+        )
+        .unwrap()
+    };
+
+    let red = &p.item[&ItemID::new("red-bricks")];
+    assert_eq!(red.label, "Bricks", "inherited from the parent");
+    assert!(red.optout_exttrade, "inherited from the grandparent");
+    let c = red.cargo_color;
+    assert_eq!((c.r, c.g, c.b), (0.8, 0.4, 0.3), "nested tables are merged");
+    assert_eq!(p.item[&ItemID::new("crate")].label, "Crate");
+
+    let names = |name| &p.street_names[&StreetNamesID::new(name)];
+    assert_eq!(names("replaced").names, ["Ash"]);
+    assert_eq!(names("replaced").suffixes, ["Road"]);
+    assert_eq!(names("appended").names, ["Oak", "Elm", "Ash"]);
+}
+
+#[test]
+fn test_extends_errors() {
+    let load = || unsafe {
+        load_prototypes_str(
+            &mlua::Lua::new(),
+            r#"
+            data:extend {
+              { type = "item", name = "cereal", label = "Cereal" },
+              { type = "item", name = "flour", extends = "wheat" },
+              { type = "item", name = "a", extends = "b" },
+              { type = "item", name = "b", extends = "c" },
+              { type = "item", name = "c", extends = "a" },
+              { type = "item", name = "d", extends = "a" },
+            }
+            "#,
+            &[],
+            &ModSettings::default(),
+            None,
+            LoadMode::Strict,
+            ConflictMode::LastWins,
+        )
+    };
+
+    let Err(PrototypeLoadError::Prototypes(errors)) = load() else {
+        panic!("broken extends must fail the load");
+    };
+    let mut found: Vec<_> = errors
+        .0
+        .iter()
+        .map(|e| {
+            assert_eq!(e.field.as_deref(), Some("extends"));
+            assert!(e
+                .location
+                .as_deref()
+                .unwrap_or_default()
+                .starts_with("data.lua:"));
+            (e.name.as_str(), e.message.as_str())
+        })
+        .collect();
+    found.sort();
+    assert_eq!(
+        found,
+        [
+            ("a", "extends cycle: a -> b -> c -> a"),
+            ("b", "extends cycle: b -> c -> a -> b"),
+            ("c", "extends cycle: c -> a -> b -> c"),
+            ("d", "extends broken item a"),
+            ("flour", "extends unknown item wheat"),
+        ]
+    );
+}
+
+#[test]
+fn test_mod_provenance_and_deletion() {
+    let stage = |folder: &str, code: &str| DataStage {
+        dir: PathBuf::from("mods").join(folder),
+        chunk_name: format!("{folder}/data.lua"),
+        package_path: String::new(),
+        code: code.to_string(),
+    };
+    let stages = [
+        stage(
+            "first",
+            r#"
+            data:extend {
+              { type = "item", name = "cereal", label = "Wheat" },
+              { type = "item", name = "flour", deleted = true },
+              { type = "item", name = "bread", label = "Bread" },
+            }
+            "#,
+        ),
+        stage(
+            "second",
+            r#"
+            data:extend {
+              { type = "item", name = "cereal", label = "Barley" },
+              { type = "item", name = "bread", deleted = true },
+            }
+            "#,
+        ),
+    ];
+    let (p, report) = unsafe {
+        load_prototypes_str(
+            &mlua::Lua::new(),
+            r#"
+            data:extend {
+              { type = "item", name = "cereal", label = "Cereal" },
+              { type = "item", name = "flour", label = "Flour" },
+              { type = "item", name = "water", label = "Water" },
+            }
+            "#,
+            &stages,
+            &ModSettings::default(),
+            None,
+            LoadMode::Strict,
+            ConflictMode::LastWins,
+        )
+        .unwrap()
+    };
+
+    assert_eq!(p.item[&ItemID::new("cereal")].label, "Barley");
+    assert!(!p.item.contains_key(&ItemID::new("flour")));
+    assert!(!p.item.contains_key(&ItemID::new("bread")));
+    assert_eq!(
+        p.provenances().collect::<Vec<_>>(),
+        [("item/cereal", "second"), ("item/water", "base_mod")]
+    );
+    assert_eq!(
+        report.deleted,
+        [
+            ("item/flour".to_string(), PathBuf::from("mods/first")),
+            ("item/bread".to_string(), PathBuf::from("mods/second")),
+        ]
+    );
+    assert!(report.added.is_empty());
+}
+
+#[test]
+fn test_default_fields() {
+    let load = |lua: &str| unsafe {
+        load_prototypes_str(
+            &mlua::Lua::new(),
+            lua,
+            &[],
+            &ModSettings::default(),
+            None,
+            LoadMode::Strict,
+            ConflictMode::LastWins,
+        )
+        .map(|(p, _)| p)
+    };
+
+    let p =
+        load(r#"data:extend { { type = "item", name = "cereal", label = "Cereal" } }"#).unwrap();
+    let cereal = &p.item[&ItemID::new("cereal")];
+    assert!(!cereal.optout_exttrade);
+    assert_eq!(cereal.order, "");
+
+    let Err(PrototypeLoadError::Prototypes(errors)) =
+        load(r#"data:extend { { type = "item", name = "cereal", label = "Cereal", order = {} } }"#)
+    else {
+        panic!("a field of the wrong type must fail the load");
+    };
+    assert_eq!(errors.0.len(), 1);
+    assert_eq!(errors.0[0].field.as_deref(), Some("order"));
+}
+
+#[test]
+fn test_export_json() {
+    let load = || unsafe {
+        load_prototypes_str(
+            &mlua::Lua::new(),
+            r#"
+            data:extend {
+              { type = "item", name = "flour", label = "Flour", order = "b" },
+              { type = "item", name = "cereal", label = "Cereal", order = "a" },
+              { type = "crop", name = "wheat", label = "Wheat", growth = "2d", harvest = "90m",
+                fallow = 18001, yield_per_field = 3 },
+              { type = "warehouse", name = "silo", label = "Silo", asset = "silo.glb",
+                price = "12.5$", size = 20, capacity = 100, items = { "cereal" } },
+            }
+            "#,
+            &[],
+            &ModSettings::default(),
+            None,
+            LoadMode::Strict,
+            ConflictMode::LastWins,
+        )
+        .unwrap()
+        .0
+    };
+    let json = crate::export_json(&load()).unwrap();
+    assert_eq!(
+        json,
+        crate::export_json(&load()).unwrap(),
+        "the export must be stable"
+    );
+
+    let cereal = json.find(r#""name": "cereal""#).unwrap();
+    let flour = json.find(r#""name": "flour""#).unwrap();
+    assert!(cereal < flour, "the prototypes follow their ordering");
+
+    let v: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let silo = &v["warehouse"][0];
+    assert_eq!(silo["name"], "silo");
+    assert_eq!(
+        silo["items"],
+        serde_json::json!(["cereal"]),
+        "the IDs are exported as names"
+    );
+    assert_eq!(silo["price"], 12.5);
+    assert_eq!(silo["asset"], "silo.glb");
+    assert!(silo.get("id").is_none());
+
+    let wheat = &v["crop"][0];
+    assert_eq!(wheat["growth"], "48h");
+    assert_eq!(wheat["harvest"], "1.50h");
+    assert_eq!(
+        wheat["fallow"], "18001t",
+        "inexact durations are kept in ticks"
+    );
+}
+
+#[test]
+fn test_reload_keeps_pinned() {
+    // a data folder loaded after base_mod that deletes one of its scenarios
+    let dir = std::env::temp_dir().join("egregoria-test-reload");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("data.lua"),
+        r#"data:extend { { type = "scenario", name = "short-commute", deleted = true } }"#,
+    )
+    .unwrap();
+    let base = LoadConfig::new("../");
+    let mut deleting = base.clone();
+    deleting.search_paths.push(dir);
+
+    let scenario = ScenarioID::new("short-commute");
+    let pinned = BTreeSet::from(["scenario/short-commute".to_string()]);
+    unsafe {
+        load_prototypes(&base).unwrap();
+
+        let Err(PrototypeLoadError::PinnedRemoved(removed)) =
+            reload_prototypes_with(&mlua::Lua::new(), &deleting, &pinned)
+        else {
+            panic!("removing a pinned prototype must fail the reload");
+        };
+        assert_eq!(removed, ["scenario/short-commute"]);
+        assert!(
+            try_prototype(scenario).is_some(),
+            "the previous prototypes are kept"
+        );
+
+        let diff = reload_prototypes_with(&mlua::Lua::new(), &deleting, &BTreeSet::new()).unwrap();
+        assert_eq!(diff.removed, ["scenario/short-commute"]);
+
+        // the other tests expect base_mod
+        load_prototypes(&base).unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+use mlua::{FromLua, Lua, Value};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// What a building offers to the people living within walking distance
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum AmenityKind {
+    /// Groceries and shops selling everyday goods
+    Grocery,
+    School,
+    /// Parks, cinemas and other places to relax
+    Leisure,
+    /// Stations and stops of the public transport
+    Transit,
+    Healthcare,
+}
+
+impl AmenityKind {
+    pub const ALL: [AmenityKind; 5] = [
+        AmenityKind::Grocery,
+        AmenityKind::School,
+        AmenityKind::Leisure,
+        AmenityKind::Transit,
+        AmenityKind::Healthcare,
+    ];
+}
+
+impl Display for AmenityKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AmenityKind::Grocery => write!(f, "Grocery"),
+            AmenityKind::School => write!(f, "School"),
+            AmenityKind::Leisure => write!(f, "Leisure"),
+            AmenityKind::Transit => write!(f, "Transit"),
+            AmenityKind::Healthcare => write!(f, "Healthcare"),
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for AmenityKind {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let s: String = FromLua::from_lua(value, lua)?;
+        match &*s {
+            "grocery" => Ok(Self::Grocery),
+            "school" => Ok(Self::School),
+            "leisure" => Ok(Self::Leisure),
+            "transit" => Ok(Self::Transit),
+            "healthcare" => Ok(Self::Healthcare),
+            _ => Err(mlua::Error::external(format!(
+                "Unknown amenity kind: {}",
+                s
+            ))),
+        }
+    }
+}
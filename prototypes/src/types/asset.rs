@@ -1,4 +1,5 @@
 use mlua::{FromLua, Value};
+use serde::{Serialize, Serializer};
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -75,6 +76,15 @@ impl<'lua> FromLua<'lua> for RenderAsset {
     }
 }
 
+/// As its path, like the prototypes write it
+impl Serialize for RenderAsset {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Mesh { path } | Self::Sprite { path } => path.serialize(s),
+        }
+    }
+}
+
 impl std::fmt::Display for RenderAsset {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
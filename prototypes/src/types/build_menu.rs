@@ -0,0 +1,51 @@
+use crate::{get_lua_default, get_lua_opt, BuildCategoryID};
+use mlua::{FromLua, Lua, Table, Value};
+use serde::Serialize;
+
+/// Where a building appears in the build menu and when it can be built
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BuildMenuEntry {
+    /// None puts the building in the "Other" tab
+    #[serde(serialize_with = "crate::export::opt_id")]
+    pub category: Option<BuildCategoryID>,
+    /// Must be one of the subcategories of the category
+    pub subcategory: Option<String>,
+    pub unlock: UnlockConditions,
+}
+
+impl BuildMenuEntry {
+    /// Reads the `category`, `subcategory` and `unlock` fields of a building
+    pub fn from_building(table: &Table) -> mlua::Result<Self> {
+        Ok(Self {
+            category: get_lua_opt(table, "category")?,
+            subcategory: get_lua_opt(table, "subcategory")?,
+            unlock: get_lua_default(table, "unlock")?,
+        })
+    }
+}
+
+/// What must happen before a building can be built, all the conditions must be met.
+/// Researched technologies will be added here.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UnlockConditions {
+    /// Inhabitants the city must have
+    pub min_population: u32,
+    /// Flag set by the scenario being played, ignored outside of scenarios
+    pub scenario_flag: Option<String>,
+}
+
+impl UnlockConditions {
+    pub fn is_empty(&self) -> bool {
+        self.min_population == 0 && self.scenario_flag.is_none()
+    }
+}
+
+impl<'lua> FromLua<'lua> for UnlockConditions {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table: Table = FromLua::from_lua(value, lua)?;
+        Ok(Self {
+            min_population: get_lua_default(&table, "population")?,
+            scenario_flag: get_lua_opt(&table, "scenario_flag")?,
+        })
+    }
+}
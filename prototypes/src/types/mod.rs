@@ -1,17 +1,25 @@
+mod amenity;
 mod asset;
+mod build_menu;
 mod geom;
 mod money;
 mod power;
 mod recipe;
+mod restriction;
+mod service;
 mod size;
 mod time;
 mod zone;
 
+pub use amenity::*;
 pub use asset::*;
+pub use build_menu::*;
 pub use geom::*;
 pub use money::*;
 pub use power::*;
 pub use recipe::*;
+pub use restriction::*;
+pub use service::*;
 pub use size::*;
 pub use time::*;
 pub use zone::*;
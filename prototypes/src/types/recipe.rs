@@ -1,9 +1,11 @@
 use crate::{get_lua, GameDuration, ItemID};
 use egui_inspect::Inspect;
 use mlua::{FromLua, Lua, Table, Value};
+use serde::Serialize;
 
-#[derive(Debug, Clone, Inspect)]
+#[derive(Debug, Clone, Inspect, Serialize)]
 pub struct RecipeItem {
+    #[serde(serialize_with = "crate::export::id")]
     pub id: ItemID,
     pub amount: i32,
 }
@@ -32,12 +34,13 @@ impl<'lua> FromLua<'lua> for RecipeItem {
     }
 }
 
-#[derive(Debug, Clone, Inspect)]
+#[derive(Debug, Clone, Inspect, Serialize)]
 pub struct Recipe {
     pub consumption: Vec<RecipeItem>,
     pub production: Vec<RecipeItem>,
 
     /// Time to execute the recipe when the facility is at full capacity
+    #[serde(serialize_with = "crate::export::duration")]
     pub duration: GameDuration,
 
     /// Quantity to store per production in terms of quantity produced. So if it takes 1ton of flour to make
@@ -0,0 +1,40 @@
+use mlua::{FromLua, Lua, Value};
+use serde::{Deserialize, Serialize};
+
+/// What a restricted area of the map protects
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RestrictionKind {
+    /// Nothing can be built, what stands there can still be demolished
+    NoBuild,
+    /// Nothing can be built or demolished, the trees are not cut and the terrain is not reshaped
+    Nature,
+    /// Nothing can be built or demolished and the terrain is not reshaped
+    Water,
+}
+
+impl RestrictionKind {
+    pub const ALL: [RestrictionKind; 3] = [Self::NoBuild, Self::Nature, Self::Water];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::NoBuild => "no-build",
+            Self::Nature => "protected nature",
+            Self::Water => "protected water",
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for RestrictionKind {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let s: String = FromLua::from_lua(value, lua)?;
+        match &*s {
+            "no_build" => Ok(Self::NoBuild),
+            "nature" => Ok(Self::Nature),
+            "water" => Ok(Self::Water),
+            _ => Err(mlua::Error::external(format!(
+                "Unknown restriction kind: {}",
+                s
+            ))),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use mlua::{FromLua, Lua, Value};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// The city services run by the government, each with its own depots and fleet of vehicles
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ServiceKind {
+    /// Tow trucks clearing the broken down vehicles
+    Towing,
+    /// Fire engines putting out the buildings on fire
+    Fire,
+    /// Garbage trucks emptying the bins of the buildings
+    Garbage,
+}
+
+impl ServiceKind {
+    pub const ALL: [ServiceKind; 3] =
+        [ServiceKind::Towing, ServiceKind::Fire, ServiceKind::Garbage];
+}
+
+impl Display for ServiceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServiceKind::Towing => write!(f, "Towing"),
+            ServiceKind::Fire => write!(f, "Fire"),
+            ServiceKind::Garbage => write!(f, "Garbage"),
+        }
+    }
+}
+
+impl<'lua> FromLua<'lua> for ServiceKind {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let s: String = FromLua::from_lua(value, lua)?;
+        match &*s {
+            "towing" => Ok(Self::Towing),
+            "fire" => Ok(Self::Fire),
+            "garbage" => Ok(Self::Garbage),
+            _ => Err(mlua::Error::external(format!(
+                "Unknown service kind: {}",
+                s
+            ))),
+        }
+    }
+}
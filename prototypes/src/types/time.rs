@@ -13,6 +13,7 @@ pub const SECONDS_PER_MINUTE: i32 = 60;
 pub const MINUTES_PER_HOUR: i32 = 60;
 pub const HOURS_PER_DAY: i32 = 24;
 pub const SECONDS_PER_DAY: i32 = SECONDS_PER_HOUR * HOURS_PER_DAY;
+pub const DAYS_PER_WEEK: i32 = 7;
 pub const TICKS_PER_REALTIME_SECOND: u64 = 50;
 pub const TICKS_PER_SECOND: u64 = TICKS_PER_REALTIME_SECOND / SECONDS_PER_REALTIME_SECOND as u64;
 pub const TICKS_PER_MINUTE: u64 = TICKS_PER_SECOND * SECONDS_PER_MINUTE as u64;
@@ -71,6 +72,89 @@ pub struct DayTime {
     pub second: i32,
 }
 
+/// A day of the week, the first day of the game is a monday
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; DAYS_PER_WEEK as usize] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    pub fn is_weekend(self) -> bool {
+        matches!(self, Weekday::Saturday | Weekday::Sunday)
+    }
+}
+
+impl Display for Weekday {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+/// A season of the year, the game starts at the beginning of spring
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [
+        Season::Spring,
+        Season::Summer,
+        Season::Autumn,
+        Season::Winter,
+    ];
+
+    /// Name of the season in prototype tables
+    pub fn lua_name(self) -> &'static str {
+        match self {
+            Season::Spring => "spring",
+            Season::Summer => "summer",
+            Season::Autumn => "autumn",
+            Season::Winter => "winter",
+        }
+    }
+
+    /// How cold it is through the year, from 0 in the middle of summer to 1 in the middle of winter
+    pub fn coldness(year_progress: f32) -> f32 {
+        0.5 + 0.5 * (std::f32::consts::TAU * (year_progress - 0.875)).cos()
+    }
+}
+
+impl Display for Season {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+impl<'lua> FromLua<'lua> for Season {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let s: String = FromLua::from_lua(value, lua)?;
+        Season::ALL
+            .into_iter()
+            .find(|season| season.lua_name() == s)
+            .ok_or_else(|| mlua::Error::external(format!("Unknown season: {}", s)))
+    }
+}
+
 /// An interval of in-game time
 /// The interval is inclusive on the start and exclusive on the end
 #[derive(Copy, Clone, Serialize, Deserialize)]
@@ -164,6 +248,24 @@ impl RecTimeInterval {
         self.inverted ^ (self.start_seconds..self.end_seconds).contains(&t_day)
     }
 
+    /// Start of the interval in seconds since the start of the day
+    pub fn start_daysec(&self) -> i32 {
+        if self.inverted {
+            self.end_seconds
+        } else {
+            self.start_seconds
+        }
+    }
+
+    /// End of the interval in seconds since the start of the day
+    pub fn end_daysec(&self) -> i32 {
+        if self.inverted {
+            self.start_seconds
+        } else {
+            self.end_seconds
+        }
+    }
+
     /// Time until the next interval
     pub fn dist_start(&self, t: &DayTime) -> i32 {
         let t_day = t.daysec();
@@ -210,6 +312,10 @@ impl DayTime {
     pub fn gamesec(&self) -> i32 {
         self.day * SECONDS_PER_DAY + self.daysec()
     }
+
+    pub fn weekday(&self) -> Weekday {
+        Weekday::ALL[(self.day - 1).rem_euclid(DAYS_PER_WEEK) as usize]
+    }
 }
 
 impl GameTime {
@@ -237,6 +343,41 @@ impl GameTime {
     pub fn daysec(&self) -> f64 {
         self.timestamp % Self::DAY as f64
     }
+
+    pub fn weekday(&self) -> Weekday {
+        self.daytime.weekday()
+    }
+
+    /// Where the time is in the year, from 0 at the start of spring to 1 at the end of winter.
+    /// None if seasons are disabled, that is if they last 0 days.
+    pub fn year_progress(&self, season_days: u32) -> Option<f32> {
+        if season_days == 0 {
+            return None;
+        }
+        let year = 4.0 * season_days as f64 * Self::DAY as f64;
+        let elapsed = self.timestamp - Self::DAY as f64;
+        Some((elapsed.rem_euclid(year) / year) as f32)
+    }
+
+    /// Day of the calendar year, the spring starting at the march equinox.
+    /// Always the equinox if seasons are disabled.
+    pub fn day_of_year(&self, season_days: u32) -> f32 {
+        const MARCH_EQUINOX: f32 = 79.0;
+        let progress = self.year_progress(season_days).unwrap_or(0.0);
+        (MARCH_EQUINOX + progress * geom::DAYS_PER_YEAR) % geom::DAYS_PER_YEAR
+    }
+
+    pub fn season(&self, season_days: u32) -> Option<Season> {
+        let progress = self.year_progress(season_days)?;
+        Some(Season::ALL[((progress * 4.0) as usize).min(3)])
+    }
+
+    /// The next instant the clock shows hour:minute, now if it already does
+    pub fn next_occurrence(&self, hour: i32, minute: i32) -> GameInstant {
+        let target = hour * SECONDS_PER_HOUR + minute * SECONDS_PER_MINUTE;
+        let wait = (target - self.daytime.daysec()).rem_euclid(SECONDS_PER_DAY);
+        self.instant() + GameDuration::from_secs(wait as u64)
+    }
 }
 
 impl GameDuration {
@@ -271,6 +412,23 @@ impl Display for GameInstant {
     }
 }
 
+impl Display for RecTimeInterval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.start_seconds == self.end_seconds {
+            return write!(f, "{}", if self.inverted { "always" } else { "never" });
+        }
+        let (start, end) = (self.start_daysec(), self.end_daysec());
+        write!(
+            f,
+            "{:02}:{:02} -> {:02}:{:02}",
+            start / SECONDS_PER_HOUR,
+            start % SECONDS_PER_HOUR / SECONDS_PER_MINUTE,
+            end / SECONDS_PER_HOUR,
+            end % SECONDS_PER_HOUR / SECONDS_PER_MINUTE,
+        )
+    }
+}
+
 impl Display for DayTime {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}d {:02}:{:02}", self.day, self.hour, self.minute)
@@ -691,6 +849,45 @@ mod tests {
         assert_eq!(interval.dist_start(&h(7)), 0);
     }
 
+    #[test]
+    fn next_occurrence_and_weekday() {
+        use super::*;
+        // the game starts at 08:00 on the first day
+        let t = GameTime::new(Tick(0));
+        assert_eq!(t.weekday(), Weekday::Monday);
+        assert_eq!(t.next_occurrence(8, 0), t.instant());
+        assert_eq!(
+            t.next_occurrence(9, 30).0 .0,
+            TICKS_PER_HOUR + 30 * TICKS_PER_MINUTE
+        );
+        assert_eq!(t.next_occurrence(7, 0).0 .0, 23 * TICKS_PER_HOUR);
+
+        let t = GameTime::new(Tick(6 * 24 * TICKS_PER_HOUR));
+        assert_eq!(t.weekday(), Weekday::Sunday);
+        assert!(t.weekday().is_weekend());
+        let t = GameTime::new(Tick(7 * 24 * TICKS_PER_HOUR));
+        assert_eq!(t.weekday(), Weekday::Monday);
+
+        assert_eq!(
+            RecTimeInterval::new((18, 0), (1, 30)).to_string(),
+            "18:00 -> 01:30"
+        );
+    }
+
+    #[test]
+    fn seasons() {
+        use super::*;
+        let day = |d: u64| GameTime::new(Tick(d * 24 * TICKS_PER_HOUR));
+        assert_eq!(day(0).season(0), None);
+        assert_eq!(day(0).season(5), Some(Season::Spring));
+        assert_eq!(day(5).season(5), Some(Season::Summer));
+        assert_eq!(day(19).season(5), Some(Season::Winter));
+        assert_eq!(day(20).season(5), Some(Season::Spring));
+
+        assert!(Season::coldness(0.375) < 0.01);
+        assert!(Season::coldness(0.875) > 0.99);
+    }
+
     #[test]
     #[rustfmt::skip]
     fn test_daytime_parsing() {
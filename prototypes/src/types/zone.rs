@@ -1,11 +1,13 @@
-use crate::{get_lua, Money};
+use crate::{get_lua, get_lua_default, Money};
 use mlua::{FromLua, Lua, Table, Value};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Zone {
     pub floor: String,
     pub filler: String,
     /// The price for each "production unit"
+    #[serde(serialize_with = "crate::export::money")]
     pub price_per_area: Money,
     /// Whether the zone filler positions should be randomized
     pub randomize_filler: bool,
@@ -18,7 +20,27 @@ impl<'lua> FromLua<'lua> for Zone {
             floor: get_lua(&table, "floor")?,
             filler: get_lua(&table, "filler")?,
             price_per_area: get_lua(&table, "price_per_area").unwrap_or(Money::new_bucks(100)),
-            randomize_filler: get_lua(&table, "randomize_filler").unwrap_or(false),
+            randomize_filler: get_lua_default(&table, "randomize_filler")?,
         })
     }
 }
+
+/// The zones a player can paint on lots, buildings tagged with a zone kind grow there on their own
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ZoneKind {
+    Residential,
+    Commercial,
+    Industrial,
+}
+
+impl<'lua> FromLua<'lua> for ZoneKind {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let s: String = FromLua::from_lua(value, lua)?;
+        match &*s {
+            "residential" => Ok(Self::Residential),
+            "commercial" => Ok(Self::Commercial),
+            "industrial" => Ok(Self::Industrial),
+            _ => Err(mlua::Error::external(format!("Unknown zone kind: {}", s))),
+        }
+    }
+}
@@ -1,28 +1,65 @@
-use thiserror::Error;
+use std::path::Path;
 
-use common::error::MultiError;
+use thiserror::Error;
 
-use crate::{CompanyKind, Prototypes};
+use crate::{
+    AudioEventPrototype, BuildingPrototype, CompanyKind, CropPrototype, DepositPrototype,
+    DockPrototype, GoodsCompanyPrototype, ItemPrototype, PropPrototype, Prototype, PrototypeError,
+    Prototypes, RenderAsset, RoadVehiclePrototype, ScenarioPrototype, ServiceDepotPrototype,
+    StreetNamesPrototype, TutorialPrototype, VehiclePrototype, WarehousePrototype,
+};
 
+/// What is wrong with a prototype, with its name and the field at fault
 #[derive(Debug, Error)]
 pub enum ValidationError {
-    #[error("{0}: only factories can have trucks")]
+    #[error("only factories can have trucks")]
     WrongTrucks(String),
-    #[error("{0}: factories must have trucks if it produces things")]
+    #[error("factories must have trucks if it produces things")]
     ZeroTrucks(String),
-    #[error("{0}.{1}: referenced prototype not found")]
+    #[error("referenced prototype not found")]
     ReferencedProtoNotFound(String, &'static str),
 
-    #[error("{0}.{1}: {2}")]
+    #[error("{2}")]
     InvalidField(String, &'static str, String),
+    #[error("{2} not found")]
+    AssetNotFound(String, &'static str, String),
+}
+
+impl ValidationError {
+    /// Name of the prototype and field at fault
+    pub fn location(&self) -> (&str, &'static str) {
+        match self {
+            ValidationError::WrongTrucks(name) | ValidationError::ZeroTrucks(name) => {
+                (name, "n_trucks")
+            }
+            ValidationError::ReferencedProtoNotFound(name, field)
+            | ValidationError::InvalidField(name, field, _)
+            | ValidationError::AssetNotFound(name, field, _) => (name, field),
+        }
+    }
+}
+
+/// The errors found, with the type of the prototype at fault
+#[derive(Default)]
+struct Errors(Vec<PrototypeError>);
+
+impl Errors {
+    fn push(&mut self, kind: &'static str, e: ValidationError) {
+        self.0.push(PrototypeError::from_validation(kind, e));
+    }
 }
 
-pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationError>> {
-    let mut errors = vec![];
+/// Checks the prototypes are consistent with each other, every problem is returned.
+/// The meshes are looked for in `models` when given, the prototypes loaded from a string have none.
+pub(crate) fn validate(proto: &Prototypes, models: Option<&Path>) -> Vec<PrototypeError> {
+    let mut errors = Errors::default();
 
     for comp in proto.goods_company.values() {
         if comp.n_trucks > 0 && comp.kind != CompanyKind::Factory {
-            errors.push(ValidationError::WrongTrucks(comp.name.clone()));
+            errors.push(
+                GoodsCompanyPrototype::NAME,
+                ValidationError::WrongTrucks(comp.name.clone()),
+            );
         }
 
         if comp.n_trucks == 0
@@ -33,48 +70,462 @@ pub(crate) fn validate(proto: &Prototypes) -> Result<(), MultiError<ValidationEr
                 .map(|r| !r.production.is_empty())
                 .unwrap_or(false)
         {
-            errors.push(ValidationError::ZeroTrucks(comp.name.clone()));
+            errors.push(
+                GoodsCompanyPrototype::NAME,
+                ValidationError::ZeroTrucks(comp.name.clone()),
+            );
+        }
+
+        for crop in &comp.crops {
+            if !proto.crop.contains_key(crop) {
+                errors.push(
+                    GoodsCompanyPrototype::NAME,
+                    ValidationError::ReferencedProtoNotFound(comp.name.clone(), "crops"),
+                );
+            }
+        }
+        if !comp.crops.is_empty() && (comp.zone.is_none() || comp.recipe.is_none()) {
+            errors.push(
+                GoodsCompanyPrototype::NAME,
+                ValidationError::InvalidField(
+                    comp.name.clone(),
+                    "crops",
+                    "only companies with a zone and a recipe can grow crops".to_string(),
+                ),
+            );
+        }
+
+        if let Some(deposit) = comp.deposit {
+            if !proto.deposit.contains_key(&deposit) {
+                errors.push(
+                    GoodsCompanyPrototype::NAME,
+                    ValidationError::ReferencedProtoNotFound(comp.name.clone(), "deposit"),
+                );
+            }
+            if comp.recipe.is_none() {
+                errors.push(
+                    GoodsCompanyPrototype::NAME,
+                    ValidationError::InvalidField(
+                        comp.name.clone(),
+                        "deposit",
+                        "only companies with a recipe can extract from a deposit".to_string(),
+                    ),
+                );
+            }
         }
 
         if let Some(ref r) = comp.recipe {
             for item in &r.consumption {
                 if !proto.item.contains_key(&item.id) {
-                    errors.push(ValidationError::ReferencedProtoNotFound(
-                        comp.name.clone(),
-                        "consumption",
-                    ));
+                    errors.push(
+                        GoodsCompanyPrototype::NAME,
+                        ValidationError::ReferencedProtoNotFound(comp.name.clone(), "consumption"),
+                    );
                 }
             }
 
             for item in &r.production {
                 if !proto.item.contains_key(&item.id) {
-                    errors.push(ValidationError::ReferencedProtoNotFound(
-                        comp.name.clone(),
-                        "production",
-                    ));
+                    errors.push(
+                        GoodsCompanyPrototype::NAME,
+                        ValidationError::ReferencedProtoNotFound(comp.name.clone(), "production"),
+                    );
                 }
             }
         }
 
         if comp.power_consumption.map_or(false, |v| v.0 < 0) {
-            errors.push(ValidationError::InvalidField(
-                comp.name.clone(),
-                "power_consumption",
-                "must not be negative".to_string(),
-            ));
+            errors.push(
+                GoodsCompanyPrototype::NAME,
+                ValidationError::InvalidField(
+                    comp.name.clone(),
+                    "power_consumption",
+                    "must not be negative".to_string(),
+                ),
+            );
         }
 
         if comp.power_production.map_or(false, |v| v.0 < 0) {
-            errors.push(ValidationError::InvalidField(
-                comp.name.clone(),
-                "power_production",
-                "must not be negative".to_string(),
-            ));
+            errors.push(
+                GoodsCompanyPrototype::NAME,
+                ValidationError::InvalidField(
+                    comp.name.clone(),
+                    "power_production",
+                    "must not be negative".to_string(),
+                ),
+            );
+        }
+    }
+
+    for prop in proto.prop.values() {
+        if prop.density < 0.0 {
+            errors.push(
+                PropPrototype::NAME,
+                ValidationError::InvalidField(
+                    prop.name.clone(),
+                    "density",
+                    "must not be negative".to_string(),
+                ),
+            );
+        }
+
+        if prop.min_scale > prop.max_scale {
+            errors.push(
+                PropPrototype::NAME,
+                ValidationError::InvalidField(
+                    prop.name.clone(),
+                    "min_scale",
+                    "must not be above max_scale".to_string(),
+                ),
+            );
+        }
+
+        if let Some((min, max)) = prop.road_distance {
+            if min > max {
+                errors.push(
+                    PropPrototype::NAME,
+                    ValidationError::InvalidField(
+                        prop.name.clone(),
+                        "min_road_distance",
+                        "must not be above max_road_distance".to_string(),
+                    ),
+                );
+            }
         }
     }
 
-    if !errors.is_empty() {
-        return Err(MultiError(errors));
+    for dock in proto.dock.values() {
+        if dock.boat_capacity == 0 {
+            errors.push(
+                DockPrototype::NAME,
+                ValidationError::InvalidField(
+                    dock.name.clone(),
+                    "boat_capacity",
+                    "must be a positive number of seconds".to_string(),
+                ),
+            );
+        }
+
+        if dock.boat_speed <= 0.0 {
+            errors.push(
+                DockPrototype::NAME,
+                ValidationError::InvalidField(
+                    dock.name.clone(),
+                    "boat_speed",
+                    "must be a positive number of seconds".to_string(),
+                ),
+            );
+        }
     }
-    Ok(())
+
+    for warehouse in proto.warehouse.values() {
+        if warehouse.capacity == 0 {
+            errors.push(
+                WarehousePrototype::NAME,
+                ValidationError::InvalidField(
+                    warehouse.name.clone(),
+                    "capacity",
+                    "must be a positive number of seconds".to_string(),
+                ),
+            );
+        }
+
+        for item in &warehouse.items {
+            if !proto.item.contains_key(item) {
+                errors.push(
+                    WarehousePrototype::NAME,
+                    ValidationError::ReferencedProtoNotFound(warehouse.name.clone(), "items"),
+                );
+            }
+        }
+    }
+
+    for depot in proto.service_depot.values() {
+        if depot.max_fleet == 0 {
+            errors.push(
+                ServiceDepotPrototype::NAME,
+                ValidationError::InvalidField(
+                    depot.name.clone(),
+                    "max_fleet",
+                    "must be a positive number of vehicles".to_string(),
+                ),
+            );
+        }
+        if depot.initial_fleet > depot.max_fleet {
+            errors.push(
+                ServiceDepotPrototype::NAME,
+                ValidationError::InvalidField(
+                    depot.name.clone(),
+                    "initial_fleet",
+                    "must not be above max_fleet".to_string(),
+                ),
+            );
+        }
+    }
+
+    for crop in proto.crop.values() {
+        if crop.growth.seconds() <= 0.0 || crop.harvest.seconds() <= 0.0 {
+            errors.push(
+                CropPrototype::NAME,
+                ValidationError::InvalidField(
+                    crop.name.clone(),
+                    "growth",
+                    "growth and harvest must last some time".to_string(),
+                ),
+            );
+        }
+        if crop.yield_per_field == 0 {
+            errors.push(
+                CropPrototype::NAME,
+                ValidationError::InvalidField(
+                    crop.name.clone(),
+                    "yield_per_field",
+                    "must be a positive number of recipe runs".to_string(),
+                ),
+            );
+        }
+    }
+
+    for deposit in proto.deposit.values() {
+        if deposit.density < 0.0 {
+            errors.push(
+                DepositPrototype::NAME,
+                ValidationError::InvalidField(
+                    deposit.name.clone(),
+                    "density",
+                    "must not be negative".to_string(),
+                ),
+            );
+        }
+        if deposit.radius <= 0.0 {
+            errors.push(
+                DepositPrototype::NAME,
+                ValidationError::InvalidField(
+                    deposit.name.clone(),
+                    "radius",
+                    "must be a positive number of meters".to_string(),
+                ),
+            );
+        }
+        if deposit.amount == 0 {
+            errors.push(
+                DepositPrototype::NAME,
+                ValidationError::InvalidField(
+                    deposit.name.clone(),
+                    "amount",
+                    "must be a positive number of recipe runs".to_string(),
+                ),
+            );
+        }
+    }
+
+    for item in proto.item.values() {
+        if item.cargo_asset.as_ref().is_some_and(|a| !a.is_mesh()) {
+            errors.push(
+                ItemPrototype::NAME,
+                ValidationError::InvalidField(
+                    item.name.clone(),
+                    "cargo_asset",
+                    "must be a mesh".to_string(),
+                ),
+            );
+        }
+    }
+
+    for vehicle in proto.vehicle.values() {
+        let (Some(models), RenderAsset::Mesh { path }) = (models, &vehicle.asset) else {
+            continue;
+        };
+        if !models.join(path).exists() {
+            errors.push(
+                VehiclePrototype::NAME,
+                ValidationError::AssetNotFound(
+                    vehicle.name.clone(),
+                    "asset",
+                    path.display().to_string(),
+                ),
+            );
+        }
+    }
+
+    for road_vehicle in proto.road_vehicle.values() {
+        if road_vehicle.length <= 0.0 {
+            errors.push(
+                RoadVehiclePrototype::NAME,
+                ValidationError::InvalidField(
+                    road_vehicle.name.clone(),
+                    "length",
+                    "must be a positive number of meters".to_string(),
+                ),
+            );
+        }
+        if road_vehicle.popularity < 0.0 {
+            errors.push(
+                RoadVehiclePrototype::NAME,
+                ValidationError::InvalidField(
+                    road_vehicle.name.clone(),
+                    "popularity",
+                    "must not be negative".to_string(),
+                ),
+            );
+        }
+    }
+
+    for pool in proto.street_names.values() {
+        if pool.names.is_empty() {
+            errors.push(
+                StreetNamesPrototype::NAME,
+                ValidationError::InvalidField(
+                    pool.name.clone(),
+                    "names",
+                    "must not be empty".to_string(),
+                ),
+            );
+        }
+
+        if pool.suffixes.is_empty() {
+            errors.push(
+                StreetNamesPrototype::NAME,
+                ValidationError::InvalidField(
+                    pool.name.clone(),
+                    "suffixes",
+                    "must not be empty".to_string(),
+                ),
+            );
+        }
+    }
+
+    for rule in proto.audio_event.values() {
+        if rule.sound.is_empty() {
+            errors.push(
+                AudioEventPrototype::NAME,
+                ValidationError::InvalidField(
+                    rule.name.clone(),
+                    "sound",
+                    "must not be empty".to_string(),
+                ),
+            );
+        }
+
+        if !rule.cooldown.is_finite() || rule.cooldown < 0.0 {
+            errors.push(
+                AudioEventPrototype::NAME,
+                ValidationError::InvalidField(
+                    rule.name.clone(),
+                    "cooldown",
+                    "must be a positive number of seconds".to_string(),
+                ),
+            );
+        }
+    }
+
+    for tutorial in proto.tutorial.values() {
+        if tutorial.steps.is_empty() {
+            errors.push(
+                TutorialPrototype::NAME,
+                ValidationError::InvalidField(
+                    tutorial.name.clone(),
+                    "steps",
+                    "must not be empty".to_string(),
+                ),
+            );
+        }
+    }
+
+    for scenario in proto.scenario.values() {
+        if let Some(tutorial) = scenario.tutorial {
+            if !proto.tutorial.contains_key(&tutorial) {
+                errors.push(
+                    ScenarioPrototype::NAME,
+                    ValidationError::ReferencedProtoNotFound(scenario.name.clone(), "tutorial"),
+                );
+            }
+        }
+
+        for restriction in &scenario.restrictions {
+            if restriction.shape.len() < 3 {
+                errors.push(
+                    ScenarioPrototype::NAME,
+                    ValidationError::InvalidField(
+                        scenario.name.clone(),
+                        "restrictions",
+                        format!("{} must have at least 3 corners", restriction.name),
+                    ),
+                );
+            }
+        }
+        for objective in &scenario.objectives {
+            for lifted in &objective.reward_lifts {
+                if !scenario.restrictions.iter().any(|r| r.name == *lifted) {
+                    errors.push(
+                        ScenarioPrototype::NAME,
+                        ValidationError::InvalidField(
+                            scenario.name.clone(),
+                            "reward_lifts",
+                            format!("no restriction named {}", lifted),
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    let menus = proto
+        .building
+        .values()
+        .map(|b| (BuildingPrototype::NAME, &b.name, &b.menu))
+        .chain(
+            proto
+                .dock
+                .values()
+                .map(|d| (DockPrototype::NAME, &d.name, &d.menu)),
+        )
+        .chain(
+            proto
+                .warehouse
+                .values()
+                .map(|w| (WarehousePrototype::NAME, &w.name, &w.menu)),
+        )
+        .chain(
+            proto
+                .service_depot
+                .values()
+                .map(|d| (ServiceDepotPrototype::NAME, &d.name, &d.menu)),
+        );
+    for (kind, name, menu) in menus {
+        let Some(category) = menu.category else {
+            if menu.subcategory.is_some() {
+                errors.push(
+                    kind,
+                    ValidationError::InvalidField(
+                        name.clone(),
+                        "subcategory",
+                        "needs a category".to_string(),
+                    ),
+                );
+            }
+            continue;
+        };
+        let Some(category) = proto.build_category.get(&category) else {
+            errors.push(
+                kind,
+                ValidationError::ReferencedProtoNotFound(name.clone(), "category"),
+            );
+            continue;
+        };
+        if let Some(ref sub) = menu.subcategory {
+            if !category.subcategories.contains(sub) {
+                errors.push(
+                    kind,
+                    ValidationError::InvalidField(
+                        name.clone(),
+                        "subcategory",
+                        format!("{} is not a subcategory of {}", sub, category.name),
+                    ),
+                );
+            }
+        }
+    }
+
+    errors.0
 }
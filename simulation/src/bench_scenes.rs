@@ -0,0 +1,260 @@
+//! Scenes measured by the benchmark mode of the game.
+//! They are generated from a seed instead of being shipped as saves, the game caches them on
+//! disk after the first run.
+
+use geom::{vec3, Vec2};
+use prototypes::{RollingStockID, ZoneKind};
+
+use crate::map::{LaneKind, LanePatternBuilder, LotID, MapProject};
+use crate::map_dynamic::{build_company, BuildingInfos};
+use crate::utils::rand_provider::RandProvider;
+use crate::world_command::{WorldCommand, WorldCommands};
+use crate::{Simulation, SimulationOptions};
+
+/// Bump when the generation changes so that the cached scenes are generated again
+pub const SCENES_VERSION: u32 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BenchScene {
+    Downtown,
+    Suburb,
+    Industrial,
+    /// Factories far from each other, their trucks take long routes across the map
+    Freight,
+}
+
+/// What a scene is made of
+struct SceneLayout {
+    terrain_size: u16,
+    /// Intersections on each side of the road grid
+    grid_size: u32,
+    /// Meters between two intersections of the grid
+    spacing: f32,
+    /// Share of the lots that get a house
+    houses: f32,
+    /// Share of the lots that get a company
+    companies: f32,
+    company_zone: ZoneKind,
+    /// Rail lines along the grid, with a train on each track
+    rail_lines: u32,
+    wagons: u32,
+    cars: usize,
+    /// Ticks simulated before the scene is saved, so that it is already busy
+    settle_ticks: u32,
+}
+
+impl BenchScene {
+    pub const ALL: [BenchScene; 4] = [
+        BenchScene::Downtown,
+        BenchScene::Suburb,
+        BenchScene::Industrial,
+        BenchScene::Freight,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BenchScene::Downtown => "downtown",
+            BenchScene::Suburb => "suburb",
+            BenchScene::Industrial => "industrial",
+            BenchScene::Freight => "freight",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    /// Name of the save the generated scene is cached in
+    pub fn save_name(self) -> String {
+        format!("bench_{}_v{}", self.name(), SCENES_VERSION)
+    }
+
+    fn seed(self) -> u64 {
+        match self {
+            BenchScene::Downtown => 0xD0_17_70_17,
+            BenchScene::Suburb => 0x5B_B0_2B,
+            BenchScene::Industrial => 0x1D_05_72_1A,
+            BenchScene::Freight => 0xF2_E1_67,
+        }
+    }
+
+    fn layout(self) -> SceneLayout {
+        match self {
+            BenchScene::Downtown => SceneLayout {
+                terrain_size: 6,
+                grid_size: 14,
+                spacing: 70.0,
+                houses: 0.8,
+                companies: 0.15,
+                company_zone: ZoneKind::Commercial,
+                rail_lines: 0,
+                wagons: 0,
+                cars: 1500,
+                settle_ticks: 3000,
+            },
+            BenchScene::Suburb => SceneLayout {
+                terrain_size: 10,
+                grid_size: 22,
+                spacing: 180.0,
+                houses: 0.5,
+                companies: 0.02,
+                company_zone: ZoneKind::Commercial,
+                rail_lines: 0,
+                wagons: 0,
+                cars: 400,
+                settle_ticks: 3000,
+            },
+            BenchScene::Industrial => SceneLayout {
+                terrain_size: 8,
+                grid_size: 10,
+                spacing: 150.0,
+                houses: 0.1,
+                companies: 0.6,
+                company_zone: ZoneKind::Industrial,
+                rail_lines: 6,
+                wagons: 12,
+                cars: 300,
+                settle_ticks: 3000,
+            },
+            BenchScene::Freight => SceneLayout {
+                terrain_size: 12,
+                grid_size: 24,
+                spacing: 200.0,
+                houses: 0.15,
+                companies: 0.5,
+                company_zone: ZoneKind::Industrial,
+                rail_lines: 4,
+                wagons: 12,
+                cars: 200,
+                settle_ticks: 6000,
+            },
+        }
+    }
+
+    /// Generates the scene, the same seed always gives the same simulation
+    pub fn generate(self) -> Simulation {
+        let mut sim = self.build();
+        let mut sched = Simulation::schedule();
+        for _ in 0..self.layout().settle_ticks {
+            sim.tick(&mut sched, WorldCommands::default().as_ref());
+        }
+        sim
+    }
+
+    /// The scene before it is simulated
+    fn build(self) -> Simulation {
+        let layout = self.layout();
+        let mut sim = Simulation::new_with_options(SimulationOptions {
+            terrain_size: layout.terrain_size,
+            save_replay: false,
+            ..Default::default()
+        });
+        let mut rng = RandProvider::new(self.seed());
+
+        let center = sim.map().environment.bounds().center();
+        WorldCommand::MapLoadTestField {
+            pos: center,
+            size: layout.grid_size,
+            spacing: layout.spacing,
+        }
+        .apply(&mut sim);
+
+        let lots: Vec<LotID> = sim.map().lots().keys().collect();
+        for lot in lots {
+            let r = rng.next_f32();
+            let built = if r < layout.companies {
+                build_company(&mut sim.map_mut(), lot, layout.company_zone, rng.next_u32())
+            } else if r < layout.companies + layout.houses {
+                sim.map_mut().build_house(lot)
+            } else {
+                None
+            };
+            if let Some(b) = built {
+                sim.write::<BuildingInfos>().insert(b);
+            }
+        }
+
+        self.add_rails(&mut sim, center, &layout);
+
+        WorldCommand::SpawnRandomCars {
+            n_cars: layout.cars,
+        }
+        .apply(&mut sim);
+
+        sim
+    }
+
+    /// Straight rail lines under the grid, with a train at the start of each track
+    fn add_rails(self, sim: &mut Simulation, center: Vec2, layout: &SceneLayout) {
+        if layout.rail_lines == 0 {
+            return;
+        }
+        let half = layout.grid_size as f32 * layout.spacing * 0.5;
+        let pat = LanePatternBuilder::new().rail(true).build();
+
+        let mut rails = vec![];
+        for i in 0..layout.rail_lines {
+            let y = center.y - half - 60.0 - i as f32 * 30.0;
+            let from = vec3(center.x - half, y, 0.0);
+            let to = vec3(center.x + half, y, 0.0);
+            if let Some((_, road)) = sim.map_mut().make_connection(
+                MapProject::ground(from),
+                MapProject::ground(to),
+                None,
+                &pat,
+            ) {
+                rails.push(road);
+            }
+        }
+
+        let mut wagons = vec![RollingStockID::new("locomotive")];
+        wagons.extend((0..layout.wagons).map(|_| RollingStockID::new("freight-wagon")));
+        let train_length = crate::transportation::train::train_length(&wagons);
+
+        for road in rails {
+            let lanes: Vec<_> = sim
+                .map()
+                .roads()
+                .get(road)
+                .map(|r| {
+                    r.lanes_iter()
+                        .filter(|(_, kind)| *kind == LaneKind::Rail)
+                        .map(|(id, _)| id)
+                        .collect()
+                })
+                .unwrap_or_default();
+            for lane in lanes {
+                WorldCommand::SpawnTrain {
+                    wagons: wagons.clone(),
+                    lane,
+                    dist: train_length + 10.0,
+                }
+                .apply(sim);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_names() {
+        for scene in BenchScene::ALL {
+            assert_eq!(BenchScene::from_name(scene.name()), Some(scene));
+        }
+        assert_eq!(BenchScene::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn scenes_are_deterministic() {
+        common::logger::MyLog::init();
+        crate::init::init();
+
+        let a = BenchScene::Downtown.build();
+        let b = BenchScene::Downtown.build();
+        assert!(!a.map().buildings().is_empty());
+        assert_eq!(a.hash(), b.hash());
+    }
+}
@@ -0,0 +1,490 @@
+//! Balance figures derived from the prototypes, to check that a data change does not wreck the
+//! economy: what each item costs to make, what the citizens earn against what they spend, and how
+//! fast the vehicles go.
+//! The report is ordered by name so that two dumps diff cleanly, and [`diff_reports`] explains the
+//! changes between two of them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{
+    try_prototype, GoodsCompanyID, GoodsCompanyPrototype, ItemID, ItemPrototype, Money,
+    RoadVehiclePrototype, RollingStockPrototype, HOURS_PER_DAY, MINUTES_PER_HOUR,
+};
+
+use crate::economy::{EXT_PRICE_MULTIPLIER, WORKER_CONSUMPTION_PER_MINUTE};
+use crate::souls::commute::WALKING_SPEED;
+use crate::souls::household::{CAR_SAVINGS_FACTOR, CAR_UPKEEP_PER_MINUTE, STARTING_SAVINGS};
+
+const MINUTES_PER_DAY: i64 = (MINUTES_PER_HOUR * HOURS_PER_DAY) as i64;
+/// Distance the travel times are given for, in meters
+const TRAVEL_DISTANCE: f32 = 1000.0;
+/// Changes smaller than this, in percent, are not reported
+const DIFF_THRESHOLD: f64 = 0.5;
+
+/// What one unit of an item costs through its cheapest recipe
+#[derive(Debug, Clone)]
+pub struct ItemCost {
+    pub per_unit: Money,
+    /// The company with the cheapest recipe, None for the items nobody produces
+    pub producer: Option<GoodsCompanyID>,
+    /// Wages of the workers during the recipe, per unit
+    pub labor: Money,
+    /// Cost of each consumed item, per unit
+    pub inputs: BTreeMap<ItemID, Money>,
+}
+
+/// Cost per unit of every item, following the recipes down to the raw materials.
+/// The wages are multiplied by `labor_multiplier`.
+pub fn item_costs(labor_multiplier: f32) -> BTreeMap<ItemID, ItemCost> {
+    let mut producers: BTreeMap<ItemID, Vec<&GoodsCompanyPrototype>> = BTreeMap::new();
+    for company in GoodsCompanyPrototype::iter() {
+        let Some(ref recipe) = company.recipe else {
+            continue;
+        };
+        for item in &recipe.production {
+            producers.entry(item.id).or_default().push(company);
+        }
+    }
+
+    let mut costs = BTreeMap::new();
+    for item in ItemPrototype::iter() {
+        item_cost_inner(&producers, item.id, &mut costs, labor_multiplier);
+    }
+    costs
+}
+
+fn item_cost_inner(
+    producers: &BTreeMap<ItemID, Vec<&GoodsCompanyPrototype>>,
+    id: ItemID,
+    costs: &mut BTreeMap<ItemID, ItemCost>,
+    labor_multiplier: f32,
+) {
+    if costs.contains_key(&id) {
+        return;
+    }
+
+    let mut cheapest: Option<ItemCost> = None;
+    for company in producers.get(&id).into_iter().flatten() {
+        let Some(ref recipe) = company.recipe else {
+            continue;
+        };
+        let qty = recipe
+            .production
+            .iter()
+            .find_map(|x| (x.id == id).then_some(x.amount))
+            .unwrap_or(0) as i64;
+
+        let mut consumption = Money::ZERO;
+        let mut inputs = BTreeMap::new();
+        for recipe_item in &recipe.consumption {
+            item_cost_inner(producers, recipe_item.id, costs, labor_multiplier);
+            let cost = costs[&recipe_item.id].per_unit * recipe_item.amount as i64;
+            consumption += cost;
+            *inputs.entry(recipe_item.id).or_insert(Money::ZERO) += cost / qty;
+        }
+
+        let wages =
+            recipe.duration.minutes() * company.n_workers as f64 * WORKER_CONSUMPTION_PER_MINUTE;
+        let labor = Money::new_inner((wages.inner() as f32 * labor_multiplier) as i64);
+
+        let per_unit = (consumption + labor) / qty;
+        if cheapest.as_ref().map_or(true, |c| per_unit < c.per_unit) {
+            cheapest = Some(ItemCost {
+                per_unit,
+                producer: Some(company.id),
+                labor: labor / qty,
+                inputs,
+            });
+        }
+    }
+
+    costs.insert(
+        id,
+        cheapest.unwrap_or(ItemCost {
+            per_unit: Money::ZERO,
+            producer: None,
+            labor: Money::ZERO,
+            inputs: BTreeMap::new(),
+        }),
+    );
+}
+
+/// Cost of an item in the report, with what it is made of
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemBalance {
+    pub cost_per_unit: Money,
+    pub producer: Option<String>,
+    pub labor: Money,
+    /// Cost of each input per unit of output, by item name
+    pub inputs: BTreeMap<String, Money>,
+}
+
+/// What a household with one worker earns and spends per game day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LivingBalance {
+    pub wage: Money,
+    /// A citizen eats one bread a day
+    pub food: Money,
+    pub car_upkeep: Money,
+    /// Left after eating, without a car
+    pub savings: Money,
+    /// Days of savings before the household can buy the standard car, None if it never can
+    pub days_to_car: Option<f32>,
+    /// Left after eating and driving
+    pub savings_with_car: Money,
+}
+
+/// How fast a way of travelling goes, on flat ground ignoring the engine power
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TravelBalance {
+    /// km/h
+    pub max_speed: f32,
+    /// Seconds to reach the top speed from a stop
+    pub time_to_max_speed: f32,
+    /// Seconds to travel a kilometer from a stop
+    pub time_per_km: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceReport {
+    pub items: BTreeMap<String, ItemBalance>,
+    pub living: LivingBalance,
+    /// By "kind/name", e.g. "road/simple_car"
+    pub travel: BTreeMap<String, TravelBalance>,
+}
+
+/// Computes the balance figures of the loaded prototypes
+pub fn balance_report() -> BalanceReport {
+    let costs = item_costs(EXT_PRICE_MULTIPLIER);
+    let name =
+        |id: &ItemID| try_prototype(*id).map_or_else(|| format!("{:?}", id), |p| p.name.clone());
+
+    let items = costs
+        .iter()
+        .map(|(id, c)| {
+            (
+                name(id),
+                ItemBalance {
+                    cost_per_unit: c.per_unit,
+                    producer: c.producer.and_then(try_prototype).map(|p| p.name.clone()),
+                    labor: c.labor,
+                    inputs: c.inputs.iter().map(|(id, &m)| (name(id), m)).collect(),
+                },
+            )
+        })
+        .collect();
+
+    let cost_of = |item: &str| {
+        costs
+            .get(&ItemID::new(item))
+            .map_or(Money::ZERO, |c| c.per_unit)
+    };
+    let wage = WORKER_CONSUMPTION_PER_MINUTE * MINUTES_PER_DAY;
+    let food = cost_of("bread");
+    let car_upkeep = CAR_UPKEEP_PER_MINUTE * MINUTES_PER_DAY;
+    let savings = wage - food;
+    let to_save = cost_of("car") * CAR_SAVINGS_FACTOR - STARTING_SAVINGS;
+    let days_to_car = if to_save <= Money::ZERO {
+        Some(0.0)
+    } else if savings > Money::ZERO {
+        Some(to_save.inner() as f32 / savings.inner() as f32)
+    } else {
+        None
+    };
+    let living = LivingBalance {
+        wage,
+        food,
+        car_upkeep,
+        savings,
+        days_to_car,
+        savings_with_car: savings - car_upkeep,
+    };
+
+    let mut travel = BTreeMap::new();
+    travel.insert(
+        "walk".to_string(),
+        travel_balance(WALKING_SPEED, f32::INFINITY),
+    );
+    for v in RoadVehiclePrototype::iter() {
+        travel.insert(
+            format!("road/{}", v.name),
+            travel_balance(v.max_speed, v.acceleration),
+        );
+    }
+    for v in RollingStockPrototype::iter() {
+        // kN per ton is m/s²
+        let acceleration = v.acc_force / v.mass.max(1) as f32;
+        travel.insert(
+            format!("rail/{}", v.name),
+            travel_balance(v.max_speed, acceleration),
+        );
+    }
+
+    BalanceReport {
+        items,
+        living,
+        travel,
+    }
+}
+
+/// Speed in m/s and acceleration in m/s²
+fn travel_balance(max_speed: f32, acceleration: f32) -> TravelBalance {
+    // a vehicle that cannot move, all zeros to keep the report valid json
+    if max_speed <= 0.0 || acceleration <= 0.0 {
+        return TravelBalance {
+            max_speed: 0.0,
+            time_to_max_speed: 0.0,
+            time_per_km: 0.0,
+        };
+    }
+    let time_to_max_speed = max_speed / acceleration;
+    let accel_distance = 0.5 * max_speed * time_to_max_speed;
+    let time_per_km = if accel_distance >= TRAVEL_DISTANCE {
+        (2.0 * TRAVEL_DISTANCE / acceleration).sqrt()
+    } else {
+        time_to_max_speed + (TRAVEL_DISTANCE - accel_distance) / max_speed
+    };
+    TravelBalance {
+        max_speed: max_speed * 3.6,
+        time_to_max_speed,
+        time_per_km,
+    }
+}
+
+fn percent(before: f64, after: f64) -> Option<f64> {
+    if before == after {
+        return None;
+    }
+    if before == 0.0 {
+        return Some(f64::INFINITY);
+    }
+    let p = (after - before) / before.abs() * 100.0;
+    (p.abs() >= DIFF_THRESHOLD).then_some(p)
+}
+
+fn change(what: &str, before: f64, after: f64) -> Option<String> {
+    percent(before, after).map(|p| format!("{} {:+.0}%", what, p))
+}
+
+fn money(m: Money) -> f64 {
+    m.inner() as f64
+}
+
+/// Human readable changes from `old` to `new`, e.g. "bread cost per unit +12% due to flour price"
+pub fn diff_reports(old: &BalanceReport, new: &BalanceReport) -> Vec<String> {
+    let mut lines = vec![];
+
+    for name in old.items.keys().filter(|k| !new.items.contains_key(*k)) {
+        lines.push(format!("{} removed", name));
+    }
+    for (name, n) in &new.items {
+        let Some(o) = old.items.get(name) else {
+            lines.push(format!("{} added, cost per unit {}", name, n.cost_per_unit));
+            continue;
+        };
+        let Some(line) = change(
+            &format!("{} cost per unit", name),
+            money(o.cost_per_unit),
+            money(n.cost_per_unit),
+        ) else {
+            continue;
+        };
+        lines.push(match cost_cause(old, new, o, n) {
+            Some(cause) => format!("{} due to {}", line, cause),
+            None => line,
+        });
+    }
+
+    let (o, n) = (&old.living, &new.living);
+    let living = [
+        ("daily wage", money(o.wage), money(n.wage)),
+        ("daily food cost", money(o.food), money(n.food)),
+        ("daily car upkeep", money(o.car_upkeep), money(n.car_upkeep)),
+        ("daily savings", money(o.savings), money(n.savings)),
+        (
+            "daily savings with a car",
+            money(o.savings_with_car),
+            money(n.savings_with_car),
+        ),
+    ];
+    lines.extend(living.iter().filter_map(|&(what, b, a)| change(what, b, a)));
+    match (o.days_to_car, n.days_to_car) {
+        (Some(b), Some(a)) => lines.extend(change("days to afford a car", b as f64, a as f64)),
+        (Some(_), None) => lines.push("households can no longer afford a car".to_string()),
+        (None, Some(a)) => lines.push(format!("households can now afford a car in {:.0} days", a)),
+        (None, None) => {}
+    }
+
+    for name in old.travel.keys().filter(|k| !new.travel.contains_key(*k)) {
+        lines.push(format!("{} removed", name));
+    }
+    for (name, n) in &new.travel {
+        let Some(o) = old.travel.get(name) else {
+            lines.push(format!("{} added, {:.0} km/h", name, n.max_speed));
+            continue;
+        };
+        lines.extend(change(
+            &format!("{} top speed", name),
+            o.max_speed as f64,
+            n.max_speed as f64,
+        ));
+        lines.extend(change(
+            &format!("{} time per km", name),
+            o.time_per_km as f64,
+            n.time_per_km as f64,
+        ));
+    }
+
+    lines
+}
+
+/// What part of the cost of an item moved the most
+fn cost_cause(
+    old: &BalanceReport,
+    new: &BalanceReport,
+    o: &ItemBalance,
+    n: &ItemBalance,
+) -> Option<String> {
+    if o.producer != n.producer {
+        return Some(match n.producer {
+            Some(ref p) => format!("cheapest producer now {}", p),
+            None => "no producer".to_string(),
+        });
+    }
+
+    let mut causes = vec![("labor".to_string(), money(n.labor) - money(o.labor))];
+    let inputs: BTreeSet<_> = o.inputs.keys().chain(n.inputs.keys()).collect();
+    for input in inputs {
+        let before = o.inputs.get(input).copied().unwrap_or(Money::ZERO);
+        let after = n.inputs.get(input).copied().unwrap_or(Money::ZERO);
+        causes.push((input.clone(), money(after) - money(before)));
+    }
+    let (cause, delta) = causes
+        .into_iter()
+        .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))?;
+    if delta == 0.0 {
+        return None;
+    }
+    if cause == "labor" {
+        return Some(cause);
+    }
+
+    if !o.inputs.contains_key(&cause) {
+        return Some(format!("new input {}", cause));
+    }
+    if !n.inputs.contains_key(&cause) {
+        return Some(format!("{} no longer used", cause));
+    }
+    // the input costs more per unit either because it got pricier or because more of it is used
+    let cost = |r: &BalanceReport| r.items.get(&cause).map(|i| i.cost_per_unit);
+    Some(match cost(old) != cost(new) {
+        true => format!("{} price", cause),
+        false => format!("{} quantity", cause),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prototypes::test_prototypes;
+
+    fn company(name: &str, workers: u32, consumption: &str, production: &str) -> String {
+        format!(
+            r#"{{
+            type = "goods-company",
+            name = "{name}",
+            label = "{name}",
+            kind = "factory",
+            bgen = "farm",
+            recipe = {{
+                production = {{ {production} }},
+                consumption = {{ {consumption} }},
+                duration = "1m",
+                storage_multiplier = 5,
+            }},
+            n_trucks = 1,
+            n_workers = {workers},
+            size = 0.0,
+            asset = "no.jpg",
+            price = 0,
+        }},"#
+        )
+    }
+
+    /// Cereal is milled into flour, baked into bread by the cheapest of two bakeries
+    fn toy_economy() {
+        let companies = [
+            company("farm", 2, "", r#"{"cereal", 2}"#),
+            company("mill", 1, r#"{"cereal", 2}"#, r#"{"flour", 1}"#),
+            company("bakery", 2, r#"{"flour", 1}"#, r#"{"bread", 2}"#),
+            company("slow-bakery", 4, r#"{"flour", 1}"#, r#"{"bread", 2}"#),
+        ]
+        .concat();
+        test_prototypes(&format!(
+            r#"
+        data:extend {{
+          {{ type = "item", name = "cereal", label = "Cereal" }},
+          {{ type = "item", name = "flour", label = "Flour" }},
+          {{ type = "item", name = "bread", label = "Bread" }},
+        }}
+        data:extend {{ {companies} }}
+        "#
+        ));
+    }
+
+    #[test]
+    fn costs_follow_the_cheapest_chain() {
+        toy_economy();
+
+        let costs = item_costs(1.0);
+        let cost = |name: &str| &costs[&ItemID::new(name)];
+        let cents = Money::new_cents;
+
+        assert_eq!(cost("cereal").per_unit, cents(10));
+        assert_eq!(cost("flour").per_unit, cents(30));
+        assert_eq!(cost("flour").inputs[&ItemID::new("cereal")], cents(20));
+
+        let bread = cost("bread");
+        assert_eq!(bread.per_unit, cents(25));
+        assert_eq!(bread.producer, Some(GoodsCompanyID::new("bakery")));
+        assert_eq!(bread.labor, cents(10));
+        assert_eq!(bread.inputs[&ItemID::new("flour")], cents(15));
+
+        let report = balance_report();
+        assert_eq!(
+            report.items.keys().collect::<Vec<_>>(),
+            ["bread", "cereal", "flour"]
+        );
+        assert_eq!(report.living.food, report.items["bread"].cost_per_unit);
+        assert!(diff_reports(&report, &report).is_empty());
+    }
+
+    #[test]
+    fn diff_explains_the_changes() {
+        toy_economy();
+
+        let old = balance_report();
+        let mut new = old.clone();
+        let flour = new.items.get_mut("flour").unwrap();
+        flour.cost_per_unit = flour.cost_per_unit * 12 / 10;
+        let bread = new.items.get_mut("bread").unwrap();
+        bread.cost_per_unit = bread.cost_per_unit + old.items["bread"].inputs["flour"] / 5;
+        *bread.inputs.get_mut("flour").unwrap() = old.items["bread"].inputs["flour"] * 6 / 5;
+        new.living.food = bread.cost_per_unit;
+        new.travel
+            .insert("road/car".to_string(), travel_balance(20.0, 2.0));
+
+        let lines = diff_reports(&old, &new);
+        let has = |line: &str| lines.iter().any(|l| l == line);
+        assert!(has("flour cost per unit +20%"), "{:?}", lines);
+        assert!(
+            has("bread cost per unit +12% due to flour price"),
+            "{:?}",
+            lines
+        );
+        assert!(has("daily food cost +12%"), "{:?}", lines);
+        assert!(has("road/car added, 72 km/h"), "{:?}", lines);
+    }
+}
@@ -3,16 +3,24 @@ use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 use serde_big_array::BigArray;
 
-use prototypes::{prototypes_iter, ItemPrototype, Money};
+use prototypes::{
+    prototypes_iter, DayTime, ItemPrototype, Money, ServiceKind, HOURS_PER_DAY, TICKS_PER_HOUR,
+};
 
 use crate::economy::{ItemID, Trade};
-use crate::SoulID;
+use crate::map::{BuildingID, DistrictID, RoadID, Traversable, TraverseKind};
+use crate::map_dynamic::Walkability;
+use crate::transportation::service_fleet::{coverage, ServiceFleets};
+use crate::{Simulation, SoulID, World};
 
 pub const HISTORY_SIZE: usize = 128;
 /// Tick to wait before the new bin
 /// Which can be recovred from FREQ * HISTORY_SIZZ / TICK_RATE
 pub const LEVEL_FREQS: [u64; 4] = [250, 1500, 15000, 75000];
 pub const LEVEL_NAMES: [&str; 4] = ["10m", "1h", "10h", "50h"];
+const TICKS_PER_DAY: u64 = TICKS_PER_HOUR * HOURS_PER_DAY as u64;
+/// How far the price of an item in the city moves away from its market value
+pub const LOCAL_PRICE_SPREAD: f64 = 0.5;
 
 /// One history of one item at one frequency level
 /// The past_ring is controlled by a shared cursor for all items
@@ -92,6 +100,24 @@ impl ItemHistories {
         }
     }
 
+    /// Quantity of the item traded during the last game day, at the finest level that covers a whole day
+    pub fn last_day(&self, item: ItemID) -> i64 {
+        let Some(level) = LEVEL_FREQS
+            .iter()
+            .position(|freq| freq * HISTORY_SIZE as u64 >= TICKS_PER_DAY)
+        else {
+            return 0;
+        };
+        let Some(h) = self.m.get(&item) else {
+            return 0;
+        };
+        let n_bins = TICKS_PER_DAY.div_ceil(LEVEL_FREQS[level]) as usize;
+        let cursor = self.cursors[level];
+        (0..n_bins)
+            .map(|i| h.levels[level].past_ring_items[(cursor + HISTORY_SIZE - i) % HISTORY_SIZE])
+            .sum()
+    }
+
     pub fn advance(&mut self, tick: u64) {
         for (c_i, (c, freq)) in self.cursors.iter_mut().zip(&LEVEL_FREQS).enumerate() {
             if tick % *freq == 0 {
@@ -112,11 +138,11 @@ impl EcoStats {
         self.internal_trade.advance(tick);
 
         for trade in trades {
-            if matches!(trade.buyer.0, SoulID::FreightStation(_)) {
+            if matches!(trade.buyer.0, SoulID::FreightStation(_) | SoulID::Dock(_)) {
                 self.exports.handle_trade(trade);
                 continue;
             }
-            if matches!(trade.seller.0, SoulID::FreightStation(_)) {
+            if matches!(trade.seller.0, SoulID::FreightStation(_) | SoulID::Dock(_)) {
                 self.imports.handle_trade(trade);
                 continue;
             }
@@ -125,6 +151,214 @@ impl EcoStats {
     }
 }
 
+impl EcoStats {
+    /// Quantity of the item sold by the companies of the city during the last game day
+    pub fn produced_last_day(&self, item: ItemID) -> i64 {
+        self.internal_trade.last_day(item) + self.exports.last_day(item)
+    }
+
+    /// Price of the item in the city during the last game day, from its market value.
+    /// Above it when the city imports the item and below when it exports it, by up to
+    /// LOCAL_PRICE_SPREAD.
+    pub fn local_price(&self, item: ItemID, ext_value: Money) -> Money {
+        let imports = self.imports.last_day(item);
+        let exports = self.exports.last_day(item);
+        let total = imports + exports + self.internal_trade.last_day(item);
+        if total == 0 {
+            return ext_value;
+        }
+        let tension = (imports - exports) as f64 / total as f64;
+        ext_value * (1.0 + LOCAL_PRICE_SPREAD * tension)
+    }
+}
+
+/// City-wide counters shown in the statistics window, scenario objectives read the same ones
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CityStats {
+    pub population: u32,
+    /// Average duration of the last trip to work of every worker, in game minutes
+    pub avg_commute_minutes: Option<f32>,
+    /// Share of the population owning a car
+    pub car_ownership: Option<f32>,
+    /// Share of the population without a job
+    pub unemployment_rate: Option<f32>,
+}
+
+impl CityStats {
+    pub fn new(world: &World) -> Self {
+        let (n_commutes, total_minutes) = world
+            .humans
+            .values()
+            .filter_map(|h| h.work.as_ref()?.last_commute)
+            .fold((0, 0.0), |(n, total), d| (n + 1, total + d.minutes()));
+
+        let n_cars = world
+            .humans
+            .values()
+            .filter(|h| h.router.personal_car.is_some())
+            .count();
+
+        let n_unemployed = world.humans.values().filter(|h| h.work.is_none()).count();
+
+        Self {
+            population: world.humans.len() as u32,
+            avg_commute_minutes: (n_commutes > 0)
+                .then(|| (total_minutes / n_commutes as f64) as f32),
+            car_ownership: (!world.humans.is_empty())
+                .then(|| n_cars as f32 / world.humans.len() as f32),
+            unemployment_rate: (!world.humans.is_empty())
+                .then(|| n_unemployed as f32 / world.humans.len() as f32),
+        }
+    }
+}
+
+/// Seconds a service vehicle may take to get somewhere for it to count as covered
+pub const COVERAGE_RESPONSE_SECONDS: f32 = 180.0;
+
+/// The city counters restricted to a district, see [`CityStats`]
+#[derive(Debug, Default, Clone)]
+pub struct DistrictStats {
+    pub buildings: u32,
+    /// Citizens living in the district
+    pub population: u32,
+    /// Citizens working in the district
+    pub jobs: u32,
+    /// Vehicles driving on the roads of the district right now
+    pub traffic: u32,
+    /// Average walk score of the scored residences of the district, None without any
+    pub land_value: Option<f32>,
+    /// Share of the intersections of the district a service reaches in time, None without any
+    pub coverage: Vec<(ServiceKind, Option<f32>)>,
+}
+
+impl DistrictStats {
+    /// Stats of every district, computed in one pass over the city
+    pub fn all(sim: &Simulation) -> BTreeMap<DistrictID, DistrictStats> {
+        let map = sim.map();
+        let world = sim.world();
+        let mut stats: BTreeMap<DistrictID, DistrictStats> = map
+            .districts()
+            .keys()
+            .map(|id| (id, DistrictStats::default()))
+            .collect();
+        if stats.is_empty() {
+            return stats;
+        }
+
+        let buildings: BTreeMap<BuildingID, DistrictID> = map
+            .buildings()
+            .keys()
+            .filter_map(|b| Some((b, map.building_district(b)?)))
+            .collect();
+        for d in buildings.values() {
+            stats.entry(*d).or_default().buildings += 1;
+        }
+
+        let walkability = sim.read::<Walkability>();
+        let mut scores: BTreeMap<DistrictID, (f32, u32)> = BTreeMap::new();
+        for (b, d) in &buildings {
+            if let Some(score) = walkability.score(*b) {
+                let (sum, n) = scores.entry(*d).or_default();
+                *sum += score.total;
+                *n += 1;
+            }
+        }
+        drop(walkability);
+        for (d, (sum, n)) in scores {
+            stats.entry(d).or_default().land_value = Some(sum / n as f32);
+        }
+
+        for h in world.humans.values() {
+            if let Some(d) = buildings.get(&h.home.house) {
+                stats.entry(*d).or_default().population += 1;
+            }
+            let Some(d) = h.work.as_ref().and_then(|w| buildings.get(&w.workplace)) else {
+                continue;
+            };
+            stats.entry(*d).or_default().jobs += 1;
+        }
+
+        let roads: BTreeMap<RoadID, DistrictID> = map
+            .roads()
+            .keys()
+            .filter_map(|r| Some((r, map.road_district(r)?)))
+            .collect();
+        for v in world.vehicles.values() {
+            let Some(Traversable {
+                kind: TraverseKind::Lane(lane),
+                ..
+            }) = v.it.get_travers()
+            else {
+                continue;
+            };
+            let Some(d) = map.lanes().get(*lane).and_then(|l| roads.get(&l.parent)) else {
+                continue;
+            };
+            stats.entry(*d).or_default().traffic += 1;
+        }
+
+        let inters: Vec<_> = map
+            .intersections()
+            .iter()
+            .filter_map(|(id, i)| Some((id, map.district_at(i.pos.xy())?)))
+            .collect();
+        let fleets = sim.read::<ServiceFleets>();
+        for service in ServiceKind::ALL {
+            let times = coverage(&map, &fleets, service);
+            let mut counts: BTreeMap<DistrictID, (u32, u32)> = BTreeMap::new();
+            for (id, d) in &inters {
+                let (total, reached) = counts.entry(*d).or_default();
+                *total += 1;
+                if times
+                    .get(id)
+                    .is_some_and(|t| *t <= COVERAGE_RESPONSE_SECONDS)
+                {
+                    *reached += 1;
+                }
+            }
+            for (d, s) in stats.iter_mut() {
+                let share = counts
+                    .get(d)
+                    .map(|&(total, reached)| reached as f32 / total as f32);
+                s.coverage.push((service, share));
+            }
+        }
+
+        stats
+    }
+}
+
+/// Number of trips started during each hour of the day, shows the rush hours
+#[derive(Default, Serialize, Deserialize)]
+pub struct TripStats {
+    day: i32,
+    /// Trips started today, by hour
+    pub today: [u32; HOURS_PER_DAY as usize],
+    /// Trips started during the previous day, by hour
+    pub last_day: [u32; HOURS_PER_DAY as usize],
+}
+
+impl TripStats {
+    /// Moves today's counters to last_day when the day changes
+    pub fn advance(&mut self, time: &DayTime) {
+        if time.day == self.day {
+            return;
+        }
+        self.last_day = if time.day == self.day + 1 {
+            self.today
+        } else {
+            [0; HOURS_PER_DAY as usize]
+        };
+        self.today = [0; HOURS_PER_DAY as usize];
+        self.day = time.day;
+    }
+
+    pub fn record(&mut self, time: &DayTime) {
+        self.advance(time);
+        self.today[time.hour as usize] += 1;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::economy::HISTORY_SIZE;
@@ -1,9 +1,21 @@
-use crate::map::{LanePattern, MapProject, MAX_ZONE_AREA};
+use crate::map::{
+    Environment, LaneKind, LanePattern, MapProject, Road, RoadSegmentKind, MAX_ZONE_AREA,
+};
+use crate::rules::GameRules;
+use crate::souls::goods_company::company_upgrade;
+use crate::transportation::service_fleet::ServiceFleets;
 use crate::world_command::WorldCommand;
 use crate::{BuildingKind, Simulation};
-use prototypes::Money;
+use geom::{PolyLine3, Vec2};
+use prototypes::{Money, RoadPrototypeID};
 use serde::{Deserialize, Serialize};
 
+/// Share of the construction cost given back when a road is bulldozed
+pub const BULLDOZE_REFUND: f64 = 0.5;
+
+/// Height above (or below) the terrain after which a road is considered a bridge (or a tunnel)
+const ELEVATED_THRESHOLD: f32 = 3.0;
+
 /// The government represents the player.
 #[derive(Serialize, Deserialize)]
 pub struct Government {
@@ -18,13 +30,91 @@ impl Default for Government {
     }
 }
 
-impl Government {
-    pub fn action_cost(action: &WorldCommand, sim: &Simulation) -> Money {
-        Money::new_bucks(match action {
+impl WorldCommand {
+    /// Money the government pays to apply this command, negative if it is refunded.
+    /// Only depends on the simulation state so that every client agrees on it.
+    pub fn cost(&self, sim: &Simulation) -> Money {
+        let rules = sim.read::<GameRules>();
+        let (road_mult, building_mult) = (rules.road_cost as f64, rules.building_cost as f64);
+        drop(rules);
+
+        match self {
+            WorldCommand::MapMakeConnection { .. }
+            | WorldCommand::MapMakeMultipleConnections(..)
+            | WorldCommand::MapRemoveRoad(_)
+            | WorldCommand::MapRemoveIntersection(_) => self.base_cost(sim) * road_mult,
+            WorldCommand::MapBuildSpecialBuilding { .. } | WorldCommand::UpgradeBuilding(_) => {
+                self.base_cost(sim) * building_mult
+            }
+            _ => self.base_cost(sim),
+        }
+    }
+
+    /// Cost before applying the multipliers of the game rules
+    fn base_cost(&self, sim: &Simulation) -> Money {
+        Money::new_bucks(match self {
             WorldCommand::MapBuildHouse(_) => 100,
             WorldCommand::AddTrain { n_wagons, .. } => 1000 + 100 * (*n_wagons as i64),
-            WorldCommand::MapMakeConnection { from, to, pat, .. } => {
-                Self::connection_cost(from, to, pat)
+            WorldCommand::MapMakeConnection {
+                from,
+                to,
+                inter,
+                pat,
+            } => {
+                return Government::connection_cost(from, to, *inter, pat, &sim.map().environment);
+            }
+            WorldCommand::MapMakeMultipleConnections(ref projs, ref links) => {
+                let m = sim.map();
+                let env = &m.environment;
+                return links
+                    .iter()
+                    .map(|(from, to, inter, pat)| {
+                        Government::connection_cost(&projs[*from], &projs[*to], *inter, pat, env)
+                    })
+                    .sum();
+            }
+            WorldCommand::MapRemoveRoad(id) => {
+                let m = sim.map();
+                let Some(r) = m.roads().get(*id) else {
+                    return Money::ZERO;
+                };
+                return -Government::refund(Government::built_road_cost(r, &m.environment));
+            }
+            WorldCommand::MapRemoveIntersection(id) => {
+                let m = sim.map();
+                let Some(i) = m.intersections().get(*id) else {
+                    return Money::ZERO;
+                };
+                return -i
+                    .roads
+                    .iter()
+                    .filter_map(|r| m.roads().get(*r))
+                    .map(|r| Government::refund(Government::built_road_cost(r, &m.environment)))
+                    .sum::<Money>();
+            }
+            WorldCommand::UpgradeBuilding(id) => {
+                return company_upgrade(sim, *id).map_or(Money::ZERO, |(up, _)| up.price);
+            }
+            WorldCommand::BuyServiceVehicle(id) => {
+                let fleets = sim.read::<ServiceFleets>();
+                let Some(depot) = fleets.depots.get(id) else {
+                    return Money::ZERO;
+                };
+                let proto = depot.proto.prototype();
+                if depot.vehicles.len() >= proto.max_fleet as usize {
+                    return Money::ZERO;
+                }
+                return proto.vehicle_price;
+            }
+            WorldCommand::SellServiceVehicle(id) => {
+                let fleets = sim.read::<ServiceFleets>();
+                let Some(depot) = fleets.depots.get(id) else {
+                    return Money::ZERO;
+                };
+                if depot.n_idle() == 0 {
+                    return Money::ZERO;
+                }
+                return -Government::refund(depot.proto.prototype().vehicle_price);
             }
             WorldCommand::UpdateZone {
                 building: bid,
@@ -49,13 +139,6 @@ impl Government {
                 return (newarea - oldarea) as i64 * zonedescr.price_per_area
                     / MAX_ZONE_AREA as i64;
             }
-            WorldCommand::MapMakeMultipleConnections(ref projs, ref links) => {
-                let mut total = 0;
-                for (from, to, _, pat) in links.iter() {
-                    total += Self::connection_cost(&projs[*from], &projs[*to], pat);
-                }
-                total
-            }
             WorldCommand::MapBuildSpecialBuilding { kind: x, .. } => match x {
                 BuildingKind::GoodsCompany(x) => {
                     let descr = x.prototype();
@@ -68,16 +151,175 @@ impl Government {
                 BuildingKind::RailFreightStation(x) => {
                     return x.prototype().price;
                 }
+                BuildingKind::Dock(x) => {
+                    return x.prototype().price;
+                }
+                BuildingKind::Warehouse(x) => {
+                    return x.prototype().price;
+                }
+                BuildingKind::ServiceDepot(x) => {
+                    return x.prototype().price;
+                }
+                BuildingKind::Leisure(x) => {
+                    return x.prototype().price;
+                }
                 BuildingKind::TrainStation => 1000,
                 _ => 0,
             },
             _ => 0,
         })
     }
+}
+
+impl Government {
+    /// Whether the government can afford to apply the command
+    pub fn can_afford(&self, action: &WorldCommand, sim: &Simulation) -> bool {
+        let cost = action.cost(sim);
+        cost <= Money::ZERO || cost <= self.money
+    }
+
+    fn refund(cost: Money) -> Money {
+        cost * BULLDOZE_REFUND
+    }
+
+    fn connection_cost(
+        from: &MapProject,
+        to: &MapProject,
+        inter: Option<Vec2>,
+        pat: &LanePattern,
+        env: &Environment,
+    ) -> Money {
+        let segment = match inter {
+            Some(x) => RoadSegmentKind::from_elbow(from.pos.xy(), to.pos.xy(), x),
+            None => RoadSegmentKind::Straight,
+        };
+        let is_rail = pat.lanes().any(|(kind, _, _)| kind.is_rail());
+        let (points, _) = Road::generate_points(from.pos, to.pos, segment, is_rail, env);
+
+        Self::road_cost(&points, pat.lanes().map(|(kind, _, _)| kind), env)
+    }
+
+    pub(crate) fn built_road_cost(road: &Road, env: &Environment) -> Money {
+        Self::road_cost(road.points(), road.lanes_iter().map(|(_, kind)| kind), env)
+    }
+
+    /// Price of the lanes laid along the points, using the road and rail prototypes.
+    /// Parts above or below the terrain are priced as bridges or tunnels.
+    pub fn road_cost(
+        points: &PolyLine3,
+        lanes: impl Iterator<Item = LaneKind>,
+        env: &Environment,
+    ) -> Money {
+        let (mut n_rail, mut n_road) = (0, 0);
+        for kind in lanes {
+            if kind.is_rail() {
+                n_rail += 1;
+            } else {
+                n_road += 1;
+            }
+        }
+
+        let (mut ground, mut bridge, mut tunnel) = (0.0, 0.0, 0.0);
+        for seg in points.segments() {
+            let len = seg.src.distance(seg.dst) as f64;
+            let mid = (seg.src + seg.dst) * 0.5;
+            match env.true_height(mid.xy()) {
+                Some(h) if mid.z - h > ELEVATED_THRESHOLD => bridge += len,
+                Some(h) if h - mid.z > ELEVATED_THRESHOLD => tunnel += len,
+                _ => ground += len,
+            }
+        }
+
+        [("road", n_road), ("rail", n_rail)]
+            .into_iter()
+            .filter(|(_, n)| *n > 0)
+            .map(|(name, n)| {
+                let proto = RoadPrototypeID::new(name).prototype();
+                let meters = ground
+                    + bridge * proto.bridge_multiplier as f64
+                    + tunnel * proto.tunnel_multiplier as f64;
+                proto.price_per_meter * n * meters
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec3;
+    use prototypes::Money;
+
+    use crate::economy::Government;
+    use crate::map::{LanePatternBuilder, MapProject};
+    use crate::multiplayer::chat::MessageKind;
+    use crate::multiplayer::MultiplayerState;
+    use crate::rules::GameRules;
+    use crate::tests::TestCtx;
+    use crate::WorldCommand;
+
+    fn connection() -> WorldCommand {
+        WorldCommand::MapMakeConnection {
+            from: MapProject::ground(vec3(0.0, 0.0, 0.0)),
+            to: MapProject::ground(vec3(100.0, 0.0, 0.0)),
+            inter: None,
+            pat: LanePatternBuilder::default().build(),
+        }
+    }
+
+    #[test]
+    fn road_is_paid_and_partially_refunded() {
+        let mut test = TestCtx::new();
+        let before = test.g.read::<Government>().money;
+        let existing: Vec<_> = test.g.map().roads().keys().collect();
+
+        let cmd = connection();
+        let cost = cmd.cost(&test.g);
+        assert!(cost > Money::ZERO);
+
+        test.apply(&[cmd]);
+        let after_build = test.g.read::<Government>().money;
+        assert_eq!(after_build, before - cost);
+
+        let road = test
+            .g
+            .map()
+            .roads()
+            .keys()
+            .find(|r| !existing.contains(r))
+            .unwrap();
+        test.apply(&[WorldCommand::MapRemoveRoad(road)]);
+        let after_remove = test.g.read::<Government>().money;
+        assert!(after_remove > after_build);
+        assert!(after_remove < before);
+    }
+
+    #[test]
+    fn free_roads_rule() {
+        let mut test = TestCtx::new();
+        test.g.write::<GameRules>().road_cost = 0.0;
+        let before = test.g.read::<Government>().money;
+
+        test.apply(&[connection()]);
+
+        assert_eq!(test.g.read::<Government>().money, before);
+    }
+
+    #[test]
+    fn road_is_rejected_without_money() {
+        let mut test = TestCtx::new();
+        test.g.write::<Government>().money = Money::ZERO;
+        let n_roads = test.g.map().roads().len();
+
+        test.apply(&[connection()]);
 
-    fn connection_cost(p1: &MapProject, p2: &MapProject, pat: &LanePattern) -> i64 {
-        let dist = p1.pos.distance(p2.pos);
-        50 + ((0.03 * dist) as i64).max(1)
-            * (pat.lanes_forward.len() + pat.lanes_backward.len()) as i64
+        assert_eq!(test.g.map().roads().len(), n_roads);
+        assert_eq!(test.g.read::<Government>().money, Money::ZERO);
+        assert!(test
+            .g
+            .read::<MultiplayerState>()
+            .chat
+            .messages
+            .iter()
+            .any(|m| matches!(m.kind, MessageKind::Warning)));
     }
 }
@@ -0,0 +1,192 @@
+//! Standing orders the government places on the market to buy or sell goods.
+//! They are matched against the orders of the companies and households in [`Market::make_trades`],
+//! after the souls traded between themselves and before the rest goes to external trading:
+//! the government buys what would have been exported and sells what would have been imported,
+//! so its orders move the local prices like any other trade would.
+//!
+//! [`Market::make_trades`]: crate::economy::Market::make_trades
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use slotmapd::{new_key_type, SlotMap};
+
+use prototypes::{ItemID, Money};
+
+use crate::SoulID;
+
+new_key_type! {
+    pub struct GovernmentOrderID;
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GovernmentOrder {
+    pub item: ItemID,
+    pub side: OrderSide,
+    /// Buys at or below this local price, sells at or above it
+    pub price: Money,
+    /// Quantity after which the order is complete
+    pub qty: u32,
+    pub filled: u32,
+    /// Money paid for a buy order or received for a sell order so far
+    pub total: Money,
+}
+
+impl GovernmentOrder {
+    pub fn remaining(&self) -> u32 {
+        self.qty.saturating_sub(self.filled)
+    }
+
+    /// Whether the order trades at this local price
+    pub fn accepts(&self, price: Money) -> bool {
+        match self.side {
+            OrderSide::Buy => price <= self.price,
+            OrderSide::Sell => price >= self.price,
+        }
+    }
+}
+
+/// Part of a government order filled against the order of a soul
+#[derive(Copy, Clone, Debug)]
+pub struct GovernmentFill {
+    pub order: GovernmentOrderID,
+    pub item: ItemID,
+    pub side: OrderSide,
+    /// The seller of a buy order or the buyer of a sell order
+    pub soul: SoulID,
+    pub qty: u32,
+    /// Per unit
+    pub price: Money,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct GovernmentOrders {
+    pub orders: SlotMap<GovernmentOrderID, GovernmentOrder>,
+    /// Goods bought by the government and not sold yet
+    pub stock: BTreeMap<ItemID, u32>,
+    /// Money earned by selling minus money spent buying today
+    pub balance_today: Money,
+    pub balance_last_day: Money,
+    day: i32,
+    /// Fills of the last matching, booked by the market update
+    #[serde(skip)]
+    pub fills: Vec<GovernmentFill>,
+}
+
+impl GovernmentOrders {
+    /// Places a new standing order, orders for nothing are ignored
+    pub fn place(
+        &mut self,
+        item: ItemID,
+        side: OrderSide,
+        price: Money,
+        qty: u32,
+    ) -> Option<GovernmentOrderID> {
+        if qty == 0 || price <= Money::ZERO {
+            log::warn!(
+                "invalid government order for {} {:?} at {}",
+                qty,
+                item,
+                price
+            );
+            return None;
+        }
+        Some(self.orders.insert(GovernmentOrder {
+            item,
+            side,
+            price,
+            qty,
+            filled: 0,
+            total: Money::ZERO,
+        }))
+    }
+
+    /// Withdraws the order, what was already traded stays traded
+    pub fn cancel(&mut self, id: GovernmentOrderID) -> Option<GovernmentOrder> {
+        self.orders.remove(id)
+    }
+
+    pub fn stock(&self, item: ItemID) -> u32 {
+        self.stock.get(&item).copied().unwrap_or(0)
+    }
+
+    /// Starts a new day of the trading balance
+    pub(crate) fn new_day(&mut self, day: i32) {
+        if self.day == day {
+            return;
+        }
+        self.balance_last_day = if day == self.day + 1 {
+            self.balance_today
+        } else {
+            Money::ZERO
+        };
+        self.balance_today = Money::ZERO;
+        self.day = day;
+    }
+}
+
+/// The standing orders of the government with what is needed to match them
+pub struct GovernmentTrading<'a> {
+    pub orders: &'a mut GovernmentOrders,
+    /// Local price of the traded items, the items without a price are not traded
+    pub prices: BTreeMap<ItemID, Money>,
+    /// Treasury, the buy orders only spend what is left of it
+    pub money: Money,
+}
+
+impl<'a> GovernmentTrading<'a> {
+    /// Records the part of an order that was filled, returns the quantity actually traded
+    pub(crate) fn fill(&mut self, id: GovernmentOrderID, soul: SoulID, available: u32) -> u32 {
+        let Some(order) = self.orders.orders.get_mut(id) else {
+            return 0;
+        };
+        let Some(&price) = self.prices.get(&order.item) else {
+            return 0;
+        };
+        let stock = self.orders.stock.entry(order.item).or_default();
+
+        let mut qty = available.min(order.remaining());
+        match order.side {
+            OrderSide::Buy => {
+                let affordable = self.money.inner().max(0) / price.inner().max(1);
+                qty = qty.min(affordable.min(u32::MAX as i64) as u32);
+            }
+            OrderSide::Sell => qty = qty.min(*stock),
+        }
+        if qty == 0 {
+            return 0;
+        }
+
+        let value = price * qty as i64;
+        match order.side {
+            OrderSide::Buy => {
+                *stock += qty;
+                self.money -= value;
+                self.orders.balance_today -= value;
+            }
+            OrderSide::Sell => {
+                *stock -= qty;
+                self.money += value;
+                self.orders.balance_today += value;
+            }
+        }
+        order.filled += qty;
+        order.total += value;
+
+        self.orders.fills.push(GovernmentFill {
+            order: id,
+            item: order.item,
+            side: order.side,
+            soul,
+            qty,
+            price,
+        });
+        qty
+    }
+}
@@ -5,13 +5,16 @@ use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 
 use geom::Vec2;
-use prototypes::{prototypes_iter, GoodsCompanyID, GoodsCompanyPrototype, ItemPrototype, Money};
+use prototypes::{prototypes_iter, ItemPrototype, Money};
 
-use crate::economy::{ItemID, WORKER_CONSUMPTION_PER_MINUTE};
+use crate::economy::{item_costs, GovernmentOrderID, GovernmentTrading, ItemID, OrderSide};
 use crate::map::BuildingID;
 use crate::map_dynamic::BuildingInfos;
 use crate::SoulID;
 
+/// The external price of an item is its production cost with the wages multiplied by this
+pub(crate) const EXT_PRICE_MULTIPLIER: f32 = 1.25;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SellOrder {
     pub pos: Vec2,
@@ -60,6 +63,14 @@ impl SingleMarket {
     pub fn capital_map(&self) -> &BTreeMap<SoulID, i32> {
         &self.capital
     }
+
+    pub fn buy_orders(&self) -> &BTreeMap<SoulID, BuyOrder> {
+        &self.buy_orders
+    }
+
+    pub fn sell_orders(&self) -> &BTreeMap<SoulID, SellOrder> {
+        &self.sell_orders
+    }
 }
 
 /// Market handles good exchanging between souls themselves and the external market.
@@ -96,7 +107,7 @@ pub fn find_trade_place(target: TradeTarget, binfos: &BuildingInfos) -> Option<B
 
 impl Default for Market {
     fn default() -> Self {
-        let prices = calculate_prices(1.25);
+        let prices = calculate_prices(EXT_PRICE_MULTIPLIER);
         Self {
             markets: prototypes_iter::<ItemPrototype>()
                 .map(|v| (v.id, SingleMarket::new(prices[&v.id], v.optout_exttrade)))
@@ -108,6 +119,40 @@ impl Default for Market {
 }
 
 impl Market {
+    /// Follows a reload of the prototypes: the new items get a market and the markets of the
+    /// removed items are dropped. A reload cannot remove an item in use, see
+    /// [`crate::Simulation::pinned_prototypes`].
+    pub fn sync_items(&mut self) {
+        let prices = calculate_prices(EXT_PRICE_MULTIPLIER);
+        self.sync_with(
+            prototypes_iter::<ItemPrototype>()
+                .map(|v| (v.id, prices[&v.id], v.optout_exttrade))
+                .collect(),
+        );
+    }
+
+    /// Keeps the markets of the items with their price and trade opt-out, adding the missing ones
+    fn sync_with(&mut self, items: BTreeMap<ItemID, (Money, bool)>) {
+        self.markets.retain(|id, _| items.contains_key(id));
+        for (id, (price, optout_exttrade)) in items {
+            self.markets
+                .entry(id)
+                .or_insert_with(|| SingleMarket::new(price, optout_exttrade));
+        }
+    }
+
+    /// The items someone owns or has an order for
+    pub fn items_in_use(&self) -> impl Iterator<Item = ItemID> + '_ {
+        self.markets
+            .iter()
+            .filter(|(_, m)| {
+                m.capital.values().any(|&c| c != 0)
+                    || !m.buy_orders.is_empty()
+                    || !m.sell_orders.is_empty()
+            })
+            .map(|(&id, _)| id)
+    }
+
     pub fn m(&mut self, kind: ItemID) -> &mut SingleMarket {
         self.markets.get_mut(&kind).unwrap()
     }
@@ -148,6 +193,13 @@ impl Market {
         }
     }
 
+    /// Withdraws the buy and sell orders of the agent for the good, its capital is kept
+    pub fn cancel(&mut self, soul: SoulID, kind: ItemID) {
+        let m = self.m(kind);
+        m.buy_orders.remove(&soul);
+        m.sell_orders.remove(&soul);
+    }
+
     /// Called when an agent tells the world it wants to buy something
     /// If an order is already placed, it will be updated.
     pub fn buy(&mut self, soul: SoulID, near: Vec2, kind: ItemID, qty: u32) {
@@ -189,8 +241,13 @@ impl Market {
     /// Returns a list of buy and sell orders matched together.
     /// A trade updates the buy and sell orders from the market, and the capital of the buyers and sellers.
     /// A trade can only be completed if the seller has enough capital.
+    /// The orders left are then filled by the government orders, the rest goes to external trading.
     /// Please do not keep the trades around much, it needs to be destroyed by the next time you call this function.
-    pub fn make_trades(&mut self, find_external: impl Fn(Vec2) -> Option<SoulID>) -> &[Trade] {
+    pub fn make_trades(
+        &mut self,
+        gov: &mut GovernmentTrading,
+        find_external: impl Fn(Vec2) -> Option<SoulID>,
+    ) -> &[Trade] {
         self.all_trades.clear();
 
         for (&kind, market) in &mut self.markets {
@@ -281,6 +338,8 @@ impl Market {
                     Some(trade)
                 }));
 
+            fill_government_orders(kind, buy_orders, sell_orders, capital, gov);
+
             // External trading
             if !*optout_exttrade {
                 // All buyers can fullfil since they can buy externally
@@ -340,73 +399,68 @@ impl Market {
     }
 }
 
-fn calculate_prices(price_multiplier: f32) -> BTreeMap<ItemID, Money> {
-    let mut item_graph: BTreeMap<ItemID, Vec<GoodsCompanyID>> = BTreeMap::new();
-    for company in GoodsCompanyPrototype::iter() {
-        let Some(ref recipe) = company.recipe else {
-            continue;
-        };
-        for item in &recipe.production {
-            item_graph.entry(item.id).or_default().push(company.id);
-        }
-    }
-
-    let mut prices = BTreeMap::new();
-    fn calculate_price_inner(
-        item_graph: &BTreeMap<ItemID, Vec<GoodsCompanyID>>,
-        id: ItemID,
-        prices: &mut BTreeMap<ItemID, Money>,
-        price_multiplier: f32,
-    ) {
-        if prices.contains_key(&id) {
-            return;
-        }
-
-        let mut minprice = None;
-        for &comp in item_graph.get(&id).unwrap_or(&vec![]) {
-            let company = &comp.prototype();
-            let mut price_consumption = Money::ZERO;
-            let Some(ref recipe) = company.recipe else {
-                continue;
-            };
-            for recipe_item in &recipe.consumption {
-                calculate_price_inner(item_graph, recipe_item.id, prices, price_multiplier);
-                price_consumption += prices[&recipe_item.id] * recipe_item.amount as i64;
-            }
-            let qty = recipe
-                .production
-                .iter()
-                .find_map(|x| (x.id == id).then_some(x.amount))
-                .unwrap_or(0) as i64;
-
-            let price_workers = recipe.duration.minutes()
-                * company.n_workers as f64
-                * WORKER_CONSUMPTION_PER_MINUTE;
-
-            let newprice = (price_consumption
-                + Money::new_inner((price_workers.inner() as f32 * price_multiplier) as i64))
-                / qty;
-
-            minprice = minprice.map(|x: Money| x.min(newprice)).or(Some(newprice));
+/// Fills the government orders for the item with the orders the souls left, in part when needed
+/// on both sides. Buy orders take from the sell orders and sell orders give to the buy orders.
+fn fill_government_orders(
+    kind: ItemID,
+    buy_orders: &mut BTreeMap<SoulID, BuyOrder>,
+    sell_orders: &mut BTreeMap<SoulID, SellOrder>,
+    capital: &mut BTreeMap<SoulID, i32>,
+    gov: &mut GovernmentTrading,
+) {
+    let Some(&price) = gov.prices.get(&kind) else {
+        return;
+    };
+    let ids: Vec<GovernmentOrderID> = gov
+        .orders
+        .orders
+        .iter()
+        .filter(|(_, o)| o.item == kind && o.accepts(price))
+        .map(|(id, _)| id)
+        .collect();
+
+    for id in ids {
+        match gov.orders.orders[id].side {
+            OrderSide::Buy => sell_orders.retain(|&seller, order| {
+                let cap = capital.entry(seller).or_default();
+                let qty = gov.fill(id, seller, order.qty.min((*cap).max(0) as u32));
+                order.qty -= qty;
+                *cap -= qty as i32;
+                order.qty > 0
+            }),
+            // citizens wait for a trade to know where to pick up what they bought,
+            // so the government only sells to the companies which get their goods as capital
+            OrderSide::Sell => buy_orders.retain(|&buyer, order| {
+                if matches!(buyer, SoulID::Human(_)) {
+                    return true;
+                }
+                let qty = gov.fill(id, buyer, order.qty);
+                order.qty -= qty;
+                *capital.entry(buyer).or_default() += qty as i32;
+                order.qty > 0
+            }),
         }
-
-        prices.insert(id, minprice.unwrap_or(Money::ZERO));
-    }
-
-    for item in ItemPrototype::iter() {
-        calculate_price_inner(&item_graph, item.id, &mut prices, price_multiplier);
     }
+}
 
-    prices
+fn calculate_prices(price_multiplier: f32) -> BTreeMap<ItemID, Money> {
+    item_costs(price_multiplier)
+        .into_iter()
+        .map(|(id, cost)| (id, cost.per_unit))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use geom::{vec2, Vec2};
     use prototypes::test_prototypes;
-    use prototypes::ItemID;
+    use prototypes::{ItemID, Money};
 
-    use crate::economy::WORKER_CONSUMPTION_PER_MINUTE;
+    use crate::economy::{
+        GovernmentOrders, GovernmentTrading, OrderSide, WORKER_CONSUMPTION_PER_MINUTE,
+    };
     use crate::world::CompanyID;
     use crate::{FreightStationID, SoulID};
 
@@ -453,7 +507,13 @@ mod tests {
         m.sell(seller, Vec2::X, cereal, 3, 5);
         m.sell(seller_far, vec2(10.0, 10.0), cereal, 3, 5);
 
-        let trades = m.make_trades(|_| Some(freight));
+        let mut orders = GovernmentOrders::default();
+        let mut gov = GovernmentTrading {
+            orders: &mut orders,
+            prices: BTreeMap::new(),
+            money: Money::ZERO,
+        };
+        let trades = m.make_trades(&mut gov, |_| Some(freight));
 
         assert_eq!(trades.len(), 1);
         let t0 = trades[0];
@@ -462,6 +522,105 @@ mod tests {
         assert_eq!(t0.qty, 2);
     }
 
+    #[test]
+    fn government_orders_fill_in_part() {
+        let seller = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+        let seller2 = SoulID::GoodsCompany(mk_ent((1 << 32) | 2));
+        let buyer = SoulID::GoodsCompany(mk_ent((1 << 32) | 3));
+
+        test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+
+        let mut m = Market::default();
+        let cereal = ItemID::new("cereal");
+        let price = Money::new_bucks(4);
+
+        let mut orders = GovernmentOrders::default();
+        let buy = orders
+            .place(cereal, OrderSide::Buy, Money::new_bucks(5), 12)
+            .unwrap();
+        let too_cheap = orders
+            .place(cereal, OrderSide::Buy, Money::new_bucks(3), 12)
+            .unwrap();
+
+        m.produce(seller, cereal, 10);
+        m.produce(seller2, cereal, 4);
+        m.sell(seller, Vec2::ZERO, cereal, 10, 10);
+        m.sell(seller2, Vec2::ZERO, cereal, 4, 4);
+
+        let mut gov = GovernmentTrading {
+            orders: &mut orders,
+            prices: BTreeMap::from([(cereal, price)]),
+            money: Money::new_bucks(1000),
+        };
+        m.make_trades(&mut gov, |_| None);
+        assert_eq!(gov.money, Money::new_bucks(1000 - 12 * 4));
+
+        // the first seller is emptied, the second one keeps selling what is left
+        assert_eq!(m.capital(seller, cereal), 0);
+        assert_eq!(m.capital(seller2, cereal), 2);
+        assert_eq!(m.inner()[&cereal].sell_order(seller2).unwrap().qty, 2);
+        assert_eq!(orders.orders[buy].remaining(), 0);
+        assert_eq!(orders.orders[too_cheap].filled, 0);
+        assert_eq!(orders.stock(cereal), 12);
+        assert_eq!(orders.fills.len(), 2);
+        assert_eq!(orders.balance_today, -Money::new_bucks(48));
+
+        // selling fills part of a bigger buy order, the rest is imported
+        let sell = orders
+            .place(cereal, OrderSide::Sell, Money::new_bucks(2), 3)
+            .unwrap();
+        orders.fills.clear();
+        m.buy(buyer, Vec2::ZERO, cereal, 5);
+        let mut gov = GovernmentTrading {
+            orders: &mut orders,
+            prices: BTreeMap::from([(cereal, price)]),
+            money: Money::ZERO,
+        };
+        m.make_trades(&mut gov, |_| None);
+        assert_eq!(gov.money, Money::new_bucks(12));
+        assert_eq!(m.capital(buyer, cereal), 5);
+        assert_eq!(orders.orders[sell].filled, 3);
+        assert_eq!(orders.stock(cereal), 9);
+        assert_eq!(orders.fills[0].soul, buyer);
+        assert_eq!(orders.fills[0].qty, 3);
+    }
+
+    #[test]
+    fn markets_follow_the_items() {
+        let cereal = ItemID::new("cereal");
+        let flour = ItemID::new("flour");
+        let soul = SoulID::GoodsCompany(mk_ent((1 << 32) | 1));
+        let mut m = Market {
+            markets: BTreeMap::new(),
+            all_trades: Default::default(),
+            potential: Default::default(),
+        };
+        m.sync_with(BTreeMap::from([(cereal, (Money::new_bucks(1), false))]));
+        m.produce(soul, cereal, 3);
+        assert_eq!(m.items_in_use().collect::<Vec<_>>(), [cereal]);
+
+        // the markets already there keep their capital
+        m.sync_with(BTreeMap::from([
+            (cereal, (Money::new_bucks(2), false)),
+            (flour, (Money::new_bucks(3), false)),
+        ]));
+        assert_eq!(m.capital(soul, cereal), 3);
+        assert_eq!(m.capital(soul, flour), 0);
+
+        m.sync_with(BTreeMap::from([(flour, (Money::new_bucks(3), false))]));
+        assert!(m.iter().all(|(&id, _)| id == flour));
+    }
+
     #[test]
     fn calculate_prices() {
         test_prototypes(
@@ -16,18 +16,28 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fmt::Debug;
 
+mod balance;
 mod ecostats;
 mod government;
+mod government_orders;
 mod market;
+mod trade_ledger;
+mod zone_demand;
 
 use crate::map::Map;
+use crate::map_dynamic::BuildingInfos;
+use crate::souls::household::Households;
 use crate::world::HumanID;
+pub use balance::*;
 pub use ecostats::*;
 pub use government::*;
+pub use government_orders::*;
 pub use market::*;
 use prototypes::{GameTime, ItemID, Money, TICKS_PER_MINUTE};
+pub use trade_ledger::*;
+pub use zone_demand::*;
 
-const WORKER_CONSUMPTION_PER_MINUTE: Money = Money::new_cents(10);
+pub(crate) const WORKER_CONSUMPTION_PER_MINUTE: Money = Money::new_cents(10);
 
 #[derive(Inspect, Default, Serialize, Deserialize)]
 pub struct Sold(pub Vec<Trade>);
@@ -46,27 +56,71 @@ pub fn market_update(world: &mut World, resources: &mut Resources) {
     let job_opening = ItemID::new("job-opening");
     let mut gvt = resources.write::<Government>();
     let tick = resources.read::<GameTime>().tick;
+    let mut zone_demand = resources.write::<ZoneDemand>();
+    let mut ledger = resources.write::<TradeLedger>();
+    let binfos = resources.read::<BuildingInfos>();
+    let mut households = resources.write::<Households>();
+    let mut gov_orders = resources.write::<GovernmentOrders>();
+    gov_orders.new_day(resources.read::<GameTime>().daytime.day);
+    gov_orders.fills.clear();
 
     if tick.0 % TICKS_PER_MINUTE == 0 {
         gvt.money -= n_workers as i64 * WORKER_CONSUMPTION_PER_MINUTE;
+
+        // the same wages, as seen by the companies' books
+        for c in world.companies.values_mut() {
+            c.comp.finances.today -= WORKER_CONSUMPTION_PER_MINUTE * c.workers.0.len() as i64;
+        }
     }
 
+    // market values of the goods, to book the trades in the companies' profit and loss
+    let values: BTreeMap<ItemID, Money> = m.iter().map(|(&id, m)| (id, m.ext_value)).collect();
+
     let freights = &world.freight_stations;
+    let docks = &world.docks;
 
+    // goods go in and out of the city through the nearest freight station or dock
     let map = resources.read::<Map>();
-    let trades = m.make_trades(|pos| {
-        freights
+    let wanted = ZoneDemand::wanted(&m, job_opening);
+    // the government trades at the local prices
+    let prices = {
+        let ecostats = resources.read::<EcoStats>();
+        gov_orders
+            .orders
+            .values()
+            .filter_map(|o| {
+                let ext_value = *values.get(&o.item)?;
+                Some((o.item, ecostats.local_price(o.item, ext_value)))
+            })
+            .collect()
+    };
+    let mut trading = GovernmentTrading {
+        orders: &mut gov_orders,
+        prices,
+        money: gvt.money,
+    };
+    let trades = m.make_trades(&mut trading, |pos| {
+        let gateways = freights
             .iter()
+            .map(|(id, f)| (SoulID::FreightStation(id), f.f.building))
+            .chain(docks.iter().map(|(id, d)| (SoulID::Dock(id), d.d.building)));
+        gateways
             .min_by_key(|(_, b)| {
-                let Some(b) = map.buildings.get(b.f.building) else {
+                let Some(b) = map.buildings.get(*b) else {
                     return OrderedFloat(f32::INFINITY);
                 };
                 OrderedFloat(b.door_pos.xy().distance2(pos))
             })
-            .map(|(id, _)| SoulID::FreightStation(id))
+            .map(|(soul, _)| soul)
     });
+    gvt.money = trading.money;
 
     resources.write::<EcoStats>().advance(tick.0, trades);
+    zone_demand.update_goods(wanted, trades, job_opening);
+
+    if tick.0 % TICKS_PER_MINUTE == 0 {
+        ledger.prune(tick);
+    }
 
     for &trade in trades.iter() {
         log::debug!("A trade was made! {:?}", trade);
@@ -79,9 +133,15 @@ pub fn market_update(world: &mut World, resources: &mut Resources) {
         }
         gvt.money += trade.money_delta;
 
+        if trade.kind != job_opening {
+            ledger.record(tick, &trade, &binfos);
+        }
+
         if let SoulID::GoodsCompany(id) = trade.seller.0 {
             if trade.kind != job_opening {
-                world.companies.get_mut(id).unwrap().sold.0.push(trade);
+                let c = world.companies.get_mut(id).unwrap();
+                c.sold.0.push(trade);
+                c.comp.finances.today += values[&trade.kind] * trade.qty as i64;
             }
         }
 
@@ -89,14 +149,41 @@ pub fn market_update(world: &mut World, resources: &mut Resources) {
             SoulID::Human(id) => {
                 if let Some(h) = world.humans.get_mut(id) {
                     h.bought.0.entry(trade.kind).or_default().push(trade);
+                    if trade.kind != job_opening {
+                        if let Some(household) = households.get_mut(h.home.house) {
+                            household.money -= values[&trade.kind] * trade.qty as i64;
+                        }
+                    }
                 }
             }
             SoulID::GoodsCompany(id) => {
                 if let Some(c) = world.companies.get_mut(id) {
-                    c.bought.0.entry(trade.kind).or_default().push(trade)
+                    c.bought.0.entry(trade.kind).or_default().push(trade);
+                    c.comp.finances.today -= values[&trade.kind] * trade.qty as i64;
                 }
             }
-            SoulID::FreightStation(_) => {}
+            SoulID::FreightStation(_) | SoulID::Dock(_) | SoulID::Warehouse(_) => {}
         }
     }
+
+    // the companies trading with the government pay or get paid like for any other trade
+    for fill in &gov_orders.fills {
+        let value = fill.price * fill.qty as i64;
+        match (fill.side, fill.soul) {
+            (OrderSide::Buy, SoulID::GoodsCompany(id)) => {
+                if let Some(c) = world.companies.get_mut(id) {
+                    c.comp.finances.today += value;
+                }
+            }
+            (OrderSide::Sell, SoulID::GoodsCompany(id)) => {
+                if let Some(c) = world.companies.get_mut(id) {
+                    c.comp.finances.today -= value;
+                }
+            }
+            _ => {}
+        }
+    }
+    gov_orders.orders.retain(|_, o| o.remaining() > 0);
+
+    zone_demand.update_jobs(&m, job_opening);
 }
@@ -0,0 +1,374 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+use slotmapd::SecondaryMap;
+
+use prototypes::{ItemID, Tick, HOURS_PER_DAY, TICKS_PER_HOUR};
+
+use crate::economy::Trade;
+use crate::map::{BuildingID, BuildingKind, Map};
+use crate::map_dynamic::BuildingInfos;
+
+/// Maximum number of trades remembered per building and per direction
+pub const LEDGER_CAPACITY: usize = 64;
+/// Trades older than this are forgotten
+pub const LEDGER_WINDOW: u64 = TICKS_PER_HOUR * HOURS_PER_DAY as u64;
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub tick: Tick,
+    pub item: ItemID,
+    pub qty: i32,
+    /// The building on the other side of the trade
+    pub partner: BuildingID,
+}
+
+/// The recent trades of one building, as ring buffers
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct BuildingLedger {
+    pub bought: VecDeque<LedgerEntry>,
+    pub sold: VecDeque<LedgerEntry>,
+}
+
+/// The sum of the recent trades with one partner for one item
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChainLink {
+    pub partner: BuildingID,
+    pub item: ItemID,
+    pub qty: i32,
+}
+
+/// One side of a flow of goods, the buildings are grouped by prototype
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FlowEnd {
+    /// Goods coming in through the freight stations and docks
+    Imports,
+    /// Goods leaving through the freight stations and docks
+    Exports,
+    Households,
+    Building(BuildingKind),
+}
+
+impl FlowEnd {
+    fn seller(kind: BuildingKind) -> Self {
+        match kind {
+            BuildingKind::RailFreightStation(_) | BuildingKind::Dock(_) => Self::Imports,
+            BuildingKind::House => Self::Households,
+            _ => Self::Building(kind),
+        }
+    }
+
+    fn buyer(kind: BuildingKind) -> Self {
+        match kind {
+            BuildingKind::RailFreightStation(_) | BuildingKind::Dock(_) => Self::Exports,
+            BuildingKind::House => Self::Households,
+            _ => Self::Building(kind),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Imports => "Imports",
+            Self::Exports => "Exports",
+            Self::Households => "Households",
+            Self::Building(kind) => kind.label(),
+        }
+    }
+}
+
+/// The quantity of an item sold by one group of buildings to another
+#[derive(Clone, Debug)]
+pub struct FlowBand {
+    pub from: FlowEnd,
+    pub to: FlowEnd,
+    pub qty: i64,
+    /// The buildings on each side with the quantity they traded, biggest first
+    pub sellers: Vec<(BuildingID, i64)>,
+    pub buyers: Vec<(BuildingID, i64)>,
+}
+
+/// Remembers where each building got its goods from and where it sent them to over the last game day
+#[derive(Default, Serialize, Deserialize)]
+pub struct TradeLedger {
+    ledgers: SecondaryMap<BuildingID, BuildingLedger>,
+}
+
+impl TradeLedger {
+    pub fn get(&self, building: BuildingID) -> Option<&BuildingLedger> {
+        self.ledgers.get(building)
+    }
+
+    /// Records the trade on both sides, trades with a soul without a building are ignored
+    pub fn record(&mut self, tick: Tick, trade: &Trade, binfos: &BuildingInfos) {
+        if trade.qty <= 0 {
+            return;
+        }
+        let Some(buyer) = binfos.building_owned_by(trade.buyer.0) else {
+            return;
+        };
+        let Some(seller) = binfos.building_owned_by(trade.seller.0) else {
+            return;
+        };
+        let entry = |partner| LedgerEntry {
+            tick,
+            item: trade.kind,
+            qty: trade.qty,
+            partner,
+        };
+
+        if let Some(ledger) = self.ledger_mut(buyer) {
+            push_capped(&mut ledger.bought, entry(seller));
+        }
+        if let Some(ledger) = self.ledger_mut(seller) {
+            push_capped(&mut ledger.sold, entry(buyer));
+        }
+    }
+
+    /// Forgets the trades older than the window
+    pub fn prune(&mut self, tick: Tick) {
+        let cutoff = tick.0.saturating_sub(LEDGER_WINDOW);
+        self.ledgers.retain(|_, ledger| {
+            ledger.bought.retain(|e| e.tick.0 >= cutoff);
+            ledger.sold.retain(|e| e.tick.0 >= cutoff);
+            !ledger.bought.is_empty() || !ledger.sold.is_empty()
+        });
+    }
+
+    /// Where the item went since the tick, grouped by the prototypes of the buildings on
+    /// both sides, biggest flows first
+    pub fn item_flows(&self, item: ItemID, since: Tick, map: &Map) -> Vec<FlowBand> {
+        self.flows_by(item, since, |b| Some(map.buildings().get(b)?.kind))
+    }
+
+    /// Each trade is counted once, from the seller's ledger
+    fn flows_by(
+        &self,
+        item: ItemID,
+        since: Tick,
+        kind_of: impl Fn(BuildingID) -> Option<BuildingKind>,
+    ) -> Vec<FlowBand> {
+        let mut bands: Vec<FlowBand> = vec![];
+        for (seller, ledger) in self.ledgers.iter() {
+            let Some(seller_kind) = kind_of(seller) else {
+                continue;
+            };
+            let from = FlowEnd::seller(seller_kind);
+            for e in &ledger.sold {
+                if e.item != item || e.tick < since {
+                    continue;
+                }
+                let Some(buyer_kind) = kind_of(e.partner) else {
+                    continue;
+                };
+                let to = FlowEnd::buyer(buyer_kind);
+                let qty = e.qty as i64;
+
+                let band = match bands.iter().position(|b| b.from == from && b.to == to) {
+                    Some(i) => &mut bands[i],
+                    None => {
+                        bands.push(FlowBand {
+                            from,
+                            to,
+                            qty: 0,
+                            sellers: vec![],
+                            buyers: vec![],
+                        });
+                        bands.last_mut().unwrap()
+                    }
+                };
+                band.qty += qty;
+                add_to(&mut band.sellers, seller, qty);
+                add_to(&mut band.buyers, e.partner, qty);
+            }
+        }
+
+        for band in &mut bands {
+            band.sellers.sort_by_key(|(_, qty)| -qty);
+            band.buyers.sort_by_key(|(_, qty)| -qty);
+        }
+        bands.sort_by_key(|b| -b.qty);
+        bands
+    }
+
+    fn ledger_mut(&mut self, building: BuildingID) -> Option<&mut BuildingLedger> {
+        Some(self.ledgers.entry(building)?.or_default())
+    }
+}
+
+impl BuildingLedger {
+    /// Where the goods came from, biggest suppliers first
+    pub fn suppliers(&self) -> Vec<ChainLink> {
+        aggregate(&self.bought)
+    }
+
+    /// Where the goods went, biggest customers first
+    pub fn customers(&self) -> Vec<ChainLink> {
+        aggregate(&self.sold)
+    }
+}
+
+fn push_capped(entries: &mut VecDeque<LedgerEntry>, entry: LedgerEntry) {
+    if entries.len() >= LEDGER_CAPACITY {
+        entries.pop_front();
+    }
+    entries.push_back(entry);
+}
+
+fn add_to(totals: &mut Vec<(BuildingID, i64)>, building: BuildingID, qty: i64) {
+    match totals.iter_mut().find(|(b, _)| *b == building) {
+        Some((_, total)) => *total += qty,
+        None => totals.push((building, qty)),
+    }
+}
+
+fn aggregate(entries: &VecDeque<LedgerEntry>) -> Vec<ChainLink> {
+    let mut links: Vec<ChainLink> = vec![];
+    for e in entries {
+        match links
+            .iter_mut()
+            .find(|l| l.partner == e.partner && l.item == e.item)
+        {
+            Some(link) => link.qty += e.qty,
+            None => links.push(ChainLink {
+                partner: e.partner,
+                item: e.item,
+                qty: e.qty,
+            }),
+        }
+    }
+    links.sort_by_key(|l| -l.qty);
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use prototypes::{test_prototypes, DockPrototypeID, GoodsCompanyID, ItemID, Money, Tick};
+    use slotmapd::SlotMap;
+
+    use crate::economy::{Trade, TradeTarget};
+    use crate::map::{BuildingID, BuildingKind};
+    use crate::map_dynamic::BuildingInfos;
+    use crate::world::CompanyID;
+    use crate::SoulID;
+
+    use super::{FlowEnd, TradeLedger, LEDGER_CAPACITY, LEDGER_WINDOW};
+
+    #[test]
+    fn ledger_aggregates_and_forgets() {
+        test_prototypes(
+            r#"
+        data:extend {
+          {
+            type = "item",
+            name = "cereal",
+            label = "Cereal"
+          }
+        }
+        "#,
+        );
+        let cereal = ItemID::new("cereal");
+
+        let mut buildings = SlotMap::<BuildingID, ()>::with_key();
+        let mut companies = SlotMap::<CompanyID, ()>::with_key();
+        let mut binfos = BuildingInfos::default();
+
+        let mut owned = || {
+            let b = buildings.insert(());
+            let soul = SoulID::GoodsCompany(companies.insert(()));
+            binfos.insert(b);
+            binfos.set_owner(b, soul);
+            (b, soul)
+        };
+        let (farm, farm_soul) = owned();
+        let (farm2, farm2_soul) = owned();
+        let (mill, mill_soul) = owned();
+
+        let trade = |seller, qty| Trade {
+            buyer: TradeTarget(mill_soul),
+            seller: TradeTarget(seller),
+            qty,
+            kind: cereal,
+            money_delta: Money::ZERO,
+        };
+
+        let mut ledger = TradeLedger::default();
+        ledger.record(Tick(1), &trade(farm_soul, 2), &binfos);
+        ledger.record(Tick(2), &trade(farm2_soul, 5), &binfos);
+        ledger.record(Tick(3), &trade(farm_soul, 1), &binfos);
+
+        let suppliers = ledger.get(mill).unwrap().suppliers();
+        assert_eq!(suppliers.len(), 2);
+        assert_eq!((suppliers[0].partner, suppliers[0].qty), (farm2, 5));
+        assert_eq!((suppliers[1].partner, suppliers[1].qty), (farm, 3));
+        assert_eq!(ledger.get(farm).unwrap().customers()[0].partner, mill);
+
+        for i in 0..LEDGER_CAPACITY as u64 * 2 {
+            ledger.record(Tick(4 + i), &trade(farm_soul, 1), &binfos);
+        }
+        assert_eq!(ledger.get(mill).unwrap().bought.len(), LEDGER_CAPACITY);
+
+        ledger.prune(Tick(10 + LEDGER_CAPACITY as u64 * 2 + LEDGER_WINDOW));
+        assert!(ledger.get(mill).is_none());
+    }
+
+    #[test]
+    fn flows_group_by_prototype() {
+        let cereal = ItemID::new("cereal");
+        let farm_kind = BuildingKind::GoodsCompany(GoodsCompanyID::new("farm"));
+
+        let mut buildings = SlotMap::<BuildingID, ()>::with_key();
+        let mut companies = SlotMap::<CompanyID, ()>::with_key();
+        let mut binfos = BuildingInfos::default();
+
+        let mut owned = || {
+            let b = buildings.insert(());
+            let soul = SoulID::GoodsCompany(companies.insert(()));
+            binfos.insert(b);
+            binfos.set_owner(b, soul);
+            (b, soul)
+        };
+        let (farm, farm_soul) = owned();
+        let (farm2, farm2_soul) = owned();
+        let (dock, dock_soul) = owned();
+        let (house, house_soul) = owned();
+        let kind_of = |b| {
+            Some(match b {
+                b if b == dock => BuildingKind::Dock(DockPrototypeID::new("dock")),
+                b if b == house => BuildingKind::House,
+                _ => farm_kind,
+            })
+        };
+
+        let trade = |seller, buyer, qty| Trade {
+            buyer: TradeTarget(buyer),
+            seller: TradeTarget(seller),
+            qty,
+            kind: cereal,
+            money_delta: Money::ZERO,
+        };
+
+        let mut ledger = TradeLedger::default();
+        ledger.record(Tick(0), &trade(farm_soul, house_soul, 100), &binfos);
+        ledger.record(Tick(1), &trade(farm_soul, house_soul, 2), &binfos);
+        ledger.record(Tick(2), &trade(farm2_soul, house_soul, 4), &binfos);
+        ledger.record(Tick(3), &trade(farm_soul, dock_soul, 4), &binfos);
+        ledger.record(Tick(4), &trade(dock_soul, house_soul, 5), &binfos);
+
+        let flows = ledger.flows_by(cereal, Tick(1), kind_of);
+        let ends: Vec<_> = flows.iter().map(|b| (b.from, b.to, b.qty)).collect();
+        assert_eq!(
+            ends,
+            vec![
+                (FlowEnd::Building(farm_kind), FlowEnd::Households, 6),
+                (FlowEnd::Imports, FlowEnd::Households, 5),
+                (FlowEnd::Building(farm_kind), FlowEnd::Exports, 4),
+            ]
+        );
+        assert_eq!(flows[0].sellers, vec![(farm2, 4), (farm, 2)]);
+        assert_eq!(flows[0].buyers, vec![(house, 6)]);
+        assert_eq!(flows[2].buyers, vec![(dock, 4)]);
+        assert!(ledger
+            .flows_by(ItemID::new("flour"), Tick(0), kind_of)
+            .is_empty());
+    }
+}
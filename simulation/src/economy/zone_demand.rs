@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+use prototypes::{ItemID, ZoneKind, TICKS_PER_MINUTE};
+
+use crate::economy::{Market, Trade};
+use crate::SoulID;
+
+/// Weight of the current tick in the demand average, so it follows the market over a few game minutes
+const DEMAND_SMOOTHING: f32 = 1.0 / (3 * TICKS_PER_MINUTE) as f32;
+
+/// How much the city wants new buildings of each zone kind, derived from the market
+#[derive(Default, Serialize, Deserialize)]
+pub struct ZoneDemand {
+    /// Job openings that nobody took, people would move in to fill them
+    pub residential: f32,
+    /// Goods per minute that people had to buy from outside the city
+    pub commercial: f32,
+    /// Goods per minute that companies had to buy from outside the city
+    pub industrial: f32,
+}
+
+impl ZoneDemand {
+    /// Demand at which the demand bars are full
+    pub const FULL: f32 = 10.0;
+
+    pub fn get(&self, kind: ZoneKind) -> f32 {
+        match kind {
+            ZoneKind::Residential => self.residential,
+            ZoneKind::Commercial => self.commercial,
+            ZoneKind::Industrial => self.industrial,
+        }
+    }
+
+    pub fn get_mut(&mut self, kind: ZoneKind) -> &mut f32 {
+        match kind {
+            ZoneKind::Residential => &mut self.residential,
+            ZoneKind::Commercial => &mut self.commercial,
+            ZoneKind::Industrial => &mut self.industrial,
+        }
+    }
+
+    /// Quantity of goods asked by humans and by companies, to be called before the trades are made
+    pub(crate) fn wanted(market: &Market, job_opening: ItemID) -> (i64, i64) {
+        let (mut humans, mut companies) = (0, 0);
+        for (kind, m) in market.iter() {
+            if *kind == job_opening {
+                continue;
+            }
+            for (soul, order) in m.buy_orders() {
+                match soul {
+                    SoulID::Human(_) => humans += order.qty as i64,
+                    SoulID::GoodsCompany(_) => companies += order.qty as i64,
+                    SoulID::FreightStation(_) | SoulID::Dock(_) | SoulID::Warehouse(_) => {}
+                }
+            }
+        }
+        (humans, companies)
+    }
+
+    /// Whatever was wanted but not sold by a company of the city is unmet demand.
+    /// Goods coming through freight stations and docks are imported so they count as unmet.
+    pub(crate) fn update_goods(
+        &mut self,
+        (mut humans, mut companies): (i64, i64),
+        trades: &[Trade],
+        job_opening: ItemID,
+    ) {
+        for trade in trades {
+            if trade.kind == job_opening || !matches!(trade.seller.0, SoulID::GoodsCompany(_)) {
+                continue;
+            }
+            match trade.buyer.0 {
+                SoulID::Human(_) => humans -= trade.qty as i64,
+                SoulID::GoodsCompany(_) => companies -= trade.qty as i64,
+                SoulID::FreightStation(_) | SoulID::Dock(_) | SoulID::Warehouse(_) => {}
+            }
+        }
+
+        let per_minute = TICKS_PER_MINUTE as f32;
+        smooth(&mut self.commercial, humans.max(0) as f32 * per_minute);
+        smooth(&mut self.industrial, companies.max(0) as f32 * per_minute);
+    }
+
+    /// Job openings still for sale after the trades are made
+    pub(crate) fn update_jobs(&mut self, market: &Market, job_opening: ItemID) {
+        let open_jobs: u32 = market
+            .inner()
+            .get(&job_opening)
+            .map(|m| m.sell_orders().values().map(|o| o.qty).sum())
+            .unwrap_or(0);
+        smooth(&mut self.residential, open_jobs as f32);
+    }
+}
+
+fn smooth(v: &mut f32, target: f32) {
+    *v += (target - *v) * DEMAND_SMOOTHING;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demand_follows_target_slowly() {
+        let mut v = 0.0;
+        smooth(&mut v, 10.0);
+        assert!(v > 0.0 && v < 1.0);
+        for _ in 0..100 * TICKS_PER_MINUTE {
+            smooth(&mut v, 10.0);
+        }
+        assert!((v - 10.0).abs() < 0.01);
+    }
+}
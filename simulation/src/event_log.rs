@@ -0,0 +1,309 @@
+//! Persistent record of what happened in the city.
+//! Systems push events here, the log window lists them and the toasts show the notable ones.
+
+use std::collections::VecDeque;
+
+use geom::Vec3;
+use prototypes::{GameInstant, GameTime, DELTA};
+use serde::{Deserialize, Serialize};
+
+use crate::map::{BuildingID, RoadID};
+use crate::transportation::train::BLOCKED_AFTER;
+use crate::{AnyEntity, Simulation};
+
+/// Number of events kept, the oldest are dropped first
+pub const MAX_EVENTS: usize = 5000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventCategory {
+    Construction,
+    Economy,
+    Transport,
+    Scenario,
+    Incident,
+}
+
+impl EventCategory {
+    pub const ALL: [EventCategory; 5] = [
+        EventCategory::Construction,
+        EventCategory::Economy,
+        EventCategory::Transport,
+        EventCategory::Scenario,
+        EventCategory::Incident,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EventCategory::Construction => "Construction",
+            EventCategory::Economy => "Economy",
+            EventCategory::Transport => "Transport",
+            EventCategory::Scenario => "Scenario",
+            EventCategory::Incident => "Incidents",
+        }
+    }
+
+    /// Name used by the prototypes, e.g. "construction"
+    pub fn id(self) -> &'static str {
+        match self {
+            EventCategory::Construction => "construction",
+            EventCategory::Economy => "economy",
+            EventCategory::Transport => "transport",
+            EventCategory::Scenario => "scenario",
+            EventCategory::Incident => "incident",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.id() == id)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    /// Only kept in the log
+    Info,
+    /// Good news, also shown as a toast
+    Success,
+    /// Something needs attention, also shown as a toast
+    Warning,
+}
+
+impl Severity {
+    pub fn is_toast(self) -> bool {
+        self >= Severity::Success
+    }
+
+    /// Name used by the prototypes, e.g. "warning"
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "info" => Some(Severity::Info),
+            "success" => Some(Severity::Success),
+            "warning" => Some(Severity::Warning),
+            _ => None,
+        }
+    }
+}
+
+/// What the event is about, to open its inspector
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventSubject {
+    Entity(AnyEntity),
+    Building(BuildingID),
+    Road(RoadID),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Event {
+    /// Increases with each event, never reused
+    pub id: u64,
+    pub at: GameInstant,
+    pub category: EventCategory,
+    pub severity: Severity,
+    pub text: String,
+    pub subject: Option<EventSubject>,
+    pub pos: Option<Vec3>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct EventLog {
+    /// Most recent last
+    events: VecDeque<Event>,
+    next_id: u64,
+}
+
+impl EventLog {
+    pub fn push(
+        &mut self,
+        at: GameInstant,
+        category: EventCategory,
+        severity: Severity,
+        text: String,
+        subject: Option<EventSubject>,
+        pos: Option<Vec3>,
+    ) {
+        if self.events.len() >= MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(Event {
+            id: self.next_id,
+            at,
+            category,
+            severity,
+            text,
+            subject,
+            pos,
+        });
+        self.next_id += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Newest first
+    pub fn iter(&self) -> impl Iterator<Item = &Event> + '_ {
+        self.events.iter().rev()
+    }
+
+    /// Newest first, events of the given categories whose text or building address contains
+    /// `search`, ignoring case
+    pub fn filtered<'a>(
+        &'a self,
+        categories: &'a [EventCategory],
+        search: &str,
+        address: impl Fn(BuildingID) -> Option<String> + 'a,
+    ) -> impl Iterator<Item = &'a Event> + 'a {
+        let search = search.to_lowercase();
+        self.iter().filter(move |e| {
+            if !categories.contains(&e.category) {
+                return false;
+            }
+            if search.is_empty() || e.text.to_lowercase().contains(&search) {
+                return true;
+            }
+            let Some(EventSubject::Building(b)) = e.subject else {
+                return false;
+            };
+            address(b).map_or(false, |a| a.to_lowercase().contains(&search))
+        })
+    }
+
+    /// Newest first, the events shown as toasts since the given time
+    pub fn toasts_since(&self, time: GameInstant) -> impl Iterator<Item = &Event> + '_ {
+        self.iter()
+            .take_while(move |e| e.at >= time)
+            .filter(|e| e.severity.is_toast())
+    }
+}
+
+/// Adds an event at the current time, placed on its subject
+pub fn log_event(
+    sim: &Simulation,
+    category: EventCategory,
+    severity: Severity,
+    text: String,
+    subject: Option<EventSubject>,
+) {
+    let pos = subject.and_then(|s| match s {
+        EventSubject::Entity(e) => sim.pos_any(e),
+        EventSubject::Building(b) => sim
+            .map()
+            .buildings()
+            .get(b)
+            .map(|b| b.obb.center().z(b.height)),
+        EventSubject::Road(r) => sim
+            .map()
+            .roads()
+            .get(r)
+            .map(|r| r.points.point_along(r.length() * 0.5)),
+    });
+    let at = sim.read::<GameTime>().instant();
+    sim.write::<EventLog>()
+        .push(at, category, severity, text, subject, pos);
+}
+
+/// Logs the trains that have been waiting for a free track for a while
+pub(crate) fn event_log_system(sim: &mut Simulation) {
+    profiling::scope!("event_log::event_log_system");
+    let blocked: Vec<_> = sim
+        .world
+        .trains
+        .iter()
+        .filter(|(_, t)| {
+            !t.it.is_none_or_wait()
+                && t.res.waited_for >= BLOCKED_AFTER
+                && t.res.waited_for < BLOCKED_AFTER + DELTA
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    for id in blocked {
+        log_event(
+            sim,
+            EventCategory::Transport,
+            Severity::Warning,
+            "A train is blocked, waiting for the track ahead".to_string(),
+            Some(EventSubject::Entity(AnyEntity::TrainID(id))),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prototypes::Tick;
+
+    use super::*;
+
+    fn push(log: &mut EventLog, tick: u64, category: EventCategory, text: &str) {
+        log.push(
+            GameInstant(Tick(tick)),
+            category,
+            Severity::Info,
+            text.to_string(),
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn log_is_capped() {
+        let mut log = EventLog::default();
+        for i in 0..MAX_EVENTS + 10 {
+            push(&mut log, i as u64, EventCategory::Economy, "event");
+        }
+        assert_eq!(log.len(), MAX_EVENTS);
+        assert_eq!(log.iter().next().unwrap().id, (MAX_EVENTS + 9) as u64);
+        assert_eq!(log.iter().last().unwrap().id, 10);
+    }
+
+    #[test]
+    fn filter_by_category_and_text() {
+        let mut log = EventLog::default();
+        push(&mut log, 0, EventCategory::Economy, "Bakery opened");
+        push(&mut log, 1, EventCategory::Construction, "Bakery completed");
+        push(&mut log, 2, EventCategory::Economy, "Farm went bankrupt");
+
+        log.push(
+            GameInstant(Tick(3)),
+            EventCategory::Construction,
+            Severity::Info,
+            "Farm completed".to_string(),
+            Some(EventSubject::Building(BuildingID::default())),
+            None,
+        );
+
+        let texts = |cats: &[EventCategory], search: &str| -> Vec<String> {
+            log.filtered(cats, search, |_| Some("12 Elm Street".to_string()))
+                .map(|e| e.text.clone())
+                .collect()
+        };
+
+        assert_eq!(
+            texts(&EventCategory::ALL, "elm street"),
+            vec!["Farm completed"]
+        );
+        assert_eq!(
+            texts(&[EventCategory::Economy], ""),
+            vec!["Farm went bankrupt", "Bakery opened"]
+        );
+        assert_eq!(
+            texts(&EventCategory::ALL, "bakery"),
+            vec!["Bakery completed", "Bakery opened"]
+        );
+        assert!(texts(&[EventCategory::Transport], "").is_empty());
+    }
+
+    #[test]
+    fn prototype_names() {
+        for category in EventCategory::ALL {
+            assert_eq!(EventCategory::from_id(category.id()), Some(category));
+        }
+        assert_eq!(EventCategory::from_id("Incidents"), None);
+        assert_eq!(Severity::from_id("success"), Some(Severity::Success));
+        assert_eq!(Severity::from_id("loud"), None);
+    }
+}
@@ -1,29 +1,52 @@
-use crate::economy::{market_update, EcoStats, Government, Market};
+use crate::economy::{
+    market_update, EcoStats, Government, GovernmentOrders, Market, TradeLedger, TripStats,
+    ZoneDemand,
+};
+use crate::event_log::{event_log_system, EventLog};
 use crate::map::Map;
 use crate::map_dynamic::{
-    dispatch_system, electricity_flow_system, itinerary_update, routing_changed_system,
-    routing_update_system, BuildingInfos, Dispatcher, ElectricityFlow, ParkingManagement,
+    abandonment_system, dispatch_system, electricity_flow_system, itinerary_update,
+    path_jobs_system, road_wear_system, roadworks_system, routing_changed_system,
+    routing_update_system, tree_growth_system, walkability_system, water_balance_system,
+    zone_growth_system, Abandonment, BuildingInfos, Dispatcher, ElectricityFlow, ParkingManagement,
+    PathJobs, RoadMaintenance, Walkability,
 };
 use crate::multiplayer::MultiplayerState;
+use crate::rules::GameRules;
+use crate::scenario::{scenario_system, ScenarioState};
+use crate::souls::company_lifecycle::{company_lifecycle_system, CompanyLifecycle};
+use crate::souls::dock::dock_system;
 use crate::souls::freight_station::freight_station_system;
 use crate::souls::goods_company::company_system;
+use crate::souls::household::{household_system, Households};
 use crate::souls::human::update_decision_system;
-use crate::transportation::pedestrian_decision_system;
+use crate::souls::sampling::{citizen_sampling_system, CitizenSampling};
+use crate::souls::venue_events::{venue_events_system, VenueEvents};
+use crate::souls::warehouse::warehouse_system;
+use crate::souls::welfare::{welfare_system, Welfare};
+use crate::transportation::deadlock::{deadlock_system, Deadlocks};
+use crate::transportation::incident::{incident_system, Incidents};
+use crate::transportation::intersection_stats::{intersection_stats_system, IntersectionStats};
 use crate::transportation::road::{vehicle_decision_system, vehicle_state_update_system};
+use crate::transportation::service_call::{service_call_system, ServiceCalls};
+use crate::transportation::service_fleet::{service_fleet_system, ServiceFleets};
 use crate::transportation::testing_vehicles::{random_vehicles_update, RandomVehicles};
 use crate::transportation::train::{
     locomotive_system, train_reservations_update, TrainReservations,
 };
+use crate::transportation::{pedestrian_decision_system, CrossingQueues};
 use crate::transportation::{transport_grid_synchronize, TransportGrid};
 use crate::utils::resources::Resources;
-use crate::world::{CompanyEnt, FreightStationEnt, HumanEnt, TrainEnt, VehicleEnt, WagonEnt};
+use crate::world::{
+    CompanyEnt, DockEnt, FreightStationEnt, HumanEnt, TrainEnt, VehicleEnt, WagonEnt, WarehouseEnt,
+};
 use crate::World;
 use crate::{
     add_souls_to_empty_buildings, utils, ParCommandBuffer, RandProvider, Replay, RunnableSystem,
-    Simulation, SimulationOptions, RNG_SEED,
+    Simulation, SimulationOptions, UsedMods, RNG_SEED,
 };
 use common::saveload::{Bincode, Encoder, JSON};
-use prototypes::{GameTime, Tick};
+use prototypes::{GameTime, LoadConfig, LoadMode, ModSettings, Tick};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
@@ -31,19 +54,26 @@ pub fn init() {
     // # Safety
     // This function is called only once, before any other function in this crate.
     unsafe {
+        // the game skips the broken prototypes of the mods, the tests catch those of the base game
         #[cfg(not(test))]
-        let base = "./";
+        let (base, mode) = ("./", LoadMode::Lenient);
         #[cfg(test)]
-        let base = "../";
+        let (base, mode) = ("../", LoadMode::Strict);
 
-        match prototypes::load_prototypes(base) {
-            Ok(_) => {}
+        match prototypes::load_prototypes(&LoadConfig {
+            mode,
+            ..LoadConfig::new(base)
+        }) {
+            Ok(report) => report.log(),
             Err(e) => {
                 panic!("Error loading prototypes: {}", e)
             }
         }
     }
 
+    utils::debug_channels::register_channels();
+
+    register_system("path_jobs", path_jobs_system);
     register_system("electricity_flow_system", electricity_flow_system);
     register_system("dispatch_system", dispatch_system);
     register_system("update_decision_system", update_decision_system);
@@ -53,40 +83,87 @@ pub fn init() {
     register_system("locomotive_system", locomotive_system);
     register_system("vehicle_decision_system", vehicle_decision_system);
     register_system("vehicle_state_update_system", vehicle_state_update_system);
+    register_system("intersection_stats_system", intersection_stats_system);
     register_system("routing_changed_system", routing_changed_system);
     register_system("routing_update_system", routing_update_system);
     register_system("itinerary_update", itinerary_update);
     register_system("market_update", market_update);
     register_system("train_reservations_update", train_reservations_update);
     register_system("freight_station", freight_station_system);
+    register_system("dock", dock_system);
+    register_system("warehouse", warehouse_system);
     register_system("random_vehicles", random_vehicles_update);
     register_system("update_map", |_, res| res.write::<Map>().update());
 
     register_system_sim("add_souls_to_empty_buildings", add_souls_to_empty_buildings);
+    register_system_sim("walkability", walkability_system);
+    register_system_sim("zone_growth", zone_growth_system);
+    register_system_sim("abandonment", abandonment_system);
+    register_system_sim("scenario", scenario_system);
+    register_system_sim("company_lifecycle", company_lifecycle_system);
+    register_system_sim("households", household_system);
+    register_system_sim("welfare", welfare_system);
+    register_system_sim("venue_events", venue_events_system);
+    register_system_sim("road_wear", road_wear_system);
+    register_system_sim("roadworks", roadworks_system);
+    register_system_sim("tree_growth", tree_growth_system);
+    register_system_sim("water_balance", water_balance_system);
+    register_system_sim("service_fleets", service_fleet_system);
+    register_system_sim("incidents", incident_system);
+    register_system_sim("service_calls", service_call_system);
+    register_system_sim("deadlocks", deadlock_system);
+    register_system_sim("event_log", event_log_system);
+    register_system_sim("citizen_sampling", citizen_sampling_system);
 
     register_resource_noserialize::<ParCommandBuffer<VehicleEnt>>();
     register_resource_noserialize::<ParCommandBuffer<TrainEnt>>();
     register_resource_noserialize::<ParCommandBuffer<HumanEnt>>();
     register_resource_noserialize::<ParCommandBuffer<WagonEnt>>();
     register_resource_noserialize::<ParCommandBuffer<FreightStationEnt>>();
+    register_resource_noserialize::<ParCommandBuffer<DockEnt>>();
+    register_resource_noserialize::<ParCommandBuffer<WarehouseEnt>>();
     register_resource_noserialize::<ParCommandBuffer<CompanyEnt>>();
     register_resource_noinit::<SimulationOptions, Bincode>("simoptions");
 
     register_resource_default::<ElectricityFlow, Bincode>("electricity_flow");
     register_resource_default::<Market, Bincode>("market");
     register_resource_default::<EcoStats, Bincode>("ecostats");
+    register_resource_default::<TradeLedger, Bincode>("trade_ledger");
+    register_resource_default::<TripStats, Bincode>("trip_stats");
+    register_resource_default::<ZoneDemand, Bincode>("zone_demand");
+    register_resource_default::<ScenarioState, Bincode>("scenario");
+    register_resource_default::<GameRules, Bincode>("game_rules");
+    register_resource_default::<CompanyLifecycle, Bincode>("company_lifecycle");
+    register_resource_default::<Households, Bincode>("households");
+    register_resource_default::<Welfare, Bincode>("welfare");
+    register_resource_default::<VenueEvents, Bincode>("venue_events");
+    register_resource_default::<RoadMaintenance, Bincode>("road_maintenance");
+    register_resource_default::<Incidents, Bincode>("incidents");
+    register_resource_default::<ServiceFleets, Bincode>("service_fleets");
+    register_resource_default::<ServiceCalls, Bincode>("service_calls");
+    register_resource_default::<Deadlocks, Bincode>("deadlocks");
+    register_resource_default::<IntersectionStats, Bincode>("intersection_stats");
+    register_resource_default::<Abandonment, Bincode>("abandonment");
+    register_resource_default::<Walkability, Bincode>("walkability");
     register_resource_default::<MultiplayerState, Bincode>("multiplayer_state");
+    register_resource_default::<EventLog, Bincode>("event_log");
+    register_resource_default::<CitizenSampling, Bincode>("citizen_sampling");
     register_resource_default::<RandomVehicles, Bincode>("random_vehicles");
+    register_resource_default::<CrossingQueues, Bincode>("crossing_queues");
     register_resource_default::<Map, Bincode>("map");
     register_resource_default::<TrainReservations, Bincode>("train_reservations");
     register_resource_default::<Government, Bincode>("government");
+    register_resource_default::<GovernmentOrders, Bincode>("government_orders");
     register_resource_default::<ParkingManagement, Bincode>("pmanagement");
+    register_resource_default::<PathJobs, Bincode>("path_jobs");
     register_resource_default::<BuildingInfos, Bincode>("binfos");
     register_resource::<GameTime, Bincode>("game_time", || GameTime::new(Tick(1)));
     register_resource::<TransportGrid, Bincode>("transport_grid", || TransportGrid::new(100));
     register_resource::<RandProvider, Bincode>("randprovider", || RandProvider::new(RNG_SEED));
     register_resource_default::<Dispatcher, Bincode>("dispatcher");
     register_resource_default::<Replay, JSON>("replay");
+    register_resource_default::<UsedMods, Bincode>("used_mods");
+    register_resource_default::<ModSettings, Bincode>("mod_settings");
 }
 
 pub struct InitFunc {
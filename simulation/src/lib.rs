@@ -1,27 +1,37 @@
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::type_complexity)]
 
+use crate::economy::{GovernmentOrders, Market};
 use crate::init::{GSYSTEMS, INIT_FUNCS, SAVELOAD_FUNCS};
 use crate::map::{BuildingKind, Map};
 use crate::map_dynamic::{Itinerary, ItineraryLeader};
+use crate::rules::GameRules;
+use crate::scenario::ScenarioState;
 use crate::souls::add_souls_to_empty_buildings;
 use crate::utils::resources::{Ref, RefMut, Resources};
 use crate::utils::scheduler::RunnableSystem;
 use crate::world_command::WorldCommand;
 use crate::world_command::WorldCommand::Init;
-use common::saveload::Encoder;
+use common::saveload::{Bincode, CompressedBincode, Encoder};
 use common::FastMap;
 use derive_more::{From, TryInto};
 use geom::Vec3;
-use prototypes::{prototype, ColorsPrototype, ColorsPrototypeID, GameTime, Tick};
+use prototypes::{
+    prototype, try_prototype, ColorsPrototype, ColorsPrototypeID, GameTime, ItemID,
+    ModSettingValue, ModSettings, ScenarioID, StreetNamesPrototype, Tick,
+};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::any::Any;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Display, Formatter};
+use std::fs::File;
 use std::hash::Hash;
 use std::ptr::addr_of;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use utils::rand_provider::RandProvider;
+use utils::savefile::{
+    self, Codec, SaveCompression, SaveFile, SaveHeader, SaveSize, ENTITIES_BLOCK,
+};
 use utils::scheduler::SeqSchedule;
 
 #[macro_use]
@@ -37,11 +47,15 @@ extern crate egui_inspect;
 #[macro_use]
 extern crate log as extern_log;
 
+pub mod bench_scenes;
 pub mod economy;
+pub mod event_log;
 pub mod init;
 pub mod map;
 pub mod map_dynamic;
 pub mod multiplayer;
+pub mod rules;
+pub mod scenario;
 pub mod souls;
 #[cfg(test)]
 mod tests;
@@ -66,6 +80,8 @@ pub enum SoulID {
     Human(HumanID),
     GoodsCompany(CompanyID),
     FreightStation(FreightStationID),
+    Dock(DockID),
+    Warehouse(WarehouseID),
 }
 
 impl Display for SoulID {
@@ -74,6 +90,8 @@ impl Display for SoulID {
             SoulID::Human(id) => write!(f, "{:?}", id),
             SoulID::GoodsCompany(id) => write!(f, "{:?}", id),
             SoulID::FreightStation(id) => write!(f, "{:?}", id),
+            SoulID::Dock(id) => write!(f, "{:?}", id),
+            SoulID::Warehouse(id) => write!(f, "{:?}", id),
         }
     }
 }
@@ -84,6 +102,8 @@ impl From<SoulID> for AnyEntity {
             SoulID::Human(id) => AnyEntity::HumanID(id),
             SoulID::GoodsCompany(id) => AnyEntity::CompanyID(id),
             SoulID::FreightStation(id) => AnyEntity::FreightStationID(id),
+            SoulID::Dock(id) => AnyEntity::DockID(id),
+            SoulID::Warehouse(id) => AnyEntity::WarehouseID(id),
         }
     }
 }
@@ -96,6 +116,8 @@ impl TryFrom<AnyEntity> for SoulID {
             AnyEntity::HumanID(id) => Ok(SoulID::Human(id)),
             AnyEntity::CompanyID(id) => Ok(SoulID::GoodsCompany(id)),
             AnyEntity::FreightStationID(id) => Ok(SoulID::FreightStation(id)),
+            AnyEntity::DockID(id) => Ok(SoulID::Dock(id)),
+            AnyEntity::WarehouseID(id) => Ok(SoulID::Warehouse(id)),
             _ => Err(()),
         }
     }
@@ -111,10 +133,80 @@ pub struct Simulation {
 const RNG_SEED: u64 = 123;
 const VERSION: &str = include_str!("../../VERSION");
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimulationOptions {
     pub terrain_size: u16,
     pub save_replay: bool,
+    /// Scenario to start once the terrain is generated
+    #[serde(default)]
+    pub scenario: Option<ScenarioID>,
+    /// Share of the new inhabitants moving in with a car
+    #[serde(default = "default_car_ownership_rate")]
+    pub car_ownership_rate: f32,
+    /// Number of days each season lasts, seasons are disabled if 0
+    #[serde(default = "default_season_days")]
+    pub season_days: u32,
+    /// Multiplier of the number of raw material deposits scattered on the terrain
+    #[serde(default = "default_deposit_abundance")]
+    pub deposit_abundance: f32,
+    /// Where the city is on the globe, the days get shorter in winter away from the equator
+    #[serde(default)]
+    pub latitude: Latitude,
+    #[serde(default)]
+    pub rules: GameRules,
+    /// Values of the settings of the mods, the startup ones must match the loaded prototypes
+    #[serde(default = "default_mod_settings")]
+    pub mod_settings: ModSettings,
+}
+
+/// How far from the equator the city is
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Latitude {
+    Equatorial,
+    #[default]
+    Temperate,
+    Northern,
+}
+
+impl Latitude {
+    pub const ALL: [Latitude; 3] = [
+        Latitude::Equatorial,
+        Latitude::Temperate,
+        Latitude::Northern,
+    ];
+
+    /// Degrees north of the equator
+    pub fn degrees(self) -> f32 {
+        match self {
+            Latitude::Equatorial => 5.0,
+            Latitude::Temperate => 45.0,
+            Latitude::Northern => 62.0,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Latitude::Equatorial => "Equatorial",
+            Latitude::Temperate => "Temperate",
+            Latitude::Northern => "Northern",
+        }
+    }
+}
+
+fn default_mod_settings() -> ModSettings {
+    prototypes::loaded_mod_settings().clone()
+}
+
+fn default_car_ownership_rate() -> f32 {
+    0.7
+}
+
+fn default_season_days() -> u32 {
+    7
+}
+
+fn default_deposit_abundance() -> f32 {
+    1.0
 }
 
 impl Default for SimulationOptions {
@@ -122,6 +214,13 @@ impl Default for SimulationOptions {
         SimulationOptions {
             terrain_size: 50,
             save_replay: true,
+            scenario: None,
+            car_ownership_rate: default_car_ownership_rate(),
+            season_days: default_season_days(),
+            deposit_abundance: default_deposit_abundance(),
+            latitude: Latitude::default(),
+            rules: GameRules::default(),
+            mod_settings: default_mod_settings(),
         }
     }
 }
@@ -280,6 +379,11 @@ impl Simulation {
         hashes
     }
 
+    /// All the hashes together, to compare two simulations at once
+    pub fn hash(&self) -> u64 {
+        common::hash_u64(self.hashes())
+    }
+
     pub fn load_replay_from_disk(save_name: &str) -> Option<Replay> {
         let path = format!("{save_name}_replay");
         let replay: Replay = common::saveload::JSON::load(&path).ok()?;
@@ -287,18 +391,185 @@ impl Simulation {
     }
 
     pub fn load_from_disk(save_name: &str) -> Option<Self> {
-        let sim: Simulation = common::saveload::CompressedBincode::load(save_name).ok()?;
+        Self::load_from_disk_with_progress(save_name, |_| {})
+    }
+
+    /// Same as load_from_disk, calling `progress` before each step
+    pub fn load_from_disk_with_progress(
+        save_name: &str,
+        mut progress: impl FnMut(SaveLoadStep),
+    ) -> Option<Self> {
+        let t = Instant::now();
+        progress(SaveLoadStep::ReadingFile);
+        let path = CompressedBincode::filename(save_name);
+        let file = File::open(&path)
+            .map_err(|e| log::error!("could not read save {}: {}", save_name, e))
+            .ok()?;
+        let file_len = file.metadata().map_or(0, |m| m.len());
+
+        let mut sim: Option<Self> = None;
+        let mut has_entities = false;
+        let read = savefile::read_save(file, |name, data| {
+            let sim = sim.get_or_insert_with(Self::new_for_load);
+            match name {
+                ENTITIES_BLOCK => {
+                    progress(SaveLoadStep::DeserializingEntities);
+                    match Bincode::decode(&data) {
+                        Ok(world) => {
+                            sim.world = world;
+                            has_entities = true;
+                        }
+                        Err(e) => log::error!("could not decode the entities: {}", e),
+                    }
+                }
+                _ => {
+                    if name == "map" {
+                        progress(SaveLoadStep::DeserializingMap);
+                    }
+                    sim.load_resource(name, data);
+                }
+            }
+        })
+        .map_err(|e| log::error!("could not decode save {}: {}", save_name, e))
+        .ok()?;
+
+        let sim = match read {
+            SaveFile::Legacy(data) => {
+                progress(SaveLoadStep::DeserializingEntities);
+                let simdeser: SimulationDeser = CompressedBincode::decode(&data)
+                    .map_err(|e| log::error!("could not decode save {}: {}", save_name, e))
+                    .ok()?;
+
+                progress(SaveLoadStep::DeserializingMap);
+                Self::from_deser(simdeser)
+            }
+            SaveFile::Blocks(header) => {
+                let mut sim = sim.unwrap_or_else(Self::new_for_load);
+                if !has_entities {
+                    log::error!("save {} has no entities", save_name);
+                    return None;
+                }
+                sim.check_loaded(&header.version);
+                sim
+            }
+        };
+
+        log::info!(
+            "loaded {} in {:.2}s: {} KB",
+            save_name,
+            t.elapsed().as_secs_f32(),
+            file_len / 1000
+        );
         Some(sim)
     }
 
+    /// Saves with the fast compression, for the autosaves
     pub fn save_to_disk(&self, save_name: &str) {
-        common::saveload::CompressedBincode::save(&self, save_name);
+        self.save_to_disk_with(save_name, SaveCompression::Fast);
+    }
+
+    pub fn save_to_disk_with(&self, save_name: &str, compression: SaveCompression) {
+        let t = Instant::now();
+        let _ = std::fs::create_dir("world");
+        let path = CompressedBincode::filename(save_name);
+        // the old save is only replaced once the new one is complete
+        let tmp = format!("{path}.tmp");
+
+        let (codec, level) = compression.codec();
+        match self
+            .write_save(&tmp, codec, level)
+            .and_then(|size| std::fs::rename(&tmp, &path).map(|_| size))
+        {
+            Ok(size) => log::info!(
+                "saved {} in {:.2}s: {} KB, {} KB before compression ({:?})",
+                save_name,
+                t.elapsed().as_secs_f32(),
+                size.file / 1000,
+                size.uncompressed / 1000,
+                compression
+            ),
+            Err(e) => log::error!("could not save {}: {}", save_name, e),
+        }
+
         let rep = self.resources.read::<Replay>();
         if rep.enabled {
             common::saveload::JSONPretty::save(&*rep, &format!("{save_name}_replay"));
         }
     }
 
+    /// Saves `save_name` again with each codec and loads it back, logging the times and the sizes
+    /// to compare the codecs on a real save. False if something failed.
+    pub fn measure_save(save_name: &str) -> bool {
+        let Some(sim) = Self::load_from_disk(save_name) else {
+            return false;
+        };
+        let name = format!("{save_name}_measure");
+        let path = CompressedBincode::filename(&name);
+
+        let mut ok = true;
+        for (codec, level) in savefile::MEASURED {
+            let t = Instant::now();
+            let size = match sim.write_save(&path, codec, level) {
+                Ok(size) => size,
+                Err(e) => {
+                    log::error!("could not save with {:?} {}: {}", codec, level, e);
+                    ok = false;
+                    continue;
+                }
+            };
+            let save_time = t.elapsed().as_secs_f32();
+
+            let t = Instant::now();
+            let loaded = Self::load_from_disk(&name);
+            let load_time = t.elapsed().as_secs_f32();
+            if !loaded.is_some_and(|loaded| loaded.is_equal(&sim)) {
+                log::error!(
+                    "the save made with {:?} {} does not load back",
+                    codec,
+                    level
+                );
+                ok = false;
+            }
+
+            log::info!(
+                "{:?} {}: saved in {:.2}s, loaded in {:.2}s, {} KB ({} KB before compression)",
+                codec,
+                level,
+                save_time,
+                load_time,
+                size.file / 1000,
+                size.uncompressed / 1000
+            );
+        }
+        let _ = std::fs::remove_file(&path);
+        ok
+    }
+
+    fn write_save(&self, path: &str, codec: Codec, level: i32) -> std::io::Result<SaveSize> {
+        let header = SaveHeader {
+            version: VERSION.to_string(),
+            tick: self.read::<GameTime>().tick,
+            mods: self.read::<UsedMods>().0.clone(),
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        };
+
+        // the entities are compressed while the resources are serialized
+        let entities = Bincode::encode(&self.world)?;
+        let resources = unsafe { &*addr_of!(SAVELOAD_FUNCS) }
+            .iter()
+            .map(|l| (l.name, (l.save)(self)));
+
+        savefile::write_save_with(
+            File::create(path)?,
+            &header,
+            std::iter::once((ENTITIES_BLOCK, entities)).chain(resources),
+            codec,
+            level,
+        )
+    }
+
     pub fn pos<E: WorldTransform>(&self, id: E) -> Option<Vec3> {
         self.world.pos(id)
     }
@@ -331,6 +602,108 @@ impl Simulation {
         self.resources.read()
     }
 
+    /// Value of a setting of a mod for this game, None if the loaded mods do not declare it
+    pub fn mod_setting(&self, mod_name: &str, key: &str) -> Option<ModSettingValue> {
+        self.read::<ModSettings>().get(mod_name, key).cloned()
+    }
+
+    /// Qualified names of the prototypes the game refers to: buildings with their build
+    /// categories, vehicles, wagons, the items someone owns, orders or stocks, the crops of the
+    /// fields, the deposits and the props of the map, the scenario with its tutorial and unlocked
+    /// buildings, and the street names given to the roads. A reload of the prototypes must not
+    /// remove them.
+    pub fn pinned_prototypes(&self) -> BTreeSet<String> {
+        let mut pinned = BTreeSet::new();
+        for b in self.map().buildings().values() {
+            if let Some(cat) = b.kind.build_menu().and_then(|menu| menu.category) {
+                pinned.extend(try_prototype(cat).map(|p| p.qualified_name()));
+            }
+            pinned.extend(match b.kind {
+                BuildingKind::GoodsCompany(id) => try_prototype(id).map(|p| p.qualified_name()),
+                BuildingKind::RailFreightStation(id) => {
+                    try_prototype(id).map(|p| p.qualified_name())
+                }
+                BuildingKind::Dock(id) => try_prototype(id).map(|p| p.qualified_name()),
+                BuildingKind::Warehouse(id) => try_prototype(id).map(|p| p.qualified_name()),
+                BuildingKind::ServiceDepot(id) => try_prototype(id).map(|p| p.qualified_name()),
+                BuildingKind::Leisure(id) => try_prototype(id).map(|p| p.qualified_name()),
+                BuildingKind::House
+                | BuildingKind::TrainStation
+                | BuildingKind::ExternalTrading => None,
+            });
+        }
+        for v in self.world.vehicles.values() {
+            pinned.extend(v.vehicle.prototype().map(|p| p.qualified_name()));
+        }
+        for w in self.world.wagons.values() {
+            pinned.extend(try_prototype(w.wagon.rolling_stock).map(|p| p.qualified_name()));
+        }
+
+        let mut items: BTreeSet<ItemID> = self.read::<Market>().items_in_use().collect();
+        let orders = self.read::<GovernmentOrders>();
+        items.extend(orders.orders.values().map(|o| o.item));
+        items.extend(orders.stock.keys().copied());
+        drop(orders);
+        for c in self.world.companies.values() {
+            items.extend(c.comp.orders.iter().map(|o| o.kind));
+            items.extend(c.bought.0.keys().copied());
+            if let Some(ref field) = c.comp.field {
+                pinned.extend(try_prototype(field.crop).map(|p| p.qualified_name()));
+            }
+        }
+        for w in self.world.warehouses.values() {
+            items.extend(w.w.rules.iter().map(|r| r.item));
+        }
+        pinned.extend(
+            items
+                .into_iter()
+                .filter_map(|id| try_prototype(id).map(|p| p.qualified_name())),
+        );
+
+        let map = self.map();
+        for d in map.deposits().values() {
+            pinned.extend(try_prototype(d.kind).map(|p| p.qualified_name()));
+        }
+        for proto in map.scenery.removed_prototypes() {
+            pinned.extend(try_prototype(proto).map(|p| p.qualified_name()));
+        }
+        let road_names: BTreeSet<&str> = map.roads().values().map(|r| r.name.as_str()).collect();
+        for pool in StreetNamesPrototype::iter() {
+            if pool.names.iter().any(|name| {
+                pool.suffixes
+                    .iter()
+                    .any(|suffix| road_names.contains(&*format!("{} {}", name, suffix)))
+            }) {
+                pinned.insert(pool.qualified_name());
+            }
+        }
+        drop(map);
+
+        let state = self.read::<ScenarioState>();
+        // the scenario the game was created with, saves older than the options may not have it
+        let created_with = self
+            .resources
+            .try_read::<SimulationOptions>()
+            .ok()
+            .and_then(|opts| opts.scenario);
+        for scenario in [state.scenario, created_with].into_iter().flatten() {
+            let Some(proto) = try_prototype(scenario) else {
+                continue;
+            };
+            pinned.insert(proto.qualified_name());
+            pinned.extend(
+                proto
+                    .tutorial
+                    .and_then(try_prototype)
+                    .map(|p| p.qualified_name()),
+            );
+        }
+        for &b in state.unlocked.iter().flatten() {
+            pinned.extend(try_prototype(b).map(|p| p.qualified_name()));
+        }
+        pinned
+    }
+
     pub fn map(&self) -> Ref<'_, Map> {
         self.resources.read()
     }
@@ -380,6 +753,24 @@ struct SimulationSer<'a> {
     res: FastMap<String, Vec<u8>>,
 }
 
+/// Steps of loading a save from disk, in order
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SaveLoadStep {
+    ReadingFile,
+    DeserializingEntities,
+    DeserializingMap,
+}
+
+/// Names and versions of the mods the simulation was created with, in load order
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsedMods(pub Vec<String>);
+
+impl Default for UsedMods {
+    fn default() -> Self {
+        Self(prototypes::loaded_mods().to_vec())
+    }
+}
+
 #[derive(Deserialize)]
 struct SimulationDeser {
     world: World,
@@ -387,34 +778,9 @@ struct SimulationDeser {
     res: FastMap<String, Vec<u8>>,
 }
 
-impl<'de> Deserialize<'de> for Simulation {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        log::info!("deserializing sim state");
-        let t = Instant::now();
-
-        let mut simdeser = <SimulationDeser as Deserialize>::deserialize(deserializer)?;
-
-        log::info!(
-            "took {}s to deserialize base deser",
-            t.elapsed().as_secs_f32()
-        );
-
-        let cur_version_parts = VERSION.split('.').collect::<Vec<_>>();
-        let deser_parts = simdeser.version.split('.').collect::<Vec<_>>();
-
-        if cur_version_parts[0] != deser_parts[0]
-            || (cur_version_parts[0] == "0" && cur_version_parts[1] != deser_parts[1])
-        {
-            log::warn!(
-                "incompatible version, save might be corrupted! save is: {} - game is: {}",
-                simdeser.version,
-                VERSION
-            );
-        }
-
+impl Simulation {
+    /// An empty simulation with its resources registered, for a save to be loaded into
+    fn new_for_load() -> Self {
         let mut sim = Self {
             world: World::default(),
             resources: Resources::default(),
@@ -426,6 +792,22 @@ impl<'de> Deserialize<'de> for Simulation {
             }
         }
 
+        sim
+    }
+
+    fn load_resource(&mut self, name: &str, data: Vec<u8>) {
+        match unsafe { &*addr_of!(SAVELOAD_FUNCS) }
+            .iter()
+            .find(|l| l.name == name)
+        {
+            Some(l) => (l.load)(self, data),
+            None => log::warn!("unknown resource {} in the save, ignoring it", name),
+        }
+    }
+
+    /// Builds the simulation from the world and the serialized resources of a save
+    fn from_deser(mut simdeser: SimulationDeser) -> Self {
+        let mut sim = Self::new_for_load();
         sim.world = simdeser.world;
 
         unsafe {
@@ -436,6 +818,64 @@ impl<'de> Deserialize<'de> for Simulation {
             }
         }
 
+        sim.check_loaded(&simdeser.version);
+        sim
+    }
+
+    /// Warns when the save was made by another version or with other mods
+    fn check_loaded(&mut self, version: &str) {
+        let cur_version_parts = VERSION.split('.').collect::<Vec<_>>();
+        let deser_parts = version.split('.').collect::<Vec<_>>();
+
+        if cur_version_parts[0] != deser_parts[0]
+            || (cur_version_parts[0] == "0" && cur_version_parts.get(1) != deser_parts.get(1))
+        {
+            log::warn!(
+                "incompatible version, save might be corrupted! save is: {} - game is: {}",
+                version,
+                VERSION
+            );
+        }
+
+        let used_mods = self.read::<UsedMods>();
+        if used_mods.0 != prototypes::loaded_mods() {
+            log::warn!(
+                "the save was made with different mods, it might be affected! save has: {:?} - game has: {:?}",
+                used_mods.0,
+                prototypes::loaded_mods()
+            );
+        }
+        drop(used_mods);
+
+        let mut mod_settings = self.write::<ModSettings>();
+        mod_settings.sanitize_loaded();
+        let differences = mod_settings.startup_differences(prototypes::loaded_mod_settings());
+        if !differences.is_empty() {
+            log::warn!(
+                "the save was made with different startup settings, the prototypes must be reloaded to match: {:?}",
+                differences
+            );
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Simulation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        log::info!("deserializing sim state");
+        let t = Instant::now();
+
+        let simdeser = <SimulationDeser as Deserialize>::deserialize(deserializer)?;
+
+        log::info!(
+            "took {}s to deserialize base deser",
+            t.elapsed().as_secs_f32()
+        );
+
+        let sim = Self::from_deser(simdeser);
+
         log::info!(
             "took {}s to deserialize in total",
             t.elapsed().as_secs_f32()
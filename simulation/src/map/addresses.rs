@@ -0,0 +1,218 @@
+//! Street names and building addresses.
+//! Addresses are not stored, they are derived from the order of the buildings along their road
+//! so renaming a street or building along it updates them.
+
+use prototypes::StreetNamesPrototype;
+
+use crate::map::{BuildingID, IntersectionID, Map, Road, RoadID};
+use crate::utils::rand_provider::RandProvider;
+
+/// A road keeps the name of the street it continues if they meet at less than ~25 degrees
+const STRAIGHT_COS: f32 = 0.9;
+/// Attempts at finding a name not used by another street before accepting a duplicate
+const NAME_TRIES: usize = 10;
+/// Longest street name accepted when renaming
+pub const MAX_STREET_NAME_LEN: usize = 40;
+
+impl Map {
+    /// Gives a name to the roads that have none.
+    /// A road continuing a named street straight through an intersection keeps its name,
+    /// otherwise it is picked from the street names prototypes. Rails are not named.
+    pub(crate) fn name_new_roads(&mut self, rng: &mut RandProvider) {
+        let unnamed: Vec<RoadID> = self
+            .roads
+            .values()
+            .filter(|r| r.name.is_empty() && r.lanes_iter().any(|(_, kind)| !kind.is_rail()))
+            .map(|r| r.id)
+            .collect();
+
+        for id in unnamed {
+            let Some(road) = self.roads.get(id) else {
+                continue;
+            };
+            let name = self
+                .continued_name(road)
+                .or_else(|| self.new_street_name(rng));
+            let Some(name) = name else {
+                return;
+            };
+            if let Some(road) = self.roads.get_mut(id) {
+                road.name = name;
+            }
+        }
+    }
+
+    /// Renames the street, the addresses along it follow
+    pub fn rename_road(&mut self, id: RoadID, name: &str) {
+        let name = name.trim();
+        if name.is_empty() || name.len() > MAX_STREET_NAME_LEN {
+            log::warn!("invalid street name {:?} for {:?}", name, id);
+            return;
+        }
+        let Some(road) = self.roads.get_mut(id) else {
+            log::warn!("trying to rename non-existing road {:?}", id);
+            return;
+        };
+        road.name = name.to_string();
+    }
+
+    /// Number of the building on its street.
+    /// Going from the start of the road, buildings on the left get odd numbers and the ones on
+    /// the right even numbers, both counting up.
+    pub fn house_number(&self, id: BuildingID) -> Option<u32> {
+        let b = self.buildings.get(id)?;
+        let road = self.roads.get(b.connected_road?)?;
+
+        let place = |id: BuildingID| {
+            let b = self.buildings.get(id)?;
+            let center = b.obb.center().z(b.height);
+            let (proj, _, dir) = road.points.project_segment_dir(center);
+            let left = dir.xy().perp_dot((center - proj).xy()) > 0.0;
+            Some((left, road.points.length_at_proj(proj)))
+        };
+
+        let (left, dist) = place(id)?;
+        let before = road
+            .connected_buildings
+            .iter()
+            .filter(|&&other| other != id)
+            .filter_map(|&other| Some((other, place(other)?)))
+            .filter(|&(other, (other_left, other_dist))| {
+                other_left == left && (other_dist, other) < (dist, id)
+            })
+            .count() as u32;
+
+        Some(match left {
+            true => 2 * before + 1,
+            false => 2 * before + 2,
+        })
+    }
+
+    /// Address of the building, like "12 Elm Street", None if its road has no name
+    pub fn address(&self, id: BuildingID) -> Option<String> {
+        let road = self.roads.get(self.buildings.get(id)?.connected_road?)?;
+        if road.name.is_empty() {
+            return None;
+        }
+        Some(format!("{} {}", self.house_number(id)?, road.name))
+    }
+
+    /// Name of the intersection from the streets meeting there, like "Elm Street & Oak Avenue",
+    /// None if none of them has a name
+    pub fn intersection_name(&self, id: IntersectionID) -> Option<String> {
+        let mut names: Vec<&str> = vec![];
+        for road in &self.intersections.get(id)?.roads {
+            let Some(road) = self.roads.get(*road) else {
+                continue;
+            };
+            if !road.name.is_empty() && !names.contains(&&*road.name) {
+                names.push(&road.name);
+            }
+        }
+        if names.is_empty() {
+            return None;
+        }
+        Some(names.join(" & "))
+    }
+
+    fn continued_name(&self, road: &Road) -> Option<String> {
+        for inter in [road.src, road.dst] {
+            let Some(i) = self.intersections.get(inter) else {
+                continue;
+            };
+            let dir = road.dir_from(inter);
+            for &other in &i.roads {
+                let Some(o) = self.roads.get(other) else {
+                    continue;
+                };
+                if other == road.id || o.name.is_empty() {
+                    continue;
+                }
+                if dir.dot(o.dir_from(inter)) < -STRAIGHT_COS {
+                    return Some(o.name.clone());
+                }
+            }
+        }
+        None
+    }
+
+    /// A name from the street names prototypes, avoiding the ones already used if possible
+    fn new_street_name(&self, rng: &mut RandProvider) -> Option<String> {
+        let pools: Vec<&StreetNamesPrototype> = StreetNamesPrototype::iter().collect();
+        let pick = |list: &[String], rng: &mut RandProvider| {
+            list.get(rng.next_u32() as usize % list.len().max(1))
+                .cloned()
+        };
+
+        let mut name = None;
+        for _ in 0..NAME_TRIES {
+            let pool = pools.get(rng.next_u32() as usize % pools.len().max(1))?;
+            let candidate = format!("{} {}", pick(&pool.names, rng)?, pick(&pool.suffixes, rng)?);
+            let used = self.roads.values().any(|r| r.name == candidate);
+            name = Some(candidate);
+            if !used {
+                break;
+            }
+        }
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3};
+
+    use crate::tests::TestCtx;
+    use crate::utils::rand_provider::RandProvider;
+    use crate::world_command::WorldCommand;
+
+    #[test]
+    fn addresses_follow_the_street() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(200.0, 0.0, 0.0),
+            vec3(400.0, 0.0, 0.0),
+        ]);
+        {
+            let mut rng = test.g.write::<RandProvider>();
+            test.g.map_mut().name_new_roads(&mut rng);
+        }
+
+        // the second road continues the first one straight, it is the same street
+        let names: Vec<String> = test
+            .g
+            .map()
+            .roads()
+            .values()
+            .map(|r| r.name.clone())
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(!names[0].is_empty());
+        assert_eq!(names[0], names[1]);
+
+        let first = test.build_house_near(vec2(50.0, 20.0));
+        let second = test.build_house_near(vec2(120.0, 20.0));
+        let across = test.build_house_near(vec2(50.0, -20.0));
+
+        let map = test.g.map();
+        let n_first = map.house_number(first).unwrap();
+        let n_second = map.house_number(second).unwrap();
+        let n_across = map.house_number(across).unwrap();
+        assert!(n_first < n_second);
+        assert_eq!(n_first % 2, n_second % 2);
+        assert_ne!(n_first % 2, n_across % 2);
+
+        let road = map.buildings()[first].connected_road.unwrap();
+        drop(map);
+
+        test.apply(&[WorldCommand::RenameRoad {
+            road,
+            name: "Main Street".to_string(),
+        }]);
+        assert_eq!(
+            test.g.map().address(first),
+            Some(format!("{} Main Street", n_first))
+        );
+    }
+}
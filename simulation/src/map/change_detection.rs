@@ -1,11 +1,16 @@
 //! This module contains the change detection system for the map.
-//! This should not be used inside the simulation as change subscribers are not serialized.
+//! Subscribers and revisions are not serialized, so the simulation should only use them as hints
+//! to refresh its caches, never as state.
 //! It is mostly for rendering purposes by decoupling it from the simulation.
+//!
+//! Each change of the map dispatches the precise [`UpdateType`]s it affects in the chunks it
+//! touches. Renderers subscribe to the types they draw, and can compare the revision of a chunk
+//! with the one they drew if they missed some updates.
 
 use crate::map::{Building, Intersection, Lot, Road};
 use common::{ChunkID, ChunkID_1024};
 use geom::Vec2;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::{Arc, Mutex};
 
 // CanonicalPosition is a trait that describes the canonical position of an object.
@@ -17,38 +22,114 @@ pub trait CanonicalPosition {
 pub type SubscriberChunkID = ChunkID_1024;
 
 bitflags::bitflags! {
-    #[derive(Debug, Copy, Clone)]
-    pub struct UpdateType: u8 {
-        const Road = 1;
-        const Building = 1 << 1;
-        const Terrain = 1 << 2;
+    #[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+    pub struct UpdateType: u16 {
+        /// Roads and lanes added, removed or reshaped, and the shape of the intersections
+        const RoadGeometry = 1;
+        /// Turns and traffic control of the intersections
+        const IntersectionControl = 1 << 1;
+        /// Wear, overhead lines, speed limits and blocked lanes of a road whose shape is unchanged
+        const RoadSurface = 1 << 2;
+        const BuildingAdded = 1 << 3;
+        const BuildingRemoved = 1 << 4;
+        /// Kind, shape, zone or dereliction of an existing building
+        const BuildingChanged = 1 << 5;
+        /// Lots added, removed or zoned
+        const LotZoning = 1 << 6;
+        /// Height of the terrain, including the overrides under roads and buildings
+        const TerrainHeight = 1 << 7;
+        /// Trees and props laid on the terrain
+        const TerrainSplat = 1 << 8;
+        /// Extent and level of the sea and the lakes
+        const Water = 1 << 9;
+    }
+}
+
+const UPDATE_TYPE_COUNT: usize = 10;
+
+impl UpdateType {
+    /// Index of each single type in the set
+    fn indices(self) -> impl Iterator<Item = usize> {
+        (0..UPDATE_TYPE_COUNT).filter(move |i| self.bits() & (1 << i) != 0)
     }
 }
 
 #[derive(Default)]
-pub struct MapSubscribers(Mutex<Vec<MapSubscriber>>);
+pub struct MapSubscribers(Mutex<SubscribersInner>);
+
+#[derive(Default)]
+struct SubscribersInner {
+    subscribers: Vec<MapSubscriber>,
+    /// Incremented at each dispatch
+    revision: u64,
+    /// Revision of the last update of each type, by chunk
+    chunk_revisions: BTreeMap<SubscriberChunkID, [u64; UPDATE_TYPE_COUNT]>,
+}
+
+impl SubscribersInner {
+    fn dispatch(&mut self, update_type: UpdateType, chunk: SubscriberChunkID) {
+        self.revision += 1;
+        let revisions = self.chunk_revisions.entry(chunk).or_default();
+        for i in update_type.indices() {
+            revisions[i] = self.revision;
+        }
+        for sub in self.subscribers.iter_mut() {
+            sub.dispatch(update_type, chunk);
+        }
+    }
+}
 
 impl MapSubscribers {
     pub fn subscribe(&self, filter: UpdateType) -> MapSubscriber {
         let sub = MapSubscriber::new(filter);
-        self.0.lock().unwrap().push(sub.clone());
+        self.0.lock().unwrap().subscribers.push(sub.clone());
         sub
     }
 
+    /// One subscriber for all the given types
+    pub fn subscribe_multi(&self, filters: &[UpdateType]) -> MapSubscriber {
+        self.subscribe(
+            filters
+                .iter()
+                .fold(UpdateType::empty(), |acc, &filter| acc | filter),
+        )
+    }
+
+    /// Revision of the last update
+    pub fn revision(&self) -> u64 {
+        self.0.lock().unwrap().revision
+    }
+
+    /// Revision of the last update of the given types in the chunk, 0 if there was none
+    pub fn chunk_revision(&self, chunk: SubscriberChunkID, filter: UpdateType) -> u64 {
+        let inner = self.0.lock().unwrap();
+        let Some(revisions) = inner.chunk_revisions.get(&chunk) else {
+            return 0;
+        };
+        filter.indices().map(|i| revisions[i]).max().unwrap_or(0)
+    }
+
+    /// Chunks with an update of the given types after the revision
+    pub fn changed_since(&self, revision: u64, filter: UpdateType) -> Vec<SubscriberChunkID> {
+        let inner = self.0.lock().unwrap();
+        inner
+            .chunk_revisions
+            .iter()
+            .filter(|(_, revisions)| filter.indices().any(|i| revisions[i] > revision))
+            .map(|(&chunk, _)| chunk)
+            .collect()
+    }
+
     pub fn dispatch_all(&self, chunks: impl Iterator<Item = SubscriberChunkID>) {
         let mut me = self.0.lock().unwrap();
         for chunk in chunks {
-            for sub in me.iter_mut() {
-                sub.dispatch(UpdateType::Road, chunk);
-                sub.dispatch(UpdateType::Building, chunk);
-                sub.dispatch(UpdateType::Terrain, chunk);
-            }
+            me.dispatch(UpdateType::all(), chunk);
         }
     }
 
     pub fn dispatch_clear(&self) {
-        let mut me = self.0.lock().unwrap();
-        for sub in me.iter_mut() {
+        let me = self.0.lock().unwrap();
+        for sub in me.subscribers.iter() {
             sub.inner.lock().unwrap().cleared = true;
         }
     }
@@ -71,11 +152,9 @@ impl MapSubscribers {
         update_type: UpdateType,
         chunks: impl Iterator<Item = SubscriberChunkID>,
     ) {
-        let mut me = self.0.lock().unwrap();
+        let me = self.0.get_mut().unwrap();
         for chunk in chunks {
-            for sub in me.iter_mut() {
-                sub.dispatch(update_type, chunk);
-            }
+            me.dispatch(update_type, chunk);
         }
     }
 }
@@ -83,10 +162,12 @@ impl MapSubscribers {
 #[derive(Default)]
 pub struct MapSubscriberInner {
     pub updated_chunks: BTreeSet<SubscriberChunkID>,
+    /// Types of the updates since they were last taken, among the ones subscribed to
+    pub updated_types: UpdateType,
     pub cleared: bool,
 }
 
-/// Describes a subscriber to a specific UpdateType
+/// Describes a subscriber to some UpdateTypes
 #[derive(Clone)]
 pub struct MapSubscriber {
     filter: UpdateType,
@@ -101,6 +182,10 @@ impl MapSubscriber {
         }
     }
 
+    pub fn filter(&self) -> UpdateType {
+        self.filter
+    }
+
     pub fn take_updated_chunks(&mut self) -> impl Iterator<Item = SubscriberChunkID> {
         let mut inner = self.inner.lock().unwrap();
         std::mem::take(&mut inner.updated_chunks).into_iter()
@@ -111,6 +196,16 @@ impl MapSubscriber {
         inner.updated_chunks.pop_first()
     }
 
+    pub fn take_updated_types(&mut self) -> UpdateType {
+        let mut inner = self.inner.lock().unwrap();
+        std::mem::replace(&mut inner.updated_types, UpdateType::empty())
+    }
+
+    /// Number of chunks updated since they were last taken
+    pub fn pending_chunks(&self) -> usize {
+        self.inner.lock().unwrap().updated_chunks.len()
+    }
+
     pub fn take_cleared(&mut self) -> bool {
         let mut inner = self.inner.lock().unwrap();
         std::mem::take(&mut inner.cleared)
@@ -122,6 +217,7 @@ impl MapSubscriber {
         }
         let mut inner = self.inner.lock().unwrap();
         inner.updated_chunks.insert(chunk_id);
+        inner.updated_types |= update_type & self.filter;
     }
 }
 
@@ -0,0 +1,180 @@
+//! Raw material deposits under the terrain, scattered at map creation from the deposit prototypes.
+//! Each patch holds a finite quantity that the extraction companies built on it deplete.
+
+use geom::{Circle, Vec2, OBB};
+use prototypes::{DepositID, DepositPrototype};
+use serde::{Deserialize, Serialize};
+use slotmapd::{new_key_type, HopSlotMap};
+
+use crate::map::{Environment, Map};
+
+new_key_type! {
+    pub struct DepositPatchID;
+}
+
+pub type Deposits = HopSlotMap<DepositPatchID, Deposit>;
+
+/// Share of the deposit left under which the extraction slows down
+const THROTTLE_SHARE: f32 = 0.5;
+/// Slowest extraction, so that the last units still come out in a reasonable time
+const MIN_RICHNESS: f32 = 0.2;
+
+/// A patch of raw material, its quantity is counted in runs of the extraction recipe
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Deposit {
+    pub id: DepositPatchID,
+    pub kind: DepositID,
+    pub shape: Circle,
+    pub initial: u32,
+    pub remaining: u32,
+}
+
+impl Deposit {
+    pub fn contains(&self, p: Vec2) -> bool {
+        self.shape.contains(p)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// In [0; 1] range, the share of the quantity left
+    pub fn share_left(&self) -> f32 {
+        if self.initial == 0 {
+            return 0.0;
+        }
+        self.remaining as f32 / self.initial as f32
+    }
+
+    /// Multiplier of the extraction speed: full until the deposit is half empty,
+    /// then slower as it runs dry, and 0 once exhausted
+    pub fn richness(&self) -> f32 {
+        if self.is_exhausted() {
+            return 0.0;
+        }
+        (self.share_left() / THROTTLE_SHARE).clamp(MIN_RICHNESS, 1.0)
+    }
+}
+
+/// Scatters the patches of every deposit prototype over the land of the terrain.
+/// `abundance` multiplies the number of patches, none are generated at 0.
+pub fn generate_deposits(env: &Environment, abundance: f32, seed: u64) -> Deposits {
+    let mut deposits = Deposits::default();
+    if abundance <= 0.0 {
+        return deposits;
+    }
+    let bounds = env.bounds();
+    let size = bounds.size();
+    let area_km2 = size.x * size.y / 1_000_000.0;
+
+    for proto in DepositPrototype::iter() {
+        let mut rng = common::rand::gen(common::hash_u64((seed, proto.id)));
+        let n = (proto.density * abundance * area_km2).round() as u32;
+
+        for _ in 0..n {
+            let center = bounds.ll + Vec2::new(rng.next_f32(), rng.next_f32()) * size;
+            let radius = proto.radius * (0.6 + 0.8 * rng.next_f32());
+            let amount = (proto.amount as f32 * (0.5 + rng.next_f32())).ceil() as u32;
+
+            // the patches lie under the land only, where they can be reached
+            if !env.is_fertile(center) {
+                continue;
+            }
+
+            deposits.insert_with_key(|id| Deposit {
+                id,
+                kind: proto.id,
+                shape: Circle::new(center, radius),
+                initial: amount,
+                remaining: amount,
+            });
+        }
+    }
+    deposits
+}
+
+impl Map {
+    pub fn deposits(&self) -> &Deposits {
+        &self.deposits
+    }
+
+    /// The deposits containing the point, the richest first
+    pub fn deposits_at(&self, p: Vec2) -> Vec<&Deposit> {
+        let mut v: Vec<&Deposit> = self.deposits.values().filter(|d| d.contains(p)).collect();
+        v.sort_by(|a, b| b.remaining.cmp(&a.remaining).then(a.id.cmp(&b.id)));
+        v
+    }
+
+    /// The richest deposit of that kind under the center of the building shape, exhausted or not
+    pub fn deposit_under(&self, obb: &OBB, kind: DepositID) -> Option<&Deposit> {
+        self.deposits_at(obb.center())
+            .into_iter()
+            .find(|d| d.kind == kind)
+    }
+
+    /// Whether an extraction company can be built there: its center is on a deposit of that kind
+    /// that is not exhausted
+    pub fn can_extract(&self, obb: &OBB, kind: DepositID) -> bool {
+        self.deposit_under(obb, kind)
+            .is_some_and(|d| !d.is_exhausted())
+    }
+
+    /// Takes one unit from the deposit under the building shape.
+    /// Returns false if there is nothing left to extract.
+    pub fn extract_deposit(&mut self, obb: &OBB, kind: DepositID) -> bool {
+        let Some(id) = self.deposit_under(obb, kind).map(|d| d.id) else {
+            return false;
+        };
+        let Some(d) = self.deposits.get_mut(id) else {
+            return false;
+        };
+        if d.remaining == 0 {
+            return false;
+        }
+        d.remaining -= 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec2;
+
+    use super::*;
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn deposits_slow_down_and_run_dry() {
+        let test = TestCtx::new();
+        let kind = DepositID::new("iron-ore");
+        let mut map = test.g.map_mut();
+        map.deposits.clear();
+        map.deposits.insert_with_key(|id| Deposit {
+            id,
+            kind,
+            shape: Circle::new(vec2(100.0, 100.0), 50.0),
+            initial: 10,
+            remaining: 10,
+        });
+
+        let on = OBB::new(vec2(110.0, 100.0), Vec2::X, 20.0, 20.0);
+        let off = OBB::new(vec2(300.0, 100.0), Vec2::X, 20.0, 20.0);
+        assert!(map.can_extract(&on, kind));
+        assert!(!map.can_extract(&off, kind));
+        assert!(!map.can_extract(&on, DepositID::new("oil")));
+
+        assert_eq!(map.deposit_under(&on, kind).unwrap().richness(), 1.0);
+        for _ in 0..8 {
+            assert!(map.extract_deposit(&on, kind));
+        }
+        let richness = map.deposit_under(&on, kind).unwrap().richness();
+        assert!(richness < 1.0 && richness > 0.0);
+
+        for _ in 0..2 {
+            assert!(map.extract_deposit(&on, kind));
+        }
+        assert!(!map.extract_deposit(&on, kind));
+        assert!(!map.can_extract(&on, kind));
+        assert_eq!(map.deposit_under(&on, kind).unwrap().richness(), 0.0);
+    }
+}
@@ -0,0 +1,195 @@
+use geom::{Color, Polygon, Segment, Vec2};
+use serde::{Deserialize, Serialize};
+use slotmapd::{new_key_type, HopSlotMap};
+
+use crate::map::{BuildingID, Map, RoadID};
+
+new_key_type! {
+    pub struct DistrictID;
+}
+
+pub type Districts = HopSlotMap<DistrictID, District>;
+
+/// Longest name a district can be given
+pub const MAX_DISTRICT_NAME_LEN: usize = 40;
+/// Districts smaller than this, in square meters, are rejected
+pub const MIN_DISTRICT_AREA: f32 = 2500.0;
+
+/// Named area of the city painted by the player.
+/// Buildings and roads belong to the district their center is in.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct District {
+    pub id: DistrictID,
+    pub name: String,
+    pub color: Color,
+    pub shape: Polygon,
+}
+
+impl District {
+    pub fn contains(&self, p: Vec2) -> bool {
+        self.shape.contains(p)
+    }
+}
+
+/// Whether the two segments cross each other, touching at an end does not count
+fn crosses(a: Segment, b: Segment) -> bool {
+    let side = |s: Segment, p: Vec2| (s.dst - s.src).perp_dot(p - s.src);
+    side(a, b.src) * side(a, b.dst) < -0.01 && side(b, a.src) * side(b, a.dst) < -0.01
+}
+
+/// Whether the shape can be a district: enough points, large enough and not crossing itself
+pub fn valid_district_shape(shape: &Polygon) -> bool {
+    if shape.len() < 3 || shape.area() < MIN_DISTRICT_AREA {
+        return false;
+    }
+    let segs: Vec<Segment> = shape.segments().collect();
+    !segs
+        .iter()
+        .enumerate()
+        .any(|(i, &a)| segs[i + 1..].iter().any(|&b| crosses(a, b)))
+}
+
+/// Whether the two shapes share some area.
+/// Shapes touching along their borders do not overlap, so neighbouring districts can share edges.
+pub fn districts_overlap(a: &Polygon, b: &Polygon) -> bool {
+    let strictly_inside = |shape: &Polygon, p: Vec2| shape.contains(p) && shape.distance(p) > 0.5;
+
+    a.segments().any(|s| b.segments().any(|s2| crosses(s, s2)))
+        || a.iter().any(|&p| strictly_inside(b, p))
+        || b.iter().any(|&p| strictly_inside(a, p))
+        || strictly_inside(b, a.barycenter())
+        || strictly_inside(a, b.barycenter())
+}
+
+impl Map {
+    pub fn districts(&self) -> &Districts {
+        &self.districts
+    }
+
+    /// The district the point is in, if any
+    pub fn district_at(&self, p: Vec2) -> Option<DistrictID> {
+        self.districts
+            .iter()
+            .find(|(_, d)| d.contains(p))
+            .map(|(id, _)| id)
+    }
+
+    pub fn building_district(&self, b: BuildingID) -> Option<DistrictID> {
+        self.district_at(self.buildings.get(b)?.obb.center())
+    }
+
+    pub fn road_district(&self, r: RoadID) -> Option<DistrictID> {
+        let road = self.roads.get(r)?;
+        self.district_at(road.points.point_along(road.points.length() * 0.5).xy())
+    }
+
+    /// Whether the shape would overlap one of the existing districts
+    pub fn district_overlaps(&self, shape: &Polygon) -> bool {
+        self.districts
+            .values()
+            .any(|d| districts_overlap(&d.shape, shape))
+    }
+
+    /// Adds a district, unless its shape is invalid or overlaps another district
+    pub fn add_district(&mut self, name: &str, color: Color, shape: Polygon) -> Option<DistrictID> {
+        info!("add_district {:?}", name);
+        let name = name.trim();
+        if name.is_empty() || name.len() > MAX_DISTRICT_NAME_LEN {
+            log::warn!("invalid district name {:?}", name);
+            return None;
+        }
+        if !valid_district_shape(&shape) || self.district_overlaps(&shape) {
+            log::warn!("invalid or overlapping district shape for {:?}", name);
+            return None;
+        }
+
+        Some(self.districts.insert_with_key(|id| District {
+            id,
+            name: name.to_string(),
+            color,
+            shape,
+        }))
+    }
+
+    /// Renames and recolors the district
+    pub fn update_district(&mut self, id: DistrictID, name: &str, color: Color) {
+        let name = name.trim();
+        if name.is_empty() || name.len() > MAX_DISTRICT_NAME_LEN {
+            log::warn!("invalid district name {:?} for {:?}", name, id);
+            return;
+        }
+        let Some(d) = self.districts.get_mut(id) else {
+            log::warn!("trying to update non-existing district {:?}", id);
+            return;
+        };
+        d.name = name.to_string();
+        d.color = color;
+    }
+
+    pub fn remove_district(&mut self, id: DistrictID) -> Option<District> {
+        info!("remove_district {:?}", id);
+        self.districts.remove(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, Color, Polygon};
+
+    use super::*;
+
+    fn square(x: f32, y: f32, size: f32) -> Polygon {
+        Polygon(vec![
+            vec2(x, y),
+            vec2(x, y + size),
+            vec2(x + size, y + size),
+            vec2(x + size, y),
+        ])
+    }
+
+    #[test]
+    fn districts_can_share_borders_but_not_overlap() {
+        let mut map = Map::empty();
+        let a = map
+            .add_district("Old town", Color::RED, square(0.0, 0.0, 100.0))
+            .unwrap();
+
+        // neighbour sharing an edge
+        let b = map.add_district("Harbour", Color::BLUE, square(100.0, 0.0, 100.0));
+        assert!(b.is_some());
+
+        assert!(map
+            .add_district("Overlap", Color::GREEN, square(50.0, 50.0, 100.0))
+            .is_none());
+        assert!(map
+            .add_district("Inside", Color::GREEN, square(20.0, 20.0, 60.0))
+            .is_none());
+        assert!(map
+            .add_district("Same", Color::GREEN, square(0.0, 0.0, 100.0))
+            .is_none());
+        assert!(map
+            .add_district("Tiny", Color::GREEN, square(500.0, 0.0, 10.0))
+            .is_none());
+
+        assert_eq!(map.district_at(vec2(50.0, 50.0)), Some(a));
+        assert_eq!(map.district_at(vec2(150.0, 50.0)), b);
+        assert_eq!(map.district_at(vec2(250.0, 50.0)), None);
+    }
+
+    #[test]
+    fn self_crossing_shapes_are_invalid() {
+        let bowtie = Polygon(vec![
+            vec2(0.0, 0.0),
+            vec2(200.0, 200.0),
+            vec2(200.0, 0.0),
+            vec2(0.0, 120.0),
+        ]);
+        assert!(!valid_district_shape(&bowtie));
+        assert!(valid_district_shape(&Polygon(vec![
+            vec2(0.0, 0.0),
+            vec2(0.0, 100.0),
+            vec2(100.0, 100.0),
+            vec2(100.0, 0.0),
+        ])));
+    }
+}
@@ -0,0 +1,68 @@
+//! Overhead lines of the rails.
+//! Electrification is a flag of the roads, kept when they are split, it only changes how the
+//! rails and the trains running on them are drawn.
+
+use crate::map::{Map, RoadID, TraverseKind, UpdateType};
+
+impl Map {
+    /// Adds or removes the overhead line above the rails of the road
+    pub fn set_road_electrified(&mut self, id: RoadID, electrified: bool) {
+        let Some(road) = self.roads.get_mut(id) else {
+            log::warn!("trying to electrify non-existing road {:?}", id);
+            return;
+        };
+        if road.electrified == electrified || !road.has_rails() {
+            return;
+        }
+        road.electrified = electrified;
+        self.subscribers.dispatch(UpdateType::RoadSurface, road);
+    }
+
+    /// Whether there is an overhead line above the lane or turn.
+    /// A turn is electrified if the road it comes from is.
+    pub fn is_electrified(&self, kind: TraverseKind) -> bool {
+        let lane = match kind {
+            TraverseKind::Lane(id) => id,
+            TraverseKind::Turn(id) => id.src,
+        };
+        self.lanes
+            .get(lane)
+            .and_then(|lane| self.roads.get(lane.parent))
+            .is_some_and(|road| road.electrified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec3;
+
+    use crate::map::{LanePatternBuilder, ProjectFilter, TraverseKind};
+    use crate::tests::TestCtx;
+    use crate::world_command::WorldCommand;
+
+    #[test]
+    fn electrification_survives_splits() {
+        let mut test = TestCtx::new();
+        let road = {
+            let mut map = test.g.map_mut();
+            let a = map.project(vec3(0.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+            let b = map.project(vec3(300.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+            let pat = LanePatternBuilder::new().rail(true).build();
+            map.make_connection(a, b, None, &pat).unwrap().1
+        };
+
+        test.apply(&[WorldCommand::SetRoadElectrified {
+            road,
+            electrified: true,
+        }]);
+        let lane = test.g.map().roads()[road].lanes_iter().next().unwrap().0;
+        assert!(test.g.map().is_electrified(TraverseKind::Lane(lane)));
+
+        let mut map = test.g.map_mut();
+        map.split_road(road, vec3(150.0, 0.0, 0.0)).unwrap();
+        assert!(map.roads().get(road).is_none());
+        let rails: Vec<_> = map.roads().values().filter(|r| r.has_rails()).collect();
+        assert_eq!(rails.len(), 2);
+        assert!(rails.iter().all(|r| r.electrified));
+    }
+}
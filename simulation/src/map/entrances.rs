@@ -0,0 +1,109 @@
+//! The entrances of the buildings, and the ways they connect to.
+//! Buildings whose prototype doesn't define any entrance only have their door,
+//! which everyone goes through.
+
+use geom::{Vec3, OBB};
+use prototypes::{EntranceDef, EntranceKind};
+use serde::{Deserialize, Serialize};
+
+use crate::map::{Building, Environment, LaneID, LaneKind, Map};
+
+/// How far an entrance can be from the way it connects to
+pub const ENTRANCE_RANGE: f32 = 30.0;
+
+/// An access point of a building, in world space
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Entrance {
+    pub pos: Vec3,
+    pub kind: EntranceKind,
+    pub optional: bool,
+}
+
+/// A required entrance of the building would not reach any way
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct UnconnectedEntrance(pub EntranceKind);
+
+impl UnconnectedEntrance {
+    /// Why the building can't be placed, shown to the player
+    pub fn reason(&self) -> String {
+        let way = match self.0 {
+            EntranceKind::Pedestrian => "sidewalk",
+            EntranceKind::Vehicle | EntranceKind::Freight => "road",
+        };
+        format!(
+            "{} entrance has no {} within {}m",
+            self.0.label(),
+            way,
+            ENTRANCE_RANGE
+        )
+    }
+}
+
+/// The lanes an entrance of that kind connects to
+fn entrance_lane_kind(kind: EntranceKind) -> LaneKind {
+    match kind {
+        EntranceKind::Pedestrian => LaneKind::Walking,
+        EntranceKind::Vehicle | EntranceKind::Freight => LaneKind::Driving,
+    }
+}
+
+impl Building {
+    /// Places the entrances of the prototype on a building occupying the obb
+    pub fn gen_entrances(env: &Environment, obb: &OBB, defs: &[EntranceDef]) -> Vec<Entrance> {
+        defs.iter()
+            .map(|def| {
+                let pos = def.pos_on(obb);
+                Entrance {
+                    pos: pos.z(env.height(pos).unwrap_or(0.0) + 0.1),
+                    kind: def.kind,
+                    optional: def.optional,
+                }
+            })
+            .collect()
+    }
+
+    /// The entrance closest to `from` for that kind of traveler.
+    /// Freight can go through the vehicle entrances, and the door is used when there is none.
+    pub fn entrance(&self, kind: EntranceKind, from: Vec3) -> Vec3 {
+        let closest = |kind| {
+            self.entrances
+                .iter()
+                .filter(move |e| e.kind == kind)
+                .map(|e| e.pos)
+                .min_by(|a, b| a.distance2(from).total_cmp(&b.distance2(from)))
+        };
+        closest(kind)
+            .or_else(|| match kind {
+                EntranceKind::Freight => closest(EntranceKind::Vehicle),
+                _ => None,
+            })
+            .unwrap_or(self.door_pos)
+    }
+
+    /// Whether pos is next to the door or one of the entrances
+    pub fn is_at_entrance(&self, pos: Vec3, dist: f32) -> bool {
+        self.door_pos.is_close(pos, dist)
+            || self.entrances.iter().any(|e| e.pos.is_close(pos, dist))
+    }
+}
+
+impl Map {
+    /// The lane the entrance connects to, if there is one in range
+    pub fn entrance_lane(&self, e: &Entrance) -> Option<LaneID> {
+        self.nearest_lane(e.pos, entrance_lane_kind(e.kind), Some(ENTRANCE_RANGE))
+    }
+
+    /// Checks that every required entrance of a building occupying the obb reaches a way
+    pub fn check_entrances(
+        &self,
+        obb: &OBB,
+        defs: &[EntranceDef],
+    ) -> Result<(), UnconnectedEntrance> {
+        for e in Building::gen_entrances(&self.environment, obb, defs) {
+            if !e.optional && self.entrance_lane(&e).is_none() {
+                return Err(UnconnectedEntrance(e.kind));
+            }
+        }
+        Ok(())
+    }
+}
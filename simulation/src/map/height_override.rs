@@ -57,7 +57,7 @@ impl OverrideSetter {
     fn finish(self, map: &mut Map) {
         map.environment.set_overrides(self.chunk, self.overrides);
         map.subscribers
-            .dispatch_chunk(UpdateType::Terrain, self.chunk);
+            .dispatch_chunk(UpdateType::TerrainHeight, self.chunk);
     }
 }
 
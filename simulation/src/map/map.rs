@@ -2,17 +2,19 @@ use crate::map::electricity_cache::ElectricityCache;
 use crate::map::height_override::find_overrides;
 use crate::map::serializing::SerializedMap;
 use crate::map::{
-    Building, BuildingID, BuildingKind, Environment, Intersection, IntersectionID, Lane, LaneID,
-    LaneKind, LanePattern, Lot, LotID, LotKind, MapSubscriber, MapSubscribers, ParkingSpotID,
-    ParkingSpots, ProjectFilter, ProjectKind, Road, RoadID, RoadSegmentKind, SpatialMap,
-    SubscriberChunkID, TerraformKind, UpdateType, Zone,
+    Building, BuildingID, BuildingKind, Deposits, Districts, Environment, FieldStage, Intersection,
+    IntersectionID, Lane, LaneID, LaneKind, LanePattern, Lot, LotID, LotKind, MapSubscriber,
+    MapSubscribers, ParkingSpotID, ParkingSpots, ProjectFilter, ProjectKind, RestrictedAction,
+    Restrictions, Road, RoadID, RoadSegmentKind, RoutingGraph, Scenery, SpatialMap,
+    SubscriberChunkID, TerraformKind, TerrainChunkID, UpdateType, Zone, ZoneBrush,
+    LAKE_EQUALIZATION_PER_DAY, TREE_SPACING,
 };
-use geom::OBB;
-use geom::{Spline3, Vec2, Vec3};
+use geom::{Polygon, Radians, Shape, Spline3, Vec2, Vec3, OBB};
 use ordered_float::OrderedFloat;
-use prototypes::{BuildingGen, Tick};
+use prototypes::{BuildingGen, Tick, DELTA};
 use serde::{Deserialize, Serialize};
 use slotmapd::HopSlotMap;
+use std::collections::BTreeSet;
 
 pub type Roads = HopSlotMap<RoadID, Road>;
 pub type Lanes = HopSlotMap<LaneID, Lane>;
@@ -20,6 +22,9 @@ pub type Intersections = HopSlotMap<IntersectionID, Intersection>;
 pub type Buildings = HopSlotMap<BuildingID, Building>;
 pub type Lots = HopSlotMap<LotID, Lot>;
 
+/// Water deeper than this at the center of a building destroys it
+const FLOOD_DEPTH: f32 = 0.5;
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct MapProject {
     pub pos: Vec3,
@@ -34,9 +39,13 @@ pub struct Map {
     pub(crate) lots: Lots,
     pub(crate) spatial_map: SpatialMap,
     pub(crate) external_train_stations: Vec<BuildingID>,
+    pub(crate) districts: Districts,
+    pub(crate) deposits: Deposits,
+    pub(crate) restrictions: Restrictions,
 
     pub electricity: ElectricityCache,
     pub environment: Environment,
+    pub(crate) scenery: Scenery,
     pub parking: ParkingSpots,
     pub subscribers: MapSubscribers,
     pub(crate) override_subscriber: MapSubscriber,
@@ -62,10 +71,19 @@ impl Map {
             buildings: Buildings::default(),
             lots: Lots::default(),
             environment: Environment::default(),
+            scenery: Scenery::default(),
             spatial_map: SpatialMap::default(),
             external_train_stations: Default::default(),
+            districts: Districts::default(),
+            deposits: Deposits::default(),
+            restrictions: Restrictions::default(),
             electricity: Default::default(),
-            override_subscriber: subscribers.subscribe(UpdateType::Road | UpdateType::Building),
+            override_subscriber: subscribers.subscribe_multi(&[
+                UpdateType::RoadGeometry,
+                UpdateType::BuildingAdded,
+                UpdateType::BuildingRemoved,
+                UpdateType::BuildingChanged,
+            ]),
             subscribers,
         }
     }
@@ -99,7 +117,10 @@ impl Map {
 
     fn remove_intersection_inner(&mut self, src: IntersectionID) {
         let inter = unwrap_ret!(self.intersections.remove(src));
-        self.subscribers.dispatch(UpdateType::Road, &inter);
+        self.subscribers.dispatch(
+            UpdateType::RoadGeometry | UpdateType::IntersectionControl,
+            &inter,
+        );
 
         for road in inter.roads {
             let r = unwrap_cont!(self.remove_road_inner(road));
@@ -118,7 +139,7 @@ impl Map {
         let ll = SubscriberChunkID::new(bounds.ll);
         let ur = SubscriberChunkID::new(bounds.ur);
         for x in ll.0..ur.0 {
-            for y in ll.0..ur.0 {
+            for y in ll.1..ur.1 {
                 self.subscribers
                     .dispatch_all(std::iter::once(SubscriberChunkID::new_i16(x, y)))
             }
@@ -129,7 +150,7 @@ impl Map {
         info!("remove_building {:?}", b);
 
         let b = self.buildings.remove(b)?;
-        self.subscribers.dispatch(UpdateType::Building, &b);
+        self.subscribers.dispatch(UpdateType::BuildingRemoved, &b);
 
         if b.kind == BuildingKind::ExternalTrading {
             self.external_train_stations.retain(|id| *id != b.id);
@@ -203,7 +224,7 @@ impl Map {
         let Some(b) = self.buildings.get_mut(id) else {
             return;
         };
-        self.subscribers.dispatch(UpdateType::Building, b);
+        self.subscribers.dispatch(UpdateType::BuildingChanged, b);
 
         let Some(ref mut z) = b.zone else {
             return;
@@ -212,8 +233,9 @@ impl Map {
 
         self.environment.remove_trees_near(&z.poly, |tree_chunk| {
             self.subscribers
-                .dispatch_chunk(UpdateType::Terrain, tree_chunk)
+                .dispatch_chunk(UpdateType::TerrainSplat, tree_chunk)
         });
+        self.scenery.remove_near(&z.poly, &mut self.subscribers);
 
         let toclean = self
             .spatial_map
@@ -239,6 +261,10 @@ impl Map {
             log::warn!("did not build {:?}: building overlaps", kind);
             return None;
         }
+        if let Err(e) = self.check_entrances(obb, kind.entrances()) {
+            log::warn!("did not build {:?}: {}", kind, e.reason());
+            return None;
+        }
         log::info!(
             "build special {:?} with shape {:?} and gen {:?} and zone {:?}",
             kind,
@@ -252,8 +278,10 @@ impl Map {
         self.environment
             .remove_trees_near(obb.expand(10.0), |tree_chunk| {
                 self.subscribers
-                    .dispatch_chunk(UpdateType::Terrain, tree_chunk)
+                    .dispatch_chunk(UpdateType::TerrainSplat, tree_chunk)
             });
+        self.scenery
+            .remove_near(obb.expand(2.0), &mut self.subscribers);
 
         let Some(id) = Building::make(
             &mut self.buildings,
@@ -272,7 +300,7 @@ impl Map {
         };
 
         self.subscribers
-            .dispatch(UpdateType::Building, &self.buildings[id]);
+            .dispatch(UpdateType::BuildingAdded, &self.buildings[id]);
 
         if kind == BuildingKind::ExternalTrading {
             self.external_train_stations.push(id);
@@ -282,11 +310,68 @@ impl Map {
         Some(id)
     }
 
+    /// Changes the kind of a building in place. The footprint keeps its side facing the road and
+    /// grows away from it. Nothing changes if the new footprint would collide with something else.
+    pub fn upgrade_building(
+        &mut self,
+        id: BuildingID,
+        kind: BuildingKind,
+        gen: BuildingGen,
+        w: f32,
+        h: f32,
+    ) -> bool {
+        let Some(b) = self.buildings.get(id) else {
+            return false;
+        };
+        let [_, up] = b.obb.axis();
+        let ext = up.mag();
+        let dir = up / ext;
+        let center = b.obb.center();
+
+        let facing = match b.connected_road.and_then(|r| self.roads.get(r)) {
+            Some(r) if (r.points.project(center.z(b.height)).xy() - center).dot(dir) < 0.0 => -dir,
+            Some(_) => dir,
+            None => -dir,
+        };
+        let front = center + facing * ext * 0.5;
+        let obb = OBB::new(front - facing * w * 0.5, dir, w, h);
+
+        let filter = ProjectFilter::BUILDING | ProjectFilter::ROAD | ProjectFilter::INTER;
+        if self
+            .spatial_map
+            .query(obb, filter)
+            .any(|k| k != ProjectKind::Building(id))
+        {
+            log::warn!("did not upgrade {:?} to {:?}: footprint overlaps", id, kind);
+            return false;
+        }
+        info!("upgrade {:?} to {:?}", id, kind);
+
+        self.clean_lots_inner(self.spatial_map.query(obb, ProjectFilter::LOT).collect());
+
+        let (mesh, door_pos, height) = Building::gen_mesh(&self.environment, obb, gen);
+        let entrances = Building::gen_entrances(&self.environment, &obb, kind.entrances());
+        let b = &mut self.buildings[id];
+        b.kind = kind;
+        b.obb = obb;
+        b.mesh = mesh;
+        b.door_pos = door_pos;
+        b.entrances = entrances;
+        b.height = height;
+
+        self.spatial_map.update(&self.buildings[id]);
+        self.subscribers
+            .dispatch(UpdateType::BuildingChanged, &self.buildings[id]);
+
+        self.check_invariants();
+        true
+    }
+
     pub fn build_house(&mut self, lot_id: LotID) -> Option<BuildingID> {
         info!("build house on {:?}", lot_id);
 
         let lot = self.lots.remove(lot_id)?;
-        self.subscribers.dispatch(UpdateType::Road, &lot);
+        self.subscribers.dispatch(UpdateType::LotZoning, &lot);
         self.spatial_map.remove(lot.id);
 
         let Some(id) = Building::make(
@@ -306,7 +391,7 @@ impl Map {
         };
 
         self.subscribers
-            .dispatch(UpdateType::Building, &self.buildings[id]);
+            .dispatch(UpdateType::BuildingAdded, &self.buildings[id]);
         self.electricity.add_object(id);
 
         self.check_invariants();
@@ -325,11 +410,31 @@ impl Map {
         self.subscribers.subscribe(filter)
     }
 
+    pub fn subscribe_multi(&self, filters: &[UpdateType]) -> MapSubscriber {
+        self.subscribers.subscribe_multi(filters)
+    }
+
+    /// Revision of the last update of the given types in the chunk, to catch up on missed updates
+    pub fn chunk_revision(&self, chunk: SubscriberChunkID, filter: UpdateType) -> u64 {
+        self.subscribers.chunk_revision(chunk, filter)
+    }
+
+    /// Revision of the last update, to find later what changed since with [`Map::changed_since`]
+    pub fn revision(&self) -> u64 {
+        self.subscribers.revision()
+    }
+
+    /// Chunks with an update of the given types after the revision
+    pub fn changed_since(&self, revision: u64, filter: UpdateType) -> Vec<SubscriberChunkID> {
+        self.subscribers.changed_since(revision, filter)
+    }
+
     fn clean_lots_inner(&mut self, to_clean: Vec<ProjectKind>) {
         for id in to_clean {
             if let ProjectKind::Lot(id) = id {
                 self.spatial_map.remove(id);
-                self.subscribers.dispatch(UpdateType::Road, &self.lots[id]);
+                self.subscribers
+                    .dispatch(UpdateType::LotZoning, &self.lots[id]);
 
                 unwrap_contlog!(
                     &self.lots.remove(id),
@@ -341,7 +446,7 @@ impl Map {
 
     fn remove_road_inner(&mut self, road_id: RoadID) -> Option<Road> {
         let road = self.remove_raw_road(road_id)?;
-        self.subscribers.dispatch(UpdateType::Road, &road);
+        self.subscribers.dispatch(UpdateType::RoadGeometry, &road);
 
         for (id, _) in road.lanes_iter() {
             self.parking.remove_spots(id);
@@ -351,7 +456,7 @@ impl Map {
         self.lots.retain(|_, lot| {
             let to_remove = lot.parent == road_id;
             if to_remove {
-                self.subscribers.dispatch(UpdateType::Road, lot);
+                self.subscribers.dispatch(UpdateType::LotZoning, lot);
                 smap.remove(lot.id);
             }
             !to_remove
@@ -371,12 +476,111 @@ impl Map {
         match self.lots.get_mut(lot) {
             Some(lot) => {
                 lot.kind = kind;
-                self.subscribers.dispatch(UpdateType::Road, lot);
+                self.subscribers.dispatch(UpdateType::LotZoning, lot);
             }
             None => log::warn!("trying to set kind of non-existing lot {:?}", lot),
         }
     }
 
+    /// Speed limit of the lane, lowered when the pavement of its road is damaged or under works
+    pub fn effective_speed_limit(&self, lane: &Lane) -> f32 {
+        self.routing_graph().effective_speed_limit(lane)
+    }
+
+    pub fn routing_graph(&self) -> RoutingGraph {
+        RoutingGraph {
+            roads: &self.roads,
+            lanes: &self.lanes,
+            intersections: &self.intersections,
+        }
+    }
+
+    /// Changes the wear of the road, its mesh is rebuilt when its condition changes
+    pub fn set_road_wear(&mut self, id: RoadID, wear: u8) {
+        let Some(road) = self.roads.get_mut(id) else {
+            log::warn!("trying to set wear of non-existing road {:?}", id);
+            return;
+        };
+        let old = road.condition();
+        road.wear = wear;
+        if road.condition() != old {
+            self.subscribers.dispatch(UpdateType::RoadSurface, road);
+        }
+    }
+
+    /// Changes the growth shown on the field of the building, its mesh is rebuilt
+    pub fn set_field_stage(&mut self, id: BuildingID, stage: FieldStage) {
+        let Some(b) = self.buildings.get_mut(id) else {
+            return;
+        };
+        let Some(ref mut z) = b.zone else {
+            return;
+        };
+        if z.stage == stage {
+            return;
+        }
+        z.stage = stage;
+        self.subscribers.dispatch(UpdateType::BuildingChanged, b);
+    }
+
+    /// Blocks the lane or opens it back, routes are found again with it
+    pub fn set_lane_blocked(&mut self, id: LaneID, blocked: bool) {
+        let Some(lane) = self.lanes.get_mut(id) else {
+            log::warn!("trying to block non-existing lane {:?}", id);
+            return;
+        };
+        if lane.blocked == blocked {
+            return;
+        }
+        lane.blocked = blocked;
+        let parent = lane.parent;
+        if let Some(road) = self.roads.get(parent) {
+            self.subscribers.dispatch(UpdateType::RoadSurface, road);
+        }
+    }
+
+    /// Marks the building as derelict or not, its mesh is rebuilt when it changes
+    pub fn set_derelict(&mut self, id: BuildingID, derelict: bool) {
+        let Some(b) = self.buildings.get_mut(id) else {
+            log::warn!(
+                "trying to set dereliction of non-existing building {:?}",
+                id
+            );
+            return;
+        };
+        if b.derelict == derelict {
+            return;
+        }
+        b.derelict = derelict;
+        self.subscribers
+            .dispatch(UpdateType::BuildingChanged, &self.buildings[id]);
+    }
+
+    /// Paints the zone on every lot under the brush.
+    /// Grown buildings under the brush that do not match the zone anymore are abandoned.
+    pub fn paint_zone(&mut self, brush: ZoneBrush, kind: LotKind) {
+        let filter = ProjectFilter::LOT | ProjectFilter::BUILDING;
+        let hits: Vec<ProjectKind> = match brush {
+            ZoneBrush::Circle(c) => self.spatial_map.query(c, filter).collect(),
+            ZoneBrush::Rect(obb) => self.spatial_map.query(obb, filter).collect(),
+        };
+
+        for hit in hits {
+            match hit {
+                ProjectKind::Lot(id) => self.set_lot_kind(id, kind),
+                ProjectKind::Building(id) => {
+                    let Some(b) = self.buildings.get_mut(id) else {
+                        continue;
+                    };
+                    if b.grown_in.is_some() {
+                        b.abandoned = b.grown_in != kind.zone_kind();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
     pub fn terraform(
         &mut self,
         tick: Tick,
@@ -387,21 +591,173 @@ impl Map {
         level: f32,
         slope: Option<(Vec3, Vec3)>,
     ) {
+        if kind == TerraformKind::Plant {
+            self.plant_trees(tick, center, radius, amount);
+            return;
+        }
+
         let modified = self
             .environment
             .terraform(tick, kind, center, radius, amount, level, slope);
 
         for id in modified {
-            self.subscribers.dispatch_chunk(UpdateType::Terrain, id);
+            self.subscribers
+                .dispatch_chunk(UpdateType::TerrainHeight, id);
+        }
+        self.water_changed();
+    }
+
+    /// Lowers the ground inside the shape by `depth` and fills the hole with water
+    pub fn dig_lake(&mut self, shape: &Polygon, depth: f32) {
+        let mut inside = None;
+        let modified = self.environment.terrain_apply(shape.bbox(), |pos| {
+            if !shape.contains(pos.xy()) {
+                return pos.z;
+            }
+            inside.get_or_insert(pos.xy());
+            pos.z - depth
+        });
+        for id in modified {
+            self.subscribers
+                .dispatch_chunk(UpdateType::TerrainHeight, id);
+        }
+
+        // the water stays a bit under the old ground so that it does not spill over
+        if let Some(pos) = inside {
+            if self.environment.water_depth(pos).is_none() {
+                self.environment.change_water_level(pos, depth * 0.8);
+            }
+        }
+        self.water_changed();
+    }
+
+    /// Plants saplings all over the shape, away from roads and buildings
+    pub fn plant_forest(&mut self, tick: Tick, shape: &Polygon) {
+        const SPACING: f32 = 25.0;
+        const SAPLINGS_PER_SPOT: f32 = 6.0;
+
+        let bbox = shape.bbox();
+        let mut y = bbox.ll.y;
+        while y < bbox.ur.y {
+            let mut x = bbox.ll.x;
+            while x < bbox.ur.x {
+                let pos = Vec2::new(x, y);
+                if shape.contains(pos) {
+                    self.plant_trees(tick, pos, SPACING * 0.7, SAPLINGS_PER_SPOT / DELTA);
+                }
+                x += SPACING;
+            }
+            y += SPACING;
+        }
+    }
+
+    /// Moves the lakes toward the water table, a day of evaporation and rain
+    pub fn equalize_water(&mut self) {
+        self.environment.equalize_water(LAKE_EQUALIZATION_PER_DAY);
+        self.water_changed();
+    }
+
+    /// Tells the subscribers where the water moved and destroys the buildings it flooded
+    fn water_changed(&mut self) {
+        let Some(area) = self.environment.take_water_changes() else {
+            return;
+        };
+        let chunks: Vec<_> = self.environment.covered_chunks(area).collect();
+        for id in chunks {
+            // the trees under the new water were removed
+            self.subscribers
+                .dispatch_chunk(UpdateType::Water | UpdateType::TerrainSplat, id);
+        }
+
+        let flooded: Vec<BuildingID> = self
+            .spatial_map
+            .query(area, ProjectFilter::BUILDING)
+            .filter_map(|kind| match kind {
+                ProjectKind::Building(id) => Some(id),
+                _ => None,
+            })
+            .filter(|&id| {
+                self.environment
+                    .water_depth(self.buildings[id].obb.center())
+                    .is_some_and(|depth| depth > FLOOD_DEPTH)
+            })
+            .collect();
+        for id in flooded {
+            info!("{:?} was flooded", id);
+            self.remove_building(id);
+        }
+    }
+
+    /// Plants saplings in the circle away from roads and buildings, more of them with a bigger amount
+    fn plant_trees(&mut self, tick: Tick, center: Vec2, radius: f32, amount: f32) {
+        let mut rng = common::rand::gen(common::hash_u64((
+            tick.0,
+            center.x.to_bits(),
+            center.y.to_bits(),
+        )));
+        let attempts = (amount.abs() * DELTA).ceil() as usize;
+
+        let mut planted = BTreeSet::new();
+        for _ in 0..attempts {
+            let angle = Radians(2.0 * std::f32::consts::PI * rng.next_f32());
+            let pos = center + angle.vec2() * radius * rng.next_f32().sqrt();
+            if !is_clear_for_tree(&self.spatial_map, pos) {
+                continue;
+            }
+            if let Some(id) = self.environment.plant_tree(pos) {
+                planted.insert(id);
+            }
+        }
+
+        for id in planted {
+            self.subscribers
+                .dispatch_chunk(UpdateType::TerrainSplat, id);
+        }
+    }
+
+    /// Grows the trees of a terrain chunk by one day
+    pub fn grow_trees(&mut self, chunk: TerrainChunkID, seed: u64) {
+        let spatial_map = &self.spatial_map;
+        let changed = self
+            .environment
+            .grow_trees(chunk, seed, |pos| is_clear_for_tree(spatial_map, pos));
+        for id in changed {
+            self.subscribers
+                .dispatch_chunk(UpdateType::TerrainSplat, id);
         }
     }
 
+    /// Whether a mature tree outside the protected nature stands within the radius
+    pub fn has_harvestable_tree(&self, center: Vec2, radius: f32) -> bool {
+        self.environment.has_mature_tree(center, radius, |pos| {
+            self.restriction_on(&pos, RestrictedAction::CutTrees)
+                .is_none()
+        })
+    }
+
+    /// Cuts the closest mature tree within the radius, a sapling is planted in its place.
+    /// The trees in protected nature are left standing. Returns whether a tree was cut.
+    pub fn harvest_tree(&mut self, center: Vec2, radius: f32) -> bool {
+        let restrictions = &self.restrictions;
+        let can_cut = |pos: Vec2| {
+            !restrictions
+                .values()
+                .any(|r| RestrictedAction::CutTrees.forbidden_by(r.kind) && r.shape.contains(pos))
+        };
+        let Some(id) = self.environment.harvest_tree(center, radius, can_cut) else {
+            return false;
+        };
+        self.subscribers
+            .dispatch_chunk(UpdateType::TerrainSplat, id);
+        true
+    }
+
     // Private mutating
 
     pub(crate) fn add_intersection(&mut self, pos: Vec3) -> IntersectionID {
         let id = Intersection::make(&mut self.intersections, &mut self.spatial_map, pos);
         self.subscribers
-            .dispatch(UpdateType::Building, &self.intersections[id]);
+            .dispatch(UpdateType::RoadGeometry, &self.intersections[id]);
         self.electricity.add_object(id);
         id
     }
@@ -410,7 +766,10 @@ impl Map {
         info!("invalidate {:?}", id);
 
         let inter = unwrap_ret!(self.intersections.get_mut(id));
-        self.subscribers.dispatch(UpdateType::Road, inter);
+        self.subscribers.dispatch(
+            UpdateType::RoadGeometry | UpdateType::IntersectionControl,
+            inter,
+        );
 
         if inter.roads.is_empty() {
             self.remove_intersection_inner(id);
@@ -424,7 +783,7 @@ impl Map {
                 self.roads.get(x),
                 "intersection has unexisting road in list"
             );
-            self.subscribers.dispatch(UpdateType::Road, road);
+            self.subscribers.dispatch(UpdateType::RoadGeometry, road);
 
             let oend_id = unwrap_cont!(road.other_end(id));
 
@@ -482,7 +841,7 @@ impl Map {
             log::error!("Trying to split unexisting road");
             return None;
         });
-        self.subscribers.dispatch(UpdateType::Road, &r);
+        self.subscribers.dispatch(UpdateType::RoadGeometry, &r);
 
         for (id, _) in r.lanes_iter() {
             self.parking.remove_to_reuse(id);
@@ -536,6 +895,16 @@ impl Map {
             self.parking.clean_reuse()
         );
 
+        for new in [r1, r2] {
+            if let Some(new) = self.roads.get_mut(new) {
+                new.wear = r.wear;
+                new.name.clone_from(&r.name);
+                new.electrified = r.electrified;
+                new.roadworks.clone_from(&r.roadworks);
+            }
+            self.close_roadworks_lanes(new);
+        }
+
         let r1 = self.roads.get(r1)?;
         let r2 = self.roads.get(r2)?;
 
@@ -633,11 +1002,14 @@ impl Map {
         #[allow(clippy::indexing_slicing)]
         let r = &self.roads[rid];
         let mut b = r.boldline();
+        let mut footprint = b.clone();
+        footprint.expand(2.0);
         b.expand(40.0);
         self.environment.remove_trees_near(&b, |tree_chunk| {
             self.subscribers
-                .dispatch_chunk(UpdateType::Terrain, tree_chunk)
+                .dispatch_chunk(UpdateType::TerrainSplat, tree_chunk)
         });
+        self.scenery.remove_near(&footprint, &mut self.subscribers);
 
         Some(rid)
     }
@@ -962,6 +1334,15 @@ impl Map {
     }
 }
 
+/// Trees do not grow on roads, intersections and buildings
+fn is_clear_for_tree(spatial_map: &SpatialMap, pos: Vec2) -> bool {
+    let filter = ProjectFilter::ROAD | ProjectFilter::INTER | ProjectFilter::BUILDING;
+    spatial_map
+        .query_around(pos, TREE_SPACING, filter)
+        .next()
+        .is_none()
+}
+
 impl MapProject {
     pub fn ground(pos: Vec3) -> Self {
         Self {
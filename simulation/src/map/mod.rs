@@ -21,37 +21,61 @@ pub use objects::*;
 pub mod procgen {
     mod building;
     pub mod heightmap;
+    pub mod osm;
     mod presets;
 
     pub use building::*;
     pub use presets::*;
 }
 
+mod addresses;
 mod change_detection;
+mod deposits;
+mod districts;
 mod electricity_cache;
+mod electrification;
+mod entrances;
 mod height_override;
 mod light_policy;
 #[allow(clippy::module_inception)]
 mod map;
 mod pathfinding;
+mod restrictions;
+mod road_layout;
+mod roadworks;
+mod scenery;
 mod serializing;
 mod spatial_map;
+mod speed_limits;
+mod svg_export;
 pub mod terrain;
 mod traffic_control;
 mod traversable;
 mod turn_policy;
+mod water;
 
 // Use self or else it would be ambiguous with "pathfinding" crate
 pub use self::pathfinding::*;
+pub use addresses::*;
 pub use change_detection::*;
+pub use deposits::*;
+pub use districts::*;
 pub use electricity_cache::*;
+pub use entrances::*;
 pub use light_policy::*;
 pub use map::*;
+pub use restrictions::*;
+pub use road_layout::*;
+pub use roadworks::*;
+pub use scenery::*;
 pub use spatial_map::*;
+pub use speed_limits::*;
+pub use svg_export::*;
 pub use terrain::*;
 pub use traffic_control::*;
 pub use traversable::*;
 pub use turn_policy::*;
+pub use water::*;
 
 pub use ::pathfinding as pathfinding_crate;
 
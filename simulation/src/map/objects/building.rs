@@ -1,10 +1,14 @@
 use crate::map::procgen::{gen_exterior_farm, gen_exterior_house, ColoredMesh};
 use crate::map::{
-    Buildings, ElectricityCache, Environment, LanePattern, RoadID, Roads, SpatialMap,
+    Buildings, ElectricityCache, Entrance, Environment, LanePattern, RoadID, Roads, SpatialMap,
 };
 use egui_inspect::debug_inspect_impl;
 use geom::{Color, Polygon, Vec2, Vec3, OBB};
-use prototypes::{BuildingGen, FreightStationPrototypeID, GoodsCompanyID};
+use prototypes::{
+    AmenityKind, BuildMenuEntry, BuildingGen, DayTime, DockPrototypeID, EntranceDef,
+    FreightStationPrototypeID, GoodsCompanyID, LeisurePrototypeID, ServiceDepotID,
+    WarehousePrototypeID, ZoneKind,
+};
 use serde::{Deserialize, Serialize};
 use slotmapd::new_key_type;
 
@@ -19,6 +23,10 @@ pub enum BuildingKind {
     House,
     GoodsCompany(GoodsCompanyID),
     RailFreightStation(FreightStationPrototypeID),
+    Dock(DockPrototypeID),
+    Warehouse(WarehousePrototypeID),
+    ServiceDepot(ServiceDepotID),
+    Leisure(LeisurePrototypeID),
     TrainStation,
     ExternalTrading,
 }
@@ -34,6 +42,71 @@ impl BuildingKind {
     pub fn is_cached_in_bkinds(&self) -> bool {
         matches!(self, BuildingKind::ExternalTrading)
     }
+
+    /// Whether customers can visit the building at the given time
+    pub fn is_open(&self, time: &DayTime) -> bool {
+        match self {
+            BuildingKind::GoodsCompany(id) => id
+                .prototype()
+                .opening_hours
+                .map_or(true, |hours| hours.is_active(time)),
+            BuildingKind::Leisure(id) => id.prototype().opening_hours.is_active(time),
+            _ => true,
+        }
+    }
+
+    /// Entrances defined by the prototype, empty when the door is the only one
+    pub fn entrances(&self) -> &'static [EntranceDef] {
+        match self {
+            BuildingKind::GoodsCompany(id) => &id.prototype().entrances,
+            BuildingKind::RailFreightStation(id) => &id.prototype().entrances,
+            BuildingKind::Dock(id) => &id.prototype().entrances,
+            BuildingKind::Warehouse(id) => &id.prototype().entrances,
+            BuildingKind::ServiceDepot(id) => &id.prototype().entrances,
+            BuildingKind::Leisure(id) => &id.prototype().entrances,
+            BuildingKind::House | BuildingKind::TrainStation | BuildingKind::ExternalTrading => &[],
+        }
+    }
+
+    /// Where the building is in the build menu, None for the buildings that can't be placed by hand
+    pub fn build_menu(&self) -> Option<&'static BuildMenuEntry> {
+        match self {
+            BuildingKind::GoodsCompany(id) => Some(&id.prototype().menu),
+            BuildingKind::Dock(id) => Some(&id.prototype().menu),
+            BuildingKind::Warehouse(id) => Some(&id.prototype().menu),
+            BuildingKind::ServiceDepot(id) => Some(&id.prototype().menu),
+            BuildingKind::Leisure(id) => Some(&id.prototype().menu),
+            _ => None,
+        }
+    }
+
+    /// What the building offers to the residences within walking distance
+    pub fn amenity(&self) -> Option<AmenityKind> {
+        match self {
+            BuildingKind::GoodsCompany(id) => id.prototype().amenity,
+            BuildingKind::Dock(id) => id.prototype().amenity,
+            BuildingKind::Warehouse(id) => id.prototype().amenity,
+            BuildingKind::ServiceDepot(id) => id.prototype().amenity,
+            BuildingKind::Leisure(id) => id.prototype().amenity,
+            BuildingKind::TrainStation => Some(AmenityKind::Transit),
+            _ => None,
+        }
+    }
+
+    /// Name shown to the player
+    pub fn label(&self) -> &'static str {
+        match self {
+            BuildingKind::House => "House",
+            BuildingKind::GoodsCompany(id) => &id.prototype().label,
+            BuildingKind::RailFreightStation(id) => &id.prototype().label,
+            BuildingKind::Dock(id) => &id.prototype().label,
+            BuildingKind::Warehouse(id) => &id.prototype().label,
+            BuildingKind::ServiceDepot(id) => &id.prototype().label,
+            BuildingKind::Leisure(id) => &id.prototype().label,
+            BuildingKind::TrainStation => "Train Station",
+            BuildingKind::ExternalTrading => "External Trading",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +124,22 @@ pub struct Zone {
     pub area: f32,
     #[serde(default = "unit_x")]
     pub filldir: Vec2,
+    /// Growth of the crop on the field of a farm, the same over the whole field
+    #[serde(default)]
+    pub stage: FieldStage,
+}
+
+/// How far the crop on a field is, from bare soil to ready to harvest
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldStage {
+    /// Bare soil, resting between two crops
+    #[default]
+    Fallow,
+    /// Seedlings
+    Sown,
+    Growing,
+    /// Being harvested
+    Ripe,
 }
 
 fn unit_x() -> Vec2 {
@@ -63,6 +152,7 @@ impl Zone {
             area: p.area(),
             poly: p,
             filldir,
+            stage: FieldStage::Fallow,
         }
     }
 }
@@ -77,21 +167,23 @@ pub struct Building {
     pub height: f32,
     pub zone: Option<Zone>,
     pub connected_road: Option<RoadID>,
+    /// Zone the building grew on, None if it was placed by hand
+    #[serde(default)]
+    pub grown_in: Option<ZoneKind>,
+    /// The zone was removed under the building, it will be demolished soon
+    #[serde(default)]
+    pub abandoned: bool,
+    /// Left empty for a long time, the building looks run down
+    #[serde(default)]
+    pub derelict: bool,
+    /// Empty when the door is the only entrance
+    #[serde(default)]
+    pub entrances: Vec<Entrance>,
 }
 
 impl Building {
-    pub fn make(
-        buildings: &mut Buildings,
-        spatial_map: &mut SpatialMap,
-        electricity: &mut ElectricityCache,
-        roads: &mut Roads,
-        env: &Environment,
-        obb: OBB,
-        kind: BuildingKind,
-        gen: BuildingGen,
-        zone: Option<Zone>,
-        mut connected_road: Option<RoadID>,
-    ) -> Option<BuildingID> {
+    /// Generates the mesh and the door of a building occupying the obb
+    pub fn gen_mesh(env: &Environment, obb: OBB, gen: BuildingGen) -> (ColoredMesh, Vec3, f32) {
         let at = obb.center().z(env.height(obb.center()).unwrap_or(0.0));
         let axis = (obb.corners[1] - obb.corners[0]).normalize();
         let size = obb.corners[0].distance(obb.corners[1]);
@@ -129,6 +221,24 @@ impl Building {
             mesh.faces.push((walkway, Color::gray(0.4).into()));
         }
 
+        (mesh, door_pos, at.z)
+    }
+
+    pub fn make(
+        buildings: &mut Buildings,
+        spatial_map: &mut SpatialMap,
+        electricity: &mut ElectricityCache,
+        roads: &mut Roads,
+        env: &Environment,
+        obb: OBB,
+        kind: BuildingKind,
+        gen: BuildingGen,
+        zone: Option<Zone>,
+        mut connected_road: Option<RoadID>,
+    ) -> Option<BuildingID> {
+        let (mesh, door_pos, height) = Self::gen_mesh(env, obb, gen);
+        let entrances = Self::gen_entrances(env, &obb, kind.entrances());
+
         let b = buildings.insert_with_key(move |id| {
             electricity.add_object(id);
             if let Some(r) = connected_road {
@@ -147,9 +257,13 @@ impl Building {
                 kind,
                 door_pos,
                 obb,
-                height: at.z,
+                height,
                 zone,
                 connected_road,
+                grown_in: None,
+                abandoned: false,
+                derelict: false,
+                entrances,
             }
         });
 
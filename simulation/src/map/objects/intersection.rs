@@ -1,6 +1,6 @@
 use crate::map::{
     Intersections, LaneID, LaneKind, Lanes, LightPolicy, Road, RoadID, Roads, SpatialMap,
-    TraverseDirection, Turn, TurnID, TurnPolicy,
+    TraverseDirection, Turn, TurnDirection, TurnID, TurnKind, TurnPolicy,
 };
 use geom::{pseudo_angle, Circle};
 use geom::{Vec2, Vec3};
@@ -289,6 +289,26 @@ impl Intersection {
     pub fn turns(&self) -> impl ExactSizeIterator<Item = &Turn> {
         self.turns.iter()
     }
+
+    /// Directions the vehicles can go to from the incoming lane, sorted from left to right
+    pub fn turn_directions_from(&self, lane: LaneID, lanes: &Lanes) -> Vec<TurnDirection> {
+        let Some(src) = lanes.get(lane) else {
+            return vec![];
+        };
+        let from = -src.orientation_from(self.id);
+        let mut dirs: Vec<TurnDirection> = self
+            .turns
+            .iter()
+            .filter(|turn| turn.id.src == lane && matches!(turn.kind, TurnKind::Driving))
+            .filter_map(|turn| {
+                let to = lanes.get(turn.id.dst)?.orientation_from(self.id);
+                Some(TurnDirection::new(from, to))
+            })
+            .collect();
+        dirs.sort_unstable();
+        dirs.dedup();
+        dirs
+    }
 }
 
 debug_inspect_impl!(IntersectionID);
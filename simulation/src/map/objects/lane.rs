@@ -77,6 +77,14 @@ pub struct Lane {
     /// Always from src to dst
     pub points: PolyLine3,
     pub dist_from_bottom: f32,
+
+    /// An incident is in the way, routes avoid the lane when they can
+    #[serde(default)]
+    pub blocked: bool,
+
+    /// Coned off by roadworks, routes avoid the lane when they can
+    #[serde(default)]
+    pub closed: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -250,6 +258,8 @@ impl Lane {
             dist_from_bottom,
             control: TrafficControl::Always,
             speed_limit,
+            blocked: false,
+            closed: false,
         })
     }
 
@@ -324,6 +334,15 @@ impl Lane {
         }
     }
 
+    /// Whether the vehicles can change from this lane to the other one, drawn as a dashed line
+    /// between them. Only lanes of the same kind going the same way allow it.
+    pub fn can_change_to(&self, other: &Lane) -> bool {
+        self.parent == other.parent
+            && self.src == other.src
+            && self.kind == other.kind
+            && self.kind.vehicles()
+    }
+
     /// Returns the vector pointing to the lane from the intersection center
     pub fn orientation_from(&self, id: IntersectionID) -> Vec2 {
         if id == self.src {
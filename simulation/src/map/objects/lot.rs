@@ -2,6 +2,7 @@ use crate::map::{Map, ProjectFilter, ProjectKind, RoadID, UpdateType};
 use geom::Vec2;
 use geom::OBB;
 use geom::{Circle, Vec3};
+use prototypes::ZoneKind;
 use serde::{Deserialize, Serialize};
 use slotmapd::new_key_type;
 use std::collections::BTreeSet;
@@ -10,10 +11,31 @@ new_key_type! {
     pub struct LotID;
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LotKind {
     Unassigned,
     Residential,
+    Commercial,
+    Industrial,
+}
+
+impl LotKind {
+    /// The zone painted on the lot, if any
+    pub fn zone_kind(self) -> Option<ZoneKind> {
+        match self {
+            LotKind::Unassigned => None,
+            LotKind::Residential => Some(ZoneKind::Residential),
+            LotKind::Commercial => Some(ZoneKind::Commercial),
+            LotKind::Industrial => Some(ZoneKind::Industrial),
+        }
+    }
+}
+
+/// Area covered by a zone painting stroke
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum ZoneBrush {
+    Circle(Circle),
+    Rect(OBB),
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -89,7 +111,8 @@ impl Lot {
                 let l = Lot::try_make(map, road, pos + axis * (w + 1.0), axis.xy(), size);
                 if let Some(id) = l {
                     lots.push(id);
-                    map.subscribers.dispatch(UpdateType::Road, &map.lots[id]);
+                    map.subscribers
+                        .dispatch(UpdateType::LotZoning, &map.lots[id]);
 
                     d += size * 0.5 + 2.0;
                     size = picksize();
@@ -123,7 +146,9 @@ impl Lot {
 
         for lot in to_remove {
             if let ProjectKind::Lot(lot) = lot {
-                map.lots.remove(lot);
+                if let Some(removed) = map.lots.remove(lot) {
+                    map.subscribers.dispatch(UpdateType::LotZoning, &removed);
+                }
                 map.spatial_map.remove(lot);
             }
         }
@@ -7,7 +7,7 @@ use geom::{Vec2, Vec3};
 
 use crate::map::{
     BuildingID, Environment, Intersection, IntersectionID, Lane, LaneDirection, LaneID, LaneKind,
-    LanePattern, Lanes, ParkingSpots, Roads, SpatialMap, MAX_SLOPE, ROAD_Z_OFFSET,
+    LanePattern, Lanes, ParkingSpots, Roads, Roadworks, SpatialMap, MAX_SLOPE, ROAD_Z_OFFSET,
 };
 
 new_key_type! {
@@ -45,12 +45,75 @@ pub struct Road {
 
     pub connected_buildings: Vec<BuildingID>,
 
+    /// How damaged the pavement is, from 0 (brand new) to 255 (broken)
+    #[serde(default)]
+    pub wear: u8,
+
+    /// Street name shown on the map and in the addresses, empty until the road is named
+    #[serde(default)]
+    pub name: String,
+
+    /// Whether the rails of the road have an overhead line, for electric trains
+    #[serde(default)]
+    pub electrified: bool,
+
+    /// Construction or upgrade in progress, some lanes are closed until it is done
+    #[serde(default)]
+    pub roadworks: Option<Roadworks>,
+
     src_interface: f32,
     dst_interface: f32,
 
     lanes_forward: Vec<(LaneID, LaneKind)>,
     lanes_backward: Vec<(LaneID, LaneKind)>,
 }
+/// Condition of the pavement of a road, derived from its wear
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RoadCondition {
+    Good,
+    Worn,
+    Damaged,
+    Broken,
+}
+
+impl RoadCondition {
+    pub const ALL: [RoadCondition; 4] = [
+        RoadCondition::Good,
+        RoadCondition::Worn,
+        RoadCondition::Damaged,
+        RoadCondition::Broken,
+    ];
+
+    pub fn from_wear(wear: u8) -> Self {
+        match wear {
+            0..=63 => RoadCondition::Good,
+            64..=127 => RoadCondition::Worn,
+            128..=191 => RoadCondition::Damaged,
+            192..=255 => RoadCondition::Broken,
+        }
+    }
+
+    /// Multiplier applied to the speed limit of the lanes
+    pub fn speed_factor(self) -> f32 {
+        match self {
+            RoadCondition::Good | RoadCondition::Worn => 1.0,
+            RoadCondition::Damaged => 0.8,
+            RoadCondition::Broken => 0.6,
+        }
+    }
+}
+
+impl std::fmt::Display for RoadCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RoadCondition::Good => "Good",
+            RoadCondition::Worn => "Worn",
+            RoadCondition::Damaged => "Damaged",
+            RoadCondition::Broken => "Broken",
+        })
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct LanePair {
     pub incoming: Option<LaneID>,
@@ -104,6 +167,10 @@ impl Road {
             interfaced_points: PolyLine3::new(vec![points.first()]),
             points,
             connected_buildings: vec![],
+            wear: 0,
+            name: String::new(),
+            electrified: false,
+            roadworks: None,
         });
         #[allow(clippy::indexing_slicing)]
         let road = &mut roads[id];
@@ -143,6 +210,10 @@ impl Road {
             .copied()
     }
 
+    pub fn has_rails(&self) -> bool {
+        self.lanes_iter().any(|(_, kind)| kind.is_rail())
+    }
+
     pub fn sidewalks(&self, from: IntersectionID) -> LanePair {
         self.mk_pair(from, |lanes| {
             lanes
@@ -213,6 +284,10 @@ impl Road {
         parking.clean_reuse();
     }
 
+    pub fn condition(&self) -> RoadCondition {
+        RoadCondition::from_wear(self.wear)
+    }
+
     pub fn length(&self) -> f32 {
         self.points.length()
     }
@@ -1,4 +1,4 @@
-use crate::map::{Intersection, IntersectionID, LaneID, Lanes};
+use crate::map::{Intersection, IntersectionID, LaneID, Lanes, Roads, TrafficControl};
 use geom::{Degrees, PolyLine3, Radians, Vec2};
 use geom::{Spline, Vec3};
 use serde::{Deserialize, Serialize};
@@ -23,6 +23,22 @@ impl TurnID {
             bidirectional,
         }
     }
+
+    /// Control of the cars whose road the crosswalk goes over, the pedestrians walk while they
+    /// are stopped
+    pub fn crossing_control(&self, lanes: &Lanes, roads: &Roads) -> TrafficControl {
+        let Some(road) = lanes.get(self.src).and_then(|l| roads.get(l.parent)) else {
+            return TrafficControl::Always;
+        };
+        if road.src != self.parent && road.dst != self.parent {
+            return TrafficControl::Always;
+        }
+        road.incoming_lanes_to(self.parent)
+            .iter()
+            .filter(|(_, kind)| kind.needs_light())
+            .find_map(|&(id, _)| Some(lanes.get(id)?.control))
+            .unwrap_or(TrafficControl::Always)
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -39,6 +55,32 @@ impl TurnKind {
     }
 }
 
+/// Where a turn goes, seen from the incoming lane. Used to draw the lane arrows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TurnDirection {
+    UTurn,
+    Left,
+    Straight,
+    Right,
+}
+
+impl TurnDirection {
+    /// From the direction at the end of the incoming lane and the one at the start of the
+    /// outgoing lane, both normalized
+    pub fn new(from: Vec2, to: Vec2) -> Self {
+        let cos = from.dot(to);
+        if cos > 0.7 {
+            TurnDirection::Straight
+        } else if cos < -0.8 {
+            TurnDirection::UTurn
+        } else if from.cross(to) > 0.0 {
+            TurnDirection::Left
+        } else {
+            TurnDirection::Right
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Turn {
     pub id: TurnID,
@@ -246,3 +288,22 @@ impl Turn {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::vec2;
+
+    #[test]
+    fn turn_directions() {
+        let east = Vec2::X;
+        assert_eq!(TurnDirection::new(east, east), TurnDirection::Straight);
+        assert_eq!(TurnDirection::new(east, Vec2::Y), TurnDirection::Left);
+        assert_eq!(TurnDirection::new(east, -Vec2::Y), TurnDirection::Right);
+        assert_eq!(TurnDirection::new(east, -east), TurnDirection::UTurn);
+        assert_eq!(
+            TurnDirection::new(east, vec2(0.9, 0.2).normalize()),
+            TurnDirection::Straight
+        );
+    }
+}
@@ -1,17 +1,72 @@
 use crate::map::{
-    LaneID, LaneKind, LanePatternBuilder, Map, Traversable, TraverseDirection, TraverseKind, TurnID,
+    Intersections, Lane, LaneID, LaneKind, LanePatternBuilder, Lanes, Map, Roads, Traversable,
+    TraverseDirection, TraverseKind, TurnID, CLOSED_LANE_SPEED,
 };
 use common::hash_u64;
 use geom::{PolyLine3, Vec3};
 use ordered_float::OrderedFloat;
-use prototypes::Tick;
+use prototypes::{Tick, SECONDS_PER_REALTIME_SECOND};
 use serde::{Deserialize, Serialize};
 use slotmapd::Key;
 
+/// Seconds added to the cost of a lane blocked by an incident or closed by roadworks, a detour is
+/// taken when there is one
+pub const BLOCKED_LANE_COST: f32 = 600.0;
+
+/// Meters a pedestrian walks during a game second, weighs the waits at the crosswalks against the
+/// distance
+pub const WALKED_PER_GAME_SECOND: f32 = 1.2 / SECONDS_PER_REALTIME_SECOND as f32;
+
+/// The parts of the map read when finding a path
+#[derive(Copy, Clone)]
+pub struct RoutingGraph<'a> {
+    pub roads: &'a Roads,
+    pub lanes: &'a Lanes,
+    pub intersections: &'a Intersections,
+}
+
+impl RoutingGraph<'_> {
+    pub fn effective_speed_limit(&self, lane: &Lane) -> f32 {
+        let factor = self.roads.get(lane.parent).map_or(1.0, |r| {
+            r.condition().speed_factor() * r.roadworks_speed_factor()
+        });
+        if lane.closed {
+            return CLOSED_LANE_SPEED.min(lane.speed_limit * factor);
+        }
+        lane.speed_limit * factor
+    }
+}
+
+/// Copy of the routing graph, so paths can be found on other threads while the map changes
+#[derive(Clone)]
+pub struct RoutingSnapshot {
+    roads: Roads,
+    lanes: Lanes,
+    intersections: Intersections,
+}
+
+impl RoutingSnapshot {
+    pub fn new(map: &Map) -> Self {
+        Self {
+            roads: map.roads.clone(),
+            lanes: map.lanes.clone(),
+            intersections: map.intersections.clone(),
+        }
+    }
+
+    pub fn graph(&self) -> RoutingGraph {
+        RoutingGraph {
+            roads: &self.roads,
+            lanes: &self.lanes,
+            intersections: &self.intersections,
+        }
+    }
+}
+
 pub trait Pathfinder {
     fn path(
         &self,
-        map: &Map,
+        graph: RoutingGraph,
         tick: Tick,
         start: Traversable,
         end: LaneID,
@@ -31,15 +86,15 @@ pub enum PathKind {
 impl Pathfinder for PathKind {
     fn path(
         &self,
-        map: &Map,
+        graph: RoutingGraph,
         tick: Tick,
         start: Traversable,
         end: LaneID,
     ) -> Option<Vec<Traversable>> {
         match self {
-            PathKind::Pedestrian => PedestrianPath.path(map, tick, start, end),
-            PathKind::Vehicle => CarPath.path(map, tick, start, end),
-            PathKind::Rail => RailPath.path(map, tick, start, end),
+            PathKind::Pedestrian => PedestrianPath.path(graph, tick, start, end),
+            PathKind::Vehicle => CarPath.path(graph, tick, start, end),
+            PathKind::Rail => RailPath.path(graph, tick, start, end),
         }
     }
 
@@ -73,13 +128,13 @@ struct PedestrianPath;
 impl Pathfinder for PedestrianPath {
     fn path(
         &self,
-        map: &Map,
+        graph: RoutingGraph,
         _tick: Tick,
         start: Traversable,
         end: LaneID,
     ) -> Option<Vec<Traversable>> {
-        let inters = &map.intersections;
-        let lanes = &map.lanes;
+        let inters = graph.intersections;
+        let lanes = graph.lanes;
 
         let end_pos = inters.get(lanes.get(end)?.dst)?.pos;
 
@@ -116,10 +171,17 @@ impl Pathfinder for PedestrianPath {
             inter
                 .into_iter()
                 .flat_map(move |inter| {
-                    inter.turns_from(lane_from_id).map(|(x, dir)| {
+                    inter.turns_from(lane_from_id).map(move |(x, dir)| {
+                        let mut cost = 0.001;
+                        if inter.find_turn(x).map_or(false, |t| t.kind.is_crosswalk()) {
+                            cost += x
+                                .crossing_control(lanes, graph.roads)
+                                .expected_crossing_wait()
+                                * WALKED_PER_GAME_SECOND;
+                        }
                         (
                             Traversable::new(TraverseKind::Turn(x), dir),
-                            OrderedFloat(0.001),
+                            OrderedFloat(cost),
                         )
                     })
                 })
@@ -165,12 +227,12 @@ struct RailPath;
 impl Pathfinder for RailPath {
     fn path(
         &self,
-        map: &Map,
+        graph: RoutingGraph,
         tick: Tick,
         start: Traversable,
         end: LaneID,
     ) -> Option<Vec<Traversable>> {
-        CarPath.path(map, tick, start, end)
+        CarPath.path(graph, tick, start, end)
     }
 
     fn nearest_lane(&self, map: &Map, pos: Vec3) -> Option<LaneID> {
@@ -191,13 +253,13 @@ struct CarPath;
 impl Pathfinder for CarPath {
     fn path(
         &self,
-        map: &Map,
+        graph: RoutingGraph,
         tick: Tick,
         start: Traversable,
         end: LaneID,
     ) -> Option<Vec<Traversable>> {
-        let inters = &map.intersections;
-        let lanes = &map.lanes;
+        let inters = graph.intersections;
+        let lanes = graph.lanes;
 
         let start_lane = start.destination_lane();
 
@@ -234,8 +296,11 @@ impl Pathfinder for CarPath {
                         let mut cost = f32::INFINITY;
 
                         if let Some(l) = lanes.get(x.dst) {
-                            cost = l.points.length() / l.speed_limit;
+                            cost = l.points.length() / graph.effective_speed_limit(l);
                             cost += common::rand::randu(l.dist_from_bottom.to_bits() ^ base_random);
+                            if l.blocked || l.closed {
+                                cost += BLOCKED_LANE_COST;
+                            }
                         }
 
                         (x.dst, OrderedFloat(cost))
@@ -0,0 +1,788 @@
+//! Import of real-world street layouts from OpenStreetMap extracts, `.osm.pbf` or `.osm` XML.
+//! The highways are projected around a chosen origin, clipped to the map and simplified to the
+//! node spacing of the game. The water and forest areas can seed lakes and trees.
+//! The result is an [`ImportedMap`] applied by a world command, the saves and replays of an
+//! imported city are the same as any other.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use common::diagnostics::Diagnostic;
+use common::{FastMap, FastSet};
+use geom::{vec2, Polygon, Segment, Vec2, AABB};
+use prototypes::Tick;
+
+use crate::map::{
+    IntersectionID, LanePattern, LanePatternBuilder, Map, RoadSegmentKind, MAX_SPEED_LIMIT,
+    MIN_SPEED_LIMIT,
+};
+
+mod pbf;
+mod xml;
+
+/// Meters along a meridian for one degree of latitude
+const METERS_PER_DEGREE: f64 = 6_371_000.0 * std::f64::consts::PI / 180.0;
+/// The roads stop this far from the edges of the map
+const BORDER_MARGIN: f32 = 50.0;
+/// Nodes closer than this are merged into one intersection
+const MERGE_DISTANCE: f32 = 8.0;
+/// Intermediate nodes of a road are at least this far apart
+const NODE_SPACING: f32 = 20.0;
+/// Intermediate nodes are removed if the road moves less than this without them
+const SIMPLIFY_TOLERANCE: f32 = 2.0;
+/// Smaller water and forest areas are left out
+const MIN_AREA: f32 = 400.0;
+const LAKE_DEPTH: f32 = 3.0;
+
+#[derive(Debug)]
+pub enum OsmError {
+    Io(std::io::Error),
+    Invalid(&'static str),
+    Unsupported(String),
+    NoRoads,
+}
+
+impl Display for OsmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OsmError::Io(e) => write!(f, "could not read the extract: {e}"),
+            OsmError::Invalid(why) => write!(f, "invalid extract: {why}"),
+            OsmError::Unsupported(what) => write!(f, "unsupported extract: {what}"),
+            OsmError::NoRoads => write!(f, "no road of the extract fits on the map"),
+        }
+    }
+}
+
+/// The elements of an extract the import looks at
+#[derive(Default)]
+pub struct OsmData {
+    /// Latitude and longitude of the nodes, in degrees
+    pub nodes: FastMap<i64, (f64, f64)>,
+    pub ways: Vec<OsmWay>,
+    /// Only counted, the multipolygons and routes are not imported
+    pub relations: usize,
+}
+
+#[derive(Default)]
+pub struct OsmWay {
+    pub id: i64,
+    pub nodes: Vec<i64>,
+    pub tags: Vec<(String, String)>,
+}
+
+impl OsmWay {
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.nodes.len() >= 4 && self.nodes.first() == self.nodes.last()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OsmImportOptions {
+    /// Multiplier of the real-world distances, 1 keeps them as they are
+    pub scale: f32,
+    /// Latitude and longitude put at the center of the map, the center of the roads if None
+    pub origin: Option<(f64, f64)>,
+    /// Dig lakes where the extract has water areas
+    pub water: bool,
+    /// Plant trees where the extract has forests and woods
+    pub forests: bool,
+}
+
+impl Default for OsmImportOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            origin: None,
+            water: true,
+            forests: true,
+        }
+    }
+}
+
+/// The steps of an import, in order
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OsmImportStep {
+    ReadingFile,
+    Parsing,
+    Converting,
+}
+
+/// The map geometry of an import, in map coordinates
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportedMap {
+    pub nodes: Vec<Vec2>,
+    /// Roads from one node to another, the one-way roads go forward
+    pub roads: Vec<(u32, u32, LanePattern)>,
+    pub water: Vec<Polygon>,
+    pub forests: Vec<Polygon>,
+}
+
+pub struct OsmImport {
+    pub map: ImportedMap,
+    /// Number of elements left out, by reason
+    pub skipped: BTreeMap<&'static str, u32>,
+}
+
+impl OsmImport {
+    /// Reports the elements left out to the diagnostics panel
+    pub fn report_skipped(&self, path: &Path) {
+        for (reason, &n) in &self.skipped {
+            let mut d = Diagnostic::warning("osm import", *reason).asset(path);
+            d.count = n;
+            d.report();
+        }
+    }
+}
+
+/// Reads the extract at the path, `.pbf` files are read as protobuf and the others as XML
+pub fn read_osm(
+    path: &Path,
+    opts: &OsmImportOptions,
+    bounds: AABB,
+    mut progress: impl FnMut(OsmImportStep),
+) -> Result<OsmImport, OsmError> {
+    progress(OsmImportStep::ReadingFile);
+    let data = std::fs::read(path).map_err(OsmError::Io)?;
+
+    progress(OsmImportStep::Parsing);
+    let osm = if path.extension().is_some_and(|ext| ext == "pbf") {
+        pbf::parse(&data)?
+    } else {
+        let text = std::str::from_utf8(&data).map_err(|_| OsmError::Invalid("not UTF-8"))?;
+        xml::parse(text)?
+    };
+    drop(data);
+
+    progress(OsmImportStep::Converting);
+    let import = convert(&osm, opts, bounds);
+    if import.map.roads.is_empty() {
+        return Err(OsmError::NoRoads);
+    }
+    Ok(import)
+}
+
+/// Builds the imported map from the extract, the map covers `bounds`
+pub fn convert(osm: &OsmData, opts: &OsmImportOptions, bounds: AABB) -> OsmImport {
+    let mut skipped = BTreeMap::new();
+    if osm.relations > 0 {
+        skipped.insert(
+            "relations are not imported (multipolygons, routes)",
+            osm.relations as u32,
+        );
+    }
+    let mut skip = |reason: &'static str| *skipped.entry(reason).or_insert(0) += 1;
+
+    // which ways are kept and how
+    let mut roads = vec![];
+    let mut water = vec![];
+    let mut forests = vec![];
+    for way in &osm.ways {
+        let kind = match way_kind(way) {
+            WayKind::Road(builder, dir) => {
+                roads.push((way, builder, dir));
+                continue;
+            }
+            WayKind::UnsupportedHighway => {
+                skip("ways of a highway class without cars (paths, tracks, construction)");
+                continue;
+            }
+            WayKind::Water if opts.water => &mut water,
+            WayKind::Forest if opts.forests => &mut forests,
+            _ => continue,
+        };
+        if !way.is_closed() {
+            skip("water or forest areas that are not closed");
+            continue;
+        }
+        kind.push(way);
+    }
+
+    let projection = Projection::new(osm, &roads, opts, bounds.center());
+    let area = AABB::new_ll_ur(
+        bounds.ll + Vec2::splat(BORDER_MARGIN),
+        bounds.ur - Vec2::splat(BORDER_MARGIN),
+    );
+
+    // the road pieces inside the map, with the osm node of their points if any
+    let mut pieces: Vec<(Vec<(Vec2, Option<i64>)>, LanePatternBuilder)> = vec![];
+    for (way, builder, dir) in roads {
+        let Some(mut points) = way
+            .nodes
+            .iter()
+            .map(|id| Some((projection.project(*osm.nodes.get(id)?), Some(*id))))
+            .collect::<Option<Vec<_>>>()
+        else {
+            skip("ways with nodes missing from the extract");
+            continue;
+        };
+        if dir == Direction::Backward {
+            points.reverse();
+        }
+        let clipped = clip_line(&points, area);
+        if clipped.is_empty() {
+            skip("roads outside of the map");
+        }
+        pieces.extend(clipped.into_iter().map(|piece| (piece, builder)));
+    }
+
+    // the nodes shared by several pieces are intersections, they must be kept
+    let mut seen = FastSet::default();
+    let mut junctions = FastSet::default();
+    for (piece, _) in &pieces {
+        for (i, &(_, id)) in piece.iter().enumerate() {
+            let Some(id) = id else { continue };
+            if !seen.insert(id) || i == 0 || i == piece.len() - 1 {
+                junctions.insert(id);
+            }
+        }
+    }
+
+    let mut graph = GraphBuilder::default();
+    for (piece, builder) in &pieces {
+        let keep = simplify(piece, &junctions);
+        let mut prev: Option<u32> = None;
+        for (&(pos, id), keep) in piece.iter().zip(keep) {
+            if !keep {
+                continue;
+            }
+            let node = graph.node(pos, id);
+            if let Some(prev) = prev {
+                graph.road(prev, node, *builder);
+            }
+            prev = Some(node);
+        }
+    }
+
+    let mut map = graph.finish();
+    for (ways, polys) in [(water, &mut map.water), (forests, &mut map.forests)] {
+        for way in ways {
+            let Some(points) = way
+                .nodes
+                .iter()
+                .map(|id| Some(projection.project(*osm.nodes.get(id)?)))
+                .collect::<Option<Vec<_>>>()
+            else {
+                skip("ways with nodes missing from the extract");
+                continue;
+            };
+            let poly = clip_polygon(&points[1..], bounds);
+            if poly.len() < 3 || poly.area() < MIN_AREA {
+                continue;
+            }
+            polys.push(poly);
+        }
+    }
+
+    OsmImport { map, skipped }
+}
+
+/// Adds the imported lakes, roads and forests to the map
+pub fn load_imported(map: &mut Map, tick: Tick, imported: &ImportedMap) {
+    let time = std::time::Instant::now();
+
+    // before the roads, the water would flood them otherwise
+    for shape in &imported.water {
+        map.dig_lake(shape, LAKE_DEPTH);
+    }
+
+    let ids: Vec<IntersectionID> = imported
+        .nodes
+        .iter()
+        .map(|&pos| {
+            let z = map.environment.height(pos).unwrap_or(0.0);
+            map.add_intersection(pos.z(z))
+        })
+        .collect();
+    for (a, b, pattern) in &imported.roads {
+        let (Some(&a), Some(&b)) = (ids.get(*a as usize), ids.get(*b as usize)) else {
+            continue;
+        };
+        map.connect(a, b, pattern, RoadSegmentKind::Straight);
+    }
+    for id in ids {
+        if map
+            .intersections
+            .get(id)
+            .is_some_and(|i| i.roads.is_empty())
+        {
+            map.remove_intersection(id);
+        }
+    }
+
+    // after the roads, the saplings keep away from them
+    for shape in &imported.forests {
+        map.plant_forest(tick, shape);
+    }
+
+    info!(
+        "loading the imported map took {}ms",
+        time.elapsed().as_secs_f32() * 1000.0
+    );
+    map.check_invariants();
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Direction {
+    Both,
+    Forward,
+    Backward,
+}
+
+enum WayKind {
+    Road(LanePatternBuilder, Direction),
+    UnsupportedHighway,
+    Water,
+    Forest,
+    Other,
+}
+
+fn way_kind(way: &OsmWay) -> WayKind {
+    if let Some(highway) = way.tag("highway") {
+        let Some(builder) = highway_pattern(highway) else {
+            return WayKind::UnsupportedHighway;
+        };
+        return WayKind::Road(road_details(way, builder), one_way(way, highway));
+    }
+    if matches!(way.tag("railway"), Some("rail" | "light_rail")) {
+        return WayKind::Road(LanePatternBuilder::new().rail(true), Direction::Both);
+    }
+    match (way.tag("natural"), way.tag("landuse"), way.tag("waterway")) {
+        (Some("water"), _, _) | (_, Some("reservoir" | "basin"), _) | (_, _, Some("riverbank")) => {
+            WayKind::Water
+        }
+        (Some("wood"), _, _) | (_, Some("forest"), _) => WayKind::Forest,
+        _ => WayKind::Other,
+    }
+}
+
+/// The road of the toolbox closest to the highway class, None for those without cars
+fn highway_pattern(highway: &str) -> Option<LanePatternBuilder> {
+    let link = highway.ends_with("_link");
+    let builder = match highway.trim_end_matches("_link") {
+        "motorway" | "trunk" => LanePatternBuilder::new()
+            .n_lanes(3)
+            .speed_limit(25.0)
+            .parking(false)
+            .sidewalks(false),
+        "primary" => LanePatternBuilder::new().n_lanes(2).speed_limit(16.0),
+        "secondary" => LanePatternBuilder::new().n_lanes(2).speed_limit(13.0),
+        "tertiary" => LanePatternBuilder::new().speed_limit(13.0),
+        "residential" | "unclassified" | "living_street" | "road" => LanePatternBuilder::new(),
+        "service" => LanePatternBuilder::new().parking(false).sidewalks(false),
+        _ => return None,
+    };
+    if link {
+        return Some(builder.n_lanes(1).parking(false));
+    }
+    Some(builder)
+}
+
+/// Applies the lane count and speed limit tags, when they are given
+fn road_details(way: &OsmWay, mut builder: LanePatternBuilder) -> LanePatternBuilder {
+    if let Some(lanes) = way.tag("lanes").and_then(|l| l.trim().parse::<u32>().ok()) {
+        let per_direction = if one_way(way, way.tag("highway").unwrap_or("")) == Direction::Both {
+            lanes / 2
+        } else {
+            lanes
+        };
+        builder = builder.n_lanes(per_direction.clamp(1, 4));
+    }
+    if let Some(limit) = way.tag("maxspeed").and_then(parse_maxspeed) {
+        builder = builder.speed_limit(limit.clamp(MIN_SPEED_LIMIT, MAX_SPEED_LIMIT));
+    }
+    builder
+}
+
+fn one_way(way: &OsmWay, highway: &str) -> Direction {
+    match way.tag("oneway") {
+        Some("yes" | "true" | "1") => Direction::Forward,
+        Some("-1" | "reverse") => Direction::Backward,
+        Some("no" | "false" | "0") => Direction::Both,
+        _ if matches!(highway, "motorway" | "motorway_link")
+            || matches!(way.tag("junction"), Some("roundabout" | "circular")) =>
+        {
+            Direction::Forward
+        }
+        _ => Direction::Both,
+    }
+}
+
+/// Speed limit in m/s of a maxspeed tag, in km/h unless it says mph
+fn parse_maxspeed(tag: &str) -> Option<f32> {
+    let (value, mph) = match tag.trim().strip_suffix("mph") {
+        Some(value) => (value, true),
+        None => (tag.trim().trim_end_matches("km/h"), false),
+    };
+    let value: f32 = value.trim().parse().ok()?;
+    let kmh = if mph { value * 1.609_344 } else { value };
+    Some(kmh / 3.6)
+}
+
+/// Equirectangular projection around the origin, accurate enough at the size of a city
+struct Projection {
+    origin: (f64, f64),
+    cos_lat: f64,
+    scale: f64,
+    center: Vec2,
+}
+
+impl Projection {
+    fn new(
+        osm: &OsmData,
+        roads: &[(&OsmWay, LanePatternBuilder, Direction)],
+        opts: &OsmImportOptions,
+        center: Vec2,
+    ) -> Self {
+        let origin = opts.origin.unwrap_or_else(|| {
+            // the middle of the box holding the roads
+            let mut min = (f64::MAX, f64::MAX);
+            let mut max = (f64::MIN, f64::MIN);
+            for (way, _, _) in roads {
+                for &(lat, lon) in way.nodes.iter().filter_map(|id| osm.nodes.get(id)) {
+                    min = (min.0.min(lat), min.1.min(lon));
+                    max = (max.0.max(lat), max.1.max(lon));
+                }
+            }
+            if min.0 > max.0 {
+                return (0.0, 0.0);
+            }
+            ((min.0 + max.0) * 0.5, (min.1 + max.1) * 0.5)
+        });
+        Self {
+            origin,
+            cos_lat: origin.0.to_radians().cos(),
+            scale: opts.scale.max(0.01) as f64 * METERS_PER_DEGREE,
+            center,
+        }
+    }
+
+    fn project(&self, (lat, lon): (f64, f64)) -> Vec2 {
+        let x = (lon - self.origin.1) * self.cos_lat * self.scale;
+        let y = (lat - self.origin.0) * self.scale;
+        self.center + vec2(x as f32, y as f32)
+    }
+}
+
+/// Part of the segment inside the area, as the start and end coefficients along it
+fn clip_segment(a: Vec2, b: Vec2, area: AABB) -> Option<(f32, f32)> {
+    let d = b - a;
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in [
+        (-d.x, a.x - area.ll.x),
+        (d.x, area.ur.x - a.x),
+        (-d.y, a.y - area.ll.y),
+        (d.y, area.ur.y - a.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+    }
+    (t0 <= t1).then_some((t0, t1))
+}
+
+/// Splits the line into the pieces inside the area, cut where they cross its border.
+/// The points created at the border have no osm node.
+fn clip_line(points: &[(Vec2, Option<i64>)], area: AABB) -> Vec<Vec<(Vec2, Option<i64>)>> {
+    let mut pieces = vec![];
+    let mut cur: Vec<(Vec2, Option<i64>)> = vec![];
+    for w in points.windows(2) {
+        let ((a, a_id), (b, b_id)) = (w[0], w[1]);
+        let Some((t0, t1)) = clip_segment(a, b, area) else {
+            pieces.push(std::mem::take(&mut cur));
+            continue;
+        };
+        if cur.is_empty() {
+            cur.push(if t0 > 0.0 {
+                (a + (b - a) * t0, None)
+            } else {
+                (a, a_id)
+            });
+        }
+        if t1 < 1.0 {
+            cur.push((a + (b - a) * t1, None));
+            pieces.push(std::mem::take(&mut cur));
+        } else {
+            cur.push((b, b_id));
+        }
+    }
+    pieces.push(cur);
+    pieces.retain(|p| p.len() >= 2);
+    pieces
+}
+
+/// Sutherland-Hodgman clipping of the polygon to the area
+fn clip_polygon(points: &[Vec2], area: AABB) -> Polygon {
+    let mut poly = points.to_vec();
+    // signed distance to each side of the area, positive inside
+    let sides: [fn(Vec2, AABB) -> f32; 4] = [
+        |p, a| p.x - a.ll.x,
+        |p, a| a.ur.x - p.x,
+        |p, a| p.y - a.ll.y,
+        |p, a| a.ur.y - p.y,
+    ];
+    for inside in sides {
+        let input = std::mem::take(&mut poly);
+        for (i, &cur) in input.iter().enumerate() {
+            let prev = input[(i + input.len() - 1) % input.len()];
+            let (dc, dp) = (inside(cur, area), inside(prev, area));
+            if (dc >= 0.0) != (dp >= 0.0) {
+                poly.push(prev + (cur - prev) * (dp / (dp - dc)));
+            }
+            if dc >= 0.0 {
+                poly.push(cur);
+            }
+        }
+    }
+    Polygon(poly)
+}
+
+/// Which points of the piece are kept: the junctions and the ends, and in between the points
+/// the road bends at, at most every [`NODE_SPACING`]
+fn simplify(piece: &[(Vec2, Option<i64>)], junctions: &FastSet<i64>) -> Vec<bool> {
+    let mut keep: Vec<bool> = piece
+        .iter()
+        .map(|(_, id)| id.map_or(true, |id| junctions.contains(&id)))
+        .collect();
+    keep[0] = true;
+    *keep.last_mut().unwrap() = true; // Unwrap ok: a piece has at least two points
+
+    let fixed: Vec<usize> = (0..piece.len()).filter(|&i| keep[i]).collect();
+    for w in fixed.windows(2) {
+        douglas_peucker(piece, w[0], w[1], &mut keep);
+
+        let mut last = piece[w[0]].0;
+        for i in w[0] + 1..w[1] {
+            if !keep[i] {
+                continue;
+            }
+            if last.distance(piece[i].0) < NODE_SPACING
+                || piece[w[1]].0.distance(piece[i].0) < NODE_SPACING
+            {
+                keep[i] = false;
+                continue;
+            }
+            last = piece[i].0;
+        }
+    }
+    keep
+}
+
+fn douglas_peucker(piece: &[(Vec2, Option<i64>)], start: usize, end: usize, keep: &mut [bool]) {
+    if end <= start + 1 {
+        return;
+    }
+    let seg = Segment::new(piece[start].0, piece[end].0);
+    let (far, dist) = (start + 1..end)
+        .map(|i| (i, seg.project(piece[i].0).distance(piece[i].0)))
+        .fold((start, 0.0f32), |acc, x| if x.1 > acc.1 { x } else { acc });
+    if dist < SIMPLIFY_TOLERANCE {
+        return;
+    }
+    keep[far] = true;
+    douglas_peucker(piece, start, far, keep);
+    douglas_peucker(piece, far, end, keep);
+}
+
+/// Collects the nodes and roads, merging the nodes that are too close
+#[derive(Default)]
+struct GraphBuilder {
+    nodes: Vec<Vec2>,
+    by_osm: FastMap<i64, u32>,
+    /// Nodes by cell of [`MERGE_DISTANCE`] side
+    cells: FastMap<(i32, i32), Vec<u32>>,
+    roads: BTreeMap<(u32, u32), (LanePatternBuilder, bool)>,
+}
+
+impl GraphBuilder {
+    fn cell(pos: Vec2) -> (i32, i32) {
+        (
+            (pos.x / MERGE_DISTANCE).floor() as i32,
+            (pos.y / MERGE_DISTANCE).floor() as i32,
+        )
+    }
+
+    fn node(&mut self, pos: Vec2, osm: Option<i64>) -> u32 {
+        if let Some(&id) = osm.and_then(|osm| self.by_osm.get(&osm)) {
+            return id;
+        }
+
+        let (cx, cy) = Self::cell(pos);
+        let close = (cx - 1..=cx + 1)
+            .flat_map(|x| (cy - 1..=cy + 1).map(move |y| (x, y)))
+            .filter_map(|c| self.cells.get(&c))
+            .flatten()
+            .copied()
+            .find(|&id| self.nodes[id as usize].distance(pos) < MERGE_DISTANCE);
+
+        let id = close.unwrap_or_else(|| {
+            let id = self.nodes.len() as u32;
+            self.nodes.push(pos);
+            self.cells.entry((cx, cy)).or_default().push(id);
+            id
+        });
+        if let Some(osm) = osm {
+            self.by_osm.insert(osm, id);
+        }
+        id
+    }
+
+    /// The widest road is kept when two ways join the same nodes
+    fn road(&mut self, from: u32, to: u32, builder: LanePatternBuilder) {
+        if from == to {
+            return;
+        }
+        let key = (from.min(to), from.max(to));
+        let reversed = from > to;
+        match self.roads.get(&key) {
+            Some((existing, _)) if existing.width() >= builder.width() => {}
+            _ => {
+                self.roads.insert(key, (builder, reversed));
+            }
+        }
+    }
+
+    fn finish(self) -> ImportedMap {
+        let roads = self
+            .roads
+            .into_iter()
+            .map(|((a, b), (builder, reversed))| {
+                let (from, to) = if reversed { (b, a) } else { (a, b) };
+                (from, to, builder.build())
+            })
+            .collect();
+        ImportedMap {
+            nodes: self.nodes,
+            roads,
+            water: vec![],
+            forests: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::LaneKind;
+
+    fn way(id: i64, nodes: &[i64], tags: &[(&str, &str)]) -> OsmWay {
+        OsmWay {
+            id,
+            nodes: nodes.to_vec(),
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn converts_roads_to_a_graph() {
+        let mut osm = OsmData::default();
+        // a straight east-west street of 5 nodes 100m apart, crossed in its middle
+        for i in 0..5 {
+            let lon = i as f64 * 100.0 / METERS_PER_DEGREE;
+            osm.nodes.insert(i, (0.0, lon));
+        }
+        let mid_lon = 200.0 / METERS_PER_DEGREE;
+        osm.nodes.insert(10, (100.0 / METERS_PER_DEGREE, mid_lon));
+        osm.nodes.insert(11, (-100.0 / METERS_PER_DEGREE, mid_lon));
+        // far outside of the map
+        osm.nodes.insert(12, (-1.0, mid_lon));
+
+        osm.ways
+            .push(way(1, &[0, 1, 2, 3, 4], &[("highway", "residential")]));
+        osm.ways.push(way(
+            2,
+            &[10, 2, 11, 12],
+            &[("highway", "primary"), ("oneway", "-1"), ("maxspeed", "30")],
+        ));
+        osm.ways.push(way(3, &[0, 10], &[("highway", "footway")]));
+        osm.ways
+            .push(way(4, &[0, 99], &[("highway", "residential")]));
+
+        let bounds = AABB::new_ll_ur(Vec2::ZERO, Vec2::splat(1000.0));
+        let import = convert(&osm, &OsmImportOptions::default(), bounds);
+        let map = &import.map;
+
+        // the straight roads only keep their ends, the crossing and the cut at the border
+        assert_eq!(map.roads.len(), 4);
+        assert!(map.nodes.iter().all(|&p| bounds.contains(p)));
+
+        let one_way: Vec<_> = map
+            .roads
+            .iter()
+            .filter(|r| r.2.lanes_backward.iter().all(|l| l.0 != LaneKind::Driving))
+            .collect();
+        assert_eq!(one_way.len(), 2);
+        // reversed, it goes from the south to the north
+        for (from, to, pattern) in one_way {
+            assert!(map.nodes[*from as usize].y < map.nodes[*to as usize].y);
+            assert!((pattern.lanes().next().unwrap().2 - 30.0 / 3.6).abs() < 1e-3);
+        }
+
+        assert_eq!(import.skipped.len(), 2);
+    }
+
+    #[test]
+    fn clips_lines_at_the_border() {
+        let area = AABB::new_ll_ur(Vec2::ZERO, Vec2::splat(10.0));
+        let line = [
+            (vec2(-5.0, 5.0), Some(1)),
+            (vec2(5.0, 5.0), Some(2)),
+            (vec2(15.0, 5.0), Some(3)),
+            (vec2(15.0, 8.0), Some(4)),
+            (vec2(5.0, 8.0), Some(5)),
+        ];
+        let pieces = clip_line(&line, area);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(
+            pieces[0],
+            vec![
+                (vec2(0.0, 5.0), None),
+                (vec2(5.0, 5.0), Some(2)),
+                (vec2(10.0, 5.0), None)
+            ]
+        );
+        assert_eq!(
+            pieces[1],
+            vec![(vec2(10.0, 8.0), None), (vec2(5.0, 8.0), Some(5))]
+        );
+
+        let poly = clip_polygon(
+            &[
+                vec2(-5.0, -5.0),
+                vec2(5.0, -5.0),
+                vec2(5.0, 5.0),
+                vec2(-5.0, 5.0),
+            ],
+            area,
+        );
+        assert!((poly.area().abs() - 25.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn parses_speed_limits() {
+        assert_eq!(parse_maxspeed("36"), Some(10.0));
+        assert!((parse_maxspeed("30 mph").unwrap() - 13.4112).abs() < 1e-3);
+        assert_eq!(parse_maxspeed("signals"), None);
+    }
+}
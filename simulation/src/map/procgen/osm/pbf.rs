@@ -0,0 +1,366 @@
+//! Reader of the `.osm.pbf` extracts: a sequence of zlib compressed protobuf blocks.
+//! Only the fields the import uses are decoded, see
+//! <https://wiki.openstreetmap.org/wiki/PBF_Format> for the whole format.
+
+use super::{OsmData, OsmError, OsmWay};
+
+/// Blocks are at most 32MiB once decompressed, the headers at most 64KiB
+const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+
+pub(super) fn parse(data: &[u8]) -> Result<OsmData, OsmError> {
+    let mut osm = OsmData::default();
+    let mut rest = data;
+
+    while !rest.is_empty() {
+        let header_len = u32::from_be_bytes(take(&mut rest, 4)?.try_into().unwrap()) as usize;
+        if header_len > MAX_HEADER_SIZE {
+            return Err(OsmError::Invalid("blob header too big"));
+        }
+
+        let mut kind = &[][..];
+        let mut datasize = 0;
+        for field in Fields(take(&mut rest, header_len)?) {
+            match field? {
+                (1, Value::Bytes(b)) => kind = b,
+                (3, Value::Varint(v)) => datasize = v as usize,
+                _ => {}
+            }
+        }
+        let blob = take(&mut rest, datasize)?;
+
+        match kind {
+            b"OSMHeader" => {
+                for feature in header_features(&decompress(blob)?)? {
+                    if feature != "OsmSchema-V0.6" && feature != "DenseNodes" {
+                        return Err(OsmError::Unsupported(format!("required feature {feature}")));
+                    }
+                }
+            }
+            b"OSMData" => primitive_block(&decompress(blob)?, &mut osm)?,
+            // unknown blobs are meant to be skipped
+            _ => {}
+        }
+    }
+
+    Ok(osm)
+}
+
+fn take<'a>(data: &mut &'a [u8], n: usize) -> Result<&'a [u8], OsmError> {
+    if data.len() < n {
+        return Err(OsmError::Invalid("truncated file"));
+    }
+    let (head, tail) = data.split_at(n);
+    *data = tail;
+    Ok(head)
+}
+
+fn decompress(blob: &[u8]) -> Result<Vec<u8>, OsmError> {
+    for field in Fields(blob) {
+        match field? {
+            (1, Value::Bytes(raw)) => return Ok(raw.to_vec()),
+            (3, Value::Bytes(zlib)) => {
+                return miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(
+                    zlib,
+                    MAX_BLOB_SIZE,
+                )
+                .map_err(|_| OsmError::Invalid("corrupted zlib block"));
+            }
+            (4 | 6 | 7, _) => {
+                return Err(OsmError::Unsupported(
+                    "blocks compressed with lzma, lz4 or zstd".to_string(),
+                ))
+            }
+            _ => {}
+        }
+    }
+    Err(OsmError::Invalid("empty blob"))
+}
+
+fn header_features(block: &[u8]) -> Result<Vec<String>, OsmError> {
+    let mut features = vec![];
+    for field in Fields(block) {
+        if let (4, Value::Bytes(b)) = field? {
+            features.push(String::from_utf8_lossy(b).into_owned());
+        }
+    }
+    Ok(features)
+}
+
+/// The coordinates of a block are stored as integers in units of `granularity` nanodegrees
+struct Block<'a> {
+    strings: Vec<&'a [u8]>,
+    granularity: i64,
+    lat_offset: i64,
+    lon_offset: i64,
+}
+
+impl Block<'_> {
+    fn lat(&self, v: i64) -> f64 {
+        1e-9 * (self.lat_offset + self.granularity * v) as f64
+    }
+
+    fn lon(&self, v: i64) -> f64 {
+        1e-9 * (self.lon_offset + self.granularity * v) as f64
+    }
+
+    fn string(&self, i: u64) -> Result<String, OsmError> {
+        self.strings
+            .get(i as usize)
+            .map(|s| String::from_utf8_lossy(s).into_owned())
+            .ok_or(OsmError::Invalid("string index out of the table"))
+    }
+}
+
+fn primitive_block(data: &[u8], osm: &mut OsmData) -> Result<(), OsmError> {
+    let mut block = Block {
+        strings: vec![],
+        granularity: 100,
+        lat_offset: 0,
+        lon_offset: 0,
+    };
+    let mut groups = vec![];
+    for field in Fields(data) {
+        match field? {
+            (1, Value::Bytes(table)) => {
+                for field in Fields(table) {
+                    if let (1, Value::Bytes(s)) = field? {
+                        block.strings.push(s);
+                    }
+                }
+            }
+            (2, Value::Bytes(group)) => groups.push(group),
+            (17, Value::Varint(v)) => block.granularity = v as i64,
+            (19, Value::Varint(v)) => block.lat_offset = v as i64,
+            (20, Value::Varint(v)) => block.lon_offset = v as i64,
+            _ => {}
+        }
+    }
+
+    // the string table may come after the groups
+    for group in groups {
+        for field in Fields(group) {
+            match field? {
+                (1, Value::Bytes(node)) => self::node(&block, node, osm)?,
+                (2, Value::Bytes(dense)) => dense_nodes(&block, dense, osm)?,
+                (3, Value::Bytes(way)) => self::way(&block, way, osm)?,
+                (4, _) => osm.relations += 1,
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn node(block: &Block, data: &[u8], osm: &mut OsmData) -> Result<(), OsmError> {
+    let (mut id, mut lat, mut lon) = (0, 0, 0);
+    for field in Fields(data) {
+        match field? {
+            (1, Value::Varint(v)) => id = zigzag(v),
+            (8, Value::Varint(v)) => lat = zigzag(v),
+            (9, Value::Varint(v)) => lon = zigzag(v),
+            _ => {}
+        }
+    }
+    osm.nodes.insert(id, (block.lat(lat), block.lon(lon)));
+    Ok(())
+}
+
+/// The ids and coordinates of dense nodes are delta coded
+fn dense_nodes(block: &Block, data: &[u8], osm: &mut OsmData) -> Result<(), OsmError> {
+    let (mut ids, mut lats, mut lons) = (vec![], vec![], vec![]);
+    for field in Fields(data) {
+        match field? {
+            (1, Value::Bytes(b)) => ids = packed(b)?,
+            (8, Value::Bytes(b)) => lats = packed(b)?,
+            (9, Value::Bytes(b)) => lons = packed(b)?,
+            _ => {}
+        }
+    }
+    if ids.len() != lats.len() || ids.len() != lons.len() {
+        return Err(OsmError::Invalid("dense nodes of different lengths"));
+    }
+
+    let (mut id, mut lat, mut lon) = (0, 0, 0);
+    for ((did, dlat), dlon) in ids.into_iter().zip(lats).zip(lons) {
+        id += zigzag(did);
+        lat += zigzag(dlat);
+        lon += zigzag(dlon);
+        osm.nodes.insert(id, (block.lat(lat), block.lon(lon)));
+    }
+    Ok(())
+}
+
+fn way(block: &Block, data: &[u8], osm: &mut OsmData) -> Result<(), OsmError> {
+    let mut way = OsmWay::default();
+    let (mut keys, mut vals) = (vec![], vec![]);
+    for field in Fields(data) {
+        match field? {
+            (1, Value::Varint(v)) => way.id = v as i64,
+            (2, Value::Bytes(b)) => keys = packed(b)?,
+            (3, Value::Bytes(b)) => vals = packed(b)?,
+            (8, Value::Bytes(b)) => {
+                let mut node = 0;
+                for delta in packed(b)? {
+                    node += zigzag(delta);
+                    way.nodes.push(node);
+                }
+            }
+            _ => {}
+        }
+    }
+    for (k, v) in keys.into_iter().zip(vals) {
+        way.tags.push((block.string(k)?, block.string(v)?));
+    }
+    osm.ways.push(way);
+    Ok(())
+}
+
+fn zigzag(v: u64) -> i64 {
+    (v >> 1) as i64 ^ -((v & 1) as i64)
+}
+
+fn packed(mut data: &[u8]) -> Result<Vec<u64>, OsmError> {
+    let mut values = vec![];
+    while !data.is_empty() {
+        values.push(varint(&mut data)?);
+    }
+    Ok(values)
+}
+
+fn varint(data: &mut &[u8]) -> Result<u64, OsmError> {
+    let mut v = 0;
+    for shift in (0..64).step_by(7) {
+        let (&b, rest) = data
+            .split_first()
+            .ok_or(OsmError::Invalid("truncated varint"))?;
+        *data = rest;
+        v |= ((b & 0x7F) as u64) << shift;
+        if b < 0x80 {
+            return Ok(v);
+        }
+    }
+    Err(OsmError::Invalid("varint too long"))
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// The fields of a protobuf message, with their number
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u32, Value<'a>), OsmError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let field = (|| {
+            let key = varint(&mut self.0)?;
+            let value = match key & 7 {
+                0 => Value::Varint(varint(&mut self.0)?),
+                1 => {
+                    take(&mut self.0, 8)?;
+                    Value::Fixed
+                }
+                2 => {
+                    let len = varint(&mut self.0)? as usize;
+                    Value::Bytes(take(&mut self.0, len)?)
+                }
+                5 => {
+                    take(&mut self.0, 4)?;
+                    Value::Fixed
+                }
+                _ => return Err(OsmError::Invalid("unknown protobuf wire type")),
+            };
+            Ok(((key >> 3) as u32, value))
+        })();
+        if field.is_err() {
+            self.0 = &[];
+        }
+        Some(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+        while v >= 0x80 {
+            out.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+    }
+
+    fn put_bytes(out: &mut Vec<u8>, field: u32, b: &[u8]) {
+        put_varint(out, (field as u64) << 3 | 2);
+        put_varint(out, b.len() as u64);
+        out.extend_from_slice(b);
+    }
+
+    fn put_packed(out: &mut Vec<u8>, field: u32, values: &[i64]) {
+        let mut b = vec![];
+        for &v in values {
+            put_varint(&mut b, ((v << 1) ^ (v >> 63)) as u64);
+        }
+        put_bytes(out, field, &b);
+    }
+
+    #[test]
+    fn reads_dense_nodes_and_ways() {
+        let mut dense = vec![];
+        put_packed(&mut dense, 1, &[10, 1]);
+        // 48.85° and 2.35° in the default granularity of 100 nanodegrees
+        put_packed(&mut dense, 8, &[488_500_000, 1000]);
+        put_packed(&mut dense, 9, &[23_500_000, -1000]);
+
+        let mut way = vec![];
+        put_varint(&mut way, 1 << 3);
+        put_varint(&mut way, 7);
+        put_packed(&mut way, 2, &[1]);
+        put_packed(&mut way, 3, &[2]);
+        put_packed(&mut way, 8, &[10, 1]);
+
+        let mut group = vec![];
+        put_bytes(&mut group, 2, &dense);
+        put_bytes(&mut group, 3, &way);
+
+        let mut table = vec![];
+        for s in ["", "highway", "residential"] {
+            put_bytes(&mut table, 1, s.as_bytes());
+        }
+        let mut block = vec![];
+        put_bytes(&mut block, 1, &table);
+        put_bytes(&mut block, 2, &group);
+
+        let mut blob = vec![];
+        let zlib = miniz_oxide::deflate::compress_to_vec_zlib(&block, 6);
+        put_bytes(&mut blob, 3, &zlib);
+
+        let mut header = vec![];
+        put_bytes(&mut header, 1, b"OSMData");
+        put_varint(&mut header, 3 << 3);
+        put_varint(&mut header, blob.len() as u64);
+
+        let mut file = (header.len() as u32).to_be_bytes().to_vec();
+        file.extend(header);
+        file.extend(blob);
+
+        let osm = parse(&file).unwrap();
+        assert_eq!(osm.nodes.len(), 2);
+        let (lat, lon) = osm.nodes[&11];
+        assert!((lat - 48.8501).abs() < 1e-9 && (lon - 2.3499).abs() < 1e-9);
+        assert_eq!(osm.ways.len(), 1);
+        assert_eq!(osm.ways[0].id, 7);
+        assert_eq!(osm.ways[0].nodes, vec![10, 11]);
+        assert_eq!(osm.ways[0].tag("highway"), Some("residential"));
+
+        assert!(parse(&file[..file.len() - 3]).is_err());
+    }
+}
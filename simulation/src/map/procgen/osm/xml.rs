@@ -0,0 +1,165 @@
+//! Reader of the `.osm` XML extracts.
+//! Only the node, way, nd, tag and relation elements are looked at, the rest is skipped.
+
+use super::{OsmData, OsmError, OsmWay};
+
+pub(super) fn parse(data: &str) -> Result<OsmData, OsmError> {
+    let mut osm = OsmData::default();
+    let mut way: Option<OsmWay> = None;
+    let mut rest = data;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        // comments, declarations and doctypes
+        if let Some(comment) = rest.strip_prefix("!--") {
+            let end = comment
+                .find("-->")
+                .ok_or(OsmError::Invalid("unclosed comment"))?;
+            rest = &comment[end + 3..];
+            continue;
+        }
+        let end = rest.find('>').ok_or(OsmError::Invalid("unclosed tag"))?;
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+
+        if let Some(name) = tag.strip_prefix('/') {
+            if name.trim() == "way" {
+                osm.ways.extend(way.take());
+            }
+            continue;
+        }
+
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let (name, attrs) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let attrs = Attributes::parse(attrs)?;
+
+        match name {
+            "node" => {
+                let id = attrs.number("id")?;
+                osm.nodes
+                    .insert(id, (attrs.number("lat")?, attrs.number("lon")?));
+            }
+            "way" => {
+                let w = OsmWay {
+                    id: attrs.number("id")?,
+                    ..Default::default()
+                };
+                if self_closing {
+                    osm.ways.push(w);
+                } else {
+                    way = Some(w);
+                }
+            }
+            "nd" => {
+                if let Some(ref mut way) = way {
+                    way.nodes.push(attrs.number("ref")?);
+                }
+            }
+            "tag" => {
+                if let Some(ref mut way) = way {
+                    way.tags
+                        .push((attrs.get("k")?.to_string(), attrs.get("v")?.to_string()));
+                }
+            }
+            "relation" => osm.relations += 1,
+            _ => {}
+        }
+    }
+
+    Ok(osm)
+}
+
+struct Attributes(Vec<(String, String)>);
+
+impl Attributes {
+    fn parse(mut s: &str) -> Result<Self, OsmError> {
+        let mut attrs = vec![];
+        loop {
+            s = s.trim_start();
+            if s.is_empty() {
+                return Ok(Self(attrs));
+            }
+            let (key, value) = s
+                .split_once('=')
+                .ok_or(OsmError::Invalid("attribute without value"))?;
+            let value = value.trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|&c| c == '"' || c == '\'')
+                .ok_or(OsmError::Invalid("unquoted attribute"))?;
+            let value = &value[1..];
+            let end = value
+                .find(quote)
+                .ok_or(OsmError::Invalid("unclosed attribute"))?;
+            attrs.push((key.trim().to_string(), unescape(&value[..end])));
+            s = &value[end + 1..];
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<&str, OsmError> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .ok_or(OsmError::Invalid("missing attribute"))
+    }
+
+    fn number<T: std::str::FromStr>(&self, key: &str) -> Result<T, OsmError> {
+        self.get(key)?
+            .parse()
+            .map_err(|_| OsmError::Invalid("attribute is not a number"))
+    }
+}
+
+fn unescape(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_nodes_and_ways() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6" generator="test">
+  <!-- a <comment> -->
+  <bounds minlat="48.8" minlon="2.3" maxlat="48.9" maxlon="2.4"/>
+  <node id="1" lat="48.85" lon="2.35" version="1"/>
+  <node id="2" lat='48.86' lon="2.36">
+    <tag k="highway" v="traffic_signals"/>
+  </node>
+  <way id="10">
+    <nd ref="1"/>
+    <nd ref="2"/>
+    <tag k="highway" v="primary"/>
+    <tag k="name" v="Rue de l&apos;Église &amp; Cie"/>
+  </way>
+  <relation id="5"><member type="way" ref="10" role="outer"/></relation>
+</osm>"#;
+
+        let osm = parse(xml).unwrap();
+        assert_eq!(osm.nodes.len(), 2);
+        assert_eq!(osm.nodes[&2], (48.86, 2.36));
+        assert_eq!(osm.ways.len(), 1);
+        assert_eq!(osm.ways[0].nodes, vec![1, 2]);
+        assert_eq!(osm.ways[0].tag("highway"), Some("primary"));
+        assert_eq!(osm.ways[0].tag("name"), Some("Rue de l'Église & Cie"));
+        assert_eq!(osm.relations, 1);
+
+        assert!(parse("<osm><node id=\"1\" lat=\"x\" lon=\"2\"/></osm>").is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! Areas of the map where the player is not allowed to build, placed by the scenarios
+//! or drawn by hand in sandbox games.
+
+use geom::{Intersect, PolyLine3, Polygon, Shape, Vec2, Vec3};
+use prototypes::RestrictionKind;
+use serde::{Deserialize, Serialize};
+use slotmapd::{new_key_type, HopSlotMap};
+
+use crate::map::{valid_district_shape, Map, Road, RoadSegmentKind};
+
+new_key_type! {
+    pub struct RestrictionID;
+}
+
+pub type Restrictions = HopSlotMap<RestrictionID, Restriction>;
+
+/// Distance between the points of a road checked against the restricted areas
+const PATH_STEP: f32 = 4.0;
+
+/// What the player does to the map, the restricted areas forbid some of it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RestrictedAction {
+    /// Roads, buildings and houses growing on lots
+    Build,
+    /// Removing roads, intersections and buildings
+    Demolish,
+    CutTrees,
+    /// Reshaping the heightmap, planting trees is always allowed
+    Terraform,
+}
+
+impl RestrictedAction {
+    pub fn forbidden_by(self, kind: RestrictionKind) -> bool {
+        use RestrictionKind::*;
+        match self {
+            Self::Build => true,
+            Self::Demolish => matches!(kind, Nature | Water),
+            Self::CutTrees => kind == Nature,
+            Self::Terraform => matches!(kind, Nature | Water),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Restriction {
+    pub id: RestrictionID,
+    pub kind: RestrictionKind,
+    pub shape: Polygon,
+    /// Set by the scenarios to lift the restriction later, empty for the ones drawn by hand
+    pub name: String,
+}
+
+impl Restriction {
+    /// Why the action is refused, shown to the player
+    pub fn reason(&self) -> String {
+        if self.name.is_empty() {
+            format!("Inside a {} area", self.kind.label())
+        } else {
+            format!("Inside the {} area {}", self.kind.label(), self.name)
+        }
+    }
+}
+
+impl Map {
+    pub fn restrictions(&self) -> &Restrictions {
+        &self.restrictions
+    }
+
+    /// Adds a restricted area, unless its shape is invalid.
+    /// Restricted areas may overlap, they do not change what is already built.
+    pub fn add_restriction(
+        &mut self,
+        kind: RestrictionKind,
+        shape: Polygon,
+        name: &str,
+    ) -> Option<RestrictionID> {
+        info!("add_restriction {:?} {:?}", kind, name);
+        if !valid_district_shape(&shape) {
+            log::warn!("invalid restriction shape for {:?}", name);
+            return None;
+        }
+        Some(self.restrictions.insert_with_key(|id| Restriction {
+            id,
+            kind,
+            shape,
+            name: name.trim().to_string(),
+        }))
+    }
+
+    pub fn remove_restriction(&mut self, id: RestrictionID) -> Option<Restriction> {
+        info!("remove_restriction {:?}", id);
+        self.restrictions.remove(id)
+    }
+
+    /// Removes the restrictions with that name, returns how many were removed
+    pub fn lift_restrictions(&mut self, name: &str) -> usize {
+        let before = self.restrictions.len();
+        self.restrictions.retain(|_, r| r.name != name);
+        before - self.restrictions.len()
+    }
+
+    /// The first restricted area forbidding the action on the shape, if any
+    pub fn restriction_on<S>(&self, shape: &S, action: RestrictedAction) -> Option<&Restriction>
+    where
+        S: Shape,
+        Polygon: Intersect<S>,
+    {
+        self.restrictions
+            .values()
+            .filter(|r| action.forbidden_by(r.kind))
+            .find(|r| r.shape.intersects(shape))
+    }
+
+    /// The first restricted area forbidding the action along the path, if any
+    pub fn restriction_on_path(
+        &self,
+        path: &PolyLine3,
+        action: RestrictedAction,
+    ) -> Option<&Restriction> {
+        let length = path.length();
+        let steps = (length / PATH_STEP).ceil().max(1.0) as usize;
+        (0..=steps)
+            .map(|i| path.point_along(length * i as f32 / steps as f32).xy())
+            .find_map(|p| self.restriction_on(&p, action))
+    }
+
+    /// The first restricted area forbidding a road between the two points, if any.
+    /// The road is laid out like it would be built, `inter` bends it.
+    pub fn restriction_on_connection(
+        &self,
+        from: Vec3,
+        to: Vec3,
+        inter: Option<Vec2>,
+    ) -> Option<&Restriction> {
+        if self.restrictions.is_empty() {
+            return None;
+        }
+        let segment = match inter {
+            Some(x) => RoadSegmentKind::from_elbow(from.xy(), to.xy(), x),
+            None => RoadSegmentKind::Straight,
+        };
+        let (points, _) = Road::generate_points(from, to, segment, false, &self.environment);
+        self.restriction_on_path(&points, RestrictedAction::Build)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, Circle, OBB};
+
+    use super::*;
+
+    #[test]
+    fn restrictions_forbid_by_kind() {
+        let mut map = Map::empty();
+        let square = Polygon(vec![
+            vec2(0.0, 0.0),
+            vec2(100.0, 0.0),
+            vec2(100.0, 100.0),
+            vec2(0.0, 100.0),
+        ]);
+        assert!(map
+            .add_restriction(RestrictionKind::NoBuild, Polygon(vec![]), "")
+            .is_none());
+        let id = map
+            .add_restriction(RestrictionKind::NoBuild, square.clone(), "")
+            .unwrap();
+
+        let inside = OBB::new(vec2(50.0, 50.0), Vec2::X, 10.0, 10.0);
+        let across = OBB::new(vec2(100.0, 50.0), Vec2::X, 10.0, 10.0);
+        let outside = OBB::new(vec2(200.0, 50.0), Vec2::X, 10.0, 10.0);
+        assert!(map
+            .restriction_on(&inside, RestrictedAction::Build)
+            .is_some());
+        assert!(map
+            .restriction_on(&across, RestrictedAction::Build)
+            .is_some());
+        assert!(map
+            .restriction_on(&outside, RestrictedAction::Build)
+            .is_none());
+        assert!(map
+            .restriction_on(&inside, RestrictedAction::Demolish)
+            .is_none());
+
+        let circle = Circle::new(vec2(150.0, 50.0), 60.0);
+        assert!(map
+            .restriction_on(&circle, RestrictedAction::Terraform)
+            .is_none());
+        map.add_restriction(RestrictionKind::Nature, square, "Reserve");
+        assert_eq!(
+            map.restriction_on(&circle, RestrictedAction::Terraform)
+                .unwrap()
+                .reason(),
+            "Inside the protected nature area Reserve"
+        );
+        assert!(map
+            .restriction_on(&vec2(50.0, 50.0), RestrictedAction::CutTrees)
+            .is_some());
+
+        assert_eq!(map.lift_restrictions("Reserve"), 1);
+        assert!(map
+            .restriction_on(&circle, RestrictedAction::Terraform)
+            .is_none());
+        assert!(map.remove_restriction(id).is_some());
+        assert!(map.restrictions().is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+//! Layouts of several roads generated at once by the road tool, before they are snapped to the map.
+
+use geom::{Line, Vec2};
+
+use crate::map::RoadSegmentKind;
+
+/// Blocks are never smaller than this, in meters
+pub const MIN_BLOCK_SIZE: f32 = 20.0;
+
+/// Street grid covering the rectangle between two corners.
+/// Returns the nodes, row by row from the `a` corner, and the streets between neighbouring nodes.
+/// The blocks are as close as possible to `block` while fitting the rectangle exactly.
+pub fn grid_layout(a: Vec2, b: Vec2, block: Vec2) -> (Vec<Vec2>, Vec<(usize, usize)>) {
+    let size = b - a;
+    let count = |len: f32, block: f32| -> usize {
+        ((len.abs() / block.max(MIN_BLOCK_SIZE)).round() as usize).max(1)
+    };
+    let nx = count(size.x, block.x);
+    let ny = count(size.y, block.y);
+    let step = Vec2::new(size.x / nx as f32, size.y / ny as f32);
+
+    let mut nodes = Vec::with_capacity((nx + 1) * (ny + 1));
+    for y in 0..=ny {
+        for x in 0..=nx {
+            nodes.push(a + Vec2::new(step.x * x as f32, step.y * y as f32));
+        }
+    }
+
+    let idx = |x: usize, y: usize| y * (nx + 1) + x;
+    let mut edges = Vec::with_capacity(nx * (ny + 1) + ny * (nx + 1));
+    for y in 0..=ny {
+        for x in 0..=nx {
+            if x < nx {
+                edges.push((idx(x, y), idx(x + 1, y)));
+            }
+            if y < ny {
+                edges.push((idx(x, y), idx(x, y + 1)));
+            }
+        }
+    }
+
+    (nodes, edges)
+}
+
+/// Whether the node is on the border of a grid made by [`grid_layout`]
+pub fn grid_on_border(nodes: &[Vec2], i: usize) -> bool {
+    let (Some(first), Some(last)) = (nodes.first(), nodes.last()) else {
+        return false;
+    };
+    let p = nodes[i];
+    let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+    close(p.x, first.x) || close(p.x, last.x) || close(p.y, first.y) || close(p.y, last.y)
+}
+
+/// A road alongside the road going from `from` to `to`, `offset` meters on its right
+/// (on its left when negative).
+/// Returns the ends of the new road and its elbow when the original road is curved.
+pub fn parallel_layout(
+    from: Vec2,
+    to: Vec2,
+    segment: RoadSegmentKind,
+    offset: f32,
+) -> Option<(Vec2, Vec2, Option<Vec2>)> {
+    let (dir_from, dir_to) = match segment {
+        RoadSegmentKind::Straight => {
+            let dir = (to - from).try_normalize()?;
+            (dir, dir)
+        }
+        RoadSegmentKind::Curved((d_from, d_to)) => (d_from.try_normalize()?, d_to.try_normalize()?),
+    };
+
+    let start = from + dir_from.perpendicular() * offset;
+    let end = to + dir_to.perpendicular() * offset;
+
+    // the elbow of the new road is where its ends' tangents cross, like the original one
+    let elbow = match segment {
+        RoadSegmentKind::Straight => None,
+        RoadSegmentKind::Curved(_) => {
+            Line::new(start, start + dir_from).intersection_point(&Line::new(end, end + dir_to))
+        }
+    };
+
+    Some((start, end, elbow))
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec2;
+
+    use super::*;
+
+    #[test]
+    fn grid_fits_the_rectangle() {
+        let (nodes, edges) = grid_layout(vec2(0.0, 0.0), vec2(100.0, 65.0), vec2(50.0, 30.0));
+        // 2 blocks of 50m along x, 2 blocks of 32.5m along y
+        assert_eq!(nodes.len(), 9);
+        assert_eq!(edges.len(), 12);
+        assert!(nodes[8].is_close(vec2(100.0, 65.0), 0.01));
+        assert!(nodes[1].is_close(vec2(50.0, 0.0), 0.01));
+        assert!(nodes[3].is_close(vec2(0.0, 32.5), 0.01));
+
+        assert!(grid_on_border(&nodes, 0));
+        assert!(grid_on_border(&nodes, 5));
+        assert!(!grid_on_border(&nodes, 4));
+
+        // dragged towards negative coordinates with a tiny block size
+        let (nodes, edges) = grid_layout(vec2(0.0, 0.0), vec2(-40.0, -40.0), vec2(1.0, 1.0));
+        assert_eq!(nodes.len(), 9);
+        assert_eq!(edges.len(), 12);
+        assert!(nodes[8].is_close(vec2(-40.0, -40.0), 0.01));
+    }
+
+    #[test]
+    fn parallel_roads_are_offset_to_the_right() {
+        let (start, end, elbow) = parallel_layout(
+            vec2(0.0, 0.0),
+            vec2(100.0, 0.0),
+            RoadSegmentKind::Straight,
+            20.0,
+        )
+        .unwrap();
+        assert!(start.is_close(vec2(0.0, -20.0), 0.01));
+        assert!(end.is_close(vec2(100.0, -20.0), 0.01));
+        assert!(elbow.is_none());
+
+        // a quarter turn around (0, 100), the parallel road on the inside
+        let from = vec2(0.0, 0.0);
+        let to = vec2(100.0, 100.0);
+        let segment = RoadSegmentKind::from_elbow(from, to, vec2(100.0, 0.0));
+        let (start, end, elbow) = parallel_layout(from, to, segment, -20.0).unwrap();
+        assert!(start.is_close(vec2(0.0, 20.0), 0.01));
+        assert!(end.is_close(vec2(80.0, 100.0), 0.01));
+        assert!(elbow.unwrap().is_close(vec2(80.0, 20.0), 0.01));
+    }
+}
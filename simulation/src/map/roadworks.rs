@@ -0,0 +1,199 @@
+//! Roads being built or upgraded stay under roadworks for a while.
+//! Some of their lanes are coned off and the others are slower: the routes go around the closed
+//! lanes when they can, and through them at a crawl otherwise.
+//! The works are advanced by the roadworks system, they take longer on long roads and when more
+//! lanes change.
+
+use serde::{Deserialize, Serialize};
+
+use crate::map::{IntersectionID, LaneID, Lanes, Map, Road, RoadID, UpdateType};
+
+/// Game seconds of work per meter of lane changed
+pub const ROADWORKS_SECONDS_PER_METER: f64 = 6.0;
+/// Multiplier applied to the speed limit of the open lanes of a road under works
+pub const ROADWORKS_SPEED_FACTOR: f32 = 0.5;
+/// Speed of the vehicles going through a closed lane, in m/s
+pub const CLOSED_LANE_SPEED: f32 = 2.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoadworksKind {
+    /// A new road, all of its lanes are laid
+    Construction,
+    /// An overhead line added above the rails
+    Electrification,
+}
+
+impl RoadworksKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            RoadworksKind::Construction => "Construction",
+            RoadworksKind::Electrification => "Electrification",
+        }
+    }
+
+    /// How many lanes of the road the works change
+    fn changed_lanes(self, road: &Road) -> usize {
+        match self {
+            RoadworksKind::Construction => road.n_lanes(),
+            RoadworksKind::Electrification => {
+                road.lanes_iter().filter(|(_, kind)| kind.is_rail()).count()
+            }
+        }
+    }
+}
+
+/// Works in progress on a road
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Roadworks {
+    pub kind: RoadworksKind,
+    /// Game seconds of work needed
+    pub total: f64,
+    /// Game seconds of work done
+    pub done: f64,
+}
+
+impl Roadworks {
+    pub fn new(road: &Road, kind: RoadworksKind) -> Self {
+        let total = road.length() as f64
+            * kind.changed_lanes(road).max(1) as f64
+            * ROADWORKS_SECONDS_PER_METER;
+        Self {
+            kind,
+            total,
+            done: 0.0,
+        }
+    }
+
+    /// From 0 (just started) to 1 (done)
+    pub fn progress(&self) -> f32 {
+        if self.total <= 0.0 {
+            return 1.0;
+        }
+        (self.done / self.total).clamp(0.0, 1.0) as f32
+    }
+
+    /// Game seconds of work left
+    pub fn remaining(&self) -> f64 {
+        (self.total - self.done).max(0.0)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done >= self.total
+    }
+}
+
+impl Road {
+    /// Multiplier applied to the speed limit of the lanes while the road is under works
+    pub fn roadworks_speed_factor(&self) -> f32 {
+        match self.roadworks {
+            Some(_) => ROADWORKS_SPEED_FACTOR,
+            None => 1.0,
+        }
+    }
+
+    /// Lanes coned off during the works on the road.
+    /// A new road keeps the vehicle lane closest to its middle open in each direction,
+    /// the others are closed. An upgrade of the rails keeps them open.
+    pub fn roadworks_closures(&self, lanes: &Lanes) -> Vec<LaneID> {
+        let Some(ref works) = self.roadworks else {
+            return vec![];
+        };
+        if works.kind != RoadworksKind::Construction {
+            return vec![];
+        }
+
+        let mut closed = vec![];
+        for from in [self.src, self.dst] {
+            self.direction_closures(lanes, from, &mut closed);
+        }
+        closed
+    }
+
+    fn direction_closures(&self, lanes: &Lanes, from: IntersectionID, closed: &mut Vec<LaneID>) {
+        let vehicle_lanes: Vec<_> = self
+            .outgoing_lanes_from(from)
+            .iter()
+            .filter(|(_, kind)| kind.vehicles())
+            .filter_map(|&(id, kind)| {
+                let lane = lanes.get(id)?;
+                let middle = lane.dist_from_bottom + kind.width() * 0.5;
+                Some((id, (middle - self.width * 0.5).abs()))
+            })
+            .collect();
+        let open = vehicle_lanes
+            .iter()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|&(id, _)| id);
+        closed.extend(
+            vehicle_lanes
+                .iter()
+                .map(|&(id, _)| id)
+                .filter(|&id| Some(id) != open),
+        );
+    }
+}
+
+impl Map {
+    /// Puts the road under works, its lanes are closed until they are done
+    pub fn start_roadworks(&mut self, id: RoadID, kind: RoadworksKind) {
+        let Some(road) = self.roads.get_mut(id) else {
+            log::warn!("trying to start roadworks on non-existing road {:?}", id);
+            return;
+        };
+        road.roadworks = Some(Roadworks::new(road, kind));
+        self.close_roadworks_lanes(id);
+    }
+
+    /// Advances the works on the road by that many game seconds.
+    /// Returns true if they got done, the lanes are then opened back.
+    pub fn advance_roadworks(&mut self, id: RoadID, seconds: f64) -> bool {
+        let Some(works) = self.roads.get_mut(id).and_then(|r| r.roadworks.as_mut()) else {
+            return false;
+        };
+        works.done += seconds;
+        if !works.is_done() {
+            return false;
+        }
+        self.finish_roadworks(id);
+        true
+    }
+
+    /// Ends the works on the road right away
+    pub fn finish_roadworks(&mut self, id: RoadID) {
+        let Some(road) = self.roads.get_mut(id) else {
+            return;
+        };
+        if road.roadworks.take().is_none() {
+            return;
+        }
+        for (lane, _) in road.lanes_iter() {
+            if let Some(lane) = self.lanes.get_mut(lane) {
+                lane.closed = false;
+            }
+        }
+        self.subscribers.dispatch(UpdateType::RoadSurface, road);
+    }
+
+    /// Closes the lanes of the works on the road, e.g. again after it was split
+    pub(crate) fn close_roadworks_lanes(&mut self, id: RoadID) {
+        let Some(road) = self.roads.get(id) else {
+            return;
+        };
+        if road.roadworks.is_none() {
+            return;
+        }
+        for lane in road.roadworks_closures(&self.lanes) {
+            if let Some(lane) = self.lanes.get_mut(lane) {
+                lane.closed = true;
+            }
+        }
+        self.subscribers.dispatch(UpdateType::RoadSurface, road);
+    }
+
+    /// Roads under works
+    pub fn roadworks(&self) -> impl Iterator<Item = (RoadID, &Roadworks)> + '_ {
+        self.roads
+            .iter()
+            .filter_map(|(id, r)| Some((id, r.roadworks.as_ref()?)))
+    }
+}
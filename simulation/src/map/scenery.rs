@@ -0,0 +1,252 @@
+//! Scenery props scattered over the terrain from the prop prototypes.
+//! The props are regenerated from their chunk coordinates when needed, only the ones removed
+//! by construction are stored.
+
+use crate::map::procgen::heightmap::tree_density;
+use crate::map::{Map, MapSubscribers, ProjectFilter, ProjectKind, SubscriberChunkID, UpdateType};
+use geom::{vec2, Circle, Intersect, Radians, Vec2, Vec3};
+use prototypes::{PropBiome, PropPrototype, PropPrototypeID};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub type SceneryChunkID = SubscriberChunkID;
+
+/// Props never go past this number per prototype and chunk
+const MAX_SLOTS: f32 = u16::MAX as f32;
+
+/// Identifies a prop among the candidates of its chunk, stable across regenerations
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PropSlot {
+    pub proto: PropPrototypeID,
+    pub slot: u16,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Prop {
+    pub id: PropSlot,
+    pub pos: Vec3,
+    pub dir: Vec2,
+    pub scale: f32,
+}
+
+/// The props removed by construction, sorted by slot in each chunk
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Scenery {
+    removed: BTreeMap<SceneryChunkID, Vec<PropSlot>>,
+}
+
+impl Scenery {
+    pub fn is_removed(&self, chunk: SceneryChunkID, slot: PropSlot) -> bool {
+        self.removed
+            .get(&chunk)
+            .map_or(false, |v| v.binary_search(&slot).is_ok())
+    }
+
+    /// The prototypes of the props removed somewhere
+    pub fn removed_prototypes(&self) -> BTreeSet<PropPrototypeID> {
+        self.removed.values().flatten().map(|s| s.proto).collect()
+    }
+
+    fn remove(&mut self, chunk: SceneryChunkID, slot: PropSlot) -> bool {
+        let v = self.removed.entry(chunk).or_default();
+        let Err(i) = v.binary_search(&slot) else {
+            return false;
+        };
+        v.insert(i, slot);
+        true
+    }
+
+    /// Removes the props under some construction for good.
+    /// Only the cheap rules are checked so that it can run before or after the construction.
+    pub(crate) fn remove_near(
+        &mut self,
+        obj: impl Intersect<Vec2>,
+        subscribers: &mut MapSubscribers,
+    ) {
+        let bbox = obj.bbox();
+        let ll = SceneryChunkID::new(bbox.ll);
+        let ur = SceneryChunkID::new(bbox.ur);
+
+        for y in ll.1..=ur.1 {
+            for x in ll.0..=ur.0 {
+                let chunk = SceneryChunkID::new_i16(x, y);
+                let mut changed = false;
+                for proto in PropPrototype::iter() {
+                    for (slot, pos) in candidates(chunk, proto) {
+                        if !bbox.contains(pos)
+                            || !obj.intersects(&pos)
+                            || !biome_allows(proto.biome, pos)
+                        {
+                            continue;
+                        }
+                        changed |= self.remove(
+                            chunk,
+                            PropSlot {
+                                proto: proto.id,
+                                slot,
+                            },
+                        );
+                    }
+                }
+                if changed {
+                    subscribers.dispatch_chunk(UpdateType::TerrainSplat, chunk);
+                }
+            }
+        }
+    }
+}
+
+/// Deterministic candidate positions of a prototype in a chunk, before the placement rules
+fn candidates(chunk: SceneryChunkID, proto: &PropPrototype) -> impl Iterator<Item = (u16, Vec2)> {
+    let cx = chunk.0 as f32;
+    let cy = chunk.1 as f32;
+    let pseed = (proto.id.hash() >> 40) as f32;
+    let corner = chunk.corner();
+
+    let hectares = SceneryChunkID::SIZE_F32 * SceneryChunkID::SIZE_F32 / 10000.0;
+    let expected = (proto.density * hectares).min(MAX_SLOTS);
+    let extra = common::rand::rand4(cx, cy, pseed, -1.0) < expected.fract();
+    let n = expected as u16 + extra as u16;
+
+    (0..n).map(move |slot| {
+        let s = slot as f32 * 4.0;
+        let jitter = vec2(
+            common::rand::rand4(cx, cy, pseed, s),
+            common::rand::rand4(cx, cy, pseed, s + 1.0),
+        );
+        (slot, corner + jitter * SceneryChunkID::SIZE_F32)
+    })
+}
+
+fn biome_allows(biome: PropBiome, pos: Vec2) -> bool {
+    match biome {
+        PropBiome::Any => true,
+        PropBiome::Forest => tree_density(pos) > 0.15,
+        PropBiome::Clearing => tree_density(pos) <= 0.0,
+    }
+}
+
+/// Applies the placement rules of the prototype at a candidate position
+fn place(map: &Map, proto: &PropPrototype, slot: u16, pos: Vec2) -> Option<Prop> {
+    if !biome_allows(proto.biome, pos) {
+        return None;
+    }
+
+    let env = &map.environment;
+    let h = env.true_height(pos)?;
+    if h < 0.0 {
+        return None;
+    }
+
+    const D: f32 = 2.0;
+    let dx = env.true_height(pos + vec2(D, 0.0))? - env.true_height(pos - vec2(D, 0.0))?;
+    let dy = env.true_height(pos + vec2(0.0, D))? - env.true_height(pos - vec2(0.0, D))?;
+    if vec2(dx, dy).mag() / (2.0 * D) > proto.max_slope {
+        return None;
+    }
+
+    let s = slot as f32 * 4.0;
+    let pseed = (proto.id.hash() >> 40) as f32;
+    let r = common::rand::rand3(pseed, s, pos.x + pos.y);
+    let scale = proto.min_scale + (proto.max_scale - proto.min_scale) * r;
+
+    if map
+        .spatial_map
+        .query(
+            Circle::new(pos, 1.5 * scale),
+            ProjectFilter::ROAD | ProjectFilter::INTER | ProjectFilter::BUILDING,
+        )
+        .next()
+        .is_some()
+    {
+        return None;
+    }
+
+    let mut dir =
+        Radians(std::f32::consts::TAU * common::rand::rand3(pseed, s, pos.x - pos.y)).vec2();
+
+    if let Some((min, max)) = proto.road_distance {
+        let pos3 = pos.z(h);
+        let (road_dist, road_pos) = map
+            .spatial_map
+            .query_around(pos, max + 50.0, ProjectFilter::ROAD)
+            .filter_map(|kind| {
+                let ProjectKind::Road(id) = kind else {
+                    return None;
+                };
+                let road = map.roads.get(id)?;
+                let proj = road.points().project(pos3).xy();
+                Some((proj.distance(pos) - road.width * 0.5, proj))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))?;
+
+        if road_dist < min || road_dist > max {
+            return None;
+        }
+        dir = (road_pos - pos).try_normalize().unwrap_or(dir);
+    }
+
+    Some(Prop {
+        id: PropSlot {
+            proto: proto.id,
+            slot,
+        },
+        pos: pos.z(h),
+        dir,
+        scale,
+    })
+}
+
+impl Map {
+    /// The props standing in the chunk, generated from the prototypes
+    pub fn generate_props(&self, chunk: SceneryChunkID) -> Vec<Prop> {
+        let mut props = vec![];
+        for proto in PropPrototype::iter() {
+            for (slot, pos) in candidates(chunk, proto) {
+                let id = PropSlot {
+                    proto: proto.id,
+                    slot,
+                };
+                if self.scenery.is_removed(chunk, id) {
+                    continue;
+                }
+                if let Some(prop) = place(self, proto, slot, pos) {
+                    props.push(prop);
+                }
+            }
+        }
+        props
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geom::AABB;
+
+    #[test]
+    fn props_are_deterministic_and_removals_stick() {
+        let test = crate::tests::TestCtx::new();
+        let mut map = test.g.map_mut();
+        let chunk = SceneryChunkID::new_i16(0, 0);
+
+        let a = map.generate_props(chunk);
+        let b = map.generate_props(chunk);
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert_eq!(a.id, b.id);
+            assert_eq!(a.pos, b.pos);
+        }
+
+        let Some(first) = a.first() else {
+            return;
+        };
+        let map = &mut *map;
+        map.scenery.remove_near(
+            AABB::centered(first.pos.xy(), Vec2::splat(1.0)),
+            &mut map.subscribers,
+        );
+        assert!(map.scenery.is_removed(chunk, first.id));
+        assert!(map.generate_props(chunk).iter().all(|p| p.id != first.id));
+    }
+}
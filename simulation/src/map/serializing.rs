@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 
 use crate::map::{
-    BuildingID, Buildings, ElectricityCache, Environment, Intersections, Lanes, Lots, Map,
-    ParkingSpots, Roads, SpatialMap,
+    BuildingID, Buildings, Deposits, Districts, ElectricityCache, Environment, Intersections,
+    Lanes, Lots, Map, ParkingSpots, Restrictions, Roads, Scenery, SpatialMap,
 };
 
 #[derive(Default, Serialize, Deserialize)]
@@ -14,7 +14,13 @@ pub(crate) struct SerializedMap {
     pub parking: ParkingSpots,
     pub lots: Lots,
     pub environment: Environment,
+    pub scenery: Scenery,
     pub external_train_stations: Vec<BuildingID>,
+    pub districts: Districts,
+    #[serde(default)]
+    pub deposits: Deposits,
+    #[serde(default)]
+    pub restrictions: Restrictions,
 }
 
 impl From<&Map> for SerializedMap {
@@ -27,7 +33,11 @@ impl From<&Map> for SerializedMap {
             parking: m.parking.clone(),
             lots: m.lots.clone(),
             environment: m.environment.clone(),
+            scenery: m.scenery.clone(),
             external_train_stations: m.external_train_stations.clone(),
+            districts: m.districts.clone(),
+            deposits: m.deposits.clone(),
+            restrictions: m.restrictions.clone(),
         }
     }
 }
@@ -44,7 +54,11 @@ impl From<SerializedMap> for Map {
             lots: sel.lots,
             parking: sel.parking,
             environment: sel.environment,
+            scenery: sel.scenery,
             external_train_stations: sel.external_train_stations,
+            districts: sel.districts,
+            deposits: sel.deposits,
+            restrictions: sel.restrictions,
             ..Self::empty()
         };
         m.electricity = ElectricityCache::build(&m);
@@ -0,0 +1,117 @@
+//! Speed limits of the roads.
+//! The limit is kept in the lanes the vehicles drive on, so it follows the road when it is split
+//! and the router reads it directly. It defaults to the one of the lane pattern the road was
+//! built with.
+
+use crate::map::{Lanes, Map, Road, RoadID, UpdateType};
+
+/// Bounds of the speed limits that can be set, in m/s
+pub const MIN_SPEED_LIMIT: f32 = 4.0;
+pub const MAX_SPEED_LIMIT: f32 = 40.0;
+
+impl Road {
+    /// Speed limit of the lanes of the vehicles, None if the road has none (a footpath, rails)
+    pub fn speed_limit(&self, lanes: &Lanes) -> Option<f32> {
+        self.lanes_iter()
+            .filter(|(_, kind)| kind.vehicles())
+            .filter_map(|(id, _)| lanes.get(id))
+            .map(|l| l.speed_limit)
+            .reduce(f32::max)
+    }
+}
+
+impl Map {
+    /// Sets the speed limit of the lanes of the vehicles of the road, clamped to the bounds
+    pub fn set_road_speed_limit(&mut self, id: RoadID, limit: f32) {
+        let Some(road) = self.roads.get(id) else {
+            log::warn!(
+                "trying to set the speed limit of non-existing road {:?}",
+                id
+            );
+            return;
+        };
+        if !limit.is_finite() {
+            log::warn!("invalid speed limit {} for {:?}", limit, id);
+            return;
+        }
+        let limit = limit.clamp(MIN_SPEED_LIMIT, MAX_SPEED_LIMIT);
+        if road.speed_limit(&self.lanes) == Some(limit) {
+            return;
+        }
+
+        for (lane, kind) in road.lanes_iter() {
+            if !kind.vehicles() {
+                continue;
+            }
+            if let Some(lane) = self.lanes.get_mut(lane) {
+                lane.speed_limit = limit;
+            }
+        }
+        self.subscribers.dispatch(UpdateType::RoadSurface, road);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec3;
+    use prototypes::Tick;
+
+    use crate::souls::commute::driving_minutes;
+    use crate::tests::TestCtx;
+    use crate::world_command::WorldCommand;
+
+    #[test]
+    fn lower_limits_make_longer_drives() {
+        let mut test = TestCtx::new();
+        // a corridor of two roads, and a side road left alone
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(200.0, 0.0, 0.0),
+            vec3(400.0, 0.0, 0.0),
+        ]);
+        test.build_roads(&[vec3(400.0, 0.0, 0.0), vec3(400.0, 200.0, 0.0)]);
+
+        let (corridor, side) = {
+            let map = test.g.map();
+            let mut corridor = vec![];
+            let mut side = None;
+            for road in map.roads().values() {
+                if road.points.first().x == road.points.last().x {
+                    side = Some(road.id);
+                } else {
+                    corridor.push(road.id);
+                }
+            }
+            (corridor, side.unwrap())
+        };
+        assert_eq!(corridor.len(), 2);
+
+        let (from, to) = (vec3(10.0, 0.0, 0.0), vec3(390.0, 0.0, 0.0));
+        let before = driving_minutes(Tick(0), from, to, &test.g.map()).unwrap();
+
+        let limit = {
+            let map = test.g.map();
+            map.roads()[corridor[0]].speed_limit(map.lanes()).unwrap()
+        };
+        test.apply(&[WorldCommand::SetSpeedLimit {
+            roads: corridor.clone(),
+            limit: limit * 0.5,
+        }]);
+
+        let map = test.g.map();
+        for &road in &corridor {
+            assert_eq!(
+                map.roads()[road].speed_limit(map.lanes()),
+                Some(limit * 0.5)
+            );
+        }
+        assert_eq!(map.roads()[side].speed_limit(map.lanes()), Some(limit));
+
+        // the whole drive is on the corridor, it takes twice as long
+        let after = driving_minutes(Tick(0), from, to, &map).unwrap();
+        assert!(
+            (after - before * 2.0).abs() < before * 0.01,
+            "{before} -> {after}"
+        );
+    }
+}
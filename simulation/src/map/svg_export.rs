@@ -0,0 +1,365 @@
+//! Top-down vector drawing of the map, to print posters or share the city.
+//! Only the map geometry is used so that it works without a GPU.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Color, Vec2, AABB};
+use prototypes::ZoneKind;
+
+use crate::map::terrain::CELL_SIZE;
+use crate::map::{BuildingKind, LaneKind, Map};
+
+/// Roads with this many driving lanes or more get their name written
+pub const MAJOR_ROAD_LANES: usize = 4;
+/// Empty space around the roads and buildings, in meters
+const MARGIN: f32 = 100.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportLayers {
+    pub water: bool,
+    pub roads: bool,
+    pub rails: bool,
+    pub buildings: bool,
+    /// Names of the major roads
+    pub labels: bool,
+}
+
+impl Default for ExportLayers {
+    fn default() -> Self {
+        Self {
+            water: true,
+            roads: true,
+            rails: true,
+            buildings: true,
+            labels: true,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportBackground {
+    #[default]
+    Light,
+    Dark,
+    Transparent,
+}
+
+impl ExportBackground {
+    pub const ALL: [ExportBackground; 3] = [
+        ExportBackground::Light,
+        ExportBackground::Dark,
+        ExportBackground::Transparent,
+    ];
+
+    fn color(self) -> Option<Color> {
+        match self {
+            ExportBackground::Light => Some(Color::new(0.95, 0.94, 0.9, 1.0)),
+            ExportBackground::Dark => Some(Color::new(0.1, 0.11, 0.13, 1.0)),
+            ExportBackground::Transparent => None,
+        }
+    }
+
+    fn label_color(self) -> Color {
+        match self {
+            ExportBackground::Dark => Color::new(0.9, 0.9, 0.9, 1.0),
+            _ => Color::new(0.15, 0.15, 0.15, 1.0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapExportOptions {
+    pub layers: ExportLayers,
+    pub background: ExportBackground,
+    /// Pixels per meter of the output
+    pub scale: f32,
+}
+
+impl Default for MapExportOptions {
+    fn default() -> Self {
+        Self {
+            layers: ExportLayers::default(),
+            background: ExportBackground::default(),
+            scale: 0.5,
+        }
+    }
+}
+
+/// Area around the roads and buildings, the whole terrain if nothing is built
+pub fn export_bounds(map: &Map) -> AABB {
+    let mut points = map
+        .roads()
+        .values()
+        .flat_map(|r| r.points().iter().map(|p| p.xy()))
+        .chain(map.buildings().values().flat_map(|b| b.obb.corners));
+    let Some(first) = points.next() else {
+        return map.environment.bounds();
+    };
+    let mut bounds = AABB::new_ll_ur(first, first);
+    for p in points {
+        bounds = bounds.union(AABB::new_ll_ur(p, p));
+    }
+    bounds.expand(MARGIN)
+}
+
+/// The map drawn from above as an SVG document
+pub fn map_to_svg(map: &Map, options: &MapExportOptions) -> String {
+    let bounds = export_bounds(map);
+    let scale = options.scale;
+    let to_svg = |p: Vec2| ((p.x - bounds.ll.x) * scale, (bounds.ur.y - p.y) * scale);
+    let colors = crate::colors();
+    let layers = &options.layers;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}">"#,
+        bounds.w() * scale,
+        bounds.h() * scale,
+        bounds.w() * scale,
+        bounds.h() * scale,
+    );
+    if let Some(bg) = options.background.color() {
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            hex(bg)
+        );
+    }
+
+    if layers.water {
+        svg.push_str(&water(map, bounds, scale, colors.sea_col));
+    }
+
+    if layers.buildings {
+        let _ = writeln!(svg, r#"<g stroke="none">"#);
+        for b in map.buildings().values() {
+            let points: Vec<String> = b
+                .obb
+                .corners
+                .iter()
+                .map(|&p| {
+                    let (x, y) = to_svg(p);
+                    format!("{x:.1},{y:.1}")
+                })
+                .collect();
+            let _ = writeln!(
+                svg,
+                r#"<polygon points="{}" fill="{}"/>"#,
+                points.join(" "),
+                hex(building_color(b.kind, b.grown_in))
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+    }
+
+    let _ = writeln!(
+        svg,
+        r#"<g fill="none" stroke-linecap="round" stroke-linejoin="round">"#
+    );
+    for road in map.roads().values() {
+        let rail_only = road.lanes_iter().all(|(_, kind)| kind.is_rail());
+        let (enabled, col, dash) = if rail_only {
+            (
+                layers.rails,
+                colors.rail_ballast_col,
+                r#" stroke-dasharray="4 2""#,
+            )
+        } else {
+            (layers.roads, colors.road_mid_col, "")
+        };
+        if !enabled {
+            continue;
+        }
+        let d = road
+            .points()
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let (x, y) = to_svg(p.xy());
+                format!("{}{x:.1},{y:.1}", if i == 0 { 'M' } else { 'L' })
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            svg,
+            r#"<path d="{}" stroke="{}" stroke-width="{:.1}"{}/>"#,
+            d,
+            hex(col),
+            road.width * scale,
+            dash
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+
+    if layers.labels {
+        let label_col = hex(options.background.label_color());
+        let size = (12.0 * scale).max(4.0);
+        let _ = writeln!(
+            svg,
+            r#"<g font-family="sans-serif" font-size="{size:.1}" fill="{label_col}" text-anchor="middle" dominant-baseline="middle">"#,
+        );
+        for (name, (pos, dir)) in major_road_labels(map) {
+            let (x, y) = to_svg(pos);
+            // the text is never upside down
+            let mut angle = (-dir.y).atan2(dir.x).to_degrees();
+            if angle > 90.0 {
+                angle -= 180.0;
+            } else if angle < -90.0 {
+                angle += 180.0;
+            }
+            let _ = writeln!(
+                svg,
+                r#"<text x="{x:.1}" y="{y:.1}" transform="rotate({angle:.1} {x:.1} {y:.1})">{}</text>"#,
+                escape(&name)
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Where to write the name of each major road: the middle of its longest section
+fn major_road_labels(map: &Map) -> BTreeMap<String, (Vec2, Vec2)> {
+    let mut longest: BTreeMap<String, (f32, Vec2, Vec2)> = BTreeMap::new();
+    for road in map.roads().values() {
+        if road.name.is_empty() {
+            continue;
+        }
+        let driving = road
+            .lanes_iter()
+            .filter(|(_, kind)| *kind == LaneKind::Driving)
+            .count();
+        if driving < MAJOR_ROAD_LANES {
+            continue;
+        }
+        let length = road.length();
+        if longest
+            .get(&road.name)
+            .is_some_and(|&(best, _, _)| best >= length)
+        {
+            continue;
+        }
+        let (pos, dir) = road.points().point_dir_along(length * 0.5);
+        longest.insert(road.name.clone(), (length, pos.xy(), dir.xy()));
+    }
+    longest
+        .into_iter()
+        .map(|(name, (_, pos, dir))| (name, (pos, dir)))
+        .collect()
+}
+
+/// Terrain cells below the water level, merged along the rows
+fn water(map: &Map, bounds: AABB, scale: f32, col: Color) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<g fill="{}" stroke="none">"#, hex(col));
+
+    let is_water = |p: Vec2| map.environment.water_depth(p).map_or(false, |d| d > 0.0);
+    let cols = (bounds.w() / CELL_SIZE).ceil() as usize;
+    let rows = (bounds.h() / CELL_SIZE).ceil() as usize;
+    let cell = CELL_SIZE * scale;
+    for row in 0..rows {
+        let y = bounds.ur.y - (row as f32 + 0.5) * CELL_SIZE;
+        let mut start = None;
+        for col in 0..=cols {
+            let x = bounds.ll.x + (col as f32 + 0.5) * CELL_SIZE;
+            let wet = col < cols && is_water(Vec2::new(x, y));
+            match (wet, start) {
+                (true, None) => start = Some(col),
+                (false, Some(s)) => {
+                    let _ = writeln!(
+                        svg,
+                        r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}"/>"#,
+                        s as f32 * cell,
+                        row as f32 * cell,
+                        (col - s) as f32 * cell,
+                        cell
+                    );
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let _ = writeln!(svg, "</g>");
+    svg
+}
+
+fn building_color(kind: BuildingKind, grown_in: Option<ZoneKind>) -> Color {
+    let colors = crate::colors();
+    match (kind, grown_in) {
+        (_, Some(ZoneKind::Residential)) | (BuildingKind::House, _) => colors.lot_residential_col,
+        (_, Some(ZoneKind::Commercial)) => colors.lot_commercial_col,
+        (_, Some(ZoneKind::Industrial)) | (BuildingKind::GoodsCompany(_), _) => {
+            colors.lot_industrial_col
+        }
+        _ => colors.roof_col,
+    }
+}
+
+fn hex(c: Color) -> String {
+    let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(c.r), byte(c.g), byte(c.b))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec3;
+
+    use super::*;
+    use crate::map::{LanePatternBuilder, ProjectFilter};
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn roads_and_labels_are_drawn() {
+        let test = TestCtx::new();
+        {
+            let mut m = test.g.map_mut();
+            let a = m.project(vec3(0.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+            let b = m.project(vec3(400.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+            let pat = LanePatternBuilder::new().n_lanes(2).build();
+            let (_, road) = m.make_connection(a, b, None, &pat).unwrap();
+            m.rename_road(road, "Main <Street> & Co");
+        }
+        test.build_roads(&[vec3(0.0, 300.0, 0.0), vec3(400.0, 300.0, 0.0)]);
+
+        let svg = map_to_svg(&test.g.map(), &MapExportOptions::default());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<path").count(), 2);
+        // only the major road is named
+        assert_eq!(svg.matches("<text").count(), 1);
+        assert!(svg.contains("Main &lt;Street&gt; &amp; Co"));
+
+        let bounds = export_bounds(&test.g.map());
+        assert!(bounds.contains(Vec2::new(0.0, 0.0)));
+        assert!(bounds.contains(Vec2::new(400.0, 300.0)));
+
+        let options = MapExportOptions {
+            layers: ExportLayers {
+                roads: false,
+                labels: false,
+                ..Default::default()
+            },
+            background: ExportBackground::Transparent,
+            ..Default::default()
+        };
+        let svg = map_to_svg(&test.g.map(), &options);
+        assert!(!svg.contains("<path"));
+        assert!(!svg.contains("<text"));
+        assert!(!svg.contains(r#"height="100%""#));
+    }
+}
@@ -1,11 +1,13 @@
 use crate::map::procgen::heightmap;
 use crate::map::procgen::heightmap::tree_density;
+use crate::map::water::{SerializedWater, WaterMap};
+use flat_spatial::grid::GridHandle;
 use flat_spatial::Grid;
 use geom::{lerp, pack_height, vec2, Intersect, Radians, Ray3, Vec2, Vec3, AABB};
 use prototypes::{Tick, DELTA};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::ops::Mul;
 
 pub type TerrainChunkID = common::ChunkID_512;
@@ -16,6 +18,42 @@ pub(super) const CELL_SIZE: f32 = TerrainChunkID::SIZE_F32 / TERRAIN_CHUNK_RESOL
 
 const TREE_GRID_SIZE: usize = 256;
 
+/// Number of growth stages of a tree, from sapling to mature
+pub const TREE_STAGES: u8 = 4;
+/// Only mature trees can be harvested or drop seeds
+pub const MATURE_STAGE: u8 = TREE_STAGES - 1;
+/// Size of a tree at each stage compared to a mature one
+const STAGE_SCALE: [f32; TREE_STAGES as usize] = [0.25, 0.5, 0.75, 1.0];
+
+/// Chance each day for a young tree to reach the next stage
+const GROWTH_CHANCE: f32 = 0.3;
+/// Chance each day for a mature tree to drop a sapling nearby
+const RESEED_CHANCE: f32 = 0.05;
+/// How far from a mature tree its saplings grow
+const RESEED_RADIUS: f32 = 12.0;
+/// No sapling grows where there are already this many trees within [`RESEED_RADIUS`]
+const MAX_NEIGHBOURS: usize = 5;
+/// The forests stop spreading in a terrain chunk once it holds this many trees
+const MAX_TREES_PER_CHUNK: usize = 2048;
+/// Smallest distance between two trees
+pub const TREE_SPACING: f32 = 4.0;
+/// Trees are uprooted when the ground under them moves more than this in one stroke
+const MAX_TREE_DISPLACEMENT: f32 = 1.0;
+/// The water level brush moves the water this much slower than the elevation brush moves the ground
+pub const WATER_LEVEL_SPEED: f32 = 0.01;
+
+/// Height of the water table, the level of the sea and of the lakes of a new map
+pub const WATER_LEVEL: f32 = -10.0;
+
+/// How the water moves at some point, used to animate rivers
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct WaterFlow {
+    /// Direction of the current, its length from 0 (still water) to 1 (fast river)
+    pub dir: Vec2,
+    /// Distance from the water surface to the ground below, 0 on land
+    pub depth: f32,
+}
+
 pub type Chunk = geom::HeightmapChunk<TERRAIN_CHUNK_RESOLUTION, { TerrainChunkID::SIZE }>;
 pub type Heightmap = geom::Heightmap<TERRAIN_CHUNK_RESOLUTION, { TerrainChunkID::SIZE }>;
 
@@ -25,12 +63,17 @@ pub struct Tree {
     pub size: f32,
     pub col: f32,
     pub dir: Vec2,
+    /// Growth stage, from 0 (sapling) to [`MATURE_STAGE`]
+    pub stage: u8,
 }
 
 #[derive(Clone)]
 pub struct Environment {
     heightmap: Heightmap,
     pub trees: Grid<Tree, Vec2>,
+    water: WaterMap,
+    /// Where the water changed since the last [`Environment::take_water_changes`]
+    water_changes: Option<AABB>,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -40,6 +83,10 @@ pub enum TerraformKind {
     Level,
     Slope,
     Erode,
+    /// Plants saplings, the heightmap is left untouched
+    Plant,
+    /// Raises or lowers the lake under the brush, digs a pond on dry ground
+    WaterLevel,
 }
 
 defer_serialize!(Environment, SerializedEnvironment);
@@ -55,6 +102,8 @@ impl Environment {
         let mut me = Self {
             heightmap: Heightmap::new(w, h),
             trees: Grid::new(TREE_GRID_SIZE as i32),
+            water: WaterMap::default(),
+            water_changes: None,
         };
         for y in 0..h {
             let chunks: Vec<_> = (0..w)
@@ -70,6 +119,7 @@ impl Environment {
                 }
             }
         }
+        me.water = WaterMap::new(&me.heightmap);
         me
     }
 
@@ -83,6 +133,131 @@ impl Environment {
         self.heightmap.height(pos)
     }
 
+    /// The water flows along the banks, downstream following the valley.
+    /// Still water (lakes and sea) is found where the valley is flat.
+    pub fn water_flow(&self, pos: Vec2) -> Option<WaterFlow> {
+        let h = self.true_height(pos)?;
+        let depth = self.water.level(pos).map_or(0.0, |level| level - h);
+        if depth <= 0.0 {
+            return Some(WaterFlow::default());
+        }
+
+        let gradient = |d: f32| {
+            let dx =
+                self.true_height(pos + vec2(d, 0.0))? - self.true_height(pos - vec2(d, 0.0))?;
+            let dy =
+                self.true_height(pos + vec2(0.0, d))? - self.true_height(pos - vec2(0.0, d))?;
+            Some(vec2(dx, dy) / (2.0 * d))
+        };
+
+        // the banks are close, the valley is far
+        let bank = gradient(CELL_SIZE).unwrap_or(Vec2::ZERO);
+        let valley = gradient(16.0 * CELL_SIZE).unwrap_or(Vec2::ZERO);
+
+        let downstream = -valley;
+        let Some(downstream_dir) = downstream.try_normalize() else {
+            return Some(WaterFlow {
+                dir: Vec2::ZERO,
+                depth,
+            });
+        };
+
+        // what the banks add to the slope of the valley, the water flows along them
+        let lateral = bank - valley;
+        let mut dir = if lateral.mag() > 0.002 {
+            lateral.perpendicular().normalize()
+        } else {
+            downstream_dir
+        };
+        if dir.dot(downstream_dir) < 0.0 {
+            dir = -dir;
+        }
+
+        let speed = ((downstream.mag() - 0.001) / 0.01).clamp(0.0, 1.0);
+
+        Some(WaterFlow {
+            dir: dir * speed,
+            depth,
+        })
+    }
+
+    pub fn water(&self) -> &WaterMap {
+        &self.water
+    }
+
+    /// Level of the water at that position, None on land
+    pub fn water_level(&self, pos: Vec2) -> Option<f32> {
+        self.water.level(pos)
+    }
+
+    /// Distance from the water surface to the ground, 0 on land and None outside the map
+    pub fn water_depth(&self, pos: Vec2) -> Option<f32> {
+        let h = self.true_height(pos)?;
+        Some(
+            self.water_level(pos)
+                .map_or(0.0, |level| (level - h).max(0.0)),
+        )
+    }
+
+    /// Where the water changed since the last call, the trees it covered are already gone
+    pub fn take_water_changes(&mut self) -> Option<AABB> {
+        self.water_changes.take()
+    }
+
+    /// Raises or lowers the water at that position by `delta` meters, digging a pond on dry
+    /// ground. The sea keeps its level.
+    pub fn change_water_level(&mut self, pos: Vec2, delta: f32) {
+        let changed = self.water.change_level(&self.heightmap, pos, delta);
+        self.water_changed(changed);
+    }
+
+    /// Moves the lakes toward the water table by at most `rate` meters
+    pub fn equalize_water(&mut self, rate: f32) {
+        let changed = self.water.equalize(&self.heightmap, rate);
+        self.water_changed(changed);
+    }
+
+    /// Terrain cells that would be flooded if the ground of the area had the heights given
+    /// from the position and the current height, with the level of their water
+    pub fn flood_preview(
+        &self,
+        area: AABB,
+        new_height: impl Fn(Vec2, f32) -> f32,
+    ) -> Vec<(Vec2, f32)> {
+        self.water.flood_preview(&self.heightmap, area, new_height)
+    }
+
+    /// Terrain cells that would be flooded if the water at that position moved by `delta`
+    pub fn level_preview(&self, pos: Vec2, delta: f32) -> Vec<(Vec2, f32)> {
+        self.water.level_preview(&self.heightmap, pos, delta)
+    }
+
+    fn settle_water(&mut self, area: AABB) {
+        let changed = self.water.settle(&self.heightmap, area);
+        self.water_changed(changed);
+    }
+
+    fn water_changed(&mut self, changed: Option<AABB>) {
+        let Some(area) = changed else {
+            return;
+        };
+        self.water_changes = Some(match self.water_changes {
+            Some(prev) => prev.union(area),
+            None => area,
+        });
+
+        let mut drowned = vec![];
+        self.trees.query_aabb_visitor(area.ll, area.ur, |(h, pos)| {
+            if !self.is_fertile(pos) {
+                drowned.push(h);
+            }
+        });
+        for h in drowned {
+            self.trees.remove(h);
+        }
+        self.trees.maintain();
+    }
+
     pub fn remove_trees_near(
         &mut self,
         obj: impl Intersect<Vec2>,
@@ -110,6 +285,128 @@ impl Environment {
         self.trees.maintain();
     }
 
+    /// Whether trees can grow there, on land inside the map
+    pub fn is_fertile(&self, pos: Vec2) -> bool {
+        self.true_height(pos)
+            .is_some_and(|h| self.water_level(pos).map_or(true, |level| h > level))
+    }
+
+    fn count_trees_around(&self, center: Vec2, radius: f32) -> usize {
+        let r = Vec2::splat(radius);
+        self.trees
+            .query(center - r, center + r)
+            .filter(|(_, pos)| pos.is_close(center, radius))
+            .count()
+    }
+
+    /// Whether a mature tree that can be cut stands within the radius
+    pub fn has_mature_tree(
+        &self,
+        center: Vec2,
+        radius: f32,
+        can_cut: impl Fn(Vec2) -> bool,
+    ) -> bool {
+        self.nearest_mature_tree(center, radius, can_cut).is_some()
+    }
+
+    fn nearest_mature_tree(
+        &self,
+        center: Vec2,
+        radius: f32,
+        can_cut: impl Fn(Vec2) -> bool,
+    ) -> Option<GridHandle> {
+        let r = Vec2::splat(radius);
+        self.trees
+            .query(center - r, center + r)
+            .filter(|(_, pos)| pos.is_close(center, radius) && can_cut(*pos))
+            .filter(|(h, _)| self.trees.get(*h).is_some_and(|(_, t)| t.is_mature()))
+            .min_by(|(_, a), (_, b)| {
+                a.distance2(center)
+                    .total_cmp(&b.distance2(center))
+                    .then(a.x.total_cmp(&b.x))
+                    .then(a.y.total_cmp(&b.y))
+            })
+            .map(|(h, _)| h)
+    }
+
+    /// Cuts the closest mature tree within the radius that can be cut and replants a sapling
+    /// in its place. Returns the chunk of the tree.
+    pub fn harvest_tree(
+        &mut self,
+        center: Vec2,
+        radius: f32,
+        can_cut: impl Fn(Vec2) -> bool,
+    ) -> Option<TerrainChunkID> {
+        let h = self.nearest_mature_tree(center, radius, can_cut)?;
+        let (pos, tree) = self.trees.get_mut(h)?;
+        tree.stage = 0;
+        Some(TerrainChunkID::new(pos))
+    }
+
+    /// Plants a sapling on dry ground, away from other trees.
+    /// Returns the chunk of the new tree.
+    pub fn plant_tree(&mut self, pos: Vec2) -> Option<TerrainChunkID> {
+        if !self.is_fertile(pos) || self.count_trees_around(pos, TREE_SPACING) > 0 {
+            return None;
+        }
+        self.trees.insert(pos, Tree::sapling(pos));
+        Some(TerrainChunkID::new(pos))
+    }
+
+    /// Grows the trees of the chunk by one day: young trees may reach the next stage and
+    /// mature trees may drop a sapling nearby, as long as the forest is not too dense there.
+    /// `can_plant` tells whether the ground is free of roads and buildings.
+    /// Returns the chunks where a tree changed.
+    pub fn grow_trees(
+        &mut self,
+        chunk: TerrainChunkID,
+        seed: u64,
+        can_plant: impl Fn(Vec2) -> bool,
+    ) -> BTreeSet<TerrainChunkID> {
+        let mut rng = common::rand::gen(seed);
+        let bbox = chunk.bbox();
+
+        let mut trees = vec![];
+        self.trees.query_aabb_visitor(bbox.ll, bbox.ur, |(h, pos)| {
+            if TerrainChunkID::new(pos) == chunk {
+                trees.push((h, pos));
+            }
+        });
+        // the grid storage order is not kept by saves, the same trees must draw the same numbers
+        trees.sort_by(|(_, a), (_, b)| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+
+        let mut changed = BTreeSet::new();
+        let mut count = trees.len();
+        for (h, pos) in trees {
+            let Some((_, tree)) = self.trees.get_mut(h) else {
+                continue;
+            };
+            if !tree.is_mature() {
+                if rng.next_f32() < GROWTH_CHANCE {
+                    tree.stage += 1;
+                    changed.insert(chunk);
+                }
+                continue;
+            }
+
+            if count >= MAX_TREES_PER_CHUNK || rng.next_f32() >= RESEED_CHANCE {
+                continue;
+            }
+            let angle = Radians(2.0 * std::f32::consts::PI * rng.next_f32());
+            let seed_pos = pos + angle.vec2() * lerp(TREE_SPACING, RESEED_RADIUS, rng.next_f32());
+            if self.count_trees_around(seed_pos, RESEED_RADIUS) >= MAX_NEIGHBOURS
+                || !can_plant(seed_pos)
+            {
+                continue;
+            }
+            if let Some(id) = self.plant_tree(seed_pos) {
+                count += 1;
+                changed.insert(id);
+            }
+        }
+        changed
+    }
+
     pub fn get_chunk(&self, id: TerrainChunkID) -> Option<&Chunk> {
         self.heightmap.get_chunk((id.0 as u16, id.1 as u16))
     }
@@ -155,11 +452,14 @@ impl Environment {
         bounds: AABB,
         f: impl FnMut(Vec3) -> f32,
     ) -> Vec<TerrainChunkID> {
-        self.heightmap
+        let modified = self
+            .heightmap
             .apply(bounds, f)
             .into_iter()
             .map(|(x, y)| TerrainChunkID::new_i16(x as i16, y as i16))
-            .collect()
+            .collect();
+        self.settle_water(bounds);
+        modified
     }
 
     pub fn terraform(
@@ -173,7 +473,15 @@ impl Environment {
         slope: Option<(Vec3, Vec3)>,
     ) -> Vec<TerrainChunkID> {
         let bbox = AABB::centered(center, Vec2::splat(radius * 2.0));
-        match kind {
+
+        let mut grounds = vec![];
+        self.trees.query_aabb_visitor(bbox.ll, bbox.ur, |(h, pos)| {
+            if let Some(z) = self.true_height(pos) {
+                grounds.push((h, pos, z));
+            }
+        });
+
+        let modified = match kind {
             TerraformKind::Elevation => self.terrain_apply(bbox, |pos| {
                 let dist = pos.xy().distance(center) / radius;
                 if dist >= 1.0 {
@@ -243,7 +551,29 @@ impl Environment {
                     .map(|(x, y)| TerrainChunkID::new_i16(x as i16, y as i16))
                     .collect()
             }
+            // the map plants the trees, it knows where the roads and buildings are
+            TerraformKind::Plant => vec![],
+            TerraformKind::WaterLevel => {
+                self.change_water_level(center, amount * WATER_LEVEL_SPEED * DELTA);
+                vec![]
+            }
+        };
+        if matches!(kind, TerraformKind::Smooth | TerraformKind::Erode) {
+            self.settle_water(bbox);
         }
+
+        // uprooted or drowned, the tree chunks are redrawn with the terrain
+        for (h, pos, z) in grounds {
+            let Some(new_z) = self.true_height(pos) else {
+                continue;
+            };
+            if (new_z - z).abs() > MAX_TREE_DISPLACEMENT || !self.is_fertile(pos) {
+                self.trees.remove(h);
+            }
+        }
+        self.trees.maintain();
+
+        modified
     }
 
     fn generate_chunk(&self, (x, y): (u16, u16)) -> Option<(Chunk, Vec<Tree>)> {
@@ -306,13 +636,38 @@ impl Tree {
         let srand = common::rand::rand3(pos.x, pos.y, 3.0);
         let scale = 5.0 + 3.0 * srand;
 
+        // the wild forests are mostly grown
+        let grand = common::rand::rand3(pos.x, pos.y, 4.0);
+        let stage = if grand < 0.75 {
+            MATURE_STAGE
+        } else {
+            ((grand - 0.75) * 4.0 * MATURE_STAGE as f32) as u8
+        };
+
         Tree {
             pos,
             size: scale,
             col: colscale,
             dir: angle.vec2(),
+            stage,
         }
     }
+
+    pub fn sapling(pos: Vec2) -> Self {
+        Tree {
+            stage: 0,
+            ..Self::new(pos)
+        }
+    }
+
+    pub fn is_mature(&self) -> bool {
+        self.stage >= MATURE_STAGE
+    }
+
+    /// Drawn size of the tree, growing with its stage
+    pub fn scale(&self) -> f32 {
+        self.size * STAGE_SCALE[self.stage.min(MATURE_STAGE) as usize]
+    }
 }
 
 type SmolTree = u16;
@@ -338,18 +693,34 @@ pub fn to_pos(encoded: SmolTree, chunk: (u32, u32)) -> Vec2 {
 struct SerializedEnvironment {
     h: Heightmap,
     trees: Vec<((u32, u32), Vec<SmolTree>)>,
+    #[serde(default)]
+    water: SerializedWater,
+    /// Growth stage of the trees, in the order of `trees`. The trees without one are mature.
+    #[serde(default)]
+    tree_stages: Vec<Vec<u8>>,
 }
 
 impl From<SerializedEnvironment> for Environment {
     fn from(ser: SerializedEnvironment) -> Self {
+        let mut water = WaterMap::from(ser.water);
+        if !water.fits(&ser.h) {
+            water = WaterMap::new(&ser.h);
+        }
         let mut terrain = Environment {
             heightmap: ser.h,
+            water,
             ..Self::default()
         };
 
+        let mut stages = ser.tree_stages.into_iter();
         for (chunk_id, trees) in ser.trees {
-            for tree in trees {
-                let tree = Tree::new(to_pos(tree, chunk_id));
+            let stages = stages.next().unwrap_or_default();
+            for (i, tree) in trees.into_iter().enumerate() {
+                let stage = stages.get(i).copied().unwrap_or(MATURE_STAGE);
+                let tree = Tree {
+                    stage: stage.min(MATURE_STAGE),
+                    ..Tree::new(to_pos(tree, chunk_id))
+                };
                 terrain.trees.insert(tree.pos, tree);
             }
         }
@@ -362,18 +733,106 @@ impl From<&Environment> for SerializedEnvironment {
         let mut t = SerializedEnvironment {
             h: ter.heightmap.clone(),
             trees: Vec::new(),
+            water: SerializedWater::from(&ter.water),
+            tree_stages: Vec::new(),
         };
 
         for (cell_id, chunk) in ter.trees.storage().cells.iter() {
             let cell_id = (cell_id.0 as u32, cell_id.1 as u32);
             let mut smoltrees = Vec::with_capacity(chunk.objs.len());
-            for (_, tree_pos) in chunk.objs.iter() {
-                let smol = new_smoltree(*tree_pos, cell_id);
-                smoltrees.push(smol);
+            let mut stages = Vec::with_capacity(chunk.objs.len());
+            for (h, tree_pos) in chunk.objs.iter() {
+                smoltrees.push(new_smoltree(*tree_pos, cell_id));
+                stages.push(ter.trees.get(*h).map_or(MATURE_STAGE, |(_, t)| t.stage));
             }
             t.trees.push((cell_id, smoltrees));
+            t.tree_stages.push(stages);
         }
 
         t
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn water_flows_downhill_and_lakes_are_still() {
+        let mut env = Environment::new(2, 2);
+        let bounds = env.bounds();
+        let center = bounds.center();
+
+        env.terrain_apply(bounds, |_| -20.0);
+        let lake = env.water_flow(center).unwrap();
+        assert!(lake.dir.mag() < 0.01);
+        assert!((lake.depth - 10.0).abs() < 0.5);
+
+        env.terrain_apply(bounds, |p| -20.0 - p.x * 0.01);
+        let river = env.water_flow(center).unwrap();
+        assert!(river.dir.x > 0.5, "{:?}", river);
+
+        env.terrain_apply(bounds, |_| 0.0);
+        assert_eq!(env.water_flow(center).unwrap(), WaterFlow::default());
+    }
+
+    #[test]
+    fn trees_grow_are_harvested_and_saved() {
+        let mut env = Environment::new(1, 1);
+        let bounds = env.bounds();
+        let center = bounds.center();
+        env.terrain_apply(bounds, |_| 0.0);
+        env.trees = Grid::new(TREE_GRID_SIZE as i32);
+
+        let chunk = env.plant_tree(center).unwrap();
+        assert!(env.plant_tree(center + vec2(1.0, 0.0)).is_none());
+        assert!(!env.has_mature_tree(center, 10.0, |_| true));
+
+        for day in 0..200 {
+            env.grow_trees(chunk, day, |_| true);
+        }
+        assert!(env.has_mature_tree(center, 10.0, |_| true));
+        assert!(env.trees.handles().count() > 1, "mature trees reseed");
+
+        let n_trees = env.trees.handles().count();
+        let n_mature = |env: &Environment| {
+            env.trees
+                .handles()
+                .filter(|h| env.trees.get(*h).unwrap().1.is_mature())
+                .count()
+        };
+        let mature = n_mature(&env);
+        assert_eq!(env.harvest_tree(center, 10.0, |_| true), Some(chunk));
+        assert_eq!(n_mature(&env), mature - 1);
+        assert_eq!(
+            env.trees.handles().count(),
+            n_trees,
+            "a sapling is replanted"
+        );
+
+        let loaded = Environment::from(SerializedEnvironment::from(&env));
+        assert_eq!(loaded.trees.handles().count(), n_trees);
+        assert_eq!(n_mature(&loaded), mature - 1);
+
+        let mut old_save = SerializedEnvironment::from(&env);
+        old_save.tree_stages.clear();
+        let loaded = Environment::from(old_save);
+        assert_eq!(
+            n_mature(&loaded),
+            n_trees,
+            "the trees of older saves are mature"
+        );
+
+        // lowered under the water, the trees drown
+        env.terraform(
+            Tick(0),
+            TerraformKind::Elevation,
+            center,
+            100.0,
+            -10000.0,
+            0.0,
+            None,
+        );
+        assert!(!env.has_mature_tree(center, 10.0, |_| true));
+    }
+}
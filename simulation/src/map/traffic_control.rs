@@ -1,5 +1,9 @@
+use prototypes::SECONDS_PER_REALTIME_SECOND;
 use serde::{Deserialize, Serialize};
 
+/// Seconds before the cars get the green light during which pedestrians no longer start crossing
+const CROSSING_CLEARANCE: u16 = 3 * SECONDS_PER_REALTIME_SECOND as u16;
+
 #[derive(Copy, Clone, Serialize, Deserialize)]
 pub enum TrafficBehavior {
     RED,
@@ -14,6 +18,19 @@ impl TrafficBehavior {
     }
 }
 
+/// Signal shown to the pedestrians at a crosswalk
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrossingPhase {
+    Walk,
+    DontWalk,
+}
+
+impl CrossingPhase {
+    pub fn is_walk(self) -> bool {
+        matches!(self, CrossingPhase::Walk)
+    }
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct TrafficLightSchedule {
     period: u16,
@@ -33,6 +50,17 @@ impl TrafficLightSchedule {
             offset,
         }
     }
+
+    fn remainder(&self, seconds: u32) -> u16 {
+        ((seconds % self.period as u32) as u16 + self.offset) % self.period
+    }
+
+    /// Pedestrians cross while the cars are at the red light, but not right before it turns green
+    fn walk_interval(&self) -> (u16, u16) {
+        let start = self.green + self.orange;
+        let end = self.period.saturating_sub(CROSSING_CLEARANCE).max(start);
+        (start, end)
+    }
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
@@ -59,8 +87,7 @@ impl TrafficControl {
         match self {
             TrafficControl::Always => TrafficBehavior::GREEN,
             TrafficControl::Light(schedule) => {
-                let remainder =
-                    ((seconds % schedule.period as u32) as u16 + schedule.offset) % schedule.period;
+                let remainder = schedule.remainder(seconds);
                 if remainder < schedule.green {
                     TrafficBehavior::GREEN
                 } else if remainder < schedule.green + schedule.orange {
@@ -72,4 +99,59 @@ impl TrafficControl {
             TrafficControl::StopSign => TrafficBehavior::STOP,
         }
     }
+
+    /// Phase of a crosswalk over lanes with this control.
+    /// Crossings without lights let the pedestrians walk whenever they want.
+    pub fn crossing_phase(&self, seconds: u32) -> CrossingPhase {
+        match self {
+            TrafficControl::Light(schedule) => {
+                let (start, end) = schedule.walk_interval();
+                if (start..end).contains(&schedule.remainder(seconds)) {
+                    CrossingPhase::Walk
+                } else {
+                    CrossingPhase::DontWalk
+                }
+            }
+            TrafficControl::Always | TrafficControl::StopSign => CrossingPhase::Walk,
+        }
+    }
+
+    /// Average seconds a pedestrian arriving at a random time waits at a crosswalk over lanes
+    /// with this control
+    pub fn expected_crossing_wait(&self) -> f32 {
+        match self {
+            TrafficControl::Light(schedule) => {
+                let (start, end) = schedule.walk_interval();
+                let dont_walk = (schedule.period - (end - start)) as f32;
+                dont_walk * dont_walk / (2.0 * schedule.period as f32)
+            }
+            TrafficControl::Always | TrafficControl::StopSign => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pedestrians_walk_during_the_red_light() {
+        let light = TrafficControl::Light(TrafficLightSchedule::from_basic(100, 40, 140, 0));
+
+        for seconds in 0..560 {
+            let phase = light.crossing_phase(seconds);
+            if phase.is_walk() {
+                assert!(light.get_behavior(seconds).is_red(), "at {seconds}");
+            }
+        }
+        assert!(light.crossing_phase(150).is_walk());
+        assert!(!light.crossing_phase(50).is_walk());
+        // clearance before the green light
+        assert!(!light.crossing_phase(279).is_walk());
+
+        let wait = light.expected_crossing_wait();
+        assert!(wait > 0.0 && wait < 280.0);
+        assert_eq!(TrafficControl::Always.expected_crossing_wait(), 0.0);
+        assert!(TrafficControl::StopSign.crossing_phase(0).is_walk());
+    }
 }
@@ -0,0 +1,756 @@
+//! The water of the map is split in regions, the sea and the lakes, each with its own level.
+//! A terrain cell is wet when the ground there is below the level of the region it belongs to.
+//! Changing the terrain only settles the cells around the change and the basins they connect to,
+//! the rest of the map is left untouched.
+
+use crate::map::terrain::{Heightmap, CELL_SIZE, TERRAIN_CHUNK_RESOLUTION, WATER_LEVEL};
+use geom::{vec2, Vec2, AABB};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+pub type WaterRegionID = u32;
+
+/// Side of the water cells, one for each sample of the terrain heightmap
+pub const WATER_CELL_SIZE: f32 = CELL_SIZE;
+
+/// The sea comes in from the edges of the map, its level never changes
+pub const SEA: WaterRegionID = 0;
+const DRY: WaterRegionID = WaterRegionID::MAX;
+
+/// Most cells flooded by a single change, bounds the work of one terraforming stroke
+const MAX_FLOOD_CELLS: usize = 1 << 20;
+
+/// Meters a lake moves toward the water table each day, evaporating above it and filling with
+/// rain below it
+pub const LAKE_EQUALIZATION_PER_DAY: f32 = 0.05;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WaterRegion {
+    /// Height of the surface
+    pub level: f32,
+    /// Number of wet cells, a region that dried up keeps its id
+    pub cells: u32,
+    /// Cells it ever covered, so it can be settled without going over the whole map
+    min: (u32, u32),
+    max: (u32, u32),
+}
+
+impl WaterRegion {
+    fn new(level: f32) -> Self {
+        Self {
+            level,
+            cells: 0,
+            min: (u32::MAX, u32::MAX),
+            max: (0, 0),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct WaterMap {
+    /// Size of the map in cells, one cell per terrain height sample
+    w: u32,
+    h: u32,
+    cells: Vec<WaterRegionID>,
+    regions: Vec<WaterRegion>,
+}
+
+/// Cells whose water changed, in cell coordinates
+#[derive(Default)]
+struct Changes(Option<((u32, u32), (u32, u32))>);
+
+impl Changes {
+    fn add(&mut self, x: u32, y: u32) {
+        self.add_range((x, y), (x, y));
+    }
+
+    fn add_range(&mut self, min: (u32, u32), max: (u32, u32)) {
+        if min.0 > max.0 || min.1 > max.1 {
+            return;
+        }
+        self.0 = Some(match self.0 {
+            None => (min, max),
+            Some((lo, hi)) => (
+                (lo.0.min(min.0), lo.1.min(min.1)),
+                (hi.0.max(max.0), hi.1.max(max.1)),
+            ),
+        });
+    }
+
+    /// The area covered by the changed cells, in meters
+    fn area(&self) -> Option<AABB> {
+        let (min, max) = self.0?;
+        Some(AABB::new_ll_ur(
+            vec2(min.0 as f32, min.1 as f32) * CELL_SIZE,
+            vec2(max.0 as f32 + 1.0, max.1 as f32 + 1.0) * CELL_SIZE,
+        ))
+    }
+}
+
+fn ground(hm: &Heightmap, x: u32, y: u32) -> f32 {
+    hm.height_nearest(vec2(x as f32, y as f32) * CELL_SIZE)
+        .unwrap_or(f32::INFINITY)
+}
+
+impl WaterMap {
+    /// Fills the map at the water table: the sea from the edges and a lake in each basin below it
+    pub fn new(hm: &Heightmap) -> Self {
+        let w = hm.w as u32 * TERRAIN_CHUNK_RESOLUTION as u32;
+        let h = hm.h as u32 * TERRAIN_CHUNK_RESOLUTION as u32;
+        let mut me = Self {
+            w,
+            h,
+            cells: vec![DRY; (w * h) as usize],
+            regions: vec![WaterRegion::new(WATER_LEVEL)],
+        };
+        let mut changes = Changes::default();
+
+        let seeds = (0..w * h)
+            .filter(|&i| me.is_border(i as usize))
+            .map(|i| (i as usize, SEA))
+            .collect();
+        me.flood(hm, seeds, &mut changes, usize::MAX);
+
+        for i in 0..me.cells.len() {
+            let (x, y) = me.xy(i);
+            if me.cells[i] != DRY || ground(hm, x, y) >= WATER_LEVEL {
+                continue;
+            }
+            let id = me.regions.len() as WaterRegionID;
+            me.regions.push(WaterRegion::new(WATER_LEVEL));
+            me.flood(hm, VecDeque::from([(i, id)]), &mut changes, usize::MAX);
+        }
+        me
+    }
+
+    /// Whether it was made for a heightmap of that size
+    pub fn fits(&self, hm: &Heightmap) -> bool {
+        self.w == hm.w as u32 * TERRAIN_CHUNK_RESOLUTION as u32
+            && self.h == hm.h as u32 * TERRAIN_CHUNK_RESOLUTION as u32
+    }
+
+    pub fn region(&self, id: WaterRegionID) -> Option<&WaterRegion> {
+        self.regions.get(id as usize).filter(|r| r.cells > 0)
+    }
+
+    /// The regions holding water
+    pub fn regions(&self) -> impl Iterator<Item = (WaterRegionID, &WaterRegion)> + '_ {
+        self.regions
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.cells > 0)
+            .map(|(id, r)| (id as WaterRegionID, r))
+    }
+
+    /// The region of the cell at that position, None if the cell is dry
+    pub fn region_at(&self, pos: Vec2) -> Option<WaterRegionID> {
+        let (x, y) = self.cell_of(pos)?;
+        let r = self.cells[self.idx(x, y)];
+        (r != DRY).then_some(r)
+    }
+
+    /// Level of the water at that position, the highest of the wet cells around it.
+    /// None on land, where none of the cells around is wet.
+    pub fn level(&self, pos: Vec2) -> Option<f32> {
+        let c = (pos / CELL_SIZE).floor();
+        let mut level: Option<f32> = None;
+        for (dx, dy) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+            let Some(r) = self.region_at((c + vec2(dx, dy)) * CELL_SIZE) else {
+                continue;
+            };
+            let l = self.regions[r as usize].level;
+            level = Some(level.map_or(l, |x| x.max(l)));
+        }
+        level
+    }
+
+    /// The wet cells of the area with their level, the cells are squares of [`CELL_SIZE`]
+    /// centered on the given positions
+    pub fn wet_cells(&self, area: AABB) -> impl Iterator<Item = (Vec2, f32)> + '_ {
+        let (min, max) = self.cell_range(area, 0);
+        (min.1..=max.1)
+            .flat_map(move |y| (min.0..=max.0).map(move |x| (x, y)))
+            .filter_map(move |(x, y)| {
+                let r = self.cells[self.idx(x, y)];
+                (r != DRY).then(|| {
+                    (
+                        vec2(x as f32, y as f32) * CELL_SIZE,
+                        self.regions[r as usize].level,
+                    )
+                })
+            })
+    }
+
+    /// Settles the water after the ground of the area changed: the cells that rose above their
+    /// water dry up, the ones that went below the water next to them are flooded, and the
+    /// regions that now touch are merged.
+    /// Returns the area where the water changed.
+    pub fn settle(&mut self, hm: &Heightmap, area: AABB) -> Option<AABB> {
+        let (min, max) = self.cell_range(area, 1);
+        let mut changes = Changes::default();
+        let merges = self.settle_cells(hm, min, max, &mut changes);
+        self.resolve(hm, merges, &mut changes);
+        changes.area()
+    }
+
+    /// Moves the level of the water at that position, a new pond is dug on dry ground
+    /// when raising it. The sea keeps its level.
+    /// Returns the area where the water changed.
+    pub fn change_level(&mut self, hm: &Heightmap, pos: Vec2, delta: f32) -> Option<AABB> {
+        let (x, y) = self.cell_of(pos)?;
+        let i = self.idx(x, y);
+        let mut changes = Changes::default();
+
+        match self.cells[i] {
+            SEA => return None,
+            DRY => {
+                if delta <= 0.0 {
+                    return None;
+                }
+                let id = self.regions.len() as WaterRegionID;
+                self.regions
+                    .push(WaterRegion::new(ground(hm, x, y) + delta));
+                let merges =
+                    self.flood(hm, VecDeque::from([(i, id)]), &mut changes, MAX_FLOOD_CELLS);
+                self.resolve(hm, merges, &mut changes);
+            }
+            r => {
+                self.set_level(hm, r, self.regions[r as usize].level + delta, &mut changes);
+            }
+        }
+        changes.area()
+    }
+
+    /// Moves the lakes toward the water table by at most `rate` meters.
+    /// Returns the area where the water changed.
+    pub fn equalize(&mut self, hm: &Heightmap, rate: f32) -> Option<AABB> {
+        let mut changes = Changes::default();
+        for id in 1..self.regions.len() as WaterRegionID {
+            let r = self.regions[id as usize];
+            if r.cells == 0 || (WATER_LEVEL - r.level).abs() < 1e-3 {
+                continue;
+            }
+            let level = r.level + (WATER_LEVEL - r.level).clamp(-rate, rate);
+            self.set_level(hm, id, level, &mut changes);
+        }
+        changes.area()
+    }
+
+    /// Cells that would be under water if the ground of the area had the new heights,
+    /// with their level. The water comes from the regions in and around the area, the cells
+    /// already wet are not returned.
+    pub fn flood_preview(
+        &self,
+        hm: &Heightmap,
+        area: AABB,
+        new_height: impl Fn(Vec2, f32) -> f32,
+    ) -> Vec<(Vec2, f32)> {
+        let (min, max) = self.cell_range(area, 1);
+        if min.0 > max.0 || min.1 > max.1 {
+            return vec![];
+        }
+        let inside = |x: u32, y: u32| x >= min.0 && x <= max.0 && y >= min.1 && y <= max.1;
+        let height = |x: u32, y: u32| {
+            let p = vec2(x as f32, y as f32) * CELL_SIZE;
+            new_height(p, ground(hm, x, y))
+        };
+
+        let mut queue = VecDeque::new();
+        for y in min.1..=max.1 {
+            for x in min.0..=max.0 {
+                let i = self.idx(x, y);
+                if self.cells[i] != DRY {
+                    continue;
+                }
+                if self.is_border(i) {
+                    queue.push_back((x, y, WATER_LEVEL));
+                }
+                for n in self.neighbours(i) {
+                    if let Some(r) = self.regions.get(self.cells[n] as usize) {
+                        queue.push_back((x, y, r.level));
+                    }
+                }
+            }
+        }
+
+        let mut flooded = vec![None; ((max.0 - min.0 + 1) * (max.1 - min.1 + 1)) as usize];
+        let local = |x: u32, y: u32| ((y - min.1) * (max.0 - min.0 + 1) + x - min.0) as usize;
+        while let Some((x, y, level)) = queue.pop_front() {
+            let l = local(x, y);
+            if flooded[l].is_some_and(|f: f32| f >= level)
+                || self.cells[self.idx(x, y)] != DRY
+                || height(x, y) >= level
+            {
+                continue;
+            }
+            flooded[l] = Some(level);
+            for n in self.neighbours(self.idx(x, y)) {
+                let (nx, ny) = self.xy(n);
+                if inside(nx, ny) {
+                    queue.push_back((nx, ny, level));
+                }
+            }
+        }
+
+        (min.1..=max.1)
+            .flat_map(|y| (min.0..=max.0).map(move |x| (x, y)))
+            .filter_map(|(x, y)| {
+                let level = flooded[local(x, y)]?;
+                Some((vec2(x as f32, y as f32) * CELL_SIZE, level))
+            })
+            .collect()
+    }
+
+    /// Cells that would be under water if the level at that position moved by `delta`,
+    /// with their level. The cells already wet are not returned.
+    pub fn level_preview(&self, hm: &Heightmap, pos: Vec2, delta: f32) -> Vec<(Vec2, f32)> {
+        let Some((x, y)) = self.cell_of(pos) else {
+            return vec![];
+        };
+        let i = self.idx(x, y);
+        let (level, seeds) = match self.cells[i] {
+            SEA => return vec![],
+            DRY if delta <= 0.0 => return vec![],
+            DRY => (ground(hm, x, y) + delta, vec![i]),
+            r => {
+                let region = &self.regions[r as usize];
+                let seeds = (region.min.1..=region.max.1)
+                    .flat_map(|y| (region.min.0..=region.max.0).map(move |x| (x, y)))
+                    .map(|(x, y)| self.idx(x, y))
+                    .filter(|&j| self.cells[j] == r)
+                    .flat_map(|j| self.neighbours(j))
+                    .collect();
+                (region.level + delta, seeds)
+            }
+        };
+
+        let mut seen = std::collections::HashSet::new();
+        let mut queue = VecDeque::from(seeds);
+        let mut flooded = vec![];
+        while let Some(j) = queue.pop_front() {
+            if flooded.len() >= MAX_FLOOD_CELLS || self.cells[j] != DRY || !seen.insert(j) {
+                continue;
+            }
+            let (x, y) = self.xy(j);
+            if ground(hm, x, y) >= level {
+                continue;
+            }
+            flooded.push((vec2(x as f32, y as f32) * CELL_SIZE, level));
+            queue.extend(self.neighbours(j));
+        }
+        flooded
+    }
+
+    fn set_level(&mut self, hm: &Heightmap, id: WaterRegionID, level: f32, changes: &mut Changes) {
+        let r = &mut self.regions[id as usize];
+        r.level = level;
+        let (min, max) = (r.min, r.max);
+        changes.add_range(min, max);
+        let (min, max) = self.grow(min, max, 1);
+        let merges = self.settle_cells(hm, min, max, changes);
+        self.resolve(hm, merges, changes);
+    }
+
+    /// Dries the cells of the range above their water, then floods the dry cells below the
+    /// water next to them. Returns the pairs of regions that touch.
+    fn settle_cells(
+        &mut self,
+        hm: &Heightmap,
+        min: (u32, u32),
+        max: (u32, u32),
+        changes: &mut Changes,
+    ) -> Vec<(WaterRegionID, WaterRegionID)> {
+        if min.0 > max.0 || min.1 > max.1 {
+            return vec![];
+        }
+
+        for y in min.1..=max.1 {
+            for x in min.0..=max.0 {
+                let i = self.idx(x, y);
+                let r = self.cells[i];
+                if r != DRY && ground(hm, x, y) >= self.regions[r as usize].level {
+                    self.set(i, DRY);
+                    changes.add(x, y);
+                }
+            }
+        }
+
+        let mut merges = vec![];
+        let mut seeds = VecDeque::new();
+        for y in min.1..=max.1 {
+            for x in min.0..=max.0 {
+                let i = self.idx(x, y);
+                let r = self.cells[i];
+                if r == DRY {
+                    if self.is_border(i) {
+                        seeds.push_back((i, SEA));
+                    }
+                    for n in self.neighbours(i) {
+                        if self.cells[n] != DRY {
+                            seeds.push_back((i, self.cells[n]));
+                        }
+                    }
+                    continue;
+                }
+                // the lakes reaching the edge of the map flow into the sea
+                if r != SEA && self.is_border(i) {
+                    merges.push((SEA, r));
+                }
+                for n in self.neighbours(i) {
+                    let other = self.cells[n];
+                    if other != DRY && other != r {
+                        merges.push((r, other));
+                    }
+                }
+            }
+        }
+
+        merges.extend(self.flood(hm, seeds, changes, MAX_FLOOD_CELLS));
+        merges
+    }
+
+    /// Spreads each region from its seeds over the dry cells below its level.
+    /// Returns the pairs of regions that met.
+    fn flood(
+        &mut self,
+        hm: &Heightmap,
+        mut queue: VecDeque<(usize, WaterRegionID)>,
+        changes: &mut Changes,
+        limit: usize,
+    ) -> Vec<(WaterRegionID, WaterRegionID)> {
+        let mut merges = vec![];
+        let mut flooded = 0;
+        while let Some((i, r)) = queue.pop_front() {
+            let cur = self.cells[i];
+            if cur == r {
+                continue;
+            }
+            if cur != DRY {
+                merges.push((r, cur));
+                continue;
+            }
+            let (x, y) = self.xy(i);
+            if ground(hm, x, y) >= self.regions[r as usize].level {
+                continue;
+            }
+            if flooded >= limit {
+                break;
+            }
+            flooded += 1;
+            self.set(i, r);
+            changes.add(x, y);
+            for n in self.neighbours(i) {
+                queue.push_back((n, r));
+            }
+        }
+        merges
+    }
+
+    /// Merges the regions that touch until none do, settling the merged regions whose level
+    /// changed
+    fn resolve(
+        &mut self,
+        hm: &Heightmap,
+        mut pending: Vec<(WaterRegionID, WaterRegionID)>,
+        changes: &mut Changes,
+    ) {
+        while let Some((a, b)) = pending.pop() {
+            if a == b || self.regions[a as usize].cells == 0 || self.regions[b as usize].cells == 0
+            {
+                continue;
+            }
+            let keep = self.merge(a, b, changes);
+            let r = self.regions[keep as usize];
+            let (min, max) = self.grow(r.min, r.max, 1);
+            pending.extend(self.settle_cells(hm, min, max, changes));
+        }
+    }
+
+    /// Pours the smaller region into the bigger one, or into the sea, at the level of the
+    /// water they hold together. Returns the region left.
+    fn merge(
+        &mut self,
+        a: WaterRegionID,
+        b: WaterRegionID,
+        changes: &mut Changes,
+    ) -> WaterRegionID {
+        let (ra, rb) = (self.regions[a as usize], self.regions[b as usize]);
+        let (keep, gone) = if a == SEA || (b != SEA && ra.cells >= rb.cells) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let (k, g) = (self.regions[keep as usize], self.regions[gone as usize]);
+
+        if keep != SEA {
+            let total = (k.cells + g.cells) as f32;
+            let level = (k.level * k.cells as f32 + g.level * g.cells as f32) / total;
+            if level != k.level {
+                self.regions[keep as usize].level = level;
+                changes.add_range(k.min, k.max);
+            }
+        }
+
+        changes.add_range(g.min, g.max);
+        for y in g.min.1..=g.max.1 {
+            for x in g.min.0..=g.max.0 {
+                let i = self.idx(x, y);
+                if self.cells[i] == gone {
+                    self.set(i, keep);
+                }
+            }
+        }
+        keep
+    }
+
+    fn set(&mut self, i: usize, r: WaterRegionID) {
+        let old = std::mem::replace(&mut self.cells[i], r);
+        if old != DRY {
+            self.regions[old as usize].cells -= 1;
+        }
+        if r != DRY {
+            let (x, y) = self.xy(i);
+            let region = &mut self.regions[r as usize];
+            region.cells += 1;
+            region.min = (region.min.0.min(x), region.min.1.min(y));
+            region.max = (region.max.0.max(x), region.max.1.max(y));
+        }
+    }
+
+    fn idx(&self, x: u32, y: u32) -> usize {
+        (y * self.w + x) as usize
+    }
+
+    fn xy(&self, i: usize) -> (u32, u32) {
+        (i as u32 % self.w, i as u32 / self.w)
+    }
+
+    fn is_border(&self, i: usize) -> bool {
+        let (x, y) = self.xy(i);
+        x == 0 || y == 0 || x + 1 == self.w || y + 1 == self.h
+    }
+
+    fn neighbours(&self, i: usize) -> impl Iterator<Item = usize> {
+        let (x, y) = self.xy(i);
+        let (w, h) = (self.w, self.h);
+        [
+            (x > 0).then(|| i - 1),
+            (x + 1 < w).then(|| i + 1),
+            (y > 0).then(|| i - w as usize),
+            (y + 1 < h).then(|| i + w as usize),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// The cell whose sample is the closest to the position
+    fn cell_of(&self, pos: Vec2) -> Option<(u32, u32)> {
+        let c = (pos / CELL_SIZE).round();
+        if c.x < 0.0 || c.y < 0.0 || c.x >= self.w as f32 || c.y >= self.h as f32 {
+            return None;
+        }
+        Some((c.x as u32, c.y as u32))
+    }
+
+    /// The cells covering the area, grown by `margin` cells and clamped to the map.
+    /// The range is empty (min > max) if the area is outside the map.
+    fn cell_range(&self, area: AABB, margin: u32) -> ((u32, u32), (u32, u32)) {
+        if self.w == 0 || self.h == 0 {
+            return ((1, 1), (0, 0));
+        }
+        let ll = (area.ll / CELL_SIZE).floor();
+        let ur = (area.ur / CELL_SIZE).ceil();
+        if ur.x < 0.0 || ur.y < 0.0 || ll.x >= self.w as f32 || ll.y >= self.h as f32 {
+            return ((1, 1), (0, 0));
+        }
+        let clamp = |v: f32, n: u32| v.clamp(0.0, (n - 1) as f32) as u32;
+        self.grow(
+            (clamp(ll.x, self.w), clamp(ll.y, self.h)),
+            (clamp(ur.x, self.w), clamp(ur.y, self.h)),
+            margin,
+        )
+    }
+
+    fn grow(&self, min: (u32, u32), max: (u32, u32), margin: u32) -> ((u32, u32), (u32, u32)) {
+        if min.0 > max.0 || min.1 > max.1 {
+            return (min, max);
+        }
+        (
+            (min.0.saturating_sub(margin), min.1.saturating_sub(margin)),
+            (
+                (max.0 + margin).min(self.w - 1),
+                (max.1 + margin).min(self.h - 1),
+            ),
+        )
+    }
+}
+
+/// The water map saved with the terrain, the cells as runs of the same region
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct SerializedWater {
+    w: u32,
+    h: u32,
+    runs: Vec<(WaterRegionID, u32)>,
+    regions: Vec<WaterRegion>,
+}
+
+impl From<&WaterMap> for SerializedWater {
+    fn from(water: &WaterMap) -> Self {
+        let mut runs: Vec<(WaterRegionID, u32)> = vec![];
+        for &r in &water.cells {
+            match runs.last_mut() {
+                Some((last, n)) if *last == r => *n += 1,
+                _ => runs.push((r, 1)),
+            }
+        }
+        Self {
+            w: water.w,
+            h: water.h,
+            runs,
+            regions: water.regions.clone(),
+        }
+    }
+}
+
+impl From<SerializedWater> for WaterMap {
+    fn from(ser: SerializedWater) -> Self {
+        let mut cells = Vec::with_capacity((ser.w * ser.h) as usize);
+        for (r, n) in ser.runs {
+            cells.extend(std::iter::repeat(r).take(n as usize));
+        }
+        Self {
+            w: ser.w,
+            h: ser.h,
+            cells,
+            regions: ser.regions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Environment;
+
+    fn flat(env: &mut Environment, z: f32) {
+        let bounds = env.bounds();
+        env.terrain_apply(bounds, |_| z);
+    }
+
+    #[test]
+    fn dug_basins_fill_from_the_water_they_touch() {
+        let mut env = Environment::new(1, 1);
+        flat(&mut env, 0.0);
+        let center = env.bounds().center();
+        assert_eq!(env.water_level(center), None);
+
+        // a hole away from the water stays dry
+        env.terrain_apply(env.bounds(), |p| {
+            if p.xy().distance(center) < 100.0 {
+                -20.0
+            } else {
+                p.z
+            }
+        });
+        assert_eq!(env.water_level(center), None);
+
+        // a channel to the edge of the map lets the sea in
+        env.terrain_apply(env.bounds(), |p| {
+            if (p.y - center.y).abs() < 20.0 && p.x < center.x {
+                -20.0
+            } else {
+                p.z
+            }
+        });
+        assert_eq!(env.water_level(center), Some(WATER_LEVEL));
+        assert!((env.water_flow(center).unwrap().depth - 10.0).abs() < 0.5);
+
+        // raising the bed drains it
+        env.terrain_apply(env.bounds(), |p| {
+            if p.xy().distance(center) < 100.0 {
+                0.0
+            } else {
+                p.z
+            }
+        });
+        assert_eq!(env.water_level(center), None);
+        assert!(env.is_fertile(center));
+    }
+
+    #[test]
+    fn lakes_keep_their_level_and_merge_when_joined() {
+        let mut env = Environment::new(1, 1);
+        flat(&mut env, 0.0);
+        let a = vec2(150.0, 250.0);
+        let b = vec2(350.0, 250.0);
+
+        env.terrain_apply(env.bounds(), |p| {
+            if p.xy().distance(a) < 60.0 || p.xy().distance(b) < 60.0 {
+                -20.0
+            } else {
+                p.z
+            }
+        });
+        assert_eq!(env.water_level(a), None);
+
+        // ponds are dug on dry ground, then raised
+        env.take_water_changes();
+        env.change_water_level(a, 5.0);
+        env.change_water_level(b, 1.0);
+        assert!(env.take_water_changes().is_some());
+        let level_a = env.water_level(a).unwrap();
+        let level_b = env.water_level(b).unwrap();
+        assert!((level_a - -15.0).abs() < 0.01);
+        assert!((level_b - -19.0).abs() < 0.01);
+        assert_ne!(env.water().region_at(a), env.water().region_at(b));
+
+        // the sea keeps its level
+        let mut sea_env = Environment::new(1, 1);
+        flat(&mut sea_env, -20.0);
+        let center = sea_env.bounds().center();
+        sea_env.take_water_changes();
+        sea_env.change_water_level(center, 5.0);
+        assert_eq!(sea_env.take_water_changes(), None);
+        assert_eq!(sea_env.water_level(center), Some(WATER_LEVEL));
+
+        // a trench between the lakes pours them into one, at the level of the water they hold
+        env.terrain_apply(env.bounds(), |p| {
+            if (p.y - 250.0).abs() < 20.0 && p.x > a.x && p.x < b.x {
+                -20.0
+            } else {
+                p.z
+            }
+        });
+        let merged = env.water_level(a).unwrap();
+        assert_eq!(env.water().region_at(a), env.water().region_at(b));
+        assert_eq!(env.water_level(b), Some(merged));
+        assert!(merged < level_a && merged > level_b);
+
+        // the lake slowly goes back to the water table
+        for _ in 0..1000 {
+            env.equalize_water(LAKE_EQUALIZATION_PER_DAY);
+        }
+        assert!((env.water_level(a).unwrap() - WATER_LEVEL).abs() < 0.01);
+    }
+
+    #[test]
+    fn preview_shows_the_cells_that_would_flood() {
+        let mut env = Environment::new(1, 1);
+        flat(&mut env, 0.0);
+        env.terrain_apply(env.bounds(), |p| if p.x < 100.0 { -20.0 } else { p.z });
+
+        let area = AABB::new_ll_ur(vec2(90.0, 200.0), vec2(200.0, 300.0));
+        let preview = env.flood_preview(area, |_, h| h - 20.0);
+        assert!(!preview.is_empty());
+        assert!(preview.iter().all(|(p, level)| {
+            *level == WATER_LEVEL && p.x >= 100.0 && p.x <= 224.0 && env.water_level(*p).is_none()
+        }));
+
+        // nothing floods when the ground stays above the water
+        assert!(env.flood_preview(area, |_, h| h - 5.0).is_empty());
+
+        let loaded = WaterMap::from(SerializedWater::from(env.water()));
+        assert_eq!(loaded.cells, env.water().cells);
+        assert_eq!(loaded.regions, env.water().regions);
+    }
+}
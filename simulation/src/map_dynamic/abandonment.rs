@@ -0,0 +1,232 @@
+//! Buildings left empty for a long time become derelict.
+//! They look run down, nothing grows next to them and they are occupied again once the demand
+//! comes back, unless the player demolishes them first.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::Vec2;
+use prototypes::{
+    GameDuration, GameInstant, GameTime, Tick, HOURS_PER_DAY, TICKS_PER_HOUR, TICKS_PER_MINUTE,
+};
+
+use crate::economy::ZoneDemand;
+use crate::event_log::{log_event, EventCategory, EventSubject, Severity};
+use crate::map::{BuildingID, BuildingKind};
+use crate::map_dynamic::{BuildingInfos, GROWTH_THRESHOLD};
+use crate::rules::GameRules;
+use crate::souls::company_lifecycle::CompanyLifecycle;
+use crate::Simulation;
+
+const TICKS_PER_DAY: u64 = TICKS_PER_HOUR * HOURS_PER_DAY as u64;
+/// Days a building stays empty before it becomes derelict, unless the game rules say otherwise
+pub const DERELICT_AFTER_DAYS: u32 = 3;
+/// Nothing grows closer than this to a derelict building
+pub const DERELICT_BLIGHT_RADIUS: f32 = 60.0;
+
+/// Buildings that lost their household or their company
+#[derive(Default, Serialize, Deserialize)]
+pub struct Abandonment {
+    /// Since when each empty building is empty
+    empty_since: BTreeMap<BuildingID, GameInstant>,
+}
+
+impl Abandonment {
+    /// The building stays empty until it is occupied again
+    pub fn vacate(&mut self, building: BuildingID, now: GameInstant) {
+        self.empty_since.entry(building).or_insert(now);
+    }
+
+    pub fn is_empty(&self, building: BuildingID) -> bool {
+        self.empty_since.contains_key(&building)
+    }
+
+    pub fn empty_since(&self, building: BuildingID) -> Option<GameInstant> {
+        self.empty_since.get(&building).copied()
+    }
+}
+
+/// Time a building stays empty before it becomes derelict
+pub fn derelict_after(rules: &GameRules) -> GameDuration {
+    GameDuration(Tick(rules.derelict_days as u64 * TICKS_PER_DAY))
+}
+
+/// Whether a derelict building is too close to the position for anything to grow there
+pub fn near_derelict(derelicts: &[Vec2], pos: Vec2) -> bool {
+    derelicts
+        .iter()
+        .any(|d| d.distance2(pos) < DERELICT_BLIGHT_RADIUS * DERELICT_BLIGHT_RADIUS)
+}
+
+/// Every minute, follows the empty buildings, lets them run down and moves households into
+/// the empty houses when people want to live in the city
+pub(crate) fn abandonment_system(sim: &mut Simulation) {
+    profiling::scope!("map_dynamic::abandonment_system");
+    if sim.get_tick() % TICKS_PER_MINUTE != 0 {
+        return;
+    }
+    track_empty(sim);
+    reoccupy_house(sim);
+    update_dereliction(sim);
+}
+
+/// Starts the timer of the closed companies and stops it for the occupied buildings
+fn track_empty(sim: &mut Simulation) {
+    let now = sim.read::<GameTime>().instant();
+    let mut occupied = vec![];
+    {
+        let map = sim.map();
+        let binfos = sim.read::<BuildingInfos>();
+        let lifecycle = sim.read::<CompanyLifecycle>();
+        let mut abandonment = sim.write::<Abandonment>();
+
+        for &building in &lifecycle.vacant {
+            abandonment.vacate(building, now);
+        }
+
+        abandonment.empty_since.retain(|&id, _| {
+            let Some(b) = map.buildings().get(id) else {
+                return false;
+            };
+            let reopened =
+                matches!(b.kind, BuildingKind::GoodsCompany(_)) && !lifecycle.is_vacant(id);
+            if binfos.owner(id).is_some() || reopened {
+                occupied.push(id);
+                return false;
+            }
+            true
+        });
+    }
+
+    for id in occupied {
+        restore(sim, id);
+    }
+}
+
+/// A household moves into the house empty for the longest time when there is residential demand
+fn reoccupy_house(sim: &mut Simulation) {
+    if sim.read::<ZoneDemand>().residential < GROWTH_THRESHOLD {
+        return;
+    }
+    let house = {
+        let map = sim.map();
+        let abandonment = sim.read::<Abandonment>();
+        abandonment
+            .empty_since
+            .iter()
+            .filter(|(&id, _)| {
+                map.buildings()
+                    .get(id)
+                    .is_some_and(|b| matches!(b.kind, BuildingKind::House))
+            })
+            .min_by_key(|(&id, &since)| (since, id))
+            .map(|(&id, _)| id)
+    };
+    let Some(house) = house else {
+        return;
+    };
+
+    sim.write::<ZoneDemand>().residential -= GROWTH_THRESHOLD;
+    sim.write::<Abandonment>().empty_since.remove(&house);
+    restore(sim, house);
+}
+
+/// The building is occupied again, it is fixed up if it was derelict
+fn restore(sim: &mut Simulation, id: BuildingID) {
+    let Some(kind) = sim
+        .map()
+        .buildings()
+        .get(id)
+        .filter(|b| b.derelict)
+        .map(|b| b.kind)
+    else {
+        return;
+    };
+    sim.map_mut().set_derelict(id, false);
+    log_event(
+        sim,
+        EventCategory::Construction,
+        Severity::Info,
+        format!(
+            "A derelict {} is occupied again",
+            kind.label().to_lowercase()
+        ),
+        Some(EventSubject::Building(id)),
+    );
+}
+
+fn update_dereliction(sim: &mut Simulation) {
+    let time = sim.read::<GameTime>();
+    let after = derelict_after(&sim.read::<GameRules>());
+    let now_derelict: Vec<(BuildingID, BuildingKind)> = {
+        let map = sim.map();
+        sim.read::<Abandonment>()
+            .empty_since
+            .iter()
+            .filter(|(_, since)| since.elapsed(&time) >= after)
+            .filter_map(|(&id, _)| map.buildings().get(id))
+            .filter(|b| !b.derelict)
+            .map(|b| (b.id, b.kind))
+            .collect()
+    };
+    drop(time);
+
+    for (id, kind) in now_derelict {
+        sim.map_mut().set_derelict(id, true);
+        log_event(
+            sim,
+            EventCategory::Construction,
+            Severity::Info,
+            format!("An empty {} became derelict", kind.label().to_lowercase()),
+            Some(EventSubject::Building(id)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3};
+
+    use super::*;
+    use crate::event_log::EventLog;
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn empty_houses_run_down_and_are_reoccupied() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(20.0, -20.0));
+        test.g.write::<GameRules>().derelict_days = 0;
+        test.g.write::<ZoneDemand>().residential = 0.0;
+
+        let now = test.g.read::<GameTime>().instant();
+        test.g.write::<Abandonment>().vacate(house, now);
+        track_empty(&mut test.g);
+        update_dereliction(&mut test.g);
+        assert!(test.g.map().buildings()[house].derelict);
+        assert!(test.g.read::<Abandonment>().is_empty(house));
+        let n_events = test.g.read::<EventLog>().len();
+
+        // nobody moves in without demand
+        reoccupy_house(&mut test.g);
+        assert!(test.g.read::<Abandonment>().is_empty(house));
+
+        test.g.write::<ZoneDemand>().residential = GROWTH_THRESHOLD;
+        reoccupy_house(&mut test.g);
+        assert!(!test.g.read::<Abandonment>().is_empty(house));
+        assert!(!test.g.map().buildings()[house].derelict);
+        assert_eq!(test.g.read::<EventLog>().len(), n_events + 1);
+    }
+
+    #[test]
+    fn nothing_grows_near_derelicts() {
+        let derelicts = [vec2(0.0, 0.0)];
+        assert!(near_derelict(&derelicts, vec2(10.0, 10.0)));
+        assert!(!near_derelict(
+            &derelicts,
+            vec2(DERELICT_BLIGHT_RADIUS + 1.0, 0.0)
+        ));
+        assert!(!near_derelict(&[], Vec2::ZERO));
+    }
+}
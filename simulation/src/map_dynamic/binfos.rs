@@ -40,6 +40,15 @@ impl BuildingInfos {
         self.owners.insert(soul, building);
     }
 
+    pub fn remove_owner(&mut self, building: BuildingID) {
+        let Some(x) = self.get_mut(building) else {
+            return;
+        };
+        if let Some(soul) = x.owner.take() {
+            self.owners.remove(&soul);
+        }
+    }
+
     pub fn owner(&self, building: BuildingID) -> Option<SoulID> {
         self.assignment.get(building).and_then(|x| x.owner)
     }
@@ -1,8 +1,8 @@
 use crate::map::{BuildingKind, ElectricityNetworkID, Map};
 use crate::map_dynamic::BuildingInfos;
 use crate::utils::resources::Resources;
-use crate::{SoulID, World};
-use prototypes::Power;
+use crate::{SimulationOptions, SoulID, World};
+use prototypes::{GameTime, Power, Season};
 use serde::Deserialize;
 use slotmapd::__impl::Serialize;
 use std::collections::BTreeMap;
@@ -29,6 +29,20 @@ impl ElectricityFlow {
     }
 }
 
+/// Power used by a house outside of the heating season
+const HOUSE_POWER: Power = Power::new(100);
+/// Extra power used by houses for heating in the middle of winter, as a share of their normal use
+const HEATING_EXTRA: f64 = 0.5;
+
+/// Multiplier of the power used by houses, raised by heating during the cold half of the year
+pub fn heating_factor(time: &GameTime, season_days: u32) -> f64 {
+    let Some(progress) = time.year_progress(season_days) else {
+        return 1.0;
+    };
+    let cold = (Season::coldness(progress) - 0.5).max(0.0) * 2.0;
+    1.0 + HEATING_EXTRA * cold as f64
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct NetworkFlow {
     pub consumed_power: Power,
@@ -46,6 +60,8 @@ pub fn electricity_flow_system(world: &mut World, resources: &mut Resources) {
     let map = resources.read::<Map>();
     let binfos = resources.read::<BuildingInfos>();
     let mut flow = resources.write::<ElectricityFlow>();
+    let time = resources.read::<GameTime>();
+    let heating = heating_factor(&time, resources.read::<SimulationOptions>().season_days);
 
     flow.flowmap.clear();
 
@@ -58,7 +74,7 @@ pub fn electricity_flow_system(world: &mut World, resources: &mut Resources) {
 
             match building.kind {
                 BuildingKind::House => {
-                    consumed_power += Power::new(100);
+                    consumed_power += HOUSE_POWER * heating;
                 }
                 BuildingKind::GoodsCompany(comp) => {
                     let proto = comp.prototype();
@@ -76,6 +92,14 @@ pub fn electricity_flow_system(world: &mut World, resources: &mut Resources) {
                     produced_power += proto.power_production.unwrap_or(Power::ZERO) * productivity;
                 }
                 BuildingKind::RailFreightStation(_) => {}
+                BuildingKind::Dock(_) => {}
+                BuildingKind::Warehouse(_) => {}
+                BuildingKind::ServiceDepot(_) => {}
+                BuildingKind::Leisure(id) => {
+                    if building.kind.is_open(&time.daytime) {
+                        consumed_power += id.prototype().power_consumption.unwrap_or(Power::ZERO);
+                    }
+                }
                 BuildingKind::TrainStation => {}
                 BuildingKind::ExternalTrading => {}
             }
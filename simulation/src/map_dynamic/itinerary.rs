@@ -1,4 +1,5 @@
-use crate::map::{Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
+use crate::map::{LaneID, Map, PathKind, Pathfinder, Traversable, TraverseDirection, TraverseKind};
+use crate::map_dynamic::{PathHandle, PathJobs, PathPoll};
 use crate::utils::resources::Resources;
 use crate::world::TrainID;
 use crate::World;
@@ -40,6 +41,18 @@ pub enum ItineraryKind {
         dest: Vec3,
         wait_ticks: u16,
     },
+    /// Waiting for the path requested to the workers
+    WaitForPath {
+        kind: PathKind,
+        dest: Vec3,
+        handle: PathHandle,
+    },
+}
+
+/// Where a route starts, if it is not on the lane of its destination
+enum RouteStart {
+    Local(Itinerary),
+    Path(Traversable, LaneID),
 }
 
 #[derive(Debug, Serialize, Deserialize, Inspect)]
@@ -52,6 +65,9 @@ pub struct Route {
 
 pub const OBJECTIVE_OK_DIST: f32 = 3.0;
 
+/// Ticks before trying again when no path was found
+const REROUTE_WAIT_TICKS: u16 = 200;
+
 impl Itinerary {
     pub const NONE: Self = Self {
         kind: ItineraryKind::None,
@@ -99,15 +115,47 @@ impl Itinerary {
         map: &Map,
         pathkind: PathKind,
     ) -> Option<Itinerary> {
+        match Self::route_start(start, end, map, pathkind)? {
+            RouteStart::Local(it) => Some(it),
+            RouteStart::Path(cur, end_lane) => {
+                let path = pathkind.path(map.routing_graph(), tick, cur, end_lane)?;
+                Self::from_path(path, start, end, map, pathkind)
+            }
+        }
+    }
+
+    /// Like [`Itinerary::route`], but the path is found by the workers while the itinerary waits
+    pub fn route_async(
+        tick: Tick,
+        start: Vec3,
+        end: Vec3,
+        map: &Map,
+        pathkind: PathKind,
+        jobs: &mut PathJobs,
+    ) -> Option<Itinerary> {
+        Some(match Self::route_start(start, end, map, pathkind)? {
+            RouteStart::Local(it) => it,
+            RouteStart::Path(cur, end_lane) => Self {
+                kind: ItineraryKind::WaitForPath {
+                    kind: pathkind,
+                    dest: end,
+                    handle: jobs.request(map, tick, pathkind, cur, end_lane),
+                },
+                reversed_local_path: Default::default(),
+            },
+        })
+    }
+
+    fn route_start(start: Vec3, end: Vec3, map: &Map, pathkind: PathKind) -> Option<RouteStart> {
         let start_lane = pathkind.nearest_lane(map, start)?;
         let end_lane = pathkind.nearest_lane(map, end)?;
 
-        let mut cur = Traversable::new(TraverseKind::Lane(start_lane), TraverseDirection::Forward);
+        let cur = Traversable::new(TraverseKind::Lane(start_lane), TraverseDirection::Forward);
 
         if start_lane == end_lane {
             if let Some(mut p) = pathkind.local_route(map, start_lane, start, end) {
                 p.reverse();
-                return Some(Itinerary {
+                return Some(RouteStart::Local(Itinerary {
                     kind: ItineraryKind::Route(
                         Route {
                             reversed_route: vec![],
@@ -117,15 +165,25 @@ impl Itinerary {
                         pathkind,
                     ),
                     reversed_local_path: p.into_vec(),
-                });
+                }));
             }
         }
 
-        let mut reversed_route: Vec<Traversable> = pathkind
-            .path(map, tick, cur, end_lane)?
-            .into_iter()
-            .rev()
-            .collect();
+        Some(RouteStart::Path(cur, end_lane))
+    }
+
+    /// Follows a path found from the lane nearest to `start`
+    fn from_path(
+        path: Vec<Traversable>,
+        start: Vec3,
+        end: Vec3,
+        map: &Map,
+        pathkind: PathKind,
+    ) -> Option<Itinerary> {
+        let mut cur = *path.first()?;
+        let start_lane = cur.destination_lane();
+
+        let mut reversed_route: Vec<Traversable> = path.into_iter().rev().collect();
 
         reversed_route.pop(); // Remove start
 
@@ -204,6 +262,7 @@ impl Itinerary {
         tick: Tick,
         time: u32,
         map: &Map,
+        jobs: &mut PathJobs,
     ) -> Vec3 {
         while let Some(p) = self.get_point() {
             let dist = position.distance(p);
@@ -245,12 +304,28 @@ impl Itinerary {
                 *wait_ticks -= 1;
                 return position;
             }
-            *self = unwrap_or!(Self::route(tick, position, dest, map, kind), {
-                *wait_ticks = 200;
+            *self = unwrap_or!(Self::route_async(tick, position, dest, map, kind, jobs), {
+                *wait_ticks = REROUTE_WAIT_TICKS;
                 return position;
             });
         }
 
+        if let ItineraryKind::WaitForPath { kind, dest, handle } = self.kind {
+            let PathPoll::Ready(path) = jobs.poll(tick, handle) else {
+                return position;
+            };
+            *self = path
+                .and_then(|path| Self::from_path(path, position, dest, map, kind))
+                .unwrap_or_else(|| Self {
+                    kind: ItineraryKind::WaitForReroute {
+                        kind,
+                        dest,
+                        wait_ticks: REROUTE_WAIT_TICKS,
+                    },
+                    reversed_local_path: Default::default(),
+                });
+        }
+
         position
     }
 
@@ -278,12 +353,92 @@ impl Itinerary {
     pub fn end_pos(&self) -> Option<Vec3> {
         match self.kind {
             ItineraryKind::None => None,
-            ItineraryKind::WaitUntil(_) | ItineraryKind::WaitForReroute { .. } => None,
+            ItineraryKind::WaitUntil(_)
+            | ItineraryKind::WaitForReroute { .. }
+            | ItineraryKind::WaitForPath { .. } => None,
             ItineraryKind::Simple(e) => Some(e),
             ItineraryKind::Route(ref r, _) => Some(r.end_pos),
         }
     }
 
+    /// Length left to travel from the given position, None when not following a route.
+    /// A straight line while the path is being found.
+    pub fn remaining_length(&self, pos: Vec3, map: &Map) -> Option<f32> {
+        let r = match self.kind {
+            ItineraryKind::Route(ref r, _) => r,
+            ItineraryKind::WaitForReroute { dest, .. }
+            | ItineraryKind::WaitForPath { dest, .. } => return Some(pos.distance(dest)),
+            _ => return None,
+        };
+
+        let mut length = 0.0;
+        let mut last = pos;
+        for &p in self.reversed_local_path.iter().rev() {
+            length += last.distance(p);
+            last = p;
+        }
+        for t in &r.reversed_route {
+            length += t.kind.length(map.lanes(), map.intersections())?;
+        }
+        Some(length)
+    }
+
+    /// Seconds left on the route when driving at the speed limits.
+    /// The turns are driven at the limit of the lane they lead to.
+    pub fn remaining_driving_time(&self, pos: Vec3, map: &Map) -> Option<f32> {
+        let r = self.get_route()?;
+        let speed = |kind: TraverseKind| {
+            let lane = match kind {
+                TraverseKind::Lane(id) => id,
+                TraverseKind::Turn(id) => id.dst,
+            };
+            Some(map.effective_speed_limit(map.lanes().get(lane)?))
+        };
+
+        let mut local = 0.0;
+        let mut last = pos;
+        for &p in self.reversed_local_path.iter().rev() {
+            local += last.distance(p);
+            last = p;
+        }
+        let mut time = local / speed(r.cur.kind)?;
+        for t in &r.reversed_route {
+            time += t.kind.length(map.lanes(), map.intersections())? / speed(t.kind)?;
+        }
+        Some(time)
+    }
+
+    /// The path left to follow from the given position up to the destination.
+    /// A straight line while the path is being found.
+    pub fn remaining_path(&self, pos: Vec3, map: &Map) -> Vec<Vec3> {
+        let mut points = vec![pos];
+        points.extend(self.reversed_local_path.iter().rev());
+
+        let r = match self.kind {
+            ItineraryKind::Route(ref r, _) => r,
+            ItineraryKind::WaitForReroute { dest, .. }
+            | ItineraryKind::WaitForPath { dest, .. } => {
+                points.push(dest);
+                return points;
+            }
+            _ => return points,
+        };
+
+        for (i, t) in r.reversed_route.iter().enumerate().rev() {
+            let Some(l) = t.points(map) else {
+                continue;
+            };
+            if i == 0 {
+                // the last lane is only followed up to the destination
+                let to_cut = l.length() - l.length_at_proj(l.project(r.end_pos));
+                points.extend(l.cut(0.0, to_cut).iter());
+                continue;
+            }
+            points.extend(l.iter());
+        }
+        points
+    }
+
     pub fn remaining_points(&self) -> usize {
         self.reversed_local_path.len()
     }
@@ -291,7 +446,7 @@ impl Itinerary {
     pub fn is_terminal(&self) -> bool {
         match &self.kind {
             ItineraryKind::None | ItineraryKind::WaitUntil(_) => true,
-            ItineraryKind::WaitForReroute { .. } => false,
+            ItineraryKind::WaitForReroute { .. } | ItineraryKind::WaitForPath { .. } => false,
             ItineraryKind::Simple(_) => self.remaining_points() <= 1,
             ItineraryKind::Route(Route { reversed_route, .. }, _) => {
                 reversed_route.is_empty() && self.remaining_points() <= 1
@@ -310,7 +465,8 @@ impl Itinerary {
         match self.kind {
             ItineraryKind::None
             | ItineraryKind::WaitUntil(_)
-            | ItineraryKind::WaitForReroute { .. } => None,
+            | ItineraryKind::WaitForReroute { .. }
+            | ItineraryKind::WaitForPath { .. } => None,
             ItineraryKind::Simple(e) => Some(e),
             ItineraryKind::Route(Route { end_pos, .. }, _) => Some(end_pos),
         }
@@ -321,7 +477,8 @@ impl Itinerary {
             ItineraryKind::None
             | ItineraryKind::WaitUntil(_)
             | ItineraryKind::Simple(_)
-            | ItineraryKind::WaitForReroute { .. } => None,
+            | ItineraryKind::WaitForReroute { .. }
+            | ItineraryKind::WaitForPath { .. } => None,
             ItineraryKind::Route(Route { cur, .. }, _) => Some(cur),
         }
     }
@@ -333,6 +490,52 @@ impl Itinerary {
         }
     }
 
+    /// The traversable after the current one
+    pub fn next_travers(&self) -> Option<&Traversable> {
+        self.get_route()?.reversed_route.last()
+    }
+
+    /// Expected seconds spent waiting at the crosswalks left on the route
+    pub fn crossing_wait(&self, map: &Map) -> f32 {
+        let Some(r) = self.get_route() else {
+            return 0.0;
+        };
+        r.reversed_route
+            .iter()
+            .chain(std::iter::once(&r.cur))
+            .filter_map(|t| match t.kind {
+                TraverseKind::Turn(id) => Some(id),
+                TraverseKind::Lane(_) => None,
+            })
+            .filter(|id| {
+                map.intersections()
+                    .get(id.parent)
+                    .and_then(|i| i.find_turn(*id))
+                    .map_or(false, |t| t.kind.is_crosswalk())
+            })
+            .map(|id| {
+                id.crossing_control(map.lanes(), map.roads())
+                    .expected_crossing_wait()
+            })
+            .sum()
+    }
+
+    /// Whether the route goes through the lane further on
+    pub fn uses_lane(&self, lane: LaneID) -> bool {
+        self.get_route().map_or(false, |r| {
+            r.reversed_route
+                .iter()
+                .any(|t| t.kind == TraverseKind::Lane(lane))
+        })
+    }
+
+    /// Throws the route away so that a new one is computed
+    pub fn reroute(&mut self) {
+        if let ItineraryKind::Route(ref r, pathkind) = self.kind {
+            *self = Self::wait_for_reroute(pathkind, r.end_pos);
+        }
+    }
+
     pub fn local_path(&self) -> &[Vec3] {
         &self.reversed_local_path
     }
@@ -344,7 +547,7 @@ impl Itinerary {
     pub fn has_ended(&self, time: f64) -> bool {
         match self.kind {
             ItineraryKind::WaitUntil(x) => time > x,
-            ItineraryKind::WaitForReroute { .. } => false,
+            ItineraryKind::WaitForReroute { .. } | ItineraryKind::WaitForPath { .. } => false,
             ItineraryKind::Route(
                 Route {
                     ref reversed_route, ..
@@ -382,6 +585,9 @@ impl Inspect<ItineraryKind> for ItineraryKind {
             ItineraryKind::WaitForReroute { wait_ticks, .. } => {
                 ui.label(format!("wait for reroute: {wait_ticks}"));
             }
+            ItineraryKind::WaitForPath { .. } => {
+                ui.label(format!("wait for path {label}"));
+            }
         };
     }
 
@@ -407,6 +613,9 @@ impl Inspect<ItineraryKind> for ItineraryKind {
             ItineraryKind::WaitForReroute { wait_ticks, .. } => {
                 ui.label(format!("wait for reroute: {wait_ticks}"));
             }
+            ItineraryKind::WaitForPath { .. } => {
+                ui.label(format!("wait for path {label}"));
+            }
         };
         false
     }
@@ -417,10 +626,11 @@ pub fn itinerary_update(world: &mut World, resources: &mut Resources) {
     let time = &*resources.read::<GameTime>();
     let map = &*resources.read::<Map>();
     let tick = resources.read::<GameTime>().tick;
+    let jobs = &mut *resources.write::<PathJobs>();
 
     world.query_it_trans_speed().for_each(
         |(it, trans, speed): (&mut Itinerary, &mut Transform, f32)| {
-            trans.pos = it.update(trans.pos, speed * DELTA, tick, time.seconds, map);
+            trans.pos = it.update(trans.pos, speed * DELTA, tick, time.seconds, map, jobs);
         },
     );
 
@@ -1,13 +1,31 @@
+mod abandonment;
 mod binfos;
 mod dispatch;
 mod electricity;
 mod itinerary;
 mod parking;
+mod path_jobs;
+mod road_wear;
+mod roadworks;
 mod router;
+mod tree_growth;
+mod trip_legs;
+mod walkability;
+mod water_balance;
+mod zone_growth;
 
+pub use abandonment::*;
 pub use binfos::*;
 pub use dispatch::*;
 pub use electricity::*;
 pub use itinerary::*;
 pub use parking::*;
+pub use path_jobs::*;
+pub use road_wear::*;
+pub use roadworks::*;
 pub use router::*;
+pub use tree_growth::*;
+pub use trip_legs::*;
+pub use walkability::*;
+pub use water_balance::*;
+pub use zone_growth::*;
@@ -0,0 +1,324 @@
+//! Paths are found by a pool of workers so long routes don't slow down the tick.
+//! A request gives a handle, and the result is given back a fixed number of ticks later whatever
+//! the time the workers took, so the simulation stays deterministic.
+//! The workers read a copy of the routing graph taken at the start of a tick, replaced when the
+//! roads change. Saving waits for the paths being computed so they are loaded back identical.
+
+use crate::map::{
+    LaneID, Map, MapSubscriber, PathKind, Pathfinder, RoutingSnapshot, Traversable, UpdateType,
+};
+use crate::utils::resources::Resources;
+use crate::World;
+use prototypes::{GameTime, Tick};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// Ticks between a request and its result
+pub const PATH_DELAY: u64 = 10;
+
+/// Ticks after which a result nobody asked for is dropped
+const RESULT_LIFETIME: u64 = 500;
+
+static INLINE_PATHS: AtomicBool = AtomicBool::new(false);
+
+/// Finds the paths inside the tick instead of on the workers, to measure what the workers save.
+/// The results are given back at the same ticks either way.
+pub fn set_inline_paths(inline: bool) {
+    INLINE_PATHS.store(inline, Ordering::Relaxed);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PathHandle(u64);
+
+pub type PathResult = Option<Vec<Traversable>>;
+
+#[derive(Debug, PartialEq)]
+pub enum PathPoll {
+    /// The result is given back at a later tick, meanwhile the requester should wait
+    Pending,
+    /// The path found, None if there is none or the request is unknown
+    Ready(PathResult),
+}
+
+pub struct PathJobs {
+    next_handle: u64,
+    /// Tick at which the result of each request is given back
+    due: BTreeMap<PathHandle, Tick>,
+    snapshot: Option<Arc<RoutingSnapshot>>,
+    road_changes: Option<MapSubscriber>,
+    sender: Sender<(PathHandle, PathResult)>,
+    /// Behind a mutex so saving can wait for the workers
+    inbox: Mutex<Inbox>,
+}
+
+struct Inbox {
+    receiver: Receiver<(PathHandle, PathResult)>,
+    /// Results received from the workers
+    results: BTreeMap<PathHandle, PathResult>,
+}
+
+impl Inbox {
+    /// Receives results until the one of the handle is there, false if the workers are gone
+    fn wait_for(&mut self, handle: PathHandle) -> bool {
+        while !self.results.contains_key(&handle) {
+            let Ok((h, path)) = self.receiver.recv() else {
+                return false;
+            };
+            self.results.insert(h, path);
+        }
+        true
+    }
+}
+
+impl Default for PathJobs {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            next_handle: 0,
+            due: Default::default(),
+            snapshot: None,
+            road_changes: None,
+            sender,
+            inbox: Mutex::new(Inbox {
+                receiver,
+                results: Default::default(),
+            }),
+        }
+    }
+}
+
+impl PathJobs {
+    /// Replaces the copy of the routing graph if the roads changed and drops the old results.
+    /// Must be called at the start of the tick, before any request.
+    pub fn begin_tick(&mut self, map: &Map, tick: Tick) {
+        let changed = match self.road_changes {
+            Some(ref mut sub) => {
+                let cleared = sub.take_cleared();
+                sub.take_updated_chunks().count() > 0 || cleared
+            }
+            None => true,
+        };
+        if changed || self.snapshot.is_none() {
+            if self.road_changes.is_none() {
+                self.road_changes = Some(map.subscribe_multi(&[
+                    UpdateType::RoadGeometry,
+                    UpdateType::IntersectionControl,
+                    UpdateType::RoadSurface,
+                ]));
+            }
+            self.snapshot = Some(Arc::new(RoutingSnapshot::new(map)));
+        }
+
+        self.due.retain(|_, due| due.0 + RESULT_LIFETIME >= tick.0);
+        let inbox = self.inbox.get_mut().unwrap();
+        while let Ok((handle, path)) = inbox.receiver.try_recv() {
+            inbox.results.insert(handle, path);
+        }
+        let due = &self.due;
+        inbox.results.retain(|handle, _| due.contains_key(handle));
+    }
+
+    /// Sends the request to the workers, its result can be polled [`PATH_DELAY`] ticks later
+    pub fn request(
+        &mut self,
+        map: &Map,
+        tick: Tick,
+        kind: PathKind,
+        start: Traversable,
+        end: LaneID,
+    ) -> PathHandle {
+        let snapshot = self
+            .snapshot
+            .get_or_insert_with(|| Arc::new(RoutingSnapshot::new(map)))
+            .clone();
+
+        let handle = PathHandle(self.next_handle);
+        self.next_handle += 1;
+        self.due.insert(handle, Tick(tick.0 + PATH_DELAY));
+
+        let sender = self.sender.clone();
+        if INLINE_PATHS.load(Ordering::Relaxed) {
+            let path = kind.path(snapshot.graph(), tick, start, end);
+            let _ = sender.send((handle, path));
+            return handle;
+        }
+        rayon::spawn(move || {
+            profiling::scope!("map_dynamic::path_job");
+            let path = kind.path(snapshot.graph(), tick, start, end);
+            let _ = sender.send((handle, path));
+        });
+
+        handle
+    }
+
+    /// The result of the request once its tick is reached.
+    /// Waits for the workers if they are late, so every client gets it at the same tick.
+    pub fn poll(&mut self, tick: Tick, handle: PathHandle) -> PathPoll {
+        let Some(&due) = self.due.get(&handle) else {
+            return PathPoll::Ready(None);
+        };
+        if tick < due {
+            return PathPoll::Pending;
+        }
+        self.due.remove(&handle);
+
+        let inbox = self.inbox.get_mut().unwrap();
+        inbox.wait_for(handle);
+        PathPoll::Ready(inbox.results.remove(&handle).flatten())
+    }
+
+    /// Number of requests whose result was not given back yet
+    pub fn pending(&self) -> usize {
+        self.due.len()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedPathJobs {
+    next_handle: u64,
+    pending: Vec<(PathHandle, Tick, PathResult)>,
+}
+
+impl From<&PathJobs> for SerializedPathJobs {
+    fn from(jobs: &PathJobs) -> Self {
+        let mut inbox = jobs.inbox.lock().unwrap();
+        Self {
+            next_handle: jobs.next_handle,
+            pending: jobs
+                .due
+                .iter()
+                .map(|(&handle, &due)| {
+                    inbox.wait_for(handle);
+                    (handle, due, inbox.results.get(&handle).cloned().flatten())
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<SerializedPathJobs> for PathJobs {
+    fn from(s: SerializedPathJobs) -> Self {
+        let mut jobs = Self {
+            next_handle: s.next_handle,
+            ..Default::default()
+        };
+        let inbox = jobs.inbox.get_mut().unwrap();
+        for (handle, due, path) in s.pending {
+            jobs.due.insert(handle, due);
+            inbox.results.insert(handle, path);
+        }
+        jobs
+    }
+}
+
+defer_serialize!(PathJobs, SerializedPathJobs);
+
+pub fn path_jobs_system(_: &mut World, resources: &mut Resources) {
+    profiling::scope!("map_dynamic::path_jobs_system");
+    let map = &*resources.read::<Map>();
+    let tick = resources.read::<GameTime>().tick;
+    resources.write::<PathJobs>().begin_tick(map, tick);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::procgen::load_parismap;
+    use crate::map::{LaneKind, TraverseDirection, TraverseKind};
+    use common::saveload::{Bincode, Encoder};
+    use std::time::{Duration, Instant};
+
+    fn lane_pairs(map: &Map, n: usize) -> Vec<(Traversable, LaneID)> {
+        let driving: Vec<LaneID> = map
+            .lanes()
+            .iter()
+            .filter(|(_, l)| l.kind == LaneKind::Driving)
+            .map(|(id, _)| id)
+            .collect();
+        let len = driving.len();
+        (0..n)
+            .map(|i| {
+                let start = driving[(i * 7) % len];
+                let end = driving[(i * 13 + len / 2) % len];
+                (
+                    Traversable::new(TraverseKind::Lane(start), TraverseDirection::Forward),
+                    end,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn results_are_given_back_at_their_tick() {
+        let mut map = Map::default();
+        load_parismap(&mut map);
+        let pairs = lane_pairs(&map, 20);
+
+        let mut jobs = PathJobs::default();
+        let tick = Tick(1);
+        jobs.begin_tick(&map, tick);
+        let handles: Vec<_> = pairs
+            .iter()
+            .map(|&(start, end)| jobs.request(&map, tick, PathKind::Vehicle, start, end))
+            .collect();
+
+        let early = Tick(tick.0 + PATH_DELAY - 1);
+        for &h in &handles {
+            assert_eq!(jobs.poll(early, h), PathPoll::Pending);
+        }
+
+        // Paths being computed survive a save
+        let mut jobs: PathJobs = Bincode::decode(&Bincode::encode(&jobs).unwrap()).unwrap();
+        assert_eq!(jobs.pending(), pairs.len());
+
+        let due = Tick(tick.0 + PATH_DELAY);
+        for (&h, &(start, end)) in handles.iter().zip(&pairs) {
+            let expected = PathKind::Vehicle.path(map.routing_graph(), tick, start, end);
+            assert_eq!(jobs.poll(due, h), PathPoll::Ready(expected));
+        }
+        assert_eq!(jobs.pending(), 0);
+    }
+
+    #[test]
+    fn bench_worst_tick() {
+        let mut map = Map::default();
+        load_parismap(&mut map);
+        let pairs = lane_pairs(&map, 200);
+        let tick = Tick(1);
+
+        let start = Instant::now();
+        for &(start, end) in &pairs {
+            PathKind::Vehicle.path(map.routing_graph(), tick, start, end);
+        }
+        let inline = start.elapsed();
+
+        let mut jobs = PathJobs::default();
+        jobs.begin_tick(&map, tick);
+
+        let start = Instant::now();
+        let handles: Vec<_> = pairs
+            .iter()
+            .map(|&(start, end)| jobs.request(&map, tick, PathKind::Vehicle, start, end))
+            .collect();
+        let request_tick = start.elapsed();
+
+        // The ticks in between, at normal speed
+        std::thread::sleep(Duration::from_millis(PATH_DELAY * 20));
+
+        let start = Instant::now();
+        for h in handles {
+            jobs.poll(Tick(tick.0 + PATH_DELAY), h);
+        }
+        let result_tick = start.elapsed();
+
+        println!(
+            "{} paths, worst tick inline: {:?}, with jobs: {:?}",
+            pairs.len(),
+            inline,
+            request_tick.max(result_tick)
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use slotmapd::Key;
+
+use prototypes::{GameTime, Money, TICKS_PER_SECOND};
+
+use crate::economy::Government;
+use crate::map::{Environment, Road, RoadCondition, RoadID, Traversable, TraverseKind};
+use crate::rules::GameRules;
+use crate::transportation::VehicleKind;
+use crate::Simulation;
+
+/// Ticks between two samples of the traffic
+const WEAR_SAMPLE_INTERVAL: u64 = TICKS_PER_SECOND;
+/// Samples of a car driving on a road needed on average to wear it by one step
+const SAMPLES_PER_WEAR: f32 = 100.0;
+/// Share of the construction cost paid to repair a broken road
+pub const REPAIR_COST_SHARE: f64 = 0.2;
+/// Days of maintenance spending kept for the budget
+pub const SPENDING_HISTORY: usize = 30;
+
+/// How much a vehicle wears the road compared to a car
+pub fn wear_weight(kind: VehicleKind) -> f32 {
+    match kind {
+        VehicleKind::Car => 1.0,
+        VehicleKind::Bus => 4.0,
+        VehicleKind::Truck => 6.0,
+    }
+}
+
+/// Price of bringing the road back to new
+pub fn repair_cost(road: &Road, env: &Environment) -> Money {
+    Government::built_road_cost(road, env) * (REPAIR_COST_SHARE * road.wear as f64 / 255.0)
+}
+
+/// Daily repairs of the worst roads paid by the government
+#[derive(Serialize, Deserialize)]
+pub struct RoadMaintenance {
+    /// Most money spent on repairs each day
+    pub daily_budget: Money,
+    /// Money spent on repairs during the last days, the current day last
+    pub spent: VecDeque<Money>,
+    /// Day of the last repairs
+    day: i32,
+}
+
+impl Default for RoadMaintenance {
+    fn default() -> Self {
+        Self {
+            daily_budget: Money::new_bucks(500),
+            spent: VecDeque::new(),
+            day: 0,
+        }
+    }
+}
+
+/// Wears the roads under the traffic and repairs them once a day
+pub(crate) fn road_wear_system(sim: &mut Simulation) {
+    profiling::scope!("map_dynamic::road_wear_system");
+    if sim.get_tick() % WEAR_SAMPLE_INTERVAL != 0 {
+        return;
+    }
+    wear_roads(sim);
+
+    let day = sim.read::<GameTime>().daytime.day;
+    if sim.read::<RoadMaintenance>().day == day {
+        return;
+    }
+    let spent = repair_roads(sim);
+
+    let mut maintenance = sim.write::<RoadMaintenance>();
+    maintenance.day = day;
+    maintenance.spent.push_back(spent);
+    if maintenance.spent.len() > SPENDING_HISTORY {
+        maintenance.spent.pop_front();
+    }
+}
+
+/// Each moving vehicle has a chance to wear the road it drives on, heavier vehicles more so
+fn wear_roads(sim: &mut Simulation) {
+    let tick = sim.get_tick();
+    let wear_rate = sim.read::<GameRules>().road_wear;
+    let mut steps: BTreeMap<RoadID, u32> = BTreeMap::new();
+    {
+        let map = sim.map();
+        for (id, v) in sim.world.vehicles.iter() {
+            if v.speed.0 < 1.0 {
+                continue;
+            }
+            let Some(Traversable {
+                kind: TraverseKind::Lane(lane),
+                ..
+            }) = v.it.get_travers()
+            else {
+                continue;
+            };
+            let Some(lane) = map.lanes().get(*lane) else {
+                continue;
+            };
+            let roll = common::rand::randu(common::hash_u64((id.data().as_ffi(), tick)) as u32);
+            if roll * SAMPLES_PER_WEAR < wear_weight(v.vehicle.kind) * wear_rate {
+                *steps.entry(lane.parent).or_default() += 1;
+            }
+        }
+    }
+
+    let mut map = sim.map_mut();
+    for (id, n) in steps {
+        let Some(wear) = map.roads().get(id).map(|r| r.wear) else {
+            continue;
+        };
+        map.set_road_wear(id, wear.saturating_add(n.min(255) as u8));
+    }
+}
+
+/// Repairs the worn roads, worst first, as long as the daily budget and the treasury allow it.
+/// Returns the money spent.
+pub(crate) fn repair_roads(sim: &mut Simulation) -> Money {
+    let budget = sim.read::<RoadMaintenance>().daily_budget;
+    let mut map = sim.map_mut();
+    let mut gvt = sim.write::<Government>();
+
+    let mut worn: Vec<(u8, RoadID, Money)> = map
+        .roads()
+        .values()
+        .filter(|r| r.condition() > RoadCondition::Good)
+        .map(|r| (r.wear, r.id, repair_cost(r, &map.environment)))
+        .collect();
+    worn.sort_by_key(|&(wear, _, _)| Reverse(wear));
+
+    let mut spent = Money::ZERO;
+    for (_, id, cost) in worn {
+        if spent + cost > budget || cost > gvt.money {
+            continue;
+        }
+        spent += cost;
+        gvt.money -= cost;
+        map.set_road_wear(id, 0);
+    }
+    spent
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec3;
+    use prototypes::Money;
+
+    use crate::economy::Government;
+    use crate::map::RoadCondition;
+    use crate::map_dynamic::{repair_roads, RoadMaintenance};
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn worn_roads_are_slower_and_repaired() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        test.build_roads(&[vec3(0.0, 100.0, 0.0), vec3(100.0, 100.0, 0.0)]);
+
+        let (worst, other, lane) = {
+            let map = test.g.map();
+            let mut roads = map.roads().keys();
+            let worst = roads.next().unwrap();
+            let other = roads.next().unwrap();
+            let lane = map.roads()[worst].lanes_iter().next().unwrap().0;
+            (worst, other, lane)
+        };
+
+        {
+            let mut map = test.g.map_mut();
+            map.set_road_wear(worst, 250);
+            map.set_road_wear(other, 100);
+            let l = &map.lanes()[lane];
+            assert_eq!(map.roads()[worst].condition(), RoadCondition::Broken);
+            assert!(map.effective_speed_limit(l) < l.speed_limit);
+        }
+
+        // only enough budget for the worst road
+        let cost = {
+            let map = test.g.map();
+            super::repair_cost(&map.roads()[worst], &map.environment)
+        };
+        test.g.write::<RoadMaintenance>().daily_budget = cost;
+        let money = test.g.read::<Government>().money;
+
+        assert_eq!(repair_roads(&mut test.g), cost);
+        assert!(cost > Money::ZERO);
+        assert_eq!(test.g.read::<Government>().money, money - cost);
+        assert_eq!(test.g.map().roads()[worst].wear, 0);
+        assert_eq!(test.g.map().roads()[other].wear, 100);
+    }
+}
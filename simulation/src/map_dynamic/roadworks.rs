@@ -0,0 +1,124 @@
+use prototypes::TICKS_PER_SECOND;
+
+use crate::event_log::{log_event, EventCategory, EventSubject, Severity};
+use crate::map::RoadID;
+use crate::rules::GameRules;
+use crate::Simulation;
+
+/// Ticks between two advances of the roadworks
+const ROADWORKS_INTERVAL: u64 = TICKS_PER_SECOND;
+
+/// Advances the works on the roads and opens them back once done.
+/// With the instant construction rule the works in progress are done right away,
+/// the construction time rule slows them down or speeds them up otherwise.
+pub(crate) fn roadworks_system(sim: &mut Simulation) {
+    profiling::scope!("map_dynamic::roadworks_system");
+    if sim.get_tick() % ROADWORKS_INTERVAL != 0 {
+        return;
+    }
+    let seconds = {
+        let rules = sim.read::<GameRules>();
+        match rules.builds_instantly() {
+            true => f64::INFINITY,
+            false => 1.0 / rules.construction_time as f64,
+        }
+    };
+
+    let done: Vec<(RoadID, String)> = {
+        let mut map = sim.map_mut();
+        let roads: Vec<RoadID> = map.roadworks().map(|(id, _)| id).collect();
+        roads
+            .into_iter()
+            .filter_map(|id| {
+                let road = map.roads().get(id)?;
+                let label = format!(
+                    "{} of {} completed",
+                    road.roadworks.as_ref()?.kind.label(),
+                    if road.name.is_empty() {
+                        "a road"
+                    } else {
+                        &road.name
+                    }
+                );
+                map.advance_roadworks(id, seconds).then_some((id, label))
+            })
+            .collect()
+    };
+
+    for (id, text) in done {
+        log_event(
+            sim,
+            EventCategory::Construction,
+            Severity::Info,
+            text,
+            Some(EventSubject::Road(id)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec3;
+
+    use crate::map::{LanePatternBuilder, ProjectFilter, RoadworksKind};
+    use crate::rules::GameRules;
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn roadworks_close_lanes_until_done() {
+        let test = TestCtx::new();
+        let road = {
+            let mut map = test.g.map_mut();
+            let a = map.project(vec3(0.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+            let b = map.project(vec3(80.0, 0.0, 0.0), 0.0, ProjectFilter::ALL);
+            let pat = LanePatternBuilder::new().n_lanes(2).build();
+            let (_, road) = map.make_connection(a, b, None, &pat).unwrap();
+            map.start_roadworks(road, RoadworksKind::Construction);
+            road
+        };
+
+        {
+            let map = test.g.map();
+            let r = &map.roads()[road];
+            let closed: Vec<_> = r
+                .lanes_iter()
+                .filter(|&(lane, _)| map.lanes()[lane].closed)
+                .collect();
+            // the outer lane of each direction
+            assert_eq!(closed.len(), 2);
+            assert!(closed.iter().all(|(_, kind)| kind.vehicles()));
+            for (lane, _) in r.lanes_iter() {
+                let l = &map.lanes()[lane];
+                assert!(map.effective_speed_limit(l) < l.speed_limit);
+            }
+        }
+
+        let total = test.g.map().roads()[road].roadworks.as_ref().unwrap().total;
+        assert!(!test.g.map_mut().advance_roadworks(road, total * 0.5));
+        assert!(test.g.map_mut().advance_roadworks(road, total * 0.5));
+
+        let map = test.g.map();
+        let r = &map.roads()[road];
+        assert!(r.roadworks.is_none());
+        assert!(r.lanes_iter().all(|(lane, _)| !map.lanes()[lane].closed));
+    }
+
+    #[test]
+    fn instant_construction_finishes_the_works() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(50.0, 0.0, 0.0)]);
+
+        let road = test.g.map().roads().keys().next().unwrap();
+        test.g
+            .map_mut()
+            .start_roadworks(road, RoadworksKind::Construction);
+        test.tick();
+        assert!(test.g.map().roads()[road].roadworks.is_some());
+
+        test.g.write::<GameRules>().instant_construction = true;
+        for _ in 0..super::ROADWORKS_INTERVAL {
+            test.tick();
+        }
+        assert!(test.g.map().roads()[road].roadworks.is_none());
+    }
+}
@@ -1,4 +1,5 @@
-use crate::map::{BuildingID, Map, PathKind};
+use crate::economy::TripStats;
+use crate::map::{Building, BuildingID, Map, PathKind};
 use crate::map_dynamic::{Itinerary, ParkingManagement, ParkingReserveError, SpotReservation};
 use crate::transportation::TransportGrid;
 use crate::transportation::{put_pedestrian_in_transport_grid, unpark, Location, VehicleState};
@@ -7,6 +8,7 @@ use crate::world::{HumanEnt, HumanID, VehicleEnt, VehicleID};
 use crate::{ParCommandBuffer, World};
 use egui_inspect::Inspect;
 use geom::{Spline3, Transform, Vec3};
+use prototypes::{EntranceKind, GameTime};
 use serde::{Deserialize, Serialize};
 use slotmapd::HopSlotMap;
 
@@ -55,7 +57,10 @@ debug_inspect_impl!(RoutingStep);
 pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("map_dynamic::routing_changed_system");
     let map: &Map = &resources.read();
+    let time: &GameTime = &resources.read();
     let parking: &mut ParkingManagement = &mut resources.write();
+    let trips: &mut TripStats = &mut resources.write();
+    trips.advance(&time.daytime);
 
     world.humans.values_mut().for_each(|h| {
         let router = &mut h.router;
@@ -91,7 +96,12 @@ pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
                         return;
                     }
                 };
-                let door_pos = bobj.door_pos;
+                let driving = match router.vehicle {
+                    Some(v) if Some(v) == router.personal_car => Some(EntranceKind::Vehicle),
+                    Some(_) => Some(EntranceKind::Freight),
+                    None => None,
+                };
+                let door_pos = arrival_entrance(bobj, driving, h.trans.pos);
                 router.steps = match router.steps_to(door_pos, parking, map, loc, &world.vehicles) {
                     Ok(x) => x,
                     Err(e) => {
@@ -104,6 +114,7 @@ pub fn routing_changed_system(world: &mut World, resources: &mut Resources) {
         }
 
         router.cur_dest = router.target_dest;
+        trips.record(&time.daytime);
 
         router.steps.reverse();
     });
@@ -178,7 +189,7 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                 RoutingStep::GetInBuilding(build) => map
                     .buildings()
                     .get(build)
-                    .map(|b| b.door_pos.is_close(pos, 3.0))
+                    .map(|b| b.is_at_entrance(pos, 3.0))
                     .unwrap_or(true),
                 RoutingStep::GetOutBuilding(_) => true,
             };
@@ -245,7 +256,7 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
                     let wpos = map
                         .buildings()
                         .get(build)
-                        .map(|x| x.door_pos)
+                        .map(|x| departure_entrance(x, &h.router.steps, pos))
                         .unwrap_or(pos);
                     walk_outside(body, wpos, cbuf_human, &mut h.location);
                 }
@@ -254,6 +265,24 @@ pub fn routing_update_system(world: &mut World, resources: &mut Resources) {
     })
 }
 
+/// The pedestrian entrance someone coming from `pos` walks in through.
+/// Drivers park near the vehicle entrance and walk from there, the company trucks use the
+/// freight one.
+pub(crate) fn arrival_entrance(b: &Building, driving: Option<EntranceKind>, pos: Vec3) -> Vec3 {
+    let from = driving.map_or(pos, |kind| b.entrance(kind, pos));
+    b.entrance(EntranceKind::Pedestrian, from)
+}
+
+/// The pedestrian entrance on the side of where the next steps go, `steps` being the remaining
+/// ones with the next on top
+pub(crate) fn departure_entrance(b: &Building, steps: &[RoutingStep], pos: Vec3) -> Vec3 {
+    let toward = match steps.last() {
+        Some(RoutingStep::WalkTo(p)) => *p,
+        _ => pos,
+    };
+    b.entrance(EntranceKind::Pedestrian, toward)
+}
+
 fn walk_inside(body: HumanID, h: &mut HumanEnt, cbuf: &ParCommandBuffer<HumanEnt>) {
     if let Some(coll) = h.collider.take() {
         cbuf.exec_ent(body, coll.destroy());
@@ -309,6 +338,16 @@ impl Router {
         self.vehicle = v;
     }
 
+    /// The vehicle used for the next trips
+    pub fn vehicle(&self) -> Option<VehicleID> {
+        self.vehicle
+    }
+
+    /// The steps not started yet, in the order they will be taken
+    pub fn upcoming_steps(&self) -> impl Iterator<Item = &RoutingStep> {
+        self.steps.iter().rev()
+    }
+
     pub(crate) fn clear_steps(&mut self, parking: &mut ParkingManagement) {
         for s in self.steps.drain(..).chain(self.cur_step.take()) {
             if let RoutingStep::Park(_, Some(spot)) = s {
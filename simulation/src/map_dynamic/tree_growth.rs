@@ -0,0 +1,30 @@
+use prototypes::{SECONDS_PER_DAY, TICKS_PER_SECOND};
+
+use crate::map::TerrainChunkID;
+use crate::Simulation;
+
+/// Every terrain chunk grows once in that many ticks
+const TICKS_PER_DAY: u64 = TICKS_PER_SECOND * SECONDS_PER_DAY as u64;
+
+/// Grows the forests a few terrain chunks at a time so that each chunk grows once per game day.
+/// The chunks of a tick only depend on the tick, nothing needs to be saved.
+pub(crate) fn tree_growth_system(sim: &mut Simulation) {
+    profiling::scope!("map_dynamic::tree_growth_system");
+    let tick = sim.get_tick();
+    let day = tick / TICKS_PER_DAY;
+    let in_day = tick % TICKS_PER_DAY;
+
+    let mut map = sim.map_mut();
+    let (w, h) = map.environment.size();
+    let n_chunks = w as u64 * h as u64;
+    if n_chunks == 0 {
+        return;
+    }
+
+    let start = n_chunks * in_day / TICKS_PER_DAY;
+    let end = n_chunks * (in_day + 1) / TICKS_PER_DAY;
+    for i in start..end {
+        let chunk = TerrainChunkID::new_i16((i % w as u64) as i16, (i / w as u64) as i16);
+        map.grow_trees(chunk, common::hash_u64((i, day)));
+    }
+}
@@ -0,0 +1,201 @@
+//! Where an entity is going, for the inspector, the tutorial and the cinematic camera.
+//! The trip is split in legs: walking, driving or riding the rails, each with its path and an
+//! estimate of how long it takes. The leg in progress follows the itinerary of the entity so it
+//! changes when the entity is rerouted, the legs not started yet are routed on the spot.
+
+use geom::Vec3;
+use prototypes::{GameDuration, Tick, SECONDS_PER_REALTIME_SECOND};
+
+use crate::map::{Map, PathKind};
+use crate::map_dynamic::{Itinerary, RoutingStep};
+use crate::souls::commute::DRIVING_SPEED;
+use crate::transportation::Location;
+use crate::world::HumanEnt;
+use crate::{AnyEntity, World};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LegKind {
+    Walk,
+    Drive,
+    Rail,
+}
+
+#[derive(Clone, Debug)]
+pub struct Leg {
+    pub kind: LegKind,
+    /// Path left to follow, from the position of the entity for the leg in progress
+    pub points: Vec<Vec3>,
+    /// Estimated time left on the leg
+    pub duration: GameDuration,
+}
+
+impl Leg {
+    pub fn length(&self) -> f32 {
+        polyline_length(&self.points)
+    }
+
+    /// Where the leg ends
+    pub fn end(&self) -> Option<Vec3> {
+        self.points.last().copied()
+    }
+
+    /// The leg left of the itinerary followed at the given position,
+    /// `speed` is used for walking and when the route is not known yet
+    fn from_itinerary(
+        kind: LegKind,
+        it: &Itinerary,
+        pos: Vec3,
+        speed: f32,
+        map: &Map,
+    ) -> Option<Leg> {
+        if it.is_none_or_wait() {
+            return None;
+        }
+        let points = it.remaining_path(pos, map);
+        let seconds = match kind {
+            LegKind::Walk => None,
+            LegKind::Drive | LegKind::Rail => it.remaining_driving_time(pos, map),
+        };
+        let seconds = match seconds {
+            Some(s) => s * SECONDS_PER_REALTIME_SECOND as f32,
+            None => {
+                let length = it
+                    .remaining_length(pos, map)
+                    .unwrap_or_else(|| polyline_length(&points));
+                length / speed * SECONDS_PER_REALTIME_SECOND as f32 + it.crossing_wait(map)
+            }
+        };
+
+        Some(Leg {
+            kind,
+            points,
+            duration: GameDuration::from_secs(seconds.max(0.0) as u64),
+        })
+    }
+}
+
+fn polyline_length(points: &[Vec3]) -> f32 {
+    points.windows(2).map(|w| w[0].distance(w[1])).sum()
+}
+
+/// The legs left of the trip of the entity, the one in progress first.
+/// Empty for entities that do not move or are not going anywhere.
+/// Routing the legs not started yet takes time, keep the result for a while rather than calling
+/// it every frame.
+pub fn trip_legs(world: &World, map: &Map, tick: Tick, entity: AnyEntity) -> Vec<Leg> {
+    match entity {
+        AnyEntity::HumanID(id) => world
+            .humans
+            .get(id)
+            .map(|h| human_legs(world, map, tick, h))
+            .unwrap_or_default(),
+        AnyEntity::VehicleID(id) => world
+            .vehicles
+            .get(id)
+            .and_then(|v| {
+                Leg::from_itinerary(LegKind::Drive, &v.it, v.trans.pos, DRIVING_SPEED, map)
+            })
+            .into_iter()
+            .collect(),
+        AnyEntity::TrainID(id) => world
+            .trains
+            .get(id)
+            .and_then(|t| {
+                Leg::from_itinerary(LegKind::Rail, &t.it, t.trans.pos, DRIVING_SPEED, map)
+            })
+            .into_iter()
+            .collect(),
+        AnyEntity::WagonID(id) => world
+            .wagons
+            .get(id)
+            .map(|w| trip_legs(world, map, tick, AnyEntity::TrainID(w.itfollower.leader)))
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+fn human_legs(world: &World, map: &Map, tick: Tick, h: &HumanEnt) -> Vec<Leg> {
+    let mut legs = vec![];
+    let current = match h.location {
+        Location::Outside => Leg::from_itinerary(
+            LegKind::Walk,
+            &h.it,
+            h.trans.pos,
+            h.pedestrian.walking_speed,
+            map,
+        ),
+        Location::Vehicle(v) => world.vehicles.get(v).and_then(|v| {
+            Leg::from_itinerary(LegKind::Drive, &v.it, v.trans.pos, DRIVING_SPEED, map)
+        }),
+        Location::Building(_) => None,
+    };
+    let mut from = current.as_ref().and_then(Leg::end).unwrap_or(h.trans.pos);
+    legs.extend(current);
+
+    for step in h.router.upcoming_steps() {
+        let (kind, pathkind, to, speed) = match *step {
+            RoutingStep::WalkTo(to) => (
+                LegKind::Walk,
+                PathKind::Pedestrian,
+                to,
+                h.pedestrian.walking_speed,
+            ),
+            RoutingStep::DriveTo(_, to) => (LegKind::Drive, PathKind::Vehicle, to, DRIVING_SPEED),
+            _ => continue,
+        };
+        let leg = Itinerary::route(tick, from, to, map, pathkind)
+            .and_then(|it| Leg::from_itinerary(kind, &it, from, speed, map))
+            .unwrap_or_else(|| {
+                let seconds = from.distance(to) / speed * SECONDS_PER_REALTIME_SECOND as f32;
+                Leg {
+                    kind,
+                    points: vec![from, to],
+                    duration: GameDuration::from_secs(seconds as u64),
+                }
+            });
+        legs.push(leg);
+        from = to;
+    }
+
+    legs
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::vec3;
+
+    use super::*;
+    use crate::souls::commute::WALKING_SPEED;
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn legs_follow_the_route() {
+        let test = TestCtx::new();
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(200.0, 0.0, 0.0),
+            vec3(400.0, 0.0, 0.0),
+        ]);
+        let map = test.g.map();
+        let (from, to) = (vec3(10.0, 0.0, 0.0), vec3(390.0, 0.0, 0.0));
+
+        let leg = |kind, pathkind, speed| {
+            let it = Itinerary::route(Tick(0), from, to, &map, pathkind).unwrap();
+            Leg::from_itinerary(kind, &it, from, speed, &map).unwrap()
+        };
+        let drive = leg(LegKind::Drive, PathKind::Vehicle, DRIVING_SPEED);
+        let walk = leg(LegKind::Walk, PathKind::Pedestrian, WALKING_SPEED);
+
+        for l in [&drive, &walk] {
+            assert_eq!(l.points[0], from);
+            assert!(
+                l.end().unwrap().xy().distance(to.xy()) < 15.0,
+                "{:?}",
+                l.end()
+            );
+            assert!((l.length() - 380.0).abs() < 30.0, "{}", l.length());
+        }
+        assert!(drive.duration.seconds() > 0.0);
+        assert!(walk.duration.seconds() > drive.duration.seconds() * 3.0);
+    }
+}
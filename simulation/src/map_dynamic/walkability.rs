@@ -0,0 +1,429 @@
+//! How good a place to live each residence is, from the walking distance to the amenities around
+//! it: groceries, schools, leisure, transit and healthcare.
+//! The walks are bounded, so an update of the map can only change the scores of the residences
+//! within that bound of it. The scores are kept and only those residences are scored again.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+use geom::Vec2;
+use prototypes::{AmenityKind, TICKS_PER_SECOND};
+
+use crate::map::{
+    BuildingID, BuildingKind, IntersectionID, LaneKind, Map, RoadID, SubscriberChunkID, UpdateType,
+};
+use crate::Simulation;
+
+/// Ticks between two refreshes of the scores
+const REFRESH_INTERVAL: u64 = TICKS_PER_SECOND;
+/// Residences scored at most per refresh, to spread the work after large changes
+const SCORES_PER_REFRESH: usize = 100;
+/// Residences around a position giving it its land value
+pub const LAND_VALUE_RADIUS: f32 = 300.0;
+/// Updates of the map that can change the walks to the amenities
+const WALK_UPDATES: UpdateType = UpdateType::RoadGeometry
+    .union(UpdateType::BuildingAdded)
+    .union(UpdateType::BuildingRemoved)
+    .union(UpdateType::BuildingChanged);
+
+/// How much an amenity counts in the score
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AmenityWeight {
+    pub weight: f32,
+    /// Walking distance in meters at which the amenity stops counting
+    pub max_distance: f32,
+}
+
+/// The weights of the amenities, the closer an amenity the more of its weight it gives
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalkabilityWeights(pub BTreeMap<AmenityKind, AmenityWeight>);
+
+impl Default for WalkabilityWeights {
+    fn default() -> Self {
+        let w = |weight, max_distance| AmenityWeight {
+            weight,
+            max_distance,
+        };
+        Self(BTreeMap::from([
+            (AmenityKind::Grocery, w(3.0, 800.0)),
+            (AmenityKind::School, w(2.0, 1000.0)),
+            (AmenityKind::Leisure, w(1.0, 1200.0)),
+            (AmenityKind::Transit, w(2.0, 800.0)),
+            (AmenityKind::Healthcare, w(1.0, 1200.0)),
+        ]))
+    }
+}
+
+impl WalkabilityWeights {
+    /// The longest walk that counts, no update of the map further than that changes a score
+    pub fn max_distance(&self) -> f32 {
+        self.0.values().map(|w| w.max_distance).fold(0.0, f32::max)
+    }
+}
+
+/// What one kind of amenity gives to the score of a residence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScorePart {
+    pub amenity: AmenityKind,
+    /// Walking distance to the nearest one in meters, None if there is none within reach
+    pub distance: Option<f32>,
+    pub points: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkScore {
+    /// From 0 to 100
+    pub total: f32,
+    pub parts: Vec<ScorePart>,
+    pos: Vec2,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Walkability {
+    weights: WalkabilityWeights,
+    scores: BTreeMap<BuildingID, WalkScore>,
+    /// Residences to score again
+    dirty: BTreeSet<BuildingID>,
+    /// Revision of the map the dirty residences are up to date with.
+    /// The revisions are not saved, so the tracking starts again when the game is loaded.
+    #[serde(skip)]
+    revision: Option<u64>,
+}
+
+impl Walkability {
+    pub fn weights(&self) -> &WalkabilityWeights {
+        &self.weights
+    }
+
+    /// Changes the weights, every residence is scored again
+    pub fn set_weights(&mut self, weights: WalkabilityWeights) {
+        self.weights = weights;
+        self.dirty.extend(self.scores.keys());
+    }
+
+    pub fn score(&self, id: BuildingID) -> Option<&WalkScore> {
+        self.scores.get(&id)
+    }
+
+    /// Average score of the residences around the position, what living there is worth.
+    /// None when nobody lives around.
+    pub fn land_value(&self, pos: Vec2) -> Option<f32> {
+        let (sum, n) = self
+            .scores
+            .values()
+            .filter(|s| s.pos.is_close(pos, LAND_VALUE_RADIUS))
+            .fold((0.0, 0), |(sum, n), s| (sum + s.total, n + 1));
+        (n > 0).then(|| sum / n as f32)
+    }
+
+    /// How much people want to move to the city, from 0.5 when no residence is walkable to 1.5
+    /// when they all are, 1 before any is scored
+    pub fn immigration_factor(&self) -> f32 {
+        match self.average() {
+            Some(avg) => 0.5 + avg / 100.0,
+            None => 1.0,
+        }
+    }
+
+    /// Average score of the residences of the city
+    pub fn average(&self) -> Option<f32> {
+        if self.scores.is_empty() {
+            return None;
+        }
+        Some(self.scores.values().map(|s| s.total).sum::<f32>() / self.scores.len() as f32)
+    }
+
+    /// Marks the residences an update of the chunks may change as dirty
+    fn invalidate(&mut self, map: &Map, chunks: &[SubscriberChunkID]) {
+        if chunks.is_empty() {
+            return;
+        }
+        let radius = self.weights.max_distance();
+        for (id, b) in map.buildings() {
+            if b.kind != BuildingKind::House {
+                continue;
+            }
+            let pos = b.door_pos.xy();
+            if chunks.iter().any(|c| c.bbox().contains_within(pos, radius)) {
+                self.dirty.insert(id);
+            }
+        }
+    }
+
+    /// Forgets the removed residences and marks the new ones as dirty
+    fn track_residences(&mut self, map: &Map) {
+        let buildings = map.buildings();
+        let is_house = |id: &BuildingID| {
+            buildings
+                .get(*id)
+                .is_some_and(|b| b.kind == BuildingKind::House)
+        };
+        self.scores.retain(|id, _| is_house(id));
+        self.dirty.retain(is_house);
+        for (id, b) in buildings {
+            if b.kind == BuildingKind::House && !self.scores.contains_key(&id) {
+                self.dirty.insert(id);
+            }
+        }
+    }
+
+    /// Finds the dirty residences since the last refresh, and scores some of them
+    fn refresh(&mut self, map: &Map) {
+        let revision = map.revision();
+        if let Some(last) = self.revision {
+            let changed = map.changed_since(last, WALK_UPDATES);
+            self.invalidate(map, &changed);
+        }
+        self.revision = Some(revision);
+        self.track_residences(map);
+
+        if self.dirty.is_empty() {
+            return;
+        }
+        let amenities = amenities(map);
+        for _ in 0..SCORES_PER_REFRESH {
+            let Some(id) = self.dirty.pop_first() else {
+                break;
+            };
+            let Some(score) = score(map, &amenities, &self.weights, id) else {
+                continue;
+            };
+            self.scores.insert(id, score);
+        }
+    }
+}
+
+/// Scores the residences near the changes of the map
+pub(crate) fn walkability_system(sim: &mut Simulation) {
+    profiling::scope!("map_dynamic::walkability_system");
+    if sim.get_tick() % REFRESH_INTERVAL != 0 {
+        return;
+    }
+    let map = sim.map();
+    sim.write::<Walkability>().refresh(&map);
+}
+
+/// An amenity and where it is on the pedestrian network
+struct Amenity {
+    kind: AmenityKind,
+    road: RoadID,
+    pos: Vec2,
+}
+
+fn amenities(map: &Map) -> Vec<Amenity> {
+    map.buildings()
+        .values()
+        .filter(|b| !b.abandoned)
+        .filter_map(|b| {
+            Some(Amenity {
+                kind: b.kind.amenity()?,
+                road: b.connected_road?,
+                pos: b.door_pos.xy(),
+            })
+        })
+        .collect()
+}
+
+fn score(
+    map: &Map,
+    amenities: &[Amenity],
+    weights: &WalkabilityWeights,
+    id: BuildingID,
+) -> Option<WalkScore> {
+    let b = map.buildings().get(id)?;
+    let pos = b.door_pos.xy();
+    let road = b.connected_road?;
+    let bound = weights.max_distance();
+    let dists = walk_distances(map, &[(road, pos)], bound);
+
+    let mut nearest = BTreeMap::<AmenityKind, f32>::new();
+    for a in amenities {
+        let d = match a.road == road {
+            true => a.pos.distance(pos),
+            false => map
+                .roads()
+                .get(a.road)
+                .into_iter()
+                .flat_map(|r| [r.src, r.dst])
+                .filter_map(|i| {
+                    Some(dists.get(&i)? + a.pos.distance(map.intersections().get(i)?.pos.xy()))
+                })
+                .fold(f32::INFINITY, f32::min),
+        };
+        if d > bound {
+            continue;
+        }
+        let best = nearest.entry(a.kind).or_insert(d);
+        *best = best.min(d);
+    }
+
+    let total_weight: f32 = weights.0.values().map(|w| w.weight).sum();
+    let parts: Vec<ScorePart> = weights
+        .0
+        .iter()
+        .map(|(&amenity, w)| {
+            let distance = nearest.get(&amenity).copied();
+            let points = match distance {
+                Some(d) if d < w.max_distance => w.weight * (1.0 - d / w.max_distance),
+                _ => 0.0,
+            };
+            ScorePart {
+                amenity,
+                distance,
+                points: points * 100.0 / total_weight.max(f32::EPSILON),
+            }
+        })
+        .collect();
+
+    Some(WalkScore {
+        total: parts.iter().map(|p| p.points).sum(),
+        parts,
+        pos,
+    })
+}
+
+/// Walking distance in meters from the positions, each on its road, to the intersections
+/// reached within the bound. Only the roads with sidewalks can be walked along.
+pub fn walk_distances(
+    map: &Map,
+    from: &[(RoadID, Vec2)],
+    bound: f32,
+) -> BTreeMap<IntersectionID, f32> {
+    let roads = map.roads();
+    let intersections = map.intersections();
+
+    let mut dists: BTreeMap<IntersectionID, f32> = BTreeMap::new();
+    let mut queue = BinaryHeap::new();
+
+    for &(road, pos) in from {
+        let Some(road) = roads.get(road) else {
+            continue;
+        };
+        for inter in [road.src, road.dst] {
+            let Some(i) = intersections.get(inter) else {
+                continue;
+            };
+            queue.push(Reverse((OrderedFloat(pos.distance(i.pos.xy())), inter)));
+        }
+    }
+
+    while let Some(Reverse((OrderedFloat(d), inter))) = queue.pop() {
+        if d > bound || dists.contains_key(&inter) {
+            continue;
+        }
+        dists.insert(inter, d);
+
+        let Some(i) = intersections.get(inter) else {
+            continue;
+        };
+        for &r in &i.roads {
+            let Some(road) = roads.get(r) else {
+                continue;
+            };
+            if !road.lanes_iter().any(|(_, kind)| kind == LaneKind::Walking) {
+                continue;
+            }
+            let Some(next) = road.other_end(inter) else {
+                continue;
+            };
+            if !dists.contains_key(&next) {
+                queue.push(Reverse((OrderedFloat(d + road.length()), next)));
+            }
+        }
+    }
+
+    dists
+}
+
+/// Walking distance from the amenities of that kind to the intersections reached within the
+/// bound, to draw how far they serve
+pub fn amenity_isochrones(
+    map: &Map,
+    kind: AmenityKind,
+    bound: f32,
+) -> BTreeMap<IntersectionID, f32> {
+    let from: Vec<_> = amenities(map)
+        .into_iter()
+        .filter(|a| a.kind == kind)
+        .map(|a| (a.road, a.pos))
+        .collect();
+    walk_distances(map, &from, bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3};
+
+    use crate::tests::TestCtx;
+
+    use super::*;
+
+    #[test]
+    fn only_the_residences_near_a_change_are_invalidated() {
+        let test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+        test.build_roads(&[vec3(4000.0, 0.0, 0.0), vec3(4200.0, 0.0, 0.0)]);
+        let near = test.build_house_near(vec2(100.0, 10.0));
+        let far = test.build_house_near(vec2(4100.0, 10.0));
+
+        let mut walk = Walkability::default();
+        let map = test.g.map();
+        walk.refresh(&map);
+        assert!(walk.score(near).is_some());
+        assert!(walk.score(far).is_some());
+        assert!(walk.dirty.is_empty());
+
+        walk.invalidate(&map, &[SubscriberChunkID::new(vec2(500.0, 0.0))]);
+        assert!(walk.dirty.contains(&near));
+        assert!(!walk.dirty.contains(&far));
+
+        // a change just beyond the longest walk leaves the residence alone
+        walk.dirty.clear();
+        let beyond = walk.weights().max_distance() + SubscriberChunkID::SIZE_F32;
+        walk.invalidate(&map, &[SubscriberChunkID::new(vec2(-beyond, 0.0))]);
+        assert!(walk.dirty.is_empty());
+    }
+
+    #[test]
+    fn amenities_score_by_walking_distance() {
+        let test = TestCtx::new();
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(300.0, 0.0, 0.0),
+            vec3(600.0, 0.0, 0.0),
+        ]);
+        let house = test.build_house_near(vec2(50.0, 10.0));
+
+        let map = test.g.map();
+        let b = &map.buildings()[house];
+        let amenities = vec![Amenity {
+            kind: AmenityKind::Grocery,
+            road: map
+                .roads()
+                .keys()
+                .find(|&r| r != b.connected_road.unwrap())
+                .unwrap(),
+            pos: vec2(450.0, 10.0),
+        }];
+        let weights = WalkabilityWeights::default();
+        let s = score(&map, &amenities, &weights, house).unwrap();
+
+        let grocery = s
+            .parts
+            .iter()
+            .find(|p| p.amenity == AmenityKind::Grocery)
+            .unwrap();
+        let d = grocery.distance.unwrap();
+        assert!(d > 350.0 && d < 450.0, "{}", d);
+        assert!(grocery.points > 0.0);
+        assert_eq!(s.total, grocery.points);
+        assert!(s
+            .parts
+            .iter()
+            .filter(|p| p.amenity != AmenityKind::Grocery)
+            .all(|p| p.distance.is_none()));
+    }
+}
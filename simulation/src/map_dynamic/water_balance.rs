@@ -0,0 +1,16 @@
+use prototypes::{SECONDS_PER_DAY, TICKS_PER_SECOND};
+
+use crate::rules::GameRules;
+use crate::Simulation;
+
+const TICKS_PER_DAY: u64 = TICKS_PER_SECOND * SECONDS_PER_DAY as u64;
+
+/// Once a day the lakes evaporate or fill with rain toward the water table, if the rules say so.
+/// The sea keeps its level.
+pub(crate) fn water_balance_system(sim: &mut Simulation) {
+    profiling::scope!("map_dynamic::water_balance_system");
+    if sim.get_tick() % TICKS_PER_DAY != 0 || !sim.read::<GameRules>().lake_equalization {
+        return;
+    }
+    sim.map_mut().equalize_water();
+}
@@ -0,0 +1,203 @@
+use geom::{Vec2, OBB};
+use ordered_float::OrderedFloat;
+use prototypes::{GoodsCompanyPrototype, ZoneKind, TICKS_PER_SECOND};
+
+use crate::economy::ZoneDemand;
+use crate::map::{BuildingID, BuildingKind, Lot, LotID, Map, RestrictedAction};
+use crate::map_dynamic::{near_derelict, BuildingInfos, Walkability};
+use crate::transportation::service_call::{ServiceCalls, GARBAGE_OVERFLOW};
+use crate::utils::rand_provider::RandProvider;
+use crate::Simulation;
+
+/// Ticks between two growth steps
+const GROWTH_INTERVAL: u64 = TICKS_PER_SECOND * 10;
+/// Demand consumed by a new building
+pub(crate) const GROWTH_THRESHOLD: f32 = 1.0;
+/// Lots drawn for a new house, it grows on the one with the highest land value
+const HOUSE_CANDIDATES: usize = 4;
+
+/// Grows buildings on painted lots where there is demand for them, away from derelict buildings
+/// and overflowing bins.
+/// Houses grow rather where the land value is high, and faster when the city is walkable.
+/// Also demolishes grown buildings whose zone was painted over.
+pub(crate) fn zone_growth_system(sim: &mut Simulation) {
+    profiling::scope!("map_dynamic::zone_growth_system");
+    if sim.get_tick() % GROWTH_INTERVAL != 0 {
+        return;
+    }
+
+    demolish_abandoned(sim);
+
+    for zone in [
+        ZoneKind::Residential,
+        ZoneKind::Commercial,
+        ZoneKind::Industrial,
+    ] {
+        let attractiveness = match zone {
+            ZoneKind::Residential => sim.read::<Walkability>().immigration_factor(),
+            _ => 1.0,
+        };
+        if sim.read::<ZoneDemand>().get(zone) * attractiveness < GROWTH_THRESHOLD {
+            continue;
+        }
+        let Some(id) = grow(sim, zone) else {
+            continue;
+        };
+        sim.write::<BuildingInfos>().insert(id);
+        *sim.write::<ZoneDemand>().get_mut(zone) -= GROWTH_THRESHOLD;
+    }
+}
+
+/// Demolishes one abandoned building at a time and gives its land back as lots
+fn demolish_abandoned(sim: &mut Simulation) {
+    let mut map = sim.map_mut();
+    let Some(id) = map
+        .buildings()
+        .iter()
+        .find(|(_, b)| b.abandoned)
+        .map(|(id, _)| id)
+    else {
+        return;
+    };
+    let Some(b) = map.remove_building(id) else {
+        return;
+    };
+    if let Some(road) = b.connected_road {
+        Lot::generate_along_road(&mut map, road);
+    }
+}
+
+fn grow(sim: &mut Simulation, zone: ZoneKind) -> Option<BuildingID> {
+    let mut rng = sim.write::<RandProvider>();
+    let mut map = sim.map_mut();
+
+    let calls = sim.read::<ServiceCalls>();
+    // the overflowing bins keep people away as much as the derelicts do
+    let blighted: Vec<Vec2> = map
+        .buildings()
+        .values()
+        .filter(|b| b.derelict || calls.garbage(b.id) >= GARBAGE_OVERFLOW)
+        .map(|b| b.obb.center())
+        .collect();
+    drop(calls);
+    let lots: Vec<LotID> = map
+        .lots()
+        .iter()
+        .filter(|(_, lot)| lot.kind.zone_kind() == Some(zone))
+        .filter(|(_, lot)| !near_derelict(&blighted, lot.shape.center()))
+        .filter(|(_, lot)| {
+            map.restriction_on(&lot.shape, RestrictedAction::Build)
+                .is_none()
+        })
+        .map(|(id, _)| id)
+        .collect();
+    if lots.is_empty() {
+        return None;
+    }
+    let lot_id = match zone {
+        ZoneKind::Residential => {
+            let walkability = sim.read::<Walkability>();
+            (0..HOUSE_CANDIDATES)
+                .map(|_| lots[rng.next_u32() as usize % lots.len()])
+                .max_by_key(|&lot| {
+                    let value = walkability.land_value(map.lots()[lot].shape.center());
+                    OrderedFloat(value.unwrap_or(0.0))
+                })?
+        }
+        _ => lots[rng.next_u32() as usize % lots.len()],
+    };
+
+    let id = if zone == ZoneKind::Residential {
+        map.build_house(lot_id)?
+    } else {
+        build_company(&mut map, lot_id, zone, rng.next_u32())?
+    };
+
+    let b = map.buildings.get_mut(id)?;
+    b.grown_in = Some(zone);
+    log::info!("{:?} grew on {:?}", b.kind, lot_id);
+
+    Some(id)
+}
+
+/// Builds a company of the zone that fits on the lot, `pick` chooses among the fitting ones
+pub(crate) fn build_company(
+    map: &mut Map,
+    lot_id: LotID,
+    zone: ZoneKind,
+    pick: u32,
+) -> Option<BuildingID> {
+    let lot = map.lots().get(lot_id)?;
+    let [_, axis] = lot.shape.axis();
+    let lot_size = axis.mag();
+    let dir = axis / lot_size;
+    let front = lot.shape.center() - dir * lot_size * 0.5;
+    let road = lot.parent;
+
+    let fitting: Vec<&GoodsCompanyPrototype> = GoodsCompanyPrototype::iter()
+        .filter(|comp| comp.zone_kind == Some(zone) && comp.zone.is_none())
+        .filter(|comp| comp.size.w <= lot_size && comp.size.h <= lot_size)
+        // extraction companies are only built by hand on their deposit
+        .filter(|comp| comp.deposit.is_none())
+        .collect();
+    if fitting.is_empty() {
+        return None;
+    }
+    let comp = fitting[pick as usize % fitting.len()];
+
+    let obb = OBB::new(
+        front + dir * comp.size.w * 0.5,
+        dir,
+        comp.size.w,
+        comp.size.h,
+    );
+    map.build_special_building(
+        &obb,
+        BuildingKind::GoodsCompany(comp.id),
+        comp.bgen,
+        None,
+        Some(road),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3, Circle};
+
+    use crate::economy::ZoneDemand;
+    use crate::map::{LotKind, ZoneBrush};
+    use crate::tests::TestCtx;
+    use crate::WorldCommand;
+
+    #[test]
+    fn painted_lot_grows_a_house() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+
+        let n_buildings = test.g.map().buildings().len();
+        let brush = Circle::new(vec2(100.0, 0.0), 50.0);
+
+        test.apply(&[WorldCommand::MapPaintZone {
+            brush: ZoneBrush::Circle(brush),
+            kind: LotKind::Residential,
+        }]);
+        assert!(test
+            .g
+            .map()
+            .lots()
+            .values()
+            .any(|lot| lot.kind == LotKind::Residential));
+
+        test.g.write::<ZoneDemand>().residential = 100.0;
+        for _ in 0..super::GROWTH_INTERVAL {
+            test.tick();
+        }
+
+        let map = test.g.map();
+        assert_eq!(map.buildings().len(), n_buildings + 1);
+        assert!(map
+            .buildings()
+            .values()
+            .any(|b| b.grown_in == Some(prototypes::ZoneKind::Residential)));
+    }
+}
@@ -0,0 +1,220 @@
+//! How strict the game is, chosen when starting a new game.
+//! The systems read the rules instead of hardcoding their values.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{Money, ScenarioRules};
+
+use crate::map_dynamic::DERELICT_AFTER_DAYS;
+use crate::souls::company_lifecycle::CLOSE_AFTER_DAYS;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Rule {
+    StartingMoney,
+    RoadCost,
+    BuildingCost,
+    UtilitiesRequired,
+    RoadWear,
+    BankruptcyDays,
+    DerelictDays,
+    Sandbox,
+    LakeEqualization,
+    InstantConstruction,
+    ConstructionTime,
+    Disasters,
+    IncomeTax,
+}
+
+/// Rules of the game, saved with it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameRules {
+    pub starting_money: Money,
+    /// Multiplies the price of roads and rails, 0 makes them free
+    pub road_cost: f32,
+    /// Multiplies the price of the special buildings, 0 makes them free
+    pub building_cost: f32,
+    /// Companies needing electricity stop during blackouts
+    pub utilities_required: bool,
+    /// Multiplies how fast the traffic wears the roads, 0 disables wear
+    pub road_wear: f32,
+    /// Days in a row losing money before a company closes
+    pub bankruptcy_days: u32,
+    /// Days a building stays empty before it becomes derelict
+    pub derelict_days: u32,
+    /// The restricted areas can be drawn and removed by the players
+    pub sandbox: bool,
+    /// The lakes slowly go back to the water table, evaporating or filling with rain
+    pub lake_equalization: bool,
+    /// The roads are done as soon as they are built, without roadworks
+    pub instant_construction: bool,
+    /// Multiplies how long the roadworks take, 0 makes them instant
+    pub construction_time: f32,
+    /// Buildings can catch fire
+    pub disasters: bool,
+    /// Share of the wages the government takes back from the households
+    pub income_tax: f32,
+    /// Whether the rules can be changed once the game started
+    pub allow_changing: bool,
+    /// Rules set by the scenario, they cannot be changed
+    pub locked: BTreeSet<Rule>,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            starting_money: Money::new_bucks(150_000),
+            road_cost: 1.0,
+            building_cost: 1.0,
+            utilities_required: true,
+            road_wear: 1.0,
+            bankruptcy_days: CLOSE_AFTER_DAYS,
+            derelict_days: DERELICT_AFTER_DAYS,
+            sandbox: false,
+            lake_equalization: false,
+            instant_construction: false,
+            construction_time: 1.0,
+            disasters: true,
+            income_tax: 0.0,
+            allow_changing: false,
+            locked: BTreeSet::new(),
+        }
+    }
+}
+
+impl GameRules {
+    pub fn is_locked(&self, rule: Rule) -> bool {
+        self.locked.contains(&rule)
+    }
+
+    /// Whether the roads are done without roadworks
+    pub fn builds_instantly(&self) -> bool {
+        self.instant_construction || self.construction_time <= 0.0
+    }
+
+    /// Sets and locks the rules imposed by the scenario
+    pub fn lock_scenario(&mut self, starting_money: Money, rules: &ScenarioRules) {
+        self.starting_money = starting_money;
+        self.locked.insert(Rule::StartingMoney);
+        // the restricted areas belong to the scenario
+        self.sandbox = false;
+        self.locked.insert(Rule::Sandbox);
+
+        let mut lock = |rule, set: bool| {
+            if set {
+                self.locked.insert(rule);
+            }
+        };
+        lock(Rule::RoadCost, rules.road_cost.is_some());
+        lock(Rule::BuildingCost, rules.building_cost.is_some());
+        lock(Rule::UtilitiesRequired, rules.utilities_required.is_some());
+        lock(Rule::RoadWear, rules.road_wear.is_some());
+        lock(Rule::BankruptcyDays, rules.bankruptcy_days.is_some());
+        lock(Rule::DerelictDays, rules.derelict_days.is_some());
+        lock(Rule::ConstructionTime, rules.construction_time.is_some());
+        lock(Rule::Disasters, rules.disasters.is_some());
+        lock(Rule::IncomeTax, rules.income_tax.is_some());
+
+        if let Some(v) = rules.road_cost {
+            self.road_cost = v;
+        }
+        if let Some(v) = rules.building_cost {
+            self.building_cost = v;
+        }
+        if let Some(v) = rules.utilities_required {
+            self.utilities_required = v;
+        }
+        if let Some(v) = rules.road_wear {
+            self.road_wear = v;
+        }
+        if let Some(v) = rules.bankruptcy_days {
+            self.bankruptcy_days = v;
+        }
+        if let Some(v) = rules.derelict_days {
+            self.derelict_days = v;
+        }
+        if let Some(v) = rules.construction_time {
+            self.construction_time = v;
+        }
+        if let Some(v) = rules.disasters {
+            self.disasters = v;
+        }
+        if let Some(v) = rules.income_tax {
+            self.income_tax = v;
+        }
+    }
+
+    /// Applies the changes made during a game, the locked rules keep their value.
+    /// Returns false if the rules cannot be changed in this game.
+    pub fn change(&mut self, new: &GameRules) -> bool {
+        if !self.allow_changing {
+            return false;
+        }
+        let old = std::mem::replace(self, new.clone());
+        self.allow_changing = true;
+        self.locked = old.locked;
+
+        for &rule in &self.locked {
+            match rule {
+                Rule::StartingMoney => self.starting_money = old.starting_money,
+                Rule::RoadCost => self.road_cost = old.road_cost,
+                Rule::BuildingCost => self.building_cost = old.building_cost,
+                Rule::UtilitiesRequired => self.utilities_required = old.utilities_required,
+                Rule::RoadWear => self.road_wear = old.road_wear,
+                Rule::BankruptcyDays => self.bankruptcy_days = old.bankruptcy_days,
+                Rule::DerelictDays => self.derelict_days = old.derelict_days,
+                Rule::Sandbox => self.sandbox = old.sandbox,
+                Rule::LakeEqualization => self.lake_equalization = old.lake_equalization,
+                Rule::InstantConstruction => self.instant_construction = old.instant_construction,
+                Rule::ConstructionTime => self.construction_time = old.construction_time,
+                Rule::Disasters => self.disasters = old.disasters,
+                Rule::IncomeTax => self.income_tax = old.income_tax,
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locked_rules_cannot_change() {
+        let mut rules = GameRules::default();
+        let mut new = GameRules {
+            road_cost: 0.0,
+            ..GameRules::default()
+        };
+        assert!(!rules.change(&new));
+        assert_eq!(rules.road_cost, 1.0);
+
+        rules.allow_changing = true;
+        rules.lock_scenario(
+            Money::new_bucks(1000),
+            &ScenarioRules {
+                road_wear: Some(2.0),
+                disasters: Some(false),
+                ..Default::default()
+            },
+        );
+        new.road_wear = 0.0;
+        new.starting_money = Money::ZERO;
+        new.sandbox = true;
+        new.disasters = true;
+        new.income_tax = 0.2;
+        new.locked.clear();
+        assert!(rules.change(&new));
+
+        assert_eq!(rules.road_cost, 0.0);
+        assert_eq!(rules.road_wear, 2.0);
+        assert_eq!(rules.starting_money, Money::new_bucks(1000));
+        assert!(!rules.sandbox);
+        assert!(!rules.disasters);
+        assert_eq!(rules.income_tax, 0.2);
+        assert!(rules.is_locked(Rule::RoadWear));
+        assert!(rules.allow_changing);
+    }
+}
@@ -0,0 +1,305 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{BuildingPrototypeID, GameTime, ObjectiveCondition, ScenarioID};
+
+use geom::Polygon;
+
+use crate::economy::{CityStats, EcoStats, Government};
+use crate::event_log::{log_event, EventCategory, Severity};
+use crate::map::BuildingKind;
+use crate::rules::GameRules;
+use crate::Simulation;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ObjectiveStatus {
+    InProgress,
+    Completed,
+    Failed,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct ObjectiveProgress {
+    pub status: ObjectiveStatus,
+    /// Between 0 and 1 as of the last check, the objective is met at 1
+    pub progress: f32,
+}
+
+/// The scenario being played, saved with the game
+#[derive(Default, Serialize, Deserialize)]
+pub struct ScenarioState {
+    pub scenario: Option<ScenarioID>,
+    pub start_day: i32,
+    last_checked_day: i32,
+    /// Same order as the objectives of the scenario prototype
+    pub objectives: Vec<ObjectiveProgress>,
+    /// Buildings that can be built, None means everything is unlocked
+    pub unlocked: Option<BTreeSet<BuildingPrototypeID>>,
+    /// Set by the scenario and its completed objectives, the buildings can require them
+    #[serde(default)]
+    pub flags: BTreeSet<String>,
+}
+
+impl ScenarioState {
+    pub fn is_unlocked(&self, building: BuildingPrototypeID) -> bool {
+        self.unlocked
+            .as_ref()
+            .map_or(true, |unlocked| unlocked.contains(&building))
+    }
+
+    /// Why the building can't be built yet, shown to the player. None when it can be built
+    pub fn locked_reason(&self, kind: BuildingKind, population: u32) -> Option<String> {
+        if let BuildingKind::GoodsCompany(id) = kind {
+            if !self.is_unlocked(id.prototype().base.id) {
+                return Some("Unlocked by a scenario objective".to_string());
+            }
+        }
+        let unlock = &kind.build_menu()?.unlock;
+        if population < unlock.min_population {
+            return Some(format!("Needs {} inhabitants", unlock.min_population));
+        }
+        if let Some(ref flag) = unlock.scenario_flag {
+            if self.scenario.is_some() && !self.flags.contains(flag) {
+                return Some(format!("Needs scenario progress: {}", flag));
+            }
+        }
+        None
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.scenario.is_some()
+            && self
+                .objectives
+                .iter()
+                .all(|o| o.status == ObjectiveStatus::Completed)
+    }
+}
+
+/// Sets up the starting conditions of the scenario, the map is expected to be generated or loaded already
+pub fn start_scenario(sim: &mut Simulation, id: ScenarioID) {
+    let proto = id.prototype();
+    log::info!("starting scenario {}", proto.name);
+
+    sim.write::<Government>().money = proto.starting_money;
+    sim.write::<GameRules>()
+        .lock_scenario(proto.starting_money, &proto.rules);
+    let day = sim.read::<GameTime>().daytime.day;
+
+    let mut map = sim.map_mut();
+    for r in &proto.restrictions {
+        if map
+            .add_restriction(r.kind, Polygon(r.shape.clone()), &r.name)
+            .is_none()
+        {
+            log::warn!("scenario {}: invalid restriction {}", proto.name, r.name);
+        }
+    }
+    drop(map);
+
+    *sim.write::<ScenarioState>() = ScenarioState {
+        scenario: Some(id),
+        start_day: day,
+        last_checked_day: day,
+        objectives: proto
+            .objectives
+            .iter()
+            .map(|_| ObjectiveProgress {
+                status: ObjectiveStatus::InProgress,
+                progress: 0.0,
+            })
+            .collect(),
+        unlocked: proto
+            .unlocked
+            .as_ref()
+            .map(|unlocked| unlocked.iter().copied().collect()),
+        flags: proto.flags.iter().cloned().collect(),
+    };
+}
+
+/// How far the condition is from being met, between 0 and 1
+pub fn condition_progress(cond: &ObjectiveCondition, city: &CityStats, eco: &EcoStats) -> f32 {
+    let progress = match *cond {
+        ObjectiveCondition::Population { at_least } => {
+            city.population as f32 / at_least.max(1) as f32
+        }
+        ObjectiveCondition::Production {
+            item,
+            at_least_per_day,
+        } => eco.produced_last_day(item) as f32 / at_least_per_day.max(1) as f32,
+        ObjectiveCondition::Commute { at_most_minutes } => match city.avg_commute_minutes {
+            Some(avg) if avg <= at_most_minutes => 1.0,
+            Some(avg) => at_most_minutes / avg,
+            None => 0.0,
+        },
+    };
+    progress.clamp(0.0, 1.0)
+}
+
+/// Checks the objectives once per game day, hands out the rewards and fails the late objectives
+pub(crate) fn scenario_system(sim: &mut Simulation) {
+    profiling::scope!("scenario::scenario_system");
+    let day = sim.read::<GameTime>().daytime.day;
+    let mut state = sim.write::<ScenarioState>();
+    let Some(id) = state.scenario else {
+        return;
+    };
+    if state.last_checked_day == day {
+        return;
+    }
+    state.last_checked_day = day;
+
+    let proto = id.prototype();
+    let city = CityStats::new(&sim.world);
+    let eco = sim.read::<EcoStats>();
+    let was_completed = state.is_completed();
+    let days_elapsed = (day - state.start_day).max(0) as u32;
+
+    let mut messages = vec![];
+    let ScenarioState {
+        objectives,
+        unlocked,
+        flags,
+        ..
+    } = &mut *state;
+
+    for (objective, progress) in proto.objectives.iter().zip(objectives.iter_mut()) {
+        if progress.status != ObjectiveStatus::InProgress {
+            continue;
+        }
+        progress.progress = condition_progress(&objective.condition, &city, &eco);
+
+        if progress.progress >= 1.0 {
+            progress.status = ObjectiveStatus::Completed;
+            sim.write::<Government>().money += objective.reward_money;
+            if let Some(unlocked) = unlocked {
+                unlocked.extend(objective.reward_unlocks.iter().copied());
+            }
+            flags.extend(objective.reward_flags.iter().cloned());
+            for name in &objective.reward_lifts {
+                if sim.map_mut().lift_restrictions(name) > 0 {
+                    messages.push((
+                        Severity::Success,
+                        format!("{} is open to construction", name),
+                    ));
+                }
+            }
+            messages.push((
+                Severity::Success,
+                format!("Objective completed: {}", objective.label),
+            ));
+            continue;
+        }
+
+        if objective
+            .time_limit_days
+            .is_some_and(|limit| days_elapsed >= limit)
+        {
+            progress.status = ObjectiveStatus::Failed;
+            messages.push((
+                Severity::Warning,
+                format!("Objective failed: {}", objective.label),
+            ));
+        }
+    }
+
+    if !was_completed && state.is_completed() {
+        messages.push((
+            Severity::Success,
+            format!("Scenario completed: {}!", proto.label),
+        ));
+    }
+    drop(state);
+    drop(eco);
+
+    for (severity, text) in messages {
+        log_event(sim, EventCategory::Scenario, severity, text, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prototypes::{
+        BuildingPrototypeID, DockPrototypeID, GameTime, GoodsCompanyID, ScenarioID, Tick,
+        TICKS_PER_HOUR,
+    };
+
+    use geom::vec2;
+
+    use crate::economy::Government;
+    use crate::map::{BuildingKind, RestrictedAction};
+    use crate::scenario::{start_scenario, ObjectiveStatus, ScenarioState};
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn scenario_sets_starting_conditions_and_fails_late_objectives() {
+        let mut test = TestCtx::new();
+        let id = ScenarioID::new("bread-basket");
+        start_scenario(&mut test.g, id);
+
+        assert_eq!(
+            test.g.read::<Government>().money,
+            id.prototype().starting_money
+        );
+        {
+            let state = test.g.read::<ScenarioState>();
+            assert!(state.objectives.len() > 1);
+            assert!(state.is_unlocked(BuildingPrototypeID::new("bakery")));
+            assert!(!state.is_unlocked(BuildingPrototypeID::new("vegetable-farm")));
+        }
+
+        let limit = id.prototype().objectives[0].time_limit_days.unwrap() as u64;
+        let tick = test.g.read::<GameTime>().tick.0;
+        *test.g.write::<GameTime>() = GameTime::new(Tick(tick + (limit + 1) * 24 * TICKS_PER_HOUR));
+        test.tick();
+
+        let state = test.g.read::<ScenarioState>();
+        assert_eq!(state.objectives[0].status, ObjectiveStatus::Failed);
+        assert!(!state.is_completed());
+    }
+
+    #[test]
+    fn scenario_places_the_restrictions_its_objectives_lift() {
+        let mut test = TestCtx::new();
+        let id = ScenarioID::new("bread-basket");
+        start_scenario(&mut test.g, id);
+
+        let east = vec2(8000.0, 5000.0);
+        assert!(test
+            .g
+            .map()
+            .restriction_on(&east, RestrictedAction::Build)
+            .is_some());
+
+        let lifts = &id.prototype().objectives[0].reward_lifts;
+        assert!(!lifts.is_empty());
+        for name in lifts {
+            assert_eq!(test.g.map_mut().lift_restrictions(name), 1);
+        }
+        assert!(test
+            .g
+            .map()
+            .restriction_on(&east, RestrictedAction::Build)
+            .is_none());
+    }
+
+    #[test]
+    fn buildings_are_locked_until_their_conditions_are_met() {
+        let mut test = TestCtx::new();
+        let car_factory = BuildingKind::GoodsCompany(GoodsCompanyID::new("car-factory"));
+        let dock = BuildingKind::Dock(DockPrototypeID::new("dock"));
+        {
+            let state = test.g.read::<ScenarioState>();
+            assert!(state.locked_reason(car_factory, 0).is_some());
+            assert!(state.locked_reason(car_factory, 1000).is_none());
+            // outside of scenarios the flags don't matter
+            assert!(state.locked_reason(dock, 0).is_none());
+        }
+
+        start_scenario(&mut test.g, ScenarioID::new("bread-basket"));
+        let mut state = test.g.write::<ScenarioState>();
+        assert!(state.locked_reason(dock, 0).is_some());
+        state.flags.insert("harbor".to_string());
+        assert!(state.locked_reason(dock, 0).is_none());
+    }
+}
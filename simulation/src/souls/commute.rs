@@ -0,0 +1,178 @@
+use geom::Vec3;
+use prototypes::{GameDuration, GameTime, Tick, SECONDS_PER_REALTIME_SECOND};
+
+use crate::map::{Building, BuildingID, Map, PathKind, WALKED_PER_GAME_SECOND};
+use crate::map_dynamic::{BuildingInfos, Destination, Itinerary};
+use crate::transportation::Location;
+use crate::world::{HumanEnt, HumanID};
+use crate::{Simulation, SoulID, World};
+
+/// Typical speed of a pedestrian, in m/s
+pub const WALKING_SPEED: f32 = 1.2;
+/// Typical speed of a car in the city, in m/s
+pub const DRIVING_SPEED: f32 = 12.0;
+/// How much longer the path is than the straight line when no route could be found
+const DETOUR_FACTOR: f32 = 1.4;
+
+pub const COMMUTE_BIN_MINUTES: f32 = 5.0;
+/// The last bin holds every commute longer than the others
+pub const COMMUTE_BINS: usize = 12;
+
+#[derive(Copy, Clone, Debug)]
+pub struct CommuteEstimate {
+    pub worker: HumanID,
+    pub home: BuildingID,
+    pub minutes: f32,
+}
+
+/// The workers employed by the company owning the building
+pub fn employees(sim: &Simulation, building: BuildingID) -> Vec<HumanID> {
+    let Some(SoulID::GoodsCompany(c)) = sim.read::<BuildingInfos>().owner(building) else {
+        return vec![];
+    };
+    sim.world()
+        .companies
+        .get(c)
+        .map(|c| c.workers.0.clone())
+        .unwrap_or_default()
+}
+
+/// Estimates how long each employee takes to go from home to the building.
+/// Uses the route of the workers already on their way there, and computes a route for the others.
+pub fn estimate_commutes(sim: &Simulation, building: BuildingID) -> Vec<CommuteEstimate> {
+    let map = sim.map();
+    let world = sim.world();
+    let tick = sim.read::<GameTime>().tick;
+    let Some(workplace) = map.buildings().get(building) else {
+        return vec![];
+    };
+
+    employees(sim, building)
+        .into_iter()
+        .filter_map(|worker| {
+            let h = world.humans.get(worker)?;
+            let home = map.buildings().get(h.home.house)?;
+            let speed = if h.router.personal_car.is_some() {
+                DRIVING_SPEED
+            } else {
+                WALKING_SPEED
+            };
+
+            let (from, to) = (home.door_pos, workplace.door_pos);
+            let minutes = current_trip_length(world, &map, h, workplace, home)
+                .map(|length| travel_minutes(length, speed))
+                .or_else(|| {
+                    if h.router.personal_car.is_some() {
+                        return driving_minutes(tick, from, to, &map);
+                    }
+                    let it = Itinerary::route(tick, from, to, &map, PathKind::Pedestrian)?;
+                    Some(travel_minutes(route_length(&it, from, &map)?, speed))
+                })
+                .unwrap_or_else(|| travel_minutes(from.distance(to) * DETOUR_FACTOR, speed));
+
+            Some(CommuteEstimate {
+                worker,
+                home: home.id,
+                minutes,
+            })
+        })
+        .collect()
+}
+
+/// Length of the trip to work of a worker already on the way, from the route being followed
+fn current_trip_length(
+    world: &World,
+    map: &Map,
+    h: &HumanEnt,
+    workplace: &Building,
+    home: &Building,
+) -> Option<f32> {
+    if h.router.target_dest != Some(Destination::Building(workplace.id)) {
+        return None;
+    }
+    let (it, pos) = match h.location {
+        Location::Outside => (&h.it, h.trans.pos),
+        Location::Vehicle(v) => {
+            let v = world.vehicles.get(v)?;
+            (&v.it, v.trans.pos)
+        }
+        Location::Building(_) => return None,
+    };
+    let travelled = home.door_pos.distance(pos);
+    Some(travelled + route_length(it, pos, map)?)
+}
+
+/// Length left of the route, the expected waits at the crosswalks count as walked distance.
+/// Only walking routes go over crosswalks.
+fn route_length(it: &Itinerary, pos: Vec3, map: &Map) -> Option<f32> {
+    Some(it.remaining_length(pos, map)? + it.crossing_wait(map) * WALKED_PER_GAME_SECOND)
+}
+
+/// Minutes to drive between the two points at the speed limits of the route, None without a route
+pub fn driving_minutes(tick: Tick, from: Vec3, to: Vec3, map: &Map) -> Option<f32> {
+    let it = Itinerary::route(tick, from, to, map, PathKind::Vehicle)?;
+    let seconds = it.remaining_driving_time(from, map)?;
+    Some(seconds * SECONDS_PER_REALTIME_SECOND as f32 / 60.0)
+}
+
+/// Rough duration of the trip between two buildings from the straight line, for when no route is known
+pub fn estimate_trip(
+    map: &Map,
+    from: BuildingID,
+    to: BuildingID,
+    has_car: bool,
+) -> Option<GameDuration> {
+    let from = map.buildings().get(from)?.door_pos;
+    let to = map.buildings().get(to)?.door_pos;
+    let speed = if has_car {
+        DRIVING_SPEED
+    } else {
+        WALKING_SPEED
+    };
+    let minutes = travel_minutes(from.distance(to) * DETOUR_FACTOR, speed);
+    Some(GameDuration::from_secs((minutes * 60.0) as u64))
+}
+
+/// In game minutes
+pub fn travel_minutes(length: f32, speed: f32) -> f32 {
+    length / speed * SECONDS_PER_REALTIME_SECOND as f32 / 60.0
+}
+
+/// Number of commutes in each bin of COMMUTE_BIN_MINUTES
+pub fn commute_histogram(commutes: &[CommuteEstimate]) -> [u32; COMMUTE_BINS] {
+    let mut bins = [0; COMMUTE_BINS];
+    for c in commutes {
+        let bin = ((c.minutes / COMMUTE_BIN_MINUTES) as usize).min(COMMUTE_BINS - 1);
+        bins[bin] += 1;
+    }
+    bins
+}
+
+pub fn average_minutes(commutes: &[CommuteEstimate]) -> Option<f32> {
+    if commutes.is_empty() {
+        return None;
+    }
+    Some(commutes.iter().map(|c| c.minutes).sum::<f32>() / commutes.len() as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use slotmapd::KeyData;
+
+    use super::{commute_histogram, CommuteEstimate, COMMUTE_BINS};
+
+    #[test]
+    fn histogram_clamps_long_commutes() {
+        let estimate = |minutes| CommuteEstimate {
+            worker: KeyData::from_ffi(1).into(),
+            home: KeyData::from_ffi(1).into(),
+            minutes,
+        };
+        let bins =
+            commute_histogram(&[estimate(1.0), estimate(4.9), estimate(7.0), estimate(500.0)]);
+
+        assert_eq!(bins[0], 2);
+        assert_eq!(bins[1], 1);
+        assert_eq!(bins[COMMUTE_BINS - 1], 1);
+    }
+}
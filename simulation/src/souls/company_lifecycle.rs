@@ -0,0 +1,424 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{GameTime, GoodsCompanyID, ItemID, Money};
+
+use crate::economy::Market;
+use crate::event_log::{log_event, EventCategory, EventSubject, Severity};
+use crate::map::{BuildingID, BuildingKind, FieldStage, Map};
+use crate::map_dynamic::BuildingInfos;
+use crate::rules::GameRules;
+use crate::souls::goods_company::{company_soul, fire_workers};
+use crate::transportation::VehicleState;
+use crate::world::{CompanyEnt, CompanyID, VehicleEnt};
+use crate::{ParCommandBuffer, Simulation, SoulID};
+
+/// Days in a row losing money before the company lets workers go
+pub const SHRINK_AFTER_DAYS: u32 = 2;
+/// Days in a row losing money before the company closes, by default
+pub const CLOSE_AFTER_DAYS: u32 = 5;
+/// Number of events kept in the history
+pub const MAX_COMPANY_EVENTS: usize = 100;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompanyEventKind {
+    Opened,
+    Shrunk { max_workers: u32 },
+    Closed,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct CompanyEvent {
+    pub day: i32,
+    pub building: BuildingID,
+    pub proto: GoodsCompanyID,
+    pub kind: CompanyEventKind,
+}
+
+/// Keeps track of the companies opening and closing, saved with the game
+#[derive(Default, Serialize, Deserialize)]
+pub struct CompanyLifecycle {
+    last_checked_day: i32,
+    /// Most recent last
+    pub events: VecDeque<CompanyEvent>,
+    /// Buildings of closed companies, waiting for their goods to be profitable again
+    pub vacant: BTreeSet<BuildingID>,
+}
+
+impl CompanyLifecycle {
+    pub fn is_vacant(&self, building: BuildingID) -> bool {
+        self.vacant.contains(&building)
+    }
+
+    fn push_event(&mut self, event: CompanyEvent) {
+        if self.events.len() >= MAX_COMPANY_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
+
+/// Average profit of the last day of the companies producing each item
+pub fn item_profitability(sim: &Simulation) -> BTreeMap<ItemID, Money> {
+    let mut sums: BTreeMap<ItemID, (Money, i64)> = BTreeMap::new();
+    for c in sim.world.companies.values() {
+        let Some(profit) = c.comp.finances.yesterday() else {
+            continue;
+        };
+        let Some(ref recipe) = c.comp.proto.prototype().recipe else {
+            continue;
+        };
+        for item in &recipe.production {
+            let (sum, n) = sums.entry(item.id).or_insert((Money::ZERO, 0));
+            *sum += profit;
+            *n += 1;
+        }
+    }
+    sums.into_iter()
+        .map(|(item, (sum, n))| (item, sum / n))
+        .collect()
+}
+
+/// Once per game day, closes the books of every company.
+/// Companies losing money shrink then close, and vacant buildings reopen when their goods are profitable.
+pub(crate) fn company_lifecycle_system(sim: &mut Simulation) {
+    profiling::scope!("souls::company_lifecycle_system");
+    let day = sim.read::<GameTime>().daytime.day;
+    {
+        let mut lifecycle = sim.write::<CompanyLifecycle>();
+        if lifecycle.last_checked_day == day {
+            return;
+        }
+        lifecycle.last_checked_day = day;
+    }
+
+    let close_after = sim.read::<GameRules>().bankruptcy_days;
+    let mut to_shrink = vec![];
+    let mut to_close = vec![];
+    let mut exhausted = vec![];
+    let map = sim.resources.read::<Map>();
+    for (id, c) in sim.world.companies.iter_mut() {
+        c.comp.finances.close_day();
+        if deposit_exhausted(&map, c.comp.building, c.comp.proto) {
+            exhausted.push(id);
+            continue;
+        }
+        // a farm only sells at the harvest, it is not judged while its crop grows
+        let growing = matches!(c.comp.field, Some(ref f) if f.stage != FieldStage::Ripe);
+        if growing {
+            c.comp.finances.losing_days = 0;
+        }
+        let losing_days = c.comp.finances.losing_days;
+        if losing_days >= close_after {
+            to_close.push(id);
+        } else if losing_days >= SHRINK_AFTER_DAYS && c.comp.max_workers > 1 {
+            to_shrink.push(id);
+        }
+    }
+    drop(map);
+
+    // before closing, so a building does not reopen the day it closed
+    reopen_vacant(sim, day);
+
+    for id in to_shrink {
+        shrink_company(sim, id, day);
+    }
+    for id in to_close {
+        close_company(sim, id, day, "went bankrupt and closed");
+    }
+    for id in exhausted {
+        close_company(sim, id, day, "ran out of its deposit and closed");
+    }
+}
+
+/// Whether the company extracts from a deposit that has nothing left
+fn deposit_exhausted(map: &Map, building: BuildingID, proto: GoodsCompanyID) -> bool {
+    let Some(deposit) = proto.prototype().deposit else {
+        return false;
+    };
+    let Some(b) = map.buildings().get(building) else {
+        return false;
+    };
+    !map.can_extract(&b.obb, deposit)
+}
+
+/// Lets a quarter of the workers go, and stops hiring for those positions
+fn shrink_company(sim: &mut Simulation, id: CompanyID, day: i32) {
+    let Some(c) = sim.world.companies.get_mut(id) else {
+        return;
+    };
+    let max_workers = (c.comp.max_workers - c.comp.max_workers.div_ceil(4)).max(1);
+    c.comp.max_workers = max_workers;
+
+    let kept = c.workers.0.len().min(max_workers as usize);
+    let fired = c.workers.0.split_off(kept);
+    if c.comp.driver.is_some_and(|d| fired.contains(&d)) {
+        c.comp.driver = None;
+    }
+    let openings = max_workers as i32 - c.workers.0.len() as i32;
+    let building = c.comp.building;
+    let proto = c.comp.proto;
+
+    fire_workers(sim, fired);
+
+    let job_opening = ItemID::new("job-opening");
+    let soul = SoulID::GoodsCompany(id);
+    let mut m = sim.write::<Market>();
+    let delta = openings - m.capital(soul, job_opening);
+    m.produce(soul, job_opening, delta);
+    drop(m);
+
+    sim.write::<CompanyLifecycle>().push_event(CompanyEvent {
+        day,
+        building,
+        proto,
+        kind: CompanyEventKind::Shrunk { max_workers },
+    });
+    log_event(
+        sim,
+        EventCategory::Economy,
+        Severity::Info,
+        format!(
+            "{} is losing money and laid off workers",
+            proto.prototype().label
+        ),
+        Some(EventSubject::Building(building)),
+    );
+}
+
+/// Fires everyone and frees the building, `why` ends the event message
+fn close_company(sim: &mut Simulation, id: CompanyID, day: i32, why: &str) {
+    let Some(c) = sim.world.companies.get_mut(id) else {
+        return;
+    };
+    let fired = std::mem::take(&mut c.workers.0);
+    let building = c.comp.building;
+    let proto = c.comp.proto;
+    let trucks = c.comp.trucks.clone();
+
+    fire_workers(sim, fired);
+
+    // trucks on the road are left to finish their trip
+    for truck in trucks {
+        if sim
+            .world
+            .vehicles
+            .get(truck)
+            .is_some_and(|v| matches!(v.vehicle.state, VehicleState::Parked(_)))
+        {
+            sim.write::<ParCommandBuffer<VehicleEnt>>().kill(truck);
+        }
+    }
+    sim.write::<ParCommandBuffer<CompanyEnt>>().kill(id);
+    sim.write::<BuildingInfos>().remove_owner(building);
+
+    let mut lifecycle = sim.write::<CompanyLifecycle>();
+    lifecycle.vacant.insert(building);
+    lifecycle.push_event(CompanyEvent {
+        day,
+        building,
+        proto,
+        kind: CompanyEventKind::Closed,
+    });
+    drop(lifecycle);
+
+    log_event(
+        sim,
+        EventCategory::Economy,
+        Severity::Warning,
+        format!("{} {}", proto.prototype().label, why),
+        Some(EventSubject::Building(building)),
+    );
+}
+
+/// Reopens at most one vacant building per day, the first one making a profitable item.
+/// An item nobody produces is profitable if someone wants to buy it.
+fn reopen_vacant(sim: &mut Simulation, day: i32) {
+    let vacant: Vec<BuildingID> = sim
+        .read::<CompanyLifecycle>()
+        .vacant
+        .iter()
+        .copied()
+        .collect();
+    if vacant.is_empty() {
+        return;
+    }
+    let profitability = item_profitability(sim);
+
+    for building in vacant {
+        let kind = sim.map().buildings().get(building).map(|b| b.kind);
+        let Some(BuildingKind::GoodsCompany(proto)) = kind else {
+            // the building was removed or replaced
+            sim.write::<CompanyLifecycle>().vacant.remove(&building);
+            continue;
+        };
+        let Some(ref recipe) = proto.prototype().recipe else {
+            continue;
+        };
+        // an emptied mine stays closed, it can be demolished
+        if deposit_exhausted(&sim.map(), building, proto) {
+            continue;
+        }
+
+        let market = sim.read::<Market>();
+        let profitable = recipe
+            .production
+            .iter()
+            .any(|item| match profitability.get(&item.id) {
+                Some(&profit) => profit > Money::ZERO,
+                None => market
+                    .iter()
+                    .find(|(&id, _)| id == item.id)
+                    .is_some_and(|(_, m)| !m.buy_orders().is_empty()),
+            });
+        drop(market);
+        if !profitable {
+            continue;
+        }
+
+        sim.write::<CompanyLifecycle>().vacant.remove(&building);
+        if company_soul(sim, building, proto).is_none() {
+            continue;
+        }
+        sim.write::<CompanyLifecycle>().push_event(CompanyEvent {
+            day,
+            building,
+            proto,
+            kind: CompanyEventKind::Opened,
+        });
+        log_event(
+            sim,
+            EventCategory::Economy,
+            Severity::Success,
+            format!("{} reopened", proto.prototype().label),
+            Some(EventSubject::Building(building)),
+        );
+        return;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3, Circle, Vec2, OBB};
+    use prototypes::{GameTime, GoodsCompanyID, Money, Tick, TICKS_PER_HOUR};
+
+    use crate::event_log::{EventLog, EventSubject, Severity};
+    use crate::map::{BuildingKind, Deposit};
+    use crate::map_dynamic::BuildingInfos;
+    use crate::souls::company_lifecycle::{CompanyLifecycle, CLOSE_AFTER_DAYS};
+    use crate::souls::goods_company::CompanyFinances;
+    use crate::tests::TestCtx;
+    use crate::SoulID;
+
+    #[test]
+    fn finances_count_losing_streak() {
+        let mut f = CompanyFinances {
+            today: Money::new_bucks(-3),
+            ..Default::default()
+        };
+        f.close_day();
+        f.today = Money::new_bucks(-1);
+        f.close_day();
+        assert_eq!(f.losing_days, 2);
+        assert_eq!(f.yesterday(), Some(Money::new_bucks(-1)));
+        assert_eq!(f.today, Money::ZERO);
+
+        f.today = Money::new_bucks(5);
+        f.close_day();
+        assert_eq!(f.losing_days, 0);
+        assert_eq!(f.history.len(), 3);
+    }
+
+    #[test]
+    fn unprofitable_company_closes() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let proto = GoodsCompanyID::new("bakery").prototype();
+        let bakery = test
+            .g
+            .map_mut()
+            .build_special_building(
+                &OBB::new(vec2(60.0, 40.0), Vec2::Y, proto.size.w, proto.size.h),
+                BuildingKind::GoodsCompany(proto.id),
+                proto.bgen,
+                None,
+                None,
+            )
+            .unwrap();
+        test.g.write::<BuildingInfos>().insert(bakery);
+        test.tick();
+
+        let Some(SoulID::GoodsCompany(comp)) = test.g.read::<BuildingInfos>().owner(bakery) else {
+            panic!("bakery should have a company")
+        };
+        let finances = &mut test.g.world_mut_unchecked().companies[comp].comp.finances;
+        finances.losing_days = CLOSE_AFTER_DAYS - 1;
+        finances.today = Money::new_bucks(-100);
+
+        let tick = test.g.read::<GameTime>().tick.0;
+        *test.g.write::<GameTime>() = GameTime::new(Tick(tick + 24 * TICKS_PER_HOUR));
+        test.tick();
+        test.tick();
+
+        assert!(test.g.read::<BuildingInfos>().owner(bakery).is_none());
+        assert!(!test.g.world().companies.contains_key(comp));
+        let lifecycle = test.g.read::<CompanyLifecycle>();
+        assert!(lifecycle.is_vacant(bakery));
+        assert_eq!(lifecycle.events.len(), 1);
+
+        let log = test.g.read::<EventLog>();
+        let closed = log.iter().next().expect("closing should be logged");
+        assert_eq!(closed.severity, Severity::Warning);
+        assert_eq!(closed.subject, Some(EventSubject::Building(bakery)));
+    }
+
+    #[test]
+    fn exhausted_mine_closes_for_good() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+        let proto = GoodsCompanyID::new("iron-mine").prototype();
+        let obb = OBB::new(vec2(100.0, 60.0), Vec2::Y, proto.size.w, proto.size.h);
+
+        let kind = proto.deposit.unwrap();
+        let mut map = test.g.map_mut();
+        map.deposits.clear();
+        map.deposits.insert_with_key(|id| Deposit {
+            id,
+            kind,
+            shape: Circle::new(obb.center(), 100.0),
+            initial: 1,
+            remaining: 1,
+        });
+        let mine = map
+            .build_special_building(
+                &obb,
+                BuildingKind::GoodsCompany(proto.id),
+                proto.bgen,
+                None,
+                None,
+            )
+            .unwrap();
+        drop(map);
+        test.g.write::<BuildingInfos>().insert(mine);
+        test.tick();
+
+        let Some(SoulID::GoodsCompany(comp)) = test.g.read::<BuildingInfos>().owner(mine) else {
+            panic!("mine should have a company")
+        };
+        assert!(test.g.map_mut().extract_deposit(&obb, kind));
+
+        let tick = test.g.read::<GameTime>().tick.0;
+        *test.g.write::<GameTime>() = GameTime::new(Tick(tick + 24 * TICKS_PER_HOUR));
+        test.tick();
+        test.tick();
+
+        assert!(!test.g.world().companies.contains_key(comp));
+        assert!(test.g.read::<CompanyLifecycle>().is_vacant(mine));
+
+        // an empty deposit is never profitable again
+        test.g.write::<CompanyLifecycle>().last_checked_day -= 1;
+        test.tick();
+        assert!(test.g.read::<BuildingInfos>().owner(mine).is_none());
+    }
+}
@@ -0,0 +1,245 @@
+//! Delivery of the goods sold by the factories.
+//! The goods change hands on the market when the trade is made, the orders then wait for a truck
+//! of the seller and are in transit until they are unloaded at the buyer.
+
+use serde::{Deserialize, Serialize};
+
+use geom::Vec2;
+use prototypes::{GameDuration, ItemID};
+
+use crate::map::BuildingID;
+use crate::souls::desire::WorkKind;
+use crate::world::{CompanyID, HumanID, VehicleID};
+use crate::Simulation;
+
+/// Orders going less than ~37 degrees away from the oldest order are delivered on the same trip
+const SAME_DIRECTION_COS: f32 = 0.8;
+/// In game seconds
+const HANDLING_SECS: u64 = 120;
+
+/// Time spent loading or unloading a truck at the dock of a building
+pub fn handling_time() -> GameDuration {
+    GameDuration::from_secs(HANDLING_SECS)
+}
+
+/// Goods sold to a building
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Shipment {
+    pub kind: ItemID,
+    pub qty: i32,
+    /// Where the goods are unloaded
+    pub to: BuildingID,
+}
+
+/// Where an order bound to a building is
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OrderState {
+    /// At the seller, waiting for one of its trucks
+    Waiting(CompanyID),
+    InTransit(VehicleID),
+}
+
+/// Picks the orders carried by the next trip of a truck leaving from `origin`.
+/// The oldest order gives the direction of the trip and is always loaded first, then the orders
+/// going the same way are added closest first until the truck is full.
+/// An order too big for the truck is split, what is left stays in `orders`.
+/// Orders whose building is gone are dropped.
+///
+/// The shipments are returned in the order they are delivered, closest first.
+/// Ties are broken by the position in `orders` so the result only depends on the inputs.
+pub fn consolidate(
+    origin: Vec2,
+    capacity: u32,
+    orders: &mut Vec<Shipment>,
+    pos: impl Fn(BuildingID) -> Option<Vec2>,
+) -> Vec<Shipment> {
+    orders.retain(|o| pos(o.to).is_some());
+    let Some(oldest) = orders.first() else {
+        return vec![];
+    };
+    if capacity == 0 {
+        return vec![];
+    }
+
+    let offset = |o: &Shipment| pos(o.to).map_or(Vec2::ZERO, |p| p - origin);
+    let dir = offset(oldest).try_normalize();
+
+    let mut candidates: Vec<(usize, f32)> = orders
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, o)| {
+            let Some(dir) = dir else {
+                return true;
+            };
+            offset(o)
+                .try_normalize()
+                .map_or(true, |d| d.dot(dir) >= SAME_DIRECTION_COS)
+        })
+        .map(|(i, o)| (i, offset(o).mag()))
+        .collect();
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+
+    let mut room = capacity as i32;
+    let mut trip: Vec<(usize, Shipment)> = vec![];
+    for i in std::iter::once(0).chain(candidates.into_iter().map(|(i, _)| i)) {
+        if room <= 0 {
+            break;
+        }
+        let order = &mut orders[i];
+        let qty = order.qty.min(room);
+        if qty <= 0 {
+            continue;
+        }
+        order.qty -= qty;
+        room -= qty;
+
+        // several orders of the same goods for the same building are unloaded together
+        if let Some((_, s)) = trip
+            .iter_mut()
+            .find(|(_, s)| s.to == order.to && s.kind == order.kind)
+        {
+            s.qty += qty;
+            continue;
+        }
+        trip.push((i, Shipment { qty, ..*order }));
+    }
+    orders.retain(|o| o.qty > 0);
+
+    trip.sort_by(|(i, a), (j, b)| offset(a).mag().total_cmp(&offset(b).mag()).then(i.cmp(j)));
+    trip.into_iter().map(|(_, s)| s).collect()
+}
+
+/// The orders bound to the building that are not delivered yet
+pub fn incoming_orders(sim: &Simulation, building: BuildingID) -> Vec<(Shipment, OrderState)> {
+    let world = sim.world();
+    let mut orders = vec![];
+    for (id, c) in world.companies.iter() {
+        for &o in &c.comp.orders {
+            if o.to == building {
+                orders.push((o, OrderState::Waiting(id)));
+            }
+        }
+    }
+    for (id, v) in world.vehicles.iter() {
+        for &s in &v.vehicle.cargo {
+            if s.to == building {
+                orders.push((s, OrderState::InTransit(id)));
+            }
+        }
+    }
+    orders
+}
+
+/// Unloads the goods bound to the building from the truck and sends its driver to the next stop,
+/// or back to work once empty
+pub(crate) fn unload(sim: &mut Simulation, truck: VehicleID, building: BuildingID) {
+    let Some(v) = sim.world.vehicles.get_mut(truck) else {
+        return;
+    };
+    v.vehicle.cargo.retain(|s| s.to != building);
+    let next = v.vehicle.cargo.first().map(|s| s.to);
+
+    let Some(driver) = sim
+        .world
+        .companies
+        .values()
+        .find(|c| c.comp.trucks.contains(&truck))
+        .and_then(|c| c.comp.driver)
+    else {
+        return;
+    };
+    set_deliver_order(sim, driver, next);
+}
+
+/// Sends the driver to the building, or back to work if None
+pub(crate) fn set_deliver_order(sim: &mut Simulation, driver: HumanID, to: Option<BuildingID>) {
+    let Some(w) = sim
+        .world
+        .humans
+        .get_mut(driver)
+        .and_then(|h| h.work.as_mut())
+    else {
+        return;
+    };
+    if let WorkKind::Driver { deliver_order, .. } = &mut w.kind {
+        *deliver_order = to;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, Vec2};
+    use prototypes::ItemID;
+    use slotmapd::KeyData;
+
+    use super::*;
+
+    fn building(id: u64) -> BuildingID {
+        KeyData::from_ffi(id).into()
+    }
+
+    fn positions(id: BuildingID) -> Option<Vec2> {
+        [
+            (1, vec2(100.0, 0.0)),
+            (2, vec2(50.0, 5.0)),
+            (3, vec2(-100.0, 0.0)),
+            (4, vec2(200.0, 10.0)),
+            (5, vec2(50.0, -5.0)),
+        ]
+        .into_iter()
+        .find(|&(n, _)| building(n) == id)
+        .map(|(_, p)| p)
+    }
+
+    fn order(to: u64, qty: i32) -> Shipment {
+        Shipment {
+            kind: ItemID::new("flour"),
+            qty,
+            to: building(to),
+        }
+    }
+
+    #[test]
+    fn consolidates_orders_going_the_same_way() {
+        let mut orders = vec![
+            order(1, 10),
+            order(3, 5),
+            order(2, 5),
+            order(4, 10),
+            order(1, 2),
+            order(9, 3),
+        ];
+
+        let trip = consolidate(Vec2::ZERO, 20, &mut orders, positions);
+
+        // the oldest order goes east, the one going west waits for the next trip
+        // the two orders for the same building are unloaded together
+        // the furthest order only fits in part
+        assert_eq!(trip, vec![order(2, 5), order(1, 12), order(4, 3)]);
+        // the order of a building that is gone is dropped
+        assert_eq!(orders, vec![order(3, 5), order(4, 7)]);
+
+        let trip = consolidate(Vec2::ZERO, 20, &mut orders, positions);
+        assert_eq!(trip, vec![order(3, 5)]);
+        let trip = consolidate(Vec2::ZERO, 20, &mut orders, positions);
+        assert_eq!(trip, vec![order(4, 7)]);
+        assert!(orders.is_empty());
+        assert!(consolidate(Vec2::ZERO, 20, &mut orders, positions).is_empty());
+    }
+
+    #[test]
+    fn consolidation_is_deterministic() {
+        // 2 and 5 are at the same distance, the first one in the list is delivered first
+        let orders = vec![order(1, 4), order(5, 4), order(2, 4), order(4, 4)];
+
+        let mut a = orders.clone();
+        let mut b = orders.clone();
+        let trip_a = consolidate(Vec2::ZERO, 12, &mut a, positions);
+        let trip_b = consolidate(Vec2::ZERO, 12, &mut b, positions);
+        assert_eq!(trip_a, trip_b);
+        assert_eq!(a, b);
+        assert_eq!(trip_a, vec![order(5, 4), order(2, 4), order(1, 4)]);
+        assert_eq!(a, vec![order(4, 4)]);
+    }
+}
@@ -5,7 +5,7 @@ use geom::Transform;
 use prototypes::{GameInstant, GameTime, ItemID};
 
 use crate::economy::{find_trade_place, Bought, Market};
-use crate::map::BuildingID;
+use crate::map::{BuildingID, Map};
 use crate::map_dynamic::{BuildingInfos, Destination};
 use crate::souls::human::HumanDecisionKind;
 use crate::transportation::Location;
@@ -37,7 +37,7 @@ impl BuyFood {
         }
     }
 
-    pub fn score(&self, time: &GameTime, loc: &Location, bought: &Bought) -> f32 {
+    pub fn score(&self, time: &GameTime, loc: &Location, bought: &Bought, map: &Map) -> f32 {
         if matches!(self.state, BuyFoodState::WaitingForTrade)
             && bought
                 .0
@@ -51,6 +51,14 @@ impl BuyFood {
             if loc == &Location::Building(id) {
                 return 1.0;
             }
+            // wait for the shop to open
+            if map
+                .buildings()
+                .get(id)
+                .is_some_and(|b| !b.kind.is_open(&time.daytime))
+            {
+                return 0.0;
+            }
         }
         self.last_ate.elapsed(time).seconds() as f32 / GameTime::DAY as f32 - 1.0
     }
@@ -0,0 +1,63 @@
+use crate::map::BuildingID;
+use crate::map_dynamic::Destination;
+use crate::souls::human::HumanDecisionKind;
+use crate::transportation::Location;
+use egui_inspect::Inspect;
+use prototypes::{GameDuration, GameInstant, GameTime};
+use serde::{Deserialize, Serialize};
+
+/// Guests leave home this early on top of the trip duration, in game minutes
+const DEPARTURE_MARGIN_MINUTES: u64 = 15;
+
+/// Invitation to an event at a venue, the guest leaves to arrive on time and stays until the end
+#[derive(Inspect, Debug, Clone, Serialize, Deserialize)]
+pub struct AttendEvent {
+    pub venue: BuildingID,
+    pub start: GameInstant,
+    pub end: GameInstant,
+    /// Estimation of the trip to the venue made when invited
+    pub travel_estimate: GameDuration,
+    /// When the guest left for the venue, None when not on the way yet
+    pub departed: Option<GameInstant>,
+    pub last_score: f32,
+}
+
+impl AttendEvent {
+    pub fn new(
+        venue: BuildingID,
+        start: GameInstant,
+        end: GameInstant,
+        travel_estimate: GameDuration,
+    ) -> Self {
+        Self {
+            venue,
+            start,
+            end,
+            travel_estimate,
+            departed: None,
+            last_score: 0.0,
+        }
+    }
+
+    /// When the guest leaves home to arrive before the start
+    pub fn departure(&self) -> GameInstant {
+        self.start - self.travel_estimate - GameDuration::from_minutes(DEPARTURE_MARGIN_MINUTES)
+    }
+
+    pub fn apply(&mut self, loc: &Location, time: &GameTime) -> HumanDecisionKind {
+        if &Location::Building(self.venue) != loc && self.departed.is_none() {
+            self.departed = Some(time.instant());
+        }
+        HumanDecisionKind::GoTo(Destination::Building(self.venue))
+    }
+
+    /// Above work and home so that the guests come, only starving makes them skip it
+    pub fn score(&self, time: &GameTime) -> f32 {
+        let now = time.instant();
+        if now >= self.departure() && now < self.end {
+            0.8
+        } else {
+            0.0
+        }
+    }
+}
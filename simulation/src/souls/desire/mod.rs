@@ -1,7 +1,9 @@
 mod buyfood;
+mod event;
 mod home;
 mod work;
 
 pub use buyfood::*;
+pub use event::*;
 pub use home::*;
 pub use work::*;
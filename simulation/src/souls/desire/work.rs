@@ -1,15 +1,20 @@
 use crate::map::BuildingID;
 use crate::map_dynamic::{Destination, Router};
+use crate::souls::delivery::handling_time;
 use crate::souls::human::HumanDecisionKind;
 use crate::transportation::Location;
 use crate::world::VehicleID;
 use egui_inspect::Inspect;
-use prototypes::{GameTime, RecTimeInterval, MINUTES_PER_HOUR};
+use prototypes::{
+    GameDuration, GameInstant, GameTime, RecTimeInterval, SECONDS_PER_DAY, SECONDS_PER_HOUR,
+    SECONDS_PER_MINUTE,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum WorkKind {
     Driver {
+        /// Next stop of the truck, the rest of the route is in its cargo
         deliver_order: Option<BuildingID>,
         truck: VehicleID,
     },
@@ -17,60 +22,171 @@ pub enum WorkKind {
 }
 debug_inspect_impl!(WorkKind);
 
+/// Workers of the same shift arrive up to this many minutes apart so they don't all leave at once
+const SHIFT_SPREAD_MINUTES: f32 = 20.0;
+/// Workers leave home this early on top of the trip duration, in game seconds
+const DEPARTURE_MARGIN: i32 = 10 * SECONDS_PER_MINUTE;
+
 #[derive(Inspect, Debug, Clone, Serialize, Deserialize)]
 pub struct Work {
     pub workplace: BuildingID,
     pub work_inter: RecTimeInterval,
     pub kind: WorkKind,
     pub last_score: f32,
+    /// When the worker left to go to work, None when not on the way
+    #[serde(default)]
+    pub commute_start: Option<GameInstant>,
+    /// How long the last trip to work took
+    #[serde(default)]
+    pub last_commute: Option<GameDuration>,
+    /// Estimation of the trip to work made when hired, until a real trip was measured
+    #[serde(default)]
+    pub travel_estimate: Option<GameDuration>,
 }
 
 impl Work {
-    pub fn new(workplace: BuildingID, kind: WorkKind, offset: f32) -> Self {
+    /// offset is in [0, 1) and staggers the workers of the same shift
+    pub fn new(workplace: BuildingID, kind: WorkKind, shift: RecTimeInterval, offset: f32) -> Self {
+        let offset = (offset * SHIFT_SPREAD_MINUTES) as i32 * SECONDS_PER_MINUTE;
         Work {
             workplace,
-            work_inter: RecTimeInterval::new(
-                (8, (offset * MINUTES_PER_HOUR as f32) as i32),
-                (18, (offset * MINUTES_PER_HOUR as f32) as i32),
+            work_inter: RecTimeInterval::new_daysec(
+                (shift.start_daysec() + offset) % SECONDS_PER_DAY,
+                (shift.end_daysec() + offset) % SECONDS_PER_DAY,
             ),
             kind,
             last_score: 0.0,
+            commute_start: None,
+            last_commute: None,
+            travel_estimate: None,
         }
     }
 
-    pub fn apply(&mut self, loc: &Location, router: &Router) -> HumanDecisionKind {
+    /// How long before the start of the shift the worker leaves home, in game seconds
+    pub fn departure_margin(&self) -> i32 {
+        let travel = self
+            .last_commute
+            .or(self.travel_estimate)
+            .map_or(0, |d| d.seconds() as i32);
+        travel + DEPARTURE_MARGIN
+    }
+
+    /// When the worker will leave home for the next shift
+    pub fn next_departure(&self, time: &GameTime) -> GameInstant {
+        let start = self.work_inter.start_daysec();
+        let departure = (start - self.departure_margin()).rem_euclid(SECONDS_PER_DAY);
+        time.next_occurrence(
+            departure / SECONDS_PER_HOUR,
+            departure % SECONDS_PER_HOUR / SECONDS_PER_MINUTE,
+        )
+    }
+
+    pub fn apply(&mut self, loc: &Location, router: &Router, time: &GameTime) -> HumanDecisionKind {
         use HumanDecisionKind::*;
+        let at_work = &Location::Building(self.workplace) == loc;
+
+        // trips of the truck are not commutes
+        let delivering = match self.kind {
+            WorkKind::Driver {
+                deliver_order,
+                truck,
+            } => deliver_order.is_some() || router.vehicle() == Some(truck),
+            WorkKind::Worker => false,
+        };
+        if at_work {
+            if let Some(start) = self.commute_start.take() {
+                self.last_commute = Some(start.elapsed(time));
+            }
+        } else if self.commute_start.is_none() && !delivering {
+            self.commute_start = Some(time.instant());
+        }
+
         match self.kind {
             WorkKind::Worker => GoTo(Destination::Building(self.workplace)),
             WorkKind::Driver {
-                deliver_order,
+                deliver_order: Some(b),
                 truck,
             } => {
-                if &Location::Building(self.workplace) != loc {
-                    MultiStack(vec![
-                        GoTo(Destination::Building(self.workplace)),
-                        SetVehicle(router.personal_car),
-                    ])
-                } else if let Some(b) = deliver_order {
-                    MultiStack(vec![
-                        SetVehicle(router.personal_car),
-                        GoTo(Destination::Building(self.workplace)),
-                        DeliverAtBuilding(b),
-                        GoTo(Destination::Building(b)),
-                        SetVehicle(Some(truck)),
-                    ])
-                } else {
-                    Yield
+                // goes to the next stop and unloads, the truck is loaded first when leaving work
+                let mut stack = vec![
+                    DeliverAtBuilding(b),
+                    Unload { truck, building: b },
+                    Wait(handling_time(), None),
+                    GoTo(Destination::Building(b)),
+                ];
+                if at_work {
+                    stack.push(Wait(handling_time(), None));
                 }
+                stack.push(SetVehicle(Some(truck)));
+                MultiStack(stack)
             }
+            WorkKind::Driver { truck, .. } if router.vehicle() == Some(truck) => MultiStack(vec![
+                SetVehicle(router.personal_car),
+                GoTo(Destination::Building(self.workplace)),
+            ]),
+            WorkKind::Driver { .. } if !at_work => MultiStack(vec![
+                GoTo(Destination::Building(self.workplace)),
+                SetVehicle(router.personal_car),
+            ]),
+            WorkKind::Driver { .. } => Yield,
         }
     }
 
     pub fn score(&self, time: &GameTime) -> f32 {
-        if self.work_inter.dist_start(&time.daytime) == 0 {
+        if self.work_inter.dist_start(&time.daytime) <= self.departure_margin() {
             0.5
         } else {
             0.0
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3};
+    use prototypes::{GameTime, RecTimeInterval, Tick, TICKS_PER_HOUR, TICKS_PER_MINUTE};
+
+    use crate::economy::TripStats;
+    use crate::souls::desire::{Work, WorkKind};
+    use crate::souls::human::spawn_human;
+    use crate::tests::TestCtx;
+
+    fn set_time(test: &mut TestCtx, tick: u64) {
+        *test.g.write::<GameTime>() = GameTime::new(Tick(tick));
+    }
+
+    #[test]
+    fn workers_leave_before_their_shift() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(20.0, -20.0));
+        let workplace = test.build_house_near(vec2(80.0, 20.0));
+
+        // the game starts at 08:00 on day 1, go to 03:00 on day 2
+        set_time(&mut test, 19 * TICKS_PER_HOUR);
+
+        let shifts = [
+            RecTimeInterval::new((8, 0), (16, 0)),
+            RecTimeInterval::new((14, 0), (22, 0)),
+        ];
+        for i in 0..10 {
+            let human = spawn_human(&mut test.g, house).unwrap();
+            test.g.world_mut_unchecked().humans[human].work =
+                Some(Work::new(workplace, WorkKind::Worker, shifts[i % 2], 0.0));
+        }
+
+        for _ in 0..100 {
+            test.tick();
+        }
+        assert_eq!(test.g.read::<TripStats>().today[3], 0);
+
+        // 07:55, the morning shift leaves to arrive on time
+        set_time(&mut test, 23 * TICKS_PER_HOUR + 55 * TICKS_PER_MINUTE);
+        for _ in 0..100 {
+            test.tick();
+        }
+        let trips = test.g.read::<TripStats>();
+        assert_eq!(trips.today[7], 5);
+        assert_eq!(trips.today.iter().sum::<u32>(), 5);
+    }
+}
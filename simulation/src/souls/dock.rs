@@ -0,0 +1,435 @@
+use serde::{Deserialize, Serialize};
+
+use geom::{Transform, Vec2};
+use prototypes::{DockPrototypeID, GameTime, DELTA, TICKS_PER_MINUTE, TICKS_PER_SECOND};
+
+use crate::map::{BuildingID, Environment, Map, WATER_LEVEL};
+use crate::map_dynamic::BuildingInfos;
+use crate::transportation::waterway::{dock_mooring, is_navigable, route_is_open, waterway_route};
+use crate::utils::resources::Resources;
+use crate::world::{DockEnt, DockID};
+use crate::World;
+use crate::{ParCommandBuffer, Simulation, SoulID};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoatState {
+    /// The boat waits at a dock, its own dock unless the way back was cut
+    Moored(DockID),
+    /// The boat carries cargo to a dock
+    Delivering(DockID),
+    /// The boat sails back to its own dock
+    Returning,
+    /// No dock can be reached from where the boat is, it waits for the water to open again
+    Stranded,
+}
+debug_inspect_impl!(BoatState);
+
+const BOATS_PER_DOCK: usize = 2;
+/// A boat leaves when that much cargo is waiting at its dock
+const MIN_BOAT_LOAD: u32 = 10;
+
+/// A cargo boat, it belongs to the dock that launched it
+#[derive(Serialize, Deserialize, Inspect)]
+pub struct Boat {
+    pub trans: Transform,
+    pub state: BoatState,
+    pub cargo: u32,
+    /// Waypoints left to sail, the next one is last
+    pub path: Vec<Vec2>,
+}
+
+/// A dock on the shore
+/// A component that identifies dock souls, managing the cargo boats sailing
+/// from it to the other docks.
+#[derive(Serialize, Deserialize, Inspect)]
+pub struct Dock {
+    pub proto: DockPrototypeID,
+    pub building: BuildingID,
+    /// Where the boats of the dock wait on the water
+    pub mooring: Option<Vec2>,
+    pub boats: Vec<Boat>,
+    pub waiting_cargo: u32,
+    pub wanted_cargo: u32,
+}
+
+pub fn dock_soul(
+    sim: &mut Simulation,
+    building: BuildingID,
+    proto: DockPrototypeID,
+) -> Option<DockID> {
+    let map = sim.map();
+    let b = map.buildings.get(building)?;
+
+    let d = Dock {
+        proto,
+        building,
+        mooring: dock_mooring(&map.environment, &b.obb),
+        boats: Vec::with_capacity(BOATS_PER_DOCK),
+        waiting_cargo: 0,
+        wanted_cargo: 0,
+    };
+
+    let height = b.height;
+    let obb = b.obb;
+    let pos = obb.center();
+    let axis = obb.axis();
+
+    drop(map);
+
+    let id = sim.world.insert(DockEnt {
+        d,
+        trans: Transform::new_dir(pos.z(height), axis[1].z(0.0).normalize()),
+    });
+
+    sim.write::<BuildingInfos>()
+        .set_owner(building, SoulID::Dock(id));
+
+    Some(id)
+}
+
+/// Height the boats float at, the level of the lake or sea they are on
+fn surface(env: &Environment, pos: Vec2) -> f32 {
+    env.water_level(pos).unwrap_or(WATER_LEVEL)
+}
+
+/// Route from the boat to a dock, the path is reversed so that the next waypoint is last
+fn route_to(env: &Environment, boat: &Boat, mooring: Vec2) -> Option<Vec<Vec2>> {
+    let mut path = waterway_route(env, boat.trans.pos.xy(), mooring)?;
+    path.reverse();
+    Some(path)
+}
+
+/// Sends the boat to the dock if it can be reached, or to the nearest dock that can
+fn reroute(
+    env: &Environment,
+    boat: &mut Boat,
+    home: DockID,
+    target: DockID,
+    moorings: &[(DockID, Vec2, u32)],
+) {
+    let pos = boat.trans.pos.xy();
+    let mut candidates: Vec<_> = moorings.iter().filter(|x| x.0 != target).collect();
+    candidates.sort_by(|a, b| a.1.distance2(pos).total_cmp(&b.1.distance2(pos)));
+
+    let target = moorings.iter().find(|x| x.0 == target);
+    for &&(id, mooring, _) in target.iter().chain(candidates.iter()) {
+        let Some(path) = route_to(env, boat, mooring) else {
+            continue;
+        };
+        boat.path = path;
+        boat.state = if id == home {
+            BoatState::Returning
+        } else {
+            BoatState::Delivering(id)
+        };
+        return;
+    }
+
+    boat.path.clear();
+    boat.state = BoatState::Stranded;
+}
+
+pub fn dock_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("souls::dock_system");
+    let cbuf = resources.read::<ParCommandBuffer<DockEnt>>();
+    let map = resources.read::<Map>();
+    let time = resources.read::<GameTime>();
+    let env = &map.environment;
+    let every_second = time.tick.0 % TICKS_PER_SECOND == 0;
+    let every_minute = time.tick.0 % TICKS_PER_MINUTE == 0;
+
+    let moorings: Vec<(DockID, Vec2, u32)> = world
+        .docks
+        .iter()
+        .filter_map(|(id, d)| Some((id, d.d.mooring?, d.d.wanted_cargo)))
+        .collect();
+    let mut delivered = vec![];
+
+    for (me, ent) in world.docks.iter_mut() {
+        let dock = &mut ent.d;
+        let Some(building) = map.buildings.get(dock.building) else {
+            cbuf.kill(me);
+            continue;
+        };
+
+        // terraforming can dry the mooring or flood the shore
+        if every_minute && !dock.mooring.map_or(false, |m| is_navigable(env, m)) {
+            dock.mooring = dock_mooring(env, &building.obb);
+        }
+        let Some(home) = dock.mooring else {
+            continue;
+        };
+
+        while dock.boats.len() < BOATS_PER_DOCK {
+            dock.boats.push(Boat {
+                trans: Transform::new_dir(home.z(surface(env, home)), ent.trans.dir),
+                state: BoatState::Moored(me),
+                cargo: 0,
+                path: vec![],
+            });
+        }
+
+        let speed = dock.proto.prototype().boat_speed;
+        let capacity = dock.proto.prototype().boat_capacity;
+
+        for boat in &mut dock.boats {
+            match boat.state {
+                BoatState::Delivering(target) | BoatState::Moored(target) if target != me => {
+                    if !moorings.iter().any(|x| x.0 == target) {
+                        reroute(env, boat, me, me, &moorings);
+                    }
+                }
+                _ => {}
+            }
+
+            match boat.state {
+                BoatState::Moored(at) => {
+                    if at != me && every_minute {
+                        if let Some(path) = route_to(env, boat, home) {
+                            boat.path = path;
+                            boat.state = BoatState::Returning;
+                        }
+                    }
+                    continue;
+                }
+                BoatState::Stranded => {
+                    if every_minute {
+                        reroute(env, boat, me, me, &moorings);
+                    }
+                    continue;
+                }
+                BoatState::Delivering(target) => {
+                    // the way can be closed by terraforming while sailing
+                    if every_second
+                        && !route_is_open(env, boat.trans.pos, boat.path.iter().rev().copied())
+                    {
+                        reroute(env, boat, me, target, &moorings);
+                        continue;
+                    }
+                }
+                BoatState::Returning => {
+                    if every_second
+                        && !route_is_open(env, boat.trans.pos, boat.path.iter().rev().copied())
+                    {
+                        reroute(env, boat, me, me, &moorings);
+                        continue;
+                    }
+                }
+            }
+
+            let mut step = speed * DELTA;
+            while let Some(&next) = boat.path.last() {
+                let pos = boat.trans.pos.xy();
+                let d = next - pos;
+                let dist = d.mag();
+                if let Some(dir) = d.try_normalize() {
+                    boat.trans.dir = dir.z0();
+                }
+                if dist > step {
+                    let p = pos + d * (step / dist);
+                    boat.trans.pos = p.z(surface(env, p));
+                    break;
+                }
+                boat.trans.pos = next.z(surface(env, next));
+                step -= dist;
+                boat.path.pop();
+            }
+            if !boat.path.is_empty() {
+                continue;
+            }
+
+            match boat.state {
+                BoatState::Delivering(target) => {
+                    delivered.push((target, boat.cargo));
+                    boat.cargo = 0;
+                    boat.state = BoatState::Moored(target);
+                    if let Some(path) = route_to(env, boat, home) {
+                        boat.path = path;
+                        boat.state = BoatState::Returning;
+                    }
+                }
+                BoatState::Returning => {
+                    // cargo that could not be delivered goes back to wait at the dock
+                    dock.waiting_cargo += boat.cargo;
+                    boat.cargo = 0;
+                    boat.state = BoatState::Moored(me);
+                }
+                _ => {}
+            }
+        }
+
+        // If enough goods are waiting, load a boat for the dock that wants the most of them
+        if !every_second || dock.waiting_cargo < MIN_BOAT_LOAD {
+            continue;
+        }
+        let Some(boat) = dock
+            .boats
+            .iter_mut()
+            .find(|b| b.state == BoatState::Moored(me))
+        else {
+            continue;
+        };
+
+        let mut destinations: Vec<_> = moorings.iter().filter(|x| x.0 != me).collect();
+        destinations.sort_by_key(|x| std::cmp::Reverse(x.2));
+        for &&(id, mooring, _) in &destinations {
+            let Some(path) = route_to(env, boat, mooring) else {
+                continue;
+            };
+            let load = dock.waiting_cargo.min(capacity);
+            dock.waiting_cargo -= load;
+            boat.cargo = load;
+            boat.path = path;
+            boat.state = BoatState::Delivering(id);
+            break;
+        }
+    }
+
+    for (target, cargo) in delivered {
+        if let Some(d) = world.docks.get_mut(target) {
+            d.d.wanted_cargo = d.d.wanted_cargo.saturating_sub(cargo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, Vec2, OBB};
+    use prototypes::{BuildingGen, DockPrototypeID};
+
+    use crate::map::{BuildingKind, WATER_LEVEL};
+    use crate::map_dynamic::BuildingInfos;
+    use crate::souls::dock::BoatState;
+    use crate::tests::TestCtx;
+    use crate::{SoulID, WorldCommand};
+
+    #[test]
+    fn boats_carry_cargo_across_the_water() {
+        let mut test = TestCtx::new();
+
+        // a lake with land on both sides
+        {
+            let mut map = test.g.map_mut();
+            let bounds = map.environment.bounds();
+            map.environment.terrain_apply(bounds, |p| {
+                if p.x > 100.0 && p.x < 400.0 {
+                    -20.0
+                } else {
+                    5.0
+                }
+            });
+        }
+
+        let build_dock = |test: &mut TestCtx, x: f32| {
+            test.apply(&[WorldCommand::MapBuildSpecialBuilding {
+                pos: OBB::new(vec2(x, 300.0), Vec2::X, 30.0, 20.0),
+                kind: BuildingKind::Dock(DockPrototypeID::new("dock")),
+                gen: BuildingGen::NoWalkway {
+                    door_pos: vec2(x, 300.0),
+                },
+                zone: None,
+                connected_road: None,
+            }]);
+            test.tick();
+
+            let map = test.g.map();
+            let b = map
+                .buildings()
+                .iter()
+                .find(|(_, b)| matches!(b.kind, BuildingKind::Dock(_)) && b.obb.center().x == x)
+                .unwrap()
+                .0;
+            let Some(SoulID::Dock(dock)) = test.g.read::<BuildingInfos>().owner(b) else {
+                panic!("dock should have a soul")
+            };
+            dock
+        };
+
+        let from = build_dock(&mut test, 80.0);
+        let to = build_dock(&mut test, 420.0);
+
+        {
+            let mut world = test.g.world_mut_unchecked();
+            world.docks.get_mut(from).unwrap().d.waiting_cargo = 50;
+            world.docks.get_mut(to).unwrap().d.wanted_cargo = 50;
+        }
+
+        for _ in 0..5000 {
+            test.tick();
+            if test.g.get(to).unwrap().d.wanted_cargo == 0 {
+                let dock = &test.g.get(from).unwrap().d;
+                assert_eq!(dock.waiting_cargo, 0);
+                assert!(dock.boats.iter().all(|b| b.trans.pos.z == WATER_LEVEL));
+                return;
+            }
+        }
+
+        panic!(
+            "boat should have delivered, states: {:?}",
+            test.g
+                .get(from)
+                .unwrap()
+                .d
+                .boats
+                .iter()
+                .map(|b| b.state)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn boats_are_stranded_when_the_water_is_cut() {
+        let mut test = TestCtx::new();
+        {
+            let mut map = test.g.map_mut();
+            let bounds = map.environment.bounds();
+            map.environment.terrain_apply(bounds, |p| {
+                if p.x > 100.0 && p.x < 400.0 {
+                    -20.0
+                } else {
+                    5.0
+                }
+            });
+        }
+
+        test.apply(&[WorldCommand::MapBuildSpecialBuilding {
+            pos: OBB::new(vec2(80.0, 300.0), Vec2::X, 30.0, 20.0),
+            kind: BuildingKind::Dock(DockPrototypeID::new("dock")),
+            gen: BuildingGen::NoWalkway {
+                door_pos: vec2(80.0, 300.0),
+            },
+            zone: None,
+            connected_road: None,
+        }]);
+        test.tick();
+        test.tick();
+
+        let id = test.g.world().docks.keys().next().unwrap();
+        {
+            let mut world = test.g.world_mut_unchecked();
+            let boat = &mut world.docks.get_mut(id).unwrap().d.boats[0];
+            boat.trans.pos = vec2(350.0, 300.0).z(WATER_LEVEL);
+            boat.state = BoatState::Returning;
+            boat.path = vec![vec2(110.0, 300.0)];
+        }
+
+        // a dam between the boat and the dock
+        {
+            let mut map = test.g.map_mut();
+            let bounds = map.environment.bounds();
+            map.environment.terrain_apply(bounds, |p| {
+                if (p.x - 250.0).abs() < 40.0 {
+                    5.0
+                } else {
+                    p.z
+                }
+            });
+        }
+
+        for _ in 0..100 {
+            test.tick();
+        }
+        let state = test.g.get(id).unwrap().d.boats[0].state;
+        assert_eq!(state, BoatState::Stranded);
+    }
+}
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+
+use prototypes::{CropID, CropPrototype, GoodsCompanyPrototype, Season, TICKS_PER_SECOND};
+
+use crate::map::{FieldStage, Zone, MAX_ZONE_AREA};
+
+/// Share of the growth during which the field only shows seedlings
+const SOWN_SHARE: f64 = 0.25;
+/// The crops grow in game time, so that they follow the seasons
+const GAME_SECONDS_PER_TICK: f64 = 1.0 / TICKS_PER_SECOND as f64;
+
+/// The crop cycle on the field of a farm: the field rests, is sown, grows and is harvested.
+/// The recipe of the farm only runs during the harvest.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Field {
+    /// Crop growing, or the last one harvested while the field rests
+    pub crop: CropID,
+    pub stage: FieldStage,
+    /// In [0; 1] range, how far the rest or the growth is.
+    /// A crop grows for days of ticks, a f32 would lose the small steps.
+    pub progress: f64,
+    /// Runs of the recipe left in the harvest
+    pub to_harvest: u32,
+}
+
+impl Field {
+    /// A resting field, the first crop of the rotation is sown in its season
+    pub fn new(proto: &GoodsCompanyPrototype) -> Option<Self> {
+        Some(Self {
+            crop: *proto.crops.last()?,
+            stage: FieldStage::Fallow,
+            progress: 1.0,
+            to_harvest: 0,
+        })
+    }
+
+    /// Runs of the recipe harvested from the field of the zone, smaller fields yield less
+    pub fn expected_yield(crop: &CropPrototype, zone: Option<&Zone>) -> u32 {
+        let share = zone.map_or(1.0, |z| (z.area / MAX_ZONE_AREA).min(1.0));
+        ((crop.yield_per_field as f32 * share).ceil() as u32).max(1)
+    }
+
+    /// Runs of the recipe per tick during the harvest at that productivity,
+    /// which already counts the size of the field
+    pub fn harvest_per_tick(&self, productivity: f32) -> f32 {
+        let crop = self.crop.prototype();
+        (productivity as f64 * crop.yield_per_field as f64 * GAME_SECONDS_PER_TICK
+            / crop.harvest.seconds()) as f32
+    }
+
+    /// The crop after the current one in the rotation that can be sown in the season
+    fn next_crop(&self, proto: &GoodsCompanyPrototype, season: Option<Season>) -> Option<CropID> {
+        let n = proto.crops.len();
+        let cur = proto
+            .crops
+            .iter()
+            .position(|&c| c == self.crop)
+            .unwrap_or(n - 1);
+        (1..=n)
+            .map(|k| proto.crops[(cur + k) % n])
+            .find(|c| c.prototype().can_sow(season))
+    }
+
+    /// Rests or grows the field by one tick.
+    /// Returns whether the crop is being harvested.
+    pub fn update(
+        &mut self,
+        proto: &GoodsCompanyPrototype,
+        zone: Option<&Zone>,
+        season: Option<Season>,
+    ) -> bool {
+        match self.stage {
+            FieldStage::Fallow => {
+                let fallow = self.crop.prototype().fallow.seconds();
+                self.progress = if fallow > 0.0 {
+                    (self.progress + GAME_SECONDS_PER_TICK / fallow).min(1.0)
+                } else {
+                    1.0
+                };
+                if self.progress < 1.0 {
+                    return false;
+                }
+                let Some(crop) = self.next_crop(proto, season) else {
+                    return false;
+                };
+                self.crop = crop;
+                self.stage = FieldStage::Sown;
+                self.progress = 0.0;
+            }
+            FieldStage::Sown | FieldStage::Growing => {
+                let crop = self.crop.prototype();
+                self.progress += GAME_SECONDS_PER_TICK / crop.growth.seconds();
+                if self.progress >= 1.0 {
+                    self.stage = FieldStage::Ripe;
+                    self.progress = 0.0;
+                    self.to_harvest = Self::expected_yield(crop, zone);
+                } else if self.progress >= SOWN_SHARE {
+                    self.stage = FieldStage::Growing;
+                }
+            }
+            FieldStage::Ripe => {}
+        }
+        self.stage == FieldStage::Ripe
+    }
+
+    /// One run of the recipe was harvested, the field rests once everything is in
+    pub fn harvested(&mut self) {
+        self.to_harvest = self.to_harvest.saturating_sub(1);
+        if self.to_harvest == 0 {
+            self.stage = FieldStage::Fallow;
+            self.progress = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prototypes::{GoodsCompanyID, Season};
+
+    use super::*;
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn fields_are_sown_in_season_and_rest_after_the_harvest() {
+        let _test = TestCtx::new();
+        let farm = GoodsCompanyID::new("cereal-farm").prototype();
+        let mut field = Field::new(farm).unwrap();
+
+        // wheat is sown in spring and autumn only
+        assert!(!field.update(farm, None, Some(Season::Summer)));
+        assert_eq!(field.stage, FieldStage::Fallow);
+        assert!(!field.update(farm, None, Some(Season::Autumn)));
+        assert_eq!(field.stage, FieldStage::Sown);
+
+        // once sown it grows whatever the season
+        let crop = field.crop.prototype();
+        let growth_ticks = crop.growth.0 .0;
+        for _ in 0..growth_ticks / 2 {
+            field.update(farm, None, Some(Season::Winter));
+        }
+        assert_eq!(field.stage, FieldStage::Growing);
+        for _ in 0..growth_ticks / 2 + 2 {
+            field.update(farm, None, Some(Season::Winter));
+        }
+        assert_eq!(field.stage, FieldStage::Ripe);
+        assert!(field.update(farm, None, Some(Season::Winter)));
+        assert_eq!(field.to_harvest, crop.yield_per_field);
+
+        for _ in 0..crop.yield_per_field {
+            field.harvested();
+        }
+        assert_eq!(field.stage, FieldStage::Fallow);
+        assert!(!field.update(farm, None, Some(Season::Spring)));
+    }
+}
@@ -5,7 +5,7 @@ use prototypes::{FreightStationPrototypeID, GameTime};
 
 use crate::map::{BuildingID, Map, PathKind};
 use crate::map_dynamic::{
-    BuildingInfos, DispatchID, DispatchKind, DispatchQueryTarget, Dispatcher, Itinerary,
+    BuildingInfos, DispatchID, DispatchKind, DispatchQueryTarget, Dispatcher, Itinerary, PathJobs,
 };
 use crate::utils::resources::Resources;
 use crate::world::{FreightStationEnt, FreightStationID, TrainID};
@@ -74,6 +74,7 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("souls::freight_station_system");
     let cbuf = resources.read::<ParCommandBuffer<FreightStationEnt>>();
     let mut dispatch = resources.write::<Dispatcher>();
+    let mut jobs = resources.write::<PathJobs>();
     let map = resources.read::<Map>();
     let time = resources.read::<GameTime>();
     let tick = time.tick;
@@ -109,9 +110,14 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
                         let ext = *map.external_train_stations.first().unwrap();
                         let bpos = map.buildings[ext].obb.center().z(0.0);
 
-                        *itin = if let Some(r) =
-                            Itinerary::route(tick, train.trans.pos, bpos, &map, PathKind::Rail)
-                        {
+                        *itin = if let Some(r) = Itinerary::route_async(
+                            tick,
+                            train.trans.pos,
+                            bpos,
+                            &map,
+                            PathKind::Rail,
+                            &mut jobs,
+                        ) {
                             r
                         } else {
                             Itinerary::wait_until(time.timestamp + 10.0);
@@ -153,7 +159,14 @@ pub fn freight_station_system(world: &mut World, resources: &mut Resources) {
         let train = world.trains.get_mut(trainid).unwrap();
 
         train.it = unwrap_or!(
-            Itinerary::route(tick, train.trans.pos, destination, &map, PathKind::Rail,),
+            Itinerary::route_async(
+                tick,
+                train.trans.pos,
+                destination,
+                &map,
+                PathKind::Rail,
+                &mut jobs
+            ),
             continue
         );
 
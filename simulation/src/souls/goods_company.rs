@@ -1,22 +1,29 @@
+use std::collections::VecDeque;
+
 use serde::{Deserialize, Serialize};
 
 use egui_inspect::Inspect;
 use geom::{Transform, Vec2};
 use prototypes::{
-    CompanyKind, GoodsCompanyID, GoodsCompanyPrototype, ItemID, Power, Recipe, DELTA,
+    try_prototype, BuildingUpgrade, CompanyKind, EntranceKind, GameTime, GoodsCompanyID,
+    GoodsCompanyPrototype, ItemID, Money, Power, Recipe, DELTA,
 };
 
 use crate::economy::{find_trade_place, Market};
-use crate::map::{Building, BuildingID, Map, Zone, MAX_ZONE_AREA};
+use crate::map::{Building, BuildingID, BuildingKind, Map, Zone, MAX_ZONE_AREA};
 use crate::map_dynamic::{BuildingInfos, ElectricityFlow};
+use crate::rules::GameRules;
+use crate::souls::commute::estimate_trip;
+use crate::souls::delivery::{consolidate, set_deliver_order, Shipment};
 use crate::souls::desire::WorkKind;
-use crate::transportation::{spawn_parked_vehicle, VehicleKind};
+use crate::transportation::{spawn_parked_vehicle, Location, VehicleKind};
 use crate::utils::resources::Resources;
 use crate::world::{CompanyEnt, HumanEnt, HumanID, VehicleID};
 use crate::{ParCommandBuffer, SoulID, VehicleEnt};
-use crate::{Simulation, World};
+use crate::{Simulation, SimulationOptions, World};
 
 use super::desire::Work;
+use super::farm::Field;
 
 pub fn recipe_init(recipe: &Recipe, soul: SoulID, near: Vec2, market: &mut Market) {
     for item in &recipe.consumption {
@@ -57,6 +64,42 @@ pub fn recipe_act(recipe: &Recipe, soul: SoulID, near: Vec2, market: &mut Market
     }
 }
 
+/// Number of days of profit and loss kept for each company
+pub const PNL_HISTORY_DAYS: usize = 14;
+
+/// Profit and loss of a company, with goods valued at their market price.
+/// Only bookkeeping, the actual money still goes through the market and the government.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CompanyFinances {
+    /// Since the start of the day
+    pub today: Money,
+    /// Profit of the previous days, oldest first
+    pub history: VecDeque<Money>,
+    /// Number of days in a row the company lost money
+    pub losing_days: u32,
+}
+
+impl CompanyFinances {
+    /// Archives the profit of the day and starts a new one
+    pub fn close_day(&mut self) {
+        let profit = std::mem::replace(&mut self.today, Money::ZERO);
+        if self.history.len() >= PNL_HISTORY_DAYS {
+            self.history.pop_front();
+        }
+        self.history.push_back(profit);
+        if profit < Money::ZERO {
+            self.losing_days += 1;
+        } else {
+            self.losing_days = 0;
+        }
+    }
+
+    /// Profit of the last complete day
+    pub fn yesterday(&self) -> Option<Money> {
+        self.history.back().copied()
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Inspect)]
 pub struct GoodsCompanyState {
     pub proto: GoodsCompanyID,
@@ -66,6 +109,17 @@ pub struct GoodsCompanyState {
     pub progress: f32,
     pub driver: Option<HumanID>,
     pub trucks: Vec<VehicleID>,
+    #[serde(default)]
+    #[inspect(skip)]
+    pub finances: CompanyFinances,
+    /// Goods sold that wait for a truck, oldest first
+    #[serde(default)]
+    #[inspect(skip)]
+    pub orders: Vec<Shipment>,
+    /// Crop cycle of the farms
+    #[serde(default)]
+    #[inspect(skip)]
+    pub field: Option<Field>,
 }
 
 impl CompanyEnt {
@@ -89,10 +143,11 @@ impl CompanyEnt {
         zone: Option<&Zone>,
         map: &Map,
         elec_flow: &ElectricityFlow,
+        rules: &GameRules,
     ) -> f32 {
         let p = self.raw_productivity(proto, zone);
 
-        if proto.power_consumption > Some(Power::ZERO) {
+        if rules.utilities_required && proto.power_consumption > Some(Power::ZERO) {
             if let Some(net_id) = map.electricity.net_id(self.comp.building) {
                 if elec_flow.blackout(net_id) {
                     return 0.0;
@@ -114,6 +169,7 @@ pub fn company_soul(
     let map = sim.map();
     let b = map.buildings().get(build_id)?;
     let door_pos = b.door_pos;
+    let freight_pos = b.entrance(EntranceKind::Freight, door_pos);
     let obb = b.obb;
     let height = b.height;
     drop(map);
@@ -122,7 +178,7 @@ pub fn company_soul(
     let mut trucks = vec![];
     if ckind == CompanyKind::Factory {
         for _ in 0..proto.n_trucks {
-            trucks.extend(spawn_parked_vehicle(sim, VehicleKind::Truck, door_pos))
+            trucks.extend(spawn_parked_vehicle(sim, VehicleKind::Truck, freight_pos))
         }
         if trucks.len() as u32 != proto.n_trucks {
             for truck in trucks {
@@ -139,6 +195,9 @@ pub fn company_soul(
         progress: 0.0,
         driver: None,
         trucks,
+        finances: Default::default(),
+        orders: vec![],
+        field: Field::new(proto),
     };
 
     let id = sim.world.insert(CompanyEnt {
@@ -171,6 +230,173 @@ pub fn company_soul(
     Some(soul)
 }
 
+/// Radius around a building in which inhabitants count towards its upgrade conditions
+pub const UPGRADE_POPULATION_RADIUS: f32 = 500.0;
+
+/// A condition of an upgrade that is not met yet
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UpgradeBlocker {
+    NotEnoughPopulation { has: u32, needs: u32 },
+    RoadTooSmall { has: u32, needs: u32 },
+}
+
+/// The upgrade available for the company in the building, if any
+pub fn company_upgrade(
+    sim: &Simulation,
+    building: BuildingID,
+) -> Option<(&'static BuildingUpgrade, &'static GoodsCompanyPrototype)> {
+    let BuildingKind::GoodsCompany(id) = sim.map().buildings().get(building)?.kind else {
+        return None;
+    };
+    let upgrade = id.prototype().upgrade.as_ref()?;
+    let to = try_prototype(upgrade.to)?;
+    let to = try_prototype(GoodsCompanyID::new(&to.name))?;
+    Some((upgrade, to))
+}
+
+/// The conditions of the upgrade the building does not meet
+pub fn upgrade_blockers(
+    sim: &Simulation,
+    building: BuildingID,
+    upgrade: &BuildingUpgrade,
+) -> Vec<UpgradeBlocker> {
+    let map = sim.map();
+    let Some(b) = map.buildings().get(building) else {
+        return vec![];
+    };
+    let mut blockers = vec![];
+
+    if upgrade.min_nearby_population > 0 {
+        let center = b.obb.center();
+        let has = sim
+            .world
+            .humans
+            .values()
+            .filter_map(|h| map.buildings().get(h.home.house))
+            .filter(|home| home.obb.center().distance(center) < UPGRADE_POPULATION_RADIUS)
+            .count() as u32;
+        if has < upgrade.min_nearby_population {
+            blockers.push(UpgradeBlocker::NotEnoughPopulation {
+                has,
+                needs: upgrade.min_nearby_population,
+            });
+        }
+    }
+
+    if upgrade.min_road_lanes > 0 {
+        let has = b
+            .connected_road
+            .and_then(|r| map.roads().get(r))
+            .map_or(0, |r| r.n_lanes() as u32);
+        if has < upgrade.min_road_lanes {
+            blockers.push(UpgradeBlocker::RoadTooSmall {
+                has,
+                needs: upgrade.min_road_lanes,
+            });
+        }
+    }
+
+    blockers
+}
+
+/// Upgrades the company in the building in place.
+/// Workers are kept as long as the new prototype has room for them and
+/// the stock is cut down to the new storage capacity.
+/// Returns false if the upgrade is not available or the new footprint does not fit.
+pub fn upgrade_company(sim: &mut Simulation, building: BuildingID) -> bool {
+    let Some((upgrade, to)) = company_upgrade(sim, building) else {
+        return false;
+    };
+    if !upgrade_blockers(sim, building, upgrade).is_empty() {
+        return false;
+    }
+
+    if !sim.map_mut().upgrade_building(
+        building,
+        BuildingKind::GoodsCompany(to.id),
+        to.bgen,
+        to.size.w,
+        to.size.h,
+    ) {
+        return false;
+    }
+
+    let Some(SoulID::GoodsCompany(id)) = sim.read::<BuildingInfos>().owner(building) else {
+        // the company will be created with the new prototype
+        return true;
+    };
+    let map = sim.map();
+    let b = &map.buildings()[building];
+    let (door_pos, freight_pos) = (b.door_pos, b.entrance(EntranceKind::Freight, b.door_pos));
+    drop(map);
+
+    let Some(c) = sim.world.companies.get_mut(id) else {
+        return true;
+    };
+    c.comp.proto = to.id;
+    c.comp.max_workers = to.n_workers;
+
+    let kept = c.workers.0.len().min(to.n_workers as usize);
+    let fired = c.workers.0.split_off(kept);
+    if c.comp.driver.is_some_and(|d| fired.contains(&d)) {
+        c.comp.driver = None;
+    }
+    let n_workers = c.workers.0.len() as i32;
+    let n_trucks = c.comp.trucks.len() as u32;
+
+    fire_workers(sim, fired);
+
+    if to.kind == CompanyKind::Factory {
+        for _ in n_trucks..to.n_trucks {
+            let Some(truck) = spawn_parked_vehicle(sim, VehicleKind::Truck, freight_pos) else {
+                break;
+            };
+            if let Some(c) = sim.world.companies.get_mut(id) {
+                c.comp.trucks.push(truck);
+            }
+        }
+    }
+
+    let job_opening = ItemID::new("job-opening");
+    let soul = SoulID::GoodsCompany(id);
+    let mut m = sim.write::<Market>();
+
+    let openings = to.n_workers as i32 - n_workers;
+    let delta = openings - m.capital(soul, job_opening);
+    m.produce(soul, job_opening, delta);
+    m.sell_all(soul, door_pos.xy(), job_opening, 0);
+
+    if let Some(ref r) = to.recipe {
+        for item in &r.production {
+            let capacity = item.amount * (r.storage_multiplier + 1);
+            let stock = m.capital(soul, item.id);
+            if stock > capacity {
+                m.produce(soul, item.id, capacity - stock);
+            }
+        }
+        recipe_init(r, soul, door_pos.xy(), &mut m);
+    }
+
+    true
+}
+
+/// Removes the job of the humans and makes them look for a new one
+pub fn fire_workers(sim: &mut Simulation, fired: Vec<HumanID>) {
+    let job_opening = ItemID::new("job-opening");
+    for human in fired {
+        let Some(h) = sim.world.humans.get_mut(human) else {
+            continue;
+        };
+        h.work = None;
+        let house = h.home.house;
+        let Some(home) = sim.map().buildings().get(house).map(|b| b.door_pos) else {
+            continue;
+        };
+        sim.write::<Market>()
+            .buy(SoulID::Human(human), home.xy(), job_opening, 1);
+    }
+}
+
 pub fn company_system(world: &mut World, res: &mut Resources) {
     profiling::scope!("souls::company_system");
     let cbuf: &ParCommandBuffer<CompanyEnt> = &res.read();
@@ -179,6 +405,10 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
     let market: &Market = &res.read();
     let map: &Map = &res.read();
     let elec_flow: &ElectricityFlow = &res.read();
+    let rules: &GameRules = &res.read();
+    let season = res
+        .read::<GameTime>()
+        .season(res.read::<SimulationOptions>().season_days);
 
     world.companies.iter_mut().for_each(|(me, c)| {
         let soul = SoulID::GoodsCompany(me);
@@ -189,18 +419,85 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
 
         let proto = c.comp.proto.prototype();
 
+        // the company was upgraded, or comes from a save without fields
+        if proto.crops.is_empty() {
+            c.comp.field = None;
+        } else if c.comp.field.is_none() {
+            c.comp.field = Field::new(proto);
+        }
+
         if let Some(recipe) = &proto.recipe {
-            if recipe_should_produce(recipe, soul, market) {
-                let productivity = c.productivity(proto, b.zone.as_ref(), map, elec_flow);
+            let has_wood = proto
+                .harvest_radius
+                .map_or(true, |r| map.has_harvestable_tree(b.obb.center(), r));
+            // mines slow down as their deposit empties, and stop once it is exhausted
+            let richness = proto.deposit.map_or(1.0, |kind| {
+                map.deposit_under(&b.obb, kind)
+                    .map_or(0.0, |d| d.richness())
+            });
 
-                c.comp.progress += productivity * DELTA / recipe.duration.seconds() as f32;
+            let harvesting = match c.comp.field {
+                Some(ref mut field) => field.update(proto, b.zone.as_ref(), season),
+                None => true,
+            };
+            if let (Some(field), Some(zone)) = (&c.comp.field, &b.zone) {
+                if field.stage != zone.stage {
+                    let (building, stage) = (c.comp.building, field.stage);
+                    cbuf.exec_ent(me, move |sim| {
+                        sim.map_mut().set_field_stage(building, stage)
+                    });
+                }
+            }
+
+            if has_wood
+                && richness > 0.0
+                && harvesting
+                && recipe_should_produce(recipe, soul, market)
+            {
+                let productivity = c.productivity(proto, b.zone.as_ref(), map, elec_flow, rules);
+
+                let productivity = productivity * proto.seasonal_output(season) * richness;
+
+                c.comp.progress += match c.comp.field {
+                    Some(ref field) => field.harvest_per_tick(productivity),
+                    None => productivity * DELTA / recipe.duration.seconds() as f32,
+                };
             }
 
             if c.comp.progress >= 1.0 {
                 c.comp.progress -= 1.0;
+                if let Some(ref mut field) = c.comp.field {
+                    field.harvested();
+                }
                 let kind = c.comp.proto;
                 let bpos = b.door_pos;
 
+                if let Some(radius) = proto.harvest_radius {
+                    let center = b.obb.center();
+                    cbuf.exec_ent(me, move |sim| {
+                        // a neighbouring company may have cut the last tree in the meantime
+                        if !sim.map_mut().harvest_tree(center, radius) {
+                            return;
+                        }
+                        let recipe = kind.prototype().recipe.as_ref().unwrap();
+                        recipe_act(recipe, soul, bpos.xy(), &mut sim.write::<Market>());
+                    });
+                    return;
+                }
+
+                if let Some(deposit) = proto.deposit {
+                    let obb = b.obb;
+                    cbuf.exec_ent(me, move |sim| {
+                        // another company on the same deposit may have emptied it in the meantime
+                        if !sim.map_mut().extract_deposit(&obb, deposit) {
+                            return;
+                        }
+                        let recipe = kind.prototype().recipe.as_ref().unwrap();
+                        recipe_act(recipe, soul, bpos.xy(), &mut sim.write::<Market>());
+                    });
+                    return;
+                }
+
                 cbuf.exec_on(me, move |market| {
                     let recipe = kind.prototype().recipe.as_ref().unwrap();
                     recipe_act(recipe, soul, bpos.xy(), market);
@@ -214,56 +511,87 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
                 if let Some(owner_build) = find_trade_place(trade.seller, binfos) {
                     cbuf.exec_ent(me, move |sim| {
                         let (world, res) = sim.world_res();
-                        if let Some(SoulID::FreightStation(owner)) =
-                            res.read::<BuildingInfos>().owner(owner_build)
-                        {
-                            if let Some(f) = world.freight_stations.get_mut(owner) {
-                                f.f.wanted_cargo += 1;
+                        match res.read::<BuildingInfos>().owner(owner_build) {
+                            Some(SoulID::FreightStation(owner)) => {
+                                if let Some(f) = world.freight_stations.get_mut(owner) {
+                                    f.f.wanted_cargo += 1;
+                                }
+                            }
+                            Some(SoulID::Dock(owner)) => {
+                                if let Some(d) = world.docks.get_mut(owner) {
+                                    d.d.wanted_cargo += 1;
+                                }
                             }
+                            _ => {}
                         }
                     });
                 }
             }
         }
 
+        if !c.comp.trucks.is_empty() {
+            for trade in c.sold.0.drain(..) {
+                let Some(to) = find_trade_place(trade.buyer, binfos) else {
+                    log::warn!("can't find the place to deliver {:?}", &trade);
+                    continue;
+                };
+                c.comp.orders.push(Shipment {
+                    kind: trade.kind,
+                    qty: trade.qty,
+                    to,
+                });
+            }
+        }
+
         (|| {
             let Some(driver) = c.comp.driver else {
                 return;
             };
-            let Some(w) = world.humans.get(driver).and_then(|h| h.work.as_ref()) else {
+            let Some(h) = world.humans.get(driver) else {
                 return;
             };
-            if !matches!(
-                w.kind,
-                WorkKind::Driver {
-                    deliver_order: None,
-                    ..
-                }
-            ) {
+            let Some(w) = h.work.as_ref() else {
+                return;
+            };
+            let WorkKind::Driver {
+                deliver_order: None,
+                truck,
+            } = w.kind
+            else {
+                return;
+            };
+            if h.location != Location::Building(c.comp.building) {
                 return;
             }
-            let Some(trade) = c.sold.0.pop() else {
+            let Some(v) = world.vehicles.get(truck) else {
                 return;
             };
-            let Some(owner_build) = find_trade_place(trade.buyer, binfos) else {
-                log::warn!("driver can't find the place to deliver for {:?}", &trade);
+
+            // the previous driver left before the end of the route, it is resumed
+            if let Some(next) = v.vehicle.cargo.first().map(|s| s.to) {
+                cbuf.exec_ent(me, move |sim| set_deliver_order(sim, driver, Some(next)));
+                return;
+            }
+
+            let trip = consolidate(
+                b.door_pos.xy(),
+                v.vehicle.kind.capacity(),
+                &mut c.comp.orders,
+                |to| map.buildings.get(to).map(|b| b.door_pos.xy()),
+            );
+            let Some(first) = trip.first().map(|s| s.to) else {
                 return;
             };
             cbuf.exec_ent(me, move |sim| {
-                let Some(h) = sim.world.humans.get_mut(driver) else {
-                    return;
-                };
-                let Some(w) = h.work.as_mut() else {
-                    return;
-                };
-                let WorkKind::Driver { deliver_order, .. } = &mut w.kind else {
+                let Some(v) = sim.world.vehicles.get_mut(truck) else {
                     return;
                 };
-                *deliver_order = Some(owner_build)
+                v.vehicle.cargo = trip;
+                set_deliver_order(sim, driver, Some(first));
             });
         })();
 
-        for &worker in c.workers.0.iter() {
+        for (i, &worker) in c.workers.0.iter().enumerate() {
             let Some(w) = world.humans.get(worker) else {
                 continue;
             };
@@ -283,15 +611,131 @@ pub fn company_system(world: &mut World, res: &mut Resources) {
                 }
 
                 let offset = common::rand::randu(common::hash_u64(worker) as u32);
+                let shift = proto.shift(i);
 
                 let b = c.comp.building;
+                let travel_estimate =
+                    estimate_trip(map, w.home.house, b, w.router.personal_car.is_some());
                 cbuf_human.exec_ent(worker, move |sim| {
                     let Some(w) = sim.world.humans.get_mut(worker) else {
                         return;
                     };
-                    w.work = Some(Work::new(b, kind, offset));
+                    let mut work = Work::new(b, kind, shift, offset);
+                    work.travel_estimate = travel_estimate;
+                    w.work = Some(work);
                 });
             }
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3, Vec2, OBB};
+    use prototypes::{GoodsCompanyID, RecTimeInterval};
+
+    use crate::map::{BuildingID, BuildingKind};
+    use crate::map_dynamic::BuildingInfos;
+    use crate::souls::desire::{Work, WorkKind};
+    use crate::souls::human::spawn_human;
+    use crate::tests::TestCtx;
+    use crate::{SoulID, WorldCommand};
+
+    fn build_bakery(test: &mut TestCtx, center: Vec2) -> BuildingID {
+        let proto = GoodsCompanyID::new("bakery").prototype();
+        let id = test
+            .g
+            .map_mut()
+            .build_special_building(
+                &OBB::new(center, Vec2::Y, proto.size.w, proto.size.h),
+                BuildingKind::GoodsCompany(proto.id),
+                proto.bgen,
+                None,
+                None,
+            )
+            .unwrap();
+        test.g.write::<BuildingInfos>().insert(id);
+        id
+    }
+
+    #[test]
+    fn upgrade_keeps_employees() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(20.0, -20.0));
+        let humans: Vec<_> = (0..20)
+            .map(|_| spawn_human(&mut test.g, house).unwrap())
+            .collect();
+
+        let bakery = build_bakery(&mut test, vec2(60.0, 40.0));
+        test.tick();
+
+        let Some(SoulID::GoodsCompany(comp)) = test.g.read::<BuildingInfos>().owner(bakery) else {
+            panic!("bakery should have a company")
+        };
+        let world = test.g.world_mut_unchecked();
+        for &human in &humans[..2] {
+            world.humans[human].work = Some(Work::new(
+                bakery,
+                WorkKind::Worker,
+                RecTimeInterval::new((8, 0), (18, 0)),
+                0.0,
+            ));
+            world.companies[comp].workers.0.push(human);
+        }
+        let workers = world.companies[comp].workers.0.clone();
+
+        test.apply(&[WorldCommand::UpgradeBuilding(bakery)]);
+
+        let upgraded = GoodsCompanyID::new("bakery-2").prototype();
+        assert_eq!(
+            test.g.map().buildings()[bakery].kind,
+            BuildingKind::GoodsCompany(upgraded.id)
+        );
+
+        let c = &test.g.world().companies[comp];
+        assert_eq!(c.comp.proto, upgraded.id);
+        assert_eq!(c.comp.max_workers, upgraded.n_workers);
+        assert_eq!(c.workers.0, workers);
+        for human in workers {
+            let work = test.g.world().humans[human].work.as_ref().unwrap();
+            assert_eq!(work.workplace, bakery);
+        }
+    }
+
+    #[test]
+    fn upgrade_rejected_when_footprint_overlaps() {
+        let mut test = TestCtx::new();
+        let bakery = build_bakery(&mut test, vec2(60.0, 40.0));
+
+        // right where the bigger bakery would grow
+        test.g
+            .map_mut()
+            .build_special_building(
+                &OBB::new(vec2(60.0, 49.0), Vec2::Y, 4.0, 4.0),
+                BuildingKind::TrainStation,
+                prototypes::BuildingGen::NoWalkway {
+                    door_pos: vec2(60.0, 49.0),
+                },
+                None,
+                None,
+            )
+            .unwrap();
+
+        let upgraded = GoodsCompanyID::new("bakery-2").prototype();
+        let mut map = test.g.map_mut();
+        let obb = map.buildings()[bakery].obb;
+        assert!(!map.upgrade_building(
+            bakery,
+            BuildingKind::GoodsCompany(upgraded.id),
+            upgraded.bgen,
+            upgraded.size.w,
+            upgraded.size.h,
+        ));
+        assert_eq!(
+            map.buildings()[bakery].kind,
+            BuildingKind::GoodsCompany(GoodsCompanyID::new("bakery"))
+        );
+        assert_eq!(map.buildings()[bakery].obb.center(), obb.center());
+    }
+}
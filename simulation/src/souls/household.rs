@@ -0,0 +1,355 @@
+use serde::{Deserialize, Serialize};
+use slotmapd::SecondaryMap;
+
+use prototypes::{
+    try_prototype, GameTime, ItemID, Money, RoadVehicleID, RoadVehiclePrototype, TICKS_PER_MINUTE,
+};
+
+use crate::economy::{Government, Market, WORKER_CONSUMPTION_PER_MINUTE};
+use crate::map::{BuildingID, PARKING_SPOT_LENGTH};
+use crate::rules::GameRules;
+use crate::transportation::{spawn_parked_car, Location, VehicleState};
+use crate::world::{HumanID, VehicleEnt, VehicleID};
+use crate::{ParCommandBuffer, Simulation, SoulID};
+
+/// Money a household moving in starts with
+pub const STARTING_SAVINGS: Money = Money::new_bucks(200);
+/// A household buys a car once it has saved this many times its price
+pub const CAR_SAVINGS_FACTOR: i64 = 2;
+/// Percentage of the price of a car given back when it is scrapped
+pub const SCRAP_VALUE_PERCENT: i64 = 30;
+/// Fuel and maintenance of a car
+pub const CAR_UPKEEP_PER_MINUTE: Money = Money::new_cents(1);
+
+/// The wallet shared by the people living in the same house
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Household {
+    pub money: Money,
+}
+
+impl Default for Household {
+    fn default() -> Self {
+        Self {
+            money: STARTING_SAVINGS,
+        }
+    }
+}
+
+/// Households by house, saved with the game
+#[derive(Default, Serialize, Deserialize)]
+pub struct Households {
+    households: SecondaryMap<BuildingID, Household>,
+}
+
+impl Households {
+    pub fn get(&self, house: BuildingID) -> Option<&Household> {
+        self.households.get(house)
+    }
+
+    /// The household of the house, moving in with the starting savings if there is none yet
+    pub fn get_mut(&mut self, house: BuildingID) -> Option<&mut Household> {
+        Some(self.households.entry(house)?.or_default())
+    }
+
+    /// The household moves out, the next one starts with the starting savings
+    pub fn remove(&mut self, house: BuildingID) {
+        self.households.remove(house);
+    }
+
+    pub fn money(&self, house: BuildingID) -> Money {
+        self.get(house).map_or(STARTING_SAVINGS, |h| h.money)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BuildingID, &Household)> {
+        self.households.iter()
+    }
+}
+
+/// The market price of a car
+pub fn car_price(market: &Market) -> Money {
+    let car = ItemID::new("car");
+    market
+        .iter()
+        .find(|(&id, _)| id == car)
+        .map_or(Money::ZERO, |(_, m)| m.ext_value)
+}
+
+/// The model sold at the market price of a car, the others cost more or less in proportion
+pub const STANDARD_CAR: &str = "simple_car";
+
+/// What a car of that model costs when the standard one is at the market price
+pub fn car_model_price(model: RoadVehicleID, market_price: Money) -> Money {
+    let (Some(proto), Some(standard)) = (
+        try_prototype(model),
+        try_prototype(RoadVehicleID::new(STANDARD_CAR)),
+    ) else {
+        return market_price;
+    };
+    if standard.price <= Money::ZERO {
+        return market_price;
+    }
+    market_price * (proto.price.inner() as f64 / standard.price.inner() as f64)
+}
+
+/// The car model of a household spending up to `budget`, weighted by popularity among the
+/// models it can afford, so the pricier ones only show up in the wealthier households.
+/// The same seed gives the same model. Only the cars fitting in a parking spot are picked.
+pub fn pick_car_model(budget: Money, market_price: Money, seed: u64) -> Option<RoadVehicleID> {
+    let models: Vec<&RoadVehiclePrototype> = RoadVehiclePrototype::iter()
+        .filter(|m| m.popularity > 0.0 && m.length <= PARKING_SPOT_LENGTH)
+        .collect();
+    let affordable: Vec<&RoadVehiclePrototype> = models
+        .iter()
+        .copied()
+        .filter(|m| car_model_price(m.id, market_price) <= budget)
+        .collect();
+    if affordable.is_empty() {
+        return models.iter().min_by_key(|m| m.price).map(|m| m.id);
+    }
+
+    let total: f32 = affordable.iter().map(|m| m.popularity).sum();
+    let mut r = (seed >> 40) as f32 / (1 << 24) as f32 * total;
+    for m in &affordable {
+        if r < m.popularity {
+            return Some(m.id);
+        }
+        r -= m.popularity;
+    }
+    affordable.last().map(|m| m.id)
+}
+
+/// The model a household of that house drives with that budget
+pub fn household_car_model(
+    house: BuildingID,
+    budget: Money,
+    market_price: Money,
+) -> Option<RoadVehicleID> {
+    pick_car_model(budget, market_price, common::hash_u64(house))
+}
+
+/// Every minute, pays the workers, charges the car owners and lets households buy or scrap their cars
+pub(crate) fn household_system(sim: &mut Simulation) {
+    profiling::scope!("souls::household_system");
+    if sim.read::<GameTime>().tick.0 % TICKS_PER_MINUTE != 0 {
+        return;
+    }
+    update_money(sim);
+    update_cars(sim);
+}
+
+/// The workers get their wages minus the income tax, which goes back to the government
+fn update_money(sim: &mut Simulation) {
+    let tax = WORKER_CONSUMPTION_PER_MINUTE * sim.read::<GameRules>().income_tax as f64;
+    let mut collected = Money::ZERO;
+    let mut households = sim.write::<Households>();
+    for h in sim.world.humans.values() {
+        let Some(household) = households.get_mut(h.home.house) else {
+            continue;
+        };
+        if h.work.is_some() {
+            household.money += WORKER_CONSUMPTION_PER_MINUTE - tax;
+            collected += tax;
+        }
+        if h.router.personal_car.is_some() {
+            household.money -= CAR_UPKEEP_PER_MINUTE;
+        }
+    }
+    drop(households);
+    sim.write::<Government>().money += collected;
+}
+
+/// Humans without a car buy one when their household can afford it,
+/// and broke households scrap theirs
+pub(crate) fn update_cars(sim: &mut Simulation) {
+    let car = ItemID::new("car");
+    let price = car_price(&sim.read::<Market>());
+
+    let mut to_deliver = vec![];
+    let mut to_order = vec![];
+    let mut to_scrap = vec![];
+    {
+        let households = sim.read::<Households>();
+        let market = sim.read::<Market>();
+        for (id, h) in sim.world.humans.iter() {
+            let house = h.home.house;
+            let at_home = h.location == Location::Building(house);
+            let money = households.money(house);
+
+            match h.router.personal_car {
+                None => {
+                    if h.bought.0.get(&car).is_some_and(|t| !t.is_empty()) {
+                        if at_home {
+                            to_deliver.push((id, house));
+                        }
+                        continue;
+                    }
+                    let ordered = market
+                        .iter()
+                        .find(|(&item, _)| item == car)
+                        .is_some_and(|(_, m)| m.buy_order(SoulID::Human(id)).is_some());
+                    if !ordered && money >= price * CAR_SAVINGS_FACTOR {
+                        to_order.push((id, house));
+                    }
+                }
+                Some(v) => {
+                    if money >= Money::ZERO || !at_home {
+                        continue;
+                    }
+                    let parked = sim
+                        .world
+                        .vehicles
+                        .get(v)
+                        .map_or(true, |v| matches!(v.vehicle.state, VehicleState::Parked(_)));
+                    if parked {
+                        to_scrap.push((id, house, v));
+                    }
+                }
+            }
+        }
+    }
+
+    for (id, house) in to_order {
+        let Some(pos) = sim.map().buildings().get(house).map(|b| b.door_pos) else {
+            continue;
+        };
+        sim.write::<Market>()
+            .buy(SoulID::Human(id), pos.xy(), car, 1);
+    }
+
+    for (id, house) in to_deliver {
+        deliver_car(sim, id, house, price);
+    }
+
+    for (id, house, v) in to_scrap {
+        let value = sim
+            .world
+            .vehicles
+            .get(v)
+            .and_then(|v| v.vehicle.model)
+            .map_or(price, |model| car_model_price(model, price));
+        scrap_car(sim, id, v);
+        if let Some(household) = sim.write::<Households>().get_mut(house) {
+            household.money += value * SCRAP_VALUE_PERCENT / 100;
+        }
+    }
+}
+
+/// Parks the bought car near the home, the purchase waits if there is no parking spot.
+/// The market sold a standard car, the household settles the difference with the model it picks
+/// with what is left of its savings.
+fn deliver_car(sim: &mut Simulation, id: HumanID, house: BuildingID, price: Money) {
+    let Some(pos) = sim.map().buildings().get(house).map(|b| b.door_pos) else {
+        return;
+    };
+    let money = sim.read::<Households>().money(house);
+    let budget = price + money / CAR_SAVINGS_FACTOR;
+    let Some(model) = household_car_model(house, budget, price) else {
+        return;
+    };
+    let Some(v) = spawn_parked_car(sim, model, pos) else {
+        return;
+    };
+    let Some(h) = sim.world.humans.get_mut(id) else {
+        sim.write::<ParCommandBuffer<VehicleEnt>>().kill(v);
+        return;
+    };
+    h.bought.0.remove(&ItemID::new("car"));
+    h.router.personal_car = Some(v);
+    h.router.use_vehicle(Some(v));
+
+    if let Some(household) = sim.write::<Households>().get_mut(house) {
+        household.money -= car_model_price(model, price) - price;
+    }
+}
+
+fn scrap_car(sim: &mut Simulation, id: HumanID, v: VehicleID) {
+    if let Some(h) = sim.world.humans.get_mut(id) {
+        h.router.personal_car = None;
+        h.router.use_vehicle(None);
+    }
+    sim.write::<ParCommandBuffer<VehicleEnt>>().kill(v);
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3};
+    use prototypes::{ItemID, Money, RoadVehicleID};
+
+    use crate::economy::{Market, Trade, TradeTarget};
+    use crate::souls::household::{
+        car_model_price, car_price, pick_car_model, update_cars, Households, CAR_SAVINGS_FACTOR,
+    };
+    use crate::souls::human::spawn_human;
+    use crate::tests::TestCtx;
+    use crate::{SimulationOptions, SoulID};
+
+    #[test]
+    fn households_buy_and_scrap_cars() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(20.0, -20.0));
+        test.g.write::<SimulationOptions>().car_ownership_rate = 0.0;
+        let human = spawn_human(&mut test.g, house).unwrap();
+        assert!(test.g.world().humans[human].router.personal_car.is_none());
+
+        let car = ItemID::new("car");
+        let price = car_price(&test.g.read::<Market>());
+        test.g.write::<Households>().get_mut(house).unwrap().money = price * CAR_SAVINGS_FACTOR;
+        update_cars(&mut test.g);
+        assert!(test
+            .g
+            .read::<Market>()
+            .iter()
+            .find(|(&id, _)| id == car)
+            .is_some_and(|(_, m)| m.buy_order(SoulID::Human(human)).is_some()));
+
+        // the trade went through
+        test.g.world_mut_unchecked().humans[human]
+            .bought
+            .0
+            .entry(car)
+            .or_default()
+            .push(Trade {
+                buyer: TradeTarget(SoulID::Human(human)),
+                seller: TradeTarget(SoulID::Human(human)),
+                qty: 1,
+                kind: car,
+                money_delta: Money::ZERO,
+            });
+        update_cars(&mut test.g);
+        let v = test.g.world().humans[human].router.personal_car.unwrap();
+        assert!(test.g.world().vehicles.contains_key(v));
+        assert!(test.g.world().vehicles[v].vehicle.model.is_some());
+
+        test.g.write::<Households>().get_mut(house).unwrap().money = Money::new_bucks(-1);
+        update_cars(&mut test.g);
+        test.tick();
+        assert!(test.g.world().humans[human].router.personal_car.is_none());
+        assert!(!test.g.world().vehicles.contains_key(v));
+        assert!(test.g.read::<Households>().money(house) > Money::ZERO);
+    }
+
+    #[test]
+    fn car_models_follow_wealth() {
+        let _test = TestCtx::new();
+        let price = Money::new_bucks(100);
+
+        for seed in 0..64u64 {
+            let seed = common::hash_u64(seed);
+            let model = pick_car_model(price, price, seed).unwrap();
+            assert!(car_model_price(model, price) <= price);
+            assert_eq!(pick_car_model(price, price, seed), Some(model));
+        }
+
+        let rich: Vec<RoadVehicleID> = (0..256u64)
+            .filter_map(|seed| pick_car_model(Money::MAX, price, common::hash_u64(seed)))
+            .collect();
+        assert!(rich.contains(&RoadVehicleID::new("sports_car")));
+
+        // broke households still drive the cheapest model
+        assert_eq!(
+            pick_car_model(Money::ZERO, price, 0),
+            Some(RoadVehicleID::new("compact_car"))
+        );
+    }
+}
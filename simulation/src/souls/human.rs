@@ -1,20 +1,22 @@
 use crate::economy::{Bought, Market};
 use crate::map::BuildingID;
 use crate::map_dynamic::{BuildingInfos, Destination, Itinerary, Router};
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::souls::delivery::unload;
+use crate::souls::desire::{AttendEvent, BuyFood, Home, Work};
+use crate::souls::household::{car_price, household_car_model, Households, CAR_SAVINGS_FACTOR};
 use crate::transportation::Speed;
 use crate::transportation::{
-    random_pedestrian_shirt_color, spawn_parked_vehicle, Location, Pedestrian, VehicleKind,
+    random_pedestrian_shirt_color, spawn_parked_car, Location, Pedestrian,
 };
 use crate::utils::rand_provider::RandProvider;
 use crate::utils::resources::Resources;
-use crate::world::{FreightStationEnt, HumanEnt, HumanID, VehicleID};
+use crate::world::{DockEnt, FreightStationEnt, HumanEnt, HumanID, VehicleEnt, VehicleID};
 use crate::World;
-use crate::{BuildingKind, Map, ParCommandBuffer, Simulation, SoulID};
+use crate::{BuildingKind, Map, ParCommandBuffer, Simulation, SimulationOptions, SoulID};
 use egui_inspect::Inspect;
 use geom::Transform;
 use lazy_static::lazy_static;
-use prototypes::{GameTime, ItemID};
+use prototypes::{GameDuration, GameInstant, GameTime, ItemID};
 use serde::{Deserialize, Serialize};
 
 #[derive(Inspect, Serialize, Deserialize, Default)]
@@ -29,6 +31,13 @@ pub enum HumanDecisionKind {
     SetVehicle(Option<VehicleID>),
     GoTo(Destination),
     DeliverAtBuilding(BuildingID),
+    /// Takes the goods bound to the building off the truck
+    Unload {
+        truck: VehicleID,
+        building: BuildingID,
+    },
+    /// Stays put for that long, the end is set when the wait starts
+    Wait(GameDuration, Option<GameInstant>),
     MultiStack(Vec<HumanDecisionKind>),
 }
 
@@ -80,18 +89,30 @@ impl Default for HumanDecisionKind {
 }
 
 impl HumanDecisionKind {
+    #[allow(clippy::too_many_arguments)]
     pub fn update(
         &mut self,
         router: &mut Router,
         binfos: &BuildingInfos,
         map: &Map,
+        time: &GameTime,
         cbuf_freight: &ParCommandBuffer<FreightStationEnt>,
+        cbuf_dock: &ParCommandBuffer<DockEnt>,
+        cbuf_vehicle: &ParCommandBuffer<VehicleEnt>,
     ) -> bool {
         match *self {
             HumanDecisionKind::GoTo(dest) => router.go_to(dest),
             HumanDecisionKind::MultiStack(ref mut decisions) => {
                 if let Some(d) = decisions.last_mut() {
-                    if d.update(router, binfos, map, cbuf_freight) {
+                    if d.update(
+                        router,
+                        binfos,
+                        map,
+                        time,
+                        cbuf_freight,
+                        cbuf_dock,
+                        cbuf_vehicle,
+                    ) {
                         decisions.pop();
                     }
                     false
@@ -117,8 +138,26 @@ impl HumanDecisionKind {
                         }
                     });
                 }
+                if matches!(b.kind, BuildingKind::Dock(_)) {
+                    let Some(SoulID::Dock(did)) = binfos.owner(bid) else {
+                        return true;
+                    };
+                    cbuf_dock.exec_ent(did, move |e| {
+                        if let Some(d) = e.world.docks.get_mut(did) {
+                            d.d.waiting_cargo += 1;
+                        }
+                    });
+                }
+                true
+            }
+            HumanDecisionKind::Unload { truck, building } => {
+                cbuf_vehicle.exec_ent(truck, move |sim| unload(sim, truck, building));
                 true
             }
+            HumanDecisionKind::Wait(duration, ref mut until) => {
+                let until = *until.get_or_insert(time.instant() + duration);
+                time.instant() >= until
+            }
             HumanDecisionKind::Yield => true,
         }
     }
@@ -130,6 +169,7 @@ enum NextDesire<'a> {
     Home(&'a mut Home),
     Work(&'a mut Work),
     Food(&'a mut BuyFood),
+    Event(&'a mut AttendEvent),
 }
 
 pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
@@ -139,6 +179,8 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
     let rc = &*resources.read();
     let rd = &*resources.read();
     let re = &*resources.read();
+    let rf = &*resources.read();
+    let rg = &*resources.read();
 
     world.humans.iter_mut().for_each(|(ent, h)| {
         update_decision(
@@ -147,6 +189,8 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
             rc,
             rd,
             re,
+            rf,
+            rg,
             ent,
             &h.trans,
             &h.location,
@@ -156,6 +200,7 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
             Some(&mut h.food),
             Some(&mut h.home),
             h.work.as_mut(),
+            h.event.as_mut(),
         )
     });
 }
@@ -164,6 +209,8 @@ pub fn update_decision_system(world: &mut World, resources: &mut Resources) {
 pub fn update_decision(
     cbuf: &ParCommandBuffer<HumanEnt>,
     cbuf_freight: &ParCommandBuffer<FreightStationEnt>,
+    cbuf_dock: &ParCommandBuffer<DockEnt>,
+    cbuf_vehicle: &ParCommandBuffer<VehicleEnt>,
     time: &GameTime,
     binfos: &BuildingInfos,
     map: &Map,
@@ -176,6 +223,7 @@ pub fn update_decision(
     food: Option<&mut BuyFood>,
     home: Option<&mut Home>,
     work: Option<&mut Work>,
+    event: Option<&mut AttendEvent>,
 ) {
     if decision.wait != 0 {
         decision.wait -= 1;
@@ -183,7 +231,15 @@ pub fn update_decision(
     }
     let pos = trans.pos;
     decision.wait = (30.0 + common::rand::rand2(pos.x, pos.y) * 50.0) as u8;
-    if !decision.kind.update(router, binfos, map, cbuf_freight) {
+    if !decision.kind.update(
+        router,
+        binfos,
+        map,
+        time,
+        cbuf_freight,
+        cbuf_dock,
+        cbuf_vehicle,
+    ) {
         return;
     }
 
@@ -211,22 +267,33 @@ pub fn update_decision(
     }
 
     if let Some(food) = food {
-        let score = food.score(time, loc, bought);
+        let score = food.score(time, loc, bought, map);
         food.last_score = score;
 
-        #[allow(unused_assignments)]
         if score > max_score {
             max_score = score;
             decision_id = NextDesire::Food(food);
         }
     }
 
+    if let Some(event) = event {
+        let score = event.score(time);
+        event.last_score = score;
+
+        #[allow(unused_assignments)]
+        if score > max_score {
+            max_score = score;
+            decision_id = NextDesire::Event(event);
+        }
+    }
+
     match decision_id {
         NextDesire::Home(home) => decision.kind = home.apply(),
-        NextDesire::Work(work) => decision.kind = work.apply(loc, router),
+        NextDesire::Work(work) => decision.kind = work.apply(loc, router, time),
         NextDesire::Food(food) => {
             decision.kind = food.apply(cbuf, binfos, time, me, trans, loc, bought)
         }
+        NextDesire::Event(event) => decision.kind = event.apply(loc, time),
         NextDesire::None => {}
     }
 }
@@ -244,7 +311,16 @@ pub fn spawn_human(sim: &mut Simulation, house: BuildingID) -> Option<HumanID> {
 
     let time = sim.read::<GameTime>().instant();
 
-    let car = spawn_parked_vehicle(sim, VehicleKind::Car, housepos);
+    let rate = sim.read::<SimulationOptions>().car_ownership_rate;
+    let has_car = sim.write::<RandProvider>().next_f32() < rate;
+    let car = if has_car {
+        let price = car_price(&sim.read::<Market>());
+        let budget = sim.read::<Households>().money(house) / CAR_SAVINGS_FACTOR;
+        household_car_model(house, budget, price)
+            .and_then(|model| spawn_parked_car(sim, model, housepos))
+    } else {
+        None
+    };
 
     let personal_info = Box::new(PersonalInfo::new(&mut sim.write::<RandProvider>()));
 
@@ -261,6 +337,8 @@ pub fn spawn_human(sim: &mut Simulation, house: BuildingID) -> Option<HumanID> {
         router: Router::new(car),
         collider: None,
         work: None,
+        event: None,
+        unemployed_since: Some(time),
         personal_info,
     });
 
@@ -268,6 +346,7 @@ pub fn spawn_human(sim: &mut Simulation, house: BuildingID) -> Option<HumanID> {
     let mut m = sim.write::<Market>();
     m.buy(soul, housepos.xy(), ItemID::new("job-opening"), 1);
 
+    sim.write::<Households>().get_mut(house);
     sim.write::<BuildingInfos>().get_in(house, soul);
     sim.write::<BuildingInfos>().set_owner(house, soul);
 
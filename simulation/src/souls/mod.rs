@@ -1,32 +1,52 @@
 use crate::map::BuildingKind;
-use crate::map_dynamic::BuildingInfos;
+use crate::map_dynamic::{Abandonment, BuildingInfos};
+use crate::souls::company_lifecycle::CompanyLifecycle;
+use crate::souls::dock::dock_soul;
 use crate::souls::freight_station::freight_station_soul;
 use crate::souls::goods_company::company_soul;
 use crate::souls::human::spawn_human;
+use crate::souls::warehouse::warehouse_soul;
 use crate::Simulation;
 
 #[macro_use]
 pub mod desire;
 
+pub mod commute;
+pub mod company_lifecycle;
+pub mod delivery;
+pub mod dock;
+pub mod farm;
 pub mod freight_station;
 pub mod goods_company;
+pub mod household;
 pub mod human;
+pub mod sampling;
+pub mod venue_events;
+pub mod warehouse;
+pub mod welfare;
 
 /// Adds souls to empty buildings
 pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
     profiling::scope!("souls::add_souls_to_empty_buildings");
     let map = sim.map();
     let infos = sim.read::<BuildingInfos>();
+    let lifecycle = sim.read::<CompanyLifecycle>();
+    let abandonment = sim.read::<Abandonment>();
     let mut empty_buildings = Vec::with_capacity(16);
 
     for (id, building) in map.buildings() {
-        if unwrap_cont!(infos.get(id)).owner.is_some() {
+        if unwrap_cont!(infos.get(id)).owner.is_some()
+            || lifecycle.is_vacant(id)
+            || abandonment.is_empty(id)
+        {
             continue;
         }
 
         empty_buildings.push((building.kind, id));
     }
     drop(infos);
+    drop(lifecycle);
+    drop(abandonment);
     drop(map);
 
     let mut n_souls_added = 0;
@@ -46,6 +66,14 @@ pub(crate) fn add_souls_to_empty_buildings(sim: &mut Simulation) {
                 freight_station_soul(sim, build_id, id);
                 n_souls_added += 1;
             }
+            BuildingKind::Dock(id) => {
+                dock_soul(sim, build_id, id);
+                n_souls_added += 1;
+            }
+            BuildingKind::Warehouse(id) => {
+                warehouse_soul(sim, build_id, id);
+                n_souls_added += 1;
+            }
             _ => {}
         }
     }
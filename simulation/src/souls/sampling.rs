@@ -0,0 +1,375 @@
+//! Follows a few citizens for a game-day to see what a typical day looks like.
+//! Only the changes of activity are recorded, with the time they happened at.
+
+use serde::{Deserialize, Serialize};
+
+use geom::Vec3;
+use prototypes::{GameDuration, GameInstant, GameTime, Money, Tick, TICKS_PER_MINUTE};
+
+use crate::economy::WORKER_CONSUMPTION_PER_MINUTE;
+use crate::souls::household::CAR_UPKEEP_PER_MINUTE;
+use crate::transportation::Location;
+use crate::utils::rand_provider::RandProvider;
+use crate::world::{HumanEnt, HumanID};
+use crate::Simulation;
+
+/// Number of citizens followed when none is asked for
+pub const DEFAULT_SAMPLE_SIZE: u32 = 20;
+/// A citizen standing still outside for that long is waiting, in game seconds
+const WAITING_AFTER_SECS: u64 = 30;
+/// Citizens at home between these hours are sleeping
+const SLEEP_FROM_HOUR: i32 = 22;
+const SLEEP_UNTIL_HOUR: i32 = 7;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Activity {
+    Sleeping,
+    AtHome,
+    Working,
+    Commuting,
+    Waiting,
+    Shopping,
+}
+
+impl Activity {
+    pub const ALL: [Activity; 6] = [
+        Activity::Sleeping,
+        Activity::AtHome,
+        Activity::Working,
+        Activity::Commuting,
+        Activity::Waiting,
+        Activity::Shopping,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Activity::Sleeping => "Sleeping",
+            Activity::AtHome => "At home",
+            Activity::Working => "Working",
+            Activity::Commuting => "Commuting",
+            Activity::Waiting => "Waiting",
+            Activity::Shopping => "Shopping",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// One followed citizen
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CitizenSample {
+    pub human: HumanID,
+    pub name: String,
+    /// When each activity started, in order
+    pub timeline: Vec<(GameInstant, Activity)>,
+    /// Meters travelled
+    pub distance: f32,
+    /// Wages paid to the household
+    pub earned: Money,
+    /// Car upkeep paid by the household
+    pub spent: Money,
+    /// When the citizen left the city before the end of the day
+    pub gone: Option<GameInstant>,
+    last_pos: Vec3,
+    still_since: Option<GameInstant>,
+}
+
+impl CitizenSample {
+    fn new(human: HumanID, h: &HumanEnt) -> Self {
+        Self {
+            human,
+            name: h.personal_info.name.clone(),
+            timeline: vec![],
+            distance: 0.0,
+            earned: Money::ZERO,
+            spent: Money::ZERO,
+            gone: None,
+            last_pos: h.trans.pos,
+            still_since: None,
+        }
+    }
+
+    /// The activities with when they started and ended, the last one ends at `end`
+    pub fn spans(
+        &self,
+        end: GameInstant,
+    ) -> impl Iterator<Item = (GameInstant, GameInstant, Activity)> + '_ {
+        let end = self.gone.unwrap_or(end);
+        self.timeline
+            .iter()
+            .enumerate()
+            .map(move |(i, &(start, activity))| {
+                let until = self.timeline.get(i + 1).map_or(end, |&(t, _)| t);
+                (start, until, activity)
+            })
+    }
+
+    /// Time spent on each activity, in the order of `Activity::ALL`
+    pub fn durations(&self, end: GameInstant) -> [GameDuration; 6] {
+        let mut durations = [GameDuration::from_secs(0); 6];
+        for (start, until, activity) in self.spans(end) {
+            let d = &mut durations[activity.index()];
+            d.0 .0 += until.0 .0.saturating_sub(start.0 .0);
+        }
+        durations
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SampleReport {
+    pub seed: u64,
+    pub start: GameInstant,
+    pub end: GameInstant,
+    pub samples: Vec<CitizenSample>,
+}
+
+impl SampleReport {
+    /// Minutes spent on the activity by each citizen, sorted
+    pub fn distribution(&self, activity: Activity) -> Vec<f64> {
+        let mut minutes: Vec<f64> = self
+            .samples
+            .iter()
+            .map(|s| s.durations(self.end)[activity.index()].minutes())
+            .collect();
+        minutes.sort_by(f64::total_cmp);
+        minutes
+    }
+
+    /// One line per citizen with the minutes spent on each activity
+    pub fn summary_csv(&self) -> String {
+        let mut csv = String::from("name");
+        for activity in Activity::ALL {
+            csv.push_str(&format!(",{} (min)", activity.label()));
+        }
+        csv.push_str(",distance (m),earned,spent,left the city\n");
+
+        for s in &self.samples {
+            csv.push_str(&csv_field(&s.name));
+            for d in s.durations(self.end) {
+                csv.push_str(&format!(",{:.0}", d.minutes()));
+            }
+            csv.push_str(&format!(
+                ",{:.0},{},{},{}\n",
+                s.distance,
+                s.earned.bucks(),
+                s.spent.bucks(),
+                s.gone.is_some()
+            ));
+        }
+        csv
+    }
+
+    /// One line per activity of each citizen
+    pub fn timeline_csv(&self) -> String {
+        let mut csv = String::from("name,activity,start,end,minutes\n");
+        for s in &self.samples {
+            for (start, until, activity) in s.spans(self.end) {
+                csv.push_str(&format!(
+                    "{},{},{},{},{:.0}\n",
+                    csv_field(&s.name),
+                    activity.label(),
+                    start,
+                    until,
+                    GameDuration(Tick(until.0 .0.saturating_sub(start.0 .0))).minutes()
+                ));
+            }
+        }
+        csv
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// The citizens being followed and the report of the last day followed, saved with the game
+#[derive(Default, Serialize, Deserialize)]
+pub struct CitizenSampling {
+    /// Citizens added to the next sample on top of the random ones
+    pub requested: Vec<HumanID>,
+    pub recording: Option<SampleReport>,
+    pub report: Option<SampleReport>,
+}
+
+/// Starts following `count` random citizens picked from the seed, plus the requested ones,
+/// for one game-day. Replaces the recording in progress.
+pub fn start_sampling(sim: &mut Simulation, count: u32, seed: u64) {
+    let start = sim.read::<GameTime>().instant();
+    let mut sampling = sim.write::<CitizenSampling>();
+    let humans = &sim.world.humans;
+
+    let mut ids: Vec<HumanID> = humans.keys().collect();
+    let mut rng = RandProvider::new(seed);
+    let count = (count as usize).min(ids.len());
+    for i in 0..count {
+        let j = i + rng.next_u32() as usize % (ids.len() - i);
+        ids.swap(i, j);
+    }
+    ids.truncate(count);
+
+    for id in std::mem::take(&mut sampling.requested) {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    let samples = ids
+        .into_iter()
+        .filter_map(|id| Some(CitizenSample::new(id, humans.get(id)?)))
+        .collect();
+
+    sampling.recording = Some(SampleReport {
+        seed,
+        start,
+        end: start + GameDuration::from_secs(GameTime::DAY as u64),
+        samples,
+    });
+}
+
+/// What the citizen is doing right now
+fn activity(h: &HumanEnt, time: &GameTime, still: bool) -> Activity {
+    match h.location {
+        Location::Building(b) if b == h.home.house => {
+            let hour = time.daytime.hour;
+            if !(SLEEP_UNTIL_HOUR..SLEEP_FROM_HOUR).contains(&hour) {
+                Activity::Sleeping
+            } else {
+                Activity::AtHome
+            }
+        }
+        Location::Building(b) if h.work.as_ref().is_some_and(|w| w.workplace == b) => {
+            Activity::Working
+        }
+        Location::Building(_) => Activity::Shopping,
+        Location::Outside | Location::Vehicle(_) if still => Activity::Waiting,
+        Location::Outside | Location::Vehicle(_) => Activity::Commuting,
+    }
+}
+
+/// Follows the sampled citizens, it only looks at the few of them
+pub(crate) fn citizen_sampling_system(sim: &mut Simulation) {
+    profiling::scope!("souls::citizen_sampling_system");
+    let mut sampling = sim.write::<CitizenSampling>();
+    let Some(ref mut recording) = sampling.recording else {
+        return;
+    };
+    let time = *sim.read::<GameTime>();
+    let now = time.instant();
+    let new_minute = time.tick.0 % TICKS_PER_MINUTE == 0;
+    let waiting_after = GameDuration::from_secs(WAITING_AFTER_SECS);
+
+    for sample in &mut recording.samples {
+        if sample.gone.is_some() {
+            continue;
+        }
+        let Some(h) = sim.world.humans.get(sample.human) else {
+            sample.gone = Some(now);
+            continue;
+        };
+
+        let pos = h.trans.pos;
+        sample.distance += pos.distance(sample.last_pos);
+        sample.last_pos = pos;
+
+        if h.speed.0 > 0.1 {
+            sample.still_since = None;
+        } else if sample.still_since.is_none() {
+            sample.still_since = Some(now);
+        }
+        let still = sample
+            .still_since
+            .is_some_and(|since| since + waiting_after <= now);
+
+        let current = activity(h, &time, still);
+        if sample.timeline.last().map(|&(_, a)| a) != Some(current) {
+            sample.timeline.push((now, current));
+        }
+
+        if new_minute {
+            if h.work.is_some() {
+                sample.earned += WORKER_CONSUMPTION_PER_MINUTE;
+            }
+            if h.router.personal_car.is_some() {
+                sample.spent += CAR_UPKEEP_PER_MINUTE;
+            }
+        }
+    }
+
+    if now >= recording.end {
+        sampling.report = sampling.recording.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3};
+
+    use super::*;
+    use crate::souls::human::spawn_human;
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn follows_citizens_for_a_day() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(20.0, -20.0));
+        let humans: Vec<_> = (0..5)
+            .map(|_| spawn_human(&mut test.g, house).unwrap())
+            .collect();
+
+        test.g.write::<CitizenSampling>().requested.push(humans[4]);
+        start_sampling(&mut test.g, 2, 42);
+        let picked: Vec<HumanID> = test
+            .g
+            .read::<CitizenSampling>()
+            .recording
+            .as_ref()
+            .unwrap()
+            .samples
+            .iter()
+            .map(|s| s.human)
+            .collect();
+        assert_eq!(picked.len(), 3);
+        assert!(picked.contains(&humans[4]));
+
+        // the same seed picks the same citizens
+        start_sampling(&mut test.g, 2, 42);
+        let again: Vec<HumanID> = test
+            .g
+            .read::<CitizenSampling>()
+            .recording
+            .as_ref()
+            .unwrap()
+            .samples
+            .iter()
+            .map(|s| s.human)
+            .collect();
+        assert_eq!(again, picked[..2]);
+
+        test.tick();
+        let end = test
+            .g
+            .read::<CitizenSampling>()
+            .recording
+            .as_ref()
+            .unwrap()
+            .end;
+        *test.g.write::<GameTime>() = GameTime::new(Tick(end.0 .0));
+        test.tick();
+
+        let sampling = test.g.read::<CitizenSampling>();
+        assert!(sampling.recording.is_none());
+        let report = sampling.report.as_ref().unwrap();
+        for s in &report.samples {
+            assert!(!s.timeline.is_empty());
+        }
+        assert_eq!(report.distribution(Activity::Working).len(), 2);
+        assert_eq!(report.summary_csv().lines().count(), 3);
+    }
+}
@@ -0,0 +1,335 @@
+//! Events held at the leisure venues, like a match or a concert.
+//! A few hours before an event, guests are picked across the city and plan their trip to arrive
+//! on time. When it ends they all leave at once, the crowd has to get through the streets and
+//! the transit around the venue. A report of the trips in and out is kept for each venue.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use slotmapd::Key;
+
+use prototypes::{
+    GameDuration, GameInstant, GameTime, LeisureEvent, Tick, SECONDS_PER_DAY, TICKS_PER_HOUR,
+    TICKS_PER_MINUTE,
+};
+
+use crate::map::{BuildingID, BuildingKind, Map};
+use crate::souls::commute::estimate_trip;
+use crate::souls::desire::AttendEvent;
+use crate::souls::human::HumanDecisionKind;
+use crate::transportation::Location;
+use crate::utils::rand_provider::RandProvider;
+use crate::world::HumanID;
+use crate::Simulation;
+
+/// Guests are picked this long before the start, so the farthest ones can leave in time
+const INVITE_AHEAD: GameDuration = GameDuration(Tick(3 * TICKS_PER_HOUR));
+/// The report is closed this long after the end even if some guests are still on their way home
+const REPORT_TIMEOUT: GameDuration = GameDuration(Tick(3 * TICKS_PER_HOUR));
+
+/// What happened at the last event of a venue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventReport {
+    pub start: GameInstant,
+    pub invited: u32,
+    /// Guests that arrived before the end
+    pub attended: u32,
+    /// Average trip to the venue of the guests that came
+    pub avg_travel_in: Option<GameDuration>,
+    /// Average trip from the venue to the next building, from the end of the event
+    pub avg_travel_out: Option<GameDuration>,
+}
+
+/// An event whose guests are invited, until they all left the venue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningEvent {
+    pub start: GameInstant,
+    pub end: GameInstant,
+    pub invited: u32,
+    /// Guests not arrived yet
+    expected: Vec<HumanID>,
+    /// Guests in the venue
+    inside: Vec<HumanID>,
+    /// Guests on their way out once the event is over
+    leaving: Vec<HumanID>,
+    attended: u32,
+    travel_in_secs: f64,
+    left: u32,
+    travel_out_secs: f64,
+}
+
+impl RunningEvent {
+    /// Guests at the venue or on their way
+    pub fn expected_attendance(&self) -> u32 {
+        (self.expected.len() + self.inside.len()) as u32
+    }
+
+    pub fn n_inside(&self) -> usize {
+        self.inside.len()
+    }
+
+    fn report(&self) -> EventReport {
+        let avg = |total: f64, n: u32| {
+            (n > 0).then(|| GameDuration::from_secs((total / n as f64) as u64))
+        };
+        EventReport {
+            start: self.start,
+            invited: self.invited,
+            attended: self.attended,
+            avg_travel_in: avg(self.travel_in_secs, self.attended),
+            avg_travel_out: avg(self.travel_out_secs, self.left),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct VenueEvents {
+    running: BTreeMap<BuildingID, RunningEvent>,
+    reports: BTreeMap<BuildingID, EventReport>,
+}
+
+impl VenueEvents {
+    pub fn running(&self, venue: BuildingID) -> Option<&RunningEvent> {
+        self.running.get(&venue)
+    }
+
+    pub fn last_report(&self, venue: BuildingID) -> Option<&EventReport> {
+        self.reports.get(&venue)
+    }
+}
+
+/// Start and end of the next event at the venue starting now or later.
+/// The day of the cycle depends on the venue, so that the venues don't all hold their events on
+/// the same day.
+pub fn next_event(
+    venue: BuildingID,
+    event: &LeisureEvent,
+    time: &GameTime,
+) -> (GameInstant, GameInstant) {
+    let every = event.every_days.max(1) as u64;
+    let offset = common::hash_u64(venue.data().as_ffi()) % every;
+    let now = time.daytime.gamesec();
+    let start_daysec = event.hours.start_daysec();
+    let duration = (event.hours.end_daysec() - start_daysec).rem_euclid(SECONDS_PER_DAY);
+
+    let mut day = time.daytime.day;
+    loop {
+        let start = day * SECONDS_PER_DAY + start_daysec;
+        if (day as u64 + offset) % every == 0 && start >= now {
+            let start = time.instant() + GameDuration::from_secs((start - now) as u64);
+            return (start, start + GameDuration::from_secs(duration as u64));
+        }
+        day += 1;
+    }
+}
+
+/// Every minute, invites the guests of the coming events and follows them in and out
+pub(crate) fn venue_events_system(sim: &mut Simulation) {
+    profiling::scope!("souls::venue_events_system");
+    if sim.read::<GameTime>().tick.0 % TICKS_PER_MINUTE != 0 {
+        return;
+    }
+    invite_guests(sim);
+    track_guests(sim);
+}
+
+/// Picks the guests of the events starting soon among the citizens free at that time
+fn invite_guests(sim: &mut Simulation) {
+    let time = *sim.read::<GameTime>();
+    let mut coming = vec![];
+    {
+        let map = sim.map();
+        let events = sim.read::<VenueEvents>();
+        for (id, b) in map.buildings() {
+            let BuildingKind::Leisure(proto) = b.kind else {
+                continue;
+            };
+            let proto = proto.prototype();
+            let Some(ref event) = proto.event else {
+                continue;
+            };
+            if events.running.contains_key(&id) {
+                continue;
+            }
+            let (start, end) = next_event(id, event, &time);
+            if start > time.instant() + INVITE_AHEAD {
+                continue;
+            }
+            coming.push((id, start, end, event.attendance.min(proto.capacity)));
+        }
+    }
+
+    for (venue, start, end, attendance) in coming {
+        let guests = pick_guests(sim, start, attendance as usize);
+        let map = sim.map();
+        let trips: Vec<_> = guests
+            .iter()
+            .map(|&guest| {
+                let h = &sim.world.humans[guest];
+                let has_car = h.router.personal_car.is_some();
+                let trip = estimate_trip(&map, h.home.house, venue, has_car);
+                (guest, trip.unwrap_or(GameDuration(Tick(0))))
+            })
+            .collect();
+        drop(map);
+
+        for (guest, trip) in trips {
+            sim.world.humans[guest].event = Some(AttendEvent::new(venue, start, end, trip));
+        }
+        sim.write::<VenueEvents>().running.insert(
+            venue,
+            RunningEvent {
+                start,
+                end,
+                invited: guests.len() as u32,
+                expected: guests,
+                inside: vec![],
+                leaving: vec![],
+                attended: 0,
+                travel_in_secs: 0.0,
+                left: 0,
+                travel_out_secs: 0.0,
+            },
+        );
+    }
+}
+
+/// Up to `n` citizens without another event and not at work when the event starts
+fn pick_guests(sim: &Simulation, start: GameInstant, n: usize) -> Vec<HumanID> {
+    let start_time = GameTime::new(start.0).daytime;
+    let mut candidates: Vec<HumanID> = sim
+        .world
+        .humans
+        .iter()
+        .filter(|(_, h)| h.event.is_none())
+        .filter(|(_, h)| {
+            h.work
+                .as_ref()
+                .map_or(true, |w| !w.work_inter.is_active(&start_time))
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    let mut rng = sim.write::<RandProvider>();
+    let n = n.min(candidates.len());
+    for i in 0..n {
+        let j = i + rng.next_u32() as usize % (candidates.len() - i);
+        candidates.swap(i, j);
+    }
+    candidates.truncate(n);
+    candidates
+}
+
+/// Counts the guests getting in, sends them all out at the end and measures their way out
+fn track_guests(sim: &mut Simulation) {
+    let time = *sim.read::<GameTime>();
+    let now = time.instant();
+    let (world, res) = sim.world_res();
+    let map = res.read::<Map>();
+    let mut events = res.write::<VenueEvents>();
+    let mut finished = vec![];
+
+    for (&venue, e) in events.running.iter_mut() {
+        let at_venue = Location::Building(venue);
+
+        for guest in std::mem::take(&mut e.expected) {
+            let Some(h) = world.humans.get(guest) else {
+                continue;
+            };
+            if h.location != at_venue {
+                e.expected.push(guest);
+                continue;
+            }
+            let departed = h.event.as_ref().and_then(|ev| ev.departed);
+            e.travel_in_secs += departed.map_or(0.0, |d| d.elapsed(&time).seconds());
+            e.attended += 1;
+            e.inside.push(guest);
+        }
+
+        if now < e.end && map.buildings().contains_key(venue) {
+            continue;
+        }
+
+        // the event is over, everyone leaves at once and the late ones turn back
+        for guest in e.expected.drain(..).chain(e.inside.drain(..)) {
+            let Some(h) = world.humans.get_mut(guest) else {
+                continue;
+            };
+            h.event = None;
+            if h.location == at_venue {
+                e.leaving.push(guest);
+            } else if matches!(h.decision.kind, HumanDecisionKind::GoTo(_)) {
+                h.decision.kind = HumanDecisionKind::Yield;
+            }
+        }
+
+        for guest in std::mem::take(&mut e.leaving) {
+            let Some(h) = world.humans.get(guest) else {
+                continue;
+            };
+            match h.location {
+                Location::Building(b) if b != venue => {
+                    e.left += 1;
+                    e.travel_out_secs += e.end.elapsed(&time).seconds();
+                }
+                _ => e.leaving.push(guest),
+            }
+        }
+
+        if e.leaving.is_empty() || now >= e.end + REPORT_TIMEOUT {
+            finished.push(venue);
+        }
+    }
+
+    for venue in finished {
+        let e = events.running.remove(&venue).unwrap();
+        for guest in e.leaving.iter().chain(&e.expected).chain(&e.inside) {
+            if let Some(h) = world.humans.get_mut(*guest) {
+                h.event = None;
+            }
+        }
+        if map.buildings().contains_key(venue) {
+            events.reports.insert(venue, e.report());
+        } else {
+            events.reports.remove(&venue);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prototypes::RecTimeInterval;
+    use slotmapd::KeyData;
+
+    #[test]
+    fn events_are_scheduled_every_few_days() {
+        let venue = BuildingID::from(KeyData::from_ffi(1));
+        let event = LeisureEvent {
+            hours: RecTimeInterval::new((20, 0), (22, 0)),
+            every_days: 3,
+            attendance: 10,
+        };
+        // the game starts at 08:00
+        let time = GameTime::new(Tick(0));
+
+        let (start, end) = next_event(venue, &event, &time);
+        assert_eq!(
+            end.0 .0 - start.0 .0,
+            2 * TICKS_PER_HOUR,
+            "the event lasts two hours"
+        );
+        let start_time = GameTime::new(start.0);
+        assert_eq!(start_time.daytime.hour, 20);
+        assert_eq!(start_time.daytime.minute, 0);
+        assert!(start.0 .0 - time.tick.0 < 3 * 24 * TICKS_PER_HOUR);
+
+        // during the event, the next one is a cycle later
+        let during = GameTime::new(Tick(start.0 .0 + TICKS_PER_MINUTE));
+        let (next, _) = next_event(venue, &event, &during);
+        assert_eq!(next.0 .0 - start.0 .0, 3 * 24 * TICKS_PER_HOUR);
+
+        // the same schedule for the same venue
+        assert_eq!(next_event(venue, &event, &time), (start, end));
+    }
+}
@@ -0,0 +1,566 @@
+//! Warehouses store goods in bulk so production spikes are not lost.
+//! Companies whose storage is full rent space in the nearest warehouse, their surplus is moved
+//! there and comes back once they sold what they had.
+//! The space that is not rented holds the stocks of the city, kept between bounds by the stock
+//! rules of the warehouse. Rules are evaluated every minute, warehouses in id order and rules in
+//! the order they are listed, so the outcome only depends on the state of the simulation.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use slotmapd::HopSlotMap;
+
+use geom::{Transform, Vec2};
+use prototypes::{
+    GameTime, ItemID, Money, WarehousePrototypeID, HOURS_PER_DAY, TICKS_PER_HOUR, TICKS_PER_MINUTE,
+};
+
+use crate::economy::{EcoStats, Government, Market};
+use crate::map::{BuildingID, Map};
+use crate::map_dynamic::BuildingInfos;
+use crate::utils::resources::Resources;
+use crate::world::{CompanyEnt, CompanyID, WarehouseEnt, WarehouseID};
+use crate::{ParCommandBuffer, Simulation, SoulID, World};
+
+/// Rent paid each day by a company for each unit of space it rents
+pub const RENT_PER_UNIT_PER_DAY: Money = Money::new_cents(2);
+/// Largest quantity asked for at once when no producer has goods for sale
+const MAX_BUY_BATCH: i32 = 20;
+
+/// Keeps the stock of an item between min and max.
+/// When the city price is low the warehouse stocks up to max, when it is high it sells down to min.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StockRule {
+    pub item: ItemID,
+    pub min: u32,
+    pub max: u32,
+    /// Buy up to max when the city price is at or below
+    pub buy_below: Option<Money>,
+    /// Sell down to min when the city price is at or above
+    pub sell_above: Option<Money>,
+}
+debug_inspect_impl!(StockRule);
+
+/// What a warehouse does with its stock of an item
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StockAction {
+    Buy(u32),
+    Sell(u32),
+    Hold,
+}
+
+impl StockRule {
+    pub fn new(item: ItemID) -> Self {
+        Self {
+            item,
+            min: 0,
+            max: 100,
+            buy_below: None,
+            sell_above: None,
+        }
+    }
+
+    /// What to do to keep the stock within the rule at this city price
+    pub fn action(&self, stock: i32, price: Money) -> StockAction {
+        let low = if self.buy_below.is_some_and(|p| price <= p) {
+            self.max
+        } else {
+            self.min
+        };
+        let high = if self.sell_above.is_some_and(|p| price >= p) {
+            self.min
+        } else {
+            self.max
+        };
+
+        if stock < low as i32 {
+            StockAction::Buy((low as i32 - stock) as u32)
+        } else if stock > high as i32 {
+            StockAction::Sell((stock - high as i32) as u32)
+        } else {
+            StockAction::Hold
+        }
+    }
+}
+
+/// Space rented by a company
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rental {
+    pub tenant: CompanyID,
+    pub space: u32,
+    /// Goods stored for the tenant
+    pub stock: BTreeMap<ItemID, i32>,
+}
+debug_inspect_impl!(Rental);
+
+impl Rental {
+    pub fn used(&self) -> i32 {
+        self.stock.values().sum()
+    }
+}
+
+/// A component that identifies warehouse souls
+#[derive(Serialize, Deserialize, Inspect)]
+pub struct Warehouse {
+    pub proto: WarehousePrototypeID,
+    pub building: BuildingID,
+    /// Evaluated in this order
+    pub rules: Vec<StockRule>,
+    /// Oldest first
+    pub rentals: Vec<Rental>,
+}
+
+impl Warehouse {
+    pub fn rented(&self) -> u32 {
+        self.rentals.iter().map(|r| r.space).sum()
+    }
+
+    /// Goods stored for the tenants
+    pub fn tenant_stock(&self) -> i32 {
+        self.rentals.iter().map(Rental::used).sum()
+    }
+
+    /// Space left for the stocks of the city
+    pub fn city_space(&self) -> u32 {
+        self.proto
+            .prototype()
+            .capacity
+            .saturating_sub(self.rented())
+    }
+
+    /// Goods the city has in the warehouse
+    pub fn city_stock(&self, soul: SoulID, market: &Market) -> i32 {
+        market
+            .iter()
+            .filter_map(|(_, m)| m.capital(soul))
+            .filter(|&c| c > 0)
+            .sum()
+    }
+
+    /// Space that can still be rented
+    fn free_space(&self, soul: SoulID, market: &Market) -> u32 {
+        self.city_space()
+            .saturating_sub(self.city_stock(soul, market).max(0) as u32)
+    }
+}
+
+pub fn warehouse_soul(
+    sim: &mut Simulation,
+    building: BuildingID,
+    proto: WarehousePrototypeID,
+) -> Option<WarehouseID> {
+    let map = sim.map();
+    let b = map.buildings.get(building)?;
+
+    let w = Warehouse {
+        proto,
+        building,
+        rules: vec![],
+        rentals: vec![],
+    };
+
+    let height = b.height;
+    let obb = b.obb;
+    drop(map);
+
+    let id = sim.world.insert(WarehouseEnt {
+        w,
+        trans: Transform::new(obb.center().z(height)),
+    });
+
+    sim.write::<BuildingInfos>()
+        .set_owner(building, SoulID::Warehouse(id));
+
+    Some(id)
+}
+
+/// Replaces the stock rules of the warehouse in the building.
+/// Rules for goods the warehouse cannot store are dropped, as well as a second rule for the same
+/// good.
+pub fn set_stock_rules(sim: &mut Simulation, building: BuildingID, rules: &[StockRule]) {
+    let Some(SoulID::Warehouse(id)) = sim.read::<BuildingInfos>().owner(building) else {
+        return;
+    };
+    let Some(w) = sim.world.warehouses.get_mut(id) else {
+        return;
+    };
+    let proto = w.w.proto.prototype();
+
+    w.w.rules.clear();
+    for rule in rules {
+        if !proto.accepts(rule.item) || w.w.rules.iter().any(|r| r.item == rule.item) {
+            continue;
+        }
+        w.w.rules.push(StockRule {
+            max: rule.max.max(rule.min),
+            ..*rule
+        });
+    }
+}
+
+/// Goods held in the city, split between the producers and the warehouses
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CityStock {
+    /// In the storage of the companies
+    pub producers: i64,
+    /// Stocks of the city and goods stored for the tenants
+    pub warehouses: i64,
+}
+
+pub fn city_stock(world: &World, market: &Market) -> BTreeMap<ItemID, CityStock> {
+    let mut stocks: BTreeMap<ItemID, CityStock> = BTreeMap::new();
+    for (&item, m) in market.iter() {
+        let s = stocks.entry(item).or_default();
+        for (soul, &qty) in m.capital_map() {
+            match soul {
+                SoulID::GoodsCompany(_) => s.producers += qty.max(0) as i64,
+                SoulID::Warehouse(_) => s.warehouses += qty.max(0) as i64,
+                _ => {}
+            }
+        }
+    }
+    for w in world.warehouses.values() {
+        for rental in &w.w.rentals {
+            for (&item, &qty) in &rental.stock {
+                stocks.entry(item).or_default().warehouses += qty as i64;
+            }
+        }
+    }
+    stocks
+}
+
+pub fn warehouse_system(world: &mut World, res: &mut Resources) {
+    profiling::scope!("souls::warehouse_system");
+    let tick = res.read::<GameTime>().tick.0;
+    if tick % TICKS_PER_MINUTE != 0 {
+        return;
+    }
+    let new_day = tick % (TICKS_PER_HOUR * HOURS_PER_DAY as u64) == 0;
+    update_warehouses(world, res, new_day);
+}
+
+fn update_warehouses(world: &mut World, res: &mut Resources, new_day: bool) {
+    let cbuf = res.read::<ParCommandBuffer<WarehouseEnt>>();
+    let map = res.read::<Map>();
+    let ecostats = res.read::<EcoStats>();
+    let mut market = res.write::<Market>();
+    let mut gvt = res.write::<Government>();
+
+    rent_space(world, &map, &market);
+
+    let World {
+        warehouses,
+        companies,
+        ..
+    } = world;
+
+    for (id, ent) in warehouses.iter_mut() {
+        let w = &mut ent.w;
+        let Some(b) = map.buildings.get(w.building) else {
+            cbuf.kill(id);
+            continue;
+        };
+        let soul = SoulID::Warehouse(id);
+
+        w.rentals.retain(|r| companies.contains_key(r.tenant));
+        for rental in &mut w.rentals {
+            move_surplus(w.proto, rental, companies, &map, &mut market);
+        }
+
+        if new_day {
+            // rentals that were emptied are given back, the others pay for the day
+            w.rentals.retain(|r| r.used() > 0);
+            for rental in &w.rentals {
+                let rent = RENT_PER_UNIT_PER_DAY * rental.space as i64;
+                gvt.money += rent;
+                if let Some(c) = companies.get_mut(rental.tenant) {
+                    c.comp.finances.today -= rent;
+                }
+            }
+        }
+
+        apply_stock_rules(soul, w, b.door_pos.xy(), &ecostats, &mut market);
+    }
+}
+
+/// Space a company needs to keep producing, one more full storage of each good it makes
+fn needed_space(c: &CompanyEnt) -> u32 {
+    let Some(ref recipe) = c.comp.proto.prototype().recipe else {
+        return 0;
+    };
+    recipe
+        .production
+        .iter()
+        .map(|item| (item.amount * (recipe.storage_multiplier + 1)).max(0) as u32)
+        .sum()
+}
+
+/// Companies that stopped producing because their storage is full rent space in the nearest
+/// warehouse that can store their goods. Companies are served in id order.
+fn rent_space(world: &mut World, map: &Map, market: &Market) {
+    let tenants: Vec<CompanyID> = world
+        .warehouses
+        .values()
+        .flat_map(|w| w.w.rentals.iter().map(|r| r.tenant))
+        .collect();
+
+    for (id, c) in world.companies.iter() {
+        if tenants.contains(&id) {
+            continue;
+        }
+        let Some(ref recipe) = c.comp.proto.prototype().recipe else {
+            continue;
+        };
+        let soul = SoulID::GoodsCompany(id);
+        let Some(full) = recipe.production.iter().find(|item| {
+            market.capital(soul, item.id) >= item.amount * (recipe.storage_multiplier + 1)
+        }) else {
+            continue;
+        };
+        let Some(pos) = map.buildings.get(c.comp.building).map(|b| b.door_pos.xy()) else {
+            continue;
+        };
+
+        let needed = needed_space(c);
+        let warehouse = world
+            .warehouses
+            .iter_mut()
+            .filter(|(wid, w)| {
+                w.w.proto.prototype().accepts(full.id)
+                    && w.w.free_space(SoulID::Warehouse(*wid), market) > 0
+            })
+            .filter_map(|(wid, w)| {
+                let wpos = map.buildings.get(w.w.building)?.door_pos.xy();
+                Some((wid, w, wpos.distance2(pos)))
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        let Some((wid, w, _)) = warehouse else {
+            continue;
+        };
+
+        let space = needed.min(w.w.free_space(SoulID::Warehouse(wid), market));
+        w.w.rentals.push(Rental {
+            tenant: id,
+            space,
+            stock: BTreeMap::new(),
+        });
+    }
+}
+
+/// Moves the goods that block the production of the tenant into the warehouse, and brings them
+/// back once the tenant has room to sell them
+fn move_surplus(
+    proto: WarehousePrototypeID,
+    rental: &mut Rental,
+    companies: &HopSlotMap<CompanyID, CompanyEnt>,
+    map: &Map,
+    market: &mut Market,
+) {
+    let Some(c) = companies.get(rental.tenant) else {
+        return;
+    };
+    let Some(ref recipe) = c.comp.proto.prototype().recipe else {
+        return;
+    };
+    let Some(near) = map.buildings.get(c.comp.building).map(|b| b.door_pos.xy()) else {
+        return;
+    };
+    let soul = SoulID::GoodsCompany(rental.tenant);
+    let proto = proto.prototype();
+
+    for item in &recipe.production {
+        if !proto.accepts(item.id) {
+            continue;
+        }
+        let keep = item.amount * recipe.storage_multiplier;
+        let full = item.amount * (recipe.storage_multiplier + 1);
+        let have = market.capital(soul, item.id);
+        let room = rental.space as i32 - rental.used();
+        let stored = rental.stock.get(&item.id).copied().unwrap_or(0);
+
+        let moved = if have >= full {
+            (have - keep).min(room)
+        } else if have < keep && stored > 0 {
+            -(keep - have).min(stored)
+        } else {
+            0
+        };
+        if moved == 0 {
+            continue;
+        }
+
+        market.produce(soul, item.id, -moved);
+        let stored = rental.stock.entry(item.id).or_default();
+        *stored += moved;
+        if *stored == 0 {
+            rental.stock.remove(&item.id);
+        }
+        market.sell_all(soul, near, item.id, keep as u32);
+    }
+}
+
+/// Places the orders of the stock rules on the market. Goods are only sold to the consumers of the
+/// city, they are never exported.
+fn apply_stock_rules(
+    soul: SoulID,
+    w: &Warehouse,
+    near: Vec2,
+    ecostats: &EcoStats,
+    market: &mut Market,
+) {
+    let mut free = w.free_space(soul, market) as i32;
+
+    for rule in &w.rules {
+        let Some(m) = market.inner().get(&rule.item) else {
+            continue;
+        };
+        let price = ecostats.local_price(rule.item, m.ext_value);
+        let stock = market.capital(soul, rule.item);
+        // the largest order of a producer, so that it can fill the order on its own
+        let batch = m
+            .sell_orders()
+            .iter()
+            .filter(|(seller, _)| **seller != soul)
+            .map(|(_, o)| o.qty as i32)
+            .max()
+            .unwrap_or(MAX_BUY_BATCH);
+
+        market.cancel(soul, rule.item);
+        match rule.action(stock, price) {
+            StockAction::Buy(qty) => {
+                let qty = (qty as i32).min(batch).min(free);
+                if qty > 0 {
+                    market.buy(soul, near, rule.item, qty as u32);
+                    free -= qty;
+                }
+            }
+            StockAction::Sell(qty) => {
+                market.sell(soul, near, rule.item, qty, qty);
+            }
+            StockAction::Hold => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, Vec2, OBB};
+    use prototypes::{BuildingGen, GoodsCompanyID, ItemID, Money, WarehousePrototypeID};
+
+    use super::*;
+    use crate::map::BuildingKind;
+    use crate::tests::TestCtx;
+    use crate::WorldCommand;
+
+    #[test]
+    fn rules_follow_the_price_bands() {
+        let mut rule = StockRule::new(ItemID::new("flour"));
+        rule.min = 10;
+        rule.max = 50;
+        let price = Money::new_bucks(10);
+
+        assert_eq!(rule.action(0, price), StockAction::Buy(10));
+        assert_eq!(rule.action(30, price), StockAction::Hold);
+        assert_eq!(rule.action(60, price), StockAction::Sell(10));
+
+        // cheap goods are stocked up to max, expensive ones sold down to min
+        rule.buy_below = Some(Money::new_bucks(10));
+        rule.sell_above = Some(Money::new_bucks(20));
+        assert_eq!(rule.action(30, price), StockAction::Buy(20));
+        assert_eq!(rule.action(30, Money::new_bucks(15)), StockAction::Hold);
+        assert_eq!(rule.action(30, Money::new_bucks(25)), StockAction::Sell(20));
+    }
+
+    fn build(test: &mut TestCtx, center: Vec2, kind: BuildingKind, w: f32, h: f32) -> BuildingID {
+        let id = test
+            .g
+            .map_mut()
+            .build_special_building(
+                &OBB::new(center, Vec2::Y, w, h),
+                kind,
+                BuildingGen::CenteredDoor {
+                    vertical_factor: 1.0,
+                },
+                None,
+                None,
+            )
+            .unwrap();
+        test.g.write::<BuildingInfos>().insert(id);
+        id
+    }
+
+    #[test]
+    fn stores_surplus_and_keeps_stocks() {
+        let mut test = TestCtx::new();
+        let proto = WarehousePrototypeID::new("warehouse").prototype();
+        let warehouse = build(
+            &mut test,
+            vec2(0.0, 0.0),
+            BuildingKind::Warehouse(proto.id),
+            proto.size.w,
+            proto.size.h,
+        );
+        let bakery = GoodsCompanyID::new("bakery").prototype();
+        let company = build(
+            &mut test,
+            vec2(200.0, 0.0),
+            BuildingKind::GoodsCompany(bakery.id),
+            bakery.size.w,
+            bakery.size.h,
+        );
+        test.tick();
+
+        let binfos = test.g.read::<BuildingInfos>();
+        let Some(SoulID::Warehouse(wid)) = binfos.owner(warehouse) else {
+            panic!("warehouse should have a soul")
+        };
+        let Some(SoulID::GoodsCompany(cid)) = binfos.owner(company) else {
+            panic!("bakery should have a company")
+        };
+        drop(binfos);
+
+        let flour = ItemID::new("flour");
+        let mut rule = StockRule::new(flour);
+        rule.min = 10;
+        test.apply(&[WorldCommand::SetStockRules {
+            building: warehouse,
+            rules: vec![rule, StockRule::new(flour)],
+        }]);
+        assert_eq!(test.g.world().warehouses[wid].w.rules, vec![rule]);
+
+        // the bakery cannot store more bread
+        let recipe = bakery.recipe.as_ref().unwrap();
+        let bread = &recipe.production[0];
+        let full = bread.amount * (recipe.storage_multiplier + 1);
+        let csoul = SoulID::GoodsCompany(cid);
+        test.g.write::<Market>().produce(csoul, bread.id, full);
+
+        {
+            let (world, res) = test.g.world_res();
+            update_warehouses(world, res, false);
+        }
+
+        let w = &test.g.world().warehouses[wid].w;
+        assert_eq!(w.rentals.len(), 1);
+        assert_eq!(w.rentals[0].tenant, cid);
+        assert_eq!(w.rentals[0].stock[&bread.id], bread.amount);
+        let market = test.g.read::<Market>();
+        assert_eq!(
+            market.capital(csoul, bread.id),
+            bread.amount * recipe.storage_multiplier
+        );
+        let wsoul = SoulID::Warehouse(wid);
+        assert_eq!(
+            market.inner()[&flour].buy_order(wsoul).map(|o| o.qty),
+            Some(10)
+        );
+        drop(market);
+
+        let stocks = city_stock(test.g.world(), &test.g.read::<Market>());
+        assert_eq!(stocks[&bread.id].warehouses, bread.amount as i64);
+        assert_eq!(
+            stocks[&bread.id].producers,
+            (bread.amount * recipe.storage_multiplier) as i64
+        );
+    }
+}
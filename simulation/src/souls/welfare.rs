@@ -0,0 +1,324 @@
+//! Unemployment of the citizens and the welfare paid to them.
+//! The unemployed get unhappy after a while and may leave the city, the government can pay them
+//! an allowance and is warned when the unemployment rate gets high.
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{
+    GameDuration, GameInstant, GameTime, Money, Tick, HOURS_PER_DAY, MINUTES_PER_HOUR,
+    TICKS_PER_HOUR, TICKS_PER_MINUTE,
+};
+
+use crate::economy::{CityStats, Government, WORKER_CONSUMPTION_PER_MINUTE};
+use crate::event_log::{log_event, EventCategory, Severity};
+use crate::map_dynamic::{Abandonment, BuildingInfos};
+use crate::souls::household::Households;
+use crate::transportation::Location;
+use crate::utils::rand_provider::RandProvider;
+use crate::world::{HumanEnt, HumanID, VehicleEnt};
+use crate::{ParCommandBuffer, Simulation, SoulID};
+
+const TICKS_PER_DAY: u64 = TICKS_PER_HOUR * HOURS_PER_DAY as u64;
+const MINUTES_PER_DAY: f32 = (MINUTES_PER_HOUR * HOURS_PER_DAY) as f32;
+
+/// Unemployed for longer than this, citizens start to get unhappy
+pub const LONG_TERM_UNEMPLOYMENT: GameDuration = GameDuration(Tick(2 * TICKS_PER_DAY));
+/// Time it takes for a long-term unemployed citizen to reach the lowest happiness
+const UNHAPPINESS_RAMP: GameDuration = GameDuration(Tick(3 * TICKS_PER_DAY));
+/// Lowest happiness of an unemployed citizen
+const MIN_HAPPINESS: f32 = 0.2;
+/// Chance of leaving the city each day of a fully unhappy citizen
+const EMIGRATION_CHANCE_PER_DAY: f32 = 0.3;
+/// The rate must fall this far below a threshold for its alert to be cleared
+const ALERT_HYSTERESIS: f32 = 0.01;
+
+/// Allowance and alerts set in the budget window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WelfarePolicy {
+    /// Allowance paid to each unemployed citizen, in percent of the wage of a worker.
+    /// No welfare when zero.
+    pub allowance_percent: u32,
+    /// Unemployment rates in percent that raise an alert when crossed, in increasing order
+    pub alert_thresholds: Vec<u32>,
+}
+
+impl Default for WelfarePolicy {
+    fn default() -> Self {
+        Self {
+            allowance_percent: 0,
+            alert_thresholds: vec![5, 10, 20],
+        }
+    }
+}
+
+impl WelfarePolicy {
+    /// Money paid to an unemployed citizen each minute
+    pub fn allowance_per_minute(&self) -> Money {
+        WORKER_CONSUMPTION_PER_MINUTE * self.allowance_percent as i64 / 100
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Welfare {
+    pub policy: WelfarePolicy,
+    /// Money paid as welfare during the current day
+    pub paid_today: Money,
+    /// Money paid as welfare during the previous day
+    pub paid_last_day: Money,
+    /// Number of alert thresholds the unemployment rate is above
+    pub alert_level: usize,
+    day: i32,
+}
+
+/// How happy the citizen is with their situation, from 0 to 1.
+/// Starts to drop once they are unemployed for longer than LONG_TERM_UNEMPLOYMENT.
+pub fn happiness(unemployed_for: Option<GameDuration>) -> f32 {
+    let Some(d) = unemployed_for else {
+        return 1.0;
+    };
+    let Some(over) = d.0 .0.checked_sub(LONG_TERM_UNEMPLOYMENT.0 .0) else {
+        return 1.0;
+    };
+    let progress = (over as f32 / UNHAPPINESS_RAMP.0 .0 as f32).min(1.0);
+    1.0 - progress * (1.0 - MIN_HAPPINESS)
+}
+
+/// The time the human has been unemployed for, None if they have a job
+pub fn unemployed_for(h: &HumanEnt, time: &GameTime) -> Option<GameDuration> {
+    if h.work.is_some() {
+        return None;
+    }
+    Some(h.unemployed_since?.elapsed(time))
+}
+
+/// Number of thresholds the rate is above, the rate must fall a bit below a threshold
+/// for it to be cleared so that a rate hovering around it does not keep raising alerts
+pub fn alert_level(rate: f32, thresholds: &[u32], current: usize) -> usize {
+    thresholds
+        .iter()
+        .enumerate()
+        .take_while(|&(i, &t)| {
+            let t = t as f32 / 100.0;
+            rate >= t || (i < current && rate >= t - ALERT_HYSTERESIS)
+        })
+        .count()
+}
+
+/// Every minute, tracks the unemployed, pays the welfare and lets the long-term unemployed leave
+pub(crate) fn welfare_system(sim: &mut Simulation) {
+    profiling::scope!("souls::welfare_system");
+    if sim.read::<GameTime>().tick.0 % TICKS_PER_MINUTE != 0 {
+        return;
+    }
+    track_unemployment(sim);
+    pay_welfare(sim);
+    emigrate(sim);
+    update_alerts(sim);
+}
+
+fn track_unemployment(sim: &mut Simulation) {
+    let now = sim.read::<GameTime>().instant();
+    for h in sim.world.humans.values_mut() {
+        match (&h.work, h.unemployed_since) {
+            (Some(_), Some(_)) => h.unemployed_since = None,
+            (None, None) => h.unemployed_since = Some(now),
+            _ => {}
+        }
+    }
+}
+
+/// The allowance goes from the government to the households of the unemployed
+fn pay_welfare(sim: &mut Simulation) {
+    let day = sim.read::<GameTime>().daytime.day;
+    let mut welfare = sim.write::<Welfare>();
+    if welfare.day != day {
+        welfare.paid_last_day = if day == welfare.day + 1 {
+            welfare.paid_today
+        } else {
+            Money::ZERO
+        };
+        welfare.paid_today = Money::ZERO;
+        welfare.day = day;
+    }
+
+    let allowance = welfare.policy.allowance_per_minute();
+    if allowance == Money::ZERO {
+        return;
+    }
+
+    let mut households = sim.write::<Households>();
+    let mut paid = Money::ZERO;
+    for h in sim.world.humans.values() {
+        if h.work.is_some() {
+            continue;
+        }
+        let Some(household) = households.get_mut(h.home.house) else {
+            continue;
+        };
+        household.money += allowance;
+        paid += allowance;
+    }
+    sim.write::<Government>().money -= paid;
+    welfare.paid_today += paid;
+}
+
+/// Unhappy citizens at home may leave the city with their household, the house stays empty
+/// until people want to move in again
+fn emigrate(sim: &mut Simulation) {
+    let time = *sim.read::<GameTime>();
+    let now = time.instant();
+
+    let mut leaving = vec![];
+    {
+        let mut rng = sim.write::<RandProvider>();
+        for (id, h) in sim.world.humans.iter() {
+            let unhappiness = 1.0 - happiness(unemployed_for(h, &time));
+            if unhappiness <= 0.0 || h.location != Location::Building(h.home.house) {
+                continue;
+            }
+            if rng.next_f32() < unhappiness * EMIGRATION_CHANCE_PER_DAY / MINUTES_PER_DAY {
+                leaving.push(id);
+            }
+        }
+    }
+
+    for id in leaving {
+        leave_city(sim, id, now);
+    }
+}
+
+fn leave_city(sim: &mut Simulation, id: HumanID, now: GameInstant) {
+    let Some(h) = sim.world.humans.get(id) else {
+        return;
+    };
+    let house = h.home.house;
+    let car = h.router.personal_car;
+
+    let mut binfos = sim.write::<BuildingInfos>();
+    binfos.get_out(house, SoulID::Human(id));
+    if binfos.owner(house) == Some(SoulID::Human(id)) {
+        binfos.remove_owner(house);
+    }
+    drop(binfos);
+
+    if let Some(car) = car {
+        sim.write::<ParCommandBuffer<VehicleEnt>>().kill(car);
+    }
+    sim.write::<ParCommandBuffer<HumanEnt>>().kill(id);
+    sim.write::<Households>().remove(house);
+    sim.write::<Abandonment>().vacate(house, now);
+}
+
+/// Once an hour, warns when the unemployment rate crosses one of the thresholds
+fn update_alerts(sim: &mut Simulation) {
+    if sim.read::<GameTime>().daytime.minute != 0 {
+        return;
+    }
+    let Some(rate) = CityStats::new(&sim.world).unemployment_rate else {
+        return;
+    };
+
+    let mut welfare = sim.write::<Welfare>();
+    let before = welfare.alert_level;
+    let after = alert_level(rate, &welfare.policy.alert_thresholds, before);
+    welfare.alert_level = after;
+    let threshold = |level: usize| welfare.policy.alert_thresholds[level - 1];
+    let event = match after.cmp(&before) {
+        std::cmp::Ordering::Greater => Some((
+            Severity::Warning,
+            format!("Unemployment rose above {}%", threshold(after)),
+        )),
+        std::cmp::Ordering::Less => Some((
+            Severity::Success,
+            format!("Unemployment fell below {}%", threshold(before)),
+        )),
+        std::cmp::Ordering::Equal => None,
+    };
+    drop(welfare);
+
+    if let Some((severity, text)) = event {
+        log_event(sim, EventCategory::Economy, severity, text, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3};
+
+    use super::*;
+    use crate::souls::household::STARTING_SAVINGS;
+    use crate::souls::human::spawn_human;
+    use crate::tests::TestCtx;
+
+    #[test]
+    fn happiness_drops_with_long_term_unemployment() {
+        assert_eq!(happiness(None), 1.0);
+        assert_eq!(happiness(Some(GameDuration::from_minutes(10))), 1.0);
+        assert_eq!(happiness(Some(LONG_TERM_UNEMPLOYMENT)), 1.0);
+        let long = GameDuration(Tick(30 * TICKS_PER_DAY));
+        assert_eq!(happiness(Some(long)), MIN_HAPPINESS);
+        let mid = happiness(Some(GameDuration(Tick(3 * TICKS_PER_DAY))));
+        assert!(MIN_HAPPINESS < mid && mid < 1.0);
+    }
+
+    #[test]
+    fn alerts_do_not_flicker_around_a_threshold() {
+        let thresholds = [5, 10, 20];
+        assert_eq!(alert_level(0.02, &thresholds, 0), 0);
+        assert_eq!(alert_level(0.12, &thresholds, 0), 2);
+        assert_eq!(alert_level(0.095, &thresholds, 2), 2);
+        assert_eq!(alert_level(0.085, &thresholds, 2), 1);
+        assert_eq!(alert_level(0.3, &thresholds, 1), 3);
+        assert_eq!(alert_level(0.3, &[], 0), 0);
+    }
+
+    #[test]
+    fn welfare_goes_from_the_government_to_the_households() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(20.0, -20.0));
+        let human = spawn_human(&mut test.g, house).unwrap();
+        test.g.write::<Welfare>().policy.allowance_percent = 50;
+
+        let gvt_before = test.g.read::<Government>().money;
+        let household_before = test.g.read::<Households>().money(house);
+
+        track_unemployment(&mut test.g);
+        pay_welfare(&mut test.g);
+
+        assert!(test.g.world().humans[human].unemployed_since.is_some());
+        let allowance = WORKER_CONSUMPTION_PER_MINUTE / 2;
+        assert_eq!(
+            test.g.read::<Households>().money(house),
+            household_before + allowance
+        );
+        assert_eq!(test.g.read::<Government>().money, gvt_before - allowance);
+        assert_eq!(test.g.read::<Welfare>().paid_today, allowance);
+
+        test.g.write::<Welfare>().policy.allowance_percent = 0;
+        pay_welfare(&mut test.g);
+        assert_eq!(test.g.read::<Government>().money, gvt_before - allowance);
+        assert_eq!(
+            Money::ZERO,
+            test.g.read::<Welfare>().policy.allowance_per_minute()
+        );
+    }
+
+    #[test]
+    fn emigrants_leave_their_house_empty() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(20.0, -20.0));
+        let human = spawn_human(&mut test.g, house).unwrap();
+        test.g.write::<Households>().get_mut(house).unwrap().money = Money::new_bucks(1);
+
+        let now = test.g.read::<GameTime>().instant();
+        leave_city(&mut test.g, human, now);
+        test.tick();
+
+        assert!(!test.g.world().humans.contains_key(human));
+        assert!(test.g.read::<Abandonment>().is_empty(house));
+        assert_eq!(test.g.read::<BuildingInfos>().owner(house), None);
+        assert_eq!(test.g.read::<Households>().money(house), STARTING_SAVINGS);
+    }
+}
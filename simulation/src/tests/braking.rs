@@ -0,0 +1,61 @@
+use prototypes::{RollingStockID, DELTA};
+
+use crate::transportation::dynamics::{braking_distance, speed_step};
+use crate::transportation::train::calculate_locomotive;
+use crate::transportation::VehicleKind;
+
+use super::TestCtx;
+
+/// Distance covered tick by tick while braking to a stop
+fn simulated_stop(mut speed: f32, dec: f32) -> f32 {
+    let mut dist = 0.0;
+    while speed > 0.0 {
+        speed = speed_step(speed, 0.0, 0.0, dec);
+        dist += speed * DELTA;
+    }
+    dist
+}
+
+#[test]
+fn stopping_distances_match_the_prototypes() {
+    // loads the prototypes
+    let _test = TestCtx::new();
+
+    let check = |speed: f32, dec: f32| {
+        let expected = braking_distance(speed, dec);
+        let got = simulated_stop(speed, dec);
+        assert!(
+            (got - expected).abs() < expected * 0.02 + 0.5,
+            "stopping from {speed} m/s at {dec} m/s^2: {got} m instead of {expected} m"
+        );
+    };
+
+    for kind in [VehicleKind::Car, VehicleKind::Truck, VehicleKind::Bus] {
+        for speed in [5.0, 15.0, 30.0] {
+            check(speed, kind.deceleration(0.0));
+            check(speed, kind.deceleration(-0.08));
+        }
+    }
+
+    let wagons = [
+        "locomotive",
+        "passenger-wagon",
+        "passenger-wagon",
+        "freight-wagon",
+    ]
+    .map(RollingStockID::new)
+    .to_vec();
+    let train = calculate_locomotive(&wagons);
+    for speed in [10.0, 30.0, 60.0] {
+        check(speed, train.deceleration(0.0));
+    }
+
+    // trains need much more room to stop, which the block reservations look ahead for
+    let car = VehicleKind::Car.braking_distance(30.0, 0.0);
+    assert!(train.braking_distance(30.0, 0.0) > car * 4.0);
+    // and trucks stop later downhill
+    assert!(
+        VehicleKind::Truck.braking_distance(20.0, -0.08)
+            > VehicleKind::Truck.braking_distance(20.0, 0.0)
+    );
+}
@@ -0,0 +1,54 @@
+//! Coalescing the commands before sending them must not change what they do.
+
+use geom::vec2;
+use prototypes::Money;
+
+use crate::map::TerraformKind;
+use crate::map_dynamic::RoadMaintenance;
+use crate::world_command::{WorldCommand, WorldCommands};
+
+use super::TestCtx;
+
+fn raise(x: f32, amount: f32) -> WorldCommand {
+    WorldCommand::Terraform {
+        kind: TerraformKind::Elevation,
+        center: vec2(x, 100.0),
+        radius: 50.0,
+        amount,
+        level: 0.0,
+        slope: None,
+    }
+}
+
+#[test]
+fn coalesced_commands_do_the_same() {
+    let commands = vec![
+        raise(100.0, 10.0),
+        raise(100.0, 20.0),
+        raise(100.0, -5.0),
+        raise(120.0, 10.0),
+        WorldCommand::SetRoadMaintenanceBudget(Money::new_bucks(100)),
+        WorldCommand::SetRoadMaintenanceBudget(Money::new_bucks(200)),
+        raise(120.0, 10.0),
+        WorldCommand::SetRoadMaintenanceBudget(Money::new_bucks(300)),
+    ];
+    let mut coalesced = WorldCommands::from(commands.clone());
+    coalesced.coalesce();
+    assert_eq!(coalesced.iter().count(), 5);
+
+    let mut test = TestCtx::new();
+    let mut test_coalesced = TestCtx::new();
+    test.apply(&commands);
+    test_coalesced.apply(coalesced.as_ref());
+
+    for x in [80.0, 100.0, 110.0, 120.0, 140.0] {
+        let pos = vec2(x, 100.0);
+        let h = test.g.map().environment.height(pos).unwrap();
+        let h_coalesced = test_coalesced.g.map().environment.height(pos).unwrap();
+        assert!((h - h_coalesced).abs() < 1e-3, "{} != {}", h, h_coalesced);
+    }
+    assert_eq!(
+        test_coalesced.g.read::<RoadMaintenance>().daily_budget,
+        Money::new_bucks(300)
+    );
+}
@@ -0,0 +1,59 @@
+use geom::vec3;
+
+use crate::map::{LightPolicy, TurnKind, TurnPolicy};
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+#[test]
+fn crosswalks_follow_the_lights_of_their_road() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[
+        vec3(-200.0, 0.0, 0.0),
+        vec3(0.0, 0.0, 0.0),
+        vec3(200.0, 0.0, 0.0),
+    ]);
+    test.build_roads(&[
+        vec3(0.0, -200.0, 0.0),
+        vec3(0.0, 0.0, 0.0),
+        vec3(0.0, 200.0, 0.0),
+    ]);
+
+    let inter = {
+        let map = test.g.map();
+        map.intersections()
+            .values()
+            .find(|i| i.roads.len() == 4)
+            .unwrap()
+            .id
+    };
+    test.apply(&[WorldCommand::MapUpdateIntersectionPolicy {
+        inter,
+        turn: TurnPolicy::default(),
+        light: LightPolicy::Lights,
+    }]);
+
+    let map = test.g.map();
+    let i = &map.intersections()[inter];
+    let crosswalks: Vec<_> = i
+        .turns()
+        .filter(|t| t.kind == TurnKind::Crosswalk)
+        .map(|t| t.id)
+        .collect();
+    assert!(!crosswalks.is_empty());
+
+    for id in crosswalks {
+        let control = id.crossing_control(map.lanes(), map.roads());
+        assert!(control.is_light());
+        assert!(control.expected_crossing_wait() > 0.0);
+
+        let mut walked = false;
+        for seconds in 0..2000 {
+            if control.crossing_phase(seconds).is_walk() {
+                walked = true;
+                assert!(control.get_behavior(seconds).is_red());
+            }
+        }
+        assert!(walked);
+    }
+}
@@ -0,0 +1,67 @@
+use geom::{vec2, vec3, Color, Polygon};
+use prototypes::ServiceKind;
+
+use crate::economy::DistrictStats;
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+#[test]
+fn buildings_belong_to_the_district_they_are_in() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+    let house = test.build_house_near(vec2(150.0, 10.0));
+    let center = test.g.map().buildings()[house].obb.center();
+
+    let square = |x: f32| {
+        Polygon(vec![
+            vec2(x, -100.0),
+            vec2(x, 100.0),
+            vec2(x + 100.0, 100.0),
+            vec2(x + 100.0, -100.0),
+        ])
+    };
+    let around = (center.x / 100.0).floor() * 100.0;
+    test.apply(&[
+        WorldCommand::MapAddDistrict {
+            name: "Old town".to_string(),
+            color: Color::RED,
+            shape: square(around),
+        },
+        // overlaps the first one, rejected
+        WorldCommand::MapAddDistrict {
+            name: "Overlap".to_string(),
+            color: Color::BLUE,
+            shape: square(around + 50.0),
+        },
+    ]);
+
+    let district = {
+        let map = test.g.map();
+        assert_eq!(map.districts().len(), 1);
+        map.building_district(house).unwrap()
+    };
+
+    let stats = DistrictStats::all(&test.g);
+    assert_eq!(stats[&district].buildings, 1);
+    assert_eq!(stats[&district].coverage.len(), ServiceKind::ALL.len());
+
+    test.apply(&[
+        WorldCommand::MapUpdateDistrict {
+            district,
+            name: "  New town ".to_string(),
+            color: Color::GREEN,
+        },
+        WorldCommand::MapUpdateDistrict {
+            district,
+            name: String::new(),
+            color: Color::BLUE,
+        },
+    ]);
+    assert_eq!(test.g.map().districts()[district].name, "New town");
+    assert_eq!(test.g.map().districts()[district].color, Color::GREEN);
+
+    test.apply(&[WorldCommand::MapRemoveDistrict(district)]);
+    assert!(test.g.map().building_district(house).is_none());
+    assert!(DistrictStats::all(&test.g).is_empty());
+}
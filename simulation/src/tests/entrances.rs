@@ -0,0 +1,147 @@
+use geom::{vec2, vec3, Vec2, Vec3, OBB};
+use prototypes::{EntranceKind, GoodsCompanyID};
+
+use crate::map::{Building, BuildingID, BuildingKind, Entrance, UnconnectedEntrance};
+use crate::map_dynamic::{arrival_entrance, departure_entrance, RoutingStep};
+
+use super::TestCtx;
+
+/// A road along the x axis, the required entrances of the supermarket face it
+fn supermarket_ctx() -> (TestCtx, BuildingKind, OBB) {
+    let test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+    let kind = BuildingKind::GoodsCompany(GoodsCompanyID::new("supermarket"));
+    let obb = OBB::new(vec2(150.0, 60.0), Vec2::Y, 80.0, 80.0);
+    (test, kind, obb)
+}
+
+fn build(test: &TestCtx, kind: BuildingKind, obb: &OBB) -> Option<BuildingID> {
+    let BuildingKind::GoodsCompany(id) = kind else {
+        unreachable!()
+    };
+    test.g
+        .map_mut()
+        .build_special_building(obb, kind, id.prototype().bgen, None, None)
+}
+
+#[test]
+fn placement_needs_the_required_entrances_connected() {
+    let (test, kind, near) = supermarket_ctx();
+    let far = OBB::new(vec2(150.0, 200.0), Vec2::Y, 80.0, 80.0);
+
+    assert_eq!(
+        test.g.map().check_entrances(&far, kind.entrances()),
+        Err(UnconnectedEntrance(EntranceKind::Pedestrian))
+    );
+    assert_eq!(build(&test, kind, &far), None);
+
+    // the optional entrance on the +x side has no sidewalk, it doesn't matter
+    assert_eq!(
+        test.g.map().check_entrances(&near, kind.entrances()),
+        Ok(())
+    );
+    let b = build(&test, kind, &near).unwrap();
+    assert_eq!(test.g.map().buildings()[b].entrances.len(), 4);
+}
+
+/// The supermarket with entrances set by hand around its center
+fn with_entrances(offsets: &[(Vec2, EntranceKind)]) -> Building {
+    let (test, kind, obb) = supermarket_ctx();
+    let b = build(&test, kind, &obb).unwrap();
+    let mut building = test.g.map().buildings()[b].clone();
+    let center = building.obb.center();
+    building.entrances = offsets
+        .iter()
+        .map(|&(off, kind)| Entrance {
+            pos: (center + off).z(0.0),
+            kind,
+            optional: false,
+        })
+        .collect();
+    building
+}
+
+fn pos(building: &Building, off: Vec2) -> Vec3 {
+    (building.obb.center() + off).z(0.0)
+}
+
+const LEFT: Vec2 = vec2(-25.0, -40.0);
+const RIGHT: Vec2 = vec2(25.0, -40.0);
+const VEHICLE: Vec2 = vec2(40.0, -40.0);
+const FREIGHT: Vec2 = vec2(-30.0, 40.0);
+
+#[test]
+fn walkers_use_the_entrance_on_their_side() {
+    let b = with_entrances(&[
+        (LEFT, EntranceKind::Pedestrian),
+        (RIGHT, EntranceKind::Pedestrian),
+        (VEHICLE, EntranceKind::Vehicle),
+    ]);
+    let from_left = vec3(0.0, 0.0, 0.0);
+    let from_right = vec3(300.0, 0.0, 0.0);
+
+    assert_eq!(arrival_entrance(&b, None, from_left), pos(&b, LEFT));
+    assert_eq!(arrival_entrance(&b, None, from_right), pos(&b, RIGHT));
+
+    // leaving toward where the next walk goes, or where they are when they don't walk next
+    let toward_right = [RoutingStep::WalkTo(from_right)];
+    assert_eq!(
+        departure_entrance(&b, &toward_right, from_left),
+        pos(&b, RIGHT)
+    );
+    let not_walking = [RoutingStep::GetInBuilding(b.id)];
+    assert_eq!(
+        departure_entrance(&b, &not_walking, from_left),
+        pos(&b, LEFT)
+    );
+}
+
+#[test]
+fn drivers_walk_from_their_entrance() {
+    let b = with_entrances(&[
+        (LEFT, EntranceKind::Pedestrian),
+        (RIGHT, EntranceKind::Pedestrian),
+        (VEHICLE, EntranceKind::Vehicle),
+        (FREIGHT, EntranceKind::Freight),
+    ]);
+    let from_left = vec3(0.0, 0.0, 0.0);
+    let from_right = vec3(300.0, 0.0, 0.0);
+
+    // the car parks by the vehicle entrance on the right whatever side it comes from
+    assert_eq!(
+        arrival_entrance(&b, Some(EntranceKind::Vehicle), from_left),
+        pos(&b, RIGHT)
+    );
+    // the truck unloads at the back, on the left
+    assert_eq!(
+        arrival_entrance(&b, Some(EntranceKind::Freight), from_right),
+        pos(&b, LEFT)
+    );
+
+    // without a freight entrance the trucks use the vehicle one
+    let b = with_entrances(&[
+        (LEFT, EntranceKind::Pedestrian),
+        (RIGHT, EntranceKind::Pedestrian),
+        (VEHICLE, EntranceKind::Vehicle),
+    ]);
+    assert_eq!(
+        arrival_entrance(&b, Some(EntranceKind::Freight), from_left),
+        pos(&b, RIGHT)
+    );
+}
+
+#[test]
+fn buildings_without_entrances_use_their_door() {
+    let b = with_entrances(&[]);
+    let from = vec3(0.0, 0.0, 0.0);
+
+    assert_eq!(arrival_entrance(&b, None, from), b.door_pos);
+    assert_eq!(
+        arrival_entrance(&b, Some(EntranceKind::Freight), from),
+        b.door_pos
+    );
+    assert_eq!(
+        departure_entrance(&b, &[RoutingStep::WalkTo(from)], from),
+        b.door_pos
+    );
+}
@@ -0,0 +1,237 @@
+use geom::{vec2, vec3, Color, Transform, Vec2, OBB};
+use prototypes::{BuildingGen, GameTime, ServiceDepotID, Tick, TICKS_PER_SECOND};
+
+use crate::map::{BuildingKind, LaneKind, PathKind};
+use crate::map_dynamic::Itinerary;
+use crate::transportation::deadlock::{
+    update_deadlocks, Deadlocks, DEADLOCK_AFTER, MAX_RECOVERY_ATTEMPTS,
+};
+use crate::transportation::incident::{break_down, clear_incident, Incidents};
+use crate::transportation::service_fleet::{FleetStatus, ServiceFleets};
+use crate::transportation::{make_vehicle_entity, Vehicle, VehicleKind, VehicleState, WRECK_FLAG};
+use crate::world::VehicleID;
+
+use super::TestCtx;
+
+/// A car driving from one end of the road to the other
+fn driving_car(test: &mut TestCtx) -> VehicleID {
+    let (trans, it) = {
+        let map = test.g.map();
+        let lane = map
+            .lanes()
+            .values()
+            .find(|l| l.kind == LaneKind::Driving)
+            .unwrap();
+        let trans = Transform::new_dir(lane.points.first(), lane.points.first_dir().unwrap());
+        let it = Itinerary::route(
+            test.g.read::<GameTime>().tick,
+            trans.pos,
+            lane.points.last(),
+            &map,
+            PathKind::Vehicle,
+        )
+        .unwrap();
+        (trans, it)
+    };
+
+    make_vehicle_entity(
+        &mut test.g,
+        trans,
+        Vehicle {
+            ang_velocity: 0.0,
+            wait_time: 0.0,
+            max_speed_multiplier: 1.0,
+            state: VehicleState::Driving,
+            kind: VehicleKind::Car,
+            model: None,
+            tint: Color::WHITE,
+            flag: 0,
+            blocked_by: None,
+            cargo: vec![],
+        },
+        it,
+        true,
+    )
+}
+
+#[test]
+fn breakdown_blocks_the_lane_until_cleared() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(400.0, 0.0, 0.0)]);
+
+    let car = driving_car(&mut test);
+    for _ in 0..20 {
+        test.tick();
+    }
+
+    assert!(break_down(&mut test.g, car));
+    // already broken down
+    assert!(!break_down(&mut test.g, car));
+
+    let lane = test.g.read::<Incidents>().active[&car].lane;
+    assert!(test.g.map().lanes()[lane].blocked);
+    let v = &test.g.world.vehicles[car];
+    assert!(matches!(v.vehicle.state, VehicleState::BrokenDown(_)));
+    assert_eq!(v.vehicle.flag, WRECK_FLAG);
+
+    for _ in 0..50 {
+        test.tick();
+    }
+    let pos = test.g.world.vehicles[car].trans.pos;
+    for _ in 0..50 {
+        test.tick();
+    }
+    assert!(test.g.world.vehicles[car].trans.pos.is_close(pos, 0.01));
+
+    clear_incident(&mut test.g, car, true);
+    assert!(!test.g.map().lanes()[lane].blocked);
+    assert!(test.g.read::<Incidents>().active.is_empty());
+    let v = &test.g.world.vehicles[car];
+    assert!(matches!(v.vehicle.state, VehicleState::Driving));
+    assert_eq!(v.vehicle.flag, 0);
+}
+
+#[test]
+fn tow_truck_comes_from_the_depot() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(400.0, 0.0, 0.0)]);
+
+    let proto = ServiceDepotID::new("tow-depot").prototype();
+    let depot = test
+        .g
+        .map_mut()
+        .build_special_building(
+            &OBB::new(vec2(300.0, 40.0), Vec2::Y, proto.size.w, proto.size.h),
+            BuildingKind::ServiceDepot(proto.id),
+            BuildingGen::CenteredDoor {
+                vertical_factor: 1.0,
+            },
+            None,
+            None,
+        )
+        .unwrap();
+
+    let car = driving_car(&mut test);
+    for _ in 0..20 {
+        test.tick();
+    }
+    assert_eq!(
+        test.g.read::<ServiceFleets>().depots[&depot].n_idle(),
+        proto.initial_fleet as usize
+    );
+
+    assert!(break_down(&mut test.g, car));
+    let truck = test.g.read::<Incidents>().active[&car].tow_truck.unwrap();
+    assert!(test.g.world.vehicles.contains_key(truck));
+    assert!(!break_down(&mut test.g, truck));
+    {
+        let fleets = test.g.read::<ServiceFleets>();
+        assert_eq!(fleets.status(truck), Some(FleetStatus::EnRoute));
+        assert_eq!(fleets.find(truck).map(|(b, _)| b), Some(depot));
+    }
+
+    clear_incident(&mut test.g, car, true);
+    assert_eq!(
+        test.g.read::<ServiceFleets>().status(truck),
+        Some(FleetStatus::Returning)
+    );
+}
+
+#[test]
+fn no_tow_truck_without_depot() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(400.0, 0.0, 0.0)]);
+
+    let car = driving_car(&mut test);
+    for _ in 0..20 {
+        test.tick();
+    }
+
+    assert!(break_down(&mut test.g, car));
+    assert!(test.g.read::<Incidents>().active[&car].tow_truck.is_none());
+}
+
+#[test]
+fn vehicles_waiting_on_each_other_are_let_through() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(400.0, 0.0, 0.0)]);
+
+    let a = driving_car(&mut test);
+    let b = driving_car(&mut test);
+    let handle = |test: &TestCtx, v: VehicleID| test.g.world.vehicles[v].collider.unwrap().0;
+    let (ha, hb) = (handle(&test, a), handle(&test, b));
+    test.g.world.vehicles[a].vehicle.blocked_by = Some(hb);
+    test.g.world.vehicles[b].vehicle.blocked_by = Some(ha);
+
+    update_deadlocks(&mut test.g);
+    assert_eq!(test.g.read::<Deadlocks>().cycles.len(), 1);
+    assert_eq!(test.g.read::<Deadlocks>().recoveries_last_hour(), 0);
+
+    let tick = test.g.read::<GameTime>().tick;
+    let later = tick.0 + (DEADLOCK_AFTER as u64 + 1) * TICKS_PER_SECOND;
+    *test.g.write::<GameTime>() = GameTime::new(Tick(later));
+    update_deadlocks(&mut test.g);
+
+    // the smallest id goes first, the other one waits behind it
+    let (first, second) = (a.min(b), a.max(b));
+    let vehicles = &test.g.world.vehicles;
+    assert!(matches!(
+        vehicles[first].vehicle.state,
+        VehicleState::Panicking(_)
+    ));
+    assert!(matches!(
+        vehicles[second].vehicle.state,
+        VehicleState::Driving
+    ));
+
+    let deadlocks = test.g.read::<Deadlocks>();
+    assert!(deadlocks.cycles.is_empty());
+    assert_eq!(deadlocks.recoveries_last_hour(), 1);
+    assert_eq!(deadlocks.total_recoveries, 1);
+}
+
+#[test]
+fn loops_forming_again_wait_longer_then_are_given_up() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(400.0, 0.0, 0.0)]);
+
+    let a = driving_car(&mut test);
+    let b = driving_car(&mut test);
+    let handle = |test: &TestCtx, v: VehicleID| test.g.world.vehicles[v].collider.unwrap().0;
+    let (ha, hb) = (handle(&test, a), handle(&test, b));
+    let lock = |test: &mut TestCtx| {
+        for (v, other) in [(a, hb), (b, ha)] {
+            let v = &mut test.g.world.vehicles[v].vehicle;
+            v.state = VehicleState::Driving;
+            v.blocked_by = Some(other);
+        }
+    };
+    let wait = |test: &mut TestCtx, seconds: f64| {
+        let tick = test.g.read::<GameTime>().tick.0;
+        let later = tick + (seconds * TICKS_PER_SECOND as f64) as u64;
+        *test.g.write::<GameTime>() = GameTime::new(Tick(later));
+        update_deadlocks(&mut test.g);
+    };
+
+    for attempt in 0..MAX_RECOVERY_ATTEMPTS {
+        lock(&mut test);
+        update_deadlocks(&mut test.g);
+        let delay = test.g.read::<Deadlocks>().recovery_delay(a.min(b)).unwrap();
+        assert_eq!(delay, DEADLOCK_AFTER * (1 << attempt) as f64);
+
+        wait(&mut test, delay - 1.0);
+        assert_eq!(test.g.read::<Deadlocks>().cycles.len(), 1);
+        wait(&mut test, 2.0);
+        assert!(test.g.read::<Deadlocks>().cycles.is_empty());
+    }
+
+    // the loop keeps coming back, it is left for the player to fix
+    lock(&mut test);
+    update_deadlocks(&mut test.g);
+    assert_eq!(test.g.read::<Deadlocks>().recovery_delay(a.min(b)), None);
+    wait(&mut test, DEADLOCK_AFTER * 100.0);
+    let deadlocks = test.g.read::<Deadlocks>();
+    assert_eq!(deadlocks.cycles.len(), 1);
+    assert_eq!(deadlocks.given_up.len(), 1);
+    assert_eq!(deadlocks.total_recoveries, MAX_RECOVERY_ATTEMPTS as u64);
+}
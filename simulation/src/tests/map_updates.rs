@@ -0,0 +1,165 @@
+//! Which update types each world command dispatches, the renderers rely on it to rebuild only
+//! what changed.
+
+use geom::{vec2, vec3, Circle};
+
+use crate::map::{
+    LanePatternBuilder, LightPolicy, LotKind, ProjectFilter, SubscriberChunkID, TerraformKind,
+    TurnPolicy, UpdateType, ZoneBrush,
+};
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+/// The types dispatched while applying the command
+fn fired(test: &mut TestCtx, command: WorldCommand) -> UpdateType {
+    let mut sub = test.g.map().subscribe(UpdateType::all());
+    test.apply(&[command]);
+    sub.take_updated_types()
+}
+
+#[test]
+fn commands_fire_their_update_types() {
+    let mut test = TestCtx::new();
+
+    let (from, to) = {
+        let map = test.g.map();
+        (
+            map.project(vec3(0.0, 0.0, 0.0), 0.0, ProjectFilter::ALL),
+            map.project(vec3(200.0, 0.0, 0.0), 0.0, ProjectFilter::ALL),
+        )
+    };
+    let built = fired(
+        &mut test,
+        WorldCommand::MapMakeConnection {
+            from,
+            to,
+            inter: None,
+            pat: LanePatternBuilder::new().build(),
+        },
+    );
+    // trees and props under the road may be removed too
+    assert_eq!(
+        built - UpdateType::TerrainSplat,
+        UpdateType::RoadGeometry | UpdateType::IntersectionControl | UpdateType::LotZoning
+    );
+
+    let (road, inter) = {
+        let map = test.g.map();
+        let (id, road) = map.roads().iter().next().unwrap();
+        (id, road.src)
+    };
+
+    assert_eq!(
+        fired(
+            &mut test,
+            WorldCommand::MapUpdateIntersectionPolicy {
+                inter,
+                turn: TurnPolicy::default(),
+                light: LightPolicy::default(),
+            }
+        ),
+        UpdateType::RoadGeometry | UpdateType::IntersectionControl
+    );
+
+    assert_eq!(
+        fired(
+            &mut test,
+            WorldCommand::RenameRoad {
+                road,
+                name: "Main street".to_string(),
+            }
+        ),
+        UpdateType::empty()
+    );
+
+    assert_eq!(
+        fired(
+            &mut test,
+            WorldCommand::MapPaintZone {
+                brush: ZoneBrush::Circle(Circle::new(vec2(100.0, 0.0), 50.0)),
+                kind: LotKind::Residential,
+            }
+        ),
+        UpdateType::LotZoning
+    );
+
+    let lot = test.g.map().lots().keys().next().unwrap();
+    assert_eq!(
+        fired(&mut test, WorldCommand::MapBuildHouse(lot)),
+        UpdateType::LotZoning | UpdateType::BuildingAdded
+    );
+
+    let house = test.g.map().buildings().keys().next().unwrap();
+    assert_eq!(
+        fired(&mut test, WorldCommand::MapRemoveBuilding(house)),
+        UpdateType::BuildingRemoved
+    );
+
+    assert_eq!(
+        fired(
+            &mut test,
+            WorldCommand::Terraform {
+                kind: TerraformKind::Elevation,
+                center: vec2(100.0, 100.0),
+                radius: 50.0,
+                amount: 10.0,
+                level: 0.0,
+                slope: None,
+            }
+        ),
+        UpdateType::TerrainHeight
+    );
+
+    let rail = {
+        let mut map = test.g.map_mut();
+        let a = map.project(vec3(0.0, 400.0, 0.0), 0.0, ProjectFilter::ALL);
+        let b = map.project(vec3(200.0, 400.0, 0.0), 0.0, ProjectFilter::ALL);
+        let pat = LanePatternBuilder::new().rail(true).build();
+        map.make_connection(a, b, None, &pat).unwrap().1
+    };
+    assert_eq!(
+        fired(
+            &mut test,
+            WorldCommand::SetRoadElectrified {
+                road: rail,
+                electrified: true,
+            }
+        ),
+        UpdateType::RoadSurface
+    );
+}
+
+#[test]
+fn chunk_revisions_track_update_types() {
+    let test = TestCtx::new();
+    let chunk = SubscriberChunkID::new(vec2(100.0, 100.0));
+    let map_revision = test.g.map().subscribers.revision();
+
+    test.g.map_mut().terraform(
+        Default::default(),
+        TerraformKind::Elevation,
+        vec2(100.0, 100.0),
+        50.0,
+        10.0,
+        0.0,
+        None,
+    );
+
+    let map = test.g.map();
+    let height = map.chunk_revision(chunk, UpdateType::TerrainHeight);
+    assert!(height > map_revision);
+    assert_eq!(map.chunk_revision(chunk, UpdateType::RoadGeometry), 0);
+    assert_eq!(
+        map.chunk_revision(chunk, UpdateType::RoadGeometry | UpdateType::TerrainHeight),
+        height
+    );
+    assert!(map
+        .subscribers
+        .changed_since(map_revision, UpdateType::TerrainHeight)
+        .contains(&chunk));
+    assert!(map
+        .subscribers
+        .changed_since(map_revision, UpdateType::BuildingAdded)
+        .is_empty());
+}
@@ -10,6 +10,16 @@ use common::logger::MyLog;
 use common::saveload::Encoder;
 use geom::{Vec2, Vec3};
 
+mod braking;
+mod coalesce;
+mod crossings;
+mod districts;
+mod entrances;
+mod incidents;
+mod map_updates;
+mod restrictions;
+mod road_layout;
+mod saves;
 mod test_iso;
 mod vehicles;
 
@@ -26,6 +36,13 @@ impl TestCtx {
         let g = Simulation::new_with_options(SimulationOptions {
             terrain_size: 1,
             save_replay: false,
+            scenario: None,
+            car_ownership_rate: 1.0,
+            season_days: 0,
+            deposit_abundance: 1.0,
+            latitude: Default::default(),
+            rules: Default::default(),
+            mod_settings: Default::default(),
         });
         let sched = Simulation::schedule();
 
@@ -0,0 +1,80 @@
+use geom::{vec2, vec3, Polygon, Vec3};
+use prototypes::RestrictionKind;
+
+use crate::map::{LanePatternBuilder, MapProject};
+use crate::rules::GameRules;
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+fn connection(from: Vec3, to: Vec3) -> WorldCommand {
+    WorldCommand::MapMakeConnection {
+        from: MapProject::ground(from),
+        to: MapProject::ground(to),
+        inter: None,
+        pat: LanePatternBuilder::default().build(),
+    }
+}
+
+#[test]
+fn restricted_areas_refuse_construction() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+    let road = test.g.map().roads().keys().next().unwrap();
+    let lot = test
+        .g
+        .map()
+        .lots()
+        .values()
+        .min_by_key(|lot| lot.shape.center().distance2(vec2(150.0, 10.0)) as i32)
+        .unwrap()
+        .id;
+
+    let square = Polygon(vec![
+        vec2(100.0, -100.0),
+        vec2(200.0, -100.0),
+        vec2(200.0, 100.0),
+        vec2(100.0, 100.0),
+    ]);
+    let add = |kind| WorldCommand::MapAddRestriction {
+        kind,
+        shape: square.clone(),
+    };
+
+    // only sandbox games can draw restricted areas
+    test.apply(&[add(RestrictionKind::NoBuild)]);
+    assert!(test.g.map().restrictions().is_empty());
+
+    test.g.write::<GameRules>().sandbox = true;
+    test.apply(&[add(RestrictionKind::NoBuild)]);
+    assert_eq!(test.g.map().restrictions().len(), 1);
+
+    let n_roads = test.g.map().roads().len();
+    test.apply(&[
+        WorldCommand::MapBuildHouse(lot),
+        connection(vec3(150.0, -150.0, 0.0), vec3(150.0, 150.0, 0.0)),
+    ]);
+    assert!(test.g.map().lots().contains_key(lot));
+    assert_eq!(test.g.map().roads().len(), n_roads);
+
+    test.apply(&[connection(
+        vec3(400.0, -150.0, 0.0),
+        vec3(400.0, 150.0, 0.0),
+    )]);
+    assert_eq!(test.g.map().roads().len(), n_roads + 1);
+
+    // what stands in a no-build area can be demolished, not in protected nature
+    test.apply(&[add(RestrictionKind::Nature)]);
+    test.apply(&[WorldCommand::MapRemoveRoad(road)]);
+    assert!(test.g.map().roads().contains_key(road));
+
+    let ids: Vec<_> = test.g.map().restrictions().keys().collect();
+    test.apply(
+        &ids.into_iter()
+            .map(WorldCommand::MapRemoveRestriction)
+            .collect::<Vec<_>>(),
+    );
+    assert!(test.g.map().restrictions().is_empty());
+    test.apply(&[WorldCommand::MapRemoveRoad(road)]);
+    assert!(!test.g.map().roads().contains_key(road));
+}
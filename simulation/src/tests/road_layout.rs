@@ -0,0 +1,35 @@
+use geom::{vec2, vec3};
+
+use crate::map::{grid_layout, LanePatternBuilder, MapProject, ProjectFilter};
+use crate::world_command::WorldCommand;
+
+use super::TestCtx;
+
+#[test]
+fn grid_connects_to_the_road_on_its_border() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(200.0, 0.0, 0.0)]);
+
+    // 2 blocks wide, 1 block deep, its bottom row on the existing road
+    let (nodes, _) = grid_layout(vec2(50.0, 0.0), vec2(150.0, 80.0), vec2(50.0, 80.0));
+    let projects: Vec<MapProject> = {
+        let map = test.g.map();
+        nodes
+            .iter()
+            .map(|p| map.project(p.z(0.0), 5.0, ProjectFilter::INTER | ProjectFilter::ROAD))
+            .collect()
+    };
+    assert!(projects[..3].iter().all(|p| !p.kind.is_ground()));
+
+    // every street starting from the road splits it, the later ones must still find it
+    let pat = LanePatternBuilder::new().build();
+    let links = [(0, 3), (1, 4), (2, 5), (3, 4), (4, 5)]
+        .into_iter()
+        .map(|(a, b)| (a, b, None, pat.clone()))
+        .collect();
+    test.apply(&[WorldCommand::MapMakeMultipleConnections(projects, links)]);
+
+    let map = test.g.map();
+    assert_eq!(map.intersections().len(), 8);
+    assert_eq!(map.roads().len(), 9);
+}
@@ -0,0 +1,45 @@
+use common::saveload::{CompressedBincode, Encoder};
+use geom::vec3;
+use prototypes::GameTime;
+
+use crate::utils::savefile::{read_header_from_disk, SaveCompression};
+use crate::Simulation;
+
+use super::TestCtx;
+
+#[test]
+fn saves_load_back_the_same() {
+    let mut test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+    for _ in 0..10 {
+        test.tick();
+    }
+
+    for compression in SaveCompression::ALL {
+        let name = format!("test_saves_{:?}", compression);
+        let path = CompressedBincode::filename(&name);
+        test.g.save_to_disk_with(&name, compression);
+
+        let header = read_header_from_disk(&path).unwrap();
+        assert_eq!(header.tick, test.g.read::<GameTime>().tick);
+
+        let loaded = Simulation::load_from_disk(&name).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(loaded.is_equal(&test.g));
+    }
+}
+
+#[test]
+fn saves_made_before_the_blocks_still_load() {
+    let test = TestCtx::new();
+    test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(300.0, 0.0, 0.0)]);
+
+    let name = "test_saves_legacy";
+    let path = CompressedBincode::filename(name);
+    CompressedBincode::save(&test.g, name);
+    assert!(read_header_from_disk(&path).is_none());
+
+    let loaded = Simulation::load_from_disk(name).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert!(loaded.is_equal(&test.g));
+}
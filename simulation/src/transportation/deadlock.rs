@@ -0,0 +1,291 @@
+//! Finds the vehicles waiting on each other in a loop, where none of them will ever move.
+//! Once a loop lasts for too long, one of its vehicles is let through to break it.
+//! A loop that forms again right after waits twice as long each time, and is given up on after
+//! a few tries: the intersection needs to be fixed by the player.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{GameInstant, GameTime, TICKS_PER_SECOND};
+
+use crate::event_log::{log_event, EventCategory, EventSubject, Severity};
+use crate::map::{Map, TraverseKind};
+use crate::transportation::intersection_stats::{intersection_of, IntersectionStats};
+use crate::transportation::VehicleState;
+use crate::world::VehicleID;
+use crate::{AnyEntity, Simulation};
+
+/// Longest loop of vehicles looked for
+pub const MAX_CYCLE_LEN: usize = 32;
+/// Seconds a loop lasts before one of its vehicles is let through
+pub const DEADLOCK_AFTER: f64 = 30.0;
+/// Times the same loop is broken before it is given up on
+pub const MAX_RECOVERY_ATTEMPTS: u32 = 4;
+/// Seconds after which the tries of a vehicle are forgotten
+const RECOVERY_MEMORY: f64 = GameTime::HOUR as f64;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+struct Retries {
+    attempts: u32,
+    last: GameInstant,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Deadlocks {
+    /// Vehicle each stopped vehicle is waiting behind, updated every second
+    #[serde(skip)]
+    pub waits: BTreeMap<VehicleID, VehicleID>,
+    /// Loops of waiting vehicles starting from their smallest id, and when they were first seen
+    pub cycles: Vec<(Vec<VehicleID>, GameInstant)>,
+    /// When the loops were broken during the last hour
+    recoveries: VecDeque<GameInstant>,
+    pub total_recoveries: u64,
+    /// Tries to break the loops, by the vehicle let through
+    retries: BTreeMap<VehicleID, Retries>,
+    /// Loops broken too many times, nothing is done about them until they clear up
+    pub given_up: Vec<Vec<VehicleID>>,
+}
+
+impl Deadlocks {
+    pub fn recoveries_last_hour(&self) -> usize {
+        self.recoveries.len()
+    }
+
+    /// Whether the vehicle is part of a loop
+    pub fn in_cycle(&self, v: VehicleID) -> bool {
+        self.cycles.iter().any(|(c, _)| c.contains(&v))
+    }
+
+    /// Seconds the loop whose smallest vehicle is `first` lasts before it is broken, doubled
+    /// each time it was broken lately. None once it was broken too many times.
+    pub fn recovery_delay(&self, first: VehicleID) -> Option<f64> {
+        let attempts = self.retries.get(&first).map_or(0, |r| r.attempts);
+        (attempts < MAX_RECOVERY_ATTEMPTS).then(|| DEADLOCK_AFTER * (1u32 << attempts) as f64)
+    }
+}
+
+/// Loops of the wait-for graph, each starting from its smallest node.
+/// Every node waits on at most one other, so following the waits from each node finds them all.
+/// The walks stop after `max_len` steps so that long queues are not followed to the end.
+pub fn find_cycles<K: Ord + Copy>(waits: &BTreeMap<K, K>, max_len: usize) -> Vec<Vec<K>> {
+    let mut cycles = vec![];
+    let mut visited = BTreeSet::new();
+
+    for &start in waits.keys() {
+        if visited.contains(&start) {
+            continue;
+        }
+        let mut path = vec![start];
+        let mut cur = start;
+        while let Some(&next) = waits.get(&cur) {
+            if path.len() > max_len {
+                break;
+            }
+            if let Some(i) = path.iter().position(|&p| p == next) {
+                let mut cycle = path[i..].to_vec();
+                let min = cycle.iter().enumerate().min_by_key(|(_, k)| **k).unwrap().0;
+                cycle.rotate_left(min);
+                cycles.push(cycle);
+                break;
+            }
+            if visited.contains(&next) {
+                break;
+            }
+            path.push(next);
+            cur = next;
+        }
+        visited.extend(path);
+    }
+
+    cycles.sort();
+    cycles
+}
+
+/// Every second, rebuilds the wait-for graph and breaks the loops that lasted too long.
+/// The vehicle with the smallest id is let through so that every client picks the same one.
+pub(crate) fn deadlock_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::deadlock_system");
+    if sim.get_tick() % TICKS_PER_SECOND != 0 {
+        return;
+    }
+    update_deadlocks(sim);
+}
+
+pub(crate) fn update_deadlocks(sim: &mut Simulation) {
+    let time = *sim.read::<GameTime>();
+    let now = time.instant();
+
+    let by_handle: BTreeMap<_, _> = sim
+        .world
+        .vehicles
+        .iter()
+        .filter_map(|(id, v)| Some((v.collider.as_ref()?.0, id)))
+        .collect();
+    let waits: BTreeMap<VehicleID, VehicleID> = sim
+        .world
+        .vehicles
+        .iter()
+        .filter(|(_, v)| matches!(v.vehicle.state, VehicleState::Driving))
+        .filter_map(|(id, v)| Some((id, *by_handle.get(&v.vehicle.blocked_by?)?)))
+        .collect();
+
+    let mut deadlocks = sim.write::<Deadlocks>();
+    let cycles = find_cycles(&waits, MAX_CYCLE_LEN)
+        .into_iter()
+        .map(|c| {
+            let since = deadlocks
+                .cycles
+                .iter()
+                .find(|(old, _)| *old == c)
+                .map_or(now, |(_, since)| *since);
+            (c, since)
+        })
+        .collect::<Vec<_>>();
+
+    while deadlocks
+        .recoveries
+        .front()
+        .is_some_and(|t| t.elapsed(&time).seconds() > GameTime::HOUR as f64)
+    {
+        deadlocks.recoveries.pop_front();
+    }
+
+    deadlocks
+        .retries
+        .retain(|_, r| r.last.elapsed(&time).seconds() < RECOVERY_MEMORY);
+    deadlocks
+        .given_up
+        .retain(|c| cycles.iter().any(|(c2, _)| c2 == c));
+
+    let mut to_break = vec![];
+    let mut give_up = vec![];
+    let kept: Vec<_> = cycles
+        .into_iter()
+        .filter(|(c, since)| {
+            let Some(delay) = deadlocks.recovery_delay(c[0]) else {
+                if !deadlocks.given_up.contains(c) {
+                    give_up.push(c.clone());
+                }
+                return true;
+            };
+            if since.elapsed(&time).seconds() < delay {
+                return true;
+            }
+            to_break.push((c[0], c.len()));
+            false
+        })
+        .collect();
+    deadlocks.cycles = kept;
+    deadlocks.waits = waits;
+    deadlocks.recoveries.extend(to_break.iter().map(|_| now));
+    deadlocks.total_recoveries += to_break.len() as u64;
+    for &(id, _) in &to_break {
+        let retries = deadlocks.retries.entry(id).or_insert(Retries {
+            attempts: 0,
+            last: now,
+        });
+        retries.attempts += 1;
+        retries.last = now;
+    }
+    deadlocks.given_up.extend(give_up.iter().cloned());
+    drop(deadlocks);
+
+    for cycle in give_up {
+        let id = cycle[0];
+        let street = sim
+            .world
+            .vehicles
+            .get(id)
+            .and_then(|v| street_name(&sim.map(), v.it.get_travers().map(|t| t.kind)));
+        let n = cycle.len();
+        let text = match street {
+            Some(street) => {
+                format!(
+                    "{n} vehicles keep getting stuck near {street}, the intersection needs a look"
+                )
+            }
+            None => format!("{n} vehicles keep getting stuck, the intersection needs a look"),
+        };
+        log_event(
+            sim,
+            EventCategory::Transport,
+            Severity::Warning,
+            text,
+            Some(EventSubject::Entity(AnyEntity::VehicleID(id))),
+        );
+    }
+
+    for (id, n) in to_break {
+        let Some(v) = sim.world.vehicles.get_mut(id) else {
+            continue;
+        };
+        v.vehicle.state = VehicleState::Panicking(now);
+        v.vehicle.blocked_by = None;
+        let travers = v.it.get_travers().map(|t| t.kind);
+        let street = street_name(&sim.map(), travers);
+        let inter = travers.and_then(|t| intersection_of(&sim.map(), t));
+        if let Some(inter) = inter {
+            sim.write::<IntersectionStats>()
+                .record_recovery(inter, IntersectionStats::hour(&time));
+        }
+
+        let text = match street {
+            Some(street) => format!("{n} vehicles were stuck waiting on each other near {street}"),
+            None => format!("{n} vehicles were stuck waiting on each other"),
+        };
+        log_event(
+            sim,
+            EventCategory::Transport,
+            Severity::Warning,
+            text,
+            Some(EventSubject::Entity(AnyEntity::VehicleID(id))),
+        );
+    }
+}
+
+/// Name of the road the vehicle is on, or of a road of the intersection it is crossing
+fn street_name(map: &Map, kind: Option<TraverseKind>) -> Option<String> {
+    let road = match kind? {
+        TraverseKind::Lane(l) => map.lanes().get(l)?.parent,
+        TraverseKind::Turn(t) => *map
+            .intersections()
+            .get(t.parent)?
+            .roads
+            .iter()
+            .find(|r| map.roads().get(**r).is_some_and(|r| !r.name.is_empty()))?,
+    };
+    map.roads()
+        .get(road)
+        .map(|r| r.name.clone())
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_are_found_once_from_their_smallest_node() {
+        // 1 -> 2 -> 3 -> 1, 4 -> 2 queued behind the loop, 5 -> 6 -> 7 a plain queue
+        let waits: BTreeMap<u32, u32> = [(1, 2), (2, 3), (3, 1), (4, 2), (5, 6), (6, 7), (9, 8)]
+            .into_iter()
+            .collect();
+        assert_eq!(find_cycles(&waits, MAX_CYCLE_LEN), vec![vec![1, 2, 3]]);
+
+        let waits: BTreeMap<u32, u32> = [(3, 2), (2, 3), (7, 5), (5, 6), (6, 7)]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            find_cycles(&waits, MAX_CYCLE_LEN),
+            vec![vec![2, 3], vec![5, 6, 7]]
+        );
+    }
+
+    #[test]
+    fn long_cycles_are_not_followed() {
+        let waits: BTreeMap<u32, u32> = (0..10).map(|i| (i, (i + 1) % 10)).collect();
+        assert!(find_cycles(&waits, 5).is_empty());
+        assert_eq!(find_cycles(&waits, 10).len(), 1);
+    }
+}
@@ -0,0 +1,59 @@
+//! Longitudinal dynamics shared by road vehicles and trains.
+//! Masses are in metric tons and powers in kW, so power / mass / speed is directly in m/s^2.
+
+use prototypes::DELTA;
+
+/// m/s^2
+pub const GRAVITY: f32 = 9.81;
+
+/// Braking never gets weaker than that, even down a steep hill
+const MIN_DECELERATION: f32 = 0.3;
+
+/// Distance needed to stop from `speed` when braking at `deceleration`
+pub fn braking_distance(speed: f32, deceleration: f32) -> f32 {
+    speed * speed / (2.0 * deceleration)
+}
+
+/// Acceleration the engine gives at that speed: limited by traction when starting,
+/// then by the power as the force it can apply is power / speed.
+/// `slope` is the sine of the grade, positive uphill.
+pub fn acceleration(max_acc: f32, power: f32, mass: f32, speed: f32, slope: f32) -> f32 {
+    let engine = max_acc.min(power / (mass * speed.abs().max(1.0)));
+    engine - GRAVITY * slope
+}
+
+/// Braking on a grade, a hill going down makes the stop longer
+pub fn deceleration(braking: f32, slope: f32) -> f32 {
+    (braking + GRAVITY * slope).max(MIN_DECELERATION)
+}
+
+/// Speed after one tick towards `desired_speed`.
+/// When the grade is stronger than the engine, `acc` is negative and the vehicle slows down
+/// even when it wants to go faster.
+pub fn speed_step(speed: f32, desired_speed: f32, acc: f32, dec: f32) -> f32 {
+    if desired_speed > speed {
+        (speed + (desired_speed - speed).min(DELTA * acc)).max(0.0)
+    } else {
+        speed - (speed - desired_speed).min(DELTA * dec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heavy_vehicles_slow_down_uphill() {
+        // a loaded truck on a 10% grade cannot keep up its speed
+        let slope = 0.1;
+        let acc = acceleration(2.0, 300.0, 18.0, 20.0, slope);
+        assert!(acc < 0.0);
+        assert!(speed_step(20.0, 22.0, acc, 4.5) < 20.0);
+
+        // a car still accelerates
+        assert!(acceleration(3.0, 90.0, 1.3, 20.0, slope) > 0.0);
+
+        // and stops later downhill
+        assert!(deceleration(6.0, -slope) < deceleration(6.0, slope));
+    }
+}
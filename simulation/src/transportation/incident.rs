@@ -0,0 +1,291 @@
+//! Vehicles break down from time to time and block their lane until a tow truck clears it.
+//! The vehicles behind wait in line, and the new routes go around the blocked lane.
+//! The tow trucks come from the government depots, no truck comes if there is none.
+
+use std::collections::BTreeMap;
+
+use geom::Vec3;
+use ordered_float::OrderedFloat;
+use prototypes::{GameInstant, GameTime, ServiceKind, TICKS_PER_MINUTE, TICKS_PER_SECOND};
+use serde::{Deserialize, Serialize};
+use slotmapd::Key;
+
+use crate::event_log::{log_event, EventCategory, EventSubject, Severity};
+use crate::map::{LaneID, Traversable, TraverseKind};
+use crate::transportation::service_fleet::{self, ServiceFleets};
+use crate::transportation::{VehicleState, WRECK_FLAG};
+use crate::world::{VehicleEnt, VehicleID};
+use crate::{AnyEntity, Simulation};
+
+/// Chance of a driving vehicle to break down during a minute
+pub const BREAKDOWN_CHANCE: f32 = 0.00002;
+/// No vehicle breaks down while there are that many incidents
+pub const MAX_INCIDENTS: usize = 3;
+/// Distance to the wreck at which the tow truck can start working
+const TOW_REACH: f32 = 15.0;
+/// Seconds the tow truck spends clearing the wreck
+pub(crate) const TOW_WORK_SECONDS: f64 = 300.0;
+/// Seconds after which the driver gets going again if no tow truck came
+const INCIDENT_TIMEOUT_SECONDS: f64 = 7200.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Incident {
+    pub lane: LaneID,
+    pub pos: Vec3,
+    pub since: GameInstant,
+    pub tow_truck: Option<VehicleID>,
+    /// When the tow truck got to the wreck
+    pub tow_arrived: Option<GameInstant>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Incidents {
+    /// By broken down vehicle
+    pub active: BTreeMap<VehicleID, Incident>,
+}
+
+/// Breaks down vehicles, and clears the incidents once the tow trucks got there
+pub(crate) fn incident_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::incident_system");
+    let tick = sim.get_tick();
+    if tick % TICKS_PER_MINUTE == 0 {
+        roll_breakdowns(sim, tick);
+    }
+    if tick % TICKS_PER_SECOND == 0 {
+        update_incidents(sim);
+    }
+}
+
+/// Whether the vehicle can break down, it has to be driving on a lane
+fn can_break_down(fleets: &ServiceFleets, id: VehicleID, v: &VehicleEnt) -> bool {
+    matches!(
+        v.vehicle.state,
+        VehicleState::Driving | VehicleState::Panicking(_)
+    ) && v.it.get_travers().map_or(false, |t| t.kind.is_lane())
+        && !fleets.is_service_vehicle(id)
+}
+
+fn roll_breakdowns(sim: &mut Simulation, tick: u64) {
+    let broken: Vec<VehicleID> = {
+        if sim.read::<Incidents>().active.len() >= MAX_INCIDENTS {
+            return;
+        }
+        let fleets = sim.read::<ServiceFleets>();
+        sim.world
+            .vehicles
+            .iter()
+            .filter(|(_, v)| v.speed.0 >= 1.0)
+            .filter(|&(id, v)| can_break_down(&fleets, id, v))
+            .filter(|(id, _)| {
+                let seed = common::hash_u64((id.data().as_ffi(), tick, "breakdown"));
+                common::rand::randu(seed as u32) < BREAKDOWN_CHANCE
+            })
+            .map(|(id, _)| id)
+            .collect()
+    };
+
+    for id in broken {
+        if sim.read::<Incidents>().active.len() >= MAX_INCIDENTS {
+            break;
+        }
+        break_down(sim, id);
+    }
+}
+
+/// Breaks down the vehicle driving closest to the position, to try out the incidents.
+/// Returns false if no vehicle can break down.
+pub fn break_down_near(sim: &mut Simulation, pos: Vec3) -> bool {
+    let closest = {
+        let fleets = sim.read::<ServiceFleets>();
+        sim.world
+            .vehicles
+            .iter()
+            .filter(|&(id, v)| can_break_down(&fleets, id, v))
+            .min_by_key(|(_, v)| OrderedFloat(v.trans.pos.distance2(pos)))
+            .map(|(id, _)| id)
+    };
+    closest.map_or(false, |id| break_down(sim, id))
+}
+
+/// Stops the vehicle on its lane, blocks the lane and calls a tow truck from the closest depot.
+/// Returns false if the vehicle is not driving on a lane.
+pub fn break_down(sim: &mut Simulation, id: VehicleID) -> bool {
+    let lane = {
+        let fleets = sim.read::<ServiceFleets>();
+        let Some(v) = sim.world.vehicles.get(id) else {
+            return false;
+        };
+        if !can_break_down(&fleets, id, v) {
+            return false;
+        }
+        let Some(&Traversable {
+            kind: TraverseKind::Lane(lane),
+            ..
+        }) = v.it.get_travers()
+        else {
+            return false;
+        };
+        lane
+    };
+
+    let now = sim.read::<GameTime>().instant();
+    // unwrap ok: just checked above
+    let v = sim.world.vehicles.get_mut(id).unwrap();
+    v.vehicle.state = VehicleState::BrokenDown(now);
+    v.vehicle.flag = WRECK_FLAG;
+    let pos = v.trans.pos;
+
+    sim.map_mut().set_lane_blocked(lane, true);
+    for (other, v) in sim.world.vehicles.iter_mut() {
+        if other != id && v.it.uses_lane(lane) {
+            v.it.reroute();
+        }
+    }
+
+    let tow_truck = service_fleet::dispatch(sim, ServiceKind::Towing, pos);
+    sim.write::<Incidents>().active.insert(
+        id,
+        Incident {
+            lane,
+            pos,
+            since: now,
+            tow_truck,
+            tow_arrived: None,
+        },
+    );
+
+    let street = {
+        let map = sim.map();
+        map.lanes()
+            .get(lane)
+            .and_then(|l| map.roads().get(l.parent))
+            .map(|r| r.name.clone())
+            .filter(|name| !name.is_empty())
+    };
+    let text = match street {
+        Some(street) => format!("A vehicle broke down on {street}, the lane is blocked"),
+        None => "A vehicle broke down, the lane is blocked".to_string(),
+    };
+    log_event(
+        sim,
+        EventCategory::Incident,
+        Severity::Warning,
+        text,
+        Some(EventSubject::Entity(AnyEntity::VehicleID(id))),
+    );
+    true
+}
+
+/// The tow truck is close to the wreck, or stopped in the line behind it
+fn tow_truck_arrived(truck: &VehicleEnt, incident: &Incident) -> bool {
+    if truck.trans.pos.distance(incident.pos) < TOW_REACH {
+        return true;
+    }
+    truck.speed.0 < 0.5
+        && matches!(truck.it.get_travers(), Some(Traversable {
+            kind: TraverseKind::Lane(lane),
+            ..
+        }) if *lane == incident.lane)
+}
+
+fn update_incidents(sim: &mut Simulation) {
+    let time = *sim.read::<GameTime>();
+    let mut arrived = vec![];
+    let mut uncalled = vec![];
+    let mut cleared = vec![];
+    {
+        let incidents = sim.read::<Incidents>();
+        for (&wreck, incident) in &incidents.active {
+            if !sim.world.vehicles.contains_key(wreck) {
+                cleared.push((wreck, false));
+                continue;
+            }
+            if let Some(at) = incident.tow_arrived {
+                if at.elapsed(&time).seconds() >= TOW_WORK_SECONDS {
+                    cleared.push((wreck, true));
+                }
+                continue;
+            }
+            if incident.since.elapsed(&time).seconds() >= INCIDENT_TIMEOUT_SECONDS {
+                cleared.push((wreck, false));
+                continue;
+            }
+            let Some(truck) = incident.tow_truck.and_then(|t| sim.world.vehicles.get(t)) else {
+                uncalled.push(wreck);
+                continue;
+            };
+            if tow_truck_arrived(truck, incident) {
+                arrived.push(wreck);
+            }
+        }
+    }
+
+    for wreck in arrived {
+        let mut incidents = sim.write::<Incidents>();
+        let Some(incident) = incidents.active.get_mut(&wreck) else {
+            continue;
+        };
+        incident.tow_arrived = Some(time.instant());
+        let truck = incident.tow_truck;
+        drop(incidents);
+        if let Some(truck) = truck {
+            service_fleet::start_working(sim, truck);
+        }
+    }
+
+    // no truck was available when it broke down, try again
+    for wreck in uncalled {
+        let Some(pos) = sim.read::<Incidents>().active.get(&wreck).map(|i| i.pos) else {
+            continue;
+        };
+        let truck = service_fleet::dispatch(sim, ServiceKind::Towing, pos);
+        if let Some(incident) = sim.write::<Incidents>().active.get_mut(&wreck) {
+            incident.tow_truck = truck;
+        }
+    }
+
+    for (wreck, towed) in cleared {
+        clear_incident(sim, wreck, towed);
+    }
+}
+
+/// Gets the vehicle going again, opens the lane if nothing else blocks it and sends the tow
+/// truck back
+pub fn clear_incident(sim: &mut Simulation, wreck: VehicleID, towed: bool) {
+    let Some(incident) = sim.write::<Incidents>().active.remove(&wreck) else {
+        return;
+    };
+
+    if let Some(v) = sim.world.vehicles.get_mut(wreck) {
+        if matches!(v.vehicle.state, VehicleState::BrokenDown(_)) {
+            v.vehicle.state = VehicleState::Driving;
+            v.vehicle.flag = 0;
+        }
+    }
+
+    let still_blocked = sim
+        .read::<Incidents>()
+        .active
+        .values()
+        .any(|i| i.lane == incident.lane);
+    if !still_blocked {
+        sim.map_mut().set_lane_blocked(incident.lane, false);
+    }
+
+    if let Some(truck) = incident.tow_truck {
+        service_fleet::send_back(sim, truck);
+    }
+
+    let text = if towed {
+        "The tow truck cleared the road"
+    } else {
+        "The broken down vehicle got going again"
+    };
+    log_event(
+        sim,
+        EventCategory::Incident,
+        Severity::Info,
+        text.to_string(),
+        Some(EventSubject::Entity(AnyEntity::VehicleID(wreck))),
+    );
+}
@@ -0,0 +1,290 @@
+//! Traffic statistics of the intersections, to find the bottlenecks of the road network.
+//! The vehicles are counted when they enter an intersection, with the time they were stopped on
+//! the lane leading to it. The counters are kept per game hour for the last day.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use prototypes::{GameTime, DELTA, TICKS_PER_HOUR, TICKS_PER_SECOND};
+
+use crate::map::{IntersectionID, LaneID, Map, TraverseKind};
+use crate::utils::resources::Resources;
+use crate::World;
+
+/// Game hours the statistics are kept for
+pub const STATS_HOURS: u64 = 24;
+/// Below this speed in m/s a vehicle is waiting
+const STOPPED_SPEED: f32 = 0.2;
+
+/// Counters of the vehicles going through an intersection, or one of its approaches
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TrafficCounts {
+    /// Vehicles that entered the intersection
+    pub entered: u32,
+    /// Seconds the vehicles that entered were stopped before
+    pub total_wait: f32,
+    /// Most vehicles stopped at once on an approach
+    pub max_queue: u32,
+}
+
+impl TrafficCounts {
+    /// Average seconds a vehicle waits before entering
+    pub fn avg_wait(&self) -> f32 {
+        if self.entered == 0 {
+            return 0.0;
+        }
+        self.total_wait / self.entered as f32
+    }
+
+    fn merge(&mut self, other: &TrafficCounts) {
+        self.entered += other.entered;
+        self.total_wait += other.total_wait;
+        self.max_queue = self.max_queue.max(other.max_queue);
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct HourStats {
+    /// Game hours since the start of the game
+    hour: u64,
+    total: TrafficCounts,
+    /// Gridlocks broken around the intersection
+    recoveries: u32,
+    approaches: BTreeMap<LaneID, TrafficCounts>,
+}
+
+/// Statistics of an intersection over the last game hours
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct IntersectionTraffic {
+    hours: VecDeque<HourStats>,
+}
+
+/// What the statistics of an intersection amount to over the last game hours
+#[derive(Debug, Default, Clone)]
+pub struct TrafficSummary {
+    pub total: TrafficCounts,
+    pub entered_per_hour: f32,
+    pub recoveries: u32,
+}
+
+impl IntersectionTraffic {
+    fn current(&mut self, hour: u64) -> &mut HourStats {
+        if self.hours.back().map_or(true, |h| h.hour != hour) {
+            self.hours.push_back(HourStats {
+                hour,
+                ..Default::default()
+            });
+        }
+        while self
+            .hours
+            .front()
+            .is_some_and(|h| h.hour + STATS_HOURS <= hour)
+        {
+            self.hours.pop_front();
+        }
+        self.hours.back_mut().unwrap()
+    }
+
+    fn window(&self, hour: u64) -> impl Iterator<Item = &HourStats> {
+        self.hours
+            .iter()
+            .filter(move |h| h.hour + STATS_HOURS > hour)
+    }
+
+    pub fn summary(&self, hour: u64) -> TrafficSummary {
+        let mut summary = TrafficSummary::default();
+        let mut first = hour;
+        for h in self.window(hour) {
+            summary.total.merge(&h.total);
+            summary.recoveries += h.recoveries;
+            first = first.min(h.hour);
+        }
+        summary.entered_per_hour = summary.total.entered as f32 / (hour - first + 1) as f32;
+        summary
+    }
+
+    /// Counters of each lane leading to the intersection
+    pub fn approaches(&self, hour: u64) -> BTreeMap<LaneID, TrafficCounts> {
+        let mut approaches = BTreeMap::<LaneID, TrafficCounts>::new();
+        for h in self.window(hour) {
+            for (&lane, counts) in &h.approaches {
+                approaches.entry(lane).or_default().merge(counts);
+            }
+        }
+        approaches
+    }
+}
+
+/// How the worst intersections are ranked
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum IntersectionSort {
+    #[default]
+    AvgWait,
+    Entered,
+    MaxQueue,
+    Recoveries,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct IntersectionStats {
+    intersections: BTreeMap<IntersectionID, IntersectionTraffic>,
+}
+
+impl IntersectionStats {
+    /// Game hours since the start of the game
+    pub fn hour(time: &GameTime) -> u64 {
+        time.tick.0 / TICKS_PER_HOUR
+    }
+
+    pub fn get(&self, id: IntersectionID) -> Option<&IntersectionTraffic> {
+        self.intersections.get(&id)
+    }
+
+    pub fn record_entry(&mut self, id: IntersectionID, from: LaneID, waited: f32, hour: u64) {
+        let h = self.intersections.entry(id).or_default().current(hour);
+        h.total.entered += 1;
+        h.total.total_wait += waited;
+        let approach = h.approaches.entry(from).or_default();
+        approach.entered += 1;
+        approach.total_wait += waited;
+    }
+
+    pub fn record_queue(&mut self, id: IntersectionID, on: LaneID, queue: u32, hour: u64) {
+        let h = self.intersections.entry(id).or_default().current(hour);
+        h.total.max_queue = h.total.max_queue.max(queue);
+        let approach = h.approaches.entry(on).or_default();
+        approach.max_queue = approach.max_queue.max(queue);
+    }
+
+    pub fn record_recovery(&mut self, id: IntersectionID, hour: u64) {
+        self.intersections
+            .entry(id)
+            .or_default()
+            .current(hour)
+            .recoveries += 1;
+    }
+
+    /// The `n` intersections with the most traffic trouble, worst first
+    pub fn worst(
+        &self,
+        hour: u64,
+        sort: IntersectionSort,
+        n: usize,
+    ) -> Vec<(IntersectionID, TrafficSummary)> {
+        let mut v: Vec<_> = self
+            .intersections
+            .iter()
+            .map(|(&id, t)| (id, t.summary(hour)))
+            .filter(|(_, s)| s.total.entered > 0 || s.recoveries > 0)
+            .collect();
+        let key = |s: &TrafficSummary| match sort {
+            IntersectionSort::AvgWait => s.total.avg_wait(),
+            IntersectionSort::Entered => s.entered_per_hour,
+            IntersectionSort::MaxQueue => s.total.max_queue as f32,
+            IntersectionSort::Recoveries => s.recoveries as f32,
+        };
+        v.sort_by(|(a_id, a), (b_id, b)| key(b).total_cmp(&key(a)).then(a_id.cmp(b_id)));
+        v.truncate(n);
+        v
+    }
+
+    /// Forgets the removed intersections
+    fn cleanup(&mut self, map: &Map) {
+        self.intersections
+            .retain(|&id, _| map.intersections().contains_key(id));
+    }
+}
+
+/// Counts the vehicles entering the intersections, and every second the queues in front of them
+pub fn intersection_stats_system(world: &mut World, resources: &mut Resources) {
+    profiling::scope!("transportation::intersection_stats_system");
+    let time = resources.read::<GameTime>();
+    let map = resources.read::<Map>();
+    let mut stats = resources.write::<IntersectionStats>();
+    let hour = IntersectionStats::hour(&time);
+    let sample_queues = time.tick.0 % TICKS_PER_SECOND == 0;
+    let mut queues = BTreeMap::<LaneID, u32>::new();
+
+    for v in world.vehicles.values_mut() {
+        if v.collider.is_none() {
+            continue;
+        }
+        let stopped = v.speed.0.abs() < STOPPED_SPEED;
+        match v.it.get_travers().map(|t| t.kind) {
+            Some(TraverseKind::Lane(lane)) => {
+                match v.vehicle.approach {
+                    Some((l, ref mut waited)) if l == lane => {
+                        if stopped {
+                            *waited += DELTA;
+                        }
+                    }
+                    _ => v.vehicle.approach = Some((lane, 0.0)),
+                }
+                if sample_queues && stopped {
+                    *queues.entry(lane).or_default() += 1;
+                }
+            }
+            Some(TraverseKind::Turn(turn)) => {
+                if let Some((lane, waited)) = v.vehicle.approach.take() {
+                    stats.record_entry(turn.parent, lane, waited, hour);
+                }
+            }
+            None => {}
+        }
+    }
+
+    for (lane, queue) in queues {
+        let Some(l) = map.lanes().get(lane) else {
+            continue;
+        };
+        stats.record_queue(l.dst, lane, queue, hour);
+    }
+
+    if time.tick.0 % TICKS_PER_HOUR == 0 {
+        stats.cleanup(&map);
+    }
+}
+
+/// The intersection a vehicle on that traversable is at or heading to
+pub fn intersection_of(map: &Map, kind: TraverseKind) -> Option<IntersectionID> {
+    match kind {
+        TraverseKind::Lane(l) => map.lanes().get(l).map(|l| l.dst),
+        TraverseKind::Turn(t) => Some(t.parent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slotmapd::KeyData;
+
+    #[test]
+    fn old_hours_leave_the_window() {
+        let inter = IntersectionID::from(KeyData::from_ffi(1));
+        let lane = LaneID::from(KeyData::from_ffi(2));
+        let mut stats = IntersectionStats::default();
+
+        stats.record_entry(inter, lane, 10.0, 0);
+        stats.record_entry(inter, lane, 0.0, 1);
+        stats.record_queue(inter, lane, 4, 1);
+        stats.record_recovery(inter, 1);
+
+        let s = stats.get(inter).unwrap().summary(1);
+        assert_eq!(s.total.entered, 2);
+        assert_eq!(s.total.avg_wait(), 5.0);
+        assert_eq!(s.entered_per_hour, 1.0);
+        assert_eq!(s.total.max_queue, 4);
+        assert_eq!(s.recoveries, 1);
+        assert_eq!(stats.get(inter).unwrap().approaches(1)[&lane].entered, 2);
+
+        let s = stats.get(inter).unwrap().summary(STATS_HOURS);
+        assert_eq!(s.total.entered, 1);
+        assert_eq!(s.total.avg_wait(), 0.0);
+
+        assert_eq!(stats.worst(1, IntersectionSort::AvgWait, 10).len(), 1);
+        assert!(stats
+            .worst(STATS_HOURS * 3, IntersectionSort::AvgWait, 10)
+            .is_empty());
+    }
+}
@@ -13,11 +13,18 @@ use crate::utils::resources::Resources;
 use crate::world::VehicleID;
 use crate::{Simulation, World};
 
+pub mod deadlock;
+pub mod dynamics;
+pub mod incident;
+pub mod intersection_stats;
 pub mod pedestrian;
 pub mod road;
+pub mod service_call;
+pub mod service_fleet;
 pub mod testing_vehicles;
 pub mod train;
 mod vehicle;
+pub mod waterway;
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Location {
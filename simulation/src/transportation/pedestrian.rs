@@ -1,21 +1,54 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::map::{Map, Traversable, TraverseKind, TurnID};
 use crate::map_dynamic::Itinerary;
 use crate::transportation::{
     Speed, TransportGrid, TransportState, TransportationGroup, Transporter,
 };
 use crate::utils::rand_provider::RandProvider;
 use crate::utils::resources::Resources;
+use crate::world::HumanID;
 use crate::World;
 use egui_inspect::Inspect;
-use geom::{angle_lerpxy, Color, Transform, Vec3};
-use prototypes::DELTA;
+use geom::{angle_lerpxy, Color, Transform, Vec2, Vec3};
+use prototypes::{GameTime, DELTA};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Inspect)]
 pub struct Pedestrian {
     pub walking_speed: f32,
     pub walk_anim: f32,
+    #[serde(default)]
+    #[inspect(skip)]
+    pub crossing: Option<CrossingState>,
+}
+
+/// Where a pedestrian is at with the crosswalk ahead
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrossingState {
+    /// Waits in the queue for the walk signal
+    Waiting(TurnID),
+    /// Got the walk signal, goes over without stopping
+    Going(TurnID),
+}
+
+/// Pedestrians waiting at each crosswalk, in arrival order
+#[derive(Default, Serialize, Deserialize)]
+pub struct CrossingQueues(BTreeMap<TurnID, Vec<HumanID>>);
+
+impl CrossingQueues {
+    pub fn waiting(&self, turn: TurnID) -> &[HumanID] {
+        self.0.get(&turn).map_or(&[], |q| q.as_slice())
+    }
 }
 
+/// Distance to the crosswalk from which pedestrians stop and wait for the walk signal
+const CROSSING_WAIT_DIST: f32 = 2.5;
+/// Spacing of the pedestrians waiting together at a crosswalk
+const CROSSING_QUEUE_SPACING: f32 = 0.6;
+/// Pedestrians waiting side by side at a crosswalk
+const CROSSING_QUEUE_ROW: usize = 3;
+
 const PED_SIZE: f32 = 0.5;
 
 pub fn put_pedestrian_in_transport_grid(
@@ -37,6 +70,7 @@ impl Pedestrian {
         Self {
             walking_speed: (0.8 + r.next_f32() * 0.8),
             walk_anim: 0.0,
+            crossing: None,
         }
     }
 }
@@ -65,12 +99,151 @@ pub fn random_pedestrian_shirt_color(r: &mut RandProvider) -> Color {
     unreachable!();
 }
 
-pub fn pedestrian_decision_system(world: &mut World, _resources: &mut Resources) {
+pub fn pedestrian_decision_system(world: &mut World, resources: &mut Resources) {
     profiling::scope!("transportation::pedestrian_decision_system");
-    world.humans
-        .values_mut()
-        //.par_bridge()
-        .for_each(|human| pedestrian_decision(&mut human.it, &mut human.trans, &mut human.speed, &mut human.pedestrian))
+    let map = &*resources.read::<Map>();
+    let seconds = resources.read::<GameTime>().seconds;
+    let queues = &mut *resources.write::<CrossingQueues>();
+
+    // forget the pedestrians that went somewhere else or are gone
+    queues.0.retain(|&turn, queue| {
+        queue.retain(|&id| {
+            world.humans.get(id).map_or(false, |h| {
+                h.pedestrian.crossing == Some(CrossingState::Waiting(turn))
+            })
+        });
+        !queue.is_empty()
+    });
+
+    // one pedestrian starts crossing per tick at each crosswalk, in arrival order
+    let mut started = BTreeSet::new();
+
+    world.humans.iter_mut().for_each(|(id, human)| {
+        if crossing_wait(
+            id,
+            map,
+            seconds,
+            queues,
+            &mut started,
+            &human.it,
+            &mut human.trans,
+            &mut human.pedestrian,
+        ) {
+            human.speed.0 = 0.0;
+            return;
+        }
+        pedestrian_decision(
+            &mut human.it,
+            &mut human.trans,
+            &mut human.speed,
+            &mut human.pedestrian,
+        )
+    })
+}
+
+/// The crosswalk the pedestrian is about to go over, and the direction it goes in
+fn crosswalk_ahead(it: &Itinerary, map: &Map, pos: Vec3, reach: f32) -> Option<(TurnID, Vec2)> {
+    let Some(Traversable {
+        kind: TraverseKind::Lane(_),
+        ..
+    }) = it.get_travers()
+    else {
+        return None;
+    };
+    let Some(Traversable {
+        kind: TraverseKind::Turn(id),
+        ..
+    }) = it.next_travers()
+    else {
+        return None;
+    };
+    let start = it.get_point()?;
+    if it.remaining_points() != 1 || !start.is_close(pos, reach) {
+        return None;
+    }
+    let turn = map.intersections().get(id.parent)?.find_turn(*id)?;
+    if !turn.kind.is_crosswalk() {
+        return None;
+    }
+    let (a, b) = (turn.points.first(), turn.points.last());
+    let end = if a.distance2(start) < b.distance2(start) {
+        b
+    } else {
+        a
+    };
+    Some((*id, (end - start).xy().try_normalize()?))
+}
+
+/// Queues the pedestrian at the crosswalk ahead until it can walk.
+/// Returns true while the pedestrian waits.
+#[allow(clippy::too_many_arguments)]
+fn crossing_wait(
+    id: HumanID,
+    map: &Map,
+    seconds: u32,
+    queues: &mut CrossingQueues,
+    started: &mut BTreeSet<TurnID>,
+    it: &Itinerary,
+    trans: &mut Transform,
+    pedestrian: &mut Pedestrian,
+) -> bool {
+    // the crowd can stand further back than where they stopped
+    let reach = match pedestrian.crossing {
+        Some(CrossingState::Waiting(_)) => f32::INFINITY,
+        _ => CROSSING_WAIT_DIST,
+    };
+    let Some((turn, dir)) = crosswalk_ahead(it, map, trans.pos, reach) else {
+        pedestrian.crossing = None;
+        return false;
+    };
+    if pedestrian.crossing == Some(CrossingState::Going(turn)) {
+        return false;
+    }
+
+    let walk = turn
+        .crossing_control(map.lanes(), map.roads())
+        .crossing_phase(seconds)
+        .is_walk();
+    let queue = queues.0.entry(turn).or_default();
+
+    if pedestrian.crossing != Some(CrossingState::Waiting(turn)) {
+        if walk && queue.is_empty() {
+            pedestrian.crossing = Some(CrossingState::Going(turn));
+            return false;
+        }
+        pedestrian.crossing = Some(CrossingState::Waiting(turn));
+        queue.push(id);
+    }
+
+    if walk && queue.first() == Some(&id) && started.insert(turn) {
+        queue.remove(0);
+        pedestrian.crossing = Some(CrossingState::Going(turn));
+        return false;
+    }
+
+    // gather in a small crowd before the crosswalk
+    let Some(place) = queue.iter().position(|&x| x == id) else {
+        return false;
+    };
+    let Some(target) = it.get_point() else {
+        return false;
+    };
+    let row = (place / CROSSING_QUEUE_ROW) as f32;
+    let side = (place % CROSSING_QUEUE_ROW) as f32 - (CROSSING_QUEUE_ROW / 2) as f32;
+    let spot = target - (dir * CROSSING_QUEUE_SPACING * (row + 1.0)).z(0.0)
+        + (dir.perpendicular() * CROSSING_QUEUE_SPACING * side).z(0.0);
+
+    let step = pedestrian.walking_speed * DELTA;
+    let delta = spot - trans.pos;
+    if delta.mag() > step {
+        trans.pos += delta.normalize_to(step);
+        pedestrian.walk_anim += 7.0 * step / pedestrian.walking_speed;
+        pedestrian.walk_anim %= 2.0 * std::f32::consts::PI;
+    } else {
+        trans.pos = spot;
+    }
+    trans.dir = angle_lerpxy(trans.dir, dir.z(0.0), DELTA);
+    true
 }
 
 pub fn pedestrian_decision(
@@ -1,13 +1,14 @@
 use crate::map::{Map, TrafficBehavior, Traversable, TraverseKind};
 use crate::map_dynamic::{Itinerary, OBJECTIVE_OK_DIST};
+use crate::transportation::{dynamics, Vehicle, VehicleState, TIME_TO_PARK, WRECK_FLAG};
 use crate::transportation::{
     Speed, TransportGrid, TransportState, TransportationGroup, Transporter,
 };
-use crate::transportation::{Vehicle, VehicleState, TIME_TO_PARK};
 use crate::utils::resources::Resources;
 use crate::world::{VehicleEnt, VehicleID};
 use crate::ParCommandBuffer;
 use crate::World;
+use flat_spatial::grid::GridHandle;
 use geom::{angle_lerpxy, Ray, Transform, Vec2, Vec3};
 use prototypes::{GameTime, DELTA};
 use slotmapd::Key;
@@ -51,16 +52,23 @@ pub fn vehicle_decision(
     let (_, self_obj) = cow.get(collider.0).expect("Handle not in transport grid");
 
     let mut desired_speed = 0.0;
-    let mut desired_dir = Vec3::ZERO;
+    let mut desired_dir = trans.dir;
     if matches!(
         vehicle.state,
         VehicleState::Driving | VehicleState::Panicking(_)
     ) {
-        let danger_length =
-            (self_obj.speed.powi(2) / (2.0 * vehicle.kind.deceleration())).min(100.0);
+        let danger_length = vehicle
+            .kind
+            .braking_distance(self_obj.speed, trans.dir.z)
+            .min(100.0);
         let neighbors = cow.query_around(trans.pos.xy(), 12.0 + danger_length);
-        let objs =
-            neighbors.map(|(id, pos)| (pos, cow.get(id).expect("Handle not in transport grid").1));
+        let objs = neighbors.map(|(id, pos)| {
+            (
+                id,
+                pos,
+                cow.get(id).expect("Handle not in transport grid").1,
+            )
+        });
 
         let (s, d) = calc_decision(me, vehicle, map, time, trans, self_obj, it, objs);
         desired_speed = s;
@@ -163,9 +171,14 @@ fn physics(
 
     let speed = obj.speed;
     let kind = vehicle.kind;
+    let slope = trans.dir.z;
 
-    let speed = speed
-        + (desired_speed - speed).clamp(-DELTA * kind.deceleration(), DELTA * kind.acceleration());
+    let speed = dynamics::speed_step(
+        speed,
+        desired_speed,
+        vehicle.acceleration(speed, slope),
+        vehicle.deceleration(slope),
+    );
 
     let max_ang_vel = (speed.abs() / kind.min_turning_radius()).clamp(0.0, 3.0);
 
@@ -191,7 +204,7 @@ pub fn calc_decision<'a>(
     trans: &Transform,
     self_obj: &TransportState,
     it: &Itinerary,
-    neighs: impl Iterator<Item = (Vec2, &'a TransportState)>,
+    neighs: impl Iterator<Item = (GridHandle, Vec2, &'a TransportState)>,
 ) -> (f32, Vec3) {
     let default_return = (0.0, trans.dir);
     if vehicle.wait_time > 0.0 {
@@ -201,12 +214,12 @@ pub fn calc_decision<'a>(
     let objective: Vec3 = unwrap_or!(it.get_point(), return default_return);
 
     let speed = self_obj.speed;
-    let time_to_stop = speed / vehicle.kind.deceleration();
-    let stop_dist = time_to_stop * speed * 0.5;
+    let stop_dist = vehicle.braking_distance(speed, trans.dir.z);
 
     let cutoff = (0.8 + stop_dist).min(1.5);
 
-    let (front_dist, flag) = calc_front_dist(vehicle, trans, self_obj, it, neighs, cutoff);
+    let (front_dist, flag, blocker) = calc_front_dist(vehicle, trans, self_obj, it, neighs, cutoff);
+    vehicle.blocked_by = None;
 
     let position = trans.pos;
     let dir_to_pos = unwrap_or!(
@@ -218,6 +231,10 @@ pub fn calc_decision<'a>(
         if since.elapsed(time).seconds() > 200.0 {
             vehicle.state = VehicleState::Driving;
         }
+        // panicking gets out of gridlocks, not through wrecks
+        if flag == WRECK_FLAG && front_dist < 0.8 + stop_dist {
+            return (0.0, dir_to_pos);
+        }
     } else if speed.abs() < 0.2 && front_dist < 1.5 {
         let me_u64: u64 = me.data().as_ffi();
         if me_u64 == flag {
@@ -230,6 +247,7 @@ pub fn calc_decision<'a>(
             flag
         };
         vehicle.wait_time = (position.x * 1000.0).fract().abs() * 0.5;
+        vehicle.blocked_by = blocker;
         return default_return;
     } else {
         // Stop at 80 cm of object in front
@@ -254,19 +272,23 @@ pub fn calc_decision<'a>(
     }) = it.get_travers()
     {
         if let Some(l) = map.lanes().get(*l_id) {
-            speed = l.speed_limit;
+            speed = map.effective_speed_limit(l);
 
             let light = l.control_point();
 
             match l.control.get_behavior(time.seconds) {
-                TrafficBehavior::RED | TrafficBehavior::ORANGE => {
-                    if light.is_close(
-                        position,
-                        OBJECTIVE_OK_DIST * 1.05
-                            + 2.0
-                            + stop_dist
-                            + (vehicle.kind.width() * 0.5 - OBJECTIVE_OK_DIST).max(0.0),
-                    ) {
+                behavior @ (TrafficBehavior::RED | TrafficBehavior::ORANGE) => {
+                    let margin = OBJECTIVE_OK_DIST * 1.05
+                        + 2.0
+                        + (vehicle.length() * 0.5 - OBJECTIVE_OK_DIST).max(0.0);
+                    let dist_to_light = light.distance(position) - margin;
+
+                    // on yellow, whoever cannot stop before the line anymore goes through
+                    let committed = matches!(behavior, TrafficBehavior::ORANGE)
+                        && self_obj.speed > 2.0
+                        && dist_to_light < stop_dist * 0.8;
+
+                    if dist_to_light < stop_dist && !committed {
                         return (0.0, dir_to_pos);
                     }
                 }
@@ -291,7 +313,8 @@ pub fn calc_decision<'a>(
     }
 
     (
-        vehicle.kind.speed_factor() * vehicle.max_speed_multiplier * speed,
+        (vehicle.kind.speed_factor() * vehicle.max_speed_multiplier * speed)
+            .min(vehicle.max_speed()),
         dir_to_pos,
     )
 }
@@ -299,14 +322,15 @@ pub fn calc_decision<'a>(
 /// Calculates the distance to the closest problematic object in front of the car.
 /// It can be another car or a pedestrian, or it can be a potential collision point from a
 /// car coming perpendicularly.
+/// Also returns the gridlock flag and the handle of that object.
 fn calc_front_dist<'a>(
     vehicle: &mut Vehicle,
     trans: &Transform,
     self_obj: &TransportState,
     it: &Itinerary,
-    neighs: impl Iterator<Item = (Vec2, &'a TransportState)>,
+    neighs: impl Iterator<Item = (GridHandle, Vec2, &'a TransportState)>,
     cutoff: f32,
-) -> (f32, u64) {
+) -> (f32, u64, Option<GridHandle>) {
     let position = trans.pos;
     let direction = trans.dir;
     let pos2 = position.xy();
@@ -315,7 +339,7 @@ fn calc_front_dist<'a>(
     let mut min_front_dist: f32 = 50.0;
 
     let my_ray = Ray {
-        from: position.xy() - direction.xy() * vehicle.kind.width() * 0.5,
+        from: position.xy() - direction.xy() * vehicle.length() * 0.5,
         dir: direction.xy(),
     };
 
@@ -324,8 +348,9 @@ fn calc_front_dist<'a>(
 
     let on_lane = it.get_travers().map_or(false, |t| t.kind.is_lane());
     let mut flag = 0;
+    let mut closest = None;
     // Collision avoidance
-    for (his_pos, nei_physics_obj) in neighs {
+    for (handle, his_pos, nei_physics_obj) in neighs {
         if (nei_physics_obj.height - position.z).abs() > 5.0 {
             continue;
         }
@@ -363,9 +388,10 @@ fn calc_front_dist<'a>(
             if dist_to_obj < min_front_dist {
                 min_front_dist = dist_to_obj;
                 flag = nei_physics_obj.flag;
+                closest = Some(handle);
             }
             if min_front_dist < cutoff {
-                return (min_front_dist, flag);
+                return (min_front_dist, flag, closest);
             }
             continue;
         }
@@ -401,7 +427,8 @@ fn calc_front_dist<'a>(
         if final_dist < min_front_dist {
             min_front_dist = final_dist;
             flag = nei_physics_obj.flag;
+            closest = Some(handle);
         }
     }
-    (min_front_dist, flag)
+    (min_front_dist, flag, closest)
 }
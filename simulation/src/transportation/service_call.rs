@@ -0,0 +1,379 @@
+//! Calls of the buildings for the city services: the fires for the fire engines and the full
+//! bins for the garbage trucks. A vehicle of the service comes from the closest depot with one
+//! available, works at the building for a while and goes back.
+//! A fire no engine puts out burns the building down, and the bins nobody empties overflow.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use slotmapd::Key;
+
+use prototypes::{
+    GameInstant, GameTime, ServiceKind, HOURS_PER_DAY, MINUTES_PER_HOUR, TICKS_PER_MINUTE,
+    TICKS_PER_SECOND,
+};
+
+use crate::event_log::{log_event, EventCategory, EventSubject, Severity};
+use crate::map::BuildingID;
+use crate::map_dynamic::BuildingInfos;
+use crate::rules::GameRules;
+use crate::transportation::incident::TOW_WORK_SECONDS;
+use crate::transportation::service_fleet;
+use crate::world::VehicleID;
+use crate::Simulation;
+
+/// Chance of a building to catch fire during a minute
+pub const FIRE_CHANCE: f32 = 0.000002;
+/// No building catches fire while there are that many fires
+pub const MAX_FIRES: usize = 2;
+/// Seconds after which a fire no engine got to burns the building down
+const BURN_DOWN_SECONDS: f64 = 3600.0;
+/// Seconds the fire engine takes to put the fire out
+const FIRE_WORK_SECONDS: f64 = 600.0;
+/// Seconds the garbage truck takes to empty the bins
+const GARBAGE_WORK_SECONDS: f64 = 60.0;
+/// Bins an occupied building fills in a day, the truck is called once one is full
+const GARBAGE_PER_DAY: f32 = 0.5;
+/// The bins overflow past this, nothing grows next to the building until they are emptied
+pub const GARBAGE_OVERFLOW: f32 = 2.0;
+/// Distance to the door at which the vehicle can start working
+const SERVICE_REACH: f32 = 20.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceCall {
+    pub since: GameInstant,
+    pub vehicle: Option<VehicleID>,
+    /// When the vehicle got to the building
+    pub arrived: Option<GameInstant>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ServiceCalls {
+    pub active: BTreeMap<(BuildingID, ServiceKind), ServiceCall>,
+    /// Bins filled since the last pickup, by building
+    pub garbage: BTreeMap<BuildingID, f32>,
+}
+
+impl ServiceCalls {
+    pub fn is_burning(&self, building: BuildingID) -> bool {
+        self.active.contains_key(&(building, ServiceKind::Fire))
+    }
+
+    pub fn n_fires(&self) -> usize {
+        self.active
+            .keys()
+            .filter(|(_, service)| *service == ServiceKind::Fire)
+            .count()
+    }
+
+    pub fn garbage(&self, building: BuildingID) -> f32 {
+        self.garbage.get(&building).copied().unwrap_or(0.0)
+    }
+
+    /// The buildings whose bins overflow
+    pub fn overflowing(&self) -> impl Iterator<Item = BuildingID> + '_ {
+        self.garbage
+            .iter()
+            .filter(|(_, &g)| g >= GARBAGE_OVERFLOW)
+            .map(|(&b, _)| b)
+    }
+}
+
+fn work_seconds(service: ServiceKind) -> f64 {
+    match service {
+        ServiceKind::Towing => TOW_WORK_SECONDS,
+        ServiceKind::Fire => FIRE_WORK_SECONDS,
+        ServiceKind::Garbage => GARBAGE_WORK_SECONDS,
+    }
+}
+
+/// Starts the fires and fills the bins every minute, follows the calls every second.
+/// The fires only start when the rules allow disasters.
+pub(crate) fn service_call_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::service_call_system");
+    let tick = sim.get_tick();
+    if tick % TICKS_PER_MINUTE == 0 {
+        if sim.read::<GameRules>().disasters {
+            roll_fires(sim, tick);
+        }
+        fill_bins(sim);
+    }
+    if tick % TICKS_PER_SECOND == 0 {
+        update_calls(sim);
+    }
+}
+
+fn roll_fires(sim: &mut Simulation, tick: u64) {
+    let burning: Vec<BuildingID> = {
+        if sim.read::<ServiceCalls>().n_fires() >= MAX_FIRES {
+            return;
+        }
+        sim.map()
+            .buildings()
+            .keys()
+            .filter(|id| {
+                let seed = common::hash_u64((id.data().as_ffi(), tick, "fire"));
+                common::rand::randu(seed as u32) < FIRE_CHANCE
+            })
+            .collect()
+    };
+
+    for building in burning {
+        if sim.read::<ServiceCalls>().n_fires() >= MAX_FIRES {
+            break;
+        }
+        start_fire(sim, building);
+    }
+}
+
+/// Sets the building on fire and calls a fire engine.
+/// Returns false if it does not exist or is already burning.
+pub fn start_fire(sim: &mut Simulation, building: BuildingID) -> bool {
+    if !sim.map().buildings().contains_key(building)
+        || sim.read::<ServiceCalls>().is_burning(building)
+    {
+        return false;
+    }
+    call(sim, building, ServiceKind::Fire);
+    log_event(
+        sim,
+        EventCategory::Incident,
+        Severity::Warning,
+        "A building caught fire".to_string(),
+        Some(EventSubject::Building(building)),
+    );
+    true
+}
+
+/// The occupied buildings fill their bins, and call a garbage truck once one is full
+fn fill_bins(sim: &mut Simulation) {
+    let per_minute = GARBAGE_PER_DAY / (HOURS_PER_DAY * MINUTES_PER_HOUR) as f32;
+    let mut full = vec![];
+    let mut overflowing = vec![];
+    {
+        let map = sim.map();
+        let binfos = sim.read::<BuildingInfos>();
+        let mut calls = sim.write::<ServiceCalls>();
+        let calls = &mut *calls;
+
+        calls
+            .garbage
+            .retain(|&b, _| map.buildings().contains_key(b));
+        for id in map.buildings().keys() {
+            if binfos.owner(id).is_none() {
+                continue;
+            }
+            let garbage = calls.garbage.entry(id).or_default();
+            let before = *garbage;
+            *garbage += per_minute;
+            if *garbage >= 1.0 && !calls.active.contains_key(&(id, ServiceKind::Garbage)) {
+                full.push(id);
+            }
+            if before < GARBAGE_OVERFLOW && *garbage >= GARBAGE_OVERFLOW {
+                overflowing.push(id);
+            }
+        }
+    }
+
+    for id in full {
+        call(sim, id, ServiceKind::Garbage);
+    }
+    for id in overflowing {
+        log_event(
+            sim,
+            EventCategory::Incident,
+            Severity::Warning,
+            "Garbage piles up, no truck came to empty the bins".to_string(),
+            Some(EventSubject::Building(id)),
+        );
+    }
+}
+
+/// Sends a vehicle of the service from the closest depot, the call waits for one if none is
+/// available
+fn call(sim: &mut Simulation, building: BuildingID, service: ServiceKind) {
+    let Some(door) = sim.map().buildings().get(building).map(|b| b.door_pos) else {
+        return;
+    };
+    let since = sim.read::<GameTime>().instant();
+    let vehicle = service_fleet::dispatch(sim, service, door);
+    sim.write::<ServiceCalls>().active.insert(
+        (building, service),
+        ServiceCall {
+            since,
+            vehicle,
+            arrived: None,
+        },
+    );
+}
+
+fn update_calls(sim: &mut Simulation) {
+    let time = *sim.read::<GameTime>();
+    let mut arrived = vec![];
+    let mut uncalled = vec![];
+    let mut done = vec![];
+    let mut burnt = vec![];
+    {
+        let map = sim.map();
+        let calls = sim.read::<ServiceCalls>();
+        for (&(building, service), call) in &calls.active {
+            let Some(door) = map.buildings().get(building).map(|b| b.door_pos) else {
+                done.push((building, service));
+                continue;
+            };
+            if let Some(at) = call.arrived {
+                if at.elapsed(&time).seconds() >= work_seconds(service) {
+                    done.push((building, service));
+                }
+                continue;
+            }
+            if service == ServiceKind::Fire
+                && call.since.elapsed(&time).seconds() >= BURN_DOWN_SECONDS
+            {
+                burnt.push(building);
+                continue;
+            }
+            let Some(v) = call.vehicle.and_then(|v| sim.world.vehicles.get(v)) else {
+                uncalled.push((building, service, door));
+                continue;
+            };
+            if v.trans.pos.distance(door) < SERVICE_REACH || v.it.has_ended(0.0) {
+                arrived.push((building, service));
+            }
+        }
+    }
+
+    for key in arrived {
+        let mut calls = sim.write::<ServiceCalls>();
+        let Some(call) = calls.active.get_mut(&key) else {
+            continue;
+        };
+        call.arrived = Some(time.instant());
+        let vehicle = call.vehicle;
+        drop(calls);
+        if let Some(vehicle) = vehicle {
+            service_fleet::start_working(sim, vehicle);
+        }
+    }
+
+    // no vehicle was available when the building called, try again
+    for (building, service, door) in uncalled {
+        let vehicle = service_fleet::dispatch(sim, service, door);
+        if let Some(call) = sim
+            .write::<ServiceCalls>()
+            .active
+            .get_mut(&(building, service))
+        {
+            call.vehicle = vehicle;
+        }
+    }
+
+    for (building, service) in done {
+        end_call(sim, building, service);
+    }
+
+    for building in burnt {
+        end_call(sim, building, ServiceKind::Fire);
+        sim.map_mut().remove_building(building);
+        log_event(
+            sim,
+            EventCategory::Incident,
+            Severity::Warning,
+            "A building burnt down, no fire engine came".to_string(),
+            None,
+        );
+    }
+}
+
+/// Removes the call, sends the vehicle back and applies what it did
+fn end_call(sim: &mut Simulation, building: BuildingID, service: ServiceKind) {
+    let Some(call) = sim
+        .write::<ServiceCalls>()
+        .active
+        .remove(&(building, service))
+    else {
+        return;
+    };
+    if let Some(vehicle) = call.vehicle {
+        service_fleet::send_back(sim, vehicle);
+    }
+    if call.arrived.is_none() {
+        return;
+    }
+
+    match service {
+        ServiceKind::Fire => log_event(
+            sim,
+            EventCategory::Incident,
+            Severity::Info,
+            "The fire engine put the fire out".to_string(),
+            Some(EventSubject::Building(building)),
+        ),
+        ServiceKind::Garbage => {
+            sim.write::<ServiceCalls>().garbage.remove(&building);
+        }
+        ServiceKind::Towing => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3};
+    use prototypes::Tick;
+
+    use super::*;
+    use crate::tests::TestCtx;
+
+    fn elapse(test: &TestCtx, seconds: f64) {
+        let tick = test.g.read::<GameTime>().tick.0;
+        let ticks = (seconds * TICKS_PER_SECOND as f64) as u64;
+        *test.g.write::<GameTime>() = GameTime::new(Tick(tick + ticks));
+    }
+
+    #[test]
+    fn fire_without_engine_burns_the_building_down() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(20.0, -20.0));
+
+        assert!(start_fire(&mut test.g, house));
+        assert!(!start_fire(&mut test.g, house));
+        let call = &test.g.read::<ServiceCalls>().active[&(house, ServiceKind::Fire)];
+        assert_eq!(call.vehicle, None);
+
+        update_calls(&mut test.g);
+        assert!(test.g.map().buildings().contains_key(house));
+
+        elapse(&test, BURN_DOWN_SECONDS);
+        update_calls(&mut test.g);
+        assert!(!test.g.map().buildings().contains_key(house));
+        assert!(test.g.read::<ServiceCalls>().active.is_empty());
+    }
+
+    #[test]
+    fn emptied_bins_start_over() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(100.0, 0.0, 0.0)]);
+        let house = test.build_house_near(vec2(20.0, -20.0));
+
+        test.g.write::<ServiceCalls>().garbage.insert(house, 1.0);
+        call(&mut test.g, house, ServiceKind::Garbage);
+
+        // nobody came, the bins are still full
+        end_call(&mut test.g, house, ServiceKind::Garbage);
+        assert_eq!(test.g.read::<ServiceCalls>().garbage(house), 1.0);
+
+        call(&mut test.g, house, ServiceKind::Garbage);
+        let now = test.g.read::<GameTime>().instant();
+        test.g
+            .write::<ServiceCalls>()
+            .active
+            .get_mut(&(house, ServiceKind::Garbage))
+            .unwrap()
+            .arrived = Some(now);
+        elapse(&test, GARBAGE_WORK_SECONDS);
+        update_calls(&mut test.g);
+        assert_eq!(test.g.read::<ServiceCalls>().garbage(house), 0.0);
+        assert!(test.g.read::<ServiceCalls>().active.is_empty());
+    }
+}
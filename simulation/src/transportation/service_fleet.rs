@@ -0,0 +1,528 @@
+//! Depots of the city services and their fleet of vehicles.
+//! The vehicles wait in their depot and only exist in the world while they are out on a call.
+//! The government buys them and pays their upkeep every day.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fmt::{Display, Formatter};
+
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+
+use geom::{Color, Transform, Vec3};
+use prototypes::{GameTime, Money, ServiceDepotID, ServiceKind, TICKS_PER_SECOND};
+
+use crate::economy::Government;
+use crate::map::{BuildingID, BuildingKind, IntersectionID, LaneKind, Map, PathKind};
+use crate::map_dynamic::{Itinerary, PathJobs};
+use crate::transportation::{make_vehicle_entity, Vehicle, VehicleKind, VehicleState};
+use crate::world::{VehicleEnt, VehicleID};
+use crate::{ParCommandBuffer, Simulation};
+
+/// Distance to the depot door at which a returning vehicle goes back in
+const DEPOT_REACH: f32 = 15.0;
+
+pub fn service_color(service: ServiceKind) -> Color {
+    match service {
+        ServiceKind::Towing => Color::new(0.95, 0.7, 0.1, 1.0),
+        ServiceKind::Fire => Color::new(0.85, 0.1, 0.1, 1.0),
+        ServiceKind::Garbage => Color::new(0.3, 0.55, 0.25, 1.0),
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FleetStatus {
+    /// Waiting in the depot
+    Idle,
+    /// Driving to a call
+    EnRoute,
+    /// Working on the call
+    Working,
+    /// Driving back to the depot
+    Returning,
+}
+
+impl Display for FleetStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FleetStatus::Idle => write!(f, "idle"),
+            FleetStatus::EnRoute => write!(f, "en route"),
+            FleetStatus::Working => write!(f, "working"),
+            FleetStatus::Returning => write!(f, "returning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FleetVehicle {
+    pub status: FleetStatus,
+    /// The vehicle in the world, None while it waits in the depot
+    pub entity: Option<VehicleID>,
+}
+
+impl FleetVehicle {
+    const IDLE: FleetVehicle = FleetVehicle {
+        status: FleetStatus::Idle,
+        entity: None,
+    };
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Depot {
+    pub proto: ServiceDepotID,
+    pub vehicles: Vec<FleetVehicle>,
+}
+
+impl Depot {
+    pub fn service(&self) -> ServiceKind {
+        self.proto.prototype().service
+    }
+
+    pub fn n_idle(&self) -> usize {
+        self.vehicles
+            .iter()
+            .filter(|v| v.status == FleetStatus::Idle)
+            .count()
+    }
+
+    pub fn daily_upkeep(&self) -> Money {
+        self.proto.prototype().vehicle_upkeep * self.vehicles.len() as i64
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct ServiceFleets {
+    pub depots: BTreeMap<BuildingID, Depot>,
+    /// Upkeep paid for each service at the start of the current day
+    pub upkeep_paid: BTreeMap<ServiceKind, Money>,
+    /// Day of the last upkeep payment
+    day: i32,
+}
+
+impl ServiceFleets {
+    /// The depot and index in its fleet of a vehicle out on a call
+    pub fn find(&self, id: VehicleID) -> Option<(BuildingID, usize)> {
+        self.depots.iter().find_map(|(&b, depot)| {
+            let i = depot.vehicles.iter().position(|v| v.entity == Some(id))?;
+            Some((b, i))
+        })
+    }
+
+    pub fn is_service_vehicle(&self, id: VehicleID) -> bool {
+        self.find(id).is_some()
+    }
+
+    pub fn status(&self, id: VehicleID) -> Option<FleetStatus> {
+        let (b, i) = self.find(id)?;
+        Some(self.depots[&b].vehicles[i].status)
+    }
+
+    fn set_status(&mut self, id: VehicleID, status: FleetStatus) {
+        if let Some((b, i)) = self.find(id) {
+            // unwrap ok: just found
+            self.depots.get_mut(&b).unwrap().vehicles[i].status = status;
+        }
+    }
+
+    pub fn depots_of(&self, service: ServiceKind) -> impl Iterator<Item = (BuildingID, &Depot)> {
+        self.depots
+            .iter()
+            .filter(move |(_, d)| d.service() == service)
+            .map(|(&b, d)| (b, d))
+    }
+
+    pub fn fleet_size(&self, service: ServiceKind) -> usize {
+        self.depots_of(service).map(|(_, d)| d.vehicles.len()).sum()
+    }
+
+    pub fn daily_upkeep(&self, service: ServiceKind) -> Money {
+        self.depots_of(service).map(|(_, d)| d.daily_upkeep()).sum()
+    }
+}
+
+/// Every second, keeps the depots in sync with the map and takes the vehicles back in.
+/// Pays the upkeep once a day.
+pub(crate) fn service_fleet_system(sim: &mut Simulation) {
+    profiling::scope!("transportation::service_fleet_system");
+    if sim.get_tick() % TICKS_PER_SECOND != 0 {
+        return;
+    }
+    sync_depots(sim);
+    take_back_vehicles(sim);
+
+    let day = sim.read::<GameTime>().daytime.day;
+    if sim.read::<ServiceFleets>().day != day {
+        pay_upkeep(sim);
+        sim.write::<ServiceFleets>().day = day;
+    }
+}
+
+/// Opens the depots built since the last sync and closes the removed ones
+fn sync_depots(sim: &mut Simulation) {
+    let map = sim.map();
+    let mut fleets = sim.write::<ServiceFleets>();
+
+    let mut removed = vec![];
+    fleets.depots.retain(|&b, depot| {
+        if map.buildings().contains_key(b) {
+            return true;
+        }
+        removed.extend(depot.vehicles.iter().filter_map(|v| v.entity));
+        false
+    });
+
+    for (id, building) in map.buildings() {
+        let BuildingKind::ServiceDepot(proto) = building.kind else {
+            continue;
+        };
+        fleets.depots.entry(id).or_insert_with(|| Depot {
+            proto,
+            vehicles: vec![FleetVehicle::IDLE; proto.prototype().initial_fleet as usize],
+        });
+    }
+    drop((map, fleets));
+
+    sim.write::<ParCommandBuffer<VehicleEnt>>()
+        .kill_all(&removed);
+}
+
+/// The vehicles back at their depot go in, the ones that disappeared are idle again
+fn take_back_vehicles(sim: &mut Simulation) {
+    let map = sim.map();
+    let mut fleets = sim.write::<ServiceFleets>();
+    let mut done = vec![];
+    for (&b, depot) in fleets.depots.iter_mut() {
+        let Some(door) = map.buildings().get(b).map(|b| b.door_pos) else {
+            continue;
+        };
+        for v in &mut depot.vehicles {
+            let Some(id) = v.entity else {
+                continue;
+            };
+            let Some(ent) = sim.world.vehicles.get(id) else {
+                *v = FleetVehicle::IDLE;
+                continue;
+            };
+            if v.status == FleetStatus::Returning
+                && (ent.it.has_ended(0.0) || ent.trans.pos.distance(door) < DEPOT_REACH)
+            {
+                done.push(id);
+                *v = FleetVehicle::IDLE;
+            }
+        }
+    }
+    drop((map, fleets));
+
+    sim.write::<ParCommandBuffer<VehicleEnt>>().kill_all(&done);
+}
+
+fn pay_upkeep(sim: &mut Simulation) {
+    let mut fleets = sim.write::<ServiceFleets>();
+    let mut paid: BTreeMap<ServiceKind, Money> = BTreeMap::new();
+    for depot in fleets.depots.values() {
+        *paid.entry(depot.service()).or_default() += depot.daily_upkeep();
+    }
+    sim.write::<Government>().money -= paid.values().copied().sum::<Money>();
+    fleets.upkeep_paid = paid;
+}
+
+/// Adds a vehicle to the fleet of the depot. Returns false if it is full.
+pub fn buy_vehicle(sim: &mut Simulation, depot: BuildingID) -> bool {
+    let mut fleets = sim.write::<ServiceFleets>();
+    let Some(depot) = fleets.depots.get_mut(&depot) else {
+        return false;
+    };
+    if depot.vehicles.len() >= depot.proto.prototype().max_fleet as usize {
+        return false;
+    }
+    depot.vehicles.push(FleetVehicle::IDLE);
+    true
+}
+
+/// Removes a vehicle waiting in the depot. Returns false if none is waiting.
+pub fn sell_vehicle(sim: &mut Simulation, depot: BuildingID) -> bool {
+    let mut fleets = sim.write::<ServiceFleets>();
+    let Some(depot) = fleets.depots.get_mut(&depot) else {
+        return false;
+    };
+    let Some(i) = depot
+        .vehicles
+        .iter()
+        .position(|v| v.status == FleetStatus::Idle)
+    else {
+        return false;
+    };
+    depot.vehicles.remove(i);
+    true
+}
+
+/// Where the vehicles of the depot get on the road: the closest driving lane to the door
+fn depot_exit(map: &Map, door: Vec3) -> Option<Transform> {
+    let lane = map.nearest_lane(door, LaneKind::Driving, None)?;
+    let lane = map.lanes().get(lane)?;
+    let (pos, _, dir) = lane.points.project_segment_dir(door);
+    Some(Transform::new_dir(pos, dir))
+}
+
+/// Sends a vehicle of the service waiting in the depot closest to the call.
+/// Returns None if no vehicle is available or no route was found.
+pub fn dispatch(sim: &mut Simulation, service: ServiceKind, to: Vec3) -> Option<VehicleID> {
+    let tick = sim.read::<GameTime>().tick;
+    let (depot, start, it) = {
+        let map = sim.map();
+        let fleets = sim.read::<ServiceFleets>();
+        let (depot, door) = fleets
+            .depots_of(service)
+            .filter(|(_, d)| d.n_idle() > 0)
+            .filter_map(|(b, _)| Some((b, map.buildings().get(b)?.door_pos)))
+            .min_by_key(|(_, door)| OrderedFloat(door.distance2(to)))?;
+        let start = depot_exit(&map, door)?;
+        let it = Itinerary::route_async(
+            tick,
+            start.pos,
+            to,
+            &map,
+            PathKind::Vehicle,
+            &mut sim.write::<PathJobs>(),
+        )?;
+        (depot, start, it)
+    };
+
+    let vehicle = Vehicle {
+        ang_velocity: 0.0,
+        wait_time: 0.0,
+        max_speed_multiplier: 1.0,
+        state: VehicleState::Driving,
+        kind: VehicleKind::Truck,
+        model: None,
+        tint: service_color(service),
+        flag: 0,
+        blocked_by: None,
+        cargo: vec![],
+    };
+    let id = make_vehicle_entity(sim, start, vehicle, it, true);
+
+    let mut fleets = sim.write::<ServiceFleets>();
+    // unwrap ok: the depot has an idle vehicle
+    let v = fleets
+        .depots
+        .get_mut(&depot)
+        .and_then(|d| {
+            d.vehicles
+                .iter_mut()
+                .find(|v| v.status == FleetStatus::Idle)
+        })
+        .unwrap();
+    *v = FleetVehicle {
+        status: FleetStatus::EnRoute,
+        entity: Some(id),
+    };
+    Some(id)
+}
+
+/// The vehicle got to its call
+pub fn start_working(sim: &mut Simulation, id: VehicleID) {
+    sim.write::<ServiceFleets>()
+        .set_status(id, FleetStatus::Working);
+}
+
+/// Sends the vehicle back to its depot
+pub fn send_back(sim: &mut Simulation, id: VehicleID) {
+    let tick = sim.read::<GameTime>().tick;
+    let Some((depot, _)) = sim.read::<ServiceFleets>().find(id) else {
+        return;
+    };
+    let Some(door) = sim.map().buildings().get(depot).map(|b| b.door_pos) else {
+        return;
+    };
+    let Some(pos) = sim.world.vehicles.get(id).map(|v| v.trans.pos) else {
+        return;
+    };
+    let it = Itinerary::route_async(
+        tick,
+        pos,
+        door,
+        &sim.map(),
+        PathKind::Vehicle,
+        &mut sim.write::<PathJobs>(),
+    )
+    .unwrap_or(Itinerary::NONE);
+    if let Some(v) = sim.world.vehicles.get_mut(id) {
+        v.it = it;
+    }
+    sim.write::<ServiceFleets>()
+        .set_status(id, FleetStatus::Returning);
+}
+
+/// Seconds it takes to drive from the closest depot of the service to each intersection.
+/// The intersections that cannot be reached are left out.
+pub fn coverage(
+    map: &Map,
+    fleets: &ServiceFleets,
+    service: ServiceKind,
+) -> BTreeMap<IntersectionID, f32> {
+    let roads = map.roads();
+    let lanes = map.lanes();
+    let intersections = map.intersections();
+
+    let mut times: BTreeMap<IntersectionID, f32> = BTreeMap::new();
+    let mut queue = BinaryHeap::new();
+
+    for (b, _) in fleets.depots_of(service) {
+        let Some(door) = map.buildings().get(b).map(|b| b.door_pos) else {
+            continue;
+        };
+        let Some(lane) = map
+            .nearest_lane(door, LaneKind::Driving, None)
+            .and_then(|l| lanes.get(l))
+        else {
+            continue;
+        };
+        let Some(road) = roads.get(lane.parent) else {
+            continue;
+        };
+        for inter in [road.src, road.dst] {
+            let Some(i) = intersections.get(inter) else {
+                continue;
+            };
+            let t = door.distance(i.pos) / lane.speed_limit;
+            queue.push(Reverse((OrderedFloat(t), inter)));
+        }
+    }
+
+    while let Some(Reverse((OrderedFloat(t), inter))) = queue.pop() {
+        if times.contains_key(&inter) {
+            continue;
+        }
+        times.insert(inter, t);
+
+        let Some(i) = intersections.get(inter) else {
+            continue;
+        };
+        for &r in &i.roads {
+            let Some(road) = roads.get(r) else {
+                continue;
+            };
+            let Some(next) = road.other_end(inter) else {
+                continue;
+            };
+            if times.contains_key(&next) {
+                continue;
+            }
+            let speed_factor = road.condition().speed_factor();
+            let best = road
+                .outgoing_lanes_from(inter)
+                .iter()
+                .filter(|(_, kind)| *kind == LaneKind::Driving)
+                .filter_map(|(l, _)| lanes.get(*l))
+                .filter(|l| !l.blocked)
+                .map(|l| l.points.length() / (l.speed_limit * speed_factor))
+                .min_by_key(|&x| OrderedFloat(x));
+            if let Some(dt) = best {
+                queue.push(Reverse((OrderedFloat(t + dt), next)));
+            }
+        }
+    }
+
+    times
+}
+
+#[cfg(test)]
+mod tests {
+    use geom::{vec2, vec3, Vec2, OBB};
+    use prototypes::{BuildingGen, ServiceDepotID, ServiceKind};
+
+    use super::*;
+    use crate::map_dynamic::BuildingInfos;
+    use crate::tests::TestCtx;
+
+    fn build_depot(test: &mut TestCtx, center: Vec2) -> BuildingID {
+        let proto = ServiceDepotID::new("tow-depot").prototype();
+        let id = test
+            .g
+            .map_mut()
+            .build_special_building(
+                &OBB::new(center, Vec2::Y, proto.size.w, proto.size.h),
+                BuildingKind::ServiceDepot(proto.id),
+                BuildingGen::CenteredDoor {
+                    vertical_factor: 1.0,
+                },
+                None,
+                None,
+            )
+            .unwrap();
+        test.g.write::<BuildingInfos>().insert(id);
+        sync_depots(&mut test.g);
+        id
+    }
+
+    #[test]
+    fn fleet_is_bought_sold_and_paid() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[vec3(0.0, 0.0, 0.0), vec3(400.0, 0.0, 0.0)]);
+        let depot = build_depot(&mut test, vec2(200.0, 40.0));
+        let proto = ServiceDepotID::new("tow-depot").prototype();
+
+        let n = |test: &TestCtx| test.g.read::<ServiceFleets>().depots[&depot].vehicles.len();
+        assert_eq!(n(&test), proto.initial_fleet as usize);
+
+        while n(&test) < proto.max_fleet as usize {
+            assert!(buy_vehicle(&mut test.g, depot));
+        }
+        assert!(!buy_vehicle(&mut test.g, depot));
+        assert!(sell_vehicle(&mut test.g, depot));
+        assert_eq!(n(&test), proto.max_fleet as usize - 1);
+
+        let before = test.g.read::<Government>().money;
+        pay_upkeep(&mut test.g);
+        let upkeep = proto.vehicle_upkeep * (proto.max_fleet as i64 - 1);
+        assert_eq!(test.g.read::<Government>().money, before - upkeep);
+        assert_eq!(
+            test.g.read::<ServiceFleets>().upkeep_paid[&ServiceKind::Towing],
+            upkeep
+        );
+
+        test.g.map_mut().remove_building(depot);
+        sync_depots(&mut test.g);
+        assert!(test.g.read::<ServiceFleets>().depots.is_empty());
+    }
+
+    #[test]
+    fn coverage_grows_along_the_roads() {
+        let mut test = TestCtx::new();
+        test.build_roads(&[
+            vec3(0.0, 0.0, 0.0),
+            vec3(200.0, 0.0, 0.0),
+            vec3(600.0, 0.0, 0.0),
+        ]);
+
+        let ends = |test: &TestCtx| {
+            let map = test.g.map();
+            let near = |x: f32| {
+                map.intersections()
+                    .iter()
+                    .min_by_key(|(_, i)| OrderedFloat((i.pos.x - x).abs()))
+                    .map(|(id, _)| id)
+                    .unwrap()
+            };
+            (near(200.0), near(600.0))
+        };
+        let (mid, far) = ends(&test);
+
+        let times = coverage(
+            &test.g.map(),
+            &test.g.read::<ServiceFleets>(),
+            ServiceKind::Towing,
+        );
+        assert!(times.is_empty());
+
+        build_depot(&mut test, vec2(100.0, 40.0));
+        let times = coverage(
+            &test.g.map(),
+            &test.g.read::<ServiceFleets>(),
+            ServiceKind::Towing,
+        );
+        assert!(times[&mid] > 0.0);
+        assert!(times[&far] > times[&mid]);
+    }
+}
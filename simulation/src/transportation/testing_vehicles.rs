@@ -1,8 +1,11 @@
 use crate::map::{Map, PathKind};
 use crate::map_dynamic::Itinerary;
+use crate::transportation::spawn_parked_car;
 use crate::utils::resources::Resources;
-use crate::{VehicleID, World};
+use crate::{Simulation, VehicleID, World};
 use common::scroll::BTreeSetScroller;
+use geom::Vec3;
+use prototypes::RoadVehiclePrototype;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 
@@ -45,3 +48,12 @@ pub fn random_vehicles_update(world: &mut World, res: &mut Resources) {
         rv.vehicles.remove(&v);
     }
 }
+
+/// Parks one car of each model the citizens drive side by side near the position,
+/// to check their scales are consistent
+pub fn spawn_car_models(sim: &mut Simulation, pos: Vec3) -> Vec<VehicleID> {
+    RoadVehiclePrototype::iter()
+        .filter(|m| m.popularity > 0.0)
+        .filter_map(|m| spawn_parked_car(sim, m.id, pos))
+        .collect()
+}
@@ -11,11 +11,14 @@ use prototypes::{RollingStockID, DELTA};
 
 use crate::map::{IntersectionID, LaneID, Map, TraverseKind};
 use crate::map_dynamic::ItineraryFollower;
-use crate::transportation::Speed;
+use crate::transportation::{dynamics, Speed};
 use crate::utils::resources::Resources;
 use crate::world::{TrainEnt, TrainID, WagonEnt};
 use crate::{Itinerary, ItineraryLeader, Simulation, World};
 
+/// Seconds standing still before a train is considered blocked and slowly forces its way
+pub const BLOCKED_AFTER: f32 = 60.0;
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct TrainReservations {
     pub reservations: BTreeMap<IntersectionID, TrainID>,
@@ -32,6 +35,37 @@ pub struct Locomotive {
     pub dec_force: f32,
     /// m
     pub length: f32,
+    /// metric ton
+    #[serde(default = "default_mass")]
+    pub mass: f32,
+    /// kW, trains from older saves accelerate as if they had no power limit
+    #[serde(default = "default_power")]
+    pub power: f32,
+}
+
+fn default_mass() -> f32 {
+    1.0
+}
+
+fn default_power() -> f32 {
+    f32::INFINITY
+}
+
+impl Locomotive {
+    /// Acceleration at that speed, `slope` being the sine of the grade
+    pub fn acceleration(&self, speed: f32, slope: f32) -> f32 {
+        dynamics::acceleration(self.acc_force, self.power, self.mass, speed, slope)
+    }
+
+    /// Braking on that grade
+    pub fn deceleration(&self, slope: f32) -> f32 {
+        dynamics::deceleration(self.dec_force, slope)
+    }
+
+    /// Distance needed to stop from that speed on that grade
+    pub fn braking_distance(&self, speed: f32, slope: f32) -> f32 {
+        dynamics::braking_distance(speed, self.deceleration(slope))
+    }
 }
 
 #[derive(Serialize, Deserialize, Inspect)]
@@ -57,8 +91,8 @@ pub struct RailWagon {
 
 pub fn calculate_locomotive(wagons: &Vec<RollingStockID>) -> Locomotive {
     let info = wagons.iter().fold(
-        (720.0, 0.0, 0.0, 0.0, 0),
-        |(speed, acc, dec, length, mass): (f32, f32, f32, f32, u32), &id| {
+        (720.0, 0.0, 0.0, 0.0, 0, 0.0),
+        |(speed, acc, dec, length, mass, power): (f32, f32, f32, f32, u32, f32), &id| {
             let rs = RollingStockID::prototype(id);
             (
                 speed.min(rs.max_speed),
@@ -66,6 +100,7 @@ pub fn calculate_locomotive(wagons: &Vec<RollingStockID>) -> Locomotive {
                 dec + rs.dec_force,
                 length + rs.length,
                 mass + rs.mass,
+                power + rs.power,
             )
         },
     );
@@ -74,6 +109,8 @@ pub fn calculate_locomotive(wagons: &Vec<RollingStockID>) -> Locomotive {
         acc_force: info.1 / info.4 as f32,
         dec_force: info.2 / info.4 as f32,
         length: info.3 + 10.0,
+        mass: info.4 as f32,
+        power: info.5,
     }
 }
 
@@ -256,7 +293,9 @@ pub fn train_reservations_update(world: &mut World, resources: &mut Resources) {
             let mut want_to_reserve = vec![];
             let mut all_ok = true;
             // Then look ahead stop_dist to reserve all intersections
-            let stop_dist = train.speed.0 * train.speed.0 / (2.0 * train.locomotive.dec_force);
+            let stop_dist = train
+                .locomotive
+                .braking_distance(train.speed.0, train.trans.dir.z);
 
             if let Some(v) = reservations.localisations.get(&travers.kind) {
                 if v.len() >= 2
@@ -366,9 +405,12 @@ pub fn locomotive_system(world: &mut World, resources: &mut Resources) {
                 .unwrap_or(t.trans.dir);
         t.trans.dir = desired_dir;
 
-        t.speed.0 += (desired_speed - t.speed.0).clamp(
-            -DELTA * t.locomotive.dec_force,
-            DELTA * t.locomotive.acc_force,
+        let slope = t.trans.dir.z;
+        t.speed.0 = dynamics::speed_step(
+            t.speed.0,
+            desired_speed,
+            t.locomotive.acceleration(t.speed.0, slope),
+            t.locomotive.deceleration(slope),
         );
         if t.speed.0 <= 0.001 {
             t.res.waited_for += DELTA;
@@ -377,7 +419,7 @@ pub fn locomotive_system(world: &mut World, resources: &mut Resources) {
         }
         for v in t.res.past_travers.values_mut() {
             *v += t.speed.0 * DELTA;
-            if t.res.waited_for > 60.0 {
+            if t.res.waited_for > BLOCKED_AFTER {
                 *v += 0.1 * DELTA;
             }
         }
@@ -396,7 +438,7 @@ pub fn locomotive_desired_speed(
         return 0.0;
     }
 
-    let stop_dist = t.speed.0 * t.speed.0 / (2.0 * t.locomotive.dec_force);
+    let stop_dist = t.locomotive.braking_distance(t.speed.0, t.trans.dir.z);
 
     let mut lastid = None;
     let mydist = t.res.cur_travers_dist;
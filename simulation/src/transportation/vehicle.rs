@@ -1,23 +1,33 @@
+use crate::map::LaneID;
 use crate::map_dynamic::{Itinerary, ParkingManagement, SpotReservation};
+use crate::souls::delivery::Shipment;
+use crate::transportation::dynamics;
 use crate::transportation::{TransportGrid, TransportState, TransportationGroup, Transporter};
 use crate::utils::rand_provider::RandProvider;
 use crate::world::{VehicleEnt, VehicleID};
 use crate::Simulation;
 use egui_inspect::Inspect;
+use flat_spatial::grid::GridHandle;
 use geom::Transform;
 use geom::{Color, Spline3, Vec3};
-use prototypes::GameInstant;
+use prototypes::{try_prototype, GameInstant, RoadVehicleID, RoadVehiclePrototype};
 use serde::{Deserialize, Serialize};
 
 /// The duration for the parking animation.
 pub const TIME_TO_PARK: f32 = 4.0;
 
+/// Gridlock flag of the broken down vehicles.
+/// The vehicles queued behind take it, so they wait instead of thinking they are in a gridlock.
+pub const WRECK_FLAG: u64 = u64::MAX;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum VehicleState {
     Parked(SpotReservation),
     Driving,
     /// Panicked when it notices it's in a gridlock
     Panicking(GameInstant),
+    /// Stopped on its lane since then, until the tow truck clears it
+    BrokenDown(GameInstant),
     RoadToPark(Spline3, f32, SpotReservation),
 }
 
@@ -38,10 +48,29 @@ pub struct Vehicle {
 
     pub state: VehicleState,
     pub kind: VehicleKind,
+    /// The road vehicle it is, the one of its kind if None
+    #[serde(default)]
+    pub model: Option<RoadVehicleID>,
     pub tint: Color,
 
     /// Used to detect gridlock
     pub flag: u64,
+
+    /// What it is stopped behind, to find the vehicles waiting on each other
+    #[serde(skip)]
+    #[inspect(skip)]
+    pub blocked_by: Option<GridHandle>,
+
+    /// Goods carried, in the order they are delivered
+    #[serde(default)]
+    #[inspect(skip)]
+    pub cargo: Vec<Shipment>,
+
+    /// Lane it is on before the next intersection and the seconds it was stopped there,
+    /// counted in the statistics of the intersection once it enters it
+    #[serde(default)]
+    #[inspect(skip)]
+    pub approach: Option<(LaneID, f32)>,
 }
 
 #[must_use]
@@ -58,6 +87,21 @@ pub fn put_vehicle_in_transport_grid(sim: &Simulation, w: f32, trans: Transform)
 }
 
 impl VehicleKind {
+    /// The road vehicle prototype this kind is made from
+    pub fn prototype(self) -> Option<&'static RoadVehiclePrototype> {
+        let name = match self {
+            VehicleKind::Car => "simple_car",
+            VehicleKind::Truck => "simple_truck",
+            VehicleKind::Bus => return None,
+        };
+        try_prototype(RoadVehicleID::new(name))
+    }
+
+    /// Units of goods it can carry
+    pub fn capacity(self) -> u32 {
+        self.prototype().map_or(0, |p| p.capacity)
+    }
+
     pub fn width(self) -> f32 {
         match self {
             VehicleKind::Car => 4.5,
@@ -66,18 +110,19 @@ impl VehicleKind {
         }
     }
 
-    pub fn acceleration(self) -> f32 {
-        match self {
-            VehicleKind::Car => 3.0,
-            VehicleKind::Truck => 2.5,
-            VehicleKind::Bus => 2.0,
-        }
+    /// Acceleration the engine gives at that speed, `slope` being the sine of the grade
+    pub fn acceleration(self, speed: f32, slope: f32) -> f32 {
+        acceleration_of(self.prototype(), speed, slope)
     }
 
-    pub fn deceleration(self) -> f32 {
-        match self {
-            VehicleKind::Car | VehicleKind::Bus | VehicleKind::Truck => 6.0,
-        }
+    /// Braking on that grade
+    pub fn deceleration(self, slope: f32) -> f32 {
+        deceleration_of(self.prototype(), slope)
+    }
+
+    /// Distance needed to stop from that speed on that grade
+    pub fn braking_distance(self, speed: f32, slope: f32) -> f32 {
+        dynamics::braking_distance(speed, self.deceleration(slope))
     }
 
     pub fn min_turning_radius(self) -> f32 {
@@ -104,9 +149,27 @@ impl VehicleKind {
     }
 }
 
+/// Traction limited acceleration, braking, mass and power.
+/// Buses have no prototype and use values of their own.
+fn dynamics_of(proto: Option<&RoadVehiclePrototype>) -> (f32, f32, f32, f32) {
+    match proto {
+        Some(p) => (p.acceleration, p.deceleration, p.mass, p.power),
+        None => (1.5, 4.5, 12.0, 200.0),
+    }
+}
+
+fn acceleration_of(proto: Option<&RoadVehiclePrototype>, speed: f32, slope: f32) -> f32 {
+    let (max_acc, _, mass, power) = dynamics_of(proto);
+    dynamics::acceleration(max_acc, power, mass, speed, slope)
+}
+
+fn deceleration_of(proto: Option<&RoadVehiclePrototype>, slope: f32) -> f32 {
+    dynamics::deceleration(dynamics_of(proto).1, slope)
+}
+
 pub fn unpark(sim: &mut Simulation, vehicle: VehicleID) {
     let v = unwrap_ret!(sim.world.vehicles.get_mut(vehicle));
-    let w = v.vehicle.kind.width();
+    let w = v.vehicle.length();
     let trans = v.trans;
 
     if let VehicleState::Parked(spot) =
@@ -128,17 +191,30 @@ pub fn spawn_parked_vehicle(
     kind: VehicleKind,
     near: Vec3,
 ) -> Option<VehicleID> {
+    let spot_id = reserve_spot_near(sim, near)?;
+    spawn_parked_vehicle_with_spot(sim, kind, None, spot_id)
+}
+
+/// Parks a car of that model near the position
+pub fn spawn_parked_car(
+    sim: &mut Simulation,
+    model: RoadVehicleID,
+    near: Vec3,
+) -> Option<VehicleID> {
+    let spot_id = reserve_spot_near(sim, near)?;
+    spawn_parked_vehicle_with_spot(sim, VehicleKind::Car, Some(model), spot_id)
+}
+
+fn reserve_spot_near(sim: &Simulation, near: Vec3) -> Option<SpotReservation> {
     let map = sim.map();
     let mut pm = sim.write::<ParkingManagement>();
-    let spot_id = pm.reserve_near(near, &map).ok()?;
-    drop((map, pm));
-
-    spawn_parked_vehicle_with_spot(sim, kind, spot_id)
+    pm.reserve_near(near, &map).ok()
 }
 
 pub fn spawn_parked_vehicle_with_spot(
     sim: &mut Simulation,
     kind: VehicleKind,
+    model: Option<RoadVehicleID>,
     spot_id: SpotReservation,
 ) -> Option<VehicleID> {
     let map = sim.map();
@@ -146,12 +222,14 @@ pub fn spawn_parked_vehicle_with_spot(
     let pos = spot_id.get(&map.parking).unwrap().trans; // Unwrap ok: Gotten using reserve_near
     drop(map);
 
-    let tint = match kind {
-        VehicleKind::Car => get_random_car_color(&mut sim.write::<RandProvider>()),
-        _ => Color::WHITE,
-    };
-
-    let vehicle = Vehicle::new(kind, spot_id, tint, &mut sim.write::<RandProvider>());
+    // the paint of the cars is picked by the renderer from their id
+    let vehicle = Vehicle::new(
+        kind,
+        model,
+        spot_id,
+        Color::WHITE,
+        &mut sim.write::<RandProvider>(),
+    );
 
     Some(make_vehicle_entity(sim, pos, vehicle, it, false))
 }
@@ -163,7 +241,7 @@ pub fn make_vehicle_entity(
     it: Itinerary,
     mk_collider: bool,
 ) -> VehicleID {
-    let w = vehicle.kind.width();
+    let w = vehicle.length();
 
     let mut collider = None;
     if mk_collider {
@@ -178,35 +256,38 @@ pub fn make_vehicle_entity(
     })
 }
 
-pub fn get_random_car_color(r: &mut RandProvider) -> Color {
-    let car_colors: [(Color, f32); 9] = [
-        (Color::from_hex(0x22_22_22), 0.22),  // Black
-        (Color::from_hex(0xff_ff_ff), 0.19),  // White
-        (Color::from_hex(0x66_66_66), 0.17),  // Gray
-        (Color::from_hex(0xb8_b8_b8), 0.14),  // Silver
-        (Color::from_hex(0x1a_3c_70), 0.1),   // Blue
-        (Color::from_hex(0xd8_22_00), 0.1),   // Red
-        (Color::from_hex(0x7c_4b_24), 0.02),  // Brown
-        (Color::from_hex(0xd4_c6_78), 0.015), // Gold
-        (Color::from_hex(0x72_cb_19), 0.015), // Green
-    ];
+/// Paint colors of the cars, with how common they are
+pub const CAR_COLORS: [(u64, f32); 9] = [
+    (0x22_22_22, 0.22),  // Black
+    (0xff_ff_ff, 0.19),  // White
+    (0x66_66_66, 0.17),  // Gray
+    (0xb8_b8_b8, 0.14),  // Silver
+    (0x1a_3c_70, 0.1),   // Blue
+    (0xd8_22_00, 0.1),   // Red
+    (0x7c_4b_24, 0.02),  // Brown
+    (0xd4_c6_78, 0.015), // Gold
+    (0x72_cb_19, 0.015), // Green
+];
 
-    let total: f32 = car_colors.iter().map(|x| x.1).sum();
+/// Index in [`CAR_COLORS`] of the color at `x` between 0 and 1, following how common they are
+pub fn car_color_index(x: f32) -> usize {
+    let total: f32 = CAR_COLORS.iter().map(|x| x.1).sum();
 
-    let r = r.next_f32() * total;
+    let r = x * total;
     let mut partial = 0.0;
-    for (col, freq) in &car_colors {
+    for (i, (_, freq)) in CAR_COLORS.iter().enumerate() {
         partial += freq;
         if partial >= r {
-            return *col;
+            return i;
         }
     }
-    unreachable!();
+    CAR_COLORS.len() - 1
 }
 
 impl Vehicle {
     pub fn new(
         kind: VehicleKind,
+        model: Option<RoadVehicleID>,
         spot: SpotReservation,
         tint: Color,
         rng: &mut RandProvider,
@@ -217,8 +298,44 @@ impl Vehicle {
             max_speed_multiplier: 0.95 + 0.1 * rng.next_f32(),
             state: VehicleState::Parked(spot),
             kind,
+            model,
             tint,
             flag: 0,
+            blocked_by: None,
+            cargo: vec![],
+            approach: None,
         }
     }
+
+    /// The road vehicle prototype it is made from, its model or else the one of its kind
+    pub fn prototype(&self) -> Option<&'static RoadVehiclePrototype> {
+        self.model
+            .and_then(try_prototype)
+            .or_else(|| self.kind.prototype())
+    }
+
+    /// Bumper to bumper, the space it takes on the road and the gaps to the others start from it
+    pub fn length(&self) -> f32 {
+        self.prototype().map_or(self.kind.width(), |p| p.length)
+    }
+
+    /// The fastest it goes, whatever the speed limit
+    pub fn max_speed(&self) -> f32 {
+        self.prototype().map_or(f32::INFINITY, |p| p.max_speed)
+    }
+
+    /// Acceleration the engine gives at that speed, `slope` being the sine of the grade
+    pub fn acceleration(&self, speed: f32, slope: f32) -> f32 {
+        acceleration_of(self.prototype(), speed, slope)
+    }
+
+    /// Braking on that grade
+    pub fn deceleration(&self, slope: f32) -> f32 {
+        deceleration_of(self.prototype(), slope)
+    }
+
+    /// Distance needed to stop from that speed on that grade
+    pub fn braking_distance(&self, speed: f32, slope: f32) -> f32 {
+        dynamics::braking_distance(speed, self.deceleration(slope))
+    }
 }
@@ -0,0 +1,189 @@
+//! Boats sail on the navigable water, found from the depth of the lakes and the sea.
+//! There is no waterway network to build: the routes go over a grid of water cells
+//! and are checked again as boats sail, since terraforming can close a channel.
+
+use crate::map::Environment;
+use geom::{vec2, Vec2, Vec3, OBB};
+
+/// Water shallower than this cannot be sailed on
+pub const NAVIGABLE_DEPTH: f32 = 3.0;
+/// Side of the cells boats are routed on
+pub const WATERWAY_CELL: f32 = 32.0;
+/// Farthest distance from a dock to the water it moors on
+pub const MOORING_DISTANCE: f32 = 30.0;
+
+type Cell = (i32, i32);
+
+pub fn is_navigable(env: &Environment, pos: Vec2) -> bool {
+    env.water_depth(pos)
+        .map_or(false, |depth| depth >= NAVIGABLE_DEPTH)
+}
+
+/// Where the boats of a dock occupying the obb moor, the navigable point closest to it
+pub fn dock_mooring(env: &Environment, obb: &OBB) -> Option<Vec2> {
+    let center = obb.center();
+    let radius = obb.axis().map(|x| x.mag()).into_iter().fold(0.0, f32::max) * 0.5;
+
+    let mut best: Option<Vec2> = None;
+    let mut dist = 0.0;
+    while dist <= MOORING_DISTANCE {
+        for i in 0..32 {
+            let dir = geom::Radians(i as f32 * std::f32::consts::TAU / 32.0).vec2();
+            let p = center + dir * (radius + dist);
+            if is_navigable(env, p)
+                && best.map_or(true, |b| b.distance(center) > p.distance(center))
+            {
+                best = Some(p);
+            }
+        }
+        if best.is_some() {
+            return best;
+        }
+        dist += WATERWAY_CELL * 0.25;
+    }
+    None
+}
+
+/// Whether the straight line between two points stays on navigable water
+pub fn segment_navigable(env: &Environment, a: Vec2, b: Vec2) -> bool {
+    let n = (a.distance(b) / (WATERWAY_CELL * 0.5)).ceil().max(1.0) as i32;
+    (0..=n).all(|i| is_navigable(env, a + (b - a) * (i as f32 / n as f32)))
+}
+
+/// Whether a boat can still follow the waypoints from its position
+pub fn route_is_open(env: &Environment, pos: Vec3, path: impl IntoIterator<Item = Vec2>) -> bool {
+    let mut cur = pos.xy();
+    for p in path {
+        if !segment_navigable(env, cur, p) {
+            return false;
+        }
+        cur = p;
+    }
+    true
+}
+
+fn cell(pos: Vec2) -> Cell {
+    (
+        (pos.x / WATERWAY_CELL).floor() as i32,
+        (pos.y / WATERWAY_CELL).floor() as i32,
+    )
+}
+
+fn cell_center(c: Cell) -> Vec2 {
+    vec2(c.0 as f32 + 0.5, c.1 as f32 + 0.5) * WATERWAY_CELL
+}
+
+/// Route over navigable water between two points, the waypoints end on `to`.
+/// Returns None if the points are on different bodies of water.
+pub fn waterway_route(env: &Environment, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+    if !is_navigable(env, from) || !is_navigable(env, to) {
+        return None;
+    }
+    if segment_navigable(env, from, to) {
+        return Some(vec![to]);
+    }
+
+    let start = cell(from);
+    let end = cell(to);
+
+    // costs are in tenths of a cell so that diagonals stay integers
+    let successors = |&(x, y): &Cell| {
+        let mut v = Vec::with_capacity(8);
+        for (dx, dy) in [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ] {
+            let c = (x + dx, y + dy);
+            if c != end && !is_navigable(env, cell_center(c)) {
+                continue;
+            }
+            v.push((c, if dx != 0 && dy != 0 { 14u32 } else { 10 }));
+        }
+        v
+    };
+    let heuristic = |&(x, y): &Cell| {
+        let dx = (x - end.0).unsigned_abs();
+        let dy = (y - end.1).unsigned_abs();
+        10 * dx.max(dy) + 4 * dx.min(dy)
+    };
+
+    let (cells, _) =
+        pathfinding::directed::astar::astar(&start, successors, heuristic, |c| *c == end)?;
+
+    let mut points: Vec<Vec2> = cells.into_iter().skip(1).map(cell_center).collect();
+    points.pop();
+    points.push(to);
+
+    // keep the waypoints that cannot be skipped in a straight line
+    let mut smoothed = Vec::with_capacity(points.len());
+    let mut cur = from;
+    let mut i = 0;
+    while i < points.len() {
+        let mut j = points.len() - 1;
+        while j > i && !segment_navigable(env, cur, points[j]) {
+            j -= 1;
+        }
+        smoothed.push(points[j]);
+        cur = points[j];
+        i = j + 1;
+    }
+
+    Some(smoothed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::WATER_LEVEL;
+
+    #[test]
+    fn routes_go_around_land() {
+        let mut env = Environment::new(2, 2);
+        let bounds = env.bounds();
+        let center = bounds.center();
+
+        // a lake with a wall in the middle, open at the top
+        env.terrain_apply(bounds, |p| {
+            let wall = (p.x - center.x).abs() < 40.0 && p.y < center.y + 200.0;
+            if wall {
+                0.0
+            } else {
+                -20.0
+            }
+        });
+
+        let from = center - vec2(150.0, 0.0);
+        let to = center + vec2(150.0, 0.0);
+        assert!(!segment_navigable(&env, from, to));
+
+        let route = waterway_route(&env, from, to).expect("should go around the wall");
+        assert_eq!(route.last(), Some(&to));
+        assert!(route_is_open(
+            &env,
+            from.z(WATER_LEVEL),
+            route.iter().copied()
+        ));
+        assert!(route.iter().any(|p| p.y > center.y + 200.0));
+
+        // closing the top cuts the lake in two
+        env.terrain_apply(bounds, |p| {
+            if (p.x - center.x).abs() < 40.0 {
+                0.0
+            } else {
+                p.z
+            }
+        });
+        assert!(!route_is_open(
+            &env,
+            from.z(WATER_LEVEL),
+            route.iter().copied()
+        ));
+        assert!(waterway_route(&env, from, to).is_none());
+    }
+}
@@ -0,0 +1,35 @@
+//! Debug-draw channels of the simulation, the game draws them when they are enabled.
+
+use common::debug_draw::{register, DebugChannel};
+use geom::LinearColor;
+
+/// Route and local path of the inspected entity
+pub static ROUTER_PATHS: DebugChannel = DebugChannel::new("router.paths", LinearColor::GREEN);
+/// Intersections reserved by trains and the ones the inspected train will reserve
+pub static TRAIN_RESERVATIONS: DebugChannel =
+    DebugChannel::new("train.reservations", LinearColor::new(0.3, 0.8, 0.3, 1.0));
+/// Where the trains are on each rail lane they occupy
+pub static LANE_RESERVATIONS: DebugChannel =
+    DebugChannel::new("lane.reservations", LinearColor::new(0.8, 0.3, 0.3, 1.0));
+/// Turns of the intersections taken by road vehicles, with the id of the vehicle
+pub static INTERSECTION_OCCUPANCY: DebugChannel = DebugChannel::new(
+    "intersection.occupancy",
+    LinearColor::new(0.9, 0.6, 0.1, 1.0),
+);
+/// Vehicle each stopped vehicle is waiting behind, loops of waiting vehicles are drawn in red
+pub static VEHICLE_WAITS: DebugChannel =
+    DebugChannel::new("vehicle.waits", LinearColor::new(0.9, 0.9, 0.2, 1.0));
+/// Cells of the map spatial index
+pub static SPATIAL_GRID: DebugChannel =
+    DebugChannel::new("map.spatialgrid", LinearColor::new(0.0, 0.0, 1.0, 0.1));
+
+pub fn register_channels() {
+    register(&[
+        &ROUTER_PATHS,
+        &TRAIN_RESERVATIONS,
+        &LANE_RESERVATIONS,
+        &INTERSECTION_OCCUPANCY,
+        &VEHICLE_WAITS,
+        &SPATIAL_GRID,
+    ]);
+}
@@ -0,0 +1,551 @@
+//! On-disk format of the saves.
+//! A save is a list of independent blocks, each prefixed with its name and its length.
+//! The header comes first and is never compressed, so that the save list can read it without
+//! loading the rest. The other blocks are compressed on worker threads as soon as they are
+//! serialized, and decompressed in parallel on load while the blocks before them are deserialized.
+//! Saves made before this format are a single zlib stream and are still read as a whole.
+//!
+//! Each block names its codec. New saves use zstd, which compresses faster than deflate for the
+//! same size, the deflate blocks of the first block saves are still read. A game that can't read a
+//! codec refuses the save instead of loading garbage.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::sync::mpsc::channel;
+
+use serde::{Deserialize, Serialize};
+
+use common::saveload::{Bincode, Encoder};
+use prototypes::Tick;
+
+/// First bytes of a save in the block format
+pub const MAGIC: &[u8; 4] = b"EGSV";
+/// Bump when the layout of the blocks changes
+pub const FORMAT_VERSION: u32 = 1;
+/// Name of the block holding the [`SaveHeader`]
+pub const HEADER_BLOCK: &str = "header";
+/// Name of the block holding the entities of the world
+pub const ENTITIES_BLOCK: &str = "entities";
+
+/// A block name or a block bigger than that means the file is corrupted
+const MAX_NAME_LEN: usize = 256;
+const MAX_BLOCK_LEN: u64 = 1 << 34;
+
+/// How hard the blocks are compressed, manual saves can afford to take longer than autosaves
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SaveCompression {
+    #[default]
+    Fast,
+    Max,
+}
+
+impl SaveCompression {
+    pub const ALL: [SaveCompression; 2] = [SaveCompression::Fast, SaveCompression::Max];
+
+    /// The codec and its level
+    pub(crate) fn codec(self) -> (Codec, i32) {
+        match self {
+            SaveCompression::Fast => (Codec::Zstd, 1),
+            SaveCompression::Max => (Codec::Zstd, 19),
+        }
+    }
+}
+
+/// The codecs and levels compared by `Simulation::measure_save`, deflate being what the saves
+/// used before zstd
+pub(crate) const MEASURED: [(Codec, i32); 4] = [
+    (Codec::Deflate, 1),
+    (Codec::Deflate, 9),
+    (Codec::Zstd, 1),
+    (Codec::Zstd, 19),
+];
+
+/// How the data of a block is stored, the byte after its name.
+/// The values are the ones of the compressed flag the first blocks had.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Codec {
+    Stored = 0,
+    /// Only written by the first block saves
+    Deflate = 1,
+    Zstd = 2,
+}
+
+impl Codec {
+    fn from_byte(b: u8) -> Option<Codec> {
+        match b {
+            0 => Some(Codec::Stored),
+            1 => Some(Codec::Deflate),
+            2 => Some(Codec::Zstd),
+            _ => None,
+        }
+    }
+
+    fn compress(self, raw: &[u8], level: i32) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::Stored => Ok(raw.to_vec()),
+            Codec::Deflate => Ok(miniz_oxide::deflate::compress_to_vec(
+                raw,
+                level.clamp(0, 10) as u8,
+            )),
+            Codec::Zstd => zstd::bulk::compress(raw, level),
+        }
+    }
+
+    /// Stops one byte past `raw_len`, a corrupted block can't fill the memory
+    fn decompress(self, data: Vec<u8>, raw_len: u64) -> Result<Vec<u8>, String> {
+        match self {
+            Codec::Stored => Ok(data),
+            Codec::Deflate => {
+                miniz_oxide::inflate::decompress_to_vec_with_limit(&data, raw_len as usize)
+                    .map_err(|e| format!("{:?}", e.status))
+            }
+            Codec::Zstd => {
+                let mut raw = vec![];
+                zstd::stream::Decoder::with_buffer(&*data)
+                    .and_then(|d| d.take(raw_len + 1).read_to_end(&mut raw))
+                    .map_err(|e| e.to_string())?;
+                Ok(raw)
+            }
+        }
+    }
+}
+
+impl AsRef<str> for SaveCompression {
+    fn as_ref(&self) -> &str {
+        match self {
+            SaveCompression::Fast => "Fast",
+            SaveCompression::Max => "Smallest",
+        }
+    }
+}
+
+/// What the save list shows about a save, without loading it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveHeader {
+    /// Version of the game that made the save
+    pub version: String,
+    pub tick: Tick,
+    pub mods: Vec<String>,
+    /// Seconds since the unix epoch
+    pub saved_at: u64,
+}
+
+/// Sizes of a written save, in bytes
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SaveSize {
+    pub uncompressed: u64,
+    pub file: u64,
+}
+
+/// Writes the header then the sections in order. Each section is compressed on the thread pool
+/// while the next ones are serialized, and written as soon as the ones before it are.
+pub fn write_save(
+    w: impl Write,
+    header: &SaveHeader,
+    sections: impl Iterator<Item = (&'static str, Vec<u8>)>,
+    compression: SaveCompression,
+) -> io::Result<SaveSize> {
+    let (codec, level) = compression.codec();
+    write_save_with(w, header, sections, codec, level)
+}
+
+pub(crate) fn write_save_with(
+    w: impl Write,
+    header: &SaveHeader,
+    sections: impl Iterator<Item = (&'static str, Vec<u8>)>,
+    codec: Codec,
+    level: i32,
+) -> io::Result<SaveSize> {
+    let mut w = BufWriter::new(w);
+    w.write_all(MAGIC)?;
+    w.write_all(&FORMAT_VERSION.to_le_bytes())?;
+
+    let header = Bincode::encode(header)?;
+    let mut size = SaveSize {
+        uncompressed: header.len() as u64,
+        file: (MAGIC.len() + 4) as u64,
+    };
+    size.file += write_block(
+        &mut w,
+        HEADER_BLOCK,
+        Codec::Stored,
+        header.len() as u64,
+        &header,
+    )?;
+
+    let (tx, rx) = channel();
+    let mut n_sections = 0;
+    for (i, (name, raw)) in sections.enumerate() {
+        n_sections += 1;
+        size.uncompressed += raw.len() as u64;
+        let tx = tx.clone();
+        rayon::spawn(move || {
+            let compressed = codec.compress(&raw, level);
+            let _ = tx.send((i, name, raw.len() as u64, compressed));
+        });
+    }
+    drop(tx);
+
+    let mut order = InOrder::default();
+    for (i, name, raw_len, data) in rx {
+        for (name, raw_len, data) in order.push(i, (name, raw_len, data)) {
+            size.file += write_block(&mut w, name, codec, raw_len, &data?)?;
+        }
+    }
+    if order.next != n_sections {
+        return Err(io::Error::new(
+            ErrorKind::Other,
+            "a section could not be compressed",
+        ));
+    }
+
+    w.flush()?;
+    Ok(size)
+}
+
+/// Puts back in order the blocks coming from the thread pool
+struct InOrder<T> {
+    pending: BTreeMap<usize, T>,
+    /// Index of the next block to hand out
+    next: usize,
+}
+
+impl<T> Default for InOrder<T> {
+    fn default() -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            next: 0,
+        }
+    }
+}
+
+impl<T> InOrder<T> {
+    /// The blocks that can be handled now that block `i` arrived
+    fn push(&mut self, i: usize, v: T) -> Vec<T> {
+        self.pending.insert(i, v);
+        let mut ready = vec![];
+        while let Some(v) = self.pending.remove(&self.next) {
+            ready.push(v);
+            self.next += 1;
+        }
+        ready
+    }
+}
+
+/// Returns the number of bytes written
+fn write_block(
+    w: &mut impl Write,
+    name: &str,
+    codec: Codec,
+    raw_len: u64,
+    data: &[u8],
+) -> io::Result<u64> {
+    w.write_all(&(name.len() as u16).to_le_bytes())?;
+    w.write_all(name.as_bytes())?;
+    w.write_all(&[codec as u8])?;
+    w.write_all(&raw_len.to_le_bytes())?;
+    w.write_all(&(data.len() as u64).to_le_bytes())?;
+    w.write_all(data)?;
+    Ok((2 + name.len() + 1 + 8 + 8 + data.len()) as u64)
+}
+
+/// A save read from disk
+pub enum SaveFile {
+    /// Made before the block format, the whole zlib-compressed save
+    Legacy(Vec<u8>),
+    Blocks(SaveHeader),
+}
+
+/// Reads a save, giving the decompressed blocks after the header to `on_block` in file order.
+/// The blocks are decompressed on the thread pool while the earlier ones are handled.
+pub fn read_save(r: impl Read, mut on_block: impl FnMut(&str, Vec<u8>)) -> io::Result<SaveFile> {
+    let mut r = BufReader::new(r);
+    let mut data = vec![];
+    (&mut r).take(MAGIC.len() as u64).read_to_end(&mut data)?;
+    if data != MAGIC {
+        r.read_to_end(&mut data)?;
+        return Ok(SaveFile::Legacy(data));
+    }
+    let header = read_header_after_magic(&mut r)?;
+
+    let mut order = InOrder::default();
+    let mut handle = |name: String, raw: Result<Vec<u8>, String>| -> io::Result<()> {
+        let raw = raw.map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        on_block(&name, raw);
+        Ok(())
+    };
+
+    let (tx, rx) = channel();
+    let mut n_blocks = 0;
+    while let Some((name, codec, raw_len, data)) = read_block(&mut r)? {
+        let tx = tx.clone();
+        let i = n_blocks;
+        n_blocks += 1;
+        rayon::spawn(move || {
+            let raw = codec
+                .decompress(data, raw_len)
+                .map_err(|e| format!("could not decompress block {}: {}", name, e))
+                .and_then(|raw| {
+                    if raw.len() as u64 != raw_len {
+                        return Err(format!("block {} does not have the right size", name));
+                    }
+                    Ok(raw)
+                });
+            let _ = tx.send((i, name, raw));
+        });
+
+        // the first blocks are deserialized while the next ones are read
+        while let Ok((i, name, raw)) = rx.try_recv() {
+            for (name, raw) in order.push(i, (name, raw)) {
+                handle(name, raw)?;
+            }
+        }
+    }
+    drop(tx);
+
+    for (i, name, raw) in rx {
+        for (name, raw) in order.push(i, (name, raw)) {
+            handle(name, raw)?;
+        }
+    }
+    if order.next != n_blocks {
+        return Err(io::Error::new(
+            ErrorKind::Other,
+            "a block could not be decompressed",
+        ));
+    }
+
+    Ok(SaveFile::Blocks(header))
+}
+
+/// Only reads the beginning of the save, None if it was made before the block format
+pub fn read_header(r: impl Read) -> io::Result<Option<SaveHeader>> {
+    let mut r = BufReader::new(r);
+    let mut magic = vec![];
+    (&mut r).take(MAGIC.len() as u64).read_to_end(&mut magic)?;
+    if magic != MAGIC {
+        return Ok(None);
+    }
+    read_header_after_magic(&mut r).map(Some)
+}
+
+/// Header of the save at `path`, None if it is missing or was made before the block format
+pub fn read_header_from_disk(path: &str) -> Option<SaveHeader> {
+    let file = File::open(path).ok()?;
+    read_header(file)
+        .map_err(|e| log::error!("could not read the header of {}: {}", path, e))
+        .ok()?
+}
+
+fn read_header_after_magic(r: &mut impl Read) -> io::Result<SaveHeader> {
+    let mut version = [0; 4];
+    r.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version > FORMAT_VERSION {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("save format {} is newer than this game", version),
+        ));
+    }
+
+    match read_block(r)? {
+        Some((name, Codec::Stored, _, data)) if name == HEADER_BLOCK => Bincode::decode(&data),
+        _ => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "the save does not start with its header",
+        )),
+    }
+}
+
+/// (name, codec, uncompressed length, data), None at the end of the file
+fn read_block(r: &mut impl Read) -> io::Result<Option<(String, Codec, u64, Vec<u8>)>> {
+    let mut name_len = [0; 2];
+    match r.read_exact(&mut name_len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let name_len = u16::from_le_bytes(name_len) as usize;
+    if name_len > MAX_NAME_LEN {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "block name too long",
+        ));
+    }
+    let mut name = vec![0; name_len];
+    r.read_exact(&mut name)?;
+    let name = String::from_utf8(name)
+        .map_err(|_| io::Error::new(ErrorKind::InvalidData, "block name is not utf8"))?;
+
+    let mut codec = [0; 1];
+    r.read_exact(&mut codec)?;
+    let codec = Codec::from_byte(codec[0]).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("block {} has an unknown codec {}", name, codec[0]),
+        )
+    })?;
+    let mut lens = [0; 16];
+    r.read_exact(&mut lens)?;
+    let raw_len = u64::from_le_bytes(lens[..8].try_into().unwrap());
+    let data_len = u64::from_le_bytes(lens[8..].try_into().unwrap());
+    if raw_len > MAX_BLOCK_LEN || data_len > MAX_BLOCK_LEN {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("block {} is too big", name),
+        ));
+    }
+
+    // the length comes from the file, the buffer only grows with the bytes that are there
+    let mut data = vec![];
+    r.take(data_len).read_to_end(&mut data)?;
+    if data.len() as u64 != data_len {
+        return Err(io::Error::new(
+            ErrorKind::UnexpectedEof,
+            format!("block {} is truncated", name),
+        ));
+    }
+    Ok(Some((name, codec, raw_len, data)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> SaveHeader {
+        SaveHeader {
+            version: "1.2.3".to_string(),
+            tick: Tick(42),
+            mods: vec!["base".to_string()],
+            saved_at: 1234,
+        }
+    }
+
+    #[test]
+    fn blocks_come_back_in_order() {
+        let sections: Vec<(&'static str, Vec<u8>)> = vec![
+            ("entities", vec![1; 10_000]),
+            ("map", (0..50_000).map(|i| (i % 251) as u8).collect()),
+            ("empty", vec![]),
+        ];
+
+        for compression in SaveCompression::ALL {
+            let mut file = vec![];
+            let size = write_save(
+                &mut file,
+                &header(),
+                sections.clone().into_iter(),
+                compression,
+            )
+            .unwrap();
+            assert_eq!(size.file, file.len() as u64);
+            assert!(size.file < size.uncompressed);
+
+            assert_eq!(read_header(&*file).unwrap(), Some(header()));
+
+            let mut blocks = vec![];
+            let read =
+                read_save(&*file, |name, data| blocks.push((name.to_string(), data))).unwrap();
+            assert!(matches!(read, SaveFile::Blocks(h) if h == header()));
+            let expected: Vec<_> = sections
+                .iter()
+                .map(|(name, data)| (name.to_string(), data.clone()))
+                .collect();
+            assert_eq!(blocks, expected);
+        }
+    }
+
+    #[test]
+    fn old_saves_are_read_whole() {
+        let old = miniz_oxide::deflate::compress_to_vec_zlib(b"old save", 1);
+        assert_eq!(read_header(&*old).unwrap(), None);
+        let read = read_save(&*old, |_, _| panic!("old saves have no blocks")).unwrap();
+        assert!(matches!(read, SaveFile::Legacy(data) if data == old));
+    }
+
+    #[test]
+    fn truncated_saves_are_errors() {
+        let mut file = vec![];
+        write_save(
+            &mut file,
+            &header(),
+            [("entities", vec![7; 1000])].into_iter(),
+            SaveCompression::Fast,
+        )
+        .unwrap();
+        file.truncate(file.len() - 3);
+        assert!(read_save(&*file, |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn corrupted_lengths_are_errors() {
+        let mut file = vec![];
+        write_save(
+            &mut file,
+            &header(),
+            std::iter::empty(),
+            SaveCompression::Fast,
+        )
+        .unwrap();
+
+        // a block claiming the biggest length with only a few bytes behind it
+        let name = "entities";
+        file.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        file.extend_from_slice(name.as_bytes());
+        file.push(Codec::Stored as u8);
+        file.extend_from_slice(&MAX_BLOCK_LEN.to_le_bytes());
+        file.extend_from_slice(&MAX_BLOCK_LEN.to_le_bytes());
+        file.extend_from_slice(b"abc");
+
+        let err = read_save(&*file, |_, _| panic!("the block is truncated")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn deflate_blocks_still_load() {
+        let sections: Vec<(&'static str, Vec<u8>)> =
+            vec![("entities", vec![3; 10_000]), ("map", vec![])];
+        let mut file = vec![];
+        write_save_with(
+            &mut file,
+            &header(),
+            sections.clone().into_iter(),
+            Codec::Deflate,
+            1,
+        )
+        .unwrap();
+
+        let mut blocks = vec![];
+        read_save(&*file, |name, data| blocks.push((name.to_string(), data))).unwrap();
+        let expected: Vec<_> = sections
+            .into_iter()
+            .map(|(name, data)| (name.to_string(), data))
+            .collect();
+        assert_eq!(blocks, expected);
+    }
+
+    #[test]
+    fn unreadable_blocks_are_errors() {
+        let mut file = vec![];
+        write_save(
+            &mut file,
+            &header(),
+            std::iter::empty(),
+            SaveCompression::Fast,
+        )
+        .unwrap();
+        let with_header = file.len();
+
+        // not zstd data
+        write_block(&mut file, "entities", Codec::Zstd, 3, b"abc").unwrap();
+        assert_eq!(read_header(&*file).unwrap(), Some(header()));
+        let err = read_save(&*file, |_, _| panic!("the block can't be read")).unwrap_err();
+        assert!(err.to_string().contains("could not decompress"));
+
+        file.truncate(with_header);
+        write_block(&mut file, "entities", Codec::Stored, 3, b"abc").unwrap();
+        file[with_header + 2 + "entities".len()] = 7;
+        assert!(read_save(&*file, |_, _| {}).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::world::{CompanyEnt, HumanEnt, TrainEnt, VehicleEnt, WagonEnt};
+use crate::world::{CompanyEnt, DockEnt, HumanEnt, TrainEnt, VehicleEnt, WagonEnt, WarehouseEnt};
 use crate::{FreightStationEnt, ParCommandBuffer, Simulation};
 use common::history::History;
 use ordered_float::OrderedFloat;
@@ -48,6 +48,8 @@ impl SeqSchedule {
             ParCommandBuffer::<TrainEnt>::apply(sim);
             ParCommandBuffer::<WagonEnt>::apply(sim);
             ParCommandBuffer::<FreightStationEnt>::apply(sim);
+            ParCommandBuffer::<DockEnt>::apply(sim);
+            ParCommandBuffer::<WarehouseEnt>::apply(sim);
             ParCommandBuffer::<CompanyEnt>::apply(sim);
 
             let elapsed = start.elapsed();
@@ -3,10 +3,12 @@ use crate::map_dynamic::{
     DispatchID, Dispatcher, Itinerary, ItineraryFollower, ItineraryLeader, ParkingManagement,
     Router,
 };
-use crate::souls::desire::{BuyFood, Home, Work};
+use crate::souls::desire::{AttendEvent, BuyFood, Home, Work};
+use crate::souls::dock::Dock;
 use crate::souls::freight_station::FreightStation;
 use crate::souls::goods_company::GoodsCompanyState;
 use crate::souls::human::{HumanDecision, PersonalInfo};
+use crate::souls::warehouse::Warehouse;
 use crate::transportation::train::{Locomotive, LocomotiveReservation, RailWagon};
 use crate::transportation::{
     Location, Pedestrian, Speed, TransportGrid, Transporter, Vehicle, VehicleKind, VehicleState,
@@ -17,6 +19,7 @@ use crate::{impl_entity, impl_trans, SoulID};
 use common::iter::chain;
 use derive_more::{From, TryInto};
 use geom::{Transform, Vec2, Vec3};
+use prototypes::GameInstant;
 use serde::Deserialize;
 use slotmapd::__impl::Serialize;
 use slotmapd::{new_key_type, HopSlotMap};
@@ -28,6 +31,8 @@ new_key_type! {
     pub struct HumanID;
     pub struct WagonID;
     pub struct FreightStationID;
+    pub struct DockID;
+    pub struct WarehouseID;
     pub struct CompanyID;
 }
 
@@ -36,6 +41,8 @@ impl_entity!(HumanID, HumanEnt, humans);
 impl_entity!(TrainID, TrainEnt, trains);
 impl_entity!(WagonID, WagonEnt, wagons);
 impl_entity!(FreightStationID, FreightStationEnt, freight_stations);
+impl_entity!(DockID, DockEnt, docks);
+impl_entity!(WarehouseID, WarehouseEnt, warehouses);
 impl_entity!(CompanyID, CompanyEnt, companies);
 
 impl_trans!(HumanID);
@@ -43,14 +50,18 @@ impl_trans!(VehicleID);
 impl_trans!(TrainID);
 impl_trans!(WagonID);
 impl_trans!(FreightStationID);
+impl_trans!(DockID);
+impl_trans!(WarehouseID);
 impl_trans!(CompanyID);
 
-#[derive(PartialEq, Eq, Copy, Clone, Debug, From, TryInto)]
+#[derive(PartialEq, Eq, Copy, Clone, Debug, From, TryInto, Serialize, Deserialize)]
 pub enum AnyEntity {
     VehicleID(VehicleID),
     TrainID(TrainID),
     WagonID(WagonID),
     FreightStationID(FreightStationID),
+    DockID(DockID),
+    WarehouseID(WarehouseID),
     CompanyID(CompanyID),
     HumanID(HumanID),
 }
@@ -99,6 +110,11 @@ pub struct HumanEnt {
     pub food: BuyFood,
     pub bought: Bought,
     pub work: Option<Work>,
+    /// Event the human is invited to, until it ends
+    #[serde(default)]
+    pub event: Option<AttendEvent>,
+    /// When the human lost their job or moved in without one, None while they have a job
+    pub unemployed_since: Option<GameInstant>,
 
     pub personal_info: Box<PersonalInfo>,
 }
@@ -164,6 +180,30 @@ impl SimDrop for FreightStationEnt {
     }
 }
 
+#[derive(Inspect, Serialize, Deserialize)]
+pub struct DockEnt {
+    pub trans: Transform,
+    pub d: Dock,
+}
+
+impl SimDrop for DockEnt {
+    fn sim_drop(self, id: DockID, res: &mut Resources) {
+        res.write::<Market>().remove(SoulID::Dock(id));
+    }
+}
+
+#[derive(Inspect, Serialize, Deserialize)]
+pub struct WarehouseEnt {
+    pub trans: Transform,
+    pub w: Warehouse,
+}
+
+impl SimDrop for WarehouseEnt {
+    fn sim_drop(self, id: WarehouseID, res: &mut Resources) {
+        res.write::<Market>().remove(SoulID::Warehouse(id));
+    }
+}
+
 #[derive(Inspect, Serialize, Deserialize)]
 pub struct CompanyEnt {
     pub trans: Transform,
@@ -186,6 +226,8 @@ pub struct World {
     pub trains: HopSlotMap<TrainID, TrainEnt>,
     pub wagons: HopSlotMap<WagonID, WagonEnt>,
     pub freight_stations: HopSlotMap<FreightStationID, FreightStationEnt>,
+    pub docks: HopSlotMap<DockID, DockEnt>,
+    pub warehouses: HopSlotMap<WarehouseID, WarehouseEnt>,
     pub companies: HopSlotMap<CompanyID, CompanyEnt>,
 }
 
@@ -212,6 +254,8 @@ impl World {
             AnyEntity::TrainID(id) => self.storage_id(id).contains_key(id),
             AnyEntity::WagonID(id) => self.storage_id(id).contains_key(id),
             AnyEntity::FreightStationID(id) => self.storage_id(id).contains_key(id),
+            AnyEntity::DockID(id) => self.storage_id(id).contains_key(id),
+            AnyEntity::WarehouseID(id) => self.storage_id(id).contains_key(id),
             AnyEntity::CompanyID(id) => self.storage_id(id).contains_key(id),
             AnyEntity::HumanID(id) => self.storage_id(id).contains_key(id),
         }
@@ -297,6 +341,8 @@ impl World {
                 self.freight_stations
                     .keys()
                     .map(AnyEntity::FreightStationID),
+                self.docks.keys().map(AnyEntity::DockID),
+                self.warehouses.keys().map(AnyEntity::WarehouseID),
                 self.companies.keys().map(AnyEntity::CompanyID),
             )),
         ))
@@ -365,6 +411,8 @@ impl Display for AnyEntity {
             AnyEntity::TrainID(id) => write!(f, "{:?}", id),
             AnyEntity::WagonID(id) => write!(f, "{:?}", id),
             AnyEntity::FreightStationID(id) => write!(f, "{:?}", id),
+            AnyEntity::DockID(id) => write!(f, "{:?}", id),
+            AnyEntity::WarehouseID(id) => write!(f, "{:?}", id),
             AnyEntity::CompanyID(id) => write!(f, "{:?}", id),
         }
     }
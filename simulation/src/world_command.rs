@@ -4,33 +4,51 @@ use std::time::Instant;
 use prototypes::RollingStockID;
 use serde::{Deserialize, Serialize};
 
-use geom::{vec3, Vec2, Vec3, OBB};
+use geom::{vec3, Circle, Color, Polygon, Vec2, Vec3, OBB};
 use prototypes::BuildingGen;
 use prototypes::GameTime;
+use prototypes::ItemID;
+use prototypes::Money;
+use prototypes::{ModSettingValue, ModSettings, RestrictionKind};
 use WorldCommand::*;
 
-use crate::economy::Government;
+use crate::economy::{Government, GovernmentOrderID, GovernmentOrders, Market, OrderSide};
+use crate::event_log::{log_event, EventCategory, EventSubject, Severity};
+use crate::map::procgen::osm::{load_imported, ImportedMap};
 use crate::map::procgen::{load_parismap, load_testfield};
 use crate::map::{
-    BuildingID, BuildingKind, Environment, IntersectionID, LaneID, LanePattern, LanePatternBuilder,
-    LightPolicy, LotID, Map, MapProject, ProjectKind, RoadID, TerraformKind, TurnPolicy, Zone,
+    generate_deposits, BuildingID, BuildingKind, DistrictID, Environment, IntersectionID, LaneID,
+    LanePattern, LanePatternBuilder, LightPolicy, LotID, LotKind, Map, MapProject, ProjectFilter,
+    ProjectKind, RestrictedAction, Restriction, RestrictionID, RoadID, RoadworksKind,
+    TerraformKind, TurnPolicy, Zone, ZoneBrush,
 };
-use crate::map_dynamic::{BuildingInfos, ParkingManagement};
-use crate::multiplayer::chat::Message;
+use crate::map_dynamic::{BuildingInfos, ParkingManagement, RoadMaintenance};
+use crate::multiplayer::chat::{Message, MessageKind};
 use crate::multiplayer::MultiplayerState;
-use crate::transportation::testing_vehicles::RandomVehicles;
+use crate::rules::GameRules;
+use crate::scenario::{start_scenario, ScenarioState};
+use crate::souls::goods_company::{company_upgrade, upgrade_company};
+use crate::souls::household::pick_car_model;
+use crate::souls::sampling::{start_sampling, CitizenSampling};
+use crate::souls::warehouse::{set_stock_rules, StockRule};
+use crate::souls::welfare::{Welfare, WelfarePolicy};
+use crate::transportation::incident::break_down_near;
+use crate::transportation::service_fleet::{buy_vehicle, sell_vehicle};
+use crate::transportation::testing_vehicles::{spawn_car_models, RandomVehicles};
 use crate::transportation::train::{spawn_train, RailWagonKind};
+use crate::transportation::waterway::dock_mooring;
 use crate::transportation::{spawn_parked_vehicle_with_spot, unpark, VehicleKind};
 use crate::utils::rand_provider::RandProvider;
+use crate::world::HumanID;
 use crate::{Replay, Simulation, SimulationOptions};
 
+pub mod wire;
+
 #[derive(Clone, Default)]
 pub struct WorldCommands {
     pub(crate) commands: Vec<WorldCommand>,
 }
 
-defer_serialize!(WorldCommands, Vec<WorldCommand>);
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WorldCommand {
     Init(Box<SimulationOptions>),
@@ -38,6 +56,11 @@ pub enum WorldCommand {
     MapRemoveRoad(RoadID),
     MapRemoveBuilding(BuildingID),
     MapBuildHouse(LotID),
+    UpgradeBuilding(BuildingID),
+    MapPaintZone {
+        brush: ZoneBrush,
+        kind: LotKind,
+    },
     Terraform {
         kind: TerraformKind,
         center: Vec2,
@@ -52,6 +75,10 @@ pub enum WorldCommand {
     SpawnRandomCars {
         n_cars: usize,
     },
+    /// Parks one car of each model near the position
+    SpawnCarModels {
+        pos: Vec3,
+    },
     AddTrain {
         dist: f32,
         n_wagons: u32,
@@ -92,11 +119,76 @@ pub enum WorldCommand {
         size: u32,
         spacing: f32,
     },
+    /// Roads, lakes and forests imported from an OpenStreetMap extract
+    MapImport(Box<ImportedMap>),
     UpdateZone {
         building: BuildingID,
         zone: Zone,
     },
     SetGameTime(GameTime),
+    SetRoadMaintenanceBudget(Money),
+    SetGameRules(GameRules),
+    RenameRoad {
+        road: RoadID,
+        name: String,
+    },
+    SetRoadElectrified {
+        road: RoadID,
+        electrified: bool,
+    },
+    /// Same limit for all the roads, e.g. a corridor dragged over
+    SetSpeedLimit {
+        roads: Vec<RoadID>,
+        limit: f32,
+    },
+    SetModSetting {
+        mod_name: String,
+        key: String,
+        value: ModSettingValue,
+    },
+    StartCitizenSampling {
+        count: u32,
+        seed: u64,
+    },
+    SampleCitizen(HumanID),
+    SetStockRules {
+        building: BuildingID,
+        rules: Vec<StockRule>,
+    },
+    SetWelfarePolicy(WelfarePolicy),
+    /// Breaks down the vehicle closest to the position
+    CreateIncident {
+        pos: Vec3,
+    },
+    /// Adds a vehicle to the fleet of a service depot
+    BuyServiceVehicle(BuildingID),
+    /// Sells a vehicle waiting in a service depot
+    SellServiceVehicle(BuildingID),
+    MapAddDistrict {
+        name: String,
+        color: Color,
+        shape: Polygon,
+    },
+    MapUpdateDistrict {
+        district: DistrictID,
+        name: String,
+        color: Color,
+    },
+    MapRemoveDistrict(DistrictID),
+    /// Standing order of the government on the market, funded by the budget
+    PlaceGovernmentOrder {
+        item: ItemID,
+        side: OrderSide,
+        price: Money,
+        qty: u32,
+    },
+    CancelGovernmentOrder(GovernmentOrderID),
+    /// Only applied in sandbox games, the scenarios place their restrictions themselves
+    MapAddRestriction {
+        kind: RestrictionKind,
+        shape: Polygon,
+    },
+    MapRemoveRestriction(RestrictionID),
 }
 
 impl AsRef<[WorldCommand]> for WorldCommands {
@@ -118,6 +210,21 @@ impl WorldCommands {
         self.commands.extend_from_slice(&src.commands);
     }
 
+    /// Merges the consecutive commands that have the effect of a single one, like the values
+    /// of a dragged slider or a terraforming brush held still, before they are sent
+    pub fn coalesce(&mut self) {
+        let mut coalesced: Vec<WorldCommand> = Vec::with_capacity(self.commands.len());
+        for cmd in self.commands.drain(..) {
+            if let Some(last) = coalesced.last_mut() {
+                if last.coalesce(&cmd) {
+                    continue;
+                }
+            }
+            coalesced.push(cmd);
+        }
+        self.commands = coalesced;
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &WorldCommand> {
         self.commands.iter()
     }
@@ -138,6 +245,101 @@ impl WorldCommands {
         self.commands.push(SetGameTime(gt))
     }
 
+    pub fn set_road_maintenance_budget(&mut self, budget: Money) {
+        self.commands.push(SetRoadMaintenanceBudget(budget))
+    }
+
+    pub fn set_game_rules(&mut self, rules: GameRules) {
+        self.commands.push(SetGameRules(rules))
+    }
+
+    pub fn rename_road(&mut self, road: RoadID, name: String) {
+        self.commands.push(RenameRoad { road, name })
+    }
+
+    pub fn set_mod_setting(&mut self, mod_name: String, key: String, value: ModSettingValue) {
+        self.commands.push(SetModSetting {
+            mod_name,
+            key,
+            value,
+        })
+    }
+
+    pub fn set_road_electrified(&mut self, road: RoadID, electrified: bool) {
+        self.commands.push(SetRoadElectrified { road, electrified })
+    }
+
+    pub fn set_speed_limit(&mut self, roads: Vec<RoadID>, limit: f32) {
+        self.commands.push(SetSpeedLimit { roads, limit })
+    }
+
+    pub fn map_add_district(&mut self, name: String, color: Color, shape: Polygon) {
+        self.commands.push(MapAddDistrict { name, color, shape })
+    }
+
+    pub fn map_update_district(&mut self, district: DistrictID, name: String, color: Color) {
+        self.commands.push(MapUpdateDistrict {
+            district,
+            name,
+            color,
+        })
+    }
+
+    pub fn map_remove_district(&mut self, district: DistrictID) {
+        self.commands.push(MapRemoveDistrict(district))
+    }
+
+    pub fn map_add_restriction(&mut self, kind: RestrictionKind, shape: Polygon) {
+        self.commands.push(MapAddRestriction { kind, shape })
+    }
+
+    pub fn map_remove_restriction(&mut self, restriction: RestrictionID) {
+        self.commands.push(MapRemoveRestriction(restriction))
+    }
+
+    pub fn start_citizen_sampling(&mut self, count: u32, seed: u64) {
+        self.commands.push(StartCitizenSampling { count, seed })
+    }
+
+    pub fn sample_citizen(&mut self, human: HumanID) {
+        self.commands.push(SampleCitizen(human))
+    }
+
+    pub fn set_stock_rules(&mut self, building: BuildingID, rules: Vec<StockRule>) {
+        self.commands.push(SetStockRules { building, rules })
+    }
+
+    pub fn set_welfare_policy(&mut self, policy: WelfarePolicy) {
+        self.commands.push(SetWelfarePolicy(policy))
+    }
+
+    pub fn buy_service_vehicle(&mut self, depot: BuildingID) {
+        self.commands.push(BuyServiceVehicle(depot))
+    }
+
+    pub fn sell_service_vehicle(&mut self, depot: BuildingID) {
+        self.commands.push(SellServiceVehicle(depot))
+    }
+
+    pub fn place_government_order(
+        &mut self,
+        item: ItemID,
+        side: OrderSide,
+        price: Money,
+        qty: u32,
+    ) {
+        self.commands.push(PlaceGovernmentOrder {
+            item,
+            side,
+            price,
+            qty,
+        })
+    }
+
+    pub fn cancel_government_order(&mut self, order: GovernmentOrderID) {
+        self.commands.push(CancelGovernmentOrder(order))
+    }
+
     pub fn add_train(&mut self, dist: f32, n_wagons: u32, laneid: LaneID) {
         self.commands.push(AddTrain {
             dist,
@@ -179,6 +381,14 @@ impl WorldCommands {
         self.commands.push(MapBuildHouse(id))
     }
 
+    pub fn upgrade_building(&mut self, id: BuildingID) {
+        self.commands.push(UpgradeBuilding(id))
+    }
+
+    pub fn map_paint_zone(&mut self, brush: ZoneBrush, kind: LotKind) {
+        self.commands.push(MapPaintZone { brush, kind })
+    }
+
     pub fn map_make_connection(
         &mut self,
         from: MapProject,
@@ -214,14 +424,263 @@ impl WorldCommand {
         matches!(
             self,
             MapBuildHouse(_)
+                | MapPaintZone { .. }
                 | MapUpdateIntersectionPolicy { .. }
                 | UpdateZone { .. }
                 | SetGameTime(_)
+                | SetRoadMaintenanceBudget(_)
+                | SetGameRules(_)
+                | RenameRoad { .. }
+                | SetRoadElectrified { .. }
+                | SetSpeedLimit { .. }
+                | MapAddDistrict { .. }
+                | MapUpdateDistrict { .. }
+                | MapRemoveDistrict(_)
+                | MapAddRestriction { .. }
+                | MapRemoveRestriction(_)
+                | SetModSetting { .. }
+                | StartCitizenSampling { .. }
+                | SampleCitizen(_)
+                | SetStockRules { .. }
+                | SetWelfarePolicy(_)
+                | BuyServiceVehicle(_)
+                | SellServiceVehicle(_)
+                | PlaceGovernmentOrder { .. }
+                | CancelGovernmentOrder(_)
         )
     }
 
+    /// The restricted area forbidding the command, None if nothing stands in its way
+    pub fn restricted_by<'a>(&self, map: &'a Map) -> Option<&'a Restriction> {
+        let (build, demolish) = (RestrictedAction::Build, RestrictedAction::Demolish);
+        match *self {
+            MapBuildSpecialBuilding { pos, ref zone, .. } => {
+                map.restriction_on(&pos, build).or_else(|| {
+                    zone.as_ref()
+                        .and_then(|z| map.restriction_on(&z.poly, build))
+                })
+            }
+            MapBuildHouse(lot) => map.restriction_on(&map.lots().get(lot)?.shape, build),
+            UpgradeBuilding(b) => map.restriction_on(&map.buildings().get(b)?.obb, build),
+            UpdateZone { ref zone, .. } => map.restriction_on(&zone.poly, build),
+            MapMakeConnection {
+                from, to, inter, ..
+            } => map.restriction_on_connection(from.pos, to.pos, inter),
+            MapMakeMultipleConnections(ref projects, ref links) => {
+                links.iter().find_map(|&(from, to, inter, _)| {
+                    map.restriction_on_connection(
+                        projects.get(from)?.pos,
+                        projects.get(to)?.pos,
+                        inter,
+                    )
+                })
+            }
+            MapRemoveRoad(id) => map.restriction_on_path(&map.roads().get(id)?.points, demolish),
+            MapRemoveIntersection(id) => {
+                map.restriction_on(&map.intersections().get(id)?.pos.xy(), demolish)
+            }
+            MapRemoveBuilding(id) => map.restriction_on(&map.buildings().get(id)?.obb, demolish),
+            Terraform {
+                kind,
+                center,
+                radius,
+                ..
+            } if kind != TerraformKind::Plant => {
+                map.restriction_on(&Circle::new(center, radius), RestrictedAction::Terraform)
+            }
+            _ => None,
+        }
+    }
+
+    /// Merges the next command into this one if applying the result is the same as applying both
+    fn coalesce(&mut self, next: &WorldCommand) -> bool {
+        let replaced = match (&*self, next) {
+            (SetGameTime(_), SetGameTime(_))
+            | (SetRoadMaintenanceBudget(_), SetRoadMaintenanceBudget(_))
+            | (SetWelfarePolicy(_), SetWelfarePolicy(_)) => true,
+            (
+                SetModSetting { mod_name, key, .. },
+                SetModSetting {
+                    mod_name: next_mod_name,
+                    key: next_key,
+                    ..
+                },
+            ) => mod_name == next_mod_name && key == next_key,
+            (
+                SetRoadElectrified { road, .. },
+                SetRoadElectrified {
+                    road: next_road, ..
+                },
+            ) => road == next_road,
+            (
+                SetSpeedLimit { roads, .. },
+                SetSpeedLimit {
+                    roads: next_roads, ..
+                },
+            ) => roads == next_roads,
+            (
+                MapUpdateDistrict { district, .. },
+                MapUpdateDistrict {
+                    district: next_district,
+                    ..
+                },
+            ) => district == next_district,
+            (
+                SetStockRules { building, .. },
+                SetStockRules {
+                    building: next_building,
+                    ..
+                },
+            ) => building == next_building,
+            (
+                MapUpdateIntersectionPolicy { inter, .. },
+                MapUpdateIntersectionPolicy {
+                    inter: next_inter, ..
+                },
+            ) => inter == next_inter,
+            _ => false,
+        };
+        if replaced {
+            *self = next.clone();
+            return true;
+        }
+
+        // raising the terrain is linear in the amount
+        if let (
+            Terraform {
+                kind: TerraformKind::Elevation,
+                center,
+                radius,
+                amount,
+                ..
+            },
+            Terraform {
+                kind: TerraformKind::Elevation,
+                center: next_center,
+                radius: next_radius,
+                amount: next_amount,
+                ..
+            },
+        ) = (self, next)
+        {
+            if center == next_center && radius == next_radius {
+                *amount += next_amount;
+                return true;
+            }
+        }
+        false
+    }
+
     pub fn apply(&self, sim: &mut Simulation) {
-        let cost = Government::action_cost(self, sim);
+        if let MapBuildSpecialBuilding { kind, .. } = *self {
+            let population = sim.world.humans.len() as u32;
+            let reason = sim.read::<ScenarioState>().locked_reason(kind, population);
+            if let Some(reason) = reason {
+                info!("rejected {:?}: not unlocked", self);
+                sim.write::<MultiplayerState>().chat.add_message(Message {
+                    name: "Construction".to_string(),
+                    text: format!("{} is not unlocked yet: {}", kind.label(), reason),
+                    sent_at: sim.read::<GameTime>().instant(),
+                    color: crate::colors().gui_danger,
+                    kind: MessageKind::Warning,
+                });
+                return;
+            }
+        }
+
+        if let MapBuildSpecialBuilding {
+            kind: BuildingKind::Dock(dock),
+            pos,
+            ..
+        } = *self
+        {
+            if dock_mooring(&sim.map().environment, &pos).is_none() {
+                info!("rejected {:?}: no navigable water nearby", self);
+                sim.write::<MultiplayerState>().chat.add_message(Message {
+                    name: "Construction".to_string(),
+                    text: format!("{} needs navigable water nearby", dock.prototype().label),
+                    sent_at: sim.read::<GameTime>().instant(),
+                    color: crate::colors().gui_danger,
+                    kind: MessageKind::Warning,
+                });
+                return;
+            }
+        }
+
+        if let MapBuildSpecialBuilding {
+            kind: BuildingKind::GoodsCompany(comp),
+            pos,
+            ..
+        } = *self
+        {
+            let proto = comp.prototype();
+            if let Some(deposit) = proto.deposit {
+                if !sim.map().can_extract(&pos, deposit) {
+                    info!("rejected {:?}: no {:?} deposit there", self, deposit);
+                    sim.write::<MultiplayerState>().chat.add_message(Message {
+                        name: "Construction".to_string(),
+                        text: format!(
+                            "{} must be built on a {} deposit",
+                            proto.label,
+                            deposit.prototype().label
+                        ),
+                        sent_at: sim.read::<GameTime>().instant(),
+                        color: crate::colors().gui_danger,
+                        kind: MessageKind::Warning,
+                    });
+                    return;
+                }
+            }
+        }
+
+        let restricted = self.restricted_by(&sim.map()).map(Restriction::reason);
+        if let Some(reason) = restricted {
+            info!("rejected {:?}: {}", self, reason);
+            // the terraforming brush sends a command every frame, its tool shows the reason instead
+            if !matches!(self, Terraform { .. }) {
+                sim.write::<MultiplayerState>().chat.add_message(Message {
+                    name: "Construction".to_string(),
+                    text: reason,
+                    sent_at: sim.read::<GameTime>().instant(),
+                    color: crate::colors().gui_danger,
+                    kind: MessageKind::Warning,
+                });
+            }
+            return;
+        }
+
+        if let UpgradeBuilding(building) = *self {
+            if let Some((_, to)) = company_upgrade(sim, building) {
+                if !sim.read::<ScenarioState>().is_unlocked(to.base.id) {
+                    info!("rejected {:?}: not unlocked", self);
+                    sim.write::<MultiplayerState>().chat.add_message(Message {
+                        name: "Scenario".to_string(),
+                        text: format!("{} is not unlocked yet", to.label),
+                        sent_at: sim.read::<GameTime>().instant(),
+                        color: crate::colors().gui_danger,
+                        kind: MessageKind::Warning,
+                    });
+                    return;
+                }
+            }
+        }
+
+        let cost = self.cost(sim);
+        let money = sim.read::<Government>().money;
+        if cost > Money::ZERO && cost > money {
+            info!(
+                "rejected {:?}: costs {} but only {} available",
+                self, cost, money
+            );
+            sim.write::<MultiplayerState>().chat.add_message(Message {
+                name: "Government".to_string(),
+                text: format!("Not enough money: costs {cost} but only {money} available"),
+                sent_at: sim.read::<GameTime>().instant(),
+                color: crate::colors().gui_danger,
+                kind: MessageKind::Warning,
+            });
+            return;
+        }
         sim.write::<Government>().money -= cost;
 
         let mut rep = sim.resources.write::<Replay>();
@@ -241,15 +700,34 @@ impl WorldCommand {
                     infos.insert(build);
                 }
             }
+            MapPaintZone { brush, kind } => sim.map_mut().paint_zone(brush, kind),
+            UpgradeBuilding(id) => {
+                if !upgrade_company(sim, id) {
+                    sim.write::<Government>().money += cost;
+                    sim.write::<MultiplayerState>().chat.add_message(Message {
+                        name: "Government".to_string(),
+                        text:
+                            "Could not upgrade the building: conditions not met or not enough room"
+                                .to_string(),
+                        sent_at: sim.read::<GameTime>().instant(),
+                        color: crate::colors().gui_danger,
+                        kind: MessageKind::Warning,
+                    });
+                }
+            }
             MapMakeConnection {
                 from,
                 to,
                 inter,
                 ref pat,
             } => {
-                sim.write::<Map>().make_connection(from, to, inter, pat);
+                let built = sim.write::<Map>().make_connection(from, to, inter, pat);
+                if let Some((_, road)) = built {
+                    start_roadworks(sim, road, RoadworksKind::Construction);
+                }
             }
             MapMakeMultipleConnections(ref projects, ref links) => {
+                let instant = sim.read::<GameRules>().builds_instantly();
                 let mut map = sim.map_mut();
                 let mut inters = BTreeMap::new();
                 for (from, to, interpoint, pat) in links {
@@ -262,13 +740,22 @@ impl WorldCommand {
                     if let Some(i) = inters.get(to) {
                         toproj.kind = ProjectKind::Inter(*i);
                     }
+                    // an earlier connection may have split the road this one starts from
+                    for proj in [&mut fromproj, &mut toproj] {
+                        if !proj.kind.check_valid(&map) {
+                            *proj = map.project(
+                                proj.pos,
+                                1.0,
+                                ProjectFilter::INTER | ProjectFilter::ROAD,
+                            );
+                        }
+                    }
 
                     if let Some((_, r)) = map.make_connection(fromproj, toproj, *interpoint, pat) {
-                        if fromproj.kind.is_ground() {
-                            inters.insert(*from, map.roads[r].src);
-                        }
-                        if toproj.kind.is_ground() {
-                            inters.insert(*to, map.roads[r].dst);
+                        inters.insert(*from, map.roads[r].src);
+                        inters.insert(*to, map.roads[r].dst);
+                        if !instant {
+                            map.start_roadworks(r, RoadworksKind::Construction);
                         }
                     }
                 }
@@ -288,17 +775,109 @@ impl WorldCommand {
                 ref zone,
                 connected_road,
             } => {
-                if let Some(id) = sim.write::<Map>().build_special_building(
+                let built = sim.write::<Map>().build_special_building(
                     &obb,
                     kind,
                     gen,
                     zone.clone(),
                     connected_road,
-                ) {
+                );
+                if let Some(id) = built {
                     sim.write::<BuildingInfos>().insert(id);
+                    log_event(
+                        sim,
+                        EventCategory::Construction,
+                        Severity::Info,
+                        format!("{} completed", kind.label()),
+                        Some(EventSubject::Building(id)),
+                    );
                 }
             }
             SetGameTime(gt) => *sim.write::<GameTime>() = gt,
+            SetRoadMaintenanceBudget(budget) => {
+                sim.write::<RoadMaintenance>().daily_budget = budget;
+            }
+            SetGameRules(ref rules) => {
+                if !sim.write::<GameRules>().change(rules) {
+                    info!("rejected {:?}: rules cannot be changed in this game", self);
+                }
+            }
+            RenameRoad { road, ref name } => sim.map_mut().rename_road(road, name),
+            SetRoadElectrified { road, electrified } => {
+                let was = sim.map().roads().get(road).map(|r| r.electrified);
+                sim.map_mut().set_road_electrified(road, electrified);
+                if electrified && was == Some(false) {
+                    start_roadworks(sim, road, RoadworksKind::Electrification);
+                }
+                // the line came down before it was done
+                let mut map = sim.map_mut();
+                let works = map.roads().get(road).and_then(|r| r.roadworks.as_ref());
+                if !electrified && works.is_some_and(|w| w.kind == RoadworksKind::Electrification) {
+                    map.finish_roadworks(road);
+                }
+            }
+            SetSpeedLimit { ref roads, limit } => {
+                let mut map = sim.map_mut();
+                for &road in roads {
+                    map.set_road_speed_limit(road, limit);
+                }
+            }
+            MapAddDistrict {
+                ref name,
+                color,
+                ref shape,
+            } => {
+                sim.map_mut().add_district(name, color, shape.clone());
+            }
+            MapUpdateDistrict {
+                district,
+                ref name,
+                color,
+            } => sim.map_mut().update_district(district, name, color),
+            MapRemoveDistrict(district) => {
+                sim.map_mut().remove_district(district);
+            }
+            MapAddRestriction { kind, ref shape } => {
+                if sim.read::<GameRules>().sandbox {
+                    sim.map_mut().add_restriction(kind, shape.clone(), "");
+                } else {
+                    info!(
+                        "rejected {:?}: restrictions are only edited in sandbox games",
+                        self
+                    );
+                }
+            }
+            MapRemoveRestriction(restriction) => {
+                if sim.read::<GameRules>().sandbox {
+                    sim.map_mut().remove_restriction(restriction);
+                } else {
+                    info!(
+                        "rejected {:?}: restrictions are only edited in sandbox games",
+                        self
+                    );
+                }
+            }
+            SetModSetting {
+                ref mod_name,
+                ref key,
+                ref value,
+            } => {
+                if !sim.write::<ModSettings>().set(mod_name, key, value) {
+                    info!("rejected {:?}: the loaded mods do not declare it", self);
+                }
+            }
+            StartCitizenSampling { count, seed } => start_sampling(sim, count, seed),
+            SampleCitizen(human) => {
+                let mut sampling = sim.write::<CitizenSampling>();
+                if !sampling.requested.contains(&human) {
+                    sampling.requested.push(human);
+                }
+            }
+            SetStockRules {
+                building,
+                ref rules,
+            } => set_stock_rules(sim, building, rules),
+            SetWelfarePolicy(ref policy) => sim.write::<Welfare>().policy = policy.clone(),
             AddTrain {
                 dist: _,
                 n_wagons: _,
@@ -316,6 +895,10 @@ impl WorldCommand {
             MapLoadTestField { pos, size, spacing } => {
                 load_testfield(&mut sim.map_mut(), pos, size, spacing)
             }
+            MapImport(ref imported) => {
+                let tick = sim.read::<GameTime>().tick;
+                load_imported(&mut sim.map_mut(), tick, imported)
+            }
             Init(ref opts) => {
                 if opts.save_replay {
                     let mut rep = sim.resources.write::<Replay>();
@@ -325,7 +908,17 @@ impl WorldCommand {
                 }
 
                 if opts.terrain_size > 0 {
-                    generate_terrain(sim, opts.terrain_size);
+                    generate_terrain(sim, opts.terrain_size, opts.deposit_abundance);
+                }
+
+                sim.write::<Government>().money = opts.rules.starting_money;
+                *sim.write::<GameRules>() = opts.rules.clone();
+                let mut mod_settings = opts.mod_settings.clone();
+                mod_settings.sanitize_loaded();
+                *sim.write::<ModSettings>() = mod_settings;
+
+                if let Some(scenario) = opts.scenario {
+                    start_scenario(sim, scenario);
                 }
 
                 sim.resources
@@ -334,7 +927,12 @@ impl WorldCommand {
             UpdateZone { building, ref zone } => {
                 let mut map = sim.map_mut();
 
-                map.update_zone(building, move |z| *z = zone.clone());
+                map.update_zone(building, move |z| {
+                    // the crop keeps growing on the new shape
+                    let stage = z.stage;
+                    *z = zone.clone();
+                    z.stage = stage;
+                });
             }
             SpawnRandomCars { n_cars } => {
                 for _ in 0..n_cars {
@@ -346,10 +944,13 @@ impl WorldCommand {
                     else {
                         continue;
                     };
+                    // random traffic drives any model, as often as they are popular
+                    let model = pick_car_model(Money::MAX, Money::ZERO, rng.next_u64());
 
                     drop((map, pm, rng));
 
-                    let Some(v_id) = spawn_parked_vehicle_with_spot(sim, VehicleKind::Car, spot)
+                    let Some(v_id) =
+                        spawn_parked_vehicle_with_spot(sim, VehicleKind::Car, model, spot)
                     else {
                         continue;
                     };
@@ -358,6 +959,41 @@ impl WorldCommand {
                     sim.write::<RandomVehicles>().vehicles.insert(v_id);
                 }
             }
+            SpawnCarModels { pos } => {
+                spawn_car_models(sim, pos);
+            }
+            CreateIncident { pos } => {
+                break_down_near(sim, pos);
+            }
+            BuyServiceVehicle(depot) => {
+                if !buy_vehicle(sim, depot) {
+                    sim.write::<Government>().money += cost;
+                }
+            }
+            SellServiceVehicle(depot) => {
+                if !sell_vehicle(sim, depot) {
+                    sim.write::<Government>().money += cost;
+                }
+            }
+            PlaceGovernmentOrder {
+                item,
+                side,
+                price,
+                qty,
+            } => {
+                // jobs are traded on the market too, but not by the government
+                let tradable = item != ItemID::new("job-opening")
+                    && sim.read::<Market>().inner().contains_key(&item);
+                if tradable {
+                    sim.write::<GovernmentOrders>()
+                        .place(item, side, price, qty);
+                } else {
+                    info!("rejected {:?}: the item is not traded on the market", self);
+                }
+            }
+            CancelGovernmentOrder(order) => {
+                sim.write::<GovernmentOrders>().cancel(order);
+            }
             SendMessage { ref message } => {
                 sim.write::<MultiplayerState>()
                     .chat
@@ -376,14 +1012,38 @@ impl WorldCommand {
                     .terraform(tick, kind, center, radius, amount, level, slope);
             }
         }
+
+        if matches!(
+            self,
+            MapMakeConnection { .. }
+                | MapMakeMultipleConnections(..)
+                | MapLoadParis
+                | MapLoadTestField { .. }
+                | MapImport(_)
+        ) {
+            let mut rng = sim.write::<RandProvider>();
+            sim.map_mut().name_new_roads(&mut rng);
+        }
+    }
+}
+
+/// Puts the road under works, unless the rules want it done right away
+fn start_roadworks(sim: &mut Simulation, road: RoadID, kind: RoadworksKind) {
+    if sim.read::<GameRules>().builds_instantly() {
+        return;
     }
+    sim.map_mut().start_roadworks(road, kind);
 }
 
-fn generate_terrain(sim: &mut Simulation, size: u16) {
+fn generate_terrain(sim: &mut Simulation, size: u16, deposit_abundance: f32) {
     info!("generating terrain..");
     let t = Instant::now();
 
-    sim.map_mut().environment = Environment::new(size, size);
+    let mut map = sim.map_mut();
+    map.environment = Environment::new(size, size);
+    // the terrain is the same for a given size, so are the deposits
+    map.deposits = generate_deposits(&map.environment, deposit_abundance, size as u64);
+    drop(map);
     info!("took {}s", t.elapsed().as_secs_f32());
 
     let c = vec3(3000.0 + 72.2 / 2.0, 200.0 / 2.0 + 1.0, 0.0);
@@ -0,0 +1,944 @@
+//! Compact encoding of the commands sent to the other players each frame.
+//! Every variant has its own tag and writes its fields one after the other: integers and ids
+//! are variable length, floats are written as is and the fields of nested types go through
+//! bincode. The tags are fixed so reordering the variants does not change the format.
+//! Each packet is compressed as a whole afterward.
+
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use slotmapd::{Key, KeyData};
+
+use common::saveload::{Bincode, Encoder};
+use geom::{Vec2, Vec3};
+
+use super::WorldCommand::{self, *};
+use super::WorldCommands;
+
+/// Number of tags, one per variant
+pub const TAGS: u8 = 42;
+
+/// Encodes the commands, see [`decode`]
+pub fn encode(commands: &[WorldCommand]) -> Vec<u8> {
+    let mut w = Writer(Vec::with_capacity(16));
+    w.varint(commands.len() as u64);
+    for cmd in commands {
+        w.command(cmd);
+    }
+    w.0
+}
+
+/// Decodes the commands, None if the data is not valid
+pub fn decode(data: &[u8]) -> Option<Vec<WorldCommand>> {
+    let mut r = Reader(data);
+    let n = r.varint()?;
+    let mut commands = Vec::with_capacity((n as usize).min(data.len()));
+    for _ in 0..n {
+        commands.push(r.command()?);
+    }
+    if !r.0.is_empty() {
+        return None;
+    }
+    Some(commands)
+}
+
+/// The commands are only serialized to be sent over the network
+impl Serialize for WorldCommands {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        encode(&self.commands).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for WorldCommands {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = Vec::<u8>::deserialize(deserializer)?;
+        decode(&data)
+            .map(Self::from)
+            .ok_or_else(|| D::Error::custom("invalid world commands"))
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn command(&mut self, cmd: &WorldCommand) {
+        match *cmd {
+            Init(ref opts) => {
+                self.u8(0);
+                self.serde(opts);
+            }
+            MapRemoveIntersection(id) => {
+                self.u8(1);
+                self.id(id);
+            }
+            MapRemoveRoad(id) => {
+                self.u8(2);
+                self.id(id);
+            }
+            MapRemoveBuilding(id) => {
+                self.u8(3);
+                self.id(id);
+            }
+            MapBuildHouse(id) => {
+                self.u8(4);
+                self.id(id);
+            }
+            UpgradeBuilding(id) => {
+                self.u8(5);
+                self.id(id);
+            }
+            MapPaintZone { ref brush, kind } => {
+                self.u8(6);
+                self.serde(brush);
+                self.serde(&kind);
+            }
+            Terraform {
+                kind,
+                center,
+                radius,
+                amount,
+                level,
+                slope,
+            } => {
+                self.u8(7);
+                self.serde(&kind);
+                self.vec2(center);
+                self.f32(radius);
+                self.f32(amount);
+                self.f32(level);
+                self.bool(slope.is_some());
+                if let Some((start, end)) = slope {
+                    self.vec3(start);
+                    self.vec3(end);
+                }
+            }
+            SendMessage { ref message } => {
+                self.u8(8);
+                self.serde(message);
+            }
+            SpawnRandomCars { n_cars } => {
+                self.u8(9);
+                self.varint(n_cars as u64);
+            }
+            AddTrain {
+                dist,
+                n_wagons,
+                lane,
+            } => {
+                self.u8(10);
+                self.f32(dist);
+                self.varint(n_wagons as u64);
+                self.id(lane);
+            }
+            SpawnTrain {
+                ref wagons,
+                lane,
+                dist,
+            } => {
+                self.u8(11);
+                self.serde(wagons);
+                self.id(lane);
+                self.f32(dist);
+            }
+            MapMakeConnection {
+                ref from,
+                ref to,
+                inter,
+                ref pat,
+            } => {
+                self.u8(12);
+                self.serde(from);
+                self.serde(to);
+                self.serde(&inter);
+                self.serde(pat);
+            }
+            MapMakeMultipleConnections(ref projects, ref links) => {
+                self.u8(13);
+                self.serde(projects);
+                self.serde(links);
+            }
+            MapUpdateIntersectionPolicy {
+                inter,
+                ref turn,
+                light,
+            } => {
+                self.u8(14);
+                self.id(inter);
+                self.serde(turn);
+                self.serde(&light);
+            }
+            MapBuildSpecialBuilding {
+                ref pos,
+                ref kind,
+                ref gen,
+                ref zone,
+                connected_road,
+            } => {
+                self.u8(15);
+                self.serde(pos);
+                self.serde(kind);
+                self.serde(gen);
+                self.serde(zone);
+                self.bool(connected_road.is_some());
+                if let Some(road) = connected_road {
+                    self.id(road);
+                }
+            }
+            MapLoadParis => self.u8(16),
+            MapLoadTestField { pos, size, spacing } => {
+                self.u8(17);
+                self.vec2(pos);
+                self.varint(size as u64);
+                self.f32(spacing);
+            }
+            UpdateZone { building, ref zone } => {
+                self.u8(18);
+                self.id(building);
+                self.serde(zone);
+            }
+            SetGameTime(ref time) => {
+                self.u8(19);
+                self.serde(time);
+            }
+            SetRoadMaintenanceBudget(ref budget) => {
+                self.u8(20);
+                self.serde(budget);
+            }
+            SetGameRules(ref rules) => {
+                self.u8(21);
+                self.serde(rules);
+            }
+            RenameRoad { road, ref name } => {
+                self.u8(22);
+                self.id(road);
+                self.str(name);
+            }
+            SetRoadElectrified { road, electrified } => {
+                self.u8(23);
+                self.id(road);
+                self.bool(electrified);
+            }
+            SetModSetting {
+                ref mod_name,
+                ref key,
+                ref value,
+            } => {
+                self.u8(24);
+                self.str(mod_name);
+                self.str(key);
+                self.serde(value);
+            }
+            StartCitizenSampling { count, seed } => {
+                self.u8(25);
+                self.varint(count as u64);
+                self.varint(seed);
+            }
+            SampleCitizen(human) => {
+                self.u8(26);
+                self.id(human);
+            }
+            SetStockRules {
+                building,
+                ref rules,
+            } => {
+                self.u8(27);
+                self.id(building);
+                self.serde(rules);
+            }
+            SetWelfarePolicy(ref policy) => {
+                self.u8(28);
+                self.serde(policy);
+            }
+            CreateIncident { pos } => {
+                self.u8(29);
+                self.vec3(pos);
+            }
+            BuyServiceVehicle(depot) => {
+                self.u8(30);
+                self.id(depot);
+            }
+            SellServiceVehicle(depot) => {
+                self.u8(31);
+                self.id(depot);
+            }
+            MapAddDistrict {
+                ref name,
+                color,
+                ref shape,
+            } => {
+                self.u8(32);
+                self.str(name);
+                self.serde(&color);
+                self.serde(shape);
+            }
+            MapUpdateDistrict {
+                district,
+                ref name,
+                color,
+            } => {
+                self.u8(33);
+                self.id(district);
+                self.str(name);
+                self.serde(&color);
+            }
+            MapRemoveDistrict(district) => {
+                self.u8(34);
+                self.id(district);
+            }
+            PlaceGovernmentOrder {
+                item,
+                side,
+                price,
+                qty,
+            } => {
+                self.u8(35);
+                self.serde(&item);
+                self.serde(&side);
+                self.serde(&price);
+                self.varint(qty as u64);
+            }
+            CancelGovernmentOrder(order) => {
+                self.u8(36);
+                self.id(order);
+            }
+            MapAddRestriction { kind, ref shape } => {
+                self.u8(37);
+                self.serde(&kind);
+                self.serde(shape);
+            }
+            MapRemoveRestriction(restriction) => {
+                self.u8(38);
+                self.id(restriction);
+            }
+            SpawnCarModels { pos } => {
+                self.u8(39);
+                self.vec3(pos);
+            }
+            SetSpeedLimit { ref roads, limit } => {
+                self.u8(40);
+                self.varint(roads.len() as u64);
+                for &road in roads {
+                    self.id(road);
+                }
+                self.f32(limit);
+            }
+            MapImport(ref imported) => {
+                self.u8(41);
+                self.serde(imported);
+            }
+        }
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+
+    fn bool(&mut self, v: bool) {
+        self.u8(v as u8);
+    }
+
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.u8(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.u8(v as u8);
+    }
+
+    fn f32(&mut self, v: f32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn vec2(&mut self, v: Vec2) {
+        self.f32(v.x);
+        self.f32(v.y);
+    }
+
+    fn vec3(&mut self, v: Vec3) {
+        self.f32(v.x);
+        self.f32(v.y);
+        self.f32(v.z);
+    }
+
+    fn str(&mut self, s: &str) {
+        self.varint(s.len() as u64);
+        self.0.extend_from_slice(s.as_bytes());
+    }
+
+    /// The index and the version, most ids have a small index and version
+    fn id(&mut self, id: impl Key) {
+        let ffi = id.data().as_ffi();
+        self.varint(ffi & 0xFFFF_FFFF);
+        self.varint(ffi >> 32);
+    }
+
+    fn serde(&mut self, v: &impl Serialize) {
+        Bincode::encode_writer(v, &mut self.0).expect("failed serializing command");
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn command(&mut self) -> Option<WorldCommand> {
+        Some(match self.u8()? {
+            0 => Init(self.serde()?),
+            1 => MapRemoveIntersection(self.id()?),
+            2 => MapRemoveRoad(self.id()?),
+            3 => MapRemoveBuilding(self.id()?),
+            4 => MapBuildHouse(self.id()?),
+            5 => UpgradeBuilding(self.id()?),
+            6 => MapPaintZone {
+                brush: self.serde()?,
+                kind: self.serde()?,
+            },
+            7 => Terraform {
+                kind: self.serde()?,
+                center: self.vec2()?,
+                radius: self.f32()?,
+                amount: self.f32()?,
+                level: self.f32()?,
+                slope: if self.bool()? {
+                    Some((self.vec3()?, self.vec3()?))
+                } else {
+                    None
+                },
+            },
+            8 => SendMessage {
+                message: self.serde()?,
+            },
+            9 => SpawnRandomCars {
+                n_cars: self.varint()? as usize,
+            },
+            10 => AddTrain {
+                dist: self.f32()?,
+                n_wagons: self.varint()?.try_into().ok()?,
+                lane: self.id()?,
+            },
+            11 => SpawnTrain {
+                wagons: self.serde()?,
+                lane: self.id()?,
+                dist: self.f32()?,
+            },
+            12 => MapMakeConnection {
+                from: self.serde()?,
+                to: self.serde()?,
+                inter: self.serde()?,
+                pat: self.serde()?,
+            },
+            13 => MapMakeMultipleConnections(self.serde()?, self.serde()?),
+            14 => MapUpdateIntersectionPolicy {
+                inter: self.id()?,
+                turn: self.serde()?,
+                light: self.serde()?,
+            },
+            15 => MapBuildSpecialBuilding {
+                pos: self.serde()?,
+                kind: self.serde()?,
+                gen: self.serde()?,
+                zone: self.serde()?,
+                connected_road: if self.bool()? { Some(self.id()?) } else { None },
+            },
+            16 => MapLoadParis,
+            17 => MapLoadTestField {
+                pos: self.vec2()?,
+                size: self.varint()?.try_into().ok()?,
+                spacing: self.f32()?,
+            },
+            18 => UpdateZone {
+                building: self.id()?,
+                zone: self.serde()?,
+            },
+            19 => SetGameTime(self.serde()?),
+            20 => SetRoadMaintenanceBudget(self.serde()?),
+            21 => SetGameRules(self.serde()?),
+            22 => RenameRoad {
+                road: self.id()?,
+                name: self.str()?,
+            },
+            23 => SetRoadElectrified {
+                road: self.id()?,
+                electrified: self.bool()?,
+            },
+            24 => SetModSetting {
+                mod_name: self.str()?,
+                key: self.str()?,
+                value: self.serde()?,
+            },
+            25 => StartCitizenSampling {
+                count: self.varint()?.try_into().ok()?,
+                seed: self.varint()?,
+            },
+            26 => SampleCitizen(self.id()?),
+            27 => SetStockRules {
+                building: self.id()?,
+                rules: self.serde()?,
+            },
+            28 => SetWelfarePolicy(self.serde()?),
+            29 => CreateIncident { pos: self.vec3()? },
+            30 => BuyServiceVehicle(self.id()?),
+            31 => SellServiceVehicle(self.id()?),
+            32 => MapAddDistrict {
+                name: self.str()?,
+                color: self.serde()?,
+                shape: self.serde()?,
+            },
+            33 => MapUpdateDistrict {
+                district: self.id()?,
+                name: self.str()?,
+                color: self.serde()?,
+            },
+            34 => MapRemoveDistrict(self.id()?),
+            35 => PlaceGovernmentOrder {
+                item: self.serde()?,
+                side: self.serde()?,
+                price: self.serde()?,
+                qty: self.varint()?.try_into().ok()?,
+            },
+            36 => CancelGovernmentOrder(self.id()?),
+            37 => MapAddRestriction {
+                kind: self.serde()?,
+                shape: self.serde()?,
+            },
+            38 => MapRemoveRestriction(self.id()?),
+            39 => SpawnCarModels { pos: self.vec3()? },
+            40 => {
+                let n = self.varint()?;
+                SetSpeedLimit {
+                    roads: (0..n).map(|_| self.id()).collect::<Option<_>>()?,
+                    limit: self.f32()?,
+                }
+            }
+            41 => MapImport(self.serde()?),
+            _ => return None,
+        })
+    }
+
+    fn bytes(&mut self, n: usize) -> Option<&[u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut v = 0;
+        for shift in (0..64).step_by(7) {
+            let b = self.u8()?;
+            v |= ((b & 0x7F) as u64) << shift;
+            if b < 0x80 {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    fn vec2(&mut self) -> Option<Vec2> {
+        Some(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    fn vec3(&mut self) -> Option<Vec3> {
+        Some(Vec3::new(self.f32()?, self.f32()?, self.f32()?))
+    }
+
+    fn str(&mut self) -> Option<String> {
+        let len = self.varint()?.try_into().ok()?;
+        String::from_utf8(self.bytes(len)?.to_vec()).ok()
+    }
+
+    fn id<K: From<KeyData>>(&mut self) -> Option<K> {
+        let idx = self.varint()?;
+        let version = self.varint()?;
+        if idx > 0xFFFF_FFFF || version > 0xFFFF_FFFF {
+            return None;
+        }
+        Some(KeyData::from_ffi((version << 32) | idx).into())
+    }
+
+    fn serde<T: DeserializeOwned>(&mut self) -> Option<T> {
+        Bincode::decode_reader(&mut self.0).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economy::OrderSide;
+    use crate::map::procgen::osm::ImportedMap;
+    use crate::map::{
+        BuildingKind, LanePatternBuilder, LightPolicy, LotKind, MapProject, ProjectKind,
+        TerraformKind, TurnPolicy, Zone, ZoneBrush,
+    };
+    use crate::multiplayer::chat::{Message, MessageKind};
+    use crate::rules::GameRules;
+    use crate::souls::warehouse::StockRule;
+    use crate::souls::welfare::WelfarePolicy;
+    use crate::{Latitude, SimulationOptions};
+    use geom::{Circle, Color, Polygon, OBB};
+    use prototypes::{
+        BuildingGen, GameInstant, GameTime, GoodsCompanyID, ItemID, ModSettingValue, Money,
+        RestrictionKind, RollingStockID, Tick,
+    };
+    use quickcheck::{Arbitrary, Gen};
+
+    fn id<K: From<KeyData>>(g: &mut Gen) -> K {
+        // from_ffi keeps the version odd, like the versions of the keys in use
+        KeyData::from_ffi((u64::arbitrary(g) % 1000) | ((u64::arbitrary(g) % 50) << 32)).into()
+    }
+
+    fn f32(g: &mut Gen) -> f32 {
+        f32::arbitrary(g)
+    }
+
+    fn vec2(g: &mut Gen) -> Vec2 {
+        Vec2::new(f32(g), f32(g))
+    }
+
+    fn vec3(g: &mut Gen) -> Vec3 {
+        Vec3::new(f32(g), f32(g), f32(g))
+    }
+
+    fn pick<T: Copy>(g: &mut Gen, values: &[T]) -> T {
+        *g.choose(values).unwrap()
+    }
+
+    fn project(g: &mut Gen) -> MapProject {
+        MapProject {
+            pos: vec3(g),
+            kind: match u8::arbitrary(g) % 5 {
+                0 => ProjectKind::Inter(id(g)),
+                1 => ProjectKind::Road(id(g)),
+                2 => ProjectKind::Building(id(g)),
+                3 => ProjectKind::Lot(id(g)),
+                _ => ProjectKind::Ground,
+            },
+        }
+    }
+
+    fn obb(g: &mut Gen) -> OBB {
+        OBB::new(vec2(g), vec2(g), f32(g), f32(g))
+    }
+
+    fn zone(g: &mut Gen) -> Zone {
+        Zone::new(
+            Polygon((0..u8::arbitrary(g) % 6).map(|_| vec2(g)).collect()),
+            vec2(g),
+        )
+    }
+
+    fn lane_pattern(g: &mut Gen) -> crate::map::LanePattern {
+        LanePatternBuilder::new()
+            .n_lanes(u32::arbitrary(g) % 4)
+            .speed_limit(f32(g))
+            .sidewalks(bool::arbitrary(g))
+            .parking(bool::arbitrary(g))
+            .one_way(bool::arbitrary(g))
+            .rail(bool::arbitrary(g))
+            .build()
+    }
+
+    fn random_command(g: &mut Gen, tag: u8) -> WorldCommand {
+        match tag {
+            0 => Init(Box::new(SimulationOptions {
+                terrain_size: u16::arbitrary(g),
+                save_replay: bool::arbitrary(g),
+                car_ownership_rate: f32(g),
+                season_days: u32::arbitrary(g),
+                deposit_abundance: f32(g),
+                latitude: pick(g, &Latitude::ALL),
+                ..Default::default()
+            })),
+            1 => MapRemoveIntersection(id(g)),
+            2 => MapRemoveRoad(id(g)),
+            3 => MapRemoveBuilding(id(g)),
+            4 => MapBuildHouse(id(g)),
+            5 => UpgradeBuilding(id(g)),
+            6 => MapPaintZone {
+                brush: if bool::arbitrary(g) {
+                    ZoneBrush::Circle(Circle::new(vec2(g), f32(g)))
+                } else {
+                    ZoneBrush::Rect(obb(g))
+                },
+                kind: pick(
+                    g,
+                    &[
+                        LotKind::Unassigned,
+                        LotKind::Residential,
+                        LotKind::Commercial,
+                        LotKind::Industrial,
+                    ],
+                ),
+            },
+            7 => Terraform {
+                kind: pick(
+                    g,
+                    &[
+                        TerraformKind::Elevation,
+                        TerraformKind::Smooth,
+                        TerraformKind::Level,
+                        TerraformKind::Slope,
+                        TerraformKind::Erode,
+                        TerraformKind::Plant,
+                        TerraformKind::WaterLevel,
+                    ],
+                ),
+                center: vec2(g),
+                radius: f32(g),
+                amount: f32(g),
+                level: f32(g),
+                slope: bool::arbitrary(g).then(|| (vec3(g), vec3(g))),
+            },
+            8 => SendMessage {
+                message: Message {
+                    name: String::arbitrary(g),
+                    text: String::arbitrary(g),
+                    sent_at: GameInstant(Tick(u64::arbitrary(g))),
+                    color: Color::new(f32(g), f32(g), f32(g), f32(g)),
+                    kind: pick(
+                        g,
+                        &[
+                            MessageKind::Info,
+                            MessageKind::Warning,
+                            MessageKind::PlayerChat,
+                        ],
+                    ),
+                },
+            },
+            9 => SpawnRandomCars {
+                n_cars: usize::arbitrary(g),
+            },
+            10 => AddTrain {
+                dist: f32(g),
+                n_wagons: u32::arbitrary(g),
+                lane: id(g),
+            },
+            11 => SpawnTrain {
+                wagons: (0..u8::arbitrary(g) % 5)
+                    .map(|_| RollingStockID::new(&String::arbitrary(g)))
+                    .collect(),
+                lane: id(g),
+                dist: f32(g),
+            },
+            12 => MapMakeConnection {
+                from: project(g),
+                to: project(g),
+                inter: bool::arbitrary(g).then(|| vec2(g)),
+                pat: lane_pattern(g),
+            },
+            13 => MapMakeMultipleConnections(
+                (0..u8::arbitrary(g) % 5).map(|_| project(g)).collect(),
+                (0..u8::arbitrary(g) % 5)
+                    .map(|_| {
+                        (
+                            usize::arbitrary(g),
+                            usize::arbitrary(g),
+                            bool::arbitrary(g).then(|| vec2(g)),
+                            lane_pattern(g),
+                        )
+                    })
+                    .collect(),
+            ),
+            14 => MapUpdateIntersectionPolicy {
+                inter: id(g),
+                turn: TurnPolicy {
+                    back_turns: bool::arbitrary(g),
+                    left_turns: bool::arbitrary(g),
+                    ..Default::default()
+                },
+                light: pick(
+                    g,
+                    &[
+                        LightPolicy::NoLights,
+                        LightPolicy::StopSigns,
+                        LightPolicy::Lights,
+                        LightPolicy::Auto,
+                    ],
+                ),
+            },
+            15 => MapBuildSpecialBuilding {
+                pos: obb(g),
+                kind: if bool::arbitrary(g) {
+                    BuildingKind::GoodsCompany(GoodsCompanyID::new(&String::arbitrary(g)))
+                } else {
+                    BuildingKind::TrainStation
+                },
+                gen: if bool::arbitrary(g) {
+                    BuildingGen::NoWalkway { door_pos: vec2(g) }
+                } else {
+                    BuildingGen::CenteredDoor {
+                        vertical_factor: f32(g),
+                    }
+                },
+                zone: bool::arbitrary(g).then(|| zone(g)),
+                connected_road: bool::arbitrary(g).then(|| id(g)),
+            },
+            16 => MapLoadParis,
+            17 => MapLoadTestField {
+                pos: vec2(g),
+                size: u32::arbitrary(g),
+                spacing: f32(g),
+            },
+            18 => UpdateZone {
+                building: id(g),
+                zone: zone(g),
+            },
+            19 => SetGameTime(GameTime::new(Tick(u64::arbitrary(g) % 1_000_000))),
+            20 => SetRoadMaintenanceBudget(Money(i64::arbitrary(g))),
+            21 => SetGameRules(GameRules {
+                road_cost: f32(g),
+                bankruptcy_days: u32::arbitrary(g),
+                ..Default::default()
+            }),
+            22 => RenameRoad {
+                road: id(g),
+                name: String::arbitrary(g),
+            },
+            23 => SetRoadElectrified {
+                road: id(g),
+                electrified: bool::arbitrary(g),
+            },
+            24 => SetModSetting {
+                mod_name: String::arbitrary(g),
+                key: String::arbitrary(g),
+                value: match u8::arbitrary(g) % 3 {
+                    0 => ModSettingValue::Bool(bool::arbitrary(g)),
+                    1 => ModSettingValue::Number(f64::arbitrary(g)),
+                    _ => ModSettingValue::Choice(String::arbitrary(g)),
+                },
+            },
+            25 => StartCitizenSampling {
+                count: u32::arbitrary(g),
+                seed: u64::arbitrary(g),
+            },
+            26 => SampleCitizen(id(g)),
+            27 => SetStockRules {
+                building: id(g),
+                rules: (0..u8::arbitrary(g) % 4)
+                    .map(|_| StockRule {
+                        item: ItemID::new(&String::arbitrary(g)),
+                        min: u32::arbitrary(g),
+                        max: u32::arbitrary(g),
+                        buy_below: bool::arbitrary(g).then(|| Money(i64::arbitrary(g))),
+                        sell_above: bool::arbitrary(g).then(|| Money(i64::arbitrary(g))),
+                    })
+                    .collect(),
+            },
+            28 => SetWelfarePolicy(WelfarePolicy {
+                allowance_percent: u32::arbitrary(g),
+                alert_thresholds: Vec::arbitrary(g),
+            }),
+            29 => CreateIncident { pos: vec3(g) },
+            30 => BuyServiceVehicle(id(g)),
+            31 => SellServiceVehicle(id(g)),
+            32 => MapAddDistrict {
+                name: String::arbitrary(g),
+                color: Color::new(f32(g), f32(g), f32(g), f32(g)),
+                shape: Polygon((0..u8::arbitrary(g) % 6).map(|_| vec2(g)).collect()),
+            },
+            33 => MapUpdateDistrict {
+                district: id(g),
+                name: String::arbitrary(g),
+                color: Color::new(f32(g), f32(g), f32(g), f32(g)),
+            },
+            34 => MapRemoveDistrict(id(g)),
+            35 => PlaceGovernmentOrder {
+                item: ItemID::new(&String::arbitrary(g)),
+                side: pick(g, &[OrderSide::Buy, OrderSide::Sell]),
+                price: Money(i64::arbitrary(g)),
+                qty: u32::arbitrary(g),
+            },
+            36 => CancelGovernmentOrder(id(g)),
+            37 => MapAddRestriction {
+                kind: pick(g, &RestrictionKind::ALL),
+                shape: Polygon((0..u8::arbitrary(g) % 6).map(|_| vec2(g)).collect()),
+            },
+            38 => MapRemoveRestriction(id(g)),
+            39 => SpawnCarModels { pos: vec3(g) },
+            40 => SetSpeedLimit {
+                roads: (0..u8::arbitrary(g) % 6).map(|_| id(g)).collect(),
+                limit: f32(g),
+            },
+            41 => MapImport(Box::new(ImportedMap {
+                nodes: (0..u8::arbitrary(g) % 6).map(|_| vec2(g)).collect(),
+                roads: (0..u8::arbitrary(g) % 6)
+                    .map(|_| (u32::arbitrary(g), u32::arbitrary(g), lane_pattern(g)))
+                    .collect(),
+                water: vec![Polygon(
+                    (0..u8::arbitrary(g) % 6).map(|_| vec2(g)).collect(),
+                )],
+                forests: vec![],
+            })),
+            _ => panic!("no random command for tag {tag}, add one with the new variant"),
+        }
+    }
+
+    /// The commands have no PartialEq, their serde encoding is compared instead
+    fn same(a: &[WorldCommand], b: &[WorldCommand]) -> bool {
+        Bincode::encode(&a).unwrap() == Bincode::encode(&b).unwrap()
+    }
+
+    #[test]
+    fn every_variant_round_trips() {
+        let mut g = Gen::new(20);
+        for i in 0..5000 {
+            let tag = (i % TAGS as usize) as u8;
+            let cmd = random_command(&mut g, tag);
+
+            let data = encode(std::slice::from_ref(&cmd));
+            assert_eq!(data[1], tag);
+            let decoded = decode(&data).unwrap_or_else(|| panic!("could not decode {:?}", cmd));
+            assert!(same(&[cmd], &decoded), "{:?}", decoded);
+        }
+    }
+
+    #[test]
+    fn many_commands_round_trip() {
+        let mut g = Gen::new(20);
+        for _ in 0..200 {
+            let commands: Vec<_> = (0..u8::arbitrary(&mut g) % 20)
+                .map(|_| {
+                    let tag = u8::arbitrary(&mut g) % TAGS;
+                    random_command(&mut g, tag)
+                })
+                .collect();
+            let cmds = WorldCommands::from(commands.clone());
+
+            let data = Bincode::encode(&cmds).unwrap();
+            let decoded: WorldCommands = Bincode::decode(&data).unwrap();
+            assert!(same(&commands, decoded.as_ref()));
+        }
+    }
+
+    #[test]
+    fn invalid_data_is_refused() {
+        let mut g = Gen::new(20);
+        assert!(decode(&[1, TAGS]).is_none());
+
+        for tag in 0..TAGS {
+            let data = encode(&[random_command(&mut g, tag)]);
+            if data.len() > 2 {
+                assert!(decode(&data[..data.len() - 1]).is_none());
+            }
+            let mut longer = data.clone();
+            longer.push(0);
+            assert!(decode(&longer).is_none());
+        }
+    }
+}